@@ -0,0 +1,27 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use thyme_core::mp::form;
+use wasm_bindgen::prelude::*;
+
+/// Compute form descriptors for a polygon
+///
+/// # Arguments
+///
+/// * `points` - A flattened array of polygon points (x0, y0, x1, y1, ...)
+///
+/// # Notes
+///
+/// Descriptors are returned in the same order as `FORM_DESCRIPTORS`
+/// in `thyme_core::constant`. An empty vector is returned if fewer than
+/// three points are provided or the array has an odd length.
+#[wasm_bindgen]
+pub fn form_descriptors(points: &[f32]) -> Vec<f32> {
+    if points.len() % 2 != 0 || points.len() < 6 {
+        return Vec::new();
+    }
+
+    let points: Vec<[f32; 2]> = points.chunks_exact(2).map(|p| [p[0], p[1]]).collect();
+
+    form::descriptors(&points).to_vec()
+}
@@ -0,0 +1,23 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs");
+
+    // The git hash is only available when building from a repository checkout
+    let hash = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty());
+
+    if let Some(hash) = hash {
+        println!("cargo:rustc-env=THYME_GIT_HASH={}", hash);
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::{BoundingBoxes, BoxColumns};
+
+/// An image identifier, image path, and path to its bounding boxes
+type Pair = (String, PathBuf, PathBuf);
+
+/// Bounding boxes read from per-image files or a single multi-image csv table
+#[derive(Debug)]
+pub enum BoxSource {
+    /// One bounding box file per image
+    Files(BoxColumns),
+    /// Bounding boxes of every paired image keyed by image file stem
+    Table(HashMap<String, BoundingBoxes>),
+}
+
+impl BoxSource {
+    /// Open the bounding boxes paired with an image
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Path to the image
+    /// * `boxes` - Path to the bounding boxes paired with the image
+    pub fn open(&self, image: &Path, boxes: &Path) -> Result<BoundingBoxes, ThymeError> {
+        match self {
+            BoxSource::Files(columns) => {
+                let csv = boxes
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+
+                if csv {
+                    BoundingBoxes::open_csv(boxes, columns)
+                } else {
+                    BoundingBoxes::open(boxes)
+                }
+            }
+            BoxSource::Table(table) => table
+                .get(&stem(image))
+                .cloned()
+                .ok_or(ThymeError::BoxesReadError),
+        }
+    }
+}
+
+/// Pair images with the bounding boxes they are assigned in a multi-image csv table
+///
+/// The image column of the table is matched against image file stems, either
+/// in full or with the image substring removed. Every pair points at the table.
+/// The number of images referenced by the table without a matching image file
+/// is returned alongside the pairs.
+///
+/// # Arguments
+///
+/// * `table` - Path to a csv table of bounding boxes with an image column
+/// * `columns` - Column names overriding header detection
+/// * `image_files` - Image files to pair with the table
+/// * `image_substring` - Substring specifying images
+pub fn table_pairs(
+    table: &str,
+    columns: &BoxColumns,
+    image_files: &[PathBuf],
+    image_substring: Option<String>,
+) -> Result<(Vec<Pair>, BoxSource, usize), ThymeError> {
+    let mut boxes = BoundingBoxes::open_csv_images(table, columns)?;
+
+    let substring = image_substring.unwrap_or_default();
+
+    let mut pairs = vec![];
+    let mut paired = HashMap::new();
+
+    for image in image_files {
+        let name = stem(image);
+        let id = name.replace(&substring, "");
+
+        let key = if boxes.contains_key(&name) {
+            name.clone()
+        } else {
+            id.clone()
+        };
+
+        if let Some(image_boxes) = boxes.remove(&key) {
+            paired.insert(name, image_boxes);
+            pairs.push((id, image.clone(), PathBuf::from(table)));
+        }
+    }
+
+    Ok((pairs, BoxSource::Table(paired), boxes.len()))
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_table_pairs() {
+        let table = std::env::temp_dir().join("thyme_test_table_pairs.csv");
+
+        std::fs::write(
+            &table,
+            "file,left,top,right,bottom,class\na_image.png,0,0,2,2,x\nb,1,1,3,3,y\nc.tif,0,0,1,1,z\na_image.png,4,4,6,6,x\n",
+        )
+        .unwrap();
+
+        let image_files = vec![
+            PathBuf::from("images/a_image.png"),
+            PathBuf::from("images/b_image.tif"),
+            PathBuf::from("images/d_image.png"),
+        ];
+
+        let (mut pairs, source, unmatched) = table_pairs(
+            table.to_str().unwrap(),
+            &BoxColumns::default(),
+            &image_files,
+            Some("_image".to_string()),
+        )
+        .unwrap();
+
+        pairs.sort_unstable();
+
+        assert_eq!(unmatched, 1);
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].0, "a");
+        assert_eq!(pairs[1].0, "b");
+        assert_eq!(pairs[0].2, table);
+
+        let boxes = source.open(&pairs[0].1, &pairs[0].2).unwrap();
+        assert_eq!(*boxes.as_xyxy(), vec![[0., 0., 2., 2.], [4., 4., 6., 6.]]);
+        assert_eq!(boxes.labels().unwrap(), &vec!["x", "x"]);
+
+        let boxes = source.open(&pairs[1].1, &pairs[1].2).unwrap();
+        assert_eq!(boxes.labels().unwrap(), &vec!["y"]);
+
+        assert!(source.open(&image_files[2], &table).is_err());
+
+        std::fs::remove_file(table).unwrap();
+    }
+}
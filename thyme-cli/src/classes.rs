@@ -0,0 +1,141 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im;
+use thyme_core::ut;
+
+/// Class masks storing the semantic class id of each pixel, keyed by image id
+///
+/// Class masks are paired with images by their stems in the same way as
+/// instance masks, so an instance and class mask pair (e.g. a panoptic
+/// segmentation) can share a directory if their substrings differ.
+#[derive(Debug, Clone)]
+pub struct ClassMasks {
+    masks: HashMap<String, PathBuf>,
+}
+
+impl ClassMasks {
+    /// Collect the class masks matching a set of images
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Class mask directory
+    /// * `substring` - Substring specifying class masks (e.g. _class)
+    /// * `image_files` - Image files to pair class masks with
+    /// * `image_substring` - Substring specifying images (e.g. _image)
+    pub fn collect(
+        directory: &str,
+        substring: Option<String>,
+        image_files: &[PathBuf],
+        image_substring: Option<String>,
+    ) -> Result<Self, ThymeError> {
+        let class_files = ut::path::collect_file_paths(
+            directory,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            substring.clone(),
+        )?;
+
+        let masks =
+            ut::path::collect_file_pairs(image_files, &class_files, image_substring, substring)
+                .into_iter()
+                .map(|(id, _, class)| (id, class))
+                .collect();
+
+        Ok(ClassMasks { masks })
+    }
+
+    /// Number of images with a matching class mask
+    pub fn len(&self) -> usize {
+        self.masks.len()
+    }
+
+    /// Check if no images have a matching class mask
+    pub fn is_empty(&self) -> bool {
+        self.masks.is_empty()
+    }
+
+    /// Path to the class mask of an image
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Image identifier shared by the image and mask pair
+    pub fn path(&self, id: &str) -> Result<&Path, ThymeError> {
+        self.masks
+            .get(id)
+            .map(|path| path.as_path())
+            .ok_or_else(|| ThymeError::OtherError("No matching class mask".to_string()))
+    }
+}
+
+/// Open a class mask and check that it has the dimensions of the instance mask
+///
+/// # Arguments
+///
+/// * `path` - Path to the class mask
+/// * `mask` - Instance mask with the same dimensions as the image
+pub fn open(path: &Path, mask: &im::ThymeMask) -> Result<im::ThymeMask, ThymeError> {
+    let classes = im::ThymeMask::open(path)?;
+
+    if classes.width() != mask.width() || classes.height() != mask.height() {
+        return Err(ThymeError::OtherError(
+            "Class mask and image are not the same size".to_string(),
+        ));
+    }
+
+    Ok(classes)
+}
+
+/// Majority class id and fraction of an object in a class mask
+///
+/// Objects without any pixels in the crop are assigned class zero with a
+/// fraction of zero.
+///
+/// # Arguments
+///
+/// * `mask` - Instance mask
+/// * `classes` - Class mask with the same dimensions as the instance mask
+/// * `crop` - Padded crop (x, y, w, h) containing the object
+/// * `label` - Label of the object in the instance mask
+pub fn object_class(
+    mask: &im::ThymeMask,
+    classes: &im::ThymeMask,
+    crop: [u32; 4],
+    label: u32,
+) -> Result<(u32, f32), ThymeError> {
+    let [x, y, w, h] = crop;
+
+    Ok(mask
+        .crop_view(x, y, w, h)
+        .majority_class(&classes.crop_view(x, y, w, h), label)?
+        .unwrap_or((0, 0.0)))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_object_class() {
+        let mask = im::ThymeMask::new(4, 2, 1, vec![1, 1, 2, 2, 1, 1, 2, 0]).unwrap();
+        let classes = im::ThymeMask::new(4, 2, 1, vec![3, 3, 3, 4, 3, 4, 4, 4]).unwrap();
+
+        assert_eq!(
+            object_class(&mask, &classes, [0, 0, 4, 2], 1).unwrap(),
+            (3, 0.75)
+        );
+        assert_eq!(
+            object_class(&mask, &classes, [2, 0, 2, 2], 2).unwrap(),
+            (4, 2.0 / 3.0)
+        );
+        assert_eq!(
+            object_class(&mask, &classes, [0, 0, 4, 2], 7).unwrap(),
+            (0, 0.0)
+        );
+    }
+}
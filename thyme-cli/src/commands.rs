@@ -0,0 +1,216 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::ffi::OsString;
+use std::path::Path;
+
+use clap::error::ErrorKind;
+use clap::{Command, Parser};
+
+use thyme_data::suggest;
+
+/// A subcommand that is not known to the command preceding it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownSubcommand {
+    /// Names of the commands preceding the unknown subcommand (e.g. thyme, utils)
+    pub path: Vec<String>,
+    /// Name of the unknown subcommand
+    pub name: String,
+    /// Known subcommands that are likely intended, from the closest
+    pub suggestions: Vec<String>,
+    /// Every known subcommand of the preceding command
+    pub available: Vec<String>,
+}
+
+impl UnknownSubcommand {
+    /// Find the first unknown subcommand in command line arguments
+    ///
+    /// Subcommands are matched from the first argument after the binary name
+    /// until an option or a command without subcommands is reached.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - Root command
+    /// * `args` - Command line arguments including the binary name
+    pub fn find(command: &Command, args: &[OsString]) -> Option<UnknownSubcommand> {
+        let command = build(command.clone(), args);
+
+        let mut command = &command;
+        let mut path = vec![command.get_bin_name().unwrap_or_default().to_string()];
+
+        for arg in args.iter().skip(1) {
+            let arg = arg.to_string_lossy();
+
+            if arg.starts_with('-') || !command.has_subcommands() {
+                return None;
+            }
+
+            match command.find_subcommand(arg.as_ref()) {
+                Some(subcommand) => {
+                    path.push(subcommand.get_name().to_string());
+                    command = subcommand;
+                }
+                None => {
+                    let available: Vec<&str> = command
+                        .get_subcommands()
+                        .filter(|subcommand| !subcommand.is_hide_set())
+                        .map(|subcommand| subcommand.get_name())
+                        .filter(|name| *name != "help")
+                        .collect();
+
+                    return Some(UnknownSubcommand {
+                        path,
+                        name: arg.to_string(),
+                        suggestions: suggest::closest(&arg, available.iter().copied()),
+                        available: available.iter().map(|name| name.to_string()).collect(),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Format the error message listing the suggested and available subcommands
+    pub fn message(&self) -> String {
+        let mut message = format!("unrecognized subcommand '{}'\n", self.name);
+
+        if !self.suggestions.is_empty() {
+            message.push_str(&format!(
+                "\n  did you mean: {}",
+                self.suggestions.join(", ")
+            ));
+        }
+
+        message.push_str(&format!(
+            "\n  available subcommands of '{}': {}",
+            self.path.join(" "),
+            self.available.join(", ")
+        ));
+
+        message
+    }
+}
+
+/// Build a command named after the binary so usage matches the invocation
+fn build(command: Command, args: &[OsString]) -> Command {
+    let bin_name = args
+        .first()
+        .and_then(|arg| Path::new(arg).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| command.get_name().to_string());
+
+    let mut command = command.bin_name(bin_name);
+    command.build();
+    command
+}
+
+/// Parse command line arguments, listing likely intended subcommands for unknown ones
+///
+/// Clap hints at a similar subcommand for some typos, but unknown subcommands
+/// are reported here with every subcommand within a small edit distance and
+/// the full list of subcommands at that position. Other errors are reported
+/// by clap.
+pub fn parse<P: Parser>() -> P {
+    let args: Vec<OsString> = std::env::args_os().collect();
+
+    match P::try_parse_from(&args) {
+        Ok(parsed) => parsed,
+        Err(err) if err.kind() == ErrorKind::InvalidSubcommand => {
+            let Some(unknown) = UnknownSubcommand::find(&P::command(), &args) else {
+                err.exit()
+            };
+
+            let mut command = build(P::command(), &args);
+            let mut command = &mut command;
+            for name in unknown.path.iter().skip(1) {
+                command = command.find_subcommand_mut(name).unwrap();
+            }
+
+            command
+                .error(ErrorKind::InvalidSubcommand, unknown.message())
+                .exit()
+        }
+        Err(err) => err.exit(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::{CommandFactory, Subcommand};
+
+    use crate::{download, profile, utils};
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(subcommand)]
+        command: Commands,
+    }
+
+    #[derive(Subcommand)]
+    enum Commands {
+        Download(download::DownloadArgs),
+        Profile(Box<profile::ProfileArgs>),
+        Utils(utils::UtilsArgs),
+    }
+
+    fn find(args: &str) -> Option<UnknownSubcommand> {
+        let args: Vec<OsString> = args.split_whitespace().map(OsString::from).collect();
+        UnknownSubcommand::find(&Cli::command(), &args)
+    }
+
+    #[test]
+    fn test_unknown_subcommand() {
+        let unknown = find("thyme utils mask2polygon -i mask.png").unwrap();
+
+        assert_eq!(unknown.path, ["thyme", "utils"]);
+        assert_eq!(unknown.name, "mask2polygon");
+        assert_eq!(unknown.suggestions[0], "mask2polygons");
+        assert!(unknown.available.contains(&"split-dataset".to_string()));
+        assert!(!unknown.available.contains(&"help".to_string()));
+
+        let unknown = find("thyme utlis").unwrap();
+        assert_eq!(unknown.path, ["thyme"]);
+        assert_eq!(unknown.suggestions, ["utils"]);
+
+        let unknown = find("thyme download weight --name dino_vit_small").unwrap();
+        assert_eq!(unknown.suggestions, ["weights"]);
+
+        let unknown = find("thyme profile polygon").unwrap();
+        assert!(unknown.suggestions.contains(&"polygons".to_string()));
+
+        let message = unknown.message();
+        assert!(message.contains("did you mean: polygons"));
+        assert!(message.contains("available subcommands of 'thyme profile': "));
+
+        // Known subcommands, options, and positional arguments are not reported
+        assert!(find("thyme utils mask2polygons -i mask.png").is_none());
+        assert!(find("thyme utils --help").is_none());
+        assert!(find("thyme download").is_none());
+
+        // Unrelated names are reported without suggestions
+        assert!(find("thyme utils xyz").unwrap().suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_parse_known_subcommand() {
+        let args = [
+            "thyme",
+            "utils",
+            "mask2polygons",
+            "-i",
+            "mask.png",
+            "-o",
+            "a.json",
+        ];
+        assert!(Cli::try_parse_from(args).is_ok());
+
+        let err = Cli::try_parse_from(["thyme", "utils", "mask2polygon"])
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidSubcommand);
+    }
+}
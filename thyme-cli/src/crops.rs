@@ -0,0 +1,137 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::{Path, PathBuf};
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im;
+use thyme_core::ut;
+
+/// Select the single-object crops in a directory sorted by path
+///
+/// # Arguments
+///
+/// * `directory` - Crop directory
+/// * `substring` - Substring specifying crops (e.g. _crop)
+/// * `glob` - Glob selecting crops by their path relative to the directory
+/// * `regex` - Regex selecting crops by their path relative to the directory
+pub fn select(
+    directory: &str,
+    substring: Option<&str>,
+    glob: Option<&str>,
+    regex: Option<&str>,
+) -> Result<Vec<PathBuf>, ThymeError> {
+    let selector = ut::path::FileSelector::new(substring, glob, regex)?;
+
+    let mut files = ut::path::select_file_paths(
+        directory,
+        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+        &selector,
+        false,
+    )?;
+
+    if files.is_empty() {
+        return Err(ThymeError::OtherError(
+            "No crop files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
+    }
+
+    files.sort_unstable();
+
+    Ok(files)
+}
+
+/// Image name and object id of a crop from its file name
+///
+/// Crops named `{image}_{object}` (e.g. object thumbnails) are split into
+/// their image name and integer object id. Any other crop is named by its
+/// file stem with an object id of zero.
+///
+/// # Arguments
+///
+/// * `path` - Path to the crop
+pub fn object_id(path: &Path) -> (String, u32) {
+    let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+
+    match stem.rsplit_once('_') {
+        Some((image, object)) if !image.is_empty() => match object.parse::<u32>() {
+            Ok(object) => (image.to_string(), object),
+            Err(_) => (stem, 0),
+        },
+        _ => (stem, 0),
+    }
+}
+
+/// Centroid (x, y) of a crop at the center of the image
+pub fn center(image: &im::ThymeImage) -> [f32; 2] {
+    [image.width() as f32 / 2.0, image.height() as f32 / 2.0]
+}
+
+/// Binary mask of the largest object in the Otsu foreground of a crop
+///
+/// # Arguments
+///
+/// * `image` - Single-object crop
+pub fn auto_mask(image: &im::ThymeImage) -> Result<im::ThymeMask, ThymeError> {
+    let mut mask = im::ThymeMask::from_otsu(image)?;
+    let labels = mask.label();
+
+    let Some((largest, _)) = labels
+        .iter()
+        .zip(mask.areas(&labels))
+        .max_by_key(|&(_, area)| area)
+    else {
+        return Err(ThymeError::OtherError(
+            "No foreground was detected by the Otsu threshold".to_string(),
+        ));
+    };
+
+    mask.crop_binary(0, 0, image.width(), image.height(), *largest)
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_core::im::{ThymeBuffer, ThymeImage};
+
+    use super::*;
+
+    #[test]
+    fn test_object_id() {
+        for (path, expected) in [
+            ("crops/plate_1_A01_12.png", ("plate_1_A01", 12)),
+            ("crops/cell.tif", ("cell", 0)),
+            ("crops/cell_a.tif", ("cell_a", 0)),
+            ("crops/_3.tif", ("_3", 0)),
+        ] {
+            assert_eq!(
+                object_id(Path::new(path)),
+                (expected.0.to_string(), expected.1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_auto_mask() {
+        // A bright 3 x 3 object and a dimmer single pixel of debris
+        let mut pixels = vec![1u8; 64];
+
+        for idx in [18, 19, 20, 26, 27, 28, 34, 35, 36] {
+            pixels[idx] = 200;
+        }
+
+        pixels[63] = 150;
+
+        let image = ThymeImage::U8(ThymeBuffer::new(8, 8, 1, pixels).unwrap());
+        let mask = auto_mask(&image).unwrap();
+
+        assert_eq!(mask.as_raw().iter().sum::<u32>(), 9);
+        assert_eq!(mask.as_raw()[63], 0);
+        assert_eq!(center(&image), [4.0, 4.0]);
+
+        let empty = ThymeImage::U8(ThymeBuffer::new(8, 8, 1, vec![5u8; 64]).unwrap());
+        assert!(auto_mask(&empty).is_err());
+    }
+}
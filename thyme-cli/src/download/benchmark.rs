@@ -4,7 +4,10 @@
 use clap::Args;
 use colored::Colorize;
 
+use super::{destination, filter_datasets};
+
 use thyme_core::ut::track::progress_log;
+use thyme_data::CacheDirectory;
 use thyme_data::data::BenchmarkDatasets;
 
 #[derive(Debug, Args)]
@@ -17,7 +20,12 @@ pub struct DownloadBenchmarkArgs {
     )]
     pub name: Option<String>,
 
-    #[arg(short, long, help = "Output path to save downloaded dataset.")]
+    #[arg(
+        short,
+        long,
+        visible_alias = "dest",
+        help = "Output directory to save downloaded datasets. Defaults to the benchmarks directory of the thyme cache (THYME_CACHE)."
+    )]
     pub output: Option<String>,
 
     #[arg(short = 'v', long, help = "Verbose output.")]
@@ -28,6 +36,18 @@ pub struct DownloadBenchmarkArgs {
 
     #[arg(long, help = "Download all available benchmark datasets.")]
     pub all: bool,
+
+    #[arg(
+        long,
+        help = "Download a subset of benchmark datasets provided as comma-separated names (e.g. name_2020,name_2021)."
+    )]
+    pub only: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip one or more benchmark datasets provided as comma-separated names when downloading all or a subset of datasets."
+    )]
+    pub exclude: Option<String>,
 }
 
 pub fn download_benchmark(args: &DownloadBenchmarkArgs) {
@@ -35,34 +55,44 @@ pub fn download_benchmark(args: &DownloadBenchmarkArgs) {
         print_benchmark();
     }
 
-    if args.all {
-        if args.output.is_none() {
-            eprintln!(
-                "[thyme::download::benchmark] Please specify the output path (--output/-o) to save all downloaded benchmark datasets."
-            );
-            std::process::exit(1);
-        }
-
-        let output = std::path::Path::new(args.output.as_ref().unwrap());
-        std::fs::create_dir_all(output).unwrap();
-
-        progress_log("Downloading all benchmark datasets", args.verbose);
+    let output = destination(args.output.as_deref(), CacheDirectory::Benchmarks);
+
+    if args.all || args.only.is_some() || args.exclude.is_some() {
+        let available: Vec<&str> = BenchmarkDatasets::iter()
+            .map(|dataset| dataset.name())
+            .collect();
+
+        let names = filter_datasets(&available, args.only.as_deref(), args.exclude.as_deref())
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            });
+
+        progress_log(
+            &format!(
+                "Downloading {} benchmark datasets to {}",
+                names.len(),
+                output.display()
+            ),
+            args.verbose,
+        );
 
-        for dataset in BenchmarkDatasets::iter() {
-            dataset.download(output, args.verbose);
-        }
+        let datasets: Vec<BenchmarkDatasets> =
+            names.into_iter().map(BenchmarkDatasets::select).collect();
+        BenchmarkDatasets::download_all(&datasets, &output, args.verbose);
 
-        std::process::exit(1);
+        return;
     }
 
-    if !(args.name.is_some() && args.output.is_some()) {
-        eprintln!("[thyme::download::benchmark] Both --name/-n and --output/-o must be specified.");
+    if args.name.is_none() {
+        eprintln!(
+            "[thyme::download::benchmark] A dataset --name/-n or a subset of datasets (--all, --only, --exclude) must be specified."
+        );
         std::process::exit(1);
     }
 
-    let output = std::path::Path::new(args.output.as_ref().unwrap());
-    let benchmark = BenchmarkDatasets::select(args.name.as_ref().unwrap());
-    benchmark.download(output, args.verbose);
+    let dataset = BenchmarkDatasets::select(args.name.as_ref().unwrap());
+    BenchmarkDatasets::download_all([&dataset], &output, args.verbose);
 }
 
 fn print_benchmark() {
@@ -0,0 +1,94 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use clap::Args;
+use colored::Colorize;
+
+use super::destination;
+
+use thyme_data::CacheDirectory;
+use thyme_data::data::{BenchmarkDatasets, SegmentationDatasets};
+
+#[derive(Debug, Args)]
+#[command(about = "List all available segmentation and benchmark datasets.")]
+pub struct DownloadListDatasetsArgs {
+    #[arg(
+        short,
+        long,
+        visible_alias = "dest",
+        help = "Directory datasets were downloaded to, used to count their images. Defaults to the datasets and benchmarks directories of the thyme cache (THYME_CACHE)."
+    )]
+    pub output: Option<String>,
+}
+
+pub fn download_list_datasets(args: &DownloadListDatasetsArgs) {
+    let datasets = destination(args.output.as_deref(), CacheDirectory::Datasets);
+    let benchmarks = destination(args.output.as_deref(), CacheDirectory::Benchmarks);
+
+    print_datasets(
+        "Segmentation datasets",
+        SegmentationDatasets::iter().map(|dataset| {
+            (
+                dataset.name(),
+                dataset.data_size(),
+                dataset.n_images(&datasets),
+                dataset.license(),
+                dataset.description(),
+            )
+        }),
+    );
+
+    print_datasets(
+        "Benchmark datasets",
+        BenchmarkDatasets::iter().map(|dataset| {
+            (
+                dataset.name(),
+                dataset.data_size(),
+                dataset.n_images(&benchmarks),
+                dataset.license(),
+                dataset.description(),
+            )
+        }),
+    );
+}
+
+/// Print the name, size, number of images, license, and description of datasets
+fn print_datasets<'a, I>(title: &str, datasets: I)
+where
+    I: Iterator<Item = (&'a str, &'a str, Option<usize>, &'a str, &'a str)>,
+{
+    println!("{:^141}", "\n");
+    println!("| {:-^141} |", "");
+    println!("| {:^141} |", "thyme".truecolor(103, 194, 69).bold());
+    println!("| {:^141} |", title);
+    println!("| {:-^141} |", "");
+    println!(
+        "| {:^16} | {:^10} | {:^8} | {:^18} | {:^77} |",
+        "dataset".bold(),
+        "size (GB)".bold(),
+        "images".bold(),
+        "license".bold(),
+        "description".bold()
+    );
+    println!(
+        "| {:-^16} | {:-^10} | {:-^8} | {:-^18} | {:-^77} |",
+        "", "", "", "", ""
+    );
+
+    for (name, size, n_images, license, description) in datasets {
+        println!(
+            "| {:^16} | {:^10} | {:^8} | {:^18} | {:<77} |",
+            name,
+            size,
+            n_images.map_or("-".to_string(), |n| n.to_string()),
+            license,
+            description,
+        );
+    }
+
+    println!(
+        "| {:-^16} | {:-^10} | {:-^8} | {:-^18} | {:-^77} |",
+        "", "", "", "", ""
+    );
+    println!("{:^141}", "\n");
+}
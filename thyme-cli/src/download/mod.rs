@@ -1,13 +1,21 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::path::PathBuf;
+
 use clap::{Args, Subcommand};
 
+use thyme_core::error::ThymeError;
+use thyme_data::CacheDirectory;
+use thyme_data::suggest;
+
 mod benchmark;
+mod list;
 mod segmentation;
 mod weights;
 
 use benchmark::{DownloadBenchmarkArgs, download_benchmark};
+use list::{DownloadListDatasetsArgs, download_list_datasets};
 use segmentation::{DownloadSegmentationArgs, download_segmentation};
 use weights::{DownloadWeightsArgs, download_weights};
 
@@ -26,6 +34,7 @@ enum DownloadCommands {
     Segmentation(DownloadSegmentationArgs),
     Benchmark(DownloadBenchmarkArgs),
     Weights(DownloadWeightsArgs),
+    ListDatasets(DownloadListDatasetsArgs),
 }
 
 pub fn download(args: &DownloadArgs) {
@@ -33,5 +42,111 @@ pub fn download(args: &DownloadArgs) {
         DownloadCommands::Segmentation(segmentation) => download_segmentation(segmentation),
         DownloadCommands::Benchmark(benchmark) => download_benchmark(benchmark),
         DownloadCommands::Weights(weights) => download_weights(weights),
+        DownloadCommands::ListDatasets(list) => download_list_datasets(list),
+    }
+}
+
+/// Select datasets from comma-separated names to include and exclude
+///
+/// Every available dataset is included if `only` is not provided.
+///
+/// # Arguments
+///
+/// * `available` - Names of the available datasets
+/// * `only` - Comma-separated names of datasets to include
+/// * `exclude` - Comma-separated names of datasets to exclude
+fn filter_datasets<'a>(
+    available: &[&'a str],
+    only: Option<&str>,
+    exclude: Option<&str>,
+) -> Result<Vec<&'a str>, ThymeError> {
+    let only = parse_names(available, only)?;
+    let exclude = parse_names(available, exclude)?;
+
+    Ok(available
+        .iter()
+        .filter(|name| only.is_empty() || only.contains(name))
+        .filter(|name| !exclude.contains(name))
+        .copied()
+        .collect())
+}
+
+/// Split comma-separated dataset names and check that each is available
+fn parse_names<'a>(available: &[&str], names: Option<&'a str>) -> Result<Vec<&'a str>, ThymeError> {
+    let names: Vec<&str> = names
+        .unwrap_or_default()
+        .split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    if let Some(name) = names.iter().find(|name| !available.contains(name)) {
+        return Err(ThymeError::OtherError(suggest::not_found(
+            "Dataset",
+            name,
+            available.iter().copied(),
+        )));
+    }
+
+    Ok(names)
+}
+
+/// Resolve the directory datasets are downloaded to
+///
+/// Datasets are saved to the thyme cache unless an output directory is provided.
+///
+/// # Arguments
+///
+/// * `output` - Output directory overriding the cache
+/// * `cache` - Cache subdirectory used if no output directory is provided
+fn destination(output: Option<&str>, cache: CacheDirectory) -> PathBuf {
+    output.map(PathBuf::from).unwrap_or_else(|| cache.path())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const AVAILABLE: [&str; 3] = ["a_2020", "b_2021", "c_2022"];
+
+    #[test]
+    fn test_filter_datasets() {
+        assert_eq!(filter_datasets(&AVAILABLE, None, None).unwrap(), AVAILABLE);
+
+        assert_eq!(
+            filter_datasets(&AVAILABLE, Some("c_2022, a_2020"), None).unwrap(),
+            ["a_2020", "c_2022"]
+        );
+
+        assert_eq!(
+            filter_datasets(&AVAILABLE, None, Some("b_2021")).unwrap(),
+            ["a_2020", "c_2022"]
+        );
+
+        assert_eq!(
+            filter_datasets(&AVAILABLE, Some("a_2020,b_2021"), Some("a_2020")).unwrap(),
+            ["b_2021"]
+        );
+
+        assert!(filter_datasets(&AVAILABLE, Some("a_2020,d_2023"), None).is_err());
+        assert!(filter_datasets(&AVAILABLE, None, Some("d_2023")).is_err());
+
+        let err = filter_datasets(&AVAILABLE, Some("b_2012"), None).unwrap_err();
+        assert!(err.to_string().contains("Did you mean: b_2021"));
+    }
+
+    #[test]
+    fn test_destination() {
+        let dest = std::env::temp_dir().join("thyme_test_destination");
+        let output = destination(dest.to_str(), CacheDirectory::Datasets);
+
+        assert_eq!(output, dest);
+        assert!(!output.starts_with(thyme_data::get_thyme_cache()));
+
+        assert_eq!(
+            destination(None, CacheDirectory::Benchmarks),
+            CacheDirectory::Benchmarks.path()
+        );
     }
 }
@@ -4,7 +4,10 @@
 use clap::Args;
 use colored::Colorize;
 
+use super::{destination, filter_datasets};
+
 use thyme_core::ut::track::progress_log;
+use thyme_data::CacheDirectory;
 use thyme_data::data::SegmentationDatasets;
 
 #[derive(Debug, Args)]
@@ -17,7 +20,12 @@ pub struct DownloadSegmentationArgs {
     )]
     pub name: Option<String>,
 
-    #[arg(short, long, help = "Output path to save downloaded dataset.")]
+    #[arg(
+        short,
+        long,
+        visible_alias = "dest",
+        help = "Output directory to save downloaded datasets. Defaults to the datasets directory of the thyme cache (THYME_CACHE)."
+    )]
     pub output: Option<String>,
 
     #[arg(short = 'v', long, help = "Verbose output.")]
@@ -28,6 +36,18 @@ pub struct DownloadSegmentationArgs {
 
     #[arg(long, help = "Download all available segmentation datasets.")]
     pub all: bool,
+
+    #[arg(
+        long,
+        help = "Download a subset of segmentation datasets provided as comma-separated names (e.g. name_2020,name_2021)."
+    )]
+    pub only: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip one or more segmentation datasets provided as comma-separated names when downloading all or a subset of datasets."
+    )]
+    pub exclude: Option<String>,
 }
 
 pub fn download_segmentation(args: &DownloadSegmentationArgs) {
@@ -35,36 +55,46 @@ pub fn download_segmentation(args: &DownloadSegmentationArgs) {
         print_segmentation();
     }
 
-    if args.all {
-        if args.output.is_none() {
-            eprintln!(
-                "[thyme::download::segmentation] Please specify the output path (--output/-o) to save all downloaded segmentation datasets."
-            );
-            std::process::exit(1);
-        }
-
-        let output = std::path::Path::new(args.output.as_ref().unwrap());
-        std::fs::create_dir_all(output).unwrap();
-
-        progress_log("Downloading all segmentation datasets", args.verbose);
+    let output = destination(args.output.as_deref(), CacheDirectory::Datasets);
+
+    if args.all || args.only.is_some() || args.exclude.is_some() {
+        let available: Vec<&str> = SegmentationDatasets::iter()
+            .map(|dataset| dataset.name())
+            .collect();
+
+        let names = filter_datasets(&available, args.only.as_deref(), args.exclude.as_deref())
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            });
+
+        progress_log(
+            &format!(
+                "Downloading {} segmentation datasets to {}",
+                names.len(),
+                output.display()
+            ),
+            args.verbose,
+        );
 
-        for dataset in SegmentationDatasets::iter() {
-            dataset.download(output, args.verbose);
-        }
+        let datasets: Vec<SegmentationDatasets> = names
+            .into_iter()
+            .map(SegmentationDatasets::select)
+            .collect();
+        SegmentationDatasets::download_all(&datasets, &output, args.verbose);
 
-        std::process::exit(1);
+        return;
     }
 
-    if !(args.name.is_some() && args.output.is_some()) {
+    if args.name.is_none() {
         eprintln!(
-            "[thyme::download::segmentation] Both --name/-n and --output/-o must be specified."
+            "[thyme::download::segmentation] A dataset --name/-n or a subset of datasets (--all, --only, --exclude) must be specified."
         );
         std::process::exit(1);
     }
 
-    let output = std::path::Path::new(args.output.as_ref().unwrap());
-    let segmentation = SegmentationDatasets::select(args.name.as_ref().unwrap());
-    segmentation.download(output, args.verbose);
+    let dataset = SegmentationDatasets::select(args.name.as_ref().unwrap());
+    SegmentationDatasets::download_all([&dataset], &output, args.verbose);
 }
 
 fn print_segmentation() {
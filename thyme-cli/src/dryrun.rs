@@ -0,0 +1,515 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use thyme_core::constant;
+use thyme_core::cv::transform::AffineTransform;
+use thyme_core::error::ThymeError;
+use thyme_core::im::{self, SegmentCoords};
+use thyme_core::io::{self, ImageInfo};
+use thyme_core::ut::track::thousands_format;
+use thyme_data::data::Weights;
+use thyme_neural::nn::Models;
+
+use crate::failures;
+use crate::filter::crop_rect;
+use crate::pipeline::Segments;
+
+/// Name of the report written next to the output of a dry run
+pub const DRY_RUN_FILE: &str = "dry_run.json";
+
+/// Images listed in the printed report before the remaining images are summarized
+const PRINTED_IMAGES: usize = 20;
+
+/// Bytes per value of text tables if the float precision is not set
+const TEXT_VALUE_BYTES: u64 = 12;
+
+/// An image of a dry run with its header and the objects of its segments
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DryRunImage {
+    /// Path to the image
+    pub image: PathBuf,
+    /// Path to the segments paired with the image
+    pub segments: PathBuf,
+    /// Width of the image (pixels)
+    pub width: u32,
+    /// Height of the image (pixels)
+    pub height: u32,
+    /// Number of channels of the image
+    pub channels: u32,
+    /// Subpixel data type of the image (e.g. u8, u16, f32)
+    pub dtype: String,
+    /// Number of objects in the segments, before object filters are applied
+    pub objects: usize,
+    /// Pixels within the padded crops of the objects
+    pub crop_pixels: u64,
+    /// Error reading the image header or segments, if any
+    pub error: Option<String>,
+}
+
+/// Work that a run would do, reported without profiling, extracting, or embedding any object
+///
+/// Images are only read up to their headers. Objects are counted from the
+/// segments paired with each image (polygons, bounding boxes, or mask
+/// labels) before object filters are applied, so counts are an upper bound
+/// on the objects of the run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DryRun {
+    /// Command that was dry run (e.g. profile::polygons)
+    pub command: String,
+    /// Number of image and segment pairs
+    pub pairs: usize,
+    /// Number of objects across every pair
+    pub objects: usize,
+    /// Number of pairs whose image header or segments could not be read
+    pub failures: usize,
+    /// Number of columns of the output table, if the run writes a table
+    pub columns: Option<usize>,
+    /// Estimated size of the output before compression (bytes)
+    pub estimated_bytes: u64,
+    /// Output directory or file of the run
+    pub output: PathBuf,
+    /// Check if the directory the output is created in is writable
+    pub output_writable: bool,
+    /// Model of a neural run
+    pub model: Option<String>,
+    /// Path to the weights of the model
+    pub weights: Option<PathBuf>,
+    /// Check if the weights of the model were already downloaded
+    pub weights_present: Option<bool>,
+    /// Number of embedding dimensions produced by the model
+    pub embedding_dim: Option<usize>,
+    /// Header and object count of each image
+    pub images: Vec<DryRunImage>,
+}
+
+impl DryRun {
+    /// Summarize the scanned images of a run
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - Command that was dry run (e.g. profile::polygons)
+    /// * `output` - Output directory or file of the run
+    /// * `images` - Images returned by `scan`
+    pub fn new(command: &str, output: &Path, images: Vec<DryRunImage>) -> DryRun {
+        DryRun {
+            command: command.to_string(),
+            pairs: images.len(),
+            objects: images.iter().map(|image| image.objects).sum(),
+            failures: images.iter().filter(|image| image.error.is_some()).count(),
+            output: output.to_path_buf(),
+            output_writable: writable(&report_directory(output)),
+            images,
+            ..Default::default()
+        }
+    }
+
+    /// Estimate the size of an output table with one row per object
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - Number of columns of the table
+    /// * `format` - Format of the table (e.g. csv, pq)
+    /// * `float_precision` - Digits after the decimal point of floats in text tables
+    pub fn table(mut self, columns: usize, format: &str, float_precision: Option<usize>) -> Self {
+        let value_bytes = match format {
+            "csv" | "tsv" | "txt" => float_precision.map_or(TEXT_VALUE_BYTES, |p| p as u64 + 8),
+            _ => 4,
+        };
+
+        self.columns = Some(columns);
+        self.estimated_bytes += self.objects as u64 * columns as u64 * value_bytes;
+        self
+    }
+
+    /// Estimate the size of object crops written as images
+    ///
+    /// # Arguments
+    ///
+    /// * `image_crops` - Number of crops of the image pixels written per object (e.g. complete and foreground)
+    /// * `mask_crops` - Number of single channel 8-bit crops written per object (e.g. binary masks)
+    pub fn crops(mut self, image_crops: usize, mask_crops: usize) -> Self {
+        self.estimated_bytes += self
+            .images
+            .iter()
+            .map(|image| {
+                let subpixel = image.channels as u64 * dtype_bytes(&image.dtype);
+                image.crop_pixels * (image_crops as u64 * subpixel + mask_crops as u64)
+            })
+            .sum::<u64>();
+
+        self
+    }
+
+    /// Report the weights and embedding dimension of the model of a neural run
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - Name of the model (e.g. dino_vit_small)
+    pub fn model(mut self, model: &str) -> Self {
+        let weights = Weights::iter()
+            .find(|weights| weights.model_name() == model)
+            .map(|weights| weights.path());
+
+        self.model = Some(model.to_string());
+        self.weights_present = weights.as_ref().map(|path| path.is_file());
+        self.weights = weights;
+        self.embedding_dim = Models::embedding_dim(model);
+        self
+    }
+
+    /// Path the report is written to, next to the output of the run
+    pub fn path(&self) -> PathBuf {
+        report_directory(&self.output).join(DRY_RUN_FILE)
+    }
+
+    /// Format the report as a table of images followed by the estimates of the run
+    pub fn format(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "| {:<40} | {:>6} | {:>6} | {:>8} | {:>5} | {:>8} |",
+                "image", "width", "height", "channels", "dtype", "objects"
+            ),
+            format!(
+                "| {:-<40} | {:->6} | {:->6} | {:->8} | {:->5} | {:->8} |",
+                "", "", "", "", "", ""
+            ),
+        ];
+
+        for image in self.images.iter().take(PRINTED_IMAGES) {
+            let name = image
+                .image
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            lines.push(match &image.error {
+                Some(error) => format!("| {:<40} | ERROR: {}", name, error),
+                None => format!(
+                    "| {:<40} | {:>6} | {:>6} | {:>8} | {:>5} | {:>8} |",
+                    name, image.width, image.height, image.channels, image.dtype, image.objects
+                ),
+            });
+        }
+
+        if self.images.len() > PRINTED_IMAGES {
+            lines.push(format!(
+                "| ... {} more images (see {})",
+                thousands_format(self.images.len() - PRINTED_IMAGES),
+                DRY_RUN_FILE
+            ));
+        }
+
+        lines.push(String::new());
+        lines.push(format!(
+            "Pairs      {} ({} failed)",
+            thousands_format(self.pairs),
+            thousands_format(self.failures)
+        ));
+        lines.push(format!("Objects    {}", thousands_format(self.objects)));
+
+        if let Some(columns) = self.columns {
+            lines.push(format!("Columns    {}", thousands_format(columns)));
+        }
+
+        lines.push(format!(
+            "Size       {:.2} MiB (estimated, uncompressed)",
+            self.estimated_bytes as f64 / (1u64 << 20) as f64
+        ));
+
+        lines.push(format!(
+            "Output     {} ({})",
+            self.output.display(),
+            match self.output_writable {
+                true => "writable",
+                false => "not writable",
+            }
+        ));
+
+        if let Some(model) = &self.model {
+            lines.push(format!(
+                "Model      {} ({} embedding dimensions, weights {})",
+                model,
+                self.embedding_dim.unwrap_or_default(),
+                match self.weights_present {
+                    Some(true) => "present",
+                    _ => "missing and downloaded when run",
+                }
+            ));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Print the report and write it next to the output of the run
+    ///
+    /// Returns the path of the report, or an error without writing the
+    /// report if the output location is not writable.
+    pub fn finish(&self) -> Result<PathBuf, ThymeError> {
+        println!("[thyme::{}] Dry run.\n\n{}\n", self.command, self.format());
+
+        if !self.output_writable {
+            return Err(ThymeError::OtherError(format!(
+                "Output location {} is not writable",
+                report_directory(&self.output).display()
+            )));
+        }
+
+        let path = self.path();
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+        io::write_bytes_atomic(&path, json)?;
+
+        println!(
+            "[thyme::{}] Dry run report written to {}.",
+            self.command,
+            path.display()
+        );
+
+        Ok(path)
+    }
+}
+
+/// Read the header and the object bounding boxes of each image and segment pair
+///
+/// # Arguments
+///
+/// * `pairs` - Identifier, image, and segment paths for each pair
+/// * `pad` - Padding added around each object crop
+/// * `boxes` - Reads the unpadded bounding boxes of the objects of a pair from its identifier, image, segments, and image header
+pub fn scan<F>(pairs: &[(String, PathBuf, PathBuf)], pad: u32, boxes: F) -> Vec<DryRunImage>
+where
+    F: Fn(&str, &Path, &Path, &ImageInfo) -> Result<Vec<[f32; 4]>, ThymeError> + Sync,
+{
+    pairs
+        .par_iter()
+        .map(|(id, image, segments)| {
+            let mut row = DryRunImage {
+                image: image.clone(),
+                segments: segments.clone(),
+                ..Default::default()
+            };
+
+            let scanned = io::probe_image(image).and_then(|info| {
+                let boxes = boxes(id, image, segments, &info)?;
+                Ok((info, boxes))
+            });
+
+            match scanned {
+                Ok((info, boxes)) => {
+                    row.crop_pixels = boxes
+                        .iter()
+                        .filter_map(|xyxy| crop_rect(xyxy, pad, info.width, info.height))
+                        .map(|crop| crop.width as u64 * crop.height as u64)
+                        .sum();
+
+                    row.objects = boxes.len();
+                    row.width = info.width;
+                    row.height = info.height;
+                    row.channels = info.channels;
+                    row.dtype = info.dtype;
+                }
+                Err(err) => row.error = Some(err.to_string()),
+            }
+
+            row
+        })
+        .collect()
+}
+
+/// Bounding boxes of the polygons in a file, read one polygon at a time
+///
+/// # Arguments
+///
+/// * `path` - Path to the polygons
+/// * `segment_coords` - Coordinate system of the polygon vertices
+/// * `transform` - Affine transform registering pixel vertices to the image
+/// * `info` - Header of the image the polygons are paired with
+pub fn polygon_boxes(
+    path: &Path,
+    segment_coords: SegmentCoords,
+    transform: Option<&AffineTransform>,
+    info: &ImageInfo,
+) -> Result<Vec<[f32; 4]>, ThymeError> {
+    io::PolygonStream::open(path)?
+        .map(|points| {
+            let mut points = points?;
+
+            if let Some(transform) = transform {
+                transform.apply_points(&mut points);
+            }
+
+            let [min_x, min_y, max_x, max_y] = failures::bounds(&points);
+
+            Ok(match segment_coords {
+                SegmentCoords::Pixels => [min_x, min_y, max_x, max_y],
+                SegmentCoords::Normalized => {
+                    let (w, h) = (info.width as f32, info.height as f32);
+                    [min_x * w, min_y * h, max_x * w, max_y * h]
+                }
+            })
+        })
+        .collect()
+}
+
+/// Bounding boxes of the objects of labeled masks (e.g. each slice of a mask stack)
+///
+/// # Arguments
+///
+/// * `masks` - Labeled masks
+pub fn mask_boxes(masks: Vec<im::ThymeMask>) -> Result<Vec<[f32; 4]>, ThymeError> {
+    let mut boxes = Vec::new();
+
+    for mask in masks {
+        boxes.extend(Segments::from_mask(mask)?.bounding_boxes.to_xyxy());
+    }
+
+    Ok(boxes)
+}
+
+/// Format of the table or embeddings written to an output, defaulting to csv for output directories
+///
+/// # Arguments
+///
+/// * `output` - Output directory or file
+pub fn output_format(output: &Path) -> String {
+    output
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+        .filter(|ext| {
+            constant::SUPPORTED_TABLE_FORMATS
+                .iter()
+                .chain(&constant::SUPPORTED_EMBEDDING_FORMATS)
+                .any(|format| format == ext)
+        })
+        .unwrap_or("csv".to_string())
+}
+
+/// Directory the output of a run is created in, which is where its dry run report is written
+fn report_directory(output: &Path) -> PathBuf {
+    match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Check if a file can be created in a directory
+fn writable(directory: &Path) -> bool {
+    let probe = directory.join(format!(".thyme_dry_run_{}", std::process::id()));
+
+    match std::fs::File::create(&probe) {
+        Ok(_) => std::fs::remove_file(&probe).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Size of a subpixel of a data type (bytes)
+fn dtype_bytes(dtype: &str) -> u64 {
+    match dtype {
+        "u8" | "i8" => 1,
+        "u16" | "i16" => 2,
+        "u64" | "i64" | "f64" => 8,
+        _ => 4,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_dry_run_scan() {
+        let directory = std::env::temp_dir().join("thyme_test_dry_run_scan");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 64,
+            height: 48,
+            objects: 5,
+            radius: [3.0, 6.0],
+            seed: 7,
+            ..Default::default()
+        }
+        .write(1, &directory)
+        .unwrap();
+
+        let image = directory.join("synth_0000_image.png");
+        let mask = directory.join("synth_0000_mask.png");
+        let polygons = directory.join("synth_0000_polygons.json");
+
+        let pairs = |segments: &Path| {
+            vec![
+                (
+                    "synth_0000".to_string(),
+                    image.clone(),
+                    segments.to_path_buf(),
+                ),
+                (
+                    "missing".to_string(),
+                    directory.join("missing.png"),
+                    segments.to_path_buf(),
+                ),
+            ]
+        };
+
+        let from_polygons = scan(&pairs(&polygons), 1, |_, _, segments, info| {
+            polygon_boxes(segments, SegmentCoords::Pixels, None, info)
+        });
+
+        let from_mask = scan(&pairs(&mask), 1, |_, _, segments, _| {
+            mask_boxes(vec![im::ThymeMask::open(segments)?])
+        });
+
+        for images in [&from_polygons, &from_mask] {
+            assert_eq!(images[0].objects, 5);
+            assert_eq!((images[0].width, images[0].height), (64, 48));
+            assert!(images[0].crop_pixels > 0);
+            assert!(images[1].error.is_some());
+        }
+
+        let output = directory.join("output");
+        let report = DryRun::new("profile::polygons", &output, from_polygons)
+            .table(10, "csv", None)
+            .crops(1, 1);
+
+        assert_eq!(report.pairs, 2);
+        assert_eq!(report.objects, 5);
+        assert_eq!(report.failures, 1);
+        assert!(report.output_writable);
+
+        let image = &report.images[0];
+        let subpixel = image.channels as u64 * dtype_bytes(&image.dtype);
+        assert_eq!(
+            report.estimated_bytes,
+            5 * 10 * TEXT_VALUE_BYTES + image.crop_pixels * (subpixel + 1)
+        );
+
+        let text = report.format();
+        assert!(text.contains("synth_0000_image.png"));
+        assert!(text.contains("ERROR"));
+
+        // Only the report is written, next to the output
+        let path = report.finish().unwrap();
+        assert_eq!(path, directory.join(DRY_RUN_FILE));
+        assert!(!output.exists());
+
+        let written: DryRun =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, report);
+
+        let model = DryRun::new("neural::mask", &output, vec![]).model("dino_vit_small");
+        assert_eq!(model.embedding_dim, Some(384));
+        assert!(model.weights.is_some());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
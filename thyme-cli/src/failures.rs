@@ -0,0 +1,448 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+
+use thyme_core::cv::points::resample_points;
+use thyme_core::error::ThymeError;
+
+/// Header for the objects that failed within an image written alongside batch outputs
+pub const FAILED_OBJECTS_HEADER: &str =
+    "image\tobject\tbbox_min_x\tbbox_min_y\tbbox_max_x\tbbox_max_y\tstage\terror";
+
+/// Step of an object's work at which it failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Locating or cropping the object from the image
+    Crop,
+    /// Drawing the binary mask of the object
+    Rasterize,
+    /// Computing the descriptors of the object
+    Measure,
+    /// Embedding the object with a model
+    Embed,
+    /// Exceeding the vertex or time budget of an object
+    Budget,
+}
+
+impl Stage {
+    /// Name of the stage in the failed objects table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Crop => "crop",
+            Stage::Rasterize => "rasterize",
+            Stage::Measure => "measure",
+            Stage::Embed => "embed",
+            Stage::Budget => "budget",
+        }
+    }
+}
+
+/// Error of a single object and the stage it failed at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectError {
+    pub stage: Stage,
+    pub message: String,
+}
+
+/// Run the work of a single object so that its failure never fails the image
+///
+/// The work sets the stage it has reached as it progresses. Errors and panics
+/// are returned as an `ObjectError` at the last stage that was entered, which
+/// starts at `Stage::Crop`.
+///
+/// # Arguments
+///
+/// * `work` - Work of the object given the stage it has reached
+///
+/// # Examples
+///
+/// ```
+/// use thyme_cli::failures::{Stage, catch};
+/// use thyme_core::error::ThymeError;
+///
+/// let failed = catch(|stage| -> Result<(), ThymeError> {
+///     *stage = Stage::Measure;
+///     Err(ThymeError::OtherError("No pixels".to_string()))
+/// });
+///
+/// let err = failed.unwrap_err();
+/// assert_eq!(err.stage, Stage::Measure);
+/// assert!(err.message.contains("No pixels"));
+/// ```
+pub fn catch<T>(work: impl FnOnce(&mut Stage) -> Result<T, ThymeError>) -> Result<T, ObjectError> {
+    let mut stage = Stage::Crop;
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| work(&mut stage)));
+
+    let message = match result {
+        Ok(Ok(value)) => return Ok(value),
+        Ok(Err(err)) => err.to_string(),
+        Err(payload) => panic_message(payload),
+    };
+
+    Err(ObjectError { stage, message })
+}
+
+/// Message of a caught panic
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+    {
+        Some(message) => format!("Panicked: {}", message),
+        None => "Panicked".to_string(),
+    }
+}
+
+/// Handling of objects with more vertices than the vertex budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OversizedPolicy {
+    /// Resample the outline to the vertex budget and measure the object
+    #[default]
+    Resample,
+    /// Skip the object without measuring it
+    Skip,
+}
+
+impl OversizedPolicy {
+    /// Parse an oversized object policy from a string (resample, skip)
+    pub fn parse(policy: &str) -> Result<Self, String> {
+        match policy {
+            "resample" => Ok(OversizedPolicy::Resample),
+            "skip" => Ok(OversizedPolicy::Skip),
+            _ => Err(format!(
+                "Invalid oversized_policy '{}'. Must be one of: resample, skip",
+                policy
+            )),
+        }
+    }
+}
+
+/// Vertex and time limits guarding the measurement of each object
+///
+/// Pathological objects (e.g. malformed polygons with hundreds of thousands
+/// of self-intersecting vertices) can take hours to measure and stall a run.
+/// Outlines with more vertices than `max_points` are resampled or skipped
+/// before they are measured, and the descriptor families of an object are
+/// abandoned once its measurement has taken longer than `max_seconds`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ObjectBudget {
+    /// Maximum number of outline vertices of an object
+    pub max_points: Option<usize>,
+    /// Handling of objects with more vertices than `max_points`
+    pub oversized: OversizedPolicy,
+    /// Maximum time spent measuring an object (seconds)
+    pub max_seconds: Option<f32>,
+}
+
+impl ObjectBudget {
+    /// Enforce the vertex budget on the outline of an object
+    ///
+    /// Outlines within the budget are left unchanged and return `Ok(None)`.
+    /// Outlines over the budget are resampled to `max_points` vertices, and
+    /// the error recording the budget is returned as `Ok(Some(_))` if the
+    /// object is still measured or as `Err(_)` if the object is skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Outline vertices of the object
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_cli::failures::{ObjectBudget, OversizedPolicy};
+    ///
+    /// let budget = ObjectBudget {
+    ///     max_points: Some(4),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let mut points = vec![[0., 0.], [1., 0.], [2., 0.], [2., 2.], [0., 2.]];
+    /// let resampled = budget.check_points(&mut points).unwrap();
+    ///
+    /// assert_eq!(points.len(), 4);
+    /// assert!(resampled.unwrap().message.contains("resampled"));
+    ///
+    /// let budget = ObjectBudget {
+    ///     oversized: OversizedPolicy::Skip,
+    ///     ..budget
+    /// };
+    ///
+    /// assert!(budget.check_points(&mut vec![[0., 0.]; 5]).is_err());
+    /// ```
+    pub fn check_points(
+        &self,
+        points: &mut Vec<[f32; 2]>,
+    ) -> Result<Option<ObjectError>, ObjectError> {
+        let Some(max_points) = self.max_points.filter(|&max| points.len() > max) else {
+            return Ok(None);
+        };
+
+        let n = points.len();
+        resample_points(points, max_points);
+
+        let error = |outcome: &str| ObjectError {
+            stage: Stage::Budget,
+            message: format!(
+                "Object has {} vertices which exceeds max_points_per_object {}. {}",
+                n, max_points, outcome
+            ),
+        };
+
+        match self.oversized {
+            OversizedPolicy::Resample => Ok(Some(error(&format!(
+                "Outline was resampled to {} vertices",
+                max_points
+            )))),
+            OversizedPolicy::Skip => Err(error("Object was skipped")),
+        }
+    }
+
+    /// Start timing the measurement of an object
+    pub fn watchdog(&self) -> Watchdog {
+        Watchdog {
+            start: Instant::now(),
+            limit: self
+                .max_seconds
+                .map(|seconds| (seconds, Duration::from_secs_f32(seconds))),
+            expired: None,
+        }
+    }
+}
+
+/// Timer abandoning the remaining descriptor families of an object over its time budget
+///
+/// The time is checked between descriptor families, so a single family is
+/// never interrupted but no further family is started once the budget is
+/// exceeded. Descriptors of abandoned families are filled with NaN and
+/// handled by the non-finite policy.
+#[derive(Debug)]
+pub struct Watchdog {
+    start: Instant,
+    limit: Option<(f32, Duration)>,
+    expired: Option<Duration>,
+}
+
+impl Watchdog {
+    /// Check if the remaining descriptor families should be abandoned
+    ///
+    /// Once expired, the watchdog stays expired for the rest of the object.
+    pub fn expired(&mut self) -> bool {
+        if self.expired.is_some() {
+            return true;
+        }
+
+        let elapsed = self.start.elapsed();
+
+        if self.limit.is_some_and(|(_, limit)| elapsed > limit) {
+            self.expired = Some(elapsed);
+        }
+
+        self.expired.is_some()
+    }
+
+    /// Error recording the time budget if the measurement was abandoned
+    ///
+    /// # Arguments
+    ///
+    /// * `measured` - Number of descriptor columns measured before the budget was exceeded
+    pub fn error(&self, measured: usize) -> Option<ObjectError> {
+        let (seconds, _) = self.limit?;
+
+        self.expired.map(|elapsed| ObjectError {
+            stage: Stage::Budget,
+            message: format!(
+                "Measurement took {:.1} seconds which exceeds max_seconds_per_object {}. Descriptors after the first {} columns were not computed",
+                elapsed.as_secs_f32(),
+                seconds,
+                measured
+            ),
+        })
+    }
+}
+
+/// Objects that failed within a single image
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FailedObjects {
+    objects: Vec<(u32, [f32; 4], ObjectError)>,
+}
+
+impl FailedObjects {
+    /// Record a failed object
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Object identifier
+    /// * `xyxy` - Bounding box of the object in image coordinates
+    /// * `error` - Error of the object
+    pub fn add(&mut self, id: u32, xyxy: [f32; 4], error: ObjectError) {
+        self.objects.push((id, xyxy, error));
+    }
+
+    /// Number of failed objects
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Whether no object failed
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Format the rows of the failed objects table
+    ///
+    /// Tabs and line breaks in error messages are replaced with spaces so
+    /// that each failure is a single row.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Image identifier
+    pub fn rows(&self, image: &str) -> Vec<String> {
+        self.objects
+            .iter()
+            .map(|(id, xyxy, error)| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    image,
+                    id,
+                    xyxy[0],
+                    xyxy[1],
+                    xyxy[2],
+                    xyxy[3],
+                    error.stage.as_str(),
+                    error.message.replace(['\t', '\n', '\r'], " ")
+                )
+            })
+            .collect()
+    }
+}
+
+/// Bounding box of polygon vertices, ignoring NaN coordinates
+///
+/// # Arguments
+///
+/// * `points` - Vertices of the polygon
+pub fn bounds(points: &[[f32; 2]]) -> [f32; 4] {
+    points
+        .iter()
+        .fold([f32::NAN; 4], |[min_x, min_y, max_x, max_y], &[x, y]| {
+            [min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)]
+        })
+}
+
+/// Assemble the failed objects table from its rows
+pub fn failed_objects(rows: &[String]) -> String {
+    let mut table = String::from(FAILED_OBJECTS_HEADER);
+
+    for row in rows {
+        table.push('\n');
+        table.push_str(row);
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_catch() {
+        assert_eq!(catch(|_| Ok(3)), Ok(3));
+
+        let err = catch(|stage| -> Result<(), ThymeError> {
+            *stage = Stage::Rasterize;
+            Err(ThymeError::PolygonsValueError)
+        })
+        .unwrap_err();
+
+        assert_eq!(err.stage, Stage::Rasterize);
+        assert_eq!(err.message, ThymeError::PolygonsValueError.to_string());
+
+        // Panics are caught at the stage that was reached
+        let err = catch(|stage| -> Result<(), ThymeError> {
+            *stage = Stage::Embed;
+            panic!("out of {}", "memory");
+        })
+        .unwrap_err();
+
+        assert_eq!(err.stage, Stage::Embed);
+        assert_eq!(err.message, "Panicked: out of memory");
+    }
+
+    #[test]
+    fn test_object_budget() {
+        assert_eq!(OversizedPolicy::parse("skip"), Ok(OversizedPolicy::Skip));
+        assert!(OversizedPolicy::parse("drop").is_err());
+
+        let mut points: Vec<[f32; 2]> = (0..1000)
+            .map(|i| {
+                let theta = i as f32 / 1000.0 * std::f32::consts::TAU;
+                [10.0 * theta.cos(), 10.0 * theta.sin()]
+            })
+            .collect();
+
+        // Outlines within the budget are unchanged
+        let budget = ObjectBudget::default();
+        assert_eq!(budget.check_points(&mut points), Ok(None));
+        assert_eq!(points.len(), 1000);
+
+        let budget = ObjectBudget {
+            max_points: Some(100),
+            ..Default::default()
+        };
+
+        let err = budget.check_points(&mut points).unwrap().unwrap();
+        assert_eq!(points.len(), 100);
+        assert_eq!(err.stage, Stage::Budget);
+        assert!(err.message.contains("1000 vertices"), "{}", err.message);
+
+        // Measurements without a time budget never expire
+        let mut watchdog = budget.watchdog();
+        assert!(!watchdog.expired());
+        assert_eq!(watchdog.error(3), None);
+
+        let mut watchdog = ObjectBudget {
+            max_seconds: Some(0.0),
+            ..Default::default()
+        }
+        .watchdog();
+
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(watchdog.expired());
+        assert!(watchdog.expired());
+
+        let err = watchdog.error(6).unwrap();
+        assert_eq!(err.stage, Stage::Budget);
+        assert!(err.message.contains("first 6 columns"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_failed_objects() {
+        let mut failed = FailedObjects::default();
+        assert!(failed.is_empty());
+
+        failed.add(
+            4,
+            bounds(&[[1., f32::NAN], [3., 2.], [2., 5.]]),
+            ObjectError {
+                stage: Stage::Measure,
+                message: "Line\none\ttwo".to_string(),
+            },
+        );
+
+        assert_eq!(failed.len(), 1);
+        assert_eq!(
+            failed.rows("a01"),
+            ["a01\t4\t1\t2\t3\t5\tmeasure\tLine one two"]
+        );
+
+        let table = failed_objects(&failed.rows("a01"));
+        assert_eq!(table.lines().next().unwrap(), FAILED_OBJECTS_HEADER);
+        assert_eq!(table.lines().count(), 2);
+    }
+}
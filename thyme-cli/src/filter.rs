@@ -0,0 +1,686 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::fmt;
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::ImageSummary;
+use thyme_core::ut::expr::Expression;
+
+/// Header for the per-image object counts written alongside batch outputs
+pub const OBJECT_COUNTS_HEADER: &str = "image\tobjects\tdropped_border\tdropped_min_size\tdropped_min_area\tdropped_max_area\tdropped_invalid_polygon\tdropped_outside_image";
+
+/// Header for the images skipped by an image gate written alongside batch outputs
+pub const SKIPPED_IMAGES_HEADER: &str =
+    "image\treason\tforeground_fraction\tfocus_score\tbackground_mean\tbackground_median";
+
+/// Reason an object was excluded before profiling, processing, or embedding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    Border,
+    MinSize,
+    MinArea,
+    MaxArea,
+    InvalidPolygon,
+    OutsideImage,
+}
+
+/// Handling of objects that touch the image border
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BorderPolicy {
+    /// Keep border objects
+    #[default]
+    Keep,
+    /// Exclude border objects
+    Drop,
+    /// Keep border objects and record which objects touch the border
+    Flag,
+}
+
+impl BorderPolicy {
+    /// Parse a border policy from a string (keep, drop, flag)
+    pub fn parse(policy: &str) -> Result<Self, String> {
+        match policy {
+            "keep" => Ok(BorderPolicy::Keep),
+            "drop" => Ok(BorderPolicy::Drop),
+            "flag" => Ok(BorderPolicy::Flag),
+            _ => Err(format!(
+                "Invalid border_policy '{}'. Must be one of: keep, drop, flag",
+                policy
+            )),
+        }
+    }
+
+    /// Border policy of commands that only support dropping border objects
+    pub fn from_drop_borders(drop_borders: bool) -> Self {
+        if drop_borders {
+            BorderPolicy::Drop
+        } else {
+            BorderPolicy::Keep
+        }
+    }
+}
+
+/// Handling of objects with non-finite (NaN or infinite) descriptors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Write non-finite values as is
+    #[default]
+    Keep,
+    /// Replace non-finite values with zero
+    Zero,
+    /// Exclude objects with any non-finite value
+    DropObject,
+}
+
+impl NonFinitePolicy {
+    /// Parse a non-finite policy from a string (keep, zero, drop-object)
+    pub fn parse(policy: &str) -> Result<Self, String> {
+        match policy {
+            "keep" => Ok(NonFinitePolicy::Keep),
+            "zero" => Ok(NonFinitePolicy::Zero),
+            "drop-object" => Ok(NonFinitePolicy::DropObject),
+            _ => Err(format!(
+                "Invalid nonfinite_policy '{}'. Must be one of: keep, zero, drop-object",
+                policy
+            )),
+        }
+    }
+
+    /// Description of how non-finite values were handled
+    pub fn describe(&self) -> &'static str {
+        match self {
+            NonFinitePolicy::Keep => "non-finite values were kept",
+            NonFinitePolicy::Zero => "non-finite values were replaced with zero",
+            NonFinitePolicy::DropObject => "objects with non-finite values were dropped",
+        }
+    }
+}
+
+/// Check if an object touches the edge of the image
+///
+/// The unpadded bounding box is compared against the image extent so that
+/// padding never makes an object near the edge count as a border object.
+///
+/// # Arguments
+///
+/// * `xyxy` - Unpadded bounding box of the object in pixel coordinates
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+pub fn touches_border(xyxy: &[f32; 4], width: u32, height: u32) -> bool {
+    let [min_x, min_y, max_x, max_y] = *xyxy;
+
+    min_x <= 0.0 || min_y <= 0.0 || max_x >= width as f32 - 1.0 || max_y >= height as f32 - 1.0
+}
+
+/// Padded crop of an object clipped to the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CropRect {
+    /// Minimum x coordinate of the crop
+    pub x: u32,
+    /// Minimum y coordinate of the crop
+    pub y: u32,
+    /// Width of the crop (always at least 1)
+    pub width: u32,
+    /// Height of the crop (always at least 1)
+    pub height: u32,
+}
+
+impl CropRect {
+    /// Crop as [x, y, width, height]
+    pub fn as_xywh(&self) -> [u32; 4] {
+        [self.x, self.y, self.width, self.height]
+    }
+}
+
+/// Pad a bounding box and clip it to the image
+///
+/// Returns `None` if the padded box does not overlap the image (e.g. boxes
+/// lying entirely outside the image or with non-finite coordinates), since
+/// there are no pixels to crop. All arithmetic is done on clamped floats so
+/// no coordinate can underflow.
+///
+/// # Arguments
+///
+/// * `xyxy` - Unpadded bounding box of the object in pixel coordinates
+/// * `pad` - Padding added to each side of the bounding box
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+pub fn crop_rect(xyxy: &[f32; 4], pad: u32, width: u32, height: u32) -> Option<CropRect> {
+    if xyxy.iter().any(|v| !v.is_finite()) {
+        return None;
+    }
+
+    let [min_x, min_y, max_x, max_y] = *xyxy;
+
+    let pad = pad as f32;
+    let (width, height) = (width as f32, height as f32);
+
+    let min_x = (min_x - pad).clamp(0.0, width) as u32;
+    let min_y = (min_y - pad).clamp(0.0, height) as u32;
+    let max_x = (max_x + pad).clamp(0.0, width) as u32;
+    let max_y = (max_y + pad).clamp(0.0, height) as u32;
+
+    if max_x <= min_x || max_y <= min_y {
+        return None;
+    }
+
+    Some(CropRect {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x,
+        height: max_y - min_y,
+    })
+}
+
+/// Filters applied to each object before it is padded and cropped
+///
+/// All filters are evaluated on the unpadded object so that the same objects
+/// are kept regardless of the padding added around them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ObjectFilter {
+    /// Handling of objects whose bounding box touches the image edge
+    pub border_policy: BorderPolicy,
+    /// Minimum bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum object area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum object area (pixels)
+    pub max_area: Option<f32>,
+    /// Exclude polygons that are self-intersecting and could not be repaired
+    pub skip_invalid_polygons: bool,
+}
+
+impl ObjectFilter {
+    /// Check if an object passes the filters
+    ///
+    /// # Arguments
+    ///
+    /// * `xyxy` - Unpadded bounding box of the object (min_x, min_y, max_x, max_y)
+    /// * `area` - Area of the object (pixels)
+    /// * `width` - Width of the image
+    /// * `height` - Height of the image
+    pub fn check(
+        &self,
+        xyxy: &[f32; 4],
+        area: f32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), DropReason> {
+        let [min_x, min_y, max_x, max_y] = *xyxy;
+
+        if self.border_policy == BorderPolicy::Drop && touches_border(xyxy, width, height) {
+            return Err(DropReason::Border);
+        }
+
+        let min_size = self.min_size as f32;
+
+        if max_x - min_x < min_size || max_y - min_y < min_size {
+            return Err(DropReason::MinSize);
+        }
+
+        if self.min_area.is_some_and(|min_area| area < min_area) {
+            return Err(DropReason::MinArea);
+        }
+
+        if self.max_area.is_some_and(|max_area| area > max_area) {
+            return Err(DropReason::MaxArea);
+        }
+
+        Ok(())
+    }
+
+    /// Check if a polygon passes the validity filter
+    ///
+    /// # Arguments
+    ///
+    /// * `degenerate` - Polygon was flagged as degenerate during validation
+    pub fn check_polygon(&self, degenerate: bool) -> Result<(), DropReason> {
+        if self.skip_invalid_polygons && degenerate {
+            return Err(DropReason::InvalidPolygon);
+        }
+
+        Ok(())
+    }
+
+    /// Validate the filter arguments, returning an error message if invalid
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_size < 1 {
+            return Err("min_size cannot be less than 1.0.".to_string());
+        }
+
+        if self.min_area.is_some_and(|min_area| min_area < 0.0) {
+            return Err("min_area cannot be negative.".to_string());
+        }
+
+        if let (Some(min_area), Some(max_area)) = (self.min_area, self.max_area) {
+            if max_area < min_area {
+                return Err("max_area cannot be less than min_area.".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Number of objects dropped by each filter for a single image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DropCounts {
+    pub border: usize,
+    pub min_size: usize,
+    pub min_area: usize,
+    pub max_area: usize,
+    pub invalid_polygon: usize,
+    pub outside_image: usize,
+}
+
+impl DropCounts {
+    /// Record a dropped object
+    pub fn add(&mut self, reason: DropReason) {
+        match reason {
+            DropReason::Border => self.border += 1,
+            DropReason::MinSize => self.min_size += 1,
+            DropReason::MinArea => self.min_area += 1,
+            DropReason::MaxArea => self.max_area += 1,
+            DropReason::InvalidPolygon => self.invalid_polygon += 1,
+            DropReason::OutsideImage => self.outside_image += 1,
+        }
+    }
+}
+
+impl fmt::Display for DropCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.border,
+            self.min_size,
+            self.min_area,
+            self.max_area,
+            self.invalid_polygon,
+            self.outside_image
+        )
+    }
+}
+
+/// Format a row of the object counts table
+///
+/// # Arguments
+///
+/// * `id` - Image identifier
+/// * `objects` - Number of objects kept
+/// * `dropped` - Number of objects dropped by each filter
+pub fn object_counts_row(id: &str, objects: usize, dropped: &DropCounts) -> String {
+    format!("{}\t{}\t{}", id, objects, dropped)
+}
+
+/// Assemble the object counts table from its rows
+pub fn object_counts(rows: &[String]) -> String {
+    let mut table = String::from(OBJECT_COUNTS_HEADER);
+
+    for row in rows {
+        table.push('\n');
+        table.push_str(row);
+    }
+
+    table
+}
+
+/// Skip whole images whose image-level metrics match an expression
+///
+/// Images are gated on their `ImageSummary` before any object is processed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageGate {
+    expression: Expression,
+}
+
+impl ImageGate {
+    /// Parse an optional gate expression over `ImageSummary::METRICS`
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Conditions joined by `||` and `&&` (e.g. `focus_score<30`)
+    pub fn new(expression: Option<&str>) -> Result<Option<ImageGate>, ThymeError> {
+        expression
+            .map(|expression| {
+                Ok(ImageGate {
+                    expression: Expression::parse(expression, &ImageSummary::METRICS)?,
+                })
+            })
+            .transpose()
+    }
+
+    /// Return the reason an image is skipped, if it is skipped
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - Image-level metrics of the image
+    pub fn check(&self, summary: &ImageSummary) -> Option<String> {
+        self.expression.matches(|metric| summary.metric(metric))
+    }
+}
+
+/// Format a row of the skipped images table
+///
+/// # Arguments
+///
+/// * `id` - Image identifier
+/// * `reason` - Clause of the gate expression matched by the image
+/// * `summary` - Image-level metrics of the image
+pub fn skipped_images_row(id: &str, reason: &str, summary: &ImageSummary) -> String {
+    let metrics: Vec<String> = ImageSummary::METRICS
+        .iter()
+        .map(|metric| summary.metric(metric).to_string())
+        .collect();
+
+    format!("{}\t{}\t{}", id, reason, metrics.join("\t"))
+}
+
+/// Assemble the skipped images table from its rows
+pub fn skipped_images(rows: &[String]) -> String {
+    let mut table = String::from(SKIPPED_IMAGES_HEADER);
+
+    for row in rows {
+        table.push('\n');
+        table.push_str(row);
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const WIDTH: u32 = 100;
+    const HEIGHT: u32 = 100;
+
+    // A 20 x 20 object well away from the image borders
+    const XYXY: [f32; 4] = [40., 40., 60., 60.];
+    const AREA: f32 = 400.;
+
+    // The same object translated towards the image origin
+    const SHIFTED: [f32; 4] = [5., 5., 25., 25.];
+
+    fn decisions(filter: &ObjectFilter) -> Vec<Result<(), DropReason>> {
+        [XYXY, SHIFTED]
+            .iter()
+            .map(|xyxy| filter.check(xyxy, AREA, WIDTH, HEIGHT))
+            .collect()
+    }
+
+    #[test]
+    fn test_min_size_threshold_ignores_position() {
+        let filter = ObjectFilter {
+            min_size: 20,
+            ..Default::default()
+        };
+        assert_eq!(decisions(&filter), vec![Ok(()), Ok(())]);
+
+        let filter = ObjectFilter {
+            min_size: 21,
+            ..Default::default()
+        };
+        assert_eq!(
+            decisions(&filter),
+            vec![Err(DropReason::MinSize), Err(DropReason::MinSize)]
+        );
+    }
+
+    #[test]
+    fn test_area_threshold_ignores_position() {
+        let filter = ObjectFilter {
+            min_size: 1,
+            min_area: Some(AREA),
+            max_area: Some(AREA),
+            ..Default::default()
+        };
+        assert_eq!(decisions(&filter), vec![Ok(()), Ok(())]);
+
+        let filter = ObjectFilter {
+            min_size: 1,
+            min_area: Some(AREA + 1.),
+            ..Default::default()
+        };
+        assert_eq!(
+            decisions(&filter),
+            vec![Err(DropReason::MinArea), Err(DropReason::MinArea)]
+        );
+
+        let filter = ObjectFilter {
+            min_size: 1,
+            max_area: Some(AREA - 1.),
+            ..Default::default()
+        };
+        assert_eq!(
+            decisions(&filter),
+            vec![Err(DropReason::MaxArea), Err(DropReason::MaxArea)]
+        );
+    }
+
+    #[test]
+    fn test_streak_passes_min_size_but_not_min_area() {
+        // A 2 x 500 debris streak has a long side but almost no area
+        let xyxy = [10., 10., 12., 510.];
+
+        let filter = ObjectFilter {
+            min_size: 2,
+            min_area: Some(100.),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            filter.check(&xyxy, 1000., 1000, 1000),
+            Ok(()),
+            "area above threshold should be kept"
+        );
+        assert_eq!(
+            filter.check(&xyxy, 50., 1000, 1000),
+            Err(DropReason::MinArea)
+        );
+    }
+
+    #[test]
+    fn test_border_policy_ignores_padding() {
+        // Objects exactly 1 px from each edge and objects touching each edge
+        let interior = [
+            [1., 40., 20., 60.],
+            [40., 1., 60., 20.],
+            [80., 40., 98., 60.],
+            [40., 80., 60., 98.],
+        ];
+
+        let border = [
+            [0., 40., 20., 60.],
+            [40., 0., 60., 20.],
+            [80., 40., 99., 60.],
+            [40., 80., 60., 99.],
+        ];
+
+        for xyxy in interior {
+            assert!(!touches_border(&xyxy, WIDTH, HEIGHT), "{:?}", xyxy);
+        }
+
+        for xyxy in border {
+            assert!(touches_border(&xyxy, WIDTH, HEIGHT), "{:?}", xyxy);
+        }
+
+        for policy in [BorderPolicy::Keep, BorderPolicy::Drop, BorderPolicy::Flag] {
+            let filter = ObjectFilter {
+                border_policy: policy,
+                min_size: 1,
+                ..Default::default()
+            };
+
+            for xyxy in interior {
+                assert_eq!(filter.check(&xyxy, AREA, WIDTH, HEIGHT), Ok(()));
+            }
+
+            let expected = match policy {
+                BorderPolicy::Drop => Err(DropReason::Border),
+                _ => Ok(()),
+            };
+
+            for xyxy in border {
+                assert_eq!(filter.check(&xyxy, AREA, WIDTH, HEIGHT), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_crop_rect() {
+        let crop = |xyxy: [f32; 4], pad| crop_rect(&xyxy, pad, 10, 10).map(|c| c.as_xywh());
+
+        assert_eq!(crop([2., 3., 8., 9.], 1), Some([1, 2, 8, 8]));
+        assert_eq!(crop([0., 0., 10., 10.], 2), Some([0, 0, 10, 10]));
+        assert_eq!(crop([3., 3., 5., 5.], 10), Some([0, 0, 10, 10]));
+
+        // Boxes outside the image, empty boxes, and non-finite boxes
+        assert_eq!(crop([12., 2., 15., 6.], 1), None);
+        assert_eq!(crop([-8., -8., -4., -4.], 2), None);
+        assert_eq!(crop([4., 4., 4., 8.], 0), None);
+        assert_eq!(crop([f32::NAN, 0., 4., 4.], 1), None);
+        assert_eq!(crop([0., 0., f32::INFINITY, 4.], 1), None);
+
+        // Boxes partially outside the image are clipped
+        assert_eq!(crop([-3., 8., 2., 14.], 1), Some([0, 7, 3, 3]));
+    }
+
+    #[test]
+    fn test_crop_rect_random_boxes() {
+        let mut state = 17u64;
+
+        // SplitMix64 samples so the boxes only depend on the seed
+        let mut sample = |min: f32, max: f32| {
+            state = state.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            min + (max - min) * ((z ^ (z >> 31)) >> 40) as f32 / (1u64 << 24) as f32
+        };
+
+        for _ in 0..10_000 {
+            let (width, height) = (sample(1., 64.) as u32, sample(1., 64.) as u32);
+
+            let x = sample(-100., 164.);
+            let y = sample(-100., 164.);
+
+            // Zero-area and inverted boxes are included
+            let xyxy = [x, y, x + sample(-4., 40.), y + sample(-4., 40.)];
+            let pad = sample(0., 20.) as u32;
+
+            let Some(crop) = crop_rect(&xyxy, pad, width, height) else {
+                continue;
+            };
+
+            assert!(crop.width > 0 && crop.height > 0, "{:?}", xyxy);
+            assert!(crop.x + crop.width <= width, "{:?}", xyxy);
+            assert!(crop.y + crop.height <= height, "{:?}", xyxy);
+        }
+    }
+
+    #[test]
+    fn test_border_policy_parse() {
+        assert_eq!(BorderPolicy::parse("keep"), Ok(BorderPolicy::Keep));
+        assert_eq!(BorderPolicy::parse("drop"), Ok(BorderPolicy::Drop));
+        assert_eq!(BorderPolicy::parse("flag"), Ok(BorderPolicy::Flag));
+        assert!(BorderPolicy::parse("clip").is_err());
+
+        assert_eq!(BorderPolicy::from_drop_borders(true), BorderPolicy::Drop);
+        assert_eq!(BorderPolicy::from_drop_borders(false), BorderPolicy::Keep);
+    }
+
+    #[test]
+    fn test_nonfinite_policy_parse() {
+        assert_eq!(NonFinitePolicy::parse("keep"), Ok(NonFinitePolicy::Keep));
+        assert_eq!(NonFinitePolicy::parse("zero"), Ok(NonFinitePolicy::Zero));
+        assert_eq!(
+            NonFinitePolicy::parse("drop-object"),
+            Ok(NonFinitePolicy::DropObject)
+        );
+        assert!(NonFinitePolicy::parse("drop").is_err());
+        assert_eq!(NonFinitePolicy::default(), NonFinitePolicy::Keep);
+    }
+
+    #[test]
+    fn test_check_polygon() {
+        let filter = ObjectFilter {
+            min_size: 1,
+            ..Default::default()
+        };
+        assert_eq!(filter.check_polygon(true), Ok(()));
+
+        let filter = ObjectFilter {
+            min_size: 1,
+            skip_invalid_polygons: true,
+            ..Default::default()
+        };
+        assert_eq!(filter.check_polygon(false), Ok(()));
+        assert_eq!(filter.check_polygon(true), Err(DropReason::InvalidPolygon));
+    }
+
+    #[test]
+    fn test_validate() {
+        let filter = ObjectFilter {
+            min_size: 1,
+            ..Default::default()
+        };
+        assert!(filter.validate().is_ok());
+
+        assert!(ObjectFilter::default().validate().is_err());
+
+        let filter = ObjectFilter {
+            min_size: 1,
+            min_area: Some(10.),
+            max_area: Some(5.),
+            ..Default::default()
+        };
+        assert!(filter.validate().is_err());
+    }
+
+    #[test]
+    fn test_object_counts() {
+        let mut dropped = DropCounts::default();
+        dropped.add(DropReason::MinSize);
+        dropped.add(DropReason::MinSize);
+        dropped.add(DropReason::MaxArea);
+        dropped.add(DropReason::OutsideImage);
+
+        let rows = vec![object_counts_row("plate1_A01", 7, &dropped)];
+        let table = object_counts(&rows);
+
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], OBJECT_COUNTS_HEADER);
+        assert_eq!(lines[1], "plate1_A01\t7\t0\t2\t0\t1\t0\t1");
+    }
+
+    #[test]
+    fn test_image_gate() {
+        use thyme_core::im::{ThymeBuffer, ThymeImage};
+
+        let image = ThymeImage::U8(ThymeBuffer::new(2, 2, 1, vec![4u8, 4, 4, 4]).unwrap());
+        let summary = ImageSummary::new(&image, &[1, 0, 0, 0]).unwrap();
+
+        let gate = ImageGate::new(Some("foreground_fraction<0.5 && background_mean>3"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            gate.check(&summary).unwrap(),
+            "foreground_fraction<0.5 && background_mean>3"
+        );
+
+        let gate = ImageGate::new(Some("focus_score>1")).unwrap().unwrap();
+        assert_eq!(gate.check(&summary), None);
+
+        assert!(ImageGate::new(None).unwrap().is_none());
+        assert!(ImageGate::new(Some("objects<1")).is_err());
+
+        let rows = vec![skipped_images_row("plate1_A01", "focus_score<30", &summary)];
+        let table = skipped_images(&rows);
+
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], SKIPPED_IMAGES_HEADER);
+        assert_eq!(lines[1], "plate1_A01\tfocus_score<30\t0.25\t0\t4\t4");
+    }
+}
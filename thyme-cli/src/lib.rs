@@ -1,6 +1,25 @@
+pub mod boxes;
+pub mod classes;
+pub mod commands;
+pub mod crops;
 pub mod download;
+pub mod dryrun;
+pub mod failures;
+pub mod filter;
 pub mod measure;
 pub mod neural;
+pub mod output;
+pub mod pipeline;
+pub mod prefetch;
+pub mod probability;
 pub mod process;
 pub mod profile;
+pub mod provenance;
+pub mod remote;
+pub mod status;
+pub mod transforms;
 pub mod utils;
+
+pub use neural::{NeuralSummary, run_neural_boxes, run_neural_mask, run_neural_polygons};
+pub use process::{ProcessSummary, run_process_boxes, run_process_mask, run_process_polygons};
+pub use profile::{ProfileSummary, run_profile_boxes, run_profile_mask, run_profile_polygons};
@@ -2,13 +2,11 @@
 // Licensed under the MIT License
 
 use clap::{Parser, Subcommand};
-use thyme_cli::{download, measure, neural, process, profile, utils};
+use thyme_cli::{commands, download, measure, neural, process, profile, utils};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    name: Option<String>,
-
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -19,12 +17,12 @@ enum Commands {
     Measure(measure::MeasureArgs),
     Neural(neural::NeuralArgs),
     Process(process::ProcessArgs),
-    Profile(profile::ProfileArgs),
+    Profile(Box<profile::ProfileArgs>),
     Utils(utils::UtilsArgs),
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let cli: Cli = commands::parse();
 
     match &cli.command {
         Some(Commands::Download(download_args)) => download::download(download_args),
@@ -9,6 +9,7 @@ use clap::Args;
 use kdam::TqdmParallelIterator;
 use polars::prelude::*;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
 use thyme_core::constant;
 use thyme_core::error::ThymeError;
@@ -16,7 +17,10 @@ use thyme_core::im;
 use thyme_core::io;
 use thyme_core::ut;
 
-#[derive(Debug, Args)]
+use crate::output::{OutputMode, prepare_output};
+use crate::provenance;
+
+#[derive(Debug, Args, Serialize)]
 pub struct FormArgs {
     #[arg(
         short = 'i',
@@ -29,7 +33,7 @@ pub struct FormArgs {
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq)."
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather)."
     )]
     pub output: Option<String>,
 
@@ -39,6 +43,59 @@ pub struct FormArgs {
     #[arg(long, help = "Substring specifying polygons (e.g. _polygons).")]
     pub polygon_substring: Option<String>,
 
+    #[arg(
+        long,
+        help = "Glob selecting polygons by their path relative to their directory.",
+        conflicts_with_all = ["polygon_substring", "polygon_regex"]
+    )]
+    pub polygon_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting polygons by their path relative to their directory.",
+        conflicts_with = "polygon_substring"
+    )]
+    pub polygon_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Replace an existing output file and its provenance sidecar. Without it, existing output files are rejected."
+    )]
+    pub overwrite: bool,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
@@ -51,13 +108,36 @@ pub fn measure_form(args: &FormArgs) {
             );
             std::process::exit(1);
         }
+    }
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `measure_form`
+fn run(args: &FormArgs) {
+    let ipc_compression = args
+        .ipc_compression
+        .to_owned()
+        .unwrap_or("none".to_string());
+
+    if !constant::SUPPORTED_IPC_COMPRESSION.contains(&ipc_compression.as_str()) {
+        eprintln!(
+            "[thyme::measure::form] ERROR: Invalid ipc_compression {}. Must be one of: {:?}.",
+            ipc_compression,
+            constant::SUPPORTED_IPC_COMPRESSION
+        );
+        std::process::exit(1);
     }
 
+    let float_format = io::FloatFormat::new(
+        args.float_precision,
+        args.float_format.as_deref().unwrap_or("auto"),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("[thyme::measure::form] ERROR: {}.", err);
+        std::process::exit(1);
+    });
+
     let polygons_path = args.polygons.to_owned().unwrap();
 
     let polygon_extension = Path::new(&polygons_path)
@@ -95,9 +175,11 @@ pub fn measure_form(args: &FormArgs) {
             .map(|s| s.to_lowercase());
 
         if let Some(ext) = &extension {
-            if !["csv", "txt", "tsv", "pq"].iter().any(|e| e == ext) {
+            if !constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
                 eprintln!(
-                    "[thyme::measure::form] ERROR: Invalid file extension. Must end with one of .csv, .txt, .tsv, .pq."
+                    "[thyme::measure::form] ERROR: Invalid file extension {}. Must be one of: {:?}.",
+                    ext,
+                    constant::SUPPORTED_TABLE_FORMATS
                 );
                 std::process::exit(1);
             }
@@ -109,7 +191,7 @@ pub fn measure_form(args: &FormArgs) {
         }
 
         if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
                 eprintln!(
                     "[thyme::measure::form] ERROR: Invalid file path. Parent directory of output file path does not exist."
                 );
@@ -117,11 +199,26 @@ pub fn measure_form(args: &FormArgs) {
             }
         }
 
-        let polygon_files = ut::path::collect_file_paths(
-            &polygons_path,
-            constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
-            args.polygon_substring.to_owned(),
+        let output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| prepare_output(&output, mode, &[&polygons_path], "measure::form"))
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::form] ERROR: {}.", err);
+                std::process::exit(1);
+            });
+
+        let polygon_files = ut::path::FileSelector::new(
+            args.polygon_substring.as_deref(),
+            args.polygon_glob.as_deref(),
+            args.polygon_regex.as_deref(),
         )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &polygons_path,
+                constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
         .unwrap_or_else(|err| {
             eprintln!("{}", err);
             std::process::exit(1);
@@ -147,7 +244,7 @@ pub fn measure_form(args: &FormArgs) {
         let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(polygon_files.len()));
         let name: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(polygon_files.len()));
         let item: Mutex<Vec<u32>> = Mutex::new(Vec::with_capacity(polygon_files.len()));
-        let data: Mutex<Vec<[f32; 23]>> = Mutex::new(Vec::with_capacity(23 * polygon_files.len()));
+        let data: Mutex<Vec<[f32; 29]>> = Mutex::new(Vec::with_capacity(29 * polygon_files.len()));
 
         (0..polygon_files.len())
             .into_par_iter()
@@ -192,7 +289,28 @@ pub fn measure_form(args: &FormArgs) {
         }
 
         if !data.is_empty() {
-            write_form(&data, &name, &item, &output);
+            let descriptors_path = write_form(
+                &data,
+                &name,
+                &item,
+                &output,
+                &ipc_compression,
+                &float_format,
+            );
+
+            if args.provenance {
+                provenance::record(
+                    args,
+                    &polygon_files,
+                    None,
+                    args.hash_inputs,
+                    &[&descriptors_path],
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("[thyme::measure::form] ERROR: {}", err);
+                    std::process::exit(1);
+                });
+            }
         }
 
         let message = if !failure.is_empty() {
@@ -234,9 +352,9 @@ pub fn measure_form(args: &FormArgs) {
         let mut stdout = std::io::stdout();
 
         for (i, d) in data.iter().enumerate() {
-            let output: Vec<String> = constant::FORM_DESCRIPTOR_NAMES
+            let output: Vec<String> = constant::FORM_DESCRIPTORS
                 .iter()
-                .copied()
+                .map(|f| f.name)
                 .zip(d.iter().map(|x| x.to_string()).collect::<Vec<String>>())
                 .map(|(c, d)| format!("object_{}\t{}\t{}\n", i, c, d))
                 .collect();
@@ -249,14 +367,21 @@ pub fn measure_form(args: &FormArgs) {
 }
 
 /// Measure form descriptors across a set of polygons
-fn form(polygons_path: &Path) -> Result<Vec<[f32; 23]>, ThymeError> {
+fn form(polygons_path: &Path) -> Result<Vec<[f32; 29]>, ThymeError> {
     let mut polygons = im::Polygons::open(polygons_path)?;
     Ok(polygons.descriptors())
 }
 
 /// Write form descriptors to data table
-fn write_form(data: &[[f32; 23]], name: &Vec<String>, item: &Vec<u32>, output: &Path) {
-    let columns = constant::FORM_DESCRIPTOR_NAMES;
+fn write_form(
+    data: &[[f32; 29]],
+    name: &Vec<String>,
+    item: &Vec<u32>,
+    output: &Path,
+    ipc_compression: &str,
+    float_format: &io::FloatFormat,
+) -> PathBuf {
+    let columns = constant::FORM_DESCRIPTORS.map(|f| f.name);
 
     let mut df = DataFrame::new(vec![
         Column::new("image".into(), &name),
@@ -287,8 +412,11 @@ fn write_form(data: &[[f32; 23]], name: &Vec<String>, item: &Vec<u32>, output: &
         output.to_path_buf()
     };
 
-    io::write_table(&mut df, descriptors_path).unwrap_or_else(|_| {
-        eprintln!("[thyme::measure::form] ERROR: Failed to write descriptors table.");
-        std::process::exit(1);
-    });
+    io::write_table_with_float_format(&mut df, &descriptors_path, ipc_compression, float_format)
+        .unwrap_or_else(|_| {
+            eprintln!("[thyme::measure::form] ERROR: Failed to write descriptors table.");
+            std::process::exit(1);
+        });
+
+    descriptors_path
 }
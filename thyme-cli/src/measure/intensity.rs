@@ -9,6 +9,7 @@ use clap::Args;
 use kdam::TqdmParallelIterator;
 use polars::prelude::*;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
 use thyme_core::constant;
 use thyme_core::error::ThymeError;
@@ -16,7 +17,10 @@ use thyme_core::im;
 use thyme_core::io;
 use thyme_core::ut;
 
-#[derive(Debug, Args)]
+use crate::output::{OutputMode, prepare_output};
+use crate::provenance;
+
+#[derive(Debug, Args, Serialize)]
 pub struct IntensityArgs {
     #[arg(short = 'i', long, help = "Image or image directory.", required = true)]
     pub images: Option<String>,
@@ -24,7 +28,7 @@ pub struct IntensityArgs {
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq)."
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather)."
     )]
     pub output: Option<String>,
 
@@ -34,6 +38,65 @@ pub struct IntensityArgs {
     #[arg(long, help = "Substring specifying images (e.g. _image).")]
     pub image_substring: Option<String>,
 
+    #[arg(
+        long,
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Include intensity percentiles (p10, p25, p75, p90), skewness, and kurtosis."
+    )]
+    pub extended_intensity: bool,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Replace an existing output file and its provenance sidecar. Without it, existing output files are rejected."
+    )]
+    pub overwrite: bool,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
@@ -46,13 +109,36 @@ pub fn measure_intensity(args: &IntensityArgs) {
             );
             std::process::exit(1);
         }
+    }
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `measure_intensity`
+fn run(args: &IntensityArgs) {
+    let ipc_compression = args
+        .ipc_compression
+        .to_owned()
+        .unwrap_or("none".to_string());
+
+    if !constant::SUPPORTED_IPC_COMPRESSION.contains(&ipc_compression.as_str()) {
+        eprintln!(
+            "[thyme::measure::intensity] ERROR: Invalid ipc_compression {}. Must be one of: {:?}.",
+            ipc_compression,
+            constant::SUPPORTED_IPC_COMPRESSION
+        );
+        std::process::exit(1);
     }
 
+    let float_format = io::FloatFormat::new(
+        args.float_precision,
+        args.float_format.as_deref().unwrap_or("auto"),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("[thyme::measure::intensity] ERROR: {}.", err);
+        std::process::exit(1);
+    });
+
     let image_path = args.images.to_owned().unwrap();
 
     let image_extension = Path::new(&image_path)
@@ -90,9 +176,11 @@ pub fn measure_intensity(args: &IntensityArgs) {
             .map(|s| s.to_lowercase());
 
         if let Some(ext) = &extension {
-            if !["csv", "txt", "tsv", "pq"].iter().any(|e| e == ext) {
+            if !constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
                 eprintln!(
-                    "[thyme::measure::intensity] ERROR: Invalid file extension. Must end with one of .csv, .txt, .tsv, .pq."
+                    "[thyme::measure::intensity] ERROR: Invalid file extension {}. Must be one of: {:?}.",
+                    ext,
+                    constant::SUPPORTED_TABLE_FORMATS
                 );
                 std::process::exit(1);
             }
@@ -104,7 +192,7 @@ pub fn measure_intensity(args: &IntensityArgs) {
         }
 
         if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
                 eprintln!(
                     "[thyme::measure::intensity] ERROR: Invalid file path. Parent directory of output file path does not exist."
                 );
@@ -112,11 +200,26 @@ pub fn measure_intensity(args: &IntensityArgs) {
             }
         }
 
-        let image_files = ut::path::collect_file_paths(
-            &image_path,
-            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-            args.image_substring.to_owned(),
+        let output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| prepare_output(&output, mode, &[&image_path], "measure::intensity"))
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::intensity] ERROR: {}.", err);
+                std::process::exit(1);
+            });
+
+        let image_files = ut::path::FileSelector::new(
+            args.image_substring.as_deref(),
+            args.image_glob.as_deref(),
+            args.image_regex.as_deref(),
         )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &image_path,
+                constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
         .unwrap_or_else(|err| {
             eprintln!("{}", err);
             std::process::exit(1);
@@ -141,13 +244,13 @@ pub fn measure_intensity(args: &IntensityArgs) {
 
         let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(image_files.len()));
         let name: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(image_files.len()));
-        let data: Mutex<Vec<[f32; 7]>> = Mutex::new(Vec::with_capacity(7 * image_files.len()));
+        let data: Mutex<Vec<Vec<f32>>> = Mutex::new(Vec::with_capacity(image_files.len()));
 
         (0..image_files.len())
             .into_par_iter()
             .tqdm_with_bar(pb)
             .for_each(|idx| {
-                let result = intensity(&image_files[idx]);
+                let result = intensity(&image_files[idx], args.extended_intensity);
 
                 let image_name = image_files[idx]
                     .file_stem()
@@ -176,7 +279,28 @@ pub fn measure_intensity(args: &IntensityArgs) {
         }
 
         if !data.is_empty() {
-            write_intensity(&data, &name, &output);
+            let descriptors_path = write_intensity(
+                &data,
+                &name,
+                &output,
+                &ipc_compression,
+                &float_format,
+                args.extended_intensity,
+            );
+
+            if args.provenance {
+                provenance::record(
+                    args,
+                    &image_files,
+                    None,
+                    args.hash_inputs,
+                    &[&descriptors_path],
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("[thyme::measure::intensity] ERROR: {}", err);
+                    std::process::exit(1);
+                });
+            }
         }
 
         let message = if !failure.is_empty() {
@@ -210,16 +334,16 @@ pub fn measure_intensity(args: &IntensityArgs) {
             std::process::exit(1);
         }
 
-        let data = intensity(Path::new(&image_path)).unwrap_or_else(|_| {
-            eprintln!(
-                "[thyme::measure::intensity] ERROR: Failed to measure intensity descriptors."
-            );
-            std::process::exit(1);
-        });
+        let data =
+            intensity(Path::new(&image_path), args.extended_intensity).unwrap_or_else(|_| {
+                eprintln!(
+                    "[thyme::measure::intensity] ERROR: Failed to measure intensity descriptors."
+                );
+                std::process::exit(1);
+            });
 
-        let output: Vec<String> = constant::INTENSITY_DESCRIPTOR_NAMES
-            .iter()
-            .copied()
+        let output: Vec<String> = descriptor_columns(args.extended_intensity)
+            .into_iter()
             .zip(data.iter().map(|x| x.to_string()).collect::<Vec<String>>())
             .map(|(c, d)| format!("{}\t{}\n", c, d))
             .collect();
@@ -233,17 +357,49 @@ pub fn measure_intensity(args: &IntensityArgs) {
 }
 
 /// Measure intensity descriptors across an image
-fn intensity(image_path: &Path) -> Result<[f32; 7], ThymeError> {
+fn intensity(image_path: &Path, extended: bool) -> Result<Vec<f32>, ThymeError> {
     let image = im::ThymeImage::open(image_path)?;
+    let view = image.crop_view(0, 0, image.width(), image.height());
+
+    if extended {
+        Ok(view.intensity_extended(None).to_vec())
+    } else {
+        Ok(view.intensity().to_vec())
+    }
+}
 
-    Ok(image
-        .crop_view(0, 0, image.width(), image.height())
-        .intensity())
+/// Generate the column names for the output descriptor table
+///
+/// # Arguments
+///
+/// * `extended` - Include percentile, skewness, and kurtosis descriptors
+fn descriptor_columns(extended: bool) -> Vec<&'static str> {
+    let mut names: Vec<&str> = constant::INTENSITY_DESCRIPTORS
+        .iter()
+        .map(|f| f.name)
+        .collect();
+
+    if extended {
+        names.extend(
+            constant::EXTENDED_INTENSITY_DESCRIPTORS
+                .iter()
+                .map(|f| f.name),
+        );
+    }
+
+    names
 }
 
 /// Write intensity descriptors to data table
-fn write_intensity(data: &[[f32; 7]], name: &Vec<String>, output: &Path) {
-    let columns = constant::INTENSITY_DESCRIPTOR_NAMES;
+fn write_intensity(
+    data: &[Vec<f32>],
+    name: &Vec<String>,
+    output: &Path,
+    ipc_compression: &str,
+    float_format: &io::FloatFormat,
+    extended: bool,
+) -> PathBuf {
+    let columns = descriptor_columns(extended);
 
     let mut df = DataFrame::new(vec![Column::new("image".into(), &name)]).unwrap();
 
@@ -270,8 +426,11 @@ fn write_intensity(data: &[[f32; 7]], name: &Vec<String>, output: &Path) {
         output.to_path_buf()
     };
 
-    io::write_table(&mut df, descriptors_path).unwrap_or_else(|_| {
-        eprintln!("[thyme::measure::intensity] ERROR: Failed to write descriptors table.");
-        std::process::exit(1);
-    });
+    io::write_table_with_float_format(&mut df, &descriptors_path, ipc_compression, float_format)
+        .unwrap_or_else(|_| {
+            eprintln!("[thyme::measure::intensity] ERROR: Failed to write descriptors table.");
+            std::process::exit(1);
+        });
+
+    descriptors_path
 }
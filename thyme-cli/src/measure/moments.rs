@@ -9,22 +9,41 @@ use clap::Args;
 use kdam::TqdmParallelIterator;
 use polars::prelude::*;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
 use thyme_core::constant;
+use thyme_core::cv;
 use thyme_core::error::ThymeError;
 use thyme_core::im;
 use thyme_core::io;
+use thyme_core::mp;
 use thyme_core::ut;
 
-#[derive(Debug, Args)]
+use crate::output::{OutputMode, prepare_output};
+use crate::provenance;
+
+#[derive(Debug, Args, Serialize)]
 pub struct MomentsArgs {
-    #[arg(short = 'i', long, help = "Image or image directory.", required = true)]
+    #[arg(
+        short = 'i',
+        long,
+        help = "Image or image directory.",
+        required_unless_present = "polygons",
+        conflicts_with = "polygons"
+    )]
     pub images: Option<String>,
 
+    #[arg(
+        short = 's',
+        long,
+        help = "Polygons or polygons directory. Moments are measured on each rasterized polygon."
+    )]
+    pub polygons: Option<String>,
+
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq)."
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather)."
     )]
     pub output: Option<String>,
 
@@ -34,6 +53,76 @@ pub struct MomentsArgs {
     #[arg(long, help = "Substring specifying images (e.g. _image).")]
     pub image_substring: Option<String>,
 
+    #[arg(
+        long,
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(long, help = "Substring specifying polygons (e.g. _polygons).")]
+    pub polygon_substring: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting polygons by their path relative to their directory.",
+        conflicts_with_all = ["polygon_substring", "polygon_regex"]
+    )]
+    pub polygon_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting polygons by their path relative to their directory.",
+        conflicts_with = "polygon_substring"
+    )]
+    pub polygon_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Replace an existing output file and its provenance sidecar. Without it, existing output files are rejected."
+    )]
+    pub overwrite: bool,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
@@ -46,11 +135,39 @@ pub fn measure_moments(args: &MomentsArgs) {
             );
             std::process::exit(1);
         }
+    }
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `measure_moments`
+fn run(args: &MomentsArgs) {
+    let ipc_compression = args
+        .ipc_compression
+        .to_owned()
+        .unwrap_or("none".to_string());
+
+    if !constant::SUPPORTED_IPC_COMPRESSION.contains(&ipc_compression.as_str()) {
+        eprintln!(
+            "[thyme::measure::moments] ERROR: Invalid ipc_compression {}. Must be one of: {:?}.",
+            ipc_compression,
+            constant::SUPPORTED_IPC_COMPRESSION
+        );
+        std::process::exit(1);
+    }
+
+    let float_format = io::FloatFormat::new(
+        args.float_precision,
+        args.float_format.as_deref().unwrap_or("auto"),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("[thyme::measure::moments] ERROR: {}.", err);
+        std::process::exit(1);
+    });
+
+    if let Some(polygons_path) = args.polygons.to_owned() {
+        measure_polygon_moments(args, &polygons_path, &ipc_compression, &float_format);
+        return;
     }
 
     let image_path = args.images.to_owned().unwrap();
@@ -90,9 +207,11 @@ pub fn measure_moments(args: &MomentsArgs) {
             .map(|s| s.to_lowercase());
 
         if let Some(ext) = &extension {
-            if !["csv", "txt", "tsv", "pq"].iter().any(|e| e == ext) {
+            if !constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
                 eprintln!(
-                    "[thyme::measure::moments] ERROR: Invalid file extension. Must end with one of .csv, .txt, .tsv, .pq."
+                    "[thyme::measure::moments] ERROR: Invalid file extension {}. Must be one of: {:?}.",
+                    ext,
+                    constant::SUPPORTED_TABLE_FORMATS
                 );
                 std::process::exit(1);
             }
@@ -104,7 +223,7 @@ pub fn measure_moments(args: &MomentsArgs) {
         }
 
         if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
                 eprintln!(
                     "[thyme::measure::moments] ERROR: Invalid file path. Parent directory of output file path does not exist."
                 );
@@ -112,11 +231,26 @@ pub fn measure_moments(args: &MomentsArgs) {
             }
         }
 
-        let image_files = ut::path::collect_file_paths(
-            &image_path,
-            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-            args.image_substring.to_owned(),
+        let output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| prepare_output(&output, mode, &[&image_path], "measure::moments"))
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::moments] ERROR: {}.", err);
+                std::process::exit(1);
+            });
+
+        let image_files = ut::path::FileSelector::new(
+            args.image_substring.as_deref(),
+            args.image_glob.as_deref(),
+            args.image_regex.as_deref(),
         )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &image_path,
+                constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
         .unwrap_or_else(|err| {
             eprintln!("{}", err);
             std::process::exit(1);
@@ -176,7 +310,22 @@ pub fn measure_moments(args: &MomentsArgs) {
         }
 
         if !data.is_empty() {
-            write_moments(&data, &name, &output);
+            let descriptors_path =
+                write_moments(&data, &name, None, &output, &ipc_compression, &float_format);
+
+            if args.provenance {
+                provenance::record(
+                    args,
+                    &image_files,
+                    None,
+                    args.hash_inputs,
+                    &[&descriptors_path],
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("[thyme::measure::moments] ERROR: {}", err);
+                    std::process::exit(1);
+                });
+            }
         }
 
         let message = if !failure.is_empty() {
@@ -215,9 +364,9 @@ pub fn measure_moments(args: &MomentsArgs) {
             std::process::exit(1);
         });
 
-        let output: Vec<String> = constant::MOMENTS_DESCRIPTOR_NAMES
+        let output: Vec<String> = constant::MOMENTS_DESCRIPTORS
             .iter()
-            .copied()
+            .map(|f| f.name)
             .zip(data.iter().map(|x| x.to_string()).collect::<Vec<String>>())
             .map(|(c, d)| format!("{}\t{}\n", c, d))
             .collect();
@@ -230,6 +379,239 @@ pub fn measure_moments(args: &MomentsArgs) {
     }
 }
 
+/// Measure moments descriptors for each object in a set of polygons
+fn measure_polygon_moments(
+    args: &MomentsArgs,
+    polygons_path: &str,
+    ipc_compression: &str,
+    float_format: &io::FloatFormat,
+) {
+    let polygon_extension = Path::new(&polygons_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+
+    let is_polygon_dir = if let Some(ext) = &polygon_extension {
+        if !constant::SUPPORTED_ARRAY_FORMATS.contains(&ext.as_str()) {
+            eprintln!(
+                "[thyme::measure::moments] ERROR: Invalid polygon extension {}. Must be one of: {:?}.",
+                ext,
+                constant::SUPPORTED_ARRAY_FORMATS
+            );
+            std::process::exit(1);
+        }
+        false
+    } else {
+        true
+    };
+
+    if let Some(output) = args.output.to_owned() {
+        if !is_polygon_dir {
+            eprintln!(
+                "[thyme::measure::moments] ERROR: If output is provided, then input polygons path must specify a polygons directory."
+            );
+            std::process::exit(1);
+        }
+
+        let output = PathBuf::from(output);
+
+        let extension = output
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase());
+
+        if let Some(ext) = &extension {
+            if !constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
+                eprintln!(
+                    "[thyme::measure::moments] ERROR: Invalid file extension {}. Must be one of: {:?}.",
+                    ext,
+                    constant::SUPPORTED_TABLE_FORMATS
+                );
+                std::process::exit(1);
+            }
+        } else {
+            eprintln!(
+                "[thyme::measure::moments] ERROR: Invalid output path. Output file must be a file with a valid extension."
+            );
+            std::process::exit(1);
+        }
+
+        if let Some(parent) = output.parent() {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
+                eprintln!(
+                    "[thyme::measure::moments] ERROR: Invalid file path. Parent directory of output file path does not exist."
+                );
+                std::process::exit(1);
+            }
+        }
+
+        let output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| prepare_output(&output, mode, &[polygons_path], "measure::moments"))
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::moments] ERROR: {}.", err);
+                std::process::exit(1);
+            });
+
+        let polygon_files = ut::path::FileSelector::new(
+            args.polygon_substring.as_deref(),
+            args.polygon_glob.as_deref(),
+            args.polygon_regex.as_deref(),
+        )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                polygons_path,
+                constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+
+        if polygon_files.is_empty() {
+            eprintln!(
+                "[thyme::measure::moments] ERROR: No polygon files were detected. Please check your path and/or substring identifier."
+            );
+            std::process::exit(1);
+        }
+
+        ut::track::progress_log(
+            &format!(
+                "Detected {} polygons.",
+                ut::track::thousands_format(polygon_files.len())
+            ),
+            args.verbose,
+        );
+
+        let pb = ut::track::progress_bar(polygon_files.len(), "Measuring moments", args.verbose);
+
+        let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(polygon_files.len()));
+        let name: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(polygon_files.len()));
+        let item: Mutex<Vec<u32>> = Mutex::new(Vec::with_capacity(polygon_files.len()));
+        let data: Mutex<Vec<[f32; 24]>> = Mutex::new(Vec::with_capacity(24 * polygon_files.len()));
+
+        (0..polygon_files.len())
+            .into_par_iter()
+            .tqdm_with_bar(pb)
+            .for_each(|idx| {
+                let result = polygons_moments(&polygon_files[idx]);
+
+                let polygon_name = polygon_files[idx]
+                    .file_stem()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+
+                if let Ok(descriptors) = result {
+                    let n = descriptors.len();
+
+                    name.lock()
+                        .unwrap()
+                        .extend((0..n).map(|_| polygon_name.clone()));
+
+                    item.lock()
+                        .unwrap()
+                        .extend((0..n as u32).collect::<Vec<u32>>());
+
+                    data.lock().unwrap().extend(descriptors);
+                } else {
+                    failure.lock().unwrap().push(format!(
+                        "{}\t{}",
+                        polygon_name,
+                        result.unwrap_err()
+                    ));
+                }
+            });
+
+        let failure = failure.into_inner().unwrap();
+        let name = name.into_inner().unwrap();
+        let item = item.into_inner().unwrap();
+        let data = data.into_inner().unwrap();
+
+        if args.verbose {
+            println!()
+        }
+
+        if !data.is_empty() {
+            let descriptors_path = write_moments(
+                &data,
+                &name,
+                Some(&item),
+                &output,
+                ipc_compression,
+                float_format,
+            );
+
+            if args.provenance {
+                provenance::record(
+                    args,
+                    &polygon_files,
+                    None,
+                    args.hash_inputs,
+                    &[&descriptors_path],
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("[thyme::measure::moments] ERROR: {}", err);
+                    std::process::exit(1);
+                });
+            }
+        }
+
+        let message = if !failure.is_empty() {
+            &format!(
+                "Complete. {} polygons measured succesfully. {} polygons failed.",
+                ut::track::thousands_format(polygon_files.len() - failure.len()),
+                ut::track::thousands_format(failure.len())
+            )
+        } else {
+            &format!(
+                "Complete. {} polygons measured successfully.",
+                ut::track::thousands_format(polygon_files.len() - failure.len()),
+            )
+        };
+
+        ut::track::progress_log(message, args.verbose);
+    } else {
+        if is_polygon_dir {
+            eprintln!(
+                "[thyme::measure::moments] ERROR: If output is not provided, then input polygon path should specify a single file."
+            );
+            std::process::exit(1);
+        }
+
+        let polygons_path = Path::new(&polygons_path);
+
+        if !polygons_path.is_file() {
+            eprintln!(
+                "[thyme::measure::moments] ERROR: The provided polygon file path does not exist."
+            );
+            std::process::exit(1);
+        }
+
+        let data = polygons_moments(polygons_path).unwrap_or_else(|_| {
+            eprintln!("[thyme::measure::moments] ERROR: Failed to measure moments descriptors.");
+            std::process::exit(1);
+        });
+
+        let mut stdout = std::io::stdout();
+
+        for (i, d) in data.iter().enumerate() {
+            let output: Vec<String> = constant::MOMENTS_DESCRIPTORS
+                .iter()
+                .map(|f| f.name)
+                .zip(d.iter().map(|x| x.to_string()).collect::<Vec<String>>())
+                .map(|(c, d)| format!("object_{}\t{}\t{}\n", i, c, d))
+                .collect();
+
+            for row in output.iter() {
+                stdout.write_all(row.as_bytes()).unwrap();
+            }
+        }
+    }
+}
+
 /// Measure moments descriptors across an image
 fn moments(image_path: &Path) -> Result<[f32; 24], ThymeError> {
     let image = im::ThymeImage::open(image_path)?;
@@ -239,12 +621,51 @@ fn moments(image_path: &Path) -> Result<[f32; 24], ThymeError> {
         .moments())
 }
 
+/// Measure moments descriptors for each object in a set of polygons
+fn polygons_moments(polygons_path: &Path) -> Result<Vec<[f32; 24]>, ThymeError> {
+    let polygons = im::Polygons::open(polygons_path)?;
+
+    Ok(polygons
+        .as_points()
+        .iter()
+        .map(|points| polygon_moments(points))
+        .collect())
+}
+
+/// Measure moments descriptors for a polygon rasterized at its original coordinates
+///
+/// Raw moments (m00 - m03) are measured relative to the top-left corner of the
+/// polygon bounding box and central moments (u11 - u03) are translation
+/// invariant, but both scale with object size. Hu moments (i1 - i7) are computed
+/// from normalized central moments and are invariant to translation, scale, and
+/// rotation up to rasterization error.
+fn polygon_moments(points: &[[f32; 2]]) -> [f32; 24] {
+    if points.is_empty() {
+        return [0.0; 24];
+    }
+
+    let (buffer, width, _, _) = cv::points::draw_points_native(points, 0);
+
+    mp::moments::descriptors(&buffer, width as usize)
+}
+
 /// Write moments descriptors to data table
-fn write_moments(data: &[[f32; 24]], name: &Vec<String>, output: &Path) {
-    let columns = constant::MOMENTS_DESCRIPTOR_NAMES;
+fn write_moments(
+    data: &[[f32; 24]],
+    name: &Vec<String>,
+    item: Option<&Vec<u32>>,
+    output: &Path,
+    ipc_compression: &str,
+    float_format: &io::FloatFormat,
+) -> PathBuf {
+    let columns = constant::MOMENTS_DESCRIPTORS.map(|f| f.name);
 
     let mut df = DataFrame::new(vec![Column::new("image".into(), &name)]).unwrap();
 
+    if let Some(item) = item {
+        df.with_column(Column::new("object".into(), item)).unwrap();
+    }
+
     // Note that this requires generating two copies of the computed descriptors
     // which is definitely not ideal. We probaby want to redesign the computation
     // so that column-major data is generated directly or we just use a flat buffer
@@ -268,8 +689,58 @@ fn write_moments(data: &[[f32; 24]], name: &Vec<String>, output: &Path) {
         output.to_path_buf()
     };
 
-    io::write_table(&mut df, descriptors_path).unwrap_or_else(|_| {
-        eprintln!("[thyme::measure::moments] ERROR: Failed to write descriptors table.");
-        std::process::exit(1);
-    });
+    io::write_table_with_float_format(&mut df, &descriptors_path, ipc_compression, float_format)
+        .unwrap_or_else(|_| {
+            eprintln!("[thyme::measure::moments] ERROR: Failed to write descriptors table.");
+            std::process::exit(1);
+        });
+
+    descriptors_path
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_polygon_moments_match_mask() {
+        let (cx, cy, radius) = (52.3f32, 47.8f32, 20.0f32);
+
+        let circle: Vec<[f32; 2]> = (0..256)
+            .map(|i| {
+                let theta = i as f32 * std::f32::consts::TAU / 256.0;
+                [cx + radius * theta.cos(), cy + radius * theta.sin()]
+            })
+            .collect();
+
+        let (width, height) = (100usize, 100usize);
+        let mask: Vec<u32> = (0..width * height)
+            .map(|idx| {
+                let x = (idx % width) as f32 - cx;
+                let y = (idx / width) as f32 - cy;
+                (x * x + y * y <= radius * radius) as u32
+            })
+            .collect();
+
+        let expected = mp::moments::descriptors(&mask, width);
+        let observed = polygon_moments(&circle);
+
+        // Hu moments are scale and translation invariant so the rasterized
+        // polygon and the full-image mask should agree
+        for (o, e) in observed[17..].iter().zip(&expected[17..]) {
+            assert!((o - e).abs() < 1e-3, "{} != {}", o, e);
+        }
+
+        // A disk has i1 = 1 / (2 * pi) and vanishing higher order invariants
+        assert!((observed[17] - 1.0 / std::f32::consts::TAU).abs() < 1e-3);
+
+        // Area should match the mask up to the rasterized outline
+        assert!((observed[0] - expected[0]).abs() / expected[0] < 0.1);
+
+        // Hu moments are preserved when the polygon is scaled
+        let scaled: Vec<[f32; 2]> = circle.iter().map(|p| [p[0] * 3.0, p[1] * 3.0]).collect();
+        let scaled = polygon_moments(&scaled);
+        assert!((scaled[17] - observed[17]).abs() < 1e-3);
+    }
 }
@@ -10,6 +10,7 @@ use clap::Args;
 use kdam::TqdmParallelIterator;
 use polars::prelude::*;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
 use thyme_core::constant;
 use thyme_core::error::ThymeError;
@@ -19,7 +20,10 @@ use thyme_core::ut;
 use thyme_data::data::Weights;
 use thyme_neural::nn::Models;
 
-#[derive(Debug, Args)]
+use crate::output::{OutputMode, npz_options, prepare_output};
+use crate::provenance;
+
+#[derive(Debug, Args, Serialize)]
 pub struct NeuralArgs {
     #[arg(short = 'i', long, help = "Image or image directory.", required = true)]
     pub images: Option<String>,
@@ -27,7 +31,7 @@ pub struct NeuralArgs {
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq, .npy, .npz)."
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather, .npy, .npz)."
     )]
     pub output: Option<String>,
 
@@ -39,6 +43,13 @@ pub struct NeuralArgs {
     )]
     pub model: Option<String>,
 
+    #[arg(
+        long,
+        help = "Channel map applied before model preprocessing: auto, gray-average, or input channel indices for each RGB channel (e.g. 0,0,0 or 1,2,0).",
+        default_value = "auto"
+    )]
+    pub channel_map: Option<String>,
+
     #[arg(
         short = 'd',
         long,
@@ -53,6 +64,65 @@ pub struct NeuralArgs {
     #[arg(long, help = "Substring specifying images (e.g. _image).")]
     pub image_substring: Option<String>,
 
+    #[arg(
+        long,
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write .npz embeddings in the version 1 layout (image, embedding) without format_version and attrs members."
+    )]
+    pub legacy_npz: bool,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Replace an existing output file and its provenance sidecar. Without it, existing output files are rejected."
+    )]
+    pub overwrite: bool,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
@@ -89,6 +159,23 @@ pub fn measure_neural(args: &NeuralArgs) {
         Device::Cpu
     };
 
+    if let Some(threads) = args.threads.to_owned() {
+        if threads < 1 {
+            println!(
+                "[thyme::measure::neural] Threads must be set to a positive integer if provided."
+            );
+            std::process::exit(1);
+        }
+    }
+
+    // The thread count only applies to models run on the cpu
+    let threads = args.threads.filter(|_| matches!(device, Device::Cpu));
+
+    ut::threads::install(threads, || run(args, device));
+}
+
+/// Run the command on the thread pool selected by `measure_neural`
+fn run(args: &NeuralArgs, device: Device) {
     let model_name = args
         .model
         .to_owned()
@@ -99,6 +186,49 @@ pub fn measure_neural(args: &NeuralArgs) {
         Weights::select(&model_name);
     }
 
+    let channel_map = im::ChannelMap::parse(args.channel_map.as_deref().unwrap_or("auto"))
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+
+    if channel_map.channels().is_some_and(|n| n != 1 && n != 3) {
+        eprintln!(
+            "[thyme::measure::neural] ERROR: channel_map must produce 1 or 3 channels but {} produces {}.",
+            channel_map,
+            channel_map.channels().unwrap()
+        );
+        std::process::exit(1);
+    }
+
+    ut::track::progress_log(
+        &format!("Channel map: {}.", channel_map.describe()),
+        args.verbose,
+    );
+
+    let ipc_compression = args
+        .ipc_compression
+        .to_owned()
+        .unwrap_or("none".to_string());
+
+    if !constant::SUPPORTED_IPC_COMPRESSION.contains(&ipc_compression.as_str()) {
+        eprintln!(
+            "[thyme::measure::neural] ERROR: Invalid ipc_compression {}. Must be one of: {:?}.",
+            ipc_compression,
+            constant::SUPPORTED_IPC_COMPRESSION
+        );
+        std::process::exit(1);
+    }
+
+    let float_format = io::FloatFormat::new(
+        args.float_precision,
+        args.float_format.as_deref().unwrap_or("auto"),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("[thyme::measure::neural] ERROR: {}.", err);
+        std::process::exit(1);
+    });
+
     let image_path = args.images.to_owned().unwrap();
 
     let image_extension = Path::new(&image_path)
@@ -121,22 +251,6 @@ pub fn measure_neural(args: &NeuralArgs) {
     };
 
     if let Some(output) = args.output.to_owned() {
-        if let Some(threads) = args.threads.to_owned() {
-            if threads < 1 {
-                println!(
-                    "[thyme::measure::neural] Threads must be set to a positive integer if provided."
-                );
-                std::process::exit(1);
-            }
-
-            if matches!(device, Device::Cpu) {
-                rayon::ThreadPoolBuilder::new()
-                    .num_threads(threads)
-                    .build_global()
-                    .unwrap();
-            }
-        }
-
         if !is_image_dir {
             eprintln!(
                 "[thyme::measure::neural] ERROR: If output is provided, then input image path must specify an image directory."
@@ -152,12 +266,16 @@ pub fn measure_neural(args: &NeuralArgs) {
             .map(|s| s.to_lowercase());
 
         if let Some(ext) = &extension {
-            if !["npy", "npz", "csv", "txt", "tsv", "pq"]
+            if !constant::SUPPORTED_TABLE_FORMATS
                 .iter()
+                .chain(&constant::SUPPORTED_EMBEDDING_FORMATS)
                 .any(|e| e == ext)
             {
                 eprintln!(
-                    "[thyme::measure::neural] ERROR: Invalid file extension. Must end with one of .npy, .npz, .csv, .txt, .tsv, .pq."
+                    "[thyme::measure::neural] ERROR: Invalid file extension {}. Must be one of: {:?} or {:?}.",
+                    ext,
+                    constant::SUPPORTED_TABLE_FORMATS,
+                    constant::SUPPORTED_EMBEDDING_FORMATS
                 );
                 std::process::exit(1);
             }
@@ -169,7 +287,7 @@ pub fn measure_neural(args: &NeuralArgs) {
         }
 
         if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
                 eprintln!(
                     "[thyme::measure::neural] ERROR: Invalid file path. Parent directory of output file path does not exist."
                 );
@@ -177,11 +295,26 @@ pub fn measure_neural(args: &NeuralArgs) {
             }
         }
 
-        let image_files = ut::path::collect_file_paths(
-            &image_path,
-            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-            args.image_substring.to_owned(),
+        let output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| prepare_output(&output, mode, &[&image_path], "measure::neural"))
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::neural] ERROR: {}.", err);
+                std::process::exit(1);
+            });
+
+        let image_files = ut::path::FileSelector::new(
+            args.image_substring.as_deref(),
+            args.image_glob.as_deref(),
+            args.image_regex.as_deref(),
         )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &image_path,
+                constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
         .unwrap_or_else(|err| {
             eprintln!("{}", err);
             std::process::exit(1);
@@ -214,7 +347,7 @@ pub fn measure_neural(args: &NeuralArgs) {
             .into_par_iter()
             .tqdm_with_bar(pb)
             .for_each(|idx| {
-                let result = neural(&image_files[idx], &model, &device);
+                let result = neural(&image_files[idx], &model, &device, &channel_map);
 
                 let image_name = image_files[idx]
                     .file_stem()
@@ -226,11 +359,16 @@ pub fn measure_neural(args: &NeuralArgs) {
                     name.lock().unwrap().push(image_name);
                     data.lock().unwrap().push(descriptors);
                 } else {
-                    failure.lock().unwrap().push(format!(
-                        "{}\t{}",
-                        image_name,
-                        result.unwrap_err()
-                    ));
+                    let err = result.unwrap_err();
+
+                    if let ThymeError::ChannelMapError(_) = err {
+                        eprintln!("[thyme::measure::neural] ERROR: {} {}", image_name, err);
+                    }
+
+                    failure
+                        .lock()
+                        .unwrap()
+                        .push(format!("{}\t{}", image_name, err));
                 }
             });
 
@@ -258,7 +396,29 @@ pub fn measure_neural(args: &NeuralArgs) {
         ut::track::progress_log(message, args.verbose);
 
         if !data.is_empty() {
-            write_neural(&data, &name, &output, extension.unwrap().as_str());
+            write_neural(
+                &data,
+                &name,
+                &output,
+                extension.unwrap().as_str(),
+                &ipc_compression,
+                &float_format,
+                &npz_options(&model_name, &channel_map, None, None, args.legacy_npz),
+            );
+
+            if args.provenance {
+                provenance::record(
+                    args,
+                    &image_files,
+                    Some((&model_name, &channel_map)),
+                    args.hash_inputs,
+                    &[&output],
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("[thyme::measure::neural] ERROR: {}", err);
+                    std::process::exit(1);
+                });
+            }
         }
     } else {
         if is_image_dir {
@@ -279,10 +439,15 @@ pub fn measure_neural(args: &NeuralArgs) {
 
         let model = Models::load(&model_name, &device, args.verbose);
 
-        let data = neural(Path::new(&image_path), &model, &device).unwrap_or_else(|_| {
-            eprintln!("[thyme::measure::neural] ERROR: Failed to measure neural descriptors.");
-            std::process::exit(1);
-        });
+        let data =
+            neural(Path::new(&image_path), &model, &device, &channel_map).unwrap_or_else(|err| {
+                if let ThymeError::ChannelMapError(_) = err {
+                    eprintln!("{}", err);
+                }
+
+                eprintln!("[thyme::measure::neural] ERROR: Failed to measure neural descriptors.");
+                std::process::exit(1);
+            });
 
         let output: String = data
             .iter()
@@ -297,9 +462,20 @@ pub fn measure_neural(args: &NeuralArgs) {
 }
 
 /// Measure neural descriptors across an image
-fn neural(image_path: &Path, model: &Models, device: &Device) -> Result<Vec<f32>, ThymeError> {
+fn neural(
+    image_path: &Path,
+    model: &Models,
+    device: &Device,
+    channel_map: &im::ChannelMap,
+) -> Result<Vec<f32>, ThymeError> {
     let image = im::ThymeImage::open(image_path)?;
 
+    // Auto is left to model preprocessing which applies the same mapping
+    let image = match channel_map {
+        im::ChannelMap::Auto => image,
+        map => image.gather_channels(map)?,
+    };
+
     Ok(model
         .forward(&model.preprocess(&image, device).unwrap())
         .unwrap()
@@ -310,11 +486,19 @@ fn neural(image_path: &Path, model: &Models, device: &Device) -> Result<Vec<f32>
 }
 
 /// Write neural descriptors to data table
-fn write_neural(data: &[Vec<f32>], name: &Vec<String>, output: &PathBuf, extension: &str) {
+fn write_neural(
+    data: &[Vec<f32>],
+    name: &Vec<String>,
+    output: &PathBuf,
+    extension: &str,
+    ipc_compression: &str,
+    float_format: &io::FloatFormat,
+    npz_options: &io::NpzOptions,
+) {
     let n_row = data.len();
     let n_col = data[0].len();
 
-    if ["csv", "txt", "tsv", "pq"].iter().any(|e| e == &extension) {
+    if constant::SUPPORTED_TABLE_FORMATS.contains(&extension) {
         let mut df = DataFrame::new(vec![Column::new("image".into(), &name)]).unwrap();
 
         let mut column_data: Vec<Vec<f32>> = vec![Vec::with_capacity(n_row); n_col];
@@ -330,10 +514,11 @@ fn write_neural(data: &[Vec<f32>], name: &Vec<String>, output: &PathBuf, extensi
                 .unwrap();
         }
 
-        io::write_table(&mut df, output).unwrap_or_else(|_| {
-            eprintln!("[thyme::measure::neural] ERROR: Failed to write embeddings to a table.");
-            std::process::exit(1);
-        });
+        io::write_table_with_float_format(&mut df, output, ipc_compression, float_format)
+            .unwrap_or_else(|_| {
+                eprintln!("[thyme::measure::neural] ERROR: Failed to write embeddings to a table.");
+                std::process::exit(1);
+            });
     } else if extension == "npy" {
         io::write_numpy(
             output,
@@ -345,12 +530,19 @@ fn write_neural(data: &[Vec<f32>], name: &Vec<String>, output: &PathBuf, extensi
             std::process::exit(1);
         });
     } else if extension == "npz" {
-        io::write_embeddings_npz(name.to_vec(), vec![], vec![], data.to_vec(), &output)
-            .unwrap_or_else(|_| {
-                eprintln!(
-                    "[thyme::measure::neural] ERROR: Failed to write embeddings to an npz array."
-                );
-                std::process::exit(1);
-            });
+        io::write_embeddings_npz_with(
+            name.to_vec(),
+            vec![],
+            vec![],
+            data.to_vec(),
+            &output,
+            npz_options,
+        )
+        .unwrap_or_else(|_| {
+            eprintln!(
+                "[thyme::measure::neural] ERROR: Failed to write embeddings to an npz array."
+            );
+            std::process::exit(1);
+        });
     }
 }
@@ -9,14 +9,19 @@ use clap::Args;
 use kdam::TqdmParallelIterator;
 use polars::prelude::*;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
 use thyme_core::constant;
+use thyme_core::cv::features;
 use thyme_core::error::ThymeError;
 use thyme_core::im;
 use thyme_core::io;
 use thyme_core::ut;
 
-#[derive(Debug, Args)]
+use crate::output::{OutputMode, prepare_output};
+use crate::provenance;
+
+#[derive(Debug, Args, Serialize)]
 pub struct TextureArgs {
     #[arg(short = 'i', long, help = "Image or image directory.", required = true)]
     pub images: Option<String>,
@@ -24,7 +29,7 @@ pub struct TextureArgs {
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq)."
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather)."
     )]
     pub output: Option<String>,
 
@@ -34,6 +39,94 @@ pub struct TextureArgs {
     #[arg(long, help = "Substring specifying images (e.g. _image).")]
     pub image_substring: Option<String>,
 
+    #[arg(
+        long,
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Co-occurence conventions (default, pyradiomics, pyradiomics:<bin_width>). Pyradiomics averages offsets at distances 1 to 3 of gray levels binned with a fixed width (25 unless provided).",
+        default_value = "default"
+    )]
+    pub texture_preset: Option<String>,
+
+    #[arg(
+        long,
+        help = "Directory to write the normalized co-occurence matrix of each object, angle, and channel as npy files (single image only).",
+        requires = "mask"
+    )]
+    pub export_glcm: Option<String>,
+
+    #[arg(
+        long,
+        help = "Labeled mask of the image defining the objects of --export-glcm.",
+        requires = "export_glcm"
+    )]
+    pub mask: Option<String>,
+
+    #[arg(
+        long,
+        help = "Comma-separated labels of the mask objects to export (e.g. 1,5,9).",
+        requires = "export_glcm"
+    )]
+    pub labels: Option<String>,
+
+    #[arg(
+        long,
+        help = "Maximum number of mask objects to export, in order of their labels.",
+        requires = "export_glcm"
+    )]
+    pub max_objects: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Replace an existing output file and its provenance sidecar. Without it, existing output files are rejected."
+    )]
+    pub overwrite: bool,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
@@ -46,11 +139,57 @@ pub fn measure_texture(args: &TextureArgs) {
             );
             std::process::exit(1);
         }
+    }
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `measure_texture`
+fn run(args: &TextureArgs) {
+    let ipc_compression = args
+        .ipc_compression
+        .to_owned()
+        .unwrap_or("none".to_string());
+
+    if !constant::SUPPORTED_IPC_COMPRESSION.contains(&ipc_compression.as_str()) {
+        eprintln!(
+            "[thyme::measure::texture] ERROR: Invalid ipc_compression {}. Must be one of: {:?}.",
+            ipc_compression,
+            constant::SUPPORTED_IPC_COMPRESSION
+        );
+        std::process::exit(1);
+    }
+
+    let float_format = io::FloatFormat::new(
+        args.float_precision,
+        args.float_format.as_deref().unwrap_or("auto"),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("[thyme::measure::texture] ERROR: {}.", err);
+        std::process::exit(1);
+    });
+
+    let preset =
+        features::TexturePreset::parse(args.texture_preset.as_deref().unwrap_or("default"))
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::texture] ERROR: {}", err);
+                std::process::exit(1);
+            });
+
+    if args.export_glcm.is_some() {
+        if args.output.is_some() {
+            eprintln!(
+                "[thyme::measure::texture] ERROR: Co-occurence matrices can only be exported for a single image without an output table."
+            );
+            std::process::exit(1);
+        }
+
+        if args.labels.is_none() && args.max_objects.is_none() {
+            eprintln!(
+                "[thyme::measure::texture] ERROR: Exporting co-occurence matrices requires --labels or --max-objects to limit the number of written files."
+            );
+            std::process::exit(1);
+        }
     }
 
     let image_path = args.images.to_owned().unwrap();
@@ -90,9 +229,11 @@ pub fn measure_texture(args: &TextureArgs) {
             .map(|s| s.to_lowercase());
 
         if let Some(ext) = &extension {
-            if !["csv", "txt", "tsv", "pq"].iter().any(|e| e == ext) {
+            if !constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
                 eprintln!(
-                    "[thyme::measure::texture] ERROR: Invalid file extension. Must end with one of .csv, .txt, .tsv, .pq."
+                    "[thyme::measure::texture] ERROR: Invalid file extension {}. Must be one of: {:?}.",
+                    ext,
+                    constant::SUPPORTED_TABLE_FORMATS
                 );
                 std::process::exit(1);
             }
@@ -104,7 +245,7 @@ pub fn measure_texture(args: &TextureArgs) {
         }
 
         if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
                 eprintln!(
                     "[thyme::measure::texture] ERROR: Invalid file path. Parent directory of output file path does not exist."
                 );
@@ -112,11 +253,26 @@ pub fn measure_texture(args: &TextureArgs) {
             }
         }
 
-        let image_files = ut::path::collect_file_paths(
-            &image_path,
-            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-            args.image_substring.to_owned(),
+        let output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| prepare_output(&output, mode, &[&image_path], "measure::texture"))
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::texture] ERROR: {}.", err);
+                std::process::exit(1);
+            });
+
+        let image_files = ut::path::FileSelector::new(
+            args.image_substring.as_deref(),
+            args.image_glob.as_deref(),
+            args.image_regex.as_deref(),
         )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &image_path,
+                constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
         .unwrap_or_else(|err| {
             eprintln!("{}", err);
             std::process::exit(1);
@@ -147,7 +303,7 @@ pub fn measure_texture(args: &TextureArgs) {
             .into_par_iter()
             .tqdm_with_bar(pb)
             .for_each(|idx| {
-                let result = texture(&image_files[idx]);
+                let result = texture(&image_files[idx], preset);
 
                 let image_name = image_files[idx]
                     .file_stem()
@@ -176,7 +332,22 @@ pub fn measure_texture(args: &TextureArgs) {
         }
 
         if !data.is_empty() {
-            write_texture(&data, &name, &output);
+            let descriptors_path =
+                write_texture(&data, &name, &output, &ipc_compression, &float_format);
+
+            if args.provenance {
+                provenance::record(
+                    args,
+                    &image_files,
+                    None,
+                    args.hash_inputs,
+                    &[&descriptors_path],
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("[thyme::measure::texture] ERROR: {}", err);
+                    std::process::exit(1);
+                });
+            }
         }
 
         let message = if !failure.is_empty() {
@@ -210,14 +381,67 @@ pub fn measure_texture(args: &TextureArgs) {
             std::process::exit(1);
         }
 
-        let data = texture(Path::new(&image_path)).unwrap_or_else(|_| {
+        if let Some(directory) = &args.export_glcm {
+            let labels: Option<Vec<u32>> = args.labels.as_ref().map(|labels| {
+                labels
+                    .split(',')
+                    .map(|label| label.trim().parse::<u32>())
+                    .collect::<Result<Vec<u32>, _>>()
+                    .unwrap_or_else(|_| {
+                        eprintln!(
+                            "[thyme::measure::texture] ERROR: Invalid labels {}. Must be comma-separated positive integers (e.g. 1,5,9).",
+                            labels
+                        );
+                        std::process::exit(1);
+                    })
+            });
+
+            let mask_path = args.mask.to_owned().unwrap();
+
+            if !Path::new(&mask_path).is_file() {
+                eprintln!(
+                    "[thyme::measure::texture] ERROR: The provided mask file path does not exist."
+                );
+                std::process::exit(1);
+            }
+
+            std::fs::create_dir_all(directory).unwrap_or_else(|_| {
+                eprintln!(
+                    "[thyme::measure::texture] ERROR: Failed to create co-occurence matrix directory."
+                );
+                std::process::exit(1);
+            });
+
+            let written = export_glcm(
+                image_path,
+                Path::new(&mask_path),
+                labels.as_deref(),
+                args.max_objects,
+                Path::new(directory),
+                preset,
+            )
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::texture] ERROR: {}", err);
+                std::process::exit(1);
+            });
+
+            ut::track::progress_log(
+                &format!(
+                    "Exported {} co-occurence matrices.",
+                    ut::track::thousands_format(written)
+                ),
+                args.verbose,
+            );
+        }
+
+        let data = texture(Path::new(&image_path), preset).unwrap_or_else(|_| {
             eprintln!("[thyme::measure::texture] ERROR: Failed to measure texture descriptors.");
             std::process::exit(1);
         });
 
-        let output: Vec<String> = constant::TEXTURE_DESCRIPTOR_NAMES
+        let output: Vec<String> = constant::TEXTURE_DESCRIPTORS
             .iter()
-            .copied()
+            .map(|f| f.name)
             .zip(data.iter().map(|x| x.to_string()).collect::<Vec<String>>())
             .map(|(c, d)| format!("{}\t{}\n", c, d))
             .collect();
@@ -231,17 +455,124 @@ pub fn measure_texture(args: &TextureArgs) {
 }
 
 /// Measure texture descriptors across an image
-fn texture(image_path: &Path) -> Result<[f32; 13], ThymeError> {
+fn texture(image_path: &Path, preset: features::TexturePreset) -> Result<[f32; 13], ThymeError> {
     let image = im::ThymeImage::open(image_path)?;
 
     Ok(image
         .crop_view(0, 0, image.width(), image.height())
-        .texture())
+        .texture_preset(preset))
+}
+
+/// Write the co-occurence matrices of each object in a labeled mask
+///
+/// Objects are cropped from the image to their bounding box and a matrix of
+/// shape (`GLCM_LEVELS`, `GLCM_LEVELS`) is written for each texture angle and
+/// channel, using the same gray-level binning as the texture descriptors.
+/// Files are named `{image}_object{label}_angle{angle}_distance{distance}_channel{channel}.npy`,
+/// or `{image}_object{label}_offset{dx}_{dy}_channel{channel}.npy` for each
+/// offset of a non-default preset. Returns the number of written matrices.
+///
+/// # Arguments
+///
+/// * `image_path` - Path to an image
+/// * `mask_path` - Path to a labeled mask of the image
+/// * `labels` - Labels of the objects to export, or every object if `None`
+/// * `max_objects` - Maximum number of objects to export in order of their labels
+/// * `directory` - Output directory of the matrices
+/// * `preset` - Gray-level discretization and offsets of the matrices
+fn export_glcm(
+    image_path: &Path,
+    mask_path: &Path,
+    labels: Option<&[u32]>,
+    max_objects: Option<usize>,
+    directory: &Path,
+    preset: features::TexturePreset,
+) -> Result<usize, ThymeError> {
+    let image = im::ThymeImage::open(image_path)?;
+    let mut mask = im::ThymeMask::open(mask_path)?;
+
+    if image.width() != mask.width() || image.height() != mask.height() {
+        return Err(ThymeError::OtherError(
+            "Image and mask do not have the same dimensions".to_string(),
+        ));
+    }
+
+    let name = image_path
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+
+    let (object_labels, polygons) = mask.polygons()?;
+    let bounding_boxes = polygons.to_bounding_boxes()?;
+
+    let mut objects: Vec<(u32, [f32; 4])> = object_labels
+        .into_iter()
+        .zip(bounding_boxes.as_xyxy().iter().copied())
+        .filter(|(label, _)| labels.is_none_or(|labels| labels.contains(label)))
+        .collect();
+
+    objects.sort_by_key(|&(label, _)| label);
+    objects.truncate(max_objects.unwrap_or(usize::MAX));
+
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let shape = vec![constant::GLCM_LEVELS as u64; 2];
+    let mut written = 0;
+
+    for (label, [min_x, min_y, max_x, max_y]) in objects {
+        let min_x = min_x.max(0.0) as u32;
+        let min_y = min_y.max(0.0) as u32;
+        let max_x = max_x.min(width) as u32;
+        let max_y = max_y.min(height) as u32;
+
+        let view = image.crop_view(min_x, min_y, max_x - min_x, max_y - min_y);
+
+        if preset != features::TexturePreset::Default {
+            let fits = |[dx, dy]: &[i32; 2]| {
+                (dx.unsigned_abs() as usize) < view.width()
+                    && (dy.unsigned_abs() as usize) < view.height()
+            };
+
+            for [dx, dy] in preset.offsets().into_iter().filter(fits) {
+                for (channel, glcm) in view.glcm_preset([dx, dy], preset).iter().enumerate() {
+                    let path = directory.join(format!(
+                        "{}_object{}_offset{}_{}_channel{}.npy",
+                        name, label, dx, dy, channel
+                    ));
+
+                    io::write_numpy(path, glcm.to_vec(), shape.clone())?;
+                    written += 1;
+                }
+            }
+
+            continue;
+        }
+
+        for angle in constant::GLCM_ANGLES {
+            for (channel, glcm) in view.glcm(angle, 1.0).iter().enumerate() {
+                let path = directory.join(format!(
+                    "{}_object{}_angle{}_distance1_channel{}.npy",
+                    name, label, angle, channel
+                ));
+
+                io::write_numpy(path, glcm.to_vec(), shape.clone())?;
+                written += 1;
+            }
+        }
+    }
+
+    Ok(written)
 }
 
 /// Write texture descriptors to data table
-fn write_texture(data: &[[f32; 13]], name: &Vec<String>, output: &Path) {
-    let columns = constant::TEXTURE_DESCRIPTOR_NAMES;
+fn write_texture(
+    data: &[[f32; 13]],
+    name: &Vec<String>,
+    output: &Path,
+    ipc_compression: &str,
+    float_format: &io::FloatFormat,
+) -> PathBuf {
+    let columns = constant::TEXTURE_DESCRIPTORS.map(|f| f.name);
 
     let mut df = DataFrame::new(vec![Column::new("image".into(), &name)]).unwrap();
 
@@ -268,8 +599,185 @@ fn write_texture(data: &[[f32; 13]], name: &Vec<String>, output: &Path) {
         output.to_path_buf()
     };
 
-    io::write_table(&mut df, descriptors_path).unwrap_or_else(|_| {
-        eprintln!("[thyme::measure::texture] ERROR: Failed to write descriptors table.");
-        std::process::exit(1);
-    });
+    io::write_table_with_float_format(&mut df, &descriptors_path, ipc_compression, float_format)
+        .unwrap_or_else(|_| {
+            eprintln!("[thyme::measure::texture] ERROR: Failed to write descriptors table.");
+            std::process::exit(1);
+        });
+
+    descriptors_path
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::Parser;
+
+    use thyme_core::cv::features::GLCM;
+    use thyme_core::mp::texture::haralick_features;
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: TextureArgs,
+    }
+
+    #[test]
+    fn test_export_glcm() {
+        let directory = std::env::temp_dir().join("thyme_test_export_glcm");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+        let matrices = directory.join("glcm");
+        std::fs::create_dir_all(&matrices).unwrap();
+
+        SynthConfig {
+            width: 48,
+            height: 48,
+            objects: 2,
+            radius: [5.0, 8.0],
+            seed: 11,
+            ..Default::default()
+        }
+        .write(1, &data)
+        .unwrap();
+
+        let image_path = data.join("synth_0000_image.png");
+        let mask_path = data.join("synth_0000_mask.png");
+
+        let written = export_glcm(
+            &image_path,
+            &mask_path,
+            None,
+            Some(10),
+            &matrices,
+            features::TexturePreset::Default,
+        )
+        .unwrap();
+        assert_eq!(written, 2 * constant::GLCM_ANGLES.len());
+        assert_eq!(std::fs::read_dir(&matrices).unwrap().count(), written);
+
+        let image = im::ThymeImage::open(&image_path).unwrap();
+        let mut mask = im::ThymeMask::open(&mask_path).unwrap();
+        let (labels, polygons) = mask.polygons().unwrap();
+        let bounding_boxes = polygons.to_bounding_boxes().unwrap();
+
+        for (label, xyxy) in labels.iter().zip(bounding_boxes.as_xyxy()) {
+            let [min_x, min_y, max_x, max_y] = xyxy.map(|v| v as u32);
+            let view = image.crop_view(min_x, min_y, max_x - min_x, max_y - min_y);
+
+            let mut features = [0.0; 13];
+
+            for angle in constant::GLCM_ANGLES {
+                let path = matrices.join(format!(
+                    "synth_0000_image_object{}_angle{}_distance1_channel0.npy",
+                    label, angle
+                ));
+
+                let (values, shape) = io::read_numpy_f32(&path).unwrap();
+                assert_eq!(shape, vec![constant::GLCM_LEVELS as u64; 2]);
+                assert!((values.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+
+                let glcm = GLCM::from_vec(values).unwrap();
+                assert_eq!(glcm.as_matrix(), view.glcm(angle, 1.0)[0].as_matrix());
+
+                for (total, feature) in features.iter_mut().zip(haralick_features(&glcm)) {
+                    *total += feature / constant::GLCM_ANGLES.len() as f32;
+                }
+            }
+
+            for (exported, measured) in features.iter().zip(view.texture()) {
+                assert!((exported - measured).abs() < 1e-4);
+            }
+        }
+
+        // Selected labels are exported before the object cap is applied
+        std::fs::remove_dir_all(&matrices).unwrap();
+        std::fs::create_dir_all(&matrices).unwrap();
+
+        let written = export_glcm(
+            &image_path,
+            &mask_path,
+            Some(&[labels[1], 999]),
+            None,
+            &matrices,
+            features::TexturePreset::Default,
+        )
+        .unwrap();
+        assert_eq!(written, constant::GLCM_ANGLES.len());
+
+        let written = export_glcm(
+            &image_path,
+            &mask_path,
+            None,
+            Some(1),
+            &matrices,
+            features::TexturePreset::Default,
+        )
+        .unwrap();
+        assert_eq!(written, constant::GLCM_ANGLES.len());
+
+        let image = image_path.to_str().unwrap();
+        let export = matrices.to_str().unwrap();
+
+        assert!(Cli::try_parse_from(["thyme", "-i", image, "--export-glcm", export]).is_err());
+        assert!(Cli::try_parse_from(["thyme", "-i", image, "--max-objects", "1"]).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_texture_preset() {
+        let directory = std::env::temp_dir().join("thyme_test_texture_preset");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+        let matrices = directory.join("glcm");
+        std::fs::create_dir_all(&matrices).unwrap();
+
+        SynthConfig {
+            width: 48,
+            height: 48,
+            objects: 1,
+            radius: [8.0, 10.0],
+            seed: 5,
+            ..Default::default()
+        }
+        .write(1, &data)
+        .unwrap();
+
+        let image_path = data.join("synth_0000_image.png");
+        let mask_path = data.join("synth_0000_mask.png");
+
+        let preset = features::TexturePreset::parse("pyradiomics:10").unwrap();
+
+        let default = texture(&image_path, features::TexturePreset::Default).unwrap();
+        let pyradiomics = texture(&image_path, preset).unwrap();
+
+        assert_ne!(default, pyradiomics);
+        assert!(
+            pyradiomics.iter().all(|x| x.is_finite()),
+            "{:?}",
+            pyradiomics
+        );
+
+        let written =
+            export_glcm(&image_path, &mask_path, None, Some(1), &matrices, preset).unwrap();
+        assert_eq!(written, preset.offsets().len());
+        assert!(
+            matrices
+                .join("synth_0000_image_object1_offset-2_1_channel0.npy")
+                .is_file()
+        );
+
+        let image = image_path.to_str().unwrap();
+        assert!(
+            Cli::try_parse_from(["thyme", "-i", image, "--texture-preset", "pyradiomics"]).is_ok()
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
@@ -9,6 +9,7 @@ use clap::Args;
 use kdam::TqdmParallelIterator;
 use polars::prelude::*;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
 use thyme_core::constant;
 use thyme_core::error::ThymeError;
@@ -16,7 +17,10 @@ use thyme_core::im;
 use thyme_core::io;
 use thyme_core::ut;
 
-#[derive(Debug, Args)]
+use crate::output::{OutputMode, prepare_output};
+use crate::provenance;
+
+#[derive(Debug, Args, Serialize)]
 pub struct ZernikeArgs {
     #[arg(short = 'i', long, help = "Image or image directory.", required = true)]
     pub images: Option<String>,
@@ -24,7 +28,7 @@ pub struct ZernikeArgs {
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq)."
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather)."
     )]
     pub output: Option<String>,
 
@@ -34,6 +38,79 @@ pub struct ZernikeArgs {
     #[arg(long, help = "Substring specifying images (e.g. _image).")]
     pub image_substring: Option<String>,
 
+    #[arg(
+        long,
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Treat images as labeled masks and measure zernike descriptors for each object."
+    )]
+    pub masks: bool,
+
+    #[arg(
+        long,
+        help = "Comma-separated labels of the mask objects to measure (e.g. 1,5,9). All objects are measured if not provided.",
+        requires = "masks"
+    )]
+    pub labels: Option<String>,
+
+    #[arg(
+        long,
+        help = "Add padding around mask objects before computing zernike descriptors.",
+        default_value = "1"
+    )]
+    pub pad: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Replace an existing output file and its provenance sidecar. Without it, existing output files are rejected."
+    )]
+    pub overwrite: bool,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
@@ -46,11 +123,39 @@ pub fn measure_zernike(args: &ZernikeArgs) {
             );
             std::process::exit(1);
         }
+    }
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `measure_zernike`
+fn run(args: &ZernikeArgs) {
+    let ipc_compression = args
+        .ipc_compression
+        .to_owned()
+        .unwrap_or("none".to_string());
+
+    if !constant::SUPPORTED_IPC_COMPRESSION.contains(&ipc_compression.as_str()) {
+        eprintln!(
+            "[thyme::measure::zernike] ERROR: Invalid ipc_compression {}. Must be one of: {:?}.",
+            ipc_compression,
+            constant::SUPPORTED_IPC_COMPRESSION
+        );
+        std::process::exit(1);
+    }
+
+    let float_format = io::FloatFormat::new(
+        args.float_precision,
+        args.float_format.as_deref().unwrap_or("auto"),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("[thyme::measure::zernike] ERROR: {}.", err);
+        std::process::exit(1);
+    });
+
+    if args.masks {
+        measure_mask_zernike(args, &ipc_compression, &float_format);
+        return;
     }
 
     let image_path = args.images.to_owned().unwrap();
@@ -90,9 +195,11 @@ pub fn measure_zernike(args: &ZernikeArgs) {
             .map(|s| s.to_lowercase());
 
         if let Some(ext) = &extension {
-            if !["csv", "txt", "tsv", "pq"].iter().any(|e| e == ext) {
+            if !constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
                 eprintln!(
-                    "[thyme::measure::zernike] ERROR: Invalid file extension. Must end with one of .csv, .txt, .tsv, .pq."
+                    "[thyme::measure::zernike] ERROR: Invalid file extension {}. Must be one of: {:?}.",
+                    ext,
+                    constant::SUPPORTED_TABLE_FORMATS
                 );
                 std::process::exit(1);
             }
@@ -104,7 +211,7 @@ pub fn measure_zernike(args: &ZernikeArgs) {
         }
 
         if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
                 eprintln!(
                     "[thyme::measure::zernike] ERROR: Invalid file path. Parent directory of output file path does not exist."
                 );
@@ -112,11 +219,26 @@ pub fn measure_zernike(args: &ZernikeArgs) {
             }
         }
 
-        let image_files = ut::path::collect_file_paths(
-            &image_path,
-            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-            args.image_substring.to_owned(),
+        let output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| prepare_output(&output, mode, &[&image_path], "measure::zernike"))
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::zernike] ERROR: {}.", err);
+                std::process::exit(1);
+            });
+
+        let image_files = ut::path::FileSelector::new(
+            args.image_substring.as_deref(),
+            args.image_glob.as_deref(),
+            args.image_regex.as_deref(),
         )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &image_path,
+                constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
         .unwrap_or_else(|err| {
             eprintln!("{}", err);
             std::process::exit(1);
@@ -176,7 +298,22 @@ pub fn measure_zernike(args: &ZernikeArgs) {
         }
 
         if !data.is_empty() {
-            write_zernike(&data, &name, &output);
+            let descriptors_path =
+                write_zernike(&data, &name, None, &output, &ipc_compression, &float_format);
+
+            if args.provenance {
+                provenance::record(
+                    args,
+                    &image_files,
+                    None,
+                    args.hash_inputs,
+                    &[&descriptors_path],
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("[thyme::measure::zernike] ERROR: {}", err);
+                    std::process::exit(1);
+                });
+            }
         }
 
         let message = if !failure.is_empty() {
@@ -215,9 +352,9 @@ pub fn measure_zernike(args: &ZernikeArgs) {
             std::process::exit(1);
         });
 
-        let output: Vec<String> = constant::ZERNIKE_DESCRIPTOR_NAMES
+        let output: Vec<String> = constant::ZERNIKE_DESCRIPTORS
             .iter()
-            .copied()
+            .map(|f| f.name)
             .zip(data.iter().map(|x| x.to_string()).collect::<Vec<String>>())
             .map(|(c, d)| format!("{}\t{}\n", c, d))
             .collect();
@@ -230,6 +367,251 @@ pub fn measure_zernike(args: &ZernikeArgs) {
     }
 }
 
+/// Measure zernike descriptors for each object in a set of labeled masks
+fn measure_mask_zernike(args: &ZernikeArgs, ipc_compression: &str, float_format: &io::FloatFormat) {
+    let mask_path = args.images.to_owned().unwrap();
+
+    let labels: Option<Vec<u32>> = args.labels.as_ref().map(|labels| {
+        labels
+            .split(',')
+            .map(|label| label.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .unwrap_or_else(|_| {
+                eprintln!(
+                    "[thyme::measure::zernike] ERROR: Invalid labels {}. Must be comma-separated positive integers (e.g. 1,5,9).",
+                    labels
+                );
+                std::process::exit(1);
+            })
+    });
+
+    let pad = args.pad.unwrap_or(1);
+
+    let mask_extension = Path::new(&mask_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+
+    let is_mask_dir = if let Some(ext) = &mask_extension {
+        if !constant::SUPPORTED_IMAGE_FORMATS.contains(&ext.as_str()) {
+            eprintln!(
+                "[thyme::measure::zernike] ERROR: Invalid mask extension {}. Must be one of: {:?}.",
+                ext,
+                constant::SUPPORTED_IMAGE_FORMATS
+            );
+            std::process::exit(1);
+        }
+        false
+    } else {
+        true
+    };
+
+    if let Some(output) = args.output.to_owned() {
+        if !is_mask_dir {
+            eprintln!(
+                "[thyme::measure::zernike] ERROR: If output is provided, then input mask path must specify a mask directory."
+            );
+            std::process::exit(1);
+        }
+
+        let output = PathBuf::from(output);
+
+        let extension = output
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase());
+
+        if let Some(ext) = &extension {
+            if !constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
+                eprintln!(
+                    "[thyme::measure::zernike] ERROR: Invalid file extension {}. Must be one of: {:?}.",
+                    ext,
+                    constant::SUPPORTED_TABLE_FORMATS
+                );
+                std::process::exit(1);
+            }
+        } else {
+            eprintln!(
+                "[thyme::measure::zernike] ERROR: Invalid output path. Output file must be a file with a valid extension."
+            );
+            std::process::exit(1);
+        }
+
+        if let Some(parent) = output.parent() {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
+                eprintln!(
+                    "[thyme::measure::zernike] ERROR: Invalid file path. Parent directory of output file path does not exist."
+                );
+                std::process::exit(1);
+            }
+        }
+
+        let output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| prepare_output(&output, mode, &[&mask_path], "measure::zernike"))
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::measure::zernike] ERROR: {}.", err);
+                std::process::exit(1);
+            });
+
+        let mask_files = ut::path::FileSelector::new(
+            args.image_substring.as_deref(),
+            args.image_glob.as_deref(),
+            args.image_regex.as_deref(),
+        )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &mask_path,
+                constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+
+        if mask_files.is_empty() {
+            eprintln!(
+                "[thyme::measure::zernike] ERROR: No mask files were detected. Please check your path and/or substring identifier."
+            );
+            std::process::exit(1);
+        }
+
+        ut::track::progress_log(
+            &format!(
+                "Detected {} masks.",
+                ut::track::thousands_format(mask_files.len())
+            ),
+            args.verbose,
+        );
+
+        let pb = ut::track::progress_bar(mask_files.len(), "Measuring zernike", args.verbose);
+
+        let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(mask_files.len()));
+        let name: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(mask_files.len()));
+        let label: Mutex<Vec<u32>> = Mutex::new(Vec::with_capacity(mask_files.len()));
+        let data: Mutex<Vec<[f32; 30]>> = Mutex::new(Vec::with_capacity(mask_files.len()));
+
+        (0..mask_files.len())
+            .into_par_iter()
+            .tqdm_with_bar(pb)
+            .for_each(|idx| {
+                let result = mask_zernike(&mask_files[idx], labels.as_deref(), pad);
+
+                let mask_name = mask_files[idx]
+                    .file_stem()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+
+                match result {
+                    Ok((labels, descriptors)) => {
+                        // Rows of a mask are added together so they stay aligned
+                        let mut name = name.lock().unwrap();
+                        let mut label = label.lock().unwrap();
+                        let mut data = data.lock().unwrap();
+
+                        name.extend(labels.iter().map(|_| mask_name.clone()));
+                        label.extend(labels);
+                        data.extend(descriptors);
+                    }
+                    Err(err) => {
+                        failure
+                            .lock()
+                            .unwrap()
+                            .push(format!("{}\t{}", mask_name, err));
+                    }
+                }
+            });
+
+        let failure = failure.into_inner().unwrap();
+        let name = name.into_inner().unwrap();
+        let label = label.into_inner().unwrap();
+        let data = data.into_inner().unwrap();
+
+        if args.verbose {
+            println!()
+        }
+
+        if !data.is_empty() {
+            let descriptors_path = write_zernike(
+                &data,
+                &name,
+                Some(&label),
+                &output,
+                ipc_compression,
+                float_format,
+            );
+
+            if args.provenance {
+                provenance::record(
+                    args,
+                    &mask_files,
+                    None,
+                    args.hash_inputs,
+                    &[&descriptors_path],
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("[thyme::measure::zernike] ERROR: {}", err);
+                    std::process::exit(1);
+                });
+            }
+        }
+
+        let message = if !failure.is_empty() {
+            &format!(
+                "Complete. {} masks measured succesfully. {} masks failed.",
+                ut::track::thousands_format(mask_files.len() - failure.len()),
+                ut::track::thousands_format(failure.len())
+            )
+        } else {
+            &format!(
+                "Complete. {} masks measured successfully.",
+                ut::track::thousands_format(mask_files.len() - failure.len()),
+            )
+        };
+
+        ut::track::progress_log(message, args.verbose);
+    } else {
+        if is_mask_dir {
+            eprintln!(
+                "[thyme::measure::zernike] ERROR: If output is not provided, then input mask path should specify a single file."
+            );
+            std::process::exit(1);
+        }
+
+        let mask_path = Path::new(&mask_path);
+
+        if !mask_path.is_file() {
+            eprintln!(
+                "[thyme::measure::zernike] ERROR: The provided mask file path does not exist."
+            );
+            std::process::exit(1);
+        }
+
+        let (labels, data) = mask_zernike(mask_path, labels.as_deref(), pad).unwrap_or_else(|_| {
+            eprintln!("[thyme::measure::zernike] ERROR: Failed to measure zernike descriptors.");
+            std::process::exit(1);
+        });
+
+        let mut stdout = std::io::stdout();
+
+        for (label, d) in labels.iter().zip(data.iter()) {
+            let output: Vec<String> = constant::ZERNIKE_DESCRIPTORS
+                .iter()
+                .map(|f| f.name)
+                .zip(d.iter().map(|x| x.to_string()).collect::<Vec<String>>())
+                .map(|(c, d)| format!("label_{}\t{}\t{}\n", label, c, d))
+                .collect();
+
+            for row in output.iter() {
+                stdout.write_all(row.as_bytes()).unwrap();
+            }
+        }
+    }
+}
+
 /// Measure zernike descriptors across an image
 fn zernike(image_path: &Path) -> Result<[f32; 30], ThymeError> {
     let image = im::ThymeImage::open(image_path)?;
@@ -239,12 +621,74 @@ fn zernike(image_path: &Path) -> Result<[f32; 30], ThymeError> {
         .zernike())
 }
 
+/// Measure zernike descriptors for each object in a labeled mask
+///
+/// Objects are cropped to their bounding box with padding and binarized in
+/// the same way as mask objects profiled with mode `m`, so descriptors match
+/// the `mask_zernike_*` columns of `profile mask` run with the same padding.
+/// Returns the label of each measured object alongside its descriptors.
+///
+/// # Arguments
+///
+/// * `mask_path` - Path to a labeled mask
+/// * `labels` - Labels of the objects to measure, or every object if `None`
+/// * `pad` - Padding added around the bounding box of each object
+fn mask_zernike(
+    mask_path: &Path,
+    labels: Option<&[u32]>,
+    pad: u32,
+) -> Result<(Vec<u32>, Vec<[f32; 30]>), ThymeError> {
+    let mut mask = im::ThymeMask::open(mask_path)?;
+
+    let (object_labels, polygons) = mask.polygons()?;
+    let bounding_boxes = polygons.to_bounding_boxes()?;
+
+    let (width, height) = (mask.width() as f32, mask.height() as f32);
+    let pad = pad as f32;
+
+    let mut measured = Vec::new();
+    let mut data = Vec::new();
+
+    for (&label, xyxy) in object_labels.iter().zip(bounding_boxes.as_xyxy()) {
+        if labels.is_some_and(|labels| !labels.contains(&label)) {
+            continue;
+        }
+
+        let [min_x, min_y, max_x, max_y] = *xyxy;
+
+        let min_x = (min_x - pad).max(0.0) as u32;
+        let min_y = (min_y - pad).max(0.0) as u32;
+        let max_x = (max_x + pad).min(width) as u32;
+        let max_y = (max_y + pad).min(height) as u32;
+
+        let (w, h) = (max_x - min_x, max_y - min_y);
+
+        let object = mask.crop_binary(min_x, min_y, w, h, label)?;
+
+        measured.push(label);
+        data.push(object.crop_view(0, 0, w, h).zernike());
+    }
+
+    Ok((measured, data))
+}
+
 /// Write zernike descriptors to data table
-fn write_zernike(data: &[[f32; 30]], name: &Vec<String>, output: &Path) {
-    let columns = constant::ZERNIKE_DESCRIPTOR_NAMES;
+fn write_zernike(
+    data: &[[f32; 30]],
+    name: &Vec<String>,
+    label: Option<&Vec<u32>>,
+    output: &Path,
+    ipc_compression: &str,
+    float_format: &io::FloatFormat,
+) -> PathBuf {
+    let columns = constant::ZERNIKE_DESCRIPTORS.map(|f| f.name);
 
     let mut df = DataFrame::new(vec![Column::new("image".into(), &name)]).unwrap();
 
+    if let Some(label) = label {
+        df.with_column(Column::new("label".into(), label)).unwrap();
+    }
+
     // Note that this requires generating two copies of the computed descriptors
     // which is definitely not ideal. We probaby want to redesign the computation
     // so that column-major data is generated directly or we just use a flat buffer
@@ -268,8 +712,151 @@ fn write_zernike(data: &[[f32; 30]], name: &Vec<String>, output: &Path) {
         output.to_path_buf()
     };
 
-    io::write_table(&mut df, descriptors_path).unwrap_or_else(|_| {
-        eprintln!("[thyme::measure::zernike] ERROR: Failed to write descriptors table.");
-        std::process::exit(1);
-    });
+    io::write_table_with_float_format(&mut df, &descriptors_path, ipc_compression, float_format)
+        .unwrap_or_else(|_| {
+            eprintln!("[thyme::measure::zernike] ERROR: Failed to write descriptors table.");
+            std::process::exit(1);
+        });
+
+    descriptors_path
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::Parser;
+
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+    use crate::profile::{ProfileMaskArgs, profile_image_mask};
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: ZernikeArgs,
+    }
+
+    #[derive(Parser)]
+    struct ProfileCli {
+        #[command(flatten)]
+        args: ProfileMaskArgs,
+    }
+
+    fn parse<T: Parser>(args: &[&str]) -> T {
+        T::parse_from(std::iter::once("thyme").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_mask_zernike_matches_profile() {
+        let directory = std::env::temp_dir().join("thyme_test_mask_zernike");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: 6,
+            radius: [3.0, 6.0],
+            seed: 4,
+            ..Default::default()
+        }
+        .write(1, &data)
+        .unwrap();
+
+        let data = data.to_str().unwrap();
+        let zernike = directory.join("zernike.csv");
+        let profile = directory.join("profile.csv");
+
+        measure_zernike(
+            &parse::<Cli>(&[
+                "-i",
+                data,
+                "-o",
+                zernike.to_str().unwrap(),
+                "--image-substring",
+                "_mask",
+                "--masks",
+            ])
+            .args,
+        );
+
+        profile_image_mask(
+            &parse::<ProfileCli>(&[
+                "-i",
+                data,
+                "-o",
+                profile.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                "--mask-substring",
+                "_mask",
+                "-m",
+                "m",
+            ])
+            .args,
+        );
+
+        let zernike = io::read_table(&zernike).unwrap();
+        let profile = io::read_table(&profile).unwrap();
+
+        assert_eq!(zernike.height(), profile.height());
+        assert_eq!(zernike.width(), 2 + 30);
+
+        // Profile objects are indexed in the order of the mask polygons
+        let mut mask = im::ThymeMask::open(format!("{}/synth_0000_mask.png", data)).unwrap();
+        let (labels, _) = mask.polygons().unwrap();
+
+        let object = 2;
+        let label = zernike.column("label").unwrap().i64().unwrap();
+        let row = label
+            .iter()
+            .position(|l| l == Some(labels[object] as i64))
+            .unwrap();
+
+        for feature in constant::ZERNIKE_DESCRIPTORS.iter() {
+            let measured = zernike.column(feature.name).unwrap().f64().unwrap();
+            let profiled = profile
+                .column(&format!("mask_{}", feature.name))
+                .unwrap()
+                .f64()
+                .unwrap();
+
+            let (measured, profiled) = (measured.get(row).unwrap(), profiled.get(object).unwrap());
+            assert!((measured - profiled).abs() < 1e-5, "{}", feature.name);
+        }
+
+        let (labels, descriptors) = mask_zernike(
+            &directory.join("data").join("synth_0000_mask.png"),
+            Some(&[labels[0], labels[3], 999]),
+            1,
+        )
+        .unwrap();
+
+        assert_eq!(labels.len(), 2);
+        assert_eq!(descriptors.len(), 2);
+
+        // Existing output tables are replaced with --overwrite
+        let table = directory.join("zernike.csv");
+        std::fs::write(&table, "label\n").unwrap();
+
+        measure_zernike(
+            &parse::<Cli>(&[
+                "-i",
+                data,
+                "-o",
+                table.to_str().unwrap(),
+                "--image-substring",
+                "_mask",
+                "--masks",
+                "--overwrite",
+            ])
+            .args,
+        );
+
+        assert_eq!(io::read_table(&table).unwrap().height(), profile.height());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
@@ -0,0 +1,725 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use clap::Args;
+use polars::prelude::{Column, DataFrame, DataType};
+use serde_json::json;
+
+use thyme_core::error::ThymeError;
+use thyme_core::io::{self, EmbeddingsNpz, NpzOptions};
+use thyme_core::ut::track::progress_log;
+
+use super::query::normalize;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Aggregate object embeddings into one embedding per image or metadata group (e.g. well)."
+)]
+pub struct NeuralAggregateArgs {
+    #[arg(
+        short = 'i',
+        long,
+        help = "Embeddings written to a .npz file by a neural command."
+    )]
+    pub input: String,
+
+    #[arg(
+        short = 'o',
+        long,
+        help = "Output .npz file with one embedding per group."
+    )]
+    pub output: String,
+
+    #[arg(
+        long,
+        help = "Group objects by image or by a column of the metadata table (e.g. well).",
+        default_value = "image"
+    )]
+    pub by: String,
+
+    #[arg(
+        long,
+        help = "Metadata table (.csv, .txt, .tsv, .pq, .arrow, .feather) assigning each image to a group (e.g. a platemap)."
+    )]
+    pub metadata: Option<String>,
+
+    #[arg(
+        long,
+        help = "Name of the join key column in the metadata table. Defaults to the --by column."
+    )]
+    pub metadata_join: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex extracting the join key from image names using the first capture group or full match (e.g. '_([A-P][0-9]{2})'). Defaults to the image name."
+    )]
+    pub well_from_filename: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exit with an error instead of skipping objects whose image has no matching metadata."
+    )]
+    pub strict_join: bool,
+
+    #[arg(
+        long,
+        help = "Statistic computed over the embeddings of each group (mean, median).",
+        default_value = "mean"
+    )]
+    pub stat: String,
+
+    #[arg(
+        long,
+        help = "Fraction of the lowest and of the highest values of each dimension excluded from the mean of each group (e.g. 0.05).",
+        default_value = "0"
+    )]
+    pub trim: f64,
+
+    #[arg(
+        long,
+        help = "Scale each object embedding to unit length before aggregating."
+    )]
+    pub normalize: bool,
+
+    #[arg(
+        long,
+        help = "Output table with the number of objects in each group. Defaults to {output}_group_sizes.csv."
+    )]
+    pub group_sizes: Option<String>,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+}
+
+pub fn neural_aggregate(args: &NeuralAggregateArgs) {
+    let options = Aggregation::new(&args.stat, args.trim, args.normalize).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    if !args.output.to_lowercase().ends_with(".npz") {
+        eprintln!("[thyme::neural::aggregate] ERROR: Output must be a .npz file.");
+        std::process::exit(1);
+    }
+
+    progress_log(
+        &format!("Loading embeddings from {}", args.input),
+        args.verbose,
+    );
+
+    let npz = io::read_embeddings_npz(&args.input).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    let keys = group_keys(&npz, args).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    let unmatched = keys.iter().filter(|key| key.is_none()).count();
+
+    if unmatched > 0 {
+        if args.strict_join {
+            eprintln!(
+                "[thyme::neural::aggregate] ERROR: {} objects have no matching row in the metadata table.",
+                unmatched
+            );
+            std::process::exit(1);
+        }
+
+        progress_log(
+            &format!(
+                "Skipping {} objects without a matching row in the metadata table",
+                unmatched
+            ),
+            args.verbose,
+        );
+    }
+
+    let groups = options
+        .aggregate(&npz.embeddings, &keys)
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+
+    write(&npz, &groups, &options, args).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    progress_log(
+        &format!(
+            "Wrote {} {} embeddings aggregated from {} objects to {}",
+            groups.len(),
+            args.by,
+            npz.embeddings.len() - unmatched,
+            args.output
+        ),
+        args.verbose,
+    );
+}
+
+/// Statistic computed over the embeddings of a group
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stat {
+    Mean,
+    Median,
+}
+
+impl Stat {
+    fn name(&self) -> &'static str {
+        match self {
+            Stat::Mean => "mean",
+            Stat::Median => "median",
+        }
+    }
+}
+
+/// Aggregated embedding of a group of objects
+#[derive(Debug, Clone, PartialEq)]
+struct Group {
+    key: String,
+    size: usize,
+    embedding: Vec<f32>,
+}
+
+/// Per-group aggregation of object embeddings
+///
+/// Untrimmed means are accumulated in a single streaming pass holding one
+/// running sum per group. Medians and trimmed means need every value of a
+/// dimension, so the rows of each group are gathered and each dimension is
+/// reduced by selection in a buffer the size of the group. This holds only
+/// the row indices of every object and one dimension of the largest group in
+/// addition to the embeddings already read from the .npz file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Aggregation {
+    stat: Stat,
+    trim: f64,
+    normalize: bool,
+}
+
+impl Aggregation {
+    /// Parse and validate the aggregation options
+    ///
+    /// # Arguments
+    ///
+    /// * `stat` - Statistic (mean or median)
+    /// * `trim` - Fraction of each tail excluded from the mean in [0, 0.5)
+    /// * `normalize` - Scale object embeddings to unit length first
+    fn new(stat: &str, trim: f64, normalize: bool) -> Result<Self, ThymeError> {
+        let stat = match stat {
+            "mean" => Stat::Mean,
+            "median" => Stat::Median,
+            _ => {
+                return Err(ThymeError::OtherError(format!(
+                    "Invalid stat '{}'. Must be one of: mean, median",
+                    stat
+                )));
+            }
+        };
+
+        if !(0.0..0.5).contains(&trim) {
+            return Err(ThymeError::OtherError(format!(
+                "Invalid trim {}. Must be in [0, 0.5)",
+                trim
+            )));
+        }
+
+        if trim > 0.0 && stat != Stat::Mean {
+            return Err(ThymeError::OtherError(
+                "trim can only be combined with the mean stat".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            stat,
+            trim,
+            normalize,
+        })
+    }
+
+    /// Aggregate the embeddings of each group, ordered by group key
+    ///
+    /// # Arguments
+    ///
+    /// * `embeddings` - Object embeddings
+    /// * `keys` - Group of each object, or `None` if the object is skipped
+    fn aggregate(
+        &self,
+        embeddings: &[Vec<f32>],
+        keys: &[Option<String>],
+    ) -> Result<Vec<Group>, ThymeError> {
+        let dim = embeddings.first().map_or(0, |e| e.len());
+
+        if embeddings.iter().any(|e| e.len() != dim) {
+            return Err(ThymeError::OtherError(
+                "Embeddings must all have the same dimension".to_string(),
+            ));
+        }
+
+        let embedding = |row: usize| match self.normalize {
+            true => normalize(&embeddings[row]),
+            false => embeddings[row].clone(),
+        };
+
+        let objects = keys
+            .iter()
+            .enumerate()
+            .filter_map(|(row, key)| key.as_deref().map(|key| (row, key)));
+
+        // Untrimmed means never hold more than one running sum per group
+        if self.stat == Stat::Mean && self.trim == 0.0 {
+            let mut sums: BTreeMap<&str, (usize, Vec<f64>)> = BTreeMap::new();
+
+            for (row, key) in objects {
+                let (size, sum) = sums.entry(key).or_insert_with(|| (0, vec![0.; dim]));
+                *size += 1;
+
+                for (s, v) in sum.iter_mut().zip(embedding(row)) {
+                    *s += v as f64;
+                }
+            }
+
+            return Ok(sums
+                .into_iter()
+                .map(|(key, (size, sum))| Group {
+                    key: key.to_string(),
+                    size,
+                    embedding: sum.iter().map(|s| (s / size as f64) as f32).collect(),
+                })
+                .collect());
+        }
+
+        let mut rows: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+
+        for (row, key) in objects {
+            rows.entry(key).or_default().push(row);
+        }
+
+        let mut values: Vec<f32> = vec![];
+
+        Ok(rows
+            .into_iter()
+            .map(|(key, rows)| {
+                // Embeddings are normalized by dividing each value by the norm of its row
+                let norms: Vec<f32> = rows
+                    .iter()
+                    .map(|&row| match self.normalize {
+                        true => norm(&embeddings[row]),
+                        false => 1.,
+                    })
+                    .collect();
+
+                let embedding = (0..dim)
+                    .map(|j| {
+                        values.clear();
+                        values.extend(
+                            rows.iter()
+                                .zip(&norms)
+                                .map(|(&row, norm)| embeddings[row][j] / norm),
+                        );
+
+                        match self.stat {
+                            Stat::Mean => trimmed_mean(&mut values, self.trim),
+                            Stat::Median => median(&mut values),
+                        }
+                    })
+                    .collect();
+
+                Group {
+                    key: key.to_string(),
+                    size: rows.len(),
+                    embedding,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Length of an embedding, or 1 for zero embeddings so that they are left unchanged
+fn norm(embedding: &[f32]) -> f32 {
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+
+    if norm > 0. { norm } else { 1. }
+}
+
+/// Mean of the values left after excluding the lowest and highest `trim` fraction
+///
+/// `floor(trim * n)` values are excluded from each tail, matching
+/// `scipy.stats.trim_mean`. Values are reordered in place.
+fn trimmed_mean(values: &mut [f32], trim: f64) -> f32 {
+    let n = values.len();
+    let k = (trim * n as f64).floor() as usize;
+
+    let mut kept = &mut values[..];
+
+    if k > 0 {
+        kept.select_nth_unstable_by(k - 1, f32::total_cmp);
+        kept = &mut kept[k..];
+
+        let m = kept.len();
+        kept.select_nth_unstable_by(m - k, f32::total_cmp);
+        kept = &mut kept[..m - k];
+    }
+
+    (kept.iter().map(|&v| v as f64).sum::<f64>() / kept.len() as f64) as f32
+}
+
+/// Median of the values, averaging the two middle values of even lengths
+///
+/// Values are reordered in place.
+fn median(values: &mut [f32]) -> f32 {
+    let n = values.len();
+    let (lower, middle, _) = values.select_nth_unstable_by(n / 2, f32::total_cmp);
+    let middle = *middle;
+
+    match n % 2 {
+        1 => middle,
+        _ => {
+            let below = lower.iter().copied().max_by(f32::total_cmp).unwrap();
+            ((below as f64 + middle as f64) / 2.) as f32
+        }
+    }
+}
+
+/// Group key of each object, or `None` if its image has no metadata
+fn group_keys(
+    npz: &EmbeddingsNpz,
+    args: &NeuralAggregateArgs,
+) -> Result<Vec<Option<String>>, ThymeError> {
+    let Some(path) = &args.metadata else {
+        if args.by != "image" {
+            return Err(ThymeError::OtherError(format!(
+                "Grouping by {} requires a metadata table",
+                args.by
+            )));
+        }
+
+        return Ok(npz.images.iter().cloned().map(Some).collect());
+    };
+
+    if args.by == "image" {
+        return Err(ThymeError::OtherError(
+            "A metadata table requires --by to name one of its columns (e.g. well)".to_string(),
+        ));
+    }
+
+    let metadata = io::read_table(path)?;
+
+    // Metadata is joined once per image rather than once per object
+    let mut images: Vec<&str> = npz.images.iter().map(|image| image.as_str()).collect();
+    images.sort_unstable();
+    images.dedup();
+
+    let mut df = DataFrame::new(vec![Column::new("image".into(), &images)])
+        .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+    io::join_metadata(
+        &mut df,
+        "image",
+        &metadata,
+        args.metadata_join.as_deref().unwrap_or(&args.by),
+        args.well_from_filename.as_deref(),
+    )?;
+
+    let groups = df
+        .column(&args.by)
+        .and_then(|column| column.cast(&DataType::String))
+        .map_err(|_| {
+            ThymeError::OtherError(format!("Metadata table is missing column: {}", args.by))
+        })?;
+
+    let lookup: HashMap<&str, Option<&str>> = images
+        .iter()
+        .copied()
+        .zip(groups.str().unwrap().into_iter())
+        .collect();
+
+    Ok(npz
+        .images
+        .iter()
+        .map(|image| lookup[image.as_str()].map(String::from))
+        .collect())
+}
+
+/// Write the aggregated embeddings and the size of each group
+fn write(
+    npz: &EmbeddingsNpz,
+    groups: &[Group],
+    aggregation: &Aggregation,
+    args: &NeuralAggregateArgs,
+) -> Result<(), ThymeError> {
+    // Attributes of the objects (e.g. model) are kept alongside the aggregation
+    let mut attrs = npz.attrs.clone();
+    attrs.insert(
+        "aggregate".to_string(),
+        json!({
+            "by": args.by,
+            "stat": aggregation.stat.name(),
+            "trim": aggregation.trim,
+            "normalize": aggregation.normalize,
+        }),
+    );
+
+    // Groups have no object identifiers or centroids, like whole images
+    io::write_embeddings_npz_with(
+        groups.iter().map(|group| group.key.clone()).collect(),
+        vec![],
+        vec![],
+        groups.iter().map(|group| group.embedding.clone()).collect(),
+        &args.output,
+        &NpzOptions {
+            attrs,
+            ..Default::default()
+        },
+    )?;
+
+    let sizes = match &args.group_sizes {
+        Some(path) => path.to_owned(),
+        None => {
+            let output = Path::new(&args.output);
+            let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            output
+                .with_file_name(format!("{}_group_sizes.csv", stem))
+                .to_string_lossy()
+                .to_string()
+        }
+    };
+
+    let mut df = DataFrame::new(vec![
+        Column::new(
+            args.by.as_str().into(),
+            groups
+                .iter()
+                .map(|group| group.key.as_str())
+                .collect::<Vec<&str>>(),
+        ),
+        Column::new(
+            "n_objects".into(),
+            groups
+                .iter()
+                .map(|group| group.size as u32)
+                .collect::<Vec<u32>>(),
+        ),
+    ])
+    .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+    io::write_table(&mut df, &sizes)
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: NeuralAggregateArgs,
+    }
+
+    fn parse(args: &[&str]) -> NeuralAggregateArgs {
+        Cli::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args
+    }
+
+    fn close(a: &[f32], b: &[f32]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    #[test]
+    fn test_aggregate_statistics() {
+        // Trimming 0.1 of 10 values excludes the lowest and highest value
+        let mut values = vec![9., 1., 100., 3., 5., -50., 7., 2., 4., 6.];
+        assert_eq!(trimmed_mean(&mut values, 0.1), 37. / 8.);
+
+        let mut values = vec![9., 1., 100., 3., 5., -50., 7., 2., 4., 6.];
+        assert_eq!(trimmed_mean(&mut values, 0.0), 87. / 10.);
+
+        // 0.25 of 9 values excludes floor(2.25) = 2 values from each tail
+        let mut values = vec![8., 0., 4., 1., 7., 2., 6., 3., 5.];
+        assert_eq!(trimmed_mean(&mut values, 0.25), 4.);
+
+        let mut values = vec![3., 1., 2.];
+        assert_eq!(median(&mut values), 2.);
+
+        let mut values = vec![4., 1., 3., 2.];
+        assert_eq!(median(&mut values), 2.5);
+
+        assert!(Aggregation::new("mode", 0.0, false).is_err());
+        assert!(Aggregation::new("mean", 0.5, false).is_err());
+        assert!(Aggregation::new("mean", -0.1, false).is_err());
+        assert!(Aggregation::new("median", 0.1, false).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_groups() {
+        let embeddings = vec![
+            vec![1., 10.],
+            vec![3., 30.],
+            vec![5., -40.],
+            vec![0., 2.],
+            vec![4., 0.],
+            vec![9., 9.],
+        ];
+
+        let keys: Vec<Option<String>> = ["b", "b", "b", "a", "a", ""]
+            .iter()
+            .map(|key| Some(key.to_string()).filter(|key| !key.is_empty()))
+            .collect();
+
+        let mean = Aggregation::new("mean", 0.0, false).unwrap();
+        let groups = mean.aggregate(&embeddings, &keys).unwrap();
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!((groups[0].key.as_str(), groups[0].size), ("a", 2));
+        assert_eq!((groups[1].key.as_str(), groups[1].size), ("b", 3));
+        assert!(close(&groups[0].embedding, &[2., 1.]));
+        assert!(close(&groups[1].embedding, &[3., 0.]));
+
+        let median = Aggregation::new("median", 0.0, false).unwrap();
+        let groups = median.aggregate(&embeddings, &keys).unwrap();
+        assert!(close(&groups[0].embedding, &[2., 1.]));
+        assert!(close(&groups[1].embedding, &[3., 10.]));
+
+        // Trimming 0.34 of 3 values keeps only the middle value of each dimension
+        let trimmed = Aggregation::new("mean", 0.34, false).unwrap();
+        let groups = trimmed.aggregate(&embeddings, &keys).unwrap();
+        assert!(close(&groups[1].embedding, &[3., 10.]));
+
+        // Embeddings are scaled to unit length before they are averaged
+        let normalized = Aggregation::new("mean", 0.0, true).unwrap();
+        let groups = normalized.aggregate(&embeddings, &keys).unwrap();
+        assert!(close(&groups[0].embedding, &[0.5, 0.5]));
+
+        let normalized = Aggregation::new("median", 0.0, true).unwrap();
+        let groups = normalized.aggregate(&embeddings, &keys).unwrap();
+        assert!(close(&groups[0].embedding, &[0.5, 0.5]));
+
+        assert!(
+            mean.aggregate(
+                &[vec![1.], vec![1., 2.]],
+                &[Some("a".into()), Some("a".into())]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_neural_aggregate() {
+        let directory = std::env::temp_dir().join("thyme_test_neural_aggregate");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("embeddings.npz");
+        let platemap = directory.join("platemap.csv");
+
+        // Wells A01 and A02 each have two images with objects of known means
+        let images: Vec<String> = [
+            "p_A01_f1", "p_A01_f1", "p_A01_f2", "p_A02_f1", "p_A02_f2", "p_B01_f1",
+        ]
+        .iter()
+        .map(|image| image.to_string())
+        .collect();
+
+        let embeddings = vec![
+            vec![1., 0., 2.],
+            vec![2., 0., 4.],
+            vec![3., 0., 6.],
+            vec![10., 1., 0.],
+            vec![20., 3., 0.],
+            vec![0., 0., 0.],
+        ];
+
+        io::write_embeddings_npz_with(
+            images.clone(),
+            vec![1, 2, 1, 1, 1, 1],
+            vec![[0., 0.]; 6],
+            embeddings,
+            &path,
+            &NpzOptions {
+                attrs: json!({"model": "dino_vit_small"})
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        std::fs::write(&platemap, "well,compound\nA01,DMSO\nA02,taxol\n").unwrap();
+
+        let path = path.to_str().unwrap();
+        let output = directory.join("wells.npz");
+        let output = output.to_str().unwrap();
+
+        neural_aggregate(&parse(&[
+            "-i",
+            path,
+            "-o",
+            output,
+            "--metadata",
+            platemap.to_str().unwrap(),
+            "--by",
+            "well",
+            "--well-from-filename",
+            "_([A-P][0-9]{2})_",
+        ]));
+
+        let npz = io::read_embeddings_npz(output).unwrap();
+
+        assert_eq!(npz.format_version, io::NPZ_FORMAT_VERSION);
+        assert_eq!(npz.images, ["A01", "A02"]);
+        assert!(npz.ids.is_empty());
+        assert!(close(&npz.embeddings[0], &[2., 0., 4.]));
+        assert!(close(&npz.embeddings[1], &[15., 2., 0.]));
+        assert_eq!(npz.attrs["model"], "dino_vit_small");
+        assert_eq!(npz.attrs["aggregate"]["by"], "well");
+        assert_eq!(npz.attrs["aggregate"]["stat"], "mean");
+
+        let sizes = std::fs::read_to_string(directory.join("wells_group_sizes.csv")).unwrap();
+        assert_eq!(sizes, "well,n_objects\nA01,3\nA02,2\n");
+
+        // Images are the default groups
+        let sizes = directory.join("sizes.csv");
+        neural_aggregate(&parse(&[
+            "-i",
+            path,
+            "-o",
+            output,
+            "--stat",
+            "median",
+            "--group-sizes",
+            sizes.to_str().unwrap(),
+        ]));
+
+        let npz = io::read_embeddings_npz(output).unwrap();
+        assert_eq!(npz.images.len(), 5);
+        assert_eq!(npz.images[0], "p_A01_f1");
+        assert!(close(&npz.embeddings[0], &[1.5, 0., 3.]));
+        assert_eq!(
+            std::fs::read_to_string(&sizes).unwrap().lines().nth(1),
+            Some("p_A01_f1,2")
+        );
+
+        let npz = io::read_embeddings_npz(path).unwrap();
+
+        for invalid in [
+            vec!["--by", "well"],
+            vec!["--by", "image", "--metadata", platemap.to_str().unwrap()],
+            vec!["--by", "plate", "--metadata", platemap.to_str().unwrap()],
+        ] {
+            let mut args = vec!["-i", path, "-o", output];
+            args.extend(&invalid);
+            assert!(group_keys(&npz, &parse(&args)).is_err(), "{:?}", invalid);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -5,26 +5,45 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
-use candle_core::{Device, utils::cuda_is_available, utils::metal_is_available};
+use candle_core::Device;
 use clap::Args;
-use kdam::TqdmParallelIterator;
 use polars::prelude::*;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
+use thyme_config::NeuralBoxesConfig;
 use thyme_core::constant;
 use thyme_core::error::ThymeError;
 use thyme_core::im;
 use thyme_core::io;
 use thyme_core::ut;
-use thyme_data::data::Weights;
+use thyme_neural::fallback::{Embed, Fallback, FallbackEmbedder};
 use thyme_neural::nn::Models;
 
-#[derive(Debug, Args)]
+use super::NeuralSummary;
+use super::cache::EmbeddingCache;
+use super::split::{self, SplitBy};
+use super::table;
+use crate::boxes;
+use crate::dryrun;
+use crate::failures::FailedObjects;
+use crate::filter::{
+    self, BorderPolicy, CropRect, DropCounts, DropReason, ObjectFilter, crop_rect,
+};
+use crate::output::{MetadataJoin, OutputMode, PerImageOutput, npz_options, prepare_output};
+use crate::prefetch::Prefetch;
+use crate::provenance;
+use crate::status::Progress;
+
+#[derive(Debug, Args, Serialize)]
 pub struct NeuralBoxesArgs {
     #[arg(short = 'i', long, help = "Image directory.", required = true)]
     pub images: Option<String>,
 
-    #[arg(short = 's', long, help = "Bounding box directory.")]
+    #[arg(
+        short = 's',
+        long,
+        help = "Bounding boxes directory, or a single csv table with an image column holding boxes for many images."
+    )]
     pub boxes: Option<String>,
 
     #[arg(long, help = "Device (cpu, cuda, metal).", default_value = "cpu")]
@@ -33,7 +52,7 @@ pub struct NeuralBoxesArgs {
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq, .npy, .npz).",
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather, .npy, .npz).",
         required = true
     )]
     pub output: Option<String>,
@@ -41,6 +60,19 @@ pub struct NeuralBoxesArgs {
     #[arg(short = 'v', long, help = "Verbose output.")]
     pub verbose: bool,
 
+    #[arg(
+        long,
+        help = "Show a live status panel with progress, throughput, workers, recent errors, and memory usage instead of the progress bar. Logs status lines instead if stdout is not a terminal."
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Format of the status lines logged instead of the status panel (plain, json). Json status lines are also logged without --tui.",
+        default_value = "plain"
+    )]
+    pub log_format: Option<String>,
+
     #[arg(short = 'd', long, help = "Exclude objects touching edge of image.")]
     pub drop_borders: bool,
 
@@ -52,6 +84,13 @@ pub struct NeuralBoxesArgs {
     )]
     pub model: Option<String>,
 
+    #[arg(
+        long,
+        help = "Channel map applied before model preprocessing: auto, gray-average, or input channel indices for each RGB channel (e.g. 0,0,0 or 1,2,0).",
+        default_value = "auto"
+    )]
+    pub channel_map: Option<String>,
+
     #[arg(
         short = 'p',
         long,
@@ -60,6 +99,13 @@ pub struct NeuralBoxesArgs {
     )]
     pub pad: Option<u32>,
 
+    #[arg(
+        long,
+        help = "Maximum edge length that object crops are downscaled to if the device runs out of memory.",
+        default_value = "1024"
+    )]
+    pub max_embed_edge: Option<u32>,
+
     #[arg(long, help = "Substring specifying images (e.g. _image).")]
     pub image_substring: Option<String>,
 
@@ -68,125 +114,435 @@ pub struct NeuralBoxesArgs {
 
     #[arg(
         long,
-        help = "Exclude objects smaller than a minimum size.",
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting bounding boxes by their path relative to their directory.",
+        conflicts_with_all = ["box_substring", "box_regex"]
+    )]
+    pub box_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting bounding boxes by their path relative to their directory.",
+        conflicts_with = "box_substring"
+    )]
+    pub box_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Bounding box csv column names as key=column pairs (e.g. xmin=left,ymin=top,xmax=right,ymax=bottom,label=class,image=file). Columns are otherwise detected from common header names."
+    )]
+    pub box_columns: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with an unpadded bounding box width or height smaller than a minimum size (pixels).",
         default_value = "1"
     )]
     pub min_size: Option<u32>,
 
+    #[arg(
+        long,
+        help = "Exclude objects with a bounding box area smaller than a minimum area (pixels)."
+    )]
+    pub min_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with a bounding box area larger than a maximum area (pixels)."
+    )]
+    pub max_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Skip images before embedding their objects if an expression of image metrics is true (e.g. 'foreground_fraction<0.001 || focus_score<30'). Conditions compare foreground_fraction, focus_score, background_mean, or background_median to a number and are joined by && and ||. Pyramidal images are gated on their smallest level. Skipped images are written to skipped_images.tsv."
+    )]
+    pub skip_image_if: Option<String>,
+
+    #[arg(
+        long,
+        help = "Metadata table (.csv, .txt, .tsv, .pq, .arrow, .feather) joined to every output row (e.g. a platemap)."
+    )]
+    pub metadata_table: Option<String>,
+
+    #[arg(
+        long,
+        help = "Name of the join key column in the metadata table (e.g. well)."
+    )]
+    pub metadata_join: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex extracting the join key from image file names using the first capture group or full match (e.g. '_([A-P][0-9]{2})_'). Defaults to the image file name."
+    )]
+    pub well_from_filename: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exit with an error instead of writing nulls when an image has no matching metadata."
+    )]
+    pub strict_join: bool,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write .npz embeddings in the version 1 layout (image, id, centroid, embedding) without format_version and attrs members."
+    )]
+    pub legacy_npz: bool,
+
+    #[arg(
+        long,
+        help = "Write the objects of each class (bounding box label) to a separate output named {output}_{class} (e.g. embeddings_cell.npz). Objects without a label are written to {output}_unclassified. Only class is supported.",
+        conflicts_with = "per_image_output"
+    )]
+    pub split_output_by: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Directory caching object embeddings by image content, crop, model, and channel map so that re-runs skip unchanged objects."
+    )]
+    pub cache_dir: Option<String>,
+
+    #[arg(
+        long,
+        help = "Read embeddings from the cache without adding new entries (e.g. a shared cluster cache).",
+        requires = "cache_dir"
+    )]
+    pub cache_readonly: bool,
+
+    #[arg(
+        long,
+        help = "Write a separate embeddings/{image} table for each image as soon as it is embedded. Requires an output directory."
+    )]
+    pub per_image_output: bool,
+
+    #[arg(
+        long,
+        help = "Format of per-image output tables (csv, txt, tsv, pq, arrow, feather).",
+        default_value = "csv"
+    )]
+    pub per_image_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip images whose per-image output table already exists.",
+        requires = "per_image_output"
+    )]
+    pub resume: bool,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (arrays, tables, reports, and provenance sidecars) from the output location before embedding. Without it, output locations holding a previous run are rejected.",
+        conflicts_with = "resume"
+    )]
+    pub overwrite: bool,
+
+    #[arg(
+        long,
+        help = "Number of decoded images queued ahead of the compute threads. If 0, images are decoded by the compute threads.",
+        default_value = "0"
+    )]
+    pub prefetch: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Number of threads decoding images ahead of the compute threads if --prefetch is set.",
+        default_value = "2"
+    )]
+    pub io_threads: Option<usize>,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Report the pairs, object counts, embedding dimension, weights, and estimated output size as a table and in dry_run.json next to the output, without loading the model or creating the output."
+    )]
+    pub dry_run: bool,
 }
 
 pub fn neural_image_boxes(args: &NeuralBoxesArgs) {
-    let device = args.device.to_owned().unwrap_or("cpu".to_string());
-
-    if !["cpu", "metal", "cuda"].iter().any(|d| d == &device) {
-        eprintln!(
-            "[thyme::neural::boxes] ERROR: Invalid device. Must be one of: cpu, metal, cuda."
-        );
+    if let Err(err) = run_neural_boxes(&NeuralBoxesConfig::from(args)) {
+        eprintln!("[thyme::neural::boxes] ERROR: {}", err);
         std::process::exit(1);
     }
+}
 
-    if device == "cuda" && !cuda_is_available() {
-        println!("[thyme::neural::boxes] Device 'cuda' specified but no cuda device was detected.");
-        std::process::exit(1);
+impl From<&NeuralBoxesArgs> for NeuralBoxesConfig {
+    fn from(args: &NeuralBoxesArgs) -> Self {
+        let defaults = NeuralBoxesConfig::default();
+
+        NeuralBoxesConfig {
+            images: args.images.to_owned().unwrap_or_default(),
+            boxes: args.boxes.to_owned(),
+            device: args.device.to_owned().unwrap_or(defaults.device),
+            output: args.output.to_owned().unwrap_or_default(),
+            verbose: args.verbose,
+            tui: args.tui,
+            log_format: args.log_format.to_owned().unwrap_or(defaults.log_format),
+            drop_borders: args.drop_borders,
+            model: args.model.to_owned().unwrap_or(defaults.model),
+            channel_map: args.channel_map.to_owned().unwrap_or(defaults.channel_map),
+            pad: args.pad.unwrap_or(defaults.pad),
+            max_embed_edge: args.max_embed_edge.unwrap_or(defaults.max_embed_edge),
+            image_substring: args.image_substring.to_owned(),
+            box_substring: args.box_substring.to_owned(),
+            image_glob: args.image_glob.to_owned(),
+            image_regex: args.image_regex.to_owned(),
+            box_glob: args.box_glob.to_owned(),
+            box_regex: args.box_regex.to_owned(),
+            box_columns: args.box_columns.to_owned(),
+            min_size: args.min_size.unwrap_or(defaults.min_size),
+            min_area: args.min_area,
+            max_area: args.max_area,
+            skip_image_if: args.skip_image_if.to_owned(),
+            metadata_table: args.metadata_table.to_owned(),
+            metadata_join: args.metadata_join.to_owned(),
+            well_from_filename: args.well_from_filename.to_owned(),
+            strict_join: args.strict_join,
+            ipc_compression: args
+                .ipc_compression
+                .to_owned()
+                .unwrap_or(defaults.ipc_compression),
+            float_precision: args.float_precision,
+            float_format: args
+                .float_format
+                .to_owned()
+                .unwrap_or(defaults.float_format),
+            legacy_npz: args.legacy_npz,
+            split_output_by: args.split_output_by.to_owned(),
+            provenance: args.provenance,
+            hash_inputs: args.hash_inputs,
+            cache_dir: args.cache_dir.to_owned(),
+            cache_readonly: args.cache_readonly,
+            per_image_output: args.per_image_output,
+            per_image_format: args
+                .per_image_format
+                .to_owned()
+                .unwrap_or(defaults.per_image_format),
+            resume: args.resume,
+            overwrite: args.overwrite,
+            prefetch: args.prefetch.unwrap_or(defaults.prefetch),
+            io_threads: args.io_threads.unwrap_or(defaults.io_threads),
+            threads: args.threads,
+            dry_run: args.dry_run,
+        }
     }
+}
 
-    if device == "metal" && !metal_is_available() {
-        println!(
-            "[thyme::neural::boxes] Device 'metal' specified but no metal device was detected."
-        );
-        std::process::exit(1);
-    }
+/// Embed the objects of image and bounding box pairs as configured
+///
+/// Images that fail to embed are reported in `object_errors.tsv` when
+/// writing to an output directory and are counted in the returned summary.
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the run
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_cli::run_neural_boxes;
+/// use thyme_config::NeuralBoxesConfig;
+///
+/// let config = NeuralBoxesConfig::new("images/", "output/")
+///     .boxes("boxes.csv")
+///     .split_output_by("class");
+///
+/// let summary = run_neural_boxes(&config).unwrap();
+/// println!("{} objects across {} images", summary.objects, summary.images);
+/// ```
+pub fn run_neural_boxes(config: &NeuralBoxesConfig) -> Result<NeuralSummary, ThymeError> {
+    config.validate()?;
+
+    let (threads, device) = super::select_device(&config.device, config.threads, config.verbose)?;
+
+    ut::threads::install(threads, || {
+        run(config, |model_name| {
+            let mut model = FallbackEmbedder::new(
+                Models::load(model_name, &device, config.verbose),
+                device.clone(),
+                config.max_embed_edge,
+            );
 
-    let (threads, device) = if device == "cuda" && cuda_is_available() {
-        ut::track::progress_log("Cuda device detected.", args.verbose);
-        (1, Device::new_cuda(0).unwrap())
-    } else if device == "metal" && metal_is_available() {
-        ut::track::progress_log("Metal device detected.", args.verbose);
-        (1, Device::new_metal(0).unwrap())
-    } else {
-        (args.threads.to_owned().unwrap(), Device::Cpu)
-    };
+            // Objects that run out of memory on the device are embedded on the cpu as a last resort
+            if !device.is_cpu() {
+                let model_name = model_name.to_string();
+                model =
+                    model.with_cpu_fallback(move || Models::load(&model_name, &Device::Cpu, false));
+            }
 
-    if threads < 1 {
-        println!("[thyme::neural::boxes] Threads must be set to a positive integer if provided.");
-        std::process::exit(1);
-    }
+            model
+        })
+    })
+}
 
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(threads)
-        .build_global()
-        .unwrap();
+/// Run the command on the thread pool selected by `run_neural_boxes`
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the run
+/// * `load` - Loads the model embedding objects given its name
+fn run<M, F>(config: &NeuralBoxesConfig, load: F) -> Result<NeuralSummary, ThymeError>
+where
+    M: Embed,
+    F: FnOnce(&str) -> FallbackEmbedder<M>,
+{
+    let model_name = config.model.to_owned();
+    let pad = config.pad;
+
+    let channel_map = im::ChannelMap::parse(&config.channel_map)?;
 
-    let model_name = args
-        .model
-        .to_owned()
-        .unwrap_or("dino_vit_small".to_string());
+    ut::track::progress_log(
+        &format!("Channel map: {}.", channel_map.describe()),
+        config.verbose,
+    );
 
-    let pad = args.pad.unwrap_or(1);
-    let min_size = args.min_size.unwrap_or(1);
+    let prefetch = Prefetch::new(Some(config.prefetch), Some(config.io_threads))?;
 
-    if !Weights::iter().any(|m| m.model_name() == model_name) {
-        // If model name is invalid, select will terminate and show error with list of available models
-        Weights::select(&model_name);
-    }
+    let filter = ObjectFilter {
+        border_policy: BorderPolicy::from_drop_borders(config.drop_borders),
+        min_size: config.min_size,
+        min_area: config.min_area,
+        max_area: config.max_area,
+        skip_invalid_polygons: false,
+    };
 
-    if min_size < 1 {
-        eprintln!("[thyme::neural::boxes] ERROR: min_size cannot be less than 1.0.");
-        std::process::exit(1);
-    }
+    filter.validate().map_err(super::invalid)?;
 
-    let image_path = args.images.to_owned().unwrap();
-    let boxes_path = args.boxes.to_owned().unwrap_or(image_path.clone());
+    let gate = filter::ImageGate::new(config.skip_image_if.as_deref())?;
 
-    if image_path == boxes_path && args.image_substring == args.box_substring {
-        eprintln!(
-            "[thyme::neural::boxes] ERROR: If images and boxes are located in same path, different image and bounding box substrings must be provided."
-        );
-        std::process::exit(1);
-    }
+    let ipc_compression = config.ipc_compression.to_owned();
+    let float_format = io::FloatFormat::new(config.float_precision, &config.float_format)?;
 
-    let image_files = ut::path::collect_file_paths(
-        &image_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.image_substring.to_owned(),
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    let split_output_by = config
+        .split_output_by
+        .as_deref()
+        .map(SplitBy::parse)
+        .transpose()?;
 
-    let boxes_files = ut::path::collect_file_paths(
-        &boxes_path,
-        constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
-        args.box_substring.to_owned(),
+    let image_path = config.images.to_owned();
+    let boxes_path = config.boxes_path().to_owned();
+
+    let image_files = ut::path::FileSelector::new(
+        config.image_substring.as_deref(),
+        config.image_glob.as_deref(),
+        config.image_regex.as_deref(),
     )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    .and_then(|selector| {
+        ut::path::select_file_paths(
+            &image_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
+
+    let box_columns = config
+        .box_columns
+        .as_deref()
+        .map(im::BoxColumns::parse)
+        .transpose()?
+        .unwrap_or_default();
 
     if image_files.is_empty() {
-        eprintln!(
-            "[thyme::neural::boxes] ERROR: No image files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No image files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
-    if boxes_files.is_empty() {
-        eprintln!(
-            "[thyme::neural::boxes] ERROR: No bounding box files were detected. Please check your path and/or substring identifier."
+    let (mut pairs, box_source) = if Path::new(&boxes_path).is_file() {
+        let (pairs, box_source, unmatched) = boxes::table_pairs(
+            &boxes_path,
+            &box_columns,
+            &image_files,
+            config.image_substring.to_owned(),
+        )?;
+
+        if unmatched > 0 {
+            eprintln!(
+                "[thyme::neural::boxes] WARNING: {} images in the bounding box table have no matching image file.",
+                unmatched
+            );
+        }
+
+        (pairs, box_source)
+    } else {
+        let boxes_files = ut::path::FileSelector::new(
+            config.box_substring.as_deref(),
+            config.box_glob.as_deref(),
+            config.box_regex.as_deref(),
+        )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &boxes_path,
+                constant::SUPPORTED_BOX_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })?;
+
+        if boxes_files.is_empty() {
+            return Err(ThymeError::OtherError(
+                "No bounding boxes files were detected. Please check your path and/or substring identifier"
+                    .to_string(),
+            ));
+        }
+
+        let pairs = ut::path::collect_file_pairs(
+            &image_files,
+            &boxes_files,
+            config.image_substring.to_owned(),
+            config.box_substring.to_owned(),
         );
-        std::process::exit(1);
-    }
 
-    let mut pairs = ut::path::collect_file_pairs(
-        &image_files,
-        &boxes_files,
-        args.image_substring.to_owned(),
-        args.box_substring.to_owned(),
-    );
+        (pairs, boxes::BoxSource::Files(box_columns))
+    };
 
     pairs.sort_unstable();
 
@@ -195,258 +551,694 @@ pub fn neural_image_boxes(args: &NeuralBoxesArgs) {
             "Detected {} image and bounding box pairs.",
             ut::track::thousands_format(pairs.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    let mut output = PathBuf::from(args.output.to_owned().unwrap());
+    if config.dry_run {
+        let images = dryrun::scan(&pairs, pad, |_, image, boxes, _| {
+            Ok(box_source.open(image, boxes)?.to_xyxy())
+        });
+
+        return super::dry_run(
+            "neural::boxes",
+            &config.output,
+            Some(config.per_image_format.as_str()).filter(|_| config.per_image_output),
+            config.float_precision,
+            &model_name,
+            images,
+        );
+    }
+
+    let output = PathBuf::from(&config.output);
 
     let extension = output
         .extension()
         .and_then(|s| s.to_str())
         .map(|s| s.to_lowercase());
 
-    if let Some(ext) = &extension {
-        if !["npy", "npz", "csv", "txt", "tsv", "pq"]
-            .iter()
-            .any(|e| e == ext)
-        {
-            eprintln!(
-                "[thyme::neural::boxes] ERROR: Invalid file extension. Must end with one of .npy, .npz, .csv, .txt, .tsv, .pq."
-            );
-            std::process::exit(1);
+    if let Some(parent) = output.parent().filter(|_| extension.is_some()) {
+        if !parent.is_dir() && !parent.as_os_str().is_empty() {
+            return Err(ThymeError::OtherError(
+                "Invalid file path. Parent directory of output file path does not exist"
+                    .to_string(),
+            ));
         }
+    }
+
+    let output = prepare_output(
+        &output,
+        OutputMode::new(config.overwrite, config.resume)?,
+        &config.inputs(),
+        "neural::boxes",
+    )?;
+
+    let metadata = config
+        .metadata_table
+        .as_ref()
+        .map(|path| {
+            let on = config.metadata_join.as_deref().unwrap_or_default();
+            let metadata = io::read_table(path)?;
+
+            // Resolve join keys before processing so that invalid patterns or unmatched
+            // images are reported without waiting for all the objects to be computed
+            let images: Vec<String> = pairs.iter().map(|(_, image, _)| stem(image)).collect();
+
+            let mut df = DataFrame::new(vec![Column::new("image".into(), &images)])
+                .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+            let missing = io::join_metadata(
+                &mut df,
+                "image",
+                &metadata,
+                on,
+                config.well_from_filename.as_deref(),
+            )?;
+
+            if missing > 0 && config.strict_join {
+                return Err(ThymeError::OtherError(format!(
+                    "{} images have no matching row in the metadata table",
+                    missing
+                )));
+            }
 
-        if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if missing > 0 {
                 eprintln!(
-                    "[thyme::neural::boxes] ERROR: Invalid file path. Parent directory of output file path does not exist."
+                    "[thyme::neural::boxes] WARNING: {} images have no matching row in the metadata table. Metadata columns will be null for these images.",
+                    missing
                 );
-                std::process::exit(1);
             }
-        }
-    } else {
-        output = ut::path::create_directory(&output).unwrap_or_else(|_| {
-            eprintln!("[thyme::neural::boxes] ERROR: Could not create directory.");
-            std::process::exit(1);
-        });
+
+            Ok(metadata)
+        })
+        .transpose()?;
+
+    let per_image = config
+        .per_image_output
+        .then(|| {
+            if !output.is_dir() {
+                return Err(ThymeError::OtherError(
+                    "per_image_output requires an output directory".to_string(),
+                ));
+            }
+
+            PerImageOutput::new(
+                &output,
+                "embeddings",
+                &config.per_image_format,
+                &ipc_compression,
+                float_format,
+                config.resume,
+                metadata.clone().map(|table| MetadataJoin {
+                    table,
+                    on: config.metadata_join.to_owned().unwrap_or_default(),
+                    well_from_filename: config.well_from_filename.to_owned(),
+                }),
+            )
+        })
+        .transpose()?;
+
+    if let Some(per_image) = &per_image {
+        let n = pairs.len();
+        pairs.retain(|(_, image, _)| !per_image.skip(&stem(image)));
+
+        ut::track::progress_log(
+            &format!(
+                "Skipping {} images with existing per-image tables.",
+                ut::track::thousands_format(n - pairs.len())
+            ),
+            config.verbose && config.resume,
+        );
     }
 
-    let pb = ut::track::progress_bar(pairs.len(), "Embedding", args.verbose);
+    let (pairs, incompatible) = super::check_images(pairs, &channel_map, "neural::boxes");
+
+    let embedding_dim = super::embedding_dim(&model_name)?;
+
+    let progress = Progress::new(
+        pairs.len(),
+        "Embedding",
+        config.verbose,
+        config.tui,
+        &config.log_format,
+    );
+    let status = progress.status();
 
     let objects: Mutex<usize> = Mutex::new(0);
+    let recovered: Mutex<usize> = Mutex::new(0);
     let success: Mutex<Vec<String>> = Mutex::new(vec![]);
-    let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
+    let failed_objects: Mutex<Vec<String>> = Mutex::new(vec![]);
 
     let name: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
     let item: Mutex<Vec<u32>> = Mutex::new(Vec::with_capacity(pairs.len()));
     let spot: Mutex<Vec<[f32; 2]>> = Mutex::new(Vec::with_capacity(2 * pairs.len()));
+    let label: Mutex<Vec<Option<String>>> = Mutex::new(Vec::with_capacity(pairs.len()));
     let data: Mutex<Vec<Vec<f32>>> = Mutex::new(Vec::with_capacity(768 * pairs.len()));
-
-    let model = Arc::new(Models::load(&model_name, &device, args.verbose));
-
-    (0..pairs.len())
-        .into_par_iter()
-        .tqdm_with_bar(pb)
-        .for_each(|idx| {
-            let (id, image, boxes) = &pairs[idx];
-            let run = neural(
+    let fallback: Mutex<Vec<Fallback>> = Mutex::new(Vec::with_capacity(pairs.len()));
+
+    let cache = config
+        .cache_dir
+        .as_ref()
+        .map(|dir| EmbeddingCache::new(dir, config.cache_readonly, &model_name, &channel_map))
+        .transpose()?;
+
+    let model = Arc::new(load(&model_name));
+
+    let runs = prefetch.run(
+        &pairs,
+        progress,
+        |(_, image, boxes)| {
+            super::open_gated(image, &channel_map, gate.as_ref(), |width, height| {
+                let bounding_boxes = box_source.open(image, boxes)?;
+                Ok(super::boxes_foreground(
+                    bounding_boxes.as_xyxy(),
+                    width,
+                    height,
+                ))
+            })
+        },
+        |(id, image, boxes), loaded| {
+            status.begin(id);
+
+            let loaded = match loaded {
+                super::Loaded::Image(loaded) => loaded,
+                super::Loaded::Skipped(reason, summary) => return Ok(Some((reason, summary))),
+            };
+
+            let (ids, centroids, embeddings, fallbacks, labels, dropped, failed) = neural(
+                loaded,
                 image,
                 boxes,
+                &box_source,
                 pad,
-                args.drop_borders,
-                min_size,
+                &filter,
                 &model.clone(),
-                &device,
-            );
-
-            if let Ok((ids, centroids, embeddings)) = run {
-                let n = ids.len();
-
-                success.lock().unwrap().push(format!("{}\t{}", id, n));
-
-                let image = image.file_stem().unwrap().to_string_lossy().to_string();
+                &channel_map,
+                cache.as_ref(),
+            )?;
+
+            let n = ids.len();
+            let n_recovered = fallbacks.iter().filter(|&&f| f != Fallback::None).count();
+            let image = stem(image);
+
+            let labels: Vec<Option<String>> = match labels {
+                Some(labels) => labels.into_iter().map(Some).collect(),
+                None => vec![None; n],
+            };
+
+            // Tables are written as soon as an image is embedded so that
+            // embeddings are never accumulated across images
+            if let Some(per_image) = &per_image {
+                let name = vec![image.clone(); n];
+                let mut df = table::dataframe(
+                    &name,
+                    &ids,
+                    &centroids,
+                    &labels,
+                    &[],
+                    &fallbacks,
+                    &embeddings,
+                    embedding_dim,
+                );
 
+                per_image.write(&image, &mut df)?;
+            } else {
                 name.lock().unwrap().extend((0..n).map(|_| image.clone()));
                 item.lock().unwrap().extend(ids);
                 spot.lock().unwrap().extend(centroids);
                 data.lock().unwrap().extend(embeddings);
+                label.lock().unwrap().extend(labels);
+                fallback.lock().unwrap().extend(fallbacks);
+            }
 
-                *objects.lock().unwrap() += n;
-            } else {
-                failure
-                    .lock()
-                    .unwrap()
-                    .push(format!("{}\t{}", id, run.unwrap_err()));
+            success
+                .lock()
+                .unwrap()
+                .push(filter::object_counts_row(id, n, &dropped));
+
+            failed_objects.lock().unwrap().extend(failed.rows(id));
+
+            *objects.lock().unwrap() += n;
+            status.add_objects(n);
+            *recovered.lock().unwrap() += n_recovered;
+
+            Ok(None)
+        },
+    );
+
+    let mut failure: Vec<String> = incompatible;
+    let mut skipped: Vec<(&str, String, im::ImageSummary)> = vec![];
+
+    for ((id, _, _), run) in pairs.iter().zip(runs) {
+        match run {
+            Ok(Some((reason, summary))) => skipped.push((id, reason, summary)),
+            Ok(None) => {}
+            Err(err) => {
+                if let ThymeError::ChannelMapError(_) = err {
+                    eprintln!("[thyme::neural::boxes] ERROR: {} {}", id, err);
+                }
+
+                failure.push(format!("{}\t{}", id, err));
             }
-        });
+        }
+    }
 
     let objects = objects.into_inner().unwrap();
+    let recovered = recovered.into_inner().unwrap();
     let success = success.into_inner().unwrap();
-    let failure = failure.into_inner().unwrap();
+    let failed_objects = failed_objects.into_inner().unwrap();
 
     let name = name.into_inner().unwrap();
     let item = item.into_inner().unwrap();
     let spot = spot.into_inner().unwrap();
+    let label = label.into_inner().unwrap();
     let data = data.into_inner().unwrap();
+    let fallback = fallback.into_inner().unwrap();
 
-    if args.verbose {
+    if config.verbose {
         println!();
     }
 
+    let skipped: Vec<String> = skipped
+        .iter()
+        .map(|(id, reason, summary)| {
+            ut::track::progress_log(&format!("{}: skipped ({}).", id, reason), config.verbose);
+            filter::skipped_images_row(id, reason, summary)
+        })
+        .collect();
+
     ut::track::progress_log(
         &format!(
             "Complete. {} profiles computed across {} images.",
             ut::track::thousands_format(objects),
             ut::track::thousands_format(success.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    if !success.is_empty() {
-        let n_row = data.len();
-        let n_col = data[0].len();
-
-        if let Some(ext) = &extension {
-            if ["csv", "txt", "tsv", "pq"].iter().any(|e| e == ext) {
-                let mut df = DataFrame::new(vec![
-                    Column::new("image".into(), &name),
-                    Column::new("object".into(), &item),
-                    Column::new(
-                        "centroid_x".into(),
-                        &spot.iter().map(|x| x[0]).collect::<Vec<f32>>(),
-                    ),
-                    Column::new(
-                        "centroid_y".into(),
-                        &spot.iter().map(|x| x[0]).collect::<Vec<f32>>(),
-                    ),
-                ])
-                .unwrap();
-
-                let mut column_data: Vec<Vec<f32>> = vec![Vec::with_capacity(n_row); n_col];
-
-                for row in &data {
-                    for (idx, &descriptor) in row.iter().enumerate() {
-                        column_data[idx].push(descriptor);
-                    }
-                }
+    if !failed_objects.is_empty() {
+        eprintln!(
+            "[thyme::neural::boxes] WARNING: {} objects failed to embed and have no embeddings (see failed_objects.tsv).",
+            ut::track::thousands_format(failed_objects.len())
+        );
+    }
+
+    if recovered > 0 {
+        eprintln!(
+            "[thyme::neural::boxes] WARNING: {} objects ran out of device memory and were embedded with a fallback (see the embed_fallback column).",
+            ut::track::thousands_format(recovered)
+        );
+    }
+
+    let mut embeddings = vec![];
+
+    if let Some(per_image) = &per_image {
+        if config.provenance {
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                Some((&model_name, &channel_map)),
+                config.hash_inputs,
+                &[per_image.directory()],
+            )?;
+        }
+    } else if !success.is_empty() {
+        let npz_options = npz_options(
+            &model_name,
+            &channel_map,
+            Some(pad),
+            Some(config.max_embed_edge),
+            config.legacy_npz,
+        );
 
-                for (idx, column) in column_data.iter().enumerate() {
-                    df.with_column(Column::new(idx.to_string().into(), column))
-                        .unwrap();
+        let write = |path: &Path,
+                     name: Vec<String>,
+                     item: Vec<u32>,
+                     spot: Vec<[f32; 2]>,
+                     label: Vec<Option<String>>,
+                     fallback: Vec<Fallback>,
+                     data: Vec<Vec<f32>>|
+         -> Result<(), ThymeError> {
+            let ext = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .map(|s| s.to_lowercase())
+                .unwrap_or_default();
+
+            if constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
+                let mut df = table::dataframe(
+                    &name,
+                    &item,
+                    &spot,
+                    &label,
+                    &[],
+                    &fallback,
+                    &data,
+                    embedding_dim,
+                );
+
+                if let Some(metadata) = &metadata {
+                    io::join_metadata(
+                        &mut df,
+                        "image",
+                        metadata,
+                        config.metadata_join.as_deref().unwrap_or_default(),
+                        config.well_from_filename.as_deref(),
+                    )?;
                 }
 
-                io::write_table(&mut df, &output).unwrap_or_else(|_| {
-                    eprintln!(
-                        "[thyme::neural::boxes] ERROR: Failed to write embeddings to a table."
-                    );
-                    std::process::exit(1);
-                });
+                io::write_table_with_float_format(&mut df, path, &ipc_compression, &float_format)
+                    .map_err(|err| {
+                    ThymeError::OtherError(format!(
+                        "Failed to write embeddings to a table: {}",
+                        err
+                    ))
+                })?;
             } else if ext == "npy" {
+                let n_row = data.len();
+                let n_col = data[0].len();
+
                 io::write_numpy(
-                    &output,
+                    path,
                     data.iter().flatten().collect(),
                     vec![n_row as u64, n_col as u64],
                 )
-                .unwrap_or_else(|_| {
-                    eprintln!(
-                        "[thyme::neural::boxes] ERROR: Failed to write embeddings to a npy array."
-                    );
-                    std::process::exit(1);
-                });
+                .map_err(|err| {
+                    ThymeError::OtherError(format!(
+                        "Failed to write embeddings to a npy array: {}",
+                        err
+                    ))
+                })?;
             } else if ext == "npz" {
-                io::write_embeddings_npz(name, item, spot, data, &output).unwrap_or_else(|_| {
-                    eprintln!(
-                        "[thyme::neural::boxes] ERROR: Failed to write embeddings to an npz array."
-                    );
-                    std::process::exit(1);
-                });
+                io::write_embeddings_npz_with(name, item, spot, data, &path, &npz_options)
+                    .map_err(|err| {
+                        ThymeError::OtherError(format!(
+                            "Failed to write embeddings to an npz array: {}",
+                            err
+                        ))
+                    })?;
             }
-        } else {
-            io::write_embeddings_npz(name, item, spot, data, &output.join("embeddings.npz"))
-                .unwrap_or_else(|_| {
-                    eprintln!(
-                        "[thyme::neural::boxes] ERROR: Failed to write embeddings to an npz array."
+
+            Ok(())
+        };
+
+        let target = match extension {
+            Some(_) => output.clone(),
+            None => output.join("embeddings.npz"),
+        };
+
+        embeddings = match split_output_by {
+            None => {
+                write(&target, name, item, spot, label, fallback, data)?;
+                vec![target]
+            }
+            Some(SplitBy::Class) => {
+                let rows = split::class_rows(label.iter().map(|l| l.as_deref()));
+                let mut counts = Vec::with_capacity(rows.len());
+
+                // Outputs are only created for the classes of embedded objects
+                for (class, rows) in rows {
+                    let path = split::class_path(&target, &class);
+
+                    write(
+                        &path,
+                        split::select(&name, &rows),
+                        split::select(&item, &rows),
+                        split::select(&spot, &rows),
+                        split::select(&label, &rows),
+                        split::select(&fallback, &rows),
+                        split::select(&data, &rows),
+                    )?;
+
+                    ut::track::progress_log(
+                        &format!(
+                            "Class {}: {} objects written to {}.",
+                            class,
+                            ut::track::thousands_format(rows.len()),
+                            path.display()
+                        ),
+                        config.verbose,
                     );
-                    std::process::exit(1);
-                });
+
+                    counts.push((class, rows.len(), path));
+                }
+
+                if output.is_dir() {
+                    io::write_bytes_atomic(
+                        output.join(split::CLASS_COUNTS_FILE),
+                        split::class_counts(&counts),
+                    )?;
+                }
+
+                counts.into_iter().map(|(_, _, path)| path).collect()
+            }
+        };
+
+        if config.provenance {
+            let artifacts: Vec<&Path> = embeddings.iter().map(|path| path.as_path()).collect();
+
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                Some((&model_name, &channel_map)),
+                config.hash_inputs,
+                &artifacts,
+            )?;
         }
     }
 
     if output.is_dir() {
-        if !success.is_empty() {
-            std::fs::write(output.join("object_counts.tsv"), success.join("\n")).unwrap();
-        }
-
-        if !failure.is_empty() {
-            std::fs::write(output.join("object_errors.tsv"), failure.join("\n")).unwrap();
-        }
+        super::write_reports(&output, &success, &skipped, &failure, &failed_objects)?;
     }
+
+    crate::output::finish_run(&output)?;
+
+    Ok(NeuralSummary {
+        images: success.len(),
+        failures: failure.len(),
+        skipped: skipped.len(),
+        objects,
+        failed_objects: failed_objects.len(),
+        recovered,
+        output,
+        embeddings,
+        dry_run: None,
+    })
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().to_string()
 }
 
 #[allow(clippy::too_many_arguments, clippy::type_complexity)]
-fn neural(
+fn neural<M: Embed>(
+    image: im::ThymeImage,
     image_path: &Path,
     boxes_path: &Path,
+    box_source: &boxes::BoxSource,
     pad: u32,
-    drop_borders: bool,
-    min_size: u32,
-    model: &Models,
-    device: &Device,
-) -> Result<(Vec<u32>, Vec<[f32; 2]>, Vec<Vec<f32>>), ThymeError> {
-    let image = im::ThymeImage::open(image_path)?;
+    filter: &ObjectFilter,
+    model: &FallbackEmbedder<M>,
+    channel_map: &im::ChannelMap,
+    cache: Option<&EmbeddingCache>,
+) -> Result<
+    (
+        Vec<u32>,
+        Vec<[f32; 2]>,
+        Vec<Vec<f32>>,
+        Vec<Fallback>,
+        Option<Vec<String>>,
+        DropCounts,
+        FailedObjects,
+    ),
+    ThymeError,
+> {
+    let mut image_cache = cache
+        .map(|cache| cache.open_image(image_path))
+        .transpose()?;
+
+    // Auto is left to model preprocessing which applies the same mapping
+    let image = match channel_map {
+        im::ChannelMap::Auto => image,
+        map => image.gather_channels(map)?,
+    };
 
-    let bounding_boxes = im::BoundingBoxes::open(boxes_path)?;
+    let bounding_boxes = box_source.open(image_path, boxes_path)?;
 
     let width = image.width();
     let height = image.height();
 
-    let pad_f32 = pad as f32;
-
     let mut ids: Vec<u32> = Vec::with_capacity(bounding_boxes.len());
     let mut centroids: Vec<[f32; 2]> = Vec::with_capacity(2 * bounding_boxes.len());
     let mut results: Vec<Vec<f32>> = Vec::with_capacity(300 * bounding_boxes.len());
+    let mut fallbacks: Vec<Fallback> = Vec::with_capacity(bounding_boxes.len());
+    let mut dropped = DropCounts::default();
+    let mut failed = FailedObjects::default();
+
+    let mut labels: Vec<String> = vec![];
+
+    for (idx, xyxy) in bounding_boxes.as_xyxy().iter().enumerate() {
+        if let Err(reason) = filter.check(
+            xyxy,
+            (xyxy[2] - xyxy[0]) * (xyxy[3] - xyxy[1]),
+            width,
+            height,
+        ) {
+            dropped.add(reason);
+            continue;
+        }
 
-    for (idx, [min_x, min_y, max_x, max_y]) in bounding_boxes.as_xyxy().iter().enumerate() {
-        let min_x = min_x - pad_f32;
-        let min_y = min_y - pad_f32;
-        let max_x = max_x + pad_f32;
-        let max_y = max_y + pad_f32;
-
-        if drop_borders
-            && (min_x <= 0.0 || min_y <= 0.0 || max_x >= width as f32 || max_y >= height as f32)
-        {
+        let Some(CropRect {
+            x: min_x_u32,
+            y: min_y_u32,
+            width: w,
+            height: h,
+        }) = crop_rect(xyxy, pad, width, height)
+        else {
+            dropped.add(DropReason::OutsideImage);
             continue;
+        };
+
+        let crop = [min_x_u32, min_y_u32, w, h];
+
+        let (embedding, fallback) =
+            match super::embed_object(&image, crop, model, image_cache.as_mut()) {
+                Ok(embedded) => embedded,
+                Err(err) => {
+                    failed.add(idx as u32, *xyxy, err);
+                    continue;
+                }
+            };
+
+        ids.push(idx as u32);
+
+        if let Some(l) = bounding_boxes.labels() {
+            labels.push(l[idx].clone());
         }
 
-        let min_x_u32 = min_x.max(0.0) as u32;
-        let min_y_u32 = min_y.max(0.0) as u32;
-        let max_x_u32 = max_x.min(width as f32) as u32;
-        let max_y_u32 = max_y.min(height as f32) as u32;
+        centroids.push([(xyxy[0] + xyxy[2]) / 2.0, (xyxy[1] + xyxy[3]) / 2.0]);
+        results.push(embedding);
+        fallbacks.push(fallback);
+    }
 
-        let w = max_x_u32 - min_x_u32;
-        let h = max_y_u32 - min_y_u32;
+    if let Some(image_cache) = image_cache {
+        image_cache.flush()?;
+    }
 
-        if w < min_size || h < min_size {
-            continue;
+    let labels = bounding_boxes.labels().map(|_| labels);
+
+    Ok((ids, centroids, results, fallbacks, labels, dropped, failed))
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::Parser;
+
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: NeuralBoxesArgs,
+    }
+
+    fn args(args: &[&str]) -> NeuralBoxesArgs {
+        Cli::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args
+    }
+
+    /// Embeds crops as their size
+    struct Stub;
+
+    impl Embed for Stub {
+        fn embed(&self, image: &im::ThymeImage, _device: &Device) -> candle_core::Result<Vec<f32>> {
+            Ok(vec![image.width() as f32, image.height() as f32])
         }
+    }
 
-        ids.push(idx as u32);
-        centroids.push([(max_x + min_x) / 2.0, (max_y + min_y) / 2.0]);
+    #[test]
+    fn test_split_output_by_class() {
+        let directory = std::env::temp_dir().join("thyme_test_neural_boxes_split");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: 1,
+            radius: [4.0, 6.0],
+            seed: 7,
+            ..Default::default()
+        }
+        .write(2, directory.join("images"))
+        .unwrap();
 
-        results.push(
-            model
-                .forward(
-                    &model
-                        .preprocess(&image.crop(min_x_u32, min_y_u32, w, h)?, device)
-                        .unwrap(),
-                )
-                .unwrap()
-                .get(0)
-                .unwrap()
-                .to_vec1()
-                .unwrap(),
+        // Three cells and two debris boxes in each image
+        let mut table = "image,xmin,ymin,xmax,ymax,label".to_string();
+
+        for image in ["synth_0000", "synth_0001"] {
+            for (k, label) in ["cell", "debris", "cell", "debris", "cell"]
+                .iter()
+                .enumerate()
+            {
+                let x = 10 * k + 2;
+                table.push_str(&format!("\n{},{},4,{},20,{}", image, x, x + 8, label));
+            }
+        }
+
+        let boxes = directory.join("boxes.csv");
+        std::fs::write(&boxes, table).unwrap();
+
+        let output = directory.join("out");
+
+        run(
+            &NeuralBoxesConfig::from(&args(&[
+                "-i",
+                directory.join("images").to_str().unwrap(),
+                "-s",
+                boxes.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                "--split-output-by",
+                "class",
+            ])),
+            |_| FallbackEmbedder::new(Stub, Device::Cpu, 1024),
+        )
+        .unwrap();
+
+        assert!(!output.join("embeddings.npz").exists());
+        assert!(!output.join("embeddings_unclassified.npz").exists());
+
+        let cell = io::read_embeddings_npz(output.join("embeddings_cell.npz")).unwrap();
+        let debris = io::read_embeddings_npz(output.join("embeddings_debris.npz")).unwrap();
+
+        assert_eq!(cell.ids.len(), 6);
+        assert_eq!(debris.ids.len(), 4);
+        assert!(cell.ids.iter().all(|&id| id % 2 == 0));
+        assert!(debris.ids.iter().all(|&id| id % 2 == 1));
+
+        // Every object is written to exactly one class
+        let objects = |npz: &io::EmbeddingsNpz| -> Vec<(String, u32)> {
+            npz.images
+                .iter()
+                .cloned()
+                .zip(npz.ids.iter().copied())
+                .collect()
+        };
+
+        let mut all = [objects(&cell), objects(&debris)].concat();
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), 10);
+
+        let counts = std::fs::read_to_string(output.join(split::CLASS_COUNTS_FILE)).unwrap();
+        assert_eq!(
+            counts,
+            "class\tobjects\toutput\ncell\t6\tembeddings_cell.npz\ndebris\t4\tembeddings_debris.npz"
         );
-    }
 
-    Ok((ids, centroids, results))
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
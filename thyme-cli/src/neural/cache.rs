@@ -0,0 +1,287 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::ChannelMap;
+use thyme_core::io;
+
+use crate::provenance;
+
+/// Version of the cache entry layout, included in every cache key
+const CACHE_VERSION: u32 = 1;
+
+/// A directory of object embeddings keyed by image content and model settings
+///
+/// Each image is stored in a single `{key}.bin` file where the key is a
+/// sha256 hash of the image content, model name, and channel map. Entries
+/// within the file are keyed by the padded crop (x, y, w, h) passed to the
+/// model, so changing the padding or segmentation only recomputes objects
+/// whose crops differ.
+pub struct EmbeddingCache {
+    dir: PathBuf,
+    readonly: bool,
+    context: String,
+}
+
+impl EmbeddingCache {
+    /// Open an embedding cache in the provided directory
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Cache directory (created if missing unless read-only)
+    /// * `readonly` - Look up embeddings without adding new entries
+    /// * `model_name` - Name of the model computing the embeddings
+    /// * `channel_map` - Channel map applied before model preprocessing
+    pub fn new<P: AsRef<Path>>(
+        dir: P,
+        readonly: bool,
+        model_name: &str,
+        channel_map: &ChannelMap,
+    ) -> Result<Self, ThymeError> {
+        let dir = dir.as_ref().to_path_buf();
+
+        if readonly && !dir.is_dir() {
+            return Err(ThymeError::DirError(format!(
+                "Read-only cache directory {} does not exist.",
+                dir.display()
+            )));
+        }
+
+        if !readonly {
+            std::fs::create_dir_all(&dir).map_err(|err| {
+                ThymeError::DirError(format!(
+                    "Could not create cache directory {}. {}",
+                    dir.display(),
+                    err
+                ))
+            })?;
+        }
+
+        Ok(Self {
+            dir,
+            readonly,
+            context: format!(
+                "version={}\nmodel={}\nchannel_map={}",
+                CACHE_VERSION, model_name, channel_map
+            ),
+        })
+    }
+
+    /// Load the cached embeddings for an image
+    ///
+    /// # Arguments
+    ///
+    /// * `image_path` - Path to the image the objects are cropped from
+    pub fn open_image(&self, image_path: &Path) -> Result<ImageCache, ThymeError> {
+        let mut hasher = Sha256::new();
+        hasher.update(provenance::sha256(image_path)?);
+        hasher.update(&self.context);
+
+        let path = self.dir.join(format!("{:x}.bin", hasher.finalize()));
+
+        let entries = match std::fs::read(&path) {
+            Ok(bytes) => decode(&bytes),
+            Err(_) => Vec::new(),
+        };
+
+        let index = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, (crop, _))| (*crop, idx))
+            .collect();
+
+        Ok(ImageCache {
+            path,
+            readonly: self.readonly,
+            entries,
+            index,
+            inserted: 0,
+        })
+    }
+}
+
+/// Cached embeddings for the objects of a single image
+pub struct ImageCache {
+    path: PathBuf,
+    readonly: bool,
+    entries: Vec<([u32; 4], Vec<f32>)>,
+    index: HashMap<[u32; 4], usize>,
+    inserted: usize,
+}
+
+impl ImageCache {
+    /// Return the cached embedding for a crop or compute and cache it
+    ///
+    /// # Arguments
+    ///
+    /// * `crop` - Padded crop (x, y, w, h) passed to the model
+    /// * `embed` - Computes the embedding if the crop is not cached
+    pub fn get_or_embed<F>(&mut self, crop: [u32; 4], embed: F) -> Result<Vec<f32>, ThymeError>
+    where
+        F: FnOnce() -> Result<Vec<f32>, ThymeError>,
+    {
+        if let Some(&idx) = self.index.get(&crop) {
+            return Ok(self.entries[idx].1.clone());
+        }
+
+        let embedding = embed()?;
+
+        if !self.readonly {
+            self.index.insert(crop, self.entries.len());
+            self.entries.push((crop, embedding.clone()));
+            self.inserted += 1;
+        }
+
+        Ok(embedding)
+    }
+
+    /// Write newly computed embeddings to the cache
+    ///
+    /// Entries are written with `io::write_bytes_atomic` so that concurrent
+    /// readers never observe a partial write.
+    pub fn flush(self) -> Result<(), ThymeError> {
+        if self.readonly || self.inserted == 0 {
+            return Ok(());
+        }
+
+        io::write_bytes_atomic(&self.path, encode(&self.entries))
+    }
+}
+
+/// Serialize entries as little-endian (x, y, w, h, n, embedding[n]) records
+fn encode(entries: &[([u32; 4], Vec<f32>)]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    for (crop, embedding) in entries {
+        for value in crop.iter().chain(&[embedding.len() as u32]) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        for value in embedding {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    bytes
+}
+
+/// Deserialize entries, ignoring a truncated trailing record
+fn decode(bytes: &[u8]) -> Vec<([u32; 4], Vec<f32>)> {
+    let word =
+        |offset: usize| -> Option<[u8; 4]> { bytes.get(offset..offset + 4)?.try_into().ok() };
+
+    let mut entries = Vec::new();
+    let mut offset = 0;
+
+    while let Some(header) = (0..5)
+        .map(|i| word(offset + 4 * i).map(u32::from_le_bytes))
+        .collect::<Option<Vec<u32>>>()
+    {
+        let n = header[4] as usize;
+        let start = offset + 20;
+
+        let Some(embedding) = (0..n)
+            .map(|i| word(start + 4 * i).map(f32::from_le_bytes))
+            .collect::<Option<Vec<f32>>>()
+        else {
+            break;
+        };
+
+        entries.push(([header[0], header[1], header[2], header[3]], embedding));
+        offset = start + 4 * n;
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::cell::Cell;
+
+    const TEST_IMAGE: &str = "../data/tests/test_rgb.png";
+
+    const CROPS: [[u32; 4]; 3] = [[0, 0, 4, 4], [2, 3, 5, 6], [10, 1, 3, 3]];
+
+    /// Embed each crop through the cache with a stub model that counts calls
+    fn embed_all(cache: &EmbeddingCache, calls: &Cell<usize>) -> Vec<Vec<f32>> {
+        let mut image_cache = cache.open_image(Path::new(TEST_IMAGE)).unwrap();
+
+        let embeddings = CROPS
+            .iter()
+            .map(|crop| {
+                image_cache
+                    .get_or_embed(*crop, || {
+                        calls.set(calls.get() + 1);
+                        Ok(crop.iter().map(|&v| v as f32 * 0.5).collect())
+                    })
+                    .unwrap()
+            })
+            .collect();
+
+        image_cache.flush().unwrap();
+
+        embeddings
+    }
+
+    #[test]
+    fn test_warm_cache_skips_model() {
+        let directory = std::env::temp_dir().join("thyme_test_embedding_cache");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let cache = EmbeddingCache::new(&directory, false, "stub", &ChannelMap::Auto).unwrap();
+
+        let calls = Cell::new(0);
+        let cold = embed_all(&cache, &calls);
+        assert_eq!(calls.get(), CROPS.len());
+
+        let calls = Cell::new(0);
+        let warm = embed_all(&cache, &calls);
+        assert_eq!(calls.get(), 0);
+        assert_eq!(cold, warm);
+
+        // Changing the model invalidates the cached embeddings
+        let other = EmbeddingCache::new(&directory, false, "other", &ChannelMap::Auto).unwrap();
+
+        let calls = Cell::new(0);
+        embed_all(&other, &calls);
+        assert_eq!(calls.get(), CROPS.len());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_readonly_cache() {
+        let directory = std::env::temp_dir().join("thyme_test_embedding_cache_readonly");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        assert!(EmbeddingCache::new(&directory, true, "stub", &ChannelMap::Auto).is_err());
+
+        std::fs::create_dir_all(&directory).unwrap();
+        let cache = EmbeddingCache::new(&directory, true, "stub", &ChannelMap::Auto).unwrap();
+
+        let calls = Cell::new(0);
+        embed_all(&cache, &calls);
+        embed_all(&cache, &calls);
+        assert_eq!(calls.get(), 2 * CROPS.len());
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let entries = vec![([1, 2, 3, 4], vec![0.5, 1.5]), ([5, 6, 7, 8], vec![2.5])];
+        let bytes = encode(&entries);
+
+        assert_eq!(decode(&bytes), entries);
+        assert_eq!(decode(&bytes[..bytes.len() - 2]), entries[..1]);
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use clap::Args;
+
+use thyme_core::catalog::{self, Column};
+use thyme_core::error::ThymeError;
+use thyme_neural::nn::Models;
+
+#[derive(Debug, Args)]
+#[command(about = "Print the ordered columns of a neural embedding table as JSON.")]
+pub struct NeuralColumnsArgs {
+    #[arg(
+        long,
+        short = 'm',
+        help = "Model name.",
+        default_value = "dino_vit_small"
+    )]
+    pub model: Option<String>,
+}
+
+pub fn neural_columns(args: &NeuralColumnsArgs) {
+    let columns =
+        columns(args.model.as_deref().unwrap_or("dino_vit_small")).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+
+    println!("{}", serde_json::to_string_pretty(&columns).unwrap());
+}
+
+/// Resolve the columns written to embedding tables by a model
+///
+/// # Arguments
+///
+/// * `model` - Model name
+fn columns(model: &str) -> Result<Vec<Column>, ThymeError> {
+    Models::embedding_dim(model)
+        .map(catalog::neural_columns)
+        .ok_or_else(|| ThymeError::OtherError(format!("Model {} not found", model)))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_neural_columns() {
+        let observed = columns("dino_vit_base").unwrap();
+
+        assert_eq!(observed.len(), 5 + 768);
+        assert_eq!(observed[3].name, "centroid_y");
+        assert_eq!(observed[4].name, "embed_fallback");
+        assert_eq!(observed[5].name, "0");
+        assert_eq!(observed.last().unwrap().name, "767");
+
+        assert!(columns("missing").is_err());
+    }
+}
@@ -0,0 +1,568 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::{Path, PathBuf};
+
+use candle_core::{Device, utils::cuda_is_available, utils::metal_is_available};
+use clap::Args;
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im;
+use thyme_core::io;
+use thyme_core::ut;
+use thyme_data::data::Weights;
+use thyme_neural::fallback::{Embed, Fallback, FallbackEmbedder};
+use thyme_neural::nn::Models;
+
+use super::table;
+use crate::crops;
+use crate::output::{OutputMode, npz_options, prepare_output};
+use crate::prefetch::Prefetch;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Compute self-supervised features from a directory of single-object crops without segments."
+)]
+pub struct NeuralCropsArgs {
+    #[arg(
+        short = 'i',
+        long,
+        help = "Directory of single-object crops.",
+        required = true
+    )]
+    pub images: Option<String>,
+
+    #[arg(long, help = "Device (cpu, cuda, metal).", default_value = "cpu")]
+    pub device: Option<String>,
+
+    #[arg(
+        short = 'o',
+        long,
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather, .npy, .npz).",
+        required = true
+    )]
+    pub output: Option<String>,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+
+    #[arg(
+        long,
+        short = 'm',
+        help = "Model name.",
+        default_value = "dino_vit_small"
+    )]
+    pub model: Option<String>,
+
+    #[arg(
+        long,
+        help = "Channel map applied before model preprocessing: auto, gray-average, or input channel indices for each RGB channel (e.g. 0,0,0 or 1,2,0).",
+        default_value = "auto"
+    )]
+    pub channel_map: Option<String>,
+
+    #[arg(
+        long,
+        help = "Maximum edge length that crops are downscaled to if the device runs out of memory.",
+        default_value = "1024"
+    )]
+    pub max_embed_edge: Option<u32>,
+
+    #[arg(long, help = "Substring specifying crops (e.g. _crop).")]
+    pub image_substring: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting crops by their path relative to the crop directory (e.g. 'plate_1_*.png').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting crops by their path relative to the crop directory (e.g. '_A0[1-3]_').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write .npz embeddings in the version 1 layout (image, id, centroid, embedding) without format_version and attrs members."
+    )]
+    pub legacy_npz: bool,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (tables, arrays, and reports) from the output location before embedding. Without it, output locations holding a previous run are rejected."
+    )]
+    pub overwrite: bool,
+
+    #[arg(short = 't', long, help = "Number of threads.")]
+    pub threads: Option<usize>,
+}
+
+pub fn neural_image_crops(args: &NeuralCropsArgs) {
+    let device = args.device.to_owned().unwrap_or("cpu".to_string());
+
+    if !["cpu", "metal", "cuda"].iter().any(|d| d == &device) {
+        eprintln!(
+            "[thyme::neural::crops] ERROR: Invalid device. Must be one of: cpu, metal, cuda."
+        );
+        std::process::exit(1);
+    }
+
+    if device == "cuda" && !cuda_is_available() {
+        println!("[thyme::neural::crops] Device 'cuda' specified but no cuda device was detected.");
+        std::process::exit(1);
+    }
+
+    if device == "metal" && !metal_is_available() {
+        println!(
+            "[thyme::neural::crops] Device 'metal' specified but no metal device was detected."
+        );
+        std::process::exit(1);
+    }
+
+    let (threads, device) = if device == "cuda" {
+        ut::track::progress_log("Cuda device detected.", args.verbose);
+        (Some(1), Device::new_cuda(0).unwrap())
+    } else if device == "metal" {
+        ut::track::progress_log("Metal device detected.", args.verbose);
+        (Some(1), Device::new_metal(0).unwrap())
+    } else {
+        (args.threads, Device::Cpu)
+    };
+
+    if threads == Some(0) {
+        println!("[thyme::neural::crops] Threads must be set to a positive integer if provided.");
+        std::process::exit(1);
+    }
+
+    let model_name = args
+        .model
+        .to_owned()
+        .unwrap_or("dino_vit_small".to_string());
+
+    if !Weights::iter().any(|m| m.model_name() == model_name) {
+        // If model name is invalid, select will terminate and show error with list of available models
+        Weights::select(&model_name);
+    }
+
+    let result = ut::threads::install(threads, || {
+        let mut model = FallbackEmbedder::new(
+            Models::load(&model_name, &device, args.verbose),
+            device.clone(),
+            args.max_embed_edge.unwrap_or(1024),
+        );
+
+        // Crops that run out of memory on the device are embedded on the cpu as a last resort
+        if !device.is_cpu() {
+            let model_name = model_name.clone();
+            model = model.with_cpu_fallback(move || Models::load(&model_name, &Device::Cpu, false));
+        }
+
+        run(
+            args,
+            &model_name,
+            Models::embedding_dim(&model_name).unwrap(),
+            &model,
+        )
+    });
+
+    if let Err(err) = result {
+        eprintln!("[thyme::neural::crops] ERROR: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Embed every crop in a directory as a single object
+///
+/// Each crop is one object named after its file (see `crops::object_id`)
+/// with its centroid at the center of the crop. Returns the number of
+/// embedded crops.
+///
+/// # Arguments
+///
+/// * `args` - Command arguments
+/// * `model_name` - Name of the model recorded in npz outputs
+/// * `embedding_dim` - Number of embedding dimensions produced by the model
+/// * `model` - Model used to embed each crop
+fn run<M: Embed>(
+    args: &NeuralCropsArgs,
+    model_name: &str,
+    embedding_dim: usize,
+    model: &FallbackEmbedder<M>,
+) -> Result<usize, ThymeError> {
+    let channel_map = im::ChannelMap::parse(args.channel_map.as_deref().unwrap_or("auto"))?;
+
+    if channel_map.channels().is_some_and(|n| n != 1 && n != 3) {
+        return Err(ThymeError::OtherError(format!(
+            "channel_map must produce 1 or 3 channels but {} produces {}",
+            channel_map,
+            channel_map.channels().unwrap()
+        )));
+    }
+
+    ut::track::progress_log(
+        &format!("Channel map: {}.", channel_map.describe()),
+        args.verbose,
+    );
+
+    let ipc_compression = args
+        .ipc_compression
+        .to_owned()
+        .unwrap_or("none".to_string());
+
+    if !constant::SUPPORTED_IPC_COMPRESSION.contains(&ipc_compression.as_str()) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid ipc_compression {}. Must be one of: {:?}",
+            ipc_compression,
+            constant::SUPPORTED_IPC_COMPRESSION
+        )));
+    }
+
+    let float_format = io::FloatFormat::new(
+        args.float_precision,
+        args.float_format.as_deref().unwrap_or("auto"),
+    )?;
+
+    let files = crops::select(
+        args.images.as_deref().unwrap_or_default(),
+        args.image_substring.as_deref(),
+        args.image_glob.as_deref(),
+        args.image_regex.as_deref(),
+    )?;
+
+    ut::track::progress_log(
+        &format!(
+            "Detected {} crops.",
+            ut::track::thousands_format(files.len())
+        ),
+        args.verbose,
+    );
+
+    let output = output_path(
+        args.output.as_deref().unwrap_or_default(),
+        OutputMode::new(args.overwrite, false)?,
+        args.images.as_deref().unwrap_or_default(),
+    )?;
+
+    let pb = ut::track::progress_bar(files.len(), "Embedding", args.verbose);
+
+    let runs = Prefetch::new(None, None)?.run(
+        &files,
+        pb,
+        |path| super::open_image(path, &channel_map),
+        |_, image| {
+            let centroid = crops::center(&image);
+
+            // Auto is left to model preprocessing which applies the same mapping
+            let image = match &channel_map {
+                im::ChannelMap::Auto => image,
+                map => image.gather_channels(map)?,
+            };
+
+            let (embedding, fallback) = model
+                .embed(&image)
+                .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+            Ok((centroid, embedding, fallback))
+        },
+    );
+
+    let mut name: Vec<String> = Vec::with_capacity(files.len());
+    let mut item: Vec<u32> = Vec::with_capacity(files.len());
+    let mut spot: Vec<[f32; 2]> = Vec::with_capacity(files.len());
+    let mut data: Vec<Vec<f32>> = Vec::with_capacity(files.len());
+    let mut fallback: Vec<Fallback> = Vec::with_capacity(files.len());
+    let mut failure: Vec<String> = vec![];
+
+    for (path, run) in files.iter().zip(runs) {
+        match run {
+            Ok((centroid, embedding, used)) => {
+                let (image, object) = crops::object_id(path);
+
+                name.push(image);
+                item.push(object);
+                spot.push(centroid);
+                data.push(embedding);
+                fallback.push(used);
+            }
+            Err(err) => failure.push(format!("{}\t{}", stem(path), err)),
+        }
+    }
+
+    if args.verbose {
+        println!();
+    }
+
+    ut::track::progress_log(
+        &format!(
+            "Complete. {} crops embedded.",
+            ut::track::thousands_format(data.len())
+        ),
+        args.verbose,
+    );
+
+    let recovered = fallback.iter().filter(|&&f| f != Fallback::None).count();
+
+    if recovered > 0 {
+        eprintln!(
+            "[thyme::neural::crops] WARNING: {} crops ran out of device memory and were embedded with a fallback (see the embed_fallback column).",
+            ut::track::thousands_format(recovered)
+        );
+    }
+
+    let embedded = data.len();
+
+    if embedded > 0 {
+        let options = npz_options(
+            model_name,
+            &channel_map,
+            None,
+            args.max_embed_edge,
+            args.legacy_npz,
+        );
+
+        let extension = output
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase());
+
+        match extension.as_deref() {
+            Some(ext) if constant::SUPPORTED_TABLE_FORMATS.contains(&ext) => {
+                let mut df = table::dataframe(
+                    &name,
+                    &item,
+                    &spot,
+                    &[],
+                    &[],
+                    &fallback,
+                    &data,
+                    embedding_dim,
+                );
+
+                io::write_table_with_float_format(
+                    &mut df,
+                    &output,
+                    &ipc_compression,
+                    &float_format,
+                )
+                .map_err(|err| {
+                    ThymeError::OtherError(format!(
+                        "Failed to write embeddings to a table: {}",
+                        err
+                    ))
+                })?;
+            }
+            Some("npy") => {
+                let n_col = data[0].len();
+
+                io::write_numpy(
+                    &output,
+                    data.iter().flatten().collect(),
+                    vec![embedded as u64, n_col as u64],
+                )
+                .map_err(|err| {
+                    ThymeError::OtherError(format!(
+                        "Failed to write embeddings to a npy array: {}",
+                        err
+                    ))
+                })?;
+            }
+            _ => {
+                let path = match output.is_dir() {
+                    true => output.join("embeddings.npz"),
+                    false => output.clone(),
+                };
+
+                io::write_embeddings_npz_with(name, item, spot, data, &path, &options).map_err(
+                    |_| {
+                        ThymeError::OtherError(
+                            "Failed to write embeddings to an npz array".to_string(),
+                        )
+                    },
+                )?;
+            }
+        }
+    }
+
+    if output.is_dir() && !failure.is_empty() {
+        io::write_bytes_atomic(output.join("object_errors.tsv"), failure.join("\n"))?;
+    }
+
+    crate::output::finish_run(&output)?;
+
+    Ok(embedded)
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().to_string()
+}
+
+/// Resolve an output table or array or create an output directory
+fn output_path(output: &str, mode: OutputMode, images: &str) -> Result<PathBuf, ThymeError> {
+    let output = PathBuf::from(output);
+
+    let extension = output
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+
+    let Some(ext) = extension else {
+        return prepare_output(&output, mode, &[images], "neural::crops");
+    };
+
+    if !constant::SUPPORTED_TABLE_FORMATS
+        .iter()
+        .chain(&constant::SUPPORTED_EMBEDDING_FORMATS)
+        .any(|e| *e == ext)
+    {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid file extension {}. Must be one of: {:?} or {:?}",
+            ext,
+            constant::SUPPORTED_TABLE_FORMATS,
+            constant::SUPPORTED_EMBEDDING_FORMATS
+        )));
+    }
+
+    if let Some(parent) = output.parent() {
+        if !parent.is_dir() && !parent.as_os_str().is_empty() {
+            return Err(ThymeError::OtherError(
+                "Invalid file path. Parent directory of output file path does not exist"
+                    .to_string(),
+            ));
+        }
+    }
+
+    prepare_output(&output, mode, &[images], "neural::crops")
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::Parser;
+
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: NeuralCropsArgs,
+    }
+
+    fn args(args: &[&str]) -> NeuralCropsArgs {
+        Cli::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args
+    }
+
+    /// Embeds crops as their size
+    struct Stub;
+
+    impl Embed for Stub {
+        fn embed(&self, image: &im::ThymeImage, _device: &Device) -> candle_core::Result<Vec<f32>> {
+            Ok(vec![image.width() as f32, image.height() as f32])
+        }
+    }
+
+    #[test]
+    fn test_neural_crops() {
+        let directory = std::env::temp_dir().join("thyme_test_neural_crops");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 20,
+            height: 30,
+            objects: 1,
+            radius: [4.0, 6.0],
+            seed: 5,
+            ..Default::default()
+        }
+        .write(2, directory.join("crops"))
+        .unwrap();
+
+        std::fs::rename(
+            directory.join("crops").join("synth_0001_image.png"),
+            directory.join("crops").join("well_B02_3.png"),
+        )
+        .unwrap();
+
+        // Six channel crops do not fit the model input under the auto channel map
+        io::write_numpy(
+            directory.join("crops").join("well_B03_4.npy"),
+            vec![0u8; 12 * 10 * 6],
+            vec![12, 10, 6],
+        )
+        .unwrap();
+
+        let crops = directory.join("crops");
+        let model = FallbackEmbedder::new(Stub, Device::Cpu, 1024);
+
+        let embed = |output: &Path| {
+            run(
+                &args(&[
+                    "-i",
+                    crops.to_str().unwrap(),
+                    "-o",
+                    output.to_str().unwrap(),
+                    "--image-regex",
+                    r"_(image|3)\.png$|_4\.npy$",
+                ]),
+                "stub",
+                2,
+                &model,
+            )
+            .unwrap()
+        };
+
+        let output = directory.join("embeddings.csv");
+        assert_eq!(embed(&output), 2);
+
+        let table = std::fs::read_to_string(&output).unwrap();
+        let rows: Vec<&str> = table.lines().collect();
+
+        // Each crop is one object named after its file and centered in the crop
+        assert_eq!(
+            rows,
+            [
+                "image,object,centroid_x,centroid_y,embed_fallback,0,1",
+                "synth_0000_image,0,10.0,15.0,none,20.0,30.0",
+                "well_B02,3,10.0,15.0,none,20.0,30.0",
+            ]
+        );
+
+        let output = directory.join("out");
+        assert_eq!(embed(&output), 2);
+        assert!(output.join("embeddings.npz").is_file());
+
+        // The incompatible crop is reported with its shape and the accepted shapes
+        assert_eq!(
+            std::fs::read_to_string(output.join("object_errors.tsv")).unwrap(),
+            "well_B03_4\t[thyme::ChannelMapError] Image channels do not fit the channel map. well_B03_4.npy has shape (12, 10, 6) but channel map auto accepts (H, W), (H, W, 1), or (H, W, 3) images. Use --channel-map gray-average or channel indices (e.g. 0,1,2) to convert other images."
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
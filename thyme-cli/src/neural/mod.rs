@@ -1,15 +1,45 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::path::{Path, PathBuf};
+
+use candle_core::{Device, utils::cuda_is_available, utils::metal_is_available};
 use clap::{Args, Subcommand};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use thyme_core::catalog;
+use thyme_core::error::ThymeError;
+use thyme_core::im::{ChannelMap, ImageSummary, ThymeImage};
+use thyme_core::io;
+use thyme_core::ut;
+use thyme_neural::fallback::{Embed, Fallback, FallbackEmbedder};
+use thyme_neural::nn::Models;
 
+use crate::dryrun::{self, DryRun, DryRunImage};
+use crate::failures::{self, ObjectError, Stage};
+use crate::filter;
+
+mod aggregate;
 mod boxes;
+mod cache;
+mod columns;
+mod crops;
 mod mask;
 mod polygons;
+mod query;
+mod split;
+mod table;
 
+use aggregate::{NeuralAggregateArgs, neural_aggregate};
+pub use boxes::run_neural_boxes;
 use boxes::{NeuralBoxesArgs, neural_image_boxes};
+use columns::{NeuralColumnsArgs, neural_columns};
+use crops::{NeuralCropsArgs, neural_image_crops};
+pub use mask::run_neural_mask;
 use mask::{NeuralMaskArgs, neural_image_mask};
+pub use polygons::run_neural_polygons;
 use polygons::{NeuralPolygonsArgs, neural_image_polygons};
+use query::{NeuralQueryArgs, neural_query};
 
 #[derive(Debug, Args)]
 #[command(about = "Compute object-level self-supervised features from image and segment pairs.")]
@@ -23,15 +53,564 @@ pub struct NeuralArgs {
 
 #[derive(Debug, Subcommand)]
 enum NeuralCommands {
+    Aggregate(NeuralAggregateArgs),
     Boxes(NeuralBoxesArgs),
+    Columns(NeuralColumnsArgs),
+    Crops(NeuralCropsArgs),
     Mask(NeuralMaskArgs),
     Polygons(NeuralPolygonsArgs),
+    Query(NeuralQueryArgs),
 }
 
 pub fn neural(args: &NeuralArgs) {
     match args.command.as_ref().unwrap() {
+        NeuralCommands::Aggregate(aggregate) => neural_aggregate(aggregate),
         NeuralCommands::Boxes(boxes) => neural_image_boxes(boxes),
+        NeuralCommands::Columns(columns) => neural_columns(columns),
+        NeuralCommands::Crops(crops) => neural_image_crops(crops),
         NeuralCommands::Mask(masks) => neural_image_mask(masks),
         NeuralCommands::Polygons(polygons) => neural_image_polygons(polygons),
+        NeuralCommands::Query(query) => neural_query(query),
+    }
+}
+
+/// Outcome of a neural run
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeuralSummary {
+    /// Number of images whose objects were embedded
+    pub images: usize,
+    /// Number of images that failed to embed
+    pub failures: usize,
+    /// Number of images skipped by `skip_image_if`
+    pub skipped: usize,
+    /// Number of objects that were embedded
+    pub objects: usize,
+    /// Number of objects that failed within images that were embedded
+    pub failed_objects: usize,
+    /// Number of objects embedded with a fallback after running out of device memory
+    pub recovered: usize,
+    /// Output directory or file
+    pub output: PathBuf,
+    /// Embedding tables or arrays, if written to single files
+    pub embeddings: Vec<PathBuf>,
+    /// Dry run report, if the run was a dry run
+    pub dry_run: Option<PathBuf>,
+}
+
+/// Convert a message from argument parsing into an error
+fn invalid(message: String) -> ThymeError {
+    ThymeError::OtherError(message.trim_end_matches('.').to_string())
+}
+
+/// Number of columns of the embeddings of a model
+fn embedding_dim(model: &str) -> Result<usize, ThymeError> {
+    Models::embedding_dim(model).ok_or_else(|| {
+        ThymeError::OtherError(format!("Embedding dimension of model {} is unknown", model))
+    })
+}
+
+/// Select the device a model is run on and the compute threads used with it
+///
+/// Models on a gpu are run from a single thread, otherwise `threads`
+/// defaults to all available threads.
+///
+/// # Arguments
+///
+/// * `device` - Device name (cpu, cuda, metal)
+/// * `threads` - Number of compute threads on the cpu
+/// * `verbose` - Log the detected device
+fn select_device(
+    device: &str,
+    threads: Option<usize>,
+    verbose: bool,
+) -> Result<(Option<usize>, Device), ThymeError> {
+    let unavailable = |device: &str| {
+        ThymeError::OtherError(format!(
+            "Device '{}' specified but no {} device was detected",
+            device, device
+        ))
+    };
+
+    match device {
+        "cuda" if !cuda_is_available() => Err(unavailable(device)),
+        "metal" if !metal_is_available() => Err(unavailable(device)),
+        "cuda" => {
+            ut::track::progress_log("Cuda device detected.", verbose);
+            Device::new_cuda(0)
+                .map(|device| (Some(1), device))
+                .map_err(|err| ThymeError::OtherError(err.to_string()))
+        }
+        "metal" => {
+            ut::track::progress_log("Metal device detected.", verbose);
+            Device::new_metal(0)
+                .map(|device| (Some(1), device))
+                .map_err(|err| ThymeError::OtherError(err.to_string()))
+        }
+        _ => Ok((threads, Device::Cpu)),
+    }
+}
+
+/// Write the run reports of a neural command to its output directory
+///
+/// # Arguments
+///
+/// * `output` - Output directory
+/// * `success` - Object count rows of the embedded images
+/// * `skipped` - Skipped image rows of the images skipped by an image gate
+/// * `failure` - Errors of the images that failed to embed
+/// * `failed_objects` - Failed object rows of the objects that failed to embed
+fn write_reports(
+    output: &Path,
+    success: &[String],
+    skipped: &[String],
+    failure: &[String],
+    failed_objects: &[String],
+) -> Result<(), ThymeError> {
+    if !success.is_empty() {
+        io::write_bytes_atomic(
+            output.join("object_counts.tsv"),
+            filter::object_counts(success),
+        )?;
+    }
+
+    if !skipped.is_empty() {
+        io::write_bytes_atomic(
+            output.join("skipped_images.tsv"),
+            filter::skipped_images(skipped),
+        )?;
+    }
+
+    if !failure.is_empty() {
+        io::write_bytes_atomic(output.join("object_errors.tsv"), failure.join("\n"))?;
+    }
+
+    if !failed_objects.is_empty() {
+        io::write_bytes_atomic(
+            output.join("failed_objects.tsv"),
+            failures::failed_objects(failed_objects),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Report the embeddings of a neural run without loading the model or creating the output
+///
+/// # Arguments
+///
+/// * `command` - Name of the command (e.g. neural::mask)
+/// * `output` - Output directory or file
+/// * `per_image_format` - Format of per-image output tables, if written
+/// * `float_precision` - Digits after the decimal point of floats in text tables
+/// * `model` - Name of the model
+/// * `images` - Images returned by `dryrun::scan`
+fn dry_run(
+    command: &str,
+    output: &str,
+    per_image_format: Option<&str>,
+    float_precision: Option<usize>,
+    model: &str,
+    images: Vec<DryRunImage>,
+) -> Result<NeuralSummary, ThymeError> {
+    let output = PathBuf::from(output);
+
+    let format = per_image_format
+        .map(|format| format.to_string())
+        .unwrap_or_else(|| dryrun::output_format(&output));
+
+    let report = DryRun::new(command, &output, images).model(model);
+    let columns = catalog::neural_columns(report.embedding_dim.unwrap_or_default()).len();
+
+    let report = report.table(columns, &format, float_precision);
+    let path = report.finish()?;
+
+    Ok(NeuralSummary {
+        images: report.pairs - report.failures,
+        failures: report.failures,
+        skipped: 0,
+        objects: report.objects,
+        failed_objects: 0,
+        recovered: 0,
+        output,
+        embeddings: vec![],
+        dry_run: Some(path),
+    })
+}
+
+/// Check that the channels of an image of shape (height, width, channels) fit the model input
+///
+/// # Arguments
+///
+/// * `path` - Path to the image
+/// * `shape` - Shape of the image as (height, width, channels)
+/// * `channel_map` - Channel map applied before model preprocessing
+fn check_shape(path: &Path, shape: [u32; 3], channel_map: &ChannelMap) -> Result<(), ThymeError> {
+    let [height, width, channels] = shape;
+
+    channel_map.check_input(channels).map_err(|_| {
+        let convert = match channel_map {
+            ChannelMap::Auto => {
+                ". Use --channel-map gray-average or channel indices (e.g. 0,1,2) to convert other images"
+            }
+            _ => "",
+        };
+
+        ThymeError::ChannelMapError(format!(
+            "{} has shape ({}, {}, {}) but channel map {} accepts {} images{}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            height,
+            width,
+            channels,
+            channel_map,
+            channel_map.accepted_shapes(),
+            convert
+        ))
+    })
+}
+
+/// Check the image headers of every pair before the model is loaded
+///
+/// Only the header of each image is read, so images with channels that do
+/// not fit the model input are reported without decoding their pixels or
+/// waiting for the model to load. Images whose header cannot be read are
+/// kept and checked once decoded (see `open_image`). Returns the pairs that
+/// fit the model input and an object error row for every other pair.
+///
+/// # Arguments
+///
+/// * `pairs` - Image and segment pairs
+/// * `channel_map` - Channel map applied before model preprocessing
+/// * `command` - Name of the command (e.g. neural::mask)
+fn check_images(
+    pairs: Vec<(String, PathBuf, PathBuf)>,
+    channel_map: &ChannelMap,
+    command: &str,
+) -> (Vec<(String, PathBuf, PathBuf)>, Vec<String>) {
+    let checked: Vec<_> = pairs
+        .into_par_iter()
+        .map(|pair| {
+            let checked = io::probe_image(&pair.1).map_or(Ok(()), |info| {
+                check_shape(
+                    &pair.1,
+                    [info.height, info.width, info.channels],
+                    channel_map,
+                )
+            });
+
+            (pair, checked)
+        })
+        .collect();
+
+    let mut compatible = Vec::with_capacity(checked.len());
+    let mut failure = vec![];
+
+    for (pair, checked) in checked {
+        match checked {
+            Ok(()) => compatible.push(pair),
+            Err(err) => {
+                eprintln!("[thyme::{}] ERROR: {} {}", command, pair.0, err);
+                failure.push(format!("{}\t{}", pair.0, err));
+            }
+        }
+    }
+
+    (compatible, failure)
+}
+
+/// Decode an image and check that its channels fit the model input
+///
+/// The header is checked before decoding where it can be read, so that
+/// incompatible images are never decoded, cropped, or embedded.
+///
+/// # Arguments
+///
+/// * `path` - Path to the image
+/// * `channel_map` - Channel map applied before model preprocessing
+fn open_image(path: &Path, channel_map: &ChannelMap) -> Result<ThymeImage, ThymeError> {
+    if let Ok(info) = io::probe_image(path) {
+        check_shape(path, [info.height, info.width, info.channels], channel_map)?;
+    }
+
+    let image = ThymeImage::open(path)?;
+    let (height, width, channels) = image.shape();
+    check_shape(path, [height, width, channels], channel_map)?;
+
+    Ok(image)
+}
+
+/// Decoded image of a pair or the reason it was skipped by an image gate
+enum Loaded {
+    /// Image decoded at full resolution
+    Image(ThymeImage),
+    /// Clause of the gate expression matched by the image and its image-level metrics
+    Skipped(String, ImageSummary),
+}
+
+/// Decode an image unless its image-level metrics match an image gate
+///
+/// Images are gated before any object is cropped or embedded. Pyramidal
+/// images are gated on their smallest level, with the foreground resampled
+/// to that level, so skipped images are never decoded at full resolution.
+/// Other images are decoded once and gated on the decoded image.
+///
+/// # Arguments
+///
+/// * `path` - Path to the image
+/// * `channel_map` - Channel map applied before model preprocessing
+/// * `gate` - Image gate, if images are gated
+/// * `foreground` - Union of the objects of the image given its width and height
+fn open_gated(
+    path: &Path,
+    channel_map: &ChannelMap,
+    gate: Option<&filter::ImageGate>,
+    foreground: impl FnOnce(u32, u32) -> Result<Vec<u32>, ThymeError>,
+) -> Result<Loaded, ThymeError> {
+    let Some(gate) = gate else {
+        return open_image(path, channel_map).map(Loaded::Image);
+    };
+
+    let levels = io::probe_levels(path).unwrap_or_default();
+
+    if let [full, .., _] = levels.as_slice() {
+        let reduced = ThymeImage::open_level(path, levels.len() - 1)?;
+        let foreground = foreground(full.width, full.height)?;
+
+        if foreground.len() != full.width as usize * full.height as usize {
+            return Err(ThymeError::OtherError(
+                "Foreground and image are not the same size".to_string(),
+            ));
+        }
+
+        let foreground = resample_foreground(
+            &foreground,
+            [full.width, full.height],
+            [reduced.width(), reduced.height()],
+        );
+
+        let summary = ImageSummary::new(&reduced, &foreground)?;
+
+        return match gate.check(&summary) {
+            Some(reason) => Ok(Loaded::Skipped(reason, summary)),
+            None => open_image(path, channel_map).map(Loaded::Image),
+        };
+    }
+
+    let image = open_image(path, channel_map)?;
+    let summary = ImageSummary::new(&image, &foreground(image.width(), image.height())?)?;
+
+    Ok(match gate.check(&summary) {
+        Some(reason) => Loaded::Skipped(reason, summary),
+        None => Loaded::Image(image),
+    })
+}
+
+/// Resample a row-major foreground to another size by nearest neighbour
+///
+/// # Arguments
+///
+/// * `foreground` - Row-major foreground pixels
+/// * `from` - Width and height of the foreground
+/// * `to` - Width and height of the resampled foreground
+fn resample_foreground(foreground: &[u32], from: [u32; 2], to: [u32; 2]) -> Vec<u32> {
+    let [from_w, from_h] = from.map(|v| v as usize);
+    let [to_w, to_h] = to.map(|v| v as usize);
+
+    let mut resampled = Vec::with_capacity(to_w * to_h);
+
+    for y in 0..to_h {
+        let src_y = (y * from_h / to_h.max(1)).min(from_h.saturating_sub(1));
+
+        for x in 0..to_w {
+            let src_x = (x * from_w / to_w.max(1)).min(from_w.saturating_sub(1));
+            resampled.push(foreground[src_y * from_w + src_x]);
+        }
+    }
+
+    resampled
+}
+
+/// Foreground of the union of bounding boxes given in pixel coordinates
+///
+/// # Arguments
+///
+/// * `boxes` - Bounding boxes as (min x, min y, max x, max y)
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+fn boxes_foreground(boxes: &[[f32; 4]], width: u32, height: u32) -> Vec<u32> {
+    let mut foreground = vec![0u32; width as usize * height as usize];
+
+    for xyxy in boxes {
+        let clip = |v: f32, max: u32| (v.max(0.0) as u32).min(max);
+        let (min_x, max_x) = (clip(xyxy[0], width), clip(xyxy[2].ceil(), width));
+        let (min_y, max_y) = (clip(xyxy[1], height), clip(xyxy[3].ceil(), height));
+
+        if min_x >= max_x {
+            continue;
+        }
+
+        for y in min_y..max_y {
+            let row = y as usize * width as usize;
+            foreground[row + min_x as usize..row + max_x as usize].fill(1);
+        }
+    }
+
+    foreground
+}
+
+/// Embed the padded crop of a single object
+///
+/// Embeddings are read from and added to the cache of the image if one is
+/// open. Errors and panics fail the object at the crop or embed stage
+/// instead of failing its image.
+///
+/// # Arguments
+///
+/// * `image` - Image the object is cropped from
+/// * `crop` - Padded crop (x, y, w, h) of the object
+/// * `model` - Model embedding the crop
+/// * `image_cache` - Cached embeddings of the image
+fn embed_object<M: Embed>(
+    image: &ThymeImage,
+    crop: [u32; 4],
+    model: &FallbackEmbedder<M>,
+    image_cache: Option<&mut cache::ImageCache>,
+) -> Result<(Vec<f32>, Fallback), ObjectError> {
+    failures::catch(|stage| {
+        let mut fallback = Fallback::None;
+
+        let mut embed = || -> Result<Vec<f32>, ThymeError> {
+            let [x, y, w, h] = crop;
+            let crop = image.crop(x, y, w, h)?;
+
+            *stage = Stage::Embed;
+            let (embedding, used) = model
+                .embed(&crop)
+                .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+            fallback = used;
+            Ok(embedding)
+        };
+
+        let embedding = match image_cache {
+            Some(image_cache) => image_cache.get_or_embed(crop, embed)?,
+            None => embed()?,
+        };
+
+        Ok((embedding, fallback))
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_check_images() {
+        let directory = std::env::temp_dir().join("thyme_test_neural_check_images");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 20,
+            height: 20,
+            objects: 1,
+            radius: [3.0, 4.0],
+            ..Default::default()
+        }
+        .write(2, &directory)
+        .unwrap();
+
+        io::write_numpy(
+            directory.join("synth_0002_image.npy"),
+            vec![0u8; 8 * 6 * 6],
+            vec![8, 6, 6],
+        )
+        .unwrap();
+
+        let pairs: Vec<(String, PathBuf, PathBuf)> = ["synth_0000", "synth_0001", "synth_0002"]
+            .iter()
+            .zip(["png", "png", "npy"])
+            .map(|(id, extension)| {
+                (
+                    id.to_string(),
+                    directory.join(format!("{}_image.{}", id, extension)),
+                    directory.join(format!("{}_mask.png", id)),
+                )
+            })
+            .collect();
+
+        // Incompatible images are reported before they are decoded and the others are kept
+        let (compatible, failure) = check_images(pairs.clone(), &ChannelMap::Auto, "neural::mask");
+        assert_eq!(compatible, pairs[..2]);
+        assert_eq!(failure.len(), 1);
+        assert!(failure[0].starts_with("synth_0002\t[thyme::ChannelMapError]"));
+        assert!(failure[0].contains("synth_0002_image.npy has shape (8, 6, 6)"));
+
+        let err = open_image(&pairs[2].1, &ChannelMap::Auto).unwrap_err();
+        assert!(matches!(err, ThymeError::ChannelMapError(_)));
+
+        // Channel maps that convert any number of channels accept every image
+        let (compatible, failure) = check_images(pairs.clone(), &ChannelMap::GrayAverage, "");
+        assert_eq!(compatible, pairs);
+        assert!(failure.is_empty());
+        assert!(open_image(&pairs[2].1, &ChannelMap::GrayAverage).is_ok());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_open_gated() {
+        let directory = std::env::temp_dir().join("thyme_test_neural_open_gated");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 20,
+            height: 20,
+            objects: 1,
+            radius: [3.0, 4.0],
+            ..Default::default()
+        }
+        .write(1, &directory)
+        .unwrap();
+
+        let image = directory.join("synth_0000_image.png");
+        let empty = |width: u32, height: u32| Ok(vec![0u32; (width * height) as usize]);
+
+        // Images are decoded without a gate and kept if the gate does not match
+        let loaded = open_gated(&image, &ChannelMap::Auto, None, empty).unwrap();
+        assert!(matches!(loaded, Loaded::Image(_)));
+
+        let gate = filter::ImageGate::new(Some("foreground_fraction > 0.5")).unwrap();
+        let loaded = open_gated(&image, &ChannelMap::Auto, gate.as_ref(), empty).unwrap();
+        assert!(matches!(loaded, Loaded::Image(_)));
+
+        // Images without objects are skipped before their objects are embedded
+        let gate = filter::ImageGate::new(Some("foreground_fraction < 0.001")).unwrap();
+        match open_gated(&image, &ChannelMap::Auto, gate.as_ref(), empty).unwrap() {
+            Loaded::Skipped(reason, summary) => {
+                assert!(reason.starts_with("foreground_fraction"));
+                assert_eq!(summary.foreground_fraction, 0.0);
+            }
+            Loaded::Image(_) => panic!("Expected the image to be skipped"),
+        }
+
+        let small = |_: u32, _: u32| Ok(vec![0u32; 4]);
+        assert!(open_gated(&image, &ChannelMap::Auto, gate.as_ref(), small).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_gate_foreground() {
+        let foreground = boxes_foreground(&[[1.0, 0.0, 3.0, 2.0], [-2.0, 3.0, 0.5, 9.0]], 4, 4);
+        assert_eq!(
+            foreground,
+            vec![0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0]
+        );
+
+        let resampled = resample_foreground(&foreground, [4, 4], [2, 2]);
+        assert_eq!(resampled, vec![0, 1, 0, 0]);
     }
 }
@@ -5,26 +5,50 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
-use candle_core::{Device, utils::cuda_is_available, utils::metal_is_available};
+use candle_core::Device;
 use clap::Args;
-use kdam::TqdmParallelIterator;
 use polars::prelude::*;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
+use thyme_config::NeuralPolygonsConfig;
 use thyme_core::constant;
+use thyme_core::cv::points::draw_points_mut;
 use thyme_core::error::ThymeError;
 use thyme_core::im;
 use thyme_core::io;
 use thyme_core::ut;
-use thyme_data::data::Weights;
+use thyme_neural::fallback::{Embed, Fallback, FallbackEmbedder};
 use thyme_neural::nn::Models;
 
-#[derive(Debug, Args)]
+use super::NeuralSummary;
+use super::cache::EmbeddingCache;
+use super::table;
+use crate::dryrun;
+use crate::failures::FailedObjects;
+use crate::filter::{
+    self, BorderPolicy, CropRect, DropCounts, DropReason, ObjectFilter, crop_rect,
+};
+use crate::output::{MetadataJoin, OutputMode, PerImageOutput, npz_options, prepare_output};
+use crate::prefetch::Prefetch;
+use crate::provenance;
+use crate::remote::RemoteInputs;
+use crate::status::Progress;
+
+#[derive(Debug, Args, Serialize)]
 pub struct NeuralPolygonsArgs {
-    #[arg(short = 'i', long, help = "Image directory.", required = true)]
+    #[arg(
+        short = 'i',
+        long,
+        help = "Image directory or s3:// or https:// url.",
+        required = true
+    )]
     pub images: Option<String>,
 
-    #[arg(short = 's', long, help = "Polygons directory.")]
+    #[arg(
+        short = 's',
+        long,
+        help = "Polygons directory or s3:// or https:// url."
+    )]
     pub polygons: Option<String>,
 
     #[arg(long, help = "Device (cpu, cuda, metal).", default_value = "cpu")]
@@ -33,7 +57,7 @@ pub struct NeuralPolygonsArgs {
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq, .npy, .npz).",
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather, .npy, .npz).",
         required = true
     )]
     pub output: Option<String>,
@@ -41,6 +65,19 @@ pub struct NeuralPolygonsArgs {
     #[arg(short = 'v', long, help = "Verbose output.")]
     pub verbose: bool,
 
+    #[arg(
+        long,
+        help = "Show a live status panel with progress, throughput, workers, recent errors, and memory usage instead of the progress bar. Logs status lines instead if stdout is not a terminal."
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Format of the status lines logged instead of the status panel (plain, json). Json status lines are also logged without --tui.",
+        default_value = "plain"
+    )]
+    pub log_format: Option<String>,
+
     #[arg(short = 'd', long, help = "Exclude objects touching edge of image.")]
     pub drop_borders: bool,
 
@@ -52,6 +89,13 @@ pub struct NeuralPolygonsArgs {
     )]
     pub model: Option<String>,
 
+    #[arg(
+        long,
+        help = "Channel map applied before model preprocessing: auto, gray-average, or input channel indices for each RGB channel (e.g. 0,0,0 or 1,2,0).",
+        default_value = "auto"
+    )]
+    pub channel_map: Option<String>,
+
     #[arg(
         short = 'p',
         long,
@@ -60,6 +104,13 @@ pub struct NeuralPolygonsArgs {
     )]
     pub pad: Option<u32>,
 
+    #[arg(
+        long,
+        help = "Maximum edge length that object crops are downscaled to if the device runs out of memory.",
+        default_value = "1024"
+    )]
+    pub max_embed_edge: Option<u32>,
+
     #[arg(long, help = "Substring specifying images (e.g. _image).")]
     pub image_substring: Option<String>,
 
@@ -68,128 +119,388 @@ pub struct NeuralPolygonsArgs {
 
     #[arg(
         long,
-        help = "Exclude objects smaller than a minimum size.",
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting polygons by their path relative to their directory.",
+        conflicts_with_all = ["polygon_substring", "polygon_regex"]
+    )]
+    pub polygon_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting polygons by their path relative to their directory.",
+        conflicts_with = "polygon_substring"
+    )]
+    pub polygon_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Coordinate system of the polygon vertices: pixels or normalized (fractions of the image width and height).",
+        default_value = "pixels"
+    )]
+    pub segment_coords: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with an unpadded bounding box width or height smaller than a minimum size (pixels).",
         default_value = "1"
     )]
     pub min_size: Option<u32>,
 
+    #[arg(
+        long,
+        help = "Exclude objects with a polygon area smaller than a minimum area (pixels)."
+    )]
+    pub min_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with a polygon area larger than a maximum area (pixels)."
+    )]
+    pub max_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Skip images before embedding their objects if an expression of image metrics is true (e.g. 'foreground_fraction<0.001 || focus_score<30'). Conditions compare foreground_fraction, focus_score, background_mean, or background_median to a number and are joined by && and ||. Pyramidal images are gated on their smallest level. Skipped images are written to skipped_images.tsv."
+    )]
+    pub skip_image_if: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude self-intersecting polygons that could not be repaired instead of measuring them as is."
+    )]
+    pub skip_invalid_polygons: bool,
+
+    #[arg(
+        long,
+        help = "Metadata table (.csv, .txt, .tsv, .pq, .arrow, .feather) joined to every output row (e.g. a platemap)."
+    )]
+    pub metadata_table: Option<String>,
+
+    #[arg(
+        long,
+        help = "Name of the join key column in the metadata table (e.g. well)."
+    )]
+    pub metadata_join: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex extracting the join key from image file names using the first capture group or full match (e.g. '_([A-P][0-9]{2})_'). Defaults to the image file name."
+    )]
+    pub well_from_filename: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exit with an error instead of writing nulls when an image has no matching metadata."
+    )]
+    pub strict_join: bool,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write .npz embeddings in the version 1 layout (image, id, centroid, embedding) without format_version and attrs members."
+    )]
+    pub legacy_npz: bool,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Directory caching object embeddings by image content, crop, model, and channel map so that re-runs skip unchanged objects."
+    )]
+    pub cache_dir: Option<String>,
+
+    #[arg(
+        long,
+        help = "Read embeddings from the cache without adding new entries (e.g. a shared cluster cache).",
+        requires = "cache_dir"
+    )]
+    pub cache_readonly: bool,
+
+    #[arg(
+        long,
+        help = "Write a separate embeddings/{image} table for each image as soon as it is embedded. Requires an output directory."
+    )]
+    pub per_image_output: bool,
+
+    #[arg(
+        long,
+        help = "Format of per-image output tables (csv, txt, tsv, pq, arrow, feather).",
+        default_value = "csv"
+    )]
+    pub per_image_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip images whose per-image output table already exists.",
+        requires = "per_image_output"
+    )]
+    pub resume: bool,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (arrays, tables, reports, and provenance sidecars) from the output location before embedding. Without it, output locations holding a previous run are rejected.",
+        conflicts_with = "resume"
+    )]
+    pub overwrite: bool,
+
+    #[arg(
+        long,
+        help = "Number of decoded images queued ahead of the compute threads. If 0, images are decoded by the compute threads.",
+        default_value = "0"
+    )]
+    pub prefetch: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Number of threads decoding images ahead of the compute threads if --prefetch is set.",
+        default_value = "2"
+    )]
+    pub io_threads: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Directory caching objects fetched from s3:// or https:// inputs. Cached objects are re-used by later runs. If not set, objects are fetched into a temporary directory and removed once their image is embedded."
+    )]
+    pub remote_cache: Option<String>,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Report the pairs, object counts, embedding dimension, weights, and estimated output size as a table and in dry_run.json next to the output, without loading the model or creating the output."
+    )]
+    pub dry_run: bool,
 }
 
 pub fn neural_image_polygons(args: &NeuralPolygonsArgs) {
-    let device = args.device.to_owned().unwrap_or("cpu".to_string());
-
-    if !["cpu", "metal", "cuda"].iter().any(|d| d == &device) {
-        eprintln!(
-            "[thyme::neural::polygons] ERROR: Invalid device. Must be one of: cpu, metal, cuda."
-        );
+    if let Err(err) = run_neural_polygons(&NeuralPolygonsConfig::from(args)) {
+        eprintln!("[thyme::neural::polygons] ERROR: {}", err);
         std::process::exit(1);
     }
+}
 
-    if device == "cuda" && !cuda_is_available() {
-        println!(
-            "[thyme::neural::polygons] Device 'cuda' specified but no cuda device was detected."
-        );
-        std::process::exit(1);
+impl From<&NeuralPolygonsArgs> for NeuralPolygonsConfig {
+    fn from(args: &NeuralPolygonsArgs) -> Self {
+        let defaults = NeuralPolygonsConfig::default();
+
+        NeuralPolygonsConfig {
+            images: args.images.to_owned().unwrap_or_default(),
+            polygons: args.polygons.to_owned(),
+            device: args.device.to_owned().unwrap_or(defaults.device),
+            output: args.output.to_owned().unwrap_or_default(),
+            verbose: args.verbose,
+            tui: args.tui,
+            log_format: args.log_format.to_owned().unwrap_or(defaults.log_format),
+            drop_borders: args.drop_borders,
+            model: args.model.to_owned().unwrap_or(defaults.model),
+            channel_map: args.channel_map.to_owned().unwrap_or(defaults.channel_map),
+            pad: args.pad.unwrap_or(defaults.pad),
+            max_embed_edge: args.max_embed_edge.unwrap_or(defaults.max_embed_edge),
+            image_substring: args.image_substring.to_owned(),
+            polygon_substring: args.polygon_substring.to_owned(),
+            image_glob: args.image_glob.to_owned(),
+            image_regex: args.image_regex.to_owned(),
+            polygon_glob: args.polygon_glob.to_owned(),
+            polygon_regex: args.polygon_regex.to_owned(),
+            segment_coords: args
+                .segment_coords
+                .to_owned()
+                .unwrap_or(defaults.segment_coords),
+            min_size: args.min_size.unwrap_or(defaults.min_size),
+            min_area: args.min_area,
+            max_area: args.max_area,
+            skip_image_if: args.skip_image_if.to_owned(),
+            skip_invalid_polygons: args.skip_invalid_polygons,
+            metadata_table: args.metadata_table.to_owned(),
+            metadata_join: args.metadata_join.to_owned(),
+            well_from_filename: args.well_from_filename.to_owned(),
+            strict_join: args.strict_join,
+            ipc_compression: args
+                .ipc_compression
+                .to_owned()
+                .unwrap_or(defaults.ipc_compression),
+            float_precision: args.float_precision,
+            float_format: args
+                .float_format
+                .to_owned()
+                .unwrap_or(defaults.float_format),
+            legacy_npz: args.legacy_npz,
+            provenance: args.provenance,
+            hash_inputs: args.hash_inputs,
+            cache_dir: args.cache_dir.to_owned(),
+            cache_readonly: args.cache_readonly,
+            per_image_output: args.per_image_output,
+            per_image_format: args
+                .per_image_format
+                .to_owned()
+                .unwrap_or(defaults.per_image_format),
+            resume: args.resume,
+            overwrite: args.overwrite,
+            prefetch: args.prefetch.unwrap_or(defaults.prefetch),
+            io_threads: args.io_threads.unwrap_or(defaults.io_threads),
+            remote_cache: args.remote_cache.to_owned(),
+            threads: args.threads,
+            dry_run: args.dry_run,
+        }
     }
+}
 
-    if device == "metal" && !metal_is_available() {
-        println!(
-            "[thyme::neural::polygons] Device 'metal' specified but no metal device was detected."
-        );
-        std::process::exit(1);
-    }
+/// Embed the objects of image and polygon pairs as configured
+///
+/// Images that fail to embed are reported in `object_errors.tsv` when
+/// writing to an output directory and are counted in the returned summary.
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the run
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_cli::run_neural_polygons;
+/// use thyme_config::NeuralPolygonsConfig;
+///
+/// let config = NeuralPolygonsConfig::new("images/", "embeddings.pq")
+///     .polygons("polygons/")
+///     .skip_invalid_polygons(true);
+///
+/// let summary = run_neural_polygons(&config).unwrap();
+/// println!("{} objects across {} images", summary.objects, summary.images);
+/// ```
+pub fn run_neural_polygons(config: &NeuralPolygonsConfig) -> Result<NeuralSummary, ThymeError> {
+    config.validate()?;
+
+    let (threads, device) = super::select_device(&config.device, config.threads, config.verbose)?;
+
+    ut::threads::install(threads, || run(config, device))
+}
 
-    let (threads, device) = if device == "cuda" && cuda_is_available() {
-        ut::track::progress_log("Cuda device detected.", args.verbose);
-        (1, Device::new_cuda(0).unwrap())
-    } else if device == "metal" && metal_is_available() {
-        ut::track::progress_log("Metal device detected.", args.verbose);
-        (1, Device::new_metal(0).unwrap())
-    } else {
-        (args.threads.to_owned().unwrap(), Device::Cpu)
-    };
+/// Run the command on the thread pool selected by `run_neural_polygons`
+fn run(config: &NeuralPolygonsConfig, device: Device) -> Result<NeuralSummary, ThymeError> {
+    let model_name = config.model.to_owned();
+    let pad = config.pad;
 
-    if threads < 1 {
-        println!(
-            "[thyme::neural::polygons] Threads must be set to a positive integer if provided."
-        );
-        std::process::exit(1);
-    }
+    let channel_map = im::ChannelMap::parse(&config.channel_map)?;
+    let segment_coords = im::SegmentCoords::parse(&config.segment_coords)?;
 
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(threads)
-        .build_global()
-        .unwrap();
+    ut::track::progress_log(
+        &format!("Channel map: {}.", channel_map.describe()),
+        config.verbose,
+    );
 
-    let model_name = args
-        .model
-        .to_owned()
-        .unwrap_or("dino_vit_small".to_string());
+    let prefetch = Prefetch::new(Some(config.prefetch), Some(config.io_threads))?;
 
-    let pad = args.pad.unwrap_or(1);
-    let min_size = args.min_size.unwrap_or(1);
+    let filter = ObjectFilter {
+        border_policy: BorderPolicy::from_drop_borders(config.drop_borders),
+        min_size: config.min_size,
+        min_area: config.min_area,
+        max_area: config.max_area,
+        skip_invalid_polygons: config.skip_invalid_polygons,
+    };
 
-    if !Weights::iter().any(|m| m.model_name() == model_name) {
-        // If model name is invalid, select will terminate and show error with list of available models
-        Weights::select(&model_name);
-    }
+    filter.validate().map_err(super::invalid)?;
 
-    if min_size < 1 {
-        eprintln!("[thyme::neural::polygons] ERROR: min_size cannot be less than 1.0.");
-        std::process::exit(1);
-    }
+    let gate = filter::ImageGate::new(config.skip_image_if.as_deref())?;
 
-    let image_path = args.images.to_owned().unwrap();
-    let polygons_path = args.polygons.to_owned().unwrap_or(image_path.clone());
+    let ipc_compression = config.ipc_compression.to_owned();
+    let float_format = io::FloatFormat::new(config.float_precision, &config.float_format)?;
 
-    if image_path == polygons_path && args.image_substring == args.polygon_substring {
-        eprintln!(
-            "[thyme::neural::polygons] ERROR: If images and polygons are located in same path, different image and polygon substrings must be provided."
-        );
-        std::process::exit(1);
-    }
+    let image_path = config.images.to_owned();
+    let polygons_path = config.polygons_path().to_owned();
 
-    let image_files = ut::path::collect_file_paths(
-        &image_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.image_substring.to_owned(),
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    let remote = RemoteInputs::new(config.remote_cache.as_deref());
 
-    let polygon_files = ut::path::collect_file_paths(
-        &polygons_path,
-        constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
-        args.polygon_substring.to_owned(),
+    let image_files = ut::path::FileSelector::new(
+        config.image_substring.as_deref(),
+        config.image_glob.as_deref(),
+        config.image_regex.as_deref(),
     )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    .and_then(|selector| {
+        remote.select_file_paths(
+            &image_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
+
+    let polygon_files = ut::path::FileSelector::new(
+        config.polygon_substring.as_deref(),
+        config.polygon_glob.as_deref(),
+        config.polygon_regex.as_deref(),
+    )
+    .and_then(|selector| {
+        remote.select_file_paths(
+            &polygons_path,
+            constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
 
     if image_files.is_empty() {
-        eprintln!(
-            "[thyme::neural::polygons] ERROR: No image files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No image files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
     if polygon_files.is_empty() {
-        eprintln!(
-            "[thyme::neural::polygons] ERROR: No polygon files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No polygon files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
     let mut pairs = ut::path::collect_file_pairs(
         &image_files,
         &polygon_files,
-        args.image_substring.to_owned(),
-        args.polygon_substring.to_owned(),
+        config.image_substring.to_owned(),
+        config.polygon_substring.to_owned(),
     );
 
     pairs.sort_unstable();
@@ -199,259 +510,557 @@ pub fn neural_image_polygons(args: &NeuralPolygonsArgs) {
             "Detected {} image and polygon pairs.",
             ut::track::thousands_format(pairs.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    let mut output = PathBuf::from(args.output.to_owned().unwrap());
+    if config.dry_run {
+        let images = dryrun::scan(&pairs, pad, |_, _, polygons, info| {
+            dryrun::polygon_boxes(polygons, segment_coords, None, info)
+        });
+
+        return super::dry_run(
+            "neural::polygons",
+            &config.output,
+            Some(config.per_image_format.as_str()).filter(|_| config.per_image_output),
+            config.float_precision,
+            &model_name,
+            images,
+        );
+    }
+
+    let output = PathBuf::from(&config.output);
 
     let extension = output
         .extension()
         .and_then(|s| s.to_str())
         .map(|s| s.to_lowercase());
 
-    if let Some(ext) = &extension {
-        if !["npy", "npz", "csv", "txt", "tsv", "pq"]
-            .iter()
-            .any(|e| e == ext)
-        {
-            eprintln!(
-                "[thyme::neural::polygons] ERROR: Invalid file extension. Must end with one of .npy, .npz, .csv, .txt, .tsv, .pq."
-            );
-            std::process::exit(1);
+    if let Some(parent) = output.parent().filter(|_| extension.is_some()) {
+        if !parent.is_dir() && !parent.as_os_str().is_empty() {
+            return Err(ThymeError::OtherError(
+                "Invalid file path. Parent directory of output file path does not exist"
+                    .to_string(),
+            ));
         }
+    }
+
+    let output = prepare_output(
+        &output,
+        OutputMode::new(config.overwrite, config.resume)?,
+        &config.inputs(),
+        "neural::polygons",
+    )?;
+
+    let metadata = config
+        .metadata_table
+        .as_ref()
+        .map(|path| {
+            let on = config.metadata_join.as_deref().unwrap_or_default();
+            let metadata = io::read_table(path)?;
+
+            // Resolve join keys before processing so that invalid patterns or unmatched
+            // images are reported without waiting for all the objects to be computed
+            let images: Vec<String> = pairs.iter().map(|(_, image, _)| stem(image)).collect();
+
+            let mut df = DataFrame::new(vec![Column::new("image".into(), &images)])
+                .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+            let missing = io::join_metadata(
+                &mut df,
+                "image",
+                &metadata,
+                on,
+                config.well_from_filename.as_deref(),
+            )?;
+
+            if missing > 0 && config.strict_join {
+                return Err(ThymeError::OtherError(format!(
+                    "{} images have no matching row in the metadata table",
+                    missing
+                )));
+            }
 
-        if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if missing > 0 {
                 eprintln!(
-                    "[thyme::neural::polygons] ERROR: Invalid file path. Parent directory of output file path does not exist."
+                    "[thyme::neural::polygons] WARNING: {} images have no matching row in the metadata table. Metadata columns will be null for these images.",
+                    missing
                 );
-                std::process::exit(1);
             }
-        }
-    } else {
-        output = ut::path::create_directory(&output).unwrap_or_else(|_| {
-            eprintln!("[thyme::neural::polygons] ERROR: Could not create directory.");
-            std::process::exit(1);
-        });
+
+            Ok(metadata)
+        })
+        .transpose()?;
+
+    let per_image = config
+        .per_image_output
+        .then(|| {
+            if !output.is_dir() {
+                return Err(ThymeError::OtherError(
+                    "per_image_output requires an output directory".to_string(),
+                ));
+            }
+
+            PerImageOutput::new(
+                &output,
+                "embeddings",
+                &config.per_image_format,
+                &ipc_compression,
+                float_format,
+                config.resume,
+                metadata.clone().map(|table| MetadataJoin {
+                    table,
+                    on: config.metadata_join.to_owned().unwrap_or_default(),
+                    well_from_filename: config.well_from_filename.to_owned(),
+                }),
+            )
+        })
+        .transpose()?;
+
+    if let Some(per_image) = &per_image {
+        let n = pairs.len();
+        pairs.retain(|(_, image, _)| !per_image.skip(&stem(image)));
+
+        ut::track::progress_log(
+            &format!(
+                "Skipping {} images with existing per-image tables.",
+                ut::track::thousands_format(n - pairs.len())
+            ),
+            config.verbose && config.resume,
+        );
     }
 
-    let pb = ut::track::progress_bar(pairs.len(), "Embedding", args.verbose);
+    let (pairs, incompatible) = super::check_images(pairs, &channel_map, "neural::polygons");
+
+    let embedding_dim = super::embedding_dim(&model_name)?;
+
+    let progress = Progress::new(
+        pairs.len(),
+        "Embedding",
+        config.verbose,
+        config.tui,
+        &config.log_format,
+    );
+    let status = progress.status();
 
     let objects: Mutex<usize> = Mutex::new(0);
+    let recovered: Mutex<usize> = Mutex::new(0);
     let success: Mutex<Vec<String>> = Mutex::new(vec![]);
-    let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
+    let failed_objects: Mutex<Vec<String>> = Mutex::new(vec![]);
 
     let name: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
     let item: Mutex<Vec<u32>> = Mutex::new(Vec::with_capacity(pairs.len()));
     let spot: Mutex<Vec<[f32; 2]>> = Mutex::new(Vec::with_capacity(2 * pairs.len()));
     let data: Mutex<Vec<Vec<f32>>> = Mutex::new(Vec::with_capacity(768 * pairs.len()));
+    let fallback: Mutex<Vec<Fallback>> = Mutex::new(Vec::with_capacity(pairs.len()));
+
+    let cache = config
+        .cache_dir
+        .as_ref()
+        .map(|dir| EmbeddingCache::new(dir, config.cache_readonly, &model_name, &channel_map))
+        .transpose()?;
+
+    let mut model = FallbackEmbedder::new(
+        Models::load(&model_name, &device, config.verbose),
+        device.clone(),
+        config.max_embed_edge,
+    );
 
-    let model = Arc::new(Models::load(&model_name, &device, args.verbose));
+    // Objects that run out of memory on the device are embedded on the cpu as a last resort
+    if !device.is_cpu() {
+        let model_name = model_name.clone();
+        model = model.with_cpu_fallback(move || Models::load(&model_name, &Device::Cpu, false));
+    }
 
-    (0..pairs.len())
-        .into_par_iter()
-        .tqdm_with_bar(pb)
-        .for_each(|idx| {
-            let (id, image, polygons) = &pairs[idx];
-            let run = neural(
+    let model = Arc::new(model);
+
+    let runs = prefetch.run(
+        &pairs,
+        progress,
+        |(_, image, polygons)| {
+            remote.fetch(image)?;
+            remote.fetch(polygons)?;
+
+            super::open_gated(image, &channel_map, gate.as_ref(), |width, height| {
+                foreground(polygons, segment_coords, width, height)
+            })
+        },
+        |(id, image, polygons), loaded| {
+            let _staged = remote.staged(&[image, polygons]);
+            status.begin(id);
+
+            let loaded = match loaded {
+                super::Loaded::Image(loaded) => loaded,
+                super::Loaded::Skipped(reason, summary) => return Ok(Some((reason, summary))),
+            };
+
+            let (ids, centroids, embeddings, fallbacks, dropped, failed) = neural(
+                loaded,
                 image,
                 polygons,
+                segment_coords,
                 pad,
-                args.drop_borders,
-                min_size,
+                &filter,
                 &model.clone(),
-                &device,
-            );
-
-            if let Ok((ids, centroids, embeddings)) = run {
-                let n = ids.len();
-
-                success.lock().unwrap().push(format!("{}\t{}", id, n));
-
-                let image = image.file_stem().unwrap().to_string_lossy().to_string();
+                &channel_map,
+                cache.as_ref(),
+            )?;
+
+            let n = ids.len();
+            let n_recovered = fallbacks.iter().filter(|&&f| f != Fallback::None).count();
+            let image = stem(image);
+
+            // Tables are written as soon as an image is embedded so that
+            // embeddings are never accumulated across images
+            if let Some(per_image) = &per_image {
+                let name = vec![image.clone(); n];
+                let mut df = table::dataframe(
+                    &name,
+                    &ids,
+                    &centroids,
+                    &[],
+                    &[],
+                    &fallbacks,
+                    &embeddings,
+                    embedding_dim,
+                );
 
+                per_image.write(&image, &mut df)?;
+            } else {
                 name.lock().unwrap().extend((0..n).map(|_| image.clone()));
                 item.lock().unwrap().extend(ids);
                 spot.lock().unwrap().extend(centroids);
                 data.lock().unwrap().extend(embeddings);
+                fallback.lock().unwrap().extend(fallbacks);
+            }
 
-                *objects.lock().unwrap() += n;
-            } else {
-                failure
-                    .lock()
-                    .unwrap()
-                    .push(format!("{}\t{}", id, run.unwrap_err()));
+            success
+                .lock()
+                .unwrap()
+                .push(filter::object_counts_row(id, n, &dropped));
+
+            failed_objects.lock().unwrap().extend(failed.rows(id));
+
+            *objects.lock().unwrap() += n;
+            status.add_objects(n);
+            *recovered.lock().unwrap() += n_recovered;
+
+            Ok(None)
+        },
+    );
+
+    let mut failure: Vec<String> = incompatible;
+    let mut skipped: Vec<(&str, String, im::ImageSummary)> = vec![];
+
+    for ((id, _, _), run) in pairs.iter().zip(runs) {
+        match run {
+            Ok(Some((reason, summary))) => skipped.push((id, reason, summary)),
+            Ok(None) => {}
+            Err(err) => {
+                if let ThymeError::ChannelMapError(_) = err {
+                    eprintln!("[thyme::neural::polygons] ERROR: {} {}", id, err);
+                }
+
+                failure.push(format!("{}\t{}", id, err));
             }
-        });
+        }
+    }
 
     let objects = objects.into_inner().unwrap();
+    let recovered = recovered.into_inner().unwrap();
     let success = success.into_inner().unwrap();
-    let failure = failure.into_inner().unwrap();
+    let failed_objects = failed_objects.into_inner().unwrap();
 
     let name = name.into_inner().unwrap();
     let item = item.into_inner().unwrap();
     let spot = spot.into_inner().unwrap();
     let data = data.into_inner().unwrap();
+    let fallback = fallback.into_inner().unwrap();
 
-    if args.verbose {
+    if config.verbose {
         println!();
     }
 
+    let skipped: Vec<String> = skipped
+        .iter()
+        .map(|(id, reason, summary)| {
+            ut::track::progress_log(&format!("{}: skipped ({}).", id, reason), config.verbose);
+            filter::skipped_images_row(id, reason, summary)
+        })
+        .collect();
+
     ut::track::progress_log(
         &format!(
             "Complete. {} profiles computed across {} images.",
             ut::track::thousands_format(objects),
             ut::track::thousands_format(success.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    if !success.is_empty() {
-        let n_row = data.len();
-        let n_col = data[0].len();
-
-        if let Some(ext) = &extension {
-            if ["csv", "txt", "tsv", "pq"].iter().any(|e| e == ext) {
-                let mut df = DataFrame::new(vec![
-                    Column::new("image".into(), &name),
-                    Column::new("object".into(), &item),
-                    Column::new(
-                        "centroid_x".into(),
-                        &spot.iter().map(|x| x[0]).collect::<Vec<f32>>(),
-                    ),
-                    Column::new(
-                        "centroid_y".into(),
-                        &spot.iter().map(|x| x[0]).collect::<Vec<f32>>(),
-                    ),
-                ])
-                .unwrap();
-
-                let mut column_data: Vec<Vec<f32>> = vec![Vec::with_capacity(n_row); n_col];
-
-                for row in &data {
-                    for (idx, &descriptor) in row.iter().enumerate() {
-                        column_data[idx].push(descriptor);
-                    }
-                }
+    if !failed_objects.is_empty() {
+        eprintln!(
+            "[thyme::neural::polygons] WARNING: {} objects failed to embed and have no embeddings (see failed_objects.tsv).",
+            ut::track::thousands_format(failed_objects.len())
+        );
+    }
 
-                for (idx, column) in column_data.iter().enumerate() {
-                    df.with_column(Column::new(idx.to_string().into(), column))
-                        .unwrap();
-                }
+    if recovered > 0 {
+        eprintln!(
+            "[thyme::neural::polygons] WARNING: {} objects ran out of device memory and were embedded with a fallback (see the embed_fallback column).",
+            ut::track::thousands_format(recovered)
+        );
+    }
+
+    let mut embeddings = vec![];
+
+    if let Some(per_image) = &per_image {
+        if config.provenance {
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                Some((&model_name, &channel_map)),
+                config.hash_inputs,
+                &[per_image.directory()],
+            )?;
+        }
+    } else if !success.is_empty() {
+        let npz_options = npz_options(
+            &model_name,
+            &channel_map,
+            Some(pad),
+            Some(config.max_embed_edge),
+            config.legacy_npz,
+        );
 
-                io::write_table(&mut df, &output).unwrap_or_else(|_| {
-                    eprintln!(
-                        "[thyme::neural::polygons] ERROR: Failed to write embeddings to a table."
-                    );
-                    std::process::exit(1);
-                });
-            } else if ext == "npy" {
-                io::write_numpy(
-                    &output,
-                    data.iter().flatten().collect(),
-                    vec![n_row as u64, n_col as u64],
-                )
-                .unwrap_or_else(|_| {
-                    eprintln!(
-                        "[thyme::neural::polygons] ERROR: Failed to write embeddings to a npy array."
-                    );
-                    std::process::exit(1);
-                });
-            } else if ext == "npz" {
-                io::write_embeddings_npz(name, item, spot, data, &output).unwrap_or_else(|_| {
-                    eprintln!(
-                        "[thyme::neural::polygons] ERROR: Failed to write embeddings to an npz array."
-                    );
-                    std::process::exit(1);
-                });
+        let target = match extension {
+            Some(_) => output.clone(),
+            None => output.join("embeddings.npz"),
+        };
+
+        let ext = extension.unwrap_or("npz".to_string());
+
+        if constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
+            let mut df = table::dataframe(
+                &name,
+                &item,
+                &spot,
+                &[],
+                &[],
+                &fallback,
+                &data,
+                embedding_dim,
+            );
+
+            if let Some(metadata) = &metadata {
+                io::join_metadata(
+                    &mut df,
+                    "image",
+                    metadata,
+                    config.metadata_join.as_deref().unwrap_or_default(),
+                    config.well_from_filename.as_deref(),
+                )?;
             }
+
+            io::write_table_with_float_format(&mut df, &target, &ipc_compression, &float_format)
+                .map_err(|err| {
+                    ThymeError::OtherError(format!(
+                        "Failed to write embeddings to a table: {}",
+                        err
+                    ))
+                })?;
+        } else if ext == "npy" {
+            let n_row = data.len();
+            let n_col = data[0].len();
+
+            io::write_numpy(
+                &target,
+                data.iter().flatten().collect(),
+                vec![n_row as u64, n_col as u64],
+            )
+            .map_err(|err| {
+                ThymeError::OtherError(format!(
+                    "Failed to write embeddings to a npy array: {}",
+                    err
+                ))
+            })?;
         } else {
-            io::write_embeddings_npz(name, item, spot, data, &output.join("embeddings.npz"))
-                .unwrap_or_else(|_| {
-                    eprintln!(
-                        "[thyme::neural::polygons] ERROR: Failed to write embeddings to an npz array."
-                    );
-                    std::process::exit(1);
-                });
+            io::write_embeddings_npz_with(name, item, spot, data, &target, &npz_options).map_err(
+                |_| {
+                    ThymeError::OtherError("Failed to write embeddings to an npz array".to_string())
+                },
+            )?;
+        }
+
+        embeddings = vec![target];
+
+        if config.provenance {
+            let artifacts: Vec<&Path> = embeddings.iter().map(|path| path.as_path()).collect();
+
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                Some((&model_name, &channel_map)),
+                config.hash_inputs,
+                &artifacts,
+            )?;
         }
     }
 
     if output.is_dir() {
-        if !success.is_empty() {
-            std::fs::write(output.join("object_counts.tsv"), success.join("\n")).unwrap();
-        }
+        super::write_reports(&output, &success, &skipped, &failure, &failed_objects)?;
+    }
 
-        if !failure.is_empty() {
-            std::fs::write(output.join("object_errors.tsv"), failure.join("\n")).unwrap();
-        }
+    crate::output::finish_run(&output)?;
+
+    Ok(NeuralSummary {
+        images: success.len(),
+        failures: failure.len(),
+        skipped: skipped.len(),
+        objects,
+        failed_objects: failed_objects.len(),
+        recovered,
+        output,
+        embeddings,
+        dry_run: None,
+    })
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().to_string()
+}
+
+/// Union of every polygon of an image for gating the image
+///
+/// # Arguments
+///
+/// * `polygons_path` - Path to the polygons of the image
+/// * `segment_coords` - Coordinate system of the polygons
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+fn foreground(
+    polygons_path: &Path,
+    segment_coords: im::SegmentCoords,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u32>, ThymeError> {
+    let mut polygons = im::Polygons::open(polygons_path)?;
+
+    if segment_coords == im::SegmentCoords::Normalized {
+        polygons.denormalize(width, height)?;
     }
+
+    let mut foreground = vec![0u32; width as usize * height as usize];
+
+    for points in polygons.as_points() {
+        draw_points_mut(&mut foreground, width, height, points, 1);
+    }
+
+    Ok(foreground)
 }
 
 #[allow(clippy::too_many_arguments, clippy::type_complexity)]
-fn neural(
+fn neural<M: Embed>(
+    image: im::ThymeImage,
     image_path: &Path,
     polygons_path: &Path,
+    segment_coords: im::SegmentCoords,
     pad: u32,
-    drop_borders: bool,
-    min_size: u32,
-    model: &Models,
-    device: &Device,
-) -> Result<(Vec<u32>, Vec<[f32; 2]>, Vec<Vec<f32>>), ThymeError> {
-    let image = im::ThymeImage::open(image_path)?;
-
-    let polygons = im::Polygons::open(polygons_path)?;
+    filter: &ObjectFilter,
+    model: &FallbackEmbedder<M>,
+    channel_map: &im::ChannelMap,
+    cache: Option<&EmbeddingCache>,
+) -> Result<
+    (
+        Vec<u32>,
+        Vec<[f32; 2]>,
+        Vec<Vec<f32>>,
+        Vec<Fallback>,
+        DropCounts,
+        FailedObjects,
+    ),
+    ThymeError,
+> {
+    let mut image_cache = cache
+        .map(|cache| cache.open_image(image_path))
+        .transpose()?;
+
+    // Auto is left to model preprocessing which applies the same mapping
+    let image = match channel_map {
+        im::ChannelMap::Auto => image,
+        map => image.gather_channels(map)?,
+    };
+
+    let mut polygons = im::Polygons::open(polygons_path)?;
+
+    if segment_coords == im::SegmentCoords::Normalized {
+        polygons.denormalize(image.width(), image.height())?;
+    }
+
     let bounding_boxes = polygons.to_bounding_boxes()?;
+    let areas = polygons.areas();
+    let degenerate = polygons.validation().degenerate.clone();
+
+    if !degenerate.is_empty() && !filter.skip_invalid_polygons {
+        eprintln!(
+            "[thyme::neural::polygons] WARNING: {} has {} self-intersecting polygons that could not be repaired and will be embedded as is.",
+            polygons_path.display(),
+            degenerate.len()
+        );
+    }
 
     let width = image.width();
     let height = image.height();
 
-    let pad_f32 = pad as f32;
-
     let mut ids: Vec<u32> = Vec::with_capacity(bounding_boxes.len());
     let mut centroids: Vec<[f32; 2]> = Vec::with_capacity(2 * bounding_boxes.len());
     let mut results: Vec<Vec<f32>> = Vec::with_capacity(300 * bounding_boxes.len());
+    let mut fallbacks: Vec<Fallback> = Vec::with_capacity(bounding_boxes.len());
+    let mut dropped = DropCounts::default();
+    let mut failed = FailedObjects::default();
 
-    for (idx, [min_x, min_y, max_x, max_y]) in bounding_boxes.as_xyxy().iter().enumerate() {
-        let min_x = min_x - pad_f32;
-        let min_y = min_y - pad_f32;
-        let max_x = max_x + pad_f32;
-        let max_y = max_y + pad_f32;
+    for (idx, xyxy) in bounding_boxes.as_xyxy().iter().enumerate() {
+        if let Err(reason) = filter.check_polygon(degenerate.binary_search(&idx).is_ok()) {
+            dropped.add(reason);
+            continue;
+        }
 
-        if drop_borders
-            && (min_x <= 0.0 || min_y <= 0.0 || max_x >= width as f32 || max_y >= height as f32)
-        {
+        if let Err(reason) = filter.check(xyxy, areas[idx], width, height) {
+            dropped.add(reason);
             continue;
         }
 
-        let min_x_u32 = min_x.max(0.0) as u32;
-        let min_y_u32 = min_y.max(0.0) as u32;
-        let max_x_u32 = max_x.min(width as f32) as u32;
-        let max_y_u32 = max_y.min(height as f32) as u32;
+        let Some(CropRect {
+            x: min_x_u32,
+            y: min_y_u32,
+            width: w,
+            height: h,
+        }) = crop_rect(xyxy, pad, width, height)
+        else {
+            dropped.add(DropReason::OutsideImage);
+            continue;
+        };
 
-        let w = max_x_u32 - min_x_u32;
-        let h = max_y_u32 - min_y_u32;
+        let crop = [min_x_u32, min_y_u32, w, h];
 
-        if w < min_size || h < min_size {
-            continue;
-        }
+        let (embedding, fallback) =
+            match super::embed_object(&image, crop, model, image_cache.as_mut()) {
+                Ok(embedded) => embedded,
+                Err(err) => {
+                    failed.add(idx as u32, *xyxy, err);
+                    continue;
+                }
+            };
 
         ids.push(idx as u32);
-        centroids.push([(max_x + min_x) / 2.0, (max_y + min_y) / 2.0]);
-
-        results.push(
-            model
-                .forward(
-                    &model
-                        .preprocess(&image.crop(min_x_u32, min_y_u32, w, h)?, device)
-                        .unwrap(),
-                )
-                .unwrap()
-                .get(0)
-                .unwrap()
-                .to_vec1()
-                .unwrap(),
-        );
+
+        centroids.push([(xyxy[0] + xyxy[2]) / 2.0, (xyxy[1] + xyxy[3]) / 2.0]);
+        results.push(embedding);
+        fallbacks.push(fallback);
+    }
+
+    if let Some(image_cache) = image_cache {
+        image_cache.flush()?;
     }
 
-    Ok((ids, centroids, results))
+    Ok((ids, centroids, results, fallbacks, dropped, failed))
 }
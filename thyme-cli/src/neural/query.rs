@@ -0,0 +1,406 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use clap::Args;
+use polars::prelude::{Column, DataFrame};
+use rayon::prelude::*;
+
+use thyme_core::error::ThymeError;
+use thyme_core::io::{self, EmbeddingsNpz};
+use thyme_core::ut::track::progress_log;
+
+#[derive(Debug, Args)]
+#[command(about = "Find the objects with embeddings most similar to a query object or vector.")]
+pub struct NeuralQueryArgs {
+    #[arg(
+        short = 'e',
+        long,
+        help = "Embeddings written to a .npz file by a neural command."
+    )]
+    pub embeddings: String,
+
+    #[arg(
+        short = 'q',
+        long,
+        help = "Query object provided as image:object_id (e.g. plate_a01:17). The query object is not returned as a neighbor.",
+        conflicts_with = "query_vector",
+        required_unless_present = "query_vector"
+    )]
+    pub query: Option<String>,
+
+    #[arg(
+        long,
+        help = "A .npy file with a query embedding (1D) or one query embedding per row (2D)."
+    )]
+    pub query_vector: Option<String>,
+
+    #[arg(
+        short = 'k',
+        long,
+        help = "Number of nearest neighbors returned for each query.",
+        default_value = "50"
+    )]
+    pub k: Option<usize>,
+
+    #[arg(
+        short = 'o',
+        long,
+        help = "Output table of neighbors (.csv, .txt, .tsv, .pq, .arrow, .feather)."
+    )]
+    pub output: String,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+}
+
+pub fn neural_query(args: &NeuralQueryArgs) {
+    progress_log(
+        &format!("Loading embeddings from {}", args.embeddings),
+        args.verbose,
+    );
+
+    let npz = io::read_embeddings_npz(&args.embeddings).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    progress_log(
+        &format!(
+            "Loaded {} embeddings (format version {}, model {})",
+            npz.embeddings.len(),
+            npz.format_version,
+            npz.attrs
+                .get("model")
+                .and_then(|model| model.as_str())
+                .unwrap_or("unknown")
+        ),
+        args.verbose,
+    );
+
+    let mut df = query(&npz, args).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    io::write_table(&mut df, &args.output).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    progress_log(
+        &format!("Wrote {} neighbors to {}", df.height(), args.output),
+        args.verbose,
+    );
+}
+
+/// Exact cosine similarity index over object embeddings
+struct CosineIndex {
+    embeddings: Vec<Vec<f32>>,
+}
+
+impl CosineIndex {
+    /// Build an index from embeddings normalized to unit length
+    fn new(embeddings: &[Vec<f32>]) -> Self {
+        Self {
+            embeddings: embeddings.iter().map(|e| normalize(e)).collect(),
+        }
+    }
+
+    /// Find the k most similar embeddings as (row, cosine similarity) pairs
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query embedding
+    /// * `k` - Number of neighbors
+    /// * `exclude` - Row excluded from the neighbors (e.g. the query object)
+    fn search(&self, query: &[f32], k: usize, exclude: Option<usize>) -> Vec<(usize, f32)> {
+        let query = normalize(query);
+
+        let mut similarity: Vec<(usize, f32)> = self
+            .embeddings
+            .par_iter()
+            .enumerate()
+            .filter(|(row, _)| Some(*row) != exclude)
+            .map(|(row, e)| (row, e.iter().zip(&query).map(|(a, b)| a * b).sum()))
+            .collect();
+
+        // Ties are broken by row so that neighbors are deterministic
+        similarity.sort_unstable_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        similarity.truncate(k);
+        similarity
+    }
+}
+
+/// Scale an embedding to unit length, leaving zero embeddings unchanged
+pub(super) fn normalize(embedding: &[f32]) -> Vec<f32> {
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+
+    if norm > 0. {
+        embedding.iter().map(|v| v / norm).collect()
+    } else {
+        embedding.to_vec()
+    }
+}
+
+/// Find the row of an object provided as image:object_id
+fn find_object(npz: &EmbeddingsNpz, query: &str) -> Result<usize, ThymeError> {
+    let (image, id) = query
+        .rsplit_once(':')
+        .and_then(|(image, id)| id.parse::<u32>().ok().map(|id| (image, id)))
+        .ok_or_else(|| {
+            ThymeError::OtherError(format!(
+                "Invalid query '{}'. Must be provided as image:object_id",
+                query
+            ))
+        })?;
+
+    if npz.ids.is_empty() {
+        return Err(ThymeError::OtherError(
+            "Embeddings do not include object identifiers required for an image:object_id query"
+                .to_string(),
+        ));
+    }
+
+    npz.images
+        .iter()
+        .zip(&npz.ids)
+        .position(|(i, &j)| i == image && j == id)
+        .ok_or_else(|| {
+            ThymeError::OtherError(format!(
+                "Query object {} was not found in the embeddings",
+                query
+            ))
+        })
+}
+
+/// Find the nearest neighbors of each query and assemble them into a table
+fn query(npz: &EmbeddingsNpz, args: &NeuralQueryArgs) -> Result<DataFrame, ThymeError> {
+    let k = args.k.unwrap_or(50);
+    let dim = npz.embeddings.first().map_or(0, |e| e.len());
+
+    // Each query is a name, an embedding, and an optional row excluded from neighbors
+    let queries: Vec<(String, Vec<f32>, Option<usize>)> = match (&args.query, &args.query_vector) {
+        (Some(query), _) => {
+            let row = find_object(npz, query)?;
+            vec![(query.clone(), npz.embeddings[row].clone(), Some(row))]
+        }
+        (None, Some(path)) => {
+            let (data, shape) = io::read_numpy_f32(path)?;
+
+            let width = match shape.as_slice() {
+                [width] | [_, width] => *width as usize,
+                _ => 0,
+            };
+
+            if width == 0 || width != dim {
+                return Err(ThymeError::OtherError(format!(
+                    "Query vector shape {:?} does not match the embedding dimension {}",
+                    shape, dim
+                )));
+            }
+
+            data.chunks_exact(width)
+                .enumerate()
+                .map(|(i, q)| (i.to_string(), q.to_vec(), None))
+                .collect()
+        }
+        (None, None) => {
+            return Err(ThymeError::OtherError(
+                "Either query or query_vector must be provided".to_string(),
+            ));
+        }
+    };
+
+    let index = CosineIndex::new(&npz.embeddings);
+
+    let mut query_name = vec![];
+    let mut rank = vec![];
+    let mut image = vec![];
+    let mut object = vec![];
+    let mut centroid_x = vec![];
+    let mut centroid_y = vec![];
+    let mut similarity = vec![];
+
+    for (name, embedding, exclude) in queries {
+        for (i, (row, score)) in index.search(&embedding, k, exclude).into_iter().enumerate() {
+            query_name.push(name.clone());
+            rank.push(i as u32 + 1);
+            image.push(npz.images[row].clone());
+            object.push(npz.ids.get(row).copied());
+            centroid_x.push(npz.centroids.get(row).map(|c| c[0]));
+            centroid_y.push(npz.centroids.get(row).map(|c| c[1]));
+            similarity.push(score);
+        }
+    }
+
+    DataFrame::new(vec![
+        Column::new("query".into(), query_name),
+        Column::new("rank".into(), rank),
+        Column::new("image".into(), image),
+        Column::new("object".into(), object),
+        Column::new("centroid_x".into(), centroid_x),
+        Column::new("centroid_y".into(), centroid_y),
+        Column::new("similarity".into(), similarity),
+    ])
+    .map_err(|err| ThymeError::OtherError(err.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: NeuralQueryArgs,
+    }
+
+    fn parse(args: &[&str]) -> NeuralQueryArgs {
+        Cli::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args
+    }
+
+    /// Embeddings where c:3 is a scaled duplicate of a:1
+    fn embeddings() -> EmbeddingsNpz {
+        EmbeddingsNpz {
+            images: vec!["a", "a", "b", "c", "d"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            ids: vec![1, 2, 1, 3, 1],
+            centroids: vec![[0., 1.], [2., 3.], [4., 5.], [6., 7.], [8., 9.]],
+            embeddings: vec![
+                vec![1., 2., 3.],
+                vec![-3., 0., 0.],
+                vec![1., 2., 2.],
+                vec![2., 4., 6.],
+                vec![0., 0., 0.],
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cosine_index() {
+        let index = CosineIndex::new(&embeddings().embeddings);
+        let neighbors = index.search(&[1., 2., 3.], 3, Some(0));
+
+        assert_eq!(neighbors.len(), 3);
+        assert_eq!(neighbors[0].0, 3);
+        assert!((neighbors[0].1 - 1.).abs() < 1e-6);
+        assert_eq!(neighbors[1].0, 2);
+        assert_eq!(neighbors[2].0, 4);
+        assert_eq!(neighbors[2].1, 0.);
+    }
+
+    #[test]
+    fn test_neural_query() {
+        let directory = std::env::temp_dir().join("thyme_test_neural_query");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let npz = embeddings();
+        let path = directory.join("embeddings.npz");
+        let output = directory.join("neighbors.csv");
+
+        io::write_embeddings_npz(
+            npz.images.clone(),
+            npz.ids.clone(),
+            npz.centroids.clone(),
+            npz.embeddings.clone(),
+            &path,
+        )
+        .unwrap();
+
+        let (path, output) = (path.to_str().unwrap(), output.to_str().unwrap());
+
+        neural_query(&parse(&["-e", path, "-q", "a:1", "-k", "2", "-o", output]));
+
+        let table = std::fs::read_to_string(output).unwrap();
+        let rows: Vec<&str> = table.lines().collect();
+
+        assert_eq!(
+            rows[0],
+            "query,rank,image,object,centroid_x,centroid_y,similarity"
+        );
+        assert!(rows[1].starts_with("a:1,1,c,3,6.0,7.0,"));
+        assert!(rows[2].starts_with("a:1,2,b,1,4.0,5.0,"));
+        assert_eq!(rows.len(), 3);
+
+        // Archives written with the version 1 layout return the same neighbors
+        let legacy = directory.join("legacy.npz");
+
+        io::write_embeddings_npz_with(
+            npz.images.clone(),
+            npz.ids.clone(),
+            npz.centroids.clone(),
+            npz.embeddings.clone(),
+            &legacy,
+            &io::NpzOptions {
+                legacy: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let legacy = legacy.to_str().unwrap();
+        neural_query(&parse(&[
+            "-e", legacy, "-q", "a:1", "-k", "2", "-o", output,
+        ]));
+        assert_eq!(std::fs::read_to_string(output).unwrap(), table);
+
+        // External query vectors are not excluded from their neighbors
+        let vector = directory.join("query.npy");
+        io::write_numpy(&vector, vec![-1f32, 0., 0., 1., 2., 3.], vec![2, 3]).unwrap();
+
+        let df = query(
+            &npz,
+            &parse(&[
+                "-e",
+                path,
+                "--query-vector",
+                vector.to_str().unwrap(),
+                "-k",
+                "2",
+                "-o",
+                output,
+            ]),
+        )
+        .unwrap();
+
+        let queries = df.column("query").unwrap().str().unwrap();
+        let images = df.column("image").unwrap().str().unwrap();
+
+        assert_eq!(df.height(), 4);
+        assert_eq!(queries.get(0), Some("0"));
+        assert_eq!(images.get(0), Some("a"));
+        assert_eq!(queries.get(2), Some("1"));
+        assert_eq!(images.get(2), Some("a"));
+        assert_eq!(images.get(3), Some("c"));
+
+        for invalid in [
+            vec!["-q", "a"],
+            vec!["-q", "a:9"],
+            vec!["--query-vector", path],
+        ] {
+            let mut args = vec!["-e", path, "-o", output];
+            args.extend(&invalid);
+            assert!(query(&npz, &parse(&args)).is_err(), "{:?}", invalid);
+        }
+
+        io::write_numpy(&vector, vec![1f32, 2.], vec![2]).unwrap();
+        let args = parse(&[
+            "-e",
+            path,
+            "--query-vector",
+            vector.to_str().unwrap(),
+            "-o",
+            output,
+        ]);
+        assert!(query(&npz, &args).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
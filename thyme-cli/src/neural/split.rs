@@ -0,0 +1,160 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use thyme_core::error::ThymeError;
+
+/// Name of the output holding objects without a class
+pub const UNCLASSIFIED: &str = "unclassified";
+
+/// File name of the per-class object counts written to output directories
+pub const CLASS_COUNTS_FILE: &str = "class_counts.tsv";
+
+/// Value that objects are split into separate outputs by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// Label of a bounding box or majority class of an object in a class mask
+    Class,
+}
+
+impl SplitBy {
+    /// Parse the value of `--split-output-by`
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value to split by (class)
+    pub fn parse(value: &str) -> Result<Self, ThymeError> {
+        match value {
+            "class" => Ok(SplitBy::Class),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid split_output_by {}. Must be one of: [\"class\"]",
+                value
+            ))),
+        }
+    }
+}
+
+/// Rows of the objects of each class
+///
+/// Each class is added the first time one of its objects is seen, and
+/// objects without a class (or with an empty class) are assigned to the
+/// `unclassified` output.
+///
+/// # Arguments
+///
+/// * `classes` - Class of each object in row order
+pub fn class_rows<'a, I>(classes: I) -> BTreeMap<String, Vec<usize>>
+where
+    I: IntoIterator<Item = Option<&'a str>>,
+{
+    let mut rows: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+
+    for (row, class) in classes.into_iter().enumerate() {
+        let class = match class.map(str::trim) {
+            Some(class) if !class.is_empty() => class,
+            _ => UNCLASSIFIED,
+        };
+
+        rows.entry(class.to_string()).or_default().push(row);
+    }
+
+    rows
+}
+
+/// Output path of the objects of a single class
+///
+/// The class is appended to the file stem (e.g. `embeddings_cell.npz`), with
+/// characters other than letters, digits, `-`, and `.` replaced by `_`.
+///
+/// # Arguments
+///
+/// * `path` - Output path of all objects
+/// * `class` - Class of the objects
+pub fn class_path(path: &Path, class: &str) -> PathBuf {
+    let class: String = class
+        .chars()
+        .map(|c| match c.is_alphanumeric() || c == '-' || c == '.' {
+            true => c,
+            false => '_',
+        })
+        .collect();
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, class, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, class),
+    };
+
+    path.with_file_name(name)
+}
+
+/// Values at the given rows
+///
+/// # Arguments
+///
+/// * `values` - Values of every object
+/// * `rows` - Rows of the selected objects
+pub fn select<T: Clone>(values: &[T], rows: &[usize]) -> Vec<T> {
+    rows.iter().map(|&row| values[row].clone()).collect()
+}
+
+/// Table of the number of objects and the output of each class
+///
+/// # Arguments
+///
+/// * `counts` - Class, number of objects, and output path of each class
+pub fn class_counts(counts: &[(String, usize, PathBuf)]) -> String {
+    let mut table = "class\tobjects\toutput".to_string();
+
+    for (class, n, path) in counts {
+        table.push_str(&format!(
+            "\n{}\t{}\t{}",
+            class,
+            n,
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_class_rows() {
+        let classes = [Some("cell"), None, Some("debris"), Some("cell"), Some(" ")];
+        let rows = class_rows(classes);
+
+        assert_eq!(
+            rows.into_iter().collect::<Vec<_>>(),
+            [
+                ("cell".to_string(), vec![0, 3]),
+                ("debris".to_string(), vec![2]),
+                (UNCLASSIFIED.to_string(), vec![1, 4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class_path() {
+        let path = Path::new("out").join("embeddings.npz");
+
+        assert_eq!(
+            class_path(&path, "cell"),
+            Path::new("out").join("embeddings_cell.npz")
+        );
+        assert_eq!(
+            class_path(&path, "T cell/CD4+"),
+            Path::new("out").join("embeddings_T_cell_CD4_.npz")
+        );
+
+        assert!(SplitBy::parse("class").is_ok());
+        assert!(SplitBy::parse("well").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use polars::prelude::{Column, DataFrame};
+
+use thyme_core::catalog;
+use thyme_neural::fallback::Fallback;
+
+/// Assemble object embeddings into a table with image, object, and centroid columns
+///
+/// A label column follows the object column if any object is labeled, followed
+/// by class_id and class_frac columns if objects were assigned classes, and an
+/// embed_fallback column records the fallback used to embed each object.
+///
+/// # Arguments
+///
+/// * `name` - Image name of each object
+/// * `item` - Object id of each object
+/// * `spot` - Centroid (x, y) of each object
+/// * `label` - Label of each object, empty if objects are unlabeled
+/// * `classes` - Majority class id and fraction of each object, empty without a class mask
+/// * `fallback` - Fallback used to embed each object
+/// * `data` - Embedding of each object
+/// * `embedding_dim` - Number of embedding dimensions produced by the model
+#[allow(clippy::too_many_arguments)]
+pub fn dataframe(
+    name: &[String],
+    item: &[u32],
+    spot: &[[f32; 2]],
+    label: &[Option<String>],
+    classes: &[(u32, f32)],
+    fallback: &[Fallback],
+    data: &[Vec<f32>],
+    embedding_dim: usize,
+) -> DataFrame {
+    let mut df = DataFrame::new(vec![
+        Column::new("image".into(), name),
+        Column::new("object".into(), item),
+        Column::new(
+            "centroid_x".into(),
+            &spot.iter().map(|x| x[0]).collect::<Vec<f32>>(),
+        ),
+        Column::new(
+            "centroid_y".into(),
+            &spot.iter().map(|x| x[1]).collect::<Vec<f32>>(),
+        ),
+        Column::new(
+            "embed_fallback".into(),
+            &fallback.iter().map(|f| f.as_str()).collect::<Vec<&str>>(),
+        ),
+    ])
+    .unwrap();
+
+    // Labels of labeled bounding boxes are kept alongside each object
+    if label.iter().any(|l| l.is_some()) {
+        df.insert_column(2, Column::new("label".into(), label))
+            .unwrap();
+    }
+
+    // Classes of objects in a class mask follow the identifier columns
+    if !classes.is_empty() {
+        let (class_id, class_frac): (Vec<u32>, Vec<f32>) = classes.iter().copied().unzip();
+        let idx = df.get_column_index("centroid_x").unwrap();
+
+        df.insert_column(idx, Column::new("class_id".into(), class_id))
+            .unwrap();
+        df.insert_column(idx + 1, Column::new("class_frac".into(), class_frac))
+            .unwrap();
+    }
+
+    let mut column_data: Vec<Vec<f32>> = vec![Vec::with_capacity(data.len()); embedding_dim];
+
+    for row in data {
+        for (idx, &descriptor) in row.iter().enumerate() {
+            column_data[idx].push(descriptor);
+        }
+    }
+
+    let columns = catalog::embedding_columns(embedding_dim);
+
+    for (column, descriptor) in columns.iter().zip(column_data) {
+        df.with_column(Column::new(column.name.as_str().into(), descriptor))
+            .unwrap();
+    }
+
+    df
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_dataframe() {
+        let name = vec!["a".to_string(); 2];
+        let label = vec![Some("x".to_string()), None];
+        let data = vec![vec![0.1, 0.2, 0.3], vec![0.4, 0.5, 0.6]];
+
+        let fallback = [Fallback::None, Fallback::Downscale];
+
+        let df = dataframe(
+            &name,
+            &[1, 2],
+            &[[1., 2.], [3., 4.]],
+            &label,
+            &[],
+            &fallback,
+            &data,
+            3,
+        );
+
+        assert_eq!(
+            df.get_column_names_str(),
+            [
+                "image",
+                "object",
+                "label",
+                "centroid_x",
+                "centroid_y",
+                "embed_fallback",
+                "0",
+                "1",
+                "2"
+            ]
+        );
+
+        let y = df.column("centroid_y").unwrap().f32().unwrap();
+        assert_eq!(y.get(1), Some(4.));
+
+        let fallback = df.column("embed_fallback").unwrap().str().unwrap();
+        assert_eq!(fallback.get(0), Some("none"));
+        assert_eq!(fallback.get(1), Some("downscale"));
+
+        let df = dataframe(&name[..0], &[], &[], &[], &[], &[], &[], 3);
+        assert_eq!(df.width(), 5 + 3);
+
+        let classes = [(2, 0.75), (0, 0.5)];
+        let df = dataframe(
+            &name,
+            &[1, 2],
+            &[[1., 2.], [3., 4.]],
+            &[],
+            &classes,
+            &[Fallback::None, Fallback::Downscale],
+            &data,
+            3,
+        );
+
+        assert_eq!(
+            df.get_column_names_str()[..5],
+            ["image", "object", "class_id", "class_frac", "centroid_x"]
+        );
+
+        let class_id = df.column("class_id").unwrap().u32().unwrap();
+        assert_eq!(class_id.get(0), Some(2));
+    }
+}
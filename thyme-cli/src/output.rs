@@ -0,0 +1,831 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::{Path, PathBuf};
+
+use polars::prelude::DataFrame;
+use serde::{Deserialize, Serialize};
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im::ChannelMap;
+use thyme_core::io;
+
+/// A metadata table joined to every output table
+#[derive(Debug, Clone)]
+pub struct MetadataJoin {
+    /// Metadata table (e.g. a platemap)
+    pub table: DataFrame,
+    /// Name of the join key column in the metadata table
+    pub on: String,
+    /// Regex extracting the join key from image file names
+    pub well_from_filename: Option<String>,
+}
+
+/// Remove temporary files left in a reused output directory by a crashed run
+///
+/// Every removed file is listed in a single log line.
+///
+/// # Arguments
+///
+/// * `output` - Output directory
+/// * `command` - Command used to prefix the log line (e.g. profile)
+pub fn remove_stale_temporary_files(
+    output: &Path,
+    command: &str,
+) -> Result<Vec<PathBuf>, ThymeError> {
+    let removed = io::remove_temporary_files(output)?;
+
+    if !removed.is_empty() {
+        eprintln!(
+            "[thyme::{}] WARNING: Removed {} stale temporary files from a previous run ({}).",
+            command,
+            removed.len(),
+            removed
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    Ok(removed)
+}
+
+/// Marker written to output directories listing the outputs of a run
+pub const RUN_MARKER: &str = "thyme_run.json";
+
+/// Outputs of a run recorded in the marker of its output directory
+///
+/// A run writes the marker with the entries that already existed in the
+/// output directory before it starts and lists the entries it added once
+/// it completes. Outputs of an interrupted run are the entries added since
+/// the marker was written.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct RunMarker {
+    /// Command that wrote the outputs (e.g. profile::mask)
+    command: String,
+    /// Entries of the output directory that existed before the run
+    existing: Vec<String>,
+    /// Entries written by the run, or none if the run did not complete
+    artifacts: Option<Vec<String>>,
+}
+
+impl RunMarker {
+    /// Read the marker of an output directory, if a run wrote one
+    fn read(output: &Path) -> Result<Option<Self>, ThymeError> {
+        let path = output.join(RUN_MARKER);
+
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let marker = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|marker| serde_json::from_str(&marker).map_err(|err| err.to_string()))
+            .map_err(|err| {
+                ThymeError::OtherError(format!(
+                    "Failed to read run marker {}. {}",
+                    path.display(),
+                    err
+                ))
+            })?;
+
+        Ok(Some(marker))
+    }
+
+    /// Write the marker to an output directory
+    fn write(&self, output: &Path) -> Result<(), ThymeError> {
+        let marker = serde_json::to_string_pretty(self)
+            .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+        io::write_bytes_atomic(output.join(RUN_MARKER), marker)
+    }
+
+    /// Entries of the output directory written by the run
+    fn artifacts(&self, output: &Path) -> Result<Vec<String>, ThymeError> {
+        match &self.artifacts {
+            Some(artifacts) => Ok(artifacts.clone()),
+            None => Ok(entries(output)?
+                .into_iter()
+                .filter(|name| !self.existing.contains(name))
+                .collect()),
+        }
+    }
+}
+
+/// Names of the entries of an output directory other than the run marker, sorted
+fn entries(output: &Path) -> Result<Vec<String>, ThymeError> {
+    let entries = std::fs::read_dir(output)
+        .map_err(|err| ThymeError::DirError(format!("{}. {}", output.display(), err)))?;
+
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name != RUN_MARKER)
+        .collect();
+
+    names.sort();
+
+    Ok(names)
+}
+
+/// Handling of an output location holding the outputs of a previous run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Refuse to write over the outputs of a previous run
+    #[default]
+    Create,
+    /// Remove the outputs of a previous run before writing
+    Overwrite,
+    /// Continue a previous run, keeping its outputs
+    Resume,
+}
+
+impl OutputMode {
+    /// Select the output mode from the overwrite and resume arguments
+    pub fn new(overwrite: bool, resume: bool) -> Result<Self, ThymeError> {
+        match (overwrite, resume) {
+            (true, true) => Err(ThymeError::OtherError(
+                "overwrite cannot be combined with resume".to_string(),
+            )),
+            (true, false) => Ok(OutputMode::Overwrite),
+            (false, true) => Ok(OutputMode::Resume),
+            (false, false) => Ok(OutputMode::Create),
+        }
+    }
+}
+
+/// Outputs of previous runs in an output directory, sorted by path
+///
+/// Only the entries listed in the run marker (see `RUN_MARKER`) that still
+/// exist are reported, so that files a run did not write are never
+/// reported or removed. Directories without a marker hold no outputs.
+///
+/// # Arguments
+///
+/// * `output` - Output directory
+pub fn run_artifacts(output: &Path) -> Result<Vec<PathBuf>, ThymeError> {
+    let Some(marker) = RunMarker::read(output)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut artifacts: Vec<PathBuf> = marker
+        .artifacts(output)?
+        .iter()
+        .map(|name| output.join(name))
+        .filter(|path| path.exists())
+        .collect();
+
+    artifacts.sort();
+
+    Ok(artifacts)
+}
+
+/// Record the outputs written by a completed run in its output directory
+///
+/// Output files are not recorded since the file is the only output.
+///
+/// # Arguments
+///
+/// * `output` - Output file or directory returned by `prepare_output`
+pub fn finish_run(output: &Path) -> Result<(), ThymeError> {
+    if !output.is_dir() {
+        return Ok(());
+    }
+
+    let Some(mut marker) = RunMarker::read(output)? else {
+        return Ok(());
+    };
+
+    marker.artifacts = Some(marker.artifacts(output)?);
+    marker.write(output)
+}
+
+/// Resolve the local inputs of a run, skipping those that do not exist (e.g. remote urls)
+fn resolve_inputs(inputs: &[&str]) -> Vec<PathBuf> {
+    inputs
+        .iter()
+        .filter_map(|input| Path::new(input).canonicalize().ok())
+        .collect()
+}
+
+/// Find an input that is or is inside an output of a previous run
+fn input_within<'a>(artifact: &Path, inputs: &'a [PathBuf]) -> Option<&'a PathBuf> {
+    let artifact = artifact.canonicalize().ok()?;
+    inputs.iter().find(|input| input.starts_with(&artifact))
+}
+
+/// Validate an output location and create it if it is a directory
+///
+/// Output files are rejected if they exist. Output directories are created
+/// if they do not exist and otherwise rejected if they hold the outputs of a
+/// previous run (see `run_artifacts`), so that new and old outputs are never
+/// mixed. With `OutputMode::Overwrite`, the existing output file or the
+/// outputs of the previous run are removed first and listed in a single log
+/// line. Outputs that are or contain an input of the run are never removed:
+/// an output file that is an input is rejected, and outputs of a previous
+/// run in a directory that are inputs are kept and become regular files of
+/// the directory. With
+/// `OutputMode::Resume`, the outputs of the previous run are kept. Stale
+/// temporary files of a crashed run are removed from every existing output
+/// directory whatever the mode. Output directories are marked with
+/// the entries they held before the run, and the run then records its own
+/// outputs with `finish_run`.
+///
+/// # Arguments
+///
+/// * `output` - Output file (with an extension) or directory
+/// * `mode` - Handling of the outputs of a previous run
+/// * `inputs` - Input files and directories of the run, which are never removed
+/// * `command` - Command used to prefix log lines (e.g. profile::mask)
+pub fn prepare_output(
+    output: &Path,
+    mode: OutputMode,
+    inputs: &[&str],
+    command: &str,
+) -> Result<PathBuf, ThymeError> {
+    if output.extension().is_some() {
+        if output.is_dir() && !is_directory_container(output) {
+            return Err(ThymeError::OtherError(format!(
+                "Output file {} is an existing directory",
+                output.display()
+            )));
+        }
+
+        if !output.exists() || mode == OutputMode::Resume {
+            return Ok(output.to_path_buf());
+        }
+
+        if mode == OutputMode::Create {
+            return Err(ThymeError::OtherError(format!(
+                "Output file {} already exists. Use --overwrite to replace it",
+                output.display()
+            )));
+        }
+
+        let mut sidecar = output.as_os_str().to_os_string();
+        sidecar.push(crate::provenance::PROVENANCE_SUFFIX);
+
+        if let Some(input) = input_within(output, &resolve_inputs(inputs)) {
+            return Err(ThymeError::OtherError(format!(
+                "Output file {} is also an input of the run ({}) and cannot be overwritten",
+                output.display(),
+                input.display()
+            )));
+        }
+
+        let removed = remove_artifacts(&[output.to_path_buf(), PathBuf::from(sidecar)])?;
+        log_removed(&removed, command);
+
+        return Ok(output.to_path_buf());
+    }
+
+    prepare_output_directory(output, mode, inputs, command)
+}
+
+/// Check if an output file is written as a directory (e.g. a zarr store)
+fn is_directory_container(output: &Path) -> bool {
+    output
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zarr"))
+}
+
+/// Validate an output directory and create it if it does not exist
+///
+/// See `prepare_output` for the handling of the outputs of a previous run.
+///
+/// # Arguments
+///
+/// * `output` - Output directory
+/// * `mode` - Handling of the outputs of a previous run
+/// * `inputs` - Input files and directories of the run, which are never removed
+/// * `command` - Command used to prefix log lines (e.g. process::mask)
+pub fn prepare_output_directory(
+    output: &Path,
+    mode: OutputMode,
+    inputs: &[&str],
+    command: &str,
+) -> Result<PathBuf, ThymeError> {
+    if !output.exists() {
+        std::fs::create_dir_all(output).map_err(|err| ThymeError::DirError(err.to_string()))?;
+
+        RunMarker {
+            command: command.to_string(),
+            ..Default::default()
+        }
+        .write(output)?;
+
+        return Ok(output.to_path_buf());
+    }
+
+    if !output.is_dir() {
+        return Err(ThymeError::OtherError(format!(
+            "Output directory {} is an existing file",
+            output.display()
+        )));
+    }
+
+    // Temporary files are never complete outputs, whichever way the previous run is handled
+    remove_stale_temporary_files(output, command)?;
+
+    let previous = RunMarker::read(output)?;
+
+    let marker = match mode {
+        OutputMode::Resume => {
+            // Outputs of the resumed run stay outputs of the continued run
+            let artifacts = match previous {
+                Some(previous) => previous.artifacts(output)?,
+                None => Vec::new(),
+            };
+
+            RunMarker {
+                command: command.to_string(),
+                existing: entries(output)?
+                    .into_iter()
+                    .filter(|name| !artifacts.contains(name))
+                    .collect(),
+                artifacts: None,
+            }
+        }
+        OutputMode::Overwrite => {
+            let inputs = resolve_inputs(inputs);
+
+            // Outputs of a previous run that are now read as inputs are kept
+            let (kept, artifacts): (Vec<PathBuf>, Vec<PathBuf>) = run_artifacts(output)?
+                .into_iter()
+                .partition(|artifact| input_within(artifact, &inputs).is_some());
+
+            if !kept.is_empty() {
+                eprintln!(
+                    "[thyme::{}] WARNING: Kept {} outputs of a previous run that are inputs of this run ({}).",
+                    command,
+                    kept.len(),
+                    kept.iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+
+            let removed = remove_artifacts(&artifacts)?;
+            log_removed(&removed, command);
+
+            RunMarker {
+                command: command.to_string(),
+                existing: entries(output)?,
+                artifacts: None,
+            }
+        }
+        OutputMode::Create => {
+            let artifacts = run_artifacts(output)?;
+
+            if !artifacts.is_empty() {
+                return Err(ThymeError::OtherError(format!(
+                    "Output directory {} holds the outputs of a previous run ({}). Use --overwrite to replace them, --resume to continue the run if supported, or a new output directory",
+                    output.display(),
+                    artifacts
+                        .iter()
+                        .filter_map(|path| path.file_name())
+                        .map(|name| name.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            }
+
+            RunMarker {
+                command: command.to_string(),
+                existing: entries(output)?,
+                artifacts: None,
+            }
+        }
+    };
+
+    marker.write(output)?;
+
+    Ok(output.to_path_buf())
+}
+
+/// Remove output files and directories, returning the paths that existed
+fn remove_artifacts(paths: &[PathBuf]) -> Result<Vec<PathBuf>, ThymeError> {
+    let mut removed = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        let result = match path.is_dir() {
+            true => std::fs::remove_dir_all(path),
+            false if path.exists() => std::fs::remove_file(path),
+            false => continue,
+        };
+
+        result.map_err(|err| {
+            ThymeError::OtherError(format!(
+                "Failed to remove previous output {}. {}",
+                path.display(),
+                err
+            ))
+        })?;
+
+        removed.push(path.clone());
+    }
+
+    Ok(removed)
+}
+
+/// Log the outputs of a previous run removed before overwriting them
+fn log_removed(removed: &[PathBuf], command: &str) {
+    if !removed.is_empty() {
+        eprintln!(
+            "[thyme::{}] WARNING: Removed {} outputs of a previous run ({}).",
+            command,
+            removed.len(),
+            removed
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+}
+
+/// Writes a separate output table for each image as soon as it is complete
+///
+/// Tables are written with `io::write_table_with_float_format`, which renames
+/// a temporary file over the table, so that an interrupted run never leaves a
+/// partial table behind. With `resume`, images whose table already exists are
+/// skipped.
+#[derive(Debug, Clone)]
+pub struct PerImageOutput {
+    directory: PathBuf,
+    format: String,
+    ipc_compression: String,
+    float_format: io::FloatFormat,
+    resume: bool,
+    metadata: Option<MetadataJoin>,
+}
+
+impl PerImageOutput {
+    /// Create a directory for per-image tables inside an output directory
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Output directory
+    /// * `name` - Name of the per-image table directory (e.g. descriptors)
+    /// * `format` - Table format (csv, txt, tsv, pq, arrow, feather)
+    /// * `ipc_compression` - Compression for arrow/feather tables
+    /// * `float_format` - Precision and notation of floats in csv/tsv tables
+    /// * `resume` - Skip images whose table already exists
+    /// * `metadata` - Metadata table joined to each table
+    pub fn new(
+        output: &Path,
+        name: &str,
+        format: &str,
+        ipc_compression: &str,
+        float_format: io::FloatFormat,
+        resume: bool,
+        metadata: Option<MetadataJoin>,
+    ) -> Result<Self, ThymeError> {
+        if !constant::SUPPORTED_TABLE_FORMATS.contains(&format) {
+            return Err(ThymeError::OtherError(format!(
+                "Invalid per_image_format {}. Must be one of: {:?}",
+                format,
+                constant::SUPPORTED_TABLE_FORMATS
+            )));
+        }
+
+        let directory = output.join(name);
+
+        std::fs::create_dir_all(&directory).map_err(|err| ThymeError::DirError(err.to_string()))?;
+
+        Ok(Self {
+            directory,
+            format: format.to_string(),
+            ipc_compression: ipc_compression.to_string(),
+            float_format,
+            resume,
+            metadata,
+        })
+    }
+
+    /// Directory containing the per-image tables
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Path of the table written for an image
+    pub fn path(&self, image: &str) -> PathBuf {
+        self.directory.join(format!("{}.{}", image, self.format))
+    }
+
+    /// Check if an image is skipped because a previous run wrote its table
+    pub fn skip(&self, image: &str) -> bool {
+        self.resume && self.path(image).is_file()
+    }
+
+    /// Join metadata to the table of a single image and write it
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Image name used as the table file stem
+    /// * `df` - Table of the objects in the image
+    pub fn write(&self, image: &str, df: &mut DataFrame) -> Result<(), ThymeError> {
+        if let Some(metadata) = &self.metadata {
+            io::join_metadata(
+                df,
+                "image",
+                &metadata.table,
+                &metadata.on,
+                metadata.well_from_filename.as_deref(),
+            )?;
+        }
+
+        io::write_table_with_float_format(
+            df,
+            self.path(image),
+            &self.ipc_compression,
+            &self.float_format,
+        )
+    }
+}
+
+/// Options for writing the embeddings of a neural model to a .npz file
+///
+/// The model name and preprocessing options are recorded in the attrs
+/// member of the archive unless the legacy layout is written.
+///
+/// # Arguments
+///
+/// * `model` - Name of the model
+/// * `channel_map` - Channel map applied before model preprocessing
+/// * `pad` - Padding around objects (if objects are embedded)
+/// * `max_embed_edge` - Maximum edge length of downscaled crops (if objects are embedded)
+/// * `legacy` - Write the version 1 layout
+pub fn npz_options(
+    model: &str,
+    channel_map: &ChannelMap,
+    pad: Option<u32>,
+    max_embed_edge: Option<u32>,
+    legacy: bool,
+) -> io::NpzOptions {
+    let mut attrs = serde_json::Map::new();
+
+    attrs.insert("model".to_string(), model.into());
+    attrs.insert("channel_map".to_string(), channel_map.to_string().into());
+
+    if let Some(pad) = pad {
+        attrs.insert("pad".to_string(), pad.into());
+    }
+
+    if let Some(max_embed_edge) = max_embed_edge {
+        attrs.insert("max_embed_edge".to_string(), max_embed_edge.into());
+    }
+
+    io::NpzOptions {
+        attrs,
+        legacy,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use polars::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_per_image_output() {
+        let output = std::env::temp_dir().join("thyme_test_per_image_output");
+        let _ = std::fs::remove_dir_all(&output);
+
+        assert!(
+            PerImageOutput::new(
+                &output,
+                "descriptors",
+                "npz",
+                "none",
+                Default::default(),
+                false,
+                None
+            )
+            .is_err()
+        );
+
+        let per_image = PerImageOutput::new(
+            &output,
+            "descriptors",
+            "csv",
+            "none",
+            Default::default(),
+            true,
+            None,
+        )
+        .unwrap();
+
+        assert!(!per_image.skip("a"));
+
+        let mut df = DataFrame::new(vec![Column::new("image".into(), &["a", "a"])]).unwrap();
+        per_image.write("a", &mut df).unwrap();
+
+        assert!(per_image.skip("a"));
+        assert_eq!(std::fs::read_dir(per_image.directory()).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_remove_stale_temporary_files() {
+        let output = std::env::temp_dir().join("thyme_test_remove_stale_temporary_files");
+        let _ = std::fs::remove_dir_all(&output);
+
+        let per_image = PerImageOutput::new(
+            &output,
+            "descriptors",
+            "csv",
+            "none",
+            Default::default(),
+            true,
+            None,
+        )
+        .unwrap();
+
+        let mut df = DataFrame::new(vec![Column::new("image".into(), &["a"])]).unwrap();
+        per_image.write("a", &mut df).unwrap();
+
+        let stale = per_image.directory().join(".b.csv.41.0.thyme-tmp");
+        std::fs::write(&stale, "image\nb").unwrap();
+
+        assert_eq!(
+            remove_stale_temporary_files(&output, "profile").unwrap(),
+            [stale.clone()]
+        );
+
+        assert!(!stale.exists());
+        assert!(per_image.skip("a"));
+
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_prepare_output() {
+        let output = std::env::temp_dir().join("thyme_test_prepare_output");
+        let _ = std::fs::remove_dir_all(&output);
+
+        // Missing directories are created and marked
+        assert_eq!(
+            prepare_output(&output, OutputMode::Create, &[], "profile").unwrap(),
+            output
+        );
+
+        assert!(output.join(RUN_MARKER).is_file());
+        assert!(run_artifacts(&output).unwrap().is_empty());
+
+        // Files the run did not write are never outputs, whatever their name,
+        // but temporary files are removed from unmarked directories too
+        std::fs::remove_dir_all(&output).unwrap();
+        std::fs::create_dir_all(output.join("polygons")).unwrap();
+        std::fs::write(output.join("polygons/mine.json"), "[]").unwrap();
+        std::fs::write(output.join("polygons/.b.json.41.0.thyme-tmp"), "").unwrap();
+        std::fs::write(output.join("object_counts.tsv"), "").unwrap();
+
+        assert!(prepare_output(&output, OutputMode::Create, &[], "profile").is_ok());
+        assert!(run_artifacts(&output).unwrap().is_empty());
+        assert!(!output.join("polygons/.b.json.41.0.thyme-tmp").exists());
+
+        let populate = || {
+            std::fs::create_dir_all(output.join("descriptors")).unwrap();
+
+            for name in [
+                "descriptors.csv",
+                "descriptors.csv.provenance.json",
+                "embeddings_cell.npz",
+                "descriptors/a.csv",
+                ".a.csv.41.0.thyme-tmp",
+            ] {
+                std::fs::write(output.join(name), "").unwrap();
+            }
+        };
+
+        // Outputs of an interrupted run are the entries added since it started
+        populate();
+
+        let artifacts = [
+            ".a.csv.41.0.thyme-tmp",
+            "descriptors",
+            "descriptors.csv",
+            "descriptors.csv.provenance.json",
+            "embeddings_cell.npz",
+        ];
+
+        let names = |paths: Vec<PathBuf>| {
+            paths
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(run_artifacts(&output).unwrap()), artifacts);
+
+        // Completed runs list their outputs in the marker
+        finish_run(&output).unwrap();
+        std::fs::write(output.join("notes.txt"), "plate 1").unwrap();
+
+        assert_eq!(names(run_artifacts(&output).unwrap()), artifacts);
+
+        // Previous runs are rejected by default
+        let err = prepare_output(&output, OutputMode::Create, &[], "profile").unwrap_err();
+        assert!(err.to_string().contains("--overwrite"));
+        assert!(output.join("descriptors.csv").exists());
+
+        // Resumed runs keep previous outputs and only remove temporary files
+        prepare_output(&output, OutputMode::Resume, &[], "profile").unwrap();
+        assert!(output.join("descriptors/a.csv").exists());
+        assert!(!output.join(".a.csv.41.0.thyme-tmp").exists());
+
+        finish_run(&output).unwrap();
+        assert_eq!(names(run_artifacts(&output).unwrap()), &artifacts[1..]);
+
+        // Overwritten runs remove the outputs of the previous run and keep other files
+        prepare_output(&output, OutputMode::Overwrite, &[], "profile").unwrap();
+        assert!(run_artifacts(&output).unwrap().is_empty());
+        assert!(!output.join("descriptors.csv").exists());
+        assert!(output.join("notes.txt").exists());
+        assert!(output.join("object_counts.tsv").exists());
+        assert!(output.join("polygons/mine.json").exists());
+
+        // Outputs of a previous run that are inputs of the next run are never removed
+        populate();
+        finish_run(&output).unwrap();
+
+        // Stale temporary files are removed even from kept outputs
+        let stale = output.join("descriptors/.b.csv.41.0.thyme-tmp");
+        std::fs::write(&stale, "").unwrap();
+
+        let input = output.join("descriptors/a.csv");
+        let input = input.to_str().unwrap();
+
+        prepare_output(&output, OutputMode::Overwrite, &[input], "profile").unwrap();
+        assert!(output.join("descriptors/a.csv").exists());
+        assert!(!output.join("descriptors.csv").exists());
+        assert!(!stale.exists());
+
+        finish_run(&output).unwrap();
+        assert!(run_artifacts(&output).unwrap().is_empty());
+
+        // Existing output files are replaced with their provenance sidecar
+        populate();
+        let table = output.join("descriptors.csv");
+
+        assert!(prepare_output(&table, OutputMode::Create, &[], "profile").is_err());
+        assert!(
+            prepare_output(
+                &table,
+                OutputMode::Overwrite,
+                &[table.to_str().unwrap()],
+                "profile"
+            )
+            .is_err()
+        );
+        assert!(table.exists());
+
+        assert!(prepare_output(&table, OutputMode::Overwrite, &[], "profile").is_ok());
+        assert!(!table.exists());
+        assert!(!output.join("descriptors.csv.provenance.json").exists());
+        assert!(output.join("embeddings_cell.npz").exists());
+
+        assert!(OutputMode::new(true, true).is_err());
+        assert_eq!(OutputMode::new(false, false).unwrap(), OutputMode::Create);
+
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_npz_options() {
+        let path = std::env::temp_dir().join("thyme_test_npz_options.npz");
+        let channel_map = ChannelMap::parse("0,0,0").unwrap();
+
+        let options = npz_options("dino_vit_small", &channel_map, Some(2), None, false);
+
+        io::write_embeddings_npz_with(
+            vec!["a".to_string()],
+            vec![1],
+            vec![[2., 3.]],
+            vec![vec![0.5]],
+            &path,
+            &options,
+        )
+        .unwrap();
+
+        let npz = io::read_embeddings_npz(&path).unwrap();
+
+        assert_eq!(npz.attrs["model"], "dino_vit_small");
+        assert_eq!(npz.attrs["channel_map"], channel_map.to_string());
+        assert_eq!(npz.attrs["pad"], 2);
+        assert!(!npz.attrs.contains_key("max_embed_edge"));
+
+        let options = npz_options("dino_vit_small", &channel_map, Some(2), None, true);
+        assert!(options.legacy);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::{BoundingBoxes, Polygons, ThymeImage, ThymeMask, ThymeMaskView, ThymeView};
+
+use crate::failures::{self, ObjectError, Stage};
+use crate::filter::{CropRect, DropCounts, DropReason, ObjectFilter, crop_rect};
+
+/// Objects of a labeled mask along with the outlines and statistics used to select and crop them
+pub struct Segments {
+    /// Labeled mask of the objects
+    pub mask: ThymeMask,
+    /// Label of each object
+    pub labels: Vec<u32>,
+    /// Outline of each object traced from the mask
+    pub polygons: Polygons,
+    /// Unpadded bounding box of each object
+    pub bounding_boxes: BoundingBoxes,
+    /// Area of each object (pixels)
+    pub areas: Vec<u32>,
+    /// Centroid of each object, or `None` if the label has no pixels
+    pub centroids: Vec<Option<[f32; 2]>>,
+}
+
+impl Segments {
+    /// Extract the objects of a labeled mask
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - Labeled mask
+    pub fn from_mask(mut mask: ThymeMask) -> Result<Segments, ThymeError> {
+        let (labels, polygons) = mask.polygons()?;
+        let bounding_boxes = polygons.to_bounding_boxes()?;
+        let areas = mask.areas(&labels);
+        let centroids = mask.centroids(&labels);
+
+        Ok(Segments {
+            mask,
+            labels,
+            polygons,
+            bounding_boxes,
+            areas,
+            centroids,
+        })
+    }
+
+    /// Number of objects
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Check if there are no objects
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+/// Selection and cropping of the objects yielded by `iter_objects`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ObjectOptions {
+    /// Padding added to each side of the bounding box of an object
+    pub pad: u32,
+    /// Filters applied to each object before it is padded and cropped
+    pub filter: ObjectFilter,
+}
+
+/// An object cropped from an image, ready to be measured
+pub struct Object<'a> {
+    /// Index of the object in the segments (its object id in profile tables)
+    pub index: usize,
+    /// Label of the object in the mask
+    pub label: u32,
+    /// Unpadded bounding box of the object (min_x, min_y, max_x, max_y)
+    pub xyxy: [f32; 4],
+    /// Padded bounding box clipped to the image
+    pub crop: CropRect,
+    /// Area of the object (pixels)
+    pub area: u32,
+    /// Centroid of the object, or `None` if the label has no pixels
+    pub centroid: Option<[f32; 2]>,
+    /// Outline of the object in image coordinates
+    pub polygon: &'a [[f32; 2]],
+    /// View of the image pixels within the crop
+    pub view: ThymeView<'a>,
+    /// Binary mask of the object within the crop
+    pub binary: ThymeMask,
+}
+
+impl Object<'_> {
+    /// View of the binary mask of the object, aligned with `view`
+    pub fn mask(&self) -> ThymeMaskView<'_> {
+        self.binary
+            .crop_view(0, 0, self.crop.width, self.crop.height)
+    }
+}
+
+/// An object that could not be cropped from the image
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedObject {
+    /// Index of the object in the segments
+    pub index: usize,
+    /// Unpadded bounding box of the object
+    pub xyxy: [f32; 4],
+    /// Error of the object and the stage it failed at
+    pub error: ObjectError,
+}
+
+/// Iterator over the objects of an image that pass the object filters
+///
+/// Objects are yielded in the order of the segments. Objects that are
+/// excluded by a filter or lie outside the image are skipped and counted in
+/// `dropped`, and objects whose binary mask cannot be drawn are yielded as a
+/// `FailedObject` so that the remaining objects are still visited.
+pub struct ObjectIter<'a> {
+    image: &'a ThymeImage,
+    segments: &'a Segments,
+    options: ObjectOptions,
+    index: usize,
+    dropped: DropCounts,
+}
+
+impl ObjectIter<'_> {
+    /// Objects that were skipped so far, by reason
+    pub fn dropped(&self) -> DropCounts {
+        self.dropped
+    }
+}
+
+impl<'a> Iterator for ObjectIter<'a> {
+    type Item = Result<Object<'a>, FailedObject>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (width, height) = (self.image.width(), self.image.height());
+        let bounding_boxes = self.segments.bounding_boxes.as_xyxy();
+
+        while self.index < self.segments.len() {
+            let idx = self.index;
+            self.index += 1;
+
+            let xyxy = bounding_boxes[idx];
+            let area = self.segments.areas[idx];
+
+            if let Err(reason) = self.options.filter.check(&xyxy, area as f32, width, height) {
+                self.dropped.add(reason);
+                continue;
+            }
+
+            let Some(crop) = crop_rect(&xyxy, self.options.pad, width, height) else {
+                self.dropped.add(DropReason::OutsideImage);
+                continue;
+            };
+
+            let label = self.segments.labels[idx];
+            let [x, y, w, h] = crop.as_xywh();
+
+            let binary = failures::catch(|stage| {
+                *stage = Stage::Rasterize;
+                self.segments.mask.crop_binary(x, y, w, h, label)
+            });
+
+            return Some(match binary {
+                Ok(binary) => Ok(Object {
+                    index: idx,
+                    label,
+                    xyxy,
+                    crop,
+                    area,
+                    centroid: self.segments.centroids[idx],
+                    polygon: &self.segments.polygons.as_points()[idx],
+                    view: self.image.crop_view(x, y, w, h),
+                    binary,
+                }),
+                Err(error) => Err(FailedObject {
+                    index: idx,
+                    xyxy,
+                    error,
+                }),
+            });
+        }
+
+        None
+    }
+}
+
+/// Iterate over the objects of an image for custom measurements
+///
+/// Each object is yielded with its padded crop of the image, its binary mask
+/// within the crop, its label, and its outline, so that descriptors can be
+/// computed and assembled into tables without reimplementing the per-object
+/// loop of `thyme profile mask`, which is itself built on this iterator.
+///
+/// # Arguments
+///
+/// * `image` - Image of the objects
+/// * `segments` - Objects of a labeled mask of the same size as the image
+/// * `options` - Padding and filters of the objects
+///
+/// # Examples
+///
+/// ```
+/// use thyme_cli::pipeline::{ObjectOptions, Segments, iter_objects};
+/// use thyme_core::im::{ThymeBuffer, ThymeImage, ThymeMask};
+///
+/// // Two objects with labels 3 and 7 and intensities 100 and 200
+/// let mut pixels = vec![0u8; 100];
+/// let mut labels = vec![0u32; 100];
+/// for (label, value, columns) in [(3, 100, 1..3), (7, 200, 5..9)] {
+///     for y in 2..5 {
+///         for x in columns.clone() {
+///             pixels[y * 10 + x] = value;
+///             labels[y * 10 + x] = label;
+///         }
+///     }
+/// }
+///
+/// let image = ThymeImage::U8(ThymeBuffer::new(10, 10, 1, pixels).unwrap());
+/// let mask = ThymeMask::new(10, 10, 1, labels).unwrap();
+/// let segments = Segments::from_mask(mask).unwrap();
+///
+/// let options = ObjectOptions { pad: 1, ..Default::default() };
+///
+/// let mut means = Vec::new();
+/// for object in iter_objects(&image, &segments, &options).unwrap() {
+///     let object = object.unwrap();
+///
+///     // Mean intensity of the object pixels
+///     let mask = object.mask();
+///     let (sum, count) = object.view.with_buffer(|view| {
+///         view.iter_f32()
+///             .zip(mask.iter())
+///             .filter(|(_, label)| **label != 0)
+///             .fold((0.0, 0.0), |(sum, count), (value, _)| (sum + value, count + 1.0))
+///     });
+///
+///     means.push((object.label, sum / count));
+/// }
+///
+/// assert_eq!(means, [(3, 100.0), (7, 200.0)]);
+/// ```
+pub fn iter_objects<'a>(
+    image: &'a ThymeImage,
+    segments: &'a Segments,
+    options: &ObjectOptions,
+) -> Result<ObjectIter<'a>, ThymeError> {
+    if image.width() != segments.mask.width() || image.height() != segments.mask.height() {
+        return Err(ThymeError::OtherError(
+            "Mask and image are not the same size".to_string(),
+        ));
+    }
+
+    Ok(ObjectIter {
+        image,
+        segments,
+        options: *options,
+        index: 0,
+        dropped: DropCounts::default(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_config::ProfileMaskConfig;
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+    use crate::run_profile_mask;
+
+    #[test]
+    fn test_iter_objects_intensity_mean() {
+        let directory = std::env::temp_dir().join("thyme_test_iter_objects");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+        let output = directory.join("output");
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: 6,
+            radius: [3.0, 6.0],
+            seed: 4,
+            ..Default::default()
+        }
+        .write(1, &data)
+        .unwrap();
+
+        let config = ProfileMaskConfig::new(data.to_str().unwrap(), output.to_str().unwrap())
+            .image_substring("_image")
+            .mask_substring("_mask")
+            .mode("f");
+
+        run_profile_mask(&config).unwrap();
+
+        let table = std::fs::read_to_string(output.join("descriptors.csv")).unwrap();
+        let rows: Vec<Vec<&str>> = table.lines().map(|row| row.split(',').collect()).collect();
+        let column = |name: &str| rows[0].iter().position(|c| *c == name).unwrap();
+        let (id, mean) = (column("object"), column("foreground_intensity_mean"));
+
+        let image = ThymeImage::open(data.join("synth_0000_image.png")).unwrap();
+        let mask = ThymeMask::open(data.join("synth_0000_mask.png")).unwrap();
+        let segments = Segments::from_mask(mask).unwrap();
+
+        let options = ObjectOptions {
+            pad: config.pad,
+            ..Default::default()
+        };
+
+        let mut measured = 0;
+
+        for object in iter_objects(&image, &segments, &options).unwrap() {
+            let object = object.unwrap();
+            let mask: Vec<u32> = object.mask().iter().copied().collect();
+
+            // Mean over the pixels of the object of every channel
+            let (sum, count) = object.view.with_buffer(|view| {
+                let channels = view.channels();
+                view.iter_f32()
+                    .enumerate()
+                    .filter(|(i, _)| mask[i / channels] != 0)
+                    .fold((0.0, 0.0), |(sum, count), (_, value)| {
+                        (sum + value as f64, count + 1.0)
+                    })
+            });
+
+            let row = rows[1..]
+                .iter()
+                .find(|row| row[id] == object.index.to_string())
+                .unwrap();
+
+            let expected: f64 = row[mean].parse().unwrap();
+            assert!((sum / count - expected).abs() < 1e-3 * expected.max(1.0));
+            measured += 1;
+        }
+
+        assert_eq!(measured, rows.len() - 1);
+        assert!(measured > 0);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
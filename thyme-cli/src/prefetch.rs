@@ -0,0 +1,242 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use thyme_core::error::ThymeError;
+
+use crate::status::Progress;
+
+/// Decodes inputs on dedicated IO threads ahead of the compute threads
+///
+/// With a prefetch depth of zero, each input is loaded by the compute thread
+/// that processes it. Otherwise up to `depth` loaded inputs are queued by
+/// `io_threads` threads while the threads of the current pool drain the
+/// queue, so that slow reads (e.g. network storage) overlap with compute
+/// instead of stalling it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Prefetch {
+    /// Maximum number of loaded inputs waiting to be processed
+    pub depth: usize,
+    /// Number of threads loading inputs
+    pub io_threads: usize,
+}
+
+impl Prefetch {
+    /// Initialize a prefetch queue from command line arguments
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - Maximum number of loaded inputs waiting to be processed
+    /// * `io_threads` - Number of threads loading inputs
+    pub fn new(depth: Option<usize>, io_threads: Option<usize>) -> Result<Self, ThymeError> {
+        let io_threads = io_threads.unwrap_or(2);
+
+        if io_threads < 1 {
+            return Err(ThymeError::OtherError(
+                "IO threads must be set to a positive integer".to_string(),
+            ));
+        }
+
+        Ok(Prefetch {
+            depth: depth.unwrap_or(0),
+            io_threads,
+        })
+    }
+
+    /// Load and process every input, returning the results in input order
+    ///
+    /// Inputs that fail to load are not processed and their load error is
+    /// returned in place of a result.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Inputs to load and process (e.g. image and segment pairs)
+    /// * `pb` - Progress bar or status panel updated as each input is processed
+    /// * `load` - Loads an input (e.g. decodes an image)
+    /// * `compute` - Processes an input and its loaded data
+    pub fn run<T, L, R, F, G>(
+        &self,
+        inputs: &[T],
+        pb: impl Into<Progress>,
+        load: F,
+        compute: G,
+    ) -> Vec<Result<R, ThymeError>>
+    where
+        T: Sync,
+        L: Send,
+        R: Send,
+        F: Fn(&T) -> Result<L, ThymeError> + Sync,
+        G: Fn(&T, L) -> Result<R, ThymeError> + Sync,
+    {
+        let pb: Mutex<Progress> = Mutex::new(pb.into());
+
+        let process = |input: &T, loaded: Result<L, ThymeError>| {
+            let result = loaded.and_then(|loaded| compute(input, loaded));
+            pb.lock().unwrap().update(&result);
+            result
+        };
+
+        if self.depth == 0 {
+            return inputs
+                .par_iter()
+                .map(|input| process(input, load(input)))
+                .collect();
+        }
+
+        let (sender, receiver) = crossbeam_channel::bounded(self.depth);
+        let next = AtomicUsize::new(0);
+
+        let mut results: Vec<(usize, Result<R, ThymeError>)> = std::thread::scope(|scope| {
+            for _ in 0..self.io_threads {
+                let sender = sender.clone();
+                let (next, load) = (&next, &load);
+
+                scope.spawn(move || {
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+
+                        if idx >= inputs.len() {
+                            break;
+                        }
+
+                        // A closed queue means that the compute threads have stopped
+                        if sender.send((idx, load(&inputs[idx]))).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+
+            drop(sender);
+
+            receiver
+                .into_iter()
+                .par_bridge()
+                .map(|(idx, loaded)| (idx, process(&inputs[idx], loaded)))
+                .collect()
+        });
+
+        results.sort_unstable_by_key(|(idx, _)| *idx);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    use thyme_core::im::ThymeImage;
+    use thyme_core::ut::synth::SynthConfig;
+    use thyme_core::ut::track::progress_bar;
+
+    use super::*;
+
+    const LATENCY: Duration = Duration::from_millis(100);
+
+    fn images(name: &str, n: usize) -> (PathBuf, Vec<PathBuf>) {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 32,
+            height: 32,
+            objects: 2,
+            radius: [3.0, 6.0],
+            ..Default::default()
+        }
+        .write(n, &directory)
+        .unwrap();
+
+        let images = (0..n)
+            .map(|idx| directory.join(format!("synth_{:04}_image.png", idx)))
+            .collect();
+
+        (directory, images)
+    }
+
+    /// Decode an image after an artificial open latency
+    fn slow_open(path: &PathBuf) -> Result<ThymeImage, ThymeError> {
+        std::thread::sleep(LATENCY);
+        ThymeImage::open(path)
+    }
+
+    /// Compute on an image for as long as it takes to open
+    fn slow_compute(_: &PathBuf, image: ThymeImage) -> Result<u32, ThymeError> {
+        std::thread::sleep(LATENCY);
+        Ok(image.width())
+    }
+
+    #[test]
+    fn test_prefetch_overlaps_io_and_compute() {
+        let (directory, images) = images("thyme_test_prefetch_overlap", 8);
+
+        let timed = |prefetch: Prefetch| {
+            // A single compute thread makes the sequential runtime the sum of IO and compute
+            thyme_core::ut::threads::install(Some(1), || {
+                let start = Instant::now();
+                let results = prefetch.run(
+                    &images,
+                    progress_bar(images.len(), "", false),
+                    slow_open,
+                    slow_compute,
+                );
+                let elapsed = start.elapsed();
+
+                assert!(results.into_iter().all(|width| width.unwrap() == 32));
+                elapsed
+            })
+        };
+
+        let sequential = timed(Prefetch::default());
+        let prefetched = timed(Prefetch::new(Some(4), Some(1)).unwrap());
+
+        // IO and compute each take 800 ms so overlapping them approaches 900 ms
+        assert!(sequential >= LATENCY * 16, "{:?}", sequential);
+        assert!(prefetched < LATENCY * 12, "{:?}", prefetched);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_prefetch_order_and_errors() {
+        let (directory, mut images) = images("thyme_test_prefetch_order", 6);
+        images.insert(2, directory.join("missing.png"));
+
+        for prefetch in [
+            Prefetch::default(),
+            Prefetch::new(Some(1), Some(1)).unwrap(),
+            Prefetch::new(Some(2), Some(3)).unwrap(),
+        ] {
+            let results = thyme_core::ut::threads::install(Some(3), || {
+                prefetch.run(
+                    &images,
+                    progress_bar(images.len(), "", false),
+                    |path| ThymeImage::open(path).map(|_| path.clone()),
+                    |path, loaded| {
+                        assert_eq!(path, &loaded);
+                        Ok(loaded)
+                    },
+                )
+            });
+
+            assert_eq!(results.len(), images.len());
+            assert!(results[2].is_err());
+
+            for (idx, result) in results.iter().enumerate() {
+                if idx != 2 {
+                    assert_eq!(result.as_ref().unwrap(), &images[idx]);
+                }
+            }
+        }
+
+        assert!(Prefetch::new(Some(4), Some(0)).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
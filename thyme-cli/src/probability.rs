@@ -0,0 +1,128 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::Path;
+
+use thyme_core::error::ThymeError;
+use thyme_core::im;
+
+/// Settings for labeling float probability maps instead of integer masks
+#[derive(Debug, Clone, Copy)]
+pub struct ProbabilityMask {
+    /// Pixels with a probability above the threshold are foreground
+    pub threshold: f32,
+    /// Minimum distance between watershed seeds used to split touching objects
+    pub min_distance: Option<u32>,
+}
+
+impl ProbabilityMask {
+    /// Initialize probability mask settings if a threshold was provided
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Probability threshold
+    /// * `min_distance` - Minimum distance between watershed seeds (pixels)
+    pub fn new(threshold: Option<f32>, min_distance: Option<u32>) -> Option<Self> {
+        threshold.map(|threshold| Self {
+            threshold,
+            min_distance,
+        })
+    }
+
+    /// Validate the probability arguments, returning an error message if invalid
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.threshold) {
+            return Err("prob_threshold must be in [0, 1].".to_string());
+        }
+
+        if self.min_distance.is_some_and(|d| d < 1) {
+            return Err("prob_min_distance cannot be less than 1.".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Open a probability map and label it as a mask
+    ///
+    /// A warning is printed if the map is not a single channel float image
+    /// with values in [0, 1], since the threshold may then be meaningless.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the probability map (e.g. npy or tiff)
+    /// * `command` - Command name used to prefix warnings (e.g. profile::mask)
+    pub fn open(&self, path: &Path, command: &str) -> Result<im::ThymeMask, ThymeError> {
+        let image = im::ThymeImage::open(path)?;
+
+        if image.channels() == 1 && !image.is_probability_map() {
+            eprintln!(
+                "[thyme::{}] WARNING: {} is not a float probability map with values in [0, 1]. Thresholding raw values at {}.",
+                command,
+                path.display(),
+                self.threshold
+            );
+        }
+
+        match self.min_distance {
+            Some(min_distance) => {
+                im::ThymeMask::from_probability_declumped(&image, self.threshold, min_distance)
+            }
+            None => im::ThymeMask::from_probability(&image, self.threshold),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(ProbabilityMask::new(None, Some(5)).is_none());
+
+        let probability = ProbabilityMask::new(Some(0.5), Some(5)).unwrap();
+        assert!(probability.validate().is_ok());
+
+        let probability = ProbabilityMask::new(Some(1.5), None).unwrap();
+        assert!(probability.validate().is_err());
+
+        let probability = ProbabilityMask::new(Some(0.5), Some(0)).unwrap();
+        assert!(probability.validate().is_err());
+    }
+
+    #[test]
+    fn test_open_thresholds() {
+        let directory = std::env::temp_dir().join("thyme_test_probability_open");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("probability.npy");
+
+        let (width, height) = (20u32, 20u32);
+        let probability: Vec<f32> = (0..width * height)
+            .map(|idx| {
+                let x = (idx % width) as f32 - 10.;
+                let y = (idx / width) as f32 - 10.;
+                0.9 * (-(x * x + y * y) / 18.).exp()
+            })
+            .collect();
+
+        im::ThymeImage::F32(im::ThymeBuffer::new(width, height, 1, probability).unwrap())
+            .save(&path)
+            .unwrap();
+
+        let mut mask = ProbabilityMask::new(Some(0.5), None)
+            .unwrap()
+            .open(&path, "test")
+            .unwrap();
+        assert_eq!(mask.label().len(), 1);
+
+        let mut mask = ProbabilityMask::new(Some(0.99), Some(3))
+            .unwrap()
+            .open(&path, "test")
+            .unwrap();
+        assert!(mask.label().is_empty());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
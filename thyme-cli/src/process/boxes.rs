@@ -1,26 +1,43 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::panic::AssertUnwindSafe;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 
 use clap::Args;
-use futures::stream::{self, StreamExt};
-use kdam::BarExt;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+use thyme_config::ProcessBoxesConfig;
 use thyme_core::constant;
 use thyme_core::error::ThymeError;
 use thyme_core::im;
+use thyme_core::io;
 use thyme_core::ut;
 
+use super::ProcessSummary;
+use super::container::{CropMetadata, CropSink};
+use super::sample::{ObjectSampler, StratifyBy, StratumValues, retain_sampled};
+use crate::boxes;
+use crate::dryrun;
+use crate::filter::{
+    self, BorderPolicy, CropRect, DropCounts, DropReason, ObjectFilter, crop_rect,
+};
+use crate::output::{OutputMode, prepare_output_directory};
+use crate::prefetch::Prefetch;
+use crate::status::Progress;
+
 #[derive(Debug, Args)]
 pub struct ProcessBoxesArgs {
     #[arg(short = 'i', long, help = "Image directory.", required = true)]
     pub images: Option<String>,
 
-    #[arg(short = 's', long, help = "Bounding boxes directory.")]
+    #[arg(
+        short = 's',
+        long,
+        help = "Bounding boxes directory, or a single csv table with an image column holding boxes for many images."
+    )]
     pub boxes: Option<String>,
 
     #[arg(short = 'o', long, help = "Output directory.", required = true)]
@@ -29,6 +46,19 @@ pub struct ProcessBoxesArgs {
     #[arg(short = 'v', long, help = "Verbose output.")]
     pub verbose: bool,
 
+    #[arg(
+        long,
+        help = "Show a live status panel with progress, throughput, workers, recent errors, and memory usage instead of the progress bar. Logs status lines instead if stdout is not a terminal."
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Format of the status lines logged instead of the status panel (plain, json). Json status lines are also logged without --tui.",
+        default_value = "plain"
+    )]
+    pub log_format: Option<String>,
+
     #[arg(short = 'd', long, help = "Exclude objects touching edge of image.")]
     pub drop_borders: bool,
 
@@ -56,11 +86,87 @@ pub struct ProcessBoxesArgs {
 
     #[arg(
         long,
-        help = "Exclude objects smaller than a minimum size.",
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting bounding boxes by their path relative to their directory.",
+        conflicts_with_all = ["box_substring", "box_regex"]
+    )]
+    pub box_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting bounding boxes by their path relative to their directory.",
+        conflicts_with = "box_substring"
+    )]
+    pub box_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Bounding box csv column names as key=column pairs (e.g. xmin=left,ymin=top,xmax=right,ymax=bottom,label=class,image=file). Columns are otherwise detected from common header names."
+    )]
+    pub box_columns: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with an unpadded bounding box width or height smaller than a minimum size (pixels).",
         default_value = "1"
     )]
     pub min_size: Option<u32>,
 
+    #[arg(
+        long,
+        help = "Exclude objects with a bounding box area smaller than a minimum area (pixels)."
+    )]
+    pub min_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with a bounding box area larger than a maximum area (pixels)."
+    )]
+    pub max_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Number of objects sampled from each image after filtering, spread evenly across strata if --stratify-by is set."
+    )]
+    pub sample_per_image: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Descriptor stratifying sampled objects. One of area, class, or a form descriptor (e.g. form_eccentricity).",
+        requires = "sample_per_image"
+    )]
+    pub stratify_by: Option<String>,
+
+    #[arg(
+        long,
+        help = "Number of quantile strata of a numeric --stratify-by descriptor (defaults to 4).",
+        requires = "stratify_by"
+    )]
+    pub strata: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Compute quantile strata from the objects of every image in a pass over all segments instead of each image.",
+        requires = "stratify_by"
+    )]
+    pub global_strata: bool,
+
+    #[arg(long, help = "Random seed for sampling objects.", default_value = "0")]
+    pub seed: Option<u64>,
+
     #[arg(
         short = 'e',
         long,
@@ -77,106 +183,243 @@ pub struct ProcessBoxesArgs {
     )]
     pub array_format: Option<String>,
 
+    #[arg(
+        long,
+        help = "Container to store extracted object images (e.g. files, hdf5, zarr). Polygons and bounding boxes are always saved as files.",
+        default_value = "files"
+    )]
+    pub output_container: Option<String>,
+
+    #[arg(
+        long,
+        help = "Embed the source image, label, bounding box, and polygon of each object as json in a text chunk of its png crops. Requires png image_format and files output_container."
+    )]
+    pub embed_metadata: bool,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (crops, segments, and reports) from the output directory before processing. Without it, output directories holding a previous run are rejected."
+    )]
+    pub overwrite: bool,
+
+    #[arg(
+        long,
+        help = "Number of decoded images queued ahead of the compute threads. If 0, images are decoded by the compute threads.",
+        default_value = "0"
+    )]
+    pub prefetch: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Number of threads decoding images ahead of the compute threads if --prefetch is set.",
+        default_value = "2"
+    )]
+    pub io_threads: Option<usize>,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Report the pairs, object counts, and estimated output size as a table and in dry_run.json next to the output, without extracting objects or creating the output."
+    )]
+    pub dry_run: bool,
 }
 
 pub fn process_image_boxes(args: &ProcessBoxesArgs) {
-    let mode = args.mode.to_owned().unwrap_or("cmbfpx".to_string());
-    let pad = args.pad.unwrap_or(1);
-    let min_size = args.min_size.unwrap_or(1);
-    let image_format = args.image_format.to_owned().unwrap_or("png".to_string());
-    let array_format = args.array_format.to_owned().unwrap_or("json".to_string());
-
-    let threads = if let Some(t) = args.threads {
-        t
-    } else {
-        std::thread::available_parallelism().unwrap_or_else(|_| {
-            eprintln!("[thyme::process::boxes] Could not automatically assign number of tasks. Please manually set the --threads (-t) argument.");
-            std::process::exit(1);
-        }).get()
-    };
-
-    if mode.chars().any(|c| !matches!(c, 'c' | 'x')) {
-        eprintln!(
-            "[thyme::process::boxes] Invalid mode. Argument mode must only contain one or more of: c, x."
-        );
+    if let Err(err) = run_process_boxes(&ProcessBoxesConfig::from(args)) {
+        eprintln!("[thyme::process::boxes] ERROR: {}", err);
         std::process::exit(1);
     }
+}
 
-    if min_size < 1 {
-        eprintln!("[thyme::process::boxes] ERROR: min_size cannot be less than 1.0.");
-        std::process::exit(1);
+impl From<&ProcessBoxesArgs> for ProcessBoxesConfig {
+    fn from(args: &ProcessBoxesArgs) -> Self {
+        let defaults = ProcessBoxesConfig::default();
+
+        ProcessBoxesConfig {
+            images: args.images.to_owned().unwrap_or_default(),
+            boxes: args.boxes.to_owned(),
+            output: args.output.to_owned().unwrap_or_default(),
+            verbose: args.verbose,
+            tui: args.tui,
+            log_format: args.log_format.to_owned().unwrap_or(defaults.log_format),
+            drop_borders: args.drop_borders,
+            mode: args.mode.to_owned().unwrap_or(defaults.mode),
+            pad: args.pad.unwrap_or(defaults.pad),
+            image_substring: args.image_substring.to_owned(),
+            box_substring: args.box_substring.to_owned(),
+            image_glob: args.image_glob.to_owned(),
+            image_regex: args.image_regex.to_owned(),
+            box_glob: args.box_glob.to_owned(),
+            box_regex: args.box_regex.to_owned(),
+            box_columns: args.box_columns.to_owned(),
+            min_size: args.min_size.unwrap_or(defaults.min_size),
+            min_area: args.min_area,
+            max_area: args.max_area,
+            sample_per_image: args.sample_per_image,
+            stratify_by: args.stratify_by.to_owned(),
+            strata: args.strata,
+            global_strata: args.global_strata,
+            seed: args.seed.unwrap_or(defaults.seed),
+            image_format: args
+                .image_format
+                .to_owned()
+                .unwrap_or(defaults.image_format),
+            array_format: args
+                .array_format
+                .to_owned()
+                .unwrap_or(defaults.array_format),
+            output_container: args
+                .output_container
+                .to_owned()
+                .unwrap_or(defaults.output_container),
+            embed_metadata: args.embed_metadata,
+            overwrite: args.overwrite,
+            prefetch: args.prefetch.unwrap_or(defaults.prefetch),
+            io_threads: args.io_threads.unwrap_or(defaults.io_threads),
+            threads: args.threads,
+            dry_run: args.dry_run,
+        }
     }
+}
 
-    if !constant::SUPPORTED_IMAGE_FORMATS.contains(&image_format.as_str()) {
-        eprintln!(
-            "[thyme::process::boxes] ERROR: Invalid image_format {}. Must be one of: {:?}.",
-            image_format,
-            constant::SUPPORTED_IMAGE_FORMATS
-        );
-        std::process::exit(1);
-    }
+/// Extract the objects of image and bounding box pairs as configured
+///
+/// Images that fail to process are reported in `object_errors.tsv` and are
+/// counted in the returned summary.
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the run
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_cli::run_process_boxes;
+/// use thyme_config::ProcessBoxesConfig;
+///
+/// let config = ProcessBoxesConfig::new("images/", "output/").boxes("boxes.csv");
+///
+/// let summary = run_process_boxes(&config).unwrap();
+/// println!("{} objects across {} images", summary.objects, summary.images);
+/// ```
+pub fn run_process_boxes(config: &ProcessBoxesConfig) -> Result<ProcessSummary, ThymeError> {
+    config.validate()?;
+
+    let threads = super::threads(config.threads)?;
+    let mode = config.mode.to_owned();
+    let pad = config.pad;
+
+    let prefetch = Prefetch::new(Some(config.prefetch), Some(config.io_threads))?;
+
+    let filter = ObjectFilter {
+        border_policy: BorderPolicy::from_drop_borders(config.drop_borders),
+        min_size: config.min_size,
+        min_area: config.min_area,
+        max_area: config.max_area,
+        skip_invalid_polygons: false,
+    };
 
-    if !constant::SUPPORTED_ARRAY_FORMATS.contains(&array_format.as_str()) {
-        eprintln!(
-            "[thyme::process::boxes] ERROR: Invalid array_format {}. Must be one of: {:?}.",
-            array_format,
-            constant::SUPPORTED_ARRAY_FORMATS
-        );
-        std::process::exit(1);
+    filter.validate().map_err(super::invalid)?;
+
+    let mut sampler = ObjectSampler::new(
+        config.sample_per_image,
+        config.stratify_by.as_deref(),
+        config.strata,
+        config.global_strata,
+        config.seed,
+    )?;
+
+    if sampler.as_ref().is_some_and(|sampler| {
+        sampler
+            .stratify_by
+            .is_some_and(|stratify_by| stratify_by.requires_polygons())
+    }) {
+        return Err(ThymeError::OtherError(
+            "stratify_by form descriptors require polygons. Use area or class for bounding boxes"
+                .to_string(),
+        ));
     }
 
-    let image_path = args.images.to_owned().unwrap();
-    let boxes_path = args.boxes.to_owned().unwrap_or(image_path.clone());
-
-    if image_path == boxes_path && args.image_substring == args.box_substring {
-        eprintln!(
-            "[thyme::process::boxes] ERROR: If images and boxes are located in same path, different image and bounding box substrings must be provided."
-        );
-        std::process::exit(1);
-    }
+    let image_path = config.images.to_owned();
+    let boxes_path = config.boxes_path().to_owned();
 
-    let image_files = ut::path::collect_file_paths(
-        &image_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.image_substring.to_owned(),
+    let image_files = ut::path::FileSelector::new(
+        config.image_substring.as_deref(),
+        config.image_glob.as_deref(),
+        config.image_regex.as_deref(),
     )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
-
-    let boxes_files = ut::path::collect_file_paths(
-        &boxes_path,
-        constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
-        args.box_substring.to_owned(),
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    .and_then(|selector| {
+        ut::path::select_file_paths(
+            &image_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
+
+    let box_columns = config
+        .box_columns
+        .as_deref()
+        .map(im::BoxColumns::parse)
+        .transpose()?
+        .unwrap_or_default();
 
     if image_files.is_empty() {
-        eprintln!(
-            "[thyme::process::boxes] ERROR: No image files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No image files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
-    if boxes_files.is_empty() {
-        eprintln!(
-            "[thyme::process::boxes] ERROR: No bounding boxes files were detected. Please check your path and/or substring identifier."
+    let (mut pairs, box_source) = if Path::new(&boxes_path).is_file() {
+        let (pairs, box_source, unmatched) = boxes::table_pairs(
+            &boxes_path,
+            &box_columns,
+            &image_files,
+            config.image_substring.to_owned(),
+        )?;
+
+        if unmatched > 0 {
+            eprintln!(
+                "[thyme::process::boxes] WARNING: {} images in the bounding box table have no matching image file.",
+                unmatched
+            );
+        }
+
+        (pairs, box_source)
+    } else {
+        let boxes_files = ut::path::FileSelector::new(
+            config.box_substring.as_deref(),
+            config.box_glob.as_deref(),
+            config.box_regex.as_deref(),
+        )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &boxes_path,
+                constant::SUPPORTED_BOX_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })?;
+
+        if boxes_files.is_empty() {
+            return Err(ThymeError::OtherError(
+                "No bounding boxes files were detected. Please check your path and/or substring identifier"
+                    .to_string(),
+            ));
+        }
+
+        let pairs = ut::path::collect_file_pairs(
+            &image_files,
+            &boxes_files,
+            config.image_substring.to_owned(),
+            config.box_substring.to_owned(),
         );
-        std::process::exit(1);
-    }
 
-    let mut pairs = ut::path::collect_file_pairs(
-        &image_files,
-        &boxes_files,
-        args.image_substring.to_owned(),
-        args.box_substring.to_owned(),
-    );
+        (pairs, boxes::BoxSource::Files(box_columns))
+    };
 
     pairs.sort_unstable();
 
@@ -185,50 +428,96 @@ pub fn process_image_boxes(args: &ProcessBoxesArgs) {
             "Detected {} image and bounding box pairs.",
             ut::track::thousands_format(pairs.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    let output = PathBuf::from(args.output.to_owned().unwrap());
-
-    let output = ut::path::create_directory(&output).unwrap_or_else(|_| {
-        eprintln!("[thyme::process::boxes] ERROR: Could not create directory.");
-        std::process::exit(1);
-    });
+    if config.dry_run {
+        let images = dryrun::scan(&pairs, pad, |_, image, boxes, _| {
+            Ok(box_source.open(image, boxes)?.to_xyxy())
+        });
 
-    if mode.contains("c") {
-        std::fs::create_dir(output.join("complete")).unwrap();
+        return super::dry_run("process::boxes", &config.output, &mode, images);
     }
 
-    if mode.contains("x") {
-        std::fs::create_dir(output.join("bounding_boxes")).unwrap();
+    if let Some(sampler) = sampler.as_mut().filter(|sampler| sampler.global()) {
+        ut::track::progress_log("Computing global strata.", config.verbose);
+
+        let edges = sampler.global_edges(&pairs, threads, |(_, image_path, boxes_path)| {
+            let info = io::probe_image(image_path)?;
+            let bounding_boxes = box_source.open(image_path, boxes_path)?;
+
+            Ok(bounding_boxes
+                .as_xyxy()
+                .iter()
+                .map(|xyxy| (xyxy, (xyxy[2] - xyxy[0]) * (xyxy[3] - xyxy[1])))
+                .filter(|(xyxy, area)| {
+                    filter.check(xyxy, *area, info.width, info.height).is_ok()
+                        && crop_rect(xyxy, pad, info.width, info.height).is_some()
+                })
+                .map(|(_, area)| area)
+                .collect())
+        });
+
+        sampler.edges = Some(edges);
     }
 
-    let rt = tokio::runtime::Runtime::new().unwrap();
+    let output = prepare_output_directory(
+        Path::new(&config.output),
+        OutputMode::new(config.overwrite, false)?,
+        &config.inputs(),
+        "process::boxes",
+    )?;
 
-    let results = rt.block_on(run_all(
+    let (crops, writer) = super::crop_sink(
+        &output,
+        &mode,
+        &config.output_container,
+        &config.image_format,
+        config.embed_metadata,
+    )?;
+
+    let progress = Progress::new(
+        pairs.len(),
+        "Processing",
+        config.verbose,
+        config.tui,
+        &config.log_format,
+    );
+
+    let results = run_all(
         pairs,
+        &box_source,
         pad,
-        args.drop_borders,
-        min_size,
+        filter,
+        sampler.as_ref(),
         &mode,
         &output,
-        &image_format,
-        &array_format,
+        crops,
+        &config.array_format,
+        &prefetch,
         threads,
-        args.verbose,
-    ));
+        progress,
+    );
+
+    super::finish_writer(writer)?;
 
     let objects: Mutex<usize> = Mutex::new(0);
     let success: Mutex<Vec<String>> = Mutex::new(vec![]);
     let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(results.len()));
+    let sampled: Mutex<Vec<(String, Vec<String>)>> = Mutex::new(vec![]);
 
     results.into_par_iter().for_each(|(id, run)| {
-        if let Ok(n_objects) = run {
+        if let Ok((n_objects, dropped, strata)) = run {
+            if sampler.is_some() {
+                sampled.lock().unwrap().push((id.clone(), strata));
+            }
+
             *objects.lock().unwrap() += n_objects as usize;
-            success
-                .lock()
-                .unwrap()
-                .push(format!("{}\t{}", id, n_objects));
+            success.lock().unwrap().push(filter::object_counts_row(
+                &id,
+                n_objects as usize,
+                &dropped,
+            ));
         } else {
             failure
                 .lock()
@@ -241,7 +530,10 @@ pub fn process_image_boxes(args: &ProcessBoxesArgs) {
     let success = success.into_inner().unwrap();
     let failure = failure.into_inner().unwrap();
 
-    if args.verbose {
+    let mut sampled = sampled.into_inner().unwrap();
+    sampled.sort_unstable();
+
+    if config.verbose {
         println!();
     }
 
@@ -251,75 +543,123 @@ pub fn process_image_boxes(args: &ProcessBoxesArgs) {
             ut::track::thousands_format(objects),
             ut::track::thousands_format(success.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    if !success.is_empty() {
-        std::fs::write(output.join("object_counts.tsv"), success.join("\n")).unwrap();
-    }
+    let sampled: Vec<String> = sampled.into_iter().flat_map(|(_, rows)| rows).collect();
 
-    if !failure.is_empty() {
-        std::fs::write(output.join("object_errors.tsv"), failure.join("\n")).unwrap();
-    }
+    super::write_reports(&output, &success, &failure, &sampled)?;
+
+    crate::output::finish_run(&output)?;
+
+    Ok(ProcessSummary {
+        images: success.len(),
+        failures: failure.len(),
+        objects,
+        output,
+        dry_run: None,
+    })
 }
 
 #[allow(clippy::too_many_arguments)]
 fn extract(
     id: &str,
+    image: im::ThymeImage,
     image_path: &Path,
     boxes_path: &Path,
+    box_source: &boxes::BoxSource,
     pad: u32,
-    drop_borders: bool,
-    min_size: u32,
+    filter: &ObjectFilter,
+    sampler: Option<&ObjectSampler>,
     mode: &str,
     output: &Path,
-    image_format: &str,
+    crops: &CropSink,
     array_format: &str,
-) -> Result<u32, ThymeError> {
-    let image = im::ThymeImage::open(image_path)?;
-
-    let mut bounding_boxes = im::BoundingBoxes::open(boxes_path)?;
+) -> Result<(u32, DropCounts, Vec<String>), ThymeError> {
+    let mut bounding_boxes = box_source.open(image_path, boxes_path)?;
 
     let width = image.width();
     let height = image.height();
 
     let mut n_objects = 0;
-    let pad_f32 = pad as f32;
-
     let mut remove_indices: Vec<usize> = Vec::with_capacity(bounding_boxes.len());
-
-    for (idx, [min_x, min_y, max_x, max_y]) in bounding_boxes.as_xyxy().iter().enumerate() {
-        let min_x = min_x - pad_f32;
-        let min_y = min_y - pad_f32;
-        let max_x = max_x + pad_f32;
-        let max_y = max_y + pad_f32;
-
-        if drop_borders
-            && (min_x <= 0.0 || min_y <= 0.0 || max_x >= width as f32 || max_y >= height as f32)
-        {
+    let mut dropped = DropCounts::default();
+    let mut kept: Vec<(usize, CropRect)> = Vec::with_capacity(bounding_boxes.len());
+
+    for (idx, xyxy) in bounding_boxes.as_xyxy().iter().enumerate() {
+        if let Err(reason) = filter.check(
+            xyxy,
+            (xyxy[2] - xyxy[0]) * (xyxy[3] - xyxy[1]),
+            width,
+            height,
+        ) {
+            dropped.add(reason);
             remove_indices.push(idx);
             continue;
         }
 
-        let min_x = min_x.max(0.0) as u32;
-        let min_y = min_y.max(0.0) as u32;
-        let max_x = max_x.min(width as f32) as u32;
-        let max_y = max_y.min(height as f32) as u32;
-
-        let w = max_x - min_x;
-        let h = max_y - min_y;
-
-        if w < min_size || h < min_size {
+        let Some(rect) = crop_rect(xyxy, pad, width, height) else {
+            dropped.add(DropReason::OutsideImage);
             remove_indices.push(idx);
             continue;
-        }
+        };
+
+        kept.push((idx, rect));
+    }
+
+    let mut strata: Vec<String> = vec![];
+
+    if let Some(sampler) = sampler {
+        let values = match sampler.stratify_by {
+            Some(StratifyBy::Class) => {
+                let labels = bounding_boxes.labels().ok_or_else(|| {
+                    ThymeError::OtherError(
+                        "stratify_by class requires bounding boxes with class labels".to_string(),
+                    )
+                })?;
+
+                StratumValues::Classes(kept.iter().map(|(idx, _)| labels[*idx].clone()).collect())
+            }
+            _ => StratumValues::Numeric(
+                kept.iter()
+                    .map(|(idx, _)| {
+                        let xyxy = bounding_boxes.as_xyxy()[*idx];
+                        (xyxy[2] - xyxy[0]) * (xyxy[3] - xyxy[1])
+                    })
+                    .collect(),
+            ),
+        };
+
+        let (sampled, rows) = sampler.sample(id, values);
+        strata = rows;
+
+        kept = retain_sampled(kept, &sampled, &mut remove_indices);
+    }
 
-        let object_name = format!("{}_{}.{}", id, idx, image_format);
+    for (
+        idx,
+        CropRect {
+            x: min_x,
+            y: min_y,
+            width: w,
+            height: h,
+        },
+    ) in kept
+    {
+        let xyxy = &bounding_boxes.as_xyxy()[idx];
+
+        let metadata = crops.embeds_metadata().then(|| CropMetadata {
+            image: image_path.display().to_string(),
+            object: idx,
+            label: bounding_boxes.labels().map(|labels| labels[idx].clone()),
+            bbox: *xyxy,
+            crop: [min_x, min_y, w, h],
+            polygon: None,
+        });
 
         if mode.contains("c") {
-            image
-                .crop(min_x, min_y, w, h)?
-                .save(output.join("complete").join(&object_name))?;
+            let crop = image.crop(min_x, min_y, w, h)?;
+            crops.save("complete", id, idx, crop, metadata.as_ref())?;
         }
 
         n_objects += 1;
@@ -332,67 +672,65 @@ fn extract(
         bounding_boxes.save(output.join("bounding_boxes").join(&object_name))?;
     }
 
-    Ok(n_objects)
+    Ok((n_objects, dropped, strata))
 }
 
-#[allow(clippy::too_many_arguments)]
-pub async fn run_all(
+/// Extract the objects of every image and segment pair on a pool of `threads` threads
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn run_all(
     pairs: Vec<(String, PathBuf, PathBuf)>,
+    box_source: &boxes::BoxSource,
     pad: u32,
-    drop_borders: bool,
-    min_size: u32,
+    filter: ObjectFilter,
+    sampler: Option<&ObjectSampler>,
     mode: &str,
     output: &Path,
-    image_format: &str,
+    crops: CropSink,
     array_format: &str,
+    prefetch: &Prefetch,
     threads: usize,
-    verbose: bool,
-) -> Vec<(String, Result<u32, ThymeError>)> {
-    let pb = Arc::new(Mutex::new(ut::track::progress_bar(
-        pairs.len(),
-        "Processing",
-        verbose,
-    )));
-
-    stream::iter(pairs)
-        .map(|(id, image, bounding_boxes)| {
-            let mode = mode.to_string();
-            let output = output.to_path_buf();
-            let image_format = image_format.to_string();
-            let array_format = array_format.to_string();
-            let pb_clone = pb.clone();
-
-            async move {
-                let id_clone = id.clone();
-                let result = tokio::task::spawn_blocking(move || {
+    progress: Progress,
+) -> Vec<(String, Result<(u32, DropCounts, Vec<String>), ThymeError>)> {
+    let status = progress.status();
+
+    let results = ut::threads::install(Some(threads), || {
+        prefetch.run(
+            &pairs,
+            progress,
+            |(_, image, _)| im::ThymeImage::open(image),
+            |(id, image_path, boxes), image| {
+                status.begin(id);
+
+                // A panic while extracting an image fails the image instead of the run
+                std::panic::catch_unwind(AssertUnwindSafe(|| {
                     extract(
-                        &id,
-                        &image,
-                        &bounding_boxes,
+                        id,
+                        image,
+                        image_path,
+                        boxes,
+                        box_source,
                         pad,
-                        drop_borders,
-                        min_size,
-                        &mode,
-                        &output,
-                        &image_format,
-                        &array_format,
+                        &filter,
+                        sampler,
+                        mode,
+                        output,
+                        &crops,
+                        array_format,
                     )
-                })
-                .await
+                }))
                 .unwrap_or_else(|_| {
                     Err(ThymeError::OtherError(
                         "Failed to extract objects.".to_string(),
                     ))
-                });
-
-                if verbose {
-                    pb_clone.lock().unwrap().update(1).unwrap();
-                }
+                })
+                .inspect(|(n_objects, ..)| status.add_objects(*n_objects as usize))
+            },
+        )
+    });
 
-                (id_clone, result)
-            }
-        })
-        .buffer_unordered(threads)
-        .collect::<Vec<_>>()
-        .await
+    pairs
+        .into_iter()
+        .map(|(id, _, _)| id)
+        .zip(results)
+        .collect()
 }
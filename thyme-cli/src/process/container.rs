@@ -0,0 +1,240 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::JoinHandle;
+
+use serde::{Deserialize, Serialize};
+
+use thyme_core::constant::SUPPORTED_OUTPUT_CONTAINERS;
+use thyme_core::error::ThymeError;
+use thyme_core::im;
+use thyme_core::io::{Hdf5Crops, ZarrCrops};
+
+/// Maximum number of crops queued for the container writer
+const CONTAINER_QUEUE: usize = 1024;
+
+/// Keyword of the PNG text chunk storing the metadata of an object crop
+pub const METADATA_KEYWORD: &str = "thyme";
+
+/// Provenance of an object crop embedded in its PNG
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CropMetadata {
+    /// Path to the image the object was cropped from
+    pub image: String,
+    /// Index of the object in the image
+    pub object: usize,
+    /// Label of the object (e.g. mask label or bounding box class)
+    pub label: Option<String>,
+    /// Unpadded bounding box of the object in image pixels (min_x, min_y, max_x, max_y)
+    pub bbox: [f32; 4],
+    /// Padded region of the image that was cropped (x, y, width, height)
+    pub crop: [u32; 4],
+    /// Polygon points of the object in image pixels
+    pub polygon: Option<Vec<[f32; 2]>>,
+}
+
+/// Destination for object crops extracted by the process commands
+#[derive(Clone)]
+pub enum CropSink {
+    /// Save each crop as an individual file named {id}_{idx}.{image_format}
+    ///
+    /// If metadata is embedded, each crop is a png with its `CropMetadata`
+    /// stored as json in a text chunk.
+    Files {
+        output: PathBuf,
+        image_format: String,
+        embed_metadata: bool,
+    },
+    /// Queue each crop for the container writer as {kind}/{id}/{idx}
+    Container(SyncSender<(String, im::ThymeImage)>),
+}
+
+impl CropSink {
+    /// Whether the metadata of each object is embedded in its crops
+    pub fn embeds_metadata(&self) -> bool {
+        matches!(
+            self,
+            CropSink::Files {
+                embed_metadata: true,
+                ..
+            }
+        )
+    }
+
+    /// Save an object crop
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Type of crop (e.g. complete, foreground, background)
+    /// * `id` - Image identifier
+    /// * `idx` - Object index
+    /// * `image` - Object crop
+    /// * `metadata` - Metadata embedded in the crop if the sink embeds metadata
+    pub fn save(
+        &self,
+        kind: &str,
+        id: &str,
+        idx: usize,
+        image: im::ThymeImage,
+        metadata: Option<&CropMetadata>,
+    ) -> Result<(), ThymeError> {
+        match self {
+            CropSink::Files {
+                output,
+                image_format,
+                embed_metadata,
+            } => {
+                let path = output
+                    .join(kind)
+                    .join(format!("{}_{}.{}", id, idx, image_format));
+
+                match metadata.filter(|_| *embed_metadata) {
+                    Some(metadata) => {
+                        let json = serde_json::to_string(metadata).map_err(|err| {
+                            ThymeError::OtherError(format!(
+                                "Failed to serialize crop metadata. {}",
+                                err
+                            ))
+                        })?;
+
+                        image.save_as_png_with_text(path, &[(METADATA_KEYWORD, &json)])
+                    }
+                    None => image.save(path),
+                }
+            }
+            CropSink::Container(sender) => sender
+                .send((format!("{}/{}/{}", kind, id, idx), image))
+                .map_err(|_| {
+                    ThymeError::OtherError("Output container writer stopped.".to_string())
+                }),
+        }
+    }
+
+    /// Save a binary object mask where pixels matching the label are set to 255
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Type of crop (e.g. mask)
+    /// * `id` - Image identifier
+    /// * `idx` - Object index
+    /// * `mask` - Object mask
+    /// * `label` - Label of the object in the mask
+    /// * `metadata` - Metadata embedded in the crop if the sink embeds metadata
+    pub fn save_mask(
+        &self,
+        kind: &str,
+        id: &str,
+        idx: usize,
+        mask: &im::ThymeMaskView,
+        label: &u32,
+        metadata: Option<&CropMetadata>,
+    ) -> Result<(), ThymeError> {
+        match self {
+            CropSink::Files {
+                output,
+                image_format,
+                embed_metadata: false,
+            } => mask.save(
+                output
+                    .join(kind)
+                    .join(format!("{}_{}.{}", id, idx, image_format)),
+                label,
+            ),
+            _ => {
+                let binary: Vec<u8> = mask
+                    .iter()
+                    .map(|p| if p == label { 255 } else { 0 })
+                    .collect();
+
+                let buffer =
+                    im::ThymeBuffer::new(mask.width() as u32, mask.height() as u32, 1, binary)?;
+
+                self.save(kind, id, idx, im::ThymeImage::U8(buffer), metadata)
+            }
+        }
+    }
+}
+
+/// Spawn the writer thread of an output container in the output directory
+///
+/// Crops are stored in `crops.zarr` for zarr containers and `crops.h5` for
+/// hdf5 containers. The returned handle yields the number of crops written
+/// once every sender has been dropped.
+///
+/// # Arguments
+///
+/// * `output` - Output directory
+/// * `container` - Output container (e.g. hdf5, zarr)
+pub fn spawn_writer(
+    output: &Path,
+    container: &str,
+) -> Result<(CropSink, JoinHandle<Result<usize, ThymeError>>), ThymeError> {
+    match container {
+        "hdf5" => spawn_hdf5_writer(&output.join("crops.h5")),
+        "zarr" => spawn_zarr_writer(&output.join("crops.zarr")),
+        _ => Err(ThymeError::OtherError(format!(
+            "Invalid output container {}. Must be one of: {:?}.",
+            container, SUPPORTED_OUTPUT_CONTAINERS
+        ))),
+    }
+}
+
+/// Spawn a single writer thread that stores queued crops in a zarr container
+///
+/// Crops are written in the order they are received. The returned handle
+/// yields the number of crops written once every sender has been dropped.
+///
+/// # Arguments
+///
+/// * `path` - Path to the zarr container (e.g. crops.zarr)
+pub fn spawn_zarr_writer(
+    path: &Path,
+) -> Result<(CropSink, JoinHandle<Result<usize, ThymeError>>), ThymeError> {
+    let mut crops = ZarrCrops::create(path, 5)?;
+
+    let (sender, receiver): (_, Receiver<(String, im::ThymeImage)>) =
+        mpsc::sync_channel(CONTAINER_QUEUE);
+
+    let handle = std::thread::spawn(move || {
+        let mut n = 0;
+
+        for (path, image) in receiver {
+            crops.write(&path, &image)?;
+            n += 1;
+        }
+
+        Ok(n)
+    });
+
+    Ok((CropSink::Container(sender), handle))
+}
+
+/// Spawn a single writer thread that stores queued crops in an hdf5 container
+///
+/// HDF5 files are written in a single pass, so crops are held in memory and
+/// the file is written once every sender has been dropped. The returned
+/// handle yields the number of crops written.
+///
+/// # Arguments
+///
+/// * `path` - Path to the hdf5 container (e.g. crops.h5)
+pub fn spawn_hdf5_writer(
+    path: &Path,
+) -> Result<(CropSink, JoinHandle<Result<usize, ThymeError>>), ThymeError> {
+    let mut crops = Hdf5Crops::create(path, 5)?;
+
+    let (sender, receiver): (_, Receiver<(String, im::ThymeImage)>) =
+        mpsc::sync_channel(CONTAINER_QUEUE);
+
+    let handle = std::thread::spawn(move || {
+        for (path, image) in receiver {
+            crops.write(&path, &image)?;
+        }
+
+        crops.finish()
+    });
+
+    Ok((CropSink::Container(sender), handle))
+}
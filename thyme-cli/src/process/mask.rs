@@ -1,26 +1,45 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::panic::AssertUnwindSafe;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 
 use clap::Args;
-use futures::stream::{self, StreamExt};
-use kdam::BarExt;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+use thyme_config::ProcessMaskConfig;
 use thyme_core::constant;
 use thyme_core::error::ThymeError;
 use thyme_core::im;
+use thyme_core::io;
 use thyme_core::ut;
 
+use super::ProcessSummary;
+use super::container::{CropMetadata, CropSink};
+use super::sample::{ObjectSampler, StratifyBy, StratumValues, retain_sampled};
+use crate::classes::{self, ClassMasks};
+use crate::dryrun;
+use crate::filter::{
+    self, BorderPolicy, CropRect, DropCounts, DropReason, ObjectFilter, crop_rect,
+};
+use crate::output::{OutputMode, prepare_output_directory};
+use crate::prefetch::Prefetch;
+use crate::remote::RemoteInputs;
+use crate::status::Progress;
+
 #[derive(Debug, Args)]
 pub struct ProcessMaskArgs {
-    #[arg(short = 'i', long, help = "Image directory.", required = true)]
+    #[arg(
+        short = 'i',
+        long,
+        help = "Image directory or s3:// or https:// url.",
+        required = true
+    )]
     pub images: Option<String>,
 
-    #[arg(short = 's', long, help = "Mask directory.")]
+    #[arg(short = 's', long, help = "Mask directory or s3:// or https:// url.")]
     pub masks: Option<String>,
 
     #[arg(short = 'o', long, help = "Output directory.", required = true)]
@@ -29,6 +48,19 @@ pub struct ProcessMaskArgs {
     #[arg(short = 'v', long, help = "Verbose output.")]
     pub verbose: bool,
 
+    #[arg(
+        long,
+        help = "Show a live status panel with progress, throughput, workers, recent errors, and memory usage instead of the progress bar. Logs status lines instead if stdout is not a terminal."
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Format of the status lines logged instead of the status panel (plain, json). Json status lines are also logged without --tui.",
+        default_value = "plain"
+    )]
+    pub log_format: Option<String>,
+
     #[arg(short = 'd', long, help = "Exclude objects touching edge of image.")]
     pub drop_borders: bool,
 
@@ -56,11 +88,101 @@ pub struct ProcessMaskArgs {
 
     #[arg(
         long,
-        help = "Exclude objects smaller than a minimum size.",
+        help = "Layout of mask files (auto, cellpose-seg). Cellpose-seg reads the masks of Cellpose _seg.npy files.",
+        default_value = "auto"
+    )]
+    pub mask_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting masks by their path relative to their directory.",
+        conflicts_with_all = ["mask_substring", "mask_regex"]
+    )]
+    pub mask_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting masks by their path relative to their directory.",
+        conflicts_with = "mask_substring"
+    )]
+    pub mask_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with an unpadded bounding box width or height smaller than a minimum size (pixels).",
         default_value = "1"
     )]
     pub min_size: Option<u32>,
 
+    #[arg(
+        long,
+        help = "Exclude objects with a mask area smaller than a minimum area (pixels)."
+    )]
+    pub min_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with a mask area larger than a maximum area (pixels)."
+    )]
+    pub max_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Class mask directory with the semantic class id of each pixel (e.g. from a panoptic segmentation). Adds the majority class id (class_id) and its pixel fraction (class_frac) for each object."
+    )]
+    pub class_mask: Option<String>,
+
+    #[arg(
+        long,
+        help = "Substring specifying class masks (e.g. _class).",
+        requires = "class_mask"
+    )]
+    pub class_substring: Option<String>,
+
+    #[arg(
+        long,
+        help = "Number of objects sampled from each image after filtering, spread evenly across strata if --stratify-by is set."
+    )]
+    pub sample_per_image: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Descriptor stratifying sampled objects. One of area, class, or a form descriptor (e.g. form_eccentricity).",
+        requires = "sample_per_image"
+    )]
+    pub stratify_by: Option<String>,
+
+    #[arg(
+        long,
+        help = "Number of quantile strata of a numeric --stratify-by descriptor (defaults to 4).",
+        requires = "stratify_by"
+    )]
+    pub strata: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Compute quantile strata from the objects of every image in a pass over all segments instead of each image.",
+        requires = "stratify_by"
+    )]
+    pub global_strata: bool,
+
+    #[arg(long, help = "Random seed for sampling objects.", default_value = "0")]
+    pub seed: Option<u64>,
+
     #[arg(
         short = 'e',
         long,
@@ -77,108 +199,219 @@ pub struct ProcessMaskArgs {
     )]
     pub array_format: Option<String>,
 
+    #[arg(
+        long,
+        help = "Container to store extracted object images (e.g. files, hdf5, zarr). Polygons and bounding boxes are always saved as files.",
+        default_value = "files"
+    )]
+    pub output_container: Option<String>,
+
+    #[arg(
+        long,
+        help = "Embed the source image, label, bounding box, and polygon of each object as json in a text chunk of its png crops. Requires png image_format and files output_container."
+    )]
+    pub embed_metadata: bool,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (crops, segments, and reports) from the output directory before processing. Without it, output directories holding a previous run are rejected."
+    )]
+    pub overwrite: bool,
+
+    #[arg(
+        long,
+        help = "Number of decoded images queued ahead of the compute threads. If 0, images are decoded by the compute threads.",
+        default_value = "0"
+    )]
+    pub prefetch: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Number of threads decoding images ahead of the compute threads if --prefetch is set.",
+        default_value = "2"
+    )]
+    pub io_threads: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Directory caching objects fetched from s3:// or https:// inputs. Cached objects are re-used by later runs. If not set, objects are fetched into a temporary directory and removed once their image is processed."
+    )]
+    pub remote_cache: Option<String>,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Report the pairs, object counts, and estimated output size as a table and in dry_run.json next to the output, without extracting objects or creating the output."
+    )]
+    pub dry_run: bool,
 }
 
 pub fn process_image_mask(args: &ProcessMaskArgs) {
-    let mode = args.mode.to_owned().unwrap_or("cmbfpx".to_string());
-    let pad = args.pad.unwrap_or(1);
-    let min_size = args.min_size.unwrap_or(1);
-    let image_format = args.image_format.to_owned().unwrap_or("png".to_string());
-    let array_format = args.array_format.to_owned().unwrap_or("json".to_string());
-
-    let threads = if let Some(t) = args.threads {
-        t
-    } else {
-        std::thread::available_parallelism().unwrap_or_else(|_| {
-            eprintln!("[thyme::process::mask] Could not automatically assign number of tasks. Please manually set the --threads (-t) argument.");
-            std::process::exit(1);
-        }).get()
-    };
-
-    if mode
-        .chars()
-        .any(|c| !matches!(c, 'c' | 'm' | 'b' | 'f' | 'p' | 'x'))
-    {
-        eprintln!(
-            "[thyme::process::mask] Invalid mode. Argument mode must only contain one or more of: c, f, b, m, p, x."
-        );
+    if let Err(err) = run_process_mask(&ProcessMaskConfig::from(args)) {
+        eprintln!("[thyme::process::mask] ERROR: {}", err);
         std::process::exit(1);
     }
+}
 
-    if min_size < 1 {
-        eprintln!("[thyme::process::mask] ERROR: min_size cannot be less than 1.0.");
-        std::process::exit(1);
+impl From<&ProcessMaskArgs> for ProcessMaskConfig {
+    fn from(args: &ProcessMaskArgs) -> Self {
+        let defaults = ProcessMaskConfig::default();
+
+        ProcessMaskConfig {
+            images: args.images.to_owned().unwrap_or_default(),
+            masks: args.masks.to_owned(),
+            output: args.output.to_owned().unwrap_or_default(),
+            verbose: args.verbose,
+            tui: args.tui,
+            log_format: args.log_format.to_owned().unwrap_or(defaults.log_format),
+            drop_borders: args.drop_borders,
+            mode: args.mode.to_owned().unwrap_or(defaults.mode),
+            pad: args.pad.unwrap_or(defaults.pad),
+            image_substring: args.image_substring.to_owned(),
+            mask_substring: args.mask_substring.to_owned(),
+            mask_format: args.mask_format.to_owned().unwrap_or(defaults.mask_format),
+            image_glob: args.image_glob.to_owned(),
+            image_regex: args.image_regex.to_owned(),
+            mask_glob: args.mask_glob.to_owned(),
+            mask_regex: args.mask_regex.to_owned(),
+            min_size: args.min_size.unwrap_or(defaults.min_size),
+            min_area: args.min_area,
+            max_area: args.max_area,
+            class_mask: args.class_mask.to_owned(),
+            class_substring: args.class_substring.to_owned(),
+            sample_per_image: args.sample_per_image,
+            stratify_by: args.stratify_by.to_owned(),
+            strata: args.strata,
+            global_strata: args.global_strata,
+            seed: args.seed.unwrap_or(defaults.seed),
+            image_format: args
+                .image_format
+                .to_owned()
+                .unwrap_or(defaults.image_format),
+            array_format: args
+                .array_format
+                .to_owned()
+                .unwrap_or(defaults.array_format),
+            output_container: args
+                .output_container
+                .to_owned()
+                .unwrap_or(defaults.output_container),
+            embed_metadata: args.embed_metadata,
+            overwrite: args.overwrite,
+            prefetch: args.prefetch.unwrap_or(defaults.prefetch),
+            io_threads: args.io_threads.unwrap_or(defaults.io_threads),
+            remote_cache: args.remote_cache.to_owned(),
+            threads: args.threads,
+            dry_run: args.dry_run,
+        }
     }
+}
 
-    if !constant::SUPPORTED_IMAGE_FORMATS.contains(&image_format.as_str()) {
-        eprintln!(
-            "[thyme::process::mask] ERROR: Invalid image_format {}. Must be one of: {:?}.",
-            image_format,
-            constant::SUPPORTED_IMAGE_FORMATS
-        );
-        std::process::exit(1);
-    }
+/// Extract the objects of image and mask pairs as configured
+///
+/// Images that fail to process are reported in `object_errors.tsv` and are
+/// counted in the returned summary.
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the run
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_cli::run_process_mask;
+/// use thyme_config::ProcessMaskConfig;
+///
+/// let config = ProcessMaskConfig::new("images/", "output/")
+///     .masks("masks/")
+///     .mode("cm");
+///
+/// let summary = run_process_mask(&config).unwrap();
+/// println!("{} objects across {} images", summary.objects, summary.images);
+/// ```
+pub fn run_process_mask(config: &ProcessMaskConfig) -> Result<ProcessSummary, ThymeError> {
+    config.validate()?;
+
+    let threads = super::threads(config.threads)?;
+    let mode = config.mode.to_owned();
+    let pad = config.pad;
+
+    let prefetch = Prefetch::new(Some(config.prefetch), Some(config.io_threads))?;
+
+    let filter = ObjectFilter {
+        border_policy: BorderPolicy::from_drop_borders(config.drop_borders),
+        min_size: config.min_size,
+        min_area: config.min_area,
+        max_area: config.max_area,
+        skip_invalid_polygons: false,
+    };
 
-    if !constant::SUPPORTED_ARRAY_FORMATS.contains(&array_format.as_str()) {
-        eprintln!(
-            "[thyme::process::mask] ERROR: Invalid array_format {}. Must be one of: {:?}.",
-            array_format,
-            constant::SUPPORTED_ARRAY_FORMATS
-        );
-        std::process::exit(1);
-    }
+    filter.validate().map_err(super::invalid)?;
 
-    let image_path = args.images.to_owned().unwrap();
-    let masks_path = args.masks.to_owned().unwrap_or(image_path.clone());
+    let mut sampler = ObjectSampler::new(
+        config.sample_per_image,
+        config.stratify_by.as_deref(),
+        config.strata,
+        config.global_strata,
+        config.seed,
+    )?;
 
-    if image_path == masks_path && args.image_substring == args.mask_substring {
-        eprintln!(
-            "[thyme::process::mask] ERROR: If images and masks are located in same path, different image and mask substrings must be provided."
-        );
-        std::process::exit(1);
-    }
+    let mask_format = im::MaskFormat::parse(&config.mask_format)?;
 
-    let image_files = ut::path::collect_file_paths(
-        &image_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.image_substring.to_owned(),
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    let image_path = config.images.to_owned();
+    let masks_path = config.masks_path().to_owned();
 
-    let mask_files = ut::path::collect_file_paths(
-        &masks_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.mask_substring.to_owned(),
+    let remote = RemoteInputs::new(config.remote_cache.as_deref());
+
+    let image_files = ut::path::FileSelector::new(
+        config.image_substring.as_deref(),
+        config.image_glob.as_deref(),
+        config.image_regex.as_deref(),
     )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    .and_then(|selector| {
+        remote.select_file_paths(
+            &image_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
+
+    let mask_files = ut::path::FileSelector::new(
+        config.mask_substring.as_deref(),
+        config.mask_glob.as_deref(),
+        config.mask_regex.as_deref(),
+    )
+    .and_then(|selector| {
+        remote.select_file_paths(
+            &masks_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
 
     if image_files.is_empty() {
-        eprintln!(
-            "[thyme::process::mask] ERROR: No image files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No image files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
     if mask_files.is_empty() {
-        eprintln!(
-            "[thyme::process::mask] ERROR: No mask files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No mask files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
     let mut pairs = ut::path::collect_file_pairs(
         &image_files,
         &mask_files,
-        args.image_substring.to_owned(),
-        args.mask_substring.to_owned(),
+        config.image_substring.to_owned(),
+        config.mask_substring.to_owned(),
     );
 
     pairs.sort_unstable();
@@ -188,66 +421,137 @@ pub fn process_image_mask(args: &ProcessMaskArgs) {
             "Detected {} image and mask pairs.",
             ut::track::thousands_format(pairs.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    let output = PathBuf::from(args.output.to_owned().unwrap());
-
-    let output = ut::path::create_directory(&output).unwrap_or_else(|_| {
-        eprintln!("[thyme::process::mask] ERROR: Could not create directory.");
-        std::process::exit(1);
-    });
-
-    if mode.contains("c") {
-        std::fs::create_dir(output.join("complete")).unwrap();
+    let class_masks: Option<ClassMasks> = config
+        .class_mask
+        .as_ref()
+        .map(|class_path| {
+            ClassMasks::collect(
+                class_path,
+                config.class_substring.to_owned(),
+                &image_files,
+                config.image_substring.to_owned(),
+            )
+        })
+        .transpose()?;
+
+    if let Some(class_masks) = &class_masks {
+        ut::track::progress_log(
+            &format!(
+                "Detected {} class masks.",
+                ut::track::thousands_format(class_masks.len())
+            ),
+            config.verbose,
+        );
     }
 
-    if mode.contains("m") {
-        std::fs::create_dir(output.join("mask")).unwrap();
-    }
+    if config.dry_run {
+        let images = dryrun::scan(&pairs, pad, |_, _, mask, _| {
+            dryrun::mask_boxes(vec![im::ThymeMask::open_format(mask, mask_format)?])
+        });
 
-    if mode.contains("f") {
-        std::fs::create_dir(output.join("foreground")).unwrap();
+        return super::dry_run("process::mask", &config.output, &mode, images);
     }
 
-    if mode.contains("b") {
-        std::fs::create_dir(output.join("background")).unwrap();
-    }
+    if let Some(sampler) = sampler.as_mut().filter(|sampler| sampler.global()) {
+        ut::track::progress_log("Computing global strata.", config.verbose);
+
+        let edges = sampler.global_edges(&pairs, threads, |(_, _, mask_path)| {
+            remote.fetch(mask_path)?;
+            let _staged = remote.staged(&[mask_path]);
+
+            let mut mask = im::ThymeMask::open_format(mask_path, mask_format)?;
+            let (labels, polygons) = mask.polygons()?;
+            let bounding_boxes = polygons.to_bounding_boxes()?;
+            let areas = mask.areas(&labels);
+
+            Ok(bounding_boxes
+                .as_xyxy()
+                .iter()
+                .enumerate()
+                .filter(|(idx, xyxy)| {
+                    let (width, height) = (mask.width(), mask.height());
+                    filter
+                        .check(xyxy, areas[*idx] as f32, width, height)
+                        .is_ok()
+                        && crop_rect(xyxy, pad, width, height).is_some()
+                })
+                .map(|(idx, _)| sampler.value(areas[idx] as f32, Some(&polygons.as_points()[idx])))
+                .collect())
+        });
 
-    if mode.contains("p") {
-        std::fs::create_dir(output.join("polygons")).unwrap();
+        sampler.edges = Some(edges);
     }
 
-    if mode.contains("x") {
-        std::fs::create_dir(output.join("bounding_boxes")).unwrap();
-    }
+    let output = prepare_output_directory(
+        Path::new(&config.output),
+        OutputMode::new(config.overwrite, false)?,
+        &config.inputs(),
+        "process::mask",
+    )?;
+
+    let (crops, writer) = super::crop_sink(
+        &output,
+        &mode,
+        &config.output_container,
+        &config.image_format,
+        config.embed_metadata,
+    )?;
 
-    let rt = tokio::runtime::Runtime::new().unwrap();
+    let progress = Progress::new(
+        pairs.len(),
+        "Processing",
+        config.verbose,
+        config.tui,
+        &config.log_format,
+    );
 
-    let results = rt.block_on(run_all(
+    let results = run_all(
         pairs,
+        mask_format,
+        class_masks,
         pad,
-        args.drop_borders,
-        min_size,
+        filter,
+        sampler.as_ref(),
         &mode,
         &output,
-        &image_format,
-        &array_format,
+        crops,
+        &config.array_format,
+        &prefetch,
+        &remote,
         threads,
-        args.verbose,
-    ));
+        progress,
+    );
+
+    super::finish_writer(writer)?;
 
     let objects: Mutex<usize> = Mutex::new(0);
     let success: Mutex<Vec<String>> = Mutex::new(vec![]);
     let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(results.len()));
+    let classified: Mutex<Vec<(String, Vec<String>)>> = Mutex::new(vec![]);
+    let sampled: Mutex<Vec<(String, Vec<String>)>> = Mutex::new(vec![]);
 
     results.into_par_iter().for_each(|(id, run)| {
-        if let Ok(n_objects) = run {
+        if let Ok((n_objects, dropped, object_classes, strata)) = run {
+            if sampler.is_some() {
+                sampled.lock().unwrap().push((id.clone(), strata));
+            }
+
+            if let Some(object_classes) = object_classes {
+                classified
+                    .lock()
+                    .unwrap()
+                    .push((id.clone(), object_classes));
+            }
+
             *objects.lock().unwrap() += n_objects as usize;
-            success
-                .lock()
-                .unwrap()
-                .push(format!("{}\t{}", id, n_objects));
+            success.lock().unwrap().push(filter::object_counts_row(
+                &id,
+                n_objects as usize,
+                &dropped,
+            ));
         } else {
             failure
                 .lock()
@@ -260,7 +564,13 @@ pub fn process_image_mask(args: &ProcessMaskArgs) {
     let success = success.into_inner().unwrap();
     let failure = failure.into_inner().unwrap();
 
-    if args.verbose {
+    let mut classified = classified.into_inner().unwrap();
+    classified.sort_unstable();
+
+    let mut sampled = sampled.into_inner().unwrap();
+    sampled.sort_unstable();
+
+    if config.verbose {
         println!();
     }
 
@@ -270,34 +580,52 @@ pub fn process_image_mask(args: &ProcessMaskArgs) {
             ut::track::thousands_format(objects),
             ut::track::thousands_format(success.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    if !success.is_empty() {
-        std::fs::write(output.join("object_counts.tsv"), success.join("\n")).unwrap();
-    }
+    if !classified.is_empty() {
+        let mut table = String::from("image\tobject\tclass_id\tclass_frac");
+
+        for row in classified.iter().flat_map(|(_, rows)| rows) {
+            table.push('\n');
+            table.push_str(row);
+        }
 
-    if !failure.is_empty() {
-        std::fs::write(output.join("object_errors.tsv"), failure.join("\n")).unwrap();
+        io::write_bytes_atomic(output.join("object_classes.tsv"), table)?;
     }
+
+    let sampled: Vec<String> = sampled.into_iter().flat_map(|(_, rows)| rows).collect();
+
+    super::write_reports(&output, &success, &failure, &sampled)?;
+
+    crate::output::finish_run(&output)?;
+
+    Ok(ProcessSummary {
+        images: success.len(),
+        failures: failure.len(),
+        objects,
+        output,
+        dry_run: None,
+    })
 }
 
-#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn extract(
     id: &str,
+    image: im::ThymeImage,
     image_path: &Path,
     mask_path: &Path,
+    mask_format: im::MaskFormat,
+    classes_path: Option<&Path>,
     pad: u32,
-    drop_borders: bool,
-    min_size: u32,
+    filter: &ObjectFilter,
+    sampler: Option<&ObjectSampler>,
     mode: &str,
     output: &Path,
-    image_format: &str,
+    crops: &CropSink,
     array_format: &str,
-) -> Result<u32, ThymeError> {
-    let image = im::ThymeImage::open(image_path)?;
-
-    let mut mask = im::ThymeMask::open(mask_path)?;
+) -> Result<(u32, DropCounts, Option<Vec<String>>, Vec<String>), ThymeError> {
+    let mut mask = im::ThymeMask::open_format(mask_path, mask_format)?;
 
     if image.width() != mask.width() || image.height() != mask.height() {
         return Err(ThymeError::OtherError(
@@ -305,84 +633,138 @@ fn extract(
         ));
     }
 
+    let classes = classes_path
+        .map(|path| classes::open(path, &mask))
+        .transpose()?;
+
     let (labels, mut polygons) = mask.polygons()?;
     let mut bounding_boxes = polygons.to_bounding_boxes()?;
+    let areas = mask.areas(&labels);
 
     let width = image.width();
     let height = image.height();
 
     let mut n_objects = 0;
-    let pad_f32 = pad as f32;
-
     let mut remove_indices: Vec<usize> = Vec::with_capacity(bounding_boxes.len());
+    let mut dropped = DropCounts::default();
+    let mut object_classes: Vec<String> = vec![];
+    let mut kept: Vec<(usize, CropRect)> = Vec::with_capacity(bounding_boxes.len());
 
-    for (idx, [min_x, min_y, max_x, max_y]) in bounding_boxes.as_xyxy().iter().enumerate() {
-        let min_x = min_x - pad_f32;
-        let min_y = min_y - pad_f32;
-        let max_x = max_x + pad_f32;
-        let max_y = max_y + pad_f32;
-
-        if drop_borders
-            && (min_x <= 0.0 || min_y <= 0.0 || max_x >= width as f32 || max_y >= height as f32)
-        {
+    for (idx, xyxy) in bounding_boxes.as_xyxy().iter().enumerate() {
+        if let Err(reason) = filter.check(xyxy, areas[idx] as f32, width, height) {
+            dropped.add(reason);
             remove_indices.push(idx);
             continue;
         }
 
-        let min_x = min_x.max(0.0) as u32;
-        let min_y = min_y.max(0.0) as u32;
-        let max_x = max_x.min(width as f32) as u32;
-        let max_y = max_y.min(height as f32) as u32;
-
-        let w = max_x - min_x;
-        let h = max_y - min_y;
-
-        if w < min_size || h < min_size {
+        let Some(rect) = crop_rect(xyxy, pad, width, height) else {
+            dropped.add(DropReason::OutsideImage);
             remove_indices.push(idx);
             continue;
-        }
+        };
+
+        kept.push((idx, rect));
+    }
+
+    let mut strata: Vec<String> = vec![];
+
+    if let Some(sampler) = sampler {
+        let values = match (sampler.stratify_by, &classes) {
+            (Some(StratifyBy::Class), Some(classes)) => StratumValues::Classes(
+                kept.iter()
+                    .map(|(idx, rect)| {
+                        classes::object_class(&mask, classes, rect.as_xywh(), labels[*idx])
+                            .map(|(class_id, _)| class_id.to_string())
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => StratumValues::Numeric(
+                kept.iter()
+                    .map(|(idx, _)| {
+                        sampler.value(areas[*idx] as f32, Some(&polygons.as_points()[*idx]))
+                    })
+                    .collect(),
+            ),
+        };
+
+        let (sampled, rows) = sampler.sample(id, values);
+        strata = rows;
+
+        kept = retain_sampled(kept, &sampled, &mut remove_indices);
+    }
+
+    for (
+        idx,
+        CropRect {
+            x: min_x,
+            y: min_y,
+            width: w,
+            height: h,
+        },
+    ) in kept
+    {
+        let xyxy = &bounding_boxes.as_xyxy()[idx];
 
         let full_object = image.crop(min_x, min_y, w, h)?;
         let mask_object = mask.crop_view(min_x, min_y, w, h);
 
-        let object_name = format!("{}_{}.{}", id, idx, image_format);
+        let metadata = crops.embeds_metadata().then(|| CropMetadata {
+            image: image_path.display().to_string(),
+            object: idx,
+            label: Some(labels[idx].to_string()),
+            bbox: *xyxy,
+            crop: [min_x, min_y, w, h],
+            polygon: Some(polygons.as_points()[idx].clone()),
+        });
 
         if mode.contains("c") {
-            full_object.save(output.join("complete").join(&object_name))?;
+            crops.save("complete", id, idx, full_object, metadata.as_ref())?;
         }
 
         if mode.chars().any(|c| matches!(c, 'm' | 'f' | 'b')) {
             if mode.contains("m") {
-                mask_object.save(output.join("mask").join(&object_name), &labels[idx])?;
+                crops.save_mask(
+                    "mask",
+                    id,
+                    idx,
+                    &mask_object,
+                    &labels[idx],
+                    metadata.as_ref(),
+                )?;
             }
 
             if mode.contains("f") {
-                image
-                    .crop_masked(
-                        min_x,
-                        min_y,
-                        w,
-                        h,
-                        &mask_object,
-                        im::MaskingStyle::Foreground,
-                    )?
-                    .save(output.join("foreground").join(&object_name))?;
+                let foreground = image.crop_masked(
+                    min_x,
+                    min_y,
+                    w,
+                    h,
+                    &mask_object,
+                    im::MaskingStyle::Foreground,
+                )?;
+                crops.save("foreground", id, idx, foreground, metadata.as_ref())?;
             }
 
             if mode.contains("b") {
-                image
-                    .crop_masked(
-                        min_x,
-                        min_y,
-                        w,
-                        h,
-                        &mask_object,
-                        im::MaskingStyle::Background,
-                    )?
-                    .save(output.join("background").join(&object_name))?;
+                let background = image.crop_masked(
+                    min_x,
+                    min_y,
+                    w,
+                    h,
+                    &mask_object,
+                    im::MaskingStyle::Background,
+                )?;
+                crops.save("background", id, idx, background, metadata.as_ref())?;
             }
         }
 
+        if let Some(classes) = &classes {
+            let (class_id, class_frac) =
+                classes::object_class(&mask, classes, [min_x, min_y, w, h], labels[idx])?;
+
+            object_classes.push(format!("{}\t{}\t{}\t{}", id, idx, class_id, class_frac));
+        }
+
         n_objects += 1;
     }
 
@@ -398,67 +780,82 @@ fn extract(
         bounding_boxes.save(output.join("bounding_boxes").join(&object_name))?;
     }
 
-    Ok(n_objects)
+    Ok((n_objects, dropped, classes.map(|_| object_classes), strata))
 }
 
-#[allow(clippy::too_many_arguments)]
-pub async fn run_all(
+/// Extract the objects of every image and segment pair on a pool of `threads` threads
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn run_all(
     pairs: Vec<(String, PathBuf, PathBuf)>,
+    mask_format: im::MaskFormat,
+    class_masks: Option<ClassMasks>,
     pad: u32,
-    drop_borders: bool,
-    min_size: u32,
+    filter: ObjectFilter,
+    sampler: Option<&ObjectSampler>,
     mode: &str,
     output: &Path,
-    image_format: &str,
+    crops: CropSink,
     array_format: &str,
+    prefetch: &Prefetch,
+    remote: &RemoteInputs,
     threads: usize,
-    verbose: bool,
-) -> Vec<(String, Result<u32, ThymeError>)> {
-    let pb = Arc::new(Mutex::new(ut::track::progress_bar(
-        pairs.len(),
-        "Processing",
-        verbose,
-    )));
-
-    stream::iter(pairs)
-        .map(|(id, image, mask)| {
-            let mode = mode.to_string();
-            let output = output.to_path_buf();
-            let image_format = image_format.to_string();
-            let array_format = array_format.to_string();
-            let pb_clone = pb.clone();
-
-            async move {
-                let id_clone = id.clone();
-                let result = tokio::task::spawn_blocking(move || {
+    progress: Progress,
+) -> Vec<(
+    String,
+    Result<(u32, DropCounts, Option<Vec<String>>, Vec<String>), ThymeError>,
+)> {
+    let status = progress.status();
+
+    let results = ut::threads::install(Some(threads), || {
+        prefetch.run(
+            &pairs,
+            progress,
+            |(_, image, mask)| {
+                remote.fetch(image)?;
+                remote.fetch(mask)?;
+                im::ThymeImage::open(image)
+            },
+            |(id, image_path, mask), image| {
+                let _staged = remote.staged(&[image_path, mask]);
+
+                let classes = class_masks
+                    .as_ref()
+                    .map(|class_masks| class_masks.path(id))
+                    .transpose()?;
+
+                status.begin(id);
+
+                // A panic while extracting an image fails the image instead of the run
+                std::panic::catch_unwind(AssertUnwindSafe(|| {
                     extract(
-                        &id,
-                        &image,
-                        &mask,
+                        id,
+                        image,
+                        image_path,
+                        mask,
+                        mask_format,
+                        classes,
                         pad,
-                        drop_borders,
-                        min_size,
-                        &mode,
-                        &output,
-                        &image_format,
-                        &array_format,
+                        &filter,
+                        sampler,
+                        mode,
+                        output,
+                        &crops,
+                        array_format,
                     )
-                })
-                .await
+                }))
                 .unwrap_or_else(|_| {
                     Err(ThymeError::OtherError(
                         "Failed to extract objects.".to_string(),
                     ))
-                });
-
-                if verbose {
-                    pb_clone.lock().unwrap().update(1).unwrap();
-                }
+                })
+                .inspect(|(n_objects, ..)| status.add_objects(*n_objects as usize))
+            },
+        )
+    });
 
-                (id_clone, result)
-            }
-        })
-        .buffer_unordered(threads)
-        .collect::<Vec<_>>()
-        .await
+    pairs
+        .into_iter()
+        .map(|(id, _, _)| id)
+        .zip(results)
+        .collect()
 }
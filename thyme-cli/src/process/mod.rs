@@ -1,14 +1,30 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
 use clap::{Args, Subcommand};
 
+use thyme_core::error::ThymeError;
+use thyme_core::io;
+
+use crate::dryrun::{DryRun, DryRunImage};
+use crate::filter;
+use container::CropSink;
+
 mod boxes;
+mod container;
 mod mask;
 mod polygons;
+mod sample;
 
+pub use boxes::run_process_boxes;
 use boxes::{ProcessBoxesArgs, process_image_boxes};
+pub use container::{CropMetadata, METADATA_KEYWORD};
+pub use mask::run_process_mask;
 use mask::{ProcessMaskArgs, process_image_mask};
+pub use polygons::run_process_polygons;
 use polygons::{ProcessPolygonsArgs, process_image_polygons};
 
 #[derive(Debug, Args)]
@@ -35,3 +51,664 @@ pub fn process(args: &ProcessArgs) {
         ProcessCommands::Polygons(polygons) => process_image_polygons(polygons),
     }
 }
+
+/// Outcome of a process run
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessSummary {
+    /// Number of images whose objects were extracted
+    pub images: usize,
+    /// Number of images that failed to process
+    pub failures: usize,
+    /// Number of objects that were extracted
+    pub objects: usize,
+    /// Output directory
+    pub output: PathBuf,
+    /// Dry run report, if the run was a dry run
+    pub dry_run: Option<PathBuf>,
+}
+
+/// Convert a message from argument parsing into an error
+fn invalid(message: String) -> ThymeError {
+    ThymeError::OtherError(message.trim_end_matches('.').to_string())
+}
+
+/// Number of compute threads, defaulting to all available threads
+fn threads(threads: Option<usize>) -> Result<usize, ThymeError> {
+    match threads {
+        Some(threads) => Ok(threads),
+        None => std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .map_err(|_| {
+                ThymeError::OtherError(
+                    "Could not automatically assign number of threads. Please set threads manually"
+                        .to_string(),
+                )
+            }),
+    }
+}
+
+/// Create the output directories of a mode and the sink receiving its crops
+///
+/// Crops are written as individual files in a directory per crop type, or
+/// are queued to a writer thread that stores them in a single container.
+///
+/// # Arguments
+///
+/// * `output` - Output directory
+/// * `mode` - Object data extracted from each object (e.g. cmbfpx)
+/// * `output_container` - Container storing crops (e.g. files, hdf5, zarr)
+/// * `image_format` - Format of crop files
+/// * `embed_metadata` - Embed object metadata in png crop files
+#[allow(clippy::type_complexity)]
+fn crop_sink(
+    output: &Path,
+    mode: &str,
+    output_container: &str,
+    image_format: &str,
+    embed_metadata: bool,
+) -> Result<(CropSink, Option<JoinHandle<Result<usize, ThymeError>>>), ThymeError> {
+    let mut directories = vec![];
+
+    if output_container == "files" {
+        for (c, directory) in [
+            ('c', "complete"),
+            ('m', "mask"),
+            ('f', "foreground"),
+            ('b', "background"),
+        ] {
+            if mode.contains(c) {
+                directories.push(directory);
+            }
+        }
+    }
+
+    for (c, directory) in [('p', "polygons"), ('x', "bounding_boxes")] {
+        if mode.contains(c) {
+            directories.push(directory);
+        }
+    }
+
+    for directory in directories {
+        std::fs::create_dir(output.join(directory))
+            .map_err(|err| ThymeError::DirError(err.to_string()))?;
+    }
+
+    if output_container != "files" {
+        let (crops, writer) = container::spawn_writer(output, output_container).map_err(|err| {
+            ThymeError::OtherError(format!("Could not create output container. {}", err))
+        })?;
+
+        return Ok((crops, Some(writer)));
+    }
+
+    let crops = CropSink::Files {
+        output: output.to_path_buf(),
+        image_format: image_format.to_string(),
+        embed_metadata,
+    };
+
+    Ok((crops, None))
+}
+
+/// Wait for the output container writer to store every queued crop
+fn finish_writer(writer: Option<JoinHandle<Result<usize, ThymeError>>>) -> Result<(), ThymeError> {
+    let Some(writer) = writer else {
+        return Ok(());
+    };
+
+    writer
+        .join()
+        .unwrap_or_else(|_| {
+            Err(ThymeError::OtherError(
+                "Output container writer panicked".to_string(),
+            ))
+        })
+        .map_err(|err| {
+            ThymeError::OtherError(format!("Failed to write output container. {}", err))
+        })?;
+
+    Ok(())
+}
+
+/// Write the run reports of a process command to its output directory
+///
+/// # Arguments
+///
+/// * `output` - Output directory
+/// * `success` - Object count rows of the processed images
+/// * `failure` - Errors of the images that failed to process
+/// * `sampled` - Sample manifest rows, if objects were sampled
+fn write_reports(
+    output: &Path,
+    success: &[String],
+    failure: &[String],
+    sampled: &[String],
+) -> Result<(), ThymeError> {
+    if !success.is_empty() {
+        io::write_bytes_atomic(
+            output.join("object_counts.tsv"),
+            filter::object_counts(success),
+        )?;
+    }
+
+    if !failure.is_empty() {
+        io::write_bytes_atomic(output.join("object_errors.tsv"), failure.join("\n"))?;
+    }
+
+    if !sampled.is_empty() {
+        io::write_bytes_atomic(
+            output.join("sample_manifest.tsv"),
+            sample::sample_manifest(sampled),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Report the crops of a process run without extracting objects or creating the output
+///
+/// # Arguments
+///
+/// * `command` - Name of the command (e.g. process::mask)
+/// * `output` - Output directory
+/// * `mode` - Object data extracted from each object (e.g. cmbfpx)
+/// * `images` - Images returned by `dryrun::scan`
+fn dry_run(
+    command: &str,
+    output: &str,
+    mode: &str,
+    images: Vec<DryRunImage>,
+) -> Result<ProcessSummary, ThymeError> {
+    let image_crops = mode.chars().filter(|c| "cfb".contains(*c)).count();
+    let mask_crops = mode.contains('m') as usize;
+
+    let output = PathBuf::from(output);
+    let report = DryRun::new(command, &output, images).crops(image_crops, mask_crops);
+    let path = report.finish()?;
+
+    Ok(ProcessSummary {
+        images: report.pairs - report.failures,
+        failures: report.failures,
+        objects: report.objects,
+        output,
+        dry_run: Some(path),
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::Parser;
+
+    use thyme_core::im::{ThymeBuffer, ThymeImage};
+    use thyme_core::io::Hdf5Crops;
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+    use crate::dryrun::DRY_RUN_FILE;
+
+    const IMAGES: usize = 2;
+    const OBJECTS: usize = 6;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: ProcessArgs,
+    }
+
+    fn run(args: &[&str]) {
+        process(&Cli::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args);
+    }
+
+    #[test]
+    fn test_process_synthetic() {
+        let directory = std::env::temp_dir().join("thyme_test_process_synthetic");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: OBJECTS,
+            radius: [3.0, 6.0],
+            seed: 2,
+            ..Default::default()
+        }
+        .write(IMAGES, &data)
+        .unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+            ("boxes", "--box-substring", "_boxes"),
+        ] {
+            let output = directory.join(command);
+
+            run(&[
+                command,
+                "-i",
+                data.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                "-m",
+                "c",
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+            ]);
+
+            let crops = std::fs::read_dir(output.join("complete")).unwrap().count();
+            assert_eq!(crops, IMAGES * OBJECTS, "{}", command);
+
+            let counts = std::fs::read_to_string(output.join("object_counts.tsv")).unwrap();
+            let counts: Vec<usize> = counts
+                .lines()
+                .skip(1)
+                .map(|row| row.split('\t').nth(1).unwrap().parse().unwrap())
+                .collect();
+
+            assert_eq!(counts, [OBJECTS; IMAGES], "{}", command);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_process_hdf5_container() {
+        let directory = std::env::temp_dir().join("thyme_test_process_hdf5_container");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: OBJECTS,
+            radius: [3.0, 6.0],
+            seed: 2,
+            ..Default::default()
+        }
+        .write(IMAGES, &data)
+        .unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+            ("boxes", "--box-substring", "_boxes"),
+        ] {
+            for container in ["files", "hdf5"] {
+                let output = directory.join(format!("{}_{}", command, container));
+
+                run(&[
+                    command,
+                    "-i",
+                    data.to_str().unwrap(),
+                    "-o",
+                    output.to_str().unwrap(),
+                    "-m",
+                    "c",
+                    "--image-substring",
+                    "_image",
+                    segments,
+                    substring,
+                    "--output-container",
+                    container,
+                ]);
+            }
+
+            let files = directory.join(format!("{}_files", command));
+            let output = directory.join(format!("{}_hdf5", command));
+
+            assert!(!output.join("complete").exists(), "{}", command);
+
+            let crops = Hdf5Crops::open(output.join("crops.h5")).unwrap();
+
+            for image in 0..IMAGES {
+                for object in 0..OBJECTS {
+                    let id = format!("synth_000{}", image);
+
+                    let crop = crops.read(&format!("complete/{}/{}", id, object)).unwrap();
+
+                    let file = ThymeImage::open(
+                        files
+                            .join("complete")
+                            .join(format!("{}_{}.png", id, object)),
+                    )
+                    .unwrap();
+
+                    assert_eq!(crop.shape(), file.shape(), "{}", command);
+
+                    match (crop, file) {
+                        (ThymeImage::U8(a), ThymeImage::U8(b)) => {
+                            assert_eq!(a.as_raw(), b.as_raw(), "{}", command)
+                        }
+                        (ThymeImage::U16(a), ThymeImage::U16(b)) => {
+                            assert_eq!(a.as_raw(), b.as_raw(), "{}", command)
+                        }
+                        _ => panic!("Crop data type was not preserved"),
+                    }
+                }
+            }
+
+            assert!(
+                crops
+                    .read(&format!("complete/synth_0000/{}", OBJECTS))
+                    .is_err()
+            );
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_process_dry_run() {
+        let directory = std::env::temp_dir().join("thyme_test_process_dry_run");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: OBJECTS,
+            radius: [3.0, 6.0],
+            seed: 2,
+            ..Default::default()
+        }
+        .write(IMAGES, &data)
+        .unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+            ("boxes", "--box-substring", "_boxes"),
+        ] {
+            let output = directory.join(command);
+
+            run(&[
+                command,
+                "-i",
+                data.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                "-m",
+                "c",
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+                "--dry-run",
+            ]);
+
+            assert!(!output.exists(), "{}", command);
+
+            let report = std::fs::read_to_string(directory.join(DRY_RUN_FILE)).unwrap();
+            let report: DryRun = serde_json::from_str(&report).unwrap();
+
+            assert_eq!(report.pairs, IMAGES, "{}", command);
+            assert_eq!(report.objects, IMAGES * OBJECTS, "{}", command);
+            assert_eq!(report.columns, None);
+
+            // Complete crops of every object with 16-bit synthetic images
+            let crop_pixels: u64 = report.images.iter().map(|image| image.crop_pixels).sum();
+            assert!(crop_pixels > 0);
+            assert!(report.estimated_bytes > crop_pixels);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_process_class_mask() {
+        let directory = std::env::temp_dir().join("thyme_test_process_class_mask");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: OBJECTS,
+            radius: [3.0, 6.0],
+            seed: 2,
+            ..Default::default()
+        }
+        .write(IMAGES, &data)
+        .unwrap();
+
+        // Every pixel of the first image belongs to class 3 and the second image has class 0
+        for (i, class) in [3, 0].into_iter().enumerate() {
+            ThymeImage::U16(ThymeBuffer::new(64, 64, 1, vec![class; 64 * 64]).unwrap())
+                .save(data.join(format!("synth_000{}_class.png", i)))
+                .unwrap();
+        }
+
+        let output = directory.join("mask");
+
+        run(&[
+            "mask",
+            "-i",
+            data.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "-m",
+            "c",
+            "--image-substring",
+            "_image",
+            "--mask-substring",
+            "_mask",
+            "--class-mask",
+            data.to_str().unwrap(),
+            "--class-substring",
+            "_class",
+        ]);
+
+        let classes = std::fs::read_to_string(output.join("object_classes.tsv")).unwrap();
+        let rows: Vec<Vec<&str>> = classes
+            .lines()
+            .map(|row| row.split('\t').collect())
+            .collect();
+
+        assert_eq!(rows[0], ["image", "object", "class_id", "class_frac"]);
+        assert_eq!(rows.len(), 1 + IMAGES * OBJECTS);
+
+        for row in &rows[1..] {
+            let expected = if row[0] == "synth_0000" { "3" } else { "0" };
+            assert_eq!((row[2], row[3]), (expected, "1"));
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_process_stratified_sample() {
+        let directory = std::env::temp_dir().join("thyme_test_process_stratified_sample");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+        std::fs::create_dir_all(&data).unwrap();
+
+        // The first image has 10 small and 2 large objects and the second image
+        // has 2 small and 10 large objects, each in a cell of an 8 x 8 grid
+        for (i, small) in [10, 2].into_iter().enumerate() {
+            let mut mask = vec![0u16; 64 * 64];
+
+            for object in 0..12 {
+                let (x, y) = (8 * (object % 8) + 1, 8 * (object / 8) + 1);
+                let side = if object < small { 2 } else { 6 };
+
+                for row in y..y + side {
+                    for col in x..x + side {
+                        mask[row * 64 + col] = object as u16 + 1;
+                    }
+                }
+            }
+
+            ThymeImage::U16(ThymeBuffer::new(64, 64, 1, mask).unwrap())
+                .save(data.join(format!("bimodal_000{}_mask.png", i)))
+                .unwrap();
+
+            ThymeImage::U8(ThymeBuffer::new(64, 64, 1, vec![100u8; 64 * 64]).unwrap())
+                .save(data.join(format!("bimodal_000{}_image.png", i)))
+                .unwrap();
+        }
+
+        let output = directory.join("mask");
+
+        run(&[
+            "mask",
+            "-i",
+            data.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "-m",
+            "cp",
+            "--image-substring",
+            "_image",
+            "--mask-substring",
+            "_mask",
+            "--sample-per-image",
+            "4",
+            "--stratify-by",
+            "area",
+            "--strata",
+            "2",
+            "--global-strata",
+            "--seed",
+            "7",
+        ]);
+
+        let crops = std::fs::read_dir(output.join("complete")).unwrap().count();
+        assert_eq!(crops, 2 * 4);
+
+        let manifest = std::fs::read_to_string(output.join("sample_manifest.tsv")).unwrap();
+        let rows: Vec<Vec<&str>> = manifest
+            .lines()
+            .map(|row| row.split('\t').collect())
+            .collect();
+
+        assert_eq!(
+            rows,
+            [
+                vec!["image", "stratum", "lower", "upper", "total", "sampled"],
+                vec!["bimodal_0000", "0", "-inf", "36", "10", "2"],
+                vec!["bimodal_0000", "1", "36", "inf", "2", "2"],
+                vec!["bimodal_0001", "0", "-inf", "36", "2", "2"],
+                vec!["bimodal_0001", "1", "36", "inf", "10", "2"],
+            ]
+        );
+
+        // Polygons of unsampled objects are removed
+        for i in 0..2 {
+            let polygons = thyme_core::im::Polygons::open(
+                output
+                    .join("polygons")
+                    .join(format!("bimodal_000{}.json", i)),
+            )
+            .unwrap();
+
+            assert_eq!(polygons.len(), 4);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_run_process_configs() {
+        let directory = std::env::temp_dir().join("thyme_test_run_process_configs");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: OBJECTS,
+            radius: [3.0, 6.0],
+            seed: 2,
+            ..Default::default()
+        }
+        .write(IMAGES, &data)
+        .unwrap();
+
+        let data = data.to_str().unwrap();
+
+        let crops = |output: &Path| {
+            let mut crops: Vec<String> = std::fs::read_dir(output.join("complete"))
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+
+            crops.sort_unstable();
+            crops
+        };
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+            ("boxes", "--box-substring", "_boxes"),
+        ] {
+            let cli = directory.join(format!("{}_cli", command));
+            let library = directory.join(format!("{}_library", command));
+            let output = library.to_str().unwrap();
+
+            run(&[
+                command,
+                "-i",
+                data,
+                "-o",
+                cli.to_str().unwrap(),
+                "-m",
+                "c",
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+            ]);
+
+            let summary = match command {
+                "mask" => run_process_mask(
+                    &thyme_config::ProcessMaskConfig::new(data, output)
+                        .image_substring("_image")
+                        .mask_substring(substring)
+                        .mode("c"),
+                ),
+                "polygons" => run_process_polygons(
+                    &thyme_config::ProcessPolygonsConfig::new(data, output)
+                        .image_substring("_image")
+                        .polygon_substring(substring)
+                        .mode("c"),
+                ),
+                _ => run_process_boxes(
+                    &thyme_config::ProcessBoxesConfig::new(data, output)
+                        .image_substring("_image")
+                        .box_substring(substring)
+                        .mode("c"),
+                ),
+            }
+            .unwrap();
+
+            assert_eq!(summary.images, IMAGES, "{}", command);
+            assert_eq!(summary.failures, 0, "{}", command);
+            assert_eq!(summary.objects, IMAGES * OBJECTS, "{}", command);
+            assert_eq!(summary.output, library, "{}", command);
+
+            // Configs built in code extract the same objects as the command line
+            assert_eq!(crops(&cli), crops(&library), "{}", command);
+        }
+
+        // Invalid configs and inputs are returned as errors
+        let unused = directory.join("unused");
+        let config = thyme_config::ProcessMaskConfig::new(data, unused.to_str().unwrap())
+            .image_substring("_image")
+            .mask_substring("_mask");
+
+        assert!(run_process_mask(&config.clone().mode("q")).is_err());
+        assert!(run_process_mask(&config.clone().output_container("tar")).is_err());
+        assert!(run_process_mask(&config.clone().mask_substring("_missing")).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
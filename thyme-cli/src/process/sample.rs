@@ -0,0 +1,316 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::mp::form;
+use thyme_core::ut;
+
+/// Header for the per-stratum sampled and total object counts written alongside process outputs
+pub const SAMPLE_MANIFEST_HEADER: &str = "image\tstratum\tlower\tupper\ttotal\tsampled";
+
+/// Descriptor stratifying the objects sampled from each image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StratifyBy {
+    /// Mask pixel count, polygon area, or bounding box area
+    Area,
+    /// Majority class of a class mask or label of a bounding box
+    Class,
+    /// Form descriptor of the object polygon (index into `FORM_DESCRIPTORS`)
+    Form(usize),
+}
+
+impl StratifyBy {
+    /// Parse a stratification descriptor (area, class, or a form descriptor)
+    pub fn parse(name: &str) -> Result<Self, ThymeError> {
+        match name {
+            "area" => Ok(StratifyBy::Area),
+            "class" => Ok(StratifyBy::Class),
+            _ => constant::FORM_DESCRIPTORS
+                .iter()
+                .position(|feature| {
+                    feature.name == name || feature.name.strip_prefix("form_") == Some(name)
+                })
+                .map(StratifyBy::Form)
+                .ok_or_else(|| {
+                    ThymeError::OtherError(format!(
+                        "Invalid stratify_by '{}'. Must be one of: area, class, or a form descriptor (e.g. form_eccentricity)",
+                        name
+                    ))
+                }),
+        }
+    }
+
+    /// Whether the descriptor is measured on the object polygon
+    pub fn requires_polygons(&self) -> bool {
+        matches!(self, StratifyBy::Form(_))
+    }
+}
+
+/// Stratification values of the objects of one image
+pub enum StratumValues {
+    /// Numeric descriptors binned into quantile strata
+    Numeric(Vec<f32>),
+    /// Classes used directly as strata
+    Classes(Vec<String>),
+}
+
+/// Sampling of a fixed number of objects per image, optionally stratified
+#[derive(Debug, Clone)]
+pub struct ObjectSampler {
+    /// Number of objects sampled from each image
+    pub per_image: usize,
+    /// Descriptor stratifying sampled objects
+    pub stratify_by: Option<StratifyBy>,
+    /// Number of quantile strata of numeric descriptors
+    pub strata: usize,
+    /// Random seed
+    pub seed: u64,
+    /// Bin edges shared by every image if strata are computed globally
+    pub edges: Option<Vec<f32>>,
+}
+
+impl ObjectSampler {
+    /// Validate sampling arguments, returning no sampler if objects are not sampled
+    ///
+    /// # Arguments
+    ///
+    /// * `per_image` - Number of objects sampled from each image
+    /// * `stratify_by` - Descriptor stratifying sampled objects
+    /// * `strata` - Number of quantile strata of numeric descriptors (default 4)
+    /// * `global_strata` - Compute strata from the objects of every image
+    /// * `seed` - Random seed
+    pub fn new(
+        per_image: Option<usize>,
+        stratify_by: Option<&str>,
+        strata: Option<usize>,
+        global_strata: bool,
+        seed: u64,
+    ) -> Result<Option<ObjectSampler>, ThymeError> {
+        let Some(per_image) = per_image else {
+            return Ok(None);
+        };
+
+        if per_image == 0 {
+            return Err(ThymeError::OtherError(
+                "sample_per_image must be greater than 0".to_string(),
+            ));
+        }
+
+        let stratify_by = stratify_by.map(StratifyBy::parse).transpose()?;
+
+        if stratify_by == Some(StratifyBy::Class) && (strata.is_some() || global_strata) {
+            return Err(ThymeError::OtherError(
+                "strata and global_strata are only supported for numeric stratify_by descriptors"
+                    .to_string(),
+            ));
+        }
+
+        let strata = strata.unwrap_or(4);
+
+        if strata == 0 {
+            return Err(ThymeError::OtherError(
+                "strata must be greater than 0".to_string(),
+            ));
+        }
+
+        Ok(Some(ObjectSampler {
+            per_image,
+            stratify_by,
+            strata,
+            seed,
+            edges: global_strata.then(Vec::new),
+        }))
+    }
+
+    /// Whether strata are computed from the objects of every image
+    pub fn global(&self) -> bool {
+        self.edges.is_some()
+    }
+
+    /// Numeric stratification value of an object
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - Area of the object used by the object filters
+    /// * `points` - Polygon of the object if stratifying by a form descriptor
+    pub fn value(&self, area: f32, points: Option<&[[f32; 2]]>) -> f32 {
+        match (self.stratify_by, points) {
+            (Some(StratifyBy::Form(i)), Some(points)) => form::descriptors(points)[i],
+            _ => area,
+        }
+    }
+
+    /// Bin edges shared by every image computed in a pass over all segments
+    ///
+    /// Images whose segments fail to read are skipped and reported as
+    /// failures when their objects are extracted.
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - Image and segment pairs
+    /// * `threads` - Number of threads
+    /// * `values` - Stratification values of the objects kept in an image
+    pub fn global_edges<F>(
+        &self,
+        pairs: &[(String, PathBuf, PathBuf)],
+        threads: usize,
+        values: F,
+    ) -> Vec<f32>
+    where
+        F: Fn(&(String, PathBuf, PathBuf)) -> Result<Vec<f32>, ThymeError> + Sync,
+    {
+        let values: Vec<f32> = ut::threads::install(Some(threads), || {
+            pairs
+                .par_iter()
+                .filter_map(|pair| values(pair).ok())
+                .flatten()
+                .collect()
+        });
+
+        ut::sample::quantile_edges(&values, self.strata)
+    }
+
+    /// Sample the objects of an image
+    ///
+    /// Returns the positions of the sampled objects in `values`, in ascending
+    /// order, and a sample manifest row for each stratum.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Image identifier
+    /// * `values` - Stratification values of the objects kept in the image
+    pub fn sample(&self, id: &str, values: StratumValues) -> (Vec<usize>, Vec<String>) {
+        // Seeded by image so samples do not depend on the order images are processed
+        let seed = id
+            .bytes()
+            .fold(self.seed ^ 0xCBF29CE484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001B3)
+            });
+
+        let (strata, names, bounds): (Vec<usize>, Vec<String>, Vec<[String; 2]>) =
+            match (self.stratify_by, values) {
+                (None, values) => {
+                    let n = match values {
+                        StratumValues::Numeric(values) => values.len(),
+                        StratumValues::Classes(values) => values.len(),
+                    };
+
+                    (
+                        vec![0; n],
+                        vec!["all".to_string()],
+                        vec![Default::default()],
+                    )
+                }
+                (Some(_), StratumValues::Classes(values)) => {
+                    let classes: Vec<&String> =
+                        values.iter().collect::<BTreeSet<_>>().into_iter().collect();
+
+                    let strata = values
+                        .iter()
+                        .map(|value| classes.binary_search(&value).unwrap())
+                        .collect();
+
+                    let names = classes.iter().map(|class| class.to_string()).collect();
+
+                    (strata, names, vec![Default::default(); classes.len()])
+                }
+                (Some(_), StratumValues::Numeric(values)) => {
+                    let edges = match &self.edges {
+                        Some(edges) => edges.clone(),
+                        None => ut::sample::quantile_edges(&values, self.strata),
+                    };
+
+                    let strata = values
+                        .iter()
+                        .map(|value| ut::sample::stratum(*value, &edges))
+                        .collect();
+
+                    let names = (0..=edges.len()).map(|k| k.to_string()).collect();
+
+                    let bounds = (0..=edges.len())
+                        .map(|k| {
+                            [
+                                k.checked_sub(1)
+                                    .map_or("-inf".to_string(), |k| edges[k].to_string()),
+                                edges
+                                    .get(k)
+                                    .map_or("inf".to_string(), |edge| edge.to_string()),
+                            ]
+                        })
+                        .collect();
+
+                    (strata, names, bounds)
+                }
+            };
+
+        let sampled = ut::sample::sample_strata(&strata, self.per_image, seed);
+
+        let mut counts = vec![[0usize; 2]; names.len()];
+        for stratum in strata.iter() {
+            counts[*stratum][0] += 1;
+        }
+
+        for row in sampled.iter() {
+            counts[strata[*row]][1] += 1;
+        }
+
+        let rows = names
+            .iter()
+            .zip(bounds)
+            .zip(counts)
+            .map(|((name, [lower, upper]), [total, sampled])| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    id, name, lower, upper, total, sampled
+                )
+            })
+            .collect();
+
+        (sampled, rows)
+    }
+}
+
+/// Keep the sampled objects of an image and mark the others for removal
+///
+/// # Arguments
+///
+/// * `kept` - Index of each object kept by the filters, in ascending order
+/// * `sampled` - Positions of the sampled objects in `kept`
+/// * `remove_indices` - Indices of objects removed from saved segments, sorted on return
+pub fn retain_sampled<T>(
+    kept: Vec<(usize, T)>,
+    sampled: &[usize],
+    remove_indices: &mut Vec<usize>,
+) -> Vec<(usize, T)> {
+    let mut sampled = sampled.iter().peekable();
+    let mut retained = Vec::with_capacity(sampled.len());
+
+    for (position, object) in kept.into_iter().enumerate() {
+        if sampled.next_if_eq(&&position).is_some() {
+            retained.push(object);
+        } else {
+            remove_indices.push(object.0);
+        }
+    }
+
+    remove_indices.sort_unstable();
+    retained
+}
+
+/// Assemble the sample manifest table from its rows
+pub fn sample_manifest(rows: &[String]) -> String {
+    let mut table = String::from(SAMPLE_MANIFEST_HEADER);
+
+    for row in rows {
+        table.push('\n');
+        table.push_str(row);
+    }
+
+    table
+}
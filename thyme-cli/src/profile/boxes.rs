@@ -2,32 +2,54 @@
 // Licensed under the MIT License
 
 use std::path::Path;
-use std::path::PathBuf;
-use std::sync::Mutex;
 
 use clap::Args;
-use kdam::TqdmParallelIterator;
 use polars::prelude::*;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
+use thyme_core::catalog;
 use thyme_core::constant;
+use thyme_core::cv::transform::AffineTransform;
 use thyme_core::error::ThymeError;
 use thyme_core::im;
 use thyme_core::io;
+use thyme_core::mp::texture;
 use thyme_core::ut;
 
-#[derive(Debug, Args)]
+use thyme_config::ProfileBoxesConfig;
+
+use super::ProfileSummary;
+use super::preview::Preview;
+use super::table::{self, Descriptors, NonFiniteReport};
+use super::thumbnail::Thumbnails;
+use crate::boxes;
+use crate::dryrun;
+use crate::failures::{self, Stage};
+use crate::filter::{
+    self, BorderPolicy, DropCounts, DropReason, NonFinitePolicy, ObjectFilter, crop_rect,
+};
+use crate::output::{MetadataJoin, OutputMode, PerImageOutput};
+use crate::prefetch::Prefetch;
+use crate::provenance;
+use crate::status::Progress;
+use crate::transforms::SegmentTransforms;
+
+#[derive(Debug, Args, Serialize)]
 pub struct ProfileBoxesArgs {
     #[arg(short = 'i', long, help = "Image directory.", required = true)]
     pub images: Option<String>,
 
-    #[arg(short = 's', long, help = "Bounding boxes directory.")]
+    #[arg(
+        short = 's',
+        long,
+        help = "Bounding boxes directory, or a single csv table with an image column holding boxes for many images."
+    )]
     pub boxes: Option<String>,
 
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq).",
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather).",
         required = true
     )]
     pub output: Option<String>,
@@ -35,9 +57,41 @@ pub struct ProfileBoxesArgs {
     #[arg(short = 'v', long, help = "Verbose output.")]
     pub verbose: bool,
 
-    #[arg(short = 'd', long, help = "Exclude objects touching edge of image.")]
+    #[arg(
+        long,
+        help = "Show a live status panel with progress, throughput, workers, recent errors, and memory usage instead of the progress bar. Logs status lines instead if stdout is not a terminal."
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Format of the status lines logged instead of the status panel (plain, json). Json status lines are also logged without --tui.",
+        default_value = "plain"
+    )]
+    pub log_format: Option<String>,
+
+    #[arg(
+        short = 'd',
+        long,
+        help = "Exclude objects touching edge of image. Equivalent to --border-policy drop.",
+        conflicts_with = "border_policy"
+    )]
     pub drop_borders: bool,
 
+    #[arg(
+        long,
+        help = "Handling of objects whose unpadded bounding box touches the edge of the image: keep, drop, or flag (keeps objects and adds a touches_border column).",
+        default_value = "keep"
+    )]
+    pub border_policy: Option<String>,
+
+    #[arg(
+        long,
+        help = "Handling of objects with non-finite (NaN or infinite) descriptors: keep, zero (replaces non-finite values with zero), or drop-object.",
+        default_value = "keep"
+    )]
+    pub nonfinite_policy: Option<String>,
+
     #[arg(
         long,
         short = 'm',
@@ -62,182 +116,714 @@ pub struct ProfileBoxesArgs {
 
     #[arg(
         long,
-        help = "Exclude objects smaller than a minimum size.",
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting bounding boxes by their path relative to their directory.",
+        conflicts_with_all = ["box_substring", "box_regex"]
+    )]
+    pub box_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting bounding boxes by their path relative to their directory.",
+        conflicts_with = "box_substring"
+    )]
+    pub box_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Bounding box csv column names as key=column pairs (e.g. xmin=left,ymin=top,xmax=right,ymax=bottom,label=class,image=file). Columns are otherwise detected from common header names."
+    )]
+    pub box_columns: Option<String>,
+
+    #[arg(
+        long,
+        help = "Table (.csv, .txt, .tsv, .pq, .arrow, .feather) of affine transforms registering the bounding boxes of each image to the image, with columns image, a, b, c, d, tx, and ty. Box corners (x, y) are mapped to (a*x + b*y + tx, c*x + d*y + ty) and each box is replaced by the bounding box of its transformed corners before profiling."
+    )]
+    pub segment_transform: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with an unpadded bounding box width or height smaller than a minimum size (pixels).",
         default_value = "1"
     )]
     pub min_size: Option<u32>,
 
+    #[arg(
+        long,
+        help = "Exclude objects with a bounding box area smaller than a minimum area (pixels)."
+    )]
+    pub min_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with a bounding box area larger than a maximum area (pixels)."
+    )]
+    pub max_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Include intensity percentiles (p10, p25, p75, p90), skewness, and kurtosis in c, f, and b descriptors."
+    )]
+    pub extended_intensity: bool,
+
+    #[arg(
+        long,
+        requires = "extended_intensity",
+        help = "Number of positive pixels of a channel above which extended intensity percentiles are selected without sorting (e.g. 10000000). Percentiles are exact and results are identical to sorting below the threshold, while skewness and kurtosis may differ in the last digits above it."
+    )]
+    pub approx_percentiles_over: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Only compute the sum, mean, and std of complete intensities (c) using summed-area tables when boxes overlap substantially. Min, max, median, mad, moments, texture, and zernike complete descriptors are skipped.",
+        conflicts_with = "extended_intensity"
+    )]
+    pub fast_complete: bool,
+
+    #[arg(
+        long,
+        help = "Compute intensity, moments, texture, and zernike descriptors separately for the listed channels of each family, provided as family:channels pairs (e.g. 'intensity:0-5;texture:0,1;moments:0'). Families that are not listed are not computed.",
+        conflicts_with = "fast_complete"
+    )]
+    pub features_channels: Option<String>,
+
+    #[arg(
+        long,
+        help = "Only accumulate texture co-occurrences anchored at every n-th row and column of each object.",
+        default_value = "1"
+    )]
+    pub texture_stride: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Escalate the texture stride for objects with more pixels than this threshold so that roughly this many pixels are sampled (e.g. 250000). Applied strides are written to a texture_stride column when verbose."
+    )]
+    pub texture_auto_stride: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Directory to write a letterboxed 8-bit thumbnail of each object crop to. Adds a thumbnail_path column referencing each thumbnail."
+    )]
+    pub thumbnails: Option<String>,
+
+    #[arg(
+        long,
+        help = "Width and height of object thumbnails (pixels).",
+        default_value = "64"
+    )]
+    pub thumbnail_size: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Profile a preview of each image and its segments downsampled by this factor in (0, 1] (e.g. 0.25) for rapid parameter tuning. Adds a preview_scale column after the object column."
+    )]
+    pub preview: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Rescale lengths, areas, and spatial frequencies of a preview to original pixel units.",
+        requires = "preview"
+    )]
+    pub preview_rescale: bool,
+
+    #[arg(
+        long,
+        help = "Metadata table (.csv, .txt, .tsv, .pq, .arrow, .feather) joined to every output row (e.g. a platemap)."
+    )]
+    pub metadata_table: Option<String>,
+
+    #[arg(
+        long,
+        help = "Name of the join key column in the metadata table (e.g. well)."
+    )]
+    pub metadata_join: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex extracting the join key from image file names using the first capture group or full match (e.g. '_([A-P][0-9]{2})_'). Defaults to the image file name."
+    )]
+    pub well_from_filename: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exit with an error instead of writing nulls when an image has no matching metadata."
+    )]
+    pub strict_join: bool,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Naming convention of descriptor columns (thyme, cellprofiler). Cellprofiler renames descriptors with a CellProfiler equivalent (e.g. AreaShape_Area, Intensity_MeanIntensity_DNA) and prefixes the others with Thyme_.",
+        default_value = "thyme"
+    )]
+    pub column_style: Option<String>,
+
+    #[arg(
+        long,
+        help = "Comma-separated names of the image channels inserted in cellprofiler column names (e.g. DNA,Actin,Mito)."
+    )]
+    pub channel_names: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Write a separate descriptors/{image} table for each image as soon as it is profiled. Requires an output directory."
+    )]
+    pub per_image_output: bool,
+
+    #[arg(
+        long,
+        help = "Format of per-image output tables (csv, txt, tsv, pq, arrow, feather).",
+        default_value = "csv"
+    )]
+    pub per_image_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip images whose per-image output table already exists.",
+        requires = "per_image_output"
+    )]
+    pub resume: bool,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (tables, reports, and provenance sidecars) from the output location before profiling. Without it, output locations holding a previous run are rejected.",
+        conflicts_with = "resume"
+    )]
+    pub overwrite: bool,
+
+    #[arg(
+        long,
+        help = "Report the pairs, object counts, table width, and estimated output size as a table and in dry_run.json next to the output, without profiling or creating the output."
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        help = "Number of decoded images queued ahead of the compute threads. If 0, images are decoded by the compute threads.",
+        default_value = "0"
+    )]
+    pub prefetch: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Number of threads decoding images ahead of the compute threads if --prefetch is set.",
+        default_value = "2"
+    )]
+    pub io_threads: Option<usize>,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
 
 pub fn profile_image_boxes(args: &ProfileBoxesArgs) {
-    if let Some(threads) = args.threads.to_owned() {
-        if threads < 1 {
-            println!(
-                "[thyme::profile::boxes] Threads must be set to a positive integer if provided."
-            );
-            std::process::exit(1);
-        }
+    if let Err(err) = run_profile_boxes(&ProfileBoxesConfig::from(args)) {
+        eprintln!("[thyme::profile::boxes] ERROR: {}", err);
+        std::process::exit(1);
+    }
+}
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
+impl From<&ProfileBoxesArgs> for ProfileBoxesConfig {
+    fn from(args: &ProfileBoxesArgs) -> Self {
+        let defaults = ProfileBoxesConfig::default();
+
+        ProfileBoxesConfig {
+            images: args.images.to_owned().unwrap_or_default(),
+            boxes: args.boxes.to_owned(),
+            output: args.output.to_owned().unwrap_or_default(),
+            verbose: args.verbose,
+            tui: args.tui,
+            log_format: args.log_format.to_owned().unwrap_or(defaults.log_format),
+            drop_borders: args.drop_borders,
+            border_policy: args
+                .border_policy
+                .to_owned()
+                .unwrap_or(defaults.border_policy),
+            nonfinite_policy: args
+                .nonfinite_policy
+                .to_owned()
+                .unwrap_or(defaults.nonfinite_policy),
+            mode: args.mode.to_owned().unwrap_or(defaults.mode),
+            pad: args.pad.unwrap_or(defaults.pad),
+            image_substring: args.image_substring.to_owned(),
+            box_substring: args.box_substring.to_owned(),
+            image_glob: args.image_glob.to_owned(),
+            image_regex: args.image_regex.to_owned(),
+            box_glob: args.box_glob.to_owned(),
+            box_regex: args.box_regex.to_owned(),
+            box_columns: args.box_columns.to_owned(),
+            segment_transform: args.segment_transform.to_owned(),
+            min_size: args.min_size.unwrap_or(defaults.min_size),
+            min_area: args.min_area,
+            max_area: args.max_area,
+            extended_intensity: args.extended_intensity,
+            approx_percentiles_over: args.approx_percentiles_over,
+            fast_complete: args.fast_complete,
+            features_channels: args.features_channels.to_owned(),
+            texture_stride: args.texture_stride.unwrap_or(defaults.texture_stride),
+            texture_auto_stride: args.texture_auto_stride,
+            thumbnails: args.thumbnails.to_owned(),
+            thumbnail_size: args.thumbnail_size.unwrap_or(defaults.thumbnail_size),
+            preview: args.preview,
+            preview_rescale: args.preview_rescale,
+            metadata_table: args.metadata_table.to_owned(),
+            metadata_join: args.metadata_join.to_owned(),
+            well_from_filename: args.well_from_filename.to_owned(),
+            strict_join: args.strict_join,
+            ipc_compression: args
+                .ipc_compression
+                .to_owned()
+                .unwrap_or(defaults.ipc_compression),
+            float_precision: args.float_precision,
+            float_format: args
+                .float_format
+                .to_owned()
+                .unwrap_or(defaults.float_format),
+            column_style: args
+                .column_style
+                .to_owned()
+                .unwrap_or(defaults.column_style),
+            channel_names: args.channel_names.to_owned(),
+            provenance: args.provenance,
+            hash_inputs: args.hash_inputs,
+            per_image_output: args.per_image_output,
+            per_image_format: args
+                .per_image_format
+                .to_owned()
+                .unwrap_or(defaults.per_image_format),
+            resume: args.resume,
+            overwrite: args.overwrite,
+            dry_run: args.dry_run,
+            prefetch: args.prefetch.unwrap_or(defaults.prefetch),
+            io_threads: args.io_threads.unwrap_or(defaults.io_threads),
+            threads: args.threads,
+        }
     }
+}
 
-    let mode = args.mode.to_owned().unwrap_or("cmbfpx".to_string());
-    let pad = args.pad.unwrap_or(1);
-    let min_size = args.min_size.unwrap_or(1);
+/// Profile image and bounding box pairs as configured
+///
+/// Images that fail to profile are reported in `object_errors.tsv` when
+/// writing to an output directory and are counted in the returned summary.
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the run
+pub fn run_profile_boxes(config: &ProfileBoxesConfig) -> Result<ProfileSummary, ThymeError> {
+    config.validate()?;
+    ut::threads::install(config.threads, || run(config))
+}
 
-    if mode.chars().any(|c| !matches!(c, 'c' | 'x')) {
-        eprintln!(
-            "[thyme::profile::boxes] Invalid mode. Argument mode must only contain one or more of: c, x."
-        );
-        std::process::exit(1);
-    }
+/// Run the command on the thread pool selected by `run_profile_boxes`
+fn run(config: &ProfileBoxesConfig) -> Result<ProfileSummary, ThymeError> {
+    let mode = config.mode.to_owned();
+    let pad = config.pad;
 
-    if min_size < 1 {
-        eprintln!("[thyme::profile::boxes] ERROR: min_size cannot be less than 1.0.");
-        std::process::exit(1);
-    }
+    let border_policy = match config.drop_borders {
+        true => BorderPolicy::Drop,
+        false => BorderPolicy::parse(&config.border_policy).map_err(super::invalid)?,
+    };
 
-    let image_path = args.images.to_owned().unwrap();
-    let boxes_path = args.boxes.to_owned().unwrap_or(image_path.clone());
+    let nonfinite_policy =
+        NonFinitePolicy::parse(&config.nonfinite_policy).map_err(super::invalid)?;
 
-    if image_path == boxes_path && args.image_substring == args.box_substring {
-        eprintln!(
-            "[thyme::profile::boxes] ERROR: If images and boxes are located in same path, different image and bounding box substrings must be provided."
-        );
-        std::process::exit(1);
-    }
+    let prefetch = Prefetch::new(Some(config.prefetch), Some(config.io_threads))?;
 
-    let image_files = ut::path::collect_file_paths(
-        &image_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.image_substring.to_owned(),
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    let filter = ObjectFilter {
+        border_policy,
+        min_size: config.min_size,
+        min_area: config.min_area,
+        max_area: config.max_area,
+        skip_invalid_polygons: false,
+    };
+
+    filter.validate().map_err(super::invalid)?;
+
+    let texture_stride = config.texture_stride;
 
-    let boxes_files = ut::path::collect_file_paths(
-        &boxes_path,
-        constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
-        args.box_substring.to_owned(),
+    // Strides are only recorded when texture descriptors are computed
+    let stride_column = config.verbose && mode.contains('c') && !config.fast_complete;
+
+    let thumbnails = Thumbnails::new(config.thumbnails.as_deref(), Some(config.thumbnail_size))?;
+    let preview = Preview::new(config.preview, config.preview_rescale)?;
+
+    let ipc_compression = config.ipc_compression.to_owned();
+    let float_format = io::FloatFormat::new(config.float_precision, &config.float_format)?;
+
+    let image_path = config.images.to_owned();
+    let boxes_path = config.boxes_path().to_owned();
+
+    let image_files = ut::path::FileSelector::new(
+        config.image_substring.as_deref(),
+        config.image_glob.as_deref(),
+        config.image_regex.as_deref(),
     )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    .and_then(|selector| {
+        ut::path::select_file_paths(
+            &image_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
+
+    let box_columns = config
+        .box_columns
+        .as_deref()
+        .map(im::BoxColumns::parse)
+        .transpose()?
+        .unwrap_or_default();
 
     if image_files.is_empty() {
-        eprintln!(
-            "[thyme::profile::boxes] ERROR: No image files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No image files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
-    if boxes_files.is_empty() {
-        eprintln!(
-            "[thyme::profile::boxes] ERROR: No bounding box files were detected. Please check your path and/or substring identifier."
+    let (mut pairs, box_source) = if Path::new(&boxes_path).is_file() {
+        let (pairs, box_source, unmatched) = boxes::table_pairs(
+            &boxes_path,
+            &box_columns,
+            &image_files,
+            config.image_substring.to_owned(),
+        )?;
+
+        if unmatched > 0 {
+            eprintln!(
+                "[thyme::profile::boxes] WARNING: {} images in the bounding box table have no matching image file.",
+                unmatched
+            );
+        }
+
+        (pairs, box_source)
+    } else {
+        let boxes_files = ut::path::FileSelector::new(
+            config.box_substring.as_deref(),
+            config.box_glob.as_deref(),
+            config.box_regex.as_deref(),
+        )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &boxes_path,
+                constant::SUPPORTED_BOX_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })?;
+
+        if boxes_files.is_empty() {
+            return Err(ThymeError::OtherError(
+                "No bounding box files were detected. Please check your path and/or substring identifier"
+                    .to_string(),
+            ));
+        }
+
+        let pairs = ut::path::collect_file_pairs(
+            &image_files,
+            &boxes_files,
+            config.image_substring.to_owned(),
+            config.box_substring.to_owned(),
         );
-        std::process::exit(1);
-    }
 
-    let mut pairs = ut::path::collect_file_pairs(
-        &image_files,
-        &boxes_files,
-        args.image_substring.to_owned(),
-        args.box_substring.to_owned(),
-    );
+        (pairs, boxes::BoxSource::Files(box_columns))
+    };
 
     pairs.sort_unstable();
 
+    let feature_channels = table::feature_channels(
+        config.features_channels.as_deref(),
+        pairs.first().map(|(_, image, _)| image.as_path()),
+    )?;
+
+    // Transforms are resolved before processing so that images without a
+    // transform are reported without waiting for any image to be profiled
+    let transforms = config
+        .segment_transform
+        .as_ref()
+        .map(SegmentTransforms::open)
+        .transpose()?;
+
+    if let Some(transforms) = &transforms {
+        for (id, image, _) in &pairs {
+            transforms.get(id, image)?;
+        }
+    }
+
     ut::track::progress_log(
         &format!(
             "Detected {} image and bounding box pairs.",
             ut::track::thousands_format(pairs.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    let mut output = PathBuf::from(args.output.to_owned().unwrap());
-
-    let extension = output
-        .extension()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_lowercase());
-
-    if let Some(ext) = extension {
-        if !["csv", "txt", "tsv", "pq"].iter().any(|e| e == &ext) {
-            eprintln!(
-                "[thyme::profile::boxes] ERROR: Invalid file extension. Must end with one of .csv, .txt, .tsv, .pq."
-            );
-            std::process::exit(1);
-        }
+    let metadata = config
+        .metadata_table
+        .as_ref()
+        .map(|path| {
+            let on = config.metadata_join.as_deref().unwrap();
+            let metadata = io::read_table(path)?;
+
+            // Resolve join keys before processing so that invalid patterns or unmatched
+            // images are reported without waiting for all the objects to be computed
+            let images: Vec<String> = pairs.iter().map(|(_, image, _)| stem(image)).collect();
+
+            let mut df = DataFrame::new(vec![Column::new("image".into(), &images)]).unwrap();
+
+            let missing = io::join_metadata(
+                &mut df,
+                "image",
+                &metadata,
+                on,
+                config.well_from_filename.as_deref(),
+            )?;
+
+            if missing > 0 && config.strict_join {
+                return Err(ThymeError::OtherError(format!(
+                    "{} images have no matching row in the metadata table",
+                    missing
+                )));
+            }
 
-        if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if missing > 0 {
                 eprintln!(
-                    "[thyme::profile::boxes] ERROR: Invalid file path. Parent directory of output file path does not exist."
+                    "[thyme::profile::boxes] WARNING: {} images have no matching row in the metadata table. Metadata columns will be null for these images.",
+                    missing
                 );
-                std::process::exit(1);
             }
-        }
-    } else {
-        output = ut::path::create_directory(&output).unwrap_or_else(|_| {
-            eprintln!("[thyme::profile::boxes] ERROR: Could not create directory.");
-            std::process::exit(1);
+
+            Ok(metadata)
+        })
+        .transpose()?;
+
+    let column_style = catalog::ColumnStyle::parse(&config.column_style)?;
+    let channel_names = config
+        .channel_names
+        .as_deref()
+        .map(catalog::parse_channel_names)
+        .transpose()?
+        .unwrap_or_default();
+
+    let profile_columns = catalog::ProfileColumns {
+        segments: catalog::Segments::Boxes,
+        mode: mode.clone(),
+        extended_intensity: config.extended_intensity,
+        texture_stride: stride_column,
+        fast_complete: config.fast_complete,
+        feature_channels: feature_channels.clone(),
+        preview: preview.is_some(),
+        column_style,
+        channel_names: channel_names.clone(),
+        ..Default::default()
+    };
+
+    let descriptor_columns = profile_columns.descriptor_names();
+    let descriptor_exponents = profile_columns.descriptor_exponents();
+
+    if config.dry_run {
+        let images = dryrun::scan(&pairs, pad, |id, image, boxes, _| {
+            let mut bounding_boxes = box_source.open(image, boxes)?;
+
+            if let Some(transforms) = &transforms {
+                bounding_boxes.affine(transforms.get(id, image)?);
+            }
+
+            Ok(bounding_boxes.to_xyxy())
         });
+
+        return super::dry_run(
+            "profile::boxes",
+            &config.output,
+            Some(config.per_image_format.as_str()).filter(|_| config.per_image_output),
+            config.float_precision,
+            images,
+            |_| profile_columns.columns().len(),
+        );
     }
 
-    let pb = ut::track::progress_bar(pairs.len(), "Profiling", args.verbose);
+    let output = super::output_path(
+        &config.output,
+        OutputMode::new(config.overwrite, config.resume)?,
+        &config.inputs(),
+        "profile::boxes",
+    )?;
+
+    let per_image = config
+        .per_image_output
+        .then(|| {
+            if !output.is_dir() {
+                return Err(ThymeError::OtherError(
+                    "per_image_output requires an output directory".to_string(),
+                ));
+            }
+
+            PerImageOutput::new(
+                &output,
+                "descriptors",
+                &config.per_image_format,
+                &ipc_compression,
+                float_format,
+                config.resume,
+                metadata.clone().map(|table| MetadataJoin {
+                    table,
+                    on: config.metadata_join.to_owned().unwrap(),
+                    well_from_filename: config.well_from_filename.to_owned(),
+                }),
+            )
+        })
+        .transpose()?;
+
+    if let Some(per_image) = &per_image {
+        let n = pairs.len();
+        pairs.retain(|(_, image, _)| !per_image.skip(&stem(image)));
+
+        ut::track::progress_log(
+            &format!(
+                "Skipping {} images with existing per-image tables.",
+                ut::track::thousands_format(n - pairs.len())
+            ),
+            config.verbose && config.resume,
+        );
+    }
 
-    let objects: Mutex<usize> = Mutex::new(0);
-    let success: Mutex<Vec<String>> = Mutex::new(vec![]);
-    let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
+    let options = ProfileOptions {
+        box_source: &box_source,
+        pad,
+        filter,
+        mode: &mode,
+        extended_intensity: config.extended_intensity,
+        approx_percentiles_over: config.approx_percentiles_over,
+        fast_complete: config.fast_complete,
+        feature_channels: feature_channels.as_ref(),
+        texture_stride,
+        texture_auto_stride: config.texture_auto_stride,
+        stride_column,
+        thumbnails: thumbnails.as_ref(),
+        preview: preview.as_ref(),
+    };
+
+    let progress = Progress::new(
+        pairs.len(),
+        "Profiling",
+        config.verbose,
+        config.tui,
+        &config.log_format,
+    );
+    let status = progress.status();
 
-    let name: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
-    let item: Mutex<Vec<u32>> = Mutex::new(Vec::with_capacity(pairs.len()));
-    let data: Mutex<Vec<Vec<f32>>> = Mutex::new(Vec::with_capacity(300 * pairs.len()));
+    // Results are collected in input order so that the output table does not
+    // depend on the number of threads used for profiling
+    let runs: Vec<Result<(Descriptors, DropCounts), ThymeError>> = prefetch.run(
+        &pairs,
+        progress,
+        |(_, image, _)| im::ThymeImage::open(image),
+        |(id, image_path, boxes), image| {
+            status.begin(id);
 
-    (0..pairs.len())
-        .into_par_iter()
-        .tqdm_with_bar(pb)
-        .for_each(|idx| {
-            let (id, image, boxes) = &pairs[idx];
-            let run = profile(image, boxes, pad, args.drop_borders, min_size, &mode);
+            let transform = match &transforms {
+                Some(transforms) => Some(transforms.get(id, image_path)?),
+                None => None,
+            };
 
-            if let Ok((ids, descriptors)) = run {
-                let n = ids.len();
+            let mut profiled = profile(image, image_path, boxes, transform, &options)?;
 
-                success.lock().unwrap().push(format!("{}\t{}", id, n));
+            profiled.0.sanitize(nonfinite_policy);
 
-                let image = image.file_stem().unwrap().to_string_lossy().to_string();
+            if let Some(preview) = &preview {
+                preview.finish(&mut profiled.0, &descriptor_exponents);
+            }
 
-                name.lock().unwrap().extend((0..n).map(|_| image.clone()));
-                item.lock().unwrap().extend(ids);
-                data.lock().unwrap().extend(descriptors);
-                *objects.lock().unwrap() += n;
-            } else {
-                failure
-                    .lock()
-                    .unwrap()
-                    .push(format!("{}\t{}", id, run.unwrap_err()));
+            // Tables are written as soon as an image is profiled so that only
+            // object ids are kept in memory until the counts are written
+            if let Some(per_image) = &per_image {
+                let columns = descriptor_columns.clone();
+                table::write_image(per_image, &stem(image_path), &mut profiled.0, &columns)?;
             }
-        });
 
-    let objects = objects.into_inner().unwrap();
-    let success = success.into_inner().unwrap();
-    let failure = failure.into_inner().unwrap();
+            status.add_objects(profiled.0.ids.len());
+
+            Ok(profiled)
+        },
+    );
+
+    let mut objects: usize = 0;
+    let mut success: Vec<String> = vec![];
+    let mut failure: Vec<String> = Vec::with_capacity(pairs.len());
+    let mut failed_objects: Vec<String> = vec![];
+    let mut report = NonFiniteReport::default();
+
+    let mut name: Vec<String> = vec![];
+    let mut data: Vec<Descriptors> = Vec::with_capacity(pairs.len());
+
+    for ((id, image, _), run) in pairs.iter().zip(runs) {
+        match run {
+            Ok((descriptors, dropped)) => {
+                let n = descriptors.ids.len();
+
+                failed_objects.extend(descriptors.failed.rows(id));
 
-    let name = name.into_inner().unwrap();
-    let item = item.into_inner().unwrap();
-    let data = data.into_inner().unwrap();
+                report.add(&descriptor_columns, &descriptors.nonfinite);
+                success.push(filter::object_counts_row(id, n, &dropped));
 
-    if args.verbose {
+                let image = stem(image);
+
+                if per_image.is_none() {
+                    name.extend((0..n).map(|_| image.clone()));
+                    data.push(descriptors);
+                }
+
+                objects += n;
+            }
+            Err(err) => failure.push(format!("{}\t{}", id, err)),
+        }
+    }
+
+    if config.verbose {
         println!();
     }
 
@@ -247,34 +833,23 @@ pub fn profile_image_boxes(args: &ProfileBoxesArgs) {
             ut::track::thousands_format(objects),
             ut::track::thousands_format(success.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    if !success.is_empty() {
-        let columns = descriptor_columns(&mode);
-
-        let mut df = DataFrame::new(vec![
-            Column::new("image".into(), &name),
-            Column::new("object".into(), &item),
-        ])
-        .unwrap();
-
-        // Note that this requires generating two copies of the computed descriptors
-        // which is definitely not ideal. We probaby want to redesign the computation
-        // so that column-major data is generated directly or we just use a flat buffer
-        // and then just handle the saving with indexing. Also look into the polars API.
-        let mut column_data: Vec<Vec<f32>> = vec![Vec::with_capacity(data.len()); data[0].len()];
-
-        for row in &data {
-            for (idx, &descriptor) in row.iter().enumerate() {
-                column_data[idx].push(descriptor);
-            }
-        }
-
-        for (column, descriptor) in columns.iter().zip(column_data) {
-            df.with_column(Column::new(column.into(), descriptor))
-                .unwrap();
+    let mut descriptors = None;
+
+    if let Some(per_image) = &per_image {
+        if config.provenance {
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                None,
+                config.hash_inputs,
+                &[per_image.directory()],
+            )?;
         }
+    } else if !success.is_empty() {
+        let mut df = table::dataframe(&name, data, &descriptor_columns);
 
         let descriptors_path = if output.is_dir() {
             output.join("descriptors.csv")
@@ -282,111 +857,408 @@ pub fn profile_image_boxes(args: &ProfileBoxesArgs) {
             output.clone()
         };
 
-        io::write_table(&mut df, descriptors_path).unwrap_or_else(|_| {
-            eprintln!("[thyme::profile::boxes] ERROR: Failed to write descriptors table.");
-            std::process::exit(1);
-        });
-    }
-
-    if output.is_dir() {
-        if !success.is_empty() {
-            std::fs::write(output.join("object_counts.tsv"), success.join("\n")).unwrap();
+        if let Some(metadata) = &metadata {
+            io::join_metadata(
+                &mut df,
+                "image",
+                metadata,
+                config.metadata_join.as_deref().unwrap(),
+                config.well_from_filename.as_deref(),
+            )?;
         }
 
-        if !failure.is_empty() {
-            std::fs::write(output.join("object_errors.tsv"), failure.join("\n")).unwrap();
+        io::write_table_with_float_format(
+            &mut df,
+            &descriptors_path,
+            &ipc_compression,
+            &float_format,
+        )
+        .map_err(|err| {
+            ThymeError::OtherError(format!("Failed to write descriptors table: {}", err))
+        })?;
+
+        if config.provenance {
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                None,
+                config.hash_inputs,
+                &[&descriptors_path],
+            )?;
         }
+
+        descriptors = Some(descriptors_path);
     }
+
+    if !report.is_empty() {
+        eprintln!(
+            "[thyme::profile::boxes] WARNING: {} objects have non-finite values in {} descriptor columns ({}).",
+            ut::track::thousands_format(report.objects()),
+            report.len(),
+            nonfinite_policy.describe()
+        );
+    }
+
+    if !failed_objects.is_empty() {
+        eprintln!(
+            "[thyme::profile::boxes] WARNING: {} objects failed to profile and have no descriptors (see failed_objects.tsv).",
+            ut::track::thousands_format(failed_objects.len())
+        );
+    }
+
+    if output.is_dir() {
+        super::write_reports(
+            &output,
+            &report,
+            &success,
+            &[],
+            &[],
+            &failure,
+            &failed_objects,
+        )?;
+    }
+
+    crate::output::finish_run(&output)?;
+
+    Ok(ProfileSummary {
+        images: success.len(),
+        failures: failure.len(),
+        skipped: 0,
+        objects,
+        failed_objects: failed_objects.len(),
+        output,
+        descriptors,
+        dry_run: None,
+    })
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().to_string()
+}
+
+/// Options shared by every image and boxes pair profiled by a run
+#[derive(Clone, Copy)]
+struct ProfileOptions<'a> {
+    /// Per-image files or multi-image table the boxes are read from
+    box_source: &'a boxes::BoxSource,
+    /// Padding added to each side of the bounding box of an object
+    pad: u32,
+    /// Filters applied to each object before it is measured
+    filter: ObjectFilter,
+    /// Descriptor families to compute
+    mode: &'a str,
+    /// If true, compute the extended intensity descriptors
+    extended_intensity: bool,
+    /// Positive subpixels of a channel above which percentiles are selected without a full sort
+    approx_percentiles_over: Option<usize>,
+    /// Only compute complete intensity sums, means, and stds from summed-area tables
+    fast_complete: bool,
+    /// Channels measured by the intensity descriptors
+    feature_channels: Option<&'a im::FeatureChannels>,
+    /// Stride of texture co-occurrence anchors
+    texture_stride: usize,
+    /// Object size above which the texture stride is escalated (pixels)
+    texture_auto_stride: Option<usize>,
+    /// If true, record the texture stride used for each object
+    stride_column: bool,
+    /// Object thumbnails written alongside the descriptors
+    thumbnails: Option<&'a Thumbnails>,
+    /// Downsampling applied to images and boxes before profiling
+    preview: Option<&'a Preview>,
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Profile an image and its bounding boxes
 fn profile(
+    image: im::ThymeImage,
     image_path: &Path,
     boxes_path: &Path,
-    pad: u32,
-    drop_borders: bool,
-    min_size: u32,
-    mode: &str,
-) -> Result<(Vec<u32>, Vec<Vec<f32>>), ThymeError> {
-    let image = im::ThymeImage::open(image_path)?;
+    transform: Option<&AffineTransform>,
+    options: &ProfileOptions,
+) -> Result<(Descriptors, DropCounts), ThymeError> {
+    let ProfileOptions {
+        box_source,
+        pad,
+        filter,
+        mode,
+        extended_intensity,
+        approx_percentiles_over,
+        fast_complete,
+        feature_channels,
+        texture_stride,
+        texture_auto_stride,
+        stride_column,
+        thumbnails,
+        preview,
+    } = *options;
+
+    if let Some(feature_channels) = feature_channels {
+        feature_channels.check(image.channels() as usize)?;
+    }
+
+    let mut bounding_boxes = box_source.open(image_path, boxes_path)?;
+
+    // Boxes are registered to the original image before any preview scaling
+    if let Some(transform) = transform {
+        bounding_boxes.affine(transform);
+    }
 
-    let bounding_boxes = im::BoundingBoxes::open(boxes_path)?;
+    // Previews measure objects on the downsampled image and scaled boxes
+    let (image, bounding_boxes) = match preview {
+        Some(preview) => (preview.image(&image)?, preview.boxes(bounding_boxes)?),
+        None => (image, bounding_boxes),
+    };
 
     let width = image.width();
     let height = image.height();
 
-    let pad_f32 = pad as f32;
+    let mut descriptors = Descriptors::with_capacity(bounding_boxes.len());
+    let mut dropped = DropCounts::default();
+    let mut border_flags: Vec<bool> = vec![];
+    let mut thumbnail_paths: Vec<Option<String>> = vec![];
 
-    let mut ids: Vec<u32> = Vec::with_capacity(bounding_boxes.len());
-    let mut results: Vec<Vec<f32>> = Vec::with_capacity(50 * bounding_boxes.len());
+    let mut labels: Vec<String> = vec![];
 
-    for (idx, [min_x, min_y, max_x, max_y]) in bounding_boxes.as_xyxy().iter().enumerate() {
-        let min_x = min_x - pad_f32;
-        let min_y = min_y - pad_f32;
-        let max_x = max_x + pad_f32;
-        let max_y = max_y + pad_f32;
+    let coordinates = bounding_boxes.as_xyxy();
 
-        if drop_borders
-            && (min_x <= 0.0 || min_y <= 0.0 || max_x >= width as f32 || max_y >= height as f32)
-        {
-            continue;
-        }
+    // Objects are filtered and cropped up front so complete intensities can
+    // be computed for every kept crop at once
+    let crops: Vec<Option<[u32; 4]>> = coordinates
+        .iter()
+        .map(|xyxy| {
+            if let Err(reason) = filter.check(xyxy, bbox_area(xyxy), width, height) {
+                dropped.add(reason);
+                return None;
+            }
+
+            let crop = crop_rect(xyxy, pad, width, height).map(|crop| crop.as_xywh());
+
+            if crop.is_none() {
+                dropped.add(DropReason::OutsideImage);
+            }
+
+            crop
+        })
+        .collect();
 
-        let min_x = min_x.max(0.0) as u32;
-        let min_y = min_y.max(0.0) as u32;
-        let max_x = max_x.min(width as f32) as u32;
-        let max_y = max_y.min(height as f32) as u32;
+    let kept: Vec<[u32; 4]> = crops.iter().flatten().copied().collect();
 
-        let w = max_x - min_x;
-        let h = max_y - min_y;
+    let mut fast = (fast_complete && mode.contains("c"))
+        .then(|| fast_complete_intensity(&image, &kept))
+        .unwrap_or_default()
+        .into_iter();
 
-        if w < min_size || h < min_size {
+    for (idx, (xyxy, crop)) in coordinates.iter().zip(crops).enumerate() {
+        let Some([min_x, min_y, w, h]) = crop else {
             continue;
-        }
+        };
+
+        // Complete intensities of every kept crop are taken in order even if
+        // an object fails so that they stay aligned with the objects
+        let complete = (mode.contains("c") && fast_complete).then(|| fast.next().unwrap());
+
+        // Each object is measured in isolation so that a failure is recorded
+        // and the remaining boxes of the image are still profiled
+        let measured = failures::catch(|stage| {
+            *stage = Stage::Measure;
+            let mut result: Vec<f32> = Vec::with_capacity(100);
+
+            result.extend(table::location(xyxy, None));
+
+            if mode.contains("x") {
+                result.extend([w as f32, h as f32, (w * h) as f32]);
+            }
+
+            let stride =
+                texture::auto_stride((w * h) as usize, texture_stride, texture_auto_stride);
+
+            if let Some(complete) = complete {
+                result.extend(complete);
+            } else if mode.contains("c") {
+                let view = image.crop_view(min_x, min_y, w, h);
+                result.extend(match feature_channels {
+                    Some(feature_channels) => view.descriptors_channels(
+                        None,
+                        feature_channels,
+                        extended_intensity,
+                        approx_percentiles_over,
+                        stride,
+                    ),
+                    None if extended_intensity => {
+                        view.descriptors_extended_strided(stride, approx_percentiles_over)
+                    }
+                    None => view.descriptors_strided(stride),
+                });
+            }
+
+            if stride_column {
+                result.push(stride as f32);
+            }
 
-        let mut result: Vec<f32> = Vec::with_capacity(100);
+            let thumbnail = match thumbnails {
+                Some(thumbnails) => {
+                    *stage = Stage::Crop;
+                    let crop = image.crop(min_x, min_y, w, h)?;
+
+                    *stage = Stage::Measure;
+                    Some(thumbnails.write(&crop, &stem(image_path), idx as u32)?)
+                }
+                None => None,
+            };
+
+            Ok((result, thumbnail))
+        });
 
-        if mode.contains("x") {
-            result.extend([w as f32, h as f32, (w * h) as f32]);
+        let (result, thumbnail) = match measured {
+            Ok(measured) => measured,
+            Err(err) => {
+                descriptors.failed.add(idx as u32, *xyxy, err);
+                continue;
+            }
+        };
+
+        if filter.border_policy == BorderPolicy::Flag {
+            border_flags.push(filter::touches_border(xyxy, width, height));
         }
 
-        if mode.contains("c") {
-            result.extend(image.crop_view(min_x, min_y, w, h).descriptors());
+        thumbnail_paths.extend(thumbnail);
+
+        descriptors.push(idx as u32, result);
+
+        if let Some(l) = bounding_boxes.labels() {
+            labels.push(l[idx].clone());
         }
+    }
+
+    if bounding_boxes.labels().is_some() {
+        descriptors.labels = Some(labels);
+    }
 
-        ids.push(idx as u32);
-        results.push(result)
+    if filter.border_policy == BorderPolicy::Flag {
+        descriptors.touches_border = Some(border_flags);
     }
 
-    Ok((ids, results))
+    if thumbnails.is_some() {
+        descriptors.thumbnails = Some(thumbnail_paths);
+    }
+
+    Ok((descriptors, dropped))
 }
 
-/// Generate the column names for the output descriptor table
-///
-/// # Arguments
+fn bbox_area(xyxy: &[f32; 4]) -> f32 {
+    (xyxy[2] - xyxy[0]) * (xyxy[3] - xyxy[1])
+}
+
+/// Compute the complete intensity sum, mean, and std of each crop averaged over channels
 ///
-/// * `mode` - Profiling mode
-fn descriptor_columns(mode: &str) -> Vec<String> {
-    let mut names: Vec<String> = vec![];
-
-    if mode.contains("x") {
-        names.extend([
-            "bbox_width".to_string(),
-            "bbox_height".to_string(),
-            "bbox_area".to_string(),
-        ]);
+/// Summed-area tables are only built when the crops cover more pixels than the
+/// image, since building a table reads every pixel once. Tables are built and
+/// queried one channel at a time to bound memory to a single channel. Crops of
+/// images with little overlap are read directly instead.
+fn fast_complete_intensity(image: &im::ThymeImage, crops: &[[u32; 4]]) -> Vec<[f32; 3]> {
+    let channels = image.channels() as usize;
+    let covered: u64 = crops.iter().map(|c| c[2] as u64 * c[3] as u64).sum();
+
+    if covered <= image.width() as u64 * image.height() as u64 {
+        return crops
+            .iter()
+            .map(|&[x, y, w, h]| {
+                image
+                    .crop_view(x, y, w, h)
+                    .with_buffer(|view| crop_intensity(view.iter_f32(), channels))
+            })
+            .collect();
     }
 
-    let suffixes: Vec<&str> = constant::INTENSITY_DESCRIPTOR_NAMES
-        .into_iter()
-        .chain(constant::MOMENTS_DESCRIPTOR_NAMES)
-        .chain(constant::TEXTURE_DESCRIPTOR_NAMES)
-        .chain(constant::ZERNIKE_DESCRIPTOR_NAMES)
-        .collect();
+    let mut results = vec![[0f32; 3]; crops.len()];
+
+    for channel in 0..channels {
+        let integral = image.integral(channel);
 
-    if mode.contains("c") {
-        names.extend(suffixes.iter().map(|s| "complete_".to_string() + s));
+        for (result, &[x, y, w, h]) in results.iter_mut().zip(crops) {
+            let intensity = integral.intensity(x as usize, y as usize, w as usize, h as usize);
+
+            for (r, v) in result.iter_mut().zip(intensity) {
+                *r += v / channels as f32;
+            }
+        }
     }
 
-    names
+    results
+}
+
+/// Sum, mean, and std of the positive subpixels of each channel averaged over channels
+fn crop_intensity(subpixels: impl Iterator<Item = f32>, channels: usize) -> [f32; 3] {
+    let mut sum = vec![0f64; channels];
+    let mut squared = vec![0f64; channels];
+    let mut n = vec![0u64; channels];
+
+    for (i, v) in subpixels.enumerate() {
+        if v > 0. {
+            let c = i % channels;
+            sum[c] += v as f64;
+            squared[c] += v as f64 * v as f64;
+            n[c] += 1;
+        }
+    }
+
+    let mut result = [0f32; 3];
+
+    for c in 0..channels {
+        let mean = if n[c] > 0 { sum[c] / n[c] as f64 } else { 0.0 };
+        let variance = if n[c] > 0 {
+            (squared[c] / n[c] as f64 - mean * mean).max(0.0)
+        } else {
+            0.0
+        };
+
+        result[0] += sum[c] as f32 / channels as f32;
+        result[1] += mean as f32 / channels as f32;
+        result[2] += variance.sqrt() as f32 / channels as f32;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_core::im::{ThymeBuffer, ThymeImage};
+
+    use super::*;
+
+    fn assert_matches_descriptors(image: &ThymeImage, crops: &[[u32; 4]]) {
+        let fast = fast_complete_intensity(image, crops);
+
+        for (&[x, y, w, h], fast) in crops.iter().zip(fast) {
+            let slow = image.crop_view(x, y, w, h).intensity();
+
+            for (a, b) in fast.iter().zip(&slow[2..5]) {
+                assert!((a - b).abs() <= 1e-3 * b.abs().max(1.), "{} != {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_complete_intensity() {
+        let (width, height) = (40, 30);
+
+        let pixels: Vec<u16> = (0..width * height * 2)
+            .map(|i| ((i * 7919) % 1013) as u16 * (i % 5 != 0) as u16)
+            .collect();
+
+        let image = ThymeImage::U16(ThymeBuffer::new(width, height, 2, pixels).unwrap());
+
+        // Crops covering less of the image than its area are read directly
+        let sparse = [[0, 0, 10, 10], [15, 12, 3, 7], [39, 29, 1, 1]];
+        assert_matches_descriptors(&image, &sparse);
+
+        // Overlapping crops covering more than the image use summed-area tables
+        let dense: Vec<[u32; 4]> = (0..20).map(|i| [i, i / 2, 20, 15]).collect();
+        assert_matches_descriptors(&image, &dense);
+
+        let float = ThymeImage::F32(
+            ThymeBuffer::new(4, 4, 1, (0..16).map(|i| i as f32 - 3.5).collect()).unwrap(),
+        );
+        assert_matches_descriptors(&float, &[[0, 0, 4, 4]; 3]);
+    }
 }
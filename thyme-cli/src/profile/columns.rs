@@ -0,0 +1,553 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use clap::Args;
+
+use thyme_core::catalog::{Column, ColumnStyle, ProfileColumns, Segments, parse_channel_names};
+use thyme_core::error::ThymeError;
+use thyme_core::im::{ChannelPairs, FeatureChannels};
+
+use super::table;
+use crate::filter::BorderPolicy;
+
+#[derive(Debug, Args)]
+#[command(about = "Print the ordered columns of a profile descriptor table as JSON.")]
+pub struct ProfileColumnsArgs {
+    #[arg(
+        long,
+        help = "Segments used for profiling (mask, polygons, boxes).",
+        default_value = "mask"
+    )]
+    pub segments: Option<String>,
+
+    #[arg(
+        long,
+        short = 'm',
+        help = "Mode. Compute descriptors across one or more features including c (complete pixels), f (foreground pixels), b (background pixels), m (binary mask), p (polygons), s (power spectrum), and x (bounding boxes). Defaults to cm for masks and polygons and cx for boxes."
+    )]
+    pub mode: Option<String>,
+
+    #[arg(
+        long,
+        help = "Number of image channels. Required for per-channel descriptors (e.g. centroid_displacement)."
+    )]
+    pub channels: Option<usize>,
+
+    #[arg(
+        short = 'v',
+        long,
+        help = "Include columns only written by verbose runs (e.g. texture_stride)."
+    )]
+    pub verbose: bool,
+
+    #[arg(
+        long,
+        help = "Include boundary band and interior intensity descriptors computed with a boundary band of the provided width (pixels)."
+    )]
+    pub membrane_width: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Include secondary object and primary-minus-secondary intensity descriptors computed from a secondary mask directory."
+    )]
+    pub secondary_masks: Option<String>,
+
+    #[arg(
+        long,
+        help = "Include the class_id and class_frac columns computed from a class mask directory."
+    )]
+    pub class_mask: Option<String>,
+
+    #[arg(
+        long,
+        help = "Include centroid displacement descriptors for each channel."
+    )]
+    pub centroid_displacement: bool,
+
+    #[arg(
+        long,
+        help = "Include mean intensity ratio and median intensity difference descriptors for channel pairs, provided as all (requires channels) or numerator:denominator pairs (e.g. 0:1,2:1)."
+    )]
+    pub channel_pairs: Option<String>,
+
+    #[arg(
+        long,
+        help = "Include intensity percentiles (p10, p25, p75, p90), skewness, and kurtosis in c, f, and b descriptors."
+    )]
+    pub extended_intensity: bool,
+
+    #[arg(
+        long,
+        help = "Include the label column written for labeled bounding boxes (e.g. a csv with a label or class column)."
+    )]
+    pub labels: bool,
+
+    #[arg(
+        long,
+        help = "Handling of objects touching the edge of the image (keep, drop, flag). Flag includes the touches_border column.",
+        default_value = "keep"
+    )]
+    pub border_policy: Option<String>,
+
+    #[arg(
+        long,
+        help = "Only include the sum, mean, and std of complete intensity descriptors written by profile boxes with fast_complete.",
+        conflicts_with = "extended_intensity"
+    )]
+    pub fast_complete: bool,
+
+    #[arg(
+        long,
+        help = "Include intensity, moments, texture, and zernike descriptors computed separately for the listed channels of each family (e.g. 'intensity:0-5;texture:0,1;moments:0').",
+        conflicts_with = "fast_complete"
+    )]
+    pub features_channels: Option<String>,
+
+    #[arg(
+        long,
+        help = "Include foreground texture descriptors computed on objects downsampled by these factors (e.g. 1,2,4)."
+    )]
+    pub texture_scales: Option<String>,
+
+    #[arg(
+        long,
+        help = "Include the thumbnail_path column written when object thumbnails are saved."
+    )]
+    pub thumbnails: bool,
+
+    #[arg(
+        long,
+        help = "Include the preview_scale column written by preview runs."
+    )]
+    pub preview: bool,
+
+    #[arg(
+        long,
+        help = "Naming convention of descriptor columns (thyme, cellprofiler).",
+        default_value = "thyme"
+    )]
+    pub column_style: Option<String>,
+
+    #[arg(
+        long,
+        help = "Comma-separated names of the image channels inserted in cellprofiler column names (e.g. DNA,Actin,Mito)."
+    )]
+    pub channel_names: Option<String>,
+}
+
+pub fn profile_columns(args: &ProfileColumnsArgs) {
+    let columns = columns(args).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    println!("{}", serde_json::to_string_pretty(&columns).unwrap());
+}
+
+/// Resolve the columns written by a profile command with the same arguments
+fn columns(args: &ProfileColumnsArgs) -> Result<Vec<Column>, ThymeError> {
+    let segments = Segments::parse(args.segments.as_deref().unwrap_or("mask"))?;
+
+    let mode = args.mode.to_owned().unwrap_or(match segments {
+        Segments::Boxes => "cx".to_string(),
+        _ => "cm".to_string(),
+    });
+
+    if mode.chars().any(|c| !segments.modes().contains(&c)) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid mode. Argument mode must only contain one or more of: {}",
+            segments
+                .modes()
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )));
+    }
+
+    if args.channels == Some(0) {
+        return Err(ThymeError::OtherError(
+            "channels must be a positive integer if provided".to_string(),
+        ));
+    }
+
+    let centroid_channels = match (args.centroid_displacement, args.channels) {
+        (true, None) => {
+            return Err(ThymeError::OtherError(
+                "channels must be provided alongside centroid_displacement".to_string(),
+            ));
+        }
+        (true, channels) => channels,
+        (false, _) => None,
+    };
+
+    let border_policy = BorderPolicy::parse(args.border_policy.as_deref().unwrap_or("keep"))
+        .map_err(ThymeError::OtherError)?;
+
+    if args.fast_complete && segments != Segments::Boxes {
+        return Err(ThymeError::OtherError(
+            "fast_complete is only supported for boxes segments".to_string(),
+        ));
+    }
+
+    if args.class_mask.is_some() && segments != Segments::Mask {
+        return Err(ThymeError::OtherError(
+            "class_mask is only supported for mask segments".to_string(),
+        ));
+    }
+
+    let feature_channels = args
+        .features_channels
+        .as_deref()
+        .map(FeatureChannels::parse)
+        .transpose()?;
+
+    if let (Some(feature_channels), Some(channels)) = (&feature_channels, args.channels) {
+        feature_channels.check(channels)?;
+    }
+
+    let channel_pairs = match (
+        args.channel_pairs.as_deref().map(ChannelPairs::parse),
+        args.channels,
+    ) {
+        (Some(Ok(ChannelPairs::All)), None) => {
+            return Err(ThymeError::OtherError(
+                "channels must be provided alongside channel_pairs all".to_string(),
+            ));
+        }
+        (Some(pairs), channels) => {
+            let pairs = pairs?;
+
+            if let Some(channels) = channels {
+                pairs.check(channels)?;
+            }
+
+            pairs.resolve(channels.unwrap_or(0))
+        }
+        (None, _) => vec![],
+    };
+
+    let texture_scales = table::texture_scales(args.texture_scales.as_deref())?;
+
+    if !texture_scales.is_empty() && segments == Segments::Boxes {
+        return Err(ThymeError::OtherError(
+            "texture_scales is only supported for mask and polygons segments".to_string(),
+        ));
+    }
+
+    let column_style = ColumnStyle::parse(args.column_style.as_deref().unwrap_or("thyme"))?;
+
+    let channel_names = args
+        .channel_names
+        .as_deref()
+        .map(parse_channel_names)
+        .transpose()?
+        .unwrap_or_default();
+
+    if !channel_names.is_empty() && column_style != ColumnStyle::CellProfiler {
+        return Err(ThymeError::OtherError(
+            "channel_names requires column_style cellprofiler".to_string(),
+        ));
+    }
+
+    if column_style == ColumnStyle::CellProfiler && texture_scales.contains(&1) {
+        return Err(ThymeError::OtherError(
+            "texture_scales cannot include 1 with column_style cellprofiler".to_string(),
+        ));
+    }
+
+    // Strides are only recorded when texture descriptors are computed
+    let texture_modes: &[char] = match segments {
+        Segments::Boxes if args.fast_complete => &[],
+        Segments::Boxes => &['c'],
+        _ => &['c', 'f', 'b'],
+    };
+
+    Ok(ProfileColumns {
+        segments,
+        texture_stride: args.verbose && mode.contains(texture_modes),
+        mode,
+        membrane: args.membrane_width.is_some(),
+        extended_intensity: args.extended_intensity,
+        centroid_channels,
+        secondary: args.secondary_masks.is_some(),
+        labels: args.labels,
+        touches_border: border_policy == BorderPolicy::Flag,
+        classes: args.class_mask.is_some(),
+        fast_complete: args.fast_complete,
+        feature_channels,
+        texture_scales,
+        channel_pairs,
+        thumbnails: args.thumbnails,
+        preview: args.preview,
+        column_style,
+        channel_names,
+    }
+    .columns())
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::{Path, PathBuf};
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::profile::boxes::{ProfileBoxesArgs, profile_image_boxes};
+    use crate::profile::mask::{ProfileMaskArgs, profile_image_mask};
+
+    const TEST_DIR: &str = "../data/tests";
+
+    #[derive(Parser)]
+    struct Cli<T: Args> {
+        #[command(flatten)]
+        args: T,
+    }
+
+    fn parse<T: Args>(args: &[&str]) -> T {
+        Cli::<T>::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args
+    }
+
+    /// Copy fixtures into a temporary directory under new names
+    fn fixture_directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for (source, target) in files {
+            std::fs::copy(Path::new(TEST_DIR).join(source), directory.join(target)).unwrap();
+        }
+
+        directory
+    }
+
+    fn header(path: &Path) -> Vec<String> {
+        let table = std::fs::read_to_string(path).unwrap();
+        table
+            .lines()
+            .next()
+            .unwrap()
+            .split(',')
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn names(args: &[&str]) -> Vec<String> {
+        columns(&parse::<ProfileColumnsArgs>(args))
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_columns_match_profile_mask() {
+        let directory = fixture_directory(
+            "thyme_test_columns_mask",
+            &[
+                ("test_rgb.tif", "a_image.tif"),
+                ("test_mask_integer_u16.npy", "a_mask.npy"),
+            ],
+        );
+
+        let path = directory.to_str().unwrap();
+        let output = directory.join("descriptors.csv");
+        let output = output.to_str().unwrap();
+
+        for options in [
+            vec!["-m", "cm"],
+            vec!["-m", "cfbmp", "--extended-intensity"],
+            vec![
+                "-m",
+                "pc",
+                "--centroid-displacement",
+                "--membrane-width",
+                "2",
+            ],
+            vec!["-m", "m", "-v"],
+            vec!["-m", "fp", "-v"],
+            vec!["-m", "c", "--border-policy", "flag"],
+            vec!["-m", "fp", "-v", "--texture-scales", "1,2,4"],
+            vec![
+                "-m",
+                "cfbm",
+                "--extended-intensity",
+                "--features-channels",
+                "intensity:0-2;texture:0,1;moments:0",
+            ],
+            vec!["-m", "f", "--channel-pairs", "all"],
+            vec![
+                "-m",
+                "cm",
+                "--channel-pairs",
+                "2:0",
+                "--centroid-displacement",
+            ],
+            vec![
+                "-m",
+                "cfbmp",
+                "--extended-intensity",
+                "--column-style",
+                "cellprofiler",
+                "--channel-names",
+                "DNA,Actin,Mito",
+                "--features-channels",
+                "intensity:0-2;texture:0,1;zernike:2",
+            ],
+            vec![
+                "-m",
+                "fp",
+                "--column-style",
+                "cellprofiler",
+                "--texture-scales",
+                "2",
+            ],
+        ] {
+            let mut args = vec![
+                "-i",
+                path,
+                "-o",
+                output,
+                "--overwrite",
+                "--image-substring",
+                "_image",
+                "--mask-substring",
+                "_mask",
+            ];
+            args.extend(&options);
+
+            profile_image_mask(&parse::<ProfileMaskArgs>(&args));
+
+            let mut catalog = options.clone();
+            catalog.extend(["--channels", "3"]);
+
+            assert_eq!(header(Path::new(output)), names(&catalog), "{:?}", options);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_columns_match_profile_boxes() {
+        let directory = fixture_directory(
+            "thyme_test_columns_boxes",
+            &[
+                ("test_rgb.png", "a_image.png"),
+                ("test_boxes.json", "a_boxes.json"),
+            ],
+        );
+
+        let path = directory.to_str().unwrap();
+        let output = directory.join("descriptors.csv");
+        let output = output.to_str().unwrap();
+
+        for options in [
+            vec!["-m", "cx"],
+            vec!["-m", "x", "-v"],
+            vec!["-m", "c", "-v"],
+            vec!["-m", "cx", "-v", "--fast-complete"],
+            vec!["-m", "cx", "--features-channels", "texture:2;zernike:0"],
+        ] {
+            let mut args = vec![
+                "-i",
+                path,
+                "-o",
+                output,
+                "--overwrite",
+                "--image-substring",
+                "_image",
+                "--box-substring",
+                "_boxes",
+            ];
+            args.extend(&options);
+
+            profile_image_boxes(&parse::<ProfileBoxesArgs>(&args));
+
+            let mut catalog = options.clone();
+            catalog.extend(["--segments", "boxes"]);
+
+            assert_eq!(header(Path::new(output)), names(&catalog), "{:?}", options);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_columns_match_profile_boxes_table() {
+        let directory = fixture_directory(
+            "thyme_test_columns_boxes_table",
+            &[
+                ("test_rgb.png", "a_image.png"),
+                ("test_rgb.png", "b_image.png"),
+            ],
+        );
+
+        let table = directory.join("boxes.csv");
+
+        std::fs::write(
+            &table,
+            "image,x1,y1,x2,y2,class\na_image.png,0,0,10,10,cell\nb,2,2,12,14,debris\na_image.png,5,5,15,15,cell\n",
+        )
+        .unwrap();
+
+        let path = directory.to_str().unwrap();
+        let output = directory.join("descriptors.csv");
+        let output = output.to_str().unwrap();
+
+        profile_image_boxes(&parse::<ProfileBoxesArgs>(&[
+            "-i",
+            path,
+            "-s",
+            table.to_str().unwrap(),
+            "-o",
+            output,
+            "--image-substring",
+            "_image",
+            "-m",
+            "x",
+        ]));
+
+        assert_eq!(
+            header(Path::new(output)),
+            names(&["--segments", "boxes", "-m", "x", "--labels"])
+        );
+
+        let rows: Vec<String> = std::fs::read_to_string(output)
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(|row| row.split(',').take(3).collect::<Vec<&str>>().join(","))
+            .collect();
+
+        assert_eq!(
+            rows,
+            ["a_image,0,cell", "a_image,1,cell", "b_image,0,debris"]
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_columns_invalid() {
+        let invalid = [
+            vec!["--segments", "boxes", "-m", "cf"],
+            vec!["--centroid-displacement"],
+            vec!["--channels", "0"],
+            vec!["--segments", "points"],
+            vec!["--fast-complete"],
+            vec!["--features-channels", "shape:0"],
+            vec!["--features-channels", "texture:3", "--channels", "3"],
+            vec!["--channel-pairs", "all"],
+            vec!["--channel-pairs", "0:3", "--channels", "3"],
+        ];
+
+        for args in invalid {
+            assert!(
+                columns(&parse::<ProfileColumnsArgs>(&args)).is_err(),
+                "{:?}",
+                args
+            );
+        }
+    }
+}
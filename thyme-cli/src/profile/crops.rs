@@ -0,0 +1,582 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::Path;
+
+use clap::Args;
+
+use thyme_core::catalog;
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im;
+use thyme_core::io;
+use thyme_core::ut;
+
+use super::ProfileSummary;
+use super::table::{self, Descriptors, NonFiniteReport};
+use crate::crops;
+use crate::filter::NonFinitePolicy;
+use crate::output::OutputMode;
+use crate::prefetch::Prefetch;
+
+/// Modes that are measured from an object mask
+const MASK_MODES: [char; 5] = ['f', 'b', 'm', 'p', 's'];
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Compute morphological descriptors from a directory of single-object crops without segments."
+)]
+pub struct ProfileCropsArgs {
+    #[arg(
+        short = 'i',
+        long,
+        help = "Directory of single-object crops.",
+        required = true
+    )]
+    pub images: Option<String>,
+
+    #[arg(
+        short = 'o',
+        long,
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather).",
+        required = true
+    )]
+    pub output: Option<String>,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+
+    #[arg(
+        long,
+        short = 'm',
+        help = "Mode. Compute descriptors across one or more features including c (complete pixels) and x (bounding boxes). Modes f (foreground pixels), b (background pixels), m (binary mask), p (polygons), and s (power spectrum) require --auto-mask.",
+        default_value = "c"
+    )]
+    pub mode: Option<String>,
+
+    #[arg(
+        long,
+        help = "Segment the largest object in the Otsu foreground of each crop so that mask modes (f, b, m, p) can be computed."
+    )]
+    pub auto_mask: bool,
+
+    #[arg(long, help = "Substring specifying crops (e.g. _crop).")]
+    pub image_substring: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting crops by their path relative to the crop directory (e.g. 'plate_1_*.png').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting crops by their path relative to the crop directory (e.g. '_A0[1-3]_').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Handling of objects with non-finite (NaN or infinite) descriptors: keep, zero (replaces non-finite values with zero), or drop-object.",
+        default_value = "keep"
+    )]
+    pub nonfinite_policy: Option<String>,
+
+    #[arg(
+        long,
+        help = "Include intensity percentiles (p10, p25, p75, p90), skewness, and kurtosis in c, f, and b descriptors."
+    )]
+    pub extended_intensity: bool,
+
+    #[arg(
+        long,
+        help = "Only accumulate texture co-occurrences anchored at every n-th row and column of each object.",
+        default_value = "1"
+    )]
+    pub texture_stride: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (tables and reports) from the output location before profiling. Without it, output locations holding a previous run are rejected."
+    )]
+    pub overwrite: bool,
+
+    #[arg(short = 't', long, help = "Number of threads.")]
+    pub threads: Option<usize>,
+}
+
+pub fn profile_image_crops(args: &ProfileCropsArgs) {
+    if let Err(err) = ut::threads::install(args.threads, || run(args)) {
+        eprintln!("[thyme::profile::crops] ERROR: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Profile every crop in a directory as a single object
+///
+/// Each crop is one object named after its file (see `crops::object_id`)
+/// with its bounding box spanning the crop and its centroid at the center
+/// of the crop.
+fn run(args: &ProfileCropsArgs) -> Result<ProfileSummary, ThymeError> {
+    let mode = args.mode.to_owned().unwrap_or("c".to_string());
+
+    let segments = match args.auto_mask {
+        true => catalog::Segments::Mask,
+        false => catalog::Segments::Boxes,
+    };
+
+    if let Some(c) = mode.chars().find(|c| MASK_MODES.contains(c)) {
+        if !args.auto_mask {
+            return Err(ThymeError::OtherError(format!(
+                "Mode {} requires an object mask but crops are profiled without segments. Pass --auto-mask to segment each crop using an Otsu threshold or use modes c and x",
+                c
+            )));
+        }
+    }
+
+    if mode.chars().any(|c| !segments.modes().contains(&c)) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid mode {}. Argument mode must only contain one or more of: {}",
+            mode,
+            segments
+                .modes()
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )));
+    }
+
+    let nonfinite_policy =
+        NonFinitePolicy::parse(args.nonfinite_policy.as_deref().unwrap_or("keep"))
+            .map_err(super::invalid)?;
+
+    let texture_stride = args.texture_stride.unwrap_or(1);
+
+    if texture_stride < 1 {
+        return Err(ThymeError::OtherError(
+            "texture_stride must be a positive integer".to_string(),
+        ));
+    }
+
+    let ipc_compression = args
+        .ipc_compression
+        .to_owned()
+        .unwrap_or("none".to_string());
+
+    if !constant::SUPPORTED_IPC_COMPRESSION.contains(&ipc_compression.as_str()) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid ipc_compression {}. Must be one of: {:?}",
+            ipc_compression,
+            constant::SUPPORTED_IPC_COMPRESSION
+        )));
+    }
+
+    let float_format = io::FloatFormat::new(
+        args.float_precision,
+        args.float_format.as_deref().unwrap_or("auto"),
+    )?;
+
+    let files = crops::select(
+        args.images.as_deref().unwrap_or_default(),
+        args.image_substring.as_deref(),
+        args.image_glob.as_deref(),
+        args.image_regex.as_deref(),
+    )?;
+
+    ut::track::progress_log(
+        &format!(
+            "Detected {} crops.",
+            ut::track::thousands_format(files.len())
+        ),
+        args.verbose,
+    );
+
+    let output = super::output_path(
+        args.output.as_deref().unwrap_or_default(),
+        OutputMode::new(args.overwrite, false)?,
+        &[args.images.as_deref().unwrap_or_default()],
+        "profile::crops",
+    )?;
+
+    let descriptor_columns = catalog::ProfileColumns {
+        segments,
+        mode: mode.clone(),
+        extended_intensity: args.extended_intensity,
+        ..Default::default()
+    }
+    .descriptor_names();
+
+    let pb = ut::track::progress_bar(files.len(), "Profiling", args.verbose);
+
+    let runs: Vec<Result<Descriptors, ThymeError>> = Prefetch::new(None, None)?.run(
+        &files,
+        pb,
+        |path| im::ThymeImage::open(path),
+        |path, image| {
+            let mut descriptors = Descriptors::with_capacity(1);
+            descriptors.channels = image.channels() as usize;
+
+            let row = profile(
+                &image,
+                &mode,
+                args.auto_mask,
+                args.extended_intensity,
+                texture_stride,
+            )?;
+
+            descriptors.push(crops::object_id(path).1, row);
+            descriptors.sanitize(nonfinite_policy);
+
+            Ok(descriptors)
+        },
+    );
+
+    let mut failure: Vec<String> = Vec::with_capacity(files.len());
+    let mut report = NonFiniteReport::default();
+
+    let mut name: Vec<String> = vec![];
+    let mut data: Vec<Descriptors> = Vec::with_capacity(files.len());
+
+    for (path, run) in files.iter().zip(runs) {
+        match run {
+            Ok(descriptors) => {
+                report.add(&descriptor_columns, &descriptors.nonfinite);
+
+                let image = crops::object_id(path).0;
+                name.extend(descriptors.ids.iter().map(|_| image.clone()));
+                data.push(descriptors);
+            }
+            Err(err) => failure.push(format!("{}\t{}", stem(path), err)),
+        }
+    }
+
+    let images = data.len();
+    let objects = name.len();
+
+    if args.verbose {
+        println!();
+    }
+
+    ut::track::progress_log(
+        &format!(
+            "Complete. {} profiles computed across {} crops.",
+            ut::track::thousands_format(objects),
+            ut::track::thousands_format(images)
+        ),
+        args.verbose,
+    );
+
+    let mut descriptors = None;
+
+    if images > 0 {
+        let mut df = table::dataframe(&name, data, &descriptor_columns);
+
+        let descriptors_path = if output.is_dir() {
+            output.join("descriptors.csv")
+        } else {
+            output.clone()
+        };
+
+        io::write_table_with_float_format(
+            &mut df,
+            &descriptors_path,
+            &ipc_compression,
+            &float_format,
+        )
+        .map_err(|err| {
+            ThymeError::OtherError(format!("Failed to write descriptors table: {}", err))
+        })?;
+
+        descriptors = Some(descriptors_path);
+    }
+
+    if !report.is_empty() {
+        eprintln!(
+            "[thyme::profile::crops] WARNING: {} objects have non-finite values in {} descriptor columns ({}).",
+            ut::track::thousands_format(report.objects()),
+            report.len(),
+            nonfinite_policy.describe()
+        );
+    }
+
+    if output.is_dir() {
+        super::write_reports(&output, &report, &[], &[], &[], &failure, &[])?;
+    }
+
+    crate::output::finish_run(&output)?;
+
+    Ok(ProfileSummary {
+        images,
+        failures: failure.len(),
+        skipped: 0,
+        objects,
+        failed_objects: 0,
+        output,
+        descriptors,
+        dry_run: None,
+    })
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().to_string()
+}
+
+/// Compute the descriptors of a crop as a single object
+///
+/// Descriptors follow the column order of boxes segments, or of mask
+/// segments if the object is segmented with an Otsu threshold.
+fn profile(
+    image: &im::ThymeImage,
+    mode: &str,
+    auto_mask: bool,
+    extended_intensity: bool,
+    texture_stride: usize,
+) -> Result<Vec<f32>, ThymeError> {
+    let (w, h) = (image.width(), image.height());
+
+    let mut result: Vec<f32> = Vec::with_capacity(100);
+
+    result.extend(table::location(&[0.0, 0.0, w as f32, h as f32], None));
+
+    let view = image.crop_view(0, 0, w, h);
+
+    if !auto_mask {
+        if mode.contains("x") {
+            result.extend([w as f32, h as f32, (w * h) as f32]);
+        }
+
+        if mode.contains("c") {
+            result.extend(match extended_intensity {
+                true => view.descriptors_extended_strided(texture_stride, None),
+                false => view.descriptors_strided(texture_stride),
+            });
+        }
+
+        return Ok(result);
+    }
+
+    let mut binary = crops::auto_mask(image)?;
+
+    if mode.contains("p") {
+        let (_, mut polygons) = binary.polygons()?;
+        result.extend(polygons.descriptors()[0]);
+    }
+
+    let mask_object = binary.crop_view(0, 0, w, h);
+    let regions = im::Regions::from_mode(mode);
+
+    if !regions.is_empty() {
+        result.extend(view.descriptors_regions(
+            &mask_object,
+            regions,
+            extended_intensity,
+            None,
+            texture_stride,
+        ));
+    }
+
+    if mode.contains("m") {
+        result.extend(&mask_object.moments());
+        result.extend(&mask_object.zernike());
+    }
+
+    if mode.contains("s") {
+        result.extend(view.spectrum(&mask_object));
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::PathBuf;
+
+    use clap::Parser;
+
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+
+    const CROPS: usize = 3;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: ProfileCropsArgs,
+    }
+
+    fn args(args: &[&str]) -> ProfileCropsArgs {
+        Cli::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args
+    }
+
+    /// Directory of single-blob crops with one crop named by its object id
+    fn crop_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 32,
+            height: 24,
+            objects: 1,
+            radius: [5.0, 8.0],
+            seed: 4,
+            ..Default::default()
+        }
+        .write(CROPS, directory.join("crops"))
+        .unwrap();
+
+        std::fs::rename(
+            directory.join("crops").join("synth_0002_image.png"),
+            directory.join("crops").join("plate_A01_image_7.png"),
+        )
+        .unwrap();
+
+        directory
+    }
+
+    /// Columns of a csv table by name
+    fn table_columns(path: &Path) -> Vec<(String, Vec<String>)> {
+        let table = std::fs::read_to_string(path).unwrap();
+        let rows: Vec<Vec<&str>> = table.lines().map(|row| row.split(',').collect()).collect();
+
+        (0..rows[0].len())
+            .map(|j| {
+                let values = rows[1..].iter().map(|row| row[j].to_string()).collect();
+                (rows[0][j].to_string(), values)
+            })
+            .collect()
+    }
+
+    fn column<'a>(columns: &'a [(String, Vec<String>)], name: &str) -> &'a [String] {
+        &columns.iter().find(|(n, _)| n == name).unwrap().1
+    }
+
+    #[test]
+    fn test_profile_crops() {
+        let directory = crop_directory("thyme_test_profile_crops");
+        let crops = directory.join("crops");
+        let output = directory.join("descriptors.csv");
+
+        let summary = run(&args(&[
+            "-i",
+            crops.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--image-substring",
+            "_image",
+            "-m",
+            "cx",
+        ]))
+        .unwrap();
+
+        assert_eq!((summary.images, summary.objects), (CROPS, CROPS));
+
+        let columns = table_columns(&output);
+
+        // Each crop is one object named after its file and centered in the crop
+        assert_eq!(
+            column(&columns, "image"),
+            ["plate_A01_image", "synth_0000_image", "synth_0001_image"]
+        );
+        assert_eq!(column(&columns, "object"), ["7", "0", "0"]);
+        assert_eq!(column(&columns, "centroid_x"), ["16.0"; CROPS]);
+        assert_eq!(column(&columns, "centroid_y"), ["12.0"; CROPS]);
+        assert_eq!(column(&columns, "bbox_area"), ["768.0"; CROPS]);
+        assert!(
+            columns
+                .iter()
+                .all(|(name, _)| !name.starts_with("foreground"))
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_crops_auto_mask() {
+        let directory = crop_directory("thyme_test_profile_crops_auto_mask");
+        let crops = directory.join("crops");
+        let crops = crops.to_str().unwrap();
+
+        // Mask modes are rejected unless crops are segmented
+        let err = run(&args(&[
+            "-i",
+            crops,
+            "-o",
+            directory.join("rejected").to_str().unwrap(),
+            "--image-substring",
+            "_image",
+            "-m",
+            "cf",
+        ]))
+        .unwrap_err();
+
+        assert!(err.to_string().contains("--auto-mask"), "{}", err);
+        assert!(!directory.join("rejected").exists());
+
+        let output = directory.join("masked");
+
+        let summary = run(&args(&[
+            "-i",
+            crops,
+            "-o",
+            output.to_str().unwrap(),
+            "--image-substring",
+            "_image",
+            "-m",
+            "cfbmp",
+            "--auto-mask",
+        ]))
+        .unwrap();
+
+        assert_eq!((summary.images, summary.failures), (CROPS, 0));
+
+        let columns = table_columns(&output.join("descriptors.csv"));
+
+        let names = catalog::ProfileColumns {
+            segments: catalog::Segments::Mask,
+            mode: "cfbmp".to_string(),
+            ..Default::default()
+        }
+        .descriptor_names();
+
+        assert_eq!(columns.len(), 2 + names.len());
+
+        // The foreground of the blob is brighter than the rest of the crop
+        for (foreground, background) in column(&columns, "foreground_intensity_mean")
+            .iter()
+            .zip(column(&columns, "background_intensity_mean"))
+        {
+            let foreground: f32 = foreground.parse().unwrap();
+            let background: f32 = background.parse().unwrap();
+            assert!(foreground > background, "{} <= {}", foreground, background);
+        }
+
+        assert_eq!(column(&columns, "centroid_x"), ["16.0"; CROPS]);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
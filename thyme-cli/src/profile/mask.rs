@@ -1,33 +1,59 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::Mutex;
 
 use clap::Args;
-use kdam::TqdmParallelIterator;
 use polars::prelude::*;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
+use thyme_core::catalog;
 use thyme_core::constant;
 use thyme_core::error::ThymeError;
 use thyme_core::im;
 use thyme_core::io;
+use thyme_core::mp::reduce::{DEVICE_MIN_SUBPIXELS, MeasureDevice, Reduce};
+use thyme_core::mp::texture;
 use thyme_core::ut;
 
-#[derive(Debug, Args)]
+use thyme_config::ProfileMaskConfig;
+
+use super::ProfileSummary;
+use super::preview::Preview;
+use super::summary::SummaryRow;
+use super::table::{self, Descriptors, NonFiniteReport};
+use super::thumbnail::Thumbnails;
+use crate::classes::{self, ClassMasks};
+use crate::dryrun;
+use crate::failures::{self, ObjectBudget, OversizedPolicy, Stage};
+use crate::filter::{self, BorderPolicy, DropCounts, NonFinitePolicy, ObjectFilter};
+use crate::output::{MetadataJoin, OutputMode, PerImageOutput};
+use crate::pipeline;
+use crate::prefetch::Prefetch;
+use crate::probability::ProbabilityMask;
+use crate::provenance;
+use crate::remote::RemoteInputs;
+use crate::status::Progress;
+
+#[derive(Debug, Args, Serialize)]
 pub struct ProfileMaskArgs {
-    #[arg(short = 'i', long, help = "Image directory.", required = true)]
+    #[arg(
+        short = 'i',
+        long,
+        help = "Image directory or s3:// or https:// url.",
+        required = true
+    )]
     pub images: Option<String>,
 
-    #[arg(short = 's', long, help = "Mask directory.")]
+    #[arg(short = 's', long, help = "Mask directory or s3:// or https:// url.")]
     pub masks: Option<String>,
 
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq).",
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather).",
         required = true
     )]
     pub output: Option<String>,
@@ -35,17 +61,56 @@ pub struct ProfileMaskArgs {
     #[arg(short = 'v', long, help = "Verbose output.")]
     pub verbose: bool,
 
-    #[arg(short = 'd', long, help = "Exclude objects touching edge of image.")]
+    #[arg(
+        long,
+        help = "Show a live status panel with progress, throughput, workers, recent errors, and memory usage instead of the progress bar. Logs status lines instead if stdout is not a terminal."
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Format of the status lines logged instead of the status panel (plain, json). Json status lines are also logged without --tui.",
+        default_value = "plain"
+    )]
+    pub log_format: Option<String>,
+
+    #[arg(
+        short = 'd',
+        long,
+        help = "Exclude objects touching edge of image. Equivalent to --border-policy drop.",
+        conflicts_with = "border_policy"
+    )]
     pub drop_borders: bool,
 
+    #[arg(
+        long,
+        help = "Handling of objects whose unpadded bounding box touches the edge of the image: keep, drop, or flag (keeps objects and adds a touches_border column).",
+        default_value = "keep"
+    )]
+    pub border_policy: Option<String>,
+
+    #[arg(
+        long,
+        help = "Handling of objects with non-finite (NaN or infinite) descriptors: keep, zero (replaces non-finite values with zero), or drop-object.",
+        default_value = "keep"
+    )]
+    pub nonfinite_policy: Option<String>,
+
     #[arg(
         long,
         short = 'm',
-        help = "Mode. Compute descriptors across one or more features including c (complete pixels), f (foreground pixels), b (background pixels), m (binary mask), p (polygons), and x (bounding boxes).",
+        help = "Mode. Compute descriptors across one or more features including c (complete pixels), f (foreground pixels), b (background pixels), m (binary mask), p (polygons), s (power spectrum), and x (bounding boxes).",
         default_value = "cm"
     )]
     pub mode: Option<String>,
 
+    #[arg(
+        long,
+        help = "Pixels measured as the background of mode b: bbox (crop pixels outside of the object) or ring:<px> (pixels within px pixels outside of the object boundary that do not belong to any object).",
+        default_value = "bbox"
+    )]
+    pub background_style: Option<String>,
+
     #[arg(
         short = 'p',
         long,
@@ -62,186 +127,1077 @@ pub struct ProfileMaskArgs {
 
     #[arg(
         long,
-        help = "Exclude objects smaller than a minimum size.",
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting masks by their path relative to their directory.",
+        conflicts_with_all = ["mask_substring", "mask_regex"]
+    )]
+    pub mask_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting masks by their path relative to their directory.",
+        conflicts_with = "mask_substring"
+    )]
+    pub mask_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Not supported for masks, which must be resampled onto the image grid before profiling. Use thyme profile polygons --segment-transform to register polygons instead."
+    )]
+    pub segment_transform: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with an unpadded bounding box width or height smaller than a minimum size (pixels).",
         default_value = "1"
     )]
     pub min_size: Option<u32>,
 
+    #[arg(
+        long,
+        help = "Exclude objects with a mask area smaller than a minimum area (pixels)."
+    )]
+    pub min_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with a mask area larger than a maximum area (pixels)."
+    )]
+    pub max_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Resample or skip objects whose outline has more vertices than a maximum before they are measured. Objects over the budget are recorded in failed_objects.tsv."
+    )]
+    pub max_points_per_object: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Handling of objects with more vertices than max_points_per_object. Options: resample (measure the object on an outline resampled to max_points_per_object vertices), skip (exclude the object) [default: resample]."
+    )]
+    pub oversized_policy: Option<String>,
+
+    #[arg(
+        long,
+        help = "Abandon the remaining descriptor families of an object once its measurement has taken longer than a maximum time (seconds). Descriptors that were not computed are handled by the nonfinite_policy and the object is recorded in failed_objects.tsv."
+    )]
+    pub max_seconds_per_object: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Skip images before profiling their objects if an expression of image metrics is true (e.g. 'foreground_fraction<0.001 || focus_score<30'). Conditions compare foreground_fraction, focus_score, background_mean, or background_median to a number and are joined by && and ||. Skipped images are written to skipped_images.tsv."
+    )]
+    pub skip_image_if: Option<String>,
+
+    #[arg(
+        long,
+        help = "Fail images with masks that may be bit-depth truncated instead of warning."
+    )]
+    pub strict_masks: bool,
+
+    #[arg(
+        long,
+        help = "Treat masks as float probability maps (e.g. npy, tiff) and label pixels with a probability above this threshold (e.g. 0.5)."
+    )]
+    pub prob_threshold: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Split touching objects in probability maps using a watershed seeded at probability maxima at least this far apart (pixels).",
+        requires = "prob_threshold"
+    )]
+    pub prob_min_distance: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Handling of (Z, H, W) numpy mask stacks (error, max-project, per-slice). Per-slice stacks are paired with (Z, H, W) or (Z, H, W, C) numpy image stacks of matching depth.",
+        default_value = "error"
+    )]
+    pub zstack_mode: Option<String>,
+
+    #[arg(
+        long,
+        help = "Layout of mask files (auto, cellpose-seg). Cellpose-seg reads the masks of Cellpose _seg.npy files, including secondary masks.",
+        default_value = "auto"
+    )]
+    pub mask_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compute boundary band and interior intensity descriptors using a boundary band of the provided width (pixels)."
+    )]
+    pub membrane_width: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Secondary mask directory (e.g. nuclei) with objects contained in the primary masks. Adds secondary count, area, area ratio, and primary-minus-secondary intensity descriptors."
+    )]
+    pub secondary_masks: Option<String>,
+
+    #[arg(
+        long,
+        help = "Substring specifying secondary masks (e.g. _nuc).",
+        requires = "secondary_masks"
+    )]
+    pub secondary_substring: Option<String>,
+
+    #[arg(
+        long,
+        help = "Class mask directory with the semantic class id of each pixel (e.g. from a panoptic segmentation). Adds the majority class id (class_id) and its pixel fraction (class_frac) for each object."
+    )]
+    pub class_mask: Option<String>,
+
+    #[arg(
+        long,
+        help = "Substring specifying class masks (e.g. _class).",
+        requires = "class_mask"
+    )]
+    pub class_substring: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compute the displacement between the mask centroid and the intensity-weighted centroid of each channel, normalized by equivalent diameter."
+    )]
+    pub centroid_displacement: bool,
+
+    #[arg(
+        long,
+        help = "Compute the mean intensity ratio and median intensity difference of foreground pixels between channel pairs, provided as all (every ordered pair) or numerator:denominator pairs (e.g. 0:1,2:1)."
+    )]
+    pub channel_pairs: Option<String>,
+
+    #[arg(
+        long,
+        help = "Include intensity percentiles (p10, p25, p75, p90), skewness, and kurtosis in c, f, and b descriptors."
+    )]
+    pub extended_intensity: bool,
+
+    #[arg(
+        long,
+        requires = "extended_intensity",
+        help = "Number of positive pixels of a channel above which extended intensity percentiles are selected without sorting (e.g. 10000000). Percentiles are exact and results are identical to sorting below the threshold, while skewness and kurtosis may differ in the last digits above it."
+    )]
+    pub approx_percentiles_over: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Compute intensity, moments, texture, and zernike descriptors separately for the listed channels of each family, provided as family:channels pairs (e.g. 'intensity:0-5;texture:0,1;moments:0'). Families that are not listed are not computed."
+    )]
+    pub features_channels: Option<String>,
+
+    #[arg(
+        long,
+        help = "Only accumulate texture co-occurrences anchored at every n-th row and column of each object.",
+        default_value = "1"
+    )]
+    pub texture_stride: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Escalate the texture stride for objects with more pixels than this threshold so that roughly this many pixels are sampled (e.g. 250000). Applied strides are written to a texture_stride column when verbose."
+    )]
+    pub texture_auto_stride: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Device that intensity sums, extrema, and moments of objects with at least 1048576 subpixels are reduced on (cpu, cuda). Smaller objects and the remaining descriptors are measured on the cpu.",
+        default_value = "cpu"
+    )]
+    pub measure_device: Option<String>,
+
+    #[arg(
+        long,
+        help = "Smooth object outlines before computing polygon (p) descriptors, provided as gaussian:<sigma> (points) or chaikin:<iterations> (e.g. gaussian:1.5). Pixel descriptors are measured on the unsmoothed mask. The mean relative change in polygon area is logged when verbose."
+    )]
+    pub smooth_contours: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compute foreground texture descriptors of each object crop and mask downsampled by these factors (e.g. 1,2,4). Scales where the downsampled crop is smaller than 8 pixels on a side are filled with zeros."
+    )]
+    pub texture_scales: Option<String>,
+
+    #[arg(
+        long,
+        help = "Directory to write a letterboxed 8-bit thumbnail of each object crop to. Adds a thumbnail_path column referencing each thumbnail."
+    )]
+    pub thumbnails: Option<String>,
+
+    #[arg(
+        long,
+        help = "Width and height of object thumbnails (pixels).",
+        default_value = "64"
+    )]
+    pub thumbnail_size: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Profile a preview of each image and its segments downsampled by this factor in (0, 1] (e.g. 0.25) for rapid parameter tuning. Adds a preview_scale column after the object column."
+    )]
+    pub preview: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Rescale lengths, areas, and spatial frequencies of a preview to original pixel units.",
+        requires = "preview"
+    )]
+    pub preview_rescale: bool,
+
+    #[arg(
+        long,
+        help = "Metadata table (.csv, .txt, .tsv, .pq, .arrow, .feather) joined to every output row (e.g. a platemap)."
+    )]
+    pub metadata_table: Option<String>,
+
+    #[arg(
+        long,
+        help = "Name of the join key column in the metadata table (e.g. well)."
+    )]
+    pub metadata_join: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex extracting the join key from image file names using the first capture group or full match (e.g. '_([A-P][0-9]{2})_'). Defaults to the image file name."
+    )]
+    pub well_from_filename: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exit with an error instead of writing nulls when an image has no matching metadata."
+    )]
+    pub strict_join: bool,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Naming convention of descriptor columns (thyme, cellprofiler). Cellprofiler renames descriptors with a CellProfiler equivalent (e.g. AreaShape_Area, Intensity_MeanIntensity_DNA) and prefixes the others with Thyme_.",
+        default_value = "thyme"
+    )]
+    pub column_style: Option<String>,
+
+    #[arg(
+        long,
+        help = "Comma-separated names of the image channels inserted in cellprofiler column names (e.g. DNA,Actin,Mito)."
+    )]
+    pub channel_names: Option<String>,
+
+    #[arg(
+        long,
+        help = "Append the polygon of each object as a polygon column. Requires parquet output (a .pq output file or --per-image-format pq)."
+    )]
+    pub include_geometry: bool,
+
+    #[arg(
+        long,
+        help = "Encoding of the polygon column (list, wkb). List writes [x, y] vertex lists (List<List<Float32>>) and wkb writes well-known binary polygons readable by GeoPandas.",
+        default_value = "list"
+    )]
+    pub geometry_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Maximum number of vertices of each polygon in the polygon column. Polygons with more vertices are resampled to equidistant vertices along their outline.",
+        default_value = "128"
+    )]
+    pub geometry_max_points: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Write a separate descriptors/{image} table for each image as soon as it is profiled. Requires an output directory."
+    )]
+    pub per_image_output: bool,
+
+    #[arg(
+        long,
+        help = "Format of per-image output tables (csv, txt, tsv, pq, arrow, feather).",
+        default_value = "csv"
+    )]
+    pub per_image_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip images whose per-image output table already exists.",
+        requires = "per_image_output"
+    )]
+    pub resume: bool,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (tables, reports, and provenance sidecars) from the output location before profiling. Without it, output locations holding a previous run are rejected.",
+        conflicts_with = "resume"
+    )]
+    pub overwrite: bool,
+
+    #[arg(
+        long,
+        help = "Report the pairs, object counts, table width, and estimated output size as a table and in dry_run.json next to the output, without profiling or creating the output."
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        help = "Number of decoded images queued ahead of the compute threads. If 0, images are decoded by the compute threads.",
+        default_value = "0"
+    )]
+    pub prefetch: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Number of threads decoding images ahead of the compute threads if --prefetch is set.",
+        default_value = "2"
+    )]
+    pub io_threads: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Directory caching objects fetched from s3:// or https:// inputs. Cached objects are re-used by later runs. If not set, objects are fetched into a temporary directory and removed once their image is profiled."
+    )]
+    pub remote_cache: Option<String>,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
 
 pub fn profile_image_mask(args: &ProfileMaskArgs) {
-    if let Some(threads) = args.threads.to_owned() {
-        if threads < 1 {
-            println!(
-                "[thyme::profile::mask] Threads must be set to a positive integer if provided."
-            );
-            std::process::exit(1);
-        }
+    if let Err(err) = run_profile_mask(&ProfileMaskConfig::from(args)) {
+        eprintln!("[thyme::profile::mask] ERROR: {}", err);
+        std::process::exit(1);
+    }
+}
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
+impl From<&ProfileMaskArgs> for ProfileMaskConfig {
+    fn from(args: &ProfileMaskArgs) -> Self {
+        let defaults = ProfileMaskConfig::default();
+
+        ProfileMaskConfig {
+            images: args.images.to_owned().unwrap_or_default(),
+            masks: args.masks.to_owned(),
+            output: args.output.to_owned().unwrap_or_default(),
+            verbose: args.verbose,
+            tui: args.tui,
+            log_format: args.log_format.to_owned().unwrap_or(defaults.log_format),
+            drop_borders: args.drop_borders,
+            border_policy: args
+                .border_policy
+                .to_owned()
+                .unwrap_or(defaults.border_policy),
+            nonfinite_policy: args
+                .nonfinite_policy
+                .to_owned()
+                .unwrap_or(defaults.nonfinite_policy),
+            mode: args.mode.to_owned().unwrap_or(defaults.mode),
+            background_style: args
+                .background_style
+                .to_owned()
+                .unwrap_or(defaults.background_style),
+            pad: args.pad.unwrap_or(defaults.pad),
+            image_substring: args.image_substring.to_owned(),
+            mask_substring: args.mask_substring.to_owned(),
+            image_glob: args.image_glob.to_owned(),
+            image_regex: args.image_regex.to_owned(),
+            mask_glob: args.mask_glob.to_owned(),
+            mask_regex: args.mask_regex.to_owned(),
+            segment_transform: args.segment_transform.to_owned(),
+            min_size: args.min_size.unwrap_or(defaults.min_size),
+            min_area: args.min_area,
+            max_area: args.max_area,
+            max_points_per_object: args.max_points_per_object,
+            oversized_policy: args
+                .oversized_policy
+                .to_owned()
+                .unwrap_or(defaults.oversized_policy),
+            max_seconds_per_object: args.max_seconds_per_object,
+            skip_image_if: args.skip_image_if.to_owned(),
+            strict_masks: args.strict_masks,
+            prob_threshold: args.prob_threshold,
+            prob_min_distance: args.prob_min_distance,
+            zstack_mode: args.zstack_mode.to_owned().unwrap_or(defaults.zstack_mode),
+            mask_format: args.mask_format.to_owned().unwrap_or(defaults.mask_format),
+            membrane_width: args.membrane_width,
+            secondary_masks: args.secondary_masks.to_owned(),
+            secondary_substring: args.secondary_substring.to_owned(),
+            class_mask: args.class_mask.to_owned(),
+            class_substring: args.class_substring.to_owned(),
+            centroid_displacement: args.centroid_displacement,
+            extended_intensity: args.extended_intensity,
+            approx_percentiles_over: args.approx_percentiles_over,
+            features_channels: args.features_channels.to_owned(),
+            channel_pairs: args.channel_pairs.to_owned(),
+            texture_stride: args.texture_stride.unwrap_or(defaults.texture_stride),
+            texture_auto_stride: args.texture_auto_stride,
+            measure_device: args
+                .measure_device
+                .to_owned()
+                .unwrap_or(defaults.measure_device),
+            smooth_contours: args.smooth_contours.to_owned(),
+            texture_scales: args.texture_scales.to_owned(),
+            thumbnails: args.thumbnails.to_owned(),
+            thumbnail_size: args.thumbnail_size.unwrap_or(defaults.thumbnail_size),
+            preview: args.preview,
+            preview_rescale: args.preview_rescale,
+            metadata_table: args.metadata_table.to_owned(),
+            metadata_join: args.metadata_join.to_owned(),
+            well_from_filename: args.well_from_filename.to_owned(),
+            strict_join: args.strict_join,
+            ipc_compression: args
+                .ipc_compression
+                .to_owned()
+                .unwrap_or(defaults.ipc_compression),
+            float_precision: args.float_precision,
+            float_format: args
+                .float_format
+                .to_owned()
+                .unwrap_or(defaults.float_format),
+            column_style: args
+                .column_style
+                .to_owned()
+                .unwrap_or(defaults.column_style),
+            channel_names: args.channel_names.to_owned(),
+            include_geometry: args.include_geometry,
+            geometry_format: args
+                .geometry_format
+                .to_owned()
+                .unwrap_or(defaults.geometry_format),
+            geometry_max_points: args
+                .geometry_max_points
+                .unwrap_or(defaults.geometry_max_points),
+            provenance: args.provenance,
+            hash_inputs: args.hash_inputs,
+            per_image_output: args.per_image_output,
+            per_image_format: args
+                .per_image_format
+                .to_owned()
+                .unwrap_or(defaults.per_image_format),
+            resume: args.resume,
+            overwrite: args.overwrite,
+            dry_run: args.dry_run,
+            prefetch: args.prefetch.unwrap_or(defaults.prefetch),
+            io_threads: args.io_threads.unwrap_or(defaults.io_threads),
+            remote_cache: args.remote_cache.to_owned(),
+            threads: args.threads,
+        }
     }
+}
+
+/// Profile image and mask pairs as configured
+///
+/// Images that fail to profile are reported in `object_errors.tsv` when
+/// writing to an output directory and are counted in the returned summary.
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the run
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_cli::run_profile_mask;
+/// use thyme_config::ProfileMaskConfig;
+///
+/// let config = ProfileMaskConfig::new("images/", "output/")
+///     .masks("masks/")
+///     .mode("cfm");
+///
+/// let summary = run_profile_mask(&config).unwrap();
+/// println!("{} objects across {} images", summary.objects, summary.images);
+/// ```
+pub fn run_profile_mask(config: &ProfileMaskConfig) -> Result<ProfileSummary, ThymeError> {
+    config.validate()?;
+    ut::threads::install(config.threads, || run(config))
+}
 
-    let mode = args.mode.to_owned().unwrap_or("cmbfpx".to_string());
-    let pad = args.pad.unwrap_or(1);
-    let min_size = args.min_size.unwrap_or(1);
+/// Run the command on the thread pool selected by `run_profile_mask`
+fn run(config: &ProfileMaskConfig) -> Result<ProfileSummary, ThymeError> {
+    let mode = config.mode.to_owned();
+    let pad = config.pad;
 
-    if mode
-        .chars()
-        .any(|c| !matches!(c, 'c' | 'm' | 'b' | 'f' | 'p' | 'x'))
-    {
-        eprintln!(
-            "[thyme::profile::mask] Invalid mode. Argument mode must only contain one or more of: c, f, b, m, p, x."
-        );
-        std::process::exit(1);
-    }
+    let border_policy = match config.drop_borders {
+        true => BorderPolicy::Drop,
+        false => BorderPolicy::parse(&config.border_policy).map_err(super::invalid)?,
+    };
 
-    if min_size < 1 {
-        eprintln!("[thyme::profile::mask] ERROR: min_size cannot be less than 1.0.");
-        std::process::exit(1);
-    }
+    let nonfinite_policy =
+        NonFinitePolicy::parse(&config.nonfinite_policy).map_err(super::invalid)?;
 
-    let image_path = args.images.to_owned().unwrap();
-    let masks_path = args.masks.to_owned().unwrap_or(image_path.clone());
+    let prefetch = Prefetch::new(Some(config.prefetch), Some(config.io_threads))?;
 
-    if image_path == masks_path && args.image_substring == args.mask_substring {
-        eprintln!(
-            "[thyme::profile::mask] ERROR: If images and masks are located in same path, different image and mask substrings must be provided."
-        );
-        std::process::exit(1);
+    let filter = ObjectFilter {
+        border_policy,
+        min_size: config.min_size,
+        min_area: config.min_area,
+        max_area: config.max_area,
+        skip_invalid_polygons: false,
+    };
+
+    filter.validate().map_err(super::invalid)?;
+
+    let budget = ObjectBudget {
+        max_points: config.max_points_per_object,
+        oversized: OversizedPolicy::parse(&config.oversized_policy).map_err(super::invalid)?,
+        max_seconds: config.max_seconds_per_object,
+    };
+
+    let gate = filter::ImageGate::new(config.skip_image_if.as_deref())?;
+
+    let probability = ProbabilityMask::new(config.prob_threshold, config.prob_min_distance);
+
+    if let Some(probability) = &probability {
+        probability.validate().map_err(super::invalid)?;
     }
 
-    let image_files = ut::path::collect_file_paths(
-        &image_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.image_substring.to_owned(),
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    let zstack = im::ZStackMode::parse(&config.zstack_mode)?;
+    let mask_format = im::MaskFormat::parse(&config.mask_format)?;
+    let background_style = im::BackgroundStyle::parse(&config.background_style)?;
+
+    let texture_stride = config.texture_stride;
+    let texture_scales = table::texture_scales(config.texture_scales.as_deref())?;
+
+    let reduce = MeasureDevice::parse(&config.measure_device)?.reducer(DEVICE_MIN_SUBPIXELS)?;
+
+    let smoothing = config
+        .smooth_contours
+        .as_deref()
+        .map(im::ContourSmoothing::parse)
+        .transpose()?;
+
+    // Strides are only recorded when texture descriptors are computed
+    let stride_column = config.verbose && mode.contains(['c', 'f', 'b']);
+
+    let thumbnails = Thumbnails::new(config.thumbnails.as_deref(), Some(config.thumbnail_size))?;
+    let preview = Preview::new(config.preview, config.preview_rescale)?;
+
+    let ipc_compression = config.ipc_compression.to_owned();
+    let float_format = io::FloatFormat::new(config.float_precision, &config.float_format)?;
 
-    let mask_files = ut::path::collect_file_paths(
-        &masks_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.mask_substring.to_owned(),
+    // Outlines are kept with the descriptors if written to a polygon column
+    let geometry = config
+        .include_geometry
+        .then(|| io::GeometryFormat::parse(&config.geometry_format))
+        .transpose()?
+        .map(|format| (format, config.geometry_max_points));
+
+    let image_path = config.images.to_owned();
+    let masks_path = config.masks_path().to_owned();
+
+    let remote = RemoteInputs::new(config.remote_cache.as_deref());
+
+    let image_files = ut::path::FileSelector::new(
+        config.image_substring.as_deref(),
+        config.image_glob.as_deref(),
+        config.image_regex.as_deref(),
     )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    .and_then(|selector| {
+        remote.select_file_paths(
+            &image_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
+
+    let mask_files = ut::path::FileSelector::new(
+        config.mask_substring.as_deref(),
+        config.mask_glob.as_deref(),
+        config.mask_regex.as_deref(),
+    )
+    .and_then(|selector| {
+        remote.select_file_paths(
+            &masks_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
 
     if image_files.is_empty() {
-        eprintln!(
-            "[thyme::profile::mask] ERROR: No image files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No image files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
     if mask_files.is_empty() {
-        eprintln!(
-            "[thyme::profile::mask] ERROR: No mask files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No mask files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
     let mut pairs = ut::path::collect_file_pairs(
         &image_files,
         &mask_files,
-        args.image_substring.to_owned(),
-        args.mask_substring.to_owned(),
+        config.image_substring.to_owned(),
+        config.mask_substring.to_owned(),
     );
 
     pairs.sort_unstable();
 
+    // Channels of the first image are checked before profiling
+    if let (Some(_), Some((_, image, _))) = (&config.features_channels, pairs.first()) {
+        remote.fetch(image)?;
+    }
+
+    let feature_channels = table::feature_channels(
+        config.features_channels.as_deref(),
+        pairs.first().map(|(_, image, _)| image.as_path()),
+    )?;
+
+    let channel_pairs = config
+        .channel_pairs
+        .as_deref()
+        .map(im::ChannelPairs::parse)
+        .transpose()?;
+
     ut::track::progress_log(
         &format!(
             "Detected {} image and mask pairs.",
             ut::track::thousands_format(pairs.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    let mut output = PathBuf::from(args.output.to_owned().unwrap());
-
-    let extension = output
-        .extension()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_lowercase());
+    let secondary_masks: Option<HashMap<String, PathBuf>> = config
+        .secondary_masks
+        .as_ref()
+        .map(|secondary_path| {
+            let secondary_files = ut::path::collect_file_paths(
+                secondary_path,
+                constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+                config.secondary_substring.to_owned(),
+            )?;
+
+            let secondary_masks: HashMap<String, PathBuf> = ut::path::collect_file_pairs(
+                &image_files,
+                &secondary_files,
+                config.image_substring.to_owned(),
+                config.secondary_substring.to_owned(),
+            )
+            .into_iter()
+            .map(|(id, _, secondary)| (id, secondary))
+            .collect();
+
+            ut::track::progress_log(
+                &format!(
+                    "Detected {} secondary masks.",
+                    ut::track::thousands_format(secondary_masks.len())
+                ),
+                config.verbose,
+            );
 
-    if let Some(ext) = extension {
-        if !["csv", "txt", "tsv", "pq"].iter().any(|e| e == &ext) {
-            eprintln!(
-                "[thyme::profile::mask] ERROR: Invalid file extension. Must end with one of .csv, .txt, .tsv, .pq."
+            Ok::<_, ThymeError>(secondary_masks)
+        })
+        .transpose()?;
+
+    let class_masks: Option<ClassMasks> = config
+        .class_mask
+        .as_ref()
+        .map(|class_path| {
+            let class_masks = ClassMasks::collect(
+                class_path,
+                config.class_substring.to_owned(),
+                &image_files,
+                config.image_substring.to_owned(),
+            )?;
+
+            ut::track::progress_log(
+                &format!(
+                    "Detected {} class masks.",
+                    ut::track::thousands_format(class_masks.len())
+                ),
+                config.verbose,
             );
-            std::process::exit(1);
-        }
 
-        if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            Ok::<_, ThymeError>(class_masks)
+        })
+        .transpose()?;
+
+    let metadata = config
+        .metadata_table
+        .as_ref()
+        .map(|path| {
+            let on = config.metadata_join.as_deref().unwrap();
+            let metadata = io::read_table(path)?;
+
+            // Resolve join keys before processing so that invalid patterns or unmatched
+            // images are reported without waiting for all the objects to be computed
+            let images: Vec<String> = pairs.iter().map(|(_, image, _)| stem(image)).collect();
+
+            let mut df = DataFrame::new(vec![Column::new("image".into(), &images)]).unwrap();
+
+            let missing = io::join_metadata(
+                &mut df,
+                "image",
+                &metadata,
+                on,
+                config.well_from_filename.as_deref(),
+            )?;
+
+            if missing > 0 && config.strict_join {
+                return Err(ThymeError::OtherError(format!(
+                    "{} images have no matching row in the metadata table",
+                    missing
+                )));
+            }
+
+            if missing > 0 {
                 eprintln!(
-                    "[thyme::profile::mask] ERROR: Invalid file path. Parent directory of output file path does not exist."
+                    "[thyme::profile::mask] WARNING: {} images have no matching row in the metadata table. Metadata columns will be null for these images.",
+                    missing
                 );
-                std::process::exit(1);
             }
-        }
-    } else {
-        output = ut::path::create_directory(&output).unwrap_or_else(|_| {
-            eprintln!("[thyme::profile::mask] ERROR: Could not create directory.");
-            std::process::exit(1);
+
+            Ok(metadata)
+        })
+        .transpose()?;
+
+    let column_style = catalog::ColumnStyle::parse(&config.column_style)?;
+    let channel_names = config
+        .channel_names
+        .as_deref()
+        .map(catalog::parse_channel_names)
+        .transpose()?
+        .unwrap_or_default();
+
+    let profile_columns = |channels: usize| catalog::ProfileColumns {
+        segments: catalog::Segments::Mask,
+        mode: mode.clone(),
+        membrane: config.membrane_width.is_some(),
+        extended_intensity: config.extended_intensity,
+        centroid_channels: Some(channels).filter(|_| config.centroid_displacement),
+        secondary: config.secondary_masks.is_some(),
+        texture_stride: stride_column,
+        labels: false,
+        touches_border: false,
+        thumbnails: thumbnails.is_some(),
+        classes: config.class_mask.is_some(),
+        fast_complete: false,
+        feature_channels: feature_channels.clone(),
+        texture_scales: texture_scales.clone(),
+        channel_pairs: channel_pairs
+            .as_ref()
+            .map(|pairs| pairs.resolve(channels))
+            .unwrap_or_default(),
+        preview: preview.is_some(),
+        column_style,
+        channel_names: channel_names.clone(),
+    };
+
+    let descriptor_columns = |channels: usize| profile_columns(channels).descriptor_names();
+
+    if config.dry_run {
+        let images = dryrun::scan(&pairs, pad, |_, _, mask, _| {
+            let masks = match &probability {
+                Some(probability) => vec![probability.open(mask, "profile::mask")?],
+                None => im::ThymeMask::open_zstack_format(mask, zstack, mask_format)?
+                    .into_iter()
+                    .map(|(mask, _)| mask)
+                    .collect(),
+            };
+
+            dryrun::mask_boxes(masks)
         });
+
+        return super::dry_run(
+            "profile::mask",
+            &config.output,
+            Some(config.per_image_format.as_str()).filter(|_| config.per_image_output),
+            config.float_precision,
+            images,
+            |channels| profile_columns(channels).columns().len(),
+        );
     }
 
-    let pb = ut::track::progress_bar(pairs.len(), "Profiling", args.verbose);
+    let output = super::output_path(
+        &config.output,
+        OutputMode::new(config.overwrite, config.resume)?,
+        &config.inputs(),
+        "profile::mask",
+    )?;
+
+    let per_image = config
+        .per_image_output
+        .then(|| {
+            if !output.is_dir() {
+                return Err(ThymeError::OtherError(
+                    "per_image_output requires an output directory".to_string(),
+                ));
+            }
 
-    let objects: Mutex<usize> = Mutex::new(0);
-    let success: Mutex<Vec<String>> = Mutex::new(vec![]);
-    let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
+            PerImageOutput::new(
+                &output,
+                "descriptors",
+                &config.per_image_format,
+                &ipc_compression,
+                float_format,
+                config.resume,
+                metadata.clone().map(|table| MetadataJoin {
+                    table,
+                    on: config.metadata_join.to_owned().unwrap(),
+                    well_from_filename: config.well_from_filename.to_owned(),
+                }),
+            )
+        })
+        .transpose()?;
+
+    if let Some(per_image) = &per_image {
+        let n = pairs.len();
+        pairs.retain(|(_, image, _)| !per_image.skip(&stem(image)));
+
+        ut::track::progress_log(
+            &format!(
+                "Skipping {} images with existing per-image tables.",
+                ut::track::thousands_format(n - pairs.len())
+            ),
+            config.verbose && config.resume,
+        );
+    }
 
-    let name: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
-    let item: Mutex<Vec<u32>> = Mutex::new(Vec::with_capacity(pairs.len()));
-    let data: Mutex<Vec<Vec<f32>>> = Mutex::new(Vec::with_capacity(300 * pairs.len()));
+    let options = ProfileOptions {
+        zstack,
+        mask_format,
+        pad,
+        filter,
+        budget,
+        gate: gate.as_ref(),
+        mode: &mode,
+        smoothing,
+        background_style,
+        membrane_width: config.membrane_width,
+        centroid_displacement: config.centroid_displacement,
+        channel_pairs: channel_pairs.as_ref(),
+        strict_masks: config.strict_masks,
+        probability: probability.as_ref(),
+        extended_intensity: config.extended_intensity,
+        approx_percentiles_over: config.approx_percentiles_over,
+        feature_channels: feature_channels.as_ref(),
+        texture_stride,
+        texture_auto_stride: config.texture_auto_stride,
+        reduce: reduce.as_deref(),
+        texture_scales: &texture_scales,
+        stride_column,
+        thumbnails: thumbnails.as_ref(),
+        preview: preview.as_ref(),
+        geometry,
+    };
+
+    let progress = Progress::new(
+        pairs.len(),
+        "Profiling",
+        config.verbose,
+        config.tui,
+        &config.log_format,
+    );
+    let status = progress.status();
+
+    // Results are collected in input order so that the output table does not
+    // depend on the number of threads used for profiling
+    let runs: Vec<Result<Vec<SliceProfile>, ThymeError>> = prefetch.run(
+        &pairs,
+        progress,
+        |(_, image, mask)| {
+            remote.fetch(image)?;
+            remote.fetch(mask)?;
+            open_images(image, zstack)
+        },
+        |(id, image, mask), images| {
+            let _staged = remote.staged(&[image, mask]);
+            status.begin(id);
+
+            let secondary = match &secondary_masks {
+                Some(secondary_masks) => match secondary_masks.get(id) {
+                    Some(secondary) => Some(secondary.as_path()),
+                    None => {
+                        return Err(ThymeError::OtherError(
+                            "No matching secondary mask".to_string(),
+                        ));
+                    }
+                },
+                None => None,
+            };
+
+            let classes = class_masks
+                .as_ref()
+                .map(|class_masks| class_masks.path(id))
+                .transpose()?;
+
+            let mut slices = profile(images, mask, secondary, classes, &options, &stem(image))?;
+
+            for (descriptors, _, _, _, skipped) in slices.iter_mut() {
+                // Objects measured over their time budget are missing trailing columns
+                if skipped.is_none() && !descriptors.ids.is_empty() {
+                    descriptors.pad_columns(descriptor_columns(descriptors.channels).len());
+                }
+
+                descriptors.sanitize(nonfinite_policy);
+
+                if let Some(preview) = &preview {
+                    let exponents = profile_columns(descriptors.channels).descriptor_exponents();
+                    preview.finish(descriptors, &exponents);
+                }
+            }
 
-    (0..pairs.len())
-        .into_par_iter()
-        .tqdm_with_bar(pb)
-        .for_each(|idx| {
-            let (id, image, mask) = &pairs[idx];
-            let run = profile(image, mask, pad, args.drop_borders, min_size, &mode);
+            // Tables are written as soon as an image is profiled so that only
+            // object ids are kept in memory until the counts are written
+            if let Some(per_image) = &per_image {
+                for (k, (descriptors, _, _, _, skipped)) in slices.iter_mut().enumerate() {
+                    if skipped.is_some() {
+                        continue;
+                    }
+
+                    let columns = descriptor_columns(descriptors.channels);
+                    let image = zstack.slice_name(&stem(image), k);
+                    table::write_image(per_image, &image, descriptors, &columns)?;
+                }
+            }
 
-            if let Ok((ids, descriptors)) = run {
-                let n = ids.len();
+            status.add_objects(
+                slices
+                    .iter()
+                    .filter(|(_, _, _, _, skipped)| skipped.is_none())
+                    .map(|(descriptors, _, _, _, _)| descriptors.ids.len())
+                    .sum(),
+            );
 
-                success.lock().unwrap().push(format!("{}\t{}", id, n));
+            Ok(slices)
+        },
+    );
 
-                let image = image.file_stem().unwrap().to_string_lossy().to_string();
+    if config.verbose {
+        println!();
+    }
 
-                name.lock().unwrap().extend((0..n).map(|_| image.clone()));
-                item.lock().unwrap().extend(ids);
-                data.lock().unwrap().extend(descriptors);
-                *objects.lock().unwrap() += n;
-            } else {
-                failure
-                    .lock()
-                    .unwrap()
-                    .push(format!("{}\t{}", id, run.unwrap_err()));
+    let mut objects: usize = 0;
+    let mut success: Vec<String> = vec![];
+    let mut failure: Vec<String> = Vec::with_capacity(pairs.len());
+    let mut failed_objects: Vec<String> = vec![];
+    let mut report = NonFiniteReport::default();
+
+    let mut name: Vec<String> = vec![];
+    let mut data: Vec<Descriptors> = Vec::with_capacity(pairs.len());
+    let mut channels: Option<usize> = None;
+    let mut summaries: Vec<SummaryRow> = Vec::with_capacity(pairs.len());
+    let mut skipped: Vec<String> = vec![];
+
+    for ((id, image, _), run) in pairs.iter().zip(runs) {
+        match run {
+            Ok(slices) => {
+                let stem = stem(image);
+
+                for (k, (descriptors, dropped, summary, image_summary, reason)) in
+                    slices.into_iter().enumerate()
+                {
+                    let id = zstack.slice_name(id, k);
+
+                    if let Some(reason) = reason {
+                        ut::track::progress_log(
+                            &format!("{}: skipped ({}).", id, reason),
+                            config.verbose,
+                        );
+
+                        skipped.push(filter::skipped_images_row(&id, &reason, &image_summary));
+                        continue;
+                    }
+
+                    if (config.centroid_displacement || channel_pairs.is_some())
+                        && per_image.is_none()
+                    {
+                        if let Err(err) = table::check_channels(&mut channels, &descriptors) {
+                            failure.push(format!("{}\t{}", id, err));
+                            continue;
+                        }
+                    }
+
+                    let n = descriptors.ids.len();
+
+                    log_mask_summary(&id, &summary, config.verbose);
+
+                    if let Some(area_change) = descriptors.area_change {
+                        ut::track::progress_log(
+                            &format!(
+                                "{}: contour smoothing changed polygon areas by {:+.2}% on average.",
+                                id,
+                                100.0 * area_change
+                            ),
+                            config.verbose,
+                        );
+                    }
+                    failed_objects.extend(descriptors.failed.rows(&id));
+
+                    report.add(
+                        &descriptor_columns(descriptors.channels),
+                        &descriptors.nonfinite,
+                    );
+                    success.push(filter::object_counts_row(&id, n, &dropped));
+
+                    let image = zstack.slice_name(&stem, k);
+
+                    summaries.push(SummaryRow {
+                        image: image.clone(),
+                        objects: n,
+                        summary: image_summary,
+                    });
+
+                    if per_image.is_none() {
+                        name.extend((0..n).map(|_| image.clone()));
+                        data.push(descriptors);
+                    }
+
+                    objects += n;
+                }
             }
-        });
-
-    let objects = objects.into_inner().unwrap();
-    let success = success.into_inner().unwrap();
-    let failure = failure.into_inner().unwrap();
-
-    let name = name.into_inner().unwrap();
-    let item = item.into_inner().unwrap();
-    let data = data.into_inner().unwrap();
+            Err(err) => {
+                if let ThymeError::MaskDepthError(_) = err {
+                    eprintln!("[thyme::profile::mask] ERROR: {} {}", id, err);
+                }
 
-    if args.verbose {
-        println!();
+                failure.push(format!("{}\t{}", id, err))
+            }
+        }
     }
 
     ut::track::progress_log(
@@ -250,34 +1206,24 @@ pub fn profile_image_mask(args: &ProfileMaskArgs) {
             ut::track::thousands_format(objects),
             ut::track::thousands_format(success.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    if !success.is_empty() {
-        let columns = descriptor_columns(&mode);
-
-        let mut df = DataFrame::new(vec![
-            Column::new("image".into(), &name),
-            Column::new("object".into(), &item),
-        ])
-        .unwrap();
-
-        // Note that this requires generating two copies of the computed descriptors
-        // which is definitely not ideal. We probaby want to redesign the computation
-        // so that column-major data is generated directly or we just use a flat buffer
-        // and then just handle the saving with indexing. Also look into the polars API.
-        let mut column_data: Vec<Vec<f32>> = vec![Vec::with_capacity(data.len()); data[0].len()];
-
-        for row in &data {
-            for (idx, &descriptor) in row.iter().enumerate() {
-                column_data[idx].push(descriptor);
-            }
-        }
-
-        for (column, descriptor) in columns.iter().zip(column_data) {
-            df.with_column(Column::new(column.into(), descriptor))
-                .unwrap();
+    let mut descriptors = None;
+
+    if let Some(per_image) = &per_image {
+        if config.provenance {
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                None,
+                config.hash_inputs,
+                &[per_image.directory()],
+            )?;
         }
+    } else if !success.is_empty() {
+        let columns = descriptor_columns(channels.unwrap_or_default());
+        let mut df = table::dataframe(&name, data, &columns);
 
         let descriptors_path = if output.is_dir() {
             output.join("descriptors.csv")
@@ -285,35 +1231,266 @@ pub fn profile_image_mask(args: &ProfileMaskArgs) {
             output.clone()
         };
 
-        io::write_table(&mut df, descriptors_path).unwrap_or_else(|_| {
-            eprintln!("[thyme::profile::mask] ERROR: Failed to write descriptors table.");
-            std::process::exit(1);
-        });
+        if let Some(metadata) = &metadata {
+            io::join_metadata(
+                &mut df,
+                "image",
+                metadata,
+                config.metadata_join.as_deref().unwrap(),
+                config.well_from_filename.as_deref(),
+            )?;
+        }
+
+        io::write_table_with_float_format(
+            &mut df,
+            &descriptors_path,
+            &ipc_compression,
+            &float_format,
+        )
+        .map_err(|err| {
+            ThymeError::OtherError(format!("Failed to write descriptors table: {}", err))
+        })?;
+
+        if config.provenance {
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                None,
+                config.hash_inputs,
+                &[&descriptors_path],
+            )?;
+        }
+
+        descriptors = Some(descriptors_path);
+    }
+
+    if !report.is_empty() {
+        eprintln!(
+            "[thyme::profile::mask] WARNING: {} objects have non-finite values in {} descriptor columns ({}).",
+            ut::track::thousands_format(report.objects()),
+            report.len(),
+            nonfinite_policy.describe()
+        );
+    }
+
+    if !failed_objects.is_empty() {
+        eprintln!(
+            "[thyme::profile::mask] WARNING: {} objects failed to profile and have no descriptors (see failed_objects.tsv).",
+            ut::track::thousands_format(failed_objects.len())
+        );
     }
 
     if output.is_dir() {
-        if !success.is_empty() {
-            std::fs::write(output.join("object_counts.tsv"), success.join("\n")).unwrap();
-        }
+        super::write_reports(
+            &output,
+            &report,
+            &success,
+            &summaries,
+            &skipped,
+            &failure,
+            &failed_objects,
+        )?;
+    }
 
-        if !failure.is_empty() {
-            std::fs::write(output.join("object_errors.tsv"), failure.join("\n")).unwrap();
-        }
+    crate::output::finish_run(&output)?;
+
+    Ok(ProfileSummary {
+        images: success.len(),
+        failures: failure.len(),
+        skipped: skipped.len(),
+        objects,
+        failed_objects: failed_objects.len(),
+        output,
+        descriptors,
+        dry_run: None,
+    })
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().to_string()
+}
+
+/// Descriptors, dropped object counts, mask summary, image summary, and the
+/// reason the image was skipped by an image gate for a single image
+type SliceProfile = (
+    Descriptors,
+    DropCounts,
+    im::MaskSummary,
+    im::ImageSummary,
+    Option<String>,
+);
+
+/// Open an image, or each slice of an image stack if slices are profiled separately
+fn open_images(
+    image_path: &Path,
+    zstack: im::ZStackMode,
+) -> Result<Vec<im::ThymeImage>, ThymeError> {
+    match zstack {
+        im::ZStackMode::PerSlice => im::ThymeImage::open_stack(image_path),
+        _ => Ok(vec![im::ThymeImage::open(image_path)?]),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Options shared by every image and mask pair profiled by a run
+#[derive(Clone, Copy, Default)]
+struct ProfileOptions<'a> {
+    /// How image and mask stacks are profiled
+    zstack: im::ZStackMode,
+    /// Format used to decode masks
+    mask_format: im::MaskFormat,
+    /// Padding added to each side of the bounding box of an object
+    pad: u32,
+    /// Filters applied to each object before it is measured
+    filter: ObjectFilter,
+    /// Per-object vertex and time budget
+    budget: ObjectBudget,
+    /// Image-level gate evaluated before any object is extracted
+    gate: Option<&'a filter::ImageGate>,
+    /// Descriptor families to compute
+    mode: &'a str,
+    /// Smoothing applied to object outlines
+    smoothing: Option<im::ContourSmoothing>,
+    /// Region used to measure background intensity
+    background_style: im::BackgroundStyle,
+    /// Width of the boundary band of membrane descriptors (pixels)
+    membrane_width: Option<u32>,
+    /// Compute intensity-weighted centroid displacements
+    centroid_displacement: bool,
+    /// Channel pairs measured as intensity ratios
+    channel_pairs: Option<&'a im::ChannelPairs>,
+    /// Fail images with masks that may be bit-depth truncated
+    strict_masks: bool,
+    /// Thresholding applied to probability maps used as masks
+    probability: Option<&'a ProbabilityMask>,
+    /// If true, compute the extended intensity descriptors
+    extended_intensity: bool,
+    /// Positive subpixels of a channel above which percentiles are selected without a full sort
+    approx_percentiles_over: Option<usize>,
+    /// Channels measured by the intensity descriptors
+    feature_channels: Option<&'a im::FeatureChannels>,
+    /// Stride of texture co-occurrence anchors
+    texture_stride: usize,
+    /// Object size above which the texture stride is escalated (pixels)
+    texture_auto_stride: Option<usize>,
+    /// Reduction applied to channels before measuring intensity
+    reduce: Option<&'a dyn Reduce>,
+    /// Scales at which texture descriptors are measured
+    texture_scales: &'a [u32],
+    /// If true, record the texture stride used for each object
+    stride_column: bool,
+    /// Object thumbnails written alongside the descriptors
+    thumbnails: Option<&'a Thumbnails>,
+    /// Downsampling applied to images and masks before profiling
+    preview: Option<&'a Preview>,
+    /// Encoding and maximum number of vertices of the polygon column
+    geometry: Option<(io::GeometryFormat, usize)>,
+}
+
+/// Profile an image and mask pair, returning one result per z-slice
 fn profile(
-    image_path: &Path,
+    images: Vec<im::ThymeImage>,
     mask_path: &Path,
-    pad: u32,
-    drop_borders: bool,
-    min_size: u32,
-    mode: &str,
-) -> Result<(Vec<u32>, Vec<Vec<f32>>), ThymeError> {
-    let image = im::ThymeImage::open(image_path)?;
+    secondary_path: Option<&Path>,
+    classes_path: Option<&Path>,
+    options: &ProfileOptions,
+    name: &str,
+) -> Result<Vec<SliceProfile>, ThymeError> {
+    let ProfileOptions {
+        zstack,
+        mask_format,
+        probability,
+        ..
+    } = *options;
+
+    // Probability maps are labeled with 32-bit labels so they are never truncated
+    let masks = match probability {
+        Some(probability) => {
+            let mask = probability.open(mask_path, "profile::mask")?;
+            let summary = im::MaskSummary::new(mask.as_raw(), u32::MAX);
+            vec![(mask, summary)]
+        }
+        None => im::ThymeMask::open_zstack_format(mask_path, zstack, mask_format)?,
+    };
+
+    if images.len() != masks.len() {
+        return Err(ThymeError::OtherError(format!(
+            "Image stack depth ({}) does not match mask stack depth ({})",
+            images.len(),
+            masks.len()
+        )));
+    }
+
+    images
+        .iter()
+        .zip(masks)
+        .enumerate()
+        .map(|(k, (image, (mask, summary)))| {
+            profile_slice(
+                image,
+                mask,
+                summary,
+                secondary_path,
+                classes_path,
+                options,
+                &zstack.slice_name(name, k),
+            )
+        })
+        .collect()
+}
 
-    let mut mask = im::ThymeMask::open(mask_path)?;
+/// Profile a single image and mask
+fn profile_slice(
+    image: &im::ThymeImage,
+    mut mask: im::ThymeMask,
+    summary: im::MaskSummary,
+    secondary_path: Option<&Path>,
+    classes_path: Option<&Path>,
+    options: &ProfileOptions,
+    name: &str,
+) -> Result<SliceProfile, ThymeError> {
+    let ProfileOptions {
+        zstack,
+        mask_format,
+        pad,
+        filter,
+        budget,
+        gate,
+        mode,
+        smoothing,
+        background_style,
+        membrane_width,
+        centroid_displacement,
+        channel_pairs,
+        strict_masks,
+        extended_intensity,
+        approx_percentiles_over,
+        feature_channels,
+        texture_stride,
+        texture_auto_stride,
+        reduce,
+        texture_scales,
+        stride_column,
+        thumbnails,
+        preview,
+        geometry,
+        ..
+    } = *options;
+
+    if strict_masks {
+        summary.check_bit_depth()?;
+    }
+
+    if let Some(feature_channels) = feature_channels {
+        feature_channels.check(image.channels() as usize)?;
+    }
+
+    let pairs = match channel_pairs {
+        Some(channel_pairs) => {
+            channel_pairs.check(image.channels() as usize)?;
+            channel_pairs.resolve(image.channels() as usize)
+        }
+        None => vec![],
+    };
 
     if image.width() != mask.width() || image.height() != mask.height() {
         return Err(ThymeError::OtherError(
@@ -321,146 +1498,424 @@ fn profile(
         ));
     }
 
-    let (labels, mut polygons) = mask.polygons()?;
-    let bounding_boxes = polygons.to_bounding_boxes()?;
+    // Previews measure objects on the downsampled image and mask
+    let resized;
+    let image = match preview {
+        Some(preview) => {
+            resized = preview.image(image)?;
+            mask = preview.mask(&mask)?;
+            &resized
+        }
+        None => image,
+    };
+
+    // Background statistics are computed from every labeled pixel, including
+    // the pixels of objects that are filtered out below
+    let image_summary = im::ImageSummary::new(image, mask.as_raw())?;
+
+    // Gated images are skipped before any object is extracted from the mask
+    if let Some(reason) = gate.and_then(|gate| gate.check(&image_summary)) {
+        let mut descriptors = Descriptors::default();
+        descriptors.channels = image.channels() as usize;
 
-    let mut polygon_descriptors = Vec::new();
-    if mode.contains("p") {
-        polygon_descriptors = polygons.descriptors();
+        let dropped = DropCounts::default();
+        return Ok((descriptors, dropped, summary, image_summary, Some(reason)));
     }
 
+    let mut segments = pipeline::Segments::from_mask(mask)?;
+
+    // Oversized outlines are resampled or skipped before any descriptor is
+    // computed from their vertices
+    let mut oversized = HashMap::new();
+    if budget.max_points.is_some_and(|max_points| {
+        segments
+            .polygons
+            .as_points()
+            .iter()
+            .any(|points| points.len() > max_points)
+    }) {
+        let mut points = segments.polygons.as_points().clone();
+
+        for (idx, points) in points.iter_mut().enumerate() {
+            if let Some(outcome) = budget.check_points(points).transpose() {
+                oversized.insert(idx, outcome);
+            }
+        }
+
+        segments.polygons = im::Polygons::new(points)?;
+    }
+
+    // Outlines are smoothed after the bounding boxes are computed so that
+    // only the polygon descriptors differ from the unsmoothed mask
+    let mut area_change = None;
+    if let Some(smoothing) = smoothing.filter(|_| mode.contains("p")) {
+        let changes = segments.polygons.smooth(smoothing);
+        area_change =
+            (!changes.is_empty()).then(|| changes.iter().sum::<f32>() / changes.len() as f32);
+    }
+
+    // Outlines are kept before polygon descriptors re-order their vertices
+    let outlines: Option<Vec<Vec<[f32; 2]>>> = geometry.map(|(_, max_points)| {
+        segments
+            .polygons
+            .as_points()
+            .iter()
+            .map(|points| table::outline(points, max_points))
+            .collect()
+    });
+
+    let polygon_descriptors = match mode.contains("p") {
+        true => segments.polygons.descriptors(),
+        false => Vec::new(),
+    };
+
+    let mask = &segments.mask;
+    let labels = &segments.labels;
+
+    let secondary = secondary_path
+        .map(|path| secondary_objects(mask, labels, path, zstack, mask_format))
+        .transpose()?;
+
+    let classes = classes_path
+        .map(|path| classes::open(path, mask))
+        .transpose()?;
+
     let width = image.width();
     let height = image.height();
 
-    let pad_f32 = pad as f32;
-
-    let mut ids: Vec<u32> = Vec::with_capacity(bounding_boxes.len());
-    let mut results: Vec<Vec<f32>> = Vec::with_capacity(300 * bounding_boxes.len());
+    let mut descriptors = Descriptors::with_capacity(segments.len());
+    descriptors.channels = image.channels() as usize;
+    descriptors.area_change = area_change;
+    let mut border_flags: Vec<bool> = vec![];
+    let mut object_classes: Vec<(u32, f32)> = vec![];
+    let mut thumbnail_paths: Vec<Option<String>> = vec![];
+    let mut object_outlines: Vec<Vec<[f32; 2]>> = vec![];
+
+    let options = pipeline::ObjectOptions { pad, filter };
+
+    let mut objects = pipeline::iter_objects(image, &segments, &options)?;
+
+    for object in objects.by_ref() {
+        let object = match object {
+            Ok(object) => object,
+            Err(failed) => {
+                descriptors
+                    .failed
+                    .add(failed.index as u32, failed.xyxy, failed.error);
+                continue;
+            }
+        };
 
-    for (idx, [min_x, min_y, max_x, max_y]) in bounding_boxes.as_xyxy().iter().enumerate() {
-        let min_x = min_x - pad_f32;
-        let min_y = min_y - pad_f32;
-        let max_x = max_x + pad_f32;
-        let max_y = max_y + pad_f32;
+        let idx = object.index;
+        let xyxy = &object.xyxy;
+        let [min_x, min_y, w, h] = object.crop.as_xywh();
 
-        if drop_borders
-            && (min_x <= 0.0 || min_y <= 0.0 || max_x >= width as f32 || max_y >= height as f32)
-        {
-            continue;
-        }
+        let resampled = match oversized.remove(&idx) {
+            Some(Err(err)) => {
+                descriptors.failed.add(idx as u32, *xyxy, err);
+                continue;
+            }
+            Some(Ok(err)) => Some(err),
+            None => None,
+        };
 
-        let min_x = min_x.max(0.0) as u32;
-        let min_y = min_y.max(0.0) as u32;
-        let max_x = max_x.min(width as f32) as u32;
-        let max_y = max_y.min(height as f32) as u32;
+        // Each object is measured in isolation so that a failure is recorded
+        // and the remaining objects of the image are still profiled
+        let mut watchdog = budget.watchdog();
+        let measured = failures::catch(|stage| {
+            let mut result: Vec<f32> = Vec::with_capacity(100);
 
-        let w = max_x - min_x;
-        let h = max_y - min_y;
+            let stride =
+                texture::auto_stride((w * h) as usize, texture_stride, texture_auto_stride);
 
-        if w < min_size || h < min_size {
-            continue;
-        }
+            let binary = &object.binary;
+            let mask_object = object.mask();
 
-        let mut result: Vec<f32> = Vec::with_capacity(100);
+            *stage = Stage::Measure;
+            result.extend(table::location(xyxy, object.centroid));
 
-        let mask_object = mask.crop_binary(min_x, min_y, w, h, labels[idx])?;
-        let mask_object = mask_object.crop_view(0, 0, w, h);
+            if mode.contains("p") {
+                result.extend(polygon_descriptors[idx]);
+            }
 
-        if mode.contains("p") {
-            result.extend(polygon_descriptors[idx]);
-        }
+            let regions = im::Regions::from_mode(mode);
 
-        if mode.contains("c") {
-            result.extend(image.crop_view(min_x, min_y, w, h).descriptors());
-        }
+            // Descriptor families are skipped once the object exceeds its time
+            // budget, leaving the row truncated after the last measured family
 
-        if mode.contains("f") {
-            result.extend(
-                image
-                    .crop_masked(
+            // Ring backgrounds extend past the crop so they are measured separately
+            let ring = match background_style {
+                im::BackgroundStyle::Ring(radius) if regions.background && !watchdog.expired() => {
+                    Some(binary.background_ring(
                         min_x,
                         min_y,
-                        w,
-                        h,
+                        radius,
+                        mask.as_raw(),
+                        width,
+                        height,
+                    )?)
+                }
+                _ => None,
+            };
+
+            let crop_regions = im::Regions {
+                background: regions.background && ring.is_none(),
+                ..regions
+            };
+
+            if !regions.is_empty() && !watchdog.expired() {
+                let view = &object.view;
+
+                result.extend(match feature_channels {
+                    Some(feature_channels) => view.descriptors_regions_channels(
                         &mask_object,
-                        im::MaskingStyle::Foreground,
-                    )?
-                    .crop_view(0, 0, w, h)
-                    .descriptors(),
-            );
+                        crop_regions,
+                        feature_channels,
+                        extended_intensity,
+                        approx_percentiles_over,
+                        stride,
+                    ),
+                    None => match reduce {
+                        Some(reduce) => view.descriptors_regions_reduced(
+                            &mask_object,
+                            crop_regions,
+                            extended_intensity,
+                            approx_percentiles_over,
+                            stride,
+                            reduce,
+                        )?,
+                        None => view.descriptors_regions(
+                            &mask_object,
+                            crop_regions,
+                            extended_intensity,
+                            approx_percentiles_over,
+                            stride,
+                        ),
+                    },
+                });
+            }
+
+            if let Some(ring) = ring.as_ref().filter(|_| !watchdog.expired()) {
+                result.extend(table::ring_background(
+                    image,
+                    ring,
+                    feature_channels,
+                    extended_intensity,
+                    approx_percentiles_over,
+                    stride,
+                ));
+            }
+
+            if mode.contains("m") && !watchdog.expired() {
+                result.extend(&mask_object.moments());
+                result.extend(&mask_object.zernike());
+            }
+
+            if mode.contains("s") && !watchdog.expired() {
+                result.extend(object.view.spectrum(&mask_object));
+            }
+
+            if let Some(membrane_width) = membrane_width.filter(|_| !watchdog.expired()) {
+                result.extend(object.view.membrane(&mask_object, membrane_width));
+            }
+
+            if centroid_displacement && !watchdog.expired() {
+                result.extend(object.view.centroid_displacement(&mask_object));
+            }
+
+            if !pairs.is_empty() && !watchdog.expired() {
+                let view = object
+                    .view
+                    .masked(&mask_object, im::MaskingStyle::Foreground);
+
+                let start = result.len();
+                result.extend(view.channel_pairs(&pairs));
+                descriptors.ratio_columns = start..start + pairs.len();
+            }
+
+            if let Some(secondary) = secondary.as_ref().filter(|_| !watchdog.expired()) {
+                let (count, area) = secondary
+                    .objects
+                    .get(&object.label)
+                    .copied()
+                    .unwrap_or((0, 0));
+
+                result.push(count as f32);
+                result.push(area as f32);
+                result.push(area as f32 / object.area.max(1) as f32);
+
+                let cytoplasm_object =
+                    secondary
+                        .cytoplasm
+                        .crop_binary(min_x, min_y, w, h, object.label)?;
+                let cytoplasm_object = cytoplasm_object.crop_view(0, 0, w, h);
+
+                let view = object
+                    .view
+                    .masked(&cytoplasm_object, im::MaskingStyle::Foreground);
+
+                result.extend(view.intensity());
+            }
+
+            // Crops are only copied out of the image when they are resized
+            *stage = Stage::Crop;
+            let crop = match texture_scales.is_empty() && thumbnails.is_none() {
+                true => None,
+                false => Some(image.crop(min_x, min_y, w, h)?),
+            };
+
+            *stage = Stage::Measure;
+            if let Some(crop) = crop
+                .as_ref()
+                .filter(|_| !texture_scales.is_empty() && !watchdog.expired())
+            {
+                result.extend(crop.texture_scales(binary, texture_scales, stride)?);
+            }
+
+            if stride_column && !watchdog.expired() {
+                result.push(stride as f32);
+            }
+
+            let object_class = classes
+                .as_ref()
+                .map(|classes| {
+                    classes::object_class(mask, classes, [min_x, min_y, w, h], object.label)
+                })
+                .transpose()?;
+
+            let thumbnail = match (thumbnails, &crop) {
+                (Some(thumbnails), Some(crop)) => Some(thumbnails.write(crop, name, idx as u32)?),
+                _ => None,
+            };
+
+            Ok((result, object_class, thumbnail))
+        });
+
+        let (result, object_class, thumbnail) = match measured {
+            Ok(measured) => measured,
+            Err(err) => {
+                descriptors.failed.add(idx as u32, *xyxy, err);
+                continue;
+            }
+        };
+
+        if filter.border_policy == BorderPolicy::Flag {
+            border_flags.push(filter::touches_border(xyxy, width, height));
         }
 
-        if mode.contains("b") {
-            result.extend(
-                image
-                    .crop_masked(
-                        min_x,
-                        min_y,
-                        w,
-                        h,
-                        &mask_object,
-                        im::MaskingStyle::Background,
-                    )?
-                    .crop_view(0, 0, w, h)
-                    .descriptors(),
-            );
+        object_classes.extend(object_class);
+        thumbnail_paths.extend(thumbnail);
+
+        if let Some(outlines) = &outlines {
+            object_outlines.push(outlines[idx].clone());
         }
 
-        if mode.contains("m") {
-            result.extend(&mask_object.moments());
-            result.extend(&mask_object.zernike());
+        // Objects measured over budget are profiled and recorded as failed
+        for err in resampled.into_iter().chain(watchdog.error(result.len())) {
+            descriptors.failed.add(idx as u32, *xyxy, err);
         }
 
-        ids.push(idx as u32);
-        results.push(result)
+        descriptors.push(idx as u32, result);
+    }
+
+    let dropped = objects.dropped();
+
+    if filter.border_policy == BorderPolicy::Flag {
+        descriptors.touches_border = Some(border_flags);
+    }
+
+    if classes.is_some() {
+        descriptors.classes = Some(object_classes);
     }
 
-    Ok((ids, results))
+    if thumbnails.is_some() {
+        descriptors.thumbnails = Some(thumbnail_paths);
+    }
+
+    descriptors.geometry = geometry.map(|(format, _)| table::Geometry {
+        format,
+        polygons: object_outlines,
+    });
+
+    Ok((descriptors, dropped, summary, image_summary, None))
 }
 
-/// Generate the column names for the output descriptor table
+/// Secondary objects associated with each primary object in a mask
+struct SecondaryObjects {
+    /// Number and total area of secondary objects keyed by primary label
+    objects: HashMap<u32, (u32, u32)>,
+    /// Primary mask with all secondary pixels removed
+    cytoplasm: im::ThymeMask,
+}
+
+/// Open a secondary mask and associate its objects to primary objects
 ///
 /// # Arguments
 ///
-/// * `mode` - Profiling mode
-fn descriptor_columns(mode: &str) -> Vec<String> {
-    let mut names: Vec<String> = vec![];
-
-    if mode.contains("p") {
-        names.extend(
-            constant::FORM_DESCRIPTOR_NAMES
-                .into_iter()
-                .map(|s| s.to_string()),
-        );
+/// * `mask` - Labeled primary mask
+/// * `labels` - Labels of the primary objects
+/// * `secondary_path` - Path to the secondary mask
+/// * `zstack` - How (Z, H, W) secondary mask stacks are handled
+/// * `mask_format` - Layout of the secondary mask file
+fn secondary_objects(
+    mask: &im::ThymeMask,
+    labels: &[u32],
+    secondary_path: &Path,
+    zstack: im::ZStackMode,
+    mask_format: im::MaskFormat,
+) -> Result<SecondaryObjects, ThymeError> {
+    let (mut secondary, _) =
+        im::ThymeMask::open_zstack_format(secondary_path, zstack, mask_format)?.remove(0);
+
+    if secondary.width() != mask.width() || secondary.height() != mask.height() {
+        return Err(ThymeError::OtherError(
+            "Secondary mask and image are not the same size".to_string(),
+        ));
     }
 
-    let suffixes: Vec<&str> = constant::INTENSITY_DESCRIPTOR_NAMES
-        .into_iter()
-        .chain(constant::MOMENTS_DESCRIPTOR_NAMES)
-        .chain(constant::TEXTURE_DESCRIPTOR_NAMES)
-        .chain(constant::ZERNIKE_DESCRIPTOR_NAMES)
-        .collect();
+    let secondary_labels = secondary.label();
+    let secondary_areas = secondary.areas(&secondary_labels);
 
-    if mode.contains("c") {
-        names.extend(suffixes.iter().map(|s| "complete_".to_string() + s));
-    }
+    let mut objects: HashMap<u32, (u32, u32)> = HashMap::with_capacity(labels.len());
 
-    if mode.contains("f") {
-        names.extend(suffixes.iter().map(|s| "foreground_".to_string() + s));
+    for (primary, area) in mask
+        .associate(&secondary, &secondary_labels)?
+        .into_iter()
+        .zip(secondary_areas)
+    {
+        if primary != 0 {
+            let entry = objects.entry(primary).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += area;
+        }
     }
 
-    if mode.contains("b") {
-        names.extend(suffixes.iter().map(|s| "background_".to_string() + s));
+    Ok(SecondaryObjects {
+        objects,
+        cytoplasm: mask.subtract(&secondary)?,
+    })
+}
+
+/// Log the labels detected in a mask and warn if they may be truncated
+fn log_mask_summary(id: &str, summary: &im::MaskSummary, verbose: bool) {
+    if let Err(err) = summary.check_bit_depth() {
+        eprintln!("[thyme::profile::mask] WARNING: {} {}", id, err);
     }
 
-    if mode.contains("m") {
-        names.extend(
-            constant::MOMENTS_DESCRIPTOR_NAMES
-                .into_iter()
-                .chain(constant::ZERNIKE_DESCRIPTOR_NAMES)
-                .map(|s| "mask_".to_string() + s),
+    ut::track::progress_log(
+        &format!(
+            "{}: {} labels (max label {}).",
+            id,
+            ut::track::thousands_format(summary.labels),
+            summary.max_label
+        ),
+        verbose,
+    );
+
+    if summary.palette {
+        ut::track::progress_log(
+            &format!("{}: palette indices were used as labels.", id),
+            verbose,
         );
     }
-
-    names
 }
@@ -1,14 +1,34 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::path::{Path, PathBuf};
+
 use clap::{Args, Subcommand};
 
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::io;
+
+use crate::dryrun::{self, DryRun, DryRunImage};
+use crate::output::OutputMode;
+
 mod boxes;
+mod columns;
+mod crops;
 mod mask;
 mod polygons;
+mod preview;
+mod summary;
+mod table;
+mod thumbnail;
 
+pub use boxes::run_profile_boxes;
 use boxes::{ProfileBoxesArgs, profile_image_boxes};
-use mask::{ProfileMaskArgs, profile_image_mask};
+use columns::{ProfileColumnsArgs, profile_columns};
+use crops::{ProfileCropsArgs, profile_image_crops};
+pub use mask::run_profile_mask;
+pub(crate) use mask::{ProfileMaskArgs, profile_image_mask};
+pub use polygons::run_profile_polygons;
 use polygons::{ProfilePolygonsArgs, profile_image_polygons};
 
 #[derive(Debug, Args)]
@@ -24,6 +44,8 @@ pub struct ProfileArgs {
 #[derive(Debug, Subcommand)]
 enum ProfileCommands {
     Boxes(ProfileBoxesArgs),
+    Columns(ProfileColumnsArgs),
+    Crops(ProfileCropsArgs),
     Mask(ProfileMaskArgs),
     Polygons(ProfilePolygonsArgs),
 }
@@ -31,7 +53,1613 @@ enum ProfileCommands {
 pub fn profile(args: &ProfileArgs) {
     match args.command.as_ref().unwrap() {
         ProfileCommands::Boxes(boxes) => profile_image_boxes(boxes),
+        ProfileCommands::Columns(columns) => profile_columns(columns),
+        ProfileCommands::Crops(crops) => profile_image_crops(crops),
         ProfileCommands::Mask(masks) => profile_image_mask(masks),
         ProfileCommands::Polygons(polygons) => profile_image_polygons(polygons),
     }
 }
+
+/// Outcome of a profile run
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileSummary {
+    /// Number of images (or z-slices) that were profiled
+    pub images: usize,
+    /// Number of images (or z-slices) that failed to profile
+    pub failures: usize,
+    /// Number of images (or z-slices) skipped by `skip_image_if`
+    pub skipped: usize,
+    /// Number of objects that were profiled
+    pub objects: usize,
+    /// Number of objects that failed within images that were profiled
+    pub failed_objects: usize,
+    /// Output directory or file
+    pub output: PathBuf,
+    /// Descriptors table, if a single table was written
+    pub descriptors: Option<PathBuf>,
+    /// Dry run report, if the run was a dry run
+    pub dry_run: Option<PathBuf>,
+}
+
+/// Convert a message from argument parsing into an error
+fn invalid(message: String) -> ThymeError {
+    ThymeError::OtherError(message.trim_end_matches('.').to_string())
+}
+
+/// Resolve an output table or create an output directory
+///
+/// # Arguments
+///
+/// * `output` - Output directory or file
+/// * `mode` - Handling of the outputs of a previous run
+/// * `inputs` - Input files and directories of the run
+/// * `command` - Command used to prefix log lines (e.g. profile::mask)
+fn output_path(
+    output: &str,
+    mode: OutputMode,
+    inputs: &[&str],
+    command: &str,
+) -> Result<PathBuf, ThymeError> {
+    let output = PathBuf::from(output);
+
+    let extension = output
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+
+    if let Some(ext) = extension {
+        if !constant::SUPPORTED_TABLE_FORMATS.contains(&ext.as_str()) {
+            return Err(ThymeError::OtherError(format!(
+                "Invalid file extension {}. Must be one of: {:?}",
+                ext,
+                constant::SUPPORTED_TABLE_FORMATS
+            )));
+        }
+
+        if let Some(parent) = output.parent() {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
+                return Err(ThymeError::OtherError(
+                    "Invalid file path. Parent directory of output file path does not exist"
+                        .to_string(),
+                ));
+            }
+        }
+    }
+
+    crate::output::prepare_output(&output, mode, inputs, command)
+}
+
+/// Report the work of a profile run without profiling or creating the output
+///
+/// # Arguments
+///
+/// * `command` - Name of the command (e.g. profile::mask)
+/// * `output` - Output directory or file
+/// * `per_image_format` - Format of per-image output tables, if written
+/// * `float_precision` - Digits after the decimal point of floats in text tables
+/// * `images` - Images returned by `dryrun::scan`
+/// * `columns` - Number of table columns given the channels of the images
+fn dry_run<F: Fn(usize) -> usize>(
+    command: &str,
+    output: &str,
+    per_image_format: Option<&str>,
+    float_precision: Option<usize>,
+    images: Vec<DryRunImage>,
+    columns: F,
+) -> Result<ProfileSummary, ThymeError> {
+    let output = PathBuf::from(output);
+
+    let format = per_image_format
+        .map(|format| format.to_string())
+        .unwrap_or_else(|| dryrun::output_format(&output));
+
+    let channels = images
+        .iter()
+        .find(|image| image.error.is_none())
+        .map_or(1, |image| image.channels as usize);
+
+    let report =
+        DryRun::new(command, &output, images).table(columns(channels), &format, float_precision);
+    let path = report.finish()?;
+
+    Ok(ProfileSummary {
+        images: report.pairs - report.failures,
+        failures: report.failures,
+        skipped: 0,
+        objects: report.objects,
+        failed_objects: 0,
+        output,
+        descriptors: None,
+        dry_run: Some(path),
+    })
+}
+
+/// Write the run reports of a profile command to its output directory
+///
+/// # Arguments
+///
+/// * `output` - Output directory
+/// * `report` - Non-finite descriptors of the profiled objects
+/// * `success` - Object count rows of the profiled images
+/// * `summaries` - Summaries of the profiled images
+/// * `skipped` - Skipped image rows of the images skipped by an image gate
+/// * `failure` - Errors of the images that failed to profile
+/// * `failed_objects` - Failed object rows of the objects that failed to profile
+fn write_reports(
+    output: &Path,
+    report: &table::NonFiniteReport,
+    success: &[String],
+    summaries: &[summary::SummaryRow],
+    skipped: &[String],
+    failure: &[String],
+    failed_objects: &[String],
+) -> Result<(), ThymeError> {
+    let write = |name: &str, contents: String| io::write_bytes_atomic(output.join(name), contents);
+
+    if !report.is_empty() {
+        write("nonfinite_report.tsv", report.to_tsv())?;
+    }
+
+    if !success.is_empty() {
+        write("object_counts.tsv", crate::filter::object_counts(success))?;
+    }
+
+    if !summaries.is_empty() {
+        summary::write_image_summary(&output.join(summary::IMAGE_SUMMARY_FILE), summaries)?;
+    }
+
+    if !skipped.is_empty() {
+        write("skipped_images.tsv", crate::filter::skipped_images(skipped))?;
+    }
+
+    if !failure.is_empty() {
+        write("object_errors.tsv", failure.join("\n"))?;
+    }
+
+    if !failed_objects.is_empty() {
+        write(
+            "failed_objects.tsv",
+            crate::failures::failed_objects(failed_objects),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::{Path, PathBuf};
+
+    use clap::Parser;
+
+    use thyme_core::constant;
+    use thyme_core::im::{ThymeBuffer, ThymeImage};
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+
+    const IMAGES: usize = 2;
+    const OBJECTS: usize = 6;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: ProfileArgs,
+    }
+
+    fn run(args: &[&str]) {
+        profile(&Cli::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args);
+    }
+
+    fn synth_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: OBJECTS,
+            radius: [3.0, 6.0],
+            seed: 1,
+            ..Default::default()
+        }
+        .write(IMAGES, directory.join("data"))
+        .unwrap();
+
+        directory
+    }
+
+    /// Objects kept in each image according to object_counts.tsv
+    fn object_counts(output: &Path) -> Vec<usize> {
+        std::fs::read_to_string(output.join("object_counts.tsv"))
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(|row| row.split('\t').nth(1).unwrap().parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_profile_synthetic() {
+        let directory = synth_directory("thyme_test_profile_synthetic");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+            ("boxes", "--box-substring", "_boxes"),
+        ] {
+            let output = directory.join(command);
+
+            run(&[
+                command,
+                "-i",
+                data,
+                "-o",
+                output.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+            ]);
+
+            let table = std::fs::read_to_string(output.join("descriptors.csv")).unwrap();
+
+            assert_eq!(table.lines().count(), 1 + IMAGES * OBJECTS, "{}", command);
+            assert_eq!(object_counts(&output), [OBJECTS; IMAGES], "{}", command);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_dry_run() {
+        let directory = synth_directory("thyme_test_profile_dry_run");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+            ("boxes", "--box-substring", "_boxes"),
+        ] {
+            let output = directory.join(command);
+            let output = output.to_str().unwrap();
+
+            let args = [
+                command,
+                "-i",
+                data,
+                "-o",
+                output,
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+            ];
+
+            run(&[args.as_slice(), &["--dry-run"]].concat());
+
+            // Only the report is written, next to the output
+            assert!(!Path::new(output).exists(), "{}", command);
+
+            let report = std::fs::read_to_string(directory.join(dryrun::DRY_RUN_FILE)).unwrap();
+            let report: DryRun = serde_json::from_str(&report).unwrap();
+
+            assert_eq!(report.command, format!("profile::{}", command));
+            assert_eq!(report.pairs, IMAGES, "{}", command);
+            assert_eq!(report.objects, IMAGES * OBJECTS, "{}", command);
+            assert_eq!(report.failures, 0, "{}", command);
+            assert!(report.output_writable);
+            assert!(report.estimated_bytes > 0);
+            assert!(report.images.iter().all(|image| image.width == 64));
+
+            // Estimated table width matches the table written by the run
+            run(&args);
+
+            let table = std::fs::read_to_string(Path::new(output).join("descriptors.csv")).unwrap();
+            let columns = table.lines().next().unwrap().split(',').count();
+            assert_eq!(report.columns, Some(columns), "{}", command);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_threads_sequential() {
+        let directory = synth_directory("thyme_test_profile_threads_sequential");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        // Each command creates its own pool so thread counts can differ within a process
+        for (command, segments, substring, threads) in [
+            ("mask", "--mask-substring", "_mask", "1"),
+            ("boxes", "--box-substring", "_boxes", "2"),
+            ("mask", "--mask-substring", "_mask", "3"),
+        ] {
+            let output = directory.join(format!("{}_{}", command, threads));
+
+            run(&[
+                command,
+                "-i",
+                data,
+                "-o",
+                output.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+                "-t",
+                threads,
+            ]);
+
+            assert_eq!(object_counts(&output), [OBJECTS; IMAGES], "{}", command);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_prefetch() {
+        let directory = synth_directory("thyme_test_profile_prefetch");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+            ("boxes", "--box-substring", "_boxes"),
+        ] {
+            let profiled = |prefetch: &str| {
+                let output = directory.join(format!("{}_{}", command, prefetch));
+
+                run(&[
+                    command,
+                    "-i",
+                    data,
+                    "-o",
+                    output.to_str().unwrap(),
+                    "--image-substring",
+                    "_image",
+                    segments,
+                    substring,
+                    "--prefetch",
+                    prefetch,
+                    "--io-threads",
+                    "2",
+                ]);
+
+                std::fs::read_to_string(output.join("descriptors.csv")).unwrap()
+            };
+
+            // Prefetched images are profiled identically and in input order
+            assert_eq!(profiled("0"), profiled("1"), "{}", command);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_tui() {
+        let directory = synth_directory("thyme_test_profile_tui");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+            ("boxes", "--box-substring", "_boxes"),
+        ] {
+            let profiled = |tui: bool| {
+                let output = directory.join(format!("{}_{}", command, tui));
+                let output = output.to_str().unwrap();
+
+                let mut args = vec![
+                    command,
+                    "-i",
+                    data,
+                    "-o",
+                    output,
+                    "--image-substring",
+                    "_image",
+                    segments,
+                    substring,
+                ];
+
+                if tui {
+                    args.push("--tui");
+                }
+
+                run(&args);
+
+                std::fs::read_to_string(Path::new(output).join("descriptors.csv")).unwrap()
+            };
+
+            // Without a terminal the status panel falls back to status lines
+            assert_eq!(profiled(false), profiled(true), "{}", command);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// Columns of a csv table by name
+    fn table_columns(path: &Path) -> Vec<(String, Vec<String>)> {
+        let table = std::fs::read_to_string(path).unwrap();
+        let rows: Vec<Vec<&str>> = table.lines().map(|row| row.split(',').collect()).collect();
+
+        (0..rows[0].len())
+            .map(|j| {
+                let values = rows[1..].iter().map(|row| row[j].to_string()).collect();
+                (rows[0][j].to_string(), values)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_profile_feature_channels() {
+        let directory = std::env::temp_dir().join("thyme_test_profile_feature_channels");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            channels: 3,
+            objects: OBJECTS,
+            radius: [3.0, 6.0],
+            seed: 2,
+            ..Default::default()
+        }
+        .write(IMAGES, directory.join("data"))
+        .unwrap();
+
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        for (command, segments, substring, mode) in [
+            ("mask", "--mask-substring", "_mask", "cfbm"),
+            ("polygons", "--polygon-substring", "_polygons", "cfp"),
+            ("boxes", "--box-substring", "_boxes", "cx"),
+        ] {
+            let profiled = |name: &str, channels: &str| {
+                let output = directory.join(format!("{}_{}", command, name));
+
+                run(&[
+                    command,
+                    "-i",
+                    data,
+                    "-o",
+                    output.to_str().unwrap(),
+                    "--image-substring",
+                    "_image",
+                    segments,
+                    substring,
+                    "-m",
+                    mode,
+                    "--features-channels",
+                    channels,
+                ]);
+
+                table_columns(&output.join("descriptors.csv"))
+            };
+
+            let full = profiled("full", "intensity:0-2;moments:0-2;texture:0-2;zernike:0-2");
+            let narrowed = profiled("narrowed", "intensity:0-2;texture:0,1;moments:0");
+
+            // Narrowed columns and values are the corresponding subset of the full run
+            let subset: Vec<&(String, Vec<String>)> = full
+                .iter()
+                .filter(|(name, _)| narrowed.iter().any(|(n, _)| n == name))
+                .collect();
+
+            assert_eq!(subset.len(), narrowed.len(), "{}", command);
+
+            for ((name, values), (expected_name, expected)) in narrowed.iter().zip(subset) {
+                assert_eq!(name, expected_name, "{}", command);
+                assert_eq!(values, expected, "{} {}", command, name);
+            }
+
+            assert!(
+                narrowed
+                    .iter()
+                    .any(|(n, _)| n == "complete_texture_contrast_c1")
+            );
+            assert!(
+                !narrowed
+                    .iter()
+                    .any(|(n, _)| n == "complete_texture_contrast_c2")
+            );
+            assert!(
+                !narrowed
+                    .iter()
+                    .any(|(n, _)| n.contains("zernike") && n.ends_with("_c0"))
+            );
+        }
+
+        // Out-of-range channels are rejected against the first image
+        let image = directory.join("data").join("synth_0000_image.png");
+        assert!(table::feature_channels(Some("texture:2"), Some(&image)).is_ok());
+        assert!(table::feature_channels(Some("texture:3"), Some(&image)).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_texture_scales() {
+        let directory = synth_directory("thyme_test_profile_texture_scales");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+        ] {
+            let output = directory.join(command);
+
+            run(&[
+                command,
+                "-i",
+                data,
+                "-o",
+                output.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+                "-m",
+                "f",
+                "--texture-scales",
+                "1,2,4",
+            ]);
+
+            let columns = table_columns(&output.join("descriptors.csv"));
+            let column = |name: &str| &columns.iter().find(|(n, _)| n == name).unwrap().1;
+
+            for feature in constant::TEXTURE_DESCRIPTORS.iter() {
+                let name = format!("foreground_{}", feature.name);
+
+                // The original scale matches the single-scale texture descriptors
+                assert_eq!(
+                    column(&format!("{}_s1", name)),
+                    column(&name),
+                    "{}",
+                    command
+                );
+
+                // Objects are too small to be measured at the coarsest scale
+                assert!(
+                    column(&format!("{}_s4", name))
+                        .iter()
+                        .all(|v| v.parse::<f32>().unwrap() == 0.0)
+                );
+            }
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_smooth_contours() {
+        let directory = synth_directory("thyme_test_profile_smooth_contours");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        let outputs = [directory.join("raw"), directory.join("smoothed")];
+
+        for (output, smoothing) in outputs.iter().zip([None, Some("gaussian:1.5")]) {
+            let mut args = vec![
+                "mask",
+                "-i",
+                data,
+                "-o",
+                output.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                "--mask-substring",
+                "_mask",
+                "-m",
+                "cp",
+            ];
+
+            if let Some(smoothing) = smoothing {
+                args.extend(["--smooth-contours", smoothing]);
+            }
+
+            run(&args);
+        }
+
+        let raw = table_columns(&outputs[0].join("descriptors.csv"));
+        let smoothed = table_columns(&outputs[1].join("descriptors.csv"));
+
+        assert!(raw.iter().any(|(name, _)| name == "form_perimeter"));
+
+        // Only polygon descriptors are measured on the smoothed outlines
+        for ((name, raw), (_, smoothed)) in raw.iter().zip(smoothed.iter()) {
+            match name.as_str() {
+                "form_perimeter" | "form_form_factor" => assert_ne!(raw, smoothed),
+                name if name.starts_with("form_") => {}
+                name => assert_eq!(raw, smoothed, "{}", name),
+            }
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_thumbnails() {
+        let directory = synth_directory("thyme_test_profile_thumbnails");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        for (command, segments, substring, size) in [
+            ("mask", "--mask-substring", "_mask", "64"),
+            ("polygons", "--polygon-substring", "_polygons", "32"),
+            ("boxes", "--box-substring", "_boxes", "48"),
+        ] {
+            let output = directory.join(command);
+            let thumbnails = directory.join(format!("{}_thumbnails", command));
+
+            run(&[
+                command,
+                "-i",
+                data,
+                "-o",
+                output.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+                "--thumbnails",
+                thumbnails.to_str().unwrap(),
+                "--thumbnail-size",
+                size,
+            ]);
+
+            let columns = table_columns(&output.join("descriptors.csv"));
+            let (name, paths) = columns.last().unwrap();
+
+            assert_eq!(name, "thumbnail_path", "{}", command);
+            assert_eq!(paths.len(), IMAGES * OBJECTS, "{}", command);
+
+            let size: u32 = size.parse().unwrap();
+
+            for path in paths {
+                let thumbnail = ThymeImage::open(path).unwrap();
+                assert_eq!(thumbnail.shape(), (size, size, 1), "{}", command);
+                assert!(Path::new(path).starts_with(&thumbnails));
+            }
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_image_summary() {
+        let directory = std::env::temp_dir().join("thyme_test_profile_image_summary");
+        let _ = std::fs::remove_dir_all(&directory);
+        let data = directory.join("data");
+        std::fs::create_dir_all(&data).unwrap();
+
+        // Two rectangles covering 10 x 5 and 6 x 6 pixels of a 40 x 30 image
+        let (width, height) = (40u32, 30u32);
+        let rectangles = [[4, 5, 14, 10], [20, 15, 26, 21]];
+
+        let label = |x: u32, y: u32| {
+            rectangles
+                .iter()
+                .position(|r| (r[0]..r[2]).contains(&x) && (r[1]..r[3]).contains(&y))
+                .map_or(0, |i| i as u16 + 1)
+        };
+
+        let mask: Vec<u16> = (0..width * height)
+            .map(|i| label(i % width, i / width))
+            .collect();
+
+        let pixels: Vec<u8> = mask
+            .iter()
+            .flat_map(|&l| if l == 0 { [20, 40, 60] } else { [255; 3] })
+            .collect();
+
+        ThymeImage::U8(ThymeBuffer::new(width, height, 3, pixels).unwrap())
+            .save(data.join("a_image.png"))
+            .unwrap();
+
+        ThymeImage::U16(ThymeBuffer::new(width, height, 1, mask).unwrap())
+            .save(data.join("a_mask.png"))
+            .unwrap();
+
+        let polygons: Vec<[[u32; 2]; 4]> = rectangles
+            .iter()
+            .map(|r| {
+                [
+                    [r[0], r[1]],
+                    [r[2] - 1, r[1]],
+                    [r[2] - 1, r[3] - 1],
+                    [r[0], r[3] - 1],
+                ]
+            })
+            .collect();
+
+        std::fs::write(
+            data.join("a_polygons.json"),
+            serde_json::json!({ "polygons": polygons }).to_string(),
+        )
+        .unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+        ] {
+            let output = directory.join(command);
+
+            run(&[
+                command,
+                "-i",
+                data.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+                "-p",
+                "0",
+            ]);
+
+            let columns = table_columns(&output.join("image_summary.csv"));
+            let column = |name: &str| &columns.iter().find(|(n, _)| n == name).unwrap().1;
+
+            assert_eq!(column("image"), &["a_image"], "{}", command);
+            assert_eq!(column("objects"), &["2"], "{}", command);
+            assert_eq!(column("width"), &["40"], "{}", command);
+            assert_eq!(column("height"), &["30"], "{}", command);
+
+            let fraction: f32 = column("foreground_fraction")[0].parse().unwrap();
+            assert!((fraction - 86.0 / 1200.0).abs() < 1e-6, "{}", command);
+
+            for (c, expected) in [20.0, 40.0, 60.0].into_iter().enumerate() {
+                for statistic in ["mean", "median"] {
+                    let name = format!("background_{}_c{}", statistic, c);
+                    let value: f32 = column(&name)[0].parse().unwrap();
+                    assert_eq!(value, expected, "{} {}", command, name);
+                }
+            }
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_skip_image_if() {
+        let directory = synth_directory("thyme_test_profile_skip_image_if");
+        let data = directory.join("data");
+
+        // The first image is blank so it has no texture to focus on
+        ThymeImage::U16(ThymeBuffer::new(64, 64, 1, vec![100u16; 64 * 64]).unwrap())
+            .save(data.join("synth_0000_image.png"))
+            .unwrap();
+
+        for (command, segments, substring) in [
+            ("mask", "--mask-substring", "_mask"),
+            ("polygons", "--polygon-substring", "_polygons"),
+        ] {
+            let output = directory.join(command);
+
+            run(&[
+                command,
+                "-i",
+                data.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+                "--skip-image-if",
+                "foreground_fraction<0.001 || focus_score<1e-6",
+            ]);
+
+            let table = std::fs::read_to_string(output.join("descriptors.csv")).unwrap();
+            assert_eq!(table.lines().count(), 1 + OBJECTS, "{}", command);
+            assert_eq!(object_counts(&output), [OBJECTS], "{}", command);
+
+            let skipped = std::fs::read_to_string(output.join("skipped_images.tsv")).unwrap();
+            let rows: Vec<Vec<&str>> = skipped.lines().map(|r| r.split('\t').collect()).collect();
+
+            assert_eq!(rows.len(), 2, "{}", command);
+            assert_eq!(rows[0][..2], ["image", "reason"], "{}", command);
+            assert_eq!(
+                rows[1][..2],
+                ["synth_0000", "focus_score<0.000001"],
+                "{}",
+                command
+            );
+            assert_eq!(rows[1][3], "0", "{}", command);
+            assert_eq!(rows[1][4], "100", "{}", command);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_image_glob() {
+        let directory = synth_directory("thyme_test_profile_image_glob");
+        let (images, masks) = (directory.join("images"), directory.join("masks"));
+
+        std::fs::create_dir_all(&images).unwrap();
+        std::fs::create_dir_all(&masks).unwrap();
+
+        // Images and masks in separate directories share their full stems
+        for name in ["synth_0000", "synth_0001"] {
+            let data = directory.join("data");
+            std::fs::copy(
+                data.join(format!("{}_image.png", name)),
+                images.join(format!("{}.png", name)),
+            )
+            .unwrap();
+            std::fs::copy(
+                data.join(format!("{}_mask.png", name)),
+                masks.join(format!("{}.png", name)),
+            )
+            .unwrap();
+        }
+
+        let (images, masks) = (images.to_str().unwrap(), masks.to_str().unwrap());
+
+        for (flag, pattern) in [
+            ("--image-glob", "*_000[1].png"),
+            ("--image-regex", "1\\.png$"),
+        ] {
+            let output = directory.join(&flag[2..]);
+            let output = output.to_str().unwrap();
+
+            run(&[
+                "mask", "-i", images, "-s", masks, "-o", output, flag, pattern,
+            ]);
+            assert_eq!(object_counts(Path::new(output)), [OBJECTS], "{}", flag);
+
+            // A glob or regex cannot be combined with a substring for the same input
+            let args = [
+                "mask",
+                "-i",
+                images,
+                "-o",
+                output,
+                flag,
+                pattern,
+                "--image-substring",
+                "_1",
+            ];
+            let cli = Cli::try_parse_from(std::iter::once("thyme").chain(args));
+            assert!(cli.is_err(), "{}", flag);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_failed_objects() {
+        let directory = synth_directory("thyme_test_profile_failed_objects");
+        let data = directory.join("data");
+        let path = data.join("synth_0000_polygons.json");
+
+        // A polygon with a NaN vertex (as written by Python's json module)
+        // is placed among the healthy polygons of the first image
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        let mut polygons: Vec<String> = json["polygons"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|polygon| polygon.to_string())
+            .collect();
+
+        polygons.insert(2, "[[NaN, 10], [20, 10], [20, 20]]".to_string());
+        std::fs::write(
+            &path,
+            format!(r#"{{"polygons": [{}]}}"#, polygons.join(",")),
+        )
+        .unwrap();
+
+        let output = directory.join("polygons");
+        let config = thyme_config::ProfilePolygonsConfig::new(
+            data.to_str().unwrap(),
+            output.to_str().unwrap(),
+        )
+        .image_substring("_image")
+        .polygon_substring("_polygons")
+        .mode("cfpm");
+
+        let summary = run_profile_polygons(&config).unwrap();
+
+        // The other objects of the image are still measured
+        assert_eq!(summary.failures, 0);
+        assert_eq!(summary.failed_objects, 1);
+        assert_eq!(summary.objects, IMAGES * OBJECTS);
+        assert_eq!(object_counts(&output), [OBJECTS; IMAGES]);
+
+        let ids = &table_columns(&output.join("descriptors.csv"))[1];
+        assert_eq!(ids.0, "object");
+        assert!(!ids.1[..OBJECTS].contains(&"2".to_string()));
+
+        let failed = std::fs::read_to_string(output.join("failed_objects.tsv")).unwrap();
+        let rows: Vec<Vec<&str>> = failed.lines().map(|r| r.split('\t').collect()).collect();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].join("\t"), crate::failures::FAILED_OBJECTS_HEADER);
+        assert_eq!(
+            rows[1][..7],
+            ["synth_0000", "2", "20", "10", "20", "20", "crop"]
+        );
+        assert!(rows[1][7].contains("non-finite"), "{}", rows[1][7]);
+
+        assert!(!output.join("object_errors.tsv").exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_object_budget() {
+        let directory = synth_directory("thyme_test_profile_object_budget");
+        let data = directory.join("data");
+        let path = data.join("synth_0000_polygons.json");
+
+        // A malformed polygon with 200,000 vertices is placed among the
+        // healthy polygons of the first image
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        let mut polygons: Vec<String> = json["polygons"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|polygon| polygon.to_string())
+            .collect();
+
+        let huge: Vec<String> = (0..200_000)
+            .map(|i| {
+                let theta = i as f32 / 200_000.0 * std::f32::consts::TAU;
+                format!(
+                    "[{},{}]",
+                    32.0 + 10.0 * theta.cos(),
+                    32.0 + 10.0 * theta.sin()
+                )
+            })
+            .collect();
+
+        polygons.insert(2, format!("[{}]", huge.join(",")));
+        std::fs::write(
+            &path,
+            format!(r#"{{"polygons": [{}]}}"#, polygons.join(",")),
+        )
+        .unwrap();
+
+        let config = |output: &Path| {
+            thyme_config::ProfilePolygonsConfig::new(
+                data.to_str().unwrap(),
+                output.to_str().unwrap(),
+            )
+            .image_substring("_image")
+            .polygon_substring("_polygons")
+            .mode("cp")
+            .max_points_per_object(1000)
+        };
+
+        let failed_rows = |output: &Path| -> Vec<Vec<String>> {
+            std::fs::read_to_string(output.join("failed_objects.tsv"))
+                .unwrap()
+                .lines()
+                .skip(1)
+                .map(|row| row.split('\t').map(str::to_string).collect())
+                .collect()
+        };
+
+        // Oversized polygons are measured on a resampled outline and flagged
+        let output = directory.join("resample");
+        let summary = run_profile_polygons(&config(&output)).unwrap();
+
+        assert_eq!(summary.failures, 0);
+        assert_eq!(summary.objects, IMAGES * OBJECTS + 1);
+        assert_eq!(summary.failed_objects, 1);
+        assert_eq!(object_counts(&output), [OBJECTS + 1, OBJECTS]);
+
+        let rows = failed_rows(&output);
+        assert_eq!(rows[0][..2], ["synth_0000", "2"]);
+        assert_eq!(rows[0][6], "budget");
+        assert!(
+            rows[0][7].contains("max_points_per_object 1000"),
+            "{}",
+            rows[0][7]
+        );
+        assert!(rows[0][7].contains("resampled"), "{}", rows[0][7]);
+
+        // Skipped polygons have no descriptors
+        let output = directory.join("skip");
+        let summary = run_profile_polygons(&config(&output).oversized_policy("skip")).unwrap();
+
+        assert_eq!(summary.objects, IMAGES * OBJECTS);
+        assert_eq!(summary.failed_objects, 1);
+
+        let ids = &table_columns(&output.join("descriptors.csv"))[1];
+        assert!(!ids.1[..OBJECTS].contains(&"2".to_string()));
+        assert!(failed_rows(&output)[0][7].contains("skipped"));
+
+        // Descriptor families after the first are abandoned over the time budget
+        let output = directory.join("timeout");
+        let summary = run_profile_polygons(
+            &config(&output)
+                .max_seconds_per_object(1e-9)
+                .nonfinite_policy("zero"),
+        )
+        .unwrap();
+
+        assert_eq!(summary.objects, IMAGES * OBJECTS + 1);
+        assert_eq!(summary.failed_objects, IMAGES * OBJECTS + 2);
+
+        let rows = failed_rows(&output);
+        assert!(rows.iter().all(|row| row[6] == "budget"));
+        assert!(
+            rows[1][7].contains("max_seconds_per_object"),
+            "{}",
+            rows[1][7]
+        );
+
+        // Location and polygon descriptors are kept and the other columns are zeroed
+        let columns = table_columns(&output.join("descriptors.csv"));
+        let measured = 2 + 6 + constant::FORM_DESCRIPTORS.len();
+
+        assert_eq!(
+            columns.len(),
+            table_columns(&directory.join("resample/descriptors.csv")).len()
+        );
+        assert!(columns.iter().any(|(name, values)| {
+            name == "form_area"
+                && values
+                    .iter()
+                    .all(|value| value.parse::<f32>().unwrap() != 0.0)
+        }));
+        assert!(columns.len() > measured);
+
+        for (name, values) in &columns[measured..] {
+            assert!(
+                values
+                    .iter()
+                    .all(|value| value.parse::<f32>().unwrap() == 0.0),
+                "{}",
+                name
+            );
+        }
+
+        // Mask outlines are resampled before their polygon descriptors are computed
+        let output = directory.join("mask");
+        let config =
+            thyme_config::ProfileMaskConfig::new(data.to_str().unwrap(), output.to_str().unwrap())
+                .image_substring("_image")
+                .mask_substring("_mask")
+                .mode("cp")
+                .max_points_per_object(8)
+                .oversized_policy("skip");
+
+        let summary = run_profile_mask(&config).unwrap();
+
+        assert_eq!(summary.failures, 0);
+        assert_eq!(summary.objects + summary.failed_objects, IMAGES * OBJECTS);
+        assert!(summary.failed_objects > 0);
+        assert!(failed_rows(&output)[0][7].contains("max_points_per_object 8"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_geometry() {
+        let directory = synth_directory("thyme_test_profile_geometry");
+        let data = directory.join("data");
+
+        let polygons = |format: &str, output: &Path| {
+            let config = thyme_config::ProfilePolygonsConfig::new(
+                data.to_str().unwrap(),
+                output.to_str().unwrap(),
+            )
+            .image_substring("_image")
+            .polygon_substring("_polygons")
+            .mode("cp")
+            .include_geometry(true)
+            .geometry_format(format)
+            .geometry_max_points(8);
+
+            run_profile_polygons(&config).map(|_| io::read_table(output).unwrap())
+        };
+
+        let df = polygons("list", &directory.join("polygons_list.pq")).unwrap();
+        assert_eq!(df.height(), IMAGES * OBJECTS);
+        assert_eq!(df.get_column_names().last().unwrap().as_str(), "polygon");
+
+        // The first polygon is reconstructed from its [x, y] vertex lists
+        let points: Vec<[f32; 2]> = df
+            .column("polygon")
+            .unwrap()
+            .list()
+            .unwrap()
+            .get_as_series(0)
+            .unwrap()
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|point| {
+                let point = point.unwrap();
+                let point = point.f32().unwrap();
+                [point.get(0).unwrap(), point.get(1).unwrap()]
+            })
+            .collect();
+
+        let object = df.column("object").unwrap().u32().unwrap().get(0).unwrap();
+        let input = thyme_core::im::Polygons::open(data.join("synth_0000_polygons.json")).unwrap();
+        let input = &input.as_points()[object as usize];
+
+        // Resampled vertices lie on the outline of the input polygon
+        assert!(input.len() > 8);
+        assert_eq!(points.len(), 8);
+
+        for [x, y] in points.iter() {
+            let distance = (0..input.len())
+                .map(|i| {
+                    let [x0, y0] = input[i];
+                    let [x1, y1] = input[(i + 1) % input.len()];
+                    let [dx, dy] = [x1 - x0, y1 - y0];
+                    let t = (((x - x0) * dx + (y - y0) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+                    ((x - x0 - t * dx).powi(2) + (y - y0 - t * dy).powi(2)).sqrt()
+                })
+                .fold(f32::INFINITY, f32::min);
+
+            assert!(
+                distance < 1e-3,
+                "{:?} is {} from the outline",
+                [x, y],
+                distance
+            );
+        }
+
+        // Well-known binary polygons hold the same vertices as f64
+        let df = polygons("wkb", &directory.join("polygons_wkb.pq")).unwrap();
+        let wkb = df
+            .column("polygon")
+            .unwrap()
+            .binary()
+            .unwrap()
+            .get(0)
+            .unwrap();
+
+        assert_eq!(wkb[..9], [1, 3, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(u32::from_le_bytes(wkb[9..13].try_into().unwrap()), 8);
+
+        for (k, [x, y]) in points.iter().enumerate() {
+            let coordinate = |offset: usize| {
+                f64::from_le_bytes(wkb[offset..offset + 8].try_into().unwrap()) as f32
+            };
+
+            assert_eq!([coordinate(13 + 16 * k), coordinate(21 + 16 * k)], [*x, *y]);
+        }
+
+        // Polygon columns cannot be written to text tables
+        assert!(polygons("list", &directory.join("polygons.csv")).is_err());
+        assert!(!directory.join("polygons.csv").exists());
+
+        // Mask outlines are traced around the pixels of each object
+        let output = directory.join("mask.pq");
+        let config =
+            thyme_config::ProfileMaskConfig::new(data.to_str().unwrap(), output.to_str().unwrap())
+                .image_substring("_image")
+                .mask_substring("_mask")
+                .include_geometry(true);
+
+        run_profile_mask(&config).unwrap();
+
+        let df = io::read_table(&output).unwrap();
+        let polygons = df.column("polygon").unwrap().list().unwrap();
+        let centroid_x = df.column("centroid_x").unwrap().f32().unwrap();
+
+        assert_eq!(polygons.len(), IMAGES * OBJECTS);
+        assert_eq!(polygons.null_count(), 0);
+
+        for (polygon, x) in polygons.into_iter().zip(centroid_x) {
+            let xs: Vec<f32> = polygon
+                .unwrap()
+                .list()
+                .unwrap()
+                .into_iter()
+                .map(|point| point.unwrap().f32().unwrap().get(0).unwrap())
+                .collect();
+
+            let [min, max] = xs
+                .iter()
+                .fold([f32::INFINITY, f32::NEG_INFINITY], |[min, max], &x| {
+                    [min.min(x), max.max(x)]
+                });
+
+            assert!(xs.len() <= 128);
+            assert!(min < x.unwrap() && x.unwrap() < max);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_preview() {
+        let directory = std::env::temp_dir().join("thyme_test_profile_preview");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 256,
+            height: 256,
+            objects: 4,
+            radius: [28.0, 36.0],
+            seed: 3,
+            ..Default::default()
+        }
+        .write(IMAGES, directory.join("data"))
+        .unwrap();
+
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        for (command, segments, substring, mode, area) in [
+            ("mask", "--mask-substring", "_mask", "cp", "form_area"),
+            (
+                "polygons",
+                "--polygon-substring",
+                "_polygons",
+                "cp",
+                "form_area",
+            ),
+            ("boxes", "--box-substring", "_boxes", "cx", "bbox_area"),
+        ] {
+            let profiled = |name: &str, preview: &[&str]| {
+                let output = directory.join(format!("{}_{}", command, name));
+                let mut args = vec![
+                    command,
+                    "-i",
+                    data,
+                    "-o",
+                    output.to_str().unwrap(),
+                    "--image-substring",
+                    "_image",
+                    segments,
+                    substring,
+                    "-m",
+                    mode,
+                    "-p",
+                    "0",
+                ];
+
+                args.extend(preview);
+                run(&args);
+
+                (
+                    table_columns(&output.join("descriptors.csv")),
+                    object_counts(&output),
+                )
+            };
+
+            let (full, full_counts) = profiled("full", &[]);
+            let (preview, preview_counts) =
+                profiled("preview", &["--preview", "0.5", "--preview-rescale"]);
+
+            // Objects of a clean fixture are all found in the preview
+            assert_eq!(preview_counts, [4; IMAGES], "{}", command);
+            assert_eq!(preview_counts, full_counts, "{}", command);
+
+            assert_eq!(preview[2].0, "preview_scale", "{}", command);
+            assert!(preview[2].1.iter().all(|scale| scale == "0.5"));
+            assert!(!full.iter().any(|(name, _)| name == "preview_scale"));
+
+            // Areas are rescaled to original pixel units
+            let column = |columns: &[(String, Vec<String>)], name: &str| -> Vec<f32> {
+                let (_, values) = columns.iter().find(|(n, _)| n == name).unwrap();
+                values.iter().map(|v| v.parse().unwrap()).collect()
+            };
+
+            for (full_area, preview_area) in
+                column(&full, area).into_iter().zip(column(&preview, area))
+            {
+                let error = (preview_area - full_area).abs() / full_area;
+                assert!(error < 0.05, "{} {} {}", command, full_area, preview_area);
+            }
+
+            let (unscaled, _) = profiled("unscaled", &["--preview", "0.5"]);
+            let ratio = column(&unscaled, "bbox_max_x")[0] / column(&full, "bbox_max_x")[0];
+            assert!((ratio - 0.5).abs() < 0.05, "{} {}", command, ratio);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// Write a class mask with class 1 on the left half and class 2 on the right half
+    fn write_class_mask(path: &Path, width: u32, height: u32) {
+        let classes = (0..width * height)
+            .map(|idx| if idx % width < width / 2 { 1 } else { 2 })
+            .collect();
+
+        ThymeImage::U16(ThymeBuffer::new(width, height, 1, classes).unwrap())
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_profile_class_mask() {
+        let directory = synth_directory("thyme_test_profile_class_mask");
+        let data = directory.join("data");
+
+        // The second class mask does not match the image dimensions
+        write_class_mask(&data.join("synth_0000_class.png"), 64, 64);
+        write_class_mask(&data.join("synth_0001_class.png"), 32, 64);
+
+        let output = directory.join("mask");
+
+        run(&[
+            "mask",
+            "-i",
+            data.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--image-substring",
+            "_image",
+            "--mask-substring",
+            "_mask",
+            "--class-mask",
+            data.to_str().unwrap(),
+            "--class-substring",
+            "_class",
+        ]);
+
+        let columns = table_columns(&output.join("descriptors.csv"));
+        let column = |name: &str| -> Vec<f32> {
+            let (_, values) = columns.iter().find(|(n, _)| n == name).unwrap();
+            values.iter().map(|v| v.parse().unwrap()).collect()
+        };
+
+        assert_eq!(columns[2].0, "class_id");
+        assert_eq!(columns[3].0, "class_frac");
+        assert_eq!(object_counts(&output), [OBJECTS]);
+
+        let (min_x, max_x) = (column("bbox_min_x"), column("bbox_max_x"));
+        let (class_id, class_frac) = (column("class_id"), column("class_frac"));
+
+        for i in 0..OBJECTS {
+            assert!(class_frac[i] > 0.5 && class_frac[i] <= 1.0);
+
+            if max_x[i] < 31.0 {
+                assert_eq!((class_id[i], class_frac[i]), (1.0, 1.0));
+            }
+
+            if min_x[i] > 33.0 {
+                assert_eq!((class_id[i], class_frac[i]), (2.0, 1.0));
+            }
+        }
+
+        let errors = std::fs::read_to_string(output.join("object_errors.tsv")).unwrap();
+        assert!(errors.contains("Class mask and image are not the same size"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_profile_mask_non_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let directory = synth_directory("thyme_test_profile_mask_non_utf8");
+        let data = directory.join("data");
+
+        // Names with spaces and invalid UTF-8 bytes are paired by their raw stems
+        for suffix in ["image.png", "mask.png"] {
+            let mut name = b"synth \xff0000_".to_vec();
+            name.extend_from_slice(suffix.as_bytes());
+
+            std::fs::rename(
+                data.join(format!("synth_0000_{}", suffix)),
+                data.join(OsStr::from_bytes(&name)),
+            )
+            .unwrap();
+        }
+
+        let output = directory.join("mask output");
+
+        run(&[
+            "mask",
+            "-i",
+            data.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--image-substring",
+            "_image",
+            "--mask-substring",
+            "_mask",
+        ]);
+
+        let table = std::fs::read_to_string(output.join("descriptors.csv")).unwrap();
+
+        assert_eq!(table.lines().count(), 1 + IMAGES * OBJECTS);
+        assert!(table.contains("synth \u{fffd}0000"));
+        assert_eq!(object_counts(&output), [OBJECTS; IMAGES]);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_profile_overwrite_keeps_inputs() {
+        let directory = synth_directory("thyme_test_profile_overwrite_keeps_inputs");
+
+        // Inputs and unrelated files share the output directory
+        let project = directory.join("project");
+        let (images, masks) = (project.join("images"), project.join("mask"));
+
+        for (target, suffix) in [(&images, "_image"), (&masks, "_mask")] {
+            std::fs::create_dir_all(target).unwrap();
+
+            for entry in std::fs::read_dir(directory.join("data")).unwrap() {
+                let path = entry.unwrap().path();
+
+                if path
+                    .file_stem()
+                    .unwrap()
+                    .to_string_lossy()
+                    .ends_with(suffix)
+                {
+                    std::fs::copy(&path, target.join(path.file_name().unwrap())).unwrap();
+                }
+            }
+        }
+
+        std::fs::create_dir_all(project.join("polygons")).unwrap();
+        std::fs::write(project.join("polygons").join("mine.json"), "[]").unwrap();
+
+        let config = thyme_config::ProfileMaskConfig::new(
+            images.to_str().unwrap(),
+            project.to_str().unwrap(),
+        )
+        .masks(masks.to_str().unwrap())
+        .image_substring("_image")
+        .mask_substring("_mask")
+        .mode("f");
+
+        for overwrite in [false, true, true] {
+            let summary = run_profile_mask(&config.clone().overwrite(overwrite)).unwrap();
+            assert_eq!(summary.objects, IMAGES * OBJECTS);
+
+            assert_eq!(std::fs::read_dir(&masks).unwrap().count(), IMAGES);
+            assert!(project.join("polygons").join("mine.json").is_file());
+        }
+
+        let artifacts = crate::output::run_artifacts(&project).unwrap();
+        assert!(artifacts.contains(&project.join("descriptors.csv")));
+        assert!(!artifacts.contains(&masks));
+
+        // Only the outputs listed in the run marker block a new run
+        assert!(run_profile_mask(&config).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_run_profile_mask_config() {
+        let directory = synth_directory("thyme_test_run_profile_mask_config");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        let cli = directory.join("cli");
+        let library = directory.join("library");
+
+        run(&[
+            "mask",
+            "-i",
+            data,
+            "-o",
+            cli.to_str().unwrap(),
+            "-m",
+            "cfm",
+            "--image-substring",
+            "_image",
+            "--mask-substring",
+            "_mask",
+        ]);
+
+        let config = thyme_config::ProfileMaskConfig::new(data, library.to_str().unwrap())
+            .image_substring("_image")
+            .mask_substring("_mask")
+            .mode("cfm")
+            .threads(2);
+
+        let summary = run_profile_mask(&config).unwrap();
+
+        assert_eq!(summary.images, IMAGES);
+        assert_eq!(summary.failures, 0);
+        assert_eq!(summary.objects, IMAGES * OBJECTS);
+        assert_eq!(summary.output, library);
+        assert_eq!(summary.descriptors, Some(library.join("descriptors.csv")));
+
+        // Configs built in code profile the same objects as the command line
+        assert_eq!(
+            std::fs::read_to_string(cli.join("descriptors.csv")).unwrap(),
+            std::fs::read_to_string(library.join("descriptors.csv")).unwrap()
+        );
+
+        // A second run into the same output is rejected unless it overwrites the first
+        assert!(run_profile_mask(&config).is_err());
+
+        let summary = run_profile_mask(&config.clone().overwrite(true)).unwrap();
+        assert_eq!(summary.objects, IMAGES * OBJECTS);
+
+        // Invalid configs and inputs are returned as errors
+        assert!(run_profile_mask(&config.clone().mode("q")).is_err());
+        assert!(run_profile_mask(&config.clone().mask_substring("_missing")).is_err());
+        assert!(
+            run_profile_mask(&config.output(directory.join("out.bin").to_str().unwrap())).is_err()
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_run_profile_segment_configs() {
+        let directory = synth_directory("thyme_test_run_profile_segment_configs");
+        let data = directory.join("data");
+        let data = data.to_str().unwrap();
+
+        for (command, segments, substring) in [
+            ("polygons", "--polygon-substring", "_polygons"),
+            ("boxes", "--box-substring", "_boxes"),
+        ] {
+            let cli = directory.join(format!("{}_cli", command));
+            let library = directory.join(format!("{}_library.csv", command));
+
+            run(&[
+                command,
+                "-i",
+                data,
+                "-o",
+                cli.to_str().unwrap(),
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+            ]);
+
+            let summary = match command {
+                "polygons" => run_profile_polygons(
+                    &thyme_config::ProfilePolygonsConfig::new(data, library.to_str().unwrap())
+                        .image_substring("_image")
+                        .polygon_substring(substring),
+                ),
+                _ => run_profile_boxes(
+                    &thyme_config::ProfileBoxesConfig::new(data, library.to_str().unwrap())
+                        .image_substring("_image")
+                        .box_substring(substring),
+                ),
+            }
+            .unwrap();
+
+            assert_eq!(summary.images, IMAGES, "{}", command);
+            assert_eq!(summary.objects, IMAGES * OBJECTS, "{}", command);
+            assert_eq!(summary.descriptors.as_ref(), Some(&library), "{}", command);
+
+            assert_eq!(
+                std::fs::read_to_string(cli.join("descriptors.csv")).unwrap(),
+                std::fs::read_to_string(&library).unwrap(),
+                "{}",
+                command
+            );
+        }
+
+        let boxes = thyme_config::ProfileBoxesConfig::new(data, directory.to_str().unwrap())
+            .image_substring("_image")
+            .box_substring("_boxes")
+            .mode("cm");
+
+        assert!(run_profile_boxes(&boxes).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -2,33 +2,62 @@
 // Licensed under the MIT License
 
 use std::path::Path;
-use std::path::PathBuf;
-use std::sync::Mutex;
 
 use clap::Args;
-use kdam::TqdmParallelIterator;
 use polars::prelude::*;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 
+use thyme_core::catalog;
 use thyme_core::constant;
-use thyme_core::cv::points::draw_centered_points;
+use thyme_core::cv::points::{draw_centered_points, draw_points_mut};
+use thyme_core::cv::transform::AffineTransform;
 use thyme_core::error::ThymeError;
 use thyme_core::im;
 use thyme_core::io;
+use thyme_core::mp::reduce::{DEVICE_MIN_SUBPIXELS, MeasureDevice, Reduce};
+use thyme_core::mp::{form, texture};
 use thyme_core::ut;
 
-#[derive(Debug, Args)]
+use thyme_config::ProfilePolygonsConfig;
+
+use super::ProfileSummary;
+use super::preview::Preview;
+use super::summary::SummaryRow;
+use super::table::{self, Descriptors, NonFiniteReport};
+use super::thumbnail::Thumbnails;
+use crate::dryrun;
+use crate::failures::{self, ObjectBudget, ObjectError, OversizedPolicy, Stage};
+use crate::filter::{
+    self, BorderPolicy, CropRect, DropCounts, DropReason, NonFinitePolicy, ObjectFilter, crop_rect,
+};
+use crate::output::{MetadataJoin, OutputMode, PerImageOutput};
+use crate::prefetch::Prefetch;
+use crate::provenance;
+use crate::remote::RemoteInputs;
+use crate::status::Progress;
+use crate::transforms::SegmentTransforms;
+
+#[derive(Debug, Args, Serialize)]
 pub struct ProfilePolygonsArgs {
-    #[arg(short = 'i', long, help = "Image directory.", required = true)]
+    #[arg(
+        short = 'i',
+        long,
+        help = "Image directory or s3:// or https:// url.",
+        required = true
+    )]
     pub images: Option<String>,
 
-    #[arg(short = 's', long, help = "Polygons directory.")]
+    #[arg(
+        short = 's',
+        long,
+        help = "Polygons directory or s3:// or https:// url."
+    )]
     pub polygons: Option<String>,
 
     #[arg(
         short = 'o',
         long,
-        help = "Output directory or file (.csv, .txt, .tsv, .pq).",
+        help = "Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather).",
         required = true
     )]
     pub output: Option<String>,
@@ -36,17 +65,56 @@ pub struct ProfilePolygonsArgs {
     #[arg(short = 'v', long, help = "Verbose output.")]
     pub verbose: bool,
 
-    #[arg(short = 'd', long, help = "Exclude objects touching edge of image.")]
+    #[arg(
+        long,
+        help = "Show a live status panel with progress, throughput, workers, recent errors, and memory usage instead of the progress bar. Logs status lines instead if stdout is not a terminal."
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Format of the status lines logged instead of the status panel (plain, json). Json status lines are also logged without --tui.",
+        default_value = "plain"
+    )]
+    pub log_format: Option<String>,
+
+    #[arg(
+        short = 'd',
+        long,
+        help = "Exclude objects touching edge of image. Equivalent to --border-policy drop.",
+        conflicts_with = "border_policy"
+    )]
     pub drop_borders: bool,
 
+    #[arg(
+        long,
+        help = "Handling of objects whose unpadded bounding box touches the edge of the image: keep, drop, or flag (keeps objects and adds a touches_border column).",
+        default_value = "keep"
+    )]
+    pub border_policy: Option<String>,
+
+    #[arg(
+        long,
+        help = "Handling of objects with non-finite (NaN or infinite) descriptors: keep, zero (replaces non-finite values with zero), or drop-object.",
+        default_value = "keep"
+    )]
+    pub nonfinite_policy: Option<String>,
+
     #[arg(
         long,
         short = 'm',
-        help = "Mode. Compute descriptors across one or more features including c (complete pixels), f (foreground pixels), b (background pixels), m (binary mask), p (polygons), and x (bounding boxes).",
+        help = "Mode. Compute descriptors across one or more features including c (complete pixels), f (foreground pixels), b (background pixels), m (binary mask), p (polygons), s (power spectrum), and x (bounding boxes).",
         default_value = "cm"
     )]
     pub mode: Option<String>,
 
+    #[arg(
+        long,
+        help = "Pixels measured as the background of mode b: bbox (crop pixels outside of the object) or ring:<px> (pixels within px pixels outside of the object boundary that do not belong to any object).",
+        default_value = "bbox"
+    )]
+    pub background_style: Option<String>,
+
     #[arg(
         short = 'p',
         long,
@@ -63,185 +131,883 @@ pub struct ProfilePolygonsArgs {
 
     #[arg(
         long,
-        help = "Exclude objects smaller than a minimum size.",
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting polygons by their path relative to their directory.",
+        conflicts_with_all = ["polygon_substring", "polygon_regex"]
+    )]
+    pub polygon_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting polygons by their path relative to their directory.",
+        conflicts_with = "polygon_substring"
+    )]
+    pub polygon_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Coordinate system of the polygon vertices: pixels or normalized (fractions of the image width and height).",
+        default_value = "pixels"
+    )]
+    pub segment_coords: Option<String>,
+
+    #[arg(
+        long,
+        help = "Table (.csv, .txt, .tsv, .pq, .arrow, .feather) of affine transforms registering the polygons of each image to the image, with columns image, a, b, c, d, tx, and ty. Vertices (x, y) are mapped to (a*x + b*y + tx, c*x + d*y + ty) before profiling. Requires pixel segment coordinates."
+    )]
+    pub segment_transform: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with an unpadded bounding box width or height smaller than a minimum size (pixels).",
         default_value = "1"
     )]
     pub min_size: Option<u32>,
 
+    #[arg(
+        long,
+        help = "Exclude objects with a polygon area smaller than a minimum area (pixels)."
+    )]
+    pub min_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Exclude objects with a polygon area larger than a maximum area (pixels)."
+    )]
+    pub max_area: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Resample or skip objects whose outline has more vertices than a maximum before they are measured. Objects over the budget are recorded in failed_objects.tsv."
+    )]
+    pub max_points_per_object: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Handling of objects with more vertices than max_points_per_object. Options: resample (measure the object on an outline resampled to max_points_per_object vertices), skip (exclude the object) [default: resample]."
+    )]
+    pub oversized_policy: Option<String>,
+
+    #[arg(
+        long,
+        help = "Abandon the remaining descriptor families of an object once its measurement has taken longer than a maximum time (seconds). Descriptors that were not computed are handled by the nonfinite_policy and the object is recorded in failed_objects.tsv."
+    )]
+    pub max_seconds_per_object: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Skip images before profiling their objects if an expression of image metrics is true (e.g. 'foreground_fraction<0.001 || focus_score<30'). Conditions compare foreground_fraction, focus_score, background_mean, or background_median to a number and are joined by && and ||. Skipped images are written to skipped_images.tsv."
+    )]
+    pub skip_image_if: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude self-intersecting polygons that could not be repaired instead of measuring them as is."
+    )]
+    pub skip_invalid_polygons: bool,
+
+    #[arg(
+        long,
+        help = "Compute boundary band and interior intensity descriptors using a boundary band of the provided width (pixels)."
+    )]
+    pub membrane_width: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Compute the displacement between the mask centroid and the intensity-weighted centroid of each channel, normalized by equivalent diameter."
+    )]
+    pub centroid_displacement: bool,
+
+    #[arg(
+        long,
+        help = "Compute the mean intensity ratio and median intensity difference of foreground pixels between channel pairs, provided as all (every ordered pair) or numerator:denominator pairs (e.g. 0:1,2:1)."
+    )]
+    pub channel_pairs: Option<String>,
+
+    #[arg(
+        long,
+        help = "Include intensity percentiles (p10, p25, p75, p90), skewness, and kurtosis in c, f, and b descriptors."
+    )]
+    pub extended_intensity: bool,
+
+    #[arg(
+        long,
+        requires = "extended_intensity",
+        help = "Number of positive pixels of a channel above which extended intensity percentiles are selected without sorting (e.g. 10000000). Percentiles are exact and results are identical to sorting below the threshold, while skewness and kurtosis may differ in the last digits above it."
+    )]
+    pub approx_percentiles_over: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Compute intensity, moments, texture, and zernike descriptors separately for the listed channels of each family, provided as family:channels pairs (e.g. 'intensity:0-5;texture:0,1;moments:0'). Families that are not listed are not computed."
+    )]
+    pub features_channels: Option<String>,
+
+    #[arg(
+        long,
+        help = "Only accumulate texture co-occurrences anchored at every n-th row and column of each object.",
+        default_value = "1"
+    )]
+    pub texture_stride: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Escalate the texture stride for objects with more pixels than this threshold so that roughly this many pixels are sampled (e.g. 250000). Applied strides are written to a texture_stride column when verbose."
+    )]
+    pub texture_auto_stride: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Device that intensity sums, extrema, and moments of objects with at least 1048576 subpixels are reduced on (cpu, cuda). Smaller objects and the remaining descriptors are measured on the cpu.",
+        default_value = "cpu"
+    )]
+    pub measure_device: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compute foreground texture descriptors of each object crop and mask downsampled by these factors (e.g. 1,2,4). Scales where the downsampled crop is smaller than 8 pixels on a side are filled with zeros."
+    )]
+    pub texture_scales: Option<String>,
+
+    #[arg(
+        long,
+        help = "Directory to write a letterboxed 8-bit thumbnail of each object crop to. Adds a thumbnail_path column referencing each thumbnail."
+    )]
+    pub thumbnails: Option<String>,
+
+    #[arg(
+        long,
+        help = "Width and height of object thumbnails (pixels).",
+        default_value = "64"
+    )]
+    pub thumbnail_size: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Profile a preview of each image and its segments downsampled by this factor in (0, 1] (e.g. 0.25) for rapid parameter tuning. Adds a preview_scale column after the object column."
+    )]
+    pub preview: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Rescale lengths, areas, and spatial frequencies of a preview to original pixel units.",
+        requires = "preview"
+    )]
+    pub preview_rescale: bool,
+
+    #[arg(
+        long,
+        help = "Metadata table (.csv, .txt, .tsv, .pq, .arrow, .feather) joined to every output row (e.g. a platemap)."
+    )]
+    pub metadata_table: Option<String>,
+
+    #[arg(
+        long,
+        help = "Name of the join key column in the metadata table (e.g. well)."
+    )]
+    pub metadata_join: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex extracting the join key from image file names using the first capture group or full match (e.g. '_([A-P][0-9]{2})_'). Defaults to the image file name."
+    )]
+    pub well_from_filename: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exit with an error instead of writing nulls when an image has no matching metadata."
+    )]
+    pub strict_join: bool,
+
+    #[arg(
+        long,
+        help = "Compression for arrow/feather output tables (lz4, zstd, none).",
+        default_value = "none"
+    )]
+    pub ipc_compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Digits after the decimal point of floats in csv/tsv output tables. Floats are then never written in scientific notation."
+    )]
+    pub float_precision: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Notation of floats in csv/tsv output tables (auto, fixed). Fixed floats are never written in scientific notation.",
+        default_value = "auto"
+    )]
+    pub float_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Naming convention of descriptor columns (thyme, cellprofiler). Cellprofiler renames descriptors with a CellProfiler equivalent (e.g. AreaShape_Area, Intensity_MeanIntensity_DNA) and prefixes the others with Thyme_.",
+        default_value = "thyme"
+    )]
+    pub column_style: Option<String>,
+
+    #[arg(
+        long,
+        help = "Comma-separated names of the image channels inserted in cellprofiler column names (e.g. DNA,Actin,Mito)."
+    )]
+    pub channel_names: Option<String>,
+
+    #[arg(
+        long,
+        help = "Append the polygon of each object as a polygon column. Requires parquet output (a .pq output file or --per-image-format pq)."
+    )]
+    pub include_geometry: bool,
+
+    #[arg(
+        long,
+        help = "Encoding of the polygon column (list, wkb). List writes [x, y] vertex lists (List<List<Float32>>) and wkb writes well-known binary polygons readable by GeoPandas.",
+        default_value = "list"
+    )]
+    pub geometry_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Maximum number of vertices of each polygon in the polygon column. Polygons with more vertices are resampled to equidistant vertices along their outline.",
+        default_value = "128"
+    )]
+    pub geometry_max_points: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Write a .provenance.json sidecar next to each output table or array."
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        long,
+        help = "Record sha256 hashes of input files in the provenance sidecar.",
+        requires = "provenance"
+    )]
+    pub hash_inputs: bool,
+
+    #[arg(
+        long,
+        help = "Write a separate descriptors/{image} table for each image as soon as it is profiled. Requires an output directory."
+    )]
+    pub per_image_output: bool,
+
+    #[arg(
+        long,
+        help = "Format of per-image output tables (csv, txt, tsv, pq, arrow, feather).",
+        default_value = "csv"
+    )]
+    pub per_image_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip images whose per-image output table already exists.",
+        requires = "per_image_output"
+    )]
+    pub resume: bool,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (tables, reports, and provenance sidecars) from the output location before profiling. Without it, output locations holding a previous run are rejected.",
+        conflicts_with = "resume"
+    )]
+    pub overwrite: bool,
+
+    #[arg(
+        long,
+        help = "Report the pairs, object counts, table width, and estimated output size as a table and in dry_run.json next to the output, without profiling or creating the output."
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        help = "Number of decoded images queued ahead of the compute threads. If 0, images are decoded by the compute threads.",
+        default_value = "0"
+    )]
+    pub prefetch: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Number of threads decoding images ahead of the compute threads if --prefetch is set.",
+        default_value = "2"
+    )]
+    pub io_threads: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Directory caching objects fetched from s3:// or https:// inputs. Cached objects are re-used by later runs. If not set, objects are fetched into a temporary directory and removed once their image is profiled."
+    )]
+    pub remote_cache: Option<String>,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
 
 pub fn profile_image_polygons(args: &ProfilePolygonsArgs) {
-    if let Some(threads) = args.threads.to_owned() {
-        if threads < 1 {
-            println!(
-                "[thyme::profile::polygons] Threads must be set to a positive integer if provided."
-            );
-            std::process::exit(1);
-        }
+    if let Err(err) = run_profile_polygons(&ProfilePolygonsConfig::from(args)) {
+        eprintln!("[thyme::profile::polygons] ERROR: {}", err);
+        std::process::exit(1);
+    }
+}
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
+impl From<&ProfilePolygonsArgs> for ProfilePolygonsConfig {
+    fn from(args: &ProfilePolygonsArgs) -> Self {
+        let defaults = ProfilePolygonsConfig::default();
+
+        ProfilePolygonsConfig {
+            images: args.images.to_owned().unwrap_or_default(),
+            polygons: args.polygons.to_owned(),
+            output: args.output.to_owned().unwrap_or_default(),
+            verbose: args.verbose,
+            tui: args.tui,
+            log_format: args.log_format.to_owned().unwrap_or(defaults.log_format),
+            drop_borders: args.drop_borders,
+            border_policy: args
+                .border_policy
+                .to_owned()
+                .unwrap_or(defaults.border_policy),
+            nonfinite_policy: args
+                .nonfinite_policy
+                .to_owned()
+                .unwrap_or(defaults.nonfinite_policy),
+            mode: args.mode.to_owned().unwrap_or(defaults.mode),
+            background_style: args
+                .background_style
+                .to_owned()
+                .unwrap_or(defaults.background_style),
+            pad: args.pad.unwrap_or(defaults.pad),
+            image_substring: args.image_substring.to_owned(),
+            polygon_substring: args.polygon_substring.to_owned(),
+            image_glob: args.image_glob.to_owned(),
+            image_regex: args.image_regex.to_owned(),
+            polygon_glob: args.polygon_glob.to_owned(),
+            polygon_regex: args.polygon_regex.to_owned(),
+            segment_coords: args
+                .segment_coords
+                .to_owned()
+                .unwrap_or(defaults.segment_coords),
+            segment_transform: args.segment_transform.to_owned(),
+            min_size: args.min_size.unwrap_or(defaults.min_size),
+            min_area: args.min_area,
+            max_area: args.max_area,
+            max_points_per_object: args.max_points_per_object,
+            oversized_policy: args
+                .oversized_policy
+                .to_owned()
+                .unwrap_or(defaults.oversized_policy),
+            max_seconds_per_object: args.max_seconds_per_object,
+            skip_image_if: args.skip_image_if.to_owned(),
+            skip_invalid_polygons: args.skip_invalid_polygons,
+            membrane_width: args.membrane_width,
+            centroid_displacement: args.centroid_displacement,
+            extended_intensity: args.extended_intensity,
+            approx_percentiles_over: args.approx_percentiles_over,
+            features_channels: args.features_channels.to_owned(),
+            channel_pairs: args.channel_pairs.to_owned(),
+            texture_stride: args.texture_stride.unwrap_or(defaults.texture_stride),
+            texture_auto_stride: args.texture_auto_stride,
+            measure_device: args
+                .measure_device
+                .to_owned()
+                .unwrap_or(defaults.measure_device),
+            texture_scales: args.texture_scales.to_owned(),
+            thumbnails: args.thumbnails.to_owned(),
+            thumbnail_size: args.thumbnail_size.unwrap_or(defaults.thumbnail_size),
+            preview: args.preview,
+            preview_rescale: args.preview_rescale,
+            metadata_table: args.metadata_table.to_owned(),
+            metadata_join: args.metadata_join.to_owned(),
+            well_from_filename: args.well_from_filename.to_owned(),
+            strict_join: args.strict_join,
+            ipc_compression: args
+                .ipc_compression
+                .to_owned()
+                .unwrap_or(defaults.ipc_compression),
+            float_precision: args.float_precision,
+            float_format: args
+                .float_format
+                .to_owned()
+                .unwrap_or(defaults.float_format),
+            column_style: args
+                .column_style
+                .to_owned()
+                .unwrap_or(defaults.column_style),
+            channel_names: args.channel_names.to_owned(),
+            include_geometry: args.include_geometry,
+            geometry_format: args
+                .geometry_format
+                .to_owned()
+                .unwrap_or(defaults.geometry_format),
+            geometry_max_points: args
+                .geometry_max_points
+                .unwrap_or(defaults.geometry_max_points),
+            provenance: args.provenance,
+            hash_inputs: args.hash_inputs,
+            per_image_output: args.per_image_output,
+            per_image_format: args
+                .per_image_format
+                .to_owned()
+                .unwrap_or(defaults.per_image_format),
+            resume: args.resume,
+            overwrite: args.overwrite,
+            dry_run: args.dry_run,
+            prefetch: args.prefetch.unwrap_or(defaults.prefetch),
+            io_threads: args.io_threads.unwrap_or(defaults.io_threads),
+            remote_cache: args.remote_cache.to_owned(),
+            threads: args.threads,
+        }
     }
+}
 
-    let mode = args.mode.to_owned().unwrap_or("cmbfpx".to_string());
-    let pad = args.pad.unwrap_or(1);
-    let min_size = args.min_size.unwrap_or(1);
+/// Profile image and polygon pairs as configured
+///
+/// Images that fail to profile are reported in `object_errors.tsv` when
+/// writing to an output directory and are counted in the returned summary.
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the run
+pub fn run_profile_polygons(config: &ProfilePolygonsConfig) -> Result<ProfileSummary, ThymeError> {
+    config.validate()?;
+    ut::threads::install(config.threads, || run(config))
+}
 
-    if mode
-        .chars()
-        .any(|c| !matches!(c, 'c' | 'm' | 'b' | 'f' | 'p' | 'x'))
-    {
-        eprintln!(
-            "[thyme::profile::polygons] Invalid mode. Argument mode must only contain one or more of: c, f, b, m, p, x."
-        );
-        std::process::exit(1);
-    }
+/// Run the command on the thread pool selected by `run_profile_polygons`
+fn run(config: &ProfilePolygonsConfig) -> Result<ProfileSummary, ThymeError> {
+    let mode = config.mode.to_owned();
+    let pad = config.pad;
 
-    if min_size < 1 {
-        eprintln!("[thyme::profile::polygons] ERROR: min_size cannot be less than 1.0.");
-        std::process::exit(1);
-    }
+    let border_policy = match config.drop_borders {
+        true => BorderPolicy::Drop,
+        false => BorderPolicy::parse(&config.border_policy).map_err(super::invalid)?,
+    };
 
-    let image_path = args.images.to_owned().unwrap();
-    let polygons_path = args.polygons.to_owned().unwrap_or(image_path.clone());
+    let segment_coords = im::SegmentCoords::parse(&config.segment_coords)?;
+    let background_style = im::BackgroundStyle::parse(&config.background_style)?;
 
-    if image_path == polygons_path && args.image_substring == args.polygon_substring {
-        eprintln!(
-            "[thyme::profile::polygons] ERROR: If images and polygons are located in same path, different image and polygon substrings must be provided."
-        );
-        std::process::exit(1);
-    }
+    let nonfinite_policy =
+        NonFinitePolicy::parse(&config.nonfinite_policy).map_err(super::invalid)?;
+
+    let prefetch = Prefetch::new(Some(config.prefetch), Some(config.io_threads))?;
+
+    let filter = ObjectFilter {
+        border_policy,
+        min_size: config.min_size,
+        min_area: config.min_area,
+        max_area: config.max_area,
+        skip_invalid_polygons: config.skip_invalid_polygons,
+    };
+
+    filter.validate().map_err(super::invalid)?;
+
+    let budget = ObjectBudget {
+        max_points: config.max_points_per_object,
+        oversized: OversizedPolicy::parse(&config.oversized_policy).map_err(super::invalid)?,
+        max_seconds: config.max_seconds_per_object,
+    };
+
+    let gate = filter::ImageGate::new(config.skip_image_if.as_deref())?;
+
+    let texture_stride = config.texture_stride;
+    let texture_scales = table::texture_scales(config.texture_scales.as_deref())?;
+
+    let reduce = MeasureDevice::parse(&config.measure_device)?.reducer(DEVICE_MIN_SUBPIXELS)?;
+
+    // Strides are only recorded when texture descriptors are computed
+    let stride_column = config.verbose && mode.contains(['c', 'f', 'b']);
+
+    let thumbnails = Thumbnails::new(config.thumbnails.as_deref(), Some(config.thumbnail_size))?;
+    let preview = Preview::new(config.preview, config.preview_rescale)?;
 
-    let image_files = ut::path::collect_file_paths(
-        &image_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.image_substring.to_owned(),
+    let ipc_compression = config.ipc_compression.to_owned();
+    let float_format = io::FloatFormat::new(config.float_precision, &config.float_format)?;
+
+    // Polygons are kept with the descriptors if written to a polygon column
+    let geometry = config
+        .include_geometry
+        .then(|| io::GeometryFormat::parse(&config.geometry_format))
+        .transpose()?
+        .map(|format| (format, config.geometry_max_points));
+
+    let image_path = config.images.to_owned();
+    let polygons_path = config.polygons_path().to_owned();
+
+    let remote = RemoteInputs::new(config.remote_cache.as_deref());
+
+    let image_files = ut::path::FileSelector::new(
+        config.image_substring.as_deref(),
+        config.image_glob.as_deref(),
+        config.image_regex.as_deref(),
     )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    .and_then(|selector| {
+        remote.select_file_paths(
+            &image_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
 
-    let polygon_files = ut::path::collect_file_paths(
-        &polygons_path,
-        constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
-        args.polygon_substring.to_owned(),
+    let polygon_files = ut::path::FileSelector::new(
+        config.polygon_substring.as_deref(),
+        config.polygon_glob.as_deref(),
+        config.polygon_regex.as_deref(),
     )
-    .unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        std::process::exit(1);
-    });
+    .and_then(|selector| {
+        remote.select_file_paths(
+            &polygons_path,
+            constant::SUPPORTED_ARRAY_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })?;
 
     if image_files.is_empty() {
-        eprintln!(
-            "[thyme::profile::polygons] ERROR: No image files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No image files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
     if polygon_files.is_empty() {
-        eprintln!(
-            "[thyme::profile::polygons] ERROR: No polygon files were detected. Please check your path and/or substring identifier."
-        );
-        std::process::exit(1);
+        return Err(ThymeError::OtherError(
+            "No polygon files were detected. Please check your path and/or substring identifier"
+                .to_string(),
+        ));
     }
 
     let mut pairs = ut::path::collect_file_pairs(
         &image_files,
         &polygon_files,
-        args.image_substring.to_owned(),
-        args.polygon_substring.to_owned(),
+        config.image_substring.to_owned(),
+        config.polygon_substring.to_owned(),
     );
 
     pairs.sort_unstable();
 
+    // Channels of the first image are checked before profiling
+    if let (Some(_), Some((_, image, _))) = (&config.features_channels, pairs.first()) {
+        remote.fetch(image)?;
+    }
+
+    let feature_channels = table::feature_channels(
+        config.features_channels.as_deref(),
+        pairs.first().map(|(_, image, _)| image.as_path()),
+    )?;
+
+    let channel_pairs = config
+        .channel_pairs
+        .as_deref()
+        .map(im::ChannelPairs::parse)
+        .transpose()?;
+
+    // Transforms are resolved before processing so that images without a
+    // transform are reported without waiting for any image to be profiled
+    let transforms = config
+        .segment_transform
+        .as_ref()
+        .map(SegmentTransforms::open)
+        .transpose()?;
+
+    if let Some(transforms) = &transforms {
+        for (id, image, _) in &pairs {
+            transforms.get(id, image)?;
+        }
+    }
+
     ut::track::progress_log(
         &format!(
             "Detected {} image and polygon pairs.",
             ut::track::thousands_format(pairs.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    let mut output = PathBuf::from(args.output.to_owned().unwrap());
-
-    let extension = output
-        .extension()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_lowercase());
-
-    if let Some(ext) = extension {
-        if !["csv", "txt", "tsv", "pq"].iter().any(|e| e == &ext) {
-            eprintln!(
-                "[thyme::profile::polygons] ERROR: Invalid file extension. Must end with one of .csv, .txt, .tsv, .pq."
-            );
-            std::process::exit(1);
-        }
+    let metadata = config
+        .metadata_table
+        .as_ref()
+        .map(|path| {
+            let on = config.metadata_join.as_deref().unwrap();
+            let metadata = io::read_table(path)?;
+
+            // Resolve join keys before processing so that invalid patterns or unmatched
+            // images are reported without waiting for all the objects to be computed
+            let images: Vec<String> = pairs.iter().map(|(_, image, _)| stem(image)).collect();
+
+            let mut df = DataFrame::new(vec![Column::new("image".into(), &images)]).unwrap();
+
+            let missing = io::join_metadata(
+                &mut df,
+                "image",
+                &metadata,
+                on,
+                config.well_from_filename.as_deref(),
+            )?;
+
+            if missing > 0 && config.strict_join {
+                return Err(ThymeError::OtherError(format!(
+                    "{} images have no matching row in the metadata table",
+                    missing
+                )));
+            }
 
-        if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if missing > 0 {
                 eprintln!(
-                    "[thyme::profile::polygons] ERROR: Invalid file path. Parent directory of output file path does not exist."
+                    "[thyme::profile::polygons] WARNING: {} images have no matching row in the metadata table. Metadata columns will be null for these images.",
+                    missing
                 );
-                std::process::exit(1);
             }
-        }
-    } else {
-        output = ut::path::create_directory(&output).unwrap_or_else(|_| {
-            eprintln!("[thyme::profile::polygons] ERROR: Could not create directory.");
-            std::process::exit(1);
+
+            Ok(metadata)
+        })
+        .transpose()?;
+
+    let column_style = catalog::ColumnStyle::parse(&config.column_style)?;
+    let channel_names = config
+        .channel_names
+        .as_deref()
+        .map(catalog::parse_channel_names)
+        .transpose()?
+        .unwrap_or_default();
+
+    let profile_columns = |channels: usize| catalog::ProfileColumns {
+        segments: catalog::Segments::Polygons,
+        mode: mode.clone(),
+        membrane: config.membrane_width.is_some(),
+        extended_intensity: config.extended_intensity,
+        centroid_channels: Some(channels).filter(|_| config.centroid_displacement),
+        texture_stride: stride_column,
+        feature_channels: feature_channels.clone(),
+        texture_scales: texture_scales.clone(),
+        channel_pairs: channel_pairs
+            .as_ref()
+            .map(|pairs| pairs.resolve(channels))
+            .unwrap_or_default(),
+        preview: preview.is_some(),
+        column_style,
+        channel_names: channel_names.clone(),
+        ..Default::default()
+    };
+
+    let descriptor_columns = |channels: usize| profile_columns(channels).descriptor_names();
+
+    if config.dry_run {
+        let images = dryrun::scan(&pairs, pad, |id, image, polygons, info| {
+            let transform = match &transforms {
+                Some(transforms) => Some(transforms.get(id, image)?),
+                None => None,
+            };
+
+            dryrun::polygon_boxes(polygons, segment_coords, transform, info)
         });
+
+        return super::dry_run(
+            "profile::polygons",
+            &config.output,
+            Some(config.per_image_format.as_str()).filter(|_| config.per_image_output),
+            config.float_precision,
+            images,
+            |channels| profile_columns(channels).columns().len(),
+        );
     }
 
-    let pb = ut::track::progress_bar(pairs.len(), "Profiling", args.verbose);
+    let output = super::output_path(
+        &config.output,
+        OutputMode::new(config.overwrite, config.resume)?,
+        &config.inputs(),
+        "profile::polygons",
+    )?;
+
+    let per_image = config
+        .per_image_output
+        .then(|| {
+            if !output.is_dir() {
+                return Err(ThymeError::OtherError(
+                    "per_image_output requires an output directory".to_string(),
+                ));
+            }
+
+            PerImageOutput::new(
+                &output,
+                "descriptors",
+                &config.per_image_format,
+                &ipc_compression,
+                float_format,
+                config.resume,
+                metadata.clone().map(|table| MetadataJoin {
+                    table,
+                    on: config.metadata_join.to_owned().unwrap(),
+                    well_from_filename: config.well_from_filename.to_owned(),
+                }),
+            )
+        })
+        .transpose()?;
+
+    if let Some(per_image) = &per_image {
+        let n = pairs.len();
+        pairs.retain(|(_, image, _)| !per_image.skip(&stem(image)));
+
+        ut::track::progress_log(
+            &format!(
+                "Skipping {} images with existing per-image tables.",
+                ut::track::thousands_format(n - pairs.len())
+            ),
+            config.verbose && config.resume,
+        );
+    }
 
-    let objects: Mutex<usize> = Mutex::new(0);
-    let success: Mutex<Vec<String>> = Mutex::new(vec![]);
-    let failure: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
+    let options = ProfileOptions {
+        segment_coords,
+        pad,
+        filter,
+        budget,
+        gate: gate.as_ref(),
+        mode: &mode,
+        background_style,
+        membrane_width: config.membrane_width,
+        centroid_displacement: config.centroid_displacement,
+        channel_pairs: channel_pairs.as_ref(),
+        extended_intensity: config.extended_intensity,
+        approx_percentiles_over: config.approx_percentiles_over,
+        feature_channels: feature_channels.as_ref(),
+        texture_stride,
+        texture_auto_stride: config.texture_auto_stride,
+        reduce: reduce.as_deref(),
+        texture_scales: &texture_scales,
+        stride_column,
+        thumbnails: thumbnails.as_ref(),
+        preview: preview.as_ref(),
+        geometry,
+    };
+
+    let progress = Progress::new(
+        pairs.len(),
+        "Profiling",
+        config.verbose,
+        config.tui,
+        &config.log_format,
+    );
+    let status = progress.status();
+
+    // Results are collected in input order so that the output table does not
+    // depend on the number of threads used for profiling
+    let runs: Vec<Result<ImageProfile, ThymeError>> = prefetch.run(
+        &pairs,
+        progress,
+        |(_, image, polygons)| {
+            remote.fetch(image)?;
+            remote.fetch(polygons)?;
+            im::ThymeImage::open(image)
+        },
+        |(id, image_path, polygons), image| {
+            let _staged = remote.staged(&[image_path, polygons]);
+            status.begin(id);
+
+            let transform = match &transforms {
+                Some(transforms) => Some(transforms.get(id, image_path)?),
+                None => None,
+            };
+
+            let mut profiled = profile(image, polygons, transform, &options, &stem(image_path))?;
+
+            // Objects measured over their time budget are missing trailing columns
+            if profiled.3.is_none() && !profiled.0.ids.is_empty() {
+                profiled
+                    .0
+                    .pad_columns(descriptor_columns(profiled.0.channels).len());
+            }
 
-    let name: Mutex<Vec<String>> = Mutex::new(Vec::with_capacity(pairs.len()));
-    let item: Mutex<Vec<u32>> = Mutex::new(Vec::with_capacity(pairs.len()));
-    let data: Mutex<Vec<Vec<f32>>> = Mutex::new(Vec::with_capacity(300 * pairs.len()));
+            profiled.0.sanitize(nonfinite_policy);
 
-    (0..pairs.len())
-        .into_par_iter()
-        .tqdm_with_bar(pb)
-        .for_each(|idx| {
-            let (id, image, polygons) = &pairs[idx];
-            let run = profile(image, polygons, pad, args.drop_borders, min_size, &mode);
+            if let Some(preview) = &preview {
+                let exponents = profile_columns(profiled.0.channels).descriptor_exponents();
+                preview.finish(&mut profiled.0, &exponents);
+            }
 
-            if let Ok((ids, descriptors)) = run {
-                let n = ids.len();
+            // Tables are written as soon as an image is profiled so that only
+            // object ids are kept in memory until the counts are written
+            if let (Some(per_image), None) = (&per_image, &profiled.3) {
+                let columns = descriptor_columns(profiled.0.channels);
+                table::write_image(per_image, &stem(image_path), &mut profiled.0, &columns)?;
+            }
 
-                success.lock().unwrap().push(format!("{}\t{}", id, n));
+            if profiled.3.is_none() {
+                status.add_objects(profiled.0.ids.len());
+            }
 
-                let image = image.file_stem().unwrap().to_string_lossy().to_string();
+            Ok(profiled)
+        },
+    );
 
-                name.lock().unwrap().extend((0..n).map(|_| image.clone()));
-                item.lock().unwrap().extend(ids);
-                data.lock().unwrap().extend(descriptors);
-                *objects.lock().unwrap() += n;
-            } else {
-                failure
-                    .lock()
-                    .unwrap()
-                    .push(format!("{}\t{}", id, run.unwrap_err()));
+    let mut objects: usize = 0;
+    let mut success: Vec<String> = vec![];
+    let mut failure: Vec<String> = Vec::with_capacity(pairs.len());
+    let mut failed_objects: Vec<String> = vec![];
+    let mut report = NonFiniteReport::default();
+
+    let mut name: Vec<String> = vec![];
+    let mut data: Vec<Descriptors> = Vec::with_capacity(pairs.len());
+    let mut channels: Option<usize> = None;
+    let mut summaries: Vec<SummaryRow> = Vec::with_capacity(pairs.len());
+    let mut skipped: Vec<String> = vec![];
+
+    for ((id, image, _), run) in pairs.iter().zip(runs) {
+        match run {
+            Ok((_, _, image_summary, Some(reason))) => {
+                ut::track::progress_log(&format!("{}: skipped ({}).", id, reason), config.verbose);
+                skipped.push(filter::skipped_images_row(id, &reason, &image_summary));
             }
-        });
+            Ok((descriptors, dropped, image_summary, None)) => {
+                if (config.centroid_displacement || channel_pairs.is_some()) && per_image.is_none()
+                {
+                    if let Err(err) = table::check_channels(&mut channels, &descriptors) {
+                        failure.push(format!("{}\t{}", id, err));
+                        continue;
+                    }
+                }
+
+                let n = descriptors.ids.len();
+
+                failed_objects.extend(descriptors.failed.rows(id));
+
+                report.add(
+                    &descriptor_columns(descriptors.channels),
+                    &descriptors.nonfinite,
+                );
+                success.push(filter::object_counts_row(id, n, &dropped));
 
-    let objects = objects.into_inner().unwrap();
-    let success = success.into_inner().unwrap();
-    let failure = failure.into_inner().unwrap();
+                let image = stem(image);
 
-    let name = name.into_inner().unwrap();
-    let item = item.into_inner().unwrap();
-    let data = data.into_inner().unwrap();
+                summaries.push(SummaryRow {
+                    image: image.clone(),
+                    objects: n,
+                    summary: image_summary,
+                });
 
-    if args.verbose {
+                if per_image.is_none() {
+                    name.extend((0..n).map(|_| image.clone()));
+                    data.push(descriptors);
+                }
+
+                objects += n;
+            }
+            Err(err) => failure.push(format!("{}\t{}", id, err)),
+        }
+    }
+
+    if config.verbose {
         println!();
     }
 
@@ -251,34 +1017,24 @@ pub fn profile_image_polygons(args: &ProfilePolygonsArgs) {
             ut::track::thousands_format(objects),
             ut::track::thousands_format(success.len())
         ),
-        args.verbose,
+        config.verbose,
     );
 
-    if !success.is_empty() {
-        let columns = descriptor_columns(&mode);
-
-        let mut df = DataFrame::new(vec![
-            Column::new("image".into(), &name),
-            Column::new("object".into(), &item),
-        ])
-        .unwrap();
-
-        // Note that this requires generating two copies of the computed descriptors
-        // which is definitely not ideal. We probaby want to redesign the computation
-        // so that column-major data is generated directly or we just use a flat buffer
-        // and then just handle the saving with indexing. Also look into the polars API.
-        let mut column_data: Vec<Vec<f32>> = vec![Vec::with_capacity(data.len()); data[0].len()];
-
-        for row in &data {
-            for (idx, &descriptor) in row.iter().enumerate() {
-                column_data[idx].push(descriptor);
-            }
-        }
-
-        for (column, descriptor) in columns.iter().zip(column_data) {
-            df.with_column(Column::new(column.into(), descriptor))
-                .unwrap();
+    let mut descriptors = None;
+
+    if let Some(per_image) = &per_image {
+        if config.provenance {
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                None,
+                config.hash_inputs,
+                &[per_image.directory()],
+            )?;
         }
+    } else if !success.is_empty() {
+        let columns = descriptor_columns(channels.unwrap_or_default());
+        let mut df = table::dataframe(&name, data, &columns);
 
         let descriptors_path = if output.is_dir() {
             output.join("descriptors.csv")
@@ -286,181 +1042,938 @@ pub fn profile_image_polygons(args: &ProfilePolygonsArgs) {
             output.clone()
         };
 
-        io::write_table(&mut df, descriptors_path).unwrap_or_else(|_| {
-            eprintln!("[thyme::profile::polygons] ERROR: Failed to write descriptors table.");
-            std::process::exit(1);
-        });
-    }
-
-    if output.is_dir() {
-        if !success.is_empty() {
-            std::fs::write(output.join("object_counts.tsv"), success.join("\n")).unwrap();
+        if let Some(metadata) = &metadata {
+            io::join_metadata(
+                &mut df,
+                "image",
+                metadata,
+                config.metadata_join.as_deref().unwrap(),
+                config.well_from_filename.as_deref(),
+            )?;
         }
 
-        if !failure.is_empty() {
-            std::fs::write(output.join("object_errors.tsv"), failure.join("\n")).unwrap();
+        io::write_table_with_float_format(
+            &mut df,
+            &descriptors_path,
+            &ipc_compression,
+            &float_format,
+        )
+        .map_err(|err| {
+            ThymeError::OtherError(format!("Failed to write descriptors table: {}", err))
+        })?;
+
+        if config.provenance {
+            provenance::record(
+                config,
+                &provenance::paired_inputs(&pairs, config.metadata_table.as_deref()),
+                None,
+                config.hash_inputs,
+                &[&descriptors_path],
+            )?;
         }
+
+        descriptors = Some(descriptors_path);
     }
+
+    if !report.is_empty() {
+        eprintln!(
+            "[thyme::profile::polygons] WARNING: {} objects have non-finite values in {} descriptor columns ({}).",
+            ut::track::thousands_format(report.objects()),
+            report.len(),
+            nonfinite_policy.describe()
+        );
+    }
+
+    if !failed_objects.is_empty() {
+        eprintln!(
+            "[thyme::profile::polygons] WARNING: {} objects failed to profile and have no descriptors (see failed_objects.tsv).",
+            ut::track::thousands_format(failed_objects.len())
+        );
+    }
+
+    if output.is_dir() {
+        super::write_reports(
+            &output,
+            &report,
+            &success,
+            &summaries,
+            &skipped,
+            &failure,
+            &failed_objects,
+        )?;
+    }
+
+    crate::output::finish_run(&output)?;
+
+    Ok(ProfileSummary {
+        images: success.len(),
+        failures: failure.len(),
+        skipped: skipped.len(),
+        objects,
+        failed_objects: failed_objects.len(),
+        output,
+        descriptors,
+        dry_run: None,
+    })
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().to_string()
+}
+
+/// Descriptors, dropped object counts, image summary, and the reason the image
+/// was skipped by an image gate for a single image
+type ImageProfile = (Descriptors, DropCounts, im::ImageSummary, Option<String>);
+
+/// Options shared by every image and polygons pair profiled by a run
+#[derive(Clone, Copy, Default)]
+struct ProfileOptions<'a> {
+    /// Coordinate system of the polygon vertices
+    segment_coords: im::SegmentCoords,
+    /// Padding added to each side of the bounding box of an object
+    pad: u32,
+    /// Filters applied to each object before it is measured
+    filter: ObjectFilter,
+    /// Per-object vertex and time budget
+    budget: ObjectBudget,
+    /// Image-level gate evaluated before any object is measured
+    gate: Option<&'a filter::ImageGate>,
+    /// Descriptor families to compute
+    mode: &'a str,
+    /// Region used to measure background intensity
+    background_style: im::BackgroundStyle,
+    /// Width of the boundary band of membrane descriptors (pixels)
+    membrane_width: Option<u32>,
+    /// Compute intensity-weighted centroid displacements
+    centroid_displacement: bool,
+    /// Channel pairs measured as intensity ratios
+    channel_pairs: Option<&'a im::ChannelPairs>,
+    /// If true, compute the extended intensity descriptors
+    extended_intensity: bool,
+    /// Positive subpixels of a channel above which percentiles are selected without a full sort
+    approx_percentiles_over: Option<usize>,
+    /// Channels measured by the intensity descriptors
+    feature_channels: Option<&'a im::FeatureChannels>,
+    /// Stride of texture co-occurrence anchors
+    texture_stride: usize,
+    /// Object size above which the texture stride is escalated (pixels)
+    texture_auto_stride: Option<usize>,
+    /// Reduction applied to channels before measuring intensity
+    reduce: Option<&'a dyn Reduce>,
+    /// Scales at which texture descriptors are measured
+    texture_scales: &'a [u32],
+    /// If true, record the texture stride used for each object
+    stride_column: bool,
+    /// Object thumbnails written alongside the descriptors
+    thumbnails: Option<&'a Thumbnails>,
+    /// Downsampling applied to images and polygons before profiling
+    preview: Option<&'a Preview>,
+    /// Encoding and maximum number of vertices of the polygon column
+    geometry: Option<(io::GeometryFormat, usize)>,
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Profile an image and its polygons
 fn profile(
-    image_path: &Path,
+    image: im::ThymeImage,
     polygons_path: &Path,
-    pad: u32,
-    drop_borders: bool,
-    min_size: u32,
-    mode: &str,
-) -> Result<(Vec<u32>, Vec<Vec<f32>>), ThymeError> {
-    let image = im::ThymeImage::open(image_path)?;
-
-    let mut polygons = im::Polygons::open(polygons_path)?;
-    let bounding_boxes = polygons.to_bounding_boxes()?;
-
-    let mut polygon_descriptors = Vec::new();
-    if mode.contains("p") {
-        polygon_descriptors = polygons.descriptors();
+    transform: Option<&AffineTransform>,
+    options: &ProfileOptions,
+    name: &str,
+) -> Result<ImageProfile, ThymeError> {
+    let ProfileOptions {
+        segment_coords,
+        pad,
+        filter,
+        budget,
+        gate,
+        mode,
+        background_style,
+        membrane_width,
+        centroid_displacement,
+        channel_pairs,
+        extended_intensity,
+        approx_percentiles_over,
+        feature_channels,
+        texture_stride,
+        texture_auto_stride,
+        reduce,
+        texture_scales,
+        stride_column,
+        thumbnails,
+        preview,
+        geometry,
+    } = *options;
+
+    // Previews measure objects on the downsampled image and scaled polygons
+    let image = match preview {
+        Some(preview) => preview.image(&image)?,
+        None => image,
+    };
+
+    if let Some(feature_channels) = feature_channels {
+        feature_channels.check(image.channels() as usize)?;
     }
 
+    let pairs = match channel_pairs {
+        Some(channel_pairs) => {
+            channel_pairs.check(image.channels() as usize)?;
+            channel_pairs.resolve(image.channels() as usize)
+        }
+        None => vec![],
+    };
+
     let width = image.width();
     let height = image.height();
 
-    let pad_f32 = pad as f32;
+    let mut descriptors = Descriptors::default();
+    descriptors.channels = image.channels() as usize;
+    let mut dropped = DropCounts::default();
+    let mut border_flags: Vec<bool> = vec![];
+    let mut thumbnail_paths: Vec<Option<String>> = vec![];
+    let mut outlines: Vec<Vec<[f32; 2]>> = vec![];
+    let mut degenerate = 0;
+
+    let regions = im::Regions::from_mode(mode);
+
+    // Polygons are only rasterized into a mask if a descriptor reads the mask,
+    // which includes reductions on another device that take the mask of the object
+    let needs_mask = regions.foreground
+        || regions.background
+        || mode.contains("m")
+        || mode.contains("s")
+        || membrane_width.is_some()
+        || centroid_displacement
+        || !pairs.is_empty()
+        || !texture_scales.is_empty()
+        || (reduce.is_some() && regions.complete);
+
+    // Union of every polygon, including filtered polygons, for the background
+    // statistics of the image summary. Image gates and ring backgrounds read
+    // every polygon so the union is drawn before any polygon is measured.
+    let ring = matches!(background_style, im::BackgroundStyle::Ring(_)) && regions.background;
+
+    let mut foreground = match gate.is_some() || ring {
+        true => polygon_union(
+            polygons_path,
+            segment_coords,
+            transform,
+            width,
+            height,
+            preview,
+            &budget,
+        )?,
+        false => vec![0u32; width as usize * height as usize],
+    };
+
+    if let Some(gate) = gate {
+        let image_summary = im::ImageSummary::new(&image, &foreground)?;
+
+        if let Some(reason) = gate.check(&image_summary) {
+            return Ok((descriptors, dropped, image_summary, Some(reason)));
+        }
+    }
 
-    let mut ids: Vec<u32> = Vec::with_capacity(bounding_boxes.len());
-    let mut results: Vec<Vec<f32>> = Vec::with_capacity(300 * bounding_boxes.len());
+    // Polygons are streamed one at a time so that files with millions of
+    // polygons are never held in memory all at once
+    for (idx, points) in io::PolygonStream::open(polygons_path)?.enumerate() {
+        let mut points = points?;
 
-    for (idx, [min_x, min_y, max_x, max_y]) in bounding_boxes.as_xyxy().iter().enumerate() {
-        let min_x = min_x - pad_f32;
-        let min_y = min_y - pad_f32;
-        let max_x = max_x + pad_f32;
-        let max_y = max_y + pad_f32;
+        // Vertices are registered to the original image before any preview scaling
+        if let Some(transform) = transform {
+            transform.apply_points(&mut points);
+        }
 
-        if drop_borders
-            && (min_x <= 0.0 || min_y <= 0.0 || max_x >= width as f32 || max_y >= height as f32)
-        {
-            continue;
+        // Pixel vertices are scaled to the preview while normalized vertices
+        // are denormalized to the size of the preview when measured
+        if let (Some(preview), im::SegmentCoords::Pixels) = (preview, segment_coords) {
+            preview.points(&mut points);
         }
 
-        let min_x = min_x.max(0.0) as u32;
-        let min_y = min_y.max(0.0) as u32;
-        let max_x = max_x.min(width as f32) as u32;
-        let max_y = max_y.min(height as f32) as u32;
+        let bounds = failures::bounds(&points);
 
-        let w = max_x - min_x;
-        let h = max_y - min_y;
+        // Oversized outlines are resampled or skipped before any quadratic
+        // validation or measurement of their vertices
+        let resampled = match budget.check_points(&mut points) {
+            Ok(resampled) => resampled,
+            Err(err) => {
+                descriptors.failed.add(idx as u32, bounds, err);
+                continue;
+            }
+        };
 
-        if w < min_size || h < min_size {
-            continue;
-        }
+        // Polygons with too few or non-finite vertices fail on their own
+        let mut polygon = match im::Polygons::new(vec![points]) {
+            Ok(polygon) => polygon,
+            Err(err) => {
+                let err = ObjectError {
+                    stage: Stage::Crop,
+                    message: err.to_string(),
+                };
+
+                descriptors.failed.add(idx as u32, bounds, err);
+                continue;
+            }
+        };
 
-        let mut result: Vec<f32> = Vec::with_capacity(100);
+        let invalid = !polygon.validate_and_repair().degenerate.is_empty();
 
-        let mask_buffer = im::ThymeMask::new(
-            w,
-            h,
-            1,
-            draw_centered_points(w, h, &polygons.as_points()[idx], 1, pad),
-        )
-        .unwrap();
+        if segment_coords == im::SegmentCoords::Normalized {
+            polygon.denormalize(width, height)?;
+        }
 
-        let mask_object = im::ThymeMaskView::new(0, 0, w, h, &mask_buffer);
+        draw_points_mut(&mut foreground, width, height, &polygon.as_points()[0], 1);
 
-        if mode.contains("p") {
-            result.extend(polygon_descriptors[idx]);
+        if invalid {
+            degenerate += 1;
         }
 
-        if mode.contains("c") {
-            result.extend(image.crop_view(min_x, min_y, w, h).descriptors());
+        if let Err(reason) = filter.check_polygon(invalid) {
+            dropped.add(reason);
+            continue;
         }
 
-        if mode.contains("f") {
-            result.extend(
-                image
-                    .crop_masked(
-                        min_x,
-                        min_y,
-                        w,
-                        h,
-                        &mask_object,
-                        im::MaskingStyle::Foreground,
-                    )?
-                    .crop_view(0, 0, w, h)
-                    .descriptors(),
-            );
+        let bounding_box = polygon.to_bounding_boxes()?;
+        let xyxy = &bounding_box.as_xyxy()[0];
+
+        if let Err(reason) = filter.check(xyxy, polygon.areas()[0], width, height) {
+            dropped.add(reason);
+            continue;
         }
 
-        if mode.contains("b") {
-            result.extend(
-                image
-                    .crop_masked(
+        let Some(CropRect {
+            x: min_x,
+            y: min_y,
+            width: w,
+            height: h,
+        }) = crop_rect(xyxy, pad, width, height)
+        else {
+            dropped.add(DropReason::OutsideImage);
+            continue;
+        };
+
+        // Outlines are kept before polygon descriptors re-order their vertices
+        let outline =
+            geometry.map(|(_, max_points)| table::outline(&polygon.as_points()[0], max_points));
+
+        // Each object is measured in isolation so that a failure is recorded
+        // and the remaining polygons of the image are still profiled
+        let mut watchdog = budget.watchdog();
+        let measured = failures::catch(|stage| {
+            // Polygon descriptors dedup and order points so they are computed
+            // before the polygon is drawn into a mask
+            *stage = Stage::Measure;
+            let polygon_descriptors = if mode.contains("p") {
+                Some(polygon.descriptors()[0])
+            } else {
+                None
+            };
+
+            let mut result: Vec<f32> = Vec::with_capacity(100);
+
+            let stride =
+                texture::auto_stride((w * h) as usize, texture_stride, texture_auto_stride);
+
+            *stage = Stage::Rasterize;
+            let mask_buffer = needs_mask.then(|| rasterize(w, h, &polygon.as_points()[0], pad));
+            let mask_object = mask_buffer
+                .as_ref()
+                .map(|mask_buffer| im::ThymeMaskView::new(0, 0, w, h, mask_buffer));
+
+            *stage = Stage::Measure;
+            result.extend(table::location(
+                xyxy,
+                Some(form::centroid(&polygon.as_points()[0])),
+            ));
+
+            if let Some(polygon_descriptors) = polygon_descriptors {
+                result.extend(polygon_descriptors);
+            }
+
+            // Descriptor families are skipped once the object exceeds its time
+            // budget, leaving the row truncated after the last measured family
+
+            // Ring backgrounds extend past the crop so they are measured separately
+            let ring = match (background_style, &mask_buffer) {
+                (im::BackgroundStyle::Ring(radius), Some(mask_buffer))
+                    if regions.background && !watchdog.expired() =>
+                {
+                    Some(mask_buffer.background_ring(
                         min_x,
                         min_y,
-                        w,
-                        h,
-                        &mask_object,
-                        im::MaskingStyle::Background,
-                    )?
-                    .crop_view(0, 0, w, h)
-                    .descriptors(),
-            );
+                        radius,
+                        &foreground,
+                        width,
+                        height,
+                    )?)
+                }
+                _ => None,
+            };
+
+            let crop_regions = im::Regions {
+                background: regions.background && ring.is_none(),
+                ..regions
+            };
+
+            if !regions.is_empty() && !watchdog.expired() {
+                let view = image.crop_view(min_x, min_y, w, h);
+
+                result.extend(match (&mask_object, feature_channels) {
+                    (Some(mask_object), Some(feature_channels)) => view
+                        .descriptors_regions_channels(
+                            mask_object,
+                            crop_regions,
+                            feature_channels,
+                            extended_intensity,
+                            approx_percentiles_over,
+                            stride,
+                        ),
+                    (Some(mask_object), None) => match reduce {
+                        Some(reduce) => view.descriptors_regions_reduced(
+                            mask_object,
+                            crop_regions,
+                            extended_intensity,
+                            approx_percentiles_over,
+                            stride,
+                            reduce,
+                        )?,
+                        None => view.descriptors_regions(
+                            mask_object,
+                            crop_regions,
+                            extended_intensity,
+                            approx_percentiles_over,
+                            stride,
+                        ),
+                    },
+                    // Only complete descriptors are selected if the polygon was not rasterized
+                    (None, Some(feature_channels)) => view.descriptors_channels(
+                        None,
+                        feature_channels,
+                        extended_intensity,
+                        approx_percentiles_over,
+                        stride,
+                    ),
+                    (None, None) if extended_intensity => {
+                        view.descriptors_extended_strided(stride, approx_percentiles_over)
+                    }
+                    (None, None) => view.descriptors_strided(stride),
+                });
+            }
+
+            if let Some(ring) = ring.as_ref().filter(|_| !watchdog.expired()) {
+                result.extend(table::ring_background(
+                    &image,
+                    ring,
+                    feature_channels,
+                    extended_intensity,
+                    approx_percentiles_over,
+                    stride,
+                ));
+            }
+
+            if let Some(mask_object) = &mask_object {
+                if mode.contains("m") && !watchdog.expired() {
+                    result.extend(&mask_object.moments());
+                    result.extend(&mask_object.zernike());
+                }
+
+                if mode.contains("s") && !watchdog.expired() {
+                    result.extend(image.crop_view(min_x, min_y, w, h).spectrum(mask_object));
+                }
+
+                if let Some(membrane_width) = membrane_width.filter(|_| !watchdog.expired()) {
+                    result.extend(
+                        image
+                            .crop_view(min_x, min_y, w, h)
+                            .membrane(mask_object, membrane_width),
+                    );
+                }
+
+                if centroid_displacement && !watchdog.expired() {
+                    result.extend(
+                        image
+                            .crop_view(min_x, min_y, w, h)
+                            .centroid_displacement(mask_object),
+                    );
+                }
+
+                if !pairs.is_empty() && !watchdog.expired() {
+                    let view = image.crop_view(min_x, min_y, w, h);
+                    let view = view.masked(mask_object, im::MaskingStyle::Foreground);
+
+                    let start = result.len();
+                    result.extend(view.channel_pairs(&pairs));
+                    descriptors.ratio_columns = start..start + pairs.len();
+                }
+            }
+
+            // Crops are only copied out of the image when they are resized
+            *stage = Stage::Crop;
+            let crop = match texture_scales.is_empty() && thumbnails.is_none() {
+                true => None,
+                false => Some(image.crop(min_x, min_y, w, h)?),
+            };
+
+            *stage = Stage::Measure;
+            if let (Some(crop), Some(mask_buffer)) = (&crop, &mask_buffer) {
+                if !texture_scales.is_empty() && !watchdog.expired() {
+                    result.extend(crop.texture_scales(mask_buffer, texture_scales, stride)?);
+                }
+            }
+
+            if stride_column && !watchdog.expired() {
+                result.push(stride as f32);
+            }
+
+            let thumbnail = match (thumbnails, &crop) {
+                (Some(thumbnails), Some(crop)) => Some(thumbnails.write(crop, name, idx as u32)?),
+                _ => None,
+            };
+
+            Ok((result, thumbnail))
+        });
+
+        let (result, thumbnail) = match measured {
+            Ok(measured) => measured,
+            Err(err) => {
+                descriptors.failed.add(idx as u32, *xyxy, err);
+                continue;
+            }
+        };
+
+        if filter.border_policy == BorderPolicy::Flag {
+            border_flags.push(filter::touches_border(xyxy, width, height));
         }
 
-        if mode.contains("m") {
-            result.extend(&mask_object.moments());
-            result.extend(&mask_object.zernike());
+        thumbnail_paths.extend(thumbnail);
+        outlines.extend(outline);
+
+        // Objects measured over budget are profiled and recorded as failed
+        for err in resampled.into_iter().chain(watchdog.error(result.len())) {
+            descriptors.failed.add(idx as u32, *xyxy, err);
         }
 
-        ids.push(idx as u32);
-        results.push(result)
+        descriptors.push(idx as u32, result);
+    }
+
+    if filter.border_policy == BorderPolicy::Flag {
+        descriptors.touches_border = Some(border_flags);
+    }
+
+    if thumbnails.is_some() {
+        descriptors.thumbnails = Some(thumbnail_paths);
+    }
+
+    descriptors.geometry = geometry.map(|(format, _)| table::Geometry {
+        format,
+        polygons: outlines,
+    });
+
+    if degenerate > 0 && !filter.skip_invalid_polygons {
+        eprintln!(
+            "[thyme::profile::polygons] WARNING: {} has {} self-intersecting polygons that could not be repaired and will be measured as is.",
+            polygons_path.display(),
+            degenerate
+        );
     }
 
-    Ok((ids, results))
+    Ok((
+        descriptors,
+        dropped,
+        im::ImageSummary::new(&image, &foreground)?,
+        None,
+    ))
 }
 
-/// Generate the column names for the output descriptor table
+/// Rasterize a polygon into a binary mask of its padded crop
 ///
 /// # Arguments
 ///
-/// * `mode` - Profiling mode
-fn descriptor_columns(mode: &str) -> Vec<String> {
-    let mut names: Vec<String> = vec![];
-
-    if mode.contains("p") {
-        names.extend(
-            constant::FORM_DESCRIPTOR_NAMES
-                .into_iter()
-                .map(|s| s.to_string()),
-        );
+/// * `w` - Width of the crop
+/// * `h` - Height of the crop
+/// * `points` - Vertices of the polygon in image coordinates
+/// * `pad` - Padding added around the polygon bounding box
+fn rasterize(w: u32, h: u32, points: &[[f32; 2]], pad: u32) -> im::ThymeMask {
+    #[cfg(test)]
+    test::RASTERIZED.with(|count| count.set(count.get() + 1));
+
+    im::ThymeMask::new(w, h, 1, draw_centered_points(w, h, points, 1, pad)).unwrap()
+}
+
+/// Draw the union of every polygon in a file into an image-sized buffer
+///
+/// # Arguments
+///
+/// * `polygons_path` - Path to the polygons
+/// * `segment_coords` - Coordinate system of the polygon vertices
+/// * `transform` - Affine transform registering the polygons to the image
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+/// * `preview` - Preview the polygons are scaled for
+/// * `budget` - Vertex budget resampling or skipping oversized polygons
+fn polygon_union(
+    polygons_path: &Path,
+    segment_coords: im::SegmentCoords,
+    transform: Option<&AffineTransform>,
+    width: u32,
+    height: u32,
+    preview: Option<&Preview>,
+    budget: &ObjectBudget,
+) -> Result<Vec<u32>, ThymeError> {
+    let mut foreground = vec![0u32; width as usize * height as usize];
+
+    for points in io::PolygonStream::open(polygons_path)? {
+        let mut points = points?;
+
+        if let Some(transform) = transform {
+            transform.apply_points(&mut points);
+        }
+
+        if let (Some(preview), im::SegmentCoords::Pixels) = (preview, segment_coords) {
+            preview.points(&mut points);
+        }
+
+        // Invalid and skipped polygons are recorded as failed objects when they are measured
+        if budget.check_points(&mut points).is_err() {
+            continue;
+        }
+
+        let Ok(mut polygon) = im::Polygons::new(vec![points]) else {
+            continue;
+        };
+
+        polygon.validate_and_repair();
+
+        if segment_coords == im::SegmentCoords::Normalized {
+            polygon.denormalize(width, height)?;
+        }
+
+        draw_points_mut(&mut foreground, width, height, &polygon.as_points()[0], 1);
     }
 
-    let suffixes: Vec<&str> = constant::INTENSITY_DESCRIPTOR_NAMES
-        .into_iter()
-        .chain(constant::MOMENTS_DESCRIPTOR_NAMES)
-        .chain(constant::TEXTURE_DESCRIPTOR_NAMES)
-        .chain(constant::ZERNIKE_DESCRIPTOR_NAMES)
-        .collect();
+    Ok(foreground)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::cell::Cell;
+
+    use thyme_core::ut::synth::SynthConfig;
 
-    if mode.contains("c") {
-        names.extend(suffixes.iter().map(|s| "complete_".to_string() + s));
+    use super::*;
+
+    thread_local! {
+        /// Number of polygons rasterized by `rasterize` on the current thread
+        pub(super) static RASTERIZED: Cell<usize> = const { Cell::new(0) };
     }
 
-    if mode.contains("f") {
-        names.extend(suffixes.iter().map(|s| "foreground_".to_string() + s));
+    #[test]
+    fn test_profile_polygons_location() {
+        let directory = std::env::temp_dir().join("thyme_test_profile_polygons_location");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: 6,
+            radius: [3.0, 6.0],
+            seed: 2,
+            ..Default::default()
+        }
+        .write(1, &directory)
+        .unwrap();
+
+        let image = directory.join("synth_0000_image.png");
+        let polygons_path = directory.join("synth_0000_polygons.json");
+
+        let (descriptors, _, _, _) = profile(
+            im::ThymeImage::open(&image).unwrap(),
+            &polygons_path,
+            None,
+            &ProfileOptions {
+                pad: 4,
+                mode: "c",
+                texture_stride: 1,
+                ..Default::default()
+            },
+            "synth_0000",
+        )
+        .unwrap();
+
+        let columns = catalog::ProfileColumns {
+            segments: catalog::Segments::Polygons,
+            mode: "c".to_string(),
+            ..Default::default()
+        }
+        .descriptor_names();
+
+        let name = vec!["synth_0000".to_string(); descriptors.ids.len()];
+        let df = table::dataframe(&name, vec![descriptors], &columns);
+
+        let column = |name: &str| -> Vec<f32> {
+            df.column(name)
+                .unwrap()
+                .f32()
+                .unwrap()
+                .into_no_null_iter()
+                .collect()
+        };
+
+        let objects: Vec<u32> = df
+            .column("object")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+
+        let polygons = im::Polygons::open(&polygons_path).unwrap();
+        let bounding_boxes = polygons.to_bounding_boxes().unwrap();
+
+        assert_eq!(objects.len(), 6);
+
+        for (i, &object) in objects.iter().enumerate() {
+            let points = &polygons.as_points()[object as usize];
+            let [x, y] = form::centroid(points);
+            let xyxy = bounding_boxes.as_xyxy()[object as usize];
+
+            // Coordinates are reported without the padding added to each crop
+            assert_eq!(column("bbox_min_x")[i], xyxy[0]);
+            assert_eq!(column("bbox_min_y")[i], xyxy[1]);
+            assert_eq!(column("bbox_max_x")[i], xyxy[2]);
+            assert_eq!(column("bbox_max_y")[i], xyxy[3]);
+            assert_eq!(column("centroid_x")[i], x);
+            assert_eq!(column("centroid_y")[i], y);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
     }
 
-    if mode.contains("b") {
-        names.extend(suffixes.iter().map(|s| "background_".to_string() + s));
+    #[test]
+    fn test_profile_polygons_normalized() {
+        let directory = std::env::temp_dir().join("thyme_test_profile_polygons_normalized");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        // Power of two dimensions keep normalized coordinates exact
+        SynthConfig {
+            width: 64,
+            height: 32,
+            objects: 4,
+            radius: [3.0, 5.0],
+            seed: 5,
+            ..Default::default()
+        }
+        .write(1, &directory)
+        .unwrap();
+
+        let image = directory.join("synth_0000_image.png");
+        let pixels = directory.join("synth_0000_polygons.json");
+        let normalized = directory.join("synth_0000_normalized.json");
+
+        let points: Vec<Vec<[f32; 2]>> = im::Polygons::open(&pixels)
+            .unwrap()
+            .to_points()
+            .into_iter()
+            .map(|polygon| polygon.iter().map(|[x, y]| [x / 64., y / 32.]).collect())
+            .collect();
+
+        im::Polygons::new(points)
+            .unwrap()
+            .save(&normalized)
+            .unwrap();
+
+        let run = |path: &Path, coords: im::SegmentCoords| {
+            profile(
+                im::ThymeImage::open(&image).unwrap(),
+                path,
+                None,
+                &ProfileOptions {
+                    segment_coords: coords,
+                    pad: 2,
+                    mode: "cmp",
+                    texture_stride: 1,
+                    ..Default::default()
+                },
+                "synth_0000",
+            )
+        };
+
+        let columns = catalog::ProfileColumns {
+            segments: catalog::Segments::Polygons,
+            mode: "cmp".to_string(),
+            ..Default::default()
+        }
+        .descriptor_names();
+
+        let table = |descriptors: Descriptors| {
+            let name = vec!["synth_0000".to_string(); descriptors.ids.len()];
+            table::dataframe(&name, vec![descriptors], &columns)
+        };
+
+        let (expected, _, _, _) = run(&pixels, im::SegmentCoords::Pixels).unwrap();
+        let (observed, _, _, _) = run(&normalized, im::SegmentCoords::Normalized).unwrap();
+
+        assert_eq!(expected.ids.len(), 4);
+        assert!(table(expected).equals_missing(&table(observed)));
+
+        // Pixel coordinates are rejected when normalized coordinates are expected
+        assert!(run(&pixels, im::SegmentCoords::Normalized).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
     }
 
-    if mode.contains("m") {
-        names.extend(
-            constant::MOMENTS_DESCRIPTOR_NAMES
-                .into_iter()
-                .chain(constant::ZERNIKE_DESCRIPTOR_NAMES)
-                .map(|s| "mask_".to_string() + s),
-        );
+    #[test]
+    fn test_profile_polygons_transform() {
+        let directory = std::env::temp_dir().join("thyme_test_profile_polygons_transform");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: 5,
+            radius: [3.0, 6.0],
+            seed: 6,
+            ..Default::default()
+        }
+        .write(1, &directory)
+        .unwrap();
+
+        let image = directory.join("synth_0000_image.png");
+        let polygons_path = directory.join("synth_0000_polygons.json");
+        let shifted_path = directory.join("synth_0000_shifted.json");
+
+        // Segments drawn on an acquisition offset by (-3, 2) pixels from the image
+        let shifted: Vec<Vec<[f32; 2]>> = im::Polygons::open(&polygons_path)
+            .unwrap()
+            .to_points()
+            .into_iter()
+            .map(|polygon| polygon.iter().map(|[x, y]| [x - 3., y + 2.]).collect())
+            .collect();
+
+        im::Polygons::new(shifted)
+            .unwrap()
+            .save(&shifted_path)
+            .unwrap();
+
+        let run = |path: &Path, transform: Option<&AffineTransform>| {
+            let (descriptors, _, _, _) = profile(
+                im::ThymeImage::open(&image).unwrap(),
+                path,
+                transform,
+                &ProfileOptions {
+                    pad: 2,
+                    mode: "cfp",
+                    texture_stride: 1,
+                    ..Default::default()
+                },
+                "synth_0000",
+            )
+            .unwrap();
+
+            let columns = catalog::ProfileColumns {
+                segments: catalog::Segments::Polygons,
+                mode: "cfp".to_string(),
+                ..Default::default()
+            }
+            .descriptor_names();
+
+            let name = vec!["synth_0000".to_string(); descriptors.ids.len()];
+            table::dataframe(&name, vec![descriptors], &columns)
+        };
+
+        let transform = AffineTransform::new([1., 0., 0., 1., 3., -2.]).unwrap();
+
+        let expected = run(&polygons_path, None);
+        let registered = run(&shifted_path, Some(&transform));
+        let unregistered = run(&shifted_path, None);
+
+        assert_eq!(expected.height(), 5);
+        assert!(expected.equals_missing(&registered));
+        assert!(!expected.equals_missing(&unregistered));
+
+        std::fs::remove_dir_all(&directory).unwrap();
     }
 
-    names
+    #[test]
+    fn test_profile_polygons_lazy_mask() {
+        let directory = std::env::temp_dir().join("thyme_test_profile_polygons_lazy_mask");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: 5,
+            radius: [3.0, 6.0],
+            seed: 3,
+            ..Default::default()
+        }
+        .write(1, &directory)
+        .unwrap();
+
+        let image = directory.join("synth_0000_image.png");
+        let polygons_path = directory.join("synth_0000_polygons.json");
+
+        let run = |mode: &str, extended: bool| {
+            RASTERIZED.with(|count| count.set(0));
+
+            let (descriptors, _, _, _) = profile(
+                im::ThymeImage::open(&image).unwrap(),
+                &polygons_path,
+                None,
+                &ProfileOptions {
+                    pad: 2,
+                    mode,
+                    extended_intensity: extended,
+                    texture_stride: 1,
+                    ..Default::default()
+                },
+                "synth_0000",
+            )
+            .unwrap();
+
+            let columns = catalog::ProfileColumns {
+                segments: catalog::Segments::Polygons,
+                mode: mode.to_string(),
+                extended_intensity: extended,
+                ..Default::default()
+            }
+            .descriptor_names();
+
+            let name = vec!["synth_0000".to_string(); descriptors.ids.len()];
+            let df = table::dataframe(&name, vec![descriptors], &columns);
+
+            (df, RASTERIZED.with(|count| count.get()))
+        };
+
+        for extended in [false, true] {
+            // Complete and polygon descriptors never read the object mask
+            let (complete, rasterized) = run("cp", extended);
+            assert_eq!(rasterized, 0);
+
+            let (masked, rasterized) = run("cpf", extended);
+            assert_eq!(rasterized, 5);
+
+            for column in complete.get_columns() {
+                assert_eq!(
+                    column,
+                    masked.column(column.name()).unwrap(),
+                    "{}",
+                    column.name()
+                );
+            }
+        }
+
+        // Power spectrum descriptors read the object mask
+        let (spectrum, rasterized) = run("cs", false);
+        assert_eq!(rasterized, 5);
+
+        let peak = spectrum
+            .column("spectrum_peak_freq")
+            .unwrap()
+            .f32()
+            .unwrap();
+        assert!(peak.into_no_null_iter().all(|f| (0.0..=0.5).contains(&f)));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
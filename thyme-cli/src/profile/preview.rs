@@ -0,0 +1,150 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::{BoundingBoxes, ThymeImage, ThymeMask};
+
+use super::table::Descriptors;
+
+/// Downsampled preview of a profile run for rapid parameter tuning
+///
+/// Images are resized by the preview scale with bilinear interpolation,
+/// masks with nearest-neighbor sampling, and polygon and bounding box
+/// coordinates are multiplied by the scale, so that objects are measured in
+/// preview pixels. Descriptors are marked with the scale so that they are
+/// never confused with full-resolution descriptors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preview {
+    /// Factor images and segments are resized by in (0, 1]
+    pub scale: f32,
+    /// Rescale lengths and areas back to original pixel units
+    pub rescale: bool,
+}
+
+impl Preview {
+    /// Initialize a preview if a scale was provided
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - Factor images and segments are resized by
+    /// * `rescale` - Rescale lengths and areas back to original pixel units
+    pub fn new(scale: Option<f32>, rescale: bool) -> Result<Option<Preview>, ThymeError> {
+        match scale {
+            Some(scale) if scale.is_finite() && scale > 0.0 && scale <= 1.0 => {
+                Ok(Some(Preview { scale, rescale }))
+            }
+            Some(scale) => Err(ThymeError::OtherError(format!(
+                "Invalid preview scale {}. Must be in (0, 1]",
+                scale
+            ))),
+            None if rescale => Err(ThymeError::OtherError(
+                "preview_rescale requires preview".to_string(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Width and height of an image resized by the preview scale
+    fn dimensions(&self, width: u32, height: u32) -> (u32, u32) {
+        let resize = |size: u32| ((size as f32 * self.scale).round() as u32).max(1);
+        (resize(width), resize(height))
+    }
+
+    /// Resize an image by the preview scale
+    pub fn image(&self, image: &ThymeImage) -> Result<ThymeImage, ThymeError> {
+        let (width, height) = self.dimensions(image.width(), image.height());
+        image.resize(width, height)
+    }
+
+    /// Resize a mask by the preview scale without blending labels
+    pub fn mask(&self, mask: &ThymeMask) -> Result<ThymeMask, ThymeError> {
+        let (width, height) = self.dimensions(mask.width(), mask.height());
+        mask.resize_nearest(width, height)
+    }
+
+    /// Scale polygon vertices in image pixels by the preview scale
+    pub fn points(&self, points: &mut [[f32; 2]]) {
+        for point in points.iter_mut().flatten() {
+            *point *= self.scale;
+        }
+    }
+
+    /// Scale bounding boxes in image pixels by the preview scale, keeping their labels
+    pub fn boxes(&self, boxes: BoundingBoxes) -> Result<BoundingBoxes, ThymeError> {
+        let labels = boxes.labels().cloned();
+
+        let scaled = BoundingBoxes::new(
+            boxes
+                .to_xyxy()
+                .into_iter()
+                .map(|xyxy| xyxy.map(|v| v * self.scale))
+                .collect(),
+        )?;
+
+        match labels {
+            Some(labels) => scaled.with_labels(labels),
+            None => Ok(scaled),
+        }
+    }
+
+    /// Mark the descriptors of an image with the preview scale
+    ///
+    /// Descriptors measured in pixels (e.g. lengths, areas, and spatial
+    /// frequencies) are rescaled to original pixel units if requested.
+    /// Dimensionless and mixed-unit descriptors (e.g. moments) are left as
+    /// measured on the preview.
+    ///
+    /// # Arguments
+    ///
+    /// * `descriptors` - Descriptors computed on the preview
+    /// * `exponents` - Pixel length exponent of each descriptor column
+    pub fn finish(&self, descriptors: &mut Descriptors, exponents: &[i32]) {
+        descriptors.preview_scale = Some(self.scale);
+
+        if self.rescale {
+            descriptors.rescale(exponents, 1.0 / self.scale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_core::im::ThymeBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_preview_segments() {
+        assert_eq!(Preview::new(None, false).unwrap(), None);
+        assert!(Preview::new(None, true).is_err());
+
+        for invalid in [0.0, -0.5, 1.5, f32::NAN] {
+            assert!(Preview::new(Some(invalid), false).is_err(), "{}", invalid);
+        }
+
+        let preview = Preview::new(Some(0.25), true).unwrap().unwrap();
+
+        let image = ThymeImage::U16(ThymeBuffer::new(40, 30, 2, vec![7u16; 40 * 30 * 2]).unwrap());
+        assert_eq!(preview.image(&image).unwrap().shape(), (8, 10, 2));
+
+        let mask = ThymeMask::new(40, 30, 1, vec![3; 40 * 30]).unwrap();
+        let resized = preview.mask(&mask).unwrap();
+        assert_eq!((resized.width(), resized.height()), (10, 8));
+        assert!(resized.as_raw().iter().all(|&label| label == 3));
+
+        let mut points = [[8.0, 4.0], [f32::NAN, 12.0]];
+        preview.points(&mut points);
+        assert_eq!(points[0], [2.0, 1.0]);
+        assert!(points[1][0].is_nan());
+
+        let boxes = BoundingBoxes::new(vec![[4.0, 8.0, 20.0, 16.0]])
+            .unwrap()
+            .with_labels(vec!["cell".to_string()])
+            .unwrap();
+
+        let scaled = preview.boxes(boxes).unwrap();
+        assert_eq!(scaled.as_xyxy(), &vec![[1.0, 2.0, 5.0, 4.0]]);
+        assert_eq!(scaled.labels().unwrap(), &vec!["cell".to_string()]);
+    }
+}
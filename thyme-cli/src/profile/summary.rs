@@ -0,0 +1,79 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::Path;
+
+use polars::prelude::{Column, DataFrame};
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::ImageSummary;
+use thyme_core::io;
+
+/// File name of the per-image summary table written to output directories
+pub const IMAGE_SUMMARY_FILE: &str = "image_summary.csv";
+
+/// Summary of a single profiled image
+#[derive(Debug, Clone)]
+pub struct SummaryRow {
+    /// Image name matching the image column of the descriptors table
+    pub image: String,
+    /// Number of objects profiled in the image
+    pub objects: usize,
+    /// Foreground coverage and background intensity of the image
+    pub summary: ImageSummary,
+}
+
+/// Write a table with one row of image-level context per profiled image
+///
+/// Per-channel background columns are suffixed with the channel index (e.g.
+/// `background_mean_c0`) and are null for images with fewer channels than the
+/// image with the most channels.
+///
+/// # Arguments
+///
+/// * `path` - Path to the output table
+/// * `rows` - Summaries of the profiled images
+pub fn write_image_summary(path: &Path, rows: &[SummaryRow]) -> Result<(), ThymeError> {
+    let channels = rows
+        .iter()
+        .map(|row| row.summary.background_mean.len())
+        .max()
+        .unwrap_or(0);
+
+    let image: Vec<&str> = rows.iter().map(|row| row.image.as_str()).collect();
+    let objects: Vec<u32> = rows.iter().map(|row| row.objects as u32).collect();
+    let width: Vec<u32> = rows.iter().map(|row| row.summary.width).collect();
+    let height: Vec<u32> = rows.iter().map(|row| row.summary.height).collect();
+    let fraction: Vec<f32> = rows
+        .iter()
+        .map(|row| row.summary.foreground_fraction)
+        .collect();
+    let focus: Vec<f32> = rows.iter().map(|row| row.summary.focus_score).collect();
+
+    let mut df = DataFrame::new(vec![
+        Column::new("image".into(), image),
+        Column::new("objects".into(), objects),
+        Column::new("width".into(), width),
+        Column::new("height".into(), height),
+        Column::new("foreground_fraction".into(), fraction),
+        Column::new("focus_score".into(), focus),
+    ])
+    .unwrap();
+
+    let mut per_channel = |name: &str, values: fn(&ImageSummary) -> &[f32]| {
+        for c in 0..channels {
+            let column: Vec<Option<f32>> = rows
+                .iter()
+                .map(|row| values(&row.summary).get(c).copied())
+                .collect();
+
+            df.with_column(Column::new(format!("{}_c{}", name, c).into(), column))
+                .unwrap();
+        }
+    };
+
+    per_channel("background_mean", |s| &s.background_mean);
+    per_channel("background_median", |s| &s.background_median);
+
+    io::write_table(&mut df, path)
+}
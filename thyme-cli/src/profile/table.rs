@@ -0,0 +1,1198 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::ops::Range;
+use std::path::Path;
+
+use polars::prelude::{Column, DataFrame};
+
+use thyme_core::cv::points::resample_points;
+use thyme_core::error::ThymeError;
+use thyme_core::im::{FeatureChannels, MaskingStyle, ThymeImage, ThymeMask};
+use thyme_core::io;
+
+use crate::failures::FailedObjects;
+use crate::filter::NonFinitePolicy;
+use crate::output::PerImageOutput;
+
+/// Header of the non-finite descriptor report written alongside batch outputs
+pub const NONFINITE_REPORT_HEADER: &str = "column\tobjects\timages";
+
+/// Polygons of the objects in a single image written to a polygon column
+#[derive(Debug, Clone, Default)]
+pub struct Geometry {
+    /// Encoding of the polygon column
+    pub format: io::GeometryFormat,
+    /// Vertices (x, y) of the polygon of each object
+    pub polygons: Vec<Vec<[f32; 2]>>,
+}
+
+/// Column-major descriptors computed for the objects in a single image
+///
+/// Descriptors are stored as [column][object] so that the output table can be
+/// assembled by moving whole columns into a DataFrame rather than transposing
+/// a row for every object.
+#[derive(Debug, Default)]
+pub struct Descriptors {
+    pub ids: Vec<u32>,
+    /// Number of channels in the image the descriptors were computed from
+    pub channels: usize,
+    /// Labels of the objects if provided with the segments (e.g. box classes)
+    pub labels: Option<Vec<String>>,
+    /// Whether each object touches the image border if border objects are flagged
+    pub touches_border: Option<Vec<bool>>,
+    /// Majority class id and fraction of each object if a class mask was provided
+    pub classes: Option<Vec<(u32, f32)>>,
+    /// Path to the thumbnail of each object if thumbnails were written
+    pub thumbnails: Option<Vec<Option<String>>>,
+    /// Polygon of each object if geometry is included in the output
+    pub geometry: Option<Geometry>,
+    /// Non-finite values found when the descriptors were sanitized
+    pub nonfinite: NonFinite,
+    /// Descriptor columns of channel ratios, which are undefined for a zero denominator
+    pub ratio_columns: Range<usize>,
+    /// Objects that failed to be measured and have no descriptors
+    pub failed: FailedObjects,
+    /// Factor the image and segments were resized by if profiled as a preview
+    pub preview_scale: Option<f32>,
+    /// Mean relative change in polygon area if outlines were smoothed
+    pub area_change: Option<f32>,
+    columns: Vec<Vec<f32>>,
+    capacity: usize,
+}
+
+impl Descriptors {
+    /// Initialize an empty set of descriptors for up to `capacity` objects
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            ids: Vec::with_capacity(capacity),
+            channels: 0,
+            labels: None,
+            touches_border: None,
+            classes: None,
+            thumbnails: None,
+            geometry: None,
+            nonfinite: NonFinite::default(),
+            ratio_columns: 0..0,
+            failed: FailedObjects::default(),
+            preview_scale: None,
+            area_change: None,
+            columns: vec![],
+            capacity,
+        }
+    }
+
+    /// Add the descriptors for a single object
+    ///
+    /// Rows are normally the same length. Rows of objects whose measurement
+    /// was abandoned (see `failures::Watchdog`) are shorter and are padded
+    /// with NaN, and columns first added by a longer row are back-filled with
+    /// NaN for the objects added before it.
+    pub fn push(&mut self, id: u32, row: Vec<f32>) {
+        while self.columns.len() < row.len() {
+            let mut column = Vec::with_capacity(self.capacity.max(self.ids.len()));
+            column.resize(self.ids.len(), f32::NAN);
+            self.columns.push(column);
+        }
+
+        let mut row = row.into_iter();
+
+        for column in self.columns.iter_mut() {
+            column.push(row.next().unwrap_or(f32::NAN));
+        }
+
+        self.ids.push(id);
+    }
+
+    /// Pad the descriptors to a number of columns with NaN
+    ///
+    /// Objects whose measurement was abandoned before their last descriptor
+    /// family leave trailing columns missing if no object of the image was
+    /// measured completely.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - Number of descriptor columns
+    pub fn pad_columns(&mut self, n: usize) {
+        while self.columns.len() < n {
+            self.columns.push(vec![f32::NAN; self.ids.len()]);
+        }
+    }
+
+    /// Count and handle non-finite descriptor values
+    ///
+    /// The number of objects with a non-finite value in each column is
+    /// recorded in `nonfinite` before the policy is applied. Dropped objects
+    /// are removed from the ids, labels, border flags, classes, thumbnail
+    /// paths, and polygons as well. Undefined channel ratios are counted but always written
+    /// as zero, independent of the policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Handling of non-finite values
+    pub fn sanitize(&mut self, policy: NonFinitePolicy) {
+        let mut flagged = vec![false; self.ids.len()];
+        let mut undefined = vec![false; self.ids.len()];
+        let mut columns = vec![0; self.columns.len()];
+
+        for (k, (column, count)) in self.columns.iter_mut().zip(columns.iter_mut()).enumerate() {
+            let ratio = self.ratio_columns.contains(&k);
+
+            for (i, value) in column.iter_mut().enumerate() {
+                if value.is_finite() {
+                    continue;
+                }
+
+                *count += 1;
+
+                match ratio {
+                    true => {
+                        *value = 0.0;
+                        undefined[i] = true;
+                    }
+                    false => flagged[i] = true,
+                }
+            }
+        }
+
+        self.nonfinite = NonFinite {
+            columns,
+            objects: flagged
+                .iter()
+                .zip(&undefined)
+                .filter(|&(&flag, &ratio)| flag || ratio)
+                .count(),
+        };
+
+        if !flagged.contains(&true) {
+            return;
+        }
+
+        match policy {
+            NonFinitePolicy::Keep => {}
+            NonFinitePolicy::Zero => {
+                for value in self.columns.iter_mut().flatten() {
+                    if !value.is_finite() {
+                        *value = 0.0;
+                    }
+                }
+            }
+            NonFinitePolicy::DropObject => {
+                fn retain<T>(values: &mut Vec<T>, flagged: &[bool]) {
+                    let mut flags = flagged.iter();
+                    values.retain(|_| !flags.next().unwrap());
+                }
+
+                for column in self.columns.iter_mut() {
+                    retain(column, &flagged);
+                }
+
+                retain(&mut self.ids, &flagged);
+
+                if let Some(labels) = self.labels.as_mut() {
+                    retain(labels, &flagged);
+                }
+
+                if let Some(touches_border) = self.touches_border.as_mut() {
+                    retain(touches_border, &flagged);
+                }
+
+                if let Some(classes) = self.classes.as_mut() {
+                    retain(classes, &flagged);
+                }
+
+                if let Some(thumbnails) = self.thumbnails.as_mut() {
+                    retain(thumbnails, &flagged);
+                }
+
+                if let Some(geometry) = self.geometry.as_mut() {
+                    retain(&mut geometry.polygons, &flagged);
+                }
+            }
+        }
+    }
+
+    /// Multiply each descriptor column by a factor raised to its pixel length exponent
+    ///
+    /// Polygon vertices are lengths and are multiplied by the factor.
+    ///
+    /// # Arguments
+    ///
+    /// * `exponents` - Pixel length exponent of each descriptor column
+    /// * `factor` - Pixel length of the descriptors in the target units
+    pub fn rescale(&mut self, exponents: &[i32], factor: f32) {
+        for (column, &exponent) in self.columns.iter_mut().zip(exponents) {
+            if exponent == 0 {
+                continue;
+            }
+
+            let factor = factor.powi(exponent);
+
+            for value in column.iter_mut() {
+                *value *= factor;
+            }
+        }
+
+        if let Some(geometry) = self.geometry.as_mut() {
+            for point in geometry.polygons.iter_mut().flatten() {
+                point[0] *= factor;
+                point[1] *= factor;
+            }
+        }
+    }
+
+    /// Move out the descriptor columns, labels, border flags, classes, thumbnails, and polygons, keeping ids, channels, and failed objects
+    fn take(&mut self) -> Descriptors {
+        Descriptors {
+            ids: self.ids.clone(),
+            channels: self.channels,
+            labels: self.labels.take(),
+            touches_border: self.touches_border.take(),
+            classes: self.classes.take(),
+            thumbnails: self.thumbnails.take(),
+            geometry: self.geometry.take(),
+            nonfinite: self.nonfinite.clone(),
+            ratio_columns: self.ratio_columns.clone(),
+            failed: FailedObjects::default(),
+            preview_scale: self.preview_scale,
+            area_change: self.area_change,
+            columns: std::mem::take(&mut self.columns),
+            capacity: 0,
+        }
+    }
+}
+
+/// Non-finite descriptor values found in the objects of a single image
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NonFinite {
+    /// Number of objects with a non-finite value in each descriptor column
+    pub columns: Vec<usize>,
+    /// Number of objects with a non-finite value in any descriptor column
+    pub objects: usize,
+}
+
+/// Summary of the non-finite descriptor values across all images of a run
+#[derive(Debug, Default)]
+pub struct NonFiniteReport {
+    /// Column name, affected objects, and affected images in column order
+    columns: Vec<(String, usize, usize)>,
+    objects: usize,
+}
+
+impl NonFiniteReport {
+    /// Add the non-finite values of a single image
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - Names of the descriptor columns of the image
+    /// * `nonfinite` - Non-finite values found in the image
+    pub fn add(&mut self, columns: &[String], nonfinite: &NonFinite) {
+        if nonfinite.objects == 0 {
+            return;
+        }
+
+        self.objects += nonfinite.objects;
+
+        for (column, &objects) in columns.iter().zip(&nonfinite.columns) {
+            if objects == 0 {
+                continue;
+            }
+
+            match self.columns.iter_mut().find(|(name, _, _)| name == column) {
+                Some((_, total, images)) => {
+                    *total += objects;
+                    *images += 1;
+                }
+                None => self.columns.push((column.clone(), objects, 1)),
+            }
+        }
+    }
+
+    /// Check if no non-finite values were found
+    pub fn is_empty(&self) -> bool {
+        self.objects == 0
+    }
+
+    /// Number of objects with a non-finite value in any descriptor column
+    pub fn objects(&self) -> usize {
+        self.objects
+    }
+
+    /// Number of descriptor columns with a non-finite value
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Format the report as a table with a row for each affected column
+    pub fn to_tsv(&self) -> String {
+        let mut table = String::from(NONFINITE_REPORT_HEADER);
+
+        for (column, objects, images) in self.columns.iter() {
+            table.push_str(&format!("\n{}\t{}\t{}", column, objects, images));
+        }
+
+        table
+    }
+}
+
+/// Check that descriptors share the channel count of the first image
+///
+/// Per-channel columns can only be assembled into a single table if every
+/// image has the same number of channels.
+///
+/// # Arguments
+///
+/// * `channels` - Channel count of the first image, set if not yet known
+/// * `descriptors` - Descriptors computed for the current image
+pub fn check_channels(
+    channels: &mut Option<usize>,
+    descriptors: &Descriptors,
+) -> Result<(), ThymeError> {
+    match *channels {
+        Some(c) if c != descriptors.channels => Err(ThymeError::OtherError(format!(
+            "Image has {} channels but the first image has {} channels.",
+            descriptors.channels, c
+        ))),
+        Some(_) => Ok(()),
+        None => {
+            *channels = Some(descriptors.channels);
+            Ok(())
+        }
+    }
+}
+
+/// Parse feature channels and check them against the first image
+///
+/// Channels are checked against the header of the first image before any
+/// image is profiled so that out-of-range channels are reported immediately.
+///
+/// # Arguments
+///
+/// * `channels` - Feature channels provided as family:channels pairs
+/// * `image` - Path to the first image
+pub fn feature_channels(
+    channels: Option<&str>,
+    image: Option<&Path>,
+) -> Result<Option<FeatureChannels>, ThymeError> {
+    let Some(channels) = channels else {
+        return Ok(None);
+    };
+
+    let channels = FeatureChannels::parse(channels)?;
+
+    if let Some(image) = image {
+        channels.check(io::probe_image(image)?.channels as usize)?;
+    }
+
+    Ok(Some(channels))
+}
+
+/// Parse a comma-separated list of texture downsampling factors
+///
+/// # Arguments
+///
+/// * `scales` - Texture scales provided as comma-separated integers (e.g. 1,2,4)
+pub fn texture_scales(scales: Option<&str>) -> Result<Vec<u32>, ThymeError> {
+    let Some(scales) = scales else {
+        return Ok(vec![]);
+    };
+
+    let mut parsed: Vec<u32> = Vec::new();
+
+    for scale in scales.split(',') {
+        let scale = scale.trim();
+
+        match scale.parse::<u32>() {
+            Ok(s) if s > 0 && !parsed.contains(&s) => parsed.push(s),
+            Ok(s) if s > 0 => {
+                return Err(ThymeError::OtherError(format!(
+                    "Texture scale '{}' was provided more than once",
+                    scale
+                )));
+            }
+            _ => {
+                return Err(ThymeError::OtherError(format!(
+                    "Invalid texture scale '{}'. Scales must be positive integers (e.g. 1,2,4)",
+                    scale
+                )));
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Background descriptors of an object measured on the pixels of a ring
+///
+/// Descriptors are identical to the background descriptors of a crop where
+/// the object mask covers every pixel outside of the ring.
+///
+/// # Arguments
+///
+/// * `image` - Image the object was segmented from
+/// * `ring` - Origin (x, y) and binary mask of the ring from `background_ring`
+/// * `feature_channels` - Channels of each descriptor family
+/// * `extended` - Include extended intensity descriptors
+/// * `approx_over` - Positive subpixels of a channel above which percentiles are selected without a full sort
+/// * `texture_stride` - Stride of co-occurrence anchors for texture descriptors
+pub fn ring_background(
+    image: &ThymeImage,
+    ring: &(u32, u32, ThymeMask),
+    feature_channels: Option<&FeatureChannels>,
+    extended: bool,
+    approx_over: Option<usize>,
+    texture_stride: usize,
+) -> Vec<f32> {
+    let (x, y, ring) = ring;
+
+    let ring = ring.crop_view(0, 0, ring.width(), ring.height());
+    let view = image.crop_view(*x, *y, ring.width() as u32, ring.height() as u32);
+
+    match feature_channels {
+        Some(feature_channels) => view.descriptors_channels(
+            Some((&ring, MaskingStyle::Foreground)),
+            feature_channels,
+            extended,
+            approx_over,
+            texture_stride,
+        ),
+        None => {
+            let view = view.masked(&ring, MaskingStyle::Foreground);
+
+            match extended {
+                true => view.descriptors_extended_strided(texture_stride, approx_over),
+                false => view.descriptors_strided(texture_stride),
+            }
+        }
+    }
+}
+
+/// Polygon of an object written to a polygon column
+///
+/// Polygons with more than `max_points` vertices are resampled to
+/// `max_points` equidistant vertices along their outline, including a
+/// closing vertex that repeats the first.
+///
+/// # Arguments
+///
+/// * `points` - Vertices (x, y) of the polygon ordered along its outline
+/// * `max_points` - Maximum number of vertices
+pub fn outline(points: &[[f32; 2]], max_points: usize) -> Vec<[f32; 2]> {
+    let mut points = points.to_vec();
+
+    if points.len() > max_points {
+        resample_points(&mut points, max_points);
+    }
+
+    points
+}
+
+/// Location descriptors leading the descriptors of each object
+///
+/// Coordinates are in image pixels before padding and are not clipped to the
+/// image. The bounding box center is used if the centroid is missing or not
+/// finite (e.g. for zero-area polygons).
+///
+/// # Arguments
+///
+/// * `xyxy` - Unpadded bounding box of the object
+/// * `centroid` - Centroid (x, y) of the object
+pub fn location(xyxy: &[f32; 4], centroid: Option<[f32; 2]>) -> [f32; 6] {
+    let [min_x, min_y, max_x, max_y] = *xyxy;
+
+    let [x, y] = centroid
+        .filter(|c| c[0].is_finite() && c[1].is_finite())
+        .unwrap_or([(min_x + max_x) / 2.0, (min_y + max_y) / 2.0]);
+
+    [min_x, min_y, max_x, max_y, x, y]
+}
+
+/// Concatenate per-image descriptors into full table columns
+///
+/// Images are concatenated in the order provided. Each column is assembled
+/// and the per-image buffers it was built from are released before moving on
+/// to the next column, so only a single column is ever held twice.
+///
+/// # Arguments
+///
+/// * `descriptors` - Column-major descriptors for each image
+pub fn concatenate(mut descriptors: Vec<Descriptors>) -> (Vec<u32>, Vec<Vec<f32>>) {
+    let n_objects: usize = descriptors.iter().map(|d| d.ids.len()).sum();
+    let n_columns: usize = descriptors
+        .iter()
+        .map(|d| d.columns.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut ids: Vec<u32> = Vec::with_capacity(n_objects);
+    for image in descriptors.iter_mut() {
+        ids.append(&mut image.ids);
+    }
+
+    let mut columns: Vec<Vec<f32>> = Vec::with_capacity(n_columns);
+    for idx in 0..n_columns {
+        let mut column: Vec<f32> = Vec::with_capacity(n_objects);
+        for image in descriptors.iter_mut() {
+            if let Some(values) = image.columns.get_mut(idx) {
+                column.extend(std::mem::take(values));
+            }
+        }
+
+        columns.push(column);
+    }
+
+    (ids, columns)
+}
+
+/// Assemble descriptors into a table with image, object, and descriptor columns
+///
+/// A label column follows the object column if any image has labeled objects,
+/// followed by a touches_border column if border objects were flagged and
+/// class_id and class_frac columns if objects were assigned classes. A
+/// preview_scale column directly follows the object column if any image was
+/// profiled as a preview. A thumbnail_path column follows the descriptor columns if thumbnails were
+/// written, with nulls for objects without a thumbnail, and a polygon column
+/// ends the table if geometry was included. Descriptor columns are left empty if no objects were profiled so that every
+/// table has the same columns.
+///
+/// # Arguments
+///
+/// * `name` - Image name of each object
+/// * `data` - Column-major descriptors for each image
+/// * `columns` - Names of the descriptor columns
+pub fn dataframe(name: &[String], data: Vec<Descriptors>, columns: &[String]) -> DataFrame {
+    let labels: Option<Vec<Option<String>>> = data.iter().any(|d| d.labels.is_some()).then(|| {
+        data.iter()
+            .flat_map(|d| match &d.labels {
+                Some(labels) => labels.iter().cloned().map(Some).collect(),
+                None => vec![None; d.ids.len()],
+            })
+            .collect()
+    });
+
+    let touches_border: Option<Vec<bool>> =
+        data.iter().any(|d| d.touches_border.is_some()).then(|| {
+            data.iter()
+                .flat_map(|d| match &d.touches_border {
+                    Some(flags) => flags.clone(),
+                    None => vec![false; d.ids.len()],
+                })
+                .collect()
+        });
+
+    let classes: Option<Vec<(u32, f32)>> = data.iter().any(|d| d.classes.is_some()).then(|| {
+        data.iter()
+            .flat_map(|d| match &d.classes {
+                Some(classes) => classes.clone(),
+                None => vec![(0, 0.0); d.ids.len()],
+            })
+            .collect()
+    });
+
+    let thumbnails: Option<Vec<Option<String>>> =
+        data.iter().any(|d| d.thumbnails.is_some()).then(|| {
+            data.iter()
+                .flat_map(|d| match &d.thumbnails {
+                    Some(paths) => paths.clone(),
+                    None => vec![None; d.ids.len()],
+                })
+                .collect()
+        });
+
+    let geometry = data
+        .iter()
+        .find_map(|d| d.geometry.as_ref())
+        .map(|geometry| {
+            let polygons: Vec<Option<Vec<[f32; 2]>>> = data
+                .iter()
+                .flat_map(|d| match &d.geometry {
+                    Some(geometry) => geometry.polygons.iter().cloned().map(Some).collect(),
+                    None => vec![None; d.ids.len()],
+                })
+                .collect();
+
+            (geometry.format, polygons)
+        });
+
+    let preview: Option<Vec<Option<f32>>> =
+        data.iter().any(|d| d.preview_scale.is_some()).then(|| {
+            data.iter()
+                .flat_map(|d| vec![d.preview_scale; d.ids.len()])
+                .collect()
+        });
+
+    let (item, mut column_data) = concatenate(data);
+    column_data.resize(columns.len(), vec![]);
+
+    let mut df = DataFrame::new(vec![
+        Column::new("image".into(), name),
+        Column::new("object".into(), &item),
+    ])
+    .unwrap();
+
+    if let Some(preview) = preview {
+        df.with_column(Column::new("preview_scale".into(), &preview))
+            .unwrap();
+    }
+
+    if let Some(labels) = labels {
+        df.with_column(Column::new("label".into(), &labels))
+            .unwrap();
+    }
+
+    if let Some(touches_border) = touches_border {
+        df.with_column(Column::new("touches_border".into(), &touches_border))
+            .unwrap();
+    }
+
+    if let Some(classes) = classes {
+        let (class_id, class_frac): (Vec<u32>, Vec<f32>) = classes.into_iter().unzip();
+
+        df.with_column(Column::new("class_id".into(), class_id))
+            .unwrap();
+        df.with_column(Column::new("class_frac".into(), class_frac))
+            .unwrap();
+    }
+
+    for (column, descriptor) in columns.iter().zip(column_data) {
+        df.with_column(Column::new(column.into(), descriptor))
+            .unwrap();
+    }
+
+    if let Some(thumbnails) = thumbnails {
+        df.with_column(Column::new("thumbnail_path".into(), &thumbnails))
+            .unwrap();
+    }
+
+    if let Some((format, polygons)) = geometry {
+        df.with_column(io::polygon_column("polygon", &polygons, format))
+            .unwrap();
+    }
+
+    df
+}
+
+/// Write the descriptors of a single image to its own table
+///
+/// The descriptor columns are released once written, leaving only the object
+/// ids and channel count for the object counts of the run.
+///
+/// # Arguments
+///
+/// * `per_image` - Per-image table output
+/// * `image` - Image name
+/// * `descriptors` - Descriptors computed for the image
+/// * `columns` - Names of the descriptor columns
+pub fn write_image(
+    per_image: &PerImageOutput,
+    image: &str,
+    descriptors: &mut Descriptors,
+    columns: &[String],
+) -> Result<(), ThymeError> {
+    let name = vec![image.to_string(); descriptors.ids.len()];
+    let mut df = dataframe(&name, vec![descriptors.take()], columns);
+    per_image.write(image, &mut df)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::{Path, PathBuf};
+
+    use clap::{Args, Parser};
+    use polars::prelude::{Column, DataFrame};
+
+    use thyme_core::im::{ThymeBuffer, ThymeImage};
+    use thyme_core::io;
+
+    use crate::profile::mask::{ProfileMaskArgs, profile_image_mask};
+
+    use super::{
+        Descriptors, NonFinite, NonFinitePolicy, NonFiniteReport, dataframe, texture_scales,
+    };
+
+    const TEST_DIR: &str = "../data/tests";
+
+    #[derive(Parser)]
+    struct Cli<T: Args> {
+        #[command(flatten)]
+        args: T,
+    }
+
+    fn profile(args: &[&str]) {
+        let args = Cli::<ProfileMaskArgs>::parse_from(
+            std::iter::once("thyme").chain(args.iter().copied()),
+        )
+        .args;
+        profile_image_mask(&args);
+    }
+
+    fn fixture_directory(name: &str, images: &[&str]) -> PathBuf {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for image in images {
+            let copy = |source: &str, target: String| {
+                std::fs::copy(Path::new(TEST_DIR).join(source), directory.join(target)).unwrap();
+            };
+
+            copy("test_rgb.tif", format!("{}_image.tif", image));
+            copy("test_mask_integer_u16.npy", format!("{}_mask.npy", image));
+        }
+
+        directory
+    }
+
+    #[test]
+    fn test_per_image_output() {
+        let images = ["a", "b", "c"];
+        let directory = fixture_directory("thyme_test_per_image_output_profile", &images);
+
+        let input = directory.to_str().unwrap();
+        let output = directory.join("output");
+        let combined = directory.join("combined.csv");
+
+        let substrings = ["--image-substring", "_image", "--mask-substring", "_mask"];
+
+        let mut args = vec![
+            "-i",
+            input,
+            "-o",
+            output.to_str().unwrap(),
+            "--per-image-output",
+        ];
+        args.extend(substrings);
+        profile(&args);
+
+        let mut args = vec!["-i", input, "-o", combined.to_str().unwrap()];
+        args.extend(substrings);
+        profile(&args);
+
+        let combined = std::fs::read_to_string(combined).unwrap();
+        let mut lines = combined.lines();
+        let header = lines.next().unwrap();
+        let rows: Vec<&str> = lines.collect();
+
+        let mut concatenated = vec![header.to_string()];
+
+        for image in images {
+            let table = std::fs::read_to_string(
+                output
+                    .join("descriptors")
+                    .join(format!("{}_image.csv", image)),
+            )
+            .unwrap();
+
+            let mut lines = table.lines();
+            assert_eq!(lines.next().unwrap(), header);
+
+            let image_rows: Vec<&str> = lines.collect();
+            assert_eq!(image_rows.len(), rows.len() / images.len());
+
+            concatenated.extend(image_rows.iter().map(|row| row.to_string()));
+        }
+
+        assert_eq!(
+            std::fs::read_dir(output.join("descriptors"))
+                .unwrap()
+                .count(),
+            3
+        );
+        assert_eq!(concatenated.join("\n"), combined.trim_end());
+        assert!(output.join("object_counts.tsv").is_file());
+
+        // Resumed runs skip images whose table already exists
+        std::fs::remove_file(output.join("descriptors").join("b_image.csv")).unwrap();
+        std::fs::remove_file(output.join("object_counts.tsv")).unwrap();
+
+        let mut args = vec![
+            "-i",
+            input,
+            "-o",
+            output.to_str().unwrap(),
+            "--per-image-output",
+            "--resume",
+        ];
+        args.extend(substrings);
+        profile(&args);
+
+        let counts = std::fs::read_to_string(output.join("object_counts.tsv")).unwrap();
+        assert_eq!(counts.lines().count(), 2);
+        assert!(output.join("descriptors").join("b_image.csv").is_file());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_concatenate_rows() {
+        let images = ["a", "b", "c"];
+        let directory = fixture_directory("thyme_test_concatenate_rows", &images);
+        let output = directory.join("descriptors.pq");
+
+        profile(&[
+            "-i",
+            directory.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--image-substring",
+            "_image",
+            "--mask-substring",
+            "_mask",
+        ]);
+
+        let profiled = io::read_table(&output).unwrap();
+        let columns: Vec<String> = profiled.get_column_names_str()[2..]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        let name: Vec<String> = profiled
+            .column("image")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        let item: Vec<u32> = profiled
+            .column("object")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+
+        let rows: Vec<Vec<f32>> = (0..profiled.height())
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|column| {
+                        let values = profiled.column(column).unwrap().f32().unwrap();
+                        values.get(row).unwrap_or(f32::NAN)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Previous row-wise path transposing every row into the table columns
+        let mut column_data: Vec<Vec<f32>> = vec![Vec::with_capacity(rows.len()); columns.len()];
+
+        for row in &rows {
+            for (idx, &descriptor) in row.iter().enumerate() {
+                column_data[idx].push(descriptor);
+            }
+        }
+
+        let mut rowwise = DataFrame::new(vec![
+            Column::new("image".into(), &name),
+            Column::new("object".into(), &item),
+        ])
+        .unwrap();
+
+        for (column, descriptor) in columns.iter().zip(column_data) {
+            rowwise
+                .with_column(Column::new(column.into(), descriptor))
+                .unwrap();
+        }
+
+        // Column-major descriptors of each image concatenated into the table
+        let mut data: Vec<Descriptors> = vec![];
+
+        for (idx, row) in rows.into_iter().enumerate() {
+            if idx == 0 || name[idx] != name[idx - 1] {
+                data.push(Descriptors::with_capacity(0));
+            }
+
+            data.last_mut().unwrap().push(item[idx], row);
+        }
+
+        assert_eq!(data.len(), images.len());
+
+        let concatenated = dataframe(&name, data, &columns);
+
+        for df in [&rowwise, &profiled] {
+            assert_eq!(
+                concatenated.get_column_names_str(),
+                df.get_column_names_str()
+            );
+            assert_eq!(concatenated.dtypes(), df.dtypes());
+            assert!(concatenated.equals_missing(df));
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// Write a 20 x 20 image and mask with an object 1 px from the left edge,
+    /// an interior object, and an object touching the right edge
+    fn border_fixture(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let mut mask = vec![0u16; 20 * 20];
+
+        for (label, x) in [(1, 1..6), (2, 9..13), (3, 16..20)] {
+            for y in 8..13 {
+                for x in x.clone() {
+                    mask[y * 20 + x] = label;
+                }
+            }
+        }
+
+        let image: Vec<u8> = mask.iter().map(|&label| 50 * label as u8 + 10).collect();
+
+        ThymeImage::U8(ThymeBuffer::new(20, 20, 1, image).unwrap())
+            .save(directory.join("a_image.png"))
+            .unwrap();
+
+        io::write_numpy(directory.join("a_mask.npy"), mask, vec![20, 20]).unwrap();
+
+        directory
+    }
+
+    #[test]
+    fn test_location() {
+        let xyxy = [-2.0, 3.0, 6.0, 9.0];
+
+        assert_eq!(
+            super::location(&xyxy, Some([1.5, 4.0])),
+            [-2.0, 3.0, 6.0, 9.0, 1.5, 4.0]
+        );
+        assert_eq!(
+            super::location(&xyxy, None),
+            [-2.0, 3.0, 6.0, 9.0, 2.0, 6.0]
+        );
+        assert_eq!(
+            super::location(&xyxy, Some([f32::NAN, 4.0])),
+            [-2.0, 3.0, 6.0, 9.0, 2.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn test_texture_scales() {
+        assert!(texture_scales(None).unwrap().is_empty());
+        assert_eq!(texture_scales(Some("1,2, 4")).unwrap(), vec![1, 2, 4]);
+        assert!(texture_scales(Some("1,0")).is_err());
+        assert!(texture_scales(Some("1,2,2")).is_err());
+        assert!(texture_scales(Some("x")).is_err());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let descriptors = || {
+            let mut descriptors = Descriptors::with_capacity(3);
+            descriptors.push(1, vec![1.0, f32::NAN]);
+            descriptors.push(2, vec![2.0, 3.0]);
+            descriptors.push(3, vec![f32::INFINITY, f32::NAN]);
+            descriptors.labels = Some(["x", "y", "z"].map(String::from).to_vec());
+            descriptors
+        };
+
+        let nonfinite = NonFinite {
+            columns: vec![1, 2],
+            objects: 2,
+        };
+
+        let mut kept = descriptors();
+        kept.sanitize(NonFinitePolicy::Keep);
+        assert_eq!(kept.nonfinite, nonfinite);
+        assert!(kept.columns[1][0].is_nan());
+
+        let mut zeroed = descriptors();
+        zeroed.sanitize(NonFinitePolicy::Zero);
+        assert_eq!(zeroed.nonfinite, nonfinite);
+        assert_eq!(
+            zeroed.columns,
+            vec![vec![1.0, 2.0, 0.0], vec![0.0, 3.0, 0.0]]
+        );
+
+        let mut dropped = descriptors();
+        dropped.sanitize(NonFinitePolicy::DropObject);
+        assert_eq!(dropped.nonfinite, nonfinite);
+        assert_eq!(dropped.ids, vec![2]);
+        assert_eq!(dropped.labels, Some(vec!["y".to_string()]));
+        assert_eq!(dropped.columns, vec![vec![2.0], vec![3.0]]);
+
+        let mut report = NonFiniteReport::default();
+        let columns = ["a".to_string(), "b".to_string()];
+        report.add(&columns, &nonfinite);
+        report.add(&columns, &dropped.nonfinite);
+        report.add(&columns, &NonFinite::default());
+
+        assert_eq!(report.objects(), 4);
+        assert_eq!(report.to_tsv(), "column\tobjects\timages\na\t2\t2\nb\t4\t2");
+    }
+
+    #[test]
+    fn test_push_truncated() {
+        // Rows of abandoned measurements are padded to the width of the other rows
+        let mut descriptors = Descriptors::with_capacity(3);
+        descriptors.push(1, vec![1.0]);
+        descriptors.push(2, vec![2.0, 3.0, 4.0]);
+        descriptors.push(3, vec![5.0, 6.0]);
+        descriptors.pad_columns(4);
+
+        let nan = |values: &[f32]| values.iter().map(|v| v.is_nan()).collect::<Vec<_>>();
+
+        assert_eq!(descriptors.columns.len(), 4);
+        assert_eq!(descriptors.columns[0], [1.0, 2.0, 5.0]);
+        assert_eq!(nan(&descriptors.columns[1]), [true, false, false]);
+        assert_eq!(nan(&descriptors.columns[2]), [true, false, true]);
+        assert_eq!(nan(&descriptors.columns[3]), [true; 3]);
+
+        descriptors.sanitize(NonFinitePolicy::Zero);
+        assert_eq!(descriptors.columns[2], [0.0, 4.0, 0.0]);
+    }
+
+    #[test]
+    fn test_sanitize_ratio_columns() {
+        let mut descriptors = Descriptors::with_capacity(2);
+        descriptors.push(1, vec![1.0, f32::NAN, 2.0]);
+        descriptors.push(2, vec![f32::NAN, 0.5, f32::NAN]);
+        descriptors.ratio_columns = 1..2;
+
+        descriptors.sanitize(NonFinitePolicy::DropObject);
+
+        // Undefined ratios are counted without dropping their object
+        assert_eq!(
+            descriptors.nonfinite,
+            NonFinite {
+                columns: vec![1, 1, 1],
+                objects: 2,
+            }
+        );
+        assert_eq!(descriptors.ids, vec![1]);
+        assert_eq!(descriptors.columns, vec![vec![1.0], vec![0.0], vec![2.0]]);
+    }
+
+    #[test]
+    fn test_border_policy() {
+        let directory = border_fixture("thyme_test_border_policy");
+        let input = directory.to_str().unwrap();
+        let output = directory.join("descriptors.csv");
+
+        for pad in ["0", "2"] {
+            for (policy, rows, flags) in [
+                (vec!["--border-policy", "keep"], 3, None),
+                (vec!["--border-policy", "drop"], 2, None),
+                (vec!["-d"], 2, None),
+                (
+                    vec!["--border-policy", "flag"],
+                    3,
+                    Some(["false", "false", "true"]),
+                ),
+            ] {
+                let mut args = vec![
+                    "-i",
+                    input,
+                    "-o",
+                    output.to_str().unwrap(),
+                    "--overwrite",
+                    "--image-substring",
+                    "_image",
+                    "--mask-substring",
+                    "_mask",
+                    "--pad",
+                    pad,
+                ];
+                args.extend(&policy);
+                profile(&args);
+
+                let table = std::fs::read_to_string(&output).unwrap();
+                let mut lines = table.lines();
+                let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+                let table: Vec<Vec<&str>> = lines.map(|row| row.split(',').collect()).collect();
+
+                assert_eq!(table.len(), rows, "{:?} pad {}", policy, pad);
+                assert_eq!(
+                    header.contains(&"touches_border"),
+                    flags.is_some(),
+                    "{:?}",
+                    policy
+                );
+
+                if let Some(flags) = flags {
+                    assert_eq!(header[2], "touches_border");
+
+                    let mut observed: Vec<(&str, &str)> =
+                        table.iter().map(|row| (row[1], row[2])).collect();
+                    observed.sort_unstable();
+
+                    let observed: Vec<&str> = observed.into_iter().map(|(_, flag)| flag).collect();
+                    assert_eq!(observed, flags, "pad {}", pad);
+                }
+            }
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_background_style() {
+        let directory = std::env::temp_dir().join("thyme_test_background_style");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        // Two 4 x 4 cells separated by a 2 pixel gap
+        let mut mask = vec![0u16; 16 * 12];
+
+        for (label, x) in [(1, 2..6), (2, 8..12)] {
+            for y in 4..8 {
+                for x in x.clone() {
+                    mask[y * 16 + x] = label;
+                }
+            }
+        }
+
+        let image: Vec<u8> = mask.iter().map(|&label| 50 * label as u8 + 10).collect();
+
+        ThymeImage::U8(ThymeBuffer::new(16, 12, 1, image).unwrap())
+            .save(directory.join("a_image.png"))
+            .unwrap();
+
+        io::write_numpy(directory.join("a_mask.npy"), mask, vec![12, 16]).unwrap();
+
+        let input = directory.to_str().unwrap();
+        let output = directory.join("descriptors.csv");
+
+        // Crops are padded so the crop of each cell includes the other cell
+        let background_max = |style: &str| -> Vec<f32> {
+            profile(&[
+                "-i",
+                input,
+                "-o",
+                output.to_str().unwrap(),
+                "--overwrite",
+                "--image-substring",
+                "_image",
+                "--mask-substring",
+                "_mask",
+                "--mode",
+                "b",
+                "--pad",
+                "4",
+                "--background-style",
+                style,
+            ]);
+
+            let table = std::fs::read_to_string(&output).unwrap();
+            let mut lines = table.lines();
+            let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+            let column = header
+                .iter()
+                .position(|&name| name == "background_intensity_max")
+                .unwrap();
+
+            lines
+                .map(|row| row.split(',').nth(column).unwrap().parse().unwrap())
+                .collect()
+        };
+
+        assert_eq!(background_max("bbox"), vec![110.0, 60.0]);
+        assert_eq!(background_max("ring:2"), vec![10.0, 10.0]);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
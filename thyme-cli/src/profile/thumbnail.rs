@@ -0,0 +1,108 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::PathBuf;
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::ThymeImage;
+
+/// Writes a letterboxed thumbnail of each profiled object
+///
+/// Thumbnails are written as `{image}_{object}.png` so that each row of the
+/// descriptors table can reference its object in interactive viewers.
+#[derive(Debug, Clone)]
+pub struct Thumbnails {
+    directory: PathBuf,
+    size: u32,
+}
+
+impl Thumbnails {
+    /// Initialize thumbnail output from command line arguments
+    ///
+    /// The thumbnail directory is created if it does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory to write thumbnails to
+    /// * `size` - Width and height of each thumbnail (pixels)
+    pub fn new(directory: Option<&str>, size: Option<u32>) -> Result<Option<Self>, ThymeError> {
+        let Some(directory) = directory else {
+            return Ok(None);
+        };
+
+        let size = size.unwrap_or(64);
+
+        if size == 0 {
+            return Err(ThymeError::OtherError(
+                "thumbnail_size must be a positive integer".to_string(),
+            ));
+        }
+
+        std::fs::create_dir_all(directory).map_err(|err| ThymeError::DirError(err.to_string()))?;
+
+        Ok(Some(Thumbnails {
+            directory: PathBuf::from(directory),
+            size,
+        }))
+    }
+
+    /// Write the thumbnail of an object and return its path
+    ///
+    /// Returns `None` without writing a file if the crop is degenerate (e.g.
+    /// a zero-area bounding box).
+    ///
+    /// # Arguments
+    ///
+    /// * `crop` - Padded crop of the object
+    /// * `image` - Name of the image the object was profiled from
+    /// * `object` - Index of the object in the segments
+    pub fn write(
+        &self,
+        crop: &ThymeImage,
+        image: &str,
+        object: u32,
+    ) -> Result<Option<String>, ThymeError> {
+        if crop.width() == 0 || crop.height() == 0 {
+            return Ok(None);
+        }
+
+        let path = self.directory.join(format!("{}_{}.png", image, object));
+        crop.thumbnail(self.size)?.save(&path)?;
+
+        Ok(Some(path.display().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_core::im::ThymeBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_thumbnails_write() {
+        let directory = std::env::temp_dir().join("thyme_test_thumbnails_write");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let thumbnails = Thumbnails::new(directory.to_str(), Some(16))
+            .unwrap()
+            .unwrap();
+
+        let crop = ThymeImage::U16(ThymeBuffer::new(5, 3, 1, vec![1000u16; 15]).unwrap());
+        let path = thumbnails.write(&crop, "a", 2).unwrap().unwrap();
+
+        assert_eq!(path, directory.join("a_2.png").display().to_string());
+        assert_eq!(ThymeImage::open(&path).unwrap().shape(), (16, 16, 1));
+
+        // Degenerate crops are skipped without writing a file
+        let empty = ThymeImage::U8(ThymeBuffer::new(0, 3, 1, vec![]).unwrap());
+        assert!(thumbnails.write(&empty, "a", 3).unwrap().is_none());
+        assert!(!directory.join("a_3.png").exists());
+
+        assert!(Thumbnails::new(None, Some(16)).unwrap().is_none());
+        assert!(Thumbnails::new(directory.to_str(), Some(0)).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
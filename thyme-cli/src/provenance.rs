@@ -0,0 +1,406 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local, Utc};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::ChannelMap;
+use thyme_core::io;
+use thyme_data::data::Weights;
+
+/// Suffix appended to an output artifact to name its provenance sidecar
+pub const PROVENANCE_SUFFIX: &str = ".provenance.json";
+
+/// Size of the buffer used when streaming files through the hasher
+const HASH_BUFFER_SIZE: usize = 1 << 20;
+
+/// Provenance record written alongside an output table or array
+#[derive(Debug, Serialize)]
+pub struct Provenance {
+    pub thyme_version: String,
+    pub git_hash: Option<String>,
+    pub command: Vec<String>,
+    pub arguments: serde_json::Value,
+    pub inputs: Vec<InputRecord>,
+    pub model: Option<ModelRecord>,
+    pub hostname: Option<String>,
+    pub timestamp: String,
+}
+
+/// Size, modification time, and optional hash of a single input file
+#[derive(Debug, Serialize)]
+pub struct InputRecord {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<String>,
+    pub sha256: Option<String>,
+}
+
+/// Neural network model and the weights used to compute embeddings
+#[derive(Debug, Serialize)]
+pub struct ModelRecord {
+    pub name: String,
+    pub weights: String,
+    pub sha256: String,
+    pub channel_map: String,
+}
+
+impl Provenance {
+    /// Record the provenance of a batch command
+    ///
+    /// # Arguments
+    ///
+    /// * `arguments` - Resolved command line arguments
+    /// * `inputs` - Input files read by the command
+    /// * `hash_inputs` - If true, record a sha256 hash for each input file
+    pub fn new<A: Serialize>(
+        arguments: &A,
+        inputs: &[PathBuf],
+        hash_inputs: bool,
+    ) -> Result<Self, ThymeError> {
+        let arguments = serde_json::to_value(arguments)
+            .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+        // Inputs are hashed in parallel so large batches are not bottlenecked
+        // on a single reader. Records remain in the same order as the inputs.
+        let inputs = inputs
+            .par_iter()
+            .map(|path| InputRecord::new(path, hash_inputs))
+            .collect::<Result<Vec<InputRecord>, ThymeError>>()?;
+
+        Ok(Self {
+            thyme_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: option_env!("THYME_GIT_HASH").map(|hash| hash.to_string()),
+            command: std::env::args().collect(),
+            arguments,
+            inputs,
+            model: None,
+            hostname: hostname(),
+            timestamp: Local::now().to_rfc3339(),
+        })
+    }
+
+    /// Attach the model name, a hash of its weights, and the input channel map
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Model name
+    /// * `weights` - Path to the model weights
+    /// * `channel_map` - Channel map applied to images before preprocessing
+    pub fn with_model(
+        mut self,
+        name: &str,
+        weights: &Path,
+        channel_map: &ChannelMap,
+    ) -> Result<Self, ThymeError> {
+        self.model = Some(ModelRecord {
+            name: name.to_string(),
+            weights: weights.to_string_lossy().to_string(),
+            sha256: sha256(weights)?,
+            channel_map: channel_map.describe(),
+        });
+
+        Ok(self)
+    }
+
+    /// Atomically write the provenance sidecar for an output artifact
+    ///
+    /// The record is written with `io::write_bytes_atomic` so a partially
+    /// written sidecar is never observed.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifact` - Path to the output table or array
+    pub fn write(&self, artifact: &Path) -> Result<PathBuf, ThymeError> {
+        let path = sidecar_path(artifact);
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+        io::write_bytes_atomic(&path, json)?;
+
+        Ok(path)
+    }
+}
+
+impl InputRecord {
+    fn new(path: &Path, hash: bool) -> Result<Self, ThymeError> {
+        let metadata = std::fs::metadata(path)
+            .map_err(|_| ThymeError::NoFileError(path.to_string_lossy().to_string()))?;
+
+        let modified = metadata.modified().ok().map(timestamp);
+
+        let sha256 = if hash { Some(sha256(path)?) } else { None };
+
+        Ok(Self {
+            path: path.to_string_lossy().to_string(),
+            size: metadata.len(),
+            modified,
+            sha256,
+        })
+    }
+}
+
+/// Write provenance sidecars for the artifacts produced by a batch command
+///
+/// # Arguments
+///
+/// * `arguments` - Resolved command line arguments
+/// * `inputs` - Input files read by the command
+/// * `model` - Name and channel map of the neural network model used by the command, if any
+/// * `hash_inputs` - If true, record a sha256 hash for each input file
+/// * `artifacts` - Output tables or arrays written by the command
+pub fn record<A: Serialize>(
+    arguments: &A,
+    inputs: &[PathBuf],
+    model: Option<(&str, &ChannelMap)>,
+    hash_inputs: bool,
+    artifacts: &[&Path],
+) -> Result<(), ThymeError> {
+    let mut provenance = Provenance::new(arguments, inputs, hash_inputs)?;
+
+    if let Some((model, channel_map)) = model {
+        provenance = provenance.with_model(model, &Weights::find(model)?.path(), channel_map)?;
+    }
+
+    for artifact in artifacts {
+        provenance.write(artifact)?;
+    }
+
+    Ok(())
+}
+
+/// Collect the input files read by a command operating on image and segment pairs
+///
+/// # Arguments
+///
+/// * `pairs` - Identifier, image, and segment paths for each pair
+/// * `metadata_table` - Optional metadata table joined to the output
+pub fn paired_inputs(
+    pairs: &[(String, PathBuf, PathBuf)],
+    metadata_table: Option<&str>,
+) -> Vec<PathBuf> {
+    pairs
+        .iter()
+        .flat_map(|(_, image, segments)| [image.clone(), segments.clone()])
+        .chain(metadata_table.map(PathBuf::from))
+        .collect()
+}
+
+/// Path of the provenance sidecar for an output artifact
+///
+/// # Arguments
+///
+/// * `artifact` - Path to the output table or array
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use thyme_cli::provenance::sidecar_path;
+///
+/// let path = sidecar_path(Path::new("output/descriptors.csv"));
+/// assert_eq!(path, Path::new("output/descriptors.csv.provenance.json"));
+/// ```
+pub fn sidecar_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_os_string();
+    name.push(PROVENANCE_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Compute the sha256 hash of a file by streaming its contents
+///
+/// # Arguments
+///
+/// * `path` - Path to file
+pub fn sha256(path: &Path) -> Result<String, ThymeError> {
+    let error = || ThymeError::NoFileError(path.to_string_lossy().to_string());
+
+    let mut reader =
+        BufReader::with_capacity(HASH_BUFFER_SIZE, File::open(path).map_err(|_| error())?);
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+    let mut hasher = Sha256::new();
+
+    loop {
+        let n = reader.read(&mut buffer).map_err(|_| error())?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Format a file modification time as an RFC 3339 timestamp
+fn timestamp(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339()
+}
+
+/// Name of the host running the command, if it can be determined
+fn hostname() -> Option<String> {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .chain(std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use clap::Parser;
+
+    use crate::profile::ProfileMaskArgs;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: ProfileMaskArgs,
+    }
+
+    const TEST_IMAGE: &str = "../data/tests/test_grayscale.png";
+    const TEST_MASK: &str = "../data/tests/test_mask_binary.png";
+
+    #[test]
+    fn test_provenance_schema() {
+        let cli = Cli::parse_from([
+            "thyme",
+            "--images",
+            "images/",
+            "--masks",
+            "masks/",
+            "--output",
+            "descriptors.csv",
+            "--mode",
+            "cf",
+            "--pad",
+            "3",
+            "--drop-borders",
+            "--provenance",
+            "--hash-inputs",
+        ]);
+
+        let inputs = vec![PathBuf::from(TEST_IMAGE), PathBuf::from(TEST_MASK)];
+        let provenance = Provenance::new(&cli.args, &inputs, true).unwrap();
+        let json = serde_json::to_value(&provenance).unwrap();
+
+        for field in [
+            "thyme_version",
+            "git_hash",
+            "command",
+            "arguments",
+            "inputs",
+            "model",
+            "hostname",
+            "timestamp",
+        ] {
+            assert!(json.get(field).is_some(), "Missing field {}", field);
+        }
+
+        assert_eq!(json["thyme_version"], env!("CARGO_PKG_VERSION"));
+
+        let arguments = &json["arguments"];
+        assert_eq!(arguments["images"], "images/");
+        assert_eq!(arguments["masks"], "masks/");
+        assert_eq!(arguments["output"], "descriptors.csv");
+        assert_eq!(arguments["mode"], "cf");
+        assert_eq!(arguments["pad"], 3);
+        assert_eq!(arguments["drop_borders"], true);
+        assert_eq!(arguments["verbose"], false);
+        assert_eq!(arguments["provenance"], true);
+        assert_eq!(arguments["hash_inputs"], true);
+        assert_eq!(arguments["min_size"], 1);
+
+        let records = json["inputs"].as_array().unwrap();
+        assert_eq!(records.len(), 2);
+
+        for (record, path) in records.iter().zip(&inputs) {
+            assert_eq!(record["path"], path.to_string_lossy().as_ref());
+            assert_eq!(record["size"], std::fs::metadata(path).unwrap().len());
+            assert!(record["modified"].is_string());
+            assert_eq!(record["sha256"].as_str().unwrap().len(), 64);
+        }
+    }
+
+    #[test]
+    fn test_provenance_without_hashes() {
+        let inputs = vec![PathBuf::from(TEST_IMAGE)];
+        let provenance = Provenance::new(&serde_json::json!({}), &inputs, false).unwrap();
+        assert!(provenance.inputs[0].sha256.is_none());
+
+        let missing = vec![PathBuf::from("../data/tests/does_not_exist.png")];
+        assert!(Provenance::new(&serde_json::json!({}), &missing, false).is_err());
+    }
+
+    #[test]
+    fn test_provenance_write() {
+        let directory = std::env::temp_dir().join("thyme_test_provenance_write");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let artifact = directory.join("descriptors.csv");
+        let inputs = vec![PathBuf::from(TEST_MASK)];
+
+        let provenance = Provenance::new(
+            &serde_json::json!({"output": "descriptors.csv"}),
+            &inputs,
+            false,
+        )
+        .unwrap()
+        .with_model(
+            "test",
+            Path::new(TEST_MASK),
+            &ChannelMap::parse("1,2,0").unwrap(),
+        )
+        .unwrap();
+
+        let path = provenance.write(&artifact).unwrap();
+        assert_eq!(path, directory.join("descriptors.csv.provenance.json"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert_eq!(json["arguments"]["output"], "descriptors.csv");
+        assert_eq!(json["model"]["name"], "test");
+        assert_eq!(json["model"]["channel_map"], "1,2,0");
+        assert_eq!(
+            json["model"]["sha256"],
+            sha256(Path::new(TEST_MASK)).unwrap()
+        );
+
+        let leftovers = std::fs::read_dir(&directory)
+            .unwrap()
+            .filter(|entry| io::is_temporary_file(entry.as_ref().unwrap().path()))
+            .count();
+
+        assert_eq!(leftovers, 0);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_sha256() {
+        let directory = std::env::temp_dir().join("thyme_test_sha256");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("abc.txt");
+        std::fs::write(&path, "abc").unwrap();
+
+        assert_eq!(
+            sha256(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
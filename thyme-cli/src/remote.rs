@@ -0,0 +1,373 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use thyme_core::error::ThymeError;
+use thyme_core::io;
+use thyme_core::ut;
+use thyme_data::remote::{self, ObjectStore, RemoteUrl};
+
+static STAGING_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Object of a remote input and the store it is fetched from
+struct RemoteObject {
+    store: Arc<dyn ObjectStore>,
+    url: String,
+    key: String,
+}
+
+/// Inputs listed from object storage and fetched as they are loaded
+///
+/// Remote files are selected like local files and mapped to staging paths
+/// so that the existing open functions can read them. Each object is fetched
+/// into memory and written to its staging path when it is first loaded. With
+/// a remote cache, staged objects are kept and re-used by later runs.
+/// Otherwise they are staged in a temporary directory, removed once their
+/// image is processed, and the directory is removed when the inputs are
+/// dropped.
+pub struct RemoteInputs {
+    staging: PathBuf,
+    persistent: bool,
+    objects: Mutex<HashMap<PathBuf, RemoteObject>>,
+}
+
+impl RemoteInputs {
+    /// Initialize remote inputs staged in a cache or temporary directory
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - Directory caching fetched objects across runs
+    pub fn new(cache: Option<&str>) -> Self {
+        let staging = match cache {
+            Some(cache) => PathBuf::from(cache),
+            None => std::env::temp_dir().join(format!(
+                "thyme_remote_{}_{}",
+                std::process::id(),
+                STAGING_COUNTER.fetch_add(1, Ordering::Relaxed)
+            )),
+        };
+
+        RemoteInputs {
+            staging,
+            persistent: cache.is_some(),
+            objects: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Select the files of a local directory or remote url sorted by path
+    ///
+    /// Remote objects are listed with every page of the listing, and globs
+    /// and regexes match their key relative to the url. Returns the local
+    /// paths of selected files and the staging paths of selected objects.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Local directory or s3:// or https:// url
+    /// * `valid_ext` - Valid file extensions
+    /// * `selector` - Substring, glob, or regex selecting files
+    /// * `recursive` - Also select files in subdirectories
+    pub fn select_file_paths(
+        &self,
+        path: &str,
+        valid_ext: &[&str],
+        selector: &ut::path::FileSelector,
+        recursive: bool,
+    ) -> Result<Vec<PathBuf>, ThymeError> {
+        if !remote::is_remote(path) {
+            return ut::path::select_file_paths(path, valid_ext, selector, recursive);
+        }
+
+        let url = RemoteUrl::parse(path).map_err(remote_error)?;
+        let store = url.store().map_err(remote_error)?;
+
+        self.select_from_store(&url, Arc::new(store), valid_ext, selector, recursive)
+    }
+
+    /// Select the objects of a remote url from an object store
+    fn select_from_store(
+        &self,
+        url: &RemoteUrl,
+        store: Arc<dyn ObjectStore>,
+        valid_ext: &[&str],
+        selector: &ut::path::FileSelector,
+        recursive: bool,
+    ) -> Result<Vec<PathBuf>, ThymeError> {
+        // Urls name a directory, or otherwise a key prefix within a directory
+        // (e.g. s3://bucket/plate1/img_) whose keys are selected relative to it
+        let keys = remote::list_keys(store.as_ref(), &url.prefix).map_err(remote_error)?;
+        let directory = url.directory();
+
+        let directory =
+            match url.prefix.is_empty() || keys.iter().any(|k| k.starts_with(&directory)) {
+                true => directory,
+                false => url.prefix[..url.prefix.rfind('/').map_or(0, |idx| idx + 1)].to_string(),
+            };
+
+        let root = match url.host.is_empty() {
+            true => self.staging.join("s3").join(&url.bucket),
+            false => self
+                .staging
+                .join("https")
+                .join(url.host.replace(':', "_"))
+                .join(&url.bucket),
+        };
+
+        let mut objects = self.objects.lock().unwrap();
+        let mut files = vec![];
+
+        for key in keys {
+            let Some(relative) = key.strip_prefix(&directory) else {
+                continue;
+            };
+
+            let relative = Path::new(relative);
+
+            // Keys that would be staged outside of the staging directory are ignored
+            if relative
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+            {
+                continue;
+            }
+
+            if !recursive && relative.components().count() > 1 {
+                continue;
+            }
+
+            let valid = relative
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| valid_ext.contains(&ext));
+
+            if !valid || !selector.matches(relative) {
+                continue;
+            }
+
+            let path = root.join(&key);
+
+            objects.insert(
+                path.clone(),
+                RemoteObject {
+                    store: store.clone(),
+                    url: url.object_url(&key),
+                    key,
+                },
+            );
+
+            files.push(path);
+        }
+
+        files.sort_unstable();
+
+        Ok(files)
+    }
+
+    /// Fetch a remote input to its staging path before it is opened
+    ///
+    /// Local files are left as is, and objects already staged in a remote
+    /// cache are not fetched again.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path returned by `select_file_paths`
+    pub fn fetch(&self, path: &Path) -> Result<(), ThymeError> {
+        let (store, url, key) = match self.objects.lock().unwrap().get(path) {
+            Some(object) => (object.store.clone(), object.url.clone(), object.key.clone()),
+            None => return Ok(()),
+        };
+
+        if self.persistent && path.is_file() {
+            return Ok(());
+        }
+
+        let contents = store
+            .get(&key)
+            .map_err(|err| ThymeError::OtherError(format!("Failed to fetch {}. {:#}", url, err)))?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|err| ThymeError::DirError(format!("{}. {}", parent.display(), err)))?;
+        }
+
+        io::write_bytes_atomic(path, contents)
+    }
+
+    /// Remove the staged copies of remote inputs once they are processed
+    ///
+    /// Files are removed when the returned guard is dropped, unless they are
+    /// kept in a remote cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Paths returned by `select_file_paths`
+    pub fn staged<'a>(&'a self, paths: &[&'a Path]) -> Staged<'a> {
+        Staged {
+            remote: self,
+            paths: paths.to_vec(),
+        }
+    }
+
+    /// Remove the staged copy of a remote input
+    fn release(&self, path: &Path) {
+        if !self.persistent && self.objects.lock().unwrap().contains_key(path) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Drop for RemoteInputs {
+    fn drop(&mut self) {
+        if !self.persistent && !self.objects.get_mut().unwrap().is_empty() {
+            let _ = std::fs::remove_dir_all(&self.staging);
+        }
+    }
+}
+
+/// Removes the staged copies of remote inputs when dropped
+pub struct Staged<'a> {
+    remote: &'a RemoteInputs,
+    paths: Vec<&'a Path>,
+}
+
+impl Drop for Staged<'_> {
+    fn drop(&mut self) {
+        for path in self.paths.iter() {
+            self.remote.release(path);
+        }
+    }
+}
+
+fn remote_error<E: std::fmt::Display>(err: E) -> ThymeError {
+    ThymeError::OtherError(format!("{:#}", err))
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_data::remote::MemoryStore;
+
+    use super::*;
+    use crate::prefetch::Prefetch;
+
+    fn store() -> Arc<dyn ObjectStore> {
+        let mut store = MemoryStore::new(2);
+
+        for key in [
+            "plate/a01_image.png",
+            "plate/a01_mask.png",
+            "plate/a02_image.png",
+            "plate/a03_image.png",
+            "plate/notes.txt",
+            "plate/nested/a04_image.png",
+            "plate2/a05_image.png",
+        ] {
+            store.insert(key, key.as_bytes().to_vec());
+        }
+
+        store.fail("plate/a03_image.png");
+
+        Arc::new(store)
+    }
+
+    #[test]
+    fn test_remote_inputs() {
+        let remote = RemoteInputs::new(None);
+        let url = RemoteUrl::parse("s3://bucket/plate/").unwrap();
+        let selector = ut::path::FileSelector::new(Some("_image"), None, None).unwrap();
+
+        let files = remote
+            .select_from_store(&url, store(), &["png"], &selector, false)
+            .unwrap();
+
+        let names: Vec<String> = files
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+
+        assert_eq!(names, ["a01_image.png", "a02_image.png", "a03_image.png"]);
+        assert!(files.iter().all(|path| path.starts_with(&remote.staging)));
+
+        remote.fetch(&files[0]).unwrap();
+        assert_eq!(std::fs::read(&files[0]).unwrap(), b"plate/a01_image.png");
+
+        {
+            let _staged = remote.staged(&[&files[0]]);
+        }
+
+        assert!(!files[0].exists());
+
+        // Failed fetches name the object and leave nothing behind
+        let err = remote.fetch(&files[2]).unwrap_err().to_string();
+        assert!(err.contains("s3://bucket/plate/a03_image.png"), "{}", err);
+        assert!(!files[2].exists());
+
+        // Fetch failures are per-image load errors like local IO failures
+        let results = Prefetch::new(Some(2), None).unwrap().run(
+            &files,
+            ut::track::progress_bar(files.len(), "Fetching", false),
+            |path| remote.fetch(path),
+            |path, _| Ok(std::fs::read(path).unwrap().len()),
+        );
+
+        assert_eq!(results[0].as_ref().unwrap(), &19);
+        assert_eq!(results[1].as_ref().unwrap(), &19);
+        assert!(results[2].is_err());
+
+        // Local paths are not fetched
+        remote.fetch(Path::new("images/a01.png")).unwrap();
+
+        // Urls that are not directories select keys by prefix within their directory
+        let select = |url: &str| {
+            let url = RemoteUrl::parse(url).unwrap();
+            let files = remote
+                .select_from_store(&url, store(), &["png"], &selector, false)
+                .unwrap();
+
+            files
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(select("s3://bucket/plate/a0"), names);
+        assert_eq!(select("s3://bucket/plate/a01_"), ["a01_image.png"]);
+        assert_eq!(select("s3://bucket/plate2"), ["a05_image.png"]);
+        assert!(select("s3://bucket/plat").is_empty());
+
+        let staging = remote.staging.clone();
+        remote.fetch(&files[1]).unwrap();
+        drop(remote);
+
+        assert!(!staging.exists());
+    }
+
+    #[test]
+    fn test_remote_inputs_cache() {
+        let cache = std::env::temp_dir().join("thyme_test_remote_cache");
+        let _ = std::fs::remove_dir_all(&cache);
+
+        let remote = RemoteInputs::new(cache.to_str());
+        let url = RemoteUrl::parse("https://minio.local:9000/bucket/plate").unwrap();
+        let selector = ut::path::FileSelector::new(None, Some("**/*_image.png"), None).unwrap();
+
+        let files = remote
+            .select_from_store(&url, store(), &["png"], &selector, true)
+            .unwrap();
+
+        assert_eq!(files.len(), 4);
+        assert!(files[0].starts_with(cache.join("https").join("minio.local_9000")));
+
+        remote.fetch(&files[0]).unwrap();
+        drop(remote.staged(&[&files[0]]));
+        drop(remote);
+
+        // Cached objects are kept and re-used by later runs
+        assert!(files[0].is_file());
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+}
@@ -0,0 +1,750 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use kdam::{Bar, BarExt, tqdm};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, List, ListItem, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use thyme_core::error::ThymeError;
+use thyme_core::ut;
+
+/// Number of recent errors kept for the status panel
+const RECENT_ERRORS: usize = 5;
+
+/// Interval between redraws of the status panel
+const REFRESH: Duration = Duration::from_millis(500);
+
+/// Interval between status lines logged when stdout is not a terminal
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Counters of a running batch command shared by its workers and progress displays
+///
+/// Inputs (e.g. image and segment pairs) are counted as they finish and
+/// objects as they are reported by the workers, so the progress bar and the
+/// status panel always show the same numbers.
+#[derive(Debug)]
+pub struct RunStatus {
+    total: usize,
+    images: AtomicUsize,
+    failures: AtomicUsize,
+    objects: AtomicUsize,
+    workers: Mutex<BTreeMap<usize, String>>,
+    errors: Mutex<VecDeque<String>>,
+    start: Instant,
+}
+
+impl RunStatus {
+    /// Initialize the counters of a run
+    ///
+    /// # Arguments
+    ///
+    /// * `total` - Number of inputs processed by the run
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            images: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+            objects: AtomicUsize::new(0),
+            workers: Mutex::new(BTreeMap::new()),
+            errors: Mutex::new(VecDeque::with_capacity(RECENT_ERRORS)),
+            start: Instant::now(),
+        }
+    }
+
+    /// Record the input the current worker thread started processing
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the input (e.g. image stem)
+    pub fn begin(&self, name: &str) {
+        self.workers
+            .lock()
+            .unwrap()
+            .insert(worker(), name.to_string());
+    }
+
+    /// Record objects processed by the current worker thread
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - Number of processed objects
+    pub fn add_objects(&self, n: usize) {
+        self.objects.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Record that the current worker thread finished its input
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - Error of the input, if it failed
+    pub fn finish(&self, error: Option<&ThymeError>) {
+        let name = self.workers.lock().unwrap().remove(&worker());
+
+        self.images.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(error) = error {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+
+            let mut errors = self.errors.lock().unwrap();
+
+            if errors.len() == RECENT_ERRORS {
+                errors.pop_front();
+            }
+
+            errors.push_back(match name {
+                Some(name) => format!("{}: {}", name, error),
+                None => error.to_string(),
+            });
+        }
+    }
+
+    /// Copy the current counters
+    pub fn snapshot(&self) -> StatusSnapshot {
+        StatusSnapshot {
+            total: self.total,
+            images: self.images.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            objects: self.objects.load(Ordering::Relaxed),
+            elapsed: self.start.elapsed(),
+            workers: self
+                .workers
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(k, name)| (*k, name.clone()))
+                .collect(),
+            errors: self.errors.lock().unwrap().iter().cloned().collect(),
+            memory: resident_memory(),
+        }
+    }
+}
+
+/// Counters of a run at a single point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusSnapshot {
+    /// Number of inputs processed by the run
+    pub total: usize,
+    /// Number of finished inputs, including failures
+    pub images: usize,
+    /// Number of inputs that failed
+    pub failures: usize,
+    /// Number of processed objects
+    pub objects: usize,
+    /// Time since the run started
+    pub elapsed: Duration,
+    /// Worker thread index and the input it is processing
+    pub workers: Vec<(usize, String)>,
+    /// Most recent errors, oldest first
+    pub errors: Vec<String>,
+    /// Resident memory of the process in bytes, if known
+    pub memory: Option<u64>,
+}
+
+impl StatusSnapshot {
+    /// Estimated time until every input is finished at the current throughput
+    pub fn eta(&self) -> Option<Duration> {
+        if self.images == 0 {
+            return None;
+        }
+
+        let remaining = self.total.saturating_sub(self.images) as f64;
+        Some(self.elapsed.mul_f64(remaining / self.images as f64))
+    }
+
+    /// Inputs and objects finished per second
+    pub fn throughput(&self) -> (f64, f64) {
+        let seconds = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        (self.images as f64 / seconds, self.objects as f64 / seconds)
+    }
+
+    /// Labeled counters shown by the status panel and the plain status log
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let percentage = match self.total {
+            0 => 100.0,
+            total => 100.0 * self.images as f64 / total as f64,
+        };
+
+        let (images, objects) = self.throughput();
+
+        vec![
+            (
+                "Images",
+                format!(
+                    "{}/{} ({:.1}%), {} failed",
+                    ut::track::thousands_format(self.images),
+                    ut::track::thousands_format(self.total),
+                    percentage,
+                    ut::track::thousands_format(self.failures)
+                ),
+            ),
+            ("Objects", ut::track::thousands_format(self.objects)),
+            (
+                "Throughput",
+                format!("{:.2} images/s, {:.1} objects/s", images, objects),
+            ),
+            (
+                "Elapsed",
+                format!(
+                    "{}, eta {}",
+                    clock(self.elapsed),
+                    self.eta().map_or("unknown".to_string(), clock)
+                ),
+            ),
+            (
+                "Memory",
+                self.memory.map_or("unknown".to_string(), |bytes| {
+                    format!("{:.1} MiB", bytes as f64 / (1u64 << 20) as f64)
+                }),
+            ),
+        ]
+    }
+
+    /// Status line of the plain status log
+    ///
+    /// # Arguments
+    ///
+    /// * `desc` - Description of the run (e.g. Profiling)
+    pub fn plain(&self, desc: &str) -> String {
+        let fields: Vec<String> = self
+            .fields()
+            .into_iter()
+            .map(|(label, value)| format!("{} {}", label, value))
+            .collect();
+
+        format!(
+            "[thyme] {} {} | {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            desc,
+            fields.join(" | ")
+        )
+    }
+
+    /// Status record of the json status log
+    ///
+    /// # Arguments
+    ///
+    /// * `desc` - Description of the run (e.g. Profiling)
+    pub fn json(&self, desc: &str) -> serde_json::Value {
+        let (images_per_second, objects_per_second) = self.throughput();
+
+        serde_json::json!({
+            "time": chrono::Local::now().to_rfc3339(),
+            "desc": desc,
+            "total": self.total,
+            "images": self.images,
+            "failures": self.failures,
+            "objects": self.objects,
+            "images_per_second": images_per_second,
+            "objects_per_second": objects_per_second,
+            "elapsed_seconds": self.elapsed.as_secs_f64(),
+            "eta_seconds": self.eta().map(|eta| eta.as_secs_f64()),
+            "memory_bytes": self.memory,
+            "workers": self
+                .workers
+                .iter()
+                .map(|(k, name)| serde_json::json!({ "worker": k, "input": name }))
+                .collect::<Vec<_>>(),
+            "errors": self.errors,
+        })
+    }
+}
+
+/// Format of the status log written instead of the status panel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One human readable line per update
+    #[default]
+    Plain,
+    /// One json record per update
+    Json,
+}
+
+impl LogFormat {
+    /// Parse a log format
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Name of the format (plain, json)
+    pub fn new(format: &str) -> Result<Self, ThymeError> {
+        match format {
+            "plain" => Ok(LogFormat::Plain),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid log format {}. Must be one of: plain, json",
+                format
+            ))),
+        }
+    }
+}
+
+/// Progress display of a batch command
+///
+/// Progress is shown with the plain progress bar, with a status panel
+/// redrawn by a reporting thread if requested and the output is a terminal,
+/// or with status lines logged by the reporting thread otherwise. All of them
+/// are fed by the same `RunStatus`.
+pub struct Progress {
+    bar: Bar,
+    status: Arc<RunStatus>,
+    // Stops the reporting thread after its last update once the run is dropped
+    _reporter: Option<Reporter>,
+}
+
+impl Progress {
+    /// Track progress with the status panel if requested, otherwise with the progress bar
+    ///
+    /// The status panel is drawn on stdout. If stdout is not a terminal, the
+    /// panel falls back to status lines logged on stdout every few seconds,
+    /// which are also logged instead of the progress bar in the json format.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - Number of inputs processed by the run
+    /// * `desc` - Description of the run (e.g. Profiling)
+    /// * `verbose` - Show the progress bar
+    /// * `tui` - Show the status panel instead of the progress bar
+    /// * `log_format` - Format of the status lines (plain, json)
+    pub fn new(n: usize, desc: &str, verbose: bool, tui: bool, log_format: &str) -> Self {
+        let log_format = LogFormat::new(log_format).unwrap_or_default();
+        Self::with_writer(n, desc, verbose, tui, log_format, std::io::stdout())
+    }
+
+    /// Track progress with a status panel or status log written on a writer
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - Number of inputs processed by the run
+    /// * `desc` - Description of the run (e.g. Profiling)
+    /// * `verbose` - Show the progress bar
+    /// * `tui` - Show the status panel instead of the progress bar
+    /// * `log_format` - Format of the status lines
+    /// * `writer` - Terminal or log the status is written on
+    pub fn with_writer<W>(
+        n: usize,
+        desc: &str,
+        verbose: bool,
+        tui: bool,
+        log_format: LogFormat,
+        writer: W,
+    ) -> Self
+    where
+        W: Write + IsTerminal + Send + 'static,
+    {
+        let status = Arc::new(RunStatus::new(n));
+        let reporter = match (tui, writer.is_terminal(), log_format) {
+            (true, true, _) => Panel::new(desc, writer)
+                .ok()
+                .map(|panel| Reporter::spawn(status.clone(), REFRESH, panel)),
+            (true, false, _) | (_, _, LogFormat::Json) => Some(Reporter::spawn(
+                status.clone(),
+                LOG_INTERVAL,
+                StatusLog {
+                    desc: desc.to_string(),
+                    format: log_format,
+                    writer,
+                },
+            )),
+            _ => None,
+        };
+
+        Progress {
+            bar: match reporter {
+                Some(_) => tqdm!(disable = true),
+                None => ut::track::progress_bar(n, desc, verbose),
+            },
+            status,
+            _reporter: reporter,
+        }
+    }
+
+    /// Counters shared with the workers of the run
+    pub fn status(&self) -> Arc<RunStatus> {
+        self.status.clone()
+    }
+
+    /// Record a finished input
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - Result of the input
+    pub fn update<R>(&mut self, result: &Result<R, ThymeError>) {
+        self.status.finish(result.as_ref().err());
+        let _ = self.bar.update(1);
+    }
+}
+
+impl From<Bar> for Progress {
+    fn from(bar: Bar) -> Self {
+        Progress {
+            status: Arc::new(RunStatus::new(bar.total)),
+            bar,
+            _reporter: None,
+        }
+    }
+}
+
+/// Display of the counters of a run updated by the reporting thread
+trait Report: Send + 'static {
+    fn report(&mut self, snapshot: &StatusSnapshot);
+}
+
+/// Reporting thread of the status panel or status log, stopped when dropped
+struct Reporter {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Reporter {
+    fn spawn<R: Report>(status: Arc<RunStatus>, interval: Duration, mut display: R) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let stop = stop.clone();
+
+            std::thread::spawn(move || {
+                loop {
+                    // Report once more after stopping so the last update is complete
+                    let stopping = stop.load(Ordering::Relaxed);
+                    display.report(&status.snapshot());
+
+                    if stopping {
+                        break;
+                    }
+
+                    std::thread::park_timeout(interval);
+                }
+            })
+        };
+
+        Reporter {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Reporter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Status panel drawn on the alternate screen of a terminal, restored when dropped
+struct Panel<W: Write> {
+    desc: String,
+    terminal: Terminal<CrosstermBackend<W>>,
+}
+
+impl<W: Write> Panel<W> {
+    fn new(desc: &str, writer: W) -> std::io::Result<Self> {
+        let mut terminal = Terminal::new(CrosstermBackend::new(writer))?;
+
+        execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+        terminal.hide_cursor()?;
+        terminal.clear()?;
+
+        Ok(Panel {
+            desc: desc.to_string(),
+            terminal,
+        })
+    }
+}
+
+impl<W: Write + Send + 'static> Report for Panel<W> {
+    fn report(&mut self, snapshot: &StatusSnapshot) {
+        let _ = self
+            .terminal
+            .draw(|frame| render(frame, snapshot, &self.desc));
+    }
+}
+
+impl<W: Write> Drop for Panel<W> {
+    fn drop(&mut self) {
+        let _ = self.terminal.show_cursor();
+        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+    }
+}
+
+/// Status lines logged on a writer that is not a terminal
+struct StatusLog<W> {
+    desc: String,
+    format: LogFormat,
+    writer: W,
+}
+
+impl<W: Write + Send + 'static> Report for StatusLog<W> {
+    fn report(&mut self, snapshot: &StatusSnapshot) {
+        let line = match self.format {
+            LogFormat::Plain => snapshot.plain(&self.desc),
+            LogFormat::Json => snapshot.json(&self.desc).to_string(),
+        };
+
+        let _ = writeln!(self.writer, "{}", line).and_then(|_| self.writer.flush());
+    }
+}
+
+/// Draw the status panel of a run on a frame
+///
+/// # Arguments
+///
+/// * `frame` - Frame covering the terminal
+/// * `snapshot` - Counters of the run
+/// * `desc` - Description of the run (e.g. Profiling)
+fn render(frame: &mut Frame, snapshot: &StatusSnapshot, desc: &str) {
+    let fields = snapshot.fields();
+    let errors = snapshot.errors.len().max(1) as u16;
+
+    let [progress, counters, workers, recent] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(fields.len() as u16 + 1),
+        Constraint::Min(3),
+        Constraint::Length(errors + 2),
+    ])
+    .areas(frame.area());
+
+    let ratio = match snapshot.total {
+        0 => 1.0,
+        total => (snapshot.images as f64 / total as f64).min(1.0),
+    };
+
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::bordered().title(format!(" [thyme] {} ", desc)))
+            .gauge_style(Style::default().fg(Color::Green))
+            .ratio(ratio)
+            .label(fields[0].1.clone()),
+        progress,
+    );
+
+    let counters_text: Vec<Line> = fields
+        .iter()
+        .skip(1)
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(format!(" {:<11}", label), Style::default().bold()),
+                Span::raw(value.clone()),
+            ])
+        })
+        .collect();
+
+    frame.render_widget(Paragraph::new(counters_text), counters);
+
+    let worker_items: Vec<ListItem> = snapshot
+        .workers
+        .iter()
+        .map(|(k, name)| ListItem::new(format!("{:>3} {}", k, name)))
+        .collect();
+
+    frame.render_widget(
+        List::new(worker_items).block(Block::bordered().title(" Workers ")),
+        workers,
+    );
+
+    let error_items: Vec<ListItem> = snapshot
+        .errors
+        .iter()
+        .map(|error| ListItem::new(error.as_str()).style(Style::default().fg(Color::Red)))
+        .collect();
+
+    frame.render_widget(
+        List::new(error_items).block(Block::bordered().title(" Recent errors ")),
+        recent,
+    );
+}
+
+/// Index of the current rayon worker thread
+fn worker() -> usize {
+    rayon::current_thread_index().unwrap_or(0)
+}
+
+/// Format a duration as hours, minutes, and seconds
+fn clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+/// Resident memory of the current process in bytes
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<u64> {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|kb| kb.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+}
+
+/// Resident memory of the current process in bytes
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_run_status() {
+        let status = RunStatus::new(4);
+
+        status.begin("a01");
+        status.add_objects(3);
+        status.finish(None);
+
+        status.begin("a02");
+        status.finish(Some(&ThymeError::OtherError("unreadable".to_string())));
+
+        let snapshot = status.snapshot();
+
+        assert_eq!(
+            (snapshot.images, snapshot.failures, snapshot.objects),
+            (2, 1, 3)
+        );
+        assert!(snapshot.workers.is_empty());
+        assert_eq!(
+            snapshot.errors,
+            ["a02: [thyme::OtherError] Error: unreadable."]
+        );
+        assert!(snapshot.eta().unwrap() <= snapshot.elapsed);
+
+        assert_eq!(
+            snapshot.fields()[0],
+            ("Images", "2/4 (50.0%), 1 failed".to_string())
+        );
+        assert!(
+            snapshot
+                .plain("Profiling")
+                .contains("Profiling | Images 2/4 (50.0%), 1 failed | Objects 3 |")
+        );
+
+        let json = snapshot.json("Profiling");
+        assert_eq!(
+            (json["images"].as_u64(), json["objects"].as_u64()),
+            (Some(2), Some(3))
+        );
+        assert_eq!(
+            json["errors"][0],
+            "a02: [thyme::OtherError] Error: unreadable."
+        );
+    }
+
+    #[test]
+    fn test_render_panel() {
+        let status = RunStatus::new(4);
+
+        status.begin("a01");
+        status.finish(Some(&ThymeError::OtherError("unreadable".to_string())));
+        status.begin("a02");
+
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(60, 20)).unwrap();
+        terminal
+            .draw(|frame| render(frame, &status.snapshot(), "Profiling"))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect();
+
+        let contains = |text: &str| rows.iter().any(|row| row.contains(text));
+
+        assert!(contains("[thyme] Profiling"));
+        assert!(contains("1/4 (25.0%), 1 failed"));
+        assert!(contains("Throughput"));
+        assert!(contains("  0 a02"));
+        assert!(contains("a01: [thyme::OtherError] Error: unreadable."));
+    }
+
+    #[test]
+    fn test_recent_errors() {
+        let status = RunStatus::new(10);
+
+        for k in 0..10 {
+            status.begin(&format!("image_{}", k));
+            status.finish(Some(&ThymeError::OtherError("failed".to_string())));
+        }
+
+        let errors = status.snapshot().errors;
+
+        assert_eq!(errors.len(), RECENT_ERRORS);
+        assert!(errors[0].starts_with("image_5"));
+        assert!(errors[RECENT_ERRORS - 1].starts_with("image_9"));
+    }
+
+    #[test]
+    fn test_progress_falls_back_without_terminal() {
+        let logged = |name: &str, tui: bool, log_format: LogFormat| {
+            let path = std::env::temp_dir().join(name);
+            let file = std::fs::File::create(&path).unwrap();
+
+            let mut progress = Progress::with_writer(2, "Profiling", false, tui, log_format, file);
+
+            progress.update(&Ok::<(), ThymeError>(()));
+            progress.update(&Err::<(), ThymeError>(ThymeError::OtherError(
+                "failed".to_string(),
+            )));
+
+            let snapshot = progress.status().snapshot();
+            assert_eq!((snapshot.images, snapshot.failures), (2, 1));
+
+            drop(progress);
+
+            let log = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            log
+        };
+
+        // The status panel falls back to status lines ending with the final counts
+        let log = logged("thyme_test_status_plain.txt", true, LogFormat::Plain);
+        assert!(
+            log.lines()
+                .last()
+                .unwrap()
+                .contains("Profiling | Images 2/2 (100.0%), 1 failed"),
+            "{}",
+            log
+        );
+
+        // Json status lines are logged with or without the status panel
+        let log = logged("thyme_test_status_json.txt", false, LogFormat::Json);
+        let last: serde_json::Value = serde_json::from_str(log.lines().last().unwrap()).unwrap();
+        assert_eq!(last["desc"], "Profiling");
+        assert_eq!(
+            (last["images"].as_u64(), last["failures"].as_u64()),
+            (Some(2), Some(1))
+        );
+
+        // Nothing is written without the status panel or json status lines
+        assert!(logged("thyme_test_status_bar.txt", false, LogFormat::Plain).is_empty());
+        assert!(LogFormat::new("xml").is_err());
+    }
+}
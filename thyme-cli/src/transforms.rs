@@ -0,0 +1,204 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use polars::prelude::*;
+
+use thyme_core::cv::transform::AffineTransform;
+use thyme_core::error::ThymeError;
+use thyme_core::io;
+
+/// Columns of a segment transform table holding the affine coefficients
+pub const COEFFICIENTS: [&str; 6] = ["a", "b", "c", "d", "tx", "ty"];
+
+/// Affine transforms registering the segments of each image to the image
+///
+/// Transforms are read from a table with an image column and one column per
+/// coefficient (a, b, c, d, tx, ty) mapping segment pixel coordinates to
+/// image pixel coordinates as `x' = a * x + b * y + tx` and
+/// `y' = c * x + d * y + ty`.
+#[derive(Debug, Clone, Default)]
+pub struct SegmentTransforms {
+    transforms: HashMap<String, AffineTransform>,
+}
+
+impl SegmentTransforms {
+    /// Read segment transforms from a table
+    ///
+    /// Images are identified by their file stem, so values of the image
+    /// column may include an extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to a csv, tsv, txt, parquet, pq, arrow, or feather table
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SegmentTransforms, ThymeError> {
+        let table = io::read_table(&path)?;
+        let path = path.as_ref().display();
+
+        let images = table
+            .column("image")
+            .and_then(|column| column.cast(&DataType::String))
+            .map_err(|_| {
+                ThymeError::OtherError(format!(
+                    "Segment transform table is missing column: image ({})",
+                    path
+                ))
+            })?;
+
+        let coefficients = COEFFICIENTS
+            .iter()
+            .map(|name| {
+                table
+                    .column(name)
+                    .and_then(|column| column.cast(&DataType::Float32))
+                    .map_err(|_| {
+                        ThymeError::OtherError(format!(
+                            "Segment transform table is missing numeric column: {} ({})",
+                            name, path
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<Column>, ThymeError>>()?;
+
+        let coefficients: Vec<Vec<Option<f32>>> = coefficients
+            .iter()
+            .map(|column| column.f32().unwrap().into_iter().collect())
+            .collect();
+
+        let mut transforms = HashMap::new();
+
+        for (row, image) in images.str().unwrap().into_iter().enumerate() {
+            let Some(image) = image else {
+                return Err(ThymeError::OtherError(format!(
+                    "Segment transform table has a missing image in row {} ({})",
+                    row + 1,
+                    path
+                )));
+            };
+
+            let mut values = [0f32; 6];
+
+            for (value, column) in values.iter_mut().zip(&coefficients) {
+                *value = column[row].ok_or_else(|| {
+                    ThymeError::OtherError(format!(
+                        "Segment transform of {} has missing coefficients ({})",
+                        image, path
+                    ))
+                })?;
+            }
+
+            let transform = AffineTransform::new(values).map_err(|err| match err {
+                ThymeError::OtherError(message) => {
+                    ThymeError::OtherError(format!("{} for image {}", message, image))
+                }
+                err => err,
+            })?;
+
+            let name = Path::new(image)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            if transforms.insert(name, transform).is_some() {
+                return Err(ThymeError::OtherError(format!(
+                    "Segment transform table has more than one row for image {} ({})",
+                    image, path
+                )));
+            }
+        }
+
+        Ok(SegmentTransforms { transforms })
+    }
+
+    /// Number of images with a transform
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+
+    /// Check if there are no transforms
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Transform of an image
+    ///
+    /// The image column is matched against the image file stem, either in full
+    /// or with the image substring removed (i.e. the image identifier).
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Image identifier
+    /// * `image` - Path to the image
+    pub fn get(&self, id: &str, image: &Path) -> Result<&AffineTransform, ThymeError> {
+        let name = image
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        self.transforms
+            .get(&name)
+            .or_else(|| self.transforms.get(id))
+            .ok_or_else(|| {
+                ThymeError::OtherError(format!(
+                    "Image {} has no row in the segment transform table",
+                    name
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_segment_transforms() {
+        let directory = std::env::temp_dir().join("thyme_test_segment_transforms");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("transforms.csv");
+        std::fs::write(
+            &path,
+            "image,a,b,c,d,tx,ty\nplate_A01_image.tif,1,0,0,1,5,-3\nA02,0,-1,1,0,9,0\n",
+        )
+        .unwrap();
+
+        let transforms = SegmentTransforms::open(&path).unwrap();
+        assert_eq!(transforms.len(), 2);
+
+        // Rows match file stems in full or the image identifier
+        let a01 = transforms
+            .get("plate_A01", Path::new("images/plate_A01_image.png"))
+            .unwrap();
+        assert_eq!(a01.apply([1., 1.]), [6., -2.]);
+
+        let a02 = transforms
+            .get("A02", Path::new("images/A02_image.png"))
+            .unwrap();
+        assert_eq!(a02.apply([2., 3.]), [6., 2.]);
+
+        let missing = transforms.get("A03", Path::new("images/A03_image.png"));
+        assert!(missing.unwrap_err().to_string().contains("A03_image"));
+
+        // Singular matrices, missing coefficients, and duplicate images are rejected
+        for (name, contents) in [
+            ("singular.csv", "image,a,b,c,d,tx,ty\nA01,1,2,2,4,0,0\n"),
+            ("missing.csv", "image,a,b,c,d,tx,ty\nA01,1,0,0,,0,0\n"),
+            ("columns.csv", "image,a,b,c,d,tx\nA01,1,0,0,1,0\n"),
+            (
+                "duplicate.csv",
+                "image,a,b,c,d,tx,ty\nA01,1,0,0,1,0,0\nA01.tif,1,0,0,1,0,0\n",
+            ),
+        ] {
+            let path = directory.join(name);
+            std::fs::write(&path, contents).unwrap();
+            assert!(SegmentTransforms::open(&path).is_err(), "{}", name);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use kdam::TqdmParallelIterator;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::io::{self, ImageInfo};
+use thyme_core::ut;
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    #[arg(short = 'i', long, help = "Image directory.", required = true)]
+    pub images: Option<String>,
+
+    #[arg(short = 's', long, help = "Mask directory.")]
+    pub masks: Option<String>,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+
+    #[arg(long, help = "Substring specifying images (e.g. _image).")]
+    pub image_substring: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(long, help = "Substring specifying masks (e.g. _mask).")]
+    pub mask_substring: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting masks by their path relative to their directory.",
+        conflicts_with_all = ["mask_substring", "mask_regex"]
+    )]
+    pub mask_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting masks by their path relative to their directory.",
+        conflicts_with = "mask_substring"
+    )]
+    pub mask_regex: Option<String>,
+
+    #[arg(short = 't', long, help = "Number of threads.")]
+    pub threads: Option<usize>,
+}
+
+/// Problems detected as (file, description)
+type Problems = Vec<(String, String)>;
+
+/// Results of checking a set of image and mask pairs
+#[derive(Debug, Default)]
+struct CheckReport {
+    /// Number of image and mask pairs detected
+    pairs: usize,
+    /// Problems detected across all files
+    problems: Problems,
+    /// Number of images with each (width x height x channels dtype) format
+    formats: BTreeMap<String, usize>,
+}
+
+pub fn utils_check(args: &CheckArgs) {
+    if let Some(threads) = args.threads.to_owned() {
+        if threads < 1 {
+            println!(
+                "[thyme::utils::check] ERROR: Threads must be set to a positive integer if provided."
+            );
+            std::process::exit(1);
+        }
+    }
+
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `utils_check`
+fn run(args: &CheckArgs) {
+    let image_path = args.images.to_owned().unwrap();
+    let masks_path = args.masks.to_owned().unwrap_or(image_path.clone());
+
+    let image_selection = (&args.image_substring, &args.image_glob, &args.image_regex);
+    let mask_selection = (&args.mask_substring, &args.mask_glob, &args.mask_regex);
+
+    if ut::path::same_path(&image_path, &masks_path) && image_selection == mask_selection {
+        eprintln!(
+            "[thyme::utils::check] ERROR: If images and masks are located in same path, different image and mask substrings must be provided."
+        );
+        std::process::exit(1);
+    }
+
+    let report = ut::path::FileSelector::new(
+        args.image_substring.as_deref(),
+        args.image_glob.as_deref(),
+        args.image_regex.as_deref(),
+    )
+    .and_then(|image_selector| {
+        let mask_selector = ut::path::FileSelector::new(
+            args.mask_substring.as_deref(),
+            args.mask_glob.as_deref(),
+            args.mask_regex.as_deref(),
+        )?;
+
+        check(
+            &image_path,
+            &masks_path,
+            &image_selector,
+            &mask_selector,
+            args.verbose,
+        )
+    })
+    .unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    for (format, n) in report.formats.iter() {
+        ut::track::progress_log(
+            &format!(
+                "{} images with format {}.",
+                ut::track::thousands_format(n),
+                format
+            ),
+            args.verbose,
+        );
+    }
+
+    for (file, problem) in report.problems.iter() {
+        eprintln!("[thyme::utils::check] ERROR: {}: {}", file, problem);
+    }
+
+    let files: HashSet<&String> = report.problems.iter().map(|(file, _)| file).collect();
+
+    println!(
+        "[thyme::utils::check] Checked {} image and mask pairs. {} problems detected across {} files.",
+        ut::track::thousands_format(report.pairs),
+        ut::track::thousands_format(report.problems.len()),
+        ut::track::thousands_format(files.len())
+    );
+
+    if !report.problems.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Check that images and masks are paired and have matching headers
+///
+/// # Arguments
+///
+/// * `image_path` - Image directory
+/// * `masks_path` - Mask directory
+/// * `image_selector` - Selects the image files
+/// * `mask_selector` - Selects the mask files
+/// * `verbose` - Show a progress bar while reading headers
+fn check(
+    image_path: &str,
+    masks_path: &str,
+    image_selector: &ut::path::FileSelector,
+    mask_selector: &ut::path::FileSelector,
+    verbose: bool,
+) -> Result<CheckReport, ThymeError> {
+    let image_files = ut::path::select_file_paths(
+        image_path,
+        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+        image_selector,
+        false,
+    )?;
+
+    let mask_files = ut::path::select_file_paths(
+        masks_path,
+        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+        mask_selector,
+        false,
+    )?;
+
+    let mut report = CheckReport::default();
+
+    if image_files.is_empty() {
+        report.problems.push((
+            image_path.to_string(),
+            "No image files were detected. Please check your path and/or substring identifier.".to_string(),
+        ));
+    }
+
+    if mask_files.is_empty() {
+        report.problems.push((
+            masks_path.to_string(),
+            "No mask files were detected. Please check your path and/or substring identifier.".to_string(),
+        ));
+    }
+
+    let mut pairs = ut::path::collect_file_pairs(
+        &image_files,
+        &mask_files,
+        image_selector.substring(),
+        mask_selector.substring(),
+    );
+
+    pairs.sort_unstable();
+
+    report.pairs = pairs.len();
+
+    let paired_images: HashSet<&PathBuf> = pairs.iter().map(|(_, image, _)| image).collect();
+    let paired_masks: HashSet<&PathBuf> = pairs.iter().map(|(_, _, mask)| mask).collect();
+
+    let mut unpaired: Problems = image_files
+        .iter()
+        .filter(|file| !paired_images.contains(file))
+        .map(|file| {
+            (
+                file_name(file),
+                "No matching mask was detected.".to_string(),
+            )
+        })
+        .chain(
+            mask_files
+                .iter()
+                .filter(|file| !paired_masks.contains(file))
+                .map(|file| {
+                    (
+                        file_name(file),
+                        "No matching image was detected.".to_string(),
+                    )
+                }),
+        )
+        .collect();
+
+    unpaired.sort_unstable();
+    report.problems.extend(unpaired);
+
+    let pb = ut::track::progress_bar(pairs.len(), "Checking headers", verbose);
+
+    let results: Vec<(Option<ImageInfo>, Problems)> = (0..pairs.len())
+        .into_par_iter()
+        .tqdm_with_bar(pb)
+        .map(|idx| {
+            let (_, image, mask) = &pairs[idx];
+            check_pair(image, mask)
+        })
+        .collect();
+
+    if verbose {
+        println!();
+    }
+
+    for (info, problems) in results {
+        if let Some(info) = info {
+            let format = format!(
+                "{}x{}x{} {}",
+                info.width, info.height, info.channels, info.dtype
+            );
+            *report.formats.entry(format).or_default() += 1;
+        }
+
+        report.problems.extend(problems);
+    }
+
+    Ok(report)
+}
+
+/// Read the headers of an image and mask pair and report any problems
+///
+/// # Arguments
+///
+/// * `image` - Path to the image
+/// * `mask` - Path to the mask
+fn check_pair(image: &Path, mask: &Path) -> (Option<ImageInfo>, Problems) {
+    let mut problems = Vec::new();
+
+    let image_info = io::probe_image(image)
+        .map_err(|err| problems.push((file_name(image), format!("Failed to read header. {}", err))))
+        .ok();
+
+    let mask_info = io::probe_image(mask)
+        .map_err(|err| problems.push((file_name(mask), format!("Failed to read header. {}", err))))
+        .ok();
+
+    if let Some(mask_info) = &mask_info {
+        if mask_info.channels != 1 {
+            problems.push((
+                file_name(mask),
+                format!(
+                    "Masks must have a single channel but {} channels were detected.",
+                    mask_info.channels
+                ),
+            ));
+        }
+    }
+
+    if let (Some(image_info), Some(mask_info)) = (&image_info, &mask_info) {
+        if (image_info.width, image_info.height) != (mask_info.width, mask_info.height) {
+            problems.push((
+                file_name(image),
+                format!(
+                    "Image dimensions ({}x{}) do not match mask {} dimensions ({}x{}).",
+                    image_info.width,
+                    image_info.height,
+                    file_name(mask),
+                    mask_info.width,
+                    mask_info.height
+                ),
+            ));
+        }
+    }
+
+    (image_info, problems)
+}
+
+/// File name of a path used to identify files in the report
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const TEST_DIR: &str = "../data/tests";
+
+    /// Copy fixtures into a temporary directory under new names
+    fn fixture_directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for (source, target) in files {
+            std::fs::copy(Path::new(TEST_DIR).join(source), directory.join(target)).unwrap();
+        }
+
+        directory
+    }
+
+    #[test]
+    fn test_check_fixture_pairs() {
+        let directory = fixture_directory(
+            "thyme_test_check_pairs",
+            &[
+                ("test_grayscale.png", "a_image.png"),
+                ("test_mask_integer.png", "a_mask.png"),
+                ("test_rgb.tif", "b_image.tif"),
+                ("test_mask_integer_u16.npy", "b_mask.npy"),
+            ],
+        );
+
+        let path = directory.to_str().unwrap();
+
+        let report = check(
+            path,
+            path,
+            &ut::path::FileSelector::new(Some("_image"), None, None).unwrap(),
+            &ut::path::FileSelector::new(Some("_mask"), None, None).unwrap(),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(report.pairs, 2);
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        assert_eq!(report.formats.values().sum::<usize>(), 2);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_check_mismatched_pair() {
+        let directory = fixture_directory(
+            "thyme_test_check_mismatched",
+            &[
+                ("test_grayscale.png", "a_image.png"),
+                ("test_mask_integer.png", "a_mask.png"),
+                ("test_rgb.png", "b_image.png"),
+                ("test_mask_binary_blobs.png", "b_mask.png"),
+                ("test_rgb.png", "c_image.png"),
+                ("test_rgb.png", "d_image.png"),
+                ("test_rgb.png", "d_mask.png"),
+            ],
+        );
+
+        let path = directory.to_str().unwrap();
+
+        let report = check(
+            path,
+            path,
+            &ut::path::FileSelector::new(Some("_image"), None, None).unwrap(),
+            &ut::path::FileSelector::new(Some("_mask"), None, None).unwrap(),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(report.pairs, 3);
+
+        let problems: Vec<&str> = report.problems.iter().map(|(f, _)| f.as_str()).collect();
+        assert_eq!(problems, ["c_image.png", "b_image.png", "d_mask.png"]);
+
+        assert!(report.problems[1].1.contains("do not match"));
+        assert!(report.problems[2].1.contains("single channel"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
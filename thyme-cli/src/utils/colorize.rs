@@ -0,0 +1,503 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use kdam::TqdmParallelIterator;
+use polars::prelude::*;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im::ThymeMask;
+use thyme_core::io;
+use thyme_core::ut;
+
+use crate::output::{OutputMode, finish_run, prepare_output_directory};
+
+/// Name of the lookup table written to the output directory
+const LUT_FILE: &str = "lut.csv";
+
+/// Columns of a lookup table of label colors
+const LUT_COLUMNS: [&str; 4] = ["label", "r", "g", "b"];
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Color each label of masks with a distinct color and write them as RGB png images."
+)]
+pub struct ColorizeArgs {
+    #[arg(short = 'i', long, help = "Mask or mask directory.", required = true)]
+    pub mask: Option<String>,
+
+    #[arg(
+        short = 'o',
+        long,
+        help = "Output png file or directory.",
+        required = true
+    )]
+    pub output: Option<String>,
+
+    #[arg(
+        long,
+        help = "Lookup table of label colors with label, r, g, and b columns (e.g. the lut.csv of a previous run). Labels missing from the table are colored by a hash of the label."
+    )]
+    pub lut: Option<String>,
+
+    #[arg(long, help = "Seed of the hashed label colors.", default_value = "0")]
+    pub seed: Option<u64>,
+
+    #[arg(long, help = "Substring specifying masks (e.g. _mask).")]
+    pub mask_substring: Option<String>,
+
+    #[arg(
+        long,
+        help = "Glob selecting masks by their path relative to their directory.",
+        conflicts_with_all = ["mask_substring", "mask_regex"]
+    )]
+    pub mask_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting masks by their path relative to their directory.",
+        conflicts_with = "mask_substring"
+    )]
+    pub mask_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (colored masks and lookup table) from the output directory before coloring. Without it, output directories holding a previous run are rejected."
+    )]
+    pub overwrite: bool,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+
+    #[arg(short = 't', long, help = "Number of threads.")]
+    pub threads: Option<usize>,
+}
+
+pub fn utils_colorize(args: &ColorizeArgs) {
+    if let Some(threads) = args.threads.to_owned() {
+        if threads < 1 {
+            println!(
+                "[thyme::utils::colorize] ERROR: Threads must be set to a positive integer if provided."
+            );
+            std::process::exit(1);
+        }
+    }
+
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `utils_colorize`
+fn run(args: &ColorizeArgs) {
+    let exit = |err: ThymeError| -> ! {
+        eprintln!("[thyme::utils::colorize] ERROR: {}", err);
+        std::process::exit(1);
+    };
+
+    let mask_path = PathBuf::from(args.mask.to_owned().unwrap());
+    let output = PathBuf::from(args.output.to_owned().unwrap());
+    let seed = args.seed.unwrap_or(0);
+
+    let lut = match args.lut.as_deref() {
+        Some(path) => read_lut(path).unwrap_or_else(|err| exit(err)),
+        None => BTreeMap::new(),
+    };
+
+    let output_extension = output
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+
+    if mask_path.is_file() {
+        if output_extension.as_deref() != Some("png") {
+            exit(ThymeError::OtherError(
+                "If mask input is a file then output must be a .png file".to_string(),
+            ));
+        }
+
+        if let Some(parent) = output.parent() {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
+                exit(ThymeError::OtherError(
+                    "Parent directory of output file path does not exist".to_string(),
+                ));
+            }
+        }
+
+        let used = colorize(&mask_path, &output, &lut, seed).unwrap_or_else(|err| exit(err));
+
+        let lut_path = output.with_file_name(format!(
+            "{}_{}",
+            output.file_stem().unwrap().to_string_lossy(),
+            LUT_FILE
+        ));
+
+        write_lut(&lut_path, &merge(lut, [used])).unwrap_or_else(|err| exit(err));
+
+        ut::track::progress_log(
+            &format!(
+                "Complete. Colored mask written to {} with lookup table {}.",
+                output.display(),
+                lut_path.display()
+            ),
+            args.verbose,
+        );
+
+        return;
+    }
+
+    if output_extension.is_some() {
+        exit(ThymeError::OtherError(
+            "If mask input is a directory then output must be a directory".to_string(),
+        ));
+    }
+
+    let mask_files = ut::path::FileSelector::new(
+        args.mask_substring.as_deref(),
+        args.mask_glob.as_deref(),
+        args.mask_regex.as_deref(),
+    )
+    .and_then(|selector| {
+        ut::path::select_file_paths(
+            args.mask.to_owned().unwrap(),
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })
+    .unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    if mask_files.is_empty() {
+        eprintln!(
+            "[thyme::utils::colorize] ERROR: No mask files were detected. Please check your path and/or substring identifier."
+        );
+        std::process::exit(1);
+    }
+
+    ut::track::progress_log(
+        &format!(
+            "Detected {} masks.",
+            ut::track::thousands_format(mask_files.len())
+        ),
+        args.verbose,
+    );
+
+    let inputs: Vec<&str> = [args.mask.as_deref(), args.lut.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let output = OutputMode::new(args.overwrite, false)
+        .and_then(|mode| prepare_output_directory(&output, mode, &inputs, "utils::colorize"))
+        .unwrap_or_else(|err| exit(err));
+
+    let (lut, failures) = colorize_all(&mask_files, &output, lut, seed, args.verbose);
+
+    for (file, err) in failures.iter() {
+        eprintln!(
+            "[thyme::utils::colorize] ERROR: {}: {}",
+            file.display(),
+            err
+        );
+    }
+
+    write_lut(output.join(LUT_FILE), &lut).unwrap_or_else(|err| exit(err));
+    finish_run(&output).unwrap_or_else(|err| exit(err));
+
+    println!(
+        "[thyme::utils::colorize] Colored {} of {} masks and {} labels to {}.",
+        ut::track::thousands_format(mask_files.len() - failures.len()),
+        ut::track::thousands_format(mask_files.len()),
+        ut::track::thousands_format(lut.len()),
+        output.display()
+    );
+
+    if !failures.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Color masks in parallel and return the lookup table of every colored label
+/// along with the masks that failed to be colored
+///
+/// # Arguments
+///
+/// * `mask_files` - Paths to masks
+/// * `output` - Output directory of the colored masks
+/// * `lut` - Colors of labels overriding the hashed colors
+/// * `seed` - Seed of the hashed colors
+/// * `verbose` - Show a progress bar
+#[allow(clippy::type_complexity)]
+fn colorize_all(
+    mask_files: &[PathBuf],
+    output: &Path,
+    lut: BTreeMap<u32, [u8; 3]>,
+    seed: u64,
+    verbose: bool,
+) -> (BTreeMap<u32, [u8; 3]>, Vec<(PathBuf, ThymeError)>) {
+    let pb = ut::track::progress_bar(mask_files.len(), "Colorizing masks", verbose);
+
+    let results: Vec<Result<BTreeMap<u32, [u8; 3]>, (PathBuf, ThymeError)>> = (0..mask_files.len())
+        .into_par_iter()
+        .tqdm_with_bar(pb)
+        .map(|idx| {
+            let source = &mask_files[idx];
+            let target = output
+                .join(source.file_stem().unwrap())
+                .with_extension("png");
+
+            colorize(source, &target, &lut, seed).map_err(|err| (source.to_path_buf(), err))
+        })
+        .collect();
+
+    if verbose {
+        println!();
+    }
+
+    let mut used = Vec::with_capacity(results.len());
+    let mut failures = Vec::new();
+
+    for result in results {
+        match result {
+            Ok(colors) => used.push(colors),
+            Err(failure) => failures.push(failure),
+        }
+    }
+
+    (merge(lut, used), failures)
+}
+
+/// Color a single mask, write it to the target path, and return the color of each label
+///
+/// # Arguments
+///
+/// * `source` - Path to the mask
+/// * `target` - Path to the colored png image
+/// * `lut` - Colors of labels overriding the hashed colors
+/// * `seed` - Seed of the hashed colors
+fn colorize(
+    source: &Path,
+    target: &Path,
+    lut: &BTreeMap<u32, [u8; 3]>,
+    seed: u64,
+) -> Result<BTreeMap<u32, [u8; 3]>, ThymeError> {
+    let mask = ThymeMask::open(source)?;
+
+    mask.colorize(lut, seed)?.save(target)?;
+
+    let labels: BTreeSet<u32> = mask
+        .as_raw()
+        .iter()
+        .filter(|&&label| label != 0)
+        .copied()
+        .collect();
+
+    Ok(labels
+        .into_iter()
+        .map(|label| {
+            let color = lut
+                .get(&label)
+                .copied()
+                .unwrap_or_else(|| ThymeMask::label_color(label, seed));
+            (label, color)
+        })
+        .collect())
+}
+
+/// Add the colors of labels used by each mask to a lookup table
+fn merge(
+    mut lut: BTreeMap<u32, [u8; 3]>,
+    used: impl IntoIterator<Item = BTreeMap<u32, [u8; 3]>>,
+) -> BTreeMap<u32, [u8; 3]> {
+    for colors in used {
+        lut.extend(colors);
+    }
+
+    lut
+}
+
+/// Read a lookup table of label colors
+///
+/// # Arguments
+///
+/// * `path` - Path to a csv, tsv, txt, parquet, pq, arrow, or feather table
+///   with label, r, g, and b columns
+fn read_lut<P: AsRef<Path>>(path: P) -> Result<BTreeMap<u32, [u8; 3]>, ThymeError> {
+    let table = io::read_table(&path)?;
+    let path = path.as_ref().display();
+
+    let columns = LUT_COLUMNS
+        .iter()
+        .map(|name| {
+            table
+                .column(name)
+                .and_then(|column| column.cast(&DataType::Int64))
+                .map_err(|_| {
+                    ThymeError::OtherError(format!(
+                        "Lookup table is missing integer column: {} ({})",
+                        name, path
+                    ))
+                })
+        })
+        .collect::<Result<Vec<Column>, ThymeError>>()?;
+
+    let columns: Vec<Vec<Option<i64>>> = columns
+        .iter()
+        .map(|column| column.i64().unwrap().into_iter().collect())
+        .collect();
+
+    let mut lut = BTreeMap::new();
+
+    for row in 0..table.height() {
+        let mut values = [0i64; 4];
+
+        for (value, column) in values.iter_mut().zip(&columns) {
+            *value = column[row].ok_or_else(|| {
+                ThymeError::OtherError(format!(
+                    "Lookup table has a missing value in row {} ({})",
+                    row + 1,
+                    path
+                ))
+            })?;
+        }
+
+        let [label, r, g, b] = values;
+
+        if label < 1 || label > u32::MAX as i64 {
+            return Err(ThymeError::OtherError(format!(
+                "Lookup table label {} in row {} must be a positive 32-bit integer ({})",
+                label,
+                row + 1,
+                path
+            )));
+        }
+
+        if [r, g, b].iter().any(|v| !(0..=255).contains(v)) {
+            return Err(ThymeError::OtherError(format!(
+                "Lookup table color of label {} must be in [0, 255] ({})",
+                label, path
+            )));
+        }
+
+        if lut
+            .insert(label as u32, [r as u8, g as u8, b as u8])
+            .is_some()
+        {
+            return Err(ThymeError::OtherError(format!(
+                "Lookup table has more than one row for label {} ({})",
+                label, path
+            )));
+        }
+    }
+
+    Ok(lut)
+}
+
+/// Write a lookup table of label colors as a csv with label, r, g, and b columns
+fn write_lut<P: AsRef<Path>>(path: P, lut: &BTreeMap<u32, [u8; 3]>) -> Result<(), ThymeError> {
+    let mut contents = LUT_COLUMNS.join(",");
+    contents.push('\n');
+
+    for (label, [r, g, b]) in lut {
+        contents.push_str(&format!("{},{},{},{}\n", label, r, g, b));
+    }
+
+    io::write_bytes_atomic(path, contents)
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_core::im::ThymeImage;
+
+    use super::*;
+
+    /// Colors of the pixels of a colored mask
+    fn pixels(path: &Path) -> Vec<[u8; 3]> {
+        let ThymeImage::U8(image) = ThymeImage::open(path).unwrap() else {
+            panic!("Colored mask is not u8");
+        };
+
+        assert_eq!(image.channels(), 3);
+
+        image
+            .as_raw()
+            .chunks_exact(3)
+            .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+            .collect()
+    }
+
+    #[test]
+    fn test_colorize_frames() {
+        let directory = std::env::temp_dir().join("thyme_test_colorize");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let masks = directory.join("masks");
+        let output = directory.join("colored");
+        std::fs::create_dir_all(&masks).unwrap();
+        std::fs::create_dir_all(&output).unwrap();
+
+        // Two frames of a movie where objects 3 and 7 move and object 9 appears
+        #[rustfmt::skip]
+        let frames: [Vec<u32>; 2] = [
+            vec![0, 3, 3, 0,
+                 0, 0, 7, 7],
+            vec![3, 3, 0, 0,
+                 0, 7, 7, 9],
+        ];
+
+        let mut mask_files = Vec::new();
+        for (k, frame) in frames.iter().enumerate() {
+            let path = masks.join(format!("frame_{}.npy", k));
+            io::write_numpy(&path, frame.clone(), vec![2, 4]).unwrap();
+            mask_files.push(path);
+        }
+
+        let (lut, failures) = colorize_all(&mask_files, &output, BTreeMap::new(), 3, false);
+        assert!(failures.is_empty(), "{:?}", failures);
+        assert_eq!(lut.keys().copied().collect::<Vec<u32>>(), [3, 7, 9]);
+
+        let first = pixels(&output.join("frame_0.png"));
+        let second = pixels(&output.join("frame_1.png"));
+
+        // Labels keep their color across frames and background stays black
+        assert_eq!(first[0], [0, 0, 0]);
+        assert_eq!(second[2], [0, 0, 0]);
+        assert_eq!(first[1], second[0]);
+        assert_eq!(first[6], second[5]);
+        assert_eq!(first[1], lut[&3]);
+        assert_eq!(second[7], lut[&9]);
+
+        // Lookup tables written by a run color later frames the same way
+        let lut_path = output.join(LUT_FILE);
+        write_lut(&lut_path, &lut).unwrap();
+        let reused = read_lut(&lut_path).unwrap();
+        assert_eq!(reused, lut);
+
+        let recolored = directory.join("recolored");
+        std::fs::create_dir_all(&recolored).unwrap();
+        let (_, failures) = colorize_all(&mask_files[1..], &recolored, reused, 11, false);
+        assert!(failures.is_empty(), "{:?}", failures);
+        assert_eq!(pixels(&recolored.join("frame_1.png")), second);
+
+        // Labels and colors out of range are rejected
+        for (name, contents) in [
+            ("background.csv", "label,r,g,b\n0,1,2,3\n"),
+            ("color.csv", "label,r,g,b\n1,1,256,3\n"),
+            ("columns.csv", "label,r,g\n1,1,2\n"),
+            ("duplicate.csv", "label,r,g,b\n1,1,2,3\n1,4,5,6\n"),
+        ] {
+            let path = directory.join(name);
+            std::fs::write(&path, contents).unwrap();
+            assert!(read_lut(&path).is_err(), "{}", name);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
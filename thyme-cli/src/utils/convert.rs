@@ -0,0 +1,495 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use kdam::TqdmParallelIterator;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im::{ConversionPolicy, Dtype, ThymeImage};
+use thyme_core::io::TiffCompression;
+use thyme_core::ut;
+use thyme_core::ut::path::FileSelector;
+
+use crate::output::{OutputMode, finish_run, prepare_output_directory};
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    #[arg(short = 'i', long, help = "Image directory.", required = true)]
+    pub images: Option<String>,
+
+    #[arg(short = 'o', long, help = "Output directory.", required = true)]
+    pub output: Option<String>,
+
+    #[arg(
+        long,
+        help = "Output image format (e.g. tif, npy, png).",
+        default_value = "tif"
+    )]
+    pub to: Option<String>,
+
+    #[arg(
+        long,
+        help = "TIFF compression (none, lzw, deflate, or packbits).",
+        default_value = "deflate"
+    )]
+    pub compression: Option<String>,
+
+    #[arg(
+        long,
+        help = "Convert subpixels to specified data type (keep, u8, u16, u32, f32, or f64).",
+        default_value = "keep"
+    )]
+    pub dtype: Option<String>,
+
+    #[arg(
+        long,
+        help = "Conversion policy for values outside of the output data type (lossy-zero, clamped, scaled-min-max, or scaled-dtype).",
+        default_value = "clamped"
+    )]
+    pub policy: Option<String>,
+
+    #[arg(
+        long,
+        help = "Allow conversions to data types that cannot represent every input value (e.g. f32 to u8)."
+    )]
+    pub allow_lossy: bool,
+
+    #[arg(
+        short = 'r',
+        long,
+        help = "Convert images in subdirectories and preserve the directory structure."
+    )]
+    pub recursive: bool,
+
+    #[arg(
+        long,
+        help = "Glob selecting images by their path relative to the image directory (e.g. '**/*_ch1_*_maxproj.tif').",
+        conflicts_with = "image_regex"
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '^plate_1/.*_ch1_')."
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (converted images) from the output directory before converting. Without it, output directories holding a previous run are rejected."
+    )]
+    pub overwrite: bool,
+
+    #[arg(short = 't', long, help = "Number of threads.")]
+    pub threads: Option<usize>,
+}
+
+/// Output format and data type of converted images
+#[derive(Debug, Clone, Copy)]
+struct ConvertOptions<'a> {
+    /// Extension of converted images (e.g. tif)
+    extension: &'a str,
+    /// Compression of converted TIFFs
+    compression: TiffCompression,
+    /// Data type of converted images if not kept
+    dtype: Option<Dtype>,
+    /// Policy for values outside of the output data type
+    policy: ConversionPolicy,
+    /// Allow conversions that cannot represent every input value
+    allow_lossy: bool,
+}
+
+pub fn utils_convert(args: &ConvertArgs) {
+    if let Some(threads) = args.threads.to_owned() {
+        if threads < 1 {
+            println!(
+                "[thyme::utils::convert] ERROR: Threads must be set to a positive integer if provided."
+            );
+            std::process::exit(1);
+        }
+    }
+
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `utils_convert`
+fn run(args: &ConvertArgs) {
+    let image_path = PathBuf::from(args.images.to_owned().unwrap());
+    let output = PathBuf::from(args.output.to_owned().unwrap());
+
+    let extension = args
+        .to
+        .to_owned()
+        .unwrap()
+        .trim()
+        .trim_start_matches('.')
+        .to_lowercase();
+
+    if !constant::SUPPORTED_IMAGE_FORMATS.contains(&extension.as_str()) {
+        eprintln!(
+            "[thyme::utils::convert] ERROR: Invalid output format '{}'. Must be one of: {}.",
+            extension,
+            constant::SUPPORTED_IMAGE_FORMATS.join(", ")
+        );
+        std::process::exit(1);
+    }
+
+    let exit = |err: ThymeError| -> ! {
+        eprintln!("[thyme::utils::convert] ERROR: {}", err);
+        std::process::exit(1);
+    };
+
+    let options = ConvertOptions {
+        extension: &extension,
+        compression: TiffCompression::parse(&args.compression.to_owned().unwrap())
+            .unwrap_or_else(|err| exit(err)),
+        dtype: match args.dtype.to_owned().unwrap().as_str() {
+            "keep" => None,
+            dtype => Some(Dtype::parse(dtype).unwrap_or_else(|err| exit(err))),
+        },
+        policy: ConversionPolicy::parse(&args.policy.to_owned().unwrap())
+            .unwrap_or_else(|err| exit(err)),
+        allow_lossy: args.allow_lossy,
+    };
+
+    let image_files = FileSelector::new(
+        None,
+        args.image_glob.as_deref(),
+        args.image_regex.as_deref(),
+    )
+    .and_then(|selector| collect_images(&image_path, &selector, args.recursive))
+    .unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    if image_files.is_empty() {
+        eprintln!(
+            "[thyme::utils::convert] ERROR: No image files were detected. Please check your path."
+        );
+        std::process::exit(1);
+    }
+
+    ut::track::progress_log(
+        &format!(
+            "Detected {} images.",
+            ut::track::thousands_format(image_files.len())
+        ),
+        args.verbose,
+    );
+
+    let output = OutputMode::new(args.overwrite, false)
+        .and_then(|mode| {
+            prepare_output_directory(
+                &output,
+                mode,
+                &[args.images.as_deref().unwrap_or_default()],
+                "utils::convert",
+            )
+        })
+        .unwrap_or_else(|err| exit(err));
+
+    let failures = convert(&image_path, &image_files, &output, &options, args.verbose);
+    finish_run(&output).unwrap_or_else(|err| exit(err));
+
+    for (file, err) in failures.iter() {
+        eprintln!("[thyme::utils::convert] ERROR: {}: {}", file.display(), err);
+    }
+
+    println!(
+        "[thyme::utils::convert] Converted {} of {} images to {}.",
+        ut::track::thousands_format(image_files.len() - failures.len()),
+        ut::track::thousands_format(image_files.len()),
+        output.display()
+    );
+
+    if !failures.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Collect image files from a directory and optionally its subdirectories
+///
+/// # Arguments
+///
+/// * `directory` - Image directory
+/// * `selector` - Selection of images by their path relative to the image directory
+/// * `recursive` - Collect images from every subdirectory
+fn collect_images(
+    directory: &Path,
+    selector: &FileSelector,
+    recursive: bool,
+) -> Result<Vec<PathBuf>, ThymeError> {
+    ut::path::select_file_paths(
+        directory,
+        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+        selector,
+        recursive,
+    )
+}
+
+/// Convert images in parallel and return the images that failed to convert
+///
+/// Converted images are written to the output directory at the path of the
+/// image relative to the image directory with the output extension.
+///
+/// # Arguments
+///
+/// * `image_path` - Image directory
+/// * `image_files` - Images inside the image directory
+/// * `output` - Output directory
+/// * `options` - Output format and data type
+/// * `verbose` - Show a progress bar
+fn convert(
+    image_path: &Path,
+    image_files: &[PathBuf],
+    output: &Path,
+    options: &ConvertOptions,
+    verbose: bool,
+) -> Vec<(PathBuf, ThymeError)> {
+    let pb = ut::track::progress_bar(image_files.len(), "Converting images", verbose);
+
+    let mut failures: Vec<(PathBuf, ThymeError)> = (0..image_files.len())
+        .into_par_iter()
+        .tqdm_with_bar(pb)
+        .filter_map(|idx| {
+            let source = &image_files[idx];
+            let relative = source.strip_prefix(image_path).unwrap_or(source);
+            let target = output.join(relative).with_extension(options.extension);
+
+            convert_image(source, &target, options)
+                .err()
+                .map(|err| (source.to_path_buf(), err))
+        })
+        .collect();
+
+    if verbose {
+        println!();
+    }
+
+    failures.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    failures
+}
+
+/// Convert a single image and write it to the target path
+///
+/// # Arguments
+///
+/// * `source` - Path to the input image
+/// * `target` - Path to the converted image
+/// * `options` - Output format and data type
+fn convert_image(source: &Path, target: &Path, options: &ConvertOptions) -> Result<(), ThymeError> {
+    let mut image = ThymeImage::open(source)?;
+
+    if let Some(dtype) = options.dtype {
+        if !options.allow_lossy && !is_lossless(&image, dtype) {
+            return Err(ThymeError::OtherError(format!(
+                "Converting {} to {} is lossy. Pass --allow-lossy to convert anyway",
+                dtype_name(&image),
+                format!("{:?}", dtype).to_lowercase()
+            )));
+        }
+
+        image = image.convert_dtype(dtype, options.policy)?;
+    }
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| ThymeError::DirError(err.to_string()))?;
+    }
+
+    match options.extension {
+        "tif" | "tiff" => image.save_as_tiff(target, options.compression),
+        _ => image.save(target),
+    }
+}
+
+/// Check if a data type can represent every value of the image data type
+///
+/// # Arguments
+///
+/// * `image` - Image to convert
+/// * `dtype` - Data type the image is converted to
+fn is_lossless(image: &ThymeImage, dtype: Dtype) -> bool {
+    matches!(
+        (image, dtype),
+        (ThymeImage::U8(_), _)
+            | (
+                ThymeImage::U16(_),
+                Dtype::U16 | Dtype::U32 | Dtype::F32 | Dtype::F64
+            )
+            | (ThymeImage::U32(_), Dtype::U32 | Dtype::F64)
+            | (ThymeImage::I32(_), Dtype::F64)
+            | (ThymeImage::F32(_), Dtype::F32 | Dtype::F64)
+            | (ThymeImage::F64(_), Dtype::F64)
+    )
+}
+
+/// Name of the data type of an image
+fn dtype_name(image: &ThymeImage) -> &'static str {
+    match image {
+        ThymeImage::U8(_) => "u8",
+        ThymeImage::U16(_) => "u16",
+        ThymeImage::U32(_) => "u32",
+        ThymeImage::U64(_) => "u64",
+        ThymeImage::I32(_) => "i32",
+        ThymeImage::I64(_) => "i64",
+        ThymeImage::F32(_) => "f32",
+        ThymeImage::F64(_) => "f64",
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const TEST_DIR: &str = "../data/tests";
+
+    /// Copy fixtures into a temporary directory tree under new names
+    fn fixture_tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+
+        for (source, target) in files {
+            let target = directory.join(target);
+            std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+            std::fs::copy(Path::new(TEST_DIR).join(source), target).unwrap();
+        }
+
+        directory
+    }
+
+    fn options(dtype: Option<Dtype>, allow_lossy: bool) -> ConvertOptions<'static> {
+        ConvertOptions {
+            extension: "tif",
+            compression: TiffCompression::Deflate,
+            dtype,
+            policy: ConversionPolicy::Clamped,
+            allow_lossy,
+        }
+    }
+
+    #[test]
+    fn test_convert_recursive_roundtrip() {
+        let files = [
+            ("test_grayscale.png", "images/a.png"),
+            ("test_rgb.tif", "images/plate_1/b.tif"),
+            ("test_rgb_u16.npy", "images/plate_1/c.npy"),
+            ("test_grayscale_f32.npy", "images/plate_2/well_1/d.npy"),
+            ("test_rgb_i32.npy", "images/plate_2/well_1/e.npy"),
+        ];
+
+        let directory = fixture_tree("thyme_test_convert_tree", &files);
+        let images = directory.join("images");
+        let output = directory.join("converted");
+
+        let all = FileSelector::All;
+
+        let image_files = collect_images(&images, &all, false).unwrap();
+        assert_eq!(image_files, vec![images.join("a.png")]);
+
+        // Globs match paths relative to the image directory
+        let glob = FileSelector::new(None, Some("plate_2/**/*.npy"), None).unwrap();
+        let selected = collect_images(&images, &glob, true).unwrap();
+        assert_eq!(
+            selected,
+            [
+                images.join("plate_2/well_1/d.npy"),
+                images.join("plate_2/well_1/e.npy")
+            ]
+        );
+        assert!(collect_images(&images, &glob, false).unwrap().is_empty());
+
+        let image_files = collect_images(&images, &all, true).unwrap();
+        assert_eq!(image_files.len(), files.len());
+
+        let failures = convert(&images, &image_files, &output, &options(None, false), false);
+        assert!(failures.is_empty(), "{:?}", failures);
+
+        for (source, target) in files {
+            let original = ThymeImage::open(Path::new(TEST_DIR).join(source)).unwrap();
+            let target = Path::new(target).strip_prefix("images").unwrap();
+            let converted = ThymeImage::open(output.join(target).with_extension("tif")).unwrap();
+
+            assert_eq!(converted.shape(), original.shape(), "{}", target.display());
+            assert_eq!(
+                dtype_name(&converted),
+                dtype_name(&original),
+                "{}",
+                target.display()
+            );
+            assert_eq!(
+                converted.to_f32(),
+                original.to_f32(),
+                "{}",
+                target.display()
+            );
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_convert_lossy() {
+        let files = [
+            ("test_grayscale.png", "images/a.png"),
+            ("test_grayscale_f32.npy", "images/b.npy"),
+        ];
+
+        let directory = fixture_tree("thyme_test_convert_lossy", &files);
+        let images = directory.join("images");
+        let output = directory.join("converted");
+
+        let image_files = collect_images(&images, &FileSelector::All, false).unwrap();
+
+        // Widening u8 to u16 keeps every value
+        let failures = convert(
+            &images,
+            &image_files[..1],
+            &output,
+            &options(Some(Dtype::U16), false),
+            false,
+        );
+        assert!(failures.is_empty(), "{:?}", failures);
+
+        let original = ThymeImage::open(&image_files[0]).unwrap();
+        let widened = ThymeImage::open(output.join("a.tif")).unwrap();
+        assert_eq!(dtype_name(&widened), "u16");
+        assert_eq!(widened.to_f32(), original.to_f32());
+
+        // Narrowing f32 to u8 is refused unless lossy conversions are allowed
+        let failures = convert(
+            &images,
+            &image_files,
+            &output,
+            &options(Some(Dtype::U8), false),
+            false,
+        );
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, images.join("b.npy"));
+        assert!(failures[0].1.to_string().contains("--allow-lossy"));
+        assert!(!output.join("b.tif").exists());
+
+        let failures = convert(
+            &images,
+            &image_files,
+            &output,
+            &options(Some(Dtype::U8), true),
+            false,
+        );
+        assert!(failures.is_empty(), "{:?}", failures);
+        assert_eq!(
+            dtype_name(&ThymeImage::open(output.join("b.tif")).unwrap()),
+            "u8"
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -18,6 +18,8 @@ use thyme_core::constant;
 use thyme_core::im;
 use thyme_core::ut;
 
+use crate::output::{OutputMode, prepare_output};
+
 #[derive(Debug, Args)]
 pub struct Images2zarrArgs {
     #[arg(short = 'i', long, help = "Image directory.", required = true)]
@@ -51,6 +53,26 @@ pub struct Images2zarrArgs {
     #[arg(long, help = "Substring specifying images (e.g. _image).")]
     pub image_substring: Option<String>,
 
+    #[arg(
+        long,
+        help = "Glob selecting images by their path relative to the image directory (e.g. '*_ch1_*_maxproj.tif').",
+        conflicts_with_all = ["image_substring", "image_regex"]
+    )]
+    pub image_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting images by their path relative to the image directory (e.g. '_ch1_.*_maxproj').",
+        conflicts_with = "image_substring"
+    )]
+    pub image_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Replace an existing output zarr file. Without it, existing output zarr files are rejected."
+    )]
+    pub overwrite: bool,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
@@ -63,13 +85,13 @@ pub fn utils_images2zarr(args: &Images2zarrArgs) {
             );
             std::process::exit(1);
         }
-
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
     }
 
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `utils_images2zarr`
+fn run(args: &Images2zarrArgs) {
     let image_path = args.images.to_owned().unwrap();
     let resize_width = args.resize_width.unwrap();
     let resize_height = args.resize_width.unwrap();
@@ -98,11 +120,19 @@ pub fn utils_images2zarr(args: &Images2zarrArgs) {
         std::process::exit(1);
     }
 
-    let image_files = ut::path::collect_file_paths(
-        &image_path,
-        constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-        args.image_substring.to_owned(),
+    let image_files = ut::path::FileSelector::new(
+        args.image_substring.as_deref(),
+        args.image_glob.as_deref(),
+        args.image_regex.as_deref(),
     )
+    .and_then(|selector| {
+        ut::path::select_file_paths(
+            &image_path,
+            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+            &selector,
+            false,
+        )
+    })
     .unwrap_or_else(|err| {
         eprintln!("{}", err);
         std::process::exit(1);
@@ -139,7 +169,7 @@ pub fn utils_images2zarr(args: &Images2zarrArgs) {
         }
 
         if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
                 eprintln!(
                     "[thyme::utils::images2zarr] ERROR: Invalid output path. Parent directory of output file path does not exist."
                 );
@@ -153,6 +183,20 @@ pub fn utils_images2zarr(args: &Images2zarrArgs) {
         std::process::exit(1);
     }
 
+    let output = OutputMode::new(args.overwrite, false)
+        .and_then(|mode| {
+            prepare_output(
+                &output,
+                mode,
+                &[args.images.as_deref().unwrap_or_default()],
+                "utils::images2zarr",
+            )
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("[thyme::utils::images2zarr] ERROR: {}.", err);
+            std::process::exit(1);
+        });
+
     let pb = ut::track::progress_bar(image_files.len(), "Convert images to zarr", args.verbose);
 
     let shape: [u64; 4] = [
@@ -251,10 +295,14 @@ pub fn utils_images2zarr(args: &Images2zarrArgs) {
 
                 if let Ok(img) = img.resize(resize_width, resize_height) {
                     let result = match zarr_dtype {
-                        DataType::UInt8 => images_array
-                            .store_chunk_elements(&[idx as u64, 0, 0, 0], img.to_u8().as_slice()),
-                        DataType::UInt16 => images_array
-                            .store_chunk_elements(&[idx as u64, 0, 0, 0], img.to_u16().as_slice()),
+                        DataType::UInt8 => images_array.store_chunk_elements(
+                            &[idx as u64, 0, 0, 0],
+                            img.to_u8_clamped().as_slice(),
+                        ),
+                        DataType::UInt16 => images_array.store_chunk_elements(
+                            &[idx as u64, 0, 0, 0],
+                            img.to_u16_clamped().as_slice(),
+                        ),
                         DataType::UInt32 => images_array
                             .store_chunk_elements(&[idx as u64, 0, 0, 0], img.to_u32().as_slice()),
                         DataType::Float32 => images_array
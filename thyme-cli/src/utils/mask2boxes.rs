@@ -13,6 +13,8 @@ use thyme_core::error::ThymeError;
 use thyme_core::im;
 use thyme_core::ut;
 
+use crate::output::{OutputMode, finish_run, prepare_output_directory};
+
 #[derive(Debug, Args)]
 pub struct Mask2boxesArgs {
     #[arg(short = 'i', long, help = "Mask or mask directory.", required = true)]
@@ -32,6 +34,40 @@ pub struct Mask2boxesArgs {
     #[arg(long, help = "Substring specifying masks (e.g. _mask).")]
     pub mask_substring: Option<String>,
 
+    #[arg(
+        long,
+        help = "Glob selecting masks by their path relative to their directory.",
+        conflicts_with_all = ["mask_substring", "mask_regex"]
+    )]
+    pub mask_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting masks by their path relative to their directory.",
+        conflicts_with = "mask_substring"
+    )]
+    pub mask_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Handling of (Z, H, W) numpy mask stacks (error, max-project, per-slice). Per-slice stacks write one output per slice suffixed with _z{k}.",
+        default_value = "error"
+    )]
+    pub zstack_mode: Option<String>,
+
+    #[arg(
+        long,
+        help = "Layout of mask files (auto, cellpose-seg). Cellpose-seg reads the masks of Cellpose _seg.npy files.",
+        default_value = "auto"
+    )]
+    pub mask_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (bounding boxes) from the output directory before converting. Without it, output directories holding a previous run are rejected."
+    )]
+    pub overwrite: bool,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
@@ -44,13 +80,25 @@ pub fn utils_mask2boxes(args: &Mask2boxesArgs) {
             );
             std::process::exit(1);
         }
-
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
     }
 
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `utils_mask2boxes`
+fn run(args: &Mask2boxesArgs) {
+    let zstack = im::ZStackMode::parse(args.zstack_mode.as_deref().unwrap_or("error"))
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+
+    let mask_format = im::MaskFormat::parse(args.mask_format.as_deref().unwrap_or("auto"))
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+
     let mask_path = args.mask.to_owned().unwrap();
 
     let mut output = PathBuf::from(args.output.to_owned().unwrap());
@@ -95,7 +143,7 @@ pub fn utils_mask2boxes(args: &Mask2boxesArgs) {
         }
 
         if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
                 eprintln!(
                     "[thyme::utils::mask2boxes] ERROR: Invalid file path. Parent directory of output file path does not exist."
                 );
@@ -103,12 +151,14 @@ pub fn utils_mask2boxes(args: &Mask2boxesArgs) {
             }
         }
 
-        mask2boxes(Path::new(&mask_path), &output, false).unwrap_or_else(|_| {
-            eprintln!(
-                "[thyme::utils::mask2boxes] ERROR: Failed to convert mask to bounding boxes."
-            );
-            std::process::exit(1);
-        });
+        mask2boxes(Path::new(&mask_path), &output, false, zstack, mask_format).unwrap_or_else(
+            |_| {
+                eprintln!(
+                    "[thyme::utils::mask2boxes] ERROR: Failed to convert mask to bounding boxes."
+                );
+                std::process::exit(1);
+            },
+        );
     } else {
         if !is_mask_dir {
             eprintln!(
@@ -117,11 +167,19 @@ pub fn utils_mask2boxes(args: &Mask2boxesArgs) {
             std::process::exit(1);
         }
 
-        let mask_files = ut::path::collect_file_paths(
-            &mask_path,
-            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-            args.mask_substring.to_owned(),
+        let mask_files = ut::path::FileSelector::new(
+            args.mask_substring.as_deref(),
+            args.mask_glob.as_deref(),
+            args.mask_regex.as_deref(),
         )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &mask_path,
+                constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
         .unwrap_or_else(|err| {
             eprintln!("{}", err);
             std::process::exit(1);
@@ -142,10 +200,14 @@ pub fn utils_mask2boxes(args: &Mask2boxesArgs) {
             args.verbose,
         );
 
-        output = ut::path::create_directory(&output).unwrap_or_else(|_| {
-            eprintln!("[thyme::utils::mask2boxes] ERROR: Could not create directory.");
-            std::process::exit(1);
-        });
+        output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| {
+                prepare_output_directory(&output, mode, &[&mask_path], "utils::mask2boxes")
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::utils::mask2boxes] ERROR: {}.", err);
+                std::process::exit(1);
+            });
 
         let pb = ut::track::progress_bar(
             mask_files.len(),
@@ -159,13 +221,20 @@ pub fn utils_mask2boxes(args: &Mask2boxesArgs) {
             .into_par_iter()
             .tqdm_with_bar(pb)
             .for_each(|idx| {
-                mask2boxes(&mask_files[idx], &output, true).unwrap_or_else(|_| {
-                    error.lock().unwrap().push(idx);
-                });
+                mask2boxes(&mask_files[idx], &output, true, zstack, mask_format).unwrap_or_else(
+                    |_| {
+                        error.lock().unwrap().push(idx);
+                    },
+                );
             });
 
         let error = error.into_inner().unwrap();
 
+        finish_run(&output).unwrap_or_else(|err| {
+            eprintln!("[thyme::utils::mask2boxes] ERROR: {}.", err);
+            std::process::exit(1);
+        });
+
         if args.verbose {
             println!()
         }
@@ -188,20 +257,35 @@ pub fn utils_mask2boxes(args: &Mask2boxesArgs) {
 }
 
 /// Convert an input mask to bounding boxes
-fn mask2boxes(mask_path: &Path, output_path: &Path, is_dir: bool) -> Result<(), ThymeError> {
-    let mut mask = im::ThymeMask::open(mask_path)?;
-
-    let (_, polygons) = mask.polygons()?;
-    let bounding_boxes = polygons.to_bounding_boxes()?;
-
-    if is_dir {
-        bounding_boxes.save(
-            output_path
-                .join(mask_path.file_stem().unwrap())
-                .with_extension("json"),
-        )?;
+fn mask2boxes(
+    mask_path: &Path,
+    output_path: &Path,
+    is_dir: bool,
+    zstack: im::ZStackMode,
+    mask_format: im::MaskFormat,
+) -> Result<(), ThymeError> {
+    let output_path = if is_dir {
+        output_path
+            .join(mask_path.file_stem().unwrap())
+            .with_extension("json")
     } else {
-        bounding_boxes.save(output_path)?;
+        output_path.to_path_buf()
+    };
+
+    // Binary and 8-bit masks are converted without widening pixels to u32
+    if super::is_u8_mask(mask_path, zstack, mask_format) {
+        let (_, polygons) = im::ThymeMaskU8::open(mask_path)?.polygons()?;
+        return polygons.to_bounding_boxes()?.save(output_path);
+    }
+
+    for (k, (mut mask, _)) in im::ThymeMask::open_zstack_format(mask_path, zstack, mask_format)?
+        .into_iter()
+        .enumerate()
+    {
+        let (_, polygons) = mask.polygons()?;
+        let bounding_boxes = polygons.to_bounding_boxes()?;
+
+        bounding_boxes.save(super::slice_output_path(&output_path, zstack, k))?;
     }
 
     Ok(())
@@ -13,6 +13,9 @@ use thyme_core::error::ThymeError;
 use thyme_core::im;
 use thyme_core::ut;
 
+use crate::output::{OutputMode, finish_run, prepare_output_directory};
+use crate::probability::ProbabilityMask;
+
 #[derive(Debug, Args)]
 pub struct Mask2polygonsArgs {
     #[arg(short = 'i', long, help = "Mask or mask directory.", required = true)]
@@ -27,6 +30,65 @@ pub struct Mask2polygonsArgs {
     #[arg(long, help = "Substring specifying masks (e.g. _mask).")]
     pub mask_substring: Option<String>,
 
+    #[arg(
+        long,
+        help = "Glob selecting masks by their path relative to their directory.",
+        conflicts_with_all = ["mask_substring", "mask_regex"]
+    )]
+    pub mask_glob: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regex selecting masks by their path relative to their directory.",
+        conflicts_with = "mask_substring"
+    )]
+    pub mask_regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Treat masks as float probability maps (e.g. npy, tiff) and label pixels with a probability above this threshold (e.g. 0.5)."
+    )]
+    pub prob_threshold: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Split touching objects in probability maps using a watershed seeded at probability maxima at least this far apart (pixels).",
+        requires = "prob_threshold"
+    )]
+    pub prob_min_distance: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Write the index of the polygon enclosing each polygon (e.g. nuclei within cells) to a parents field."
+    )]
+    pub hierarchy: bool,
+
+    #[arg(
+        long,
+        help = "Handling of (Z, H, W) numpy mask stacks (error, max-project, per-slice). Per-slice stacks write one output per slice suffixed with _z{k}.",
+        default_value = "error"
+    )]
+    pub zstack_mode: Option<String>,
+
+    #[arg(
+        long,
+        help = "Layout of mask files (auto, cellpose-seg). Cellpose-seg reads the masks of Cellpose _seg.npy files.",
+        default_value = "auto"
+    )]
+    pub mask_format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Smooth polygon outlines, provided as gaussian:<sigma> (points) or chaikin:<iterations> (e.g. gaussian:1.5). The mean relative change in polygon area is logged when verbose."
+    )]
+    pub smooth_contours: Option<String>,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (polygons) from the output directory before converting. Without it, output directories holding a previous run are rejected."
+    )]
+    pub overwrite: bool,
+
     #[arg(short = 't', long, help = "Number of threads.")]
     pub threads: Option<usize>,
 }
@@ -39,13 +101,56 @@ pub fn utils_mask2polygons(args: &Mask2polygonsArgs) {
             );
             std::process::exit(1);
         }
+    }
 
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build_global()
-            .unwrap();
+    ut::threads::install(args.threads, || run(args));
+}
+
+/// Run the command on the thread pool selected by `utils_mask2polygons`
+fn run(args: &Mask2polygonsArgs) {
+    let probability = ProbabilityMask::new(args.prob_threshold, args.prob_min_distance);
+
+    if let Some(Err(err)) = probability.map(|probability| probability.validate()) {
+        eprintln!("[thyme::utils::mask2polygons] ERROR: {}", err);
+        std::process::exit(1);
+    }
+
+    let zstack = im::ZStackMode::parse(args.zstack_mode.as_deref().unwrap_or("error"))
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+
+    if zstack != im::ZStackMode::Error && probability.is_some() {
+        eprintln!(
+            "[thyme::utils::mask2polygons] ERROR: zstack_mode cannot be combined with prob_threshold."
+        );
+        std::process::exit(1);
+    }
+
+    let mask_format = im::MaskFormat::parse(args.mask_format.as_deref().unwrap_or("auto"))
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+
+    if mask_format != im::MaskFormat::Auto && probability.is_some() {
+        eprintln!(
+            "[thyme::utils::mask2polygons] ERROR: mask_format cannot be combined with prob_threshold."
+        );
+        std::process::exit(1);
     }
 
+    let smoothing = args
+        .smooth_contours
+        .as_deref()
+        .map(im::ContourSmoothing::parse)
+        .transpose()
+        .unwrap_or_else(|err| {
+            eprintln!("[thyme::utils::mask2polygons] ERROR: {}", err);
+            std::process::exit(1);
+        });
+
     let mask_path = args.mask.to_owned().unwrap();
 
     let mut output = PathBuf::from(args.output.to_owned().unwrap());
@@ -90,7 +195,7 @@ pub fn utils_mask2polygons(args: &Mask2polygonsArgs) {
         }
 
         if let Some(parent) = output.parent() {
-            if !parent.is_dir() && parent.to_str().unwrap() != "" {
+            if !parent.is_dir() && !parent.as_os_str().is_empty() {
                 eprintln!(
                     "[thyme::utils::mask2polygons] ERROR: Invalid file path. Parent directory of output file path does not exist."
                 );
@@ -98,10 +203,22 @@ pub fn utils_mask2polygons(args: &Mask2polygonsArgs) {
             }
         }
 
-        mask2polygons(Path::new(&mask_path), &output, false).unwrap_or_else(|_| {
+        let changes = mask2polygons(
+            Path::new(&mask_path),
+            &output,
+            false,
+            probability.as_ref(),
+            args.hierarchy,
+            zstack,
+            mask_format,
+            smoothing,
+        )
+        .unwrap_or_else(|_| {
             eprintln!("[thyme::utils::mask2polygons] ERROR: Failed to convert mask to polygons.");
             std::process::exit(1);
         });
+
+        log_area_change(&changes, smoothing, args.verbose);
     } else {
         if !is_mask_dir {
             eprintln!(
@@ -110,11 +227,19 @@ pub fn utils_mask2polygons(args: &Mask2polygonsArgs) {
             std::process::exit(1);
         }
 
-        let mask_files = ut::path::collect_file_paths(
-            &mask_path,
-            constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
-            args.mask_substring.to_owned(),
+        let mask_files = ut::path::FileSelector::new(
+            args.mask_substring.as_deref(),
+            args.mask_glob.as_deref(),
+            args.mask_regex.as_deref(),
         )
+        .and_then(|selector| {
+            ut::path::select_file_paths(
+                &mask_path,
+                constant::SUPPORTED_IMAGE_FORMATS.as_slice(),
+                &selector,
+                false,
+            )
+        })
         .unwrap_or_else(|err| {
             eprintln!("{}", err);
             std::process::exit(1);
@@ -135,10 +260,14 @@ pub fn utils_mask2polygons(args: &Mask2polygonsArgs) {
             args.verbose,
         );
 
-        output = ut::path::create_directory(&output).unwrap_or_else(|_| {
-            eprintln!("[thyme::utils::mask2polygons] ERROR: Could not create directory.");
-            std::process::exit(1);
-        });
+        output = OutputMode::new(args.overwrite, false)
+            .and_then(|mode| {
+                prepare_output_directory(&output, mode, &[&mask_path], "utils::mask2polygons")
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("[thyme::utils::mask2polygons] ERROR: {}.", err);
+                std::process::exit(1);
+            });
 
         let pb = ut::track::progress_bar(
             mask_files.len(),
@@ -147,18 +276,35 @@ pub fn utils_mask2polygons(args: &Mask2polygonsArgs) {
         );
 
         let error: Mutex<Vec<usize>> = Mutex::new(Vec::with_capacity(mask_files.len()));
+        let changes: Mutex<Vec<f32>> = Mutex::new(vec![]);
 
         (0..mask_files.len())
             .into_par_iter()
             .tqdm_with_bar(pb)
             .for_each(|idx| {
-                mask2polygons(&mask_files[idx], &output, true).unwrap_or_else(|_| {
+                mask2polygons(
+                    &mask_files[idx],
+                    &output,
+                    true,
+                    probability.as_ref(),
+                    args.hierarchy,
+                    zstack,
+                    mask_format,
+                    smoothing,
+                )
+                .map(|image_changes| changes.lock().unwrap().extend(image_changes))
+                .unwrap_or_else(|_| {
                     error.lock().unwrap().push(idx);
                 });
             });
 
         let error = error.into_inner().unwrap();
 
+        finish_run(&output).unwrap_or_else(|err| {
+            eprintln!("[thyme::utils::mask2polygons] ERROR: {}.", err);
+            std::process::exit(1);
+        });
+
         if args.verbose {
             println!()
         }
@@ -177,24 +323,260 @@ pub fn utils_mask2polygons(args: &Mask2polygonsArgs) {
         };
 
         ut::track::progress_log(message, args.verbose);
+        log_area_change(&changes.into_inner().unwrap(), smoothing, args.verbose);
     }
 }
 
-/// Convert an input mask to polygons
-fn mask2polygons(mask_path: &Path, output_path: &Path, is_dir: bool) -> Result<(), ThymeError> {
-    let mut mask = im::ThymeMask::open(mask_path)?;
+/// Log the mean relative change in the area of smoothed polygons
+fn log_area_change(changes: &[f32], smoothing: Option<im::ContourSmoothing>, verbose: bool) {
+    if smoothing.is_none() || changes.is_empty() {
+        return;
+    }
+
+    ut::track::progress_log(
+        &format!(
+            "Contour smoothing changed the area of {} polygons by {:+.2}% on average.",
+            ut::track::thousands_format(changes.len()),
+            100.0 * changes.iter().sum::<f32>() / changes.len() as f32
+        ),
+        verbose,
+    );
+}
 
-    let (_, polygons) = mask.polygons()?;
+/// Convert an input mask to polygons, returning the relative area change of smoothed polygons
+#[allow(clippy::too_many_arguments)]
+fn mask2polygons(
+    mask_path: &Path,
+    output_path: &Path,
+    is_dir: bool,
+    probability: Option<&ProbabilityMask>,
+    hierarchy: bool,
+    zstack: im::ZStackMode,
+    mask_format: im::MaskFormat,
+    smoothing: Option<im::ContourSmoothing>,
+) -> Result<Vec<f32>, ThymeError> {
+    let mut changes = vec![];
+    let mut smooth = |polygons: &mut im::Polygons| {
+        if let Some(smoothing) = smoothing {
+            changes.extend(polygons.smooth(smoothing));
+        }
+    };
 
-    if is_dir {
-        polygons.save(
-            output_path
-                .join(mask_path.file_stem().unwrap())
-                .with_extension("json"),
-        )?;
+    let output_path = if is_dir {
+        output_path
+            .join(mask_path.file_stem().unwrap())
+            .with_extension("json")
     } else {
-        polygons.save(output_path)?;
+        output_path.to_path_buf()
+    };
+
+    // Binary and 8-bit masks are converted without widening pixels to u32
+    if probability.is_none() && super::is_u8_mask(mask_path, zstack, mask_format) {
+        let mut mask = im::ThymeMaskU8::open(mask_path)?;
+
+        if hierarchy {
+            let (labels, mut polygons, parents) = mask.polygons_with_hierarchy()?;
+            smooth(&mut polygons);
+            save_with_parents(&polygons, &labels, &parents, &output_path)?;
+        } else {
+            let (_, mut polygons) = mask.polygons()?;
+            smooth(&mut polygons);
+            polygons.save(output_path)?;
+        }
+
+        return Ok(changes);
+    }
+
+    let masks = match probability {
+        Some(probability) => vec![probability.open(mask_path, "utils::mask2polygons")?],
+        None => im::ThymeMask::open_zstack_format(mask_path, zstack, mask_format)?
+            .into_iter()
+            .map(|(mask, _)| mask)
+            .collect(),
+    };
+
+    for (k, mut mask) in masks.into_iter().enumerate() {
+        let output_path = super::slice_output_path(&output_path, zstack, k);
+
+        if hierarchy {
+            let (labels, mut polygons, parents) = mask.polygons_with_hierarchy()?;
+            smooth(&mut polygons);
+            save_with_parents(&polygons, &labels, &parents, &output_path)?;
+        } else {
+            let (_, mut polygons) = mask.polygons()?;
+            smooth(&mut polygons);
+            polygons.save(output_path)?;
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Save polygons with the index of the polygon of each parent label
+fn save_with_parents(
+    polygons: &im::Polygons,
+    labels: &[u32],
+    parents: &[Option<u32>],
+    output_path: &Path,
+) -> Result<(), ThymeError> {
+    let parents: Vec<Option<usize>> = parents
+        .iter()
+        .map(|parent| parent.and_then(|p| labels.iter().position(|&l| l == p)))
+        .collect();
+
+    polygons.save_with_parents(output_path, &parents)
+}
+
+#[cfg(test)]
+mod test {
+
+    use thyme_core::io;
+
+    use super::*;
+
+    #[test]
+    fn test_mask2polygons_u8_mask() {
+        let directory = std::env::temp_dir().join("thyme_test_mask2polygons_u8");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let mask: Vec<u8> = (0..144)
+            .map(|i| match (i % 12 % 4, i / 12 % 4) {
+                (0, _) | (_, 0) => 0,
+                _ => 255,
+            })
+            .collect();
+
+        let wide: Vec<u32> = mask.iter().map(|&x| x as u32).collect();
+
+        io::write_numpy(directory.join("u8.npy"), mask, vec![12, 12]).unwrap();
+        io::write_numpy(directory.join("u32.npy"), wide, vec![12, 12]).unwrap();
+
+        assert!(super::super::is_u8_mask(
+            &directory.join("u8.npy"),
+            im::ZStackMode::Error,
+            im::MaskFormat::Auto
+        ));
+
+        assert!(!super::super::is_u8_mask(
+            &directory.join("u32.npy"),
+            im::ZStackMode::Error,
+            im::MaskFormat::Auto
+        ));
+
+        // Polygons without parents are written last and re-opened below
+        for hierarchy in [true, false] {
+            for name in ["u8", "u32"] {
+                mask2polygons(
+                    &directory.join(name).with_extension("npy"),
+                    &directory,
+                    true,
+                    None,
+                    hierarchy,
+                    im::ZStackMode::Error,
+                    im::MaskFormat::Auto,
+                    None,
+                )
+                .unwrap();
+            }
+
+            let u8 = std::fs::read_to_string(directory.join("u8.json")).unwrap();
+            let u32 = std::fs::read_to_string(directory.join("u32.json")).unwrap();
+
+            assert_eq!(u8, u32);
+        }
+
+        let polygons = im::Polygons::open(directory.join("u8.json")).unwrap();
+        assert_eq!(polygons.len(), 9);
+
+        std::fs::remove_dir_all(&directory).unwrap();
     }
 
-    Ok(())
+    #[test]
+    fn test_mask2polygons_smooth_contours() {
+        let directory = std::env::temp_dir().join("thyme_test_mask2polygons_smooth");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let mask: Vec<u32> = (0..144)
+            .map(|i| match (i % 12 % 4, i / 12 % 4) {
+                (0, _) | (_, 0) => 0,
+                _ => 1 + i / 48 * 3 + i % 12 / 4,
+            })
+            .collect();
+
+        io::write_numpy(directory.join("mask.npy"), mask, vec![12, 12]).unwrap();
+
+        let mut points = vec![];
+        for smoothing in [None, Some(im::ContourSmoothing::Chaikin(1))] {
+            let changes = mask2polygons(
+                &directory.join("mask.npy"),
+                &directory,
+                true,
+                None,
+                false,
+                im::ZStackMode::Error,
+                im::MaskFormat::Auto,
+                smoothing,
+            )
+            .unwrap();
+
+            assert_eq!(changes.len(), smoothing.map_or(0, |_| 9));
+            assert!(changes.iter().all(|&change| change < 0.0));
+
+            let polygons = im::Polygons::open(directory.join("mask.json")).unwrap();
+            points.push(polygons.as_points()[0].len());
+        }
+
+        // Every corner of the outlines is cut in two
+        assert_eq!(points[1], 2 * points[0]);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_mask2polygons_cellpose_seg() {
+        let directory = std::env::temp_dir().join("thyme_test_mask2polygons_cellpose");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let data = Path::new("../data/tests");
+
+        for (name, format) in [
+            ("test_mask_cellpose.npy", im::MaskFormat::Auto),
+            ("test_mask_cellpose_seg.npy", im::MaskFormat::CellposeSeg),
+        ] {
+            mask2polygons(
+                &data.join(name),
+                &directory,
+                true,
+                None,
+                false,
+                im::ZStackMode::Error,
+                format,
+                None,
+            )
+            .unwrap();
+        }
+
+        let plain = im::Polygons::open(directory.join("test_mask_cellpose.json")).unwrap();
+        let seg = im::Polygons::open(directory.join("test_mask_cellpose_seg.json")).unwrap();
+
+        assert_eq!(plain.len(), 5);
+        assert_eq!(plain.as_points(), seg.as_points());
+
+        // Seg files are rejected unless read with the cellpose-seg format
+        assert!(
+            mask2polygons(
+                &data.join("test_mask_cellpose_seg.npy"),
+                &directory,
+                true,
+                None,
+                false,
+                im::ZStackMode::Error,
+                im::MaskFormat::Auto,
+                None,
+            )
+            .is_err()
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
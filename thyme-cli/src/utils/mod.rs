@@ -1,15 +1,34 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::path::{Path, PathBuf};
+
 use clap::{Args, Subcommand};
 
+use thyme_core::im::{MaskFormat, ZStackMode};
+use thyme_core::io;
+
+mod check;
+mod colorize;
+mod convert;
 mod images2zarr;
 mod mask2boxes;
 mod mask2polygons;
+mod platemap_heatmap;
+mod read_meta;
+mod split_dataset;
+mod synth;
 
+use check::{CheckArgs, utils_check};
+use colorize::{ColorizeArgs, utils_colorize};
+use convert::{ConvertArgs, utils_convert};
 use images2zarr::{Images2zarrArgs, utils_images2zarr};
 use mask2boxes::{Mask2boxesArgs, utils_mask2boxes};
 use mask2polygons::{Mask2polygonsArgs, utils_mask2polygons};
+use platemap_heatmap::{PlatemapHeatmapArgs, utils_platemap_heatmap};
+use read_meta::{ReadMetaArgs, utils_read_meta};
+use split_dataset::{SplitDatasetArgs, utils_split_dataset};
+use synth::{SynthArgs, utils_synth};
 
 #[derive(Debug, Args)]
 #[command(about = "General utilities for converting and transforming image/image-related data.")]
@@ -23,15 +42,96 @@ pub struct UtilsArgs {
 
 #[derive(Debug, Subcommand)]
 enum UtilsCommands {
+    Check(CheckArgs),
+    Colorize(ColorizeArgs),
+    Convert(ConvertArgs),
     Images2zarr(Images2zarrArgs),
     Mask2boxes(Mask2boxesArgs),
     Mask2polygons(Mask2polygonsArgs),
+    PlatemapHeatmap(PlatemapHeatmapArgs),
+    ReadMeta(ReadMetaArgs),
+    SplitDataset(SplitDatasetArgs),
+    Synth(SynthArgs),
 }
 
 pub fn utils(args: &UtilsArgs) {
     match args.command.as_ref().unwrap() {
+        UtilsCommands::Check(check_args) => utils_check(check_args),
+        UtilsCommands::Colorize(colorize_args) => utils_colorize(colorize_args),
+        UtilsCommands::Convert(convert_args) => utils_convert(convert_args),
         UtilsCommands::Images2zarr(images2zarr_args) => utils_images2zarr(images2zarr_args),
         UtilsCommands::Mask2boxes(mask2boxes_args) => utils_mask2boxes(mask2boxes_args),
         UtilsCommands::Mask2polygons(mask2polygons_args) => utils_mask2polygons(mask2polygons_args),
+        UtilsCommands::PlatemapHeatmap(platemap_heatmap_args) => {
+            utils_platemap_heatmap(platemap_heatmap_args)
+        }
+        UtilsCommands::ReadMeta(read_meta_args) => utils_read_meta(read_meta_args),
+        UtilsCommands::SplitDataset(split_dataset_args) => utils_split_dataset(split_dataset_args),
+        UtilsCommands::Synth(synth_args) => utils_synth(synth_args),
+    }
+}
+
+/// Output path for the k-th slice of a mask stack
+///
+/// Slices are suffixed with `_z{k}` before the extension when stacks are
+/// split per slice and written to the provided path otherwise.
+///
+/// # Arguments
+///
+/// * `path` - Output path for the full mask (e.g. polygons.json)
+/// * `zstack` - How mask stacks are handled
+/// * `k` - Index of the z-slice
+fn slice_output_path(path: &Path, zstack: ZStackMode, k: usize) -> PathBuf {
+    if zstack != ZStackMode::PerSlice {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = zstack.slice_name(&stem, k);
+
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{}.{}", name, ext.to_string_lossy())),
+        None => path.with_file_name(name),
+    }
+}
+
+/// Check if a mask can be opened as an 8-bit `ThymeMaskU8`
+///
+/// Only single-channel 8-bit png and npy masks are opened as 8-bit masks.
+/// Other masks (e.g. palette pngs or multi-page tiffs) are opened as a
+/// `ThymeMask` so that they are decoded exactly as before.
+///
+/// # Arguments
+///
+/// * `path` - Path to a mask
+/// * `zstack` - How mask stacks are handled
+/// * `format` - Layout of the mask file
+fn is_u8_mask(path: &Path, zstack: ZStackMode, format: MaskFormat) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+
+    zstack == ZStackMode::Error
+        && format == MaskFormat::Auto
+        && matches!(extension.as_deref(), Some("png") | Some("npy"))
+        && io::probe_image(path).is_ok_and(|info| info.channels == 1 && info.dtype == "u8")
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_slice_output_path() {
+        let path = Path::new("out/polygons.json");
+
+        assert_eq!(slice_output_path(path, ZStackMode::Error, 0), path);
+        assert_eq!(slice_output_path(path, ZStackMode::MaxProject, 0), path);
+        assert_eq!(
+            slice_output_path(path, ZStackMode::PerSlice, 2),
+            Path::new("out/polygons_z2.json")
+        );
     }
 }
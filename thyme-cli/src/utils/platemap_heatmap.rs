@@ -0,0 +1,539 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use clap::Args;
+use polars::prelude::{DataFrame, DataType};
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::{ThymeBuffer, ThymeImage};
+use thyme_core::io;
+use thyme_core::ut;
+
+#[derive(Debug, Args)]
+#[command(about = "Render a plate heatmap of a column of an aggregated profile table.")]
+pub struct PlatemapHeatmapArgs {
+    #[arg(
+        short = 'i',
+        long,
+        help = "Profile table (csv, tsv, txt, parquet, pq, arrow, or feather).",
+        required = true
+    )]
+    pub input: Option<String>,
+
+    #[arg(long, help = "Numeric column mapped to well colors.", required = true)]
+    pub value: Option<String>,
+
+    #[arg(
+        long,
+        help = "Column with well names (e.g. A01 or plate1_A01).",
+        default_value = "well"
+    )]
+    pub well_column: Option<String>,
+
+    #[arg(
+        short = 'o',
+        long,
+        help = "Output heatmap image (e.g. heatmap.png).",
+        required = true
+    )]
+    pub output: Option<String>,
+
+    #[arg(
+        long,
+        help = "Plate format (96, 384, or auto). Auto selects the smallest plate containing every well.",
+        default_value = "auto"
+    )]
+    pub plate: Option<String>,
+
+    #[arg(
+        long,
+        help = "Width and height of each well (pixels).",
+        default_value = "32"
+    )]
+    pub cell_size: Option<u32>,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+}
+
+/// Values of each well keyed by (row, column)
+type WellValues = BTreeMap<(usize, usize), f64>;
+
+/// Color of wells without a value
+const MISSING: [u8; 3] = [128, 128, 128];
+
+/// Color of the background and labels
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const FOREGROUND: [u8; 3] = [0, 0, 0];
+
+/// Viridis colormap sampled at nine evenly spaced positions
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+pub fn utils_platemap_heatmap(args: &PlatemapHeatmapArgs) {
+    let cell = args.cell_size.unwrap();
+
+    if cell < 8 {
+        eprintln!("[thyme::utils::platemap_heatmap] ERROR: Cell size must be at least 8 pixels.");
+        std::process::exit(1);
+    }
+
+    let df = io::read_table(args.input.to_owned().unwrap()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    let (values, skipped) = well_values(
+        &df,
+        &args.well_column.to_owned().unwrap(),
+        &args.value.to_owned().unwrap(),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("[thyme::utils::platemap_heatmap] ERROR: {}", err);
+        std::process::exit(1);
+    });
+
+    if skipped > 0 {
+        eprintln!(
+            "[thyme::utils::platemap_heatmap] WARNING: Skipped {} rows without a valid well name.",
+            ut::track::thousands_format(skipped)
+        );
+    }
+
+    let (rows, columns) =
+        plate_shape(&args.plate.to_owned().unwrap(), &values).unwrap_or_else(|err| {
+            eprintln!("[thyme::utils::platemap_heatmap] ERROR: {}", err);
+            std::process::exit(1);
+        });
+
+    ut::track::progress_log(
+        &format!(
+            "Rendering {} wells with values on a {}-well plate.",
+            ut::track::thousands_format(values.len()),
+            rows * columns
+        ),
+        args.verbose,
+    );
+
+    let output = args.output.to_owned().unwrap();
+
+    render(&values, rows, columns, cell)
+        .and_then(|heatmap| heatmap.save(Path::new(&output)))
+        .unwrap_or_else(|err| {
+            eprintln!("[thyme::utils::platemap_heatmap] ERROR: {}", err);
+            std::process::exit(1);
+        });
+}
+
+/// Parse the zero-indexed (row, column) of a well name
+///
+/// Wells are named by a row letter from A to P and a column number from 1
+/// to 24 (e.g. A01, b7). If the name is not a well, the last underscore or
+/// dash separated token that is a well is used (e.g. plate1_A01).
+///
+/// # Arguments
+///
+/// * `name` - Well name
+fn parse_well(name: &str) -> Option<(usize, usize)> {
+    let parse = |token: &str| {
+        let mut chars = token.trim().chars();
+        let row = chars.next()?.to_ascii_uppercase();
+        let column: usize = chars.as_str().parse().ok()?;
+
+        if !('A'..='P').contains(&row) || !(1..=24).contains(&column) {
+            return None;
+        }
+
+        Some((row as usize - 'A' as usize, column - 1))
+    };
+
+    parse(name).or_else(|| name.rsplit(['_', '-']).find_map(parse))
+}
+
+/// Average value of each well in a table
+///
+/// Returns the mean of each (row, column) well over all rows of the table
+/// and the number of rows skipped because of an invalid well name. Missing
+/// and non-finite values are ignored.
+///
+/// # Arguments
+///
+/// * `df` - Table with a well column and a numeric value column
+/// * `well_column` - Name of the column with well names
+/// * `value_column` - Name of the numeric column
+fn well_values(
+    df: &DataFrame,
+    well_column: &str,
+    value_column: &str,
+) -> Result<(WellValues, usize), ThymeError> {
+    let wells = df
+        .column(well_column)
+        .and_then(|column| column.cast(&DataType::String))
+        .map_err(|_| ThymeError::OtherError(format!("Table is missing column: {}", well_column)))?;
+
+    let values = df
+        .column(value_column)
+        .and_then(|column| column.cast(&DataType::Float64))
+        .map_err(|_| {
+            ThymeError::OtherError(format!("Table is missing numeric column: {}", value_column))
+        })?;
+
+    let mut sums: BTreeMap<(usize, usize), (f64, usize)> = BTreeMap::new();
+    let mut skipped = 0;
+
+    for (well, value) in wells
+        .str()
+        .unwrap()
+        .into_iter()
+        .zip(values.f64().unwrap().into_iter())
+    {
+        let Some(well) = well.and_then(parse_well) else {
+            skipped += 1;
+            continue;
+        };
+
+        if let Some(value) = value.filter(|value| value.is_finite()) {
+            let (sum, n) = sums.entry(well).or_default();
+            *sum += value;
+            *n += 1;
+        }
+    }
+
+    let means = sums
+        .into_iter()
+        .map(|(well, (sum, n))| (well, sum / n as f64))
+        .collect();
+
+    Ok((means, skipped))
+}
+
+/// Number of (rows, columns) of a plate format
+///
+/// # Arguments
+///
+/// * `plate` - Plate format (96, 384, or auto)
+/// * `values` - Values of each (row, column) well
+fn plate_shape(plate: &str, values: &WellValues) -> Result<(usize, usize), ThymeError> {
+    let fits_96 = values.keys().all(|&(row, column)| row < 8 && column < 12);
+
+    match plate.trim().to_lowercase().as_str() {
+        "96" if fits_96 => Ok((8, 12)),
+        "96" => Err(ThymeError::OtherError(
+            "Table contains wells outside of a 96-well plate".to_string(),
+        )),
+        "384" => Ok((16, 24)),
+        "auto" if fits_96 => Ok((8, 12)),
+        "auto" => Ok((16, 24)),
+        _ => Err(ThymeError::OtherError(format!(
+            "Invalid plate format '{}'. Must be one of: 96, 384, auto",
+            plate
+        ))),
+    }
+}
+
+/// Map a value in [0, 1] to a color of the viridis colormap
+///
+/// # Arguments
+///
+/// * `t` - Position in the colormap
+fn viridis(t: f64) -> [u8; 3] {
+    let position = t.clamp(0.0, 1.0) * (VIRIDIS.len() - 1) as f64;
+    let lower = (position.floor() as usize).min(VIRIDIS.len() - 2);
+    let fraction = position - lower as f64;
+
+    let mut color = [0; 3];
+    for (c, value) in color.iter_mut().enumerate() {
+        let a = VIRIDIS[lower][c] as f64;
+        let b = VIRIDIS[lower + 1][c] as f64;
+        *value = (a + (b - a) * fraction).round() as u8;
+    }
+
+    color
+}
+
+/// Rows of a 3x5 bitmap glyph with the leftmost pixel in the highest bit
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        _ => [0; 5],
+    }
+}
+
+/// An rgb image that rectangles and text are drawn on
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, color: [u8; 3]) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: color.repeat((width * height) as usize),
+        }
+    }
+
+    /// Fill a rectangle clipped to the canvas
+    fn fill(&mut self, x: u32, y: u32, w: u32, h: u32, color: [u8; 3]) {
+        for j in y..(y + h).min(self.height) {
+            for i in x..(x + w).min(self.width) {
+                let idx = 3 * (j * self.width + i) as usize;
+                self.pixels[idx..idx + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Width of text drawn with glyphs scaled by `scale`
+    fn text_width(text: &str, scale: u32) -> u32 {
+        (text.chars().count() as u32 * 4).saturating_sub(1) * scale
+    }
+
+    /// Draw text with its top left corner at (x, y)
+    fn text(&mut self, x: u32, y: u32, text: &str, scale: u32) {
+        for (k, c) in text.chars().enumerate() {
+            let left = x + k as u32 * 4 * scale;
+
+            for (j, row) in glyph(c.to_ascii_uppercase()).iter().enumerate() {
+                for i in 0..3 {
+                    if row & (0b100 >> i) != 0 {
+                        let (i, j) = (left + i * scale, y + j as u32 * scale);
+                        self.fill(i, j, scale, scale, FOREGROUND);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw text centered on (x, y)
+    fn text_centered(&mut self, x: u32, y: u32, text: &str, scale: u32) {
+        let x = x.saturating_sub(Self::text_width(text, scale) / 2);
+        let y = y.saturating_sub(5 * scale / 2);
+        self.text(x, y, text, scale);
+    }
+}
+
+/// Render a labeled plate heatmap with a colorbar
+///
+/// Wells are drawn as `cell` sized squares with row letters on the left and
+/// column numbers on the top. Values are scaled from their minimum to their
+/// maximum through the viridis colormap, with the range labeled under the
+/// colorbar. Wells without a value are drawn gray.
+///
+/// # Arguments
+///
+/// * `values` - Values of each (row, column) well
+/// * `rows` - Number of plate rows
+/// * `columns` - Number of plate columns
+/// * `cell` - Width and height of each well (pixels)
+fn render(
+    values: &WellValues,
+    rows: usize,
+    columns: usize,
+    cell: u32,
+) -> Result<ThymeImage, ThymeError> {
+    let scale = (cell / 10).max(1);
+    let (rows, columns) = (rows as u32, columns as u32);
+
+    let bar_top = cell * (rows + 1) + cell / 2;
+    let width = cell * (columns + 2);
+    let height = bar_top + cell / 2 + 7 * scale + cell / 2;
+
+    let mut canvas = Canvas::new(width, height, BACKGROUND);
+
+    let min = values.values().copied().fold(f64::INFINITY, f64::min);
+    let max = values.values().copied().fold(f64::NEG_INFINITY, f64::max);
+    let position = |value: f64| {
+        if max > min {
+            (value - min) / (max - min)
+        } else {
+            0.5
+        }
+    };
+
+    for row in 0..rows {
+        let label = char::from(b'A' + row as u8).to_string();
+        canvas.text_centered(cell / 2, cell * (row + 1) + cell / 2, &label, scale);
+    }
+
+    for column in 0..columns {
+        let label = (column + 1).to_string();
+        canvas.text_centered(cell * (column + 1) + cell / 2, cell / 2, &label, scale);
+    }
+
+    for row in 0..rows {
+        for column in 0..columns {
+            let color = values
+                .get(&(row as usize, column as usize))
+                .map_or(MISSING, |&value| viridis(position(value)));
+
+            let (x, y) = (cell * (column + 1), cell * (row + 1));
+            canvas.fill(x + 1, y + 1, cell - 2, cell - 2, color);
+        }
+    }
+
+    let bar_width = cell * columns;
+    for i in 0..bar_width {
+        let color = viridis(i as f64 / (bar_width - 1) as f64);
+        canvas.fill(cell + i, bar_top, 1, cell / 2, color);
+    }
+
+    if !values.is_empty() {
+        let label_top = bar_top + cell / 2 + 2 * scale;
+        let max_label = format!("{:.2}", max);
+        canvas.text(cell, label_top, &format!("{:.2}", min), scale);
+        canvas.text(
+            (cell + bar_width).saturating_sub(Canvas::text_width(&max_label, scale)),
+            label_top,
+            &max_label,
+            scale,
+        );
+    }
+
+    Ok(ThymeImage::U8(ThymeBuffer::new(
+        canvas.width,
+        canvas.height,
+        3,
+        canvas.pixels,
+    )?))
+}
+
+#[cfg(test)]
+mod test {
+
+    use polars::prelude::*;
+
+    use super::*;
+
+    /// Color of the pixel at the center of a well
+    fn well_color(heatmap: &ThymeImage, row: u32, column: u32, cell: u32) -> [u8; 3] {
+        let (x, y) = (cell * (column + 1) + cell / 2, cell * (row + 1) + cell / 2);
+        let pixels = heatmap.to_u8_clamped();
+        let idx = 3 * (y * heatmap.width() + x) as usize;
+        [pixels[idx], pixels[idx + 1], pixels[idx + 2]]
+    }
+
+    #[test]
+    fn test_parse_well() {
+        assert_eq!(parse_well("A01"), Some((0, 0)));
+        assert_eq!(parse_well("h12"), Some((7, 11)));
+        assert_eq!(parse_well("P24"), Some((15, 23)));
+        assert_eq!(parse_well("plate1_C7"), Some((2, 6)));
+        assert_eq!(parse_well("plate1-B02_s1"), Some((1, 1)));
+        assert_eq!(parse_well("Q01"), None);
+        assert_eq!(parse_well("A25"), None);
+        assert_eq!(parse_well("A00"), None);
+    }
+
+    #[test]
+    fn test_plate_shape() {
+        let mut values = BTreeMap::from([((7, 11), 1.0)]);
+        assert_eq!(plate_shape("auto", &values).unwrap(), (8, 12));
+        assert_eq!(plate_shape("384", &values).unwrap(), (16, 24));
+
+        values.insert((8, 0), 1.0);
+        assert_eq!(plate_shape("auto", &values).unwrap(), (16, 24));
+        assert!(plate_shape("96", &values).is_err());
+        assert!(plate_shape("1536", &values).is_err());
+    }
+
+    #[test]
+    fn test_viridis() {
+        assert_eq!(viridis(0.0), VIRIDIS[0]);
+        assert_eq!(viridis(0.5), VIRIDIS[4]);
+        assert_eq!(viridis(1.0), VIRIDIS[8]);
+        assert_eq!(viridis(2.0), VIRIDIS[8]);
+    }
+
+    #[test]
+    fn test_render_96_well_heatmap() {
+        // Every well of a 96-well plate except H12, with two images in A01
+        let mut wells = vec!["plate1_A01".to_string(), "control".to_string()];
+        let mut counts = vec![20.0, 10.0];
+
+        for row in 0..8u8 {
+            for column in 1..=12 {
+                if (row, column) != (7, 12) {
+                    wells.push(format!("plate1_{}{:02}", (b'A' + row) as char, column));
+                    counts.push(50.0);
+                }
+            }
+        }
+
+        counts[2] = -20.0;
+        counts[14] = 100.0;
+
+        let df = DataFrame::new(vec![
+            Column::new("well".into(), wells),
+            Column::new("object_count".into(), counts),
+        ])
+        .unwrap();
+
+        let (values, skipped) = well_values(&df, "well", "object_count").unwrap();
+        assert_eq!(skipped, 1);
+        assert_eq!(values.len(), 95);
+        assert_eq!(values[&(0, 0)], 0.0);
+        assert_eq!(values[&(1, 0)], 100.0);
+
+        let (rows, columns) = plate_shape("auto", &values).unwrap();
+        assert_eq!((rows, columns), (8, 12));
+
+        let cell = 20;
+        let heatmap = render(&values, rows, columns, cell).unwrap();
+
+        assert_eq!((heatmap.width(), heatmap.height()), (280, 224));
+
+        assert_eq!(well_color(&heatmap, 0, 0, cell), VIRIDIS[0]);
+        assert_eq!(well_color(&heatmap, 1, 0, cell), VIRIDIS[8]);
+        assert_eq!(well_color(&heatmap, 3, 5, cell), VIRIDIS[4]);
+        assert_eq!(well_color(&heatmap, 7, 11, cell), MISSING);
+
+        let output = std::env::temp_dir().join("thyme_test_platemap_heatmap.png");
+        heatmap.save(&output).unwrap();
+        assert_eq!(ThymeImage::open(&output).unwrap().channels(), 3);
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(well_values(&df, "plate", "object_count").is_err());
+        assert!(well_values(&df, "well", "intensity").is_err());
+    }
+}
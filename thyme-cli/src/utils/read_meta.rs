@@ -0,0 +1,156 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::path::Path;
+
+use clap::Args;
+use serde_json::Value;
+
+use thyme_core::error::ThymeError;
+use thyme_core::io;
+
+use crate::process::METADATA_KEYWORD;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Print the object metadata embedded in a png crop written with --embed-metadata."
+)]
+pub struct ReadMetaArgs {
+    #[arg(
+        help = "Png crop written by a process command with --embed-metadata.",
+        required = true
+    )]
+    pub crop: Option<String>,
+}
+
+pub fn utils_read_meta(args: &ReadMetaArgs) {
+    let crop = args.crop.to_owned().unwrap();
+
+    let metadata = read_metadata(Path::new(&crop)).unwrap_or_else(|err| {
+        eprintln!("[thyme::utils::read_meta] ERROR: {}", err);
+        std::process::exit(1);
+    });
+
+    println!("{}", serde_json::to_string_pretty(&metadata).unwrap());
+}
+
+/// Read the json metadata embedded in a png crop
+///
+/// # Arguments
+///
+/// * `path` - Path to a png crop
+fn read_metadata(path: &Path) -> Result<Value, ThymeError> {
+    let text = io::read_png_text(path)?;
+
+    let Some((_, json)) = text.iter().find(|(keyword, _)| keyword == METADATA_KEYWORD) else {
+        return Err(ThymeError::OtherError(format!(
+            "No embedded metadata was found in {}",
+            path.display()
+        )));
+    };
+
+    serde_json::from_str(json)
+        .map_err(|err| ThymeError::OtherError(format!("Invalid embedded metadata. {}", err)))
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::Parser;
+
+    use thyme_core::im;
+    use thyme_core::ut::synth::SynthConfig;
+
+    use super::*;
+    use crate::process::{CropMetadata, ProcessArgs, process};
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: ProcessArgs,
+    }
+
+    #[test]
+    fn test_read_meta_roundtrip() {
+        let directory = std::env::temp_dir().join("thyme_test_read_meta_roundtrip");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let data = directory.join("data");
+
+        SynthConfig {
+            width: 64,
+            height: 64,
+            objects: 3,
+            radius: [3.0, 6.0],
+            seed: 3,
+            ..Default::default()
+        }
+        .write(1, &data)
+        .unwrap();
+
+        for (command, segments, substring, modes) in [
+            ("mask", "--mask-substring", "_mask", "cmfx"),
+            ("polygons", "--polygon-substring", "_polygons", "cbx"),
+            ("boxes", "--box-substring", "_boxes", "cx"),
+        ] {
+            let output = directory.join(command);
+
+            let args = [
+                "thyme",
+                command,
+                "-i",
+                data.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                "-m",
+                modes,
+                "--image-substring",
+                "_image",
+                segments,
+                substring,
+                "--embed-metadata",
+            ];
+
+            process(&Cli::parse_from(args).args);
+
+            let image = data.join("synth_0000_image.png");
+            let boxes = output.join("bounding_boxes").join("synth_0000.json");
+            let boxes = im::BoundingBoxes::open(boxes).unwrap();
+
+            for kind in ["complete", "mask", "foreground", "background"] {
+                if !output.join(kind).exists() {
+                    continue;
+                }
+
+                let crop = output.join(kind).join("synth_0000_1.png");
+                let metadata: CropMetadata =
+                    serde_json::from_value(read_metadata(&crop).unwrap()).unwrap();
+
+                assert_eq!(metadata.image, image.display().to_string(), "{}", command);
+                assert_eq!(metadata.object, 1);
+                assert_eq!(metadata.bbox, boxes.as_xyxy()[1]);
+
+                // Crops are the padded bounding box of the object
+                let [x, y, w, h] = metadata.crop;
+                let (height, width, _) = im::ThymeImage::open(&crop).unwrap().shape();
+                assert_eq!((width, height), (w, h));
+                assert!(x as f32 <= metadata.bbox[0] && y as f32 <= metadata.bbox[1]);
+
+                match command {
+                    "boxes" => assert!(metadata.polygon.is_none()),
+                    _ => assert!(!metadata.polygon.unwrap().is_empty()),
+                }
+
+                match command {
+                    "mask" => assert!(metadata.label.is_some()),
+                    _ => assert!(metadata.label.is_none()),
+                }
+            }
+        }
+
+        let plain = data.join("synth_0000_image.png");
+        assert!(read_metadata(&plain).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,449 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use clap::Args;
+use polars::prelude::{BooleanChunked, DataFrame, DataType, NewChunkedArray};
+
+use thyme_core::error::ThymeError;
+use thyme_core::io;
+use thyme_core::ut;
+use thyme_core::ut::split::{SplitSpec, split_groups};
+
+use crate::output::{OutputMode, finish_run, prepare_output_directory};
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Split the rows of a crop or descriptor table into train/val/test sets without splitting groups (e.g. source images)."
+)]
+pub struct SplitDatasetArgs {
+    #[arg(
+        short = 'i',
+        long,
+        help = "Table with one row per object (csv, tsv, txt, parquet, pq, arrow, or feather).",
+        required = true
+    )]
+    pub input: Option<String>,
+
+    #[arg(
+        short = 'o',
+        long,
+        help = "Output directory for the table of each split (and materialized files).",
+        required = true
+    )]
+    pub output: Option<String>,
+
+    #[arg(
+        long,
+        help = "Fractions of rows in each split as train,test or train,val,test.",
+        default_value = "0.7,0.15,0.15"
+    )]
+    pub split: Option<String>,
+
+    #[arg(
+        long,
+        help = "Column whose rows are always assigned to the same split (e.g. image).",
+        default_value = "image"
+    )]
+    pub group_by: Option<String>,
+
+    #[arg(
+        long,
+        help = "Column whose values are each split in the requested fractions (e.g. class_id)."
+    )]
+    pub stratify_by: Option<String>,
+
+    #[arg(long, help = "Random seed.", default_value = "0")]
+    pub seed: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Place the files of each split in split subdirectories: none, symlink, or copy.",
+        default_value = "none"
+    )]
+    pub materialize: Option<String>,
+
+    #[arg(
+        long,
+        help = "Column with the file of each row used by --materialize. Relative paths are resolved against the directory of the input table.",
+        default_value = "path"
+    )]
+    pub path_column: Option<String>,
+
+    #[arg(
+        long,
+        help = "Remove the outputs of a previous run (split tables and files) from the output directory before splitting. Without it, output directories holding a previous run are rejected."
+    )]
+    pub overwrite: bool,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+}
+
+/// How the files of each split are placed in the split subdirectories
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Materialize {
+    None,
+    Symlink,
+    Copy,
+}
+
+impl Materialize {
+    fn parse(materialize: &str) -> Result<Materialize, ThymeError> {
+        match materialize {
+            "none" => Ok(Materialize::None),
+            "symlink" => Ok(Materialize::Symlink),
+            "copy" => Ok(Materialize::Copy),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid materialize '{}'. Must be one of: none, symlink, copy",
+                materialize
+            ))),
+        }
+    }
+}
+
+/// Number of rows and groups written to a split
+#[derive(Debug, PartialEq, Eq)]
+struct SplitCount {
+    name: &'static str,
+    rows: usize,
+    groups: usize,
+}
+
+pub fn utils_split_dataset(args: &SplitDatasetArgs) {
+    let (output, counts) = split_dataset(args).unwrap_or_else(|err| {
+        eprintln!("[thyme::utils::split_dataset] ERROR: {}", err);
+        std::process::exit(1);
+    });
+
+    let summary: Vec<String> = counts
+        .iter()
+        .map(|count| {
+            format!(
+                "{} rows ({} groups) in {}",
+                ut::track::thousands_format(count.rows),
+                ut::track::thousands_format(count.groups),
+                count.name
+            )
+        })
+        .collect();
+
+    ut::track::progress_log(
+        &format!(
+            "Complete. {} written to {}.",
+            summary.join(", "),
+            output.display()
+        ),
+        args.verbose,
+    );
+}
+
+/// Split the input table and write the table (and files) of each split
+fn split_dataset(args: &SplitDatasetArgs) -> Result<(PathBuf, Vec<SplitCount>), ThymeError> {
+    let spec = SplitSpec::parse(args.split.as_deref().unwrap_or("0.7,0.15,0.15"))?;
+    let materialize = Materialize::parse(args.materialize.as_deref().unwrap_or("none"))?;
+
+    let input = PathBuf::from(args.input.to_owned().unwrap());
+    let df = io::read_table(&input)?;
+
+    let groups = string_column(&df, args.group_by.as_deref().unwrap_or("image"))?;
+    let strata = args
+        .stratify_by
+        .as_deref()
+        .map(|column| string_column(&df, column))
+        .transpose()?;
+
+    let paths = match materialize {
+        Materialize::None => None,
+        _ => Some(string_column(
+            &df,
+            args.path_column.as_deref().unwrap_or("path"),
+        )?),
+    };
+
+    let splits = split_groups(&groups, strata.as_deref(), &spec, args.seed.unwrap_or(0));
+
+    let extension = input
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("csv")
+        .to_lowercase();
+
+    let root = input.parent().unwrap_or(Path::new(""));
+    let output = prepare_output_directory(
+        Path::new(args.output.as_deref().unwrap_or_default()),
+        OutputMode::new(args.overwrite, false)?,
+        &[args.input.as_deref().unwrap_or_default()],
+        "utils::split_dataset",
+    )?;
+
+    let mut counts = vec![];
+
+    for (k, &name) in spec.names().iter().enumerate() {
+        let rows: Vec<usize> = (0..splits.len()).filter(|&row| splits[row] == k).collect();
+
+        let selected =
+            BooleanChunked::from_iter_values("split".into(), splits.iter().map(|&s| s == k));
+        let mut table = df
+            .filter(&selected)
+            .map_err(|err| ThymeError::OtherError(err.to_string()))?;
+
+        io::write_table(&mut table, output.join(format!("{}.{}", name, extension)))?;
+
+        if let Some(paths) = &paths {
+            for &row in rows.iter() {
+                let Some(path) = &paths[row] else {
+                    return Err(ThymeError::OtherError(format!(
+                        "Row {} has no value in the path column",
+                        row
+                    )));
+                };
+
+                materialize_file(Path::new(path), root, &output.join(name), materialize)?;
+            }
+        }
+
+        counts.push(SplitCount {
+            name,
+            rows: rows.len(),
+            groups: rows
+                .iter()
+                .map(|&row| &groups[row])
+                .collect::<BTreeSet<_>>()
+                .len(),
+        });
+    }
+
+    finish_run(&output)?;
+
+    Ok((output, counts))
+}
+
+/// Values of a column as strings, with nulls kept as `None`
+fn string_column(df: &DataFrame, column: &str) -> Result<Vec<Option<String>>, ThymeError> {
+    let values = df
+        .column(column)
+        .and_then(|values| values.cast(&DataType::String))
+        .map_err(|_| ThymeError::OtherError(format!("Table is missing column: {}", column)))?;
+
+    Ok(values
+        .str()
+        .unwrap()
+        .into_iter()
+        .map(|value| value.map(str::to_string))
+        .collect())
+}
+
+/// Symlink or copy a file into the directory of its split
+///
+/// Relative paths keep their subdirectories (e.g. complete/a01_0.png) so that
+/// crops of different modes do not collide. Absolute paths, and relative
+/// paths that leave the input directory, are placed by file name.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file in the input table
+/// * `root` - Directory relative paths are resolved against
+/// * `directory` - Directory of the split
+/// * `materialize` - Whether the file is symlinked or copied
+fn materialize_file(
+    path: &Path,
+    root: &Path,
+    directory: &Path,
+    materialize: Materialize,
+) -> Result<(), ThymeError> {
+    let nested = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+
+    let source = match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => root.join(path),
+    };
+
+    let destination = match nested {
+        true => directory.join(path),
+        false => directory.join(path.file_name().unwrap_or(path.as_os_str())),
+    };
+
+    if !source.is_file() {
+        return Err(ThymeError::NoFileError(source.display().to_string()));
+    }
+
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| ThymeError::DirError(format!("{}. {}", parent.display(), err)))?;
+    }
+
+    let result = match materialize {
+        Materialize::None => Ok(()),
+        Materialize::Copy => std::fs::copy(&source, &destination).map(|_| ()),
+        Materialize::Symlink => {
+            let source = source
+                .canonicalize()
+                .map_err(|err| ThymeError::NoFileError(err.to_string()))?;
+
+            symlink(&source, &destination)
+        }
+    };
+
+    result.map_err(|err| {
+        ThymeError::OtherError(format!(
+            "Failed to materialize {} at {}. {}",
+            source.display(),
+            destination.display(),
+            err
+        ))
+    })
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, destination)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(source, destination)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::collections::BTreeMap;
+
+    use clap::Parser;
+    use polars::prelude::*;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: SplitDatasetArgs,
+    }
+
+    fn parse(args: &[&str]) -> SplitDatasetArgs {
+        Cli::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args
+    }
+
+    #[test]
+    fn test_split_dataset() {
+        let directory = std::env::temp_dir().join("thyme_test_split_dataset");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(directory.join("crops").join("complete")).unwrap();
+
+        let (mut images, mut objects, mut paths, mut classes) = (vec![], vec![], vec![], vec![]);
+
+        for image in 0..20 {
+            for object in 0..(1 + image % 4) {
+                let path = format!("crops/complete/a{:02}_{}.png", image, object);
+                std::fs::write(directory.join(&path), path.as_bytes()).unwrap();
+
+                images.push(format!("a{:02}", image));
+                objects.push(object as u32);
+                paths.push(path);
+                classes.push((image % 2) as u32);
+            }
+        }
+
+        let n = images.len();
+        let mut df = DataFrame::new(vec![
+            Column::new("image".into(), images),
+            Column::new("object".into(), objects),
+            Column::new("path".into(), paths),
+            Column::new("class_id".into(), classes),
+        ])
+        .unwrap();
+
+        let table = directory.join("crops.csv");
+        io::write_table(&mut df, &table).unwrap();
+
+        let output = directory.join("splits");
+        let run = |extra: &[&str]| {
+            let mut args = vec![
+                "-i",
+                table.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                "--seed",
+                "42",
+            ];
+
+            args.extend(extra);
+            split_dataset(&parse(&args))
+        };
+
+        let (written, counts) =
+            run(&["--stratify-by", "class_id", "--materialize", "copy"]).unwrap();
+        assert_eq!(written, output);
+        assert_eq!(counts.iter().map(|c| c.rows).sum::<usize>(), n);
+        assert_eq!(counts.iter().map(|c| c.groups).sum::<usize>(), 20);
+
+        let mut split_of: BTreeMap<String, &str> = BTreeMap::new();
+        for count in counts.iter() {
+            let split = io::read_table(output.join(format!("{}.csv", count.name))).unwrap();
+            assert_eq!(split.height(), count.rows);
+
+            for (image, path) in string_column(&split, "image")
+                .unwrap()
+                .into_iter()
+                .zip(string_column(&split, "path").unwrap())
+            {
+                // Objects of an image never straddle splits
+                let previous = split_of.insert(image.unwrap(), count.name);
+                assert!(previous.is_none_or(|previous| previous == count.name));
+
+                let path = path.unwrap();
+                let copied = output.join(count.name).join(&path);
+                assert_eq!(std::fs::read(copied).unwrap(), path.as_bytes());
+            }
+        }
+
+        assert_eq!(split_of.len(), 20);
+
+        // Re-running into the directory of a previous run requires --overwrite
+        assert!(run(&["--split", "0.5,0.5"]).is_err());
+
+        let (written, symlinked) = run(&[
+            "--split",
+            "0.5,0.5",
+            "--materialize",
+            "symlink",
+            "--overwrite",
+        ])
+        .unwrap();
+        assert_eq!(written, output);
+        assert!(!output.join("val.csv").exists());
+        assert!(!output.join("val").exists());
+        assert!(directory.join("crops").join("complete").is_dir());
+        assert_eq!(
+            symlinked.iter().map(|c| c.name).collect::<Vec<_>>(),
+            ["train", "test"]
+        );
+
+        let first = string_column(&io::read_table(written.join("train.csv")).unwrap(), "path")
+            .unwrap()[0]
+            .clone()
+            .unwrap();
+
+        #[cfg(unix)]
+        assert!(
+            written
+                .join("train")
+                .join(&first)
+                .symlink_metadata()
+                .unwrap()
+                .file_type()
+                .is_symlink()
+        );
+
+        assert!(run(&["--split", "0.5,0.6"]).is_err());
+        assert!(run(&["--group-by", "well"]).is_err());
+        assert!(run(&["--materialize", "move"]).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
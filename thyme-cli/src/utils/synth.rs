@@ -0,0 +1,190 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use clap::Args;
+
+use thyme_core::error::ThymeError;
+use thyme_core::im::Dtype;
+use thyme_core::ut;
+use thyme_core::ut::synth::SynthConfig;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Generate synthetic images of elliptical objects with matching masks, polygons, and bounding boxes."
+)]
+pub struct SynthArgs {
+    #[arg(short = 'o', long, help = "Output directory.", required = true)]
+    pub output: Option<String>,
+
+    #[arg(long, help = "Number of images.", default_value = "10")]
+    pub images: Option<usize>,
+
+    #[arg(long, help = "Number of objects in each image.", default_value = "50")]
+    pub objects_per_image: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Width and height of each image (pixels).",
+        default_value = "512"
+    )]
+    pub size: Option<u32>,
+
+    #[arg(long, help = "Number of image channels.", default_value = "1")]
+    pub channels: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Image data type (u8, u16, u32, f32, f64). Images are written as png for 8 and 16-bit images with 1 or 3 channels and npy otherwise.",
+        default_value = "u16"
+    )]
+    pub dtype: Option<String>,
+
+    #[arg(
+        long,
+        help = "Range of the semi-major axis of each object as min,max (pixels).",
+        default_value = "8,16"
+    )]
+    pub radius: Option<String>,
+
+    #[arg(
+        long,
+        help = "Range of the eccentricity of each object as min,max in [0, 1).",
+        default_value = "0,0.8"
+    )]
+    pub eccentricity: Option<String>,
+
+    #[arg(
+        long,
+        help = "Maximum fraction of each object that may overlap other objects in [0, 1). Objects are separated by background when zero.",
+        default_value = "0"
+    )]
+    pub overlap: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Standard deviation of gaussian intensity noise as a fraction of the data type range.",
+        default_value = "0.02"
+    )]
+    pub noise: Option<f32>,
+
+    #[arg(long, help = "Random seed.", default_value = "0")]
+    pub seed: Option<u64>,
+
+    #[arg(short = 'v', long, help = "Verbose output.")]
+    pub verbose: bool,
+}
+
+pub fn utils_synth(args: &SynthArgs) {
+    let config = config(args).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    let output = args.output.to_owned().unwrap();
+    let images = args.images.unwrap_or(10);
+
+    let stems = config.write(images, &output).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    ut::track::progress_log(
+        &format!(
+            "Complete. {} synthetic images with {} objects each written to {}.",
+            ut::track::thousands_format(stems.len()),
+            ut::track::thousands_format(config.objects),
+            output
+        ),
+        args.verbose,
+    );
+}
+
+/// Resolve the generator settings from the command line arguments
+fn config(args: &SynthArgs) -> Result<SynthConfig, ThymeError> {
+    let size = args.size.unwrap_or(512);
+
+    let config = SynthConfig {
+        width: size,
+        height: size,
+        channels: args.channels.unwrap_or(1),
+        objects: args.objects_per_image.unwrap_or(50),
+        dtype: Dtype::parse(args.dtype.as_deref().unwrap_or("u16"))?,
+        radius: parse_range(args.radius.as_deref().unwrap_or("8,16"), "radius")?,
+        eccentricity: parse_range(
+            args.eccentricity.as_deref().unwrap_or("0,0.8"),
+            "eccentricity",
+        )?,
+        overlap: args.overlap.unwrap_or(0.0),
+        noise: args.noise.unwrap_or(0.02),
+        seed: args.seed.unwrap_or(0),
+    };
+
+    config.validate()?;
+
+    Ok(config)
+}
+
+/// Parse a range provided as min,max
+fn parse_range(range: &str, name: &str) -> Result<[f32; 2], ThymeError> {
+    let values: Vec<f32> = range
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| {
+            ThymeError::OtherError(format!(
+                "Invalid {} '{}'. Must be provided as min,max",
+                name, range
+            ))
+        })?;
+
+    match values.as_slice() {
+        &[min, max] => Ok([min, max]),
+        _ => Err(ThymeError::OtherError(format!(
+            "Invalid {} '{}'. Must be provided as min,max",
+            name, range
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: SynthArgs,
+    }
+
+    fn parse(args: &[&str]) -> SynthArgs {
+        Cli::parse_from(std::iter::once("thyme").chain(args.iter().copied())).args
+    }
+
+    #[test]
+    fn test_synth_config() {
+        let config = config(&parse(&[
+            "-o", "demo", "--size", "128", "--dtype", "f32", "--radius", "4, 6", "--seed", "3",
+        ]))
+        .unwrap();
+
+        assert_eq!((config.width, config.height), (128, 128));
+        assert_eq!(config.dtype, Dtype::F32);
+        assert_eq!(config.radius, [4.0, 6.0]);
+        assert_eq!(config.seed, 3);
+
+        for invalid in [
+            vec!["--dtype", "i8"],
+            vec!["--radius", "4"],
+            vec!["--eccentricity", "0,a"],
+            vec!["--overlap", "1"],
+            vec!["--size", "16"],
+        ] {
+            let mut args = vec!["-o", "demo"];
+            args.extend(&invalid);
+            assert!(super::config(&parse(&args)).is_err(), "{:?}", invalid);
+        }
+    }
+}
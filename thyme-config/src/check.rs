@@ -0,0 +1,210 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::ut::path::{is_remote, same_path};
+
+pub(crate) fn other(message: &str) -> ThymeError {
+    ThymeError::OtherError(message.to_string())
+}
+
+/// Fail if an option is set without the option it requires
+pub(crate) fn requires(set: bool, required: bool, message: &str) -> Result<(), ThymeError> {
+    match set && !required {
+        true => Err(other(message)),
+        false => Ok(()),
+    }
+}
+
+pub(crate) fn check_paths(images: &str, output: &str) -> Result<(), ThymeError> {
+    if images.is_empty() {
+        return Err(other("images must be provided"));
+    }
+
+    if output.is_empty() {
+        return Err(other("output must be provided"));
+    }
+
+    Ok(())
+}
+
+/// Fail if remote inputs are combined with options that need local files
+///
+/// Remote inputs are fetched into a temporary directory and removed once
+/// read unless a remote cache is provided, so provenance records that
+/// read the input files after the run require the cache.
+pub(crate) fn check_remote(
+    inputs: &[&str],
+    output: &str,
+    remote_cache: Option<&str>,
+    provenance: bool,
+) -> Result<(), ThymeError> {
+    if is_remote(output) {
+        return Err(other("output must be a local path"));
+    }
+
+    if remote_cache.is_some_and(is_remote) {
+        return Err(other("remote_cache must be a local path"));
+    }
+
+    let remote = inputs.iter().any(|input| is_remote(input));
+
+    requires(
+        remote && provenance,
+        remote_cache.is_some(),
+        "provenance of remote inputs requires remote_cache",
+    )
+}
+
+pub(crate) fn check_threads(threads: Option<usize>) -> Result<(), ThymeError> {
+    match threads {
+        Some(0) => Err(other("threads must be a positive integer if provided")),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_mode(mode: &str, allowed: &[char]) -> Result<(), ThymeError> {
+    if mode.chars().any(|c| !allowed.contains(&c)) {
+        let allowed: Vec<String> = allowed.iter().map(|c| c.to_string()).collect();
+
+        return Err(ThymeError::OtherError(format!(
+            "Invalid mode {}. Argument mode must only contain one or more of: {}",
+            mode,
+            allowed.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn check_borders(drop_borders: bool, border_policy: &str) -> Result<(), ThymeError> {
+    match drop_borders && border_policy != "keep" && border_policy != "drop" {
+        true => Err(other("drop_borders cannot be combined with border_policy")),
+        false => Ok(()),
+    }
+}
+
+pub(crate) fn check_outputs(
+    ipc_compression: &str,
+    metadata_table: Option<&str>,
+    metadata_join: Option<&str>,
+    provenance: bool,
+    hash_inputs: bool,
+) -> Result<(), ThymeError> {
+    if !constant::SUPPORTED_IPC_COMPRESSION.contains(&ipc_compression) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid ipc_compression {}. Must be one of: {:?}",
+            ipc_compression,
+            constant::SUPPORTED_IPC_COMPRESSION
+        )));
+    }
+
+    requires(
+        metadata_table.is_some(),
+        metadata_join.is_some(),
+        "metadata_join must be provided alongside metadata_table",
+    )?;
+
+    requires(hash_inputs, provenance, "hash_inputs requires provenance")
+}
+
+pub(crate) fn check_resume(
+    per_image_output: bool,
+    resume: bool,
+    overwrite: bool,
+) -> Result<(), ThymeError> {
+    requires(resume, per_image_output, "resume requires per_image_output")?;
+
+    match resume && overwrite {
+        true => Err(other("overwrite cannot be combined with resume")),
+        false => Ok(()),
+    }
+}
+
+pub(crate) fn check_float_format(float_format: &str) -> Result<(), ThymeError> {
+    match constant::SUPPORTED_FLOAT_FORMATS.contains(&float_format) {
+        true => Ok(()),
+        false => Err(ThymeError::OtherError(format!(
+            "Invalid float_format {}. Must be one of: {:?}",
+            float_format,
+            constant::SUPPORTED_FLOAT_FORMATS
+        ))),
+    }
+}
+
+pub(crate) fn check_log_format(log_format: &str) -> Result<(), ThymeError> {
+    match constant::SUPPORTED_LOG_FORMATS.contains(&log_format) {
+        true => Ok(()),
+        false => Err(ThymeError::OtherError(format!(
+            "Invalid log_format {}. Must be one of: {:?}",
+            log_format,
+            constant::SUPPORTED_LOG_FORMATS
+        ))),
+    }
+}
+
+/// Substring, glob, and regex selecting the files of an input
+pub(crate) type Selection<'a> = (&'a Option<String>, &'a Option<String>, &'a Option<String>);
+
+/// Fail if more than one of a substring, glob, or regex selects an input
+///
+/// Glob and regex syntax is checked when the files are collected.
+fn check_selection(name: &str, (substring, glob, regex): Selection) -> Result<(), ThymeError> {
+    if [substring, glob, regex].into_iter().flatten().count() > 1 {
+        return Err(ThymeError::OtherError(format!(
+            "Only one of {0}_substring, {0}_glob, or {0}_regex can be provided",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+/// Name, path, and selection of the files of a segment input (e.g. mask)
+pub(crate) type Input<'a> = (&'a str, &'a str, Selection<'a>);
+
+/// Check the selections of the images and segments of a run
+///
+/// Fails if more than one of a substring, glob, or regex selects either
+/// input, or if both inputs share a path and selection.
+///
+/// # Arguments
+///
+/// * `images` - Path and selection of the images
+/// * `segments` - Name, path, and selection of the segments
+/// * `sources` - Inputs named in the error (e.g. images and masks)
+/// * `substrings` - Selections named in the error (e.g. image and mask)
+pub(crate) fn check_inputs(
+    (images, image_selection): (&str, Selection),
+    (segment, segments, segment_selection): Input,
+    sources: &str,
+    substrings: &str,
+) -> Result<(), ThymeError> {
+    check_selection("image", image_selection)?;
+    check_selection(segment, segment_selection)?;
+
+    check_substrings(
+        (images, image_selection),
+        (segments, segment_selection),
+        sources,
+        substrings,
+    )
+}
+
+/// Fail if two segment sources share a path and selection
+pub(crate) fn check_substrings(
+    a: (&str, Selection),
+    b: (&str, Selection),
+    sources: &str,
+    substrings: &str,
+) -> Result<(), ThymeError> {
+    if same_path(a.0, b.0) && a.1 == b.1 {
+        return Err(ThymeError::OtherError(format!(
+            "If {} are located in same path, different {} substrings must be provided",
+            sources, substrings
+        )));
+    }
+
+    Ok(())
+}
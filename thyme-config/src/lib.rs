@@ -0,0 +1,36 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+//! Configuration of thyme commands for use as a library
+//!
+//! Each config mirrors the arguments of a command line subcommand with the
+//! command line defaults already applied, so that pipelines can construct,
+//! serialize, and validate runs without going through argument parsing.
+//! Configs are provided for the `profile`, `process`, and `neural` commands.
+//!
+//! # Examples
+//!
+//! ```
+//! use thyme_config::ProfileMaskConfig;
+//!
+//! let config = ProfileMaskConfig::new("images/", "descriptors.csv")
+//!     .masks("masks/")
+//!     .mode("cfm")
+//!     .pad(2);
+//!
+//! assert!(config.validate().is_ok());
+//! assert_eq!(config.min_size, 1);
+//! ```
+
+#[macro_use]
+mod macros;
+
+mod check;
+
+pub mod neural;
+pub mod process;
+pub mod profile;
+
+pub use neural::{NeuralBoxesConfig, NeuralMaskConfig, NeuralPolygonsConfig};
+pub use process::{ProcessBoxesConfig, ProcessMaskConfig, ProcessPolygonsConfig};
+pub use profile::{ProfileBoxesConfig, ProfileMaskConfig, ProfilePolygonsConfig};
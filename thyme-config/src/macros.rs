@@ -0,0 +1,51 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+/// Implement a chainable setter for each listed field of a config
+///
+/// Fields listed under `optional` are wrapped in `Some` when set, and string
+/// fields accept any value that converts into a `String`.
+macro_rules! setters {
+    (
+        $config:ty {
+            values { $($value:ident: $kind:ty),* $(,)? }
+            strings { $($string:ident),* $(,)? }
+            optional { $($optional:ident: $optional_kind:ty),* $(,)? }
+            optional_strings { $($optional_string:ident),* $(,)? }
+        }
+    ) => {
+        impl $config {
+            $(
+                #[doc = concat!("Set `", stringify!($value), "`")]
+                pub fn $value(mut self, $value: $kind) -> Self {
+                    self.$value = $value;
+                    self
+                }
+            )*
+
+            $(
+                #[doc = concat!("Set `", stringify!($string), "`")]
+                pub fn $string(mut self, $string: impl Into<String>) -> Self {
+                    self.$string = $string.into();
+                    self
+                }
+            )*
+
+            $(
+                #[doc = concat!("Set `", stringify!($optional), "`")]
+                pub fn $optional(mut self, $optional: $optional_kind) -> Self {
+                    self.$optional = Some($optional);
+                    self
+                }
+            )*
+
+            $(
+                #[doc = concat!("Set `", stringify!($optional_string), "`")]
+                pub fn $optional_string(mut self, $optional_string: impl Into<String>) -> Self {
+                    self.$optional_string = Some($optional_string.into());
+                    self
+                }
+            )*
+        }
+    };
+}
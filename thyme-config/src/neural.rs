@@ -0,0 +1,1235 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use serde::{Deserialize, Serialize};
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im::{BoxColumns, ChannelMap, ImageSummary, MaskFormat, SegmentCoords};
+use thyme_core::ut::expr::Expression;
+use thyme_core::ut::path::is_remote;
+use thyme_core::ut::suggest;
+
+use crate::check::{
+    check_float_format, check_inputs, check_log_format, check_outputs, check_paths, check_remote,
+    check_resume, check_substrings, check_threads, other, requires,
+};
+
+/// Devices that models can be run on
+const SUPPORTED_DEVICES: [&str; 3] = ["cpu", "cuda", "metal"];
+
+/// Configuration of `thyme neural mask`
+///
+/// Fields mirror the command line arguments of the same name and default to
+/// the command line defaults.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_config::NeuralMaskConfig;
+///
+/// let config = NeuralMaskConfig::new("images/", "embeddings.npz")
+///     .masks("masks/")
+///     .model("dino_vit_base")
+///     .channel_map("gray-average");
+///
+/// assert_eq!(config.device, "cpu");
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NeuralMaskConfig {
+    /// Image directory or s3:// or https:// url
+    pub images: String,
+    /// Mask directory or url, defaulting to the image directory
+    pub masks: Option<String>,
+    /// Device models are run on (cpu, cuda, metal)
+    pub device: String,
+    /// Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather, .npy, .npz)
+    pub output: String,
+    /// Log progress
+    pub verbose: bool,
+    /// Show a live status panel instead of the progress bar if stdout is a terminal
+    pub tui: bool,
+    /// Format of the status lines logged if stdout is not a terminal (plain, json)
+    pub log_format: String,
+    /// Exclude objects touching the edge of the image
+    pub drop_borders: bool,
+    /// Model name
+    pub model: String,
+    /// Channel map applied before model preprocessing (auto, gray-average, or channel indices)
+    pub channel_map: String,
+    /// Padding around objects before embedding (pixels)
+    pub pad: u32,
+    /// Maximum edge length crops are downscaled to if the device runs out of memory
+    pub max_embed_edge: u32,
+    /// Substring specifying images
+    pub image_substring: Option<String>,
+    /// Substring specifying masks
+    pub mask_substring: Option<String>,
+    /// Layout of mask files (auto, cellpose-seg)
+    pub mask_format: String,
+    /// Glob pattern specifying images by their path relative to the image directory
+    pub image_glob: Option<String>,
+    /// Regex specifying images by their path relative to the image directory
+    pub image_regex: Option<String>,
+    /// Glob pattern specifying masks by their path relative to their directory
+    pub mask_glob: Option<String>,
+    /// Regex specifying masks by their path relative to their directory
+    pub mask_regex: Option<String>,
+    /// Minimum unpadded bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum mask area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum mask area (pixels)
+    pub max_area: Option<f32>,
+    /// Skip images whose image-level metrics match this expression
+    pub skip_image_if: Option<String>,
+    /// Fail images with masks that may be bit-depth truncated
+    pub strict_masks: bool,
+    /// Class mask directory with the semantic class id of each pixel
+    pub class_mask: Option<String>,
+    /// Substring specifying class masks
+    pub class_substring: Option<String>,
+    /// Metadata table joined to every output row
+    pub metadata_table: Option<String>,
+    /// Name of the join key column in the metadata table
+    pub metadata_join: Option<String>,
+    /// Regex extracting the join key from image file names
+    pub well_from_filename: Option<String>,
+    /// Fail instead of writing nulls for images without matching metadata
+    pub strict_join: bool,
+    /// Compression of arrow/feather output tables (lz4, zstd, none)
+    pub ipc_compression: String,
+    /// Digits after the decimal point of floats in csv/tsv output tables
+    pub float_precision: Option<usize>,
+    /// Notation of floats in csv/tsv output tables (auto, fixed)
+    pub float_format: String,
+    /// Write .npz embeddings in the version 1 layout
+    pub legacy_npz: bool,
+    /// Write the objects of each class to a separate output (class)
+    pub split_output_by: Option<String>,
+    /// Write a provenance sidecar next to each output table or array
+    pub provenance: bool,
+    /// Record sha256 hashes of input files in the provenance sidecar
+    pub hash_inputs: bool,
+    /// Directory caching object embeddings across runs
+    pub cache_dir: Option<String>,
+    /// Read embeddings from the cache without adding new entries
+    pub cache_readonly: bool,
+    /// Write a separate embeddings table for each image
+    pub per_image_output: bool,
+    /// Format of per-image output tables
+    pub per_image_format: String,
+    /// Skip images whose per-image output table already exists
+    pub resume: bool,
+    /// Remove the outputs of a previous run from the output location
+    pub overwrite: bool,
+    /// Number of decoded images queued ahead of the compute threads
+    pub prefetch: usize,
+    /// Number of threads decoding images if prefetching
+    pub io_threads: usize,
+    /// Directory caching objects fetched from remote inputs
+    pub remote_cache: Option<String>,
+    /// Number of compute threads on the cpu, defaulting to all available threads
+    pub threads: Option<usize>,
+    /// Report the pairs, objects, and output size of the run without loading the model
+    pub dry_run: bool,
+}
+
+impl Default for NeuralMaskConfig {
+    fn default() -> Self {
+        Self {
+            images: String::new(),
+            masks: None,
+            device: "cpu".to_string(),
+            output: String::new(),
+            verbose: false,
+            tui: false,
+            log_format: "plain".to_string(),
+            drop_borders: false,
+            model: "dino_vit_small".to_string(),
+            channel_map: "auto".to_string(),
+            pad: 1,
+            max_embed_edge: 1024,
+            image_substring: None,
+            mask_substring: None,
+            mask_format: "auto".to_string(),
+            image_glob: None,
+            image_regex: None,
+            mask_glob: None,
+            mask_regex: None,
+            min_size: 1,
+            min_area: None,
+            max_area: None,
+            skip_image_if: None,
+            strict_masks: false,
+            class_mask: None,
+            class_substring: None,
+            metadata_table: None,
+            metadata_join: None,
+            well_from_filename: None,
+            strict_join: false,
+            ipc_compression: "none".to_string(),
+            float_precision: None,
+            float_format: "auto".to_string(),
+            legacy_npz: false,
+            split_output_by: None,
+            provenance: false,
+            hash_inputs: false,
+            cache_dir: None,
+            cache_readonly: false,
+            per_image_output: false,
+            per_image_format: "csv".to_string(),
+            resume: false,
+            overwrite: false,
+            prefetch: 0,
+            io_threads: 2,
+            remote_cache: None,
+            threads: None,
+            dry_run: false,
+        }
+    }
+}
+
+setters! {
+    NeuralMaskConfig {
+        values {
+            verbose: bool,
+            tui: bool,
+            drop_borders: bool,
+            pad: u32,
+            max_embed_edge: u32,
+            min_size: u32,
+            strict_masks: bool,
+            strict_join: bool,
+            legacy_npz: bool,
+            provenance: bool,
+            hash_inputs: bool,
+            cache_readonly: bool,
+            per_image_output: bool,
+            resume: bool,
+            overwrite: bool,
+            prefetch: usize,
+            io_threads: usize,
+            dry_run: bool,
+        }
+        strings {
+            images,
+            device,
+            output,
+            log_format,
+            model,
+            channel_map,
+            mask_format,
+            ipc_compression,
+            float_format,
+            per_image_format,
+        }
+        optional {
+            min_area: f32,
+            max_area: f32,
+            float_precision: usize,
+            threads: usize,
+        }
+        optional_strings {
+            skip_image_if,
+            masks,
+            image_substring,
+            mask_substring,
+            image_glob,
+            image_regex,
+            mask_glob,
+            mask_regex,
+            class_mask,
+            class_substring,
+            metadata_table,
+            metadata_join,
+            well_from_filename,
+            split_output_by,
+            cache_dir,
+            remote_cache,
+        }
+    }
+}
+
+impl NeuralMaskConfig {
+    /// Initialize a config with command line defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Image directory
+    /// * `output` - Output directory or file
+    pub fn new(images: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            images: images.into(),
+            output: output.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Mask directory, defaulting to the image directory
+    pub fn masks_path(&self) -> &str {
+        self.masks.as_deref().unwrap_or(&self.images)
+    }
+
+    /// Input files and directories read by the run
+    pub fn inputs(&self) -> Vec<&str> {
+        [
+            Some(self.images.as_str()),
+            Some(self.masks_path()),
+            self.class_mask.as_deref(),
+            self.metadata_table.as_deref(),
+            self.cache_dir.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Check that the config describes a valid run
+    ///
+    /// Only the config itself is checked. Model names are checked against
+    /// the available weights and input files are checked when the command is
+    /// run.
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        check_paths(&self.images, &self.output)?;
+        check_threads(self.threads)?;
+        check_log_format(&self.log_format)?;
+
+        if let Some(skip_image_if) = &self.skip_image_if {
+            Expression::parse(skip_image_if, &ImageSummary::METRICS)?;
+        }
+
+        check_remote(
+            &[&self.images, self.masks_path()],
+            &self.output,
+            self.remote_cache.as_deref(),
+            self.provenance,
+        )?;
+
+        check_model(&self.model, &self.device, &self.channel_map)?;
+        check_embeddings(
+            &self.output,
+            self.per_image_output,
+            self.metadata_table.as_deref(),
+            self.cache_dir.is_some(),
+            self.cache_readonly,
+        )?;
+        check_outputs(
+            &self.ipc_compression,
+            self.metadata_table.as_deref(),
+            self.metadata_join.as_deref(),
+            self.provenance,
+            self.hash_inputs,
+        )?;
+        check_resume(self.per_image_output, self.resume, self.overwrite)?;
+        check_float_format(&self.float_format)?;
+        check_split(self.split_output_by.as_deref(), self.per_image_output)?;
+
+        MaskFormat::parse(&self.mask_format)?;
+
+        requires(
+            self.class_substring.is_some(),
+            self.class_mask.is_some(),
+            "class_substring requires class_mask",
+        )?;
+
+        requires(
+            self.split_output_by.is_some(),
+            self.class_mask.is_some(),
+            "split_output_by requires class_mask",
+        )?;
+
+        let masks = self.masks_path();
+        let mask_selection = (&self.mask_substring, &self.mask_glob, &self.mask_regex);
+
+        check_inputs(
+            (
+                &self.images,
+                (&self.image_substring, &self.image_glob, &self.image_regex),
+            ),
+            ("mask", masks, mask_selection),
+            "images and masks",
+            "image and mask",
+        )?;
+
+        if let Some(classes) = &self.class_mask {
+            check_substrings(
+                (masks, mask_selection),
+                (classes, (&self.class_substring, &None, &None)),
+                "masks and class masks",
+                "mask and class",
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Configuration of `thyme neural polygons`
+///
+/// Fields mirror the command line arguments of the same name and default to
+/// the command line defaults.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_config::NeuralPolygonsConfig;
+///
+/// let config = NeuralPolygonsConfig::new("images/", "embeddings.pq")
+///     .polygons("polygons/")
+///     .skip_invalid_polygons(true);
+///
+/// assert_eq!(config.model, "dino_vit_small");
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NeuralPolygonsConfig {
+    /// Image directory or s3:// or https:// url
+    pub images: String,
+    /// Polygons directory or url, defaulting to the image directory
+    pub polygons: Option<String>,
+    /// Device models are run on (cpu, cuda, metal)
+    pub device: String,
+    /// Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather, .npy, .npz)
+    pub output: String,
+    /// Log progress
+    pub verbose: bool,
+    /// Show a live status panel instead of the progress bar if stdout is a terminal
+    pub tui: bool,
+    /// Format of the status lines logged if stdout is not a terminal (plain, json)
+    pub log_format: String,
+    /// Exclude objects touching the edge of the image
+    pub drop_borders: bool,
+    /// Model name
+    pub model: String,
+    /// Channel map applied before model preprocessing (auto, gray-average, or channel indices)
+    pub channel_map: String,
+    /// Padding around objects before embedding (pixels)
+    pub pad: u32,
+    /// Maximum edge length crops are downscaled to if the device runs out of memory
+    pub max_embed_edge: u32,
+    /// Substring specifying images
+    pub image_substring: Option<String>,
+    /// Substring specifying polygons
+    pub polygon_substring: Option<String>,
+    /// Glob pattern specifying images by their path relative to the image directory
+    pub image_glob: Option<String>,
+    /// Regex specifying images by their path relative to the image directory
+    pub image_regex: Option<String>,
+    /// Glob pattern specifying polygons by their path relative to their directory
+    pub polygon_glob: Option<String>,
+    /// Regex specifying polygons by their path relative to their directory
+    pub polygon_regex: Option<String>,
+    /// Coordinate system of the polygon vertices (pixels, normalized)
+    pub segment_coords: String,
+    /// Minimum unpadded bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum polygon area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum polygon area (pixels)
+    pub max_area: Option<f32>,
+    /// Skip images whose image-level metrics match this expression
+    pub skip_image_if: Option<String>,
+    /// Exclude self-intersecting polygons that could not be repaired
+    pub skip_invalid_polygons: bool,
+    /// Metadata table joined to every output row
+    pub metadata_table: Option<String>,
+    /// Name of the join key column in the metadata table
+    pub metadata_join: Option<String>,
+    /// Regex extracting the join key from image file names
+    pub well_from_filename: Option<String>,
+    /// Fail instead of writing nulls for images without matching metadata
+    pub strict_join: bool,
+    /// Compression of arrow/feather output tables (lz4, zstd, none)
+    pub ipc_compression: String,
+    /// Digits after the decimal point of floats in csv/tsv output tables
+    pub float_precision: Option<usize>,
+    /// Notation of floats in csv/tsv output tables (auto, fixed)
+    pub float_format: String,
+    /// Write .npz embeddings in the version 1 layout
+    pub legacy_npz: bool,
+    /// Write a provenance sidecar next to each output table or array
+    pub provenance: bool,
+    /// Record sha256 hashes of input files in the provenance sidecar
+    pub hash_inputs: bool,
+    /// Directory caching object embeddings across runs
+    pub cache_dir: Option<String>,
+    /// Read embeddings from the cache without adding new entries
+    pub cache_readonly: bool,
+    /// Write a separate embeddings table for each image
+    pub per_image_output: bool,
+    /// Format of per-image output tables
+    pub per_image_format: String,
+    /// Skip images whose per-image output table already exists
+    pub resume: bool,
+    /// Remove the outputs of a previous run from the output location
+    pub overwrite: bool,
+    /// Number of decoded images queued ahead of the compute threads
+    pub prefetch: usize,
+    /// Number of threads decoding images if prefetching
+    pub io_threads: usize,
+    /// Directory caching objects fetched from remote inputs
+    pub remote_cache: Option<String>,
+    /// Number of compute threads on the cpu, defaulting to all available threads
+    pub threads: Option<usize>,
+    /// Report the pairs, objects, and output size of the run without loading the model
+    pub dry_run: bool,
+}
+
+impl Default for NeuralPolygonsConfig {
+    fn default() -> Self {
+        Self {
+            images: String::new(),
+            polygons: None,
+            device: "cpu".to_string(),
+            output: String::new(),
+            verbose: false,
+            tui: false,
+            log_format: "plain".to_string(),
+            drop_borders: false,
+            model: "dino_vit_small".to_string(),
+            channel_map: "auto".to_string(),
+            pad: 1,
+            max_embed_edge: 1024,
+            image_substring: None,
+            polygon_substring: None,
+            image_glob: None,
+            image_regex: None,
+            polygon_glob: None,
+            polygon_regex: None,
+            segment_coords: "pixels".to_string(),
+            min_size: 1,
+            min_area: None,
+            max_area: None,
+            skip_image_if: None,
+            skip_invalid_polygons: false,
+            metadata_table: None,
+            metadata_join: None,
+            well_from_filename: None,
+            strict_join: false,
+            ipc_compression: "none".to_string(),
+            float_precision: None,
+            float_format: "auto".to_string(),
+            legacy_npz: false,
+            provenance: false,
+            hash_inputs: false,
+            cache_dir: None,
+            cache_readonly: false,
+            per_image_output: false,
+            per_image_format: "csv".to_string(),
+            resume: false,
+            overwrite: false,
+            prefetch: 0,
+            io_threads: 2,
+            remote_cache: None,
+            threads: None,
+            dry_run: false,
+        }
+    }
+}
+
+setters! {
+    NeuralPolygonsConfig {
+        values {
+            verbose: bool,
+            tui: bool,
+            drop_borders: bool,
+            pad: u32,
+            max_embed_edge: u32,
+            min_size: u32,
+            skip_invalid_polygons: bool,
+            strict_join: bool,
+            legacy_npz: bool,
+            provenance: bool,
+            hash_inputs: bool,
+            cache_readonly: bool,
+            per_image_output: bool,
+            resume: bool,
+            overwrite: bool,
+            prefetch: usize,
+            io_threads: usize,
+            dry_run: bool,
+        }
+        strings {
+            images,
+            device,
+            output,
+            log_format,
+            model,
+            channel_map,
+            segment_coords,
+            ipc_compression,
+            float_format,
+            per_image_format,
+        }
+        optional {
+            min_area: f32,
+            max_area: f32,
+            float_precision: usize,
+            threads: usize,
+        }
+        optional_strings {
+            skip_image_if,
+            polygons,
+            image_substring,
+            polygon_substring,
+            image_glob,
+            image_regex,
+            polygon_glob,
+            polygon_regex,
+            metadata_table,
+            metadata_join,
+            well_from_filename,
+            cache_dir,
+            remote_cache,
+        }
+    }
+}
+
+impl NeuralPolygonsConfig {
+    /// Initialize a config with command line defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Image directory
+    /// * `output` - Output directory or file
+    pub fn new(images: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            images: images.into(),
+            output: output.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Polygons directory, defaulting to the image directory
+    pub fn polygons_path(&self) -> &str {
+        self.polygons.as_deref().unwrap_or(&self.images)
+    }
+
+    /// Input files and directories read by the run
+    pub fn inputs(&self) -> Vec<&str> {
+        [
+            Some(self.images.as_str()),
+            Some(self.polygons_path()),
+            self.metadata_table.as_deref(),
+            self.cache_dir.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Check that the config describes a valid run
+    ///
+    /// Only the config itself is checked. Model names are checked against
+    /// the available weights and input files are checked when the command is
+    /// run.
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        check_paths(&self.images, &self.output)?;
+        check_threads(self.threads)?;
+        check_log_format(&self.log_format)?;
+
+        if let Some(skip_image_if) = &self.skip_image_if {
+            Expression::parse(skip_image_if, &ImageSummary::METRICS)?;
+        }
+
+        check_remote(
+            &[&self.images, self.polygons_path()],
+            &self.output,
+            self.remote_cache.as_deref(),
+            self.provenance,
+        )?;
+
+        check_model(&self.model, &self.device, &self.channel_map)?;
+        check_embeddings(
+            &self.output,
+            self.per_image_output,
+            self.metadata_table.as_deref(),
+            self.cache_dir.is_some(),
+            self.cache_readonly,
+        )?;
+        check_outputs(
+            &self.ipc_compression,
+            self.metadata_table.as_deref(),
+            self.metadata_join.as_deref(),
+            self.provenance,
+            self.hash_inputs,
+        )?;
+        check_resume(self.per_image_output, self.resume, self.overwrite)?;
+        check_float_format(&self.float_format)?;
+
+        SegmentCoords::parse(&self.segment_coords)?;
+
+        check_inputs(
+            (
+                &self.images,
+                (&self.image_substring, &self.image_glob, &self.image_regex),
+            ),
+            (
+                "polygon",
+                self.polygons_path(),
+                (
+                    &self.polygon_substring,
+                    &self.polygon_glob,
+                    &self.polygon_regex,
+                ),
+            ),
+            "images and polygons",
+            "image and polygon",
+        )
+    }
+}
+
+/// Configuration of `thyme neural boxes`
+///
+/// Fields mirror the command line arguments of the same name and default to
+/// the command line defaults.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_config::NeuralBoxesConfig;
+///
+/// let config = NeuralBoxesConfig::new("images/", "output/")
+///     .boxes("boxes.csv")
+///     .split_output_by("class");
+///
+/// assert_eq!(config.max_embed_edge, 1024);
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NeuralBoxesConfig {
+    /// Image directory
+    pub images: String,
+    /// Bounding boxes directory or csv table, defaulting to the image directory
+    pub boxes: Option<String>,
+    /// Device models are run on (cpu, cuda, metal)
+    pub device: String,
+    /// Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather, .npy, .npz)
+    pub output: String,
+    /// Log progress
+    pub verbose: bool,
+    /// Show a live status panel instead of the progress bar if stdout is a terminal
+    pub tui: bool,
+    /// Format of the status lines logged if stdout is not a terminal (plain, json)
+    pub log_format: String,
+    /// Exclude objects touching the edge of the image
+    pub drop_borders: bool,
+    /// Model name
+    pub model: String,
+    /// Channel map applied before model preprocessing (auto, gray-average, or channel indices)
+    pub channel_map: String,
+    /// Padding around objects before embedding (pixels)
+    pub pad: u32,
+    /// Maximum edge length crops are downscaled to if the device runs out of memory
+    pub max_embed_edge: u32,
+    /// Substring specifying images
+    pub image_substring: Option<String>,
+    /// Substring specifying bounding boxes
+    pub box_substring: Option<String>,
+    /// Glob pattern specifying images by their path relative to the image directory
+    pub image_glob: Option<String>,
+    /// Regex specifying images by their path relative to the image directory
+    pub image_regex: Option<String>,
+    /// Glob pattern specifying bounding boxes by their path relative to their directory
+    pub box_glob: Option<String>,
+    /// Regex specifying bounding boxes by their path relative to their directory
+    pub box_regex: Option<String>,
+    /// Bounding box csv column names as key=column pairs
+    pub box_columns: Option<String>,
+    /// Minimum unpadded bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum bounding box area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum bounding box area (pixels)
+    pub max_area: Option<f32>,
+    /// Skip images whose image-level metrics match this expression
+    pub skip_image_if: Option<String>,
+    /// Metadata table joined to every output row
+    pub metadata_table: Option<String>,
+    /// Name of the join key column in the metadata table
+    pub metadata_join: Option<String>,
+    /// Regex extracting the join key from image file names
+    pub well_from_filename: Option<String>,
+    /// Fail instead of writing nulls for images without matching metadata
+    pub strict_join: bool,
+    /// Compression of arrow/feather output tables (lz4, zstd, none)
+    pub ipc_compression: String,
+    /// Digits after the decimal point of floats in csv/tsv output tables
+    pub float_precision: Option<usize>,
+    /// Notation of floats in csv/tsv output tables (auto, fixed)
+    pub float_format: String,
+    /// Write .npz embeddings in the version 1 layout
+    pub legacy_npz: bool,
+    /// Write the objects of each box label to a separate output (class)
+    pub split_output_by: Option<String>,
+    /// Write a provenance sidecar next to each output table or array
+    pub provenance: bool,
+    /// Record sha256 hashes of input files in the provenance sidecar
+    pub hash_inputs: bool,
+    /// Directory caching object embeddings across runs
+    pub cache_dir: Option<String>,
+    /// Read embeddings from the cache without adding new entries
+    pub cache_readonly: bool,
+    /// Write a separate embeddings table for each image
+    pub per_image_output: bool,
+    /// Format of per-image output tables
+    pub per_image_format: String,
+    /// Skip images whose per-image output table already exists
+    pub resume: bool,
+    /// Remove the outputs of a previous run from the output location
+    pub overwrite: bool,
+    /// Number of decoded images queued ahead of the compute threads
+    pub prefetch: usize,
+    /// Number of threads decoding images if prefetching
+    pub io_threads: usize,
+    /// Number of compute threads on the cpu, defaulting to all available threads
+    pub threads: Option<usize>,
+    /// Report the pairs, objects, and output size of the run without loading the model
+    pub dry_run: bool,
+}
+
+impl Default for NeuralBoxesConfig {
+    fn default() -> Self {
+        Self {
+            images: String::new(),
+            boxes: None,
+            device: "cpu".to_string(),
+            output: String::new(),
+            verbose: false,
+            tui: false,
+            log_format: "plain".to_string(),
+            drop_borders: false,
+            model: "dino_vit_small".to_string(),
+            channel_map: "auto".to_string(),
+            pad: 1,
+            max_embed_edge: 1024,
+            image_substring: None,
+            box_substring: None,
+            image_glob: None,
+            image_regex: None,
+            box_glob: None,
+            box_regex: None,
+            box_columns: None,
+            min_size: 1,
+            min_area: None,
+            max_area: None,
+            skip_image_if: None,
+            metadata_table: None,
+            metadata_join: None,
+            well_from_filename: None,
+            strict_join: false,
+            ipc_compression: "none".to_string(),
+            float_precision: None,
+            float_format: "auto".to_string(),
+            legacy_npz: false,
+            split_output_by: None,
+            provenance: false,
+            hash_inputs: false,
+            cache_dir: None,
+            cache_readonly: false,
+            per_image_output: false,
+            per_image_format: "csv".to_string(),
+            resume: false,
+            overwrite: false,
+            prefetch: 0,
+            io_threads: 2,
+            threads: None,
+            dry_run: false,
+        }
+    }
+}
+
+setters! {
+    NeuralBoxesConfig {
+        values {
+            verbose: bool,
+            tui: bool,
+            drop_borders: bool,
+            pad: u32,
+            max_embed_edge: u32,
+            min_size: u32,
+            strict_join: bool,
+            legacy_npz: bool,
+            provenance: bool,
+            hash_inputs: bool,
+            cache_readonly: bool,
+            per_image_output: bool,
+            resume: bool,
+            overwrite: bool,
+            prefetch: usize,
+            io_threads: usize,
+            dry_run: bool,
+        }
+        strings {
+            images,
+            device,
+            output,
+            log_format,
+            model,
+            channel_map,
+            ipc_compression,
+            float_format,
+            per_image_format,
+        }
+        optional {
+            min_area: f32,
+            max_area: f32,
+            float_precision: usize,
+            threads: usize,
+        }
+        optional_strings {
+            skip_image_if,
+            boxes,
+            image_substring,
+            box_substring,
+            image_glob,
+            image_regex,
+            box_glob,
+            box_regex,
+            box_columns,
+            metadata_table,
+            metadata_join,
+            well_from_filename,
+            split_output_by,
+            cache_dir,
+        }
+    }
+}
+
+impl NeuralBoxesConfig {
+    /// Initialize a config with command line defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Image directory
+    /// * `output` - Output directory or file
+    pub fn new(images: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            images: images.into(),
+            output: output.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Bounding boxes directory or table, defaulting to the image directory
+    pub fn boxes_path(&self) -> &str {
+        self.boxes.as_deref().unwrap_or(&self.images)
+    }
+
+    /// Input files and directories read by the run
+    pub fn inputs(&self) -> Vec<&str> {
+        [
+            Some(self.images.as_str()),
+            Some(self.boxes_path()),
+            self.metadata_table.as_deref(),
+            self.cache_dir.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Check that the config describes a valid run
+    ///
+    /// Only the config itself is checked. Model names are checked against
+    /// the available weights and input files are checked when the command is
+    /// run.
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        check_paths(&self.images, &self.output)?;
+        check_threads(self.threads)?;
+        check_log_format(&self.log_format)?;
+
+        if let Some(skip_image_if) = &self.skip_image_if {
+            Expression::parse(skip_image_if, &ImageSummary::METRICS)?;
+        }
+
+        // Bounding boxes are only read from local files
+        if [&self.images, self.boxes_path(), &self.output]
+            .into_iter()
+            .any(is_remote)
+        {
+            return Err(other("images, boxes, and output must be local paths"));
+        }
+
+        check_model(&self.model, &self.device, &self.channel_map)?;
+        check_embeddings(
+            &self.output,
+            self.per_image_output,
+            self.metadata_table.as_deref(),
+            self.cache_dir.is_some(),
+            self.cache_readonly,
+        )?;
+        check_outputs(
+            &self.ipc_compression,
+            self.metadata_table.as_deref(),
+            self.metadata_join.as_deref(),
+            self.provenance,
+            self.hash_inputs,
+        )?;
+        check_resume(self.per_image_output, self.resume, self.overwrite)?;
+        check_float_format(&self.float_format)?;
+        check_split(self.split_output_by.as_deref(), self.per_image_output)?;
+
+        if let Some(box_columns) = &self.box_columns {
+            BoxColumns::parse(box_columns)?;
+        }
+
+        check_inputs(
+            (
+                &self.images,
+                (&self.image_substring, &self.image_glob, &self.image_regex),
+            ),
+            (
+                "box",
+                self.boxes_path(),
+                (&self.box_substring, &self.box_glob, &self.box_regex),
+            ),
+            "images and boxes",
+            "image and bounding box",
+        )
+    }
+}
+
+/// Fail if the model, device, or channel map cannot be used to run a model
+fn check_model(model: &str, device: &str, channel_map: &str) -> Result<(), ThymeError> {
+    if !constant::SUPPORTED_MODELS.contains(&model) {
+        return Err(ThymeError::OtherError(suggest::not_found(
+            "Weights",
+            model,
+            constant::SUPPORTED_MODELS,
+        )));
+    }
+
+    if !SUPPORTED_DEVICES.contains(&device) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid device {}. Must be one of: {:?}",
+            device, SUPPORTED_DEVICES
+        )));
+    }
+
+    let channel_map = ChannelMap::parse(channel_map)?;
+
+    match channel_map.channels() {
+        Some(n) if n != 1 && n != 3 => Err(ThymeError::OtherError(format!(
+            "channel_map must produce 1 or 3 channels but {} produces {}",
+            channel_map, n
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Fail if embeddings cannot be written to the output as configured
+///
+/// Outputs without an extension are directories holding an `embeddings.npz`
+/// array, so joined metadata requires a table file or per-image tables.
+fn check_embeddings(
+    output: &str,
+    per_image_output: bool,
+    metadata_table: Option<&str>,
+    cache_dir: bool,
+    cache_readonly: bool,
+) -> Result<(), ThymeError> {
+    let extension = std::path::Path::new(output)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    if let Some(extension) = &extension {
+        if !constant::SUPPORTED_TABLE_FORMATS
+            .iter()
+            .chain(&constant::SUPPORTED_EMBEDDING_FORMATS)
+            .any(|format| format == extension)
+        {
+            return Err(ThymeError::OtherError(format!(
+                "Invalid file extension {}. Must be one of: {:?} or {:?}",
+                extension,
+                constant::SUPPORTED_TABLE_FORMATS,
+                constant::SUPPORTED_EMBEDDING_FORMATS
+            )));
+        }
+    }
+
+    let table_output = per_image_output
+        || extension
+            .as_deref()
+            .is_some_and(|extension| constant::SUPPORTED_TABLE_FORMATS.contains(&extension));
+
+    if metadata_table.is_some() && !table_output {
+        return Err(ThymeError::OtherError(format!(
+            "metadata_table requires a table output. Must be one of: {:?}",
+            constant::SUPPORTED_TABLE_FORMATS
+        )));
+    }
+
+    requires(
+        cache_readonly,
+        cache_dir,
+        "cache_readonly requires cache_dir",
+    )
+}
+
+/// Fail if objects are split into outputs that cannot be written
+fn check_split(split_output_by: Option<&str>, per_image_output: bool) -> Result<(), ThymeError> {
+    match split_output_by {
+        Some(_) if per_image_output => Err(other(
+            "split_output_by cannot be combined with per_image_output",
+        )),
+        Some(split) if split != "class" => Err(ThymeError::OtherError(format!(
+            "Invalid split_output_by {}. Only class is supported",
+            split
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_neural_mask_config_validate() {
+        let config = NeuralMaskConfig::new("images/", "output/");
+
+        assert_eq!(config.masks_path(), "images/");
+        assert_eq!((config.pad, config.max_embed_edge), (1, 1024));
+        assert_eq!(config.model, "dino_vit_small");
+
+        // Same-path images and masks require distinct substrings
+        assert!(config.validate().is_err());
+
+        let config = config.masks("masks/");
+        assert!(config.validate().is_ok());
+
+        assert!(config.clone().device("tpu").validate().is_err());
+
+        let err = config
+            .clone()
+            .model("dino_vit_smal")
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("Did you mean: dino_vit_small"));
+
+        assert!(config.clone().channel_map("0,1").validate().is_err());
+        assert!(config.clone().channel_map("1,2,0").validate().is_ok());
+        assert!(config.clone().output("output.json").validate().is_err());
+        assert!(config.clone().cache_readonly(true).validate().is_err());
+        assert!(config.clone().resume(true).validate().is_err());
+        assert!(config.clone().split_output_by("class").validate().is_err());
+
+        assert!(
+            config
+                .clone()
+                .metadata_table("platemap.csv")
+                .metadata_join("well")
+                .validate()
+                .is_err()
+        );
+
+        assert!(
+            config
+                .clone()
+                .output("embeddings.pq")
+                .metadata_table("platemap.csv")
+                .metadata_join("well")
+                .validate()
+                .is_ok()
+        );
+
+        assert!(
+            config
+                .clone()
+                .class_mask("classes/")
+                .split_output_by("label")
+                .validate()
+                .is_err()
+        );
+
+        assert!(
+            config
+                .clone()
+                .class_mask("classes/")
+                .split_output_by("class")
+                .validate()
+                .is_ok()
+        );
+
+        // Omitted fields are filled with defaults when deserialized
+        let json = r#"{"images": "images/", "masks": "masks/", "output": "out.npz", "pad": 3}"#;
+        let parsed: NeuralMaskConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.pad, 3);
+        assert_eq!(parsed.channel_map, "auto");
+        assert!(parsed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_neural_segment_configs_validate() {
+        let polygons = NeuralPolygonsConfig::new("images/", "output/").polygons("polygons/");
+        assert!(polygons.validate().is_ok());
+
+        assert!(
+            polygons
+                .clone()
+                .segment_coords("meters")
+                .validate()
+                .is_err()
+        );
+        assert!(polygons.clone().polygons("images/").validate().is_err());
+        assert!(
+            polygons
+                .clone()
+                .images("s3://bucket/images")
+                .provenance(true)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            polygons
+                .clone()
+                .images("s3://bucket/images")
+                .provenance(true)
+                .remote_cache("cache/")
+                .validate()
+                .is_ok()
+        );
+
+        let boxes = NeuralBoxesConfig::new("images/", "output/").boxes("boxes.csv");
+        assert!(boxes.validate().is_ok());
+
+        assert!(boxes.clone().threads(0).validate().is_err());
+        assert!(
+            boxes
+                .clone()
+                .skip_image_if("focus_score < 0.1")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            boxes
+                .clone()
+                .skip_image_if("sharpness < 0.1")
+                .validate()
+                .is_err()
+        );
+        assert!(boxes.clone().box_columns("xmin").validate().is_err());
+        assert!(
+            boxes
+                .clone()
+                .images("s3://bucket/images")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            boxes
+                .clone()
+                .split_output_by("class")
+                .per_image_output(true)
+                .validate()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,1002 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use serde::{Deserialize, Serialize};
+
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im::{BoxColumns, MaskFormat, SegmentCoords};
+use thyme_core::ut::path::is_remote;
+
+use crate::check::{
+    check_inputs, check_log_format, check_mode, check_paths, check_remote, check_substrings,
+    check_threads, other, requires,
+};
+
+/// Configuration of `thyme process mask`
+///
+/// Fields mirror the command line arguments of the same name and default to
+/// the command line defaults.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_config::ProcessMaskConfig;
+///
+/// let config = ProcessMaskConfig::new("images/", "output/")
+///     .masks("masks/")
+///     .mode("cfm")
+///     .output_container("hdf5");
+///
+/// assert_eq!(config.image_format, "png");
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessMaskConfig {
+    /// Image directory or s3:// or https:// url
+    pub images: String,
+    /// Mask directory or url, defaulting to the image directory
+    pub masks: Option<String>,
+    /// Output directory
+    pub output: String,
+    /// Log progress
+    pub verbose: bool,
+    /// Show a live status panel instead of the progress bar if stdout is a terminal
+    pub tui: bool,
+    /// Format of the status lines logged if stdout is not a terminal (plain, json)
+    pub log_format: String,
+    /// Exclude objects touching the edge of the image
+    pub drop_borders: bool,
+    /// Object data to extract (any of c, f, b, m, p, x)
+    pub mode: String,
+    /// Padding around extracted objects (pixels)
+    pub pad: u32,
+    /// Substring specifying images
+    pub image_substring: Option<String>,
+    /// Substring specifying masks
+    pub mask_substring: Option<String>,
+    /// Layout of mask files (auto, cellpose-seg)
+    pub mask_format: String,
+    /// Glob pattern specifying images by their path relative to the image directory
+    pub image_glob: Option<String>,
+    /// Regex specifying images by their path relative to the image directory
+    pub image_regex: Option<String>,
+    /// Glob pattern specifying masks by their path relative to their directory
+    pub mask_glob: Option<String>,
+    /// Regex specifying masks by their path relative to their directory
+    pub mask_regex: Option<String>,
+    /// Minimum unpadded bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum mask area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum mask area (pixels)
+    pub max_area: Option<f32>,
+    /// Class mask directory with the semantic class id of each pixel
+    pub class_mask: Option<String>,
+    /// Substring specifying class masks
+    pub class_substring: Option<String>,
+    /// Number of objects sampled from each image after filtering
+    pub sample_per_image: Option<usize>,
+    /// Descriptor stratifying sampled objects (area, class, or a form descriptor)
+    pub stratify_by: Option<String>,
+    /// Number of quantile strata of a numeric stratify_by descriptor
+    pub strata: Option<usize>,
+    /// Compute quantile strata from the objects of every image
+    pub global_strata: bool,
+    /// Random seed for sampling objects
+    pub seed: u64,
+    /// Format of extracted object images (e.g. png, jpeg, npy)
+    pub image_format: String,
+    /// Format of extracted polygons and bounding boxes (json)
+    pub array_format: String,
+    /// Container of extracted object images (files, hdf5, zarr)
+    pub output_container: String,
+    /// Embed the source of each object as json in its png crops
+    pub embed_metadata: bool,
+    /// Remove the outputs of a previous run from the output directory
+    pub overwrite: bool,
+    /// Number of decoded images queued ahead of the compute threads
+    pub prefetch: usize,
+    /// Number of threads decoding images if prefetching
+    pub io_threads: usize,
+    /// Directory caching objects fetched from remote inputs
+    pub remote_cache: Option<String>,
+    /// Number of compute threads, defaulting to all available threads
+    pub threads: Option<usize>,
+    /// Report the pairs, objects, and output size of the run without extracting objects
+    pub dry_run: bool,
+}
+
+impl Default for ProcessMaskConfig {
+    fn default() -> Self {
+        Self {
+            images: String::new(),
+            masks: None,
+            output: String::new(),
+            verbose: false,
+            tui: false,
+            log_format: "plain".to_string(),
+            drop_borders: false,
+            mode: "cm".to_string(),
+            pad: 1,
+            image_substring: None,
+            mask_substring: None,
+            mask_format: "auto".to_string(),
+            image_glob: None,
+            image_regex: None,
+            mask_glob: None,
+            mask_regex: None,
+            min_size: 1,
+            min_area: None,
+            max_area: None,
+            class_mask: None,
+            class_substring: None,
+            sample_per_image: None,
+            stratify_by: None,
+            strata: None,
+            global_strata: false,
+            seed: 0,
+            image_format: "png".to_string(),
+            array_format: "json".to_string(),
+            output_container: "files".to_string(),
+            embed_metadata: false,
+            overwrite: false,
+            prefetch: 0,
+            io_threads: 2,
+            remote_cache: None,
+            threads: None,
+            dry_run: false,
+        }
+    }
+}
+
+setters! {
+    ProcessMaskConfig {
+        values {
+            verbose: bool,
+            tui: bool,
+            drop_borders: bool,
+            pad: u32,
+            min_size: u32,
+            global_strata: bool,
+            seed: u64,
+            embed_metadata: bool,
+            overwrite: bool,
+            prefetch: usize,
+            io_threads: usize,
+            dry_run: bool,
+        }
+        strings {
+            images,
+            output,
+            log_format,
+            mode,
+            mask_format,
+            image_format,
+            array_format,
+            output_container,
+        }
+        optional {
+            min_area: f32,
+            max_area: f32,
+            sample_per_image: usize,
+            strata: usize,
+            threads: usize,
+        }
+        optional_strings {
+            masks,
+            image_substring,
+            mask_substring,
+            image_glob,
+            image_regex,
+            mask_glob,
+            mask_regex,
+            class_mask,
+            class_substring,
+            stratify_by,
+            remote_cache,
+        }
+    }
+}
+
+impl ProcessMaskConfig {
+    /// Initialize a config with command line defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Image directory
+    /// * `output` - Output directory
+    pub fn new(images: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            images: images.into(),
+            output: output.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Mask directory, defaulting to the image directory
+    pub fn masks_path(&self) -> &str {
+        self.masks.as_deref().unwrap_or(&self.images)
+    }
+
+    /// Input files and directories read by the run
+    pub fn inputs(&self) -> Vec<&str> {
+        [
+            Some(self.images.as_str()),
+            Some(self.masks_path()),
+            self.class_mask.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Check that the config describes a valid run
+    ///
+    /// Only the config itself is checked. Input files are checked when the
+    /// command is run.
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        check_paths(&self.images, &self.output)?;
+        check_threads(self.threads)?;
+        check_log_format(&self.log_format)?;
+        check_mode(&self.mode, &['c', 'm', 'b', 'f', 'p', 'x'])?;
+
+        check_remote(
+            &[&self.images, self.masks_path()],
+            &self.output,
+            self.remote_cache.as_deref(),
+            false,
+        )?;
+
+        check_crops(
+            &self.image_format,
+            &self.array_format,
+            &self.output_container,
+            self.embed_metadata,
+        )?;
+
+        check_sample(
+            self.sample_per_image,
+            self.stratify_by.as_deref(),
+            self.strata,
+            self.global_strata,
+        )?;
+
+        MaskFormat::parse(&self.mask_format)?;
+
+        requires(
+            self.class_substring.is_some(),
+            self.class_mask.is_some(),
+            "class_substring requires class_mask",
+        )?;
+
+        requires(
+            self.stratify_by.as_deref() == Some("class"),
+            self.class_mask.is_some(),
+            "stratify_by class requires class_mask",
+        )?;
+
+        let masks = self.masks_path();
+        let mask_selection = (&self.mask_substring, &self.mask_glob, &self.mask_regex);
+
+        check_inputs(
+            (
+                &self.images,
+                (&self.image_substring, &self.image_glob, &self.image_regex),
+            ),
+            ("mask", masks, mask_selection),
+            "images and masks",
+            "image and mask",
+        )?;
+
+        if let Some(classes) = &self.class_mask {
+            check_substrings(
+                (masks, mask_selection),
+                (classes, (&self.class_substring, &None, &None)),
+                "masks and class masks",
+                "mask and class",
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Configuration of `thyme process polygons`
+///
+/// Fields mirror the command line arguments of the same name and default to
+/// the command line defaults.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_config::ProcessPolygonsConfig;
+///
+/// let config = ProcessPolygonsConfig::new("images/", "output/")
+///     .polygons("polygons/")
+///     .segment_coords("normalized");
+///
+/// assert_eq!(config.mode, "cm");
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessPolygonsConfig {
+    /// Image directory or s3:// or https:// url
+    pub images: String,
+    /// Polygons directory or url, defaulting to the image directory
+    pub polygons: Option<String>,
+    /// Output directory
+    pub output: String,
+    /// Log progress
+    pub verbose: bool,
+    /// Show a live status panel instead of the progress bar if stdout is a terminal
+    pub tui: bool,
+    /// Format of the status lines logged if stdout is not a terminal (plain, json)
+    pub log_format: String,
+    /// Exclude objects touching the edge of the image
+    pub drop_borders: bool,
+    /// Object data to extract (any of c, f, b, m, p, x)
+    pub mode: String,
+    /// Padding around extracted objects (pixels)
+    pub pad: u32,
+    /// Substring specifying images
+    pub image_substring: Option<String>,
+    /// Substring specifying polygons
+    pub polygon_substring: Option<String>,
+    /// Glob pattern specifying images by their path relative to the image directory
+    pub image_glob: Option<String>,
+    /// Regex specifying images by their path relative to the image directory
+    pub image_regex: Option<String>,
+    /// Glob pattern specifying polygons by their path relative to their directory
+    pub polygon_glob: Option<String>,
+    /// Regex specifying polygons by their path relative to their directory
+    pub polygon_regex: Option<String>,
+    /// Coordinate system of the polygon vertices (pixels, normalized)
+    pub segment_coords: String,
+    /// Minimum unpadded bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum polygon area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum polygon area (pixels)
+    pub max_area: Option<f32>,
+    /// Exclude self-intersecting polygons that could not be repaired
+    pub skip_invalid_polygons: bool,
+    /// Number of objects sampled from each image after filtering
+    pub sample_per_image: Option<usize>,
+    /// Descriptor stratifying sampled objects (area or a form descriptor)
+    pub stratify_by: Option<String>,
+    /// Number of quantile strata of a numeric stratify_by descriptor
+    pub strata: Option<usize>,
+    /// Compute quantile strata from the objects of every image
+    pub global_strata: bool,
+    /// Random seed for sampling objects
+    pub seed: u64,
+    /// Format of extracted object images (e.g. png, jpeg, npy)
+    pub image_format: String,
+    /// Format of extracted polygons and bounding boxes (json)
+    pub array_format: String,
+    /// Container of extracted object images (files, hdf5, zarr)
+    pub output_container: String,
+    /// Embed the source of each object as json in its png crops
+    pub embed_metadata: bool,
+    /// Remove the outputs of a previous run from the output directory
+    pub overwrite: bool,
+    /// Number of decoded images queued ahead of the compute threads
+    pub prefetch: usize,
+    /// Number of threads decoding images if prefetching
+    pub io_threads: usize,
+    /// Directory caching objects fetched from remote inputs
+    pub remote_cache: Option<String>,
+    /// Number of compute threads, defaulting to all available threads
+    pub threads: Option<usize>,
+    /// Report the pairs, objects, and output size of the run without extracting objects
+    pub dry_run: bool,
+}
+
+impl Default for ProcessPolygonsConfig {
+    fn default() -> Self {
+        Self {
+            images: String::new(),
+            polygons: None,
+            output: String::new(),
+            verbose: false,
+            tui: false,
+            log_format: "plain".to_string(),
+            drop_borders: false,
+            mode: "cm".to_string(),
+            pad: 1,
+            image_substring: None,
+            polygon_substring: None,
+            image_glob: None,
+            image_regex: None,
+            polygon_glob: None,
+            polygon_regex: None,
+            segment_coords: "pixels".to_string(),
+            min_size: 1,
+            min_area: None,
+            max_area: None,
+            skip_invalid_polygons: false,
+            sample_per_image: None,
+            stratify_by: None,
+            strata: None,
+            global_strata: false,
+            seed: 0,
+            image_format: "png".to_string(),
+            array_format: "json".to_string(),
+            output_container: "files".to_string(),
+            embed_metadata: false,
+            overwrite: false,
+            prefetch: 0,
+            io_threads: 2,
+            remote_cache: None,
+            threads: None,
+            dry_run: false,
+        }
+    }
+}
+
+setters! {
+    ProcessPolygonsConfig {
+        values {
+            verbose: bool,
+            tui: bool,
+            drop_borders: bool,
+            pad: u32,
+            min_size: u32,
+            skip_invalid_polygons: bool,
+            global_strata: bool,
+            seed: u64,
+            embed_metadata: bool,
+            overwrite: bool,
+            prefetch: usize,
+            io_threads: usize,
+            dry_run: bool,
+        }
+        strings {
+            images,
+            output,
+            log_format,
+            mode,
+            segment_coords,
+            image_format,
+            array_format,
+            output_container,
+        }
+        optional {
+            min_area: f32,
+            max_area: f32,
+            sample_per_image: usize,
+            strata: usize,
+            threads: usize,
+        }
+        optional_strings {
+            polygons,
+            image_substring,
+            polygon_substring,
+            image_glob,
+            image_regex,
+            polygon_glob,
+            polygon_regex,
+            stratify_by,
+            remote_cache,
+        }
+    }
+}
+
+impl ProcessPolygonsConfig {
+    /// Initialize a config with command line defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Image directory
+    /// * `output` - Output directory
+    pub fn new(images: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            images: images.into(),
+            output: output.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Polygons directory, defaulting to the image directory
+    pub fn polygons_path(&self) -> &str {
+        self.polygons.as_deref().unwrap_or(&self.images)
+    }
+
+    /// Input files and directories read by the run
+    pub fn inputs(&self) -> Vec<&str> {
+        vec![self.images.as_str(), self.polygons_path()]
+    }
+
+    /// Check that the config describes a valid run
+    ///
+    /// Only the config itself is checked. Input files are checked when the
+    /// command is run.
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        check_paths(&self.images, &self.output)?;
+        check_threads(self.threads)?;
+        check_log_format(&self.log_format)?;
+        check_mode(&self.mode, &['c', 'm', 'b', 'f', 'p', 'x'])?;
+
+        check_remote(
+            &[&self.images, self.polygons_path()],
+            &self.output,
+            self.remote_cache.as_deref(),
+            false,
+        )?;
+
+        check_crops(
+            &self.image_format,
+            &self.array_format,
+            &self.output_container,
+            self.embed_metadata,
+        )?;
+
+        check_sample(
+            self.sample_per_image,
+            self.stratify_by.as_deref(),
+            self.strata,
+            self.global_strata,
+        )?;
+
+        SegmentCoords::parse(&self.segment_coords)?;
+
+        if self.stratify_by.as_deref() == Some("class") {
+            return Err(other(
+                "stratify_by class requires class labels, which polygons do not have",
+            ));
+        }
+
+        check_inputs(
+            (
+                &self.images,
+                (&self.image_substring, &self.image_glob, &self.image_regex),
+            ),
+            (
+                "polygon",
+                self.polygons_path(),
+                (
+                    &self.polygon_substring,
+                    &self.polygon_glob,
+                    &self.polygon_regex,
+                ),
+            ),
+            "images and polygons",
+            "image and polygon",
+        )
+    }
+}
+
+/// Configuration of `thyme process boxes`
+///
+/// Fields mirror the command line arguments of the same name and default to
+/// the command line defaults.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_config::ProcessBoxesConfig;
+///
+/// let config = ProcessBoxesConfig::new("images/", "output/")
+///     .boxes("boxes.csv")
+///     .sample_per_image(10);
+///
+/// assert_eq!(config.mode, "cx");
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessBoxesConfig {
+    /// Image directory
+    pub images: String,
+    /// Bounding boxes directory or csv table, defaulting to the image directory
+    pub boxes: Option<String>,
+    /// Output directory
+    pub output: String,
+    /// Log progress
+    pub verbose: bool,
+    /// Show a live status panel instead of the progress bar if stdout is a terminal
+    pub tui: bool,
+    /// Format of the status lines logged if stdout is not a terminal (plain, json)
+    pub log_format: String,
+    /// Exclude objects touching the edge of the image
+    pub drop_borders: bool,
+    /// Object data to extract (any of c, x)
+    pub mode: String,
+    /// Padding around extracted objects (pixels)
+    pub pad: u32,
+    /// Substring specifying images
+    pub image_substring: Option<String>,
+    /// Substring specifying bounding boxes
+    pub box_substring: Option<String>,
+    /// Glob pattern specifying images by their path relative to the image directory
+    pub image_glob: Option<String>,
+    /// Regex specifying images by their path relative to the image directory
+    pub image_regex: Option<String>,
+    /// Glob pattern specifying bounding boxes by their path relative to their directory
+    pub box_glob: Option<String>,
+    /// Regex specifying bounding boxes by their path relative to their directory
+    pub box_regex: Option<String>,
+    /// Bounding box csv column names as key=column pairs
+    pub box_columns: Option<String>,
+    /// Minimum unpadded bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum bounding box area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum bounding box area (pixels)
+    pub max_area: Option<f32>,
+    /// Number of objects sampled from each image after filtering
+    pub sample_per_image: Option<usize>,
+    /// Descriptor stratifying sampled objects (area, class)
+    pub stratify_by: Option<String>,
+    /// Number of quantile strata of a numeric stratify_by descriptor
+    pub strata: Option<usize>,
+    /// Compute quantile strata from the objects of every image
+    pub global_strata: bool,
+    /// Random seed for sampling objects
+    pub seed: u64,
+    /// Format of extracted object images (e.g. png, jpeg, npy)
+    pub image_format: String,
+    /// Format of extracted bounding boxes (json)
+    pub array_format: String,
+    /// Container of extracted object images (files, hdf5, zarr)
+    pub output_container: String,
+    /// Embed the source of each object as json in its png crops
+    pub embed_metadata: bool,
+    /// Remove the outputs of a previous run from the output directory
+    pub overwrite: bool,
+    /// Number of decoded images queued ahead of the compute threads
+    pub prefetch: usize,
+    /// Number of threads decoding images if prefetching
+    pub io_threads: usize,
+    /// Number of compute threads, defaulting to all available threads
+    pub threads: Option<usize>,
+    /// Report the pairs, objects, and output size of the run without extracting objects
+    pub dry_run: bool,
+}
+
+impl Default for ProcessBoxesConfig {
+    fn default() -> Self {
+        Self {
+            images: String::new(),
+            boxes: None,
+            output: String::new(),
+            verbose: false,
+            tui: false,
+            log_format: "plain".to_string(),
+            drop_borders: false,
+            mode: "cx".to_string(),
+            pad: 1,
+            image_substring: None,
+            box_substring: None,
+            image_glob: None,
+            image_regex: None,
+            box_glob: None,
+            box_regex: None,
+            box_columns: None,
+            min_size: 1,
+            min_area: None,
+            max_area: None,
+            sample_per_image: None,
+            stratify_by: None,
+            strata: None,
+            global_strata: false,
+            seed: 0,
+            image_format: "png".to_string(),
+            array_format: "json".to_string(),
+            output_container: "files".to_string(),
+            embed_metadata: false,
+            overwrite: false,
+            prefetch: 0,
+            io_threads: 2,
+            threads: None,
+            dry_run: false,
+        }
+    }
+}
+
+setters! {
+    ProcessBoxesConfig {
+        values {
+            verbose: bool,
+            tui: bool,
+            drop_borders: bool,
+            pad: u32,
+            min_size: u32,
+            global_strata: bool,
+            seed: u64,
+            embed_metadata: bool,
+            overwrite: bool,
+            prefetch: usize,
+            io_threads: usize,
+            dry_run: bool,
+        }
+        strings {
+            images,
+            output,
+            log_format,
+            mode,
+            image_format,
+            array_format,
+            output_container,
+        }
+        optional {
+            min_area: f32,
+            max_area: f32,
+            sample_per_image: usize,
+            strata: usize,
+            threads: usize,
+        }
+        optional_strings {
+            boxes,
+            image_substring,
+            box_substring,
+            image_glob,
+            image_regex,
+            box_glob,
+            box_regex,
+            box_columns,
+            stratify_by,
+        }
+    }
+}
+
+impl ProcessBoxesConfig {
+    /// Initialize a config with command line defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Image directory
+    /// * `output` - Output directory
+    pub fn new(images: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            images: images.into(),
+            output: output.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Bounding boxes directory or table, defaulting to the image directory
+    pub fn boxes_path(&self) -> &str {
+        self.boxes.as_deref().unwrap_or(&self.images)
+    }
+
+    /// Input files and directories read by the run
+    pub fn inputs(&self) -> Vec<&str> {
+        vec![self.images.as_str(), self.boxes_path()]
+    }
+
+    /// Check that the config describes a valid run
+    ///
+    /// Only the config itself is checked. Input files are checked when the
+    /// command is run.
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        check_paths(&self.images, &self.output)?;
+        check_threads(self.threads)?;
+        check_log_format(&self.log_format)?;
+        check_mode(&self.mode, &['c', 'x'])?;
+        // Bounding boxes are only read from local files
+        if [&self.images, self.boxes_path(), &self.output]
+            .into_iter()
+            .any(is_remote)
+        {
+            return Err(other("images, boxes, and output must be local paths"));
+        }
+
+        check_crops(
+            &self.image_format,
+            &self.array_format,
+            &self.output_container,
+            self.embed_metadata,
+        )?;
+
+        check_sample(
+            self.sample_per_image,
+            self.stratify_by.as_deref(),
+            self.strata,
+            self.global_strata,
+        )?;
+
+        if let Some(box_columns) = &self.box_columns {
+            BoxColumns::parse(box_columns)?;
+        }
+
+        check_inputs(
+            (
+                &self.images,
+                (&self.image_substring, &self.image_glob, &self.image_regex),
+            ),
+            (
+                "box",
+                self.boxes_path(),
+                (&self.box_substring, &self.box_glob, &self.box_regex),
+            ),
+            "images and boxes",
+            "image and bounding box",
+        )
+    }
+}
+
+/// Fail if extracted objects cannot be written in the requested formats
+fn check_crops(
+    image_format: &str,
+    array_format: &str,
+    output_container: &str,
+    embed_metadata: bool,
+) -> Result<(), ThymeError> {
+    if !constant::SUPPORTED_IMAGE_FORMATS.contains(&image_format) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid image_format {}. Must be one of: {:?}",
+            image_format,
+            constant::SUPPORTED_IMAGE_FORMATS
+        )));
+    }
+
+    if !constant::SUPPORTED_ARRAY_FORMATS.contains(&array_format) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid array_format {}. Must be one of: {:?}",
+            array_format,
+            constant::SUPPORTED_ARRAY_FORMATS
+        )));
+    }
+
+    if !constant::SUPPORTED_OUTPUT_CONTAINERS.contains(&output_container) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid output_container {}. Must be one of: {:?}",
+            output_container,
+            constant::SUPPORTED_OUTPUT_CONTAINERS
+        )));
+    }
+
+    // Metadata is embedded in a text chunk of png files
+    requires(
+        embed_metadata,
+        image_format == "png" && output_container == "files",
+        "embed_metadata requires png image_format and files output_container",
+    )
+}
+
+/// Fail if sampling options are set without the options they refine
+///
+/// Stratifying descriptors are checked when the command is run.
+fn check_sample(
+    sample_per_image: Option<usize>,
+    stratify_by: Option<&str>,
+    strata: Option<usize>,
+    global_strata: bool,
+) -> Result<(), ThymeError> {
+    requires(
+        stratify_by.is_some(),
+        sample_per_image.is_some(),
+        "stratify_by requires sample_per_image",
+    )?;
+
+    requires(
+        strata.is_some(),
+        stratify_by.is_some(),
+        "strata requires stratify_by",
+    )?;
+
+    requires(
+        global_strata,
+        stratify_by.is_some(),
+        "global_strata requires stratify_by",
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_process_mask_config_validate() {
+        let config = ProcessMaskConfig::new("images/", "output/");
+
+        assert_eq!(config.masks_path(), "images/");
+        assert_eq!((config.pad, config.min_size, config.seed), (1, 1, 0));
+        assert_eq!(config.output_container, "files");
+
+        // Same-path images and masks require distinct substrings
+        assert!(config.validate().is_err());
+
+        let config = config.image_substring("_image").mask_substring("_mask");
+        assert!(config.validate().is_ok());
+
+        assert!(config.clone().mode("cs").validate().is_err());
+        assert!(config.clone().image_format("gif").validate().is_err());
+        assert!(config.clone().output_container("n5").validate().is_err());
+        assert!(config.clone().output_container("zarr").validate().is_ok());
+        assert!(config.clone().mask_format("cellpose").validate().is_err());
+        assert!(config.clone().stratify_by("area").validate().is_err());
+        assert!(config.clone().global_strata(true).validate().is_err());
+        assert!(config.clone().class_substring("_class").validate().is_err());
+        assert!(config.clone().output("s3://bucket/out").validate().is_err());
+        assert!(config.clone().log_format("xml").validate().is_err());
+        assert!(config.clone().tui(true).log_format("json").validate().is_ok());
+
+        assert!(
+            config
+                .clone()
+                .embed_metadata(true)
+                .output_container("hdf5")
+                .validate()
+                .is_err()
+        );
+
+        assert!(
+            config
+                .clone()
+                .sample_per_image(4)
+                .stratify_by("class")
+                .validate()
+                .is_err()
+        );
+
+        assert!(
+            config
+                .clone()
+                .sample_per_image(4)
+                .stratify_by("class")
+                .class_mask("classes/")
+                .validate()
+                .is_ok()
+        );
+
+        // Omitted fields are filled with defaults when deserialized
+        let json = r#"{"images": "images/", "masks": "masks/", "output": "out/", "pad": 3}"#;
+        let parsed: ProcessMaskConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.pad, 3);
+        assert_eq!(parsed.mode, "cm");
+        assert!(parsed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_process_segment_configs_validate() {
+        let polygons = ProcessPolygonsConfig::new("images/", "output/").polygons("polygons/");
+        assert!(polygons.validate().is_ok());
+
+        assert!(
+            polygons
+                .clone()
+                .segment_coords("meters")
+                .validate()
+                .is_err()
+        );
+        assert!(polygons.clone().polygons("images/").validate().is_err());
+        assert!(
+            polygons
+                .clone()
+                .sample_per_image(2)
+                .stratify_by("class")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            polygons
+                .clone()
+                .sample_per_image(2)
+                .stratify_by("form_eccentricity")
+                .strata(3)
+                .validate()
+                .is_ok()
+        );
+
+        let boxes = ProcessBoxesConfig::new("images/", "output/").boxes("boxes.csv");
+        assert!(boxes.validate().is_ok());
+        assert_eq!(boxes.mode, "cx");
+
+        assert!(boxes.clone().mode("cm").validate().is_err());
+        assert!(boxes.clone().threads(0).validate().is_err());
+        assert!(boxes.clone().box_columns("xmin").validate().is_err());
+        assert!(
+            boxes
+                .clone()
+                .images("s3://bucket/images")
+                .validate()
+                .is_err()
+        );
+    }
+}
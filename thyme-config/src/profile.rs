@@ -0,0 +1,1765 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use serde::{Deserialize, Serialize};
+
+use thyme_core::catalog::{ColumnStyle, parse_channel_names};
+use thyme_core::constant;
+use thyme_core::error::ThymeError;
+use thyme_core::im::{
+    BackgroundStyle, ChannelPairs, ContourSmoothing, ImageSummary, MaskFormat, ZStackMode,
+};
+use thyme_core::mp::reduce::MeasureDevice;
+use thyme_core::ut::expr::Expression;
+
+use crate::check::{
+    check_borders, check_float_format, check_inputs, check_log_format, check_mode, check_outputs,
+    check_paths, check_remote, check_resume, check_substrings, check_threads, other, requires,
+};
+
+/// Configuration of `thyme profile mask`
+///
+/// Fields mirror the command line arguments of the same name and default to
+/// the command line defaults.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_config::ProfileMaskConfig;
+///
+/// let config = ProfileMaskConfig::new("images/", "output/")
+///     .image_substring("_image")
+///     .mask_substring("_mask")
+///     .membrane_width(2);
+///
+/// assert_eq!(config.mode, "cm");
+/// assert_eq!(config.membrane_width, Some(2));
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileMaskConfig {
+    /// Image directory
+    pub images: String,
+    /// Mask directory, defaulting to the image directory
+    pub masks: Option<String>,
+    /// Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather)
+    pub output: String,
+    /// Log progress and per-image summaries
+    pub verbose: bool,
+    /// Show a live status panel instead of the progress bar if stdout is a terminal
+    pub tui: bool,
+    /// Format of the status lines logged if stdout is not a terminal (plain, json)
+    pub log_format: String,
+    /// Exclude objects touching the edge of the image
+    pub drop_borders: bool,
+    /// Handling of objects touching the edge of the image (keep, drop, flag)
+    pub border_policy: String,
+    /// Handling of objects with non-finite descriptors (keep, zero, drop-object)
+    pub nonfinite_policy: String,
+    /// Descriptor families to compute (any of c, f, b, m, p, x, s)
+    pub mode: String,
+    /// Pixels measured as the background of mode b (bbox, ring:<px>)
+    pub background_style: String,
+    /// Padding around extracted objects (pixels)
+    pub pad: u32,
+    /// Substring specifying images
+    pub image_substring: Option<String>,
+    /// Substring specifying masks
+    pub mask_substring: Option<String>,
+    /// Glob pattern specifying images by their path relative to the image directory
+    pub image_glob: Option<String>,
+    /// Regex specifying images by their path relative to the image directory
+    pub image_regex: Option<String>,
+    /// Glob pattern specifying masks by their path relative to their directory
+    pub mask_glob: Option<String>,
+    /// Regex specifying masks by their path relative to their directory
+    pub mask_regex: Option<String>,
+    /// Affine transforms of segments, which are not supported for masks
+    pub segment_transform: Option<String>,
+    /// Minimum unpadded bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum mask area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum mask area (pixels)
+    pub max_area: Option<f32>,
+    /// Maximum number of outline vertices of an object before it is resampled or skipped
+    pub max_points_per_object: Option<usize>,
+    /// Handling of objects with more than max_points_per_object vertices (resample, skip)
+    pub oversized_policy: String,
+    /// Time after which the remaining descriptor families of an object are abandoned (seconds)
+    pub max_seconds_per_object: Option<f32>,
+    /// Skip images whose image-level metrics match this expression
+    pub skip_image_if: Option<String>,
+    /// Fail images with masks that may be bit-depth truncated
+    pub strict_masks: bool,
+    /// Probability above which pixels of float probability maps are labeled
+    pub prob_threshold: Option<f32>,
+    /// Minimum distance between watershed seeds in probability maps (pixels)
+    pub prob_min_distance: Option<u32>,
+    /// Handling of (Z, H, W) mask stacks (error, max-project, per-slice)
+    pub zstack_mode: String,
+    /// Layout of mask files (auto, cellpose-seg)
+    pub mask_format: String,
+    /// Width of the boundary band of membrane descriptors (pixels)
+    pub membrane_width: Option<u32>,
+    /// Secondary mask directory with objects contained in the primary masks
+    pub secondary_masks: Option<String>,
+    /// Substring specifying secondary masks
+    pub secondary_substring: Option<String>,
+    /// Class mask directory with the semantic class id of each pixel
+    pub class_mask: Option<String>,
+    /// Substring specifying class masks
+    pub class_substring: Option<String>,
+    /// Compute intensity-weighted centroid displacements
+    pub centroid_displacement: bool,
+    /// Include percentiles, skewness, and kurtosis in intensity descriptors
+    pub extended_intensity: bool,
+    /// Positive subpixels of a channel above which extended intensity descriptors are computed without sorting
+    pub approx_percentiles_over: Option<usize>,
+    /// Channels of each descriptor family (e.g. `intensity:0-5;texture:0,1`)
+    pub features_channels: Option<String>,
+    /// Channel pairs of intensity ratio descriptors (e.g. `all` or `0:1,2:1`)
+    pub channel_pairs: Option<String>,
+    /// Stride of texture co-occurrence anchors
+    pub texture_stride: usize,
+    /// Object size above which the texture stride is escalated (pixels)
+    pub texture_auto_stride: Option<usize>,
+    /// Device intensity sums and moments of large objects are reduced on (cpu, cuda)
+    pub measure_device: String,
+    /// Smoothing of outlines before polygon descriptors (gaussian:<sigma>, chaikin:<iterations>)
+    pub smooth_contours: Option<String>,
+    /// Downsampling factors of multi-scale texture descriptors (e.g. `1,2,4`)
+    pub texture_scales: Option<String>,
+    /// Directory to write object thumbnails to
+    pub thumbnails: Option<String>,
+    /// Width and height of object thumbnails (pixels)
+    pub thumbnail_size: u32,
+    /// Factor images and segments are downsampled by in a preview run (e.g. 0.25)
+    pub preview: Option<f32>,
+    /// Rescale lengths and areas of a preview run to original pixel units
+    pub preview_rescale: bool,
+    /// Metadata table joined to every output row
+    pub metadata_table: Option<String>,
+    /// Name of the join key column in the metadata table
+    pub metadata_join: Option<String>,
+    /// Regex extracting the join key from image file names
+    pub well_from_filename: Option<String>,
+    /// Fail instead of writing nulls for images without matching metadata
+    pub strict_join: bool,
+    /// Compression of arrow/feather output tables (lz4, zstd, none)
+    pub ipc_compression: String,
+    /// Digits after the decimal point of floats in csv/tsv output tables
+    pub float_precision: Option<usize>,
+    /// Notation of floats in csv/tsv output tables (auto, fixed)
+    pub float_format: String,
+    /// Naming convention of descriptor columns (thyme, cellprofiler)
+    pub column_style: String,
+    /// Comma-separated names of the image channels used by the cellprofiler column style (e.g. `DNA,Actin`)
+    pub channel_names: Option<String>,
+    /// Append the polygon of each object to parquet output tables
+    pub include_geometry: bool,
+    /// Encoding of the polygon column (list, wkb)
+    pub geometry_format: String,
+    /// Maximum number of vertices of each polygon in the polygon column
+    pub geometry_max_points: usize,
+    /// Write a provenance sidecar next to each output table
+    pub provenance: bool,
+    /// Record sha256 hashes of input files in the provenance sidecar
+    pub hash_inputs: bool,
+    /// Write a separate descriptors table for each image
+    pub per_image_output: bool,
+    /// Format of per-image output tables
+    pub per_image_format: String,
+    /// Skip images whose per-image output table already exists
+    pub resume: bool,
+    /// Remove the outputs of a previous run from the output location
+    pub overwrite: bool,
+    /// Report the pairs, objects, and output size of the run without profiling
+    pub dry_run: bool,
+    /// Number of decoded images queued ahead of the compute threads
+    pub prefetch: usize,
+    /// Number of threads decoding images if prefetching
+    pub io_threads: usize,
+    /// Directory caching objects fetched from remote inputs
+    pub remote_cache: Option<String>,
+    /// Number of compute threads, defaulting to all available threads
+    pub threads: Option<usize>,
+}
+
+impl Default for ProfileMaskConfig {
+    fn default() -> Self {
+        Self {
+            images: String::new(),
+            masks: None,
+            output: String::new(),
+            verbose: false,
+            tui: false,
+            log_format: "plain".to_string(),
+            drop_borders: false,
+            border_policy: "keep".to_string(),
+            nonfinite_policy: "keep".to_string(),
+            mode: "cm".to_string(),
+            background_style: "bbox".to_string(),
+            pad: 1,
+            image_substring: None,
+            mask_substring: None,
+            image_glob: None,
+            image_regex: None,
+            mask_glob: None,
+            mask_regex: None,
+            segment_transform: None,
+            min_size: 1,
+            min_area: None,
+            max_area: None,
+            max_points_per_object: None,
+            oversized_policy: "resample".to_string(),
+            max_seconds_per_object: None,
+            skip_image_if: None,
+            strict_masks: false,
+            prob_threshold: None,
+            prob_min_distance: None,
+            zstack_mode: "error".to_string(),
+            mask_format: "auto".to_string(),
+            membrane_width: None,
+            secondary_masks: None,
+            secondary_substring: None,
+            class_mask: None,
+            class_substring: None,
+            centroid_displacement: false,
+            extended_intensity: false,
+            approx_percentiles_over: None,
+            features_channels: None,
+            channel_pairs: None,
+            texture_stride: 1,
+            texture_auto_stride: None,
+            measure_device: "cpu".to_string(),
+            smooth_contours: None,
+            texture_scales: None,
+            thumbnails: None,
+            thumbnail_size: 64,
+            preview: None,
+            preview_rescale: false,
+            metadata_table: None,
+            metadata_join: None,
+            well_from_filename: None,
+            strict_join: false,
+            ipc_compression: "none".to_string(),
+            float_precision: None,
+            float_format: "auto".to_string(),
+            column_style: "thyme".to_string(),
+            channel_names: None,
+            include_geometry: false,
+            geometry_format: "list".to_string(),
+            geometry_max_points: 128,
+            provenance: false,
+            hash_inputs: false,
+            per_image_output: false,
+            per_image_format: "csv".to_string(),
+            resume: false,
+            overwrite: false,
+            dry_run: false,
+            prefetch: 0,
+            io_threads: 2,
+            remote_cache: None,
+            threads: None,
+        }
+    }
+}
+
+setters! {
+    ProfileMaskConfig {
+        values {
+            verbose: bool,
+            tui: bool,
+            drop_borders: bool,
+            pad: u32,
+            min_size: u32,
+            strict_masks: bool,
+            centroid_displacement: bool,
+            extended_intensity: bool,
+            texture_stride: usize,
+            thumbnail_size: u32,
+            preview_rescale: bool,
+            strict_join: bool,
+            provenance: bool,
+            hash_inputs: bool,
+            per_image_output: bool,
+            resume: bool,
+            overwrite: bool,
+            dry_run: bool,
+            prefetch: usize,
+            io_threads: usize,
+            include_geometry: bool,
+            geometry_max_points: usize,
+        }
+        strings {
+            images,
+            output,
+            log_format,
+            border_policy,
+            nonfinite_policy,
+            oversized_policy,
+            mode,
+            background_style,
+            zstack_mode,
+            mask_format,
+            measure_device,
+            ipc_compression,
+            float_format,
+            column_style,
+            geometry_format,
+            per_image_format,
+        }
+        optional {
+            min_area: f32,
+            max_area: f32,
+            max_points_per_object: usize,
+            max_seconds_per_object: f32,
+            prob_threshold: f32,
+            prob_min_distance: u32,
+            membrane_width: u32,
+            texture_auto_stride: usize,
+            approx_percentiles_over: usize,
+            float_precision: usize,
+            preview: f32,
+            threads: usize,
+        }
+        optional_strings {
+            skip_image_if,
+            masks,
+            image_substring,
+            mask_substring,
+            image_glob,
+            image_regex,
+            mask_glob,
+            mask_regex,
+            segment_transform,
+            secondary_masks,
+            secondary_substring,
+            class_mask,
+            class_substring,
+            features_channels,
+            channel_pairs,
+            smooth_contours,
+            texture_scales,
+            thumbnails,
+            metadata_table,
+            metadata_join,
+            well_from_filename,
+            remote_cache,
+            channel_names,
+        }
+    }
+}
+
+impl ProfileMaskConfig {
+    /// Initialize a config with command line defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Image directory
+    /// * `output` - Output directory or file
+    pub fn new(images: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            images: images.into(),
+            output: output.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Mask directory, defaulting to the image directory
+    pub fn masks_path(&self) -> &str {
+        self.masks.as_deref().unwrap_or(&self.images)
+    }
+
+    /// Input files and directories read by the run
+    pub fn inputs(&self) -> Vec<&str> {
+        [
+            Some(self.images.as_str()),
+            Some(self.masks_path()),
+            self.secondary_masks.as_deref(),
+            self.class_mask.as_deref(),
+            self.metadata_table.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Check that the config describes a valid run
+    ///
+    /// Only the config itself is checked. Input files are checked when the
+    /// command is run.
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        check_paths(&self.images, &self.output)?;
+        check_threads(self.threads)?;
+        check_log_format(&self.log_format)?;
+        check_mode(&self.mode, &['c', 'm', 'b', 'f', 'p', 'x', 's'])?;
+        check_borders(self.drop_borders, &self.border_policy)?;
+        BackgroundStyle::parse(&self.background_style)?;
+
+        if let Some(skip_image_if) = &self.skip_image_if {
+            Expression::parse(skip_image_if, &ImageSummary::METRICS)?;
+        }
+
+        check_remote(
+            &[&self.images, self.masks_path()],
+            &self.output,
+            self.remote_cache.as_deref(),
+            self.provenance,
+        )?;
+
+        check_texture(self.texture_stride, self.texture_auto_stride)?;
+        check_budget(self.max_points_per_object, self.max_seconds_per_object)?;
+        check_approx_percentiles(self.approx_percentiles_over, self.extended_intensity)?;
+        check_measure_device(&self.measure_device, self.features_channels.as_deref())?;
+
+        if let Some(channel_pairs) = &self.channel_pairs {
+            ChannelPairs::parse(channel_pairs)?;
+        }
+
+        if let Some(smooth_contours) = &self.smooth_contours {
+            ContourSmoothing::parse(smooth_contours)?;
+        }
+        check_outputs(
+            &self.ipc_compression,
+            self.metadata_table.as_deref(),
+            self.metadata_join.as_deref(),
+            self.provenance,
+            self.hash_inputs,
+        )?;
+        check_resume(self.per_image_output, self.resume, self.overwrite)?;
+        check_float_format(&self.float_format)?;
+        check_column_style(
+            &self.column_style,
+            self.channel_names.as_deref(),
+            self.texture_scales.as_deref(),
+        )?;
+        check_preview(self.preview, self.preview_rescale)?;
+        check_geometry(
+            self.include_geometry,
+            &self.geometry_format,
+            self.geometry_max_points,
+            &self.output,
+            self.per_image_output
+                .then_some(self.per_image_format.as_str()),
+        )?;
+
+        requires(
+            self.prob_min_distance.is_some(),
+            self.prob_threshold.is_some(),
+            "prob_min_distance requires prob_threshold",
+        )?;
+
+        requires(
+            self.secondary_substring.is_some(),
+            self.secondary_masks.is_some(),
+            "secondary_substring requires secondary_masks",
+        )?;
+
+        requires(
+            self.class_substring.is_some(),
+            self.class_mask.is_some(),
+            "class_substring requires class_mask",
+        )?;
+
+        requires(
+            self.smooth_contours.is_some(),
+            self.mode.contains('p'),
+            "smooth_contours requires the p (polygons) mode",
+        )?;
+
+        // Label masks cannot be transformed without resampling their pixels
+        if self.segment_transform.is_some() {
+            return Err(other(
+                "segment_transform is not supported for masks. Resample the masks onto the image grid with nearest-neighbour interpolation before profiling, or convert them to polygons with `thyme utils mask2polygons` and profile them with `thyme profile polygons --segment-transform`",
+            ));
+        }
+
+        if self.membrane_width == Some(0) {
+            return Err(other(
+                "membrane_width must be a positive integer if provided",
+            ));
+        }
+
+        let zstack = ZStackMode::parse(&self.zstack_mode)?;
+        MaskFormat::parse(&self.mask_format)?;
+
+        if zstack != ZStackMode::Error && self.prob_threshold.is_some() {
+            return Err(other("zstack_mode cannot be combined with prob_threshold"));
+        }
+
+        if self.mask_format != "auto" && self.prob_threshold.is_some() {
+            return Err(other("mask_format cannot be combined with prob_threshold"));
+        }
+
+        // Secondary and class masks are read at full resolution
+        if self.preview.is_some() {
+            for (conflict, name) in [
+                (self.secondary_masks.is_some(), "secondary_masks"),
+                (self.class_mask.is_some(), "class_mask"),
+            ] {
+                if conflict {
+                    return Err(ThymeError::OtherError(format!(
+                        "preview cannot be combined with {}",
+                        name
+                    )));
+                }
+            }
+        }
+
+        if zstack == ZStackMode::PerSlice {
+            for (conflict, name) in [
+                (self.secondary_masks.is_some(), "secondary_masks"),
+                (self.class_mask.is_some(), "class_mask"),
+                (self.resume, "resume"),
+            ] {
+                if conflict {
+                    return Err(ThymeError::OtherError(format!(
+                        "zstack_mode per-slice cannot be combined with {}",
+                        name
+                    )));
+                }
+            }
+        }
+
+        let masks = self.masks_path();
+        let mask_selection = (&self.mask_substring, &self.mask_glob, &self.mask_regex);
+
+        check_inputs(
+            (
+                &self.images,
+                (&self.image_substring, &self.image_glob, &self.image_regex),
+            ),
+            ("mask", masks, mask_selection),
+            "images and masks",
+            "image and mask",
+        )?;
+
+        if let Some(secondary) = &self.secondary_masks {
+            check_substrings(
+                (masks, mask_selection),
+                (secondary, (&self.secondary_substring, &None, &None)),
+                "masks and secondary masks",
+                "mask and secondary",
+            )?;
+        }
+
+        if let Some(classes) = &self.class_mask {
+            check_substrings(
+                (masks, mask_selection),
+                (classes, (&self.class_substring, &None, &None)),
+                "masks and class masks",
+                "mask and class",
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Configuration of `thyme profile polygons`
+///
+/// Fields mirror the command line arguments of the same name and default to
+/// the command line defaults.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_config::ProfilePolygonsConfig;
+///
+/// let config = ProfilePolygonsConfig::new("images/", "descriptors.pq")
+///     .polygons("polygons/")
+///     .segment_coords("normalized");
+///
+/// assert_eq!(config.mode, "cm");
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfilePolygonsConfig {
+    /// Image directory
+    pub images: String,
+    /// Polygons directory, defaulting to the image directory
+    pub polygons: Option<String>,
+    /// Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather)
+    pub output: String,
+    /// Log progress
+    pub verbose: bool,
+    /// Show a live status panel instead of the progress bar if stdout is a terminal
+    pub tui: bool,
+    /// Format of the status lines logged if stdout is not a terminal (plain, json)
+    pub log_format: String,
+    /// Exclude objects touching the edge of the image
+    pub drop_borders: bool,
+    /// Handling of objects touching the edge of the image (keep, drop, flag)
+    pub border_policy: String,
+    /// Handling of objects with non-finite descriptors (keep, zero, drop-object)
+    pub nonfinite_policy: String,
+    /// Descriptor families to compute (any of c, f, b, m, p, x, s)
+    pub mode: String,
+    /// Pixels measured as the background of mode b (bbox, ring:<px>)
+    pub background_style: String,
+    /// Padding around extracted objects (pixels)
+    pub pad: u32,
+    /// Substring specifying images
+    pub image_substring: Option<String>,
+    /// Substring specifying polygons
+    pub polygon_substring: Option<String>,
+    /// Glob pattern specifying images by their path relative to the image directory
+    pub image_glob: Option<String>,
+    /// Regex specifying images by their path relative to the image directory
+    pub image_regex: Option<String>,
+    /// Glob pattern specifying polygons by their path relative to their directory
+    pub polygon_glob: Option<String>,
+    /// Regex specifying polygons by their path relative to their directory
+    pub polygon_regex: Option<String>,
+    /// Coordinate system of the polygon vertices (pixels, normalized)
+    pub segment_coords: String,
+    /// Table of per-image affine transforms applied to polygon vertices (image, a, b, c, d, tx, ty)
+    pub segment_transform: Option<String>,
+    /// Minimum unpadded bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum polygon area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum polygon area (pixels)
+    pub max_area: Option<f32>,
+    /// Maximum number of outline vertices of an object before it is resampled or skipped
+    pub max_points_per_object: Option<usize>,
+    /// Handling of objects with more than max_points_per_object vertices (resample, skip)
+    pub oversized_policy: String,
+    /// Time after which the remaining descriptor families of an object are abandoned (seconds)
+    pub max_seconds_per_object: Option<f32>,
+    /// Skip images whose image-level metrics match this expression
+    pub skip_image_if: Option<String>,
+    /// Exclude self-intersecting polygons that could not be repaired
+    pub skip_invalid_polygons: bool,
+    /// Width of the boundary band of membrane descriptors (pixels)
+    pub membrane_width: Option<u32>,
+    /// Compute intensity-weighted centroid displacements
+    pub centroid_displacement: bool,
+    /// Include percentiles, skewness, and kurtosis in intensity descriptors
+    pub extended_intensity: bool,
+    /// Positive subpixels of a channel above which extended intensity descriptors are computed without sorting
+    pub approx_percentiles_over: Option<usize>,
+    /// Channels of each descriptor family (e.g. `intensity:0-5;texture:0,1`)
+    pub features_channels: Option<String>,
+    /// Channel pairs of intensity ratio descriptors (e.g. `all` or `0:1,2:1`)
+    pub channel_pairs: Option<String>,
+    /// Stride of texture co-occurrence anchors
+    pub texture_stride: usize,
+    /// Object size above which the texture stride is escalated (pixels)
+    pub texture_auto_stride: Option<usize>,
+    /// Device intensity sums and moments of large objects are reduced on (cpu, cuda)
+    pub measure_device: String,
+    /// Downsampling factors of multi-scale texture descriptors (e.g. `1,2,4`)
+    pub texture_scales: Option<String>,
+    /// Directory to write object thumbnails to
+    pub thumbnails: Option<String>,
+    /// Width and height of object thumbnails (pixels)
+    pub thumbnail_size: u32,
+    /// Factor images and segments are downsampled by in a preview run (e.g. 0.25)
+    pub preview: Option<f32>,
+    /// Rescale lengths and areas of a preview run to original pixel units
+    pub preview_rescale: bool,
+    /// Metadata table joined to every output row
+    pub metadata_table: Option<String>,
+    /// Name of the join key column in the metadata table
+    pub metadata_join: Option<String>,
+    /// Regex extracting the join key from image file names
+    pub well_from_filename: Option<String>,
+    /// Fail instead of writing nulls for images without matching metadata
+    pub strict_join: bool,
+    /// Compression of arrow/feather output tables (lz4, zstd, none)
+    pub ipc_compression: String,
+    /// Digits after the decimal point of floats in csv/tsv output tables
+    pub float_precision: Option<usize>,
+    /// Notation of floats in csv/tsv output tables (auto, fixed)
+    pub float_format: String,
+    /// Naming convention of descriptor columns (thyme, cellprofiler)
+    pub column_style: String,
+    /// Comma-separated names of the image channels used by the cellprofiler column style (e.g. `DNA,Actin`)
+    pub channel_names: Option<String>,
+    /// Append the polygon of each object to parquet output tables
+    pub include_geometry: bool,
+    /// Encoding of the polygon column (list, wkb)
+    pub geometry_format: String,
+    /// Maximum number of vertices of each polygon in the polygon column
+    pub geometry_max_points: usize,
+    /// Write a provenance sidecar next to each output table
+    pub provenance: bool,
+    /// Record sha256 hashes of input files in the provenance sidecar
+    pub hash_inputs: bool,
+    /// Write a separate descriptors table for each image
+    pub per_image_output: bool,
+    /// Format of per-image output tables
+    pub per_image_format: String,
+    /// Skip images whose per-image output table already exists
+    pub resume: bool,
+    /// Remove the outputs of a previous run from the output location
+    pub overwrite: bool,
+    /// Report the pairs, objects, and output size of the run without profiling
+    pub dry_run: bool,
+    /// Number of decoded images queued ahead of the compute threads
+    pub prefetch: usize,
+    /// Number of threads decoding images if prefetching
+    pub io_threads: usize,
+    /// Directory caching objects fetched from remote inputs
+    pub remote_cache: Option<String>,
+    /// Number of compute threads, defaulting to all available threads
+    pub threads: Option<usize>,
+}
+
+impl Default for ProfilePolygonsConfig {
+    fn default() -> Self {
+        Self {
+            images: String::new(),
+            polygons: None,
+            output: String::new(),
+            verbose: false,
+            tui: false,
+            log_format: "plain".to_string(),
+            drop_borders: false,
+            border_policy: "keep".to_string(),
+            nonfinite_policy: "keep".to_string(),
+            mode: "cm".to_string(),
+            background_style: "bbox".to_string(),
+            pad: 1,
+            image_substring: None,
+            polygon_substring: None,
+            image_glob: None,
+            image_regex: None,
+            polygon_glob: None,
+            polygon_regex: None,
+            segment_coords: "pixels".to_string(),
+            segment_transform: None,
+            min_size: 1,
+            min_area: None,
+            max_area: None,
+            max_points_per_object: None,
+            oversized_policy: "resample".to_string(),
+            max_seconds_per_object: None,
+            skip_image_if: None,
+            skip_invalid_polygons: false,
+            membrane_width: None,
+            centroid_displacement: false,
+            extended_intensity: false,
+            approx_percentiles_over: None,
+            features_channels: None,
+            channel_pairs: None,
+            texture_stride: 1,
+            texture_auto_stride: None,
+            measure_device: "cpu".to_string(),
+            texture_scales: None,
+            thumbnails: None,
+            thumbnail_size: 64,
+            preview: None,
+            preview_rescale: false,
+            metadata_table: None,
+            metadata_join: None,
+            well_from_filename: None,
+            strict_join: false,
+            ipc_compression: "none".to_string(),
+            float_precision: None,
+            float_format: "auto".to_string(),
+            column_style: "thyme".to_string(),
+            channel_names: None,
+            include_geometry: false,
+            geometry_format: "list".to_string(),
+            geometry_max_points: 128,
+            provenance: false,
+            hash_inputs: false,
+            per_image_output: false,
+            per_image_format: "csv".to_string(),
+            resume: false,
+            overwrite: false,
+            dry_run: false,
+            prefetch: 0,
+            io_threads: 2,
+            remote_cache: None,
+            threads: None,
+        }
+    }
+}
+
+setters! {
+    ProfilePolygonsConfig {
+        values {
+            verbose: bool,
+            tui: bool,
+            drop_borders: bool,
+            pad: u32,
+            min_size: u32,
+            skip_invalid_polygons: bool,
+            centroid_displacement: bool,
+            extended_intensity: bool,
+            texture_stride: usize,
+            thumbnail_size: u32,
+            preview_rescale: bool,
+            strict_join: bool,
+            provenance: bool,
+            hash_inputs: bool,
+            per_image_output: bool,
+            resume: bool,
+            overwrite: bool,
+            dry_run: bool,
+            prefetch: usize,
+            io_threads: usize,
+            include_geometry: bool,
+            geometry_max_points: usize,
+        }
+        strings {
+            images,
+            output,
+            log_format,
+            border_policy,
+            nonfinite_policy,
+            oversized_policy,
+            mode,
+            background_style,
+            segment_coords,
+            measure_device,
+            ipc_compression,
+            float_format,
+            column_style,
+            geometry_format,
+            per_image_format,
+        }
+        optional {
+            min_area: f32,
+            max_area: f32,
+            max_points_per_object: usize,
+            max_seconds_per_object: f32,
+            membrane_width: u32,
+            texture_auto_stride: usize,
+            approx_percentiles_over: usize,
+            float_precision: usize,
+            preview: f32,
+            threads: usize,
+        }
+        optional_strings {
+            skip_image_if,
+            polygons,
+            image_substring,
+            polygon_substring,
+            image_glob,
+            image_regex,
+            polygon_glob,
+            polygon_regex,
+            segment_transform,
+            features_channels,
+            channel_pairs,
+            texture_scales,
+            thumbnails,
+            metadata_table,
+            metadata_join,
+            well_from_filename,
+            remote_cache,
+            channel_names,
+        }
+    }
+}
+
+impl ProfilePolygonsConfig {
+    /// Initialize a config with command line defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Image directory
+    /// * `output` - Output directory or file
+    pub fn new(images: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            images: images.into(),
+            output: output.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Polygons directory, defaulting to the image directory
+    pub fn polygons_path(&self) -> &str {
+        self.polygons.as_deref().unwrap_or(&self.images)
+    }
+
+    /// Input files and directories read by the run
+    pub fn inputs(&self) -> Vec<&str> {
+        [
+            Some(self.images.as_str()),
+            Some(self.polygons_path()),
+            self.metadata_table.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Check that the config describes a valid run
+    ///
+    /// Only the config itself is checked. Input files are checked when the
+    /// command is run.
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        check_paths(&self.images, &self.output)?;
+        check_threads(self.threads)?;
+        check_log_format(&self.log_format)?;
+        check_mode(&self.mode, &['c', 'm', 'b', 'f', 'p', 'x', 's'])?;
+        check_borders(self.drop_borders, &self.border_policy)?;
+        BackgroundStyle::parse(&self.background_style)?;
+
+        if let Some(skip_image_if) = &self.skip_image_if {
+            Expression::parse(skip_image_if, &ImageSummary::METRICS)?;
+        }
+
+        check_remote(
+            &[&self.images, self.polygons_path()],
+            &self.output,
+            self.remote_cache.as_deref(),
+            self.provenance,
+        )?;
+
+        check_texture(self.texture_stride, self.texture_auto_stride)?;
+        check_budget(self.max_points_per_object, self.max_seconds_per_object)?;
+        check_approx_percentiles(self.approx_percentiles_over, self.extended_intensity)?;
+        check_measure_device(&self.measure_device, self.features_channels.as_deref())?;
+
+        if let Some(channel_pairs) = &self.channel_pairs {
+            ChannelPairs::parse(channel_pairs)?;
+        }
+        check_outputs(
+            &self.ipc_compression,
+            self.metadata_table.as_deref(),
+            self.metadata_join.as_deref(),
+            self.provenance,
+            self.hash_inputs,
+        )?;
+        check_resume(self.per_image_output, self.resume, self.overwrite)?;
+        check_float_format(&self.float_format)?;
+        check_column_style(
+            &self.column_style,
+            self.channel_names.as_deref(),
+            self.texture_scales.as_deref(),
+        )?;
+        check_preview(self.preview, self.preview_rescale)?;
+        check_geometry(
+            self.include_geometry,
+            &self.geometry_format,
+            self.geometry_max_points,
+            &self.output,
+            self.per_image_output
+                .then_some(self.per_image_format.as_str()),
+        )?;
+
+        requires(
+            self.segment_transform.is_some(),
+            self.segment_coords == "pixels",
+            "segment_transform requires pixel segment_coords",
+        )?;
+
+        if self.membrane_width == Some(0) {
+            return Err(other(
+                "membrane_width must be a positive integer if provided",
+            ));
+        }
+
+        check_inputs(
+            (
+                &self.images,
+                (&self.image_substring, &self.image_glob, &self.image_regex),
+            ),
+            (
+                "polygon",
+                self.polygons_path(),
+                (
+                    &self.polygon_substring,
+                    &self.polygon_glob,
+                    &self.polygon_regex,
+                ),
+            ),
+            "images and polygons",
+            "image and polygon",
+        )
+    }
+}
+
+/// Configuration of `thyme profile boxes`
+///
+/// Fields mirror the command line arguments of the same name and default to
+/// the command line defaults.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_config::ProfileBoxesConfig;
+///
+/// let config = ProfileBoxesConfig::new("images/", "output/")
+///     .boxes("boxes.csv")
+///     .fast_complete(true);
+///
+/// assert_eq!(config.mode, "cx");
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileBoxesConfig {
+    /// Image directory
+    pub images: String,
+    /// Bounding boxes directory or csv table, defaulting to the image directory
+    pub boxes: Option<String>,
+    /// Output directory or file (.csv, .txt, .tsv, .pq, .arrow, .feather)
+    pub output: String,
+    /// Log progress
+    pub verbose: bool,
+    /// Show a live status panel instead of the progress bar if stdout is a terminal
+    pub tui: bool,
+    /// Format of the status lines logged if stdout is not a terminal (plain, json)
+    pub log_format: String,
+    /// Exclude objects touching the edge of the image
+    pub drop_borders: bool,
+    /// Handling of objects touching the edge of the image (keep, drop, flag)
+    pub border_policy: String,
+    /// Handling of objects with non-finite descriptors (keep, zero, drop-object)
+    pub nonfinite_policy: String,
+    /// Descriptor families to compute (any of c, x)
+    pub mode: String,
+    /// Padding around extracted objects (pixels)
+    pub pad: u32,
+    /// Substring specifying images
+    pub image_substring: Option<String>,
+    /// Substring specifying bounding boxes
+    pub box_substring: Option<String>,
+    /// Glob pattern specifying images by their path relative to the image directory
+    pub image_glob: Option<String>,
+    /// Regex specifying images by their path relative to the image directory
+    pub image_regex: Option<String>,
+    /// Glob pattern specifying bounding boxes by their path relative to their directory
+    pub box_glob: Option<String>,
+    /// Regex specifying bounding boxes by their path relative to their directory
+    pub box_regex: Option<String>,
+    /// Bounding box csv column names as key=column pairs
+    pub box_columns: Option<String>,
+    /// Table of per-image affine transforms applied to box corners (image, a, b, c, d, tx, ty)
+    pub segment_transform: Option<String>,
+    /// Minimum unpadded bounding box width and height (pixels)
+    pub min_size: u32,
+    /// Minimum bounding box area (pixels)
+    pub min_area: Option<f32>,
+    /// Maximum bounding box area (pixels)
+    pub max_area: Option<f32>,
+    /// Include percentiles, skewness, and kurtosis in intensity descriptors
+    pub extended_intensity: bool,
+    /// Positive subpixels of a channel above which extended intensity descriptors are computed without sorting
+    pub approx_percentiles_over: Option<usize>,
+    /// Only compute complete intensity sums, means, and stds from summed-area tables
+    pub fast_complete: bool,
+    /// Channels of each descriptor family (e.g. `intensity:0-5;texture:0,1`)
+    pub features_channels: Option<String>,
+    /// Stride of texture co-occurrence anchors
+    pub texture_stride: usize,
+    /// Object size above which the texture stride is escalated (pixels)
+    pub texture_auto_stride: Option<usize>,
+    /// Directory to write object thumbnails to
+    pub thumbnails: Option<String>,
+    /// Width and height of object thumbnails (pixels)
+    pub thumbnail_size: u32,
+    /// Factor images and segments are downsampled by in a preview run (e.g. 0.25)
+    pub preview: Option<f32>,
+    /// Rescale lengths and areas of a preview run to original pixel units
+    pub preview_rescale: bool,
+    /// Metadata table joined to every output row
+    pub metadata_table: Option<String>,
+    /// Name of the join key column in the metadata table
+    pub metadata_join: Option<String>,
+    /// Regex extracting the join key from image file names
+    pub well_from_filename: Option<String>,
+    /// Fail instead of writing nulls for images without matching metadata
+    pub strict_join: bool,
+    /// Compression of arrow/feather output tables (lz4, zstd, none)
+    pub ipc_compression: String,
+    /// Digits after the decimal point of floats in csv/tsv output tables
+    pub float_precision: Option<usize>,
+    /// Notation of floats in csv/tsv output tables (auto, fixed)
+    pub float_format: String,
+    /// Naming convention of descriptor columns (thyme, cellprofiler)
+    pub column_style: String,
+    /// Comma-separated names of the image channels used by the cellprofiler column style (e.g. `DNA,Actin`)
+    pub channel_names: Option<String>,
+    /// Write a provenance sidecar next to each output table
+    pub provenance: bool,
+    /// Record sha256 hashes of input files in the provenance sidecar
+    pub hash_inputs: bool,
+    /// Write a separate descriptors table for each image
+    pub per_image_output: bool,
+    /// Format of per-image output tables
+    pub per_image_format: String,
+    /// Skip images whose per-image output table already exists
+    pub resume: bool,
+    /// Remove the outputs of a previous run from the output location
+    pub overwrite: bool,
+    /// Report the pairs, objects, and output size of the run without profiling
+    pub dry_run: bool,
+    /// Number of decoded images queued ahead of the compute threads
+    pub prefetch: usize,
+    /// Number of threads decoding images if prefetching
+    pub io_threads: usize,
+    /// Number of compute threads, defaulting to all available threads
+    pub threads: Option<usize>,
+}
+
+impl Default for ProfileBoxesConfig {
+    fn default() -> Self {
+        Self {
+            images: String::new(),
+            boxes: None,
+            output: String::new(),
+            verbose: false,
+            tui: false,
+            log_format: "plain".to_string(),
+            drop_borders: false,
+            border_policy: "keep".to_string(),
+            nonfinite_policy: "keep".to_string(),
+            mode: "cx".to_string(),
+            pad: 1,
+            image_substring: None,
+            box_substring: None,
+            image_glob: None,
+            image_regex: None,
+            box_glob: None,
+            box_regex: None,
+            box_columns: None,
+            segment_transform: None,
+            min_size: 1,
+            min_area: None,
+            max_area: None,
+            extended_intensity: false,
+            approx_percentiles_over: None,
+            fast_complete: false,
+            features_channels: None,
+            texture_stride: 1,
+            texture_auto_stride: None,
+            thumbnails: None,
+            thumbnail_size: 64,
+            preview: None,
+            preview_rescale: false,
+            metadata_table: None,
+            metadata_join: None,
+            well_from_filename: None,
+            strict_join: false,
+            ipc_compression: "none".to_string(),
+            float_precision: None,
+            float_format: "auto".to_string(),
+            column_style: "thyme".to_string(),
+            channel_names: None,
+            provenance: false,
+            hash_inputs: false,
+            per_image_output: false,
+            per_image_format: "csv".to_string(),
+            resume: false,
+            overwrite: false,
+            dry_run: false,
+            prefetch: 0,
+            io_threads: 2,
+            threads: None,
+        }
+    }
+}
+
+setters! {
+    ProfileBoxesConfig {
+        values {
+            verbose: bool,
+            tui: bool,
+            drop_borders: bool,
+            pad: u32,
+            min_size: u32,
+            extended_intensity: bool,
+            fast_complete: bool,
+            texture_stride: usize,
+            thumbnail_size: u32,
+            preview_rescale: bool,
+            strict_join: bool,
+            provenance: bool,
+            hash_inputs: bool,
+            per_image_output: bool,
+            resume: bool,
+            overwrite: bool,
+            dry_run: bool,
+            prefetch: usize,
+            io_threads: usize,
+        }
+        strings {
+            images,
+            output,
+            log_format,
+            border_policy,
+            nonfinite_policy,
+            mode,
+            ipc_compression,
+            float_format,
+            column_style,
+            per_image_format,
+        }
+        optional {
+            min_area: f32,
+            max_area: f32,
+            texture_auto_stride: usize,
+            approx_percentiles_over: usize,
+            float_precision: usize,
+            preview: f32,
+            threads: usize,
+        }
+        optional_strings {
+            boxes,
+            image_substring,
+            box_substring,
+            image_glob,
+            image_regex,
+            box_glob,
+            box_regex,
+            box_columns,
+            segment_transform,
+            features_channels,
+            thumbnails,
+            metadata_table,
+            metadata_join,
+            well_from_filename,
+            channel_names,
+        }
+    }
+}
+
+impl ProfileBoxesConfig {
+    /// Initialize a config with command line defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Image directory
+    /// * `output` - Output directory or file
+    pub fn new(images: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            images: images.into(),
+            output: output.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Bounding boxes directory or table, defaulting to the image directory
+    pub fn boxes_path(&self) -> &str {
+        self.boxes.as_deref().unwrap_or(&self.images)
+    }
+
+    /// Input files and directories read by the run
+    pub fn inputs(&self) -> Vec<&str> {
+        [
+            Some(self.images.as_str()),
+            Some(self.boxes_path()),
+            self.metadata_table.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Check that the config describes a valid run
+    ///
+    /// Only the config itself is checked. Input files are checked when the
+    /// command is run.
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        check_paths(&self.images, &self.output)?;
+        check_threads(self.threads)?;
+        check_log_format(&self.log_format)?;
+        check_mode(&self.mode, &['c', 'x'])?;
+        check_borders(self.drop_borders, &self.border_policy)?;
+        check_texture(self.texture_stride, self.texture_auto_stride)?;
+        check_approx_percentiles(self.approx_percentiles_over, self.extended_intensity)?;
+        check_outputs(
+            &self.ipc_compression,
+            self.metadata_table.as_deref(),
+            self.metadata_join.as_deref(),
+            self.provenance,
+            self.hash_inputs,
+        )?;
+        check_resume(self.per_image_output, self.resume, self.overwrite)?;
+        check_float_format(&self.float_format)?;
+        check_column_style(&self.column_style, self.channel_names.as_deref(), None)?;
+        check_preview(self.preview, self.preview_rescale)?;
+
+        if self.fast_complete && self.extended_intensity {
+            return Err(other(
+                "fast_complete cannot be combined with extended_intensity",
+            ));
+        }
+
+        if self.fast_complete && self.features_channels.is_some() {
+            return Err(other(
+                "fast_complete cannot be combined with features_channels",
+            ));
+        }
+
+        check_inputs(
+            (
+                &self.images,
+                (&self.image_substring, &self.image_glob, &self.image_regex),
+            ),
+            (
+                "box",
+                self.boxes_path(),
+                (&self.box_substring, &self.box_glob, &self.box_regex),
+            ),
+            "images and boxes",
+            "image and bounding box",
+        )
+    }
+}
+
+fn check_texture(stride: usize, auto_stride: Option<usize>) -> Result<(), ThymeError> {
+    match stride == 0 || auto_stride == Some(0) {
+        true => Err(other(
+            "texture_stride and texture_auto_stride must be positive integers if provided",
+        )),
+        false => Ok(()),
+    }
+}
+
+fn check_budget(
+    max_points_per_object: Option<usize>,
+    max_seconds_per_object: Option<f32>,
+) -> Result<(), ThymeError> {
+    if max_points_per_object.is_some_and(|max| max < 4) {
+        return Err(other(
+            "max_points_per_object must be at least 4 if provided",
+        ));
+    }
+
+    match max_seconds_per_object.is_some_and(|max| !(max > 0.0 && max.is_finite())) {
+        true => Err(other(
+            "max_seconds_per_object must be a positive number if provided",
+        )),
+        false => Ok(()),
+    }
+}
+
+fn check_approx_percentiles(
+    approx_percentiles_over: Option<usize>,
+    extended_intensity: bool,
+) -> Result<(), ThymeError> {
+    if approx_percentiles_over == Some(0) {
+        return Err(other(
+            "approx_percentiles_over must be a positive integer if provided",
+        ));
+    }
+
+    requires(
+        approx_percentiles_over.is_some(),
+        extended_intensity,
+        "approx_percentiles_over requires extended_intensity",
+    )
+}
+
+fn check_measure_device(
+    measure_device: &str,
+    features_channels: Option<&str>,
+) -> Result<(), ThymeError> {
+    let device = MeasureDevice::parse(measure_device)?;
+
+    // Per-channel descriptors are measured on single-channel copies on the cpu
+    match device != MeasureDevice::Cpu && features_channels.is_some() {
+        true => Err(other(
+            "measure_device cuda cannot be combined with features_channels",
+        )),
+        false => Ok(()),
+    }
+}
+
+fn check_column_style(
+    column_style: &str,
+    channel_names: Option<&str>,
+    texture_scales: Option<&str>,
+) -> Result<(), ThymeError> {
+    let cellprofiler = ColumnStyle::parse(column_style)? == ColumnStyle::CellProfiler;
+
+    if let Some(channel_names) = channel_names {
+        parse_channel_names(channel_names)?;
+    }
+
+    requires(
+        channel_names.is_some(),
+        cellprofiler,
+        "channel_names requires column_style cellprofiler",
+    )?;
+
+    // Unscaled texture descriptors are named with a scale of one
+    let unit_scale = texture_scales.is_some_and(|scales| {
+        scales
+            .split(',')
+            .any(|scale| scale.trim().parse::<u32>() == Ok(1))
+    });
+
+    match cellprofiler && unit_scale {
+        true => Err(other(
+            "texture_scales cannot include 1 with column_style cellprofiler",
+        )),
+        false => Ok(()),
+    }
+}
+
+fn check_preview(preview: Option<f32>, preview_rescale: bool) -> Result<(), ThymeError> {
+    if let Some(preview) = preview {
+        if !(preview > 0.0 && preview <= 1.0) {
+            return Err(ThymeError::OtherError(format!(
+                "Invalid preview {}. Must be in (0, 1]",
+                preview
+            )));
+        }
+    }
+
+    requires(
+        preview_rescale,
+        preview.is_some(),
+        "preview_rescale requires preview",
+    )
+}
+
+/// Fail if object polygons are requested for tables that cannot hold them
+///
+/// Polygons are written as nested list or binary columns, which only parquet
+/// tables hold, so the output (or per-image format) must be parquet.
+fn check_geometry(
+    include_geometry: bool,
+    geometry_format: &str,
+    geometry_max_points: usize,
+    output: &str,
+    per_image_format: Option<&str>,
+) -> Result<(), ThymeError> {
+    if !constant::SUPPORTED_GEOMETRY_FORMATS.contains(&geometry_format) {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid geometry_format {}. Must be one of: {:?}",
+            geometry_format,
+            constant::SUPPORTED_GEOMETRY_FORMATS
+        )));
+    }
+
+    if geometry_max_points < 4 {
+        return Err(other("geometry_max_points must be at least 4"));
+    }
+
+    if !include_geometry {
+        return Ok(());
+    }
+
+    let format = per_image_format.map(str::to_string).unwrap_or_else(|| {
+        std::path::Path::new(output)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or("csv".to_string())
+    });
+
+    match format.as_str() {
+        "pq" => Ok(()),
+        _ => Err(ThymeError::OtherError(format!(
+            "include_geometry requires parquet output but the {} table is {}. Write to a .pq file or set per_image_format to pq",
+            match per_image_format {
+                Some(_) => "per-image",
+                None => "output",
+            },
+            format
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_profile_mask_config_defaults() {
+        let config = ProfileMaskConfig::new("images/", "output/");
+
+        assert_eq!(config.masks_path(), "images/");
+        assert_eq!(config.border_policy, "keep");
+        assert_eq!((config.pad, config.min_size), (1, 1));
+        assert_eq!((config.prefetch, config.io_threads), (0, 2));
+        assert_eq!(config.thumbnail_size, 64);
+
+        // Same-path images and masks require distinct substrings
+        assert!(config.validate().is_err());
+
+        let config = config.image_substring("_image").mask_substring("_mask");
+        assert!(config.validate().is_ok());
+
+        // Paths are compared by their components rather than as strings
+        let config = ProfileMaskConfig::new("images/", "output/").masks("images");
+        assert!(config.validate().is_err());
+
+        // Omitted fields are filled with defaults when deserialized
+        let json = r#"{"images": "images/", "masks": "masks/", "output": "out.csv", "pad": 3}"#;
+        let parsed: ProfileMaskConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.pad, 3);
+        assert_eq!(parsed.mode, "cm");
+        assert!(parsed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_profile_segment_configs_validate() {
+        let polygons = ProfilePolygonsConfig::new("images/", "output/").polygons("polygons/");
+        assert!(polygons.validate().is_ok());
+        assert_eq!(polygons.segment_coords, "pixels");
+
+        assert!(polygons.clone().mode("cmz").validate().is_err());
+        assert!(
+            polygons
+                .clone()
+                .background_style("ring:0")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            polygons
+                .clone()
+                .background_style("ring:3")
+                .validate()
+                .is_ok()
+        );
+        assert!(polygons.clone().membrane_width(0).validate().is_err());
+        assert!(
+            polygons
+                .clone()
+                .max_points_per_object(2)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            polygons
+                .clone()
+                .max_points_per_object(4)
+                .max_seconds_per_object(0.5)
+                .validate()
+                .is_ok()
+        );
+        assert!(polygons.clone().polygons("images/").validate().is_err());
+        assert!(polygons.clone().channel_pairs("0:1,2:1").validate().is_ok());
+        assert!(
+            polygons
+                .clone()
+                .segment_transform("transforms.csv")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            polygons
+                .clone()
+                .segment_transform("transforms.csv")
+                .segment_coords("normalized")
+                .validate()
+                .is_err()
+        );
+
+        let boxes = ProfileBoxesConfig::new("images/", "output/").boxes("boxes.csv");
+        assert!(boxes.validate().is_ok());
+        assert_eq!(boxes.mode, "cx");
+
+        assert!(boxes.clone().mode("cm").validate().is_err());
+        assert!(
+            boxes
+                .clone()
+                .fast_complete(true)
+                .extended_intensity(true)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            boxes
+                .clone()
+                .fast_complete(true)
+                .features_channels("intensity:0")
+                .validate()
+                .is_err()
+        );
+        assert!(boxes.clone().io_threads(2).threads(0).validate().is_err());
+        assert!(
+            boxes
+                .clone()
+                .column_style("cellprofiler")
+                .channel_names("DNA")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            boxes
+                .clone()
+                .segment_transform("transforms.csv")
+                .validate()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_profile_mask_config_validate() {
+        let valid = ProfileMaskConfig::new("images/", "output/").masks("masks/");
+        assert!(valid.validate().is_ok());
+
+        for invalid in [
+            valid.clone().images(""),
+            valid.clone().threads(0),
+            valid.clone().mode("cq"),
+            valid.clone().background_style("ring"),
+            valid.clone().skip_image_if("focus_score<"),
+            valid
+                .clone()
+                .image_substring("_ch1")
+                .image_glob("*_ch1.tif"),
+            valid.clone().mask_glob("*.png").mask_regex("png$"),
+            valid.clone().drop_borders(true).border_policy("flag"),
+            valid.clone().texture_stride(0),
+            valid.clone().membrane_width(0),
+            valid.clone().zstack_mode("mean-project"),
+            valid.clone().mask_format("stardist"),
+            valid
+                .clone()
+                .mask_format("cellpose-seg")
+                .prob_threshold(0.5),
+            valid.clone().zstack_mode("max-project").prob_threshold(0.5),
+            valid
+                .clone()
+                .zstack_mode("per-slice")
+                .class_mask("classes/"),
+            valid.clone().prob_min_distance(3),
+            valid.clone().secondary_substring("_nuc"),
+            valid.clone().secondary_masks("masks/"),
+            valid.clone().ipc_compression("gzip"),
+            valid.clone().float_format("scientific"),
+            valid.clone().metadata_table("platemap.csv"),
+            valid.clone().hash_inputs(true),
+            valid.clone().resume(true),
+            valid
+                .clone()
+                .per_image_output(true)
+                .resume(true)
+                .overwrite(true),
+            valid.clone().output("s3://plates/output"),
+            valid.clone().masks("s3://plates/masks").provenance(true),
+            valid.clone().remote_cache("s3://plates/cache"),
+            valid.clone().channel_pairs("0:0"),
+            valid.clone().preview(1.5),
+            valid.clone().preview_rescale(true),
+            valid.clone().preview(0.5).class_mask("classes/"),
+            valid.clone().measure_device("metal"),
+            valid
+                .clone()
+                .measure_device("cuda")
+                .features_channels("intensity:0"),
+            valid.clone().smooth_contours("gaussian:1.5"),
+            valid.clone().mode("cp").smooth_contours("gaussian:-1"),
+            valid.clone().mode("cp").smooth_contours("chaikin:20"),
+            valid.clone().segment_transform("transforms.csv"),
+            valid.clone().approx_percentiles_over(10_000_000),
+            valid
+                .clone()
+                .extended_intensity(true)
+                .approx_percentiles_over(0),
+            valid.clone().column_style("cp"),
+            valid.clone().channel_names("DNA,Actin"),
+            valid
+                .clone()
+                .column_style("cellprofiler")
+                .channel_names("DNA,DNA"),
+            valid
+                .clone()
+                .column_style("cellprofiler")
+                .texture_scales("1,2"),
+            valid.clone().include_geometry(true),
+            valid.clone().include_geometry(true).output("profiles.csv"),
+            valid
+                .clone()
+                .include_geometry(true)
+                .per_image_output(true)
+                .output("profiles.pq"),
+            valid.clone().geometry_format("geojson"),
+            valid.clone().geometry_max_points(3),
+            valid.clone().max_points_per_object(3),
+            valid.clone().max_seconds_per_object(0.0),
+            valid.clone().max_seconds_per_object(f32::NAN),
+        ] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid);
+        }
+
+        // Remote inputs are only removed after profiling without a cache
+        let remote = valid.clone().images("s3://plates/images").provenance(true);
+        assert!(remote.clone().remote_cache("cache/").validate().is_ok());
+
+        assert!(valid.clone().drop_borders(true).validate().is_ok());
+        assert!(valid.clone().measure_device("cuda").validate().is_ok());
+
+        // Polygons are only written to parquet tables
+        let geometry = valid.clone().include_geometry(true).geometry_format("wkb");
+        assert!(geometry.clone().output("profiles.pq").validate().is_ok());
+        assert!(
+            geometry
+                .per_image_output(true)
+                .per_image_format("pq")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            valid
+                .clone()
+                .column_style("cellprofiler")
+                .channel_names("DNA,Actin")
+                .texture_scales("2,4")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            valid
+                .clone()
+                .extended_intensity(true)
+                .approx_percentiles_over(10_000_000)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            valid
+                .clone()
+                .mode("cp")
+                .smooth_contours("chaikin:2")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            valid
+                .clone()
+                .preview(0.25)
+                .preview_rescale(true)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            valid
+                .clone()
+                .float_precision(4)
+                .float_format("fixed")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            valid
+                .clone()
+                .skip_image_if("foreground_fraction<0.001 || focus_score<30")
+                .validate()
+                .is_ok()
+        );
+
+        // Images and masks in the same directory can be told apart by globs
+        let same = ProfileMaskConfig::new("images/", "output/").image_glob("*.tif");
+        assert!(same.clone().validate().is_ok());
+        assert!(same.mask_glob("*.tif").validate().is_err());
+
+        assert!(
+            valid
+                .secondary_masks("masks/")
+                .mask_substring("_mask")
+                .secondary_substring("_nuc")
+                .validate()
+                .is_ok()
+        );
+    }
+}
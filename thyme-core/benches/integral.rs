@@ -0,0 +1,58 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+
+use thyme_core::im::{ThymeBuffer, ThymeImage};
+
+/// Generate a single-channel 4096x4096 image and 5000 large overlapping boxes
+fn synthetic_boxes() -> (ThymeImage, Vec<[u32; 4]>) {
+    let size = 4096;
+
+    let image = (0..size * size)
+        .map(|i| ((i * 37 + i / 7) % 4093) as u16 + 1)
+        .collect();
+
+    let image = ThymeImage::U16(ThymeBuffer::new(size, size, 1, image).unwrap());
+
+    let boxes = (0..5000u32)
+        .map(|i| {
+            let w = 256 + (i * 13) % 256;
+            let h = 256 + (i * 29) % 256;
+            let x = (i * 7919) % (size - w);
+            let y = (i * 104729) % (size - h);
+            [x, y, w, h]
+        })
+        .collect();
+
+    (image, boxes)
+}
+
+fn bench_integral(c: &mut Criterion) {
+    let (image, boxes) = synthetic_boxes();
+
+    c.bench_function("complete_intensity_direct_5000_4096x4096", |b| {
+        b.iter(|| {
+            for &[x, y, w, h] in boxes.iter() {
+                black_box(image.crop_view(x, y, w, h).intensity());
+            }
+        })
+    });
+
+    c.bench_function("complete_intensity_integral_5000_4096x4096", |b| {
+        b.iter(|| {
+            let integral = image.integral(0);
+            for &[x, y, w, h] in boxes.iter() {
+                black_box(integral.intensity(x as usize, y as usize, w as usize, h as usize));
+            }
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_integral
+}
+
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+
+use thyme_core::im::ThymeBuffer;
+use thyme_core::mp::intensity;
+
+/// Generate a single-channel object with 10 million positive pixels
+fn synthetic_object() -> ThymeBuffer<u16, Vec<u16>> {
+    let size = 3163;
+
+    let pixels = (0..size * size)
+        .map(|i| ((i * 37 + i / 7) % 4093) as u16 + 1)
+        .collect();
+
+    ThymeBuffer::new(size as u32, size as u32, 1, pixels).unwrap()
+}
+
+fn bench_intensity(c: &mut Criterion) {
+    let object = synthetic_object();
+    let view = object.crop_view(0, 0, object.width(), object.height());
+
+    c.bench_function("intensity_objects_10M", |b| {
+        b.iter(|| black_box(intensity::objects(&view, false, None)))
+    });
+
+    c.bench_function("intensity_objects_extended_sorted_10M", |b| {
+        b.iter(|| black_box(intensity::objects(&view, true, None)))
+    });
+
+    c.bench_function("intensity_objects_extended_selected_10M", |b| {
+        b.iter(|| black_box(intensity::objects(&view, true, Some(1_000_000))))
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_intensity
+}
+
+criterion_main!(benches);
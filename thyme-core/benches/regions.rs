@@ -0,0 +1,73 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+
+use thyme_core::im::{MaskingStyle, Regions, ThymeBuffer, ThymeMask};
+
+/// Generate a 3-channel 64x64 image and 200 dense elliptical 64x64 masks
+fn synthetic_objects() -> (ThymeBuffer<u16, Vec<u16>>, Vec<ThymeMask>) {
+    let image = (0..64 * 64 * 3)
+        .map(|i| ((i * 37 + i / 7) % 4093) as u16 + 1)
+        .collect();
+
+    let image = ThymeBuffer::new(64, 64, 3, image).unwrap();
+
+    let masks = (0..200)
+        .map(|i| {
+            let a = 24.0 + (i % 9) as f32;
+            let b = 24.0 + (i % 7) as f32;
+
+            let buffer = (0..64 * 64)
+                .map(|j| {
+                    let x = (j % 64) as f32 - 32.0;
+                    let y = (j / 64) as f32 - 32.0;
+                    ((x / a).powi(2) + (y / b).powi(2) <= 1.0) as u32
+                })
+                .collect();
+
+            ThymeMask::new(64, 64, 1, buffer).unwrap()
+        })
+        .collect();
+
+    (image, masks)
+}
+
+fn bench_regions(c: &mut Criterion) {
+    let (image, masks) = synthetic_objects();
+    let view = image.crop_view(0, 0, 64, 64);
+
+    c.bench_function("regions_separate_200_64x64", |b| {
+        b.iter(|| {
+            for mask in masks.iter() {
+                let mask = mask.crop_view(0, 0, 64, 64);
+                black_box(view.descriptors());
+                black_box(view.masked(&mask, MaskingStyle::Foreground).descriptors());
+                black_box(view.masked(&mask, MaskingStyle::Background).descriptors());
+            }
+        })
+    });
+
+    c.bench_function("regions_single_pass_200_64x64", |b| {
+        b.iter(|| {
+            for mask in masks.iter() {
+                let mask = mask.crop_view(0, 0, 64, 64);
+                black_box(view.descriptors_regions(
+                    &mask,
+                    Regions::from_mode("cfb"),
+                    false,
+                    None,
+                    1,
+                ));
+            }
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_regions
+}
+
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+
+use thyme_core::im::ThymeMask;
+use thyme_core::mp::zernike;
+
+/// Generate 10,000 30x30 binary objects with varying ellipse shapes
+fn synthetic_objects() -> Vec<ThymeMask> {
+    (0..10_000)
+        .map(|i| {
+            let a = 6.0 + (i % 9) as f32;
+            let b = 6.0 + (i % 7) as f32;
+
+            let buffer = (0..30 * 30)
+                .map(|j| {
+                    let x = (j % 30) as f32 - 15.0;
+                    let y = (j / 30) as f32 - 15.0;
+                    ((x / a).powi(2) + (y / b).powi(2) <= 1.0) as u32
+                })
+                .collect();
+
+            ThymeMask::new(30, 30, 1, buffer).unwrap()
+        })
+        .collect()
+}
+
+fn bench_zernike(c: &mut Criterion) {
+    let objects = synthetic_objects();
+
+    c.bench_function("zernike_objects_10k_30x30", |b| {
+        b.iter(|| {
+            for object in objects.iter() {
+                black_box(zernike::objects(&object.crop_view(0, 0, 30, 30)));
+            }
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_zernike
+}
+
+criterion_main!(benches);
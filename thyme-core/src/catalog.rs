@@ -0,0 +1,1267 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use serde::Serialize;
+
+use crate::constant::{self, Feature};
+use crate::error::ThymeError;
+use crate::im::FeatureChannels;
+
+/// A column of an object-level output table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Column {
+    /// Column name as written to the output table
+    pub name: String,
+    /// Data type of the column values (str, u32, f32, bool)
+    pub dtype: &'static str,
+    /// Descriptor family (e.g. form, intensity, texture)
+    pub category: &'static str,
+    /// Unit of measurement, empty for dimensionless or mixed-unit descriptors
+    pub unit: &'static str,
+    /// Pixels the descriptor was computed from (complete, foreground, background, mask, cytoplasm)
+    pub region: Option<&'static str>,
+    /// Image channel of a per-channel descriptor
+    pub channel: Option<usize>,
+    /// Short description of the column
+    pub description: &'static str,
+}
+
+impl Column {
+    fn new(feature: &Feature, dtype: &'static str) -> Self {
+        Self {
+            name: feature.name.to_string(),
+            dtype,
+            category: feature.category,
+            unit: feature.unit,
+            region: None,
+            channel: None,
+            description: feature.description,
+        }
+    }
+
+    /// Power of the pixel length in the unit of the column
+    ///
+    /// Lengths (px) have an exponent of 1, areas (px^2) of 2, and spatial
+    /// frequencies (1/px) of -1. Dimensionless and mixed-unit columns have an
+    /// exponent of 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::catalog::{ProfileColumns, Segments};
+    ///
+    /// let columns = ProfileColumns {
+    ///     segments: Segments::Boxes,
+    ///     mode: "x".to_string(),
+    ///     ..Default::default()
+    /// }
+    /// .columns();
+    ///
+    /// let exponents: Vec<i32> = columns.iter().map(|c| c.length_exponent()).collect();
+    /// assert_eq!(exponents, [0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2]);
+    /// ```
+    pub fn length_exponent(&self) -> i32 {
+        match self.unit {
+            "px" => 1,
+            "px^2" => 2,
+            "1/px" => -1,
+            _ => 0,
+        }
+    }
+
+    fn descriptor(feature: &Feature) -> Self {
+        Self::new(feature, "f32")
+    }
+
+    fn with_region(feature: &Feature, region: &'static str) -> Self {
+        Self {
+            name: format!("{}_{}", region, feature.name),
+            region: Some(region),
+            ..Self::descriptor(feature)
+        }
+    }
+
+    fn with_channel(feature: &Feature, channel: usize) -> Self {
+        Self {
+            name: format!("{}_c{}", feature.name, channel),
+            channel: Some(channel),
+            ..Self::descriptor(feature)
+        }
+    }
+
+    fn with_region_channel(feature: &Feature, region: &'static str, channel: usize) -> Self {
+        Self {
+            name: format!("{}_{}_c{}", region, feature.name, channel),
+            channel: Some(channel),
+            ..Self::with_region(feature, region)
+        }
+    }
+
+    fn with_pair(feature: &Feature, (a, b): (usize, usize)) -> Self {
+        Self {
+            name: format!("{}_c{}_c{}", feature.name, a, b),
+            channel: Some(a),
+            ..Self::descriptor(feature)
+        }
+    }
+
+    fn with_region_scale(feature: &Feature, region: &'static str, scale: u32) -> Self {
+        Self {
+            name: format!("{}_{}_s{}", region, feature.name, scale),
+            ..Self::with_region(feature, region)
+        }
+    }
+}
+
+/// Segments that objects are profiled from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Segments {
+    #[default]
+    Mask,
+    Polygons,
+    Boxes,
+}
+
+impl Segments {
+    /// Parse segments from a string (mask, polygons, boxes)
+    pub fn parse(segments: &str) -> Result<Self, ThymeError> {
+        match segments {
+            "mask" => Ok(Segments::Mask),
+            "polygons" => Ok(Segments::Polygons),
+            "boxes" => Ok(Segments::Boxes),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid segments '{}'. Must be one of: mask, polygons, boxes",
+                segments
+            ))),
+        }
+    }
+
+    /// Profiling mode characters accepted for these segments
+    pub fn modes(&self) -> &'static [char] {
+        match self {
+            Segments::Mask | Segments::Polygons => &['c', 'm', 'b', 'f', 'p', 'x', 's'],
+            Segments::Boxes => &['c', 'x'],
+        }
+    }
+}
+
+/// Options that determine the columns of a profile descriptor table
+///
+/// Options that are not computed for the selected segments are ignored so
+/// that the columns always match the output of the corresponding command.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::catalog::{ProfileColumns, Segments};
+///
+/// let columns = ProfileColumns {
+///     segments: Segments::Mask,
+///     mode: "cm".to_string(),
+///     centroid_channels: Some(3),
+///     ..Default::default()
+/// };
+///
+/// let names = columns.descriptor_names();
+/// assert_eq!(names[0], "bbox_min_x");
+/// assert_eq!(names[6], "complete_intensity_min");
+/// assert_eq!(names.last().unwrap(), "centroid_displacement_c2");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ProfileColumns {
+    /// Segments that objects are profiled from
+    pub segments: Segments,
+    /// Profiling mode (e.g. cfbmp)
+    pub mode: String,
+    /// Include boundary band and interior intensity descriptors
+    pub membrane: bool,
+    /// Include percentile, skewness, and kurtosis descriptors
+    pub extended_intensity: bool,
+    /// Include centroid displacement descriptors for this many channels
+    pub centroid_channels: Option<usize>,
+    /// Include secondary object and primary-minus-secondary intensity descriptors
+    pub secondary: bool,
+    /// Include the texture stride applied to each object
+    pub texture_stride: bool,
+    /// Include the label column of labeled bounding boxes
+    pub labels: bool,
+    /// Include a column flagging objects that touch the image border
+    pub touches_border: bool,
+    /// Include the majority class columns of objects in a class mask
+    pub classes: bool,
+    /// Only include the sum, mean, and std of complete intensity descriptors
+    pub fast_complete: bool,
+    /// Compute c, f, and b descriptor families separately for these channels
+    pub feature_channels: Option<FeatureChannels>,
+    /// Include mean intensity ratio and median intensity difference descriptors of these channel pairs
+    pub channel_pairs: Vec<(usize, usize)>,
+    /// Include foreground texture descriptors of objects downsampled by these factors
+    pub texture_scales: Vec<u32>,
+    /// Include the path to the thumbnail of each object after the descriptors
+    pub thumbnails: bool,
+    /// Include the scale of preview runs after the object column
+    pub preview: bool,
+    /// Naming convention of the descriptor columns
+    pub column_style: ColumnStyle,
+    /// Names of the image channels used by the cellprofiler column style (e.g. DNA)
+    pub channel_names: Vec<String>,
+}
+
+impl ProfileColumns {
+    /// All columns of the descriptor table in output order
+    ///
+    /// Columns joined from a metadata table, which follow the thumbnail path
+    /// column, are not included.
+    pub fn columns(&self) -> Vec<Column> {
+        let mut columns = vec![
+            Column::new(&constant::IMAGE_COLUMN, "str"),
+            Column::new(&constant::OBJECT_COLUMN, "u32"),
+        ];
+
+        if self.preview {
+            columns.push(Column::new(&constant::PREVIEW_SCALE_COLUMN, "f32"));
+        }
+
+        if self.labels && self.segments == Segments::Boxes {
+            columns.push(Column::new(&constant::LABEL_COLUMN, "str"));
+        }
+
+        if self.touches_border {
+            columns.push(Column::new(&constant::TOUCHES_BORDER_COLUMN, "bool"));
+        }
+
+        if self.classes {
+            columns.push(Column::new(&constant::CLASS_ID_COLUMN, "u32"));
+            columns.push(Column::new(&constant::CLASS_FRAC_COLUMN, "f32"));
+        }
+
+        columns.extend(self.descriptors());
+
+        if self.thumbnails {
+            columns.push(Column::new(&constant::THUMBNAIL_PATH_COLUMN, "str"));
+        }
+
+        columns
+    }
+
+    /// Names of the descriptor columns that follow the image and object columns
+    ///
+    /// The object location columns are always included first.
+    pub fn descriptor_names(&self) -> Vec<String> {
+        self.descriptors().into_iter().map(|c| c.name).collect()
+    }
+
+    /// Pixel length exponents of the descriptor columns, in the order of `descriptor_names`
+    pub fn descriptor_exponents(&self) -> Vec<i32> {
+        self.descriptors()
+            .iter()
+            .map(Column::length_exponent)
+            .collect()
+    }
+
+    fn descriptors(&self) -> Vec<Column> {
+        let mut columns: Vec<Column> = constant::LOCATION_DESCRIPTORS
+            .iter()
+            .map(Column::descriptor)
+            .collect();
+
+        let objects = self.segments != Segments::Boxes;
+        let mode = |c: char| self.mode.contains(c);
+
+        if objects && mode('p') {
+            columns.extend(constant::FORM_DESCRIPTORS.iter().map(Column::descriptor));
+        }
+
+        if !objects && mode('x') {
+            columns.extend(constant::BBOX_DESCRIPTORS.iter().map(Column::descriptor));
+        }
+
+        let mut features: Vec<&Feature> = constant::INTENSITY_DESCRIPTORS.iter().collect();
+
+        if self.extended_intensity {
+            features.extend(constant::EXTENDED_INTENSITY_DESCRIPTORS.iter());
+        }
+
+        features.extend(
+            constant::MOMENTS_DESCRIPTORS
+                .iter()
+                .chain(constant::TEXTURE_DESCRIPTORS.iter())
+                .chain(constant::ZERNIKE_DESCRIPTORS.iter()),
+        );
+
+        for (region, computed) in [
+            ("complete", mode('c')),
+            ("foreground", objects && mode('f')),
+            ("background", objects && mode('b')),
+        ] {
+            if !computed {
+                continue;
+            }
+
+            // Fast complete descriptors are read from summed-area tables
+            if region == "complete" && self.fast_complete {
+                columns.extend(
+                    constant::INTENSITY_DESCRIPTORS[2..5]
+                        .iter()
+                        .map(|f| Column::with_region(f, region)),
+                );
+            } else if let Some(channels) = &self.feature_channels {
+                columns.extend(self.channel_columns(channels, region));
+            } else {
+                columns.extend(features.iter().map(|f| Column::with_region(f, region)));
+            }
+        }
+
+        if objects && mode('m') {
+            columns.extend(
+                constant::MOMENTS_DESCRIPTORS
+                    .iter()
+                    .chain(constant::ZERNIKE_DESCRIPTORS.iter())
+                    .map(|f| Column::with_region(f, "mask")),
+            );
+        }
+
+        if objects && mode('s') {
+            columns.extend(
+                constant::SPECTRUM_DESCRIPTORS
+                    .iter()
+                    .map(Column::descriptor),
+            );
+        }
+
+        if objects && self.membrane {
+            columns.extend(
+                constant::MEMBRANE_DESCRIPTORS
+                    .iter()
+                    .map(Column::descriptor),
+            );
+        }
+
+        if let Some(channels) = self.centroid_channels.filter(|_| objects) {
+            columns.extend(
+                (0..channels)
+                    .map(|i| Column::with_channel(&constant::CENTROID_DISPLACEMENT_DESCRIPTOR, i)),
+            );
+        }
+
+        if objects {
+            for feature in constant::CHANNEL_PAIR_DESCRIPTORS.iter() {
+                columns.extend(
+                    self.channel_pairs
+                        .iter()
+                        .map(|&pair| Column::with_pair(feature, pair)),
+                );
+            }
+        }
+
+        if self.segments == Segments::Mask && self.secondary {
+            columns.extend(
+                constant::SECONDARY_DESCRIPTORS
+                    .iter()
+                    .map(Column::descriptor),
+            );
+            columns.extend(
+                constant::INTENSITY_DESCRIPTORS
+                    .iter()
+                    .map(|f| Column::with_region(f, "cytoplasm")),
+            );
+        }
+
+        if objects {
+            for &scale in &self.texture_scales {
+                columns.extend(
+                    constant::TEXTURE_DESCRIPTORS
+                        .iter()
+                        .map(|f| Column::with_region_scale(f, "foreground", scale)),
+                );
+            }
+        }
+
+        if self.texture_stride {
+            columns.push(Column::descriptor(&constant::TEXTURE_STRIDE_DESCRIPTOR));
+        }
+
+        if self.column_style != ColumnStyle::Thyme {
+            for column in columns.iter_mut() {
+                column.name = self.column_style.name(&column.name, &self.channel_names);
+            }
+        }
+
+        columns
+    }
+
+    /// Per-channel columns of a region ordered by family, channel, and descriptor
+    fn channel_columns(&self, channels: &FeatureChannels, region: &'static str) -> Vec<Column> {
+        let mut columns = vec![];
+
+        for (family, selected) in channels.families() {
+            let features: Vec<&Feature> = match family {
+                "intensity" if self.extended_intensity => constant::INTENSITY_DESCRIPTORS
+                    .iter()
+                    .chain(constant::EXTENDED_INTENSITY_DESCRIPTORS.iter())
+                    .collect(),
+                "intensity" => constant::INTENSITY_DESCRIPTORS.iter().collect(),
+                "moments" => constant::MOMENTS_DESCRIPTORS.iter().collect(),
+                "texture" => constant::TEXTURE_DESCRIPTORS.iter().collect(),
+                _ => constant::ZERNIKE_DESCRIPTORS.iter().collect(),
+            };
+
+            for &channel in selected {
+                columns.extend(
+                    features
+                        .iter()
+                        .map(|f| Column::with_region_channel(f, region, channel)),
+                );
+            }
+        }
+
+        columns
+    }
+}
+
+/// All columns of a neural embedding table in output order
+///
+/// # Arguments
+///
+/// * `embedding_dim` - Number of embedding dimensions produced by the model
+pub fn neural_columns(embedding_dim: usize) -> Vec<Column> {
+    let mut columns = vec![
+        Column::new(&constant::IMAGE_COLUMN, "str"),
+        Column::new(&constant::OBJECT_COLUMN, "u32"),
+    ];
+
+    columns.extend(
+        constant::NEURAL_CENTROID_DESCRIPTORS
+            .iter()
+            .map(Column::descriptor),
+    );
+
+    columns.push(Column::new(&constant::EMBED_FALLBACK_COLUMN, "str"));
+    columns.extend(embedding_columns(embedding_dim));
+    columns
+}
+
+/// Embedding columns of a neural embedding table named by dimension index
+///
+/// # Arguments
+///
+/// * `embedding_dim` - Number of embedding dimensions produced by the model
+pub fn embedding_columns(embedding_dim: usize) -> Vec<Column> {
+    (0..embedding_dim)
+        .map(|i| Column {
+            name: i.to_string(),
+            ..Column::descriptor(&constant::EMBEDDING_DESCRIPTOR)
+        })
+        .collect()
+}
+
+/// Naming convention of profile descriptor columns
+///
+/// The cellprofiler style renames descriptors with a CellProfiler equivalent
+/// after its measurement names (e.g. `form_area` to `AreaShape_Area` and
+/// `foreground_intensity_mean` to `Intensity_MeanIntensity_DNA`). Only the
+/// names follow CellProfiler, since values are computed by thyme (e.g.
+/// texture is measured at 64 gray levels and averaged over four directions).
+/// Descriptors without an equivalent keep their name behind the `Thyme_`
+/// prefix, and the image, object, and metadata columns are never renamed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnStyle {
+    #[default]
+    Thyme,
+    CellProfiler,
+}
+
+/// Prefix of descriptors without a CellProfiler equivalent
+pub const CELLPROFILER_PREFIX: &str = "Thyme_";
+
+/// Channel name of descriptors pooled over every channel of a multi-channel image
+pub const CELLPROFILER_POOLED_CHANNEL: &str = "Image";
+
+/// Descriptors renamed regardless of channel (thyme, CellProfiler)
+const CELLPROFILER_OBJECT: [(&str, &str); 24] = [
+    ("bbox_min_x", "AreaShape_BoundingBoxMinimum_X"),
+    ("bbox_min_y", "AreaShape_BoundingBoxMinimum_Y"),
+    ("bbox_max_x", "AreaShape_BoundingBoxMaximum_X"),
+    ("bbox_max_y", "AreaShape_BoundingBoxMaximum_Y"),
+    ("centroid_x", "Location_Center_X"),
+    ("centroid_y", "Location_Center_Y"),
+    ("form_centroid_x", "AreaShape_Center_X"),
+    ("form_centroid_y", "AreaShape_Center_Y"),
+    ("form_area", "AreaShape_Area"),
+    ("form_area_bbox", "AreaShape_BoundingBoxArea"),
+    ("form_area_convex", "AreaShape_ConvexArea"),
+    ("form_perimeter", "AreaShape_Perimeter"),
+    ("form_solidity", "AreaShape_Solidity"),
+    ("form_extent", "AreaShape_Extent"),
+    ("form_form_factor", "AreaShape_FormFactor"),
+    ("form_equivalent_diameter", "AreaShape_EquivalentDiameter"),
+    ("form_eccentricity", "AreaShape_Eccentricity"),
+    ("form_major_axis", "AreaShape_MajorAxisLength"),
+    ("form_minor_axis", "AreaShape_MinorAxisLength"),
+    ("form_minimum_radius", "AreaShape_MinimumRadius"),
+    ("form_maximum_radius", "AreaShape_MaximumRadius"),
+    ("form_mean_radius", "AreaShape_MeanRadius"),
+    ("form_min_feret", "AreaShape_MinFeretDiameter"),
+    ("form_max_feret", "AreaShape_MaxFeretDiameter"),
+];
+
+/// Foreground intensity descriptors and their CellProfiler Intensity measurement
+const CELLPROFILER_INTENSITY: [(&str, &str); 9] = [
+    ("intensity_min", "MinIntensity"),
+    ("intensity_max", "MaxIntensity"),
+    ("intensity_sum", "IntegratedIntensity"),
+    ("intensity_mean", "MeanIntensity"),
+    ("intensity_std", "StdIntensity"),
+    ("intensity_median", "MedianIntensity"),
+    ("intensity_mad", "MADIntensity"),
+    ("intensity_p25", "LowerQuartileIntensity"),
+    ("intensity_p75", "UpperQuartileIntensity"),
+];
+
+/// Foreground texture descriptors and their CellProfiler Texture measurement
+const CELLPROFILER_TEXTURE: [(&str, &str); 13] = [
+    ("texture_energy", "AngularSecondMoment"),
+    ("texture_contrast", "Contrast"),
+    ("texture_correlation", "Correlation"),
+    ("texture_sum_of_squares", "Variance"),
+    (
+        "texture_inverse_difference_moment",
+        "InverseDifferenceMoment",
+    ),
+    ("texture_sum_average", "SumAverage"),
+    ("texture_sum_variance", "SumVariance"),
+    ("texture_sum_entropy", "SumEntropy"),
+    ("texture_entropy", "Entropy"),
+    ("texture_difference_variance", "DifferenceVariance"),
+    ("texture_difference_entropy", "DifferenceEntropy"),
+    ("texture_infocorr1", "InfoMeas1"),
+    ("texture_infocorr2", "InfoMeas2"),
+];
+
+/// Mask moment prefixes and their CellProfiler AreaShape measurement
+const CELLPROFILER_MOMENTS: [(&str, &str); 3] = [
+    ("m", "SpatialMoment"),
+    ("u", "CentralMoment"),
+    ("i", "HuMoment"),
+];
+
+impl ColumnStyle {
+    /// Parse a column style from a string (thyme, cellprofiler)
+    pub fn parse(style: &str) -> Result<Self, ThymeError> {
+        match style {
+            "thyme" => Ok(ColumnStyle::Thyme),
+            "cellprofiler" => Ok(ColumnStyle::CellProfiler),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid column style '{}'. Must be one of: thyme, cellprofiler",
+                style
+            ))),
+        }
+    }
+
+    /// Name of a descriptor column in this style
+    ///
+    /// Channels are named by `channel_names` (e.g. DNA for `_c0`), falling
+    /// back to `C{i}` for channels without a name. Descriptors pooled over
+    /// every channel take the channel name if exactly one is provided and
+    /// `Image` otherwise. Texture names end with the scale (1, or the factor
+    /// of `_s{k}` columns), the direction (always 00 since directions are
+    /// averaged), and the number of gray levels.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Thyme name of a descriptor column (e.g. foreground_intensity_mean_c0)
+    /// * `channel_names` - Names of the image channels
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::catalog::ColumnStyle;
+    ///
+    /// let names = ["DNA".to_string(), "Actin".to_string()];
+    /// let style = ColumnStyle::CellProfiler;
+    ///
+    /// assert_eq!(style.name("form_area", &names), "AreaShape_Area");
+    /// assert_eq!(
+    ///     style.name("foreground_intensity_mean_c1", &names),
+    ///     "Intensity_MeanIntensity_Actin"
+    /// );
+    /// assert_eq!(
+    ///     style.name("foreground_texture_contrast_s3", &names[..1]),
+    ///     "Texture_Contrast_DNA_3_00_64"
+    /// );
+    /// assert_eq!(
+    ///     style.name("complete_intensity_mean", &names),
+    ///     "Thyme_complete_intensity_mean"
+    /// );
+    /// ```
+    pub fn name(&self, name: &str, channel_names: &[String]) -> String {
+        match self {
+            ColumnStyle::Thyme => name.to_string(),
+            ColumnStyle::CellProfiler => cellprofiler_name(name, channel_names)
+                .unwrap_or_else(|| format!("{}{}", CELLPROFILER_PREFIX, name)),
+        }
+    }
+
+    /// Thyme name of a descriptor column named in this style
+    ///
+    /// Returns `None` if the name is not a descriptor column of this style.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of a descriptor column in this style (e.g. Intensity_MeanIntensity_DNA)
+    /// * `channel_names` - Names of the image channels
+    pub fn thyme_name(&self, name: &str, channel_names: &[String]) -> Option<String> {
+        match self {
+            ColumnStyle::Thyme => Some(name.to_string()),
+            ColumnStyle::CellProfiler => match name.strip_prefix(CELLPROFILER_PREFIX) {
+                Some(name) => Some(name.to_string()),
+                None => thyme_name(name, channel_names),
+            },
+        }
+    }
+}
+
+/// Parse comma-separated channel names (e.g. DNA,Actin,Mito)
+///
+/// Names must be unique and non-empty, and cannot be `Image` since it names
+/// descriptors pooled over every channel.
+pub fn parse_channel_names(names: &str) -> Result<Vec<String>, ThymeError> {
+    let names: Vec<String> = names.split(',').map(|n| n.trim().to_string()).collect();
+
+    for (i, name) in names.iter().enumerate() {
+        if name.is_empty() || name == CELLPROFILER_POOLED_CHANNEL || names[..i].contains(name) {
+            return Err(ThymeError::OtherError(format!(
+                "Invalid channel name '{}'. Channel names must be unique, non-empty, and not {}",
+                name, CELLPROFILER_POOLED_CHANNEL
+            )));
+        }
+    }
+
+    Ok(names)
+}
+
+/// Split a trailing `_{prefix}{index}` suffix (e.g. `_c2` or `_s4`) from a name
+fn split_index(name: &str, prefix: char) -> (&str, Option<usize>) {
+    let split = name.rsplit_once('_').and_then(|(rest, suffix)| {
+        let index = suffix.strip_prefix(prefix)?;
+        match index.chars().all(|c| c.is_ascii_digit()) {
+            true => Some((rest, index.parse().ok()?)),
+            false => None,
+        }
+    });
+
+    match split {
+        Some((rest, index)) => (rest, Some(index)),
+        None => (name, None),
+    }
+}
+
+/// CellProfiler name of a channel
+fn cellprofiler_channel(channel: Option<usize>, channel_names: &[String]) -> String {
+    match (channel, channel_names) {
+        (Some(i), names) => names.get(i).cloned().unwrap_or_else(|| format!("C{}", i)),
+        (None, [name]) => name.clone(),
+        (None, _) => CELLPROFILER_POOLED_CHANNEL.to_string(),
+    }
+}
+
+/// Thyme channel suffix of a CellProfiler channel name
+fn thyme_channel(name: &str, channel_names: &[String]) -> Option<String> {
+    if name == CELLPROFILER_POOLED_CHANNEL || channel_names == [name] {
+        return Some(String::new());
+    }
+
+    let index = match channel_names.iter().position(|n| n == name) {
+        Some(index) => index,
+        None => name.strip_prefix('C')?.parse().ok()?,
+    };
+
+    Some(format!("_c{}", index))
+}
+
+/// CellProfiler name of a thyme descriptor, or `None` without an equivalent
+fn cellprofiler_name(name: &str, channel_names: &[String]) -> Option<String> {
+    let lookup = |table: &[(&str, &'static str)], key: &str| {
+        table.iter().find(|(thyme, _)| *thyme == key).map(|e| e.1)
+    };
+
+    if let Some(renamed) = lookup(&CELLPROFILER_OBJECT, name) {
+        return Some(renamed.to_string());
+    }
+
+    if let Some(feature) = name.strip_prefix("mask_") {
+        if let Some(nm) = feature.strip_prefix("zernike_") {
+            let (n, m) = (nm.get(..1)?, nm.get(1..)?);
+            return Some(format!("AreaShape_Zernike_{}_{}", n, m));
+        }
+
+        let moment = feature.strip_prefix("moments_")?;
+        let (kind, index) = moment.split_at_checked(1)?;
+        let measurement = lookup(&CELLPROFILER_MOMENTS, kind)?;
+
+        return Some(match kind {
+            "i" => format!(
+                "AreaShape_{}_{}",
+                measurement,
+                index.parse::<usize>().ok()? - 1
+            ),
+            _ => format!(
+                "AreaShape_{}_{}_{}",
+                measurement,
+                index.get(..1)?,
+                index.get(1..)?
+            ),
+        });
+    }
+
+    let feature = name.strip_prefix("foreground_")?;
+    let (feature, scale) = split_index(feature, 's');
+    let (feature, channel) = split_index(feature, 'c');
+    let channel = cellprofiler_channel(channel, channel_names);
+
+    if let Some(measurement) = lookup(&CELLPROFILER_TEXTURE, feature) {
+        return Some(format!(
+            "Texture_{}_{}_{}_00_{}",
+            measurement,
+            channel,
+            scale.unwrap_or(1),
+            constant::GLCM_LEVELS
+        ));
+    }
+
+    let measurement = lookup(&CELLPROFILER_INTENSITY, feature).filter(|_| scale.is_none())?;
+    Some(format!("Intensity_{}_{}", measurement, channel))
+}
+
+/// Thyme name of a CellProfiler descriptor, or `None` if it is not renamed by thyme
+fn thyme_name(name: &str, channel_names: &[String]) -> Option<String> {
+    let lookup = |table: &[(&'static str, &str)], key: &str| {
+        table.iter().find(|(_, cp)| *cp == key).map(|e| e.0)
+    };
+
+    if let Some(renamed) = lookup(&CELLPROFILER_OBJECT, name) {
+        return Some(renamed.to_string());
+    }
+
+    let (module, rest) = name.split_once('_')?;
+
+    match module {
+        "AreaShape" => {
+            let (measurement, index) = rest.split_once('_')?;
+
+            if measurement == "Zernike" {
+                let (n, m) = index.split_once('_')?;
+                return Some(format!("mask_zernike_{}{}", n, m));
+            }
+
+            let kind = lookup(&CELLPROFILER_MOMENTS, measurement)?;
+
+            match kind {
+                "i" => Some(format!(
+                    "mask_moments_i{}",
+                    index.parse::<usize>().ok()? + 1
+                )),
+                _ => {
+                    let (p, q) = index.split_once('_')?;
+                    Some(format!("mask_moments_{}{}{}", kind, p, q))
+                }
+            }
+        }
+        "Intensity" => {
+            let (measurement, channel) = rest.split_once('_')?;
+            let feature = lookup(&CELLPROFILER_INTENSITY, measurement)?;
+            let channel = thyme_channel(channel, channel_names)?;
+            Some(format!("foreground_{}{}", feature, channel))
+        }
+        "Texture" => {
+            let mut parts = rest.rsplitn(4, '_');
+            let (levels, direction, scale) = (parts.next()?, parts.next()?, parts.next()?);
+            let (measurement, channel) = parts.next()?.split_once('_')?;
+
+            if direction != "00" || levels != constant::GLCM_LEVELS.to_string() {
+                return None;
+            }
+
+            let feature = lookup(&CELLPROFILER_TEXTURE, measurement)?;
+            let channel = thyme_channel(channel, channel_names)?;
+            let scale = match scale {
+                "1" => String::new(),
+                scale => format!("_s{}", scale.parse::<u32>().ok()?),
+            };
+
+            Some(format!("foreground_{}{}{}", feature, channel, scale))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn names(columns: &ProfileColumns) -> Vec<String> {
+        columns.descriptor_names()
+    }
+
+    #[test]
+    fn test_profile_columns_counts() {
+        let suffixes = 7 + 24 + 13 + 30;
+
+        let mask = ProfileColumns {
+            mode: "cfbmp".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(names(&mask).len(), 6 + 29 + 3 * suffixes + 24 + 30);
+
+        let extended = ProfileColumns {
+            mode: "c".to_string(),
+            extended_intensity: true,
+            ..Default::default()
+        };
+
+        assert_eq!(names(&extended).len(), 6 + suffixes + 6);
+        assert_eq!(names(&extended)[13], "complete_intensity_p10");
+
+        let fast = ProfileColumns {
+            segments: Segments::Boxes,
+            mode: "cx".to_string(),
+            fast_complete: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            names(&fast)[9..],
+            [
+                "complete_intensity_sum",
+                "complete_intensity_mean",
+                "complete_intensity_std"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_profile_columns_segments() {
+        let boxes = ProfileColumns {
+            segments: Segments::Boxes,
+            mode: "cxfm".to_string(),
+            membrane: true,
+            texture_stride: true,
+            ..Default::default()
+        };
+
+        let observed = names(&boxes);
+        assert_eq!(observed[4..6], ["centroid_x", "centroid_y"]);
+        assert_eq!(observed[6..9], ["bbox_width", "bbox_height", "bbox_area"]);
+        assert_eq!(observed[9], "complete_intensity_min");
+        assert_eq!(observed.last().unwrap(), "texture_stride");
+        assert_eq!(observed.len(), 6 + 3 + 74 + 1);
+
+        let polygons = ProfileColumns {
+            segments: Segments::Polygons,
+            mode: "x".to_string(),
+            secondary: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            names(&polygons),
+            [
+                "bbox_min_x",
+                "bbox_min_y",
+                "bbox_max_x",
+                "bbox_max_y",
+                "centroid_x",
+                "centroid_y"
+            ]
+        );
+
+        let mask = ProfileColumns {
+            mode: "m".to_string(),
+            secondary: true,
+            ..Default::default()
+        };
+
+        let observed = names(&mask);
+        assert_eq!(observed[60], "secondary_count");
+        assert_eq!(observed.last().unwrap(), "cytoplasm_intensity_mad");
+
+        let spectrum = ProfileColumns {
+            mode: "ms".to_string(),
+            secondary: true,
+            ..Default::default()
+        };
+
+        let observed = names(&spectrum);
+        assert_eq!(
+            observed[60..64],
+            [
+                "spectrum_peak_freq",
+                "spectrum_peak_power",
+                "spectral_entropy",
+                "secondary_count"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_profile_columns_metadata() {
+        let columns = ProfileColumns {
+            mode: "c".to_string(),
+            centroid_channels: Some(2),
+            ..Default::default()
+        }
+        .columns();
+
+        assert_eq!(columns[0].name, "image");
+        assert_eq!(columns[0].dtype, "str");
+        assert_eq!(columns[1].dtype, "u32");
+        assert_eq!(columns[2].name, "bbox_min_x");
+        assert_eq!(columns[2].category, "position");
+        assert_eq!(columns[8].region, Some("complete"));
+        assert_eq!(columns[8].category, "intensity");
+
+        let last = columns.last().unwrap();
+        assert_eq!(last.name, "centroid_displacement_c1");
+        assert_eq!(last.channel, Some(1));
+
+        let labeled = ProfileColumns {
+            segments: Segments::Boxes,
+            mode: "x".to_string(),
+            labels: true,
+            ..Default::default()
+        }
+        .columns();
+
+        assert_eq!(labeled[2].name, "label");
+        assert_eq!(labeled[2].dtype, "str");
+        assert_eq!(labeled.len(), 3 + 6 + 3);
+
+        let flagged = ProfileColumns {
+            segments: Segments::Boxes,
+            mode: "x".to_string(),
+            labels: true,
+            touches_border: true,
+            ..Default::default()
+        }
+        .columns();
+
+        assert_eq!(flagged[3].name, "touches_border");
+        assert_eq!(flagged[3].dtype, "bool");
+        assert_eq!(flagged.len(), 4 + 6 + 3);
+
+        let classified = ProfileColumns {
+            mode: "p".to_string(),
+            touches_border: true,
+            classes: true,
+            ..Default::default()
+        }
+        .columns();
+
+        let names: Vec<&str> = classified.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names[2..6],
+            ["touches_border", "class_id", "class_frac", "bbox_min_x"]
+        );
+        assert_eq!(classified[3].dtype, "u32");
+
+        let thumbnails = ProfileColumns {
+            mode: "p".to_string(),
+            thumbnails: true,
+            ..Default::default()
+        }
+        .columns();
+
+        assert_eq!(thumbnails.len(), 2 + 6 + 29 + 1);
+        assert_eq!(thumbnails.last().unwrap().name, "thumbnail_path");
+        assert_eq!(thumbnails.last().unwrap().dtype, "str");
+
+        let preview = ProfileColumns {
+            mode: "ps".to_string(),
+            touches_border: true,
+            preview: true,
+            ..Default::default()
+        };
+
+        let columns = preview.columns();
+        assert_eq!(columns[2].name, "preview_scale");
+        assert_eq!(columns[3].name, "touches_border");
+
+        // Exponents follow the units of each descriptor
+        let exponents = preview.descriptor_exponents();
+        let names = preview.descriptor_names();
+        let exponent = |name: &str| exponents[names.iter().position(|n| n == name).unwrap()];
+
+        assert_eq!(exponents.len(), names.len());
+        assert_eq!(exponent("bbox_min_x"), 1);
+        assert_eq!(exponent("form_area"), 2);
+        assert_eq!(exponent("form_solidity"), 0);
+        assert_eq!(exponent("form_orientation"), 0);
+        assert_eq!(exponent("spectrum_peak_freq"), -1);
+    }
+
+    #[test]
+    fn test_profile_columns_feature_channels() {
+        let columns = ProfileColumns {
+            mode: "cfm".to_string(),
+            feature_channels: Some(
+                FeatureChannels::parse("intensity:0-2;texture:0,1;moments:0").unwrap(),
+            ),
+            ..Default::default()
+        }
+        .columns();
+
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        let region = 3 * 7 + 24 + 2 * 13;
+
+        assert_eq!(names.len(), 2 + 6 + 2 * region + 24 + 30);
+        assert_eq!(names[8], "complete_intensity_min_c0");
+        assert_eq!(names[15], "complete_intensity_min_c1");
+        assert_eq!(names[29], "complete_moments_m00_c0");
+        assert_eq!(names[8 + region], "foreground_intensity_min_c0");
+        assert_eq!(columns[15].channel, Some(1));
+        assert_eq!(columns[15].region, Some("complete"));
+        assert_eq!(names[8 + 2 * region], "mask_moments_m00");
+    }
+
+    #[test]
+    fn test_profile_columns_texture_scales() {
+        let columns = ProfileColumns {
+            mode: "p".to_string(),
+            texture_scales: vec![1, 2, 4],
+            texture_stride: true,
+            ..Default::default()
+        };
+
+        let observed = names(&columns);
+        let start = observed.len() - 1 - 3 * 13;
+
+        assert_eq!(observed[start], "foreground_texture_energy_s1");
+        assert_eq!(observed[start + 14], "foreground_texture_contrast_s2");
+        assert_eq!(observed[start + 26], "foreground_texture_energy_s4");
+        assert_eq!(observed.last().unwrap(), "texture_stride");
+
+        let boxes = ProfileColumns {
+            segments: Segments::Boxes,
+            mode: "x".to_string(),
+            texture_scales: vec![1, 2, 4],
+            ..Default::default()
+        };
+
+        assert_eq!(names(&boxes).len(), 6 + 3);
+    }
+
+    #[test]
+    fn test_profile_columns_channel_pairs() {
+        let columns = ProfileColumns {
+            mode: "f".to_string(),
+            centroid_channels: Some(2),
+            channel_pairs: vec![(0, 1), (2, 1)],
+            secondary: true,
+            ..Default::default()
+        };
+
+        let observed = columns.columns();
+        let start = observed
+            .iter()
+            .position(|c| c.name == "intensity_mean_ratio_c0_c1")
+            .unwrap();
+
+        assert_eq!(observed[start - 1].name, "centroid_displacement_c1");
+        assert_eq!(
+            observed[start..start + 4]
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<&str>>(),
+            [
+                "intensity_mean_ratio_c0_c1",
+                "intensity_mean_ratio_c2_c1",
+                "intensity_median_diff_c0_c1",
+                "intensity_median_diff_c2_c1"
+            ]
+        );
+        assert_eq!(observed[start + 1].channel, Some(2));
+        assert_eq!(observed[start + 4].name, "secondary_count");
+
+        let boxes = ProfileColumns {
+            segments: Segments::Boxes,
+            mode: "c".to_string(),
+            channel_pairs: vec![(0, 1)],
+            ..Default::default()
+        };
+
+        assert_eq!(names(&boxes).len(), 6 + 7 + 24 + 13 + 30);
+    }
+
+    #[test]
+    fn test_cellprofiler_names() {
+        let style = ColumnStyle::CellProfiler;
+        let names: Vec<String> = ["DNA", "Actin", "Mito"].map(String::from).to_vec();
+        let single = &names[..1];
+
+        for (thyme, cellprofiler, channel_names) in [
+            ("form_area", "AreaShape_Area", &names[..]),
+            ("form_max_feret", "AreaShape_MaxFeretDiameter", &names),
+            ("bbox_min_x", "AreaShape_BoundingBoxMinimum_X", &names),
+            ("centroid_y", "Location_Center_Y", &names),
+            ("mask_zernike_42", "AreaShape_Zernike_4_2", &names),
+            ("mask_moments_u21", "AreaShape_CentralMoment_2_1", &names),
+            ("mask_moments_i1", "AreaShape_HuMoment_0", &names),
+            (
+                "foreground_intensity_mean",
+                "Intensity_MeanIntensity_DNA",
+                single,
+            ),
+            (
+                "foreground_intensity_mean",
+                "Intensity_MeanIntensity_Image",
+                &names,
+            ),
+            (
+                "foreground_intensity_sum_c2",
+                "Intensity_IntegratedIntensity_Mito",
+                &names,
+            ),
+            (
+                "foreground_intensity_p75_c4",
+                "Intensity_UpperQuartileIntensity_C4",
+                &names,
+            ),
+            (
+                "foreground_texture_contrast",
+                "Texture_Contrast_DNA_1_00_64",
+                single,
+            ),
+            (
+                "foreground_texture_infocorr2_c1",
+                "Texture_InfoMeas2_Actin_1_00_64",
+                &names,
+            ),
+            (
+                "foreground_texture_sum_of_squares_s3",
+                "Texture_Variance_Image_3_00_64",
+                &names,
+            ),
+            (
+                "complete_intensity_mean",
+                "Thyme_complete_intensity_mean",
+                &names,
+            ),
+            (
+                "foreground_intensity_p10_c0",
+                "Thyme_foreground_intensity_p10_c0",
+                &names,
+            ),
+            (
+                "foreground_zernike_00",
+                "Thyme_foreground_zernike_00",
+                &names,
+            ),
+            (
+                "intensity_mean_ratio_c0_c1",
+                "Thyme_intensity_mean_ratio_c0_c1",
+                &names,
+            ),
+            ("texture_stride", "Thyme_texture_stride", &names),
+        ] {
+            assert_eq!(style.name(thyme, channel_names), cellprofiler, "{}", thyme);
+            assert_eq!(
+                style.thyme_name(cellprofiler, channel_names).as_deref(),
+                Some(thyme),
+                "{}",
+                cellprofiler
+            );
+        }
+
+        // Names that are not written by thyme are not mapped back
+        for name in [
+            "Intensity_MeanIntensity_GFP",
+            "Texture_Contrast_DNA_3_01_256",
+            "Granularity_1_DNA",
+        ] {
+            assert_eq!(style.thyme_name(name, &names), None, "{}", name);
+        }
+
+        assert_eq!(ColumnStyle::Thyme.name("form_area", &names), "form_area");
+        assert!(ColumnStyle::parse("cp").is_err());
+        assert_eq!(
+            parse_channel_names(" DNA, Actin").unwrap(),
+            ["DNA", "Actin"]
+        );
+
+        for invalid in ["DNA,DNA", "DNA,", "Image"] {
+            assert!(parse_channel_names(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_profile_columns_cellprofiler() {
+        let columns = |style: ColumnStyle| {
+            ProfileColumns {
+                mode: "cfbmp".to_string(),
+                extended_intensity: true,
+                texture_scales: vec![2],
+                column_style: style,
+                channel_names: vec!["DNA".to_string()],
+                ..Default::default()
+            }
+            .columns()
+        };
+
+        let thyme = columns(ColumnStyle::Thyme);
+        let cellprofiler = columns(ColumnStyle::CellProfiler);
+        assert_eq!(thyme.len(), cellprofiler.len());
+
+        // Identifiers are kept and every renamed descriptor is unique
+        assert_eq!(cellprofiler[0].name, "image");
+        assert_eq!(cellprofiler[1].name, "object");
+
+        let unique: std::collections::HashSet<&str> =
+            cellprofiler.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(unique.len(), cellprofiler.len());
+
+        for (a, b) in thyme.iter().zip(&cellprofiler).skip(2) {
+            assert_eq!(
+                ColumnStyle::CellProfiler.thyme_name(&b.name, &["DNA".to_string()]),
+                Some(a.name.clone())
+            );
+            assert_eq!(
+                (a.category, a.region, a.channel),
+                (b.category, b.region, b.channel)
+            );
+        }
+
+        let names: Vec<&str> = cellprofiler.iter().map(|c| c.name.as_str()).collect();
+        assert!(names.contains(&"Intensity_MeanIntensity_DNA"));
+        assert!(names.contains(&"Texture_Contrast_DNA_2_00_64"));
+        assert!(names.contains(&"AreaShape_Zernike_0_0"));
+    }
+
+    #[test]
+    fn test_neural_columns() {
+        let columns = neural_columns(4);
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+
+        assert_eq!(
+            names,
+            [
+                "image",
+                "object",
+                "centroid_x",
+                "centroid_y",
+                "embed_fallback",
+                "0",
+                "1",
+                "2",
+                "3"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segments_parse() {
+        assert_eq!(Segments::parse("boxes").unwrap(), Segments::Boxes);
+        assert!(Segments::parse("points").is_err());
+    }
+}
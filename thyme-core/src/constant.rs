@@ -1,6 +1,17 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use serde::Serialize;
+
+// All currently supported neural network model weights
+pub const SUPPORTED_MODELS: [&str; 5] = [
+    "dino_vit_small",
+    "dino_vit_base",
+    "dinobloom_vit_base",
+    "scdino_vit_small",
+    "subcell_vit_base",
+];
+
 // All currently supported image formats
 pub const SUPPORTED_IMAGE_FORMATS: [&str; 18] = [
     "avif", "bmp", "dds", "hdr", "ico", "jpeg", "jpg", "exr", "png", "pbm", "pgm", "ppm", "qoi",
@@ -10,6 +21,33 @@ pub const SUPPORTED_IMAGE_FORMATS: [&str; 18] = [
 // All currently supported array formats
 pub const SUPPORTED_ARRAY_FORMATS: [&str; 1] = ["json"];
 
+// All currently supported bounding box input formats
+pub const SUPPORTED_BOX_FORMATS: [&str; 2] = ["json", "csv"];
+
+// All currently supported output table formats
+pub const SUPPORTED_TABLE_FORMATS: [&str; 6] = ["csv", "txt", "tsv", "pq", "arrow", "feather"];
+
+// All currently supported output embedding formats (in addition to tables)
+pub const SUPPORTED_EMBEDDING_FORMATS: [&str; 2] = ["npy", "npz"];
+
+// All currently supported arrow IPC compression codecs
+pub const SUPPORTED_IPC_COMPRESSION: [&str; 3] = ["lz4", "zstd", "none"];
+
+// All currently supported notations of floats in csv/tsv tables
+pub const SUPPORTED_FLOAT_FORMATS: [&str; 2] = ["auto", "fixed"];
+
+// All currently supported formats of status lines logged by long-running commands
+pub const SUPPORTED_LOG_FORMATS: [&str; 2] = ["plain", "json"];
+
+// All currently supported layouts of mask files
+pub const SUPPORTED_MASK_FORMATS: [&str; 2] = ["auto", "cellpose-seg"];
+
+// All currently supported encodings of object polygons in parquet tables
+pub const SUPPORTED_GEOMETRY_FORMATS: [&str; 2] = ["list", "wkb"];
+
+// All currently supported containers of extracted object images
+pub const SUPPORTED_OUTPUT_CONTAINERS: [&str; 3] = ["files", "hdf5", "zarr"];
+
 // The currently supported common image formats
 pub const IMAGE_DYNAMIC_FORMATS: [&str; 17] = [
     "avif", "bmp", "dds", "hdr", "ico", "jpeg", "jpg", "exr", "png", "pbm", "pgm", "ppm", "qoi",
@@ -27,6 +65,27 @@ pub const BOUNDING_BOX_JSON_VALID_KEYS: [&str; 7] = [
     "xyxy",
 ];
 
+// The csv column names (lowercase) detected for each bounding box field
+pub const BOUNDING_BOX_CSV_MIN_X_KEYS: [&str; 8] =
+    ["xmin", "x_min", "min_x", "x1", "x0", "left", "bbox_x", "x"];
+pub const BOUNDING_BOX_CSV_MIN_Y_KEYS: [&str; 8] =
+    ["ymin", "y_min", "min_y", "y1", "y0", "top", "bbox_y", "y"];
+pub const BOUNDING_BOX_CSV_MAX_X_KEYS: [&str; 5] = ["xmax", "x_max", "max_x", "x2", "right"];
+pub const BOUNDING_BOX_CSV_MAX_Y_KEYS: [&str; 5] = ["ymax", "y_max", "max_y", "y2", "bottom"];
+pub const BOUNDING_BOX_CSV_WIDTH_KEYS: [&str; 3] = ["width", "w", "bbox_width"];
+pub const BOUNDING_BOX_CSV_HEIGHT_KEYS: [&str; 3] = ["height", "h", "bbox_height"];
+pub const BOUNDING_BOX_CSV_LABEL_KEYS: [&str; 5] =
+    ["label", "class", "class_name", "category", "category_id"];
+pub const BOUNDING_BOX_CSV_IMAGE_KEYS: [&str; 7] = [
+    "image",
+    "image_name",
+    "image_id",
+    "filename",
+    "file_name",
+    "file",
+    "path",
+];
+
 // The valid json keys indicating polygon values
 pub const POLYGON_JSON_VALID_KEYS: [&str; 5] =
     ["polygons", "contours", "outlines", "shapes", "points"];
@@ -39,116 +98,771 @@ pub const FACTORIAL: [f32; 10] = [
 // Hard default settings for the gray-level co-occurence matrix calculations
 pub const GLCM_LEVELS: usize = 64;
 pub const GLCM_ARRAY_SIZE: usize = GLCM_LEVELS * GLCM_LEVELS;
+pub const GLCM_ANGLES: [f32; 4] = [0.0, 45.0, 90.0, 135.0];
+
+/// A single descriptor in the feature catalog
+///
+/// Descriptors computed over several regions of an object (e.g. complete or
+/// foreground pixels) are prefixed by the region in output tables and
+/// per-channel descriptors are suffixed by the channel index (e.g. `_c0`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Feature {
+    /// Column name before any region prefix or channel suffix
+    pub name: &'static str,
+    /// Descriptor family (e.g. form, intensity, texture)
+    pub category: &'static str,
+    /// Unit of measurement, empty for dimensionless or mixed-unit descriptors
+    pub unit: &'static str,
+    /// Computed separately for each image channel
+    pub per_channel: bool,
+    /// Short description of the descriptor
+    pub description: &'static str,
+}
+
+const fn feature(
+    name: &'static str,
+    category: &'static str,
+    unit: &'static str,
+    description: &'static str,
+) -> Feature {
+    Feature {
+        name,
+        category,
+        unit,
+        per_channel: false,
+        description,
+    }
+}
+
+// Identifier columns shared by all object-level output tables
+pub const IMAGE_COLUMN: Feature = feature("image", "identifier", "", "Image file name.");
+pub const OBJECT_COLUMN: Feature = feature(
+    "object",
+    "identifier",
+    "",
+    "Index of the object in the segments.",
+);
+pub const LABEL_COLUMN: Feature = feature(
+    "label",
+    "identifier",
+    "",
+    "Label (e.g. class) of the object provided with labeled bounding boxes.",
+);
+pub const TOUCHES_BORDER_COLUMN: Feature = feature(
+    "touches_border",
+    "identifier",
+    "",
+    "Whether the unpadded bounding box of the object touches the edge of the image.",
+);
+pub const CLASS_ID_COLUMN: Feature = feature(
+    "class_id",
+    "identifier",
+    "",
+    "Majority class id over the object pixels in the class mask (lowest id on ties).",
+);
+pub const CLASS_FRAC_COLUMN: Feature = feature(
+    "class_frac",
+    "identifier",
+    "",
+    "Fraction of the object pixels assigned to the majority class id.",
+);
+pub const PREVIEW_SCALE_COLUMN: Feature = feature(
+    "preview_scale",
+    "identifier",
+    "",
+    "Factor the image and segments were resized by in a preview run. Descriptors of preview runs are not full-resolution descriptors.",
+);
+pub const THUMBNAIL_PATH_COLUMN: Feature = feature(
+    "thumbnail_path",
+    "identifier",
+    "",
+    "Path to the letterboxed thumbnail of the object, empty if the crop was degenerate.",
+);
+
+// Object location columns leading every profile descriptor table
+pub const LOCATION_DESCRIPTORS: [Feature; 6] = [
+    feature(
+        "bbox_min_x",
+        "position",
+        "px",
+        "Unpadded bounding box minimum x coordinate.",
+    ),
+    feature(
+        "bbox_min_y",
+        "position",
+        "px",
+        "Unpadded bounding box minimum y coordinate.",
+    ),
+    feature(
+        "bbox_max_x",
+        "position",
+        "px",
+        "Unpadded bounding box maximum x coordinate.",
+    ),
+    feature(
+        "bbox_max_y",
+        "position",
+        "px",
+        "Unpadded bounding box maximum y coordinate.",
+    ),
+    feature(
+        "centroid_x",
+        "position",
+        "px",
+        "Polygon centroid, mask center of mass, or bounding box center x coordinate.",
+    ),
+    feature(
+        "centroid_y",
+        "position",
+        "px",
+        "Polygon centroid, mask center of mass, or bounding box center y coordinate.",
+    ),
+];
+
+// Morphological descriptors computed from object polygons
+pub const FORM_DESCRIPTORS: [Feature; 29] = [
+    feature(
+        "form_centroid_x",
+        "form",
+        "px",
+        "Polygon centroid x coordinate.",
+    ),
+    feature(
+        "form_centroid_y",
+        "form",
+        "px",
+        "Polygon centroid y coordinate.",
+    ),
+    feature(
+        "form_center_x",
+        "form",
+        "px",
+        "Mean x coordinate of polygon vertices.",
+    ),
+    feature(
+        "form_center_y",
+        "form",
+        "px",
+        "Mean y coordinate of polygon vertices.",
+    ),
+    feature("form_area", "form", "px^2", "Polygon area."),
+    feature(
+        "form_area_bbox",
+        "form",
+        "px^2",
+        "Area of the axis-aligned bounding box.",
+    ),
+    feature(
+        "form_area_convex",
+        "form",
+        "px^2",
+        "Area of the convex hull.",
+    ),
+    feature("form_perimeter", "form", "px", "Polygon perimeter."),
+    feature(
+        "form_elongation",
+        "form",
+        "",
+        "Ratio of the shorter to longer bounding box side.",
+    ),
+    feature(
+        "form_thread_length",
+        "form",
+        "px",
+        "Length of a thread with the same area and perimeter.",
+    ),
+    feature(
+        "form_thread_width",
+        "form",
+        "px",
+        "Width of a thread with the same area and perimeter.",
+    ),
+    feature(
+        "form_solidity",
+        "form",
+        "",
+        "Ratio of area to convex hull area.",
+    ),
+    feature(
+        "form_extent",
+        "form",
+        "",
+        "Ratio of area to bounding box area.",
+    ),
+    feature(
+        "form_form_factor",
+        "form",
+        "",
+        "Circularity computed as 4 pi area / perimeter^2.",
+    ),
+    feature(
+        "form_equivalent_diameter",
+        "form",
+        "px",
+        "Diameter of a circle with the same area.",
+    ),
+    feature(
+        "form_eccentricity",
+        "form",
+        "",
+        "Eccentricity of the fitted ellipse.",
+    ),
+    feature(
+        "form_major_axis",
+        "form",
+        "px",
+        "Major axis length of the fitted ellipse.",
+    ),
+    feature(
+        "form_minor_axis",
+        "form",
+        "px",
+        "Minor axis length of the fitted ellipse.",
+    ),
+    feature(
+        "form_minimum_radius",
+        "form",
+        "px",
+        "Minimum distance from the centroid to the polygon edges.",
+    ),
+    feature(
+        "form_maximum_radius",
+        "form",
+        "px",
+        "Maximum distance from the centroid to the polygon vertices.",
+    ),
+    feature(
+        "form_mean_radius",
+        "form",
+        "px",
+        "Mean distance from the centroid to the polygon vertices.",
+    ),
+    feature("form_min_feret", "form", "px", "Minimum caliper diameter."),
+    feature("form_max_feret", "form", "px", "Maximum caliper diameter."),
+    feature(
+        "form_min_feret_angle",
+        "form",
+        "rad",
+        "Angle of the direction the minimum caliper diameter is measured along in [0, pi).",
+    ),
+    feature(
+        "form_max_feret_angle",
+        "form",
+        "rad",
+        "Angle of the line joining the two most distant polygon vertices in [0, pi).",
+    ),
+    feature(
+        "form_min_rect_width",
+        "form",
+        "px",
+        "Longer side of the minimum-area rotated bounding rectangle.",
+    ),
+    feature(
+        "form_min_rect_height",
+        "form",
+        "px",
+        "Shorter side of the minimum-area rotated bounding rectangle.",
+    ),
+    feature(
+        "form_min_rect_angle",
+        "form",
+        "rad",
+        "Angle of the longer side of the minimum-area rotated bounding rectangle in [0, pi).",
+    ),
+    feature(
+        "form_orientation",
+        "form",
+        "rad",
+        "Angle of the fitted ellipse major axis from the x-axis towards the y-axis in [0, pi).",
+    ),
+];
+
+// Bounding box descriptors computed from object bounding boxes
+pub const BBOX_DESCRIPTORS: [Feature; 3] = [
+    feature("bbox_width", "bbox", "px", "Bounding box width."),
+    feature("bbox_height", "bbox", "px", "Bounding box height."),
+    feature("bbox_area", "bbox", "px^2", "Bounding box area."),
+];
 
-// Names for morphological descriptors
-pub const FORM_DESCRIPTOR_NAMES: [&str; 23] = [
-    "form_centroid_x",
-    "form_centroid_y",
-    "form_center_x",
-    "form_center_y",
-    "form_area",
-    "form_area_bbox",
-    "form_area_convex",
-    "form_perimeter",
-    "form_elongation",
-    "form_thread_length",
-    "form_thread_width",
-    "form_solidity",
-    "form_extent",
-    "form_form_factor",
-    "form_equivalent_diameter",
-    "form_eccentricity",
-    "form_major_axis",
-    "form_minor_axis",
-    "form_minimum_radius",
-    "form_maximum_radius",
-    "form_mean_radius",
-    "form_min_feret",
-    "form_max_feret",
-];
-
-pub const INTENSITY_DESCRIPTOR_NAMES: [&str; 7] = [
-    "intensity_min",
-    "intensity_max",
-    "intensity_sum",
-    "intensity_mean",
-    "intensity_std",
-    "intensity_median",
-    "intensity_mad",
-];
-
-pub const MOMENTS_DESCRIPTOR_NAMES: [&str; 24] = [
-    "moments_m00",
-    "moments_m10",
-    "moments_m01",
-    "moments_m11",
-    "moments_m20",
-    "moments_m02",
-    "moments_m21",
-    "moments_m12",
-    "moments_m30",
-    "moments_m03",
-    "moments_u11",
-    "moments_u20",
-    "moments_u02",
-    "moments_u21",
-    "moments_u12",
-    "moments_u30",
-    "moments_u03",
-    "moments_i1",
-    "moments_i2",
-    "moments_i3",
-    "moments_i4",
-    "moments_i5",
-    "moments_i6",
-    "moments_i7",
-];
-
-pub const TEXTURE_DESCRIPTOR_NAMES: [&str; 13] = [
-    "texture_energy",
-    "texture_contrast",
-    "texture_correlation",
-    "texture_sum_of_squares",
-    "texture_inverse_difference_moment",
-    "texture_sum_average",
-    "texture_sum_variance",
-    "texture_sum_entropy",
-    "texture_entropy",
-    "texture_difference_variance",
-    "texture_difference_entropy",
-    "texture_infocorr1",
-    "texture_infocorr2",
-];
-
-pub const ZERNIKE_DESCRIPTOR_NAMES: [&str; 30] = [
-    "zernike_00",
-    "zernike_11",
-    "zernike_20",
-    "zernike_22",
-    "zernike_31",
-    "zernike_33",
-    "zernike_40",
-    "zernike_42",
-    "zernike_44",
-    "zernike_51",
-    "zernike_53",
-    "zernike_55",
-    "zernike_60",
-    "zernike_62",
-    "zernike_64",
-    "zernike_66",
-    "zernike_71",
-    "zernike_73",
-    "zernike_75",
-    "zernike_77",
-    "zernike_80",
-    "zernike_82",
-    "zernike_84",
-    "zernike_86",
-    "zernike_88",
-    "zernike_91",
-    "zernike_93",
-    "zernike_95",
-    "zernike_97",
-    "zernike_99",
+// Intensity descriptors averaged over channels
+pub const INTENSITY_DESCRIPTORS: [Feature; 7] = [
+    feature(
+        "intensity_min",
+        "intensity",
+        "intensity",
+        "Minimum pixel intensity.",
+    ),
+    feature(
+        "intensity_max",
+        "intensity",
+        "intensity",
+        "Maximum pixel intensity.",
+    ),
+    feature(
+        "intensity_sum",
+        "intensity",
+        "intensity",
+        "Sum of pixel intensities.",
+    ),
+    feature(
+        "intensity_mean",
+        "intensity",
+        "intensity",
+        "Mean pixel intensity.",
+    ),
+    feature(
+        "intensity_std",
+        "intensity",
+        "intensity",
+        "Standard deviation of pixel intensities.",
+    ),
+    feature(
+        "intensity_median",
+        "intensity",
+        "intensity",
+        "Median pixel intensity.",
+    ),
+    feature(
+        "intensity_mad",
+        "intensity",
+        "intensity",
+        "Median absolute deviation of pixel intensities.",
+    ),
 ];
+
+pub const EXTENDED_INTENSITY_DESCRIPTORS: [Feature; 6] = [
+    feature(
+        "intensity_p10",
+        "intensity",
+        "intensity",
+        "10th percentile intensity.",
+    ),
+    feature(
+        "intensity_p25",
+        "intensity",
+        "intensity",
+        "25th percentile intensity.",
+    ),
+    feature(
+        "intensity_p75",
+        "intensity",
+        "intensity",
+        "75th percentile intensity.",
+    ),
+    feature(
+        "intensity_p90",
+        "intensity",
+        "intensity",
+        "90th percentile intensity.",
+    ),
+    feature(
+        "intensity_skew",
+        "intensity",
+        "",
+        "Skewness of pixel intensities.",
+    ),
+    feature(
+        "intensity_kurtosis",
+        "intensity",
+        "",
+        "Excess kurtosis of pixel intensities.",
+    ),
+];
+
+pub const MEMBRANE_DESCRIPTORS: [Feature; 5] = [
+    feature(
+        "membrane_boundary_mean",
+        "membrane",
+        "intensity",
+        "Mean intensity of the boundary band.",
+    ),
+    feature(
+        "membrane_boundary_median",
+        "membrane",
+        "intensity",
+        "Median intensity of the boundary band.",
+    ),
+    feature(
+        "membrane_interior_mean",
+        "membrane",
+        "intensity",
+        "Mean intensity of the interior.",
+    ),
+    feature(
+        "membrane_interior_median",
+        "membrane",
+        "intensity",
+        "Median intensity of the interior.",
+    ),
+    feature(
+        "membrane_ratio",
+        "membrane",
+        "",
+        "Ratio of boundary band to interior mean intensity.",
+    ),
+];
+
+pub const SPECTRUM_DESCRIPTORS: [Feature; 3] = [
+    feature(
+        "spectrum_peak_freq",
+        "spectrum",
+        "1/px",
+        "Spatial frequency of the radially averaged power spectrum peak.",
+    ),
+    feature(
+        "spectrum_peak_power",
+        "spectrum",
+        "",
+        "Fraction of spectral power in the peak frequency bin.",
+    ),
+    feature(
+        "spectral_entropy",
+        "spectrum",
+        "",
+        "Normalized entropy of the radially averaged power spectrum.",
+    ),
+];
+
+pub const SECONDARY_DESCRIPTORS: [Feature; 3] = [
+    feature(
+        "secondary_count",
+        "secondary",
+        "count",
+        "Number of secondary objects contained in the object.",
+    ),
+    feature(
+        "secondary_area",
+        "secondary",
+        "px^2",
+        "Total area of the contained secondary objects.",
+    ),
+    feature(
+        "secondary_area_ratio",
+        "secondary",
+        "",
+        "Ratio of secondary area to object area.",
+    ),
+];
+
+// Offset between the mask centroid and intensity-weighted centroid of each channel
+pub const CENTROID_DISPLACEMENT_DESCRIPTOR: Feature = Feature {
+    name: "centroid_displacement",
+    category: "centroid",
+    unit: "",
+    per_channel: true,
+    description: "Distance between the mask and intensity-weighted centroids normalized by equivalent diameter.",
+};
+
+// Intensity comparisons between the channels of an ordered channel pair
+pub const CHANNEL_PAIR_DESCRIPTORS: [Feature; 2] = [
+    feature(
+        "intensity_mean_ratio",
+        "intensity",
+        "",
+        "Ratio of the mean intensity of the first channel to the second channel, zero if the second channel mean is zero.",
+    ),
+    feature(
+        "intensity_median_diff",
+        "intensity",
+        "intensity",
+        "Median intensity of the first channel minus the second channel.",
+    ),
+];
+
+// Texture stride applied to each object when written by verbose runs
+pub const TEXTURE_STRIDE_DESCRIPTOR: Feature = feature(
+    "texture_stride",
+    "texture",
+    "px",
+    "Stride of texture co-occurrence anchors applied to the object.",
+);
+
+pub const MOMENTS_DESCRIPTORS: [Feature; 24] = [
+    feature("moments_m00", "moments", "", "Raw moment m00."),
+    feature("moments_m10", "moments", "", "Raw moment m10."),
+    feature("moments_m01", "moments", "", "Raw moment m01."),
+    feature("moments_m11", "moments", "", "Raw moment m11."),
+    feature("moments_m20", "moments", "", "Raw moment m20."),
+    feature("moments_m02", "moments", "", "Raw moment m02."),
+    feature("moments_m21", "moments", "", "Raw moment m21."),
+    feature("moments_m12", "moments", "", "Raw moment m12."),
+    feature("moments_m30", "moments", "", "Raw moment m30."),
+    feature("moments_m03", "moments", "", "Raw moment m03."),
+    feature("moments_u11", "moments", "", "Central moment u11."),
+    feature("moments_u20", "moments", "", "Central moment u20."),
+    feature("moments_u02", "moments", "", "Central moment u02."),
+    feature("moments_u21", "moments", "", "Central moment u21."),
+    feature("moments_u12", "moments", "", "Central moment u12."),
+    feature("moments_u30", "moments", "", "Central moment u30."),
+    feature("moments_u03", "moments", "", "Central moment u03."),
+    feature("moments_i1", "moments", "", "Hu invariant moment 1."),
+    feature("moments_i2", "moments", "", "Hu invariant moment 2."),
+    feature("moments_i3", "moments", "", "Hu invariant moment 3."),
+    feature("moments_i4", "moments", "", "Hu invariant moment 4."),
+    feature("moments_i5", "moments", "", "Hu invariant moment 5."),
+    feature("moments_i6", "moments", "", "Hu invariant moment 6."),
+    feature("moments_i7", "moments", "", "Hu invariant moment 7."),
+];
+
+// Haralick descriptors of the gray-level co-occurrence matrix
+pub const TEXTURE_DESCRIPTORS: [Feature; 13] = [
+    feature("texture_energy", "texture", "", "Angular second moment."),
+    feature("texture_contrast", "texture", "", "Contrast."),
+    feature("texture_correlation", "texture", "", "Correlation."),
+    feature(
+        "texture_sum_of_squares",
+        "texture",
+        "",
+        "Sum of squares variance.",
+    ),
+    feature(
+        "texture_inverse_difference_moment",
+        "texture",
+        "",
+        "Inverse difference moment (homogeneity).",
+    ),
+    feature("texture_sum_average", "texture", "", "Sum average."),
+    feature("texture_sum_variance", "texture", "", "Sum variance."),
+    feature("texture_sum_entropy", "texture", "", "Sum entropy."),
+    feature("texture_entropy", "texture", "", "Entropy."),
+    feature(
+        "texture_difference_variance",
+        "texture",
+        "",
+        "Difference variance.",
+    ),
+    feature(
+        "texture_difference_entropy",
+        "texture",
+        "",
+        "Difference entropy.",
+    ),
+    feature(
+        "texture_infocorr1",
+        "texture",
+        "",
+        "Information measure of correlation 1.",
+    ),
+    feature(
+        "texture_infocorr2",
+        "texture",
+        "",
+        "Information measure of correlation 2.",
+    ),
+];
+
+// Zernike moment magnitudes indexed by order and repetition
+pub const ZERNIKE_DESCRIPTORS: [Feature; 30] = [
+    feature(
+        "zernike_00",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=0, m=0).",
+    ),
+    feature(
+        "zernike_11",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=1, m=1).",
+    ),
+    feature(
+        "zernike_20",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=2, m=0).",
+    ),
+    feature(
+        "zernike_22",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=2, m=2).",
+    ),
+    feature(
+        "zernike_31",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=3, m=1).",
+    ),
+    feature(
+        "zernike_33",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=3, m=3).",
+    ),
+    feature(
+        "zernike_40",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=4, m=0).",
+    ),
+    feature(
+        "zernike_42",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=4, m=2).",
+    ),
+    feature(
+        "zernike_44",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=4, m=4).",
+    ),
+    feature(
+        "zernike_51",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=5, m=1).",
+    ),
+    feature(
+        "zernike_53",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=5, m=3).",
+    ),
+    feature(
+        "zernike_55",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=5, m=5).",
+    ),
+    feature(
+        "zernike_60",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=6, m=0).",
+    ),
+    feature(
+        "zernike_62",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=6, m=2).",
+    ),
+    feature(
+        "zernike_64",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=6, m=4).",
+    ),
+    feature(
+        "zernike_66",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=6, m=6).",
+    ),
+    feature(
+        "zernike_71",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=7, m=1).",
+    ),
+    feature(
+        "zernike_73",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=7, m=3).",
+    ),
+    feature(
+        "zernike_75",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=7, m=5).",
+    ),
+    feature(
+        "zernike_77",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=7, m=7).",
+    ),
+    feature(
+        "zernike_80",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=8, m=0).",
+    ),
+    feature(
+        "zernike_82",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=8, m=2).",
+    ),
+    feature(
+        "zernike_84",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=8, m=4).",
+    ),
+    feature(
+        "zernike_86",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=8, m=6).",
+    ),
+    feature(
+        "zernike_88",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=8, m=8).",
+    ),
+    feature(
+        "zernike_91",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=9, m=1).",
+    ),
+    feature(
+        "zernike_93",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=9, m=3).",
+    ),
+    feature(
+        "zernike_95",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=9, m=5).",
+    ),
+    feature(
+        "zernike_97",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=9, m=7).",
+    ),
+    feature(
+        "zernike_99",
+        "zernike",
+        "",
+        "Zernike moment magnitude (n=9, m=9).",
+    ),
+];
+
+// Object centroid columns written by the neural commands
+pub const NEURAL_CENTROID_DESCRIPTORS: [Feature; 2] = [
+    feature(
+        "centroid_x",
+        "position",
+        "px",
+        "Bounding box center x coordinate.",
+    ),
+    feature(
+        "centroid_y",
+        "position",
+        "px",
+        "Bounding box center y coordinate.",
+    ),
+];
+
+// Diagnostic column written by the neural commands
+pub const EMBED_FALLBACK_COLUMN: Feature = feature(
+    "embed_fallback",
+    "diagnostic",
+    "",
+    "Fallback used to embed the object after running out of device memory (none, retry, downscale, or cpu).",
+);
+
+// Embedding columns written by the neural commands (named by index)
+pub const EMBEDDING_DESCRIPTOR: Feature = feature(
+    "embedding",
+    "embedding",
+    "",
+    "Self-supervised model embedding dimension.",
+);
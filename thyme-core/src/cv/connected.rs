@@ -52,6 +52,9 @@ impl UnionFind {
 
 /// Two-pass 8-connected component labeling on mask buffers
 ///
+/// Buffers of any pixel type (e.g. u8 or u32 masks) are labeled without
+/// conversion, where pixels equal to the default value are background.
+///
 /// # Arguments
 ///
 /// * `width` - Width of mask
@@ -74,8 +77,17 @@ impl UnionFind {
 /// let buffer_two: Vec<u32> = vec![10, 10, 10, 0, 0, 0, 20, 20, 20];
 /// let labels_two = connected_components(width, height, &buffer_two);
 /// assert_eq!(labels_two, [1, 1, 1, 0, 0, 0, 2, 2, 2]);
+///
+/// let buffer_u8: Vec<u8> = vec![255, 255, 255, 0, 0, 0, 255, 255, 255];
+/// assert_eq!(connected_components(width, height, &buffer_u8), labels_two);
 /// ```
-pub fn connected_components(width: u32, height: u32, buffer: &[u32]) -> Vec<u32> {
+pub fn connected_components<T: PartialOrd + Copy + Default>(
+    width: u32,
+    height: u32,
+    buffer: &[T],
+) -> Vec<u32> {
+    // Background pixels are the default (zero) value of the data type
+    let zero = T::default();
     let width = width as usize;
     let height = height as usize;
     let size = width * height;
@@ -88,7 +100,7 @@ pub fn connected_components(width: u32, height: u32, buffer: &[u32]) -> Vec<u32>
     for y in 0..height {
         for x in 0..width {
             let idx = y * width + x;
-            if buffer[idx] == 0 {
+            if buffer[idx] == zero {
                 // Ignore background pixels
                 continue;
             }
@@ -96,22 +108,22 @@ pub fn connected_components(width: u32, height: u32, buffer: &[u32]) -> Vec<u32>
             let mut neighbors = vec![];
 
             // Check left neighbor
-            if x > 0 && buffer[idx - 1] > 0 {
+            if x > 0 && buffer[idx - 1] > zero {
                 neighbors.push(labels[idx - 1]);
             }
 
             // Check top neighbor
-            if y > 0 && buffer[idx - width] > 0 {
+            if y > 0 && buffer[idx - width] > zero {
                 neighbors.push(labels[idx - width]);
             }
 
             // Check top-left neighbor (diagonal)
-            if x > 0 && y > 0 && buffer[idx - width - 1] > 0 {
+            if x > 0 && y > 0 && buffer[idx - width - 1] > zero {
                 neighbors.push(labels[idx - width - 1]);
             }
 
             // Check top-right neighbor (diagonal)
-            if x < width - 1 && y > 0 && buffer[idx - width + 1] > 0 {
+            if x < width - 1 && y > 0 && buffer[idx - width + 1] > zero {
                 neighbors.push(labels[idx - width + 1]);
             }
 
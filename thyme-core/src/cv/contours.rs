@@ -4,8 +4,13 @@
 use std::cmp::Ordering;
 use std::collections::VecDeque;
 
+use crate::cv::points::{point_in_polygon, signed_area};
+
 /// Find contours using 8-connectivity
 ///
+/// Buffers of any ordered pixel type (e.g. u8, u32, or f32 masks) are traced
+/// without converting their pixels.
+///
 /// # Arguments
 ///
 /// * `width` - Width of mask
@@ -29,15 +34,70 @@ use std::collections::VecDeque;
 /// let buffer_one: Vec<u32> = vec![12, 12, 0, 12, 12, 0, 0, 0, 0];
 /// let contours_one = find_contours(width, height, &buffer_one, &0, Greater);
 ///
-/// assert_eq!(contours_one, [[[0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0]]])
+/// assert_eq!(contours_one, [[[0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0]]]);
+///
+/// let buffer_u8: Vec<u8> = vec![255, 255, 0, 255, 255, 0, 0, 0, 0];
+/// assert_eq!(find_contours(width, height, &buffer_u8, &0, Greater), contours_one);
 /// ```
-pub fn find_contours(
+pub fn find_contours<T: PartialOrd + Copy>(
     width: u32,
     height: u32,
-    pixels: &[u32],
-    threshold: &u32,
+    pixels: &[T],
+    threshold: &T,
     order: Ordering,
 ) -> Vec<Vec<[f32; 2]>> {
+    find_contours_with_hierarchy(width, height, pixels, threshold, order)
+        .into_iter()
+        .filter(|contour| contour.border_type() == &BorderType::Outer)
+        .map(|contour| contour.into_points())
+        .collect()
+}
+
+/// Find outer and hole contours along with their parent relationships
+///
+/// # Arguments
+///
+/// * `width` - Width of mask
+/// * `height` - Height of mask
+/// * `buffer` - A row-major mask buffer
+/// * `threshold` - Threshold value for foreground/background pixels
+/// * `order` - An ordering specifying how a pixel will be compared to threshold
+///
+/// # Notes
+///
+/// The parent of each contour is an index into the returned contours. Outer
+/// contours of objects inside a hole have the hole as parent, and holes have
+/// the outer contour of the object they are cut from as parent. Top-level
+/// outer contours have no parent.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering::Greater;
+/// use thyme_core::cv::contours::{BorderType, find_contours_with_hierarchy};
+///
+/// let buffer: Vec<u32> = vec![
+///     1, 1, 1, 1, 1,
+///     1, 0, 0, 0, 1,
+///     1, 0, 1, 0, 1,
+///     1, 0, 0, 0, 1,
+///     1, 1, 1, 1, 1,
+/// ];
+///
+/// let contours = find_contours_with_hierarchy(5, 5, &buffer, &0, Greater);
+///
+/// assert_eq!(contours.len(), 3);
+/// assert_eq!(contours[1].border_type(), &BorderType::Hole);
+/// assert_eq!(contours[1].parent(), Some(0));
+/// assert_eq!(contours[2].parent(), Some(1));
+/// ```
+pub fn find_contours_with_hierarchy<T: PartialOrd + Copy>(
+    width: u32,
+    height: u32,
+    pixels: &[T],
+    threshold: &T,
+    order: Ordering,
+) -> Vec<Contour> {
     let width = width as usize;
     let height = height as usize;
     let padded_width = width + 2;
@@ -50,7 +110,10 @@ pub fn find_contours(
     for y in 0..height {
         for x in 0..width {
             let pixel = pixels[y * width + x];
-            image_values[at(x + 1, y + 1)] = if pixel.cmp(threshold) == order { 1 } else { 0 };
+            image_values[at(x + 1, y + 1)] = match pixel.partial_cmp(threshold) == Some(order) {
+                true => 1,
+                false => 0,
+            };
         }
     }
 
@@ -67,9 +130,11 @@ pub fn find_contours(
 
     let mut contours: Vec<Contour> = Vec::new();
     let mut curr_border_num = 1;
-    let mut parent_border_num = 1;
+    let mut parent_border_num;
 
     for y in 1..=height {
+        parent_border_num = 1;
+
         for x in 1..=width {
             if image_values[at(x, y)] == 0 {
                 continue;
@@ -89,6 +154,10 @@ pub fn find_contours(
                 }
                 (false, (x as i32 + 1, y as i32))
             } else {
+                // Track the last border encountered along the row
+                if image_values[at(x, y)] != 1 {
+                    parent_border_num = image_values[at(x, y)].unsigned_abs() as usize;
+                }
                 continue;
             };
 
@@ -191,14 +260,14 @@ pub fn find_contours(
             }
 
             contours.push(Contour::new(contour_points, border_type, parent));
+
+            if image_values[at(x, y)] != 1 {
+                parent_border_num = image_values[at(x, y)].unsigned_abs() as usize;
+            }
         }
     }
 
     contours
-        .into_iter()
-        .filter(|contour| contour.border_type() == &BorderType::Outer)
-        .map(|contour| contour.into_points())
-        .collect()
 }
 
 ///  Contour for storiing outlines of segmented objects
@@ -269,12 +338,12 @@ fn rotate_to_value(values: &mut VecDeque<[i32; 2]>, value: [i32; 2]) {
 ///
 /// assert_eq!(contours, [[[2.0, 1.0], [1.0, 2.0], [2.0, 2.0]], [[0.0, 0.0], [0.0, 1.0], [1.0, 0.0]]]);
 /// ```
-pub fn find_labeled_contours(
+pub fn find_labeled_contours<T: PartialOrd + Copy>(
     width: u32,
     height: u32,
-    pixels: &[u32],
-    labels: &Vec<u32>,
-) -> (Vec<u32>, Vec<Vec<[f32; 2]>>) {
+    pixels: &[T],
+    labels: &[T],
+) -> (Vec<T>, Vec<Vec<[f32; 2]>>) {
     let mut contours = Vec::with_capacity(labels.len());
     let mut retained = Vec::with_capacity(labels.len());
 
@@ -294,6 +363,89 @@ pub fn find_labeled_contours(
     (retained, contours)
 }
 
+/// Find contours for each labeled object along with the label enclosing it
+///
+/// # Arguments
+///
+/// * `width` - Width of mask
+/// * `height` - Height of mask
+/// * `buffer` - A row-major mask buffer
+/// * `labels` - Positive non-zero integers specifying unique segmented objects
+///
+/// # Notes
+///
+/// Contours and retained labels are identical to `find_labeled_contours`. An
+/// object is a child of another object if it lies within one of its holes,
+/// whether the hole is background or filled by other labels, and the innermost
+/// enclosing object is returned as parent.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::contours::find_labeled_contours_with_hierarchy;
+///
+/// let buffer: Vec<u32> = vec![
+///     1, 1, 1, 1,
+///     1, 2, 2, 1,
+///     1, 2, 2, 1,
+///     1, 1, 1, 1,
+/// ];
+///
+/// let (labels, _, parents) = find_labeled_contours_with_hierarchy(4, 4, &buffer, &[1, 2]);
+///
+/// assert_eq!(labels, vec![1, 2]);
+/// assert_eq!(parents, vec![None, Some(1)]);
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn find_labeled_contours_with_hierarchy<T: PartialOrd + Copy>(
+    width: u32,
+    height: u32,
+    pixels: &[T],
+    labels: &[T],
+) -> (Vec<T>, Vec<Vec<[f32; 2]>>, Vec<Option<T>>) {
+    let mut contours = Vec::with_capacity(labels.len());
+    let mut retained = Vec::with_capacity(labels.len());
+    let mut holes: Vec<(T, f32, Vec<[f32; 2]>)> = Vec::new();
+
+    for label in labels {
+        let mut outer = Vec::new();
+
+        for contour in find_contours_with_hierarchy(width, height, pixels, label, Ordering::Equal) {
+            match contour.border_type() {
+                BorderType::Outer => outer.push(contour.into_points()),
+                BorderType::Hole => {
+                    let area = signed_area(contour.as_points()).abs();
+                    holes.push((*label, area, contour.into_points()));
+                }
+            }
+        }
+
+        if let Some(contour) = outer.into_iter().max_by_key(|contour| contour.len()) {
+            if contour.len() > 2 {
+                contours.push(contour);
+                retained.push(*label)
+            }
+        }
+    }
+
+    // Nested holes are visited from the innermost outwards
+    holes.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+
+    let parents = retained
+        .iter()
+        .zip(contours.iter())
+        .map(|(label, contour)| {
+            let [x, y] = contour[0];
+            holes
+                .iter()
+                .find(|(parent, _, hole)| parent != label && point_in_polygon(x, y, hole))
+                .map(|(parent, _, _)| *parent)
+        })
+        .collect();
+
+    (retained, contours, parents)
+}
+
 #[cfg(test)]
 mod test {
 
@@ -386,6 +538,41 @@ mod test {
         (10, 10, buffer)
     }
 
+    /// A blob with two holes, each containing a smaller blob, where the first
+    /// smaller blob has a hole containing a third blob
+    fn nested_blobs() -> (u32, u32, Vec<u32>) {
+        let (width, height) = (40usize, 24usize);
+        let mut buffer = vec![0u32; width * height];
+
+        let mut fill = |x0: usize, x1: usize, y0: usize, y1: usize, value: u32| {
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    buffer[y * width + x] = value;
+                }
+            }
+        };
+
+        fill(1, 38, 1, 22, 1);
+        fill(3, 19, 3, 20, 0);
+        fill(5, 17, 5, 18, 1);
+        fill(7, 15, 7, 16, 0);
+        fill(10, 12, 10, 13, 1);
+        fill(22, 36, 3, 20, 0);
+        fill(25, 33, 6, 17, 1);
+
+        (width as u32, height as u32, buffer)
+    }
+
+    fn depth(parents: &[Option<usize>], mut idx: usize) -> usize {
+        let mut depth = 0;
+        while let Some(parent) = parents[idx] {
+            idx = parent;
+            depth += 1;
+        }
+
+        depth
+    }
+
     #[test]
     fn test_four_regions_small() {
         let (w, h, buffer) = four_regions_small();
@@ -496,7 +683,7 @@ mod test {
 
         assert_eq!(contours.len(), 1);
 
-        let (_, contours) = find_labeled_contours(w, h, &buffer, &vec![1u32, 2u32]);
+        let (_, contours) = find_labeled_contours(w, h, &buffer, &[1u32, 2u32]);
 
         let p0 = &contours[0];
         let p1 = &contours[1];
@@ -565,4 +752,81 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn test_find_contours_with_hierarchy() {
+        let (width, height, buffer) = nested_blobs();
+        let contours = find_contours_with_hierarchy(width, height, &buffer, &0, Ordering::Greater);
+
+        let outer: Vec<usize> = (0..contours.len())
+            .filter(|&i| contours[i].border_type() == &BorderType::Outer)
+            .collect();
+
+        assert_eq!(outer.len(), 4);
+        assert_eq!(contours.len(), 7);
+
+        let parents: Vec<Option<usize>> = contours.iter().map(|c| c.parent()).collect();
+
+        let mut depths: Vec<usize> = outer.iter().map(|&i| depth(&parents, i)).collect();
+        depths.sort();
+        assert_eq!(depths, vec![0, 2, 2, 4]);
+
+        for contour in contours.iter() {
+            if let Some(parent) = contour.parent() {
+                assert_ne!(contour.border_type(), contours[parent].border_type());
+            }
+        }
+
+        let plain = find_contours(width, height, &buffer, &0, Ordering::Greater);
+        assert_eq!(plain.len(), outer.len());
+    }
+
+    #[test]
+    fn test_find_labeled_contours_with_hierarchy() {
+        let (width, height, mut buffer) = nested_blobs();
+
+        // Relabel the blobs from the outermost to innermost as 1, 2, 3 and 4
+        let labels = crate::cv::connected_components(width, height, &buffer);
+        for (pixel, label) in buffer.iter_mut().zip(labels) {
+            *pixel = label;
+        }
+
+        let mut unique: Vec<u32> = buffer.iter().filter(|&&x| x != 0).cloned().collect();
+        unique.sort();
+        unique.dedup();
+
+        let (retained, contours, parents) =
+            find_labeled_contours_with_hierarchy(width, height, &buffer, &unique);
+
+        let (plain_labels, plain_contours) = find_labeled_contours(width, height, &buffer, &unique);
+
+        assert_eq!(retained, plain_labels);
+        assert_eq!(contours, plain_contours);
+
+        let parents: Vec<Option<usize>> = parents
+            .iter()
+            .map(|parent| parent.map(|p| retained.iter().position(|&l| l == p).unwrap()))
+            .collect();
+
+        let mut depths: Vec<usize> = (0..retained.len()).map(|i| depth(&parents, i)).collect();
+        depths.sort();
+        assert_eq!(depths, vec![0, 1, 1, 2]);
+
+        // Labels filling a hole entirely are still children of the enclosing label
+        #[rustfmt::skip]
+        let buffer: Vec<u32> = vec![
+            1, 1, 1, 1, 1, 1,
+            1, 2, 2, 2, 2, 1,
+            1, 2, 3, 3, 2, 1,
+            1, 2, 3, 3, 2, 1,
+            1, 2, 2, 2, 2, 1,
+            1, 1, 1, 1, 1, 1,
+        ];
+
+        let (retained, _, parents) =
+            find_labeled_contours_with_hierarchy(6, 6, &buffer, &[1, 2, 3]);
+
+        assert_eq!(retained, vec![1, 2, 3]);
+        assert_eq!(parents, vec![None, Some(1), Some(2)]);
+    }
 }
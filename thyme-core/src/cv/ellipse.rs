@@ -7,6 +7,13 @@ use crate::cv::points::resample_points;
 
 /// Fit a best fitting ellipse to a set of points and extract elliptic parameters
 ///
+/// Returns the major axis length, minor axis length, eccentricity, and the
+/// orientation of the major axis in radians. Orientation is measured from the
+/// positive x-axis towards the positive y-axis and lies in [0, pi). If the
+/// least-squares system is rank deficient or does not describe an ellipse of
+/// plausible size (e.g. small or noisy contours), the parameters are instead
+/// estimated from second central moments with `fit_ellipse_moments`.
+///
 /// # Arguments
 ///
 /// * `points` - A set of ordered and deduplicated points
@@ -17,6 +24,7 @@ use crate::cv::points::resample_points;
 /// use thyme_core::cv::ellipse::fit_ellipse_lstsq;
 /// let points = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]];
 /// let params = fit_ellipse_lstsq(&points);
+/// assert!(params.iter().all(|p| p.is_finite()));
 /// ```
 #[inline]
 pub fn fit_ellipse_lstsq(points: &[[f32; 2]]) -> [f32; 4] {
@@ -41,23 +49,26 @@ pub fn fit_ellipse_lstsq(points: &[[f32; 2]]) -> [f32; 4] {
         .fold((0.0, 0.0), |(cx, cy), p| (cx + p[0], cy + p[1]));
 
     let n = (points.len() - 1) as f32;
-    let points: Vec<[f32; 2]> = points
+    let centered: Vec<[f32; 2]> = points
         .iter()
         .map(|p| [p[0] - cx / n, p[1] - cy / n])
         .collect();
 
     let design: MatrixXx5<f32> = MatrixXx5::from_columns(&[
-        DVector::from_iterator(points.len(), points.iter().map(|p| p[0] * p[0])),
-        DVector::from_iterator(points.len(), points.iter().map(|p| p[0] * p[1])),
-        DVector::from_iterator(points.len(), points.iter().map(|p| p[1] * p[1])),
-        DVector::from_iterator(points.len(), points.iter().map(|p| p[0])),
-        DVector::from_iterator(points.len(), points.iter().map(|p| p[1])),
+        DVector::from_iterator(centered.len(), centered.iter().map(|p| p[0] * p[0])),
+        DVector::from_iterator(centered.len(), centered.iter().map(|p| p[0] * p[1])),
+        DVector::from_iterator(centered.len(), centered.iter().map(|p| p[1] * p[1])),
+        DVector::from_iterator(centered.len(), centered.iter().map(|p| p[0])),
+        DVector::from_iterator(centered.len(), centered.iter().map(|p| p[1])),
     ]);
 
-    let y = DVector::from_iterator(points.len(), points.iter().map(|_| 1.0_f32));
+    let y = DVector::from_iterator(centered.len(), centered.iter().map(|_| 1.0_f32));
 
     let epsilon = 1e-8;
-    let results = lstsq::lstsq(&design, &y, epsilon).unwrap();
+    let results = match lstsq::lstsq(&design, &y, epsilon) {
+        Ok(results) if results.rank == 5 => results,
+        _ => return fit_ellipse_moments(&points),
+    };
 
     let a: f32 = results.solution[0];
     let b: f32 = results.solution[1] / 2.0;
@@ -66,6 +77,11 @@ pub fn fit_ellipse_lstsq(points: &[[f32; 2]]) -> [f32; 4] {
     let f: f32 = results.solution[4] / 2.0;
     let g: f32 = -1.0;
 
+    // Only a positive definite quadratic form describes an ellipse around the center
+    if a <= 0.0 || c <= 0.0 || a * c - b * b <= 0.0 {
+        return fit_ellipse_moments(&points);
+    }
+
     let denominator = b * b - a * c;
     let numerator = 2.0 * (a * f * f + c * d * d + g * b * b - 2.0 * b * d * f - a * c * g);
     let factor = ((a - c) * (a - c) + 4.0 * b * b).sqrt();
@@ -73,43 +89,196 @@ pub fn fit_ellipse_lstsq(points: &[[f32; 2]]) -> [f32; 4] {
     let mut axis_length_major = (numerator / denominator / (factor - a - c)).sqrt();
     let mut axis_length_minor = (numerator / denominator / (-factor - a - c)).sqrt();
 
-    let mut width_gt_height = true;
     if axis_length_major < axis_length_minor {
-        width_gt_height = false;
         std::mem::swap(&mut axis_length_major, &mut axis_length_minor);
     }
 
+    // A fitted ellipse much larger than the contour is an unstable fit
+    let diagonal = bounding_diagonal(&points);
+
+    if !axis_length_major.is_finite()
+        || !axis_length_minor.is_finite()
+        || axis_length_major > diagonal
+    {
+        return fit_ellipse_moments(&points);
+    }
+
     let mut r = (axis_length_minor / axis_length_major).powf(2.0);
     r = if r > 1.0 { 1.0 / r } else { r };
     let eccentricity = (1.0 - r).sqrt();
 
-    let mut phi = if b == 0.0 {
-        if a < c {
-            0.0
-        } else {
-            std::f32::consts::PI / 2.0
-        }
+    // The major axis is perpendicular to the eigenvector of the largest eigenvalue
+    let orientation = orientation(a, b, c) + std::f32::consts::FRAC_PI_2;
+
+    [
+        axis_length_major * 2.0,
+        axis_length_minor * 2.0,
+        eccentricity,
+        orientation.rem_euclid(std::f32::consts::PI),
+    ]
+}
+
+/// Estimate elliptic parameters from the second central moments of a polygon
+///
+/// Returns the major axis length, minor axis length, eccentricity, and the
+/// orientation of the major axis in radians of the ellipse with the same
+/// second central moments as the polygon. Moments of the vertices are used
+/// for polygons enclosing no area (e.g. collinear points). Orientation is
+/// measured from the positive x-axis towards the positive y-axis and lies in
+/// [0, pi).
+///
+/// # Arguments
+///
+/// * `points` - A set of ordered and deduplicated points
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::ellipse::fit_ellipse_moments;
+/// let points = [[-2.0, -1.0], [2.0, -1.0], [2.0, 1.0], [-2.0, 1.0]];
+/// let params = fit_ellipse_moments(&points);
+/// assert!(params[0] > params[1]);
+/// assert!(params[3].abs() < 1e-6);
+/// ```
+pub fn fit_ellipse_moments(points: &[[f32; 2]]) -> [f32; 4] {
+    let n = points.len();
+
+    let (mut m00, mut m10, mut m01) = (0.0f64, 0.0f64, 0.0f64);
+    let (mut m20, mut m02, mut m11) = (0.0f64, 0.0f64, 0.0f64);
+
+    for i in 0..n {
+        let [x0, y0] = points[i].map(|v| v as f64);
+        let [x1, y1] = points[(i + 1) % n].map(|v| v as f64);
+
+        let cross = x0 * y1 - x1 * y0;
+
+        m00 += cross;
+        m10 += (x0 + x1) * cross;
+        m01 += (y0 + y1) * cross;
+        m20 += (x0 * x0 + x0 * x1 + x1 * x1) * cross;
+        m02 += (y0 * y0 + y0 * y1 + y1 * y1) * cross;
+        m11 += (x0 * y1 + 2.0 * x0 * y0 + 2.0 * x1 * y1 + x1 * y0) * cross;
+    }
+
+    let (mu20, mu02, mu11) = if m00.abs() > 1e-6 {
+        let m00 = m00 / 2.0;
+        let (cx, cy) = (m10 / 6.0 / m00, m01 / 6.0 / m00);
+
+        (
+            m20 / 12.0 / m00 - cx * cx,
+            m02 / 12.0 / m00 - cy * cy,
+            m11 / 24.0 / m00 - cx * cy,
+        )
     } else {
-        let mut inner = ((2.0 * b) / (a - c)).atan() / 2.0;
-        inner += if a > c {
-            std::f32::consts::PI / 2.0
-        } else {
-            0.0
-        };
-        inner
+        let k = n as f64;
+        let cx = points.iter().map(|p| p[0] as f64).sum::<f64>() / k;
+        let cy = points.iter().map(|p| p[1] as f64).sum::<f64>() / k;
+
+        points.iter().fold((0.0, 0.0, 0.0), |(xx, yy, xy), p| {
+            let (dx, dy) = (p[0] as f64 - cx, p[1] as f64 - cy);
+            (xx + dx * dx / k, yy + dy * dy / k, xy + dx * dy / k)
+        })
     };
 
-    phi += if !width_gt_height {
-        std::f32::consts::PI / 2.0
+    let mean = (mu20 + mu02) / 2.0;
+    let spread = (((mu20 - mu02) / 2.0).powi(2) + mu11 * mu11).sqrt();
+    let major = (mean + spread).max(0.0);
+    let minor = (mean - spread).max(0.0);
+
+    let eccentricity = if major > 0.0 {
+        (1.0 - minor / major).sqrt()
     } else {
         0.0
     };
-    phi %= std::f32::consts::PI;
+
+    let orientation = orientation(mu20 as f32, mu11 as f32, mu02 as f32);
 
     [
-        axis_length_major * 2.0,
-        axis_length_minor * 2.0,
-        eccentricity,
-        phi,
+        4.0 * major.sqrt() as f32,
+        4.0 * minor.sqrt() as f32,
+        eccentricity as f32,
+        orientation.rem_euclid(std::f32::consts::PI),
     ]
 }
+
+/// Angle of the eigenvector with the largest eigenvalue of [[a, b], [b, c]]
+fn orientation(a: f32, b: f32, c: f32) -> f32 {
+    0.5 * (2.0 * b).atan2(a - c)
+}
+
+/// Length of the diagonal of the bounding box of a set of points
+fn bounding_diagonal(points: &[[f32; 2]]) -> f32 {
+    let (min, max) = points.iter().fold(
+        ([f32::MAX, f32::MAX], [f32::MIN, f32::MIN]),
+        |(min, max), p| {
+            (
+                [min[0].min(p[0]), min[1].min(p[1])],
+                [max[0].max(p[0]), max[1].max(p[1])],
+            )
+        },
+    );
+
+    ((max[0] - min[0]).powi(2) + (max[1] - min[1]).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ellipse(a: f32, b: f32, angle: f32, n: usize) -> Vec<[f32; 2]> {
+        (0..n)
+            .map(|i| {
+                let t = 2.0 * std::f32::consts::PI * i as f32 / n as f32;
+                let (x, y) = (a * t.cos(), b * t.sin());
+                [
+                    5.0 + x * angle.cos() - y * angle.sin(),
+                    -3.0 + x * angle.sin() + y * angle.cos(),
+                ]
+            })
+            .collect()
+    }
+
+    fn angle_error(observed: f32, expected: f32) -> f32 {
+        let diff = (observed - expected).rem_euclid(std::f32::consts::PI);
+        diff.min(std::f32::consts::PI - diff).to_degrees()
+    }
+
+    #[test]
+    fn test_fit_ellipse_orientation() {
+        for degrees in [0.0f32, 30.0, 75.0, 90.0, 120.0, 165.0] {
+            let angle = degrees.to_radians();
+            let points = ellipse(10.0, 4.0, angle, 180);
+
+            let [major, minor, eccentricity, orientation] = fit_ellipse_lstsq(&points);
+            assert!(angle_error(orientation, angle) < 1.0, "{}", degrees);
+            assert!((major - 20.0).abs() < 0.1, "{}", major);
+            assert!((minor - 8.0).abs() < 0.1, "{}", minor);
+            assert!((eccentricity - (1.0f32 - 0.16).sqrt()).abs() < 1e-3);
+
+            let [major, minor, _, orientation] = fit_ellipse_moments(&points);
+            assert!(angle_error(orientation, angle) < 1.0, "{}", degrees);
+            assert!((major - 20.0).abs() < 0.2, "{}", major);
+            assert!((minor - 8.0).abs() < 0.2, "{}", minor);
+        }
+    }
+
+    #[test]
+    fn test_fit_ellipse_degenerate() {
+        let contours = [
+            vec![[0.0, 0.0], [10.0, 0.01], [20.0, 0.0], [10.0, -0.01]],
+            vec![[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]],
+            vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+        ];
+
+        for points in contours {
+            let params = fit_ellipse_lstsq(&points);
+            assert!(params.iter().all(|p| p.is_finite()), "{:?}", params);
+            assert!(params[0] >= params[1]);
+            assert!((0.0..std::f32::consts::PI).contains(&params[3]));
+        }
+
+        // Collinear points are oriented along their line
+        let diagonal = fit_ellipse_lstsq(&[[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]);
+        assert!(angle_error(diagonal[3], std::f32::consts::FRAC_PI_4) < 1.0);
+    }
+}
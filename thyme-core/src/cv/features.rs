@@ -5,9 +5,175 @@ use std::ops::Deref;
 
 use num::{FromPrimitive, ToPrimitive};
 
-use crate::constant::{GLCM_ARRAY_SIZE, GLCM_LEVELS};
+use crate::constant::{GLCM_ANGLES, GLCM_ARRAY_SIZE, GLCM_LEVELS};
+use crate::error::ThymeError;
 use crate::im::ThymeViewBuffer;
 
+/// Conventions used to build and aggregate gray-level co-occurence matrices
+///
+/// The default preset rescales each channel from its minimum to its maximum
+/// over `GLCM_LEVELS` gray levels and pairs pixels at a distance of one in four
+/// directions. The pyradiomics preset discretizes gray levels with a fixed bin
+/// width anchored at a multiple of the bin width below the minimum (levels
+/// beyond `GLCM_LEVELS` are merged into the last level), and pairs pixels at
+/// every offset of chebyshev distance 1 to 3 (4, 8, and 12 directions).
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::features::TexturePreset;
+///
+/// assert_eq!(TexturePreset::parse("default").unwrap(), TexturePreset::Default);
+/// assert_eq!(
+///     TexturePreset::parse("pyradiomics").unwrap(),
+///     TexturePreset::Pyradiomics { bin_width: 25.0 }
+/// );
+/// assert_eq!(
+///     TexturePreset::parse("pyradiomics:10").unwrap(),
+///     TexturePreset::Pyradiomics { bin_width: 10.0 }
+/// );
+/// assert!(TexturePreset::parse("pyradiomics:0").is_err());
+/// assert!(TexturePreset::parse("haralick").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TexturePreset {
+    /// Min-max rescaled gray levels paired at a distance of one
+    #[default]
+    Default,
+    /// Fixed bin width gray levels paired at distances 1 to 3, following pyradiomics
+    Pyradiomics { bin_width: f32 },
+}
+
+impl TexturePreset {
+    /// Default bin width of the pyradiomics preset (binWidth in pyradiomics)
+    pub const PYRADIOMICS_BIN_WIDTH: f32 = 25.0;
+
+    /// Largest distance of the pyradiomics preset
+    pub const PYRADIOMICS_MAX_DISTANCE: i32 = 3;
+
+    /// Parse a texture preset from a string (default, pyradiomics, pyradiomics:<bin_width>)
+    ///
+    /// # Arguments
+    ///
+    /// * `preset` - String representation of the texture preset
+    pub fn parse(preset: &str) -> Result<TexturePreset, ThymeError> {
+        let invalid = || {
+            ThymeError::OtherError(format!(
+                "Invalid texture preset '{}'. Must be default, pyradiomics, or pyradiomics:<bin_width> with a positive bin width",
+                preset
+            ))
+        };
+
+        let (name, bin_width) = match preset.split_once(':') {
+            Some((name, bin_width)) => (name.trim(), Some(bin_width.trim())),
+            None => (preset.trim(), None),
+        };
+
+        match (name, bin_width) {
+            ("default", None) => Ok(TexturePreset::Default),
+            ("pyradiomics", None) => Ok(TexturePreset::Pyradiomics {
+                bin_width: Self::PYRADIOMICS_BIN_WIDTH,
+            }),
+            ("pyradiomics", Some(bin_width)) => match bin_width.parse::<f32>() {
+                Ok(bin_width) if bin_width.is_finite() && bin_width > 0.0 => {
+                    Ok(TexturePreset::Pyradiomics { bin_width })
+                }
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Column and row offsets (dx, dy) of the co-occurence matrices averaged into descriptors
+    ///
+    /// Mirrored offsets are omitted as matrices are symmetric.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::cv::features::TexturePreset;
+    ///
+    /// assert_eq!(TexturePreset::Default.offsets(), [[1, 0], [1, 1], [0, 1], [-1, 1]]);
+    /// assert_eq!(TexturePreset::Pyradiomics { bin_width: 25.0 }.offsets().len(), 24);
+    /// ```
+    pub fn offsets(&self) -> Vec<[i32; 2]> {
+        match self {
+            TexturePreset::Default => GLCM_ANGLES
+                .iter()
+                .map(|&angle| angle_offset(angle, 1.0))
+                .collect(),
+            TexturePreset::Pyradiomics { .. } => {
+                let d = Self::PYRADIOMICS_MAX_DISTANCE;
+                let mut offsets = Vec::new();
+
+                for distance in 1..=d {
+                    for dy in 0..=distance {
+                        for dx in -distance..=distance {
+                            // Keep one offset of each mirrored pair on the ring of this distance
+                            let ring = dx.abs().max(dy) == distance;
+                            if ring && (dy > 0 || dx > 0) {
+                                offsets.push([dx, dy]);
+                            }
+                        }
+                    }
+                }
+
+                offsets
+            }
+        }
+    }
+
+    /// Map pixel values to gray-level indices of a co-occurence matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - Pixel values of a single channel
+    fn discretize(&self, pixels: &[f32]) -> Vec<usize> {
+        let (min_val, max_val) = pixels.iter().fold((f32::MAX, f32::MIN), |(min, max), &p| {
+            (min.min(p), max.max(p))
+        });
+
+        match *self {
+            TexturePreset::Default => {
+                let (sa, sb, sc) = if max_val != GLCM_LEVELS as f32 - 1.0 || min_val != 0.0 {
+                    (min_val, max_val, GLCM_LEVELS as f32 - 1.0)
+                } else if min_val == max_val {
+                    // Homogeneous images are set to zero
+                    (0.0, 1.0, 0.0)
+                } else {
+                    (0.0, 1.0, 1.0)
+                };
+
+                pixels
+                    .iter()
+                    .map(|&pixel| ((pixel - sa) / (sb - sa) * sc).round() as usize)
+                    .collect()
+            }
+            TexturePreset::Pyradiomics { bin_width } => {
+                // Bins start at the multiple of the bin width below the minimum
+                let low = min_val - min_val.rem_euclid(bin_width);
+
+                pixels
+                    .iter()
+                    .map(|&pixel| {
+                        (((pixel - low) / bin_width).floor() as usize).min(GLCM_LEVELS - 1)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Round the neighbour offset of an angle (in degrees) and distance to whole pixels
+fn angle_offset(angle: f32, distance: f32) -> [i32; 2] {
+    let radians = angle.to_radians();
+
+    [
+        (radians.cos() * distance).round() as i32,
+        (radians.sin() * distance).round() as i32,
+    ]
+}
+
 #[derive(Debug, Clone)]
 pub struct GLCM {
     data: [f32; GLCM_ARRAY_SIZE],
@@ -47,75 +213,58 @@ impl GLCM {
     where
         T: ToPrimitive,
     {
-        let radians = angle.to_radians();
-
-        let mut min_val = f32::MAX;
-        let mut max_val = f32::MIN;
+        GLCM::new_with_preset(
+            pixels,
+            width,
+            height,
+            channel,
+            channels,
+            angle_offset(angle, distance),
+            TexturePreset::Default,
+        )
+    }
 
+    /// Create a new normalized gray-level co-occurence matrix following a texture preset
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - A row-major raw pixel buffer
+    /// * `width` - Width of image
+    /// * `height` - Height of image
+    /// * `channel` - Which channel to compute the comatrix
+    /// * `channels` - Number of channels in image
+    /// * `offset` - Column and row offset (dx, dy) from each pixel to its neighbour
+    /// * `preset` - Gray-level discretization of the pixels
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::cv::features::{GLCM, TexturePreset};
+    /// let buffer: Vec<f32> = vec![1.0, 2.0, 2.0, 1.0];
+    /// let preset = TexturePreset::Pyradiomics { bin_width: 1.0 };
+    /// let comatrix = GLCM::new_with_preset(&buffer, 2, 2, 0, 1, [1, 0], preset);
+    /// assert_eq!(comatrix.as_matrix()[0][1], 0.5);
+    /// ```
+    pub fn new_with_preset<T>(
+        pixels: &[T],
+        width: usize,
+        height: usize,
+        channel: usize,
+        channels: usize,
+        offset: [i32; 2],
+        preset: TexturePreset,
+    ) -> GLCM
+    where
+        T: ToPrimitive,
+    {
         let pixel_vec: Vec<f32> = pixels
             .iter()
             .skip(channel)
             .step_by(channels)
-            .map(|p| {
-                let value = p.to_f32().unwrap();
-                min_val = min_val.min(value);
-                max_val = max_val.max(value);
-                value
-            })
+            .map(|p| p.to_f32().unwrap())
             .collect();
 
-        let (sa, sb, sc) = if max_val != GLCM_LEVELS as f32 - 1.0 || min_val != 0.0 {
-            (min_val, max_val, GLCM_LEVELS as f32 - 1.0)
-        } else if min_val == max_val {
-            // Homogeneous images are set to zero
-            (0.0, 1.0, 0.0)
-        } else {
-            (0.0, 1.0, 1.0)
-        };
-
-        let (w, h) = (width as i32, height as i32);
-        let offset_x = (radians.cos() * distance).round() as i32;
-        let offset_y = (radians.sin() * distance).round() as i32;
-
-        let mut comatrix = [0.0; GLCM_ARRAY_SIZE];
-
-        let scale_pixel =
-            |pixel: f32| -> usize { ((pixel - sa) / (sb - sa) * sc).round() as usize };
-
-        let mut comatrix_sum = 0f32;
-
-        for y in 0..h {
-            for x in 0..w {
-                let idx = (y * w + x) as usize;
-                let root = pixel_vec[idx];
-
-                let i_offset = x + offset_x;
-                let j_offset = y + offset_y;
-
-                if i_offset >= w || i_offset < 0 || j_offset >= h || j_offset < 0 {
-                    continue;
-                }
-
-                let neighbour_idx = (j_offset * w + i_offset) as usize;
-                let neighbour = pixel_vec[neighbour_idx];
-
-                let root_scaled = scale_pixel(root);
-                let neighbour_scaled = scale_pixel(neighbour);
-
-                comatrix[root_scaled * GLCM_LEVELS + neighbour_scaled] += 1.0;
-                comatrix[neighbour_scaled * GLCM_LEVELS + root_scaled] += 1.0;
-
-                comatrix_sum += 2.0;
-            }
-        }
-
-        comatrix.iter_mut().for_each(|v| *v /= comatrix_sum);
-
-        GLCM {
-            data: comatrix,
-            rows: GLCM_LEVELS,
-            cols: GLCM_LEVELS,
-        }
+        GLCM::from_pixels(&pixel_vec, width, height, offset, 1, preset)
     }
 
     /// Create a new normalized gray-level co-occurence matrix from aa ThymeObjectBuffer
@@ -126,65 +275,85 @@ impl GLCM {
     /// * `channel` - Which channel to compute the comatrix
     /// * `angle` - Angle (in degrees) for computing neighbour co-occurence
     /// * `distance` - Number of pixels to neighbouring pixels
-    ///
-    /// # Note
-    ///
-    /// This is pretty redunant with the default constructor. We could possibly
-    /// just accept a Vec<f32> instead and perform the other operations in the
-    /// glcm_multichannel function. This would avoid the need for object specific
-    /// functions.
+    /// * `stride` - Only accumulate pairs anchored at every `stride`-th row and column
     pub fn new_from_object<T, Container>(
         object: &ThymeViewBuffer<T, Container>,
         channel: usize,
         angle: f32,
         distance: f32,
+        stride: usize,
     ) -> GLCM
     where
         T: ToPrimitive + FromPrimitive,
         Container: Deref<Target = [T]>,
     {
-        let radians = angle.to_radians();
-
-        let mut min_val = f32::MAX;
-        let mut max_val = f32::MIN;
+        GLCM::new_from_object_with_preset(
+            object,
+            channel,
+            angle_offset(angle, distance),
+            stride,
+            TexturePreset::Default,
+        )
+    }
 
+    /// Create a new normalized gray-level co-occurence matrix from a ThymeObjectBuffer following a texture preset
+    ///
+    /// # Arguments
+    ///
+    /// * `object` - A ThymeObjectBuffer
+    /// * `channel` - Which channel to compute the comatrix
+    /// * `offset` - Column and row offset (dx, dy) from each pixel to its neighbour
+    /// * `stride` - Only accumulate pairs anchored at every `stride`-th row and column
+    /// * `preset` - Gray-level discretization of the pixels
+    pub fn new_from_object_with_preset<T, Container>(
+        object: &ThymeViewBuffer<T, Container>,
+        channel: usize,
+        offset: [i32; 2],
+        stride: usize,
+        preset: TexturePreset,
+    ) -> GLCM
+    where
+        T: ToPrimitive + FromPrimitive,
+        Container: Deref<Target = [T]>,
+    {
         let pixel_vec: Vec<f32> = object
             .iter()
             .skip(channel)
             .step_by(object.channels())
-            .map(|p| {
-                let value = p.to_f32().unwrap();
-                min_val = min_val.min(value);
-                max_val = max_val.max(value);
-                value
-            })
+            .map(|p| p.to_f32().unwrap())
             .collect();
 
-        let (sa, sb, sc) = if max_val != GLCM_LEVELS as f32 - 1.0 || min_val != 0.0 {
-            (min_val, max_val, GLCM_LEVELS as f32 - 1.0)
-        } else if min_val == max_val {
-            // Homogeneous images are set to zero
-            (0.0, 1.0, 0.0)
-        } else {
-            (0.0, 1.0, 1.0)
-        };
-
-        let (w, h) = (object.width() as i32, object.height() as i32);
-        let offset_x = (radians.cos() * distance).round() as i32;
-        let offset_y = (radians.sin() * distance).round() as i32;
+        GLCM::from_pixels(
+            &pixel_vec,
+            object.width(),
+            object.height(),
+            offset,
+            stride,
+            preset,
+        )
+    }
 
-        let mut comatrix = [0.0; GLCM_ARRAY_SIZE];
+    /// Accumulate the symmetric co-occurences of discretized pixels and normalize by their sum
+    fn from_pixels(
+        pixel_vec: &[f32],
+        width: usize,
+        height: usize,
+        offset: [i32; 2],
+        stride: usize,
+        preset: TexturePreset,
+    ) -> GLCM {
+        let levels = preset.discretize(pixel_vec);
 
-        let scale_pixel =
-            |pixel: f32| -> usize { ((pixel - sa) / (sb - sa) * sc).round() as usize };
+        let (w, h) = (width as i32, height as i32);
+        let [offset_x, offset_y] = offset;
 
+        let mut comatrix = [0.0; GLCM_ARRAY_SIZE];
         let mut comatrix_sum = 0f32;
 
-        for y in 0..h {
-            for x in 0..w {
-                let idx = (y * w + x) as usize;
-                let root = pixel_vec[idx];
+        let stride = stride.max(1);
 
+        for y in (0..h).step_by(stride) {
+            for x in (0..w).step_by(stride) {
                 let i_offset = x + offset_x;
                 let j_offset = y + offset_y;
 
@@ -192,11 +361,8 @@ impl GLCM {
                     continue;
                 }
 
-                let neighbour_idx = (j_offset * w + i_offset) as usize;
-                let neighbour = pixel_vec[neighbour_idx];
-
-                let root_scaled = scale_pixel(root);
-                let neighbour_scaled = scale_pixel(neighbour);
+                let root_scaled = levels[(y * w + x) as usize];
+                let neighbour_scaled = levels[(j_offset * w + i_offset) as usize];
 
                 comatrix[root_scaled * GLCM_LEVELS + neighbour_scaled] += 1.0;
                 comatrix[neighbour_scaled * GLCM_LEVELS + root_scaled] += 1.0;
@@ -214,6 +380,52 @@ impl GLCM {
         }
     }
 
+    /// Create a gray-level co-occurence matrix from row-major values
+    ///
+    /// Values are used as provided, so matrices that were exported with
+    /// `to_vec` remain normalized.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Row-major values of a `GLCM_LEVELS` x `GLCM_LEVELS` matrix
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::cv::features::GLCM;
+    /// let buffer: Vec<f32> = vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+    /// let comatrix = GLCM::new(&buffer, 3, 3, 0, 1, 0.0, 1.0);
+    ///
+    /// let copy = GLCM::from_vec(comatrix.to_vec()).unwrap();
+    /// assert_eq!(copy.as_matrix(), comatrix.as_matrix());
+    /// assert!(GLCM::from_vec(vec![1.0; 4]).is_err());
+    /// ```
+    pub fn from_vec(data: Vec<f32>) -> Result<GLCM, ThymeError> {
+        let data: [f32; GLCM_ARRAY_SIZE] = data.try_into().map_err(|data: Vec<f32>| {
+            ThymeError::OtherError(format!(
+                "Co-occurence matrix must have {} values but {} were provided",
+                GLCM_ARRAY_SIZE,
+                data.len()
+            ))
+        })?;
+
+        Ok(GLCM {
+            data,
+            rows: GLCM_LEVELS,
+            cols: GLCM_LEVELS,
+        })
+    }
+
+    /// Row-major values of the matrix
+    pub fn to_vec(&self) -> Vec<f32> {
+        self.data.to_vec()
+    }
+
+    /// Rows of the matrix
+    pub fn as_matrix(&self) -> Vec<&[f32]> {
+        self.data.chunks(self.cols).collect()
+    }
+
     pub fn rows(&self) -> usize {
         self.rows
     }
@@ -287,16 +499,18 @@ where
 /// * `channels` - Number of channels in image
 /// * `angle` - Angle (in degrees) for computing neighbour co-occurence
 /// * `distance` - Number of pixels to neighbouring pixels
+/// * `stride` - Only accumulate pairs anchored at every `stride`-th row and column
 pub fn glcm_multichannel_object<T, Container>(
     object: &ThymeViewBuffer<T, Container>,
     angle: f32,
     distance: f32,
+    stride: usize,
 ) -> Vec<GLCM>
 where
     T: ToPrimitive + FromPrimitive,
     Container: Deref<Target = [T]>,
 {
     (0..object.channels())
-        .map(|channel| GLCM::new_from_object(object, channel, angle, distance))
+        .map(|channel| GLCM::new_from_object(object, channel, angle, distance, stride))
         .collect()
 }
@@ -2,8 +2,16 @@ pub mod connected;
 pub mod contours;
 pub mod ellipse;
 pub mod features;
+pub mod morphology;
 pub mod points;
+pub mod threshold;
 pub mod transform;
+pub mod watershed;
 
 pub use connected::connected_components;
-pub use contours::{find_contours, find_labeled_contours};
+pub use contours::{
+    find_contours, find_contours_with_hierarchy, find_labeled_contours,
+    find_labeled_contours_with_hierarchy,
+};
+pub use threshold::otsu;
+pub use watershed::watershed;
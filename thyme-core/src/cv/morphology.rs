@@ -0,0 +1,254 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+/// Two-pass chessboard distance transform on mask buffers
+///
+/// Each foreground (non-zero) pixel is assigned the chessboard (8-connected)
+/// distance to the nearest background pixel. Pixels outside of the buffer are
+/// treated as background so foreground pixels on the buffer edge have a
+/// distance of one. Background pixels are assigned a distance of zero.
+///
+/// # Arguments
+///
+/// * `width` - Width of mask
+/// * `height` - Height of mask
+/// * `buffer` - A row-major mask buffer
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::morphology::distance_chessboard;
+///
+/// let buffer: Vec<u32> = vec![
+///     1, 1, 1, 1, 1,
+///     1, 1, 1, 1, 1,
+///     1, 1, 1, 1, 1,
+///     1, 1, 1, 1, 1,
+///     1, 1, 1, 1, 1,
+/// ];
+///
+/// let distance = distance_chessboard(5, 5, &buffer);
+/// assert_eq!(distance[0], 1);
+/// assert_eq!(distance[6], 2);
+/// assert_eq!(distance[12], 3);
+/// ```
+pub fn distance_chessboard(width: u32, height: u32, buffer: &[u32]) -> Vec<u32> {
+    let distance: Vec<u32> = buffer
+        .iter()
+        .map(|&v| if v == 0 { 0 } else { u32::MAX })
+        .collect();
+
+    // Neighbors outside of the buffer are background (distance of zero)
+    chamfer(width, height, distance, 0)
+}
+
+/// Propagate chessboard distances from pixels with a distance of zero
+///
+/// # Arguments
+///
+/// * `width` - Width of mask
+/// * `height` - Height of mask
+/// * `distance` - Initial distances with zero at sources and `u32::MAX` elsewhere
+/// * `outside` - Distance assigned to neighbors outside of the buffer
+fn chamfer(width: u32, height: u32, mut distance: Vec<u32>, outside: u32) -> Vec<u32> {
+    let width = width as usize;
+    let height = height as usize;
+
+    let at = |distance: &[u32], x: isize, y: isize| -> u32 {
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+            outside
+        } else {
+            distance[y as usize * width + x as usize]
+        }
+    };
+
+    // Forward pass over left, top-left, top, and top-right neighbors
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let idx = y as usize * width + x as usize;
+
+            if distance[idx] == 0 {
+                continue;
+            }
+
+            let nearest = at(&distance, x - 1, y)
+                .min(at(&distance, x - 1, y - 1))
+                .min(at(&distance, x, y - 1))
+                .min(at(&distance, x + 1, y - 1));
+
+            distance[idx] = distance[idx].min(nearest.saturating_add(1));
+        }
+    }
+
+    // Backward pass over right, bottom-right, bottom, and bottom-left neighbors
+    for y in (0..height as isize).rev() {
+        for x in (0..width as isize).rev() {
+            let idx = y as usize * width + x as usize;
+
+            if distance[idx] == 0 {
+                continue;
+            }
+
+            let nearest = at(&distance, x + 1, y)
+                .min(at(&distance, x + 1, y + 1))
+                .min(at(&distance, x, y + 1))
+                .min(at(&distance, x - 1, y + 1));
+
+            distance[idx] = distance[idx].min(nearest.saturating_add(1));
+        }
+    }
+
+    distance
+}
+
+/// Erode foreground pixels in a mask buffer using a square structuring element
+///
+/// Eroding by `radius` pixels is equivalent to iteratively applying a 3x3
+/// square erosion `radius` times. Retained pixels keep their original label.
+///
+/// # Arguments
+///
+/// * `width` - Width of mask
+/// * `height` - Height of mask
+/// * `buffer` - A row-major mask buffer
+/// * `radius` - Number of pixels to erode from the object boundary
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::morphology::erode;
+///
+/// let buffer: Vec<u32> = vec![
+///     0, 0, 0, 0, 0,
+///     0, 2, 2, 2, 0,
+///     0, 2, 2, 2, 0,
+///     0, 2, 2, 2, 0,
+///     0, 0, 0, 0, 0,
+/// ];
+///
+/// let eroded = erode(5, 5, &buffer, 1);
+/// assert_eq!(eroded.iter().filter(|&&v| v > 0).count(), 1);
+/// assert_eq!(eroded[12], 2);
+/// ```
+pub fn erode(width: u32, height: u32, buffer: &[u32], radius: u32) -> Vec<u32> {
+    distance_chessboard(width, height, buffer)
+        .into_iter()
+        .zip(buffer)
+        .map(|(d, &v)| if d > radius { v } else { 0 })
+        .collect()
+}
+
+/// Dilate foreground pixels in a mask buffer using a square structuring element
+///
+/// Dilating by `radius` pixels is equivalent to iteratively applying a 3x3
+/// square dilation `radius` times. Pixels outside of the buffer are never
+/// foreground, so objects on the buffer edge are dilated without wrapping.
+/// Dilated pixels are set to one regardless of the label they grew from.
+///
+/// # Arguments
+///
+/// * `width` - Width of mask
+/// * `height` - Height of mask
+/// * `buffer` - A row-major mask buffer
+/// * `radius` - Number of pixels to grow the object boundary by
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::morphology::dilate;
+///
+/// let buffer: Vec<u32> = vec![
+///     0, 0, 0, 0, 0,
+///     0, 0, 0, 0, 0,
+///     0, 0, 2, 0, 0,
+///     0, 0, 0, 0, 0,
+///     0, 0, 0, 0, 0,
+/// ];
+///
+/// let dilated = dilate(5, 5, &buffer, 1);
+/// assert_eq!(dilated.iter().filter(|&&v| v > 0).count(), 9);
+/// assert_eq!(dilated[12], 2);
+/// assert_eq!(dilated[6], 1);
+/// ```
+pub fn dilate(width: u32, height: u32, buffer: &[u32], radius: u32) -> Vec<u32> {
+    let distance: Vec<u32> = buffer
+        .iter()
+        .map(|&v| if v == 0 { u32::MAX } else { 0 })
+        .collect();
+
+    chamfer(width, height, distance, u32::MAX)
+        .into_iter()
+        .zip(buffer)
+        .map(|(d, &v)| match (v, d <= radius) {
+            (0, true) => 1,
+            (_, true) => v,
+            _ => 0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn square() -> (u32, u32, Vec<u32>) {
+        let (w, h) = (9u32, 9u32);
+        let mut buffer = vec![0u32; (w * h) as usize];
+
+        for y in 1..8 {
+            for x in 1..8 {
+                buffer[y * w as usize + x] = 1;
+            }
+        }
+
+        (w, h, buffer)
+    }
+
+    #[test]
+    fn test_distance_chessboard_square() {
+        let (w, h, buffer) = square();
+        let distance = distance_chessboard(w, h, &buffer);
+
+        for y in 0..h as usize {
+            for x in 0..w as usize {
+                let ring = x.min(y).min(8 - x).min(8 - y) as u32;
+                assert_eq!(distance[y * w as usize + x], ring);
+            }
+        }
+    }
+
+    #[test]
+    fn test_distance_chessboard_edge() {
+        let buffer = vec![1u32; 9];
+        let distance = distance_chessboard(3, 3, &buffer);
+        assert_eq!(distance, vec![1, 1, 1, 1, 2, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_erode_square() {
+        let (w, h, buffer) = square();
+
+        assert_eq!(erode(w, h, &buffer, 0), buffer);
+        assert_eq!(erode(w, h, &buffer, 1).iter().sum::<u32>(), 25);
+        assert_eq!(erode(w, h, &buffer, 2).iter().sum::<u32>(), 9);
+        assert_eq!(erode(w, h, &buffer, 4).iter().sum::<u32>(), 0);
+    }
+
+    #[test]
+    fn test_dilate_square() {
+        let (w, h, buffer) = square();
+
+        assert_eq!(dilate(w, h, &buffer, 0), buffer);
+        assert_eq!(dilate(w, h, &buffer, 1).iter().sum::<u32>(), 81);
+
+        // Objects on the buffer edge are not wrapped to the opposite edge
+        let mut corner = vec![0u32; 25];
+        corner[0] = 1;
+
+        let dilated = dilate(5, 5, &corner, 2);
+        assert_eq!(dilated.iter().sum::<u32>(), 9);
+        assert_eq!(dilated[4], 0);
+        assert_eq!(dilated[20], 0);
+    }
+}
@@ -1,27 +1,56 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::cmp::Ordering;
+
+use crate::error::ThymeError;
+
+/// Total ordering of points by x and then y
+#[inline]
+fn cmp_points(a: &[f32; 2], b: &[f32; 2]) -> Ordering {
+    a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1]))
+}
+
 /// Compute the convex hull for a set of (x, y) points
 ///
+/// Points are sorted with a total ordering so the hull does not depend on
+/// the order of the input points. Only the corners of the hull are returned.
+/// Duplicate points and points lying on an edge of the hull (collinear with
+/// its corners) are excluded, with orientations computed in f64 so nearly
+/// collinear points are treated consistently. Any NaN or infinite point
+/// returns a `PolygonsValueError`.
+///
+/// # Arguments
+///
+/// * `points` - A set of (x, y) points
+///
 /// # Examples
 ///
 /// ```
 /// use thyme_core::cv::points::convex_hull;
 ///
-/// let points = [[0., 1.], [1., 1.], [0.5, 0.5], [1., 0.], [0., 0.]];
-/// let hull = convex_hull(&points);
+/// let points = [[0., 1.], [1., 1.], [0.5, 0.5], [1., 0.], [0., 0.], [0.5, 0.]];
+/// let hull = convex_hull(&points).unwrap();
 ///
 /// assert_eq!(hull, [[0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0]]);
+/// assert!(convex_hull(&[[0., 0.], [f32::NAN, 1.], [1., 0.]]).is_err());
 /// ```
-pub fn convex_hull(points: &[[f32; 2]]) -> Vec<[f32; 2]> {
+pub fn convex_hull(points: &[[f32; 2]]) -> Result<Vec<[f32; 2]>, ThymeError> {
+    if points.iter().flatten().any(|v| !v.is_finite()) {
+        return Err(ThymeError::PolygonsValueError);
+    }
+
     let mut sorted_points = points.to_vec();
-    sorted_points.sort_by(|a, b| {
-        a[0].partial_cmp(&b[0])
-            .unwrap()
-            .then(a[1].partial_cmp(&b[1]).unwrap())
-    });
+    sorted_points.sort_by(cmp_points);
+    sorted_points.dedup();
 
+    if sorted_points.len() < 2 {
+        return Ok(sorted_points);
+    }
+
+    // Collinear turns (a zero cross product) are not counted as ccw
     fn _ccw(p: [f32; 2], q: [f32; 2], r: [f32; 2]) -> bool {
+        let (p, q, r) = (p.map(f64::from), q.map(f64::from), r.map(f64::from));
         (q[1] - p[1]) * (r[0] - q[0]) > (q[0] - p[0]) * (r[1] - q[1])
     }
 
@@ -57,7 +86,7 @@ pub fn convex_hull(points: &[[f32; 2]]) -> Vec<[f32; 2]> {
     upper_hull.pop();
 
     lower_hull.append(&mut upper_hull);
-    lower_hull
+    Ok(lower_hull)
 }
 
 /// Deduplicate redundant points for a set of (x,y) points
@@ -75,11 +104,7 @@ pub fn convex_hull(points: &[[f32; 2]]) -> Vec<[f32; 2]> {
 pub fn dedup_points(points: &mut Vec<[f32; 2]>) {
     const EPSILON: f32 = f32::EPSILON;
 
-    points.sort_unstable_by(|a, b| {
-        a[0].partial_cmp(&b[0])
-            .unwrap()
-            .then(a[1].partial_cmp(&b[1]).unwrap())
-    });
+    points.sort_unstable_by(cmp_points);
 
     points.dedup_by(|a, b| (a[0] - b[0]).abs() < EPSILON && (a[1] - b[1]).abs() < EPSILON);
 }
@@ -113,8 +138,9 @@ pub fn resample_points(points: &mut Vec<[f32; 2]>, n_points: usize) {
     // If very short path or repeated points, we replicate points
     let total_length: f32 = distances.iter().sum();
     if total_length == 0.0 {
+        let first = points[0];
         points.clear();
-        points.extend(std::iter::repeat_n(points[0], n_points).take(n_points));
+        points.resize(n_points, first);
         return;
     }
 
@@ -155,6 +181,113 @@ pub fn resample_points(points: &mut Vec<[f32; 2]>, n_points: usize) {
     points.extend(resampled);
 }
 
+/// Smooth a closed outline by gaussian convolution of its coordinates
+///
+/// Each coordinate is convolved with a gaussian kernel (truncated at three
+/// standard deviations) along the point sequence, wrapping around the end of
+/// the outline. Points must be ordered along the outline and a duplicated
+/// closing point is dropped. Outlines with fewer than 3 points are unchanged.
+///
+/// # Arguments
+///
+/// * `points` - Points ordered along a closed outline
+/// * `sigma` - Standard deviation of the kernel (points)
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::points::smooth_gaussian;
+///
+/// let mut points = vec![[0., 0.], [2., 0.], [2., 2.], [0., 2.]];
+/// smooth_gaussian(&mut points, 0.5);
+///
+/// assert!(points[0][0] > 0. && points[0][1] > 0.);
+/// assert!(points[2][0] < 2. && points[2][1] < 2.);
+/// ```
+pub fn smooth_gaussian(points: &mut Vec<[f32; 2]>, sigma: f32) {
+    if points.len() > 1 && points[0] == points[points.len() - 1] {
+        points.pop();
+    }
+
+    let n = points.len();
+    if n < 3 || sigma <= 0.0 {
+        return;
+    }
+
+    let radius = ((3.0 * sigma).ceil() as usize).max(1);
+    let kernel: Vec<f32> = (0..=2 * radius)
+        .map(|i| {
+            let d = i as f32 - radius as f32;
+            (-d * d / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+
+    let total: f32 = kernel.iter().sum();
+
+    let smoothed: Vec<[f32; 2]> = (0..n)
+        .map(|i| {
+            let mut point = [0.0; 2];
+            for (k, weight) in kernel.iter().enumerate() {
+                let j = (i + k + n * (radius / n + 1) - radius) % n;
+                point[0] += weight * points[j][0];
+                point[1] += weight * points[j][1];
+            }
+
+            [point[0] / total, point[1] / total]
+        })
+        .collect();
+
+    *points = smoothed;
+}
+
+/// Smooth a closed outline by iterative corner-cutting (Chaikin's algorithm)
+///
+/// Every iteration replaces each edge with points at one and three quarters
+/// of its length, doubling the number of points. Points must be ordered along
+/// the outline and a duplicated closing point is dropped. Outlines with fewer
+/// than 3 points are unchanged.
+///
+/// # Arguments
+///
+/// * `points` - Points ordered along a closed outline
+/// * `iterations` - Number of corner-cutting iterations
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::points::smooth_chaikin;
+///
+/// let mut points = vec![[0., 0.], [4., 0.], [4., 4.], [0., 4.]];
+/// smooth_chaikin(&mut points, 1);
+///
+/// assert_eq!(points[..2], [[1., 0.], [3., 0.]]);
+/// assert_eq!(points.len(), 8);
+/// ```
+pub fn smooth_chaikin(points: &mut Vec<[f32; 2]>, iterations: usize) {
+    if points.len() > 1 && points[0] == points[points.len() - 1] {
+        points.pop();
+    }
+
+    if points.len() < 3 {
+        return;
+    }
+
+    for _ in 0..iterations {
+        let n = points.len();
+        let mut cut = Vec::with_capacity(2 * n);
+
+        for i in 0..n {
+            let p1 = points[i];
+            let p2 = points[(i + 1) % n];
+
+            cut.push([0.75 * p1[0] + 0.25 * p2[0], 0.75 * p1[1] + 0.25 * p2[1]]);
+            cut.push([0.25 * p1[0] + 0.75 * p2[0], 0.25 * p1[1] + 0.75 * p2[1]]);
+        }
+
+        *points = cut;
+    }
+}
+
 /// Re-order outline points
 ///
 /// # Examples
@@ -187,6 +320,104 @@ pub fn order_points(points: &mut [[f32; 2]]) {
     });
 }
 
+/// Compute the signed area of a polygon
+///
+/// The area is positive for counterclockwise and negative for clockwise
+/// polygons, where counterclockwise is defined with the y-axis pointing up.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::points::signed_area;
+///
+/// let points = [[0., 0.], [2., 0.], [2., 2.], [0., 2.]];
+/// assert_eq!(signed_area(&points), 4.);
+///
+/// let points = [[0., 0.], [0., 2.], [2., 2.], [2., 0.]];
+/// assert_eq!(signed_area(&points), -4.);
+/// ```
+pub fn signed_area(points: &[[f32; 2]]) -> f32 {
+    let n = points.len();
+
+    let mut area = 0.0;
+    for i in 0..n {
+        let p1 = points[i];
+        let p2 = points[(i + 1) % n];
+        area += p1[0] * p2[1] - p2[0] * p1[1];
+    }
+
+    area / 2.0
+}
+
+/// Count the number of crossings between non-adjacent edges of a polygon
+///
+/// Edges are swept in order of their minimum x-coordinate so that only
+/// edges with overlapping extents are tested against each other. Edges
+/// that only touch at a point are not considered crossing.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::points::self_intersections;
+///
+/// let square = [[0., 0.], [2., 0.], [2., 2.], [0., 2.]];
+/// assert_eq!(self_intersections(&square), 0);
+///
+/// let bowtie = [[0., 0.], [2., 2.], [2., 0.], [0., 2.]];
+/// assert_eq!(self_intersections(&bowtie), 1);
+/// ```
+pub fn self_intersections(points: &[[f32; 2]]) -> usize {
+    let n = if points.len() > 1 && points[0] == points[points.len() - 1] {
+        points.len() - 1
+    } else {
+        points.len()
+    };
+
+    if n < 4 {
+        return 0;
+    }
+
+    let mut edges: Vec<usize> = (0..n).collect();
+    let min_x = |i: usize| points[i][0].min(points[(i + 1) % n][0]);
+    let max_x = |i: usize| points[i][0].max(points[(i + 1) % n][0]);
+
+    edges.sort_by(|&a, &b| min_x(a).partial_cmp(&min_x(b)).unwrap());
+
+    fn orientation(p: [f32; 2], q: [f32; 2], r: [f32; 2]) -> f32 {
+        (q[0] - p[0]) * (r[1] - p[1]) - (q[1] - p[1]) * (r[0] - p[0])
+    }
+
+    let mut crossings = 0;
+
+    for (k, &a) in edges.iter().enumerate() {
+        let (p1, p2) = (points[a], points[(a + 1) % n]);
+
+        for &b in edges[k + 1..].iter() {
+            if min_x(b) > max_x(a) {
+                break;
+            }
+
+            let gap = a.abs_diff(b);
+            if gap <= 1 || gap == n - 1 {
+                continue;
+            }
+
+            let (q1, q2) = (points[b], points[(b + 1) % n]);
+
+            let d1 = orientation(q1, q2, p1);
+            let d2 = orientation(q1, q2, p2);
+            let d3 = orientation(p1, p2, q1);
+            let d4 = orientation(p1, p2, q2);
+
+            if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+                crossings += 1;
+            }
+        }
+    }
+
+    crossings
+}
+
 /// Mutably draw points onto a row-major canvas of specified size
 ///
 /// # Arguments
@@ -401,6 +632,63 @@ pub fn draw_points(points: &[[f32; 2]], pad: u32) -> Vec<u32> {
     buffer
 }
 
+/// Draw points onto a row-major canvas at their original coordinates
+///
+/// Unlike `draw_points`, the points are not re-centered or rounded
+/// relative to their bounding box. The canvas covers the integer-aligned
+/// bounding box of the points plus padding, so each point is drawn on the
+/// same pixel grid as the source image. The canvas origin in the original
+/// coordinates is returned alongside the canvas dimensions.
+///
+/// # Arguments
+///
+/// * `points` - A set of (x, y) points
+/// * `pad` - Number of pixels to pad all sides of the canvas
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::points::draw_points_native;
+///
+/// let points = [[11., 21.], [10., 21.], [11., 20.], [10., 20.]];
+/// let (buffer, width, height, origin) = draw_points_native(&points, 1);
+///
+/// assert_eq!((width, height), (4, 4));
+/// assert_eq!(origin, [9., 19.]);
+/// assert_eq!(buffer, vec![0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0]);
+/// ```
+pub fn draw_points_native(points: &[[f32; 2]], pad: u32) -> (Vec<u32>, u32, u32, [f32; 2]) {
+    let &[fx, fy] = &points[0];
+
+    let mut min_x = fx;
+    let mut min_y = fy;
+    let mut max_x = fx;
+    let mut max_y = fy;
+
+    for &[x, y] in points {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+
+    let origin = [min_x.floor() - pad as f32, min_y.floor() - pad as f32];
+
+    let width = (max_x.floor() - origin[0]) as u32 + 1 + pad;
+    let height = (max_y.floor() - origin[1]) as u32 + 1 + pad;
+
+    let shifted: Vec<[f32; 2]> = points
+        .iter()
+        .map(|p| [p[0] - origin[0], p[1] - origin[1]])
+        .collect();
+
+    let mut buffer = vec![0u32; width as usize * height as usize];
+
+    draw_points_mut(&mut buffer, width, height, &shifted, 1u32);
+
+    (buffer, width, height, origin)
+}
+
 /// Draw centered and filled points onto a canvas
 pub fn draw_centered_points(
     width: u32,
@@ -409,7 +697,7 @@ pub fn draw_centered_points(
     color: u32,
     pad: u32,
 ) -> Vec<u32> {
-    let mut buffer = vec![0; (width * height) as usize];
+    let mut buffer = vec![0; width as usize * height as usize];
 
     let draw_width = width - 2 * pad;
     let draw_height = height - 2 * pad;
@@ -465,3 +753,128 @@ pub fn point_to_segment_distance(px: f32, py: f32, p1: [f32; 2], p2: [f32; 2]) -
 
     ((px - closest_x).powi(2) + (py - closest_y).powi(2)).sqrt()
 }
+
+/// Check if a point lies inside a polygon using ray casting
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::points::point_in_polygon;
+///
+/// let square = [[0., 0.], [4., 0.], [4., 4.], [0., 4.]];
+/// assert!(point_in_polygon(2., 2., &square));
+/// assert!(!point_in_polygon(5., 2., &square));
+/// ```
+pub fn point_in_polygon(px: f32, py: f32, points: &[[f32; 2]]) -> bool {
+    let n = points.len();
+
+    let mut inside = false;
+    let mut j = n.wrapping_sub(1);
+    for i in 0..n {
+        let [xi, yi] = points[i];
+        let [xj, yj] = points[j];
+
+        if (yi > py) != (yj > py) && px < (xj - xi) * (py - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+
+        j = i;
+    }
+
+    inside
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::mp::form::area;
+    use crate::ut::synth::SplitMix64;
+
+    /// Fisher-Yates shuffle of points
+    fn shuffle(points: &mut [[f32; 2]], rng: &mut SplitMix64) {
+        for i in (1..points.len()).rev() {
+            points.swap(i, rng.next_u64() as usize % (i + 1));
+        }
+    }
+
+    #[test]
+    fn test_convex_hull_permutation_invariant() {
+        let mut rng = SplitMix64::new(7);
+
+        for _ in 0..200 {
+            let n = 3 + rng.next_u64() as usize % 40;
+
+            // Snap half of the point sets to a coarse grid to create collinear
+            // and duplicate points
+            let grid = rng.next_u64() % 2 == 0;
+            let mut points: Vec<[f32; 2]> = (0..n)
+                .map(|_| {
+                    let p = [rng.range(-50.0, 50.0) as f32, rng.range(-50.0, 50.0) as f32];
+                    match grid {
+                        true => p.map(|v| (v / 10.0).round() * 10.0),
+                        false => p,
+                    }
+                })
+                .collect();
+
+            let hull = convex_hull(&points).unwrap();
+            let expected = area(&hull);
+
+            for _ in 0..5 {
+                shuffle(&mut points, &mut rng);
+
+                let shuffled = convex_hull(&points).unwrap();
+                assert_eq!(shuffled, hull);
+                assert_eq!(area(&shuffled), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_convex_hull_collinear() {
+        // Points on the edges of a square (including its corners twice) are excluded
+        let mut points = vec![];
+        for i in 0..=4 {
+            let i = i as f32;
+            points.extend([[i, 0.], [4., i], [i, 4.], [0., i]]);
+        }
+
+        points.extend([[1., 1.], [2., 3.], [0., 0.]]);
+
+        let hull = convex_hull(&points).unwrap();
+        assert_eq!(hull, [[0., 0.], [0., 4.], [4., 4.], [4., 0.]]);
+
+        // Nearly collinear points are classified exactly in f64
+        let line = [[0., 0.], [0.1, 0.1], [0.3, 0.3], [0.7, 0.7], [1.0, 1.0]];
+        assert_eq!(convex_hull(&line).unwrap(), [[0., 0.], [1., 1.]]);
+
+        assert_eq!(convex_hull(&[[2., 3.], [2., 3.]]).unwrap(), [[2., 3.]]);
+        assert!(convex_hull(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_convex_hull_non_finite() {
+        let mut rng = SplitMix64::new(11);
+
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            for _ in 0..20 {
+                let mut points: Vec<[f32; 2]> = (0..10)
+                    .map(|_| [rng.range(0.0, 10.0) as f32, rng.range(0.0, 10.0) as f32])
+                    .collect();
+
+                let i = rng.next_u64() as usize % points.len();
+                points[i][(rng.next_u64() % 2) as usize] = value;
+
+                assert!(matches!(
+                    convex_hull(&points),
+                    Err(ThymeError::PolygonsValueError)
+                ));
+            }
+        }
+
+        let mut points = vec![[0., 0.], [f32::NAN, 1.], [1., 0.], [1., 1.]];
+        dedup_points(&mut points);
+        assert_eq!(points.len(), 4);
+    }
+}
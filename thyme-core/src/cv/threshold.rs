@@ -0,0 +1,100 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+/// Number of histogram bins used to search for an Otsu threshold
+const OTSU_BINS: usize = 256;
+
+/// Otsu threshold maximizing the between-class variance of a set of values
+///
+/// Values are binned into a 256-bin histogram between their minimum and
+/// maximum. Non-finite values are ignored. Returns `None` if there are no
+/// finite values or all values are equal, since no threshold separates them.
+///
+/// # Arguments
+///
+/// * `values` - Values to threshold (e.g. pixel intensities)
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::otsu;
+///
+/// let values = [1.0, 2.0, 1.5, 9.0, 10.0, 9.5];
+/// let threshold = otsu(&values).unwrap();
+///
+/// assert!(threshold > 2.0 && threshold < 9.0);
+/// assert_eq!(otsu(&[3.0; 4]), None);
+/// ```
+pub fn otsu(values: &[f32]) -> Option<f32> {
+    let (min, max) = values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        });
+
+    if min >= max {
+        return None;
+    }
+
+    let scale = OTSU_BINS as f32 / (max - min);
+    let mut histogram = [0u64; OTSU_BINS];
+
+    for &v in values.iter().filter(|v| v.is_finite()) {
+        histogram[(((v - min) * scale) as usize).min(OTSU_BINS - 1)] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| i as f64 * n as f64)
+        .sum();
+
+    let mut below_n = 0u64;
+    let mut below_sum = 0f64;
+    let mut best = (0usize, f64::NEG_INFINITY);
+
+    // Pixels in bins up to and including the threshold bin are background
+    for (i, &n) in histogram.iter().enumerate().take(OTSU_BINS - 1) {
+        below_n += n;
+        below_sum += i as f64 * n as f64;
+
+        let above_n = total - below_n;
+
+        if below_n == 0 || above_n == 0 {
+            continue;
+        }
+
+        let below_mean = below_sum / below_n as f64;
+        let above_mean = (sum - below_sum) / above_n as f64;
+        let variance = below_n as f64 * above_n as f64 * (below_mean - above_mean).powi(2);
+
+        if variance > best.1 {
+            best = (i, variance);
+        }
+    }
+
+    Some(min + (best.0 + 1) as f32 / scale)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_otsu_bimodal() {
+        // Two clusters of unequal size with a single non-finite value
+        let mut values: Vec<f32> = (0..90).map(|i| 10.0 + (i % 7) as f32).collect();
+        values.extend((0..10).map(|i| 200.0 + (i % 5) as f32));
+        values.push(f32::NAN);
+
+        let threshold = otsu(&values).unwrap();
+        assert!(threshold > 16.0 && threshold <= 200.0, "{}", threshold);
+
+        assert!(values.iter().filter(|&&v| v > threshold).count() == 10);
+        assert_eq!(otsu(&[]), None);
+        assert_eq!(otsu(&[f32::NAN, 1.0]), None);
+    }
+}
@@ -3,11 +3,14 @@
 
 use std::ops::{Add, Div, Mul, Sub};
 
-use fast_image_resize;
+#[cfg(feature = "resize")]
 use fast_image_resize::{FilterType, PixelType, images::Image};
+#[cfg(feature = "resize")]
 use image::{DynamicImage, GenericImage, ImageBuffer, Pixel};
 use num::{FromPrimitive, ToPrimitive};
 
+use crate::error::ThymeError;
+
 /// Resize a 2D image-rs ImageBuffer
 ///
 /// # Arguments
@@ -15,6 +18,7 @@ use num::{FromPrimitive, ToPrimitive};
 /// * `image` - A u8 or u16 Luma or RGB ImageBuffer
 /// * `new_width` - New width following resizing
 /// * `new_height` - New height following resizing
+#[cfg(feature = "resize")]
 pub fn resize_bilinear_default<I, P>(
     image: &I,
     new_width: u32,
@@ -40,6 +44,7 @@ where
 /// * `new_width` - New width following resizing
 /// * `new_height` - New height following resizing
 /// * `pixel_type` - RGB or Luma pixel type
+#[cfg(feature = "resize")]
 pub fn resize_bilinear_fast(
     source: &DynamicImage,
     new_width: u32,
@@ -60,12 +65,42 @@ pub fn resize_bilinear_fast(
     destination.into_vec()
 }
 
+/// Source pixels and normalized weights of each output pixel along one axis
+///
+/// The center of output pixel `i` is sampled at `(i + 0.5) * size / new_size`
+/// in the source (pixel-center alignment) and the triangle filter is widened
+/// by the scale factor when downsampling, as in fast_image_resize and Pillow.
+/// Pixels beyond the edges are excluded so edge pixels are not extrapolated.
+fn bilinear_weights(size: usize, new_size: usize) -> Vec<(usize, Vec<f64>)> {
+    let ratio = size as f64 / new_size as f64;
+    let scale = ratio.max(1.0);
+
+    (0..new_size)
+        .map(|i| {
+            let center = (i as f64 + 0.5) * ratio;
+            let start = (center - scale + 0.5).floor().max(0.0) as usize;
+            let end = ((center + scale + 0.5).floor() as usize).min(size);
+
+            let mut weights: Vec<f64> = (start..end)
+                .map(|j| (1.0 - ((j as f64 + 0.5 - center) / scale).abs()).max(0.0))
+                .collect();
+
+            let total: f64 = weights.iter().sum();
+            weights.iter_mut().for_each(|w| *w /= total);
+
+            (start, weights)
+        })
+        .collect()
+}
+
 /// Resizes a 2D image buffer using bilinear interpolation
 ///
-/// This is going to be pretty inefficient but will only be called for
-/// a small subset of images that aren't u8 or u16 type. If an application
-/// with a lot of float type images are used, then we can re-implement a
-/// faster/more efficient approach.
+/// This is the resize of image types and channel counts that are not handled
+/// by fast_image_resize or image-rs. Pixel centers are aligned and bilinear
+/// (triangle) weights are widened when downsampling using the same sampling
+/// convention as both crates, so that images resized by different paths
+/// agree up to rounding. Rows are resized before columns without rounding
+/// the intermediate result.
 ///
 /// # Arguments
 ///
@@ -76,6 +111,15 @@ pub fn resize_bilinear_fast(
 /// * `new_width` - Target width
 /// * `new_height` - Target height
 /// * `round` - Round values before casting to original type
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::transform::resize_bilinear_general;
+///
+/// let resized = resize_bilinear_general(&[0u8, 100], 2, 1, 1, 4, 1, true);
+/// assert_eq!(resized, [0, 25, 75, 100]);
+/// ```
 pub fn resize_bilinear_general<T>(
     buffer: &[T],
     width: usize,
@@ -96,35 +140,39 @@ where
         return buffer.to_vec();
     }
 
-    let x_ratio = (width as f64).max(1.0) / (new_width as f64).max(1.0);
-    let y_ratio = (height as f64).max(1.0) / (new_height as f64).max(1.0);
+    if width == 0 || height == 0 {
+        return result;
+    }
 
-    for y in 0..new_height {
-        let y_f = (y as f64 - 0.5) * y_ratio;
-        let y1 = (y_f.floor() as usize).max(0);
-        let y2 = (y_f.ceil() as usize).min(height - 1);
-        let y_diff = y_f - y1 as f64;
+    let horizontal = bilinear_weights(width, new_width);
+    let vertical = bilinear_weights(height, new_height);
 
-        for x in 0..new_width {
-            let x_f = (x as f64 - 0.5) * x_ratio;
-            let x1 = (x_f.floor() as usize).max(0);
-            let x2 = (x_f.ceil() as usize).min(width - 1);
-            let x_diff = x_f - x1 as f64;
+    let mut rows = vec![0f64; height * new_width * channels];
 
+    for y in 0..height {
+        for (x, (start, weights)) in horizontal.iter().enumerate() {
             for c in 0..channels {
-                let a = buffer[(y1 * width + x1) * channels + c].to_f64().unwrap();
-                let b = buffer[(y1 * width + x2) * channels + c].to_f64().unwrap();
-                let c_val = buffer[(y2 * width + x1) * channels + c].to_f64().unwrap();
-                let d = buffer[(y2 * width + x2) * channels + c].to_f64().unwrap();
+                rows[(y * new_width + x) * channels + c] = weights
+                    .iter()
+                    .enumerate()
+                    .map(|(k, w)| {
+                        w * buffer[(y * width + start + k) * channels + c]
+                            .to_f64()
+                            .unwrap()
+                    })
+                    .sum();
+            }
+        }
+    }
 
-                let interpolant = if x_diff < 1e-5 && y_diff < 1e-5 {
-                    a // Snap to exact pixel if very close
-                } else {
-                    a * (1.0 - x_diff) * (1.0 - y_diff)
-                        + b * x_diff * (1.0 - y_diff)
-                        + c_val * (1.0 - x_diff) * y_diff
-                        + d * x_diff * y_diff
-                };
+    for (y, (start, weights)) in vertical.iter().enumerate() {
+        for x in 0..new_width {
+            for c in 0..channels {
+                let interpolant: f64 = weights
+                    .iter()
+                    .enumerate()
+                    .map(|(k, w)| w * rows[((start + k) * new_width + x) * channels + c])
+                    .sum();
 
                 let val = if round {
                     interpolant.round()
@@ -140,18 +188,329 @@ where
     result
 }
 
+/// Summed-area tables of the intensities of a single image channel
+///
+/// Sums, squared sums, and counts of the positive subpixels are accumulated
+/// in f64/u64 so that the intensity sum, mean, and standard deviation of any
+/// rectangle are O(1) lookups without overflowing for large images. Subpixels
+/// with non-positive intensity are ignored to match the intensity descriptors.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::transform::IntegralImage;
+///
+/// let buffer: Vec<u8> = vec![1, 2, 3, 4, 0, 6];
+/// let integral = IntegralImage::new(&buffer, 3, 2, 1, 0);
+///
+/// assert_eq!(integral.sum(0, 0, 3, 2), 16.0);
+/// assert_eq!(integral.count(1, 0, 2, 2), 3);
+/// assert_eq!(integral.squared_sum(2, 1, 1, 1), 36.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct IntegralImage {
+    width: usize,
+    height: usize,
+    sum: Vec<f64>,
+    squared: Vec<f64>,
+    count: Vec<u64>,
+}
+
+impl IntegralImage {
+    /// Build the summed-area tables of a channel of an interleaved image buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - Image buffer in row-major order (width * height * channels)
+    /// * `width` - Width of the image
+    /// * `height` - Height of the image
+    /// * `channels` - Number of channels
+    /// * `channel` - Channel to build the tables from
+    pub fn new<T: ToPrimitive>(
+        buffer: &[T],
+        width: usize,
+        height: usize,
+        channels: usize,
+        channel: usize,
+    ) -> Self {
+        assert_eq!(buffer.len(), width * height * channels);
+        assert!(channel < channels);
+
+        // Tables have a leading row and column of zeros so lookups need no bounds checks
+        let stride = width + 1;
+        let mut sum = vec![0f64; stride * (height + 1)];
+        let mut squared = vec![0f64; stride * (height + 1)];
+        let mut count = vec![0u64; stride * (height + 1)];
+
+        for y in 0..height {
+            let mut row_sum = 0f64;
+            let mut row_squared = 0f64;
+            let mut row_count = 0u64;
+
+            for x in 0..width {
+                let v = buffer[(y * width + x) * channels + channel]
+                    .to_f64()
+                    .unwrap_or(0.0);
+
+                if v > 0. {
+                    row_sum += v;
+                    row_squared += v * v;
+                    row_count += 1;
+                }
+
+                let above = y * stride + x + 1;
+                let idx = above + stride;
+
+                sum[idx] = sum[above] + row_sum;
+                squared[idx] = squared[above] + row_squared;
+                count[idx] = count[above] + row_count;
+            }
+        }
+
+        Self {
+            width,
+            height,
+            sum,
+            squared,
+            count,
+        }
+    }
+
+    /// Width of the image the tables were built from
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the image the tables were built from
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    fn corners(&self, x: usize, y: usize, w: usize, h: usize) -> [usize; 4] {
+        assert!(x + w <= self.width && y + h <= self.height);
+
+        let stride = self.width + 1;
+        [
+            y * stride + x,
+            y * stride + x + w,
+            (y + h) * stride + x,
+            (y + h) * stride + x + w,
+        ]
+    }
+
+    /// Sum of the positive intensities in a rectangle
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Minimum x-coordinate (left)
+    /// * `y` - Minimum y-coordinate (top)
+    /// * `w` - Width of the rectangle
+    /// * `h` - Height of the rectangle
+    pub fn sum(&self, x: usize, y: usize, w: usize, h: usize) -> f64 {
+        let [a, b, c, d] = self.corners(x, y, w, h);
+        self.sum[d] - self.sum[b] - self.sum[c] + self.sum[a]
+    }
+
+    /// Sum of the squared positive intensities in a rectangle
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Minimum x-coordinate (left)
+    /// * `y` - Minimum y-coordinate (top)
+    /// * `w` - Width of the rectangle
+    /// * `h` - Height of the rectangle
+    pub fn squared_sum(&self, x: usize, y: usize, w: usize, h: usize) -> f64 {
+        let [a, b, c, d] = self.corners(x, y, w, h);
+        self.squared[d] - self.squared[b] - self.squared[c] + self.squared[a]
+    }
+
+    /// Number of positive intensities in a rectangle
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Minimum x-coordinate (left)
+    /// * `y` - Minimum y-coordinate (top)
+    /// * `w` - Width of the rectangle
+    /// * `h` - Height of the rectangle
+    pub fn count(&self, x: usize, y: usize, w: usize, h: usize) -> u64 {
+        let [a, b, c, d] = self.corners(x, y, w, h);
+        self.count[d] + self.count[a] - self.count[b] - self.count[c]
+    }
+
+    /// Intensity sum, mean, and standard deviation of the positive intensities in a rectangle
+    ///
+    /// Matches the sum, mean, and standard deviation of the intensity
+    /// descriptors for a single channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Minimum x-coordinate (left)
+    /// * `y` - Minimum y-coordinate (top)
+    /// * `w` - Width of the rectangle
+    /// * `h` - Height of the rectangle
+    pub fn intensity(&self, x: usize, y: usize, w: usize, h: usize) -> [f32; 3] {
+        let sum = self.sum(x, y, w, h);
+        let n = self.count(x, y, w, h);
+
+        if n == 0 {
+            return [sum as f32, 0.0, 0.0];
+        }
+
+        let mean = sum / n as f64;
+        let variance = (self.squared_sum(x, y, w, h) / n as f64 - mean * mean).max(0.0);
+
+        [sum as f32, mean as f32, variance.sqrt() as f32]
+    }
+}
+
+/// Affine transform of 2D points in pixel coordinates
+///
+/// Points are mapped as `x' = a * x + b * y + tx` and `y' = c * x + d * y + ty`,
+/// e.g. to register segments drawn on one acquisition to the image of another.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::transform::AffineTransform;
+///
+/// // Rotate by 90 degrees around the origin and shift right
+/// let transform = AffineTransform::new([0., -1., 1., 0., 10., 0.]).unwrap();
+///
+/// assert_eq!(transform.apply([2., 3.]), [7., 2.]);
+/// assert_eq!(transform.apply_xyxy(&[0., 0., 2., 1.]), [9., 0., 10., 2.]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AffineTransform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub tx: f32,
+    pub ty: f32,
+}
+
+impl AffineTransform {
+    /// Initialize an affine transform from its coefficients
+    ///
+    /// # Arguments
+    ///
+    /// * `coefficients` - Coefficients in a, b, c, d, tx, ty order
+    pub fn new(coefficients: [f32; 6]) -> Result<Self, ThymeError> {
+        let [a, b, c, d, tx, ty] = coefficients;
+
+        if coefficients.iter().any(|v| !v.is_finite()) {
+            return Err(ThymeError::OtherError(format!(
+                "Affine transform has non-finite coefficients: {:?}",
+                coefficients
+            )));
+        }
+
+        // Singular matrices collapse segments onto a line or a point
+        let determinant = a as f64 * d as f64 - b as f64 * c as f64;
+
+        if determinant.abs() < 1e-9 {
+            return Err(ThymeError::OtherError(format!(
+                "Affine transform is singular (determinant of {}): {:?}",
+                determinant, coefficients
+            )));
+        }
+
+        Ok(AffineTransform { a, b, c, d, tx, ty })
+    }
+
+    /// Parse an affine transform from comma-separated coefficients
+    ///
+    /// # Arguments
+    ///
+    /// * `coefficients` - Coefficients in a,b,c,d,tx,ty order (e.g. 1,0,0,1,5,-3)
+    pub fn parse(coefficients: &str) -> Result<Self, ThymeError> {
+        let invalid = || {
+            ThymeError::OtherError(format!(
+                "Invalid affine transform: {}. Expected six comma-separated numbers in a,b,c,d,tx,ty order",
+                coefficients
+            ))
+        };
+
+        let values: Vec<f32> = coefficients
+            .split(',')
+            .map(|v| v.trim().parse::<f32>().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+
+        AffineTransform::new(values.try_into().map_err(|_| invalid())?)
+    }
+
+    /// Transform that leaves points unchanged
+    pub fn identity() -> Self {
+        AffineTransform {
+            a: 1.,
+            b: 0.,
+            c: 0.,
+            d: 1.,
+            tx: 0.,
+            ty: 0.,
+        }
+    }
+
+    /// Transform a point
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - Point in x, y order
+    pub fn apply(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        [
+            self.a * x + self.b * y + self.tx,
+            self.c * x + self.d * y + self.ty,
+        ]
+    }
+
+    /// Transform points in place
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Points in x, y order
+    pub fn apply_points(&self, points: &mut [[f32; 2]]) {
+        for point in points.iter_mut() {
+            *point = self.apply(*point);
+        }
+    }
+
+    /// Transform the corners of a bounding box and return their axis-aligned bounding box
+    ///
+    /// # Arguments
+    ///
+    /// * `xyxy` - Bounding box in min_x, min_y, max_x, max_y order
+    pub fn apply_xyxy(&self, &[min_x, min_y, max_x, max_y]: &[f32; 4]) -> [f32; 4] {
+        let corners = [
+            [min_x, min_y],
+            [max_x, min_y],
+            [max_x, max_y],
+            [min_x, max_y],
+        ]
+        .map(|corner| self.apply(corner));
+
+        corners.iter().fold(
+            [f32::MAX, f32::MAX, f32::MIN, f32::MIN],
+            |[min_x, min_y, max_x, max_y], [x, y]| {
+                [min_x.min(*x), min_y.min(*y), max_x.max(*x), max_y.max(*y)]
+            },
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
 
     use super::*;
-    use fast_image_resize::PixelType;
-    use image::Luma;
 
     #[test]
+    #[cfg(feature = "resize")]
     fn test_resize_consistency() {
         // There is going to be some variability in how integer types are
         // handled across various resize implementations so we accept consistency
         // at some specified error.
+        use image::Luma;
+
         const MAX_ERROR: f32 = 5.0;
 
         let buffer_u8 = vec![0, 1, 2, 3, 4, 5, 6, 7, 8];
@@ -194,6 +553,72 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(feature = "resize")]
+    fn test_resize_cross_path() {
+        use image::Luma;
+
+        use crate::ut::synth::SplitMix64;
+
+        let mut rng = SplitMix64::new(3);
+
+        for (width, height, new_width, new_height) in [
+            (17, 13, 40, 31),
+            (40, 31, 7, 5),
+            (17, 13, 7, 30),
+            (64, 64, 17, 17),
+            (9, 3, 9, 12),
+        ] {
+            let pixels: Vec<u8> = (0..width * height)
+                .map(|_| rng.range(0.0, 256.0) as u8)
+                .collect();
+
+            let image = DynamicImage::ImageLuma8(
+                ImageBuffer::<Luma<u8>, Vec<u8>>::from_vec(width, height, pixels.clone()).unwrap(),
+            );
+
+            let fast = resize_bilinear_fast(&image, new_width, new_height, PixelType::U8);
+
+            // The same data with a dummy channel is resized by the generic path
+            let two_channel: Vec<u8> = pixels.iter().flat_map(|&p| [p, 255 - p]).collect();
+            let general = resize_bilinear_general(
+                &two_channel,
+                width as usize,
+                height as usize,
+                2,
+                new_width as usize,
+                new_height as usize,
+                true,
+            );
+
+            for (i, (a, b)) in fast.iter().zip(general.iter().step_by(2)).enumerate() {
+                assert!(
+                    a.abs_diff(*b) <= 1,
+                    "{}x{} -> {}x{} differs at {}: {} {}",
+                    width,
+                    height,
+                    new_width,
+                    new_height,
+                    i,
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_resize_general_pixel_centers() {
+        // Upsampling a ramp by two samples a quarter pixel from each source center
+        let ramp: Vec<f32> = vec![0.0, 4.0, 8.0];
+        let resized = resize_bilinear_general(&ramp, 3, 1, 1, 6, 1, false);
+        assert_eq!(resized, [0.0, 1.0, 3.0, 5.0, 7.0, 8.0]);
+
+        // Downsampling is not shifted towards either edge
+        let resized = resize_bilinear_general(&[0u8, 10, 20, 30], 4, 1, 1, 2, 1, true);
+        assert_eq!(resized, [7, 23]);
+    }
+
     #[test]
     fn test_resize_general() {
         let buffer_u8_symmetric = vec![0, 1, 2, 3, 4, 5, 6, 7, 8];
@@ -230,4 +655,99 @@ mod test {
             23 * 24 * 2
         );
     }
+
+    #[test]
+    fn test_integral_image() {
+        let (width, height, channels) = (7, 5, 2);
+
+        let buffer: Vec<i32> = (0..width * height * channels)
+            .map(|i| ((i * 37) % 11) as i32 - 2)
+            .collect();
+
+        for channel in 0..channels {
+            let integral = IntegralImage::new(&buffer, width, height, channels, channel);
+
+            for (x, y, w, h) in [(0, 0, 7, 5), (2, 1, 3, 3), (6, 4, 1, 1), (3, 2, 0, 2)] {
+                let values: Vec<f64> = (y..y + h)
+                    .flat_map(|j| (x..x + w).map(move |i| (i, j)))
+                    .map(|(i, j)| buffer[(j * width + i) * channels + channel] as f64)
+                    .filter(|&v| v > 0.)
+                    .collect();
+
+                let sum: f64 = values.iter().sum();
+                let squared: f64 = values.iter().map(|v| v * v).sum();
+
+                assert_eq!(integral.sum(x, y, w, h), sum);
+                assert_eq!(integral.squared_sum(x, y, w, h), squared);
+                assert_eq!(integral.count(x, y, w, h), values.len() as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_integral_image_large_values() {
+        // Squared u64 intensities overflow integer accumulators
+        let buffer = vec![u64::MAX / 2; 4 * 4];
+        let integral = IntegralImage::new(&buffer, 4, 4, 1, 0);
+
+        let v = (u64::MAX / 2) as f64;
+        assert_eq!(integral.sum(0, 0, 4, 4), 16. * v);
+        assert_eq!(integral.squared_sum(0, 0, 4, 4), 16. * v * v);
+
+        let [sum, mean, std] = integral.intensity(1, 1, 2, 2);
+        assert_eq!(sum, (4. * v) as f32);
+        assert_eq!(mean, v as f32);
+        assert_eq!(std, 0.0);
+    }
+
+    #[test]
+    fn test_affine_translation() {
+        let transform = AffineTransform::new([1., 0., 0., 1., 5., -3.]).unwrap();
+
+        assert_eq!(transform.apply([0., 0.]), [5., -3.]);
+        assert_eq!(transform.apply([2.5, 4.]), [7.5, 1.]);
+        assert_eq!(transform.apply_xyxy(&[1., 2., 4., 6.]), [6., -1., 9., 3.]);
+    }
+
+    #[test]
+    fn test_affine_scale() {
+        let transform = AffineTransform::parse("2, 0, 0, 0.5, 0, 0").unwrap();
+
+        assert_eq!(transform.apply([3., 8.]), [6., 4.]);
+
+        let mut points = [[1., 2.], [4., 2.], [4., 6.]];
+        transform.apply_points(&mut points);
+        assert_eq!(points, [[2., 1.], [8., 1.], [8., 3.]]);
+
+        // Negative scales flip the corners that bound the box
+        let flip = AffineTransform::new([-1., 0., 0., 1., 10., 0.]).unwrap();
+        assert_eq!(flip.apply_xyxy(&[1., 2., 4., 6.]), [6., 2., 9., 6.]);
+    }
+
+    #[test]
+    fn test_affine_rotation() {
+        // Clockwise rotation by 90 degrees in image coordinates (y pointing down)
+        // around the origin followed by a shift keeping points in a 10x10 image
+        let transform = AffineTransform::new([0., -1., 1., 0., 9., 0.]).unwrap();
+
+        assert_eq!(transform.apply([0., 0.]), [9., 0.]);
+        assert_eq!(transform.apply([2., 3.]), [6., 2.]);
+        assert_eq!(transform.apply([9., 9.]), [0., 9.]);
+
+        // Corners are transformed before the axis-aligned box is re-derived
+        assert_eq!(transform.apply_xyxy(&[1., 2., 4., 3.]), [6., 1., 7., 4.]);
+    }
+
+    #[test]
+    fn test_affine_invalid() {
+        assert!(AffineTransform::new([1., 2., 2., 4., 0., 0.]).is_err());
+        assert!(AffineTransform::new([0., 0., 0., 0., 1., 1.]).is_err());
+        assert!(AffineTransform::new([1., 0., 0., f32::NAN, 0., 0.]).is_err());
+        assert!(AffineTransform::parse("1,0,0,1,0").is_err());
+        assert!(AffineTransform::parse("1,0,0,1,0,x").is_err());
+        assert_eq!(
+            AffineTransform::parse("1,0,0,1,0,0").unwrap(),
+            AffineTransform::identity()
+        );
+    }
 }
@@ -0,0 +1,246 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::cv::connected_components;
+
+/// A pixel queued for flooding, ordered by elevation then insertion order
+struct Queued {
+    elevation: f32,
+    order: usize,
+    idx: usize,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Highest elevation first, earliest insertion first among ties
+        self.elevation
+            .total_cmp(&other.elevation)
+            .then_with(|| other.order.cmp(&self.order))
+    }
+}
+
+/// Maximum over a square (2 * radius + 1) window computed separably
+fn max_filter(width: usize, height: usize, buffer: &[f32], radius: usize) -> Vec<f32> {
+    let mut rows = vec![f32::MIN; buffer.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let x0 = x.saturating_sub(radius);
+            let x1 = (x + radius).min(width - 1);
+
+            rows[y * width + x] = buffer[y * width + x0..=y * width + x1]
+                .iter()
+                .fold(f32::MIN, |a, &b| a.max(b));
+        }
+    }
+
+    let mut filtered = vec![f32::MIN; buffer.len()];
+
+    for y in 0..height {
+        let y0 = y.saturating_sub(radius);
+        let y1 = (y + radius).min(height - 1);
+
+        for x in 0..width {
+            filtered[y * width + x] = (y0..=y1)
+                .map(|yy| rows[yy * width + x])
+                .fold(f32::MIN, f32::max);
+        }
+    }
+
+    filtered
+}
+
+/// Split thresholded objects by flooding an elevation map from its maxima
+///
+/// Pixels with an elevation above the threshold are foreground. Seeds are
+/// placed at local maxima of the elevation map, and maxima closer than
+/// `min_distance` (chessboard distance) to a higher maxima are merged into
+/// it. Foreground pixels are then flooded from the seeds in order of
+/// decreasing elevation using 8-connectivity. Foreground regions that are
+/// not reached from any seed are labeled as individual components.
+///
+/// # Arguments
+///
+/// * `width` - Width of elevation map
+/// * `height` - Height of elevation map
+/// * `elevation` - A row-major elevation map (e.g. probabilities)
+/// * `threshold` - Pixels above the threshold are foreground
+/// * `min_distance` - Minimum distance between seeds (pixels)
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::cv::watershed;
+///
+/// // Two touching peaks separated by a saddle
+/// let elevation: Vec<f32> = vec![0.9, 0.8, 0.6, 0.8, 0.9];
+///
+/// assert_eq!(watershed(5, 1, &elevation, 0.5, 1), [1, 1, 1, 2, 2]);
+/// assert_eq!(watershed(5, 1, &elevation, 0.5, 5), [1, 1, 1, 1, 1]);
+/// ```
+pub fn watershed(
+    width: u32,
+    height: u32,
+    elevation: &[f32],
+    threshold: f32,
+    min_distance: u32,
+) -> Vec<u32> {
+    let width = width as usize;
+    let height = height as usize;
+    let radius = min_distance.max(1) as usize;
+
+    let mut labels = vec![0u32; elevation.len()];
+
+    if elevation.is_empty() {
+        return labels;
+    }
+
+    let foreground: Vec<bool> = elevation.iter().map(|&e| e > threshold).collect();
+    let maxima = max_filter(width, height, elevation, radius);
+
+    let mut seeds: Vec<usize> = (0..elevation.len())
+        .filter(|&idx| foreground[idx] && elevation[idx] >= maxima[idx])
+        .collect();
+
+    seeds.sort_by(|&a, &b| elevation[b].total_cmp(&elevation[a]).then(a.cmp(&b)));
+
+    let mut suppressed = vec![false; elevation.len()];
+    let mut queue = BinaryHeap::new();
+    let mut order = 0;
+    let mut next_label = 1;
+
+    for idx in seeds {
+        if suppressed[idx] {
+            continue;
+        }
+
+        let (x, y) = (idx % width, idx / width);
+
+        for yy in y.saturating_sub(radius - 1)..=(y + radius - 1).min(height - 1) {
+            for xx in x.saturating_sub(radius - 1)..=(x + radius - 1).min(width - 1) {
+                suppressed[yy * width + xx] = true;
+            }
+        }
+
+        labels[idx] = next_label;
+        next_label += 1;
+
+        queue.push(Queued {
+            elevation: elevation[idx],
+            order,
+            idx,
+        });
+        order += 1;
+    }
+
+    while let Some(Queued { idx, .. }) = queue.pop() {
+        let (x, y) = (idx % width, idx / width);
+
+        for yy in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+            for xx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                let neighbor = yy * width + xx;
+
+                if foreground[neighbor] && labels[neighbor] == 0 {
+                    labels[neighbor] = labels[idx];
+
+                    queue.push(Queued {
+                        elevation: elevation[neighbor],
+                        order,
+                        idx: neighbor,
+                    });
+                    order += 1;
+                }
+            }
+        }
+    }
+
+    // Seeds of small regions may be suppressed by a nearby higher maxima
+    let unreached: Vec<u32> = (0..elevation.len())
+        .map(|idx| (foreground[idx] && labels[idx] == 0) as u32)
+        .collect();
+
+    if unreached.contains(&1) {
+        let components = connected_components(width as u32, height as u32, &unreached);
+
+        for (label, component) in labels.iter_mut().zip(components) {
+            if component != 0 {
+                *label = next_label - 1 + component;
+            }
+        }
+    }
+
+    labels
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn bumps(width: usize, height: usize, centers: &[[f32; 2]], sigma: f32) -> Vec<f32> {
+        let mut buffer = vec![0f32; width * height];
+
+        for y in 0..height {
+            for x in 0..width {
+                for [cx, cy] in centers {
+                    let d2 = (x as f32 - cx).powi(2) + (y as f32 - cy).powi(2);
+                    buffer[y * width + x] += 0.9 * (-d2 / (2. * sigma * sigma)).exp();
+                }
+            }
+        }
+
+        buffer
+    }
+
+    fn count(labels: &[u32]) -> usize {
+        let mut unique: Vec<u32> = labels.iter().filter(|&&l| l != 0).copied().collect();
+        unique.sort_unstable();
+        unique.dedup();
+        unique.len()
+    }
+
+    #[test]
+    fn test_watershed_splits_touching_bumps() {
+        let elevation = bumps(30, 20, &[[9., 10.], [19., 10.]], 3.);
+
+        // Thresholding alone merges the two bumps into a single component
+        let binary: Vec<u32> = elevation.iter().map(|&e| (e > 0.3) as u32).collect();
+        assert_eq!(count(&connected_components(30, 20, &binary)), 1);
+
+        let labels = watershed(30, 20, &elevation, 0.3, 3);
+        assert_eq!(count(&labels), 2);
+        assert_ne!(labels[10 * 30 + 9], labels[10 * 30 + 19]);
+
+        // Seeds closer than the minimum distance are merged
+        assert_eq!(count(&watershed(30, 20, &elevation, 0.3, 12)), 1);
+    }
+
+    #[test]
+    fn test_watershed_labels_every_foreground_pixel() {
+        let elevation = bumps(30, 20, &[[5., 5.], [8., 14.], [22., 9.]], 2.);
+        let labels = watershed(30, 20, &elevation, 0.2, 20);
+
+        for (e, l) in elevation.iter().zip(&labels) {
+            assert_eq!(*e > 0.2, *l != 0);
+        }
+
+        assert_eq!(count(&labels), 3);
+    }
+}
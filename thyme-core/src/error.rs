@@ -7,18 +7,23 @@ use std::fmt;
 pub enum ThymeError {
     BoundingBoxError,
     BufferSizeError,
+    BufferOverflowError,
     ChannelBoundsError,
+    ChannelMapError(String),
     ConversionError,
     ImageError(&'static str),
     ImageReadError,
     ImageWriteError,
     ImageFormatError,
     ImageExtensionError,
+    NpyError(String),
     MaskError(&'static str),
     MaskFormatError,
+    MaskDepthError(String),
     PolygonsSizeError,
     PolygonsReadError,
     PolygonsWriteError,
+    PolygonsValueError,
     BoxesSizeError,
     BoxesReadError,
     BoxesWriteError,
@@ -42,12 +47,25 @@ impl fmt::Display for ThymeError {
                     "[thyme::BufferSizeError] The buffer does not match provided size"
                 )
             }
+            ThymeError::BufferOverflowError => {
+                write!(
+                    f,
+                    "[thyme::BufferOverflowError] The buffer dimensions exceed addressable memory."
+                )
+            }
             ThymeError::ChannelBoundsError => {
                 write!(
                     f,
                     "[thyme::ChannelBoundsError] The indexed channel is out of bounds."
                 )
             }
+            ThymeError::ChannelMapError(message) => {
+                write!(
+                    f,
+                    "[thyme::ChannelMapError] Image channels do not fit the channel map. {}.",
+                    message
+                )
+            }
             ThymeError::ConversionError => {
                 write!(
                     f,
@@ -75,13 +93,27 @@ impl fmt::Display for ThymeError {
                     "[thyme::ImageExtensionError] Could not detect a valid image extension for input."
                 )
             }
+            ThymeError::NpyError(message) => {
+                write!(
+                    f,
+                    "[thyme::NpyError] Failed to read numpy array. {}.",
+                    message
+                )
+            }
             ThymeError::MaskError(message) => {
                 write!(f, "[thyme::MaskError] Failed to create mask. {}", message)
             }
             ThymeError::MaskFormatError => {
                 write!(
                     f,
-                    "[thyme::MaskFormatError] Only 1-channel u8 and u16 masks are currently supported."
+                    "[thyme::MaskFormatError] Only 1-channel u8, u16, and u32 masks are currently supported."
+                )
+            }
+            ThymeError::MaskDepthError(message) => {
+                write!(
+                    f,
+                    "[thyme::MaskDepthError] Mask labels may be bit-depth truncated. {}.",
+                    message
                 )
             }
             ThymeError::PolygonsSizeError => {
@@ -99,6 +131,12 @@ impl fmt::Display for ThymeError {
                     "[thyme::PolygonsWriteError] Failed to successfully write polygons to output."
                 )
             }
+            ThymeError::PolygonsValueError => {
+                write!(
+                    f,
+                    "[thyme::PolygonsValueError] Polygons contain non-finite (NaN or infinite) vertices."
+                )
+            }
             ThymeError::BoxesSizeError => {
                 write!(
                     f,
@@ -0,0 +1,255 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use crate::cv::morphology::dilate;
+use crate::error::ThymeError;
+use crate::im::ThymeMask;
+
+/// Pixels used to measure the background of an object
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::BackgroundStyle;
+///
+/// assert_eq!(BackgroundStyle::parse("bbox").unwrap(), BackgroundStyle::BoundingBox);
+/// assert_eq!(BackgroundStyle::parse("ring:3").unwrap(), BackgroundStyle::Ring(3));
+/// assert!(BackgroundStyle::parse("ring:0").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackgroundStyle {
+    /// All pixels of the object crop outside of the object mask
+    #[default]
+    BoundingBox,
+    /// Pixels within this many pixels outside of the object boundary that
+    /// do not belong to any object
+    Ring(u32),
+}
+
+impl BackgroundStyle {
+    /// Parse a background style from a string (bbox or ring:<px>)
+    ///
+    /// # Arguments
+    ///
+    /// * `style` - String representation of the background style
+    pub fn parse(style: &str) -> Result<BackgroundStyle, ThymeError> {
+        let style = style.trim();
+
+        if style == "bbox" {
+            return Ok(BackgroundStyle::BoundingBox);
+        }
+
+        match style
+            .strip_prefix("ring:")
+            .map(|px| px.trim().parse::<u32>())
+        {
+            Some(Ok(px)) if px > 0 => Ok(BackgroundStyle::Ring(px)),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid background style '{}'. Must be one of: bbox, ring:<px> with px > 0",
+                style
+            ))),
+        }
+    }
+}
+
+impl ThymeMask {
+    /// Select the background pixels in a ring around a binary object crop
+    ///
+    /// The object is dilated by `radius` pixels within a window that extends
+    /// the crop by `radius` pixels on each side, clipped to the image borders.
+    /// Pixels of the dilated object are kept if they are neither part of the
+    /// object nor of any other object in the image-level foreground.
+    ///
+    /// Returns the origin of the window in image coordinates and a binary
+    /// mask of the ring pixels with the dimensions of the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Minimum x-coordinate of the crop in the image
+    /// * `y` - Minimum y-coordinate of the crop in the image
+    /// * `radius` - Width of the ring in pixels
+    /// * `foreground` - Row-major image pixels with non-zero values for object pixels
+    /// * `width` - Width of the image
+    /// * `height` - Height of the image
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::ThymeMask;
+    ///
+    /// // A single pixel object at (1, 1) next to another object at (3, 1)
+    /// let foreground: Vec<u32> = vec![
+    ///     0, 0, 0, 0,
+    ///     0, 1, 0, 2,
+    ///     0, 0, 0, 0,
+    /// ];
+    ///
+    /// let object = ThymeMask::new(1, 1, 1, vec![1]).unwrap();
+    /// let (x, y, ring) = object.background_ring(1, 1, 2, &foreground, 4, 3).unwrap();
+    ///
+    /// assert_eq!((x, y, ring.width(), ring.height()), (0, 0, 4, 3));
+    /// assert_eq!(ring.as_raw(), &vec![1, 1, 1, 1, 1, 0, 1, 0, 1, 1, 1, 1]);
+    /// ```
+    pub fn background_ring(
+        &self,
+        x: u32,
+        y: u32,
+        radius: u32,
+        foreground: &[u32],
+        width: u32,
+        height: u32,
+    ) -> Result<(u32, u32, ThymeMask), ThymeError> {
+        if foreground.len() != width as usize * height as usize {
+            return Err(ThymeError::MaskError(
+                "Foreground and image are not the same size.",
+            ));
+        }
+
+        if x as u64 + self.width() as u64 > width as u64
+            || y as u64 + self.height() as u64 > height as u64
+        {
+            return Err(ThymeError::MaskError("Cropping coordinates out of bounds"));
+        }
+
+        let x0 = x.saturating_sub(radius);
+        let y0 = y.saturating_sub(radius);
+        let x1 = (x + self.width()).saturating_add(radius).min(width);
+        let y1 = (y + self.height()).saturating_add(radius).min(height);
+
+        let (w, h) = ((x1 - x0) as usize, (y1 - y0) as usize);
+        let (ox, oy) = ((x - x0) as usize, (y - y0) as usize);
+
+        let c = self.channels() as usize;
+        let crop_w = self.width() as usize;
+        let object = self.as_raw();
+
+        let mut window = vec![0u32; w * h];
+
+        for row in 0..self.height() as usize {
+            for col in 0..crop_w {
+                window[(oy + row) * w + ox + col] = object[(row * crop_w + col) * c];
+            }
+        }
+
+        let ring = dilate(w as u32, h as u32, &window, radius)
+            .into_iter()
+            .zip(window)
+            .enumerate()
+            .map(|(idx, (dilated, object))| {
+                let image_idx = (y0 as usize + idx / w) * width as usize + x0 as usize + idx % w;
+                u32::from(dilated != 0 && object == 0 && foreground[image_idx] == 0)
+            })
+            .collect();
+
+        Ok((x0, y0, ThymeMask::new(w as u32, h as u32, 1, ring)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::im::{MaskingStyle, ThymeBuffer, ThymeImage};
+
+    /// Two 4 x 4 cells separated by a 2 pixel gap in a 14 x 8 image
+    fn two_cells() -> (u32, u32, Vec<u32>) {
+        let (width, height) = (14u32, 8u32);
+
+        let labels = (0..width * height)
+            .map(|i| match (i % width, i / width) {
+                (2..6, 2..6) => 1,
+                (8..12, 2..6) => 2,
+                _ => 0,
+            })
+            .collect();
+
+        (width, height, labels)
+    }
+
+    #[test]
+    fn test_background_style_parse() {
+        assert_eq!(
+            BackgroundStyle::parse(" ring: 4 ").unwrap(),
+            BackgroundStyle::Ring(4)
+        );
+        assert_eq!(BackgroundStyle::default(), BackgroundStyle::BoundingBox);
+        assert!(BackgroundStyle::parse("ring").is_err());
+        assert!(BackgroundStyle::parse("ring:-1").is_err());
+        assert!(BackgroundStyle::parse("box").is_err());
+    }
+
+    #[test]
+    fn test_background_ring_excludes_neighbors() {
+        let (width, height, labels) = two_cells();
+        let mask = ThymeMask::new(width, height, 1, labels.clone()).unwrap();
+
+        // Intensities of the background are 1 and cell B is 100
+        let pixels: Vec<u16> = labels.iter().map(|&l| [1, 50, 100][l as usize]).collect();
+        let image = ThymeImage::U16(ThymeBuffer::new(width, height, 1, pixels).unwrap());
+
+        // Crop of cell A padded by 4 pixels so the crop includes cell B
+        let object = mask.crop_binary(0, 0, 10, 8, 1).unwrap();
+        let (x, y, ring) = object
+            .background_ring(0, 0, 3, mask.as_raw(), width, height)
+            .unwrap();
+
+        assert_eq!((x, y, ring.width(), ring.height()), (0, 0, 13, 8));
+
+        for (idx, &selected) in ring.as_raw().iter().enumerate() {
+            let (col, row) = (idx as u32 % 13, idx as u32 / 13);
+
+            // Ring pixels are outside of both cells and within 3 pixels of cell A
+            let expected = labels[(row * width + col) as usize] == 0 && (col < 9);
+            assert_eq!(selected == 1, expected, "pixel ({}, {})", col, row);
+        }
+
+        let ring_view = ring.crop_view(0, 0, ring.width(), ring.height());
+        let view = image.crop_view(x, y, ring.width(), ring.height());
+        let ring_max = view
+            .masked(&ring_view, MaskingStyle::Foreground)
+            .intensity()[1];
+
+        let object_view = object.crop_view(0, 0, 10, 8);
+        let view = image.crop_view(0, 0, 10, 8);
+        let bbox_max = view
+            .masked(&object_view, MaskingStyle::Background)
+            .intensity()[1];
+
+        // Only the bounding box background includes the pixels of cell B
+        assert_eq!(ring_max, 1.0);
+        assert_eq!(bbox_max, 100.0);
+    }
+
+    #[test]
+    fn test_background_ring_image_borders() {
+        let (width, height, labels) = two_cells();
+        let mask = ThymeMask::new(width, height, 1, labels).unwrap();
+
+        // The ring window is clipped to the image when it extends past the borders
+        let object = mask.crop_binary(8, 2, 4, 4, 2).unwrap();
+        let (x, y, ring) = object
+            .background_ring(8, 2, 5, mask.as_raw(), width, height)
+            .unwrap();
+
+        assert_eq!((x, y, ring.width(), ring.height()), (3, 0, 11, 8));
+        assert_eq!(
+            ring.as_raw().iter().sum::<u32>(),
+            11 * 8 - 16 - labels_in_window(mask.as_raw(), width, 3, 6)
+        );
+
+        assert!(
+            object
+                .background_ring(12, 2, 1, mask.as_raw(), width, height)
+                .is_err()
+        );
+    }
+
+    /// Number of foreground pixels of cell A inside the columns [x0, x1) of the image
+    fn labels_in_window(labels: &[u32], width: u32, x0: u32, x1: u32) -> u32 {
+        labels
+            .iter()
+            .enumerate()
+            .filter(|&(idx, &l)| l == 1 && (x0..x1).contains(&(idx as u32 % width)))
+            .count() as u32
+    }
+}
@@ -1,21 +1,30 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufWriter, Read};
+use std::io::Read;
 use std::path::Path;
 
 use serde::Serialize;
 use serde_json::Value;
 
-use crate::constant::BOUNDING_BOX_JSON_VALID_KEYS;
+use crate::constant::{
+    BOUNDING_BOX_CSV_HEIGHT_KEYS, BOUNDING_BOX_CSV_IMAGE_KEYS, BOUNDING_BOX_CSV_LABEL_KEYS,
+    BOUNDING_BOX_CSV_MAX_X_KEYS, BOUNDING_BOX_CSV_MAX_Y_KEYS, BOUNDING_BOX_CSV_MIN_X_KEYS,
+    BOUNDING_BOX_CSV_MIN_Y_KEYS, BOUNDING_BOX_CSV_WIDTH_KEYS, BOUNDING_BOX_JSON_VALID_KEYS,
+};
+use crate::cv::transform::AffineTransform;
 use crate::error::ThymeError;
+use crate::io::write_atomic;
+use crate::ut::path::portable_file_stem;
 
 /// A bounding box container for storing locations of detected objects
 ///
 /// The bounding boxes are stored in xyxy format. Any input set of
 /// bounding boxes that has a box with a non-positive area will
-/// return an error.
+/// return an error. Each box can optionally carry a label (e.g. a
+/// class name) that is passed through to outputs.
 ///
 /// # Examples
 ///
@@ -33,6 +42,7 @@ use crate::error::ThymeError;
 #[derive(Debug, Clone)]
 pub struct BoundingBoxes {
     data: Vec<[f32; 4]>,
+    labels: Option<Vec<String>>,
 }
 
 impl BoundingBoxes {
@@ -62,7 +72,35 @@ impl BoundingBoxes {
             return Err(ThymeError::BoxesSizeError);
         }
 
-        Ok(Self { data })
+        Ok(Self { data, labels: None })
+    }
+
+    /// Attach a label to each bounding box
+    ///
+    /// # Arguments
+    ///
+    /// * `labels` - A label for each bounding box in the same order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::BoundingBoxes;
+    ///
+    /// let boxes = BoundingBoxes::new(vec![[0., 0., 1., 1.]]).unwrap();
+    /// let boxes = boxes.with_labels(vec!["cell".to_string()]).unwrap();
+    /// assert_eq!(boxes.labels().unwrap()[0], "cell");
+    /// ```
+    pub fn with_labels(mut self, labels: Vec<String>) -> Result<Self, ThymeError> {
+        if labels.len() != self.data.len() {
+            return Err(ThymeError::OtherError(format!(
+                "Found {} labels for {} bounding boxes",
+                labels.len(),
+                self.data.len()
+            )));
+        }
+
+        self.labels = Some(labels);
+        Ok(self)
     }
 }
 
@@ -92,11 +130,67 @@ impl BoundingBoxes {
             if ext == "json" {
                 return read_boxes_json(path);
             }
+
+            if ext == "csv" {
+                return read_boxes_csv(path, &BoxColumns::default());
+            }
         }
 
         Err(ThymeError::BoxesReadError)
     }
 
+    /// Open bounding boxes for a single image from a csv table
+    ///
+    /// Columns are detected from the header unless overridden. A csv without
+    /// a header must contain xmin, ymin, xmax, ymax, and an optional label in
+    /// that order.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to a csv table of bounding boxes
+    /// * `columns` - Column names overriding header detection
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use thyme_core::im::{BoundingBoxes, BoxColumns};
+    ///
+    /// let columns = BoxColumns::parse("xmin=left,ymin=top").unwrap();
+    /// let bounding_boxes = BoundingBoxes::open_csv("boxes.csv", &columns);
+    /// ```
+    pub fn open_csv<P: AsRef<Path>>(
+        path: P,
+        columns: &BoxColumns,
+    ) -> Result<BoundingBoxes, ThymeError> {
+        read_boxes_csv(path, columns)
+    }
+
+    /// Open bounding boxes for many images from a single csv table
+    ///
+    /// Boxes are grouped by the file stem of the image column (e.g. a value
+    /// of `plate/a.png` or `plate\a.png` is stored under `a`) and keep their
+    /// order in the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to a csv table of bounding boxes with an image column
+    /// * `columns` - Column names overriding header detection
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use thyme_core::im::{BoundingBoxes, BoxColumns};
+    ///
+    /// let boxes = BoundingBoxes::open_csv_images("boxes.csv", &BoxColumns::default()).unwrap();
+    /// let image_boxes = boxes.get("a");
+    /// ```
+    pub fn open_csv_images<P: AsRef<Path>>(
+        path: P,
+        columns: &BoxColumns,
+    ) -> Result<HashMap<String, BoundingBoxes>, ThymeError> {
+        read_boxes_csv_images(path, columns)
+    }
+
     /// Save bounding boxes at the provided path
     ///
     /// # Arguments
@@ -119,7 +213,10 @@ impl BoundingBoxes {
 
         if let Some(ext) = extension {
             if ext == "json" {
-                return write_boxes_json(path, &self.data);
+                return match &self.labels {
+                    Some(labels) => write_labeled_boxes_json(path, &self.data, labels),
+                    None => write_boxes_json(path, &self.data),
+                };
             }
         }
 
@@ -148,6 +245,11 @@ impl BoundingBoxes {
 // >>> CONVERSION METHODS
 
 impl BoundingBoxes {
+    /// Return a reference to the bounding box labels if provided
+    pub fn labels(&self) -> Option<&Vec<String>> {
+        self.labels.as_ref()
+    }
+
     /// Return a reference to underlying bounding boxes data
     pub fn as_xyxy(&self) -> &Vec<[f32; 4]> {
         &self.data
@@ -179,6 +281,7 @@ impl BoundingBoxes {
         }
 
         let mut data: Vec<[f32; 4]> = Vec::with_capacity(self.len() - indices.len());
+        let mut labels: Vec<String> = Vec::with_capacity(data.capacity());
         let mut indices_iter = indices.iter().peekable();
         let mut next_remove = indices_iter.next().copied();
 
@@ -186,11 +289,45 @@ impl BoundingBoxes {
             if Some(idx) == next_remove {
                 next_remove = indices_iter.next().copied();
             } else {
-                data.push(*bounding_box)
+                data.push(*bounding_box);
+
+                if let Some(l) = &self.labels {
+                    labels.push(l[idx].clone());
+                }
             }
         }
 
         self.data = data;
+
+        if self.labels.is_some() {
+            self.labels = Some(labels);
+        }
+    }
+
+    /// Apply an affine transform to the corners of each bounding box
+    ///
+    /// Each bounding box is replaced by the axis-aligned bounding box of its
+    /// transformed corners, so rotated boxes grow to enclose the rotated object.
+    ///
+    /// # Arguments
+    ///
+    /// * `transform` - Affine transform of pixel coordinates
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::cv::transform::AffineTransform;
+    /// use thyme_core::im::BoundingBoxes;
+    ///
+    /// let mut boxes = BoundingBoxes::new(vec![[1., 2., 4., 6.]]).unwrap();
+    /// boxes.affine(&AffineTransform::new([2., 0., 0., 2., 1., 0.]).unwrap());
+    ///
+    /// assert_eq!(boxes.as_xyxy()[0], [3., 4., 9., 12.]);
+    /// ```
+    pub fn affine(&mut self, transform: &AffineTransform) {
+        for xyxy in self.data.iter_mut() {
+            *xyxy = transform.apply_xyxy(xyxy);
+        }
     }
 }
 
@@ -241,7 +378,20 @@ pub fn read_boxes_json<P: AsRef<Path>>(path: P) -> Result<BoundingBoxes, ThymeEr
                 .collect();
 
             if let Ok(boxes) = boxes {
-                return BoundingBoxes::new(boxes);
+                let boxes = BoundingBoxes::new(boxes)?;
+
+                return match data.get("labels").and_then(|v| v.as_array()) {
+                    Some(labels) => boxes.with_labels(
+                        labels
+                            .iter()
+                            .map(|l| match l.as_str() {
+                                Some(l) => l.to_string(),
+                                None => l.to_string(),
+                            })
+                            .collect(),
+                    ),
+                    None => Ok(boxes),
+                };
             }
         };
     }
@@ -255,13 +405,358 @@ where
     P: AsRef<Path>,
     T: Serialize,
 {
-    let file = File::create(path).map_err(|_| ThymeError::BoxesWriteError)?;
-    let writer = BufWriter::new(file);
+    write_atomic(path, |writer| {
+        serde_json::to_writer(writer, &serde_json::json!({ "bounding_boxes": boxes }))
+            .map_err(|_| ThymeError::BoxesWriteError)
+    })
+}
 
-    serde_json::to_writer(writer, &serde_json::json!({ "bounding_boxes": boxes }))
-        .map_err(|_| ThymeError::BoxesWriteError)?;
+/// Write labeled bounding boxes to a json file
+fn write_labeled_boxes_json<P, T>(
+    path: P,
+    boxes: &Vec<[T; 4]>,
+    labels: &[String],
+) -> Result<(), ThymeError>
+where
+    P: AsRef<Path>,
+    T: Serialize,
+{
+    write_atomic(path, |writer| {
+        serde_json::to_writer(
+            writer,
+            &serde_json::json!({ "bounding_boxes": boxes, "labels": labels }),
+        )
+        .map_err(|_| ThymeError::BoxesWriteError)
+    })
+}
 
-    Ok(())
+/// Column names of bounding boxes stored in a csv table
+///
+/// Columns that are not provided are detected from the header using common
+/// names (e.g. xmin, x_min, x1, left). Boxes can be specified by their maximum
+/// coordinates or by a width and height.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::BoxColumns;
+///
+/// let columns = BoxColumns::parse("xmin=left,label=species").unwrap();
+/// assert_eq!(columns.min_x.as_deref(), Some("left"));
+/// assert_eq!(columns.label.as_deref(), Some("species"));
+/// assert!(BoxColumns::parse("radius=r").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BoxColumns {
+    /// Image the box belongs to in a multi-image table
+    pub image: Option<String>,
+    /// Minimum x-coordinate
+    pub min_x: Option<String>,
+    /// Minimum y-coordinate
+    pub min_y: Option<String>,
+    /// Maximum x-coordinate
+    pub max_x: Option<String>,
+    /// Maximum y-coordinate
+    pub max_y: Option<String>,
+    /// Box width used when a maximum x-coordinate is not available
+    pub width: Option<String>,
+    /// Box height used when a maximum y-coordinate is not available
+    pub height: Option<String>,
+    /// Label or class of the box
+    pub label: Option<String>,
+}
+
+impl BoxColumns {
+    /// Parse column names from comma-separated key=column pairs
+    ///
+    /// Valid keys are image, xmin, ymin, xmax, ymax, width, height, and label.
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - Column names (e.g. xmin=left,ymin=top,xmax=right,ymax=bottom)
+    pub fn parse(columns: &str) -> Result<Self, ThymeError> {
+        let mut parsed = BoxColumns::default();
+
+        for pair in columns.split(',').filter(|p| !p.trim().is_empty()) {
+            let Some((key, name)) = pair.split_once('=') else {
+                return Err(ThymeError::OtherError(format!(
+                    "Invalid box column '{}'. Columns must be provided as key=column pairs",
+                    pair
+                )));
+            };
+
+            let field = match key.trim().to_lowercase().as_str() {
+                "image" => &mut parsed.image,
+                "xmin" => &mut parsed.min_x,
+                "ymin" => &mut parsed.min_y,
+                "xmax" => &mut parsed.max_x,
+                "ymax" => &mut parsed.max_y,
+                "width" => &mut parsed.width,
+                "height" => &mut parsed.height,
+                "label" => &mut parsed.label,
+                key => {
+                    return Err(ThymeError::OtherError(format!(
+                        "Invalid box column key '{}'. Must be one of: image, xmin, ymin, xmax, ymax, width, height, label",
+                        key
+                    )));
+                }
+            };
+
+            *field = Some(name.trim().to_string());
+        }
+
+        Ok(parsed)
+    }
+
+    /// Check if no columns were provided
+    pub fn is_empty(&self) -> bool {
+        *self == BoxColumns::default()
+    }
+}
+
+/// Bounding box rows read from a csv table
+struct CsvBoxes {
+    images: Option<Vec<String>>,
+    boxes: Vec<[f32; 4]>,
+    labels: Option<Vec<String>>,
+}
+
+/// Split a csv row into trimmed fields, respecting double quotes
+fn split_csv_row(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Read bounding box rows from a csv table with or without a header
+fn read_csv<P: AsRef<Path>>(path: P, columns: &BoxColumns) -> Result<CsvBoxes, ThymeError> {
+    let mut contents = String::new();
+
+    File::open(path)
+        .map_err(|err| ThymeError::NoFileError(err.to_string()))?
+        .read_to_string(&mut contents)
+        .map_err(|err| ThymeError::NoFileError(err.to_string()))?;
+
+    let mut rows = contents
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(split_csv_row)
+        .peekable();
+
+    let Some(first) = rows.peek() else {
+        return Ok(CsvBoxes {
+            images: None,
+            boxes: vec![],
+            labels: None,
+        });
+    };
+
+    let numeric = |field: &str| field.parse::<f32>().is_ok();
+
+    // A header is detected if any field of the first row is not a number
+    let (indices, header) = if first.iter().all(|f| numeric(f)) {
+        if !columns.is_empty() {
+            return Err(ThymeError::OtherError(
+                "Box columns cannot be selected by name in a csv without a header".to_string(),
+            ));
+        }
+
+        let label = match first.len() {
+            4 => None,
+            5 => Some(4),
+            n => {
+                return Err(ThymeError::OtherError(format!(
+                    "A csv without a header must have 4 (xmin, ymin, xmax, ymax) or 5 (with label) columns, found {}",
+                    n
+                )));
+            }
+        };
+
+        (
+            [None, Some(0), Some(1), Some(2), Some(3), None, None, label],
+            None,
+        )
+    } else {
+        let header: Vec<String> = rows
+            .next()
+            .unwrap()
+            .iter()
+            .map(|h| h.to_lowercase())
+            .collect();
+
+        let find = |name: &Option<String>, keys: &[&str]| -> Result<Option<usize>, ThymeError> {
+            match name {
+                Some(name) => header
+                    .iter()
+                    .position(|h| *h == name.to_lowercase())
+                    .map(Some)
+                    .ok_or(ThymeError::OtherError(format!(
+                        "Box column '{}' was not found in the csv header",
+                        name
+                    ))),
+                None => Ok(keys.iter().find_map(|k| header.iter().position(|h| h == k))),
+            }
+        };
+
+        let indices = [
+            find(&columns.image, &BOUNDING_BOX_CSV_IMAGE_KEYS)?,
+            find(&columns.min_x, &BOUNDING_BOX_CSV_MIN_X_KEYS)?,
+            find(&columns.min_y, &BOUNDING_BOX_CSV_MIN_Y_KEYS)?,
+            find(&columns.max_x, &BOUNDING_BOX_CSV_MAX_X_KEYS)?,
+            find(&columns.max_y, &BOUNDING_BOX_CSV_MAX_Y_KEYS)?,
+            find(&columns.width, &BOUNDING_BOX_CSV_WIDTH_KEYS)?,
+            find(&columns.height, &BOUNDING_BOX_CSV_HEIGHT_KEYS)?,
+            find(&columns.label, &BOUNDING_BOX_CSV_LABEL_KEYS)?,
+        ];
+
+        (indices, Some(header))
+    };
+
+    let [image, min_x, min_y, max_x, max_y, width, height, label] = indices;
+
+    let (Some(min_x), Some(min_y)) = (min_x, min_y) else {
+        return Err(ThymeError::OtherError(format!(
+            "Could not detect xmin and ymin columns in csv header {:?}. Columns can be set with box_columns",
+            header.unwrap_or_default()
+        )));
+    };
+
+    let (Some(x), Some(y)) = (max_x.or(width), max_y.or(height)) else {
+        return Err(ThymeError::OtherError(format!(
+            "Could not detect xmax and ymax (or width and height) columns in csv header {:?}. Columns can be set with box_columns",
+            header.unwrap_or_default()
+        )));
+    };
+
+    let mut boxes = CsvBoxes {
+        images: image.map(|_| vec![]),
+        boxes: vec![],
+        labels: label.map(|_| vec![]),
+    };
+
+    let offset = if header.is_some() { 2 } else { 1 };
+
+    for (i, row) in rows.enumerate() {
+        let field = |j: usize| -> Result<&str, ThymeError> {
+            row.get(j)
+                .map(|f| f.as_str())
+                .ok_or(ThymeError::OtherError(format!(
+                    "Row {} of the bounding box csv has {} columns, expected at least {}",
+                    i + offset,
+                    row.len(),
+                    j + 1
+                )))
+        };
+
+        let value = |j: usize| -> Result<f32, ThymeError> {
+            let v = field(j)?;
+            v.parse::<f32>().map_err(|_| {
+                ThymeError::OtherError(format!(
+                    "Invalid bounding box value '{}' in row {} of the bounding box csv",
+                    v,
+                    i + offset
+                ))
+            })
+        };
+
+        let (min_x, min_y) = (value(min_x)?, value(min_y)?);
+
+        let max_x = if max_x.is_some() {
+            value(x)?
+        } else {
+            min_x + value(x)?
+        };
+        let max_y = if max_y.is_some() {
+            value(y)?
+        } else {
+            min_y + value(y)?
+        };
+
+        boxes.boxes.push([min_x, min_y, max_x, max_y]);
+
+        if let (Some(images), Some(j)) = (boxes.images.as_mut(), image) {
+            images.push(field(j)?.to_string());
+        }
+
+        if let (Some(labels), Some(j)) = (boxes.labels.as_mut(), label) {
+            labels.push(field(j)?.to_string());
+        }
+    }
+
+    Ok(boxes)
+}
+
+/// Read bounding boxes for a single image stored as csv format
+fn read_boxes_csv<P: AsRef<Path>>(
+    path: P,
+    columns: &BoxColumns,
+) -> Result<BoundingBoxes, ThymeError> {
+    let csv = read_csv(path, columns)?;
+    let boxes = BoundingBoxes::new(csv.boxes)?;
+
+    match csv.labels {
+        Some(labels) => boxes.with_labels(labels),
+        None => Ok(boxes),
+    }
+}
+
+/// Read bounding boxes for many images stored in a single csv table
+fn read_boxes_csv_images<P: AsRef<Path>>(
+    path: P,
+    columns: &BoxColumns,
+) -> Result<HashMap<String, BoundingBoxes>, ThymeError> {
+    let csv = read_csv(path, columns)?;
+
+    let Some(images) = csv.images else {
+        return Err(ThymeError::OtherError(
+            "Could not detect an image column in the bounding box csv. The column can be set with box_columns (e.g. image=file)".to_string(),
+        ));
+    };
+
+    let mut grouped: HashMap<String, (Vec<[f32; 4]>, Vec<String>)> = HashMap::new();
+
+    for (i, image) in images.iter().enumerate() {
+        let stem = portable_file_stem(image).unwrap_or_default().to_string();
+
+        let (boxes, labels) = grouped.entry(stem).or_default();
+        boxes.push(csv.boxes[i]);
+
+        if let Some(l) = &csv.labels {
+            labels.push(l[i].clone());
+        }
+    }
+
+    grouped
+        .into_iter()
+        .map(|(image, (boxes, labels))| {
+            let boxes = BoundingBoxes::new(boxes)?;
+
+            let boxes = match csv.labels {
+                Some(_) => boxes.with_labels(labels)?,
+                None => boxes,
+            };
+
+            Ok((image, boxes))
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -306,4 +801,135 @@ mod test {
 
         std::fs::remove_file(OUTPUT).unwrap();
     }
+
+    /// Write a csv table to a temporary file
+    fn write_csv(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("thyme_test_boxes_{}.csv", name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    pub fn test_open_csv_header_variants() {
+        let expected = vec![[0., 1., 4., 5.], [2., 3., 6., 9.]];
+
+        for (i, header) in [
+            "xmin,ymin,xmax,ymax",
+            "x_min,y_min,x_max,y_max",
+            "X1,Y1,X2,Y2",
+            "left,top,right,bottom",
+            "\"min_x\", \"min_y\", \"max_x\", \"max_y\"",
+        ]
+        .iter()
+        .enumerate()
+        {
+            let path = write_csv(
+                &format!("variant_{}", i),
+                &format!("{}\n0,1,4,5\n2,3,6,9\n", header),
+            );
+
+            let boxes = BoundingBoxes::open(&path).unwrap();
+            assert_eq!(*boxes.as_xyxy(), expected, "{}", header);
+            assert!(boxes.labels().is_none());
+
+            std::fs::remove_file(path).unwrap();
+        }
+
+        let path = write_csv("xywh", "x,y,width,height,class\n0,1,4,4,a\n2,3,4,6,b\n");
+        let boxes = BoundingBoxes::open(&path).unwrap();
+        assert_eq!(*boxes.as_xyxy(), vec![[0., 1., 4., 5.], [2., 3., 6., 9.]]);
+        assert_eq!(boxes.labels().unwrap(), &vec!["a", "b"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn test_open_csv_headerless() {
+        let path = write_csv("headerless", "0,1,4,5\n2.5,3,6,9\n");
+        let boxes = BoundingBoxes::open(&path).unwrap();
+        assert_eq!(*boxes.as_xyxy(), vec![[0., 1., 4., 5.], [2.5, 3., 6., 9.]]);
+        std::fs::remove_file(path).unwrap();
+
+        let path = write_csv("headerless_label", "0,1,4,5,1\n2,3,6,9,2\n");
+        let boxes = BoundingBoxes::open(&path).unwrap();
+        assert_eq!(boxes.labels().unwrap(), &vec!["1", "2"]);
+        std::fs::remove_file(path).unwrap();
+
+        let path = write_csv("headerless_invalid", "0,1,4\n");
+        assert!(BoundingBoxes::open(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn test_open_csv_columns() {
+        let path = write_csv(
+            "columns",
+            "id,a,b,c,d,kind\n0,0,1,4,5,cell\n1,2,3,6,9,debris\n",
+        );
+
+        assert!(BoundingBoxes::open(&path).is_err());
+
+        let columns = BoxColumns::parse("xmin=a,ymin=b,xmax=c,ymax=d,label=kind").unwrap();
+        let boxes = BoundingBoxes::open_csv(&path, &columns).unwrap();
+        assert_eq!(*boxes.as_xyxy(), vec![[0., 1., 4., 5.], [2., 3., 6., 9.]]);
+        assert_eq!(boxes.labels().unwrap(), &vec!["cell", "debris"]);
+
+        let columns = BoxColumns::parse("xmin=missing").unwrap();
+        assert!(BoundingBoxes::open_csv(&path, &columns).is_err());
+
+        std::fs::remove_file(path).unwrap();
+
+        assert!(BoxColumns::parse("xmin").is_err());
+        assert!(BoxColumns::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    pub fn test_open_csv_images() {
+        let path = write_csv(
+            "images",
+            "image,xmin,ymin,xmax,ymax,label\nplate/a.png,0,0,2,2,x\nC:\\data\\b.tif,1,1,3,3,y\na,4,4,5,5,z\n",
+        );
+
+        let boxes = BoundingBoxes::open_csv_images(&path, &BoxColumns::default()).unwrap();
+
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(
+            *boxes["a"].as_xyxy(),
+            vec![[0., 0., 2., 2.], [4., 4., 5., 5.]]
+        );
+        assert_eq!(boxes["a"].labels().unwrap(), &vec!["x", "z"]);
+        assert_eq!(*boxes["b"].as_xyxy(), vec![[1., 1., 3., 3.]]);
+
+        std::fs::remove_file(path).unwrap();
+
+        let path = write_csv("images_missing", "0,0,2,2\n");
+        assert!(BoundingBoxes::open_csv_images(&path, &BoxColumns::default()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn test_labels_remove_and_save() {
+        const OUTPUT: &str = "TEST_BOX_WRITE_LABELS.json";
+
+        let mut boxes =
+            BoundingBoxes::new(vec![[0., 0., 1., 1.], [0., 0., 2., 2.], [1., 1., 3., 3.]])
+                .unwrap()
+                .with_labels(vec!["a".into(), "b".into(), "c".into()])
+                .unwrap();
+
+        boxes.remove(&[1]);
+        assert_eq!(boxes.labels().unwrap(), &vec!["a", "c"]);
+
+        boxes.save(OUTPUT).unwrap();
+        let reloaded = BoundingBoxes::open(OUTPUT).unwrap();
+        assert_eq!(reloaded.as_xyxy(), boxes.as_xyxy());
+        assert_eq!(reloaded.labels(), boxes.labels());
+        std::fs::remove_file(OUTPUT).unwrap();
+
+        assert!(
+            BoundingBoxes::new(vec![[0., 0., 1., 1.]])
+                .unwrap()
+                .with_labels(vec![])
+                .is_err()
+        );
+    }
 }
@@ -6,7 +6,7 @@ use std::marker::PhantomData;
 use std::ops::Deref;
 use std::slice::ChunksExact;
 
-use num::{FromPrimitive, ToPrimitive};
+use num::{Bounded, FromPrimitive, ToPrimitive, Zero};
 
 use crate::error::ThymeError;
 use crate::im::{MaskingStyle, ThymeMaskView, ThymeViewBuffer};
@@ -46,6 +46,9 @@ use crate::im::{MaskingStyle, ThymeMaskView, ThymeViewBuffer};
 ///
 /// assert!(buffer.is_err()); // Buffer size does not match dimensions
 /// ```
+///
+/// Buffer sizes are computed with 64-bit arithmetic, so large whole-slide
+/// planes (e.g. 70,000 x 70,000 pixels) are supported wherever memory allows.
 #[derive(Debug, Clone)]
 pub struct ThymeBuffer<T, Container> {
     w: u32,                   // Width
@@ -82,17 +85,58 @@ where
         channels: u32,
         buffer: Container,
     ) -> Result<ThymeBuffer<T, Container>, ThymeError> {
-        if width * height * channels == buffer.len() as u32 {
-            Ok(ThymeBuffer {
-                w: width,
-                h: height,
-                c: channels,
-                buffer,
-                _phantom: PhantomData,
-            })
-        } else {
-            Err(ThymeError::BufferSizeError)
-        }
+        check_len::<T>(width, height, channels, buffer.len())?;
+
+        Ok(ThymeBuffer {
+            w: width,
+            h: height,
+            c: channels,
+            buffer,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+/// Number of subpixels in a buffer with the provided dimensions
+///
+/// The product is computed with 64-bit arithmetic and an error is returned
+/// if the buffer would exceed the memory addressable on this platform.
+///
+/// # Arguments
+///
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `channels` - Number of image channels
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::buffer_len;
+///
+/// assert_eq!(buffer_len::<u8>(10, 10, 3).unwrap(), 300);
+/// assert!(buffer_len::<u8>(u32::MAX, u32::MAX, u32::MAX).is_err());
+/// ```
+pub fn buffer_len<T>(width: u32, height: u32, channels: u32) -> Result<usize, ThymeError> {
+    let len = (width as u64)
+        .checked_mul(height as u64)
+        .and_then(|len| len.checked_mul(channels as u64))
+        .ok_or(ThymeError::BufferOverflowError)?;
+
+    let max_len = isize::MAX as u64 / std::mem::size_of::<T>().max(1) as u64;
+
+    if len > max_len {
+        return Err(ThymeError::BufferOverflowError);
+    }
+
+    usize::try_from(len).map_err(|_| ThymeError::BufferOverflowError)
+}
+
+/// Check that a buffer length matches the provided dimensions
+fn check_len<T>(width: u32, height: u32, channels: u32, len: usize) -> Result<(), ThymeError> {
+    if buffer_len::<T>(width, height, channels)? == len {
+        Ok(())
+    } else {
+        Err(ThymeError::BufferSizeError)
     }
 }
 
@@ -125,7 +169,7 @@ where
 
     /// Length of the raw image
     pub fn len(&self) -> usize {
-        (self.w * self.h * self.c) as usize
+        self.w as usize * self.h as usize * self.c as usize
     }
 
     /// Check if buffer is empty
@@ -153,22 +197,119 @@ where
         &self.buffer
     }
 
-    /// Cast subpixels to u8 and return the buffer
-    pub fn to_u8(&self) -> Vec<u8> {
+    /// Cast subpixels to u8, setting out-of-range values to zero
+    ///
+    /// Values above 255 become black rather than saturating, so this should
+    /// only be used when all subpixels are known to be in range.
+    pub fn to_u8_lossy_zero(&self) -> Vec<u8> {
         self.buffer
             .iter()
             .map(|x| x.to_u8().unwrap_or(0u8))
             .collect()
     }
 
-    /// Cast subpixels to u16 and return the buffer
-    pub fn to_u16(&self) -> Vec<u16> {
+    /// Cast subpixels to u8, saturating out-of-range values to 0 or 255
+    pub fn to_u8_clamped(&self) -> Vec<u8> {
+        self.cast_clamped()
+    }
+
+    /// Linearly scale subpixels from the buffer minimum and maximum to [0, 255]
+    pub fn to_u8_scaled(&self) -> Vec<u8> {
+        self.cast_scaled(self.min_max(), [0.0, u8::MAX as f64], true)
+    }
+
+    /// Cast subpixels to u16, setting out-of-range values to zero
+    ///
+    /// Values above 65535 become black rather than saturating, so this should
+    /// only be used when all subpixels are known to be in range.
+    pub fn to_u16_lossy_zero(&self) -> Vec<u16> {
         self.buffer
             .iter()
             .map(|x| x.to_u16().unwrap_or(0u16))
             .collect()
     }
 
+    /// Cast subpixels to u16, saturating out-of-range values to 0 or 65535
+    pub fn to_u16_clamped(&self) -> Vec<u16> {
+        self.cast_clamped()
+    }
+
+    /// Linearly scale subpixels from the buffer minimum and maximum to [0, 65535]
+    pub fn to_u16_scaled(&self) -> Vec<u16> {
+        self.cast_scaled(self.min_max(), [0.0, u16::MAX as f64], true)
+    }
+
+    /// Return the minimum and maximum subpixel values (NaN values are ignored)
+    pub fn min_max(&self) -> [f64; 2] {
+        self.buffer
+            .iter()
+            .filter_map(|x| x.to_f64())
+            .filter(|x| !x.is_nan())
+            .fold(None, |range: Option<[f64; 2]>, x| match range {
+                Some([min, max]) => Some([min.min(x), max.max(x)]),
+                None => Some([x, x]),
+            })
+            .unwrap_or([0.0, 0.0])
+    }
+
+    /// Cast subpixels to another type, saturating values outside its range
+    pub(crate) fn cast_clamped<U>(&self) -> Vec<U>
+    where
+        U: FromPrimitive + Bounded + Zero + ToPrimitive,
+    {
+        let min = U::min_value().to_f64().unwrap_or(f64::MIN);
+        let max = U::max_value().to_f64().unwrap_or(f64::MAX);
+
+        self.buffer
+            .iter()
+            .map(|x| {
+                let x = x.to_f64().unwrap_or(f64::NAN);
+                if x.is_nan() {
+                    U::from_f64(x).unwrap_or(U::zero())
+                } else if x <= min {
+                    U::min_value()
+                } else if x >= max {
+                    U::max_value()
+                } else {
+                    U::from_f64(x).unwrap_or(U::zero())
+                }
+            })
+            .collect()
+    }
+
+    /// Linearly map subpixels from one range to another, saturating at the bounds
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Input range mapped to the output range
+    /// * `to` - Output range
+    /// * `round` - Round scaled values to the nearest integer
+    pub(crate) fn cast_scaled<U>(&self, from: [f64; 2], to: [f64; 2], round: bool) -> Vec<U>
+    where
+        U: FromPrimitive + Zero,
+    {
+        let [lo, hi] = from;
+        let span = hi - lo;
+
+        self.buffer
+            .iter()
+            .map(|x| {
+                let x = x.to_f64().unwrap_or(f64::NAN);
+
+                // Constant buffers have no range so they map to the lower bound
+                let scaled = if span > 0.0 {
+                    to[0] + ((x - lo) * (to[1] - to[0]) / span).clamp(0.0, to[1] - to[0])
+                } else {
+                    to[0]
+                };
+
+                let scaled = if round { scaled.round() } else { scaled };
+
+                U::from_f64(scaled).unwrap_or(U::zero())
+            })
+            .collect()
+    }
+
     /// Cast subpixels to u16 and return the buffer
     pub fn to_u32(&self) -> Vec<u32> {
         self.buffer
@@ -255,7 +396,7 @@ where
         w: u32,
         h: u32,
     ) -> Result<ThymeBuffer<T, Container>, ThymeError> {
-        if x + w > self.w || y + h > self.h {
+        if x as u64 + w as u64 > self.w as u64 || y as u64 + h as u64 > self.h as u64 {
             return Err(ThymeError::ImageError("Cropping coordinates out of bounds"));
         }
 
@@ -263,7 +404,7 @@ where
         let orig_w = self.w as usize;
         let orig_buffer = self.buffer.as_ref();
 
-        let mut new_buffer = Vec::with_capacity((w * h * self.c) as usize);
+        let mut new_buffer = Vec::with_capacity(w as usize * h as usize * c);
 
         for row in y..y + h {
             let start = ((row as usize) * orig_w + (x as usize)) * c;
@@ -335,6 +476,68 @@ where
             _phantom: PhantomData,
         })
     }
+
+    /// Create a new buffer by gathering channels in the provided order
+    ///
+    /// Output channel i is a copy of input channel `channels[i]`, so channels
+    /// can be reordered, dropped, or replicated.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - Input channel index for each output channel
+    pub fn gather_channels(
+        &self,
+        channels: &[u32],
+    ) -> Result<ThymeBuffer<T, Container>, ThymeError> {
+        if channels.is_empty() {
+            return Err(ThymeError::ChannelMapError(
+                "At least one channel must be gathered".to_string(),
+            ));
+        }
+
+        if channels.iter().any(|&channel| channel >= self.c) {
+            return Err(ThymeError::ChannelBoundsError);
+        }
+
+        let mut gathered = Vec::with_capacity(self.w as usize * self.h as usize * channels.len());
+
+        for pixel in self.buffer.chunks_exact(self.c as usize) {
+            for &channel in channels {
+                gathered.push(pixel[channel as usize].clone());
+            }
+        }
+
+        Ok(ThymeBuffer {
+            w: self.w,
+            h: self.h,
+            c: channels.len() as u32,
+            buffer: Container::from_iter(gathered),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Create a new single channel buffer by averaging over channels
+    ///
+    /// Averages are rounded to the nearest value for integer types.
+    pub fn average_channels(&self) -> ThymeBuffer<T, Container> {
+        // Integer types truncate 0.5 to zero when converting from a float
+        let integer = T::from_f64(0.5).and_then(|v| v.to_f64()) == Some(0.0);
+        let rc = 1f64 / self.c as f64;
+
+        let averaged = self.buffer.chunks_exact(self.c as usize).map(|pixel| {
+            let mean = pixel.iter().map(|v| v.to_f64().unwrap()).sum::<f64>() * rc;
+            let mean = if integer { mean.round() } else { mean };
+            T::from_f64(mean).unwrap()
+        });
+
+        ThymeBuffer {
+            w: self.w,
+            h: self.h,
+            c: 1,
+            buffer: Container::from_iter(averaged),
+            _phantom: PhantomData,
+        }
+    }
 }
 
 // <<< TRANSFORM METHODS
@@ -402,7 +605,7 @@ mod test {
     fn test_buffer_to_u8() {
         let buffer = ThymeBuffer::new(1, 2, 2, [2.5, 3.9, 4.8, 2.2].as_slice()).unwrap();
 
-        let u8_vec = buffer.to_u8();
+        let u8_vec = buffer.to_u8_lossy_zero();
         assert_eq!(u8_vec, [2, 3, 4, 2]);
     }
 
@@ -410,10 +613,32 @@ mod test {
     fn test_buffer_to_u16() {
         let buffer = ThymeBuffer::new(1, 2, 2, [2.5, 3.9, 4.8, 2.2].as_slice()).unwrap();
 
-        let u16_vec = buffer.to_u16();
+        let u16_vec = buffer.to_u16_lossy_zero();
         assert_eq!(u16_vec, [2, 3, 4, 2]);
     }
 
+    #[test]
+    fn test_buffer_u16_to_u8_policies() {
+        let buffer = ThymeBuffer::new(2, 2, 1, vec![0u16, 100, 300, 1000]).unwrap();
+
+        assert_eq!(buffer.to_u8_lossy_zero(), [0, 100, 0, 0]);
+        assert_eq!(buffer.to_u8_clamped(), [0, 100, 255, 255]);
+        assert_eq!(buffer.to_u8_scaled(), [0, 26, 77, 255]);
+
+        let constant = ThymeBuffer::new(1, 2, 1, vec![700u16, 700]).unwrap();
+        assert_eq!(constant.to_u8_scaled(), [0, 0]);
+    }
+
+    #[test]
+    fn test_buffer_float_to_u16_policies() {
+        let buffer = ThymeBuffer::new(1, 4, 1, vec![-1.0f32, 0.5, 70000.0, f32::NAN]).unwrap();
+
+        assert_eq!(buffer.to_u16_lossy_zero(), [0, 0, 0, 0]);
+        assert_eq!(buffer.to_u16_clamped(), [0, 0, 65535, 0]);
+        assert_eq!(buffer.min_max(), [-1.0, 70000.0]);
+        assert_eq!(buffer.to_u16_scaled()[..3], [0, 1, 65535]);
+    }
+
     #[test]
     fn test_iter() {
         let buffer = ThymeBuffer::new(1, 3, 2, [1, 2, 3, 4, 5, 6].as_slice()).unwrap();
@@ -459,4 +684,76 @@ mod test {
             assert_eq!(a[1], b[1]);
         }
     }
+
+    #[test]
+    fn test_gather_channels() {
+        let buffer: ThymeBuffer<u8, Vec<u8>> =
+            ThymeBuffer::new(2, 1, 3, vec![1, 2, 3, 4, 5, 6]).unwrap();
+
+        let gathered = buffer.gather_channels(&[1, 2, 0]).unwrap();
+        assert_eq!(gathered.shape(), (1, 2, 3));
+        assert_eq!(gathered.as_raw(), &vec![2, 3, 1, 5, 6, 4]);
+
+        let replicated = buffer.gather_channels(&[2, 2]).unwrap();
+        assert_eq!(replicated.channels(), 2);
+        assert_eq!(replicated.as_raw(), &vec![3, 3, 6, 6]);
+
+        assert!(buffer.gather_channels(&[0, 3]).is_err());
+        assert!(buffer.gather_channels(&[]).is_err());
+    }
+
+    #[test]
+    fn test_average_channels() {
+        let buffer: ThymeBuffer<u8, Vec<u8>> = ThymeBuffer::new(2, 1, 2, vec![1, 2, 4, 8]).unwrap();
+
+        let averaged = buffer.average_channels();
+        assert_eq!(averaged.channels(), 1);
+        assert_eq!(averaged.as_raw(), &vec![2, 6]);
+
+        let buffer: ThymeBuffer<f32, Vec<f32>> =
+            ThymeBuffer::new(2, 1, 2, vec![1., 2., 4., 8.]).unwrap();
+
+        assert_eq!(buffer.average_channels().as_raw(), &vec![1.5, 6.0]);
+    }
+
+    #[test]
+    fn test_check_len_uses_64_bit_arithmetic() {
+        // 65,536 x 65,536 wraps to zero with 32-bit arithmetic
+        assert!(matches!(
+            check_len::<u8>(65_536, 65_536, 1, 0),
+            Err(ThymeError::BufferSizeError)
+        ));
+
+        #[cfg(target_pointer_width = "64")]
+        assert!(check_len::<u8>(65_536, 65_536, 1, 1 << 32).is_ok());
+
+        assert!(matches!(
+            buffer_len::<u8>(u32::MAX, u32::MAX, u32::MAX),
+            Err(ThymeError::BufferOverflowError)
+        ));
+
+        assert!(matches!(
+            buffer_len::<f64>(u32::MAX, u32::MAX, 1),
+            Err(ThymeError::BufferOverflowError)
+        ));
+    }
+
+    #[test]
+    #[ignore = "allocates a ~4.9 GB buffer"]
+    fn test_buffer_above_u32_pixel_count() {
+        let width = 70_000u32;
+        let height = 70_000u32;
+
+        let mut data = vec![0u8; width as usize * height as usize];
+        *data.last_mut().unwrap() = 7;
+
+        let buffer: ThymeBuffer<u8, Vec<u8>> = ThymeBuffer::new(width, height, 1, data).unwrap();
+        assert_eq!(buffer.len(), 4_900_000_000);
+
+        let view = buffer.crop_view(width - 2, height - 2, 2, 2);
+        assert_eq!(view.iter().copied().collect::<Vec<u8>>(), vec![0, 0, 0, 7]);
+
+        let crop = buffer.crop(width - 2, height - 2, 2, 2).unwrap();
+        assert_eq!(crop.as_raw(), &vec![0, 0, 0, 7]);
+    }
 }
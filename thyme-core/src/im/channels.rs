@@ -0,0 +1,540 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::fmt;
+
+use crate::error::ThymeError;
+
+/// A mapping from input image channels to output image channels
+///
+/// Channel maps make the conversion of arbitrary microscopy images into the
+/// fixed-channel inputs expected by neural network models explicit.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::ChannelMap;
+///
+/// // Replicate the first channel into three output channels
+/// let map = ChannelMap::parse("0,0,0").unwrap();
+/// assert_eq!(map, ChannelMap::Gather(vec![0, 0, 0]));
+///
+/// // Average all input channels into a single output channel
+/// let map = ChannelMap::parse("gray-average").unwrap();
+/// assert_eq!(map, ChannelMap::GrayAverage);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelMap {
+    /// Replicate 1-channel images, keep 3-channel images, and average others
+    Auto,
+    /// Output channel i is a copy of input channel map\[i\]
+    Gather(Vec<u32>),
+    /// Average all input channels into a single output channel
+    GrayAverage,
+}
+
+impl ChannelMap {
+    /// Parse a channel map from a string
+    ///
+    /// Valid channel maps are `auto`, `gray-average`, or a comma-separated
+    /// list of input channel indices (e.g. `0,0,0` or `1,2,0`).
+    ///
+    /// # Arguments
+    ///
+    /// * `map` - String representation of the channel map
+    pub fn parse(map: &str) -> Result<ChannelMap, ThymeError> {
+        let map = map.trim();
+
+        match map {
+            "auto" => Ok(ChannelMap::Auto),
+            "gray-average" => Ok(ChannelMap::GrayAverage),
+            _ => map
+                .split(',')
+                .map(|index| index.trim().parse::<u32>())
+                .collect::<Result<Vec<u32>, _>>()
+                .map(ChannelMap::Gather)
+                .map_err(|_| {
+                    ThymeError::OtherError(format!(
+                        "Invalid channel map '{}'. Must be auto, gray-average, or comma-separated channel indices (e.g. 0,0,0)",
+                        map
+                    ))
+                }),
+        }
+    }
+
+    /// Resolve the channel map for an image with the provided number of channels
+    ///
+    /// Explicit channel maps are returned unchanged and `Auto` is replaced by
+    /// the explicit channel map it applies to an image with `channels` channels.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - Number of channels in the input image
+    pub fn resolve(&self, channels: u32) -> ChannelMap {
+        match self {
+            ChannelMap::Auto => match channels {
+                1 => ChannelMap::Gather(vec![0, 0, 0]),
+                3 => ChannelMap::Gather(vec![0, 1, 2]),
+                _ => ChannelMap::GrayAverage,
+            },
+            map => map.clone(),
+        }
+    }
+
+    /// Check that an image with the provided number of channels fits the channel map
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - Number of channels in the input image
+    pub fn check(&self, channels: u32) -> Result<(), ThymeError> {
+        if let ChannelMap::Gather(indices) = self {
+            if let Some(&index) = indices.iter().find(|&&index| index >= channels) {
+                return Err(ThymeError::ChannelMapError(format!(
+                    "Channel map {} requires at least {} channels but the image has {}",
+                    self,
+                    index + 1,
+                    channels
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that an image with the provided number of channels can be passed to a model
+    ///
+    /// Models take 3-channel inputs, so unlike `check`, `Auto` only accepts
+    /// 1 and 3-channel images. Averaging other images (e.g. a stack of six
+    /// fluorescence channels) into a single channel is rarely intended, and
+    /// such images must be converted with `GrayAverage` or explicit indices.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - Number of channels in the input image
+    pub fn check_input(&self, channels: u32) -> Result<(), ThymeError> {
+        match self {
+            ChannelMap::Auto if channels != 1 && channels != 3 => {
+                Err(ThymeError::ChannelMapError(format!(
+                    "Channel map auto only accepts 1 or 3-channel images but the image has {} channels. Use gray-average or channel indices (e.g. 0,1,2) to convert other images",
+                    channels
+                )))
+            }
+            map => map.check(channels),
+        }
+    }
+
+    /// Image shapes accepted by `check_input`, as (height, width, channels)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::ChannelMap;
+    ///
+    /// assert_eq!(ChannelMap::Auto.accepted_shapes(), "(H, W), (H, W, 1), or (H, W, 3)");
+    /// assert_eq!(
+    ///     ChannelMap::parse("0,3,1").unwrap().accepted_shapes(),
+    ///     "(H, W, C) with C >= 4"
+    /// );
+    /// ```
+    pub fn accepted_shapes(&self) -> String {
+        match self {
+            ChannelMap::Auto => "(H, W), (H, W, 1), or (H, W, 3)".to_string(),
+            ChannelMap::Gather(indices) => match indices.iter().max() {
+                Some(&index) if index > 0 => format!("(H, W, C) with C >= {}", index + 1),
+                _ => "(H, W) or (H, W, C)".to_string(),
+            },
+            ChannelMap::GrayAverage => "(H, W) or (H, W, C)".to_string(),
+        }
+    }
+
+    /// Number of output channels, if independent of the input image
+    pub fn channels(&self) -> Option<usize> {
+        match self {
+            ChannelMap::Auto => None,
+            ChannelMap::Gather(indices) => Some(indices.len()),
+            ChannelMap::GrayAverage => Some(1),
+        }
+    }
+
+    /// A human-readable description of the channel map
+    pub fn describe(&self) -> String {
+        match self {
+            ChannelMap::Auto => {
+                "auto (1 channel: 0,0,0; 3 channels: 0,1,2; otherwise: rejected)".to_string()
+            }
+            map => map.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ChannelMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChannelMap::Auto => write!(f, "auto"),
+            ChannelMap::GrayAverage => write!(f, "gray-average"),
+            ChannelMap::Gather(indices) => {
+                let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+                write!(f, "{}", indices.join(","))
+            }
+        }
+    }
+}
+
+/// Image channels that each descriptor family is computed for
+///
+/// Selected families are computed separately for each of their channels
+/// instead of being averaged over all channels. Families without channels
+/// are not computed.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::FeatureChannels;
+///
+/// let channels = FeatureChannels::parse("intensity:0-2;texture:0,1;moments:0").unwrap();
+/// assert_eq!(channels.intensity, vec![0, 1, 2]);
+/// assert_eq!(channels.texture, vec![0, 1]);
+/// assert!(channels.zernike.is_empty());
+/// assert_eq!(channels.union(), vec![0, 1, 2]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureChannels {
+    /// Channels of the intensity descriptors
+    pub intensity: Vec<usize>,
+    /// Channels of the moments descriptors
+    pub moments: Vec<usize>,
+    /// Channels of the texture descriptors
+    pub texture: Vec<usize>,
+    /// Channels of the zernike descriptors
+    pub zernike: Vec<usize>,
+}
+
+impl FeatureChannels {
+    /// Parse feature channels from a string
+    ///
+    /// Feature channels are provided as semicolon-separated family:channels
+    /// pairs where families are intensity, moments, texture, or zernike and
+    /// channels are comma-separated indices or inclusive ranges (e.g.
+    /// `intensity:0-5;texture:0,1;moments:0`).
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - String representation of the feature channels
+    pub fn parse(channels: &str) -> Result<FeatureChannels, ThymeError> {
+        let invalid = || {
+            ThymeError::OtherError(format!(
+                "Invalid feature channels '{}'. Must be provided as family:channels pairs separated by semicolons (e.g. intensity:0-5;texture:0,1;moments:0)",
+                channels
+            ))
+        };
+
+        let mut parsed = FeatureChannels::default();
+
+        for pair in channels.split(';').filter(|pair| !pair.trim().is_empty()) {
+            let (family, indices) = pair.split_once(':').ok_or_else(invalid)?;
+
+            let family = match family.trim() {
+                "intensity" => &mut parsed.intensity,
+                "moments" => &mut parsed.moments,
+                "texture" => &mut parsed.texture,
+                "zernike" => &mut parsed.zernike,
+                family => {
+                    return Err(ThymeError::OtherError(format!(
+                        "Invalid feature family '{}'. Must be one of: intensity, moments, texture, zernike",
+                        family
+                    )));
+                }
+            };
+
+            if !family.is_empty() {
+                return Err(invalid());
+            }
+
+            for index in indices.split(',') {
+                let (start, end) = index.split_once('-').unwrap_or((index, index));
+
+                let (Ok(start), Ok(end)) = (start.trim().parse::<usize>(), end.trim().parse())
+                else {
+                    return Err(invalid());
+                };
+
+                if start > end {
+                    return Err(invalid());
+                }
+
+                family.extend(start..=end);
+            }
+
+            family.sort_unstable();
+            family.dedup();
+        }
+
+        if parsed == FeatureChannels::default() {
+            return Err(invalid());
+        }
+
+        Ok(parsed)
+    }
+
+    /// Selected channels of each family in output order
+    pub fn families(&self) -> [(&'static str, &[usize]); 4] {
+        [
+            ("intensity", &self.intensity),
+            ("moments", &self.moments),
+            ("texture", &self.texture),
+            ("zernike", &self.zernike),
+        ]
+    }
+
+    /// Channels selected by any family in ascending order
+    pub fn union(&self) -> Vec<usize> {
+        let mut union: Vec<usize> = self
+            .families()
+            .iter()
+            .flat_map(|(_, channels)| channels.iter().copied())
+            .collect();
+
+        union.sort_unstable();
+        union.dedup();
+        union
+    }
+
+    /// Check that an image with the provided number of channels has every selected channel
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - Number of channels in the image
+    pub fn check(&self, channels: usize) -> Result<(), ThymeError> {
+        for (family, selected) in self.families() {
+            if let Some(&channel) = selected.iter().find(|&&channel| channel >= channels) {
+                return Err(ThymeError::OtherError(format!(
+                    "Feature channels select channel {} for {} descriptors but the image has {} channels",
+                    channel, family, channels
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Ordered pairs of image channels compared by intensity ratio descriptors
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::ChannelPairs;
+///
+/// let pairs = ChannelPairs::parse("0:1, 2:1").unwrap();
+/// assert_eq!(pairs.resolve(3), vec![(0, 1), (2, 1)]);
+///
+/// // Every ordered pair of distinct channels
+/// let pairs = ChannelPairs::parse("all").unwrap();
+/// assert_eq!(pairs.resolve(2), vec![(0, 1), (1, 0)]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelPairs {
+    /// Every ordered pair of distinct channels in the image
+    All,
+    /// Numerator and denominator channel of each pair
+    Pairs(Vec<(usize, usize)>),
+}
+
+impl ChannelPairs {
+    /// Parse channel pairs from a string
+    ///
+    /// Valid channel pairs are `all` or a comma-separated list of
+    /// numerator:denominator channel indices (e.g. `0:1,2:1`).
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - String representation of the channel pairs
+    pub fn parse(pairs: &str) -> Result<ChannelPairs, ThymeError> {
+        let pairs = pairs.trim();
+
+        if pairs == "all" {
+            return Ok(ChannelPairs::All);
+        }
+
+        let invalid = || {
+            ThymeError::OtherError(format!(
+                "Invalid channel pairs '{}'. Must be all or comma-separated pairs of distinct channels (e.g. 0:1,2:1)",
+                pairs
+            ))
+        };
+
+        let mut parsed: Vec<(usize, usize)> = vec![];
+
+        for pair in pairs.split(',') {
+            let (a, b) = pair.split_once(':').ok_or_else(invalid)?;
+
+            let (Ok(a), Ok(b)) = (a.trim().parse::<usize>(), b.trim().parse::<usize>()) else {
+                return Err(invalid());
+            };
+
+            if a == b {
+                return Err(invalid());
+            }
+
+            if !parsed.contains(&(a, b)) {
+                parsed.push((a, b));
+            }
+        }
+
+        Ok(ChannelPairs::Pairs(parsed))
+    }
+
+    /// Resolve the channel pairs of an image with the provided number of channels
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - Number of channels in the image
+    pub fn resolve(&self, channels: usize) -> Vec<(usize, usize)> {
+        match self {
+            ChannelPairs::All => (0..channels)
+                .flat_map(|a| (0..channels).filter(move |&b| b != a).map(move |b| (a, b)))
+                .collect(),
+            ChannelPairs::Pairs(pairs) => pairs.clone(),
+        }
+    }
+
+    /// Check that an image with the provided number of channels has every paired channel
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - Number of channels in the image
+    pub fn check(&self, channels: usize) -> Result<(), ThymeError> {
+        match self {
+            ChannelPairs::All if channels < 2 => Err(ThymeError::OtherError(format!(
+                "Channel pairs require at least 2 channels but the image has {} channels",
+                channels
+            ))),
+            ChannelPairs::All => Ok(()),
+            ChannelPairs::Pairs(pairs) => {
+                match pairs.iter().find(|&&(a, b)| a.max(b) >= channels) {
+                    Some(&(a, b)) => Err(ThymeError::OtherError(format!(
+                        "Channel pair {}:{} requires at least {} channels but the image has {} channels",
+                        a,
+                        b,
+                        a.max(b) + 1,
+                        channels
+                    ))),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ChannelMap::parse("auto").unwrap(), ChannelMap::Auto);
+        assert_eq!(
+            ChannelMap::parse("gray-average").unwrap(),
+            ChannelMap::GrayAverage
+        );
+        assert_eq!(
+            ChannelMap::parse("1, 2,0").unwrap(),
+            ChannelMap::Gather(vec![1, 2, 0])
+        );
+        assert!(ChannelMap::parse("").is_err());
+        assert!(ChannelMap::parse("0,-1,2").is_err());
+        assert!(ChannelMap::parse("rgb").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        for map in ["auto", "gray-average", "0,0,0", "1,2,0"] {
+            assert_eq!(ChannelMap::parse(map).unwrap().to_string(), map);
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let map = ChannelMap::Auto;
+        assert_eq!(map.resolve(1), ChannelMap::Gather(vec![0, 0, 0]));
+        assert_eq!(map.resolve(3), ChannelMap::Gather(vec![0, 1, 2]));
+        assert_eq!(map.resolve(4), ChannelMap::GrayAverage);
+
+        let map = ChannelMap::Gather(vec![1, 2, 0]);
+        assert_eq!(map.resolve(1), map);
+    }
+
+    #[test]
+    fn test_check() {
+        let map = ChannelMap::Gather(vec![1, 2, 0]);
+        assert!(map.check(3).is_ok());
+        assert!(map.check(4).is_ok());
+        assert!(matches!(map.check(2), Err(ThymeError::ChannelMapError(_))));
+
+        assert!(ChannelMap::Auto.check(2).is_ok());
+        assert!(ChannelMap::GrayAverage.check(5).is_ok());
+    }
+
+    #[test]
+    fn test_check_input() {
+        for channels in [1, 3] {
+            assert!(ChannelMap::Auto.check_input(channels).is_ok());
+        }
+
+        for channels in [2, 4, 6] {
+            assert!(matches!(
+                ChannelMap::Auto.check_input(channels),
+                Err(ThymeError::ChannelMapError(_))
+            ));
+        }
+
+        let map = ChannelMap::Gather(vec![0, 5, 2]);
+        assert!(map.check_input(6).is_ok());
+        assert!(map.check_input(5).is_err());
+
+        assert!(ChannelMap::GrayAverage.check_input(6).is_ok());
+    }
+
+    #[test]
+    fn test_feature_channels() {
+        let channels = FeatureChannels::parse(" zernike : 3, 1-2 ;intensity:0;").unwrap();
+        assert_eq!(channels.zernike, vec![1, 2, 3]);
+        assert_eq!(channels.intensity, vec![0]);
+        assert!(channels.moments.is_empty());
+
+        assert!(channels.check(4).is_ok());
+        assert!(channels.check(3).is_err());
+
+        for invalid in [
+            "",
+            "intensity",
+            "intensity:",
+            "intensity:2-1",
+            "intensity:a",
+            "intensity:-1",
+            "shape:0",
+            "texture:0;texture:1",
+        ] {
+            assert!(FeatureChannels::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_channel_pairs() {
+        let pairs = ChannelPairs::parse("2:0,0:2,2:0").unwrap();
+        assert_eq!(pairs.resolve(1), vec![(2, 0), (0, 2)]);
+        assert!(pairs.check(3).is_ok());
+        assert!(pairs.check(2).is_err());
+
+        let all = ChannelPairs::parse(" all ").unwrap();
+        assert_eq!(all.resolve(3).len(), 6);
+        assert!(all.resolve(1).is_empty());
+        assert!(all.check(2).is_ok());
+        assert!(all.check(1).is_err());
+
+        for invalid in ["", "0", "0:", "0:0", "0:1;1:0", "a:1", "-1:0"] {
+            assert!(ChannelPairs::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
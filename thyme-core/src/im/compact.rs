@@ -0,0 +1,305 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::BTreeSet;
+#[cfg(feature = "io")]
+use std::path::Path;
+
+#[cfg(feature = "io")]
+use image::{DynamicImage, open as open_dynamic};
+#[cfg(feature = "io")]
+use npyz::{DType, TypeChar};
+
+#[cfg(feature = "io")]
+use crate::constant;
+use crate::cv::{
+    connected_components, find_labeled_contours, find_labeled_contours_with_hierarchy,
+};
+use crate::error::ThymeError;
+use crate::im::{Polygons, ThymeBuffer, ThymeMask};
+#[cfg(feature = "io")]
+use crate::io::parse_numpy;
+
+/// A row-major container storing 8-bit mask pixels
+///
+/// Binary masks (e.g. from thresholding) and masks with up to 255 labels are
+/// stored in a quarter of the memory of a `ThymeMask`. Polygons and labels are
+/// identical to those of the same mask widened to a `ThymeMask`.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::ThymeMaskU8;
+///
+/// let mut mask = ThymeMaskU8::new(3, 3, 1, vec![255, 255, 0, 255, 255, 0, 0, 0, 0]).unwrap();
+/// let mut wide = mask.to_mask();
+///
+/// let (labels, polygons) = mask.polygons().unwrap();
+/// let (wide_labels, wide_polygons) = wide.polygons().unwrap();
+///
+/// assert_eq!(labels, wide_labels);
+/// assert_eq!(polygons.as_points(), wide_polygons.as_points());
+/// ```
+pub type ThymeMaskU8 = ThymeBuffer<u8, Vec<u8>>;
+
+/// Labels of a mask after binary masks are split into connected components
+enum Labeled {
+    /// Labels fit in the 8-bit mask
+    Narrow(Vec<u8>),
+    /// Labels and a u32 label buffer for masks with labels above 255
+    Wide(Vec<u32>, Vec<u32>),
+}
+
+#[cfg(feature = "io")]
+impl ThymeMaskU8 {
+    /// Open an 8-bit grayscale mask without widening its pixels
+    ///
+    /// Masks with any other data type (e.g. 16-bit or palette images) must be
+    /// opened as a `ThymeMask`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to an 8-bit grayscale image or numpy array
+    ///
+    /// ```no_run
+    /// use thyme_core::im::ThymeMaskU8;
+    /// let mask = ThymeMaskU8::open("mask.png");
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ThymeMaskU8, ThymeError> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase());
+
+        let not_u8 = ThymeError::MaskError("An 8-bit grayscale mask was not detected.");
+
+        match extension.as_deref() {
+            Some("npy") => {
+                let bytes = std::fs::read(&path).map_err(|_| ThymeError::ImageReadError)?;
+                let npy = parse_numpy(&bytes)?;
+                let shape = npy.shape().to_vec();
+
+                let (h, w) = match shape[..] {
+                    [h, w] | [h, w, 1] => (h as u32, w as u32),
+                    _ => {
+                        return Err(ThymeError::MaskError(
+                            "Numpy array masks must have an (H, W) shape.",
+                        ));
+                    }
+                };
+
+                match npy.dtype() {
+                    DType::Plain(x) if (x.type_char(), x.size_field()) == (TypeChar::Uint, 1) => {
+                        let pixels = npy.into_vec().map_err(|_| ThymeError::ImageReadError)?;
+                        ThymeMaskU8::new(w, h, 1, pixels)
+                    }
+                    _ => Err(not_u8),
+                }
+            }
+            Some(ext) if constant::IMAGE_DYNAMIC_FORMATS.contains(&ext) => {
+                let image = open_dynamic(&path).map_err(|_| ThymeError::ImageReadError)?;
+                let (width, height) = (image.width(), image.height());
+
+                match image {
+                    DynamicImage::ImageLuma8(buffer) => {
+                        ThymeMaskU8::new(width, height, 1, buffer.into_raw())
+                    }
+                    DynamicImage::ImageLumaA8(buffer) => ThymeMaskU8::new(
+                        width,
+                        height,
+                        1,
+                        buffer.into_raw().chunks_exact(2).map(|p| p[0]).collect(),
+                    ),
+                    _ => Err(not_u8),
+                }
+            }
+            _ => Err(ThymeError::ImageExtensionError),
+        }
+    }
+}
+
+impl ThymeMaskU8 {
+    /// Widen the mask pixels into a `ThymeMask`
+    pub fn to_mask(&self) -> ThymeMask {
+        ThymeMask::new(self.width(), self.height(), self.channels(), self.to_u32()).unwrap()
+    }
+
+    /// Extract polygons from a segmentation mask
+    ///
+    /// # Notes
+    ///
+    /// Binary masks are re-labeled using connected components as in
+    /// `ThymeMask::polygons`. The mask is re-labeled in place if every label
+    /// fits in 8 bits. Otherwise, labels are only widened to a temporary u32
+    /// buffer and the mask is left unchanged.
+    pub fn polygons(&mut self) -> Result<(Vec<u32>, Polygons), ThymeError> {
+        let (width, height) = (self.width(), self.height());
+
+        let (labels, contours) = match self.label() {
+            Labeled::Narrow(labels) => {
+                let (labels, contours) =
+                    find_labeled_contours(width, height, &self.buffer, &labels);
+                (labels.into_iter().map(u32::from).collect(), contours)
+            }
+            Labeled::Wide(labels, buffer) => find_labeled_contours(width, height, &buffer, &labels),
+        };
+
+        Ok((labels, Polygons::new(contours)?))
+    }
+
+    /// Extract polygons from a segmentation mask along with the parent of each object
+    ///
+    /// Binary masks are re-labeled as in `polygons` and parents are identical
+    /// to `ThymeMask::polygons_with_hierarchy`.
+    #[allow(clippy::type_complexity)]
+    pub fn polygons_with_hierarchy(
+        &mut self,
+    ) -> Result<(Vec<u32>, Polygons, Vec<Option<u32>>), ThymeError> {
+        let (width, height) = (self.width(), self.height());
+
+        let (labels, contours, parents) = match self.label() {
+            Labeled::Narrow(labels) => {
+                let (labels, contours, parents) =
+                    find_labeled_contours_with_hierarchy(width, height, &self.buffer, &labels);
+
+                (
+                    labels.into_iter().map(u32::from).collect(),
+                    contours,
+                    parents.into_iter().map(|p| p.map(u32::from)).collect(),
+                )
+            }
+            Labeled::Wide(labels, buffer) => {
+                find_labeled_contours_with_hierarchy(width, height, &buffer, &labels)
+            }
+        };
+
+        Ok((labels, Polygons::new(contours)?, parents))
+    }
+
+    /// Re-label binary masks using connected components and return unique labels
+    fn label(&mut self) -> Labeled {
+        let labels: Vec<u8> = unique(&self.buffer);
+
+        // As with `ThymeMask::label`, only binary masks are re-labeled
+        if labels.len() != 1 {
+            return Labeled::Narrow(labels);
+        }
+
+        let components = connected_components(self.width(), self.height(), &self.buffer);
+        let labels: Vec<u32> = unique(&components);
+
+        match labels.last().is_some_and(|&label| label > u8::MAX as u32) {
+            true => Labeled::Wide(labels, components),
+            false => {
+                for (pixel, label) in self.buffer.iter_mut().zip(components) {
+                    *pixel = label as u8;
+                }
+
+                Labeled::Narrow(labels.into_iter().map(|label| label as u8).collect())
+            }
+        }
+    }
+}
+
+/// Sorted unique non-zero values of a buffer
+fn unique<T: Ord + Copy + Default>(buffer: &[T]) -> Vec<T> {
+    buffer
+        .iter()
+        .filter(|&&x| x != T::default())
+        .copied()
+        .collect::<BTreeSet<T>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // Binary mask with one object per 3 x 3 cell of a grid
+    fn grid(cells: u32) -> ThymeMaskU8 {
+        let size = cells * 3;
+        let pixels = (0..size * size)
+            .map(|i| match (i % size % 3, i / size % 3) {
+                (1, 1) | (0, 1) | (1, 0) => 255,
+                _ => 0,
+            })
+            .collect();
+
+        ThymeMaskU8::new(size, size, 1, pixels).unwrap()
+    }
+
+    #[test]
+    fn test_polygons_match_wide_mask() {
+        // 16 objects fit in 8-bit labels and 400 objects do not
+        for cells in [4, 20] {
+            let mut mask = grid(cells);
+            let mut wide = mask.to_mask();
+
+            let (labels, polygons) = mask.polygons().unwrap();
+            let (wide_labels, wide_polygons) = wide.polygons().unwrap();
+
+            assert_eq!(labels.len(), (cells * cells) as usize);
+            assert_eq!(labels, wide_labels);
+            assert_eq!(polygons.as_points(), wide_polygons.as_points());
+
+            match cells {
+                4 => assert_eq!(mask.to_u32(), *wide.as_raw()),
+                _ => assert!(mask.as_raw().iter().all(|&x| x == 0 || x == 255)),
+            }
+
+            let (labels, _, parents) = grid(cells).polygons_with_hierarchy().unwrap();
+            let (wide_labels, _, wide_parents) =
+                grid(cells).to_mask().polygons_with_hierarchy().unwrap();
+
+            assert_eq!((labels, parents), (wide_labels, wide_parents));
+        }
+
+        // Integer-labeled masks are not re-labeled
+        let mut labeled = ThymeMaskU8::new(3, 1, 1, vec![7, 0, 3]).unwrap();
+        assert_eq!(labeled.polygons().unwrap().0, Vec::<u32>::new());
+        assert_eq!(labeled.as_raw(), &vec![7, 0, 3]);
+    }
+
+    #[cfg(feature = "io")]
+    #[test]
+    fn test_open_memory() {
+        use crate::io::tracked::{peak_allocation, retained_allocation};
+
+        let path = std::env::temp_dir().join("thyme_test_compact_mask.png");
+        let mask = grid(300);
+        image::GrayImage::from_raw(900, 900, mask.as_raw().clone())
+            .unwrap()
+            .save(&path)
+            .unwrap();
+
+        // Masks hold a quarter of the memory of the same mask widened to u32
+        let (compact, narrow) = retained_allocation(|| ThymeMaskU8::open(&path).unwrap());
+        let (wide, widened) = retained_allocation(|| ThymeMask::open(&path).unwrap());
+
+        assert_eq!(compact.to_u32(), *wide.as_raw());
+        assert_eq!(narrow, 900 * 900);
+        assert_eq!(widened, 4 * narrow);
+
+        // Buffers allocated by the png decoder are shared by both masks so
+        // the peak is only reduced by the u32 copy that is no longer made
+        let narrow = peak_allocation(|| drop(ThymeMaskU8::open(&path).unwrap()));
+        let widened = peak_allocation(|| drop(ThymeMask::open(&path).unwrap()));
+
+        assert!(
+            widened as f64 > 2.5 * narrow as f64,
+            "Peak allocation of {} bytes for u8 and {} bytes for u32 masks",
+            narrow,
+            widened
+        );
+
+        let rgb = std::env::temp_dir().join("thyme_test_compact_mask_rgb.png");
+        image::RgbImage::new(4, 4).save(&rgb).unwrap();
+        assert!(ThymeMaskU8::open(&rgb).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&rgb).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use crate::error::ThymeError;
+
+/// Data types an image can be converted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dtype {
+    U8,
+    U16,
+    U32,
+    F32,
+    F64,
+}
+
+impl Dtype {
+    /// Parse a data type from a string (u8, u16, u32, f32, f64)
+    ///
+    /// # Arguments
+    ///
+    /// * `dtype` - String representation of the data type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::Dtype;
+    ///
+    /// assert_eq!(Dtype::parse("u16").unwrap(), Dtype::U16);
+    /// assert!(Dtype::parse("i8").is_err());
+    /// ```
+    pub fn parse(dtype: &str) -> Result<Dtype, ThymeError> {
+        match dtype.trim().to_lowercase().as_str() {
+            "u8" => Ok(Dtype::U8),
+            "u16" => Ok(Dtype::U16),
+            "u32" => Ok(Dtype::U32),
+            "f32" => Ok(Dtype::F32),
+            "f64" => Ok(Dtype::F64),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid dtype '{}'. Must be one of: u8, u16, u32, f32, f64",
+                dtype
+            ))),
+        }
+    }
+
+    /// Range of values that scaled conversions map onto
+    ///
+    /// Integer types span their full range and float types span [0, 1].
+    pub fn scale_range(&self) -> [f64; 2] {
+        match self {
+            Dtype::U8 => [0.0, u8::MAX as f64],
+            Dtype::U16 => [0.0, u16::MAX as f64],
+            Dtype::U32 => [0.0, u32::MAX as f64],
+            Dtype::F32 | Dtype::F64 => [0.0, 1.0],
+        }
+    }
+}
+
+/// Policies for handling values that do not fit in the target data type
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::{ConversionPolicy, Dtype, ThymeBuffer, ThymeImage};
+///
+/// let image = ThymeImage::U16(ThymeBuffer::new(3, 1, 1, vec![0u16, 100, 1000]).unwrap());
+///
+/// let clamped = image.convert_dtype(Dtype::U8, ConversionPolicy::Clamped).unwrap();
+/// assert_eq!(clamped.to_u8_clamped(), vec![0, 100, 255]);
+///
+/// let scaled = image.convert_dtype(Dtype::U8, ConversionPolicy::ScaledMinMax).unwrap();
+/// assert_eq!(scaled.to_u8_clamped(), vec![0, 26, 255]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversionPolicy {
+    /// Set out-of-range values to zero (only safe when values are known to fit)
+    LossyZero,
+    /// Saturate out-of-range values at the bounds of the target type
+    Clamped,
+    /// Linearly scale from the image minimum and maximum to the target range
+    ScaledMinMax,
+    /// Linearly scale from the source type range to the target range
+    ///
+    /// Float images are assumed to span [0, 1].
+    ScaledDtype,
+}
+
+impl ConversionPolicy {
+    /// Parse a conversion policy from a string (lossy-zero, clamped, scaled-min-max, scaled-dtype)
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - String representation of the conversion policy
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::ConversionPolicy;
+    ///
+    /// assert_eq!(
+    ///     ConversionPolicy::parse("scaled-min-max").unwrap(),
+    ///     ConversionPolicy::ScaledMinMax
+    /// );
+    /// assert!(ConversionPolicy::parse("rounded").is_err());
+    /// ```
+    pub fn parse(policy: &str) -> Result<ConversionPolicy, ThymeError> {
+        match policy.trim().to_lowercase().as_str() {
+            "lossy-zero" => Ok(ConversionPolicy::LossyZero),
+            "clamped" => Ok(ConversionPolicy::Clamped),
+            "scaled-min-max" => Ok(ConversionPolicy::ScaledMinMax),
+            "scaled-dtype" => Ok(ConversionPolicy::ScaledDtype),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid conversion policy '{}'. Must be one of: lossy-zero, clamped, scaled-min-max, scaled-dtype",
+                policy
+            ))),
+        }
+    }
+}
@@ -1,18 +1,46 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+#[cfg(feature = "io")]
+use std::fs::File;
+#[cfg(feature = "io")]
+use std::io::BufReader;
+use std::ops::Deref;
+#[cfg(feature = "io")]
 use std::path::Path;
 
+#[cfg(feature = "resize")]
 use fast_image_resize::PixelType;
-use image::{DynamicImage, ImageBuffer, Luma, Rgb, open as open_dynamic};
-use npyz::{self, DType, NpyFile, TypeChar};
 
+#[cfg(feature = "io")]
+use image::open as open_dynamic;
+#[cfg(any(feature = "io", feature = "resize"))]
+use image::{DynamicImage, ImageBuffer, Luma, Rgb};
+#[cfg(feature = "io")]
+use npyz::{self, DType, NpyFile, TypeChar};
+use num::{FromPrimitive, ToPrimitive};
+#[cfg(feature = "io")]
+use tiff::{
+    ColorType,
+    decoder::{Decoder, DecodingResult},
+    encoder::{TiffEncoder, colortype},
+};
+
+#[cfg(feature = "io")]
 use crate::constant;
+#[cfg(feature = "resize")]
 use crate::cv::transform;
+use crate::cv::transform::IntegralImage;
 use crate::error::ThymeError;
-use crate::im::{MaskingStyle, ThymeBuffer, ThymeMaskView, ThymeView};
+use crate::im::{
+    ChannelMap, ConversionPolicy, Dtype, MaskingStyle, ThymeBuffer, ThymeMaskView, ThymeView,
+};
 use crate::impl_enum_dispatch;
-use crate::io::write_numpy;
+#[cfg(feature = "io")]
+use crate::io::{
+    TiffCompression, interleave_planes, parse_numpy, write_atomic, write_dynamic_atomic,
+    write_numpy, write_png_text, write_tiff_page, write_tiff_planes,
+};
 
 /// A wrapper for representing and storing array-shaped pixels
 ///
@@ -58,6 +86,7 @@ pub enum ThymeImage {
 
 // >>> I/O METHODS
 
+#[cfg(feature = "io")]
 impl ThymeImage {
     /// Open a new image from a provided path
     ///
@@ -78,15 +107,16 @@ impl ThymeImage {
 
         if let Some(ext) = extension {
             if ext == "npy" {
-                if let Ok(bytes) = std::fs::read(&path) {
-                    if let Ok(npy) = NpyFile::new(&bytes[..]) {
-                        Self::new_from_numpy(npy.clone()).unwrap();
+                let bytes = std::fs::read(&path).map_err(|_| ThymeError::ImageReadError)?;
+                return Self::new_from_numpy(parse_numpy(&bytes)?);
+            }
 
-                        return Self::new_from_numpy(npy);
-                    }
+            // Float, 32/64-bit and multi-page TIFFs (e.g. probability maps) are not
+            // covered by DynamicImage so they are decoded directly before falling back
+            if ext == "tif" || ext == "tiff" {
+                if let Ok(image) = Self::new_from_tiff(&path) {
+                    return Ok(image);
                 }
-
-                return Err(ThymeError::ImageReadError);
             }
 
             if constant::IMAGE_DYNAMIC_FORMATS.iter().any(|e| e == &ext) {
@@ -101,6 +131,140 @@ impl ThymeImage {
         Err(ThymeError::ImageExtensionError)
     }
 
+    /// Initialize a new image from a 32 or 64-bit float grayscale TIFF
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to a float grayscale TIFF
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use thyme_core::im::ThymeImage;
+    /// let image = ThymeImage::new_from_float_tiff("probability.tif");
+    /// ```
+    pub fn new_from_float_tiff<P: AsRef<Path>>(path: P) -> Result<ThymeImage, ThymeError> {
+        let file = File::open(path).map_err(|_| ThymeError::ImageReadError)?;
+        let mut decoder =
+            Decoder::new(BufReader::new(file)).map_err(|_| ThymeError::ImageReadError)?;
+
+        let (width, height) = decoder
+            .dimensions()
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        if !matches!(decoder.colortype(), Ok(ColorType::Gray(_))) {
+            return Err(ThymeError::ImageFormatError);
+        }
+
+        match decoder
+            .read_image()
+            .map_err(|_| ThymeError::ImageReadError)?
+        {
+            DecodingResult::F32(buffer) => {
+                Ok(ThymeImage::F32(ThymeBuffer::new(width, height, 1, buffer)?))
+            }
+            DecodingResult::F64(buffer) => {
+                Ok(ThymeImage::F64(ThymeBuffer::new(width, height, 1, buffer)?))
+            }
+            _ => Err(ThymeError::ImageFormatError),
+        }
+    }
+
+    /// Initialize a new image from a grayscale or rgb TIFF
+    ///
+    /// Samples keep their stored data type. Pages following a grayscale first
+    /// page with the same dimensions and data type are stacked as additional
+    /// channels, which reads back multi-channel images written by
+    /// [`ThymeImage::save_as_tiff`]. Pages with other dimensions (e.g. the
+    /// levels of a pyramid) end the stack.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to a grayscale or rgb TIFF
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use thyme_core::im::ThymeImage;
+    /// let image = ThymeImage::new_from_tiff("channels.tif");
+    /// ```
+    pub fn new_from_tiff<P: AsRef<Path>>(path: P) -> Result<ThymeImage, ThymeError> {
+        let file = File::open(path).map_err(|_| ThymeError::ImageReadError)?;
+        let mut decoder =
+            Decoder::new(BufReader::new(file)).map_err(|_| ThymeError::ImageReadError)?;
+
+        let (width, height) = decoder
+            .dimensions()
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        let colortype = decoder
+            .colortype()
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        let samples = match colortype {
+            ColorType::Gray(8 | 16 | 32 | 64) => 1,
+            ColorType::RGB(8 | 16 | 32 | 64) => 3,
+            _ => return Err(ThymeError::ImageFormatError),
+        };
+
+        let mut pages = vec![
+            decoder
+                .read_image()
+                .map_err(|_| ThymeError::ImageReadError)?,
+        ];
+
+        while samples == 1 && decoder.more_images() {
+            decoder
+                .next_image()
+                .map_err(|_| ThymeError::ImageReadError)?;
+
+            if decoder.dimensions().ok() != Some((width, height))
+                || decoder.colortype().ok() != Some(colortype)
+            {
+                break;
+            }
+
+            pages.push(
+                decoder
+                    .read_image()
+                    .map_err(|_| ThymeError::ImageReadError)?,
+            );
+        }
+
+        let channels = (samples * pages.len()) as u32;
+
+        macro_rules! stack_pages {
+            ($variant:ident) => {{
+                let planes = pages
+                    .into_iter()
+                    .map(|page| match page {
+                        DecodingResult::$variant(plane) => Ok(plane),
+                        _ => Err(ThymeError::ImageFormatError),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(ThymeImage::$variant(ThymeBuffer::new(
+                    width,
+                    height,
+                    channels,
+                    interleave_planes(planes, samples),
+                )?))
+            }};
+        }
+
+        match pages[0] {
+            DecodingResult::U8(_) => stack_pages!(U8),
+            DecodingResult::U16(_) => stack_pages!(U16),
+            DecodingResult::U32(_) => stack_pages!(U32),
+            DecodingResult::U64(_) => stack_pages!(U64),
+            DecodingResult::I32(_) => stack_pages!(I32),
+            DecodingResult::I64(_) => stack_pages!(I64),
+            DecodingResult::F32(_) => stack_pages!(F32),
+            DecodingResult::F64(_) => stack_pages!(F64),
+            _ => Err(ThymeError::ImageFormatError),
+        }
+    }
+
     /// Initialize a new image from a DynamicImage
     ///
     /// # Arguments
@@ -210,6 +374,9 @@ impl ThymeImage {
 
     /// Initialize a new image from a numpy array buffer
     ///
+    /// Buffers parsed with `io::parse_numpy` have a header validated against
+    /// their payload, so corrupt files fail before any pixels are allocated.
+    ///
     /// # Arguments
     ///
     /// * `npy` - A (height, width, channel) shaped numpy array buffer
@@ -217,11 +384,11 @@ impl ThymeImage {
     /// # Examples
     ///
     /// ```no_run
-    /// use npyz::NpyFile;
     /// use thyme_core::im::ThymeImage;
+    /// use thyme_core::io::parse_numpy;
     ///
     /// let bytes = std::fs::read("image.npy").unwrap();
-    /// let npy = NpyFile::new(&bytes[..]).unwrap();
+    /// let npy = parse_numpy(&bytes).unwrap();
     /// let image = ThymeImage::new_from_numpy(npy);
     /// ```
     pub fn new_from_numpy(npy: NpyFile<&[u8]>) -> Result<ThymeImage, ThymeError> {
@@ -243,37 +410,37 @@ impl ThymeImage {
                     w,
                     h,
                     c,
-                    npy.into_vec().unwrap(),
+                    npy.into_vec().map_err(|_| ThymeError::ImageReadError)?,
                 )?)),
                 (TypeChar::Uint, 2) => Ok(ThymeImage::U16(ThymeBuffer::new(
                     w,
                     h,
                     c,
-                    npy.into_vec().unwrap(),
+                    npy.into_vec().map_err(|_| ThymeError::ImageReadError)?,
                 )?)),
                 (TypeChar::Int, 4) => Ok(ThymeImage::I32(ThymeBuffer::new(
                     w,
                     h,
                     c,
-                    npy.into_vec().unwrap(),
+                    npy.into_vec().map_err(|_| ThymeError::ImageReadError)?,
                 )?)),
                 (TypeChar::Int, 8) => Ok(ThymeImage::I64(ThymeBuffer::new(
                     w,
                     h,
                     c,
-                    npy.into_vec().unwrap(),
+                    npy.into_vec().map_err(|_| ThymeError::ImageReadError)?,
                 )?)),
                 (TypeChar::Float, 4) => Ok(ThymeImage::F32(ThymeBuffer::new(
                     w,
                     h,
                     c,
-                    npy.into_vec().unwrap(),
+                    npy.into_vec().map_err(|_| ThymeError::ImageReadError)?,
                 )?)),
                 (TypeChar::Float, 8) => Ok(ThymeImage::F64(ThymeBuffer::new(
                     w,
                     h,
                     c,
-                    npy.into_vec().unwrap(),
+                    npy.into_vec().map_err(|_| ThymeError::ImageReadError)?,
                 )?)),
                 _ => Err(ThymeError::ImageError(
                     "A numpy array with a valid data type was not detected.",
@@ -308,6 +475,10 @@ impl ThymeImage {
                 return self.save_as_numpy(path);
             }
 
+            if ext == "tif" || ext == "tiff" {
+                return self.save_as_tiff(path, TiffCompression::None);
+            }
+
             if constant::IMAGE_DYNAMIC_FORMATS.iter().any(|e| e == &ext) {
                 return self.save_as_default(path);
             }
@@ -344,9 +515,7 @@ impl ThymeImage {
                 )
                 .ok_or(ThymeError::ImageWriteError)?;
 
-                image_buffer
-                    .save(path)
-                    .map_err(|_| ThymeError::ImageWriteError)
+                write_dynamic_atomic(&DynamicImage::from(image_buffer), path)
             }
             (ThymeImage::U16(buffer), 1) => {
                 let image_buffer = ImageBuffer::<Luma<u16>, Vec<u16>>::from_raw(
@@ -356,9 +525,7 @@ impl ThymeImage {
                 )
                 .ok_or(ThymeError::ImageWriteError)?;
 
-                image_buffer
-                    .save(path)
-                    .map_err(|_| ThymeError::ImageWriteError)
+                write_dynamic_atomic(&DynamicImage::from(image_buffer), path)
             }
             (ThymeImage::U8(buffer), 3) => {
                 let image_buffer = ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(
@@ -368,9 +535,7 @@ impl ThymeImage {
                 )
                 .ok_or(ThymeError::ImageWriteError)?;
 
-                image_buffer
-                    .save(path)
-                    .map_err(|_| ThymeError::ImageWriteError)
+                write_dynamic_atomic(&DynamicImage::from(image_buffer), path)
             }
             (ThymeImage::U16(buffer), 3) => {
                 let image_buffer = ImageBuffer::<Rgb<u16>, Vec<u16>>::from_raw(
@@ -380,9 +545,7 @@ impl ThymeImage {
                 )
                 .ok_or(ThymeError::ImageWriteError)?;
 
-                image_buffer
-                    .save(path)
-                    .map_err(|_| ThymeError::ImageWriteError)
+                write_dynamic_atomic(&DynamicImage::from(image_buffer), path)
             }
             (ThymeImage::F32(buffer), 3) => {
                 let image_buffer = ImageBuffer::<Rgb<f32>, Vec<f32>>::from_raw(
@@ -392,9 +555,7 @@ impl ThymeImage {
                 )
                 .ok_or(ThymeError::ImageWriteError)?;
 
-                image_buffer
-                    .save(path)
-                    .map_err(|_| ThymeError::ImageWriteError)
+                write_dynamic_atomic(&DynamicImage::from(image_buffer), path)
             }
             _ => Err(ThymeError::ImageError(
                 "Only 1 or 3 channel RGB/grayscale images can be saved as a default image format (e.g. png).",
@@ -402,6 +563,199 @@ impl ThymeImage {
         }
     }
 
+    /// Save image as a TIFF
+    ///
+    /// Images of every data type are written without conversion. Unsigned and
+    /// float 3-channel images are written as a single rgb page while all other
+    /// images are written with one grayscale page per channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to output image
+    /// * `compression` - Compression of each page
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use thyme_core::im::{ThymeBuffer, ThymeImage};
+    /// use thyme_core::io::TiffCompression;
+    ///
+    /// let image = ThymeImage::U16(ThymeBuffer::new(2, 1, 4, vec![0u16; 8]).unwrap());
+    /// image.save_as_tiff("channels.tif", TiffCompression::Deflate).unwrap();
+    /// ```
+    pub fn save_as_tiff<P: AsRef<Path>>(
+        self,
+        path: P,
+        compression: TiffCompression,
+    ) -> Result<(), ThymeError> {
+        let (width, height) = (self.width(), self.height());
+        let channels = self.channels() as usize;
+
+        write_atomic(path, |file| {
+            let mut encoder = TiffEncoder::new(file).map_err(|_| ThymeError::ImageWriteError)?;
+            let encoder = &mut encoder;
+
+            match self {
+                ThymeImage::U8(buffer) if channels == 3 => write_tiff_page::<colortype::RGB8, _>(
+                    encoder,
+                    width,
+                    height,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::U16(buffer) if channels == 3 => write_tiff_page::<colortype::RGB16, _>(
+                    encoder,
+                    width,
+                    height,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::U32(buffer) if channels == 3 => write_tiff_page::<colortype::RGB32, _>(
+                    encoder,
+                    width,
+                    height,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::U64(buffer) if channels == 3 => write_tiff_page::<colortype::RGB64, _>(
+                    encoder,
+                    width,
+                    height,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::F32(buffer) if channels == 3 => {
+                    write_tiff_page::<colortype::RGB32Float, _>(
+                        encoder,
+                        width,
+                        height,
+                        buffer.as_raw(),
+                        compression,
+                    )
+                }
+                ThymeImage::F64(buffer) if channels == 3 => {
+                    write_tiff_page::<colortype::RGB64Float, _>(
+                        encoder,
+                        width,
+                        height,
+                        buffer.as_raw(),
+                        compression,
+                    )
+                }
+                ThymeImage::U8(buffer) => write_tiff_planes::<colortype::Gray8, _>(
+                    encoder,
+                    width,
+                    height,
+                    channels,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::U16(buffer) => write_tiff_planes::<colortype::Gray16, _>(
+                    encoder,
+                    width,
+                    height,
+                    channels,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::U32(buffer) => write_tiff_planes::<colortype::Gray32, _>(
+                    encoder,
+                    width,
+                    height,
+                    channels,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::U64(buffer) => write_tiff_planes::<colortype::Gray64, _>(
+                    encoder,
+                    width,
+                    height,
+                    channels,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::I32(buffer) => write_tiff_planes::<colortype::GrayI32, _>(
+                    encoder,
+                    width,
+                    height,
+                    channels,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::I64(buffer) => write_tiff_planes::<colortype::GrayI64, _>(
+                    encoder,
+                    width,
+                    height,
+                    channels,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::F32(buffer) => write_tiff_planes::<colortype::Gray32Float, _>(
+                    encoder,
+                    width,
+                    height,
+                    channels,
+                    buffer.as_raw(),
+                    compression,
+                ),
+                ThymeImage::F64(buffer) => write_tiff_planes::<colortype::Gray64Float, _>(
+                    encoder,
+                    width,
+                    height,
+                    channels,
+                    buffer.as_raw(),
+                    compression,
+                ),
+            }
+            .map_err(|_| ThymeError::ImageWriteError)
+        })
+    }
+
+    /// Save image as a PNG with text chunks
+    ///
+    /// Unsigned 8 and 16-bit images with 1 to 4 channels are written as gray,
+    /// gray-alpha, rgb, or rgba PNGs with each keyword and text written as an
+    /// iTXt chunk. Text chunks can be read back with `io::read_png_text`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to output image
+    /// * `text` - Keyword and text of each chunk
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use thyme_core::im::{ThymeBuffer, ThymeImage};
+    ///
+    /// let image = ThymeImage::U8(ThymeBuffer::new(2, 2, 3, vec![0u8; 12]).unwrap());
+    /// image.save_as_png_with_text("crop.png", &[("thyme", "{}")]).unwrap();
+    /// ```
+    pub fn save_as_png_with_text<P: AsRef<Path>>(
+        &self,
+        path: P,
+        text: &[(&str, &str)],
+    ) -> Result<(), ThymeError> {
+        let (width, height, channels) = (self.width(), self.height(), self.channels());
+
+        match self {
+            ThymeImage::U8(buffer) => {
+                write_png_text(path, width, height, channels, buffer.as_raw(), 8, text)
+            }
+            ThymeImage::U16(buffer) => {
+                let data: Vec<u8> = buffer
+                    .as_raw()
+                    .iter()
+                    .flat_map(|v| v.to_be_bytes())
+                    .collect();
+
+                write_png_text(path, width, height, channels, &data, 16, text)
+            }
+            _ => Err(ThymeError::ImageError(
+                "Only u8 or u16 images can be saved as a png with text chunks.",
+            )),
+        }
+    }
+
     /// Save image as a numpy format
     ///
     /// # Arguments
@@ -478,23 +832,160 @@ impl ThymeImage {
             ThymeImage::F64(_) => f64::MAX,
         }
     }
+
+    /// Check if the image is a single channel float image with values in [0, 1]
+    pub fn is_probability_map(&self) -> bool {
+        if self.channels() != 1 {
+            return false;
+        }
+
+        match self {
+            ThymeImage::F32(buffer) => buffer.iter().all(|v| (0.0..=1.0).contains(v)),
+            ThymeImage::F64(buffer) => buffer.iter().all(|v| (0.0..=1.0).contains(v)),
+            _ => false,
+        }
+    }
 }
 
 // <<< PROPERTY METHODS
 
 // >>> CONVERSION METHODS
 
-impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_u8(&self) -> Vec<u8>);
-impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_u16(&self) -> Vec<u16>);
+impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_u8_lossy_zero(&self) -> Vec<u8>);
+impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_u8_clamped(&self) -> Vec<u8>);
+impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_u8_scaled(&self) -> Vec<u8>);
+impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_u16_lossy_zero(&self) -> Vec<u16>);
+impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_u16_clamped(&self) -> Vec<u16>);
+impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_u16_scaled(&self) -> Vec<u16>);
 impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_u32(&self) -> Vec<u32>);
 impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_f32(&self) -> Vec<f32>);
 impl_enum_dispatch!(ThymeImage, U8, U16, U32, I32, I64, U64, F32, F64; to_f64(&self) -> Vec<f64>);
 
+impl ThymeImage {
+    /// Convert the image to another data type
+    ///
+    /// # Arguments
+    ///
+    /// * `dtype` - Target data type
+    /// * `policy` - How values that do not fit in the target data type are handled
+    pub fn convert_dtype(
+        &self,
+        dtype: Dtype,
+        policy: ConversionPolicy,
+    ) -> Result<ThymeImage, ThymeError> {
+        let source_range = match self {
+            ThymeImage::F32(_) | ThymeImage::F64(_) => [0.0, 1.0],
+            _ => [self.dtype_min(), self.dtype_max()],
+        };
+
+        match self {
+            ThymeImage::U8(buffer) => convert_buffer(buffer, source_range, dtype, policy),
+            ThymeImage::U16(buffer) => convert_buffer(buffer, source_range, dtype, policy),
+            ThymeImage::U32(buffer) => convert_buffer(buffer, source_range, dtype, policy),
+            ThymeImage::U64(buffer) => convert_buffer(buffer, source_range, dtype, policy),
+            ThymeImage::I32(buffer) => convert_buffer(buffer, source_range, dtype, policy),
+            ThymeImage::I64(buffer) => convert_buffer(buffer, source_range, dtype, policy),
+            ThymeImage::F32(buffer) => convert_buffer(buffer, source_range, dtype, policy),
+            ThymeImage::F64(buffer) => convert_buffer(buffer, source_range, dtype, policy),
+        }
+    }
+}
+
+/// Convert a buffer to a new image with the provided data type and policy
+fn convert_buffer<T, Container>(
+    buffer: &ThymeBuffer<T, Container>,
+    source_range: [f64; 2],
+    dtype: Dtype,
+    policy: ConversionPolicy,
+) -> Result<ThymeImage, ThymeError>
+where
+    T: ToPrimitive + FromPrimitive,
+    Container: Deref<Target = [T]>,
+{
+    let (width, height, channels) = (buffer.width(), buffer.height(), buffer.channels());
+
+    let from = match policy {
+        ConversionPolicy::ScaledDtype => source_range,
+        _ => buffer.min_max(),
+    };
+
+    macro_rules! convert {
+        ($variant:ident, $t:ty, $lossy:ident, $round:expr) => {
+            ThymeImage::$variant(ThymeBuffer::new(
+                width,
+                height,
+                channels,
+                match policy {
+                    ConversionPolicy::LossyZero => buffer.$lossy(),
+                    ConversionPolicy::Clamped => buffer.cast_clamped::<$t>(),
+                    _ => buffer.cast_scaled::<$t>(from, dtype.scale_range(), $round),
+                },
+            )?)
+        };
+    }
+
+    Ok(match dtype {
+        Dtype::U8 => convert!(U8, u8, to_u8_lossy_zero, true),
+        Dtype::U16 => convert!(U16, u16, to_u16_lossy_zero, true),
+        Dtype::U32 => convert!(U32, u32, to_u32, true),
+        Dtype::F32 => convert!(F32, f32, to_f32, false),
+        Dtype::F64 => convert!(F64, f64, to_f64, false),
+    })
+}
+
 // <<< CONVERSION METHODS
 
 // >>> TRANSFORM METHODS
 
 impl ThymeImage {
+    /// Build the summed-area tables of an image channel
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - Channel to build the tables from
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{ThymeBuffer, ThymeImage};
+    ///
+    /// let buffer = ThymeBuffer::new(2, 2, 1, vec![1u16, 2, 3, 4]).unwrap();
+    /// let integral = ThymeImage::U16(buffer).integral(0);
+    ///
+    /// assert_eq!(integral.sum(0, 0, 2, 2), 10.0);
+    /// ```
+    pub fn integral(&self, channel: usize) -> IntegralImage {
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        let channels = self.channels() as usize;
+
+        match self {
+            ThymeImage::U8(buffer) => {
+                IntegralImage::new(buffer.as_raw(), width, height, channels, channel)
+            }
+            ThymeImage::U16(buffer) => {
+                IntegralImage::new(buffer.as_raw(), width, height, channels, channel)
+            }
+            ThymeImage::U32(buffer) => {
+                IntegralImage::new(buffer.as_raw(), width, height, channels, channel)
+            }
+            ThymeImage::U64(buffer) => {
+                IntegralImage::new(buffer.as_raw(), width, height, channels, channel)
+            }
+            ThymeImage::I32(buffer) => {
+                IntegralImage::new(buffer.as_raw(), width, height, channels, channel)
+            }
+            ThymeImage::I64(buffer) => {
+                IntegralImage::new(buffer.as_raw(), width, height, channels, channel)
+            }
+            ThymeImage::F32(buffer) => {
+                IntegralImage::new(buffer.as_raw(), width, height, channels, channel)
+            }
+            ThymeImage::F64(buffer) => {
+                IntegralImage::new(buffer.as_raw(), width, height, channels, channel)
+            }
+        }
+    }
+
     /// Generate a zero-copy crop of an image subregion
     ///
     /// # Arguments
@@ -584,12 +1075,60 @@ impl ThymeImage {
         }
     }
 
+    /// Create a new image with channels gathered according to a channel map
+    ///
+    /// # Arguments
+    ///
+    /// * `map` - Channel map specifying how output channels are generated
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{ChannelMap, ThymeBuffer, ThymeImage};
+    ///
+    /// let buffer = ThymeBuffer::new(2, 1, 3, vec![1u8, 2, 3, 4, 5, 6]).unwrap();
+    /// let image = ThymeImage::U8(buffer);
+    ///
+    /// let reordered = image.gather_channels(&ChannelMap::parse("1,2,0").unwrap()).unwrap();
+    /// assert_eq!(reordered.to_u8_clamped(), vec![2, 3, 1, 5, 6, 4]);
+    ///
+    /// // Images with too few channels for the map produce an error
+    /// assert!(image.gather_channels(&ChannelMap::parse("0,3,0").unwrap()).is_err());
+    /// ```
+    pub fn gather_channels(&self, map: &ChannelMap) -> Result<ThymeImage, ThymeError> {
+        map.check(self.channels())?;
+
+        match map.resolve(self.channels()) {
+            ChannelMap::Gather(channels) => match self {
+                ThymeImage::U8(buffer) => Ok(ThymeImage::U8(buffer.gather_channels(&channels)?)),
+                ThymeImage::U16(buffer) => Ok(ThymeImage::U16(buffer.gather_channels(&channels)?)),
+                ThymeImage::U32(buffer) => Ok(ThymeImage::U32(buffer.gather_channels(&channels)?)),
+                ThymeImage::U64(buffer) => Ok(ThymeImage::U64(buffer.gather_channels(&channels)?)),
+                ThymeImage::I32(buffer) => Ok(ThymeImage::I32(buffer.gather_channels(&channels)?)),
+                ThymeImage::I64(buffer) => Ok(ThymeImage::I64(buffer.gather_channels(&channels)?)),
+                ThymeImage::F32(buffer) => Ok(ThymeImage::F32(buffer.gather_channels(&channels)?)),
+                ThymeImage::F64(buffer) => Ok(ThymeImage::F64(buffer.gather_channels(&channels)?)),
+            },
+            _ => match self {
+                ThymeImage::U8(buffer) => Ok(ThymeImage::U8(buffer.average_channels())),
+                ThymeImage::U16(buffer) => Ok(ThymeImage::U16(buffer.average_channels())),
+                ThymeImage::U32(buffer) => Ok(ThymeImage::U32(buffer.average_channels())),
+                ThymeImage::U64(buffer) => Ok(ThymeImage::U64(buffer.average_channels())),
+                ThymeImage::I32(buffer) => Ok(ThymeImage::I32(buffer.average_channels())),
+                ThymeImage::I64(buffer) => Ok(ThymeImage::I64(buffer.average_channels())),
+                ThymeImage::F32(buffer) => Ok(ThymeImage::F32(buffer.average_channels())),
+                ThymeImage::F64(buffer) => Ok(ThymeImage::F64(buffer.average_channels())),
+            },
+        }
+    }
+
     /// Resize the image
     ///
     /// # Arguments
     ///
     /// * `width` - Width of resized image
     /// * `height` - Height of resized image
+    #[cfg(feature = "resize")]
     pub fn resize(&self, width: u32, height: u32) -> Result<ThymeImage, ThymeError> {
         let channels = self.channels();
         match (self, channels) {
@@ -794,11 +1333,156 @@ impl ThymeImage {
             )?)),
         }
     }
+
+    /// Compute foreground texture descriptors of an object crop at multiple scales
+    ///
+    /// The crop is downsampled by each scale factor with `resize` and the
+    /// binary object mask is downsampled to the same size so that texture is
+    /// always measured on the foreground of the object. The texture stride is
+    /// divided by the scale factor. Downsampled scales where either side of
+    /// the crop would be smaller than 8 pixels are filled with zeros. A scale
+    /// of one matches the foreground texture descriptors of the crop.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - Binary mask of the object with the dimensions of the crop
+    /// * `scales` - Downsampling factors (e.g. 1, 2, 4)
+    /// * `stride` - Stride of co-occurrence anchors at the original scale
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{ThymeBuffer, ThymeImage, ThymeMask};
+    ///
+    /// let pixels = (0..32 * 32).map(|i| ((i % 32 + i / 32) % 2 * 255) as u8).collect();
+    /// let image = ThymeImage::U8(ThymeBuffer::new(32, 32, 1, pixels).unwrap());
+    /// let mask = ThymeMask::new(32, 32, 1, vec![1; 32 * 32]).unwrap();
+    ///
+    /// let texture = image.texture_scales(&mask, &[1, 2, 8], 1).unwrap();
+    /// assert_eq!(texture.len(), 3 * 13);
+    /// assert!(texture[13..26].iter().any(|&v| v != 0.0));
+    /// assert!(texture[26..].iter().all(|&v| v == 0.0));
+    /// ```
+    #[cfg(feature = "resize")]
+    pub fn texture_scales(
+        &self,
+        mask: &crate::im::ThymeMask,
+        scales: &[u32],
+        stride: usize,
+    ) -> Result<Vec<f32>, ThymeError> {
+        const MIN_SIDE: u32 = 8;
+
+        let (width, height) = (self.width(), self.height());
+
+        if mask.width() != width || mask.height() != height {
+            return Err(ThymeError::OtherError(
+                "Mask and image are not the same size".to_string(),
+            ));
+        }
+
+        let mut texture = Vec::with_capacity(scales.len() * 13);
+
+        for &scale in scales {
+            let scale = scale.max(1);
+            let stride = (stride / scale as usize).max(1);
+
+            if scale == 1 {
+                let view = self.crop_view(0, 0, width, height);
+                let mask = mask.crop_view(0, 0, width, height);
+                let view = view.masked(&mask, MaskingStyle::Foreground);
+                texture.extend(view.texture_strided(stride));
+                continue;
+            }
+
+            let (w, h) = (width / scale, height / scale);
+
+            if w < MIN_SIDE || h < MIN_SIDE {
+                texture.extend([0.0; 13]);
+                continue;
+            }
+
+            let image = self.resize(w, h)?;
+            let mask = mask.downsample(w, h)?;
+
+            let view = image.crop_view(0, 0, w, h);
+            let mask = mask.crop_view(0, 0, w, h);
+            let view = view.masked(&mask, MaskingStyle::Foreground);
+            texture.extend(view.texture_strided(stride));
+        }
+
+        Ok(texture)
+    }
+
+    /// Downscale the image into a square 8-bit thumbnail
+    ///
+    /// Values are scaled from the range of the data type into u8, so that
+    /// thumbnails of different objects share the same intensity scale. Single
+    /// and three-channel images are kept as grayscale and RGB and all other
+    /// images are averaged into a grayscale thumbnail. The image is resized so
+    /// that its longest side spans `size` pixels and is centered on a black
+    /// square canvas (letterboxed) to preserve the aspect ratio.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Width and height of the thumbnail
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{ThymeBuffer, ThymeImage};
+    ///
+    /// let image = ThymeImage::U16(ThymeBuffer::new(8, 4, 1, vec![65535u16; 32]).unwrap());
+    /// let thumbnail = image.thumbnail(16).unwrap();
+    ///
+    /// assert_eq!(thumbnail.shape(), (16, 16, 1));
+    /// assert_eq!(thumbnail.to_u8_clamped()[0], 0);
+    /// assert_eq!(thumbnail.to_u8_clamped()[16 * 8], 255);
+    /// ```
+    #[cfg(feature = "resize")]
+    pub fn thumbnail(&self, size: u32) -> Result<ThymeImage, ThymeError> {
+        let (width, height) = (self.width(), self.height());
+
+        if width == 0 || height == 0 || size == 0 {
+            return Err(ThymeError::ImageError(
+                "Thumbnails require a non-empty image and size.",
+            ));
+        }
+
+        let image = self.convert_dtype(Dtype::U8, ConversionPolicy::ScaledDtype)?;
+        let image = match image.channels() {
+            1 | 3 => image,
+            _ => image.gather_channels(&ChannelMap::GrayAverage)?,
+        };
+
+        let scale = size as f32 / width.max(height) as f32;
+        let w = ((width as f32 * scale).round() as u32).clamp(1, size);
+        let h = ((height as f32 * scale).round() as u32).clamp(1, size);
+
+        let resized = image.resize(w, h)?.to_u8_clamped();
+
+        let channels = image.channels() as usize;
+        let (x, y) = (((size - w) / 2) as usize, ((size - h) / 2) as usize);
+        let (size, w) = (size as usize, w as usize);
+
+        let mut canvas = vec![0u8; size * size * channels];
+
+        for (row, pixels) in resized.chunks_exact(w * channels).enumerate() {
+            let start = ((y + row) * size + x) * channels;
+            canvas[start..start + pixels.len()].copy_from_slice(pixels);
+        }
+
+        Ok(ThymeImage::U8(ThymeBuffer::new(
+            size as u32,
+            size as u32,
+            channels as u32,
+            canvas,
+        )?))
+    }
 }
 
 // <<< TRANSFORM METHODS
 
-#[cfg(test)]
+#[cfg(all(test, feature = "io", feature = "resize"))]
 mod test {
 
     use super::*;
@@ -873,8 +1557,8 @@ mod test {
         let img_default = ThymeImage::open(TEST_DEFAULT).unwrap();
         let img_numpy = ThymeImage::open(TEST_NUMPY).unwrap();
 
-        assert_eq!(img.to_u8(), img_default.to_u8());
-        assert_eq!(img.to_u8(), img_numpy.to_u8());
+        assert_eq!(img.to_u8_clamped(), img_default.to_u8_clamped());
+        assert_eq!(img.to_u8_clamped(), img_numpy.to_u8_clamped());
 
         std::fs::remove_file(TEST_DEFAULT).unwrap();
         std::fs::remove_file(TEST_NUMPY).unwrap();
@@ -896,8 +1580,8 @@ mod test {
         let img_default = ThymeImage::open(TEST_DEFAULT).unwrap();
         let img_numpy = ThymeImage::open(TEST_NUMPY).unwrap();
 
-        assert_eq!(img.to_u8(), img_default.to_u8());
-        assert_eq!(img.to_u8(), img_numpy.to_u8());
+        assert_eq!(img.to_u8_clamped(), img_default.to_u8_clamped());
+        assert_eq!(img.to_u8_clamped(), img_numpy.to_u8_clamped());
 
         std::fs::remove_file(TEST_DEFAULT).unwrap();
         std::fs::remove_file(TEST_NUMPY).unwrap();
@@ -924,7 +1608,7 @@ mod test {
         img.clone().save(TEST_NUMPY).unwrap();
 
         let img_numpy = ThymeImage::open(TEST_NUMPY).unwrap();
-        assert_eq!(img.to_u8(), img_numpy.to_u8());
+        assert_eq!(img.to_u8_clamped(), img_numpy.to_u8_clamped());
         std::fs::remove_file(TEST_NUMPY).unwrap();
     }
 
@@ -952,7 +1636,7 @@ mod test {
         assert_eq!(image.width(), 10);
         assert_eq!(image.height(), 10);
 
-        let data = image.to_u8();
+        let data = image.to_u8_clamped();
         assert_eq!(data[0], 0);
         assert_eq!(data[1], 1);
     }
@@ -988,7 +1672,7 @@ mod test {
         assert_eq!(image.width(), 10);
         assert_eq!(image.height(), 10);
 
-        let data = image.to_u16();
+        let data = image.to_u16_clamped();
         assert_eq!(data[0], 0);
         assert_eq!(data[1], 1);
     }
@@ -1006,7 +1690,7 @@ mod test {
         assert_eq!(image.width(), 10);
         assert_eq!(image.height(), 10);
 
-        let data = image.to_u8();
+        let data = image.to_u8_clamped();
         assert_eq!((data[0], data[1], data[2]), (0, 0, 0));
         assert_eq!((data[3], data[4], data[5]), (1, 1, 1));
     }
@@ -1024,7 +1708,7 @@ mod test {
         assert_eq!(image.width(), 10);
         assert_eq!(image.height(), 10);
 
-        let data = image.to_u8();
+        let data = image.to_u8_clamped();
         assert_eq!((data[0], data[1], data[2]), (0, 0, 0));
         assert_eq!((data[3], data[4], data[5]), (1, 1, 1));
     }
@@ -1044,7 +1728,7 @@ mod test {
         assert_eq!(image.width(), 10);
         assert_eq!(image.height(), 10);
 
-        let data = image.to_u16();
+        let data = image.to_u16_clamped();
         assert_eq!((data[0], data[1], data[2]), (0, 0, 0));
         assert_eq!((data[3], data[4], data[5]), (1, 1, 1));
     }
@@ -1064,7 +1748,7 @@ mod test {
         assert_eq!(image.width(), 10);
         assert_eq!(image.height(), 10);
 
-        let data = image.to_u16();
+        let data = image.to_u16_clamped();
         assert_eq!((data[0], data[1], data[2]), (0, 0, 0));
         assert_eq!((data[3], data[4], data[5]), (1, 1, 1));
     }
@@ -1138,6 +1822,97 @@ mod test {
         assert_eq!(upsampled.height(), 24);
     }
 
+    #[cfg(feature = "resize")]
+    #[test]
+    fn test_resize_u8_channels_agree() {
+        let pixels: Vec<u8> = (0..15 * 11).map(|i| ((i * 37) % 251) as u8).collect();
+        let one_channel = ThymeImage::U8(ThymeBuffer::new(15, 11, 1, pixels.clone()).unwrap());
+
+        let pixels = pixels.iter().flat_map(|&p| [p, 0]).collect();
+        let two_channel = ThymeImage::U8(ThymeBuffer::new(15, 11, 2, pixels).unwrap());
+
+        for (width, height) in [(32, 25), (6, 4), (15, 30)] {
+            let one = one_channel.resize(width, height).unwrap().to_u8_clamped();
+            let two = two_channel.resize(width, height).unwrap().to_u8_clamped();
+
+            assert!(
+                one.iter()
+                    .zip(two.iter().step_by(2))
+                    .all(|(a, b)| a.abs_diff(*b) <= 1)
+            );
+        }
+    }
+
+    #[cfg(feature = "resize")]
+    #[test]
+    fn test_texture_scales() {
+        use crate::im::ThymeMask;
+
+        let (width, height) = (48, 40);
+        let pixels = (0..width * height)
+            .map(|i| ((i % width + i / width) % 2 * 200 + (i % 7) * 5) as u8)
+            .collect();
+        let image = ThymeImage::U8(ThymeBuffer::new(width, height, 1, pixels).unwrap());
+
+        let mask = (0..width * height)
+            .map(|i| u32::from((i % width) > 4 && (i / width) > 3))
+            .collect();
+        let mask = ThymeMask::new(width, height, 1, mask).unwrap();
+
+        let texture = image.texture_scales(&mask, &[1, 2, 4], 2).unwrap();
+        assert_eq!(texture.len(), 3 * 13);
+
+        // A scale of one matches the single-scale foreground texture
+        let view = image.crop_view(0, 0, width, height);
+        let expected = view
+            .masked(
+                &mask.crop_view(0, 0, width, height),
+                MaskingStyle::Foreground,
+            )
+            .texture_strided(2);
+        assert_eq!(&texture[..13], &expected);
+
+        // A one pixel checkerboard is averaged away when downsampled
+        assert!(texture[27] < texture[1], "{} {}", texture[27], texture[1]);
+
+        // Crops below eight pixels per side at a scale are filled with zeros
+        let texture = image.texture_scales(&mask, &[1, 8], 2).unwrap();
+        assert_eq!(&texture[..13], &expected);
+        assert!(texture[13..].iter().all(|&v| v == 0.0));
+
+        let small = ThymeMask::new(2, 2, 1, vec![1; 4]).unwrap();
+        assert!(image.texture_scales(&small, &[1], 1).is_err());
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let rgb = ThymeImage::open(format!("{}.png", TEST_RGB)).unwrap();
+        let crop = rgb.crop(10, 20, 60, 30).unwrap();
+
+        let thumbnail = crop.thumbnail(64).unwrap();
+        assert_eq!(thumbnail.shape(), (64, 64, 3));
+
+        // Wide crops are centered vertically between black bars
+        let pixels = thumbnail.to_u8_clamped();
+        assert!(pixels[..64 * 3 * 15].iter().all(|&v| v == 0));
+        assert!(pixels[64 * 3 * 49..].iter().all(|&v| v == 0));
+
+        // Values are scaled from the data type range rather than the crop range
+        let pixels: Vec<u16> = (0..40 * 50 * 2)
+            .map(|i| (i % 2) as u16 * 257 * 100)
+            .collect();
+        let image = ThymeImage::U16(ThymeBuffer::new(40, 50, 2, pixels).unwrap());
+
+        let thumbnail = image.thumbnail(20).unwrap();
+        assert_eq!(thumbnail.shape(), (20, 20, 1));
+        assert_eq!(thumbnail.to_u8_clamped()[20 * 10 + 10], 50);
+        assert_eq!(thumbnail.to_u8_clamped()[20 * 10], 0);
+
+        let empty = ThymeImage::U8(ThymeBuffer::new(0, 4, 1, vec![]).unwrap());
+        assert!(empty.thumbnail(16).is_err());
+        assert!(crop.thumbnail(0).is_err());
+    }
+
     #[test]
     fn test_resize_u16() {
         let dynamic = DynamicImage::ImageLuma16(ImageBuffer::<Luma<u16>, Vec<u16>>::from_fn(
@@ -1184,4 +1959,148 @@ mod test {
         assert_eq!(upsampled.width(), 23);
         assert_eq!(upsampled.height(), 24);
     }
+
+    #[test]
+    fn test_gather_channels() {
+        let gray =
+            ThymeImage::U16(ThymeBuffer::<u16, Vec<u16>>::new(2, 1, 1, vec![10, 20]).unwrap());
+
+        let replicated = gray.gather_channels(&ChannelMap::Auto).unwrap();
+        assert_eq!(replicated.channels(), 3);
+        assert_eq!(replicated.to_u16_clamped(), vec![10, 10, 10, 20, 20, 20]);
+
+        let error = gray.gather_channels(&ChannelMap::parse("0,1,0").unwrap());
+        assert!(matches!(error, Err(ThymeError::ChannelMapError(_))));
+
+        let multichannel = ThymeImage::U8(
+            ThymeBuffer::<u8, Vec<u8>>::new(1, 2, 4, vec![1, 2, 3, 4, 5, 6, 7, 8]).unwrap(),
+        );
+
+        let averaged = multichannel.gather_channels(&ChannelMap::Auto).unwrap();
+        assert_eq!(averaged.channels(), 1);
+        assert_eq!(averaged.to_u8_clamped(), vec![3, 7]);
+
+        let reordered = multichannel
+            .gather_channels(&ChannelMap::parse("3,2,1").unwrap())
+            .unwrap();
+        assert_eq!(reordered.to_u8_clamped(), vec![4, 3, 2, 8, 7, 6]);
+    }
+
+    #[test]
+    fn test_float_tiff_open() {
+        use tiff::encoder::{TiffEncoder, colortype};
+
+        const TEST_TIFF: &str = "TEST_OPEN_FLOAT_GRAY.tif";
+
+        let probability = vec![0.0f32, 0.25, 0.5, 1.0, 0.75, 0.125];
+
+        let file = File::create(TEST_TIFF).unwrap();
+        TiffEncoder::new(file)
+            .unwrap()
+            .write_image::<colortype::Gray32Float>(3, 2, &probability)
+            .unwrap();
+
+        let image = ThymeImage::open(TEST_TIFF).unwrap();
+
+        assert_eq!(image.shape(), (2, 3, 1));
+        assert!(image.is_probability_map());
+        assert_eq!(image.to_f32(), probability);
+
+        std::fs::remove_file(TEST_TIFF).unwrap();
+    }
+
+    #[test]
+    fn test_png_with_text_roundtrip() {
+        let path = std::env::temp_dir().join("thyme_test_png_with_text_roundtrip.png");
+        let text = [("thyme", "{\"object\":1}")];
+
+        let rgb = ThymeImage::U8(ThymeBuffer::new(3, 2, 3, (0..18).collect()).unwrap());
+        rgb.save_as_png_with_text(&path, &text).unwrap();
+
+        let observed = ThymeImage::open(&path).unwrap();
+        assert_eq!(observed.shape(), rgb.shape());
+        assert_eq!(observed.to_f32(), rgb.to_f32());
+        assert_eq!(
+            crate::io::read_png_text(&path).unwrap(),
+            vec![("thyme".to_string(), text[0].1.to_string())]
+        );
+
+        let gray = ThymeImage::U16(ThymeBuffer::new(2, 2, 1, vec![0, 1, 256, 65535]).unwrap());
+        gray.save_as_png_with_text(&path, &text).unwrap();
+
+        let observed = ThymeImage::open(&path).unwrap();
+        assert_eq!(observed.dtype_max(), gray.dtype_max());
+        assert_eq!(observed.to_f32(), gray.to_f32());
+
+        let float = ThymeImage::F32(ThymeBuffer::new(1, 1, 1, vec![0.5]).unwrap());
+        assert!(float.save_as_png_with_text(&path, &text).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tiff_roundtrip() {
+        const TEST_TIFF: &str = "TEST_TIFF_ROUNDTRIP.tif";
+
+        let images = [
+            ThymeImage::U8(ThymeBuffer::new(3, 2, 1, (0..6).collect()).unwrap()),
+            ThymeImage::U16(
+                ThymeBuffer::new(3, 2, 3, (0..18).map(|v| v * 1000).collect()).unwrap(),
+            ),
+            ThymeImage::U16(ThymeBuffer::new(3, 2, 5, (0..30).collect()).unwrap()),
+            ThymeImage::I32(ThymeBuffer::new(3, 2, 2, (-6..6).collect()).unwrap()),
+            ThymeImage::F32(
+                ThymeBuffer::new(3, 2, 3, (0..18).map(|v| v as f32 / 7.0).collect()).unwrap(),
+            ),
+            ThymeImage::F64(
+                ThymeBuffer::new(3, 2, 2, (0..12).map(|v| v as f64 * 0.1).collect()).unwrap(),
+            ),
+        ];
+
+        for compression in [
+            TiffCompression::None,
+            TiffCompression::Lzw,
+            TiffCompression::Deflate,
+            TiffCompression::Packbits,
+        ] {
+            for image in images.iter() {
+                image.clone().save_as_tiff(TEST_TIFF, compression).unwrap();
+
+                let opened = ThymeImage::open(TEST_TIFF).unwrap();
+                assert_eq!(opened.shape(), image.shape(), "{:?}", compression);
+                assert_eq!(opened.dtype_max(), image.dtype_max());
+                assert_eq!(opened.to_f32(), image.to_f32());
+            }
+        }
+
+        std::fs::remove_file(TEST_TIFF).unwrap();
+    }
+
+    #[test]
+    fn test_convert_dtype_u16_to_u8() {
+        let image =
+            ThymeImage::U16(ThymeBuffer::new(4, 1, 1, vec![0u16, 200, 300, 65535]).unwrap());
+
+        let convert = |policy| match image.convert_dtype(Dtype::U8, policy).unwrap() {
+            ThymeImage::U8(buffer) => buffer.into_raw(),
+            _ => panic!("Expected u8 image"),
+        };
+
+        assert_eq!(convert(ConversionPolicy::LossyZero), vec![0, 200, 0, 0]);
+        assert_eq!(convert(ConversionPolicy::Clamped), vec![0, 200, 255, 255]);
+        assert_eq!(convert(ConversionPolicy::ScaledMinMax), vec![0, 1, 1, 255]);
+        assert_eq!(convert(ConversionPolicy::ScaledDtype), vec![0, 1, 1, 255]);
+
+        let dim = ThymeImage::U16(ThymeBuffer::new(2, 1, 1, vec![250u16, 500]).unwrap());
+        let scaled = dim
+            .convert_dtype(Dtype::U8, ConversionPolicy::ScaledMinMax)
+            .unwrap();
+        assert_eq!(scaled.to_u8_clamped(), vec![0, 255]);
+
+        let float = image
+            .convert_dtype(Dtype::F32, ConversionPolicy::ScaledDtype)
+            .unwrap();
+        assert_eq!(float.to_f32()[3], 1.0);
+        assert_eq!(float.shape(), image.shape());
+    }
 }
@@ -1,16 +1,38 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+#[cfg(feature = "io")]
+use std::fs::File;
+#[cfg(feature = "io")]
+use std::io::BufReader;
+#[cfg(feature = "io")]
 use std::path::Path;
 
+#[cfg(feature = "io")]
 use image::{DynamicImage, ImageBuffer, Luma, open as open_dynamic};
+#[cfg(feature = "io")]
 use npyz::{self, DType, NpyFile, TypeChar, WriterBuilder};
-
+#[cfg(feature = "io")]
+use png::{ColorType as PngColorType, Decoder as PngDecoder, Transformations};
+#[cfg(feature = "io")]
+use tiff::{
+    ColorType,
+    decoder::{Decoder, DecodingResult},
+};
+
+#[cfg(feature = "io")]
 use crate::constant;
-use crate::cv::{connected_components, find_labeled_contours};
+use crate::cv::{
+    connected_components, find_labeled_contours, find_labeled_contours_with_hierarchy, otsu,
+    watershed,
+};
 use crate::error::ThymeError;
-use crate::im::{Polygons, ThymeBuffer, ThymeViewBuffer};
+use crate::im::{Polygons, ThymeBuffer, ThymeImage, ThymeViewBuffer};
+#[cfg(feature = "io")]
+use crate::io::{
+    is_object_numpy, parse_cellpose_seg, parse_numpy, write_bytes_atomic, write_dynamic_atomic,
+};
 
 /// A row-major container storing mask pixels
 ///
@@ -43,8 +65,66 @@ use crate::im::{Polygons, ThymeBuffer, ThymeViewBuffer};
 /// ```
 pub type ThymeMask = ThymeBuffer<u32, Vec<u32>>;
 
+/// Layout of mask files
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::MaskFormat;
+///
+/// assert_eq!(MaskFormat::parse("cellpose-seg").unwrap(), MaskFormat::CellposeSeg);
+/// assert!(MaskFormat::parse("stardist").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MaskFormat {
+    /// Masks are decoded according to their extension
+    #[default]
+    Auto,
+    /// Cellpose `_seg.npy` files holding the masks in a pickled dictionary
+    CellposeSeg,
+}
+
+impl MaskFormat {
+    /// Parse a mask format (auto or cellpose-seg)
+    pub fn parse(format: &str) -> Result<Self, ThymeError> {
+        match format {
+            "auto" => Ok(MaskFormat::Auto),
+            "cellpose-seg" => Ok(MaskFormat::CellposeSeg),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid mask_format {}. Must be one of: {:?}",
+                format,
+                crate::constant::SUPPORTED_MASK_FORMATS
+            ))),
+        }
+    }
+}
+
+/// Parse the mask array of a numpy file with the provided layout
+///
+/// Numpy files holding pickled objects are rejected with a hint to read
+/// them as Cellpose seg files.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes of a numpy file
+/// * `format` - Layout of the mask file
+#[cfg(feature = "io")]
+pub(crate) fn parse_mask_numpy(
+    bytes: &[u8],
+    format: MaskFormat,
+) -> Result<NpyFile<&[u8]>, ThymeError> {
+    match format {
+        MaskFormat::CellposeSeg => parse_cellpose_seg(bytes),
+        MaskFormat::Auto if is_object_numpy(bytes) => Err(ThymeError::NpyError(
+            "The numpy file holds pickled objects instead of a mask array. Cellpose _seg.npy files are read with the cellpose-seg mask format".to_string(),
+        )),
+        MaskFormat::Auto => parse_numpy(bytes),
+    }
+}
+
 // >>> I/O METHODS
 
+#[cfg(feature = "io")]
 impl ThymeMask {
     /// Open a new mask from a provided path
     ///
@@ -57,26 +137,110 @@ impl ThymeMask {
     /// let image = ThymeMask::open("mask.png");
     /// ```
     pub fn open<P: AsRef<Path>>(path: P) -> Result<ThymeMask, ThymeError> {
+        Self::open_with_summary(path).map(|(mask, _)| mask)
+    }
+
+    /// Open a new mask and summarize its labels
+    ///
+    /// The summary records the data type the mask was decoded from so that
+    /// masks which may have been re-encoded at a lower bit depth can be
+    /// flagged with [`MaskSummary::check_bit_depth`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to an image with a valid extension
+    ///
+    /// ```no_run
+    /// use thyme_core::im::ThymeMask;
+    /// let (mask, summary) = ThymeMask::open_with_summary("mask.png").unwrap();
+    /// println!("{} labels (max label {})", summary.labels, summary.max_label);
+    /// ```
+    pub fn open_with_summary<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<(ThymeMask, MaskSummary), ThymeError> {
+        Self::open_format_with_summary(path, MaskFormat::Auto)
+    }
+
+    /// Open a new mask with the provided layout
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to a mask
+    /// * `format` - Layout of the mask file
+    ///
+    /// ```no_run
+    /// use thyme_core::im::{MaskFormat, ThymeMask};
+    /// let mask = ThymeMask::open_format("image_seg.npy", MaskFormat::CellposeSeg);
+    /// ```
+    pub fn open_format<P: AsRef<Path>>(
+        path: P,
+        format: MaskFormat,
+    ) -> Result<ThymeMask, ThymeError> {
+        Self::open_format_with_summary(path, format).map(|(mask, _)| mask)
+    }
+
+    /// Open a new mask with the provided layout and summarize its labels
+    ///
+    /// Cellpose seg files are read regardless of their extension and all
+    /// other masks are decoded according to their extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to a mask
+    /// * `format` - Layout of the mask file
+    ///
+    /// ```no_run
+    /// use thyme_core::im::{MaskFormat, ThymeMask};
+    /// let (mask, summary) =
+    ///     ThymeMask::open_format_with_summary("image_seg.npy", MaskFormat::CellposeSeg).unwrap();
+    /// ```
+    pub fn open_format_with_summary<P: AsRef<Path>>(
+        path: P,
+        format: MaskFormat,
+    ) -> Result<(ThymeMask, MaskSummary), ThymeError> {
         let extension = path
             .as_ref()
             .extension()
             .and_then(|s| s.to_str())
             .map(|s| s.to_lowercase());
 
+        let summarize = |(mask, dtype_max): (ThymeMask, u32)| {
+            let summary = MaskSummary::new(mask.as_raw(), dtype_max);
+            (mask, summary)
+        };
+
+        if format == MaskFormat::CellposeSeg {
+            let bytes = std::fs::read(&path).map_err(|_| ThymeError::ImageReadError)?;
+            return Self::decode_numpy(parse_cellpose_seg(&bytes)?).map(summarize);
+        }
+
         if let Some(ext) = extension {
             if ext == "npy" {
-                if let Ok(bytes) = std::fs::read(&path) {
-                    if let Ok(npy) = NpyFile::new(&bytes[..]) {
-                        return Self::new_from_numpy(npy);
-                    }
+                let bytes = std::fs::read(&path).map_err(|_| ThymeError::ImageReadError)?;
+                return Self::decode_numpy(parse_mask_numpy(&bytes, format)?).map(summarize);
+            }
+
+            // 32-bit integer TIFFs are not covered by DynamicImage so grayscale
+            // TIFFs are decoded directly before falling back to DynamicImage
+            if ext == "tif" || ext == "tiff" {
+                if let Ok(mask) = Self::decode_tiff(&path) {
+                    return Ok(summarize(mask));
                 }
+            }
 
-                return Err(ThymeError::ImageReadError);
+            // Indexed-color PNGs are expanded to RGB by DynamicImage so their
+            // palette indices are read directly as labels
+            if ext == "png" {
+                if let Some(mask) = Self::decode_palette_png(&path)? {
+                    let (mask, mut summary) = summarize(mask);
+                    summary.palette = true;
+                    return Ok((mask, summary));
+                }
             }
 
             if constant::IMAGE_DYNAMIC_FORMATS.iter().any(|e| e == &ext) {
                 if let Ok(image) = open_dynamic(&path) {
-                    return Self::new_from_dynamic(image);
+                    return Self::decode_dynamic(image).map(summarize);
                 }
 
                 return Err(ThymeError::ImageReadError);
@@ -103,50 +267,67 @@ impl ThymeMask {
     /// let image = ThymeMask::new_from_dynamic(dynamic);
     /// ```
     pub fn new_from_dynamic(mask: DynamicImage) -> Result<ThymeMask, ThymeError> {
+        Self::decode_dynamic(mask).map(|(mask, _)| mask)
+    }
+
+    /// Decode a DynamicImage mask along with the maximum value of its data type
+    fn decode_dynamic(mask: DynamicImage) -> Result<(ThymeMask, u32), ThymeError> {
         let width = mask.width();
         let height = mask.height();
 
         match mask {
-            DynamicImage::ImageLuma8(buffer) => Ok(ThymeMask::new(
-                width,
-                height,
-                1,
-                buffer
-                    .into_raw()
-                    .into_iter()
-                    .map(|pixel| pixel as u32)
-                    .collect(),
-            )?),
-            DynamicImage::ImageLumaA8(buffer) => Ok(ThymeMask::new(
-                width,
-                height,
-                1,
-                buffer
-                    .into_raw()
-                    .chunks_exact(2)
-                    .map(|pixel| pixel[0] as u32)
-                    .collect(),
-            )?),
-            DynamicImage::ImageLuma16(buffer) => Ok(ThymeMask::new(
-                width,
-                height,
-                1,
-                buffer
-                    .into_raw()
-                    .into_iter()
-                    .map(|pixel| pixel as u32)
-                    .collect(),
-            )?),
-            DynamicImage::ImageLumaA16(buffer) => Ok(ThymeMask::new(
-                width,
-                height,
-                1,
-                buffer
-                    .into_raw()
-                    .chunks_exact(2)
-                    .map(|pixel| pixel[0] as u32)
-                    .collect(),
-            )?),
+            DynamicImage::ImageLuma8(buffer) => Ok((
+                ThymeMask::new(
+                    width,
+                    height,
+                    1,
+                    buffer
+                        .into_raw()
+                        .into_iter()
+                        .map(|pixel| pixel as u32)
+                        .collect(),
+                )?,
+                u8::MAX as u32,
+            )),
+            DynamicImage::ImageLumaA8(buffer) => Ok((
+                ThymeMask::new(
+                    width,
+                    height,
+                    1,
+                    buffer
+                        .into_raw()
+                        .chunks_exact(2)
+                        .map(|pixel| pixel[0] as u32)
+                        .collect(),
+                )?,
+                u8::MAX as u32,
+            )),
+            DynamicImage::ImageLuma16(buffer) => Ok((
+                ThymeMask::new(
+                    width,
+                    height,
+                    1,
+                    buffer
+                        .into_raw()
+                        .into_iter()
+                        .map(|pixel| pixel as u32)
+                        .collect(),
+                )?,
+                u16::MAX as u32,
+            )),
+            DynamicImage::ImageLumaA16(buffer) => Ok((
+                ThymeMask::new(
+                    width,
+                    height,
+                    1,
+                    buffer
+                        .into_raw()
+                        .chunks_exact(2)
+                        .map(|pixel| pixel[0] as u32)
+                        .collect(),
+                )?,
+                u16::MAX as u32,
+            )),
             _ => Err(ThymeError::MaskError(
                 "A dynamic image mask with a valid data type was not detected.",
             )),
@@ -155,6 +336,9 @@ impl ThymeMask {
 
     /// Initialize a new image from a numpy array buffer
     ///
+    /// Buffers parsed with `io::parse_numpy` have a header validated against
+    /// their payload, so corrupt files fail before any pixels are allocated.
+    ///
     /// # Arguments
     ///
     /// * `npy` - A (height, width, channel) shaped numpy array buffer
@@ -162,14 +346,19 @@ impl ThymeMask {
     /// # Examples
     ///
     /// ```no_run
-    /// use npyz::NpyFile;
     /// use thyme_core::im::ThymeMask;
+    /// use thyme_core::io::parse_numpy;
     ///
     /// let bytes = std::fs::read("mask.npy").unwrap();
-    /// let npy = NpyFile::new(&bytes[..]).unwrap();
+    /// let npy = parse_numpy(&bytes).unwrap();
     /// let image = ThymeMask::new_from_numpy(npy);
     /// ```
     pub fn new_from_numpy(npy: NpyFile<&[u8]>) -> Result<ThymeMask, ThymeError> {
+        Self::decode_numpy(npy).map(|(mask, _)| mask)
+    }
+
+    /// Decode a numpy mask along with the maximum value of its data type
+    fn decode_numpy(npy: NpyFile<&[u8]>) -> Result<(ThymeMask, u32), ThymeError> {
         let shape = npy.shape().to_vec();
 
         let (h, w, c) = match shape.len() {
@@ -183,32 +372,43 @@ impl ThymeMask {
         };
 
         if c != 1 {
-            return Err(ThymeError::MaskFormatError);
+            return Err(ThymeError::MaskError(
+                "Numpy array masks must have an (H, W) shape. Three-dimensional (Z, H, W) stacks must be max-projected or split into slices.",
+            ));
         }
 
         match npy.dtype() {
             DType::Plain(x) => match (x.type_char(), x.size_field()) {
-                (TypeChar::Uint, 1) => Ok(ThymeMask::new(
-                    w,
-                    h,
-                    1,
-                    npy.into_vec()
-                        .unwrap()
-                        .into_iter()
-                        .map(|pixel: u8| pixel as u32)
-                        .collect(),
-                )?),
-                (TypeChar::Uint, 2) => Ok(ThymeMask::new(
-                    w,
-                    h,
-                    1,
-                    npy.into_vec()
-                        .unwrap()
-                        .into_iter()
-                        .map(|pixel: u16| pixel as u32)
-                        .collect(),
-                )?),
-                (TypeChar::Uint, 4) => Ok(ThymeMask::new(w, h, 1, npy.into_vec().unwrap())?),
+                (TypeChar::Uint, 1) => Ok((
+                    ThymeMask::new(
+                        w,
+                        h,
+                        1,
+                        npy.into_vec()
+                            .map_err(|_| ThymeError::ImageReadError)?
+                            .into_iter()
+                            .map(|pixel: u8| pixel as u32)
+                            .collect(),
+                    )?,
+                    u8::MAX as u32,
+                )),
+                (TypeChar::Uint, 2) => Ok((
+                    ThymeMask::new(
+                        w,
+                        h,
+                        1,
+                        npy.into_vec()
+                            .map_err(|_| ThymeError::ImageReadError)?
+                            .into_iter()
+                            .map(|pixel: u16| pixel as u32)
+                            .collect(),
+                    )?,
+                    u16::MAX as u32,
+                )),
+                (TypeChar::Uint, 4) => {
+                    let pixels = npy.into_vec().map_err(|_| ThymeError::ImageReadError)?;
+                    Ok((ThymeMask::new(w, h, 1, pixels)?, u32::MAX))
+                }
                 _ => Err(ThymeError::MaskError(
                     "A numpy mask array with a valid data type was not detected.",
                 )),
@@ -218,13 +418,323 @@ impl ThymeMask {
             )),
         }
     }
+
+    /// Initialize a new mask from a grayscale TIFF
+    ///
+    /// TIFFs are decoded directly so that 32-bit integer masks, which are not
+    /// supported by DynamicImage, preserve their full label range.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to an 8, 16, or 32-bit grayscale TIFF
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use thyme_core::im::ThymeMask;
+    /// let mask = ThymeMask::new_from_tiff("mask.tif");
+    /// ```
+    pub fn new_from_tiff<P: AsRef<Path>>(path: P) -> Result<ThymeMask, ThymeError> {
+        Self::decode_tiff(path).map(|(mask, _)| mask)
+    }
+
+    /// Decode a TIFF mask along with the maximum value of its data type
+    fn decode_tiff<P: AsRef<Path>>(path: P) -> Result<(ThymeMask, u32), ThymeError> {
+        let file = File::open(path).map_err(|_| ThymeError::ImageReadError)?;
+        let mut decoder =
+            Decoder::new(BufReader::new(file)).map_err(|_| ThymeError::ImageReadError)?;
+
+        let (width, height) = decoder
+            .dimensions()
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        match decoder.colortype() {
+            Ok(ColorType::Gray(_)) => {}
+            Ok(_) => return Err(ThymeError::MaskFormatError),
+            Err(_) => return Err(ThymeError::ImageReadError),
+        }
+
+        let (buffer, dtype_max) = match decoder
+            .read_image()
+            .map_err(|_| ThymeError::ImageReadError)?
+        {
+            DecodingResult::U8(buffer) => (
+                buffer.into_iter().map(|pixel| pixel as u32).collect(),
+                u8::MAX as u32,
+            ),
+            DecodingResult::U16(buffer) => (
+                buffer.into_iter().map(|pixel| pixel as u32).collect(),
+                u16::MAX as u32,
+            ),
+            DecodingResult::U32(buffer) => (buffer, u32::MAX),
+            DecodingResult::I32(buffer) => {
+                if buffer.iter().any(|&pixel| pixel < 0) {
+                    return Err(ThymeError::MaskError(
+                        "Integer TIFF masks cannot contain negative labels.",
+                    ));
+                }
+
+                (
+                    buffer.into_iter().map(|pixel| pixel as u32).collect(),
+                    i32::MAX as u32,
+                )
+            }
+            _ => {
+                return Err(ThymeError::MaskError(
+                    "A TIFF mask with a valid data type was not detected.",
+                ));
+            }
+        };
+
+        Ok((ThymeMask::new(width, height, 1, buffer)?, dtype_max))
+    }
+
+    /// Decode the palette indices of an indexed-color PNG as labels
+    ///
+    /// Returns `None` if the PNG is not indexed-color so that it is decoded
+    /// as a DynamicImage instead. The data type maximum is the largest index
+    /// representable at the bit depth of the PNG.
+    fn decode_palette_png<P: AsRef<Path>>(path: P) -> Result<Option<(ThymeMask, u32)>, ThymeError> {
+        let file = File::open(path).map_err(|_| ThymeError::ImageReadError)?;
+
+        let mut decoder = PngDecoder::new(BufReader::new(file));
+        decoder.set_transformations(Transformations::IDENTITY);
+
+        let mut reader = decoder
+            .read_info()
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        let info = reader.info();
+
+        if info.color_type != PngColorType::Indexed {
+            return Ok(None);
+        }
+
+        let (width, height) = (info.width, info.height);
+        let depth = info.bit_depth as usize;
+
+        let mut bytes = vec![0u8; reader.output_buffer_size()];
+        let frame = reader
+            .next_frame(&mut bytes)
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        // Indices with fewer than 8 bits are packed into each byte starting
+        // from the most significant bit, and each row starts on a new byte
+        let per_byte = 8 / depth;
+        let lowest = (1u32 << depth) - 1;
+
+        let buffer: Vec<u32> = bytes
+            .chunks_exact(frame.line_size)
+            .take(height as usize)
+            .flat_map(|row| {
+                (0..width as usize).map(move |x| {
+                    let byte = row[x / per_byte] as u32;
+                    let shift = 8 - depth * (x % per_byte + 1);
+                    (byte >> shift) & lowest
+                })
+            })
+            .collect();
+
+        Ok(Some((ThymeMask::new(width, height, 1, buffer)?, lowest)))
+    }
 }
 
 // <<< I/O METHODS
 
+/// Summary of the labels in a mask and the data type they were decoded from
+///
+/// Labeled masks that are re-encoded at a lower bit depth (e.g. a 16-bit PNG
+/// saved as 8-bit) silently merge labels that collide modulo the new maximum.
+/// The summary is used to flag masks that may have been truncated.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::MaskSummary;
+///
+/// let summary = MaskSummary::new(&[0, 1, 2, 2, 255], u8::MAX as u32);
+///
+/// assert_eq!(summary.labels, 3);
+/// assert_eq!(summary.max_label, 255);
+/// assert!(summary.check_bit_depth().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaskSummary {
+    /// Number of unique non-zero labels
+    pub labels: usize,
+    /// Largest label in the mask
+    pub max_label: u32,
+    /// Largest value representable by the data type the mask was decoded from
+    pub dtype_max: u32,
+    /// Whether labels were read from the palette indices of an indexed-color image
+    pub palette: bool,
+}
+
+impl MaskSummary {
+    /// Summarize the labels in a mask buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - Mask pixels
+    /// * `dtype_max` - Maximum value of the data type the mask was decoded from
+    pub fn new(buffer: &[u32], dtype_max: u32) -> Self {
+        let labels: HashSet<u32> = buffer.iter().filter(|&&x| x != 0).copied().collect();
+
+        Self {
+            labels: labels.len(),
+            max_label: labels.iter().max().copied().unwrap_or(0),
+            dtype_max,
+            palette: false,
+        }
+    }
+
+    /// Check whether the mask labels may have been truncated to a lower bit depth
+    ///
+    /// A mask is flagged if it has exactly 255 or 256 unique labels, or if it has
+    /// more than one label and the maximum label equals the data type maximum.
+    /// Binary masks (e.g. 0/255) are never flagged.
+    pub fn is_possibly_truncated(&self) -> bool {
+        self.labels == 255
+            || self.labels == 256
+            || (self.labels > 1 && self.max_label == self.dtype_max)
+    }
+
+    /// Return an error if the mask labels may have been truncated
+    pub fn check_bit_depth(&self) -> Result<(), ThymeError> {
+        if self.is_possibly_truncated() {
+            return Err(ThymeError::MaskDepthError(format!(
+                "Detected {} unique labels with a maximum label of {} (data type maximum of {}). Check that the mask was not re-encoded at a lower bit depth",
+                self.labels, self.max_label, self.dtype_max
+            )));
+        }
+
+        Ok(())
+    }
+}
+
 // >>> TRANSFORM METHODS
 
 impl ThymeMask {
+    /// Initialize a labeled mask by thresholding a probability map
+    ///
+    /// Pixels with a probability above the threshold are foreground and are
+    /// labeled using 8-connected components.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - A single channel probability map
+    /// * `threshold` - Pixels above the threshold are foreground
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{ThymeBuffer, ThymeImage, ThymeMask};
+    ///
+    /// let probability = vec![0.1f32, 0.9, 0.8, 0.2, 0.1, 0.7];
+    /// let image = ThymeImage::F32(ThymeBuffer::new(6, 1, 1, probability).unwrap());
+    ///
+    /// let mask = ThymeMask::from_probability(&image, 0.5).unwrap();
+    /// assert_eq!(mask.as_raw(), &vec![0, 1, 1, 0, 0, 2]);
+    /// ```
+    pub fn from_probability(image: &ThymeImage, threshold: f32) -> Result<ThymeMask, ThymeError> {
+        let probability = Self::probability(image)?;
+
+        let foreground: Vec<u32> = probability
+            .iter()
+            .map(|&p| (p > threshold) as u32)
+            .collect();
+
+        ThymeMask::new(
+            image.width(),
+            image.height(),
+            1,
+            connected_components(image.width(), image.height(), &foreground),
+        )
+    }
+
+    /// Initialize a labeled mask by thresholding a probability map and splitting
+    /// touching objects using a watershed seeded at local probability maxima
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - A single channel probability map
+    /// * `threshold` - Pixels above the threshold are foreground
+    /// * `min_distance` - Minimum distance between object seeds (pixels)
+    pub fn from_probability_declumped(
+        image: &ThymeImage,
+        threshold: f32,
+        min_distance: u32,
+    ) -> Result<ThymeMask, ThymeError> {
+        let probability = Self::probability(image)?;
+
+        ThymeMask::new(
+            image.width(),
+            image.height(),
+            1,
+            watershed(
+                image.width(),
+                image.height(),
+                &probability,
+                threshold,
+                min_distance,
+            ),
+        )
+    }
+
+    /// Initialize a labeled mask by thresholding an image at its Otsu threshold
+    ///
+    /// Channels are averaged before thresholding. Pixels above the threshold
+    /// are foreground and are labeled using 8-connected components. Images
+    /// with a single intensity have no threshold and return an empty mask.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - An image with one or more channels
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{ThymeBuffer, ThymeImage, ThymeMask};
+    ///
+    /// let pixels = vec![2u8, 200, 210, 3, 1, 190];
+    /// let image = ThymeImage::U8(ThymeBuffer::new(6, 1, 1, pixels).unwrap());
+    ///
+    /// let mask = ThymeMask::from_otsu(&image).unwrap();
+    /// assert_eq!(mask.as_raw(), &vec![0, 1, 1, 0, 0, 2]);
+    /// ```
+    pub fn from_otsu(image: &ThymeImage) -> Result<ThymeMask, ThymeError> {
+        let channels = image.channels() as usize;
+
+        let intensity: Vec<f32> = image
+            .to_f32()
+            .chunks_exact(channels)
+            .map(|pixel| pixel.iter().sum::<f32>() / channels as f32)
+            .collect();
+
+        let foreground: Vec<u32> = match otsu(&intensity) {
+            Some(threshold) => intensity.iter().map(|&v| (v > threshold) as u32).collect(),
+            None => vec![0; intensity.len()],
+        };
+
+        ThymeMask::new(
+            image.width(),
+            image.height(),
+            1,
+            connected_components(image.width(), image.height(), &foreground),
+        )
+    }
+
+    /// Probabilities of a single channel probability map
+    fn probability(image: &ThymeImage) -> Result<Vec<f32>, ThymeError> {
+        if image.channels() != 1 {
+            return Err(ThymeError::MaskError(
+                "Probability maps must have a single channel.",
+            ));
+        }
+
+        Ok(image.to_f32())
+    }
+
     /// Re-label the mask using connected components and return unique labels
     ///
     /// # Notes
@@ -269,6 +779,363 @@ impl ThymeMask {
         Ok((labels, Polygons::new(contours)?))
     }
 
+    /// Extract polygons from a segmentation mask along with the parent of each object
+    ///
+    /// # Notes
+    ///
+    /// Parents are aligned with the returned labels and specify the label of
+    /// the innermost object enclosing each object, or `None` for top-level
+    /// objects (e.g. nuclei within cells or cells within colonies).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::ThymeMask;
+    ///
+    /// let mut mask = ThymeMask::new(6, 6, 1, vec![
+    ///     1, 1, 1, 1, 1, 1,
+    ///     1, 0, 0, 0, 0, 1,
+    ///     1, 0, 2, 2, 0, 1,
+    ///     1, 0, 2, 2, 0, 1,
+    ///     1, 0, 0, 0, 0, 1,
+    ///     1, 1, 1, 1, 1, 1,
+    /// ]).unwrap();
+    ///
+    /// let (labels, _, parents) = mask.polygons_with_hierarchy().unwrap();
+    ///
+    /// assert_eq!(labels, vec![1, 2]);
+    /// assert_eq!(parents, vec![None, Some(1)]);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn polygons_with_hierarchy(
+        &mut self,
+    ) -> Result<(Vec<u32>, Polygons, Vec<Option<u32>>), ThymeError> {
+        let labels = self.label();
+        let (labels, contours, parents) = find_labeled_contours_with_hierarchy(
+            self.width(),
+            self.height(),
+            &self.buffer,
+            &labels,
+        );
+
+        Ok((labels, Polygons::new(contours)?, parents))
+    }
+
+    /// Count the number of pixels assigned to each label
+    ///
+    /// # Arguments
+    ///
+    /// * `labels` - Labels to count pixels for (e.g. as returned by `polygons`)
+    pub fn areas(&self, labels: &[u32]) -> Vec<u32> {
+        let mut counts: HashMap<u32, u32> = HashMap::with_capacity(labels.len());
+
+        for &label in self.as_raw().iter().filter(|&&x| x != 0) {
+            *counts.entry(label).or_insert(0) += 1;
+        }
+
+        labels
+            .iter()
+            .map(|label| counts.get(label).copied().unwrap_or(0))
+            .collect()
+    }
+
+    /// Compute the centroid (x, y) of the pixels assigned to each label
+    ///
+    /// # Arguments
+    ///
+    /// * `labels` - Labels to compute centroids for (e.g. as returned by `polygons`)
+    ///
+    /// # Notes
+    ///
+    /// Centroids are in pixel index coordinates so a single pixel object at
+    /// column x and row y has a centroid of (x, y). Labels with no pixels
+    /// return `None`.
+    pub fn centroids(&self, labels: &[u32]) -> Vec<Option<[f32; 2]>> {
+        let width = self.width() as usize;
+        let mut sums: HashMap<u32, [f64; 3]> = HashMap::with_capacity(labels.len());
+
+        for (idx, &label) in self.as_raw().iter().enumerate() {
+            if label == 0 {
+                continue;
+            }
+
+            let sum = sums.entry(label).or_insert([0.0; 3]);
+            sum[0] += (idx % width) as f64;
+            sum[1] += (idx / width) as f64;
+            sum[2] += 1.0;
+        }
+
+        labels
+            .iter()
+            .map(|label| {
+                sums.get(label)
+                    .map(|[x, y, n]| [(x / n) as f32, (y / n) as f32])
+            })
+            .collect()
+    }
+
+    /// Assign each object in a secondary mask to the object containing its centroid
+    ///
+    /// # Arguments
+    ///
+    /// * `secondary` - A labeled mask with the same dimensions (e.g. nuclei)
+    /// * `secondary_labels` - Labels of the secondary objects to assign
+    ///
+    /// # Notes
+    ///
+    /// The returned vector contains the label of this mask at the (rounded)
+    /// centroid of each secondary object, or zero if the centroid falls on the
+    /// background. Secondary objects are therefore assigned to at most one
+    /// object even if they overlap several.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::ThymeMask;
+    ///
+    /// let cells = ThymeMask::new(4, 1, 1, vec![1, 1, 2, 2]).unwrap();
+    /// let nuclei = ThymeMask::new(4, 1, 1, vec![0, 5, 0, 0]).unwrap();
+    ///
+    /// assert_eq!(cells.associate(&nuclei, &[5]).unwrap(), vec![1]);
+    /// ```
+    pub fn associate(
+        &self,
+        secondary: &ThymeMask,
+        secondary_labels: &[u32],
+    ) -> Result<Vec<u32>, ThymeError> {
+        if self.width() != secondary.width() || self.height() != secondary.height() {
+            return Err(ThymeError::MaskError(
+                "Primary and secondary masks are not the same size",
+            ));
+        }
+
+        let width = self.width() as usize;
+        let primary: &[u32] = self.as_raw();
+
+        Ok(secondary
+            .centroids(secondary_labels)
+            .into_iter()
+            .map(|centroid| {
+                centroid
+                    .map(|[x, y]| primary[y.round() as usize * width + x.round() as usize])
+                    .unwrap_or(0)
+            })
+            .collect())
+    }
+
+    /// Remove all pixels that are foreground in another mask
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - A mask with the same dimensions (e.g. nuclei subtracted from cells)
+    pub fn subtract(&self, other: &ThymeMask) -> Result<ThymeMask, ThymeError> {
+        if self.width() != other.width() || self.height() != other.height() {
+            return Err(ThymeError::MaskError(
+                "Primary and secondary masks are not the same size",
+            ));
+        }
+
+        let buffer = self
+            .as_raw()
+            .iter()
+            .zip(other.as_raw().iter())
+            .map(|(&a, &b)| if b == 0 { a } else { 0 })
+            .collect();
+
+        ThymeMask::new(self.width(), self.height(), self.channels(), buffer)
+    }
+
+    /// Downsample a binary mask by majority vote
+    ///
+    /// Each pixel of the downsampled mask is foreground if at least half of
+    /// the pixels it covers in the original mask are foreground, so that the
+    /// mask stays aligned with an image resized to the same dimensions.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the downsampled mask
+    /// * `height` - Height of the downsampled mask
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::ThymeMask;
+    ///
+    /// let mask = ThymeMask::new(4, 2, 1, vec![1, 1, 0, 0, 1, 0, 0, 1]).unwrap();
+    /// let downsampled = mask.downsample(2, 1).unwrap();
+    /// assert_eq!(downsampled.as_raw(), &vec![1, 0]);
+    /// ```
+    pub fn downsample(&self, width: u32, height: u32) -> Result<ThymeMask, ThymeError> {
+        if width == 0 || height == 0 || width > self.width() || height > self.height() {
+            return Err(ThymeError::MaskError(
+                "Downsampled mask must be smaller than the mask",
+            ));
+        }
+
+        let (w, h) = (self.width() as usize, self.height() as usize);
+        let (nw, nh) = (width as usize, height as usize);
+        let c = self.channels() as usize;
+        let buffer: &[u32] = self.as_raw();
+
+        let mut downsampled = Vec::with_capacity(nw * nh);
+
+        for j in 0..nh {
+            let (y0, y1) = (j * h / nh, (j + 1) * h / nh);
+
+            for i in 0..nw {
+                let (x0, x1) = (i * w / nw, (i + 1) * w / nw);
+
+                let foreground = (y0..y1)
+                    .flat_map(|y| (x0..x1).map(move |x| (y * w + x) * c))
+                    .filter(|&idx| buffer[idx] != 0)
+                    .count();
+
+                downsampled.push(u32::from(2 * foreground >= (y1 - y0) * (x1 - x0)));
+            }
+        }
+
+        ThymeMask::new(width, height, 1, downsampled)
+    }
+
+    /// Resize a mask by nearest-neighbor sampling
+    ///
+    /// Each pixel takes the label of the pixel whose center is nearest to its
+    /// own center, so labels are never blended and the mask stays aligned
+    /// with an image resized to the same dimensions.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the resized mask
+    /// * `height` - Height of the resized mask
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::ThymeMask;
+    ///
+    /// let mask = ThymeMask::new(4, 2, 1, vec![1, 1, 2, 2, 3, 3, 4, 4]).unwrap();
+    /// let resized = mask.resize_nearest(2, 1).unwrap();
+    /// assert_eq!(resized.as_raw(), &vec![3, 4]);
+    /// ```
+    pub fn resize_nearest(&self, width: u32, height: u32) -> Result<ThymeMask, ThymeError> {
+        if width == 0 || height == 0 {
+            return Err(ThymeError::MaskError(
+                "Resized mask must have a non-zero width and height",
+            ));
+        }
+
+        let (w, h) = (self.width() as usize, self.height() as usize);
+        let (nw, nh) = (width as usize, height as usize);
+        let c = self.channels() as usize;
+        let buffer: &[u32] = self.as_raw();
+
+        // Source index of the pixel center nearest to each resized pixel center
+        let nearest =
+            |i: usize, n: usize, size: usize| ((2 * i + 1) * size / (2 * n)).min(size - 1);
+
+        let columns: Vec<usize> = (0..nw).map(|i| nearest(i, nw, w)).collect();
+        let mut resized = Vec::with_capacity(nw * nh * c);
+
+        for j in 0..nh {
+            let y = nearest(j, nh, h);
+
+            for &x in columns.iter() {
+                let idx = (y * w + x) * c;
+                resized.extend_from_slice(&buffer[idx..idx + c]);
+            }
+        }
+
+        ThymeMask::new(width, height, self.channels(), resized)
+    }
+
+    /// Deterministic color of a label
+    ///
+    /// Colors are derived from a hash of the label and seed, so a label keeps
+    /// its color across masks (e.g. tracked objects in the frames of a movie).
+    /// Every channel is at least 48 so that labels stand out from the black
+    /// background.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - Label of an object
+    /// * `seed` - Seed of the hash (e.g. to reshuffle colors of adjacent labels)
+    pub fn label_color(label: u32, seed: u64) -> [u8; 3] {
+        // splitmix64 finalizer
+        let mut z = (label as u64 ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        let channel = |shift: u32| 48 + ((z >> shift) & 0xffff) as u32 % 208;
+        [channel(0) as u8, channel(16) as u8, channel(32) as u8]
+    }
+
+    /// Color each label of a mask as an RGB image
+    ///
+    /// Labels in the lookup table take its color and every other label takes
+    /// the color of `label_color`. Background pixels (label 0) are black.
+    ///
+    /// # Arguments
+    ///
+    /// * `lut` - Color of labels (r, g, b) overriding the hashed colors
+    /// * `seed` - Seed of the hashed colors
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use thyme_core::im::{ThymeImage, ThymeMask};
+    ///
+    /// let mask = ThymeMask::new(3, 1, 1, vec![0, 1, 2]).unwrap();
+    /// let lut = BTreeMap::from([(2, [255, 0, 0])]);
+    ///
+    /// let ThymeImage::U8(colored) = mask.colorize(&lut, 0).unwrap() else {
+    ///     unreachable!()
+    /// };
+    ///
+    /// assert_eq!(colored.channels(), 3);
+    /// assert_eq!(colored.as_raw()[..3], [0, 0, 0]);
+    /// assert_eq!(colored.as_raw()[3..6], ThymeMask::label_color(1, 0));
+    /// assert_eq!(colored.as_raw()[6..], [255, 0, 0]);
+    /// ```
+    pub fn colorize(
+        &self,
+        lut: &BTreeMap<u32, [u8; 3]>,
+        seed: u64,
+    ) -> Result<ThymeImage, ThymeError> {
+        if self.channels() != 1 {
+            return Err(ThymeError::MaskError(
+                "Only single channel masks can be colorized",
+            ));
+        }
+
+        let mut colors: HashMap<u32, [u8; 3]> = HashMap::new();
+        let mut buffer = Vec::with_capacity(self.len() * 3);
+
+        for &label in self.as_raw().iter() {
+            if label == 0 {
+                buffer.extend_from_slice(&[0, 0, 0]);
+                continue;
+            }
+
+            let color = colors.entry(label).or_insert_with(|| {
+                lut.get(&label)
+                    .copied()
+                    .unwrap_or_else(|| ThymeMask::label_color(label, seed))
+            });
+
+            buffer.extend_from_slice(color);
+        }
+
+        Ok(ThymeImage::U8(ThymeBuffer::new(
+            self.width(),
+            self.height(),
+            3,
+            buffer,
+        )?))
+    }
+
     /// Crops image while only including pixels with a specified label
     ///
     /// # Arguments
@@ -286,7 +1153,7 @@ impl ThymeMask {
         h: u32,
         label: u32,
     ) -> Result<ThymeMask, ThymeError> {
-        if x + w > self.width() || y + h > self.height() {
+        if x as u64 + w as u64 > self.width() as u64 || y as u64 + h as u64 > self.height() as u64 {
             return Err(ThymeError::MaskError("Cropping coordinates out of bounds"));
         }
 
@@ -294,7 +1161,7 @@ impl ThymeMask {
         let orig_w = self.width() as usize;
         let orig_buffer: &[u32] = self.buffer.as_ref();
 
-        let mut new_buffer = Vec::with_capacity((w * h * self.channels()) as usize);
+        let mut new_buffer = Vec::with_capacity(w as usize * h as usize * c);
 
         for row in y..y + h {
             let start = ((row as usize) * orig_w + (x as usize)) * c;
@@ -316,8 +1183,75 @@ impl ThymeMask {
 /// A type for mask object buffer
 pub type ThymeMaskView<'a> = ThymeViewBuffer<'a, u32, Vec<u32>>;
 
+impl ThymeMaskView<'_> {
+    /// Majority class of the pixels assigned to a label in an aligned class mask
+    ///
+    /// # Arguments
+    ///
+    /// * `classes` - Class mask cropped to the same region (e.g. semantic class ids)
+    /// * `label` - Label of the object in this mask
+    ///
+    /// # Notes
+    ///
+    /// Returns the most frequent class id over the pixels of the object along
+    /// with the fraction of object pixels assigned to that class. Ties are won
+    /// by the lowest class id and zero-valued class pixels are counted as class
+    /// zero. Labels without any pixels in the crop return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::ThymeMask;
+    ///
+    /// let instances = ThymeMask::new(4, 1, 1, vec![1, 1, 1, 2]).unwrap();
+    /// let classes = ThymeMask::new(4, 1, 1, vec![3, 3, 7, 7]).unwrap();
+    ///
+    /// let instances = instances.crop_view(0, 0, 4, 1);
+    /// let classes = classes.crop_view(0, 0, 4, 1);
+    ///
+    /// assert_eq!(instances.majority_class(&classes, 1).unwrap(), Some((3, 2.0 / 3.0)));
+    /// assert_eq!(instances.majority_class(&classes, 5).unwrap(), None);
+    /// ```
+    pub fn majority_class(
+        &self,
+        classes: &ThymeMaskView,
+        label: u32,
+    ) -> Result<Option<(u32, f32)>, ThymeError> {
+        if self.width() != classes.width() || self.height() != classes.height() {
+            return Err(ThymeError::MaskError(
+                "Mask and class mask crops are not the same size",
+            ));
+        }
+
+        let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+
+        for (&instance, &class) in self.iter().zip(classes.iter()) {
+            if instance == label {
+                *counts.entry(class).or_insert(0) += 1;
+            }
+        }
+
+        let total: usize = counts.values().sum();
+
+        // Classes are visited in ascending order so only a larger count replaces the majority
+        let majority =
+            counts
+                .into_iter()
+                .fold(
+                    None,
+                    |majority: Option<(u32, usize)>, (class, n)| match majority {
+                        Some((_, m)) if m >= n => majority,
+                        _ => Some((class, n)),
+                    },
+                );
+
+        Ok(majority.map(|(class, n)| (class, n as f32 / total as f32)))
+    }
+}
+
 // I/O METHODS
 
+#[cfg(feature = "io")]
 impl<'a> ThymeMaskView<'a> {
     /// Save an object
     ///
@@ -355,24 +1289,22 @@ impl<'a> ThymeMaskView<'a> {
                 }
 
                 writer.finish().map_err(|_| ThymeError::ImageWriteError)?;
-                std::fs::write(&path, buffer).map_err(|_| ThymeError::ImageWriteError)?;
+                write_bytes_atomic(&path, buffer)?;
 
                 return Ok(());
             }
 
             if constant::IMAGE_DYNAMIC_FORMATS.iter().any(|e| e == &ext) {
-                ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(
+                let buffer = ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(
                     self.width() as u32,
                     self.height() as u32,
                     self.iter()
                         .map(|p| if p == label { 255u8 } else { 0u8 })
                         .collect(),
                 )
-                .unwrap()
-                .save(path)
-                .map_err(|_| ThymeError::ImageWriteError)?;
+                .unwrap();
 
-                return Ok(());
+                return write_dynamic_atomic(&DynamicImage::from(buffer), path);
             }
         }
 
@@ -383,18 +1315,23 @@ impl<'a> ThymeMaskView<'a> {
 // <<< I/O METHODS
 
 /// Type of masking style to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MaskingStyle {
     Foreground,
     Background,
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "io"))]
 mod test {
 
     use super::*;
+    use crate::im::ZStackMode;
 
     const TEST_MASK: &str = "../data/tests/test_mask";
     const TEST_BLOB: &str = "../data/tests/test_mask_binary_blobs.png";
+    const TEST_U16_PNG: &str = "../data/tests/test_mask_integer_300_u16.png";
+    const TEST_U32_TIFF: &str = "../data/tests/test_mask_integer_300_u32.tif";
+    const TEST_PALETTE_PNG: &str = "../data/tests/test_mask_integer_palette.png";
 
     #[test]
     fn test_mask_open() {
@@ -420,6 +1357,33 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_mask_open_cellpose_seg() {
+        let seg = format!("{}_cellpose_seg.npy", TEST_MASK);
+
+        let (mut mask, summary) =
+            ThymeMask::open_format_with_summary(&seg, MaskFormat::CellposeSeg).unwrap();
+        let (mut plain, plain_summary) =
+            ThymeMask::open_with_summary(format!("{}_cellpose.npy", TEST_MASK)).unwrap();
+
+        assert_eq!((mask.width(), mask.height()), (64, 48));
+        assert_eq!(mask.as_raw(), plain.as_raw());
+        assert_eq!(summary.labels, plain_summary.labels);
+        assert_eq!(summary.dtype_max, u16::MAX as u32);
+        assert_eq!(mask.label().len(), plain.label().len());
+        assert_eq!(summary.labels, 5);
+
+        // Seg files are not read as plain arrays
+        let error = ThymeMask::open(&seg).err().unwrap().to_string();
+        assert!(error.contains("cellpose-seg"), "{}", error);
+
+        let slices =
+            ThymeMask::open_zstack_format(&seg, ZStackMode::PerSlice, MaskFormat::CellposeSeg)
+                .unwrap();
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].0.as_raw(), plain.as_raw());
+    }
+
     #[test]
     fn test_mask_save() {
         const TEST_DEFAULT: &str = "TEST_SAVE_DEFAULT_MASK.png";
@@ -485,6 +1449,75 @@ mod test {
         assert_eq!(labels[4], 5);
     }
 
+    #[test]
+    fn test_mask_areas() {
+        let data: Vec<u32> = vec![0, 1, 1, 2, 0, 1, 2, 2, 2];
+        let mask = ThymeMask::new(3, 3, 1, data).unwrap();
+
+        assert_eq!(mask.areas(&[1, 2]), vec![3, 4]);
+        assert_eq!(mask.areas(&[2, 3]), vec![4, 0]);
+    }
+
+    /// Two 8x8 cells side by side with nested 2x2 nuclei and a stray nucleus
+    fn nested_squares() -> (ThymeMask, ThymeMask) {
+        let (width, height) = (20usize, 10usize);
+        let mut cells = vec![0u32; width * height];
+        let mut nuclei = vec![0u32; width * height];
+
+        for y in 1..9 {
+            for x in 1..9 {
+                cells[y * width + x] = 1;
+                cells[y * width + x + 10] = 2;
+            }
+        }
+
+        for y in 4..6 {
+            for x in 2..4 {
+                nuclei[y * width + x] = 10;
+                nuclei[y * width + x + 3] = 11;
+                nuclei[y * width + x + 12] = 12;
+            }
+        }
+
+        // Nucleus centered on the background between the two cells
+        nuclei[9] = 13;
+
+        (
+            ThymeMask::new(width as u32, height as u32, 1, cells).unwrap(),
+            ThymeMask::new(width as u32, height as u32, 1, nuclei).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_mask_centroids() {
+        let (cells, _) = nested_squares();
+
+        let centroids = cells.centroids(&[1, 2, 3]);
+        assert_eq!(centroids, vec![Some([4.5, 4.5]), Some([14.5, 4.5]), None]);
+    }
+
+    #[test]
+    fn test_mask_associate() {
+        let (cells, nuclei) = nested_squares();
+
+        let associations = cells.associate(&nuclei, &[10, 11, 12, 13]).unwrap();
+        assert_eq!(associations, vec![1, 1, 2, 0]);
+
+        let small = ThymeMask::new(2, 2, 1, vec![0; 4]).unwrap();
+        assert!(cells.associate(&small, &[1]).is_err());
+    }
+
+    #[test]
+    fn test_mask_subtract() {
+        let (cells, nuclei) = nested_squares();
+
+        let cytoplasm = cells.subtract(&nuclei).unwrap();
+
+        assert_eq!(cytoplasm.areas(&[1, 2]), vec![64 - 8, 64 - 4]);
+        assert_eq!(cytoplasm.as_raw()[4 * 20 + 2], 0);
+        assert_eq!(cytoplasm.as_raw()[4 * 20 + 1], 1);
+    }
+
     #[test]
     fn test_mask_crop_binary() {
         let width = 2;
@@ -497,4 +1530,352 @@ mod test {
 
         assert_eq!(binary.as_raw(), &[0, 1, 0, 0]);
     }
+
+    #[test]
+    fn test_mask_downsample() {
+        #[rustfmt::skip]
+        let data: Vec<u32> = vec![
+            1, 1, 0, 0, 0, 1,
+            1, 0, 0, 0, 1, 1,
+            0, 0, 1, 1, 0, 0,
+            0, 0, 1, 1, 0, 0,
+        ];
+
+        let mask = ThymeMask::new(6, 4, 1, data).unwrap();
+
+        let downsampled = mask.downsample(3, 2).unwrap();
+        assert_eq!(downsampled.as_raw(), &[1, 0, 1, 0, 1, 0]);
+
+        let downsampled = mask.downsample(6, 4).unwrap();
+        assert_eq!(downsampled.as_raw(), mask.as_raw());
+
+        assert!(mask.downsample(0, 2).is_err());
+        assert!(mask.downsample(7, 2).is_err());
+    }
+
+    #[test]
+    fn test_mask_resize_nearest() {
+        #[rustfmt::skip]
+        let data: Vec<u32> = vec![
+            1, 1, 0, 0, 2, 2,
+            1, 1, 0, 0, 2, 2,
+            0, 0, 3, 3, 0, 0,
+            0, 0, 3, 3, 0, 0,
+        ];
+
+        let mask = ThymeMask::new(6, 4, 1, data).unwrap();
+
+        // Labels are sampled without blending
+        let resized = mask.resize_nearest(3, 2).unwrap();
+        assert_eq!(resized.as_raw(), &[1, 0, 2, 0, 3, 0]);
+
+        let upsampled = mask.resize_nearest(12, 8).unwrap();
+        assert_eq!(
+            upsampled.resize_nearest(6, 4).unwrap().as_raw(),
+            mask.as_raw()
+        );
+
+        assert_eq!(mask.resize_nearest(6, 4).unwrap().as_raw(), mask.as_raw());
+        assert!(mask.resize_nearest(0, 2).is_err());
+    }
+
+    #[test]
+    fn test_mask_colorize() {
+        let colors = |mask: &ThymeMask, lut: &BTreeMap<u32, [u8; 3]>, seed: u64| {
+            let ThymeImage::U8(colored) = mask.colorize(lut, seed).unwrap() else {
+                panic!("Colorized mask is not u8");
+            };
+            assert_eq!(colored.channels(), 3);
+            colored
+                .as_raw()
+                .chunks_exact(3)
+                .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                .collect::<Vec<[u8; 3]>>()
+        };
+
+        let lut = BTreeMap::new();
+        let first = colors(&ThymeMask::new(4, 1, 1, vec![0, 5, 7, 5]).unwrap(), &lut, 3);
+        let second = colors(&ThymeMask::new(2, 2, 1, vec![7, 9, 0, 5]).unwrap(), &lut, 3);
+
+        // Labels keep their color across masks and background stays black
+        assert_eq!(first[0], [0, 0, 0]);
+        assert_eq!(second[2], [0, 0, 0]);
+        assert_eq!(first[1], first[3]);
+        assert_eq!(first[1], second[3]);
+        assert_eq!(first[2], second[0]);
+        assert_ne!(first[1], first[2]);
+        assert!(first[1..].iter().flatten().all(|&v| v >= 48));
+
+        // Seeds reshuffle colors and lookup tables override them
+        assert_ne!(ThymeMask::label_color(5, 3), ThymeMask::label_color(5, 4));
+
+        let lut = BTreeMap::from([(5, [1, 2, 3])]);
+        let first = colors(&ThymeMask::new(4, 1, 1, vec![0, 5, 7, 5]).unwrap(), &lut, 3);
+        assert_eq!(first[1], [1, 2, 3]);
+        assert_eq!(first[2], ThymeMask::label_color(7, 3));
+
+        assert!(
+            ThymeMask::new(1, 1, 2, vec![1, 1])
+                .unwrap()
+                .colorize(&lut, 0)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_mask_open_u16_png() {
+        let (mut mask, summary) = ThymeMask::open_with_summary(TEST_U16_PNG).unwrap();
+
+        assert_eq!(mask.width(), 100);
+        assert_eq!(mask.height(), 75);
+        assert_eq!(summary.labels, 300);
+        assert_eq!(summary.max_label, 300);
+        assert_eq!(summary.dtype_max, u16::MAX as u32);
+        assert!(summary.check_bit_depth().is_ok());
+
+        let labels = mask.label();
+        assert_eq!(labels, (1..=300).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_mask_open_palette_png() {
+        let (palette, summary) = ThymeMask::open_with_summary(TEST_PALETTE_PNG).unwrap();
+        let (reference, expected) =
+            ThymeMask::open_with_summary(format!("{}_integer_u16.npy", TEST_MASK)).unwrap();
+
+        // Palette colors differ from the indices so labels are not read as colors
+        assert_eq!(palette.as_raw(), reference.as_raw());
+        assert_eq!(summary.labels, expected.labels);
+        assert_eq!(summary.max_label, expected.max_label);
+        assert_eq!(summary.dtype_max, u8::MAX as u32);
+        assert!(summary.palette);
+        assert!(!expected.palette);
+
+        // Grayscale PNGs are still decoded as DynamicImage
+        let (_, summary) = ThymeMask::open_with_summary(TEST_U16_PNG).unwrap();
+        assert!(!summary.palette);
+
+        // Indices with fewer than 8 bits are unpacked from each byte
+        let path = std::env::temp_dir().join("thyme_test_mask_open_palette_png.png");
+        let (width, height) = (5u32, 2u32);
+        let labels: Vec<u32> = (0..width * height).map(|i| i % 3 + i / 5).collect();
+
+        let rows: Vec<u8> = labels
+            .chunks(width as usize)
+            .flat_map(|row| {
+                row.chunks(4).map(|pixels| {
+                    pixels.iter().enumerate().fold(0u8, |byte, (k, &label)| {
+                        byte | ((label as u8) << (6 - 2 * k))
+                    })
+                })
+            })
+            .collect();
+
+        let mut encoder = png::Encoder::new(
+            std::io::BufWriter::new(File::create(&path).unwrap()),
+            width,
+            height,
+        );
+        encoder.set_color(PngColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Two);
+        encoder.set_palette(vec![0u8, 0, 0, 9, 9, 9, 50, 50, 50, 200, 200, 200]);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&rows)
+            .unwrap();
+
+        let (mask, summary) = ThymeMask::open_with_summary(&path).unwrap();
+        assert_eq!(mask.as_raw(), labels.as_slice());
+        assert_eq!(summary.dtype_max, 3);
+        assert!(summary.palette);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mask_open_u32_tiff() {
+        let (mut mask, summary) = ThymeMask::open_with_summary(TEST_U32_TIFF).unwrap();
+
+        assert_eq!(mask.width(), 100);
+        assert_eq!(mask.height(), 75);
+        assert_eq!(summary.labels, 300);
+        assert_eq!(summary.max_label, 100_300);
+        assert_eq!(summary.dtype_max, u32::MAX);
+        assert!(summary.check_bit_depth().is_ok());
+
+        // Objects are 3x3 squares centered in 5x5 cells on a 20 x 15 grid
+        assert_eq!(mask.as_raw()[0], 0);
+        assert_eq!(mask.as_raw()[100 + 1], 100_001);
+        assert_eq!(mask.as_raw()[(5 * 14 + 3) * 100 + 5 * 19 + 3], 100_300);
+
+        let labels = mask.label();
+        assert_eq!(labels, (100_001..=100_300).collect::<Vec<u32>>());
+
+        let png = ThymeMask::open(TEST_U16_PNG).unwrap();
+        for (a, b) in mask.as_raw().iter().zip(png.as_raw()) {
+            assert_eq!(*a, if *b == 0 { 0 } else { b + 100_000 });
+        }
+    }
+
+    #[test]
+    fn test_mask_bit_depth_truncation() {
+        let mask = ThymeMask::open(TEST_U16_PNG).unwrap();
+
+        // Re-encoding the 300 labels as 8-bit merges labels modulo 256
+        let truncated = image::GrayImage::from_raw(
+            mask.width(),
+            mask.height(),
+            mask.as_raw().iter().map(|&x| x as u8).collect(),
+        )
+        .unwrap();
+
+        let truncated = ThymeMask::new_from_dynamic(DynamicImage::ImageLuma8(truncated)).unwrap();
+        let summary = MaskSummary::new(truncated.as_raw(), u8::MAX as u32);
+
+        assert_eq!(summary.labels, 255);
+        assert_eq!(summary.max_label, 255);
+        assert!(summary.is_possibly_truncated());
+        assert!(matches!(
+            summary.check_bit_depth(),
+            Err(ThymeError::MaskDepthError(_))
+        ));
+
+        // A mask reaching the data type maximum is flagged even with few labels
+        let summary = MaskSummary::new(&[0, 1, 2, 65535], u16::MAX as u32);
+        assert!(summary.is_possibly_truncated());
+
+        let summary = MaskSummary::new(&[0, 1, 2, 3], u8::MAX as u32);
+        assert!(!summary.is_possibly_truncated());
+    }
+
+    #[test]
+    fn test_mask_binary_not_truncated() {
+        for path in [
+            "../data/tests/test_mask_binary.png",
+            "../data/tests/test_mask_binary_255.npy",
+            "../data/tests/test_mask_binary_255_u16.npy",
+        ] {
+            let (_, summary) = ThymeMask::open_with_summary(path).unwrap();
+            assert_eq!(summary.labels, 1, "{}", path);
+            assert!(summary.check_bit_depth().is_ok(), "{}", path);
+        }
+    }
+
+    fn gaussian_bump(width: u32, height: u32, sigma: f32, peak: f32) -> ThymeImage {
+        let (cx, cy) = (width as f32 / 2., height as f32 / 2.);
+
+        let probability: Vec<f32> = (0..width * height)
+            .map(|idx| {
+                let x = (idx % width) as f32 - cx;
+                let y = (idx / width) as f32 - cy;
+                peak * (-(x * x + y * y) / (2. * sigma * sigma)).exp()
+            })
+            .collect();
+
+        ThymeImage::F32(ThymeBuffer::new(width, height, 1, probability).unwrap())
+    }
+
+    #[test]
+    fn test_from_probability() {
+        let image = gaussian_bump(32, 24, 4., 0.95);
+        assert!(image.is_probability_map());
+
+        let mut mask = ThymeMask::from_probability(&image, 0.5).unwrap();
+        assert_eq!(mask.label().len(), 1);
+
+        let (_, polygons) = mask.polygons().unwrap();
+        assert_eq!(polygons.len(), 1);
+
+        let mut mask = ThymeMask::from_probability(&image, 0.99).unwrap();
+        assert!(mask.label().is_empty());
+
+        let mut mask = ThymeMask::from_probability_declumped(&image, 0.5, 5).unwrap();
+        assert_eq!(mask.label().len(), 1);
+
+        let rgb = ThymeImage::F32(ThymeBuffer::new(1, 1, 3, vec![0.5f32; 3]).unwrap());
+        assert!(ThymeMask::from_probability(&rgb, 0.5).is_err());
+        assert!(!rgb.is_probability_map());
+    }
+
+    #[test]
+    fn test_from_otsu() {
+        // A bright two channel disk on a dim background
+        let (width, height) = (24u32, 20u32);
+
+        let pixels: Vec<u16> = (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = ((i % width) as f32 - 12.0, (i / width) as f32 - 10.0);
+                match x * x + y * y < 25.0 {
+                    true => [900, 700],
+                    false => [40 + (i % 3) as u16, 20],
+                }
+            })
+            .collect();
+
+        let image = ThymeImage::U16(ThymeBuffer::new(width, height, 2, pixels).unwrap());
+
+        let mut mask = ThymeMask::from_otsu(&image).unwrap();
+        assert_eq!(mask.label(), vec![1]);
+        assert_eq!(mask.areas(&[1]), vec![69]);
+
+        let constant = ThymeImage::U8(ThymeBuffer::new(4, 4, 1, vec![7u8; 16]).unwrap());
+        assert!(ThymeMask::from_otsu(&constant).unwrap().label().is_empty());
+    }
+
+    #[test]
+    fn test_majority_class() {
+        // Two 4x4 objects on a 8x4 mask overlapping three class regions
+        let width = 8;
+        let height = 4;
+
+        let instances: Vec<u32> = (0..width * height)
+            .map(|idx| if idx % width < 4 { 1 } else { 2 })
+            .collect();
+
+        // Object 1 is 3/4 class 5 and 1/4 class 2, object 2 is half class 0 and half class 9
+        let classes: Vec<u32> = (0..width * height)
+            .map(|idx| match (idx % width, idx / width) {
+                (0..=2, _) => 5,
+                (3, _) => 2,
+                (_, 0..=1) => 9,
+                _ => 0,
+            })
+            .collect();
+
+        let instances = ThymeMask::new(width as u32, height as u32, 1, instances).unwrap();
+        let classes = ThymeMask::new(width as u32, height as u32, 1, classes).unwrap();
+
+        let instances = instances.crop_view(0, 0, 8, 4);
+        let classes_view = classes.crop_view(0, 0, 8, 4);
+
+        assert_eq!(
+            instances.majority_class(&classes_view, 1).unwrap(),
+            Some((5, 0.75))
+        );
+
+        // Ties are won by the lowest class id
+        assert_eq!(
+            instances.majority_class(&classes_view, 2).unwrap(),
+            Some((0, 0.5))
+        );
+
+        assert_eq!(instances.majority_class(&classes_view, 3).unwrap(), None);
+
+        // Crops of the object only count pixels within the crop
+        let crop = classes.crop_view(3, 0, 2, 4);
+        let instances_crop = ThymeMask::new(8, 4, 1, vec![1; 32]).unwrap();
+        let instances_crop = instances_crop.crop_view(3, 0, 2, 4);
+
+        assert_eq!(
+            instances_crop.majority_class(&crop, 1).unwrap(),
+            Some((2, 0.5))
+        );
+
+        assert!(
+            instances
+                .majority_class(&classes.crop_view(0, 0, 4, 4), 1)
+                .is_err()
+        );
+    }
 }
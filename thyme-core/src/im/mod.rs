@@ -1,19 +1,39 @@
+mod background;
 mod boxes;
 mod buffer;
+mod channels;
+mod compact;
+mod dtype;
 mod image;
 mod mask;
 mod polygons;
+mod pyramid;
+mod summary;
 mod view;
+mod zstack;
 
-pub use buffer::ThymeBuffer;
+pub use buffer::{ThymeBuffer, buffer_len};
+pub use channels::{ChannelMap, ChannelPairs, FeatureChannels};
+pub use dtype::{ConversionPolicy, Dtype};
 pub use image::ThymeImage;
 
+pub use view::Regions;
 pub use view::ThymeView;
 pub use view::ThymeViewBuffer;
+pub use view::ViewOps;
 
-pub use boxes::BoundingBoxes;
-pub use polygons::Polygons;
+pub use background::BackgroundStyle;
+pub use boxes::{BoundingBoxes, BoxColumns};
+pub use polygons::{ContourSmoothing, PolygonValidation, Polygons, SegmentCoords};
 
+pub use mask::MaskFormat;
+pub use mask::MaskSummary;
 pub use mask::MaskingStyle;
 pub use mask::ThymeMask;
 pub use mask::ThymeMaskView;
+
+pub use compact::ThymeMaskU8;
+
+pub use pyramid::PyramidLevel;
+pub use summary::ImageSummary;
+pub use zstack::ZStackMode;
@@ -2,16 +2,20 @@
 // Licensed under the MIT License
 
 use std::fs::File;
-use std::io::{BufWriter, Read};
+use std::io::Read;
 use std::path::Path;
 
 use serde::Serialize;
 use serde_json::Value;
 
 use crate::constant::POLYGON_JSON_VALID_KEYS;
-use crate::cv::points::{dedup_points, order_points, resample_points};
+use crate::cv::points::{
+    dedup_points, order_points, resample_points, self_intersections, signed_area, smooth_chaikin,
+    smooth_gaussian,
+};
 use crate::error::ThymeError;
 use crate::im::boxes::BoundingBoxes;
+use crate::io::write_atomic;
 use crate::mp::form;
 
 /// A polygon container for storing object outlines
@@ -47,11 +51,122 @@ pub struct Polygons {
     data: Vec<Vec<[f32; 2]>>,
     deduped: bool,
     ordered: bool,
+    validation: PolygonValidation,
+}
+
+/// A summary of the polygons normalized, repaired, or flagged during validation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolygonValidation {
+    /// Number of clockwise polygons reversed to counterclockwise (excluding repaired)
+    pub reversed: usize,
+    /// Number of self-intersecting polygons repaired by re-ordering their points
+    pub repaired: usize,
+    /// Indices of self-intersecting polygons that could not be repaired
+    pub degenerate: Vec<usize>,
+}
+
+/// Coordinate system of the vertices of polygons read from file
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::SegmentCoords;
+///
+/// assert_eq!(SegmentCoords::parse("normalized").unwrap(), SegmentCoords::Normalized);
+/// assert_eq!(SegmentCoords::parse("pixels").unwrap(), SegmentCoords::default());
+/// assert!(SegmentCoords::parse("percent").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SegmentCoords {
+    /// Vertices are in image pixels
+    #[default]
+    Pixels,
+    /// Vertices are fractions of the image width and height in [0, 1]
+    Normalized,
+}
+
+impl SegmentCoords {
+    /// Parse a coordinate system from a string (pixels, normalized)
+    ///
+    /// # Arguments
+    ///
+    /// * `coords` - String representation of the coordinate system
+    pub fn parse(coords: &str) -> Result<SegmentCoords, ThymeError> {
+        match coords {
+            "pixels" => Ok(SegmentCoords::Pixels),
+            "normalized" => Ok(SegmentCoords::Normalized),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid segment coordinates '{}'. Must be one of: pixels, normalized",
+                coords
+            ))),
+        }
+    }
+}
+
+/// Smoothing of polygon outlines traced from pixelated mask boundaries
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::ContourSmoothing;
+///
+/// assert_eq!(ContourSmoothing::parse("gaussian:1.5").unwrap(), ContourSmoothing::Gaussian(1.5));
+/// assert_eq!(ContourSmoothing::parse("chaikin:2").unwrap(), ContourSmoothing::Chaikin(2));
+/// assert!(ContourSmoothing::parse("gaussian:0").is_err());
+/// assert!(ContourSmoothing::parse("median:3").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContourSmoothing {
+    /// Gaussian convolution of the outline coordinates with this sigma (points)
+    Gaussian(f32),
+    /// This many iterations of corner-cutting
+    Chaikin(usize),
+}
+
+impl ContourSmoothing {
+    /// Maximum number of corner-cutting iterations (each doubles the points)
+    pub const MAX_CHAIKIN_ITERATIONS: usize = 8;
+
+    /// Parse a smoothing method from a string (gaussian:<sigma>, chaikin:<iterations>)
+    ///
+    /// # Arguments
+    ///
+    /// * `smoothing` - String representation of the smoothing method
+    pub fn parse(smoothing: &str) -> Result<ContourSmoothing, ThymeError> {
+        let invalid = || {
+            ThymeError::OtherError(format!(
+                "Invalid contour smoothing '{}'. Must be gaussian:<sigma> with a positive sigma or chaikin:<iterations> with 1 to {} iterations",
+                smoothing,
+                Self::MAX_CHAIKIN_ITERATIONS
+            ))
+        };
+
+        let (method, value) = smoothing.split_once(':').ok_or_else(invalid)?;
+
+        match method.trim() {
+            "gaussian" => match value.trim().parse::<f32>() {
+                Ok(sigma) if sigma.is_finite() && sigma > 0.0 => {
+                    Ok(ContourSmoothing::Gaussian(sigma))
+                }
+                _ => Err(invalid()),
+            },
+            "chaikin" => match value.trim().parse::<usize>() {
+                Ok(iterations) if (1..=Self::MAX_CHAIKIN_ITERATIONS).contains(&iterations) => {
+                    Ok(ContourSmoothing::Chaikin(iterations))
+                }
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
 }
 
 impl Polygons {
     /// Initialize a new polygons container
     ///
+    /// Polygons must have at least 3 points and only finite vertices, so that
+    /// corrupt polygon files return an error before any descriptor is computed.
+    ///
     /// # Arguments
     ///
     /// * `data` - Polygons in (N, 2, K) format
@@ -80,12 +195,43 @@ impl Polygons {
             return Err(ThymeError::PolygonsSizeError);
         }
 
+        if data.iter().flatten().flatten().any(|v| !v.is_finite()) {
+            return Err(ThymeError::PolygonsValueError);
+        }
+
         Ok(Self {
             data,
             deduped: false,
             ordered: false,
+            validation: PolygonValidation::default(),
         })
     }
+
+    /// Initialize a new polygons container from an iterator of polygons
+    ///
+    /// # Arguments
+    ///
+    /// * `polygons` - Iterator of polygon points (e.g. an `io::PolygonStream`)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::Polygons;
+    ///
+    /// let polygons = Polygons::try_from_iter((0..3).map(|i| {
+    ///     let i = i as f32;
+    ///     Ok(vec![[i, 0.], [i + 1., 0.], [i + 1., 1.]])
+    /// }))
+    /// .unwrap();
+    ///
+    /// assert_eq!(polygons.len(), 3);
+    /// ```
+    pub fn try_from_iter<I>(polygons: I) -> Result<Self, ThymeError>
+    where
+        I: IntoIterator<Item = Result<Vec<[f32; 2]>, ThymeError>>,
+    {
+        Self::new(polygons.into_iter().collect::<Result<_, _>>()?)
+    }
 }
 
 // >>> I/O METHODS
@@ -93,6 +239,9 @@ impl Polygons {
 impl Polygons {
     /// Open polygons from the provided path
     ///
+    /// Opened polygons are validated and repaired (see `validate_and_repair`)
+    /// and the validation summary is available from `validation`.
+    ///
     /// # Arguments
     ///
     /// * `path` - A path to polygons with a valid extension
@@ -112,7 +261,9 @@ impl Polygons {
 
         if let Some(ext) = extension {
             if ext == "json" {
-                return read_polygons_json(path);
+                let mut polygons = read_polygons_json(path)?;
+                polygons.validate_and_repair();
+                return Ok(polygons);
             }
         }
 
@@ -147,6 +298,57 @@ impl Polygons {
 
         Err(ThymeError::PolygonsWriteError)
     }
+
+    /// Save polygons at the provided path along with the parent of each polygon
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to save polygons
+    /// * `parents` - Index of the enclosing polygon for each polygon (if any)
+    ///
+    /// # Notes
+    ///
+    /// Parents are written as an additional `parents` field that is ignored
+    /// when polygons are read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use thyme_core::im::ThymeMask;
+    ///
+    /// let mut mask = ThymeMask::open("mask.png").unwrap();
+    /// let (labels, polygons, parents) = mask.polygons_with_hierarchy().unwrap();
+    ///
+    /// let parents: Vec<Option<usize>> = parents
+    ///     .iter()
+    ///     .map(|parent| parent.and_then(|p| labels.iter().position(|&l| l == p)))
+    ///     .collect();
+    ///
+    /// polygons.save_with_parents("polygons.json", &parents).unwrap();
+    /// ```
+    pub fn save_with_parents<P: AsRef<Path>>(
+        &self,
+        path: P,
+        parents: &[Option<usize>],
+    ) -> Result<(), ThymeError> {
+        if parents.len() != self.len() {
+            return Err(ThymeError::PolygonsWriteError);
+        }
+
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase());
+
+        if let Some(ext) = extension {
+            if ext == "json" {
+                return write_polygons_json_with_parents(path, &self.data, parents);
+            }
+        }
+
+        Err(ThymeError::PolygonsWriteError)
+    }
 }
 
 // <<< I/O METHODS
@@ -162,6 +364,11 @@ impl Polygons {
     pub fn is_empty(&self) -> bool {
         self.data.len() == 0
     }
+
+    /// Return the summary of the most recent validation
+    pub fn validation(&self) -> &PolygonValidation {
+        &self.validation
+    }
 }
 
 // <<< PROPERTY METHODS
@@ -219,17 +426,115 @@ impl Polygons {
         }
     }
 
-    /// Order the points in each polygon
+    /// Order the points in each polygon counterclockwise
     pub fn order_points(&mut self) {
         if !self.ordered {
-            self.data
-                .iter_mut()
-                .for_each(|polygon| order_points(polygon));
+            self.data.iter_mut().for_each(|polygon| {
+                order_points(polygon);
+
+                if signed_area(polygon) < 0.0 {
+                    polygon.reverse();
+                }
+            });
 
             self.ordered = true;
         }
     }
 
+    /// Scale polygons with normalized vertices to image pixels
+    ///
+    /// The x and y coordinates of every vertex are multiplied by the image
+    /// width and height, respectively. Normalized vertices may fall slightly
+    /// outside [0, 1] (e.g. for objects cut by the image edge), but any
+    /// coordinate above 1.5 indicates that the polygons are already in pixels
+    /// and is reported as an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the image the polygons were annotated on
+    /// * `height` - Height of the image the polygons were annotated on
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::Polygons;
+    ///
+    /// let mut polygons = Polygons::new(vec![vec![[0., 0.], [0.5, 0.], [0.5, 0.25]]]).unwrap();
+    /// polygons.denormalize(100, 40).unwrap();
+    ///
+    /// assert_eq!(polygons.as_points()[0], [[0., 0.], [50., 0.], [50., 10.]]);
+    /// assert!(polygons.denormalize(100, 40).is_err());
+    /// ```
+    pub fn denormalize(&mut self, width: u32, height: u32) -> Result<(), ThymeError> {
+        if let Some(value) = self
+            .data
+            .iter()
+            .flatten()
+            .flat_map(|point| point.iter().copied())
+            .find(|value| *value > 1.5)
+        {
+            return Err(ThymeError::OtherError(format!(
+                "Polygons were expected to be normalized to [0, 1] but include a coordinate of {}",
+                value
+            )));
+        }
+
+        let (width, height) = (width as f32, height as f32);
+
+        for point in self.data.iter_mut().flatten() {
+            point[0] *= width;
+            point[1] *= height;
+        }
+
+        Ok(())
+    }
+
+    /// Smooth each polygon outline and return the relative change in its area
+    ///
+    /// Points must be ordered along each outline (e.g. polygons traced from a
+    /// mask), so polygons should be smoothed before computing descriptors.
+    /// The relative area change is zero for polygons without area.
+    ///
+    /// # Arguments
+    ///
+    /// * `smoothing` - Smoothing method applied to every outline
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{ContourSmoothing, Polygons};
+    ///
+    /// let mut polygons = Polygons::new(vec![vec![[0., 0.], [4., 0.], [4., 4.], [0., 4.]]]).unwrap();
+    /// let changes = polygons.smooth(ContourSmoothing::Chaikin(1));
+    ///
+    /// assert_eq!(polygons.as_points()[0].len(), 8);
+    /// assert_eq!(changes, [-0.125]);
+    /// ```
+    pub fn smooth(&mut self, smoothing: ContourSmoothing) -> Vec<f32> {
+        let changes = self
+            .data
+            .iter_mut()
+            .map(|polygon| {
+                let before = form::area(polygon);
+
+                match smoothing {
+                    ContourSmoothing::Gaussian(sigma) => smooth_gaussian(polygon, sigma),
+                    ContourSmoothing::Chaikin(iterations) => smooth_chaikin(polygon, iterations),
+                }
+
+                match before > 0.0 {
+                    true => (form::area(polygon) - before) / before,
+                    false => 0.0,
+                }
+            })
+            .collect();
+
+        self.deduped = false;
+        self.ordered = false;
+
+        changes
+    }
+
     /// Resample each polygon to an equal number of equidistant points
     pub fn resample_points(&mut self, n: usize) {
         self.dedup_points();
@@ -239,6 +544,64 @@ impl Polygons {
             .for_each(|polygon| resample_points(polygon, n));
     }
 
+    /// Normalize winding order and repair self-intersecting polygons
+    ///
+    /// Clockwise polygons are reversed so that every polygon is counterclockwise
+    /// (positive signed area). Polygons with a single self-intersection (e.g.
+    /// bowties) are repaired by re-ordering their points by angle around their
+    /// centroid. Polygons with more than one self-intersection, or that still
+    /// intersect after re-ordering, are left unchanged and flagged as degenerate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::Polygons;
+    ///
+    /// let mut polygons = Polygons::new(vec![
+    ///     vec![[0., 0.], [0., 2.], [2., 2.], [2., 0.]],
+    ///     vec![[0., 0.], [2., 2.], [2., 0.], [0., 2.]],
+    /// ])
+    /// .unwrap();
+    ///
+    /// let validation = polygons.validate_and_repair();
+    ///
+    /// assert_eq!(validation.reversed, 1);
+    /// assert_eq!(validation.repaired, 1);
+    /// assert!(validation.degenerate.is_empty());
+    /// ```
+    pub fn validate_and_repair(&mut self) -> &PolygonValidation {
+        let mut validation = PolygonValidation::default();
+
+        for (idx, polygon) in self.data.iter_mut().enumerate() {
+            let crossings = self_intersections(polygon);
+            let repaired = crossings > 0;
+
+            if repaired {
+                let mut ordered = polygon.clone();
+                order_points(&mut ordered);
+
+                if crossings > 1 || self_intersections(&ordered) > 0 {
+                    validation.degenerate.push(idx);
+                    continue;
+                }
+
+                *polygon = ordered;
+                validation.repaired += 1;
+            }
+
+            if signed_area(polygon) < 0.0 {
+                polygon.reverse();
+
+                if !repaired {
+                    validation.reversed += 1;
+                }
+            }
+        }
+
+        self.validation = validation;
+        &self.validation
+    }
+
     /// Remove polygons based on an array of pre-sorted (ascending) indices
     pub fn remove(&mut self, indices: &[usize]) {
         if indices.is_empty() {
@@ -258,10 +621,26 @@ impl Polygons {
         }
 
         self.data = data;
+
+        self.validation.degenerate = self
+            .validation
+            .degenerate
+            .iter()
+            .filter(|idx| indices.binary_search(idx).is_err())
+            .map(|idx| idx - indices.partition_point(|removed| removed < idx))
+            .collect();
+    }
+
+    /// Compute the area enclosed by each polygon
+    ///
+    /// Areas are computed with the shoelace formula using the points in their
+    /// current order, so polygons should be ordered along their outline.
+    pub fn areas(&self) -> Vec<f32> {
+        self.data.iter().map(|points| form::area(points)).collect()
     }
 
     /// Compute morphological measurements from polygons
-    pub fn descriptors(&mut self) -> Vec<[f32; 23]> {
+    pub fn descriptors(&mut self) -> Vec<[f32; 29]> {
         if !self.deduped {
             self.dedup_points();
             self.deduped = true;
@@ -341,19 +720,36 @@ where
     P: AsRef<Path>,
     T: Serialize,
 {
-    let file = File::create(path).map_err(|_| ThymeError::PolygonsWriteError)?;
-    let writer = BufWriter::new(file);
-
-    serde_json::to_writer(writer, &serde_json::json!({ "polygons": polygons }))
-        .map_err(|_| ThymeError::PolygonsWriteError)?;
+    write_atomic(path, |writer| {
+        serde_json::to_writer(writer, &serde_json::json!({ "polygons": polygons }))
+            .map_err(|_| ThymeError::PolygonsWriteError)
+    })
+}
 
-    Ok(())
+/// Write polygons and the index of their parent polygons to a json file
+pub fn write_polygons_json_with_parents<P, T>(
+    path: P,
+    polygons: &[Vec<[T; 2]>],
+    parents: &[Option<usize>],
+) -> Result<(), ThymeError>
+where
+    P: AsRef<Path>,
+    T: Serialize,
+{
+    write_atomic(path, |writer| {
+        serde_json::to_writer(
+            writer,
+            &serde_json::json!({ "polygons": polygons, "parents": parents }),
+        )
+        .map_err(|_| ThymeError::PolygonsWriteError)
+    })
 }
 
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use crate::im::ThymeMask;
 
     const TEST_DATA_JSON: &str = "../data/tests/test_polygons.json";
 
@@ -392,4 +788,169 @@ mod test {
 
         std::fs::remove_file(OUTPUT).unwrap();
     }
+
+    #[test]
+    pub fn test_write_json_with_parents() {
+        const OUTPUT: &str = "TEST_POLYGONS_WRITE_PARENTS.json";
+
+        let polygons = Polygons::open(TEST_DATA_JSON).unwrap();
+
+        assert!(polygons.save_with_parents(OUTPUT, &[None]).is_err());
+
+        polygons
+            .save_with_parents(OUTPUT, &[None, Some(0)])
+            .unwrap();
+
+        let json: Value = serde_json::from_str(&std::fs::read_to_string(OUTPUT).unwrap()).unwrap();
+        assert_eq!(json["parents"], serde_json::json!([null, 0]));
+
+        let reopened = Polygons::open(OUTPUT).unwrap();
+        assert_eq!(reopened.as_points(), polygons.as_points());
+
+        std::fs::remove_file(OUTPUT).unwrap();
+    }
+
+    #[test]
+    pub fn test_validate_and_repair_reversed() {
+        let mut polygons = Polygons::new(vec![
+            vec![[0., 0.], [0., 3.], [4., 3.], [4., 0.]],
+            vec![[0., 0.], [2., 0.], [2., 2.], [0., 2.]],
+        ])
+        .unwrap();
+
+        let validation = polygons.validate_and_repair().clone();
+
+        assert_eq!(validation.reversed, 1);
+        assert_eq!(validation.repaired, 0);
+        assert!(validation.degenerate.is_empty());
+
+        for polygon in polygons.as_points() {
+            assert!(signed_area(polygon) > 0.0);
+        }
+
+        assert_eq!(polygons.areas(), vec![12., 4.]);
+        assert_eq!(form::centroid(&polygons.as_points()[0]), [2., 1.5]);
+    }
+
+    #[test]
+    pub fn test_validate_and_repair_bowtie() {
+        let bowtie = vec![[0., 0.], [4., 4.], [4., 0.], [0., 4.]];
+
+        // Both lobes of a bowtie cancel out in the shoelace formula
+        assert_eq!(form::area(&bowtie), 0.);
+
+        let mut polygons = Polygons::new(vec![bowtie]).unwrap();
+        let validation = polygons.validate_and_repair().clone();
+
+        assert_eq!(validation.repaired, 1);
+        assert!(validation.degenerate.is_empty());
+        assert_eq!(self_intersections(&polygons.as_points()[0]), 0);
+        assert!(signed_area(&polygons.as_points()[0]) > 0.0);
+        assert_eq!(polygons.areas(), vec![16.]);
+        assert_eq!(form::centroid(&polygons.as_points()[0]), [2., 2.]);
+    }
+
+    #[test]
+    pub fn test_validate_and_repair_degenerate() {
+        // A pentagram crosses itself five times and is not a trivial bowtie
+        let pentagram: Vec<[f32; 2]> = (0..5)
+            .map(|i| {
+                let t = 2.0 * std::f32::consts::PI * (2 * i) as f32 / 5.0;
+                [t.cos(), t.sin()]
+            })
+            .collect();
+
+        assert_eq!(self_intersections(&pentagram), 5);
+
+        let triangle = vec![[0., 0.], [1., 0.], [1., 1.]];
+
+        let mut polygons =
+            Polygons::new(vec![triangle.clone(), pentagram.clone(), triangle]).unwrap();
+
+        let validation = polygons.validate_and_repair().clone();
+
+        assert_eq!(validation.degenerate, vec![1]);
+        assert_eq!(validation.repaired, 0);
+        assert_eq!(polygons.as_points()[1], pentagram);
+
+        polygons.remove(&[0]);
+        assert_eq!(polygons.validation().degenerate, vec![0]);
+
+        polygons.remove(&[0]);
+        assert!(polygons.validation().degenerate.is_empty());
+    }
+
+    #[test]
+    pub fn test_order_points_counterclockwise() {
+        let mut polygons =
+            Polygons::new(vec![vec![[1., 1.], [0., 1.], [1., 0.], [0., 0.]]]).unwrap();
+        polygons.order_points();
+
+        assert!(signed_area(&polygons.as_points()[0]) > 0.0);
+    }
+
+    #[test]
+    pub fn test_areas() {
+        let polygons = Polygons::new(vec![
+            vec![[0., 0.], [4., 0.], [4., 3.], [0., 3.]],
+            vec![[0., 0.], [0., 2.], [2., 2.], [2., 0.]],
+        ])
+        .unwrap();
+
+        assert_eq!(polygons.areas(), vec![12., 4.]);
+    }
+
+    #[test]
+    pub fn test_smooth_circle_perimeter() {
+        let radius = 30.0f32;
+        let size = 70;
+        let center = size as f32 / 2.0;
+
+        let data: Vec<u32> = (0..size * size)
+            .map(|i| {
+                let (x, y) = ((i % size) as f32, (i / size) as f32);
+                ((x - center).powi(2) + (y - center).powi(2) <= radius * radius) as u32
+            })
+            .collect();
+
+        let mut mask = ThymeMask::new(size, size, 1, data).unwrap();
+        let (_, polygons) = mask.polygons().unwrap();
+
+        // Outlines are traced through the centers of the boundary pixels
+        let circumference = 2.0 * std::f32::consts::PI * (radius - 0.5);
+        let error = |polygons: &Polygons| {
+            (form::perimeter(&polygons.as_points()[0]) - circumference).abs() / circumference
+        };
+
+        let raw = error(&polygons);
+        assert!(raw > 0.05);
+
+        for smoothing in [
+            ContourSmoothing::Gaussian(1.5),
+            ContourSmoothing::Chaikin(3),
+        ] {
+            let mut smoothed = polygons.clone();
+            let changes = smoothed.smooth(smoothing);
+
+            assert!(error(&smoothed) < raw);
+            assert!(changes[0].abs() < 0.01);
+        }
+
+        let mut smoothed = polygons.clone();
+        smoothed.smooth(ContourSmoothing::Gaussian(1.5));
+        assert!(error(&smoothed) < 0.01);
+
+        assert!(form::form_factor(&polygons.as_points()[0]) < 0.9);
+        assert!(form::form_factor(&smoothed.as_points()[0]) > 0.99);
+    }
+
+    #[test]
+    pub fn test_new_non_finite() {
+        let result = Polygons::new(vec![
+            vec![[0., 0.], [4., 0.], [4., 3.]],
+            vec![[0., 0.], [f32::NAN, 2.], [2., 2.]],
+        ]);
+
+        assert!(matches!(result, Err(ThymeError::PolygonsValueError)));
+    }
 }
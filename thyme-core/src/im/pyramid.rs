@@ -0,0 +1,307 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+#[cfg(feature = "io")]
+use std::fs::File;
+#[cfg(feature = "io")]
+use std::io::BufReader;
+#[cfg(feature = "io")]
+use std::path::Path;
+
+#[cfg(feature = "io")]
+use tiff::{
+    ColorType,
+    decoder::{Decoder, DecodingResult},
+};
+
+use crate::error::ThymeError;
+#[cfg(feature = "io")]
+use crate::im::{ThymeBuffer, ThymeImage};
+#[cfg(feature = "io")]
+use crate::io::{ImageInfo, probe_levels};
+
+/// Resolution level read from pyramidal images
+///
+/// Level 0 is the full-resolution image and each following level is a
+/// reduced-resolution copy stored in the same file.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::PyramidLevel;
+///
+/// let sizes = [(4096, 4096), (1024, 1024), (256, 256)];
+///
+/// assert_eq!(PyramidLevel::parse("auto").unwrap().select(&sizes, 512).unwrap(), 1);
+/// assert_eq!(PyramidLevel::parse("2").unwrap().select(&sizes, 512).unwrap(), 2);
+/// assert!(PyramidLevel::Level(3).select(&sizes, 512).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PyramidLevel {
+    /// Smallest level with both dimensions at least as large as a target size
+    #[default]
+    Auto,
+    /// A specific level
+    Level(usize),
+}
+
+impl PyramidLevel {
+    /// Parse a pyramid level from a string (auto or a level index)
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - String representation of the pyramid level
+    pub fn parse(level: &str) -> Result<PyramidLevel, ThymeError> {
+        match level.trim() {
+            "auto" => Ok(PyramidLevel::Auto),
+            level => level.parse().map(PyramidLevel::Level).map_err(|_| {
+                ThymeError::OtherError(format!(
+                    "Invalid pyramid level '{}'. Must be auto or a non-negative integer",
+                    level
+                ))
+            }),
+        }
+    }
+
+    /// Select a level from the (width, height) of each level
+    ///
+    /// The full-resolution level is selected automatically if no reduced
+    /// level reaches the target size.
+    ///
+    /// # Arguments
+    ///
+    /// * `sizes` - Width and height of each level ordered from full resolution
+    /// * `target` - Minimum width and height of an automatically selected level
+    pub fn select(&self, sizes: &[(u32, u32)], target: u32) -> Result<usize, ThymeError> {
+        match *self {
+            PyramidLevel::Auto => Ok(sizes
+                .iter()
+                .rposition(|&(width, height)| width >= target && height >= target)
+                .unwrap_or(0)),
+            PyramidLevel::Level(level) if level < sizes.len() => Ok(level),
+            PyramidLevel::Level(level) => Err(ThymeError::OtherError(format!(
+                "Pyramid level {} was requested but the image has {} levels",
+                level,
+                sizes.len()
+            ))),
+        }
+    }
+
+    /// Resolve the level of an image file
+    ///
+    /// Images that are not pyramidal have a single full-resolution level.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to an image
+    /// * `target` - Minimum width and height of an automatically selected level
+    #[cfg(feature = "io")]
+    pub fn resolve<P: AsRef<Path>>(&self, path: P, target: u32) -> Result<usize, ThymeError> {
+        let sizes: Vec<(u32, u32)> = probe_levels(path)?
+            .iter()
+            .map(|info: &ImageInfo| (info.width, info.height))
+            .collect();
+
+        self.select(&sizes, target)
+    }
+}
+
+#[cfg(feature = "io")]
+impl ThymeImage {
+    /// Open a resolution level of a pyramidal tiff
+    ///
+    /// Level 0 is opened with `ThymeImage::open` so that any supported image,
+    /// pyramidal or not, can be opened at full resolution. Reduced levels are
+    /// decoded directly from their IFD without decoding the full image.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to an image with a valid extension
+    /// * `level` - Resolution level (see `probe_levels`)
+    ///
+    /// ```no_run
+    /// use thyme_core::im::ThymeImage;
+    /// let image = ThymeImage::open_level("slide.tif", 2);
+    /// ```
+    pub fn open_level<P: AsRef<Path>>(path: P, level: usize) -> Result<ThymeImage, ThymeError> {
+        if level == 0 {
+            return Self::open(path);
+        }
+
+        let levels = probe_levels(&path)?;
+
+        if level >= levels.len() {
+            return Err(ThymeError::OtherError(format!(
+                "Pyramid level {} was requested but the image has {} levels",
+                level,
+                levels.len()
+            )));
+        }
+
+        let file = File::open(path).map_err(|_| ThymeError::ImageReadError)?;
+        let mut decoder =
+            Decoder::new(BufReader::new(file)).map_err(|_| ThymeError::ImageReadError)?;
+
+        decoder
+            .seek_to_image(level)
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        let (width, height) = decoder
+            .dimensions()
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        // Alpha channels are dropped to match images opened at full resolution
+        let (samples, channels) = match decoder
+            .colortype()
+            .map_err(|_| ThymeError::ImageFormatError)?
+        {
+            ColorType::Gray(_) => (1, 1),
+            ColorType::GrayA(_) => (2, 1),
+            ColorType::RGB(_) => (3, 3),
+            ColorType::RGBA(_) => (4, 3),
+            _ => return Err(ThymeError::ImageFormatError),
+        };
+
+        macro_rules! level {
+            ($variant:ident, $buffer:expr) => {
+                Ok(ThymeImage::$variant(ThymeBuffer::new(
+                    width,
+                    height,
+                    channels,
+                    drop_alpha($buffer, samples, channels),
+                )?))
+            };
+        }
+
+        match decoder
+            .read_image()
+            .map_err(|_| ThymeError::ImageReadError)?
+        {
+            DecodingResult::U8(buffer) => level!(U8, buffer),
+            DecodingResult::U16(buffer) => level!(U16, buffer),
+            DecodingResult::F32(buffer) => level!(F32, buffer),
+            DecodingResult::F64(buffer) => level!(F64, buffer),
+            _ => Err(ThymeError::ImageFormatError),
+        }
+    }
+}
+
+/// Keep the leading color channels of interleaved pixels with extra samples
+#[cfg(feature = "io")]
+fn drop_alpha<T: Copy>(buffer: Vec<T>, samples: usize, channels: u32) -> Vec<T> {
+    if samples == channels as usize {
+        return buffer;
+    }
+
+    buffer
+        .chunks_exact(samples)
+        .flat_map(|pixel| pixel[..channels as usize].iter().copied())
+        .collect()
+}
+
+#[cfg(test)]
+#[cfg(feature = "io")]
+mod test {
+
+    use super::*;
+
+    use tiff::encoder::{TiffEncoder, colortype};
+    use tiff::tags::Tag;
+
+    fn pixels(image: ThymeImage) -> Vec<u8> {
+        match image {
+            ThymeImage::U8(buffer) => buffer.into_raw(),
+            _ => panic!("Expected an 8-bit image"),
+        }
+    }
+
+    /// Write a tiff with a 64x48 full-resolution level and a 16x12 reduced level
+    fn write_pyramid(path: &Path) -> (Vec<u8>, Vec<u8>) {
+        let full: Vec<u8> = (0..64 * 48).map(|i| (i % 251) as u8).collect();
+        let reduced: Vec<u8> = (0..16 * 12).map(|i| (i % 7) as u8).collect();
+
+        let mut encoder = TiffEncoder::new(File::create(path).unwrap()).unwrap();
+
+        encoder
+            .write_image::<colortype::Gray8>(64, 48, &full)
+            .unwrap();
+
+        let mut image = encoder.new_image::<colortype::Gray8>(16, 12).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::NewSubfileType, 1u32)
+            .unwrap();
+        image.write_data(&reduced).unwrap();
+
+        (full, reduced)
+    }
+
+    #[test]
+    fn test_pyramid_level_parse() {
+        assert_eq!(PyramidLevel::parse("auto").unwrap(), PyramidLevel::Auto);
+        assert_eq!(PyramidLevel::parse(" 3").unwrap(), PyramidLevel::Level(3));
+        assert!(PyramidLevel::parse("-1").is_err());
+        assert!(PyramidLevel::parse("largest").is_err());
+    }
+
+    #[test]
+    fn test_pyramid_level_select() {
+        let sizes = [(64, 48), (16, 12)];
+
+        assert_eq!(PyramidLevel::Auto.select(&sizes, 12).unwrap(), 1);
+        assert_eq!(PyramidLevel::Auto.select(&sizes, 13).unwrap(), 0);
+        assert_eq!(PyramidLevel::Auto.select(&sizes, 100).unwrap(), 0);
+        assert_eq!(PyramidLevel::Level(0).select(&sizes, 100).unwrap(), 0);
+        assert!(PyramidLevel::Level(2).select(&sizes, 12).is_err());
+    }
+
+    #[test]
+    fn test_open_level() {
+        let path = std::env::temp_dir().join("thyme_test_open_level.tif");
+        let (full, reduced) = write_pyramid(&path);
+
+        let levels = probe_levels(&path).unwrap();
+        assert_eq!(levels.len(), 2);
+        assert_eq!((levels[1].width, levels[1].height), (16, 12));
+
+        let image = ThymeImage::open_level(&path, 0).unwrap();
+        assert_eq!(image.shape(), (48, 64, 1));
+        assert_eq!(pixels(image), full);
+
+        let image = ThymeImage::open_level(&path, 1).unwrap();
+        assert_eq!(image.shape(), (12, 16, 1));
+        assert_eq!(pixels(image), reduced);
+
+        assert!(ThymeImage::open_level(&path, 2).is_err());
+        assert_eq!(PyramidLevel::Auto.resolve(&path, 8).unwrap(), 1);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_level_not_pyramidal() {
+        let path = Path::new("../data/tests/test_rgb.tif");
+
+        assert_eq!(probe_levels(path).unwrap().len(), 1);
+        assert_eq!(PyramidLevel::Auto.resolve(path, 1).unwrap(), 0);
+        assert!(PyramidLevel::Level(1).resolve(path, 1).is_err());
+
+        let image = ThymeImage::open_level(path, 0).unwrap();
+        assert_eq!(pixels(image), pixels(ThymeImage::open(path).unwrap()));
+
+        // Pages of a multi-page stack share dimensions so they are not levels
+        let stack = std::env::temp_dir().join("thyme_test_open_level_stack.tif");
+        let mut encoder = TiffEncoder::new(File::create(&stack).unwrap()).unwrap();
+
+        for _ in 0..2 {
+            encoder
+                .write_image::<colortype::Gray16>(8, 8, &[0u16; 64])
+                .unwrap();
+        }
+
+        assert_eq!(probe_levels(&stack).unwrap().len(), 1);
+        assert!(ThymeImage::open_level(&stack, 1).is_err());
+
+        std::fs::remove_file(stack).unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::cmp::Ordering;
+
+use crate::error::ThymeError;
+use crate::im::ThymeImage;
+
+/// Foreground coverage and background intensity of a whole image
+///
+/// Background pixels are all pixels not covered by any object, so that image
+/// level context for normalizing object descriptors is computed in a single
+/// pass over the image. Background statistics are zero if every pixel is
+/// covered by an object.
+///
+/// The focus score is the variance of the 4-neighbor laplacian of the channel
+/// mean over all interior pixels, so that blurry or empty fields have scores
+/// near zero.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::{ImageSummary, ThymeBuffer, ThymeImage};
+///
+/// let image = ThymeImage::U8(ThymeBuffer::new(2, 2, 1, vec![9u8, 1, 2, 6]).unwrap());
+/// let summary = ImageSummary::new(&image, &[1, 0, 0, 0]).unwrap();
+///
+/// assert_eq!(summary.foreground_fraction, 0.25);
+/// assert_eq!(summary.background_mean, [3.0]);
+/// assert_eq!(summary.background_median, [2.0]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageSummary {
+    /// Width of the image
+    pub width: u32,
+    /// Height of the image
+    pub height: u32,
+    /// Fraction of pixels covered by any object
+    pub foreground_fraction: f32,
+    /// Variance of the laplacian of the channel mean
+    pub focus_score: f32,
+    /// Mean of the background pixels in each channel
+    pub background_mean: Vec<f32>,
+    /// Median of the background pixels in each channel
+    pub background_median: Vec<f32>,
+}
+
+impl ImageSummary {
+    /// Names of the scalar metrics returned by `metric`
+    pub const METRICS: [&'static str; 4] = [
+        "foreground_fraction",
+        "focus_score",
+        "background_mean",
+        "background_median",
+    ];
+
+    /// Summarize an image given the union of its object pixels
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Image the objects were segmented from
+    /// * `foreground` - Row-major pixels of the image with non-zero values for object pixels
+    pub fn new(image: &ThymeImage, foreground: &[u32]) -> Result<Self, ThymeError> {
+        let (width, height, channels) = (image.width(), image.height(), image.channels());
+        let pixels = width as usize * height as usize;
+
+        if foreground.len() != pixels {
+            return Err(ThymeError::OtherError(
+                "Foreground and image are not the same size".to_string(),
+            ));
+        }
+
+        let covered = foreground.iter().filter(|&&v| v != 0).count();
+        let channels = channels as usize;
+
+        let mut background: Vec<Vec<f32>> = vec![Vec::with_capacity(pixels - covered); channels];
+        let mut mean = vec![0f32; pixels];
+
+        image.crop_view(0, 0, width, height).with_buffer(|view| {
+            for (idx, value) in view.iter_f32().enumerate() {
+                mean[idx / channels] += value / channels as f32;
+
+                if foreground[idx / channels] == 0 {
+                    background[idx % channels].push(value);
+                }
+            }
+        });
+
+        let background_mean = background
+            .iter()
+            .map(|values| match values.is_empty() {
+                true => 0.0,
+                false => {
+                    (values.iter().map(|&v| v as f64).sum::<f64>() / values.len() as f64) as f32
+                }
+            })
+            .collect();
+
+        let background_median = background.iter_mut().map(|values| median(values)).collect();
+
+        Ok(ImageSummary {
+            width,
+            height,
+            foreground_fraction: match pixels {
+                0 => 0.0,
+                _ => covered as f32 / pixels as f32,
+            },
+            focus_score: focus_score(&mean, width as usize, height as usize),
+            background_mean,
+            background_median,
+        })
+    }
+
+    /// Value of a scalar metric by name
+    ///
+    /// Per-channel background statistics are averaged over channels. Returns
+    /// NaN for names that are not in `METRICS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the metric
+    pub fn metric(&self, name: &str) -> f32 {
+        let average = |values: &[f32]| match values.is_empty() {
+            true => 0.0,
+            false => values.iter().sum::<f32>() / values.len() as f32,
+        };
+
+        match name {
+            "foreground_fraction" => self.foreground_fraction,
+            "focus_score" => self.focus_score,
+            "background_mean" => average(&self.background_mean),
+            "background_median" => average(&self.background_median),
+            _ => f32::NAN,
+        }
+    }
+}
+
+/// Variance of the 4-neighbor laplacian over the interior pixels of a plane
+fn focus_score(plane: &[f32], width: usize, height: usize) -> f32 {
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let (mut sum, mut sum_sq) = (0f64, 0f64);
+
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let idx = y * width + x;
+            let laplacian =
+                (plane[idx - width] + plane[idx + width] + plane[idx - 1] + plane[idx + 1]
+                    - 4.0 * plane[idx]) as f64;
+
+            sum += laplacian;
+            sum_sq += laplacian * laplacian;
+        }
+    }
+
+    let n = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / n;
+
+    (sum_sq / n - mean * mean).max(0.0) as f32
+}
+
+/// Median of a set of values, partially reordering them in place
+fn median(values: &mut [f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let compare = |a: &f32, b: &f32| a.partial_cmp(b).unwrap_or(Ordering::Equal);
+
+    let n = values.len();
+    let (lower, upper, _) = values.select_nth_unstable_by(n / 2, compare);
+    let upper = *upper;
+
+    if n % 2 == 0 {
+        let lower = lower.iter().copied().max_by(compare).unwrap();
+        (lower + upper) / 2.0
+    } else {
+        upper
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::im::ThymeBuffer;
+
+    #[test]
+    fn test_image_summary() {
+        // A 10 x 8 two-channel image with a 4 x 5 foreground rectangle
+        let (width, height) = (10u32, 8u32);
+        let foreground: Vec<u32> = (0..width * height)
+            .map(|i| u32::from((2..6).contains(&(i % width)) && (1..6).contains(&(i / width))))
+            .collect();
+
+        let pixels: Vec<u16> = (0..width * height)
+            .flat_map(|i| match foreground[i as usize] {
+                0 => [(i % 3) as u16, 7],
+                _ => [1000, 1000],
+            })
+            .collect();
+
+        let image = ThymeImage::U16(ThymeBuffer::new(width, height, 2, pixels).unwrap());
+        let summary = ImageSummary::new(&image, &foreground).unwrap();
+
+        assert_eq!((summary.width, summary.height), (10, 8));
+        assert_eq!(summary.foreground_fraction, 20.0 / 80.0);
+        assert_eq!(summary.background_median, [1.0, 7.0]);
+        assert_eq!(summary.background_mean[1], 7.0);
+        assert!((summary.background_mean[0] - 1.0).abs() < 0.05);
+
+        // Fully covered images have no background
+        let summary = ImageSummary::new(&image, &vec![1; 80]).unwrap();
+        assert_eq!(summary.foreground_fraction, 1.0);
+        assert_eq!(summary.background_mean, [0.0, 0.0]);
+
+        assert!(ImageSummary::new(&image, &[0; 4]).is_err());
+
+        assert_eq!(summary.metric("foreground_fraction"), 1.0);
+        assert!(summary.metric("area").is_nan());
+
+        let mut even = [4.0, 1.0, 3.0, 2.0];
+        assert_eq!(median(&mut even), 2.5);
+    }
+
+    #[test]
+    fn test_focus_score() {
+        let (width, height) = (8u32, 6u32);
+        let foreground = vec![0u32; (width * height) as usize];
+
+        let summary = |pixels: Vec<u8>| {
+            let image = ThymeImage::U8(ThymeBuffer::new(width, height, 1, pixels).unwrap());
+            ImageSummary::new(&image, &foreground).unwrap()
+        };
+
+        // Constant and linear gradients have a laplacian of zero everywhere
+        let blank = summary(vec![30; 48]);
+        let gradient = summary((0..48).map(|i| (i % 8) as u8 * 10).collect());
+
+        assert_eq!(blank.focus_score, 0.0);
+        assert_eq!(gradient.focus_score, 0.0);
+
+        // A checkerboard has a laplacian of +/- 4 * 100 at alternating pixels
+        let checkerboard = summary((0..48).map(|i| 100 * ((i % 8 + i / 8) % 2) as u8).collect());
+        assert_eq!(checkerboard.focus_score, 160000.0);
+        assert_eq!(checkerboard.metric("focus_score"), 160000.0);
+    }
+}
@@ -5,9 +5,14 @@ use std::ops::Deref;
 
 use num::{FromPrimitive, ToPrimitive};
 
-use crate::im::ThymeBuffer;
+use crate::cv::features::{GLCM, TexturePreset, glcm_multichannel_object};
+use crate::error::ThymeError;
+use crate::im::{FeatureChannels, MaskingStyle, ThymeBuffer, ThymeMaskView};
 use crate::impl_enum_dispatch;
-use crate::mp::{intensity, moments, texture, zernike};
+use crate::mp::intensity::IntensityAccumulator;
+use crate::mp::moments::MomentsAccumulator;
+use crate::mp::reduce::Reduce;
+use crate::mp::{intensity, membrane, moments, spectrum, texture, zernike};
 
 /// A wrapper around valid view types
 pub enum ThymeView<'a> {
@@ -21,24 +26,173 @@ pub enum ThymeView<'a> {
     F64(ThymeViewBuffer<'a, f64, Vec<f64>>),
 }
 
+/// Pixel regions of an object that are measured by `descriptors_regions`
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::Regions;
+///
+/// let regions = Regions::from_mode("cbm");
+/// assert!(regions.complete && !regions.foreground && regions.background);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Regions {
+    /// All pixels of the object crop
+    pub complete: bool,
+    /// Pixels inside the object mask
+    pub foreground: bool,
+    /// Pixels outside the object mask
+    pub background: bool,
+}
+
+impl Regions {
+    /// Select regions from a profiling mode (c, f, b)
+    pub fn from_mode(mode: &str) -> Self {
+        Regions {
+            complete: mode.contains('c'),
+            foreground: mode.contains('f'),
+            background: mode.contains('b'),
+        }
+    }
+
+    /// Check if no regions are selected
+    pub fn is_empty(&self) -> bool {
+        !(self.complete || self.foreground || self.background)
+    }
+}
+
 // >>> PROPERTY METHODS
 
 impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; width(&'a self) -> usize);
 impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; height(&'a self) -> usize);
 impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; channels(&'a self) -> usize);
 
+impl<'a> ThymeView<'a> {
+    /// Apply a function to the underlying view buffer regardless of data type
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Function receiving the view as a type-erased `ViewOps`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{ThymeBuffer, ThymeImage};
+    ///
+    /// let image = ThymeImage::U16(ThymeBuffer::new(2, 2, 1, vec![1u16, 2, 3, 4]).unwrap());
+    /// let view = image.crop_view(0, 0, 2, 2);
+    ///
+    /// let sum: f32 = view.with_buffer(|v| v.iter_f32().sum());
+    /// assert_eq!(sum, 10.0);
+    /// ```
+    pub fn with_buffer<R>(&self, f: impl FnOnce(&dyn ViewOps) -> R) -> R {
+        match self {
+            ThymeView::U8(v) => f(v),
+            ThymeView::U16(v) => f(v),
+            ThymeView::U32(v) => f(v),
+            ThymeView::U64(v) => f(v),
+            ThymeView::I32(v) => f(v),
+            ThymeView::I64(v) => f(v),
+            ThymeView::F32(v) => f(v),
+            ThymeView::F64(v) => f(v),
+        }
+    }
+
+    /// Restrict the view to foreground or background pixels of a mask without copying
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - A binary mask view with the same dimensions as the view
+    /// * `mask_style` - Foreground or background masking style
+    pub fn masked(
+        &'a self,
+        mask: &'a ThymeMaskView<'a>,
+        mask_style: MaskingStyle,
+    ) -> ThymeView<'a> {
+        match self {
+            ThymeView::U8(v) => ThymeView::U8(v.masked(mask, mask_style)),
+            ThymeView::U16(v) => ThymeView::U16(v.masked(mask, mask_style)),
+            ThymeView::U32(v) => ThymeView::U32(v.masked(mask, mask_style)),
+            ThymeView::U64(v) => ThymeView::U64(v.masked(mask, mask_style)),
+            ThymeView::I32(v) => ThymeView::I32(v.masked(mask, mask_style)),
+            ThymeView::I64(v) => ThymeView::I64(v.masked(mask, mask_style)),
+            ThymeView::F32(v) => ThymeView::F32(v.masked(mask, mask_style)),
+            ThymeView::F64(v) => ThymeView::F64(v.masked(mask, mask_style)),
+        }
+    }
+}
+
 // <<< PROPERTY METHODS
 
 // >>> MEASURE METHODS
 
 impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; intensity(&'a self) -> [f32; 7]);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; intensity_extended(&'a self, approx_over: Option<usize>) -> [f32; 13]);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; membrane(&'a self, mask: &ThymeMaskView<'a>, width: u32) -> [f32; 5]);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; spectrum(&'a self, mask: &ThymeMaskView<'a>) -> [f32; 3]);
 impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; moments(&'a self) -> [f32; 24]);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; centroid_displacement(&'a self, mask: &ThymeMaskView<'a>) -> Vec<f32>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; channel_pairs(&'a self, pairs: &[(usize, usize)]) -> Vec<f32>);
 impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; texture(&'a self) -> [f32; 13]);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; texture_strided(&'a self, stride: usize) -> [f32; 13]);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; glcm(&'a self, angle: f32, distance: f32) -> Vec<GLCM>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; texture_preset(&'a self, preset: TexturePreset) -> [f32; 13]);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; glcm_preset(&'a self, offset: [i32; 2], preset: TexturePreset) -> Vec<GLCM>);
 impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; zernike(&'a self) -> [f32; 30]);
 impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descriptors(&'a self) -> Vec<f32>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descriptors_extended(&'a self) -> Vec<f32>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descriptors_strided(&'a self, texture_stride: usize) -> Vec<f32>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descriptors_extended_strided(&'a self, texture_stride: usize, approx_over: Option<usize>) -> Vec<f32>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descriptors_masked(&'a self, mask: &ThymeMaskView<'a>) -> Vec<f32>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descriptors_regions(&'a self, mask: &ThymeMaskView<'a>, regions: Regions, extended: bool, approx_over: Option<usize>, texture_stride: usize) -> Vec<f32>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descriptors_regions_reduced(&'a self, mask: &ThymeMaskView<'a>, regions: Regions, extended: bool, approx_over: Option<usize>, texture_stride: usize, reduce: &dyn Reduce) -> Result<Vec<f32>, ThymeError>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descriptors_channels(&'a self, mask: Option<(&ThymeMaskView<'a>, MaskingStyle)>, channels: &FeatureChannels, extended: bool, approx_over: Option<usize>, texture_stride: usize) -> Vec<f32>);
+impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descriptors_regions_channels(&'a self, mask: &ThymeMaskView<'a>, regions: Regions, channels: &FeatureChannels, extended: bool, approx_over: Option<usize>, texture_stride: usize) -> Vec<f32>);
 
 // <<< MEASURE METHODS
 
+/// Type-erased access to the dimensions and pixels of a view
+///
+/// This trait is object safe so that library code can operate on views of
+/// any data type (e.g. through `ThymeView::with_buffer`) without matching on
+/// every variant.
+pub trait ViewOps {
+    /// Width of the view
+    fn width(&self) -> usize;
+
+    /// Height of the view
+    fn height(&self) -> usize;
+
+    /// Number of channels
+    fn channels(&self) -> usize;
+
+    /// Iterate over subpixels in row-major order converted to f32
+    fn iter_f32(&self) -> Box<dyn Iterator<Item = f32> + '_>;
+}
+
+impl<T, Container> ViewOps for ThymeViewBuffer<'_, T, Container>
+where
+    T: ToPrimitive + FromPrimitive,
+    Container: Deref<Target = [T]>,
+{
+    fn width(&self) -> usize {
+        ThymeViewBuffer::width(self)
+    }
+
+    fn height(&self) -> usize {
+        ThymeViewBuffer::height(self)
+    }
+
+    fn channels(&self) -> usize {
+        ThymeViewBuffer::channels(self)
+    }
+
+    fn iter_f32(&self) -> Box<dyn Iterator<Item = f32> + '_> {
+        Box::new(self.iter().map(|v| v.to_f32().unwrap_or(0.0)))
+    }
+}
+
 /// A row-major buffer that defines an image view/crop/subregion
 ///
 /// The cropped object represents a zero-copy reference to a larger
@@ -67,12 +221,14 @@ impl_enum_dispatch!(ThymeView<'a>, U8, U16, U32, U64, I32, I64, F32, F64; descri
 #[derive(Clone)]
 pub struct ThymeViewBuffer<'a, T, Container> {
     buffer: &'a ThymeBuffer<T, Container>,
-    width: usize,    // Full image width
-    channels: usize, // Full image channels
-    x: usize,        // Minimum x-value of crop
-    y: usize,        // Minimum y-value of crop
-    w: usize,        // Width of crop
-    h: usize,        // Height of crop
+    width: usize,                                        // Full image width
+    channels: usize,                                     // Full image channels
+    x: usize,                                            // Minimum x-value of crop
+    y: usize,                                            // Minimum y-value of crop
+    w: usize,                                            // Width of crop
+    h: usize,                                            // Height of crop
+    mask: Option<(&'a ThymeMaskView<'a>, MaskingStyle)>, // Pixels to exclude
+    zeros: Vec<T>, // A zero pixel returned for excluded pixels
 }
 
 impl<'a, T, Container> ThymeViewBuffer<'a, T, Container>
@@ -109,6 +265,50 @@ where
             y: y as usize,
             w: w as usize,
             h: h as usize,
+            mask: None,
+            zeros: Vec::new(),
+        }
+    }
+
+    /// Restrict the view to foreground or background pixels of a mask without copying
+    ///
+    /// Excluded pixels are returned as zero by the view iterators, so measuring
+    /// a masked view is equivalent to measuring the output of `crop_masked`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - A binary mask view with the same dimensions as the view
+    /// * `mask_style` - Foreground or background masking style
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{MaskingStyle, ThymeBuffer, ThymeMask};
+    ///
+    /// let buffer = ThymeBuffer::<u8, Vec<u8>>::new(2, 2, 1, vec![1, 2, 3, 4]).unwrap();
+    /// let mask = ThymeMask::new(2, 2, 1, vec![1, 0, 0, 1]).unwrap();
+    ///
+    /// let view = buffer.crop_view(0, 0, 2, 2);
+    /// let mask = mask.crop_view(0, 0, 2, 2);
+    ///
+    /// let foreground = view.masked(&mask, MaskingStyle::Foreground);
+    /// assert_eq!(foreground.iter().copied().collect::<Vec<u8>>(), vec![1, 0, 0, 4]);
+    /// ```
+    pub fn masked<'b>(
+        &'b self,
+        mask: &'b ThymeMaskView<'b>,
+        mask_style: MaskingStyle,
+    ) -> ThymeViewBuffer<'b, T, Container> {
+        ThymeViewBuffer {
+            buffer: self.buffer,
+            width: self.width,
+            channels: self.channels,
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+            mask: Some((mask, mask_style)),
+            zeros: (0..self.channels).map(|_| T::from_u8(0).unwrap()).collect(),
         }
     }
 }
@@ -158,10 +358,50 @@ where
     Container: Deref<Target = [T]>,
 {
     /// Compute the intensity descriptors for the object
-    #[allow(clippy::identity_op, clippy::erasing_op)]
+    ///
+    /// The min, max, sum, mean, and standard deviation of the non-zero pixels
+    /// are computed per channel and averaged over channels. The median and mad
+    /// are computed over the non-zero pixels of all channels.
     pub fn intensity(&'a self) -> [f32; 7] {
-        let results = intensity::objects(self);
+        let results = intensity::objects(self, false, None);
+        self.average_intensity(&results)
+    }
+
+    /// Compute the intensity descriptors with percentiles, skewness, and kurtosis
+    ///
+    /// The first seven descriptors are identical to `intensity` and are followed
+    /// by the p10, p25, p75, p90, skewness, and excess kurtosis of the non-zero
+    /// pixels, averaged over channels.
+    ///
+    /// # Arguments
+    ///
+    /// * `approx_over` - Positive subpixels of a channel above which percentiles are selected without a full sort
+    pub fn intensity_extended(&'a self, approx_over: Option<usize>) -> [f32; 13] {
+        let results = intensity::objects(self, true, approx_over);
+        self.average_intensity_extended(&results)
+    }
 
+    /// Average per-channel base and extended intensity descriptors
+    fn average_intensity_extended(&self, results: &[f32]) -> [f32; 13] {
+        let c = self.channels();
+        let rc = 1f32 / c as f32;
+        let base = c * 5 + 2;
+
+        let mut extended: [f32; 13] = [0f32; 13];
+        extended[..7].copy_from_slice(&self.average_intensity(&results[..base]));
+
+        for (j, value) in extended[7..].iter_mut().enumerate() {
+            for i in 0..c {
+                *value += results[base + i + j * c] * rc;
+            }
+        }
+
+        extended
+    }
+
+    /// Average per-channel intensity descriptors into a fixed number of outputs
+    #[allow(clippy::identity_op, clippy::erasing_op)]
+    fn average_intensity(&self, results: &[f32]) -> [f32; 7] {
         let c = self.channels();
         let rc = 1f32 / c as f32;
         let len = results.len();
@@ -174,26 +414,124 @@ where
         average[6] = results[len - 1];
 
         for i in 0..c {
-            average[0] = results[i + 0 * c] * rc;
-            average[1] = results[i + 1 * c] * rc;
-            average[2] = results[i + 2 * c] * rc;
-            average[3] = results[i + 3 * c] * rc;
-            average[4] = results[i + 3 * c] * rc;
+            average[0] += results[i + 0 * c] * rc;
+            average[1] += results[i + 1 * c] * rc;
+            average[2] += results[i + 2 * c] * rc;
+            average[3] += results[i + 3 * c] * rc;
+            average[4] += results[i + 4 * c] * rc;
+        }
+
+        average
+    }
+
+    /// Compute the boundary band and interior intensity descriptors for the object
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - A binary mask view with the same dimensions as the object
+    /// * `width` - Width of the boundary band in pixels
+    #[allow(clippy::identity_op, clippy::erasing_op)]
+    pub fn membrane(&'a self, mask: &ThymeMaskView, width: u32) -> [f32; 5] {
+        let results = membrane::objects(self, mask, width);
+
+        let c = self.channels();
+        let rc = 1f32 / c as f32;
+
+        // We average over channel values to avoid variable
+        // sized outputs in variable channel experiments
+        let mut average: [f32; 5] = [0f32; 5];
+
+        for i in 0..c {
+            average[0] += results[i + 0 * c] * rc;
+            average[1] += results[i + 1 * c] * rc;
+            average[2] += results[i + 2 * c] * rc;
+            average[3] += results[i + 3 * c] * rc;
+            average[4] += results[i + 4 * c] * rc;
         }
 
         average
     }
 
+    /// Compute the radially averaged power spectrum descriptors for the object
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - A binary mask view with the same dimensions as the object
+    pub fn spectrum(&'a self, mask: &ThymeMaskView) -> [f32; 3] {
+        spectrum::objects(self, mask)
+    }
+
     /// Compute the image moments for the object
     pub fn moments(&'a self) -> [f32; 24] {
         moments::objects(self)
     }
 
+    /// Compute the intensity-weighted centroid displacement of each channel
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - A binary mask view with the same dimensions as the object
+    pub fn centroid_displacement(&'a self, mask: &ThymeMaskView) -> Vec<f32> {
+        moments::centroid_displacement(self, mask)
+    }
+
+    /// Compute the mean intensity ratio and median intensity difference of channel pairs
+    ///
+    /// Ratios of all pairs are followed by the differences of all pairs, and
+    /// ratios with a zero denominator are NaN.
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - Numerator and denominator channel of each pair
+    pub fn channel_pairs(&'a self, pairs: &[(usize, usize)]) -> Vec<f32> {
+        intensity::channel_pairs(self, pairs)
+    }
+
     /// Compute the texture descriptors for the object
     pub fn texture(&'a self) -> [f32; 13] {
         texture::objects(self)
     }
 
+    /// Compute the texture descriptors for the object on a strided pixel grid
+    ///
+    /// # Arguments
+    ///
+    /// * `stride` - Only accumulate co-occurrences anchored at every `stride`-th row and column
+    pub fn texture_strided(&'a self, stride: usize) -> [f32; 13] {
+        texture::objects_strided(self, stride)
+    }
+
+    /// Compute the normalized gray-level co-occurence matrix of each channel
+    ///
+    /// # Arguments
+    ///
+    /// * `angle` - Angle (in degrees) for computing neighbour co-occurence
+    /// * `distance` - Number of pixels to neighbouring pixels
+    pub fn glcm(&'a self, angle: f32, distance: f32) -> Vec<GLCM> {
+        glcm_multichannel_object(self, angle, distance, 1)
+    }
+
+    /// Compute the texture descriptors for the object following a texture preset
+    ///
+    /// # Arguments
+    ///
+    /// * `preset` - Gray-level discretization, offsets, and descriptor conventions
+    pub fn texture_preset(&'a self, preset: TexturePreset) -> [f32; 13] {
+        texture::objects_preset(self, preset)
+    }
+
+    /// Compute the normalized gray-level co-occurence matrix of each channel following a texture preset
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Column and row offset (dx, dy) from each pixel to its neighbour
+    /// * `preset` - Gray-level discretization of the pixels
+    pub fn glcm_preset(&'a self, offset: [i32; 2], preset: TexturePreset) -> Vec<GLCM> {
+        (0..self.channels())
+            .map(|channel| GLCM::new_from_object_with_preset(self, channel, offset, 1, preset))
+            .collect()
+    }
+
     /// Compute the zernike moments for the object
     pub fn zernike(&'a self) -> [f32; 30] {
         zernike::objects(self)
@@ -201,13 +539,466 @@ where
 
     /// Compute all view descriptors
     pub fn descriptors(&'a self) -> Vec<f32> {
+        self.descriptors_strided(1)
+    }
+
+    /// Compute all view descriptors with texture computed on a strided pixel grid
+    ///
+    /// # Arguments
+    ///
+    /// * `texture_stride` - Stride of co-occurrence anchors for texture descriptors
+    pub fn descriptors_strided(&'a self, texture_stride: usize) -> Vec<f32> {
         self.intensity()
             .into_iter()
             .chain(self.moments())
-            .chain(self.texture())
+            .chain(self.texture_strided(texture_stride))
             .chain(self.zernike())
             .collect()
     }
+
+    /// Compute all view descriptors using only the foreground pixels of a mask
+    ///
+    /// Equivalent to computing `descriptors` on a foreground `crop_masked`
+    /// copy of the view without allocating an intermediate buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - A binary mask view with the same dimensions as the view
+    pub fn descriptors_masked(&self, mask: &ThymeMaskView) -> Vec<f32> {
+        self.masked(mask, MaskingStyle::Foreground).descriptors()
+    }
+
+    /// Compute all view descriptors with extended intensity descriptors
+    pub fn descriptors_extended(&'a self) -> Vec<f32> {
+        self.descriptors_extended_strided(1, None)
+    }
+
+    /// Compute all view descriptors with extended intensity descriptors and strided texture
+    ///
+    /// # Arguments
+    ///
+    /// * `texture_stride` - Stride of co-occurrence anchors for texture descriptors
+    /// * `approx_over` - Positive subpixels of a channel above which percentiles are selected without a full sort
+    pub fn descriptors_extended_strided(
+        &'a self,
+        texture_stride: usize,
+        approx_over: Option<usize>,
+    ) -> Vec<f32> {
+        self.intensity_extended(approx_over)
+            .into_iter()
+            .chain(self.moments())
+            .chain(self.texture_strided(texture_stride))
+            .chain(self.zernike())
+            .collect()
+    }
+
+    /// Compute view descriptors for several regions of an object in a single pass
+    ///
+    /// Intensity and moment descriptors of every selected region are accumulated
+    /// from one pass over the pixels, routing each pixel by the mask. Texture and
+    /// zernike descriptors are then computed on the masked views. Descriptors are
+    /// returned in complete, foreground, background order and are identical to
+    /// concatenating `descriptors_strided` (or `descriptors_extended_strided`)
+    /// of each region.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - A binary mask view with the same dimensions as the view
+    /// * `regions` - Regions to compute descriptors for
+    /// * `extended` - Include extended intensity descriptors
+    /// * `approx_over` - Positive subpixels of a channel above which percentiles are selected without a full sort
+    /// * `texture_stride` - Stride of co-occurrence anchors for texture descriptors
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{MaskingStyle, Regions, ThymeBuffer, ThymeMask};
+    ///
+    /// let buffer = ThymeBuffer::<u8, Vec<u8>>::new(3, 3, 1, (1..10).collect()).unwrap();
+    /// let mask = ThymeMask::new(3, 3, 1, vec![0, 1, 0, 1, 1, 1, 0, 1, 0]).unwrap();
+    ///
+    /// let view = buffer.crop_view(0, 0, 3, 3);
+    /// let mask = mask.crop_view(0, 0, 3, 3);
+    ///
+    /// let descriptors = view.descriptors_regions(&mask, Regions::from_mode("cf"), false, None, 1);
+    ///
+    /// let expected: Vec<f32> = view
+    ///     .descriptors()
+    ///     .into_iter()
+    ///     .chain(view.masked(&mask, MaskingStyle::Foreground).descriptors())
+    ///     .collect();
+    ///
+    /// assert_eq!(descriptors, expected);
+    /// ```
+    pub fn descriptors_regions(
+        &'a self,
+        mask: &ThymeMaskView,
+        regions: Regions,
+        extended: bool,
+        approx_over: Option<usize>,
+        texture_stride: usize,
+    ) -> Vec<f32> {
+        let selected = [regions.complete, regions.foreground, regions.background];
+
+        let c = self.channels();
+        let w = self.width();
+
+        let mut intensities: Vec<Option<IntensityAccumulator>> = selected
+            .iter()
+            .map(|&s| s.then(|| IntensityAccumulator::new(c, self.len())))
+            .collect();
+
+        let mut moments = [MomentsAccumulator::default(); 3];
+
+        for (p, pixel) in self.iter_pixels().enumerate() {
+            let foreground = is_foreground(mask, p % w, p / w);
+            let routed = [true, foreground, !foreground];
+
+            for (k, v) in pixel.iter().enumerate() {
+                let v = v.to_f32().unwrap();
+
+                // Moments are indexed by subpixel to match `moments::objects`
+                let i = p * c + k;
+
+                for ((intensity, moments), routed) in
+                    intensities.iter_mut().zip(moments.iter_mut()).zip(routed)
+                {
+                    if let (Some(intensity), true) = (intensity.as_mut(), routed) {
+                        intensity.push(k, v);
+                        moments.push(i % w, i / w, v);
+                    }
+                }
+            }
+        }
+
+        let mut results = Vec::with_capacity(selected.len() * 80);
+
+        for (r, accumulator) in intensities.into_iter().enumerate() {
+            let Some(accumulator) = accumulator else {
+                continue;
+            };
+
+            if extended {
+                results.extend(
+                    self.average_intensity_extended(&accumulator.finish(true, approx_over)),
+                );
+            } else {
+                results.extend(self.average_intensity(&accumulator.finish(false, None)));
+            }
+
+            results.extend(moments[r].finish());
+            results.extend(self.region_texture(mask, r, texture_stride));
+        }
+
+        results
+    }
+
+    /// Compute view descriptors for several regions with reductions on another device
+    ///
+    /// Intensity sums and raw moments of every selected region are computed by
+    /// `reduce` from a single upload of the object, while the median, mad, and
+    /// extended intensity descriptors are computed from the pixels on the cpu.
+    /// Descriptors follow the layout of `descriptors_regions`, and are
+    /// identical to it when reduced by `CpuReduce`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - A binary mask view with the same dimensions as the view
+    /// * `regions` - Regions to compute descriptors for
+    /// * `extended` - Include extended intensity descriptors
+    /// * `approx_over` - Positive subpixels of a channel above which percentiles are selected without a full sort
+    /// * `texture_stride` - Stride of co-occurrence anchors for texture descriptors
+    /// * `reduce` - Reducer of the intensity sums and raw moments
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{Regions, ThymeBuffer, ThymeMask};
+    /// use thyme_core::mp::reduce::CpuReduce;
+    ///
+    /// let buffer = ThymeBuffer::<u8, Vec<u8>>::new(3, 3, 1, (1..10).collect()).unwrap();
+    /// let mask = ThymeMask::new(3, 3, 1, vec![0, 1, 0, 1, 1, 1, 0, 1, 0]).unwrap();
+    ///
+    /// let view = buffer.crop_view(0, 0, 3, 3);
+    /// let mask = mask.crop_view(0, 0, 3, 3);
+    ///
+    /// let regions = Regions::from_mode("cfb");
+    /// let reduced = view.descriptors_regions_reduced(&mask, regions, false, None, 1, &CpuReduce);
+    ///
+    /// assert_eq!(reduced.unwrap(), view.descriptors_regions(&mask, regions, false, None, 1));
+    /// ```
+    pub fn descriptors_regions_reduced(
+        &'a self,
+        mask: &ThymeMaskView,
+        regions: Regions,
+        extended: bool,
+        approx_over: Option<usize>,
+        texture_stride: usize,
+        reduce: &dyn Reduce,
+    ) -> Result<Vec<f32>, ThymeError> {
+        let selected = [regions.complete, regions.foreground, regions.background];
+
+        let c = self.channels();
+        let w = self.width();
+
+        let mut pixels: Vec<f32> = Vec::with_capacity(self.len());
+        let mut foreground: Vec<bool> = Vec::with_capacity(self.len() / c.max(1));
+
+        // Positive values of each channel are kept for the order statistics
+        let mut values: Vec<Vec<Vec<f32>>> = vec![vec![vec![]; c]; 3];
+
+        for (p, pixel) in self.iter_pixels().enumerate() {
+            let inside = is_foreground(mask, p % w, p / w);
+            let routed = [true, inside, !inside];
+
+            for (k, v) in pixel.iter().enumerate() {
+                let v = v.to_f32().unwrap();
+
+                if v > 0. {
+                    for (r, values) in values.iter_mut().enumerate() {
+                        if selected[r] && routed[r] {
+                            values[k].push(v);
+                        }
+                    }
+                }
+
+                pixels.push(v);
+            }
+
+            foreground.push(inside);
+        }
+
+        let background: Vec<bool> = match regions.background {
+            true => foreground.iter().map(|&f| !f).collect(),
+            false => vec![],
+        };
+
+        let region_pixels = [
+            None,
+            Some(foreground.as_slice()),
+            Some(background.as_slice()),
+        ];
+
+        let reduced: Vec<Option<&[bool]>> = (0..3)
+            .filter(|&r| selected[r])
+            .map(|r| region_pixels[r])
+            .collect();
+
+        let mut reductions = reduce.reduce(&pixels, w, c, &reduced)?.into_iter();
+
+        // Pixels are no longer needed once reduced
+        drop(pixels);
+
+        let mut results = Vec::with_capacity(selected.len() * 80);
+
+        for (r, values) in values.into_iter().enumerate() {
+            if !selected[r] {
+                continue;
+            }
+
+            let reduction = reductions.next().ok_or_else(|| {
+                ThymeError::OtherError("Reducer returned fewer regions than requested".to_string())
+            })?;
+
+            let intensity =
+                intensity::finish_reduced(values, &reduction.intensity, extended, approx_over);
+
+            if extended {
+                results.extend(self.average_intensity_extended(&intensity));
+            } else {
+                results.extend(self.average_intensity(&intensity));
+            }
+
+            results.extend(MomentsAccumulator::from_raw(reduction.moments).finish());
+            results.extend(self.region_texture(mask, r, texture_stride));
+        }
+
+        Ok(results)
+    }
+
+    /// Texture and zernike descriptors of a region (complete, foreground, background)
+    fn region_texture(
+        &'a self,
+        mask: &ThymeMaskView,
+        region: usize,
+        texture_stride: usize,
+    ) -> Vec<f32> {
+        let (texture, zernike) = match region {
+            0 => (self.texture_strided(texture_stride), self.zernike()),
+            _ => {
+                let style = if region == 1 {
+                    MaskingStyle::Foreground
+                } else {
+                    MaskingStyle::Background
+                };
+
+                let view = self.masked(mask, style);
+                (view.texture_strided(texture_stride), view.zernike())
+            }
+        };
+
+        texture.into_iter().chain(zernike).collect()
+    }
+
+    /// Compute view descriptors separately for the selected channels of each family
+    ///
+    /// Each selected channel is copied into a single-channel buffer and only
+    /// the families selecting that channel are computed on it. Descriptors are
+    /// returned in intensity, moments, texture, zernike order with the channels
+    /// of each family in ascending order.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - Optional mask and style restricting the measured pixels
+    /// * `channels` - Channels of each descriptor family
+    /// * `extended` - Include extended intensity descriptors
+    /// * `approx_over` - Positive subpixels of a channel above which percentiles are selected without a full sort
+    /// * `texture_stride` - Stride of co-occurrence anchors for texture descriptors
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::{FeatureChannels, ThymeBuffer};
+    ///
+    /// let data: Vec<u8> = (1..19).collect();
+    /// let buffer = ThymeBuffer::<u8, Vec<u8>>::new(3, 3, 2, data).unwrap();
+    ///
+    /// let channels = FeatureChannels::parse("intensity:1").unwrap();
+    /// let view = buffer.crop_view(0, 0, 3, 3);
+    /// let descriptors = view.descriptors_channels(None, &channels, false, None, 1);
+    ///
+    /// let channel: Vec<u8> = buffer.as_raw().iter().skip(1).step_by(2).copied().collect();
+    /// let channel = ThymeBuffer::<u8, Vec<u8>>::new(3, 3, 1, channel).unwrap();
+    ///
+    /// assert_eq!(descriptors, channel.crop_view(0, 0, 3, 3).intensity());
+    /// ```
+    pub fn descriptors_channels(
+        &'a self,
+        mask: Option<(&ThymeMaskView, MaskingStyle)>,
+        channels: &FeatureChannels,
+        extended: bool,
+        approx_over: Option<usize>,
+        texture_stride: usize,
+    ) -> Vec<f32>
+    where
+        T: Copy,
+    {
+        let buffers = self.channel_buffers(&channels.union());
+        measure_channels(
+            &buffers,
+            mask,
+            channels,
+            extended,
+            approx_over,
+            texture_stride,
+        )
+    }
+
+    /// Compute per-channel view descriptors for several regions of an object
+    ///
+    /// Descriptors are returned in complete, foreground, background order and
+    /// are identical to concatenating `descriptors_channels` of each region.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - A binary mask view with the same dimensions as the view
+    /// * `regions` - Regions to compute descriptors for
+    /// * `channels` - Channels of each descriptor family
+    /// * `extended` - Include extended intensity descriptors
+    /// * `approx_over` - Positive subpixels of a channel above which percentiles are selected without a full sort
+    /// * `texture_stride` - Stride of co-occurrence anchors for texture descriptors
+    pub fn descriptors_regions_channels(
+        &'a self,
+        mask: &ThymeMaskView,
+        regions: Regions,
+        channels: &FeatureChannels,
+        extended: bool,
+        approx_over: Option<usize>,
+        texture_stride: usize,
+    ) -> Vec<f32>
+    where
+        T: Copy,
+    {
+        // Channels are copied once and shared by every region
+        let buffers = self.channel_buffers(&channels.union());
+
+        [
+            (regions.complete, None),
+            (regions.foreground, Some(MaskingStyle::Foreground)),
+            (regions.background, Some(MaskingStyle::Background)),
+        ]
+        .into_iter()
+        .filter(|(selected, _)| *selected)
+        .flat_map(|(_, style)| {
+            let mask = style.map(|style| (mask, style));
+            measure_channels(
+                &buffers,
+                mask,
+                channels,
+                extended,
+                approx_over,
+                texture_stride,
+            )
+        })
+        .collect()
+    }
+
+    /// Copy channels of the view into single-channel buffers
+    fn channel_buffers(&self, channels: &[usize]) -> Vec<(usize, ThymeBuffer<T, Vec<T>>)>
+    where
+        T: Copy,
+    {
+        channels
+            .iter()
+            .map(|&k| {
+                let data: Vec<T> = self.iter_pixels().map(|pixel| pixel[k]).collect();
+                let buffer = ThymeBuffer::new(self.w as u32, self.h as u32, 1, data).unwrap();
+                (k, buffer)
+            })
+            .collect()
+    }
+}
+
+/// Compute the selected families on single-channel buffers of the same view
+fn measure_channels<T>(
+    buffers: &[(usize, ThymeBuffer<T, Vec<T>>)],
+    mask: Option<(&ThymeMaskView, MaskingStyle)>,
+    channels: &FeatureChannels,
+    extended: bool,
+    approx_over: Option<usize>,
+    texture_stride: usize,
+) -> Vec<f32>
+where
+    T: Copy + ToPrimitive + FromPrimitive,
+{
+    let mut results = Vec::new();
+
+    for (family, selected) in channels.families() {
+        for channel in selected {
+            let (_, buffer) = buffers.iter().find(|(k, _)| k == channel).unwrap();
+
+            let view = buffer.crop_view(0, 0, buffer.width(), buffer.height());
+            let masked;
+
+            let view = match mask {
+                Some((mask, style)) => {
+                    masked = view.masked(mask, style);
+                    &masked
+                }
+                None => &view,
+            };
+
+            match family {
+                "intensity" if extended => results.extend(view.intensity_extended(approx_over)),
+                "intensity" => results.extend(view.intensity()),
+                "moments" => results.extend(view.moments()),
+                "texture" => results.extend(view.texture_strided(texture_stride)),
+                _ => results.extend(view.zernike()),
+            }
+        }
+    }
+
+    results
 }
 
 // <<< MEASURE METHODS
@@ -231,6 +1022,8 @@ where
             h: self.h,
             i: self.y,
             j: self.w * self.channels,
+            mask: self.mask,
+            zeros: &self.zeros,
         }
     }
 
@@ -246,12 +1039,38 @@ where
             h: self.h,
             i: 0,
             j: 0,
+            mask: self.mask,
+            zeros: &self.zeros,
         }
     }
 }
 
 // <<< ITERATOR METHODS
 
+/// Check if a pixel (relative to the view origin) is in the foreground of a mask
+#[inline]
+fn is_foreground(mask: &ThymeMaskView, col: usize, row: usize) -> bool {
+    col < mask.w
+        && row < mask.h
+        && mask.buffer.as_raw()[((mask.y + row) * mask.width + mask.x + col) * mask.channels] != 0
+}
+
+/// Check if a pixel (relative to the view origin) is excluded by a mask
+#[inline]
+fn is_excluded(mask: Option<(&ThymeMaskView, MaskingStyle)>, col: usize, row: usize) -> bool {
+    match mask {
+        None => false,
+        Some((mask, mask_style)) => {
+            let foreground = is_foreground(mask, col, row);
+
+            match mask_style {
+                MaskingStyle::Foreground => !foreground,
+                MaskingStyle::Background => foreground,
+            }
+        }
+    }
+}
+
 /// An iterator over subpixels
 pub struct SubpixelIterator<'a, T, Container>
 where
@@ -267,6 +1086,8 @@ where
     h: usize,
     i: usize,
     j: usize,
+    mask: Option<(&'a ThymeMaskView<'a>, MaskingStyle)>,
+    zeros: &'a [T],
 }
 
 impl<'a, T, Container> Iterator for SubpixelIterator<'a, T, Container>
@@ -284,6 +1105,7 @@ where
         let j_mod = self.j % (self.w * self.channels);
 
         let idx = self.i * (self.width * self.channels) + (self.x * self.channels) + j_mod;
+        let excluded = is_excluded(self.mask, j_mod / self.channels, self.i - self.y);
 
         if j_mod == (self.w * self.channels) - 1 {
             self.i += 1;
@@ -291,6 +1113,10 @@ where
 
         self.j += 1;
 
+        if excluded {
+            return Some(&self.zeros[0]);
+        }
+
         Some(&self.buffer.as_raw()[idx])
     }
 }
@@ -310,6 +1136,8 @@ where
     h: usize,
     i: usize,
     j: usize,
+    mask: Option<(&'a ThymeMaskView<'a>, MaskingStyle)>,
+    zeros: &'a [T],
 }
 
 impl<'a, T, Container> Iterator for PixelIterator<'a, T, Container>
@@ -325,6 +1153,7 @@ where
         }
 
         let idx = ((self.y + self.j) * self.width + (self.x + self.i)) * self.channels;
+        let excluded = is_excluded(self.mask, self.i, self.j);
 
         self.i += 1;
 
@@ -333,6 +1162,10 @@ where
             self.j += 1;
         }
 
+        if excluded {
+            return Some(self.zeros);
+        }
+
         Some(&self.buffer.as_raw()[idx..idx + self.channels])
     }
 }
@@ -426,4 +1259,264 @@ mod test {
             size_23_crop.iter_pixels().count()
         );
     }
+
+    /// A multichannel image and an irregular mask covering a subregion of it
+    fn masked_object() -> (ThymeBuffer<u8, Vec<u8>>, crate::im::ThymeMask) {
+        let (width, height) = (16u32, 12u32);
+
+        let data: Vec<u8> = (0..width * height * 3)
+            .map(|i| ((i * 37 + i / 7) % 251) as u8)
+            .collect();
+
+        let mask: Vec<u32> = (0..10 * 8)
+            .map(|i| {
+                let (x, y) = ((i % 10) as f32 - 4.5, (i / 10) as f32 - 3.5);
+                (x * x + y * y < 14.0) as u32
+            })
+            .collect();
+
+        (
+            ThymeBuffer::new(width, height, 3, data).unwrap(),
+            crate::im::ThymeMask::new(10, 8, 1, mask).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_masked_matches_crop_masked() {
+        let (buffer, mask) = masked_object();
+        let mask = mask.crop_view(0, 0, 10, 8);
+
+        for style in [MaskingStyle::Foreground, MaskingStyle::Background] {
+            let expected = buffer.crop_masked(3, 2, 10, 8, &mask, style).unwrap();
+            let expected = expected.crop_view(0, 0, 10, 8);
+
+            let view = buffer.crop_view(3, 2, 10, 8);
+            let masked = view.masked(&mask, style);
+
+            assert!(masked.iter().eq(expected.iter()));
+            assert!(masked.iter_pixels().eq(expected.iter_pixels()));
+            assert_eq!(masked.descriptors(), expected.descriptors());
+            assert_eq!(
+                masked.descriptors_extended(),
+                expected.descriptors_extended()
+            );
+        }
+
+        let expected = buffer
+            .crop_masked(3, 2, 10, 8, &mask, MaskingStyle::Foreground)
+            .unwrap();
+
+        assert_eq!(
+            buffer.crop_view(3, 2, 10, 8).descriptors_masked(&mask),
+            expected.crop_view(0, 0, 10, 8).descriptors()
+        );
+    }
+
+    #[test]
+    fn test_view_descriptors_masked() {
+        let (buffer, mask) = masked_object();
+        let mask = mask.crop_view(0, 0, 10, 8);
+
+        let image = crate::im::ThymeImage::U8(buffer);
+
+        let expected = image
+            .crop_masked(3, 2, 10, 8, &mask, MaskingStyle::Foreground)
+            .unwrap();
+
+        let view = image.crop_view(3, 2, 10, 8);
+
+        assert_eq!(
+            view.descriptors_masked(&mask),
+            expected.crop_view(0, 0, 10, 8).descriptors()
+        );
+
+        let masked = view.masked(&mask, MaskingStyle::Foreground);
+        assert_eq!(masked.texture(), expected.crop_view(0, 0, 10, 8).texture());
+        assert_eq!(masked.zernike(), expected.crop_view(0, 0, 10, 8).zernike());
+    }
+
+    #[test]
+    fn test_descriptors_regions() {
+        let (buffer, mask) = masked_object();
+        let mask = mask.crop_view(0, 0, 10, 8);
+
+        let image = crate::im::ThymeImage::U8(buffer);
+        let view = image.crop_view(3, 2, 10, 8);
+
+        for mode in ["c", "f", "b", "cf", "cb", "fb", "cfb"] {
+            for (extended, stride) in [(false, 1), (true, 1), (false, 3)] {
+                let mut expected = vec![];
+
+                for (region, style) in [
+                    ('c', None),
+                    ('f', Some(MaskingStyle::Foreground)),
+                    ('b', Some(MaskingStyle::Background)),
+                ] {
+                    if !mode.contains(region) {
+                        continue;
+                    }
+
+                    let descriptors = |v: &ThymeView| match extended {
+                        true => v.descriptors_extended_strided(stride, None),
+                        false => v.descriptors_strided(stride),
+                    };
+
+                    expected.extend(match style {
+                        Some(style) => descriptors(&view.masked(&mask, style)),
+                        None => descriptors(&view),
+                    });
+                }
+
+                let observed = view.descriptors_regions(
+                    &mask,
+                    Regions::from_mode(mode),
+                    extended,
+                    None,
+                    stride,
+                );
+
+                assert_eq!(observed, expected, "{} {} {}", mode, extended, stride);
+            }
+        }
+
+        assert!(Regions::from_mode("mp").is_empty());
+        assert!(
+            view.descriptors_regions(&mask, Regions::default(), false, None, 1)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_descriptors_regions_reduced() {
+        use crate::mp::reduce::CpuReduce;
+        use crate::ut::synth::SplitMix64;
+
+        let (_, mask) = masked_object();
+        let mask = mask.crop_view(0, 0, 10, 8);
+
+        // Random intensities with zeros so that regions have channels without positive pixels
+        let mut rng = SplitMix64::new(1452);
+        let data: Vec<f32> = (0..10 * 8 * 2)
+            .map(|_| match rng.range(0.0, 1.0) < 0.3 {
+                true => 0.0,
+                false => rng.range(0.0, 1000.0) as f32,
+            })
+            .collect();
+
+        let buffer = ThymeBuffer::<f32, Vec<f32>>::new(10, 8, 2, data).unwrap();
+        let view = buffer.crop_view(0, 0, 10, 8);
+
+        for mode in ["c", "f", "b", "cf", "cb", "fb", "cfb"] {
+            for (extended, stride) in [(false, 1), (true, 2)] {
+                let regions = Regions::from_mode(mode);
+
+                let observed = view
+                    .descriptors_regions_reduced(&mask, regions, extended, None, stride, &CpuReduce)
+                    .unwrap();
+
+                let expected = view.descriptors_regions(&mask, regions, extended, None, stride);
+
+                let bits = |v: &[f32]| v.iter().map(|v| v.to_bits()).collect::<Vec<u32>>();
+                assert_eq!(bits(&observed), bits(&expected), "{} {}", mode, extended);
+            }
+        }
+    }
+
+    #[test]
+    fn test_descriptors_regions_channels() {
+        let (buffer, mask) = masked_object();
+        let mask = mask.crop_view(0, 0, 10, 8);
+        let view = buffer.crop_view(3, 2, 10, 8);
+
+        let regions = Regions::from_mode("cfb");
+        let full =
+            FeatureChannels::parse("intensity:0-2;moments:0-2;texture:0-2;zernike:0-2").unwrap();
+
+        let observed = view.descriptors_regions_channels(&mask, regions, &full, true, None, 2);
+
+        // Every family of every channel matches the descriptors of a single-channel image
+        let single: Vec<Vec<f32>> = (0..3)
+            .map(|k| {
+                let data = view.iter_pixels().map(|pixel| pixel[k]).collect();
+                let buffer = ThymeBuffer::<u8, Vec<u8>>::new(10, 8, 1, data).unwrap();
+                buffer
+                    .crop_view(0, 0, 10, 8)
+                    .descriptors_regions(&mask, regions, true, None, 2)
+            })
+            .collect();
+
+        let sizes = [13, 24, 13, 30];
+        let region_size = 80;
+        let mut offset = 0;
+
+        for r in 0..3 {
+            let mut start = r * region_size;
+            for size in sizes {
+                for descriptors in &single {
+                    assert_eq!(
+                        observed[offset..offset + size],
+                        descriptors[start..start + size]
+                    );
+                    offset += size;
+                }
+                start += size;
+            }
+        }
+
+        assert_eq!(offset, observed.len());
+
+        // Narrowed families are the corresponding subset of the full descriptors
+        let narrowed = FeatureChannels::parse("moments:0;texture:1,2").unwrap();
+        let observed = view.descriptors_channels(None, &narrowed, false, None, 2);
+
+        let full = view.descriptors_channels(None, &full, false, None, 2);
+        let moments = 3 * 7;
+        let texture = moments + 3 * 24 + 13;
+
+        assert_eq!(observed[..24], full[moments..moments + 24]);
+        assert_eq!(observed[24..], full[texture..texture + 26]);
+    }
+
+    #[test]
+    fn test_intensity_channel_average() {
+        // Channels of a 2x2 image with min, max, sum, mean, and std of
+        // (2, 2, 8, 2, 0), (4, 4, 16, 4, 0), and (1, 3, 8, 2, 1)
+        let data = vec![2, 4, 1, 2, 4, 3, 2, 4, 1, 2, 4, 3];
+        let buffer = ThymeBuffer::<u8, Vec<u8>>::new(2, 2, 3, data).unwrap();
+        let view = ThymeViewBuffer::new(0, 0, 2, 2, &buffer);
+
+        let expected = [7. / 3., 3., 32. / 3., 8. / 3., 1. / 3., 2.5, 1.];
+
+        for (observed, expected) in view.intensity().iter().zip(expected) {
+            assert!(
+                (observed - expected).abs() < 1e-6,
+                "{} {}",
+                observed,
+                expected
+            );
+        }
+
+        assert_eq!(view.intensity_extended(None)[..7], view.intensity());
+
+        // A single channel is unchanged by averaging
+        let buffer = ThymeBuffer::<u8, Vec<u8>>::new(2, 2, 1, vec![1, 3, 1, 3]).unwrap();
+        let view = ThymeViewBuffer::new(0, 0, 2, 2, &buffer);
+        assert_eq!(view.intensity(), [1., 3., 8., 2., 1., 2., 1.]);
+    }
+
+    #[test]
+    fn test_with_buffer() {
+        let (buffer, _) = masked_object();
+        let image = crate::im::ThymeImage::U8(buffer);
+        let view = image.crop_view(3, 2, 10, 8);
+
+        let (w, h, c, n) =
+            view.with_buffer(|v| (v.width(), v.height(), v.channels(), v.iter_f32().count()));
+        assert_eq!((w, h, c, n), (10, 8, 3, 240));
+
+        let image =
+            crate::im::ThymeImage::F32(ThymeBuffer::new(2, 1, 1, vec![0.5f32, 1.5]).unwrap());
+        let view = image.crop_view(0, 0, 2, 1);
+        assert_eq!(view.with_buffer(|v| v.iter_f32().sum::<f32>()), 2.0);
+    }
 }
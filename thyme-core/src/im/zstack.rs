@@ -0,0 +1,500 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+#[cfg(feature = "io")]
+use std::path::Path;
+
+#[cfg(feature = "io")]
+use npyz::{DType, TypeChar};
+#[cfg(feature = "io")]
+use num::{FromPrimitive, ToPrimitive};
+
+use crate::error::ThymeError;
+use crate::im::ThymeMask;
+#[cfg(feature = "io")]
+use crate::im::mask::parse_mask_numpy;
+#[cfg(feature = "io")]
+use crate::im::{MaskFormat, MaskSummary, ThymeBuffer, ThymeImage};
+#[cfg(feature = "io")]
+use crate::io::parse_numpy;
+
+/// Handling of (Z, H, W) z-stack masks
+///
+/// Numpy arrays with three dimensions are ambiguous as they may be either
+/// (H, W, C) images or (Z, H, W) stacks, so stacks are only recognized when
+/// a mode other than `Error` is explicitly requested.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::im::ZStackMode;
+///
+/// assert_eq!(ZStackMode::parse("max-project").unwrap(), ZStackMode::MaxProject);
+/// assert_eq!(ZStackMode::PerSlice.slice_name("image", 2), "image_z2");
+/// assert_eq!(ZStackMode::MaxProject.slice_name("image", 0), "image");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZStackMode {
+    /// Only (H, W) masks are accepted
+    #[default]
+    Error,
+    /// Collapse the stack by taking the maximum label at each pixel
+    MaxProject,
+    /// Treat each z-slice as a separate image
+    PerSlice,
+}
+
+impl ZStackMode {
+    /// Parse a z-stack mode from a string (error, max-project, or per-slice)
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - String representation of the z-stack mode
+    pub fn parse(mode: &str) -> Result<ZStackMode, ThymeError> {
+        match mode.trim() {
+            "error" => Ok(ZStackMode::Error),
+            "max-project" => Ok(ZStackMode::MaxProject),
+            "per-slice" => Ok(ZStackMode::PerSlice),
+            mode => Err(ThymeError::OtherError(format!(
+                "Invalid zstack mode '{}'. Must be one of: error, max-project, per-slice",
+                mode
+            ))),
+        }
+    }
+
+    /// Name of the k-th slice of a stack with the provided file stem
+    ///
+    /// Slices are only named `{stem}_z{k}` when stacks are split per slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `stem` - File stem of the stack
+    /// * `k` - Index of the z-slice
+    pub fn slice_name(&self, stem: &str, k: usize) -> String {
+        match self {
+            ZStackMode::PerSlice => format!("{}_z{}", stem, k),
+            _ => stem.to_string(),
+        }
+    }
+}
+
+impl ThymeMask {
+    /// Collapse z-slices into a single mask using the maximum label at each pixel
+    ///
+    /// # Arguments
+    ///
+    /// * `slices` - Masks with identical dimensions
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::im::ThymeMask;
+    ///
+    /// let slices = vec![
+    ///     ThymeMask::new(2, 1, 1, vec![1, 0]).unwrap(),
+    ///     ThymeMask::new(2, 1, 1, vec![3, 2]).unwrap(),
+    /// ];
+    ///
+    /// let projection = ThymeMask::max_project(&slices).unwrap();
+    /// assert_eq!(projection.as_raw(), &vec![3, 2]);
+    /// ```
+    pub fn max_project(slices: &[ThymeMask]) -> Result<ThymeMask, ThymeError> {
+        let first = slices.first().ok_or(ThymeError::MaskError(
+            "A z-stack must contain at least one slice.",
+        ))?;
+
+        let mut projection = first.as_raw().clone();
+
+        for slice in slices.iter().skip(1) {
+            if slice.width() != first.width() || slice.height() != first.height() {
+                return Err(ThymeError::MaskError(
+                    "All slices of a z-stack must have the same dimensions.",
+                ));
+            }
+
+            projection
+                .iter_mut()
+                .zip(slice.as_raw().iter())
+                .for_each(|(max, &label)| *max = (*max).max(label));
+        }
+
+        ThymeMask::new(first.width(), first.height(), 1, projection)
+    }
+}
+
+#[cfg(feature = "io")]
+impl ThymeMask {
+    /// Open each z-slice of a (Z, H, W) numpy mask stack
+    ///
+    /// Two-dimensional numpy arrays and all other formats are opened as a
+    /// stack with a single slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to a mask with a valid extension
+    ///
+    /// ```no_run
+    /// use thyme_core::im::ThymeMask;
+    /// let slices = ThymeMask::open_stack("mask.npy").unwrap();
+    /// ```
+    pub fn open_stack<P: AsRef<Path>>(path: P) -> Result<Vec<ThymeMask>, ThymeError> {
+        Ok(Self::open_stack_with_summary(path, MaskFormat::Auto)?
+            .into_iter()
+            .map(|(mask, _)| mask)
+            .collect())
+    }
+
+    /// Open a mask using the provided z-stack mode and summarize the labels in each slice
+    ///
+    /// A single mask is returned unless the mode is `PerSlice`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to a mask with a valid extension
+    /// * `mode` - How (Z, H, W) numpy stacks are handled
+    ///
+    /// ```no_run
+    /// use thyme_core::im::{ThymeMask, ZStackMode};
+    /// let slices = ThymeMask::open_zstack("mask.npy", ZStackMode::MaxProject).unwrap();
+    /// assert_eq!(slices.len(), 1);
+    /// ```
+    pub fn open_zstack<P: AsRef<Path>>(
+        path: P,
+        mode: ZStackMode,
+    ) -> Result<Vec<(ThymeMask, MaskSummary)>, ThymeError> {
+        Self::open_zstack_format(path, mode, MaskFormat::Auto)
+    }
+
+    /// Open a mask with the provided layout and z-stack mode and summarize the labels in each slice
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to a mask
+    /// * `mode` - How (Z, H, W) numpy stacks are handled
+    /// * `format` - Layout of the mask file
+    ///
+    /// ```no_run
+    /// use thyme_core::im::{MaskFormat, ThymeMask, ZStackMode};
+    /// let slices =
+    ///     ThymeMask::open_zstack_format("image_seg.npy", ZStackMode::PerSlice, MaskFormat::CellposeSeg)
+    ///         .unwrap();
+    /// ```
+    pub fn open_zstack_format<P: AsRef<Path>>(
+        path: P,
+        mode: ZStackMode,
+        format: MaskFormat,
+    ) -> Result<Vec<(ThymeMask, MaskSummary)>, ThymeError> {
+        match mode {
+            ZStackMode::Error => Ok(vec![Self::open_format_with_summary(path, format)?]),
+            ZStackMode::MaxProject => {
+                let slices = Self::open_stack_with_summary(path, format)?;
+                let dtype_max = slices[0].1.dtype_max;
+
+                let masks: Vec<ThymeMask> = slices.into_iter().map(|(mask, _)| mask).collect();
+                let projection = Self::max_project(&masks)?;
+                let summary = MaskSummary::new(projection.as_raw(), dtype_max);
+
+                Ok(vec![(projection, summary)])
+            }
+            ZStackMode::PerSlice => Self::open_stack_with_summary(path, format),
+        }
+    }
+
+    /// Open each z-slice of a mask stack along with a summary of its labels
+    fn open_stack_with_summary<P: AsRef<Path>>(
+        path: P,
+        format: MaskFormat,
+    ) -> Result<Vec<(ThymeMask, MaskSummary)>, ThymeError> {
+        let Some(bytes) = read_numpy_stack(path.as_ref())? else {
+            return Ok(vec![Self::open_format_with_summary(path, format)?]);
+        };
+
+        let npy = parse_mask_numpy(&bytes, format)?;
+        let shape = npy.shape().to_vec();
+
+        let (z, h, w) = match shape.len() {
+            2 => return Ok(vec![Self::open_format_with_summary(path, format)?]),
+            3 => (shape[0] as usize, shape[1] as u32, shape[2] as u32),
+            _ => {
+                return Err(ThymeError::MaskError(
+                    "Numpy array mask stacks must have a (Z, H, W) shape.",
+                ));
+            }
+        };
+
+        let (pixels, dtype_max): (Vec<u32>, u32) = match npy.dtype() {
+            DType::Plain(x) => match (x.type_char(), x.size_field()) {
+                (TypeChar::Uint, 1) => (
+                    npy.into_vec::<u8>()
+                        .map_err(|_| ThymeError::ImageReadError)?
+                        .into_iter()
+                        .map(|pixel| pixel as u32)
+                        .collect(),
+                    u8::MAX as u32,
+                ),
+                (TypeChar::Uint, 2) => (
+                    npy.into_vec::<u16>()
+                        .map_err(|_| ThymeError::ImageReadError)?
+                        .into_iter()
+                        .map(|pixel| pixel as u32)
+                        .collect(),
+                    u16::MAX as u32,
+                ),
+                (TypeChar::Uint, 4) => (
+                    npy.into_vec::<u32>()
+                        .map_err(|_| ThymeError::ImageReadError)?,
+                    u32::MAX,
+                ),
+                _ => {
+                    return Err(ThymeError::MaskError(
+                        "A numpy mask array with a valid data type was not detected.",
+                    ));
+                }
+            },
+            _ => {
+                return Err(ThymeError::MaskError(
+                    "Only plain numpy mask arrays are currentled supported.",
+                ));
+            }
+        };
+
+        split_stack(pixels, z, w, h, 1)?
+            .into_iter()
+            .map(|mask| {
+                let summary = MaskSummary::new(mask.as_raw(), dtype_max);
+                Ok((mask, summary))
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "io")]
+impl ThymeImage {
+    /// Open each z-slice of a (Z, H, W) or (Z, H, W, C) numpy image stack
+    ///
+    /// Two-dimensional numpy arrays and all other formats are opened as a
+    /// stack with a single slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to an image with a valid extension
+    ///
+    /// ```no_run
+    /// use thyme_core::im::ThymeImage;
+    /// let slices = ThymeImage::open_stack("image.npy").unwrap();
+    /// ```
+    pub fn open_stack<P: AsRef<Path>>(path: P) -> Result<Vec<ThymeImage>, ThymeError> {
+        let Some(bytes) = read_numpy_stack(path.as_ref())? else {
+            return Ok(vec![Self::open(path)?]);
+        };
+
+        let npy = parse_numpy(&bytes)?;
+        let shape = npy.shape().to_vec();
+
+        let (z, h, w, c) = match shape.len() {
+            2 => return Ok(vec![Self::open(path)?]),
+            3 => (shape[0] as usize, shape[1] as u32, shape[2] as u32, 1u32),
+            4 => (
+                shape[0] as usize,
+                shape[1] as u32,
+                shape[2] as u32,
+                shape[3] as u32,
+            ),
+            _ => {
+                return Err(ThymeError::ImageError(
+                    "Numpy array stacks must have a (Z, H, W) or (Z, H, W, C) shape.",
+                ));
+            }
+        };
+
+        macro_rules! split {
+            ($variant:ident, $t:ty) => {
+                split_stack(
+                    npy.into_vec::<$t>()
+                        .map_err(|_| ThymeError::ImageReadError)?,
+                    z,
+                    w,
+                    h,
+                    c,
+                )?
+                .into_iter()
+                .map(ThymeImage::$variant)
+                .collect()
+            };
+        }
+
+        match npy.dtype() {
+            DType::Plain(x) => match (x.type_char(), x.size_field()) {
+                (TypeChar::Uint, 1) => Ok(split!(U8, u8)),
+                (TypeChar::Uint, 2) => Ok(split!(U16, u16)),
+                (TypeChar::Int, 4) => Ok(split!(I32, i32)),
+                (TypeChar::Int, 8) => Ok(split!(I64, i64)),
+                (TypeChar::Float, 4) => Ok(split!(F32, f32)),
+                (TypeChar::Float, 8) => Ok(split!(F64, f64)),
+                _ => Err(ThymeError::ImageError(
+                    "A numpy array with a valid data type was not detected.",
+                )),
+            },
+            _ => Err(ThymeError::ImageError(
+                "Only plain numpy arrays are currentled supported.",
+            )),
+        }
+    }
+}
+
+/// Read the bytes of a numpy file, or `None` if the path is not a numpy file
+#[cfg(feature = "io")]
+fn read_numpy_stack(path: &Path) -> Result<Option<Vec<u8>>, ThymeError> {
+    let is_numpy = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|s| s.eq_ignore_ascii_case("npy"));
+
+    if !is_numpy {
+        return Ok(None);
+    }
+
+    std::fs::read(path)
+        .map(Some)
+        .map_err(|_| ThymeError::ImageReadError)
+}
+
+/// Split a row-major (Z, H, W, C) buffer into one buffer per z-slice
+#[cfg(feature = "io")]
+fn split_stack<T: Clone + ToPrimitive + FromPrimitive>(
+    pixels: Vec<T>,
+    z: usize,
+    w: u32,
+    h: u32,
+    c: u32,
+) -> Result<Vec<ThymeBuffer<T, Vec<T>>>, ThymeError> {
+    let slice_len = (w * h * c) as usize;
+
+    if z == 0 || slice_len == 0 || pixels.len() != z * slice_len {
+        return Err(ThymeError::BufferSizeError);
+    }
+
+    pixels
+        .chunks(slice_len)
+        .map(|slice| ThymeBuffer::new(w, h, c, slice.to_vec()))
+        .collect()
+}
+
+#[cfg(test)]
+#[cfg(feature = "io")]
+mod test {
+
+    use super::*;
+
+    use std::io::Write;
+
+    /// Write a (Z, H, W) numpy array of u16 labels
+    fn write_stack(path: &Path, shape: &[usize], pixels: &[u16]) {
+        let dims: Vec<String> = shape.iter().map(|d| d.to_string()).collect();
+        let mut header = format!(
+            "{{'descr': '<u2', 'fortran_order': False, 'shape': ({},), }}",
+            dims.join(", ")
+        );
+
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+
+        let mut file = std::fs::File::create(path).unwrap();
+        file.write_all(b"\x93NUMPY\x01\x00").unwrap();
+        file.write_all(&(header.len() as u16).to_le_bytes())
+            .unwrap();
+        file.write_all(header.as_bytes()).unwrap();
+        for pixel in pixels {
+            file.write_all(&pixel.to_le_bytes()).unwrap();
+        }
+    }
+
+    /// A (3, 32, 32) stack with one 8x8 square per slice that shifts along x
+    fn square_stack() -> Vec<u16> {
+        let mut pixels = vec![0u16; 3 * 32 * 32];
+
+        for k in 0..3 {
+            for y in 4..12 {
+                for x in (4 + 6 * k)..(12 + 6 * k) {
+                    pixels[k * 32 * 32 + y * 32 + x] = k as u16 + 1;
+                }
+            }
+        }
+
+        pixels
+    }
+
+    #[test]
+    fn test_zstack_mode_parse() {
+        assert_eq!(ZStackMode::parse("error").unwrap(), ZStackMode::Error);
+        assert_eq!(
+            ZStackMode::parse("per-slice").unwrap(),
+            ZStackMode::PerSlice
+        );
+        assert!(ZStackMode::parse("mean-project").is_err());
+        assert_eq!(ZStackMode::default(), ZStackMode::Error);
+    }
+
+    #[test]
+    fn test_zstack_max_project() {
+        let path = std::env::temp_dir().join("thyme_test_zstack_max_project.npy");
+        write_stack(&path, &[3, 32, 32], &square_stack());
+
+        assert!(ThymeMask::open(&path).is_err());
+        assert!(ThymeMask::open_zstack(&path, ZStackMode::Error).is_err());
+
+        let masks = ThymeMask::open_zstack(&path, ZStackMode::MaxProject).unwrap();
+        assert_eq!(masks.len(), 1);
+
+        let (mut mask, summary) = masks.into_iter().next().unwrap();
+        assert_eq!((mask.width(), mask.height()), (32, 32));
+        assert_eq!(summary.labels, 3);
+        assert_eq!(summary.dtype_max, u16::MAX as u32);
+
+        // Overlapping columns keep the largest label
+        assert_eq!(mask.as_raw()[4 * 32 + 4], 1);
+        assert_eq!(mask.as_raw()[4 * 32 + 10], 2);
+        assert_eq!(mask.as_raw()[4 * 32 + 17], 3);
+
+        let labels = mask.label();
+        assert_eq!(mask.areas(&labels), vec![48, 48, 64]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_zstack_per_slice() {
+        let path = std::env::temp_dir().join("thyme_test_zstack_per_slice.npy");
+        write_stack(&path, &[3, 32, 32], &square_stack());
+
+        let masks = ThymeMask::open_zstack(&path, ZStackMode::PerSlice).unwrap();
+        assert_eq!(masks.len(), 3);
+
+        for (k, (mask, summary)) in masks.iter().enumerate() {
+            assert_eq!((mask.width(), mask.height()), (32, 32));
+            assert_eq!(summary.labels, 1);
+            assert_eq!(summary.max_label, k as u32 + 1);
+            assert_eq!(mask.as_raw()[4 * 32 + 4 + 6 * k], k as u32 + 1);
+        }
+
+        let images = ThymeImage::open_stack(&path).unwrap();
+        assert_eq!(images.len(), 3);
+        assert_eq!(images[2].shape(), (32, 32, 1));
+
+        let rgb = std::env::temp_dir().join("thyme_test_zstack_per_slice_rgb.npy");
+        write_stack(&rgb, &[3, 32, 32, 2], &[7u16; 3 * 32 * 32 * 2]);
+
+        let images = ThymeImage::open_stack(&rgb).unwrap();
+        assert_eq!(images.len(), 3);
+        assert_eq!(images[0].shape(), (32, 32, 2));
+
+        // Non-stack inputs are opened as a single slice
+        let masks = ThymeMask::open_stack("../data/tests/test_mask_integer.png").unwrap();
+        assert_eq!(masks.len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&rgb).unwrap();
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(feature = "io")]
+use image::{DynamicImage, ImageFormat};
+
+use crate::error::ThymeError;
+
+/// Extension of the temporary files that outputs are written to before renaming
+pub const TEMPORARY_EXTENSION: &str = "thyme-tmp";
+
+static TEMPORARY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write a file by writing a temporary file and renaming it to the path
+///
+/// The temporary file is created next to the output, so both are on the same
+/// filesystem, with a name unique to this process (e.g.
+/// `.descriptors.csv.4128.0.thyme-tmp`). Once `write` succeeds the file is
+/// flushed and synced to disk before being renamed, so the path either keeps
+/// its previous contents or holds the complete new file. The temporary file
+/// is removed if writing fails.
+///
+/// # Arguments
+///
+/// * `path` - Path to the output file
+/// * `write` - Writes the contents of the file
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::Write;
+/// use thyme_core::error::ThymeError;
+/// use thyme_core::io::write_atomic;
+///
+/// write_atomic("counts.tsv", |file| {
+///     file.write_all(b"image\tobjects\n")
+///         .map_err(|err| ThymeError::OtherError(err.to_string()))
+/// })
+/// .unwrap();
+/// ```
+pub fn write_atomic<P, F>(path: P, write: F) -> Result<(), ThymeError>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), ThymeError>,
+{
+    let path = path.as_ref();
+
+    let (temporary, file) = create_temporary(path).map_err(|err| {
+        ThymeError::OtherError(format!(
+            "Failed to create temporary file for {}. {}",
+            path.display(),
+            err
+        ))
+    })?;
+
+    let mut writer = BufWriter::new(file);
+
+    let result = write(&mut writer).and_then(|_| {
+        let finish = || -> io::Result<()> {
+            writer
+                .into_inner()
+                .map_err(|err| err.into_error())?
+                .sync_all()?;
+            std::fs::rename(&temporary, path)
+        };
+
+        finish().map_err(|err| {
+            ThymeError::OtherError(format!("Failed to write {}. {}", path.display(), err))
+        })
+    });
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+        return result;
+    }
+
+    sync_parent(path);
+
+    Ok(())
+}
+
+/// Write bytes to a file by writing a temporary file and renaming it
+///
+/// # Arguments
+///
+/// * `path` - Path to the output file
+/// * `contents` - Contents of the file
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::io::write_bytes_atomic;
+///
+/// write_bytes_atomic("object_errors.tsv", "a01.png\n").unwrap();
+/// ```
+pub fn write_bytes_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(
+    path: P,
+    contents: C,
+) -> Result<(), ThymeError> {
+    write_atomic(path, |file| {
+        file.write_all(contents.as_ref())
+            .map_err(|err| ThymeError::OtherError(err.to_string()))
+    })
+}
+
+/// Write an image in the format of its path extension (e.g. png) atomically
+///
+/// # Arguments
+///
+/// * `image` - Image to write
+/// * `path` - Path to the output image
+#[cfg(feature = "io")]
+pub(crate) fn write_dynamic_atomic<P: AsRef<Path>>(
+    image: &DynamicImage,
+    path: P,
+) -> Result<(), ThymeError> {
+    let format = ImageFormat::from_path(&path).map_err(|_| ThymeError::ImageExtensionError)?;
+
+    write_atomic(path, |file| {
+        image
+            .write_to(file, format)
+            .map_err(|_| ThymeError::ImageWriteError)
+    })
+}
+
+/// Check if a path is a temporary file left by [`write_atomic`]
+///
+/// # Arguments
+///
+/// * `path` - Path to a file
+pub fn is_temporary_file<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+    hidden
+        && path
+            .extension()
+            .is_some_and(|ext| ext == TEMPORARY_EXTENSION)
+}
+
+/// Remove temporary files left in a directory by interrupted writes
+///
+/// Subdirectories are searched recursively without following symbolic links.
+/// Temporary files are only left behind when a previous run was killed while
+/// writing, so this should only be called on output directories that are not
+/// being written to by another process.
+///
+/// Returns the removed files sorted by path.
+///
+/// # Arguments
+///
+/// * `directory` - Output directory
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::io::remove_temporary_files;
+///
+/// for path in remove_temporary_files("output/").unwrap() {
+///     println!("Removed {}", path.display());
+/// }
+/// ```
+pub fn remove_temporary_files<P: AsRef<Path>>(directory: P) -> Result<Vec<PathBuf>, ThymeError> {
+    let mut removed = Vec::new();
+    let mut directories = vec![directory.as_ref().to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        let entries = std::fs::read_dir(&directory)
+            .map_err(|err| ThymeError::DirError(format!("{}. {}", directory.display(), err)))?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            if file_type.is_dir() {
+                directories.push(path);
+            } else if file_type.is_file() && is_temporary_file(&path) {
+                std::fs::remove_file(&path).map_err(|err| {
+                    ThymeError::OtherError(format!(
+                        "Failed to remove temporary file {}. {}",
+                        path.display(),
+                        err
+                    ))
+                })?;
+
+                removed.push(path);
+            }
+        }
+    }
+
+    removed.sort_unstable();
+
+    Ok(removed)
+}
+
+/// Create a new temporary file next to an output path
+fn create_temporary(path: &Path) -> io::Result<(PathBuf, File)> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?;
+
+    loop {
+        let mut temporary = std::ffi::OsString::from(".");
+        temporary.push(name);
+        temporary.push(format!(
+            ".{}.{}.{}",
+            std::process::id(),
+            TEMPORARY_COUNTER.fetch_add(1, Ordering::Relaxed),
+            TEMPORARY_EXTENSION
+        ));
+
+        let temporary = path.with_file_name(temporary);
+
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temporary)
+        {
+            Ok(file) => return Ok((temporary, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Sync the directory entry of a renamed file where the platform supports it
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        if let Ok(directory) = File::open(parent) {
+            let _ = directory.sync_all();
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("thyme_atomic_{}", name));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn failing(file: &mut BufWriter<File>) -> Result<(), ThymeError> {
+        // Enough bytes to spill the buffer into the temporary file
+        file.write_all(&[b'x'; 64 * 1024]).unwrap();
+        Err(ThymeError::OtherError("Interrupted".to_string()))
+    }
+
+    #[test]
+    fn test_write_atomic_failure() {
+        let directory = directory("failure");
+        let path = directory.join("descriptors.csv");
+
+        assert!(write_atomic(&path, failing).is_err());
+        assert!(!path.exists());
+
+        write_bytes_atomic(&path, "area\n1\n").unwrap();
+        assert!(write_atomic(&path, failing).is_err());
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "area\n1\n");
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_remove_temporary_files() {
+        let directory = directory("remove");
+        std::fs::create_dir(directory.join("descriptors")).unwrap();
+
+        let stale = [
+            directory.join(".descriptors.csv.12.0.thyme-tmp"),
+            directory
+                .join("descriptors")
+                .join(".a01.csv.12.3.thyme-tmp"),
+        ];
+
+        for path in stale.iter() {
+            std::fs::write(path, "area\n").unwrap();
+        }
+
+        for name in ["descriptors.csv", ".hidden", "data.thyme-tmp"] {
+            std::fs::write(directory.join(name), "").unwrap();
+        }
+
+        let mut expected = stale.to_vec();
+        expected.sort_unstable();
+
+        assert_eq!(remove_temporary_files(&directory).unwrap(), expected);
+        assert!(stale.iter().all(|path| !path.exists()));
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 4);
+        assert!(remove_temporary_files(&directory).unwrap().is_empty());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
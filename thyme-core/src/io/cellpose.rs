@@ -0,0 +1,232 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use npyz::{NpyFile, NpyHeader};
+
+use crate::error::ThymeError;
+use crate::io::npy::{check_numpy_header, max_array_bytes};
+use crate::io::pickle::{Value, unpickle};
+
+fn invalid(message: &str) -> ThymeError {
+    ThymeError::NpyError(format!("Invalid Cellpose seg file. {}", message))
+}
+
+/// Header text and pickle stream of a numpy file holding a pickled object
+///
+/// Returns `None` if the file is not a numpy file or holds a plain array.
+fn object_payload(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    if bytes.len() < 10 || &bytes[..6] != b"\x93NUMPY" {
+        return None;
+    }
+
+    let (len, start): (usize, usize) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 => (
+            u32::from_le_bytes(bytes.get(8..12)?.try_into().ok()?) as usize,
+            12,
+        ),
+        _ => return None,
+    };
+
+    let header = std::str::from_utf8(bytes.get(start..start.checked_add(len)?)?).ok()?;
+
+    header
+        .contains("'|O'")
+        .then(|| (header, &bytes[start + len..]))
+}
+
+/// Check if a numpy file holds pickled objects instead of a plain array
+///
+/// Cellpose `_seg.npy` files are numpy files holding a single pickled
+/// dictionary, which cannot be opened as a plain numpy array.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes of a numpy file
+pub fn is_object_numpy(bytes: &[u8]) -> bool {
+    object_payload(bytes).is_some()
+}
+
+/// Parse the masks of a Cellpose `_seg.npy` file
+///
+/// Cellpose saves its results as a numpy file holding a pickled dictionary
+/// with `masks`, `outlines`, `flows`, and metadata. The pickle stream is
+/// evaluated without executing any code and the `masks` array is returned
+/// as a numpy array borrowing its pixels from `bytes`, validated like any
+/// other array parsed with `parse_numpy`. Arrays pickled in C order with the
+/// `_reconstruct` (protocols 2 to 4) or `_frombuffer` (protocol 5) layouts
+/// are supported.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes of a Cellpose `_seg.npy` file
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::io::parse_cellpose_seg;
+///
+/// let bytes = std::fs::read("image_seg.npy").unwrap();
+/// let masks = parse_cellpose_seg(&bytes).unwrap();
+/// println!("{:?}", masks.shape());
+/// ```
+pub fn parse_cellpose_seg(bytes: &[u8]) -> Result<NpyFile<&[u8]>, ThymeError> {
+    let Some((header, stream)) = object_payload(bytes) else {
+        return Err(invalid(
+            "The file is not a numpy file holding a pickled dictionary",
+        ));
+    };
+
+    if !header.contains("'shape': ()") {
+        return Err(invalid("The file does not hold a single dictionary"));
+    }
+
+    let value = unpickle(stream).map_err(|err| invalid(&err.to_string()))?;
+
+    let masks = value
+        .find_dict("masks")
+        .and_then(|dict| dict.get("masks"))
+        .ok_or_else(|| invalid("The dictionary has no 'masks' entry"))?;
+
+    let (descr, shape, data) = pickled_array(masks)?;
+
+    let shape = match shape.as_slice() {
+        [n] => format!("({},)", n),
+        shape => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+
+    let text = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}\n",
+        descr, shape
+    );
+
+    let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+    npy.extend((text.len() as u16).to_le_bytes());
+    npy.extend(text.bytes());
+
+    let header = NpyHeader::from_reader(npy.as_slice())
+        .map_err(|err| invalid(&format!("The masks array is not readable: {}", err)))?;
+
+    check_numpy_header(&header, data.len() as u64, max_array_bytes()?)?;
+
+    Ok(NpyFile::with_header(header, data))
+}
+
+/// Data type, shape, and C-ordered buffer of a pickled numpy array
+fn pickled_array<'a>(value: &Value<'a>) -> Result<(String, Vec<u64>, &'a [u8]), ThymeError> {
+    let not_array = || invalid("The 'masks' entry is not a numpy array");
+
+    let Value::Object { args, state, .. } = value else {
+        return Err(not_array());
+    };
+
+    let (dtype, shape, fortran, data) = match (value.callable_name(), args.as_ref(), state) {
+        // ndarray.__reduce__ reconstructs an empty array and restores its state
+        (Some("_reconstruct"), _, Some(state)) => match state.as_ref() {
+            Value::Tuple(items) if items.len() == 5 => (&items[2], &items[1], &items[3], &items[4]),
+            _ => return Err(not_array()),
+        },
+        // Protocol 5 pickles contiguous arrays as a buffer with a dtype, shape, and order
+        (Some("_frombuffer"), Value::Tuple(items), _) if items.len() == 4 => {
+            let fortran = Value::Bool(matches!(items[3], Value::Str("F")));
+            return array_parts(&items[1], &items[2], &fortran, &items[0]);
+        }
+        _ => return Err(not_array()),
+    };
+
+    array_parts(dtype, shape, fortran, data)
+}
+
+/// Validate the data type, shape, order, and buffer of a pickled array
+fn array_parts<'a>(
+    dtype: &Value<'a>,
+    shape: &Value<'a>,
+    fortran: &Value<'a>,
+    data: &Value<'a>,
+) -> Result<(String, Vec<u64>, &'a [u8]), ThymeError> {
+    // numpy.dtype is reconstructed from its type string and restores its byte order
+    let descr = match (dtype.callable_name(), dtype) {
+        (Some("dtype"), Value::Object { args, state, .. }) => {
+            let kind = match args.as_ref() {
+                Value::Tuple(items) => match items.first() {
+                    Some(Value::Str(kind)) => *kind,
+                    _ => return Err(invalid("The masks data type is not readable")),
+                },
+                _ => return Err(invalid("The masks data type is not readable")),
+            };
+
+            let order = match state.as_deref() {
+                Some(Value::Tuple(items)) => match items.get(1) {
+                    Some(Value::Str(">")) => ">",
+                    Some(Value::Str("|")) => "|",
+                    _ => "<",
+                },
+                _ => "<",
+            };
+
+            format!("{}{}", order, kind)
+        }
+        _ => return Err(invalid("The masks data type is not readable")),
+    };
+
+    let shape = match shape {
+        Value::Tuple(items) => items
+            .iter()
+            .map(|n| match n {
+                Value::Int(n) if *n >= 0 => Ok(*n as u64),
+                _ => Err(invalid("The masks shape is not readable")),
+            })
+            .collect::<Result<Vec<u64>, ThymeError>>()?,
+        _ => return Err(invalid("The masks shape is not readable")),
+    };
+
+    if matches!(fortran, Value::Bool(true)) {
+        return Err(invalid(
+            "Fortran-ordered masks are not supported. Save the masks in C order",
+        ));
+    }
+
+    match data {
+        Value::Bytes(data) => Ok((descr, shape, data)),
+        _ => Err(invalid("The masks buffer is not readable")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const TEST_SEG: &str = "../data/tests/test_mask_cellpose_seg.npy";
+    const TEST_PLAIN: &str = "../data/tests/test_mask_cellpose.npy";
+
+    #[test]
+    fn test_parse_cellpose_seg() {
+        let bytes = std::fs::read(TEST_SEG).unwrap();
+        assert!(is_object_numpy(&bytes));
+
+        let masks = parse_cellpose_seg(&bytes).unwrap();
+        assert_eq!(masks.shape(), [48, 64]);
+
+        let plain = std::fs::read(TEST_PLAIN).unwrap();
+        assert!(!is_object_numpy(&plain));
+        assert!(parse_cellpose_seg(&plain).is_err());
+
+        let plain = crate::io::parse_numpy(&plain).unwrap();
+        assert_eq!(
+            masks.into_vec::<u16>().unwrap(),
+            plain.into_vec::<u16>().unwrap()
+        );
+
+        // Truncated files fail without panicking
+        assert!(parse_cellpose_seg(&bytes[..bytes.len() / 2]).is_err());
+        assert!(parse_cellpose_seg(&bytes[..8]).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use zarrs::array::codec::GzipCodec;
+use zarrs::array::{Array, ArrayBuilder, DataType, FillValue};
+use zarrs::filesystem::FilesystemStore;
+use zarrs::group::GroupBuilder;
+use zarrs::storage::ReadableWritableListableStorage;
+
+use crate::error::ThymeError;
+use crate::im::{ThymeBuffer, ThymeImage};
+
+/// A zarr hierarchy storing object crops as individual arrays
+///
+/// Crops are stored as (height, width, channels) arrays in a single gzip
+/// compressed chunk with their data type preserved. Crop paths are split
+/// on `/` into nested groups (e.g. `complete/plate1_A01/3`).
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::im::ThymeImage;
+/// use thyme_core::io::ZarrCrops;
+///
+/// let image = ThymeImage::open("image.png").unwrap();
+///
+/// let mut crops = ZarrCrops::create("crops.zarr", 5).unwrap();
+/// crops.write("complete/image/0", &image.crop(0, 0, 10, 10).unwrap()).unwrap();
+///
+/// let crop = crops.read("complete/image/0").unwrap();
+/// ```
+pub struct ZarrCrops {
+    store: ReadableWritableListableStorage,
+    gzip_compression: u32,
+    groups: HashSet<String>,
+}
+
+impl ZarrCrops {
+    /// Create a new zarr hierarchy for storing crops
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the zarr hierarchy (e.g. crops.zarr)
+    /// * `gzip_compression` - Gzip compression level (0 - 9)
+    pub fn create<P: AsRef<Path>>(path: P, gzip_compression: u32) -> Result<Self, ThymeError> {
+        if gzip_compression > 9 {
+            return Err(ThymeError::OtherError(
+                "Gzip compression level must be in [0, 9]".to_string(),
+            ));
+        }
+
+        let mut crops = Self::open(path)?;
+        crops.gzip_compression = gzip_compression;
+        crops.create_group("")?;
+
+        Ok(crops)
+    }
+
+    /// Open an existing zarr hierarchy of crops
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the zarr hierarchy (e.g. crops.zarr)
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ThymeError> {
+        let store = FilesystemStore::new(path.as_ref())
+            .map_err(|err| ThymeError::DirError(format!("Failed to open zarr store. {}", err)))?;
+
+        Ok(Self {
+            store: Arc::new(store),
+            gzip_compression: 5,
+            groups: HashSet::new(),
+        })
+    }
+
+    /// Write a crop to the provided path within the hierarchy
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the crop within the hierarchy (e.g. complete/plate1_A01/3)
+    /// * `image` - Crop to store
+    pub fn write(&mut self, path: &str, image: &ThymeImage) -> Result<(), ThymeError> {
+        let path = path.trim_matches('/');
+
+        let groups: Vec<&str> = path.split('/').collect();
+        for depth in 1..groups.len() {
+            self.create_group(&groups[..depth].join("/"))?;
+        }
+
+        let (data_type, fill_value) = match image {
+            ThymeImage::U8(_) => (DataType::UInt8, FillValue::from(0u8)),
+            ThymeImage::U16(_) => (DataType::UInt16, FillValue::from(0u16)),
+            ThymeImage::U32(_) => (DataType::UInt32, FillValue::from(0u32)),
+            ThymeImage::U64(_) => (DataType::UInt64, FillValue::from(0u64)),
+            ThymeImage::I32(_) => (DataType::Int32, FillValue::from(0i32)),
+            ThymeImage::I64(_) => (DataType::Int64, FillValue::from(0i64)),
+            ThymeImage::F32(_) => (DataType::Float32, FillValue::from(0f32)),
+            ThymeImage::F64(_) => (DataType::Float64, FillValue::from(0f64)),
+        };
+
+        let shape = vec![
+            image.height() as u64,
+            image.width() as u64,
+            image.channels() as u64,
+        ];
+
+        let mut builder = ArrayBuilder::new(
+            shape.clone(),
+            data_type,
+            shape.try_into().map_err(|_| ThymeError::ImageWriteError)?,
+            fill_value,
+        );
+
+        builder.dimension_names(["y", "x", "c"].into());
+
+        if self.gzip_compression > 0 {
+            builder.bytes_to_bytes_codecs(vec![Arc::new(
+                GzipCodec::new(self.gzip_compression).map_err(|_| ThymeError::ImageWriteError)?,
+            )]);
+        }
+
+        let array = builder
+            .build(self.store.clone(), &format!("/{}", path))
+            .map_err(|_| ThymeError::ImageWriteError)?;
+
+        array
+            .store_metadata()
+            .map_err(|_| ThymeError::ImageWriteError)?;
+
+        let origin = [0, 0, 0];
+
+        match image {
+            ThymeImage::U8(buffer) => array.store_chunk_elements(&origin, buffer.as_raw()),
+            ThymeImage::U16(buffer) => array.store_chunk_elements(&origin, buffer.as_raw()),
+            ThymeImage::U32(buffer) => array.store_chunk_elements(&origin, buffer.as_raw()),
+            ThymeImage::U64(buffer) => array.store_chunk_elements(&origin, buffer.as_raw()),
+            ThymeImage::I32(buffer) => array.store_chunk_elements(&origin, buffer.as_raw()),
+            ThymeImage::I64(buffer) => array.store_chunk_elements(&origin, buffer.as_raw()),
+            ThymeImage::F32(buffer) => array.store_chunk_elements(&origin, buffer.as_raw()),
+            ThymeImage::F64(buffer) => array.store_chunk_elements(&origin, buffer.as_raw()),
+        }
+        .map_err(|_| ThymeError::ImageWriteError)
+    }
+
+    /// Read a crop from the provided path within the hierarchy
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the crop within the hierarchy (e.g. complete/plate1_A01/3)
+    pub fn read(&self, path: &str) -> Result<ThymeImage, ThymeError> {
+        let array = Array::open(self.store.clone(), &format!("/{}", path.trim_matches('/')))
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        let (height, width, channels) = match array.shape() {
+            &[h, w, c] => (h as u32, w as u32, c as u32),
+            _ => return Err(ThymeError::ImageReadError),
+        };
+
+        macro_rules! retrieve {
+            ($variant:ident, $t:ty) => {
+                ThymeImage::$variant(ThymeBuffer::new(
+                    width,
+                    height,
+                    channels,
+                    array
+                        .retrieve_chunk_elements::<$t>(&[0, 0, 0])
+                        .map_err(|_| ThymeError::ImageReadError)?,
+                )?)
+            };
+        }
+
+        Ok(match array.data_type() {
+            DataType::UInt8 => retrieve!(U8, u8),
+            DataType::UInt16 => retrieve!(U16, u16),
+            DataType::UInt32 => retrieve!(U32, u32),
+            DataType::UInt64 => retrieve!(U64, u64),
+            DataType::Int32 => retrieve!(I32, i32),
+            DataType::Int64 => retrieve!(I64, i64),
+            DataType::Float32 => retrieve!(F32, f32),
+            DataType::Float64 => retrieve!(F64, f64),
+            _ => return Err(ThymeError::ImageFormatError),
+        })
+    }
+
+    /// Store group metadata for the provided path if it has not been stored
+    fn create_group(&mut self, path: &str) -> Result<(), ThymeError> {
+        if self.groups.contains(path) {
+            return Ok(());
+        }
+
+        GroupBuilder::new()
+            .build(self.store.clone(), &format!("/{}", path))
+            .and_then(|group| group.store_metadata().map_err(Into::into))
+            .map_err(|_| ThymeError::DirError(format!("Failed to create zarr group /{}", path)))?;
+
+        self.groups.insert(path.to_string());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const TEST_IMAGE: &str = "../data/tests/test_rgb.png";
+
+    #[test]
+    fn test_write_read_crops() {
+        const OUTPUT: &str = "TEST_CROPS_WRITE.zarr";
+        const FILES: &str = "TEST_CROPS_WRITE_FILES";
+
+        let _ = std::fs::remove_dir_all(OUTPUT);
+        let _ = std::fs::remove_dir_all(FILES);
+        std::fs::create_dir(FILES).unwrap();
+
+        let image = ThymeImage::open(TEST_IMAGE).unwrap();
+
+        let mut crops = ZarrCrops::create(OUTPUT, 5).unwrap();
+
+        for idx in 0..5u32 {
+            let crop = image.crop(idx * 3, idx * 2, 10 + idx, 8 + idx).unwrap();
+
+            crops
+                .write(&format!("complete/test_rgb/{}", idx), &crop)
+                .unwrap();
+
+            crop.save(format!("{}/test_rgb_{}.png", FILES, idx))
+                .unwrap();
+        }
+
+        let crops = ZarrCrops::open(OUTPUT).unwrap();
+
+        for idx in 0..5u32 {
+            let crop = crops.read(&format!("complete/test_rgb/{}", idx)).unwrap();
+
+            let file = ThymeImage::open(format!("{}/test_rgb_{}.png", FILES, idx)).unwrap();
+
+            assert_eq!(crop.shape(), file.shape());
+
+            match (crop, file) {
+                (ThymeImage::U8(a), ThymeImage::U8(b)) => assert_eq!(a.as_raw(), b.as_raw()),
+                (ThymeImage::U16(a), ThymeImage::U16(b)) => assert_eq!(a.as_raw(), b.as_raw()),
+                _ => panic!("Crop data type was not preserved"),
+            }
+        }
+
+        assert!(crops.read("complete/test_rgb/5").is_err());
+
+        std::fs::remove_dir_all(OUTPUT).unwrap();
+        std::fs::remove_dir_all(FILES).unwrap();
+    }
+
+    #[test]
+    fn test_write_read_dtypes() {
+        const OUTPUT: &str = "TEST_CROPS_DTYPES.zarr";
+
+        let _ = std::fs::remove_dir_all(OUTPUT);
+
+        let mut crops = ZarrCrops::create(OUTPUT, 0).unwrap();
+
+        let u16 = ThymeImage::U16(ThymeBuffer::new(2, 1, 1, vec![300u16, 65535]).unwrap());
+        let f32 = ThymeImage::F32(ThymeBuffer::new(1, 1, 2, vec![0.5f32, -1.0]).unwrap());
+
+        crops.write("a/u16", &u16).unwrap();
+        crops.write("b/f32", &f32).unwrap();
+
+        match crops.read("a/u16").unwrap() {
+            ThymeImage::U16(buffer) => assert_eq!(buffer.as_raw(), &vec![300, 65535]),
+            _ => panic!("Expected u16 crop"),
+        }
+
+        match crops.read("b/f32").unwrap() {
+            ThymeImage::F32(buffer) => assert_eq!(buffer.as_raw(), &vec![0.5, -1.0]),
+            _ => panic!("Expected f32 crop"),
+        }
+
+        std::fs::remove_dir_all(OUTPUT).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use polars::prelude::*;
+
+use crate::constant;
+use crate::error::ThymeError;
+
+/// Encoding of object polygons in a table column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GeometryFormat {
+    /// Nested list of (x, y) vertices (`List<List<Float32>>`)
+    #[default]
+    List,
+    /// Well-known binary polygons with f64 vertices (e.g. for GeoPandas)
+    Wkb,
+}
+
+impl GeometryFormat {
+    /// Parse a geometry format (list or wkb)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::io::GeometryFormat;
+    ///
+    /// assert_eq!(GeometryFormat::parse("wkb").unwrap(), GeometryFormat::Wkb);
+    /// assert!(GeometryFormat::parse("geojson").is_err());
+    /// ```
+    pub fn parse(format: &str) -> Result<Self, ThymeError> {
+        match format {
+            "list" => Ok(GeometryFormat::List),
+            "wkb" => Ok(GeometryFormat::Wkb),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid geometry_format {}. Must be one of: {:?}",
+                format,
+                constant::SUPPORTED_GEOMETRY_FORMATS
+            ))),
+        }
+    }
+}
+
+/// Encode a polygon as a well-known binary (WKB) polygon with a single ring
+///
+/// Vertices are written as little-endian f64 coordinates and the ring is
+/// closed by repeating the first vertex if it is not already closed.
+///
+/// # Arguments
+///
+/// * `points` - Vertices (x, y) of the polygon
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::io::polygon_wkb;
+///
+/// let wkb = polygon_wkb(&[[0., 0.], [2., 0.], [2., 2.]]);
+///
+/// // Byte order, geometry type, ring count, point count, and 4 f64 vertices
+/// assert_eq!(wkb.len(), 1 + 4 + 4 + 4 + 4 * 16);
+/// assert_eq!(wkb[..5], [1, 3, 0, 0, 0]);
+/// ```
+pub fn polygon_wkb(points: &[[f32; 2]]) -> Vec<u8> {
+    let closed = points.is_empty() || points.first() == points.last();
+    let n = points.len() + !closed as usize;
+
+    let mut wkb = Vec::with_capacity(13 + 16 * n);
+    wkb.push(1);
+    wkb.extend(3u32.to_le_bytes());
+    wkb.extend(1u32.to_le_bytes());
+    wkb.extend((n as u32).to_le_bytes());
+
+    for [x, y] in points.iter().chain(points.first().filter(|_| !closed)) {
+        wkb.extend((*x as f64).to_le_bytes());
+        wkb.extend((*y as f64).to_le_bytes());
+    }
+
+    wkb
+}
+
+/// Assemble object polygons into a table column
+///
+/// List columns hold the vertices of each polygon as `[x, y]` lists and WKB
+/// columns hold a well-known binary polygon for each object. Objects without
+/// a polygon are null. Nested and binary columns can only be written to
+/// parquet and arrow tables.
+///
+/// # Arguments
+///
+/// * `name` - Name of the column
+/// * `polygons` - Vertices (x, y) of the polygon of each object
+/// * `format` - Encoding of the polygons
+///
+/// # Examples
+///
+/// ```
+/// use polars::prelude::*;
+/// use thyme_core::io::{GeometryFormat, polygon_column};
+///
+/// let polygons = vec![Some(vec![[0., 0.], [2., 0.], [2., 2.]]), None];
+/// let column = polygon_column("polygon", &polygons, GeometryFormat::List);
+///
+/// assert_eq!(column.len(), 2);
+/// assert_eq!(column.null_count(), 1);
+/// assert_eq!(
+///     column.dtype(),
+///     &DataType::List(Box::new(DataType::List(Box::new(DataType::Float32))))
+/// );
+/// ```
+pub fn polygon_column(
+    name: &str,
+    polygons: &[Option<Vec<[f32; 2]>>],
+    format: GeometryFormat,
+) -> Column {
+    match format {
+        GeometryFormat::List => {
+            let dtype = DataType::List(Box::new(DataType::List(Box::new(DataType::Float32))));
+
+            let polygons: ListChunked = polygons
+                .iter()
+                .map(|points| {
+                    points.as_ref().map(|points| {
+                        points
+                            .iter()
+                            .map(|point| Some(Series::new(PlSmallStr::EMPTY, point.as_slice())))
+                            .collect::<ListChunked>()
+                            .into_series()
+                    })
+                })
+                .collect();
+
+            // Columns without any vertices are cast so that every table has the same schema
+            polygons
+                .with_name(name.into())
+                .into_series()
+                .cast(&dtype)
+                .unwrap()
+                .into_column()
+        }
+        GeometryFormat::Wkb => BinaryChunked::from_iter_options(
+            name.into(),
+            polygons
+                .iter()
+                .map(|points| points.as_deref().map(polygon_wkb)),
+        )
+        .into_column(),
+    }
+}
+
+/// Check that a table has no nested or binary columns before writing it as text
+///
+/// # Arguments
+///
+/// * `df` - A DataFrame
+/// * `format` - Name of the text table format (e.g. CSV)
+pub(crate) fn check_flat(df: &DataFrame, format: &str) -> Result<(), ThymeError> {
+    match df
+        .get_columns()
+        .iter()
+        .find(|column| matches!(column.dtype(), DataType::List(_) | DataType::Binary))
+    {
+        Some(column) => Err(ThymeError::OtherError(format!(
+            "Column {} holds nested or binary values that cannot be written to a {} file. Write the table as parquet instead",
+            column.name(),
+            format
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_polygon_column() {
+        let polygons = vec![
+            Some(vec![[0.0, 0.0], [4.0, 0.0], [4.0, 3.0]]),
+            None,
+            Some(vec![[1.0, 1.0], [2.0, 1.0], [2.0, 2.0], [1.0, 1.0]]),
+        ];
+
+        let column = polygon_column("polygon", &polygons, GeometryFormat::List);
+        let list = column.list().unwrap();
+
+        let points: Vec<Vec<f32>> = list
+            .get_as_series(0)
+            .unwrap()
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|point| point.unwrap().f32().unwrap().into_no_null_iter().collect())
+            .collect();
+
+        assert_eq!(points, [[0.0, 0.0], [4.0, 0.0], [4.0, 3.0]]);
+        assert!(list.get_as_series(1).is_none());
+
+        // Open rings are closed and closed rings are written as is
+        let column = polygon_column("polygon", &polygons, GeometryFormat::Wkb);
+        let wkb = column.binary().unwrap();
+
+        assert_eq!(wkb.get(0).unwrap().len(), 13 + 4 * 16);
+        assert_eq!(wkb.get(1), None);
+        assert_eq!(wkb.get(2).unwrap().len(), 13 + 4 * 16);
+        assert_eq!(wkb.get(0).unwrap()[13..29], wkb.get(0).unwrap()[61..]);
+
+        let x = f64::from_le_bytes(wkb.get(0).unwrap()[29..37].try_into().unwrap());
+        assert_eq!(x, 4.0);
+
+        // Columns without polygons share the schema of other tables
+        let column = polygon_column("polygon", &[None], GeometryFormat::List);
+        assert_eq!(
+            column.dtype(),
+            &DataType::List(Box::new(DataType::List(Box::new(DataType::Float32))))
+        );
+
+        let df = DataFrame::new(vec![column]).unwrap();
+        assert!(check_flat(&df, "CSV").is_err());
+    }
+}
@@ -0,0 +1,346 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use hdf5_pure::{DType, DatasetBuilder, File, FileBuilder, GroupBuilder};
+
+use crate::error::ThymeError;
+use crate::im::{ThymeBuffer, ThymeImage};
+
+/// An HDF5 file storing object crops as individual datasets
+///
+/// Crops are stored as (height, width, channels) datasets in a single deflate
+/// compressed chunk with their data type preserved. Crop paths are split on
+/// `/` into nested groups (e.g. `complete/plate1_A01/3`).
+///
+/// HDF5 files are written in a single pass, so crops are held in memory until
+/// `finish` writes the file. The file is written atomically, and a run that
+/// fails before `finish` leaves no partial file behind.
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::im::ThymeImage;
+/// use thyme_core::io::Hdf5Crops;
+///
+/// let image = ThymeImage::open("image.png").unwrap();
+///
+/// let mut crops = Hdf5Crops::create("crops.h5", 5).unwrap();
+/// crops.write("complete/image/0", &image.crop(0, 0, 10, 10).unwrap()).unwrap();
+/// crops.finish().unwrap();
+///
+/// let crop = Hdf5Crops::open("crops.h5").unwrap().read("complete/image/0").unwrap();
+/// ```
+pub struct Hdf5Crops {
+    path: PathBuf,
+    deflate_compression: u32,
+    crops: BTreeMap<String, ThymeImage>,
+    file: Option<File>,
+}
+
+/// Crops and subgroups of a group in the HDF5 file
+#[derive(Default)]
+struct Node<'a> {
+    crops: Vec<(&'a str, &'a ThymeImage)>,
+    groups: BTreeMap<&'a str, Node<'a>>,
+}
+
+impl<'a> Node<'a> {
+    /// Arrange crops into nested groups by their paths
+    fn new(crops: &'a BTreeMap<String, ThymeImage>) -> Self {
+        let mut root = Node::default();
+
+        for (path, image) in crops {
+            let mut node = &mut root;
+            let mut names = path.split('/').peekable();
+
+            while let Some(name) = names.next() {
+                match names.peek() {
+                    Some(_) => node = node.groups.entry(name).or_default(),
+                    None => node.crops.push((name, image)),
+                }
+            }
+        }
+
+        root
+    }
+
+    /// Add the crops and subgroups of the node to the root of the file
+    fn build_file(&self, builder: &mut FileBuilder, level: u32) {
+        for (name, image) in &self.crops {
+            dataset(builder.create_dataset(name), image, level);
+        }
+
+        for (name, node) in &self.groups {
+            let mut group = builder.create_group(name);
+            node.build_group(&mut group, level);
+            builder.add_group(group.finish());
+        }
+    }
+
+    /// Add the crops and subgroups of the node to a group
+    fn build_group(&self, builder: &mut GroupBuilder, level: u32) {
+        for (name, image) in &self.crops {
+            dataset(builder.create_dataset(name), image, level);
+        }
+
+        for (name, node) in &self.groups {
+            let mut group = builder.create_group(name);
+            node.build_group(&mut group, level);
+            builder.add_group(group.finish());
+        }
+    }
+}
+
+/// Store a crop in a dataset with its data type preserved
+fn dataset(builder: &mut DatasetBuilder, image: &ThymeImage, level: u32) {
+    match image {
+        ThymeImage::U8(buffer) => builder.with_data(buffer.as_raw()),
+        ThymeImage::U16(buffer) => builder.with_data(buffer.as_raw()),
+        ThymeImage::U32(buffer) => builder.with_data(buffer.as_raw()),
+        ThymeImage::U64(buffer) => builder.with_data(buffer.as_raw()),
+        ThymeImage::I32(buffer) => builder.with_data(buffer.as_raw()),
+        ThymeImage::I64(buffer) => builder.with_data(buffer.as_raw()),
+        ThymeImage::F32(buffer) => builder.with_data(buffer.as_raw()),
+        ThymeImage::F64(buffer) => builder.with_data(buffer.as_raw()),
+    };
+
+    let shape = [
+        image.height() as u64,
+        image.width() as u64,
+        image.channels() as u64,
+    ];
+
+    builder.with_shape(&shape);
+
+    if level > 0 && shape.iter().all(|&n| n > 0) {
+        builder.with_chunks(&shape).with_deflate(level);
+    }
+}
+
+impl Hdf5Crops {
+    /// Create a new HDF5 file for storing crops
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the HDF5 file (e.g. crops.h5)
+    /// * `deflate_compression` - Deflate compression level (0 - 9)
+    pub fn create<P: AsRef<Path>>(path: P, deflate_compression: u32) -> Result<Self, ThymeError> {
+        if deflate_compression > 9 {
+            return Err(ThymeError::OtherError(
+                "Deflate compression level must be in [0, 9]".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            deflate_compression,
+            crops: BTreeMap::new(),
+            file: None,
+        })
+    }
+
+    /// Open an existing HDF5 file of crops
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the HDF5 file (e.g. crops.h5)
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ThymeError> {
+        let file = File::open_streaming(path.as_ref()).map_err(|err| {
+            ThymeError::OtherError(format!(
+                "Failed to open HDF5 file {}. {}",
+                path.as_ref().display(),
+                err
+            ))
+        })?;
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            deflate_compression: 5,
+            crops: BTreeMap::new(),
+            file: Some(file),
+        })
+    }
+
+    /// Stage a crop at the provided path within the file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the crop within the file (e.g. complete/plate1_A01/3)
+    /// * `image` - Crop to store
+    pub fn write(&mut self, path: &str, image: &ThymeImage) -> Result<(), ThymeError> {
+        let path = path.trim_matches('/');
+
+        if path.split('/').any(|name| name.is_empty() || name == ".") {
+            return Err(ThymeError::OtherError(format!(
+                "Invalid crop path '{}' in HDF5 file",
+                path
+            )));
+        }
+
+        if self.crops.contains_key(path) {
+            return Err(ThymeError::OtherError(format!(
+                "Crop '{}' was already written to the HDF5 file",
+                path
+            )));
+        }
+
+        self.crops.insert(path.to_string(), image.clone());
+
+        Ok(())
+    }
+
+    /// Write the staged crops to the HDF5 file
+    ///
+    /// Returns the number of crops written.
+    pub fn finish(&mut self) -> Result<usize, ThymeError> {
+        let mut builder = FileBuilder::new();
+        Node::new(&self.crops).build_file(&mut builder, self.deflate_compression);
+
+        let bytes = builder.finish().map_err(|err| {
+            ThymeError::OtherError(format!("Failed to write HDF5 crops. {}", err))
+        })?;
+
+        crate::io::write_bytes_atomic(&self.path, bytes)?;
+
+        let n = self.crops.len();
+        self.crops.clear();
+
+        Ok(n)
+    }
+
+    /// Read a crop from the provided path within the file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the crop within the file (e.g. complete/plate1_A01/3)
+    pub fn read(&self, path: &str) -> Result<ThymeImage, ThymeError> {
+        let file = self.file.as_ref().ok_or(ThymeError::ImageReadError)?;
+
+        let dataset = file
+            .dataset(path.trim_matches('/'))
+            .map_err(|_| ThymeError::ImageReadError)?;
+
+        let (height, width, channels) = match dataset.shape().as_deref() {
+            Ok(&[h, w, c]) => (h as u32, w as u32, c as u32),
+            _ => return Err(ThymeError::ImageReadError),
+        };
+
+        macro_rules! retrieve {
+            ($variant:ident, $t:ty) => {
+                ThymeImage::$variant(ThymeBuffer::new(
+                    width,
+                    height,
+                    channels,
+                    dataset
+                        .read::<$t>()
+                        .map_err(|_| ThymeError::ImageReadError)?,
+                )?)
+            };
+        }
+
+        Ok(
+            match dataset.dtype().map_err(|_| ThymeError::ImageReadError)? {
+                DType::U8 => retrieve!(U8, u8),
+                DType::U16 => retrieve!(U16, u16),
+                DType::U32 => retrieve!(U32, u32),
+                DType::U64 => retrieve!(U64, u64),
+                DType::I32 => retrieve!(I32, i32),
+                DType::I64 => retrieve!(I64, i64),
+                DType::F32 => retrieve!(F32, f32),
+                DType::F64 => retrieve!(F64, f64),
+                _ => return Err(ThymeError::ImageFormatError),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const TEST_IMAGE: &str = "../data/tests/test_rgb.png";
+
+    #[test]
+    fn test_write_read_crops() {
+        const OUTPUT: &str = "TEST_CROPS_WRITE.h5";
+        const FILES: &str = "TEST_CROPS_WRITE_H5_FILES";
+
+        let _ = std::fs::remove_file(OUTPUT);
+        let _ = std::fs::remove_dir_all(FILES);
+        std::fs::create_dir(FILES).unwrap();
+
+        let image = ThymeImage::open(TEST_IMAGE).unwrap();
+
+        let mut crops = Hdf5Crops::create(OUTPUT, 5).unwrap();
+
+        for idx in 0..5u32 {
+            let crop = image.crop(idx * 3, idx * 2, 10 + idx, 8 + idx).unwrap();
+
+            crops
+                .write(&format!("complete/test_rgb/{}", idx), &crop)
+                .unwrap();
+
+            crop.save(format!("{}/test_rgb_{}.png", FILES, idx))
+                .unwrap();
+        }
+
+        assert!(crops.write("complete/test_rgb/0", &image).is_err());
+        assert!(!Path::new(OUTPUT).exists());
+        assert_eq!(crops.finish().unwrap(), 5);
+
+        let crops = Hdf5Crops::open(OUTPUT).unwrap();
+
+        for idx in 0..5u32 {
+            let crop = crops.read(&format!("complete/test_rgb/{}", idx)).unwrap();
+
+            let file = ThymeImage::open(format!("{}/test_rgb_{}.png", FILES, idx)).unwrap();
+
+            assert_eq!(crop.shape(), file.shape());
+
+            match (crop, file) {
+                (ThymeImage::U8(a), ThymeImage::U8(b)) => assert_eq!(a.as_raw(), b.as_raw()),
+                (ThymeImage::U16(a), ThymeImage::U16(b)) => assert_eq!(a.as_raw(), b.as_raw()),
+                _ => panic!("Crop data type was not preserved"),
+            }
+        }
+
+        assert!(crops.read("complete/test_rgb/5").is_err());
+
+        std::fs::remove_file(OUTPUT).unwrap();
+        std::fs::remove_dir_all(FILES).unwrap();
+    }
+
+    #[test]
+    fn test_write_read_dtypes() {
+        const OUTPUT: &str = "TEST_CROPS_DTYPES.h5";
+
+        let _ = std::fs::remove_file(OUTPUT);
+
+        let mut crops = Hdf5Crops::create(OUTPUT, 0).unwrap();
+
+        let u16 = ThymeImage::U16(ThymeBuffer::new(2, 1, 1, vec![300u16, 65535]).unwrap());
+        let f32 = ThymeImage::F32(ThymeBuffer::new(1, 1, 2, vec![0.5f32, -1.0]).unwrap());
+
+        crops.write("a/u16", &u16).unwrap();
+        crops.write("f32", &f32).unwrap();
+        crops.finish().unwrap();
+
+        let crops = Hdf5Crops::open(OUTPUT).unwrap();
+
+        match crops.read("a/u16").unwrap() {
+            ThymeImage::U16(buffer) => assert_eq!(buffer.as_raw(), &vec![300, 65535]),
+            _ => panic!("Expected u16 crop"),
+        }
+
+        match crops.read("f32").unwrap() {
+            ThymeImage::F32(buffer) => assert_eq!(buffer.as_raw(), &vec![0.5, -1.0]),
+            _ => panic!("Expected f32 crop"),
+        }
+
+        std::fs::remove_file(OUTPUT).unwrap();
+    }
+}
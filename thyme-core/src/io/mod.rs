@@ -1,10 +1,90 @@
+mod atomic;
+#[cfg(feature = "io")]
+mod cellpose;
+#[cfg(feature = "zarr")]
+mod container;
+#[cfg(feature = "io")]
+mod geometry;
+#[cfg(feature = "hdf5")]
+mod hdf5;
+#[cfg(feature = "io")]
 mod npy;
+#[cfg(feature = "io")]
+mod pickle;
+#[cfg(feature = "io")]
+mod png_text;
+#[cfg(feature = "io")]
+mod probe;
+#[cfg(feature = "io")]
+mod stream;
+#[cfg(feature = "io")]
 mod table;
+#[cfg(feature = "io")]
+mod tif;
+#[cfg(all(test, feature = "io"))]
+pub(crate) mod tracked;
 
-pub use npy::write_embeddings_npz;
+#[cfg(feature = "io")]
+pub(crate) use atomic::write_dynamic_atomic;
+pub use atomic::{TEMPORARY_EXTENSION, is_temporary_file, remove_temporary_files};
+pub use atomic::{write_atomic, write_bytes_atomic};
+
+#[cfg(feature = "io")]
+pub use cellpose::{is_object_numpy, parse_cellpose_seg};
+
+#[cfg(feature = "zarr")]
+pub use container::ZarrCrops;
+
+#[cfg(feature = "hdf5")]
+pub use hdf5::Hdf5Crops;
+
+#[cfg(feature = "io")]
+pub use geometry::{GeometryFormat, polygon_column, polygon_wkb};
+
+#[cfg(feature = "io")]
 pub use npy::write_numpy;
+#[cfg(feature = "io")]
+pub use npy::{EmbeddingDtype, NPZ_FORMAT_VERSION, NpzCompression, NpzOptions};
+#[cfg(feature = "io")]
+pub use npy::{EmbeddingsNpz, read_embeddings_npz, read_numpy_f32};
+#[cfg(feature = "io")]
+pub use npy::{
+    MAX_ARRAY_BYTES, MAX_ARRAY_BYTES_VAR, check_numpy_header, max_array_bytes, parse_numpy,
+};
+#[cfg(feature = "io")]
+pub use npy::{write_embeddings_npz, write_embeddings_npz_with};
 
+#[cfg(feature = "io")]
+pub use png_text::read_png_text;
+#[cfg(feature = "io")]
+pub(crate) use png_text::write_png_text;
+
+#[cfg(feature = "io")]
+pub use probe::{ImageInfo, probe_image, probe_levels};
+
+#[cfg(feature = "io")]
+pub use stream::PolygonStream;
+
+#[cfg(feature = "io")]
+pub use table::FloatFormat;
+#[cfg(feature = "io")]
+pub use table::join_metadata;
+#[cfg(feature = "io")]
+pub use table::read_table;
+#[cfg(feature = "io")]
 pub use table::write_table;
-pub use table::write_table_csv;
+#[cfg(feature = "io")]
+pub use table::write_table_ipc;
+#[cfg(feature = "io")]
 pub use table::write_table_pq;
-pub use table::write_table_tsv;
+#[cfg(feature = "io")]
+pub use table::{write_table_csv, write_table_csv_with_format};
+#[cfg(feature = "io")]
+pub use table::{write_table_tsv, write_table_tsv_with_format};
+#[cfg(feature = "io")]
+pub use table::{write_table_with_compression, write_table_with_float_format};
+
+#[cfg(feature = "io")]
+pub use tif::TiffCompression;
+#[cfg(feature = "io")]
+pub(crate) use tif::{interleave_planes, write_tiff_page, write_tiff_planes};
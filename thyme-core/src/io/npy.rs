@@ -1,18 +1,24 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
-use std::fs::File;
 use std::io;
 use std::path::Path;
 
-use npyz::{self, WriterBuilder};
+use npyz::half::f16;
+use npyz::{self, DType, NpyFile, NpyHeader, TypeChar, WriterBuilder};
 use npyz::{TypeStr, npz};
-use zip::write::ExtendedFileOptions;
+use serde_json::{Map, Value};
+use zip::CompressionMethod;
+use zip::write::{ExtendedFileOptions, FileOptions};
 
 use crate::error::ThymeError;
+use crate::io::write_atomic;
 
 /// Write a numpy file from a vector of specified shape
 ///
+/// Values are serialized through a buffered writer directly into the file,
+/// so no serialized copy of the array is held in memory.
+///
 /// # Arguments
 ///
 /// * `path` - Path to output numpy file
@@ -26,25 +32,417 @@ pub fn write_numpy<T, P: AsRef<Path>>(
 where
     T: npyz::Serialize + npyz::AutoSerialize,
 {
-    let mut buffer = vec![];
-    let mut writer = npyz::WriteOptions::<T>::new()
-        .default_dtype()
-        .shape(&shape)
-        .writer(&mut buffer)
-        .begin_nd()
-        .map_err(|_| ThymeError::ImageWriteError)?;
+    write_atomic(path, |file| {
+        let mut writer = npyz::WriteOptions::<T>::new()
+            .default_dtype()
+            .shape(&shape)
+            .writer(file)
+            .begin_nd()
+            .map_err(|_| ThymeError::ImageWriteError)?;
+
+        writer
+            .extend(data)
+            .map_err(|_| ThymeError::ImageWriteError)?;
+
+        writer.finish().map_err(|_| ThymeError::ImageWriteError)
+    })
+}
+
+/// Default limit on the size of arrays read from numpy files (16 GiB)
+pub const MAX_ARRAY_BYTES: u64 = 16 << 30;
+
+/// Environment variable overriding [`MAX_ARRAY_BYTES`] (e.g. `THYME_MAX_ARRAY_BYTES=1073741824`)
+pub const MAX_ARRAY_BYTES_VAR: &str = "THYME_MAX_ARRAY_BYTES";
+
+/// Limit on the size in bytes of arrays read from numpy files
+///
+/// Returns [`MAX_ARRAY_BYTES`] unless `THYME_MAX_ARRAY_BYTES` is set, in
+/// which case it must hold a positive number of bytes.
+pub fn max_array_bytes() -> Result<u64, ThymeError> {
+    let Ok(value) = std::env::var(MAX_ARRAY_BYTES_VAR) else {
+        return Ok(MAX_ARRAY_BYTES);
+    };
+
+    value
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|&limit| limit > 0)
+        .ok_or_else(|| {
+            ThymeError::OtherError(format!(
+                "Invalid {} '{}'. Must be a positive number of bytes",
+                MAX_ARRAY_BYTES_VAR, value
+            ))
+        })
+}
+
+/// Parse an in-memory numpy file after validating its header against its payload
+///
+/// The header is checked with [`check_numpy_header`] before any array data
+/// is allocated, so truncated or corrupt files declaring a huge shape fail
+/// with an error instead of attempting an enormous allocation.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes of a complete .npy file
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::io::parse_numpy;
+///
+/// // A header declaring a (100000, 100000) u16 array without any data
+/// let text = "{'descr': '<u2', 'fortran_order': False, 'shape': (100000, 100000), }";
+/// let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+/// bytes.extend((text.len() as u16 + 1).to_le_bytes());
+/// bytes.extend(text.bytes().chain([b'\n']));
+///
+/// let error = parse_numpy(&bytes).err().unwrap().to_string();
+/// assert!(error.contains("shape [100000, 100000]"));
+/// ```
+pub fn parse_numpy(bytes: &[u8]) -> Result<NpyFile<&[u8]>, ThymeError> {
+    let mut data = bytes;
+
+    let header = NpyHeader::from_reader(&mut data)
+        .map_err(|error| ThymeError::NpyError(format!("Invalid header: {}", error)))?;
+
+    check_numpy_header(&header, data.len() as u64, max_array_bytes()?)?;
+
+    Ok(NpyFile::with_header(header, data))
+}
+
+/// Check that the array declared by a numpy header is readable
+///
+/// The number of bytes declared by the shape and data type must match the
+/// number of bytes following the header and must not exceed the limit.
+/// Errors include the declared shape and data type.
+///
+/// # Arguments
+///
+/// * `header` - Parsed numpy header
+/// * `payload` - Number of bytes following the header
+/// * `limit` - Largest number of bytes that may be read
+pub fn check_numpy_header(header: &NpyHeader, payload: u64, limit: u64) -> Result<(), ThymeError> {
+    let array = || {
+        format!(
+            "Array with shape {:?} and dtype {}",
+            header.shape(),
+            header.dtype().descr()
+        )
+    };
+
+    // The element count is recomputed since the header count may wrap
+    let declared = header
+        .shape()
+        .iter()
+        .try_fold(1u64, |count, &n| count.checked_mul(n))
+        .zip(header.dtype().num_bytes())
+        .and_then(|(count, size)| count.checked_mul(size as u64));
 
-    for d in data {
-        let _ = writer.push(&d);
+    let Some(declared) = declared else {
+        return Err(ThymeError::NpyError(format!(
+            "{} overflows the addressable size",
+            array()
+        )));
+    };
+
+    if declared > limit {
+        return Err(ThymeError::NpyError(format!(
+            "{} declares {} bytes, exceeding the limit of {} bytes (set {} to raise it)",
+            array(),
+            declared,
+            limit,
+            MAX_ARRAY_BYTES_VAR
+        )));
+    }
+
+    if declared != payload {
+        return Err(ThymeError::NpyError(format!(
+            "{} declares {} bytes but the file holds {} bytes of data. The file may be truncated or corrupt",
+            array(),
+            declared,
+            payload
+        )));
     }
 
-    writer.finish().map_err(|_| ThymeError::ImageWriteError)?;
-    std::fs::write(path, buffer).map_err(|_| ThymeError::ImageWriteError)?;
     Ok(())
 }
 
+/// Read a numeric numpy file as 32-bit floats
+///
+/// Returns the data alongside the shape of the array.
+///
+/// # Arguments
+///
+/// * `path` - Path to a numpy file containing a float or integer array
+pub fn read_numpy_f32<P: AsRef<Path>>(path: P) -> Result<(Vec<f32>, Vec<u64>), ThymeError> {
+    let bytes = std::fs::read(path)
+        .map_err(|_| ThymeError::OtherError("Failed to read .npy file".to_string()))?;
+
+    let npy = parse_numpy(&bytes)?;
+
+    let shape = npy.shape().to_vec();
+
+    let invalid = || ThymeError::OtherError("Failed to read values from .npy file".to_string());
+
+    macro_rules! cast {
+        ($t:ty) => {
+            npy.into_vec::<$t>()
+                .map_err(|_| invalid())?
+                .into_iter()
+                .map(|v| v as f32)
+                .collect()
+        };
+    }
+
+    let data: Vec<f32> = match npy.dtype() {
+        DType::Plain(x) => match (x.type_char(), x.size_field()) {
+            (TypeChar::Float, 2) => npy
+                .into_vec::<f16>()
+                .map_err(|_| invalid())?
+                .into_iter()
+                .map(f16::to_f32)
+                .collect(),
+            (TypeChar::Float, 4) => cast!(f32),
+            (TypeChar::Float, 8) => cast!(f64),
+            (TypeChar::Int, 1) => cast!(i8),
+            (TypeChar::Int, 2) => cast!(i16),
+            (TypeChar::Int, 4) => cast!(i32),
+            (TypeChar::Int, 8) => cast!(i64),
+            (TypeChar::Uint, 1) => cast!(u8),
+            (TypeChar::Uint, 2) => cast!(u16),
+            (TypeChar::Uint, 4) => cast!(u32),
+            (TypeChar::Uint, 8) => cast!(u64),
+            _ => return Err(invalid()),
+        },
+        _ => return Err(invalid()),
+    };
+
+    Ok((data, shape))
+}
+
+/// Version of the .npz embeddings schema written by `write_embeddings_npz`
+///
+/// Version 2 archives contain the arrays `image` (unicode), `object` (u32),
+/// `centroid` (n x 2 f32), and `embedding` (n x d f32 or f16), a scalar
+/// `format_version` (u32), and a scalar unicode `attrs` holding a JSON
+/// object (e.g. model name, dtype, and preprocessing options). The `object`
+/// and `centroid` arrays are omitted if the embedded items have no object
+/// identifiers (e.g. whole images).
+///
+/// Version 1 archives have no `format_version` or `attrs` members, store the
+/// object identifiers in an `id` array, and store image names with a fixed
+/// width of 53 characters so longer names cannot be written.
+pub const NPZ_FORMAT_VERSION: u32 = 2;
+
+/// Single object embeddings read from a .npz file
+///
+/// Identifiers and centroids are empty if they were not written, and the
+/// attributes are empty for version 1 archives.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddingsNpz {
+    /// Version of the .npz schema (1 if the archive has no format_version)
+    pub format_version: u32,
+    /// Attributes of the embeddings (e.g. model name and preprocessing)
+    pub attrs: Map<String, Value>,
+    /// Image names for each object
+    pub images: Vec<String>,
+    /// Object identifiers
+    pub ids: Vec<u32>,
+    /// Object centroids
+    pub centroids: Vec<[f32; 2]>,
+    /// Object self-supervised features/embeddings
+    pub embeddings: Vec<Vec<f32>>,
+}
+
+/// Read neural network single object embeddings from a .npz file
+///
+/// This is the inverse of `write_embeddings_npz` and reads both version 1
+/// and version 2 archives (see [`NPZ_FORMAT_VERSION`]). Embeddings written
+/// as 16-bit floats are converted back to 32-bit floats.
+///
+/// # Arguments
+///
+/// * `path` - Path to a .npz file with image, object, centroid, and embedding arrays
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::io::read_embeddings_npz;
+/// let npz = read_embeddings_npz("embeddings.npz").unwrap();
+/// ```
+pub fn read_embeddings_npz<P: AsRef<Path>>(path: P) -> Result<EmbeddingsNpz, ThymeError> {
+    let mut npz = npz::NpzArchive::open(path)
+        .map_err(|_| ThymeError::OtherError("Failed to open .npz file".to_string()))?;
+
+    let missing =
+        |name: &str| ThymeError::OtherError(format!("Missing {} array in .npz file", name));
+    let invalid =
+        |name: &str| ThymeError::OtherError(format!("Failed to read {} array in .npz file", name));
+
+    let embedding = npz
+        .by_name("embedding")
+        .map_err(|_| invalid("embedding"))?
+        .ok_or_else(|| missing("embedding"))?;
+
+    let shape = embedding.shape().to_vec();
+
+    if shape.len() != 2 {
+        return Err(ThymeError::OtherError(
+            "Embeddings in .npz file must be a 2D array".to_string(),
+        ));
+    }
+
+    let m = shape[1] as usize;
+
+    let values: Vec<f32> = match embedding.dtype() {
+        DType::Plain(x) if x.type_char() == TypeChar::Float && x.size_field() == 2 => embedding
+            .into_vec::<f16>()
+            .map_err(|_| invalid("embedding"))?
+            .into_iter()
+            .map(f16::to_f32)
+            .collect(),
+        _ => embedding
+            .into_vec::<f32>()
+            .map_err(|_| invalid("embedding"))?,
+    };
+
+    let embeddings: Vec<Vec<f32>> = values
+        .chunks_exact(m.max(1))
+        .map(|row| row.to_vec())
+        .collect();
+
+    let images: Vec<String> = npz
+        .by_name("image")
+        .map_err(|_| invalid("image"))?
+        .ok_or_else(|| missing("image"))?
+        .into_vec::<String>()
+        .map_err(|_| invalid("image"))?;
+
+    let format_version = match npz
+        .by_name("format_version")
+        .map_err(|_| invalid("format_version"))?
+    {
+        Some(version) => version
+            .into_vec::<u32>()
+            .ok()
+            .and_then(|version| version.first().copied())
+            .ok_or_else(|| invalid("format_version"))?,
+        None => 1,
+    };
+
+    if format_version > NPZ_FORMAT_VERSION {
+        return Err(ThymeError::OtherError(format!(
+            "Unsupported .npz format version {}. Must be at most {}",
+            format_version, NPZ_FORMAT_VERSION
+        )));
+    }
+
+    let attrs: Map<String, Value> = match npz.by_name("attrs").map_err(|_| invalid("attrs"))? {
+        Some(attrs) => attrs
+            .into_vec::<String>()
+            .ok()
+            .and_then(|attrs| serde_json::from_str(attrs.first()?).ok())
+            .ok_or_else(|| invalid("attrs"))?,
+        None => Map::new(),
+    };
+
+    let id = if format_version == 1 { "id" } else { "object" };
+
+    let ids: Vec<u32> = match npz.by_name(id).map_err(|_| invalid(id))? {
+        Some(ids) => ids.into_vec::<u32>().map_err(|_| invalid(id))?,
+        None => vec![],
+    };
+
+    let centroids: Vec<[f32; 2]> = match npz.by_name("centroid").map_err(|_| invalid("centroid"))? {
+        Some(centroid) => centroid
+            .into_vec::<f32>()
+            .map_err(|_| invalid("centroid"))?
+            .chunks_exact(2)
+            .map(|c| [c[0], c[1]])
+            .collect(),
+        None => vec![],
+    };
+
+    let n = embeddings.len();
+
+    if images.len() != n
+        || (!ids.is_empty() && ids.len() != n)
+        || (!centroids.is_empty() && centroids.len() != n)
+    {
+        return Err(ThymeError::OtherError(
+            "Arrays in .npz file must have the same number of objects".to_string(),
+        ));
+    }
+
+    Ok(EmbeddingsNpz {
+        format_version,
+        attrs,
+        images,
+        ids,
+        centroids,
+        embeddings,
+    })
+}
+
+/// Compression of the arrays stored in a .npz file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NpzCompression {
+    /// Store arrays without compression (equivalent to `numpy.savez`)
+    Stored,
+    /// Compress arrays with deflate (equivalent to `numpy.savez_compressed`)
+    #[default]
+    Deflated,
+}
+
+impl NpzCompression {
+    fn method(&self) -> CompressionMethod {
+        match self {
+            NpzCompression::Stored => CompressionMethod::Stored,
+            NpzCompression::Deflated => CompressionMethod::Deflated,
+        }
+    }
+}
+
+/// Data type of the embeddings stored in a .npz file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmbeddingDtype {
+    /// 32-bit floats
+    #[default]
+    F32,
+    /// 16-bit floats converted from 32-bit floats as they are written
+    F16,
+}
+
+impl EmbeddingDtype {
+    /// Numpy name of the data type
+    fn name(&self) -> &'static str {
+        match self {
+            EmbeddingDtype::F32 => "float32",
+            EmbeddingDtype::F16 => "float16",
+        }
+    }
+}
+
+/// Options for writing embeddings to a .npz file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NpzOptions {
+    /// Compression of each array in the archive
+    pub compression: NpzCompression,
+    /// Data type of the embedding array
+    pub dtype: EmbeddingDtype,
+    /// Attributes written to the attrs member (the dtype is added on write)
+    pub attrs: Map<String, Value>,
+    /// Write the version 1 layout without format_version and attrs members
+    pub legacy: bool,
+}
+
 /// Write neural network single object embeddings to a .npz file
 ///
+/// Arrays are written with the default [`NpzOptions`], i.e. deflate
+/// compressed 32-bit float embeddings in the version 2 layout with no
+/// attributes other than the dtype.
+///
 /// # Arguments
 ///
 /// * `images` - Image names for each object
@@ -56,8 +454,8 @@ where
 /// # Examples
 ///
 /// ```no_run
-/// ```
 /// use thyme_core::io::write_embeddings_npz;
+/// ```
 pub fn write_embeddings_npz<P: AsRef<Path>>(
     images: Vec<String>,
     ids: Vec<u32>,
@@ -65,151 +463,313 @@ pub fn write_embeddings_npz<P: AsRef<Path>>(
     embeddings: Vec<Vec<f32>>,
     output: &P,
 ) -> Result<(), ThymeError> {
-    let file = io::BufWriter::new(
-        File::create(output)
-            .map_err(|_| ThymeError::OtherError("Failed to create .npz file".to_string()))?,
-    );
+    write_embeddings_npz_with(
+        images,
+        ids,
+        centroids,
+        embeddings,
+        output,
+        &NpzOptions::default(),
+    )
+}
 
-    let mut zip = zip::ZipWriter::new(file);
+/// Write neural network single object embeddings to a .npz file with options
+///
+/// Each array is serialized directly into its member of the zip archive,
+/// which is written through a buffered writer into the output file, so the
+/// archive is never assembled in memory. The layout of the archive is
+/// described by [`NPZ_FORMAT_VERSION`].
+///
+/// # Arguments
+///
+/// * `images` - Image names for each object
+/// * `ids` - Object identifiers
+/// * `centroids` - Object centroids
+/// * `embeddings` - Object self-supervised features/embeddings
+/// * `output` - Path to output .npz file
+/// * `options` - Compression and embedding data type
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::io::{EmbeddingDtype, NpzCompression, NpzOptions, write_embeddings_npz_with};
+///
+/// let options = NpzOptions {
+///     compression: NpzCompression::Stored,
+///     dtype: EmbeddingDtype::F16,
+///     ..Default::default()
+/// };
+///
+/// write_embeddings_npz_with(
+///     vec!["a".to_string()],
+///     vec![1],
+///     vec![[2., 3.]],
+///     vec![vec![0.5, 1.5]],
+///     &"embeddings.npz",
+///     &options,
+/// )
+/// .unwrap();
+/// ```
+pub fn write_embeddings_npz_with<P: AsRef<Path>>(
+    images: Vec<String>,
+    ids: Vec<u32>,
+    centroids: Vec<[f32; 2]>,
+    embeddings: Vec<Vec<f32>>,
+    output: &P,
+    options: &NpzOptions,
+) -> Result<(), ThymeError> {
+    write_atomic(output, |file| {
+        let mut zip = zip::ZipWriter::new(file);
 
-    if images.len() != embeddings.len() {
-        return Err(ThymeError::OtherError(
-            "Image names and embeddings must have same length when saving .npz.".to_string(),
-        ));
-    }
+        if images.len() != embeddings.len() {
+            return Err(ThymeError::OtherError(
+                "Image names and embeddings must have same length when saving .npz.".to_string(),
+            ));
+        }
 
-    if !ids.is_empty() && ids.len() != embeddings.len() {
-        return Err(ThymeError::OtherError(
-            "Object identifiers and embeddings must have same length when saving .npz.".to_string(),
-        ));
-    }
+        if !ids.is_empty() && ids.len() != embeddings.len() {
+            return Err(ThymeError::OtherError(
+                "Object identifiers and embeddings must have same length when saving .npz."
+                    .to_string(),
+            ));
+        }
 
-    if !centroids.is_empty() && centroids.len() != embeddings.len() {
-        return Err(ThymeError::OtherError(
-            "Object centroids and embeddings must have same length when saving .npz.".to_string(),
-        ));
-    }
+        if !centroids.is_empty() && centroids.len() != embeddings.len() {
+            return Err(ThymeError::OtherError(
+                "Object centroids and embeddings must have same length when saving .npz."
+                    .to_string(),
+            ));
+        }
 
-    let n = embeddings.len() as u64;
-    let m = embeddings[0].len() as u64;
+        let n = embeddings.len() as u64;
+        let m = embeddings.first().map_or(0, |embedding| embedding.len()) as u64;
 
-    // IMAGE NAMES
+        // Members larger than 4 GiB need zip64 headers
+        let member = |bytes: u64| {
+            FileOptions::<ExtendedFileOptions>::default()
+                .compression_method(options.compression.method())
+                .large_file(bytes >= u32::MAX as u64)
+        };
 
-    zip.start_file::<_, ExtendedFileOptions>(
-        npz::file_name_from_array_name("image"),
-        Default::default(),
-    )
-    .map_err(|_| {
-        ThymeError::OtherError(
-            "Failed to initiailize zip file for image names in .npz file".to_string(),
-        )
-    })?;
+        // IMAGE NAMES
 
-    let mut writer = npyz::WriteOptions::new()
-        .dtype(npyz::DType::Plain("<U53".parse::<TypeStr>().unwrap()))
-        .shape(&[n])
-        .writer(&mut zip)
-        .begin_nd()
+        // Version 1 archives store image names with a fixed width
+        let width = match options.legacy {
+            true => 53,
+            false => images
+                .iter()
+                .map(|image| image.chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(1),
+        };
+
+        zip.start_file::<_, ExtendedFileOptions>(
+            npz::file_name_from_array_name("image"),
+            member(n * width as u64 * 4),
+        )
         .map_err(|_| {
             ThymeError::OtherError(
-                "Failed to initiailize writer for image names in .npz file".to_string(),
+                "Failed to initiailize zip file for image names in .npz file".to_string(),
             )
         })?;
 
-    writer
-        .extend(images.iter().map(|image| image.as_str()))
+        write_npz_strings(
+            &mut zip,
+            &[n],
+            width,
+            images.iter().map(|image| image.as_str()),
+        )
         .map_err(|_| {
-            ThymeError::OtherError("Failed to add image names to .npz file".to_string())
+            ThymeError::OtherError("Failed to write image names to .npz file".to_string())
         })?;
 
-    writer.finish().map_err(|_| {
-        ThymeError::OtherError("Failed to write image names to .npz file".to_string())
-    })?;
+        // IDENTIFIERS
 
-    // IDENTIFIERS
+        if !ids.is_empty() {
+            let name = if options.legacy { "id" } else { "object" };
 
-    if !ids.is_empty() {
-        zip.start_file::<_, ExtendedFileOptions>(
-            npz::file_name_from_array_name("id"),
-            Default::default(),
-        )
-        .map_err(|_| {
-            ThymeError::OtherError(
-                "Failed to initiailize zip file for identifiers in .npz file".to_string(),
+            zip.start_file::<_, ExtendedFileOptions>(
+                npz::file_name_from_array_name(name),
+                member(n * 4),
             )
-        })?;
+            .map_err(|_| {
+                ThymeError::OtherError(
+                    "Failed to initiailize zip file for identifiers in .npz file".to_string(),
+                )
+            })?;
 
-        let mut writer = npyz::WriteOptions::new()
-            .default_dtype()
-            .shape(&[n])
-            .writer(&mut zip)
-            .begin_nd()
+            let mut writer = npyz::WriteOptions::new()
+                .default_dtype()
+                .shape(&[n])
+                .writer(&mut zip)
+                .begin_nd()
+                .map_err(|_| {
+                    ThymeError::OtherError(
+                        "Failed to initialize writer for identifiers in .npz file".to_string(),
+                    )
+                })?;
+
+            writer.extend(ids).map_err(|_| {
+                ThymeError::OtherError("Failed to add identifiers to .npz file".to_string())
+            })?;
+
+            writer.finish().map_err(|_| {
+                ThymeError::OtherError("Failed to write identifiers to .npz file".to_string())
+            })?;
+        }
+
+        // CENTROIDS
+
+        if !centroids.is_empty() {
+            zip.start_file::<_, ExtendedFileOptions>(
+                npz::file_name_from_array_name("centroid"),
+                member(n * 2 * 4),
+            )
             .map_err(|_| {
                 ThymeError::OtherError(
-                    "Failed to initialize writer for identifiers in .npz file".to_string(),
+                    "Failed to initiailize zip file for centroids in .npz file".to_string(),
                 )
             })?;
 
-        writer.extend(ids).map_err(|_| {
-            ThymeError::OtherError("Failed to add identifiers to .npz file".to_string())
-        })?;
+            let mut writer = npyz::WriteOptions::new()
+                .default_dtype()
+                .shape(&[n, 2])
+                .writer(&mut zip)
+                .begin_nd()
+                .map_err(|_| {
+                    ThymeError::OtherError(
+                        "Failed to initialize writer for centroids in .npz file".to_string(),
+                    )
+                })?;
 
-        writer.finish().map_err(|_| {
-            ThymeError::OtherError("Failed to write identifiers to .npz file".to_string())
-        })?;
-    }
+            writer
+                .extend(centroids.iter().flat_map(|r| r.iter().cloned()))
+                .map_err(|_| {
+                    ThymeError::OtherError("Failed to add centroids to .npz file".to_string())
+                })?;
+
+            writer.finish().map_err(|_| {
+                ThymeError::OtherError("Failed to write centroids to .npz file".to_string())
+            })?;
+        }
 
-    // CENTROIDS
+        // EMBEDDINGS
 
-    if !centroids.is_empty() {
         zip.start_file::<_, ExtendedFileOptions>(
-            npz::file_name_from_array_name("centroid"),
-            Default::default(),
+            npz::file_name_from_array_name("embedding"),
+            member(n * m * 4),
         )
         .map_err(|_| {
             ThymeError::OtherError(
-                "Failed to initiailize zip file for centroids in .npz file".to_string(),
+                "Failed to initiailize zip file for embeddings in .npz file".to_string(),
             )
         })?;
 
-        let mut writer = npyz::WriteOptions::new()
-            .default_dtype()
-            .shape(&[n, 2])
-            .writer(&mut zip)
-            .begin_nd()
+        let values = embeddings.iter().flat_map(|r| r.iter().cloned());
+
+        match options.dtype {
+            EmbeddingDtype::F32 => write_npz_member(&mut zip, &[n, m], values),
+            EmbeddingDtype::F16 => write_npz_member(&mut zip, &[n, m], values.map(f16::from_f32)),
+        }?;
+
+        // FORMAT VERSION AND ATTRIBUTES
+
+        if !options.legacy {
+            let mut attrs = options.attrs.clone();
+            attrs.insert("dtype".to_string(), Value::from(options.dtype.name()));
+            let attrs = Value::Object(attrs).to_string();
+
+            zip.start_file::<_, ExtendedFileOptions>(
+                npz::file_name_from_array_name("format_version"),
+                member(4),
+            )
             .map_err(|_| {
                 ThymeError::OtherError(
-                    "Failed to initialize writer for centroids in .npz file".to_string(),
+                    "Failed to initiailize zip file for format version in .npz file".to_string(),
                 )
             })?;
 
-        writer
-            .extend(centroids.iter().flat_map(|r| r.iter().cloned()))
+            let mut writer = npyz::WriteOptions::new()
+                .default_dtype()
+                .shape(&[])
+                .writer(&mut zip)
+                .begin_nd()
+                .map_err(|_| {
+                    ThymeError::OtherError(
+                        "Failed to initialize writer for format version in .npz file".to_string(),
+                    )
+                })?;
+
+            writer
+                .push(&NPZ_FORMAT_VERSION)
+                .and_then(|_| writer.finish())
+                .map_err(|_| {
+                    ThymeError::OtherError(
+                        "Failed to write format version to .npz file".to_string(),
+                    )
+                })?;
+
+            let width = attrs.chars().count();
+
+            zip.start_file::<_, ExtendedFileOptions>(
+                npz::file_name_from_array_name("attrs"),
+                member(width as u64 * 4),
+            )
             .map_err(|_| {
-                ThymeError::OtherError("Failed to add centroids to .npz file".to_string())
+                ThymeError::OtherError(
+                    "Failed to initiailize zip file for attributes in .npz file".to_string(),
+                )
             })?;
 
-        writer.finish().map_err(|_| {
-            ThymeError::OtherError("Failed to write centroids to .npz file".to_string())
-        })?;
-    }
+            write_npz_strings(&mut zip, &[], width, std::iter::once(attrs.as_str())).map_err(
+                |_| ThymeError::OtherError("Failed to write attributes to .npz file".to_string()),
+            )?;
+        }
 
-    // EMBEDDINGS
+        zip.finish()
+            .map_err(|_| ThymeError::OtherError("Failed to zip .npz file".to_string()))?;
 
-    zip.start_file::<_, ExtendedFileOptions>(
-        npz::file_name_from_array_name("embedding"),
-        Default::default(),
-    )
-    .map_err(|_| {
-        ThymeError::OtherError(
-            "Failed to initiailize zip file for embeddings in .npz file".to_string(),
-        )
-    })?;
+        Ok(())
+    })
+}
 
+/// Serialize fixed width unicode strings into the current member of a zip archive
+fn write_npz_strings<'a, W>(
+    zip: &mut zip::ZipWriter<W>,
+    shape: &[u64],
+    width: usize,
+    values: impl Iterator<Item = &'a str>,
+) -> io::Result<()>
+where
+    W: io::Write + io::Seek,
+{
     let mut writer = npyz::WriteOptions::new()
+        .dtype(npyz::DType::Plain(
+            format!("<U{}", width).parse::<TypeStr>().unwrap(),
+        ))
+        .shape(shape)
+        .writer(zip)
+        .begin_nd()?;
+
+    writer.extend(values)?;
+    writer.finish()
+}
+
+/// Serialize the embeddings into the current member of a zip archive
+fn write_npz_member<T, W>(
+    zip: &mut zip::ZipWriter<W>,
+    shape: &[u64],
+    values: impl Iterator<Item = T>,
+) -> Result<(), ThymeError>
+where
+    T: npyz::Serialize + npyz::AutoSerialize,
+    W: io::Write + io::Seek,
+{
+    let mut writer = npyz::WriteOptions::<T>::new()
         .default_dtype()
-        .shape(&[n, m])
-        .writer(&mut zip)
+        .shape(shape)
+        .writer(zip)
         .begin_nd()
         .map_err(|_| {
             ThymeError::OtherError(
@@ -218,15 +778,341 @@ pub fn write_embeddings_npz<P: AsRef<Path>>(
         })?;
 
     writer
-        .extend(embeddings.iter().flat_map(|r| r.iter().cloned()))
+        .extend(values)
         .map_err(|_| ThymeError::OtherError("Failed to add embeddings to .npz file".to_string()))?;
 
-    writer.finish().map_err(|_| {
-        ThymeError::OtherError("Failed to write image names to .npz file".to_string())
-    })?;
+    writer
+        .finish()
+        .map_err(|_| ThymeError::OtherError("Failed to write embeddings to .npz file".to_string()))
+}
+
+#[cfg(test)]
+mod test {
 
-    zip.finish()
-        .map_err(|_| ThymeError::OtherError("Failed to zip .npz file".to_string()))?;
+    use std::fs::File;
 
-    Ok(())
+    use super::*;
+    use crate::io::tracked::peak_allocation;
+
+    #[test]
+    fn test_write_streaming() {
+        const LIMIT: isize = 16 << 20;
+
+        // A 256 MB embedding matrix and 128 MB array written under a 16 MB allocation limit
+        let (n, m) = (1 << 14, 1 << 12);
+
+        let embeddings: Vec<Vec<f32>> = (0..n)
+            .map(|i| (0..m).map(|j| (i * m + j) as f32).collect())
+            .collect();
+
+        let images: Vec<String> = (0..n).map(|i| format!("image_{}", i)).collect();
+        let path = std::env::temp_dir().join("thyme_test_write_streaming.npz");
+
+        let options = NpzOptions {
+            compression: NpzCompression::Stored,
+            ..Default::default()
+        };
+
+        let inputs = (images.clone(), embeddings.clone());
+
+        let peak = peak_allocation(|| {
+            write_embeddings_npz_with(inputs.0, vec![], vec![], inputs.1, &path, &options).unwrap()
+        });
+
+        assert!(peak < LIMIT, "Peak allocation of {} bytes", peak);
+
+        let npz = read_embeddings_npz(&path).unwrap();
+        assert_eq!(npz.images, images);
+        assert_eq!(npz.embeddings, embeddings);
+        drop(npz);
+
+        let path = std::env::temp_dir().join("thyme_test_write_streaming.npy");
+        let data: Vec<f32> = embeddings.into_iter().take(n / 2).flatten().collect();
+        let expected = data.clone();
+
+        let peak =
+            peak_allocation(|| write_numpy(&path, data, vec![n as u64 / 2, m as u64]).unwrap());
+        assert!(peak < LIMIT, "Peak allocation of {} bytes", peak);
+
+        assert_eq!(
+            read_numpy_f32(&path).unwrap(),
+            (expected, vec![n as u64 / 2, m as u64])
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("npz")).unwrap();
+    }
+
+    #[test]
+    fn test_write_embeddings_npz_f16() {
+        let path = std::env::temp_dir().join("thyme_test_write_embeddings_f16.npz");
+        let embeddings = vec![vec![0.5, -1., 2.], vec![0.1, 3., 65504.]];
+
+        for compression in [NpzCompression::Stored, NpzCompression::Deflated] {
+            let options = NpzOptions {
+                compression,
+                dtype: EmbeddingDtype::F16,
+                ..Default::default()
+            };
+
+            write_embeddings_npz_with(
+                vec!["a".to_string(), "b".to_string()],
+                vec![1, 2],
+                vec![],
+                embeddings.clone(),
+                &path,
+                &options,
+            )
+            .unwrap();
+
+            let npz = read_embeddings_npz(&path).unwrap();
+
+            assert_eq!(npz.ids, [1, 2]);
+            assert!(npz.centroids.is_empty());
+
+            for (a, b) in npz
+                .embeddings
+                .iter()
+                .flatten()
+                .zip(embeddings.iter().flatten())
+            {
+                assert!((a - b).abs() <= b.abs() * 1e-3, "{} {}", a, b);
+            }
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_embeddings_npz() {
+        let path = std::env::temp_dir().join("thyme_test_read_embeddings.npz");
+
+        write_embeddings_npz(
+            vec!["a".to_string(), "b_image".to_string()],
+            vec![1, 7],
+            vec![[1., 2.], [3., 4.]],
+            vec![vec![0.5, -1., 2.], vec![0., 3., -0.25]],
+            &path,
+        )
+        .unwrap();
+
+        let npz = read_embeddings_npz(&path).unwrap();
+
+        assert_eq!(npz.images, ["a", "b_image"]);
+        assert_eq!(npz.ids, [1, 7]);
+        assert_eq!(npz.centroids, [[1., 2.], [3., 4.]]);
+        assert_eq!(npz.embeddings, [vec![0.5, -1., 2.], vec![0., 3., -0.25]]);
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(read_embeddings_npz(&path).is_err());
+    }
+
+    #[test]
+    fn test_write_embeddings_npz_failure() {
+        let path = std::env::temp_dir().join("thyme_test_write_embeddings_failure.npz");
+
+        let write = |embeddings: Vec<Vec<f32>>| {
+            let images = vec!["a".to_string(); embeddings.len()];
+            write_embeddings_npz(images, vec![1, 2], vec![], embeddings, &path)
+        };
+
+        // Ragged embeddings fail after the image and object members are written
+        assert!(write(vec![vec![0.5, 1.0], vec![2.0]]).is_err());
+        assert!(!path.exists());
+
+        write(vec![vec![0.5, 1.0], vec![2.0, 3.0]]).unwrap();
+        assert!(write(vec![vec![4.0, 5.0], vec![6.0]]).is_err());
+
+        let npz = read_embeddings_npz(&path).unwrap();
+        assert_eq!(npz.embeddings, [vec![0.5, 1.0], vec![2.0, 3.0]]);
+
+        let leftovers = std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                let name = name.to_string_lossy();
+                name.starts_with(".thyme_test_write_embeddings_failure.npz.")
+            })
+            .count();
+
+        assert_eq!(leftovers, 0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_embeddings_npz_versions() {
+        let path = std::env::temp_dir().join("thyme_test_embeddings_npz_versions.npz");
+        // Image names longer than 53 characters require the version 2 layout
+        let long_name = "a".repeat(60);
+
+        let mut attrs = Map::new();
+        attrs.insert("model".to_string(), Value::from("dino_vit_small"));
+        attrs.insert("pad".to_string(), Value::from(2));
+
+        for legacy in [false, true] {
+            let options = NpzOptions {
+                attrs: attrs.clone(),
+                legacy,
+                ..Default::default()
+            };
+
+            let images = match legacy {
+                true => vec!["b".to_string(), "a".repeat(53)],
+                false => vec!["b".to_string(), long_name.clone()],
+            };
+
+            write_embeddings_npz_with(
+                images.clone(),
+                vec![3, 4],
+                vec![[1., 2.], [3., 4.]],
+                vec![vec![0.5, -1.], vec![2., 0.25]],
+                &path,
+                &options,
+            )
+            .unwrap();
+
+            let members: Vec<String> = zip::ZipArchive::new(File::open(&path).unwrap())
+                .unwrap()
+                .file_names()
+                .map(|name| name.to_string())
+                .collect();
+
+            let npz = read_embeddings_npz(&path).unwrap();
+
+            assert_eq!(npz.images, images);
+            assert_eq!(npz.ids, [3, 4]);
+            assert_eq!(npz.centroids, [[1., 2.], [3., 4.]]);
+            assert_eq!(npz.embeddings, [vec![0.5, -1.], vec![2., 0.25]]);
+
+            if legacy {
+                assert_eq!(npz.format_version, 1);
+                assert!(npz.attrs.is_empty());
+                assert!(members.contains(&"id.npy".to_string()));
+                assert!(!members.contains(&"attrs.npy".to_string()));
+
+                let options = NpzOptions {
+                    legacy,
+                    ..Default::default()
+                };
+
+                assert!(
+                    write_embeddings_npz_with(
+                        vec![long_name.clone()],
+                        vec![],
+                        vec![],
+                        vec![vec![0.]],
+                        &path,
+                        &options
+                    )
+                    .is_err()
+                );
+            } else {
+                assert_eq!(npz.format_version, NPZ_FORMAT_VERSION);
+                assert_eq!(npz.attrs["model"], "dino_vit_small");
+                assert_eq!(npz.attrs["pad"], 2);
+                assert_eq!(npz.attrs["dtype"], "float32");
+                assert!(members.contains(&"object.npy".to_string()));
+                assert!(members.contains(&"format_version.npy".to_string()));
+            }
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_numpy_f32() {
+        let path = std::env::temp_dir().join("thyme_test_read_numpy_f32.npy");
+
+        write_numpy(&path, vec![1f64, 2., 3., 4.], vec![2, 2]).unwrap();
+        assert_eq!(
+            read_numpy_f32(&path).unwrap(),
+            (vec![1., 2., 3., 4.], vec![2, 2])
+        );
+
+        write_numpy(&path, vec![5u8, 6], vec![2]).unwrap();
+        assert_eq!(read_numpy_f32(&path).unwrap(), (vec![5., 6.], vec![2]));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Bytes of a version 1.0 numpy file with a header and `payload` zero bytes of data
+    fn numpy_bytes(descr: &str, shape: &str, payload: usize) -> Vec<u8> {
+        let text = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descr, shape
+        );
+
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend((text.len() as u16 + 1).to_le_bytes());
+        bytes.extend(text.bytes().chain([b'\n']));
+        bytes.extend(vec![0u8; payload]);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_numpy() {
+        let valid = numpy_bytes("<u2", "(3, 4)", 24);
+        let npy = parse_numpy(&valid).unwrap();
+        assert_eq!(npy.into_vec::<u16>().unwrap(), vec![0; 12]);
+
+        // Header-only files declaring a huge shape fail without allocating the array
+        let header_only = numpy_bytes("<f8", "(1000000, 1000000, 3)", 0);
+        let peak = peak_allocation(|| {
+            let error = parse_numpy(&header_only).err().unwrap().to_string();
+            assert!(error.contains("shape [1000000, 1000000, 3]"), "{}", error);
+            assert!(error.contains("'<f8'"), "{}", error);
+        });
+
+        assert!(peak < 1 << 20, "{}", peak);
+
+        let mismatched = numpy_bytes("<u1", "(4, 4)", 15);
+        let error = parse_numpy(&mismatched).err().unwrap().to_string();
+        assert!(error.contains("declares 16 bytes"), "{}", error);
+        assert!(error.contains("holds 15 bytes"), "{}", error);
+
+        assert!(parse_numpy(&numpy_bytes("<u1", "(4, 4)", 17)).is_err());
+        assert!(parse_numpy(&valid[..20]).is_err());
+
+        let overflow = numpy_bytes("<u8", "(2305843009213693952,)", 0);
+        let error = parse_numpy(&overflow).err().unwrap().to_string();
+        assert!(error.contains("overflows"), "{}", error);
+
+        // Arrays over the limit fail before their payload is compared
+        let header = NpyHeader::from_reader(&valid[..]).unwrap();
+        assert!(check_numpy_header(&header, 24, 24).is_ok());
+
+        let error = check_numpy_header(&header, 24, 23).unwrap_err().to_string();
+        assert!(error.contains(MAX_ARRAY_BYTES_VAR), "{}", error);
+    }
+
+    #[test]
+    fn test_open_corrupt_numpy() {
+        use crate::im::{ThymeImage, ThymeMask, ThymeMaskU8, ZStackMode};
+
+        let directory = std::env::temp_dir().join("thyme_test_open_corrupt_numpy");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let header_only = directory.join("header_only.npy");
+        let mismatched = directory.join("mismatched.npy");
+
+        std::fs::write(&header_only, numpy_bytes("<u2", "(100000, 100000)", 0)).unwrap();
+        std::fs::write(&mismatched, numpy_bytes("<u1", "(2, 64, 64)", 64 * 64)).unwrap();
+
+        for path in [&header_only, &mismatched] {
+            assert!(ThymeImage::open(path).is_err());
+            assert!(ThymeImage::open_stack(path).is_err());
+            assert!(ThymeMask::open(path).is_err());
+            assert!(ThymeMask::open_zstack(path, ZStackMode::PerSlice).is_err());
+            assert!(ThymeMaskU8::open(path).is_err());
+            assert!(read_numpy_f32(path).is_err());
+        }
+
+        let error = ThymeImage::open(&header_only).unwrap_err().to_string();
+        assert!(error.starts_with("[thyme::NpyError]"), "{}", error);
+        assert!(error.contains("shape [100000, 100000] and dtype '<u2'"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
@@ -0,0 +1,509 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::HashMap;
+
+use crate::error::ThymeError;
+
+/// Maximum number of values copied by the memo and DUP opcodes of a stream
+///
+/// Memo entries are copied each time they are read, so a short stream that
+/// nests reads of the same container could otherwise decode into a tree that
+/// grows exponentially with its length.
+const MAX_COPIED_VALUES: usize = 1 << 22;
+
+/// A value of a pickle stream
+///
+/// Only the values needed to locate numpy arrays inside pickled containers
+/// are represented. Strings and bytes borrow from the pickle stream so that
+/// array buffers are never copied, and objects are kept as the callable,
+/// arguments, and state used to reconstruct them instead of being built.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value<'a> {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(&'a str),
+    Bytes(&'a [u8]),
+    Tuple(Vec<Value<'a>>),
+    List(Vec<Value<'a>>),
+    Dict(Vec<(Value<'a>, Value<'a>)>),
+    Global {
+        module: &'a str,
+        name: &'a str,
+    },
+    Object {
+        callable: Box<Value<'a>>,
+        args: Box<Value<'a>>,
+        state: Option<Box<Value<'a>>>,
+    },
+}
+
+impl<'a> Value<'a> {
+    /// Value stored under a string key of a dictionary
+    pub(crate) fn get(&self, key: &str) -> Option<&Value<'a>> {
+        match self {
+            Value::Dict(items) => items
+                .iter()
+                .find(|(k, _)| matches!(k, Value::Str(k) if *k == key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// First dictionary containing a key, searched depth-first through containers and object states
+    pub(crate) fn find_dict(&self, key: &str) -> Option<&Value<'a>> {
+        if self.get(key).is_some() {
+            return Some(self);
+        }
+
+        match self {
+            Value::Tuple(items) | Value::List(items) => {
+                items.iter().find_map(|item| item.find_dict(key))
+            }
+            Value::Dict(items) => items.iter().find_map(|(_, value)| value.find_dict(key)),
+            Value::Object { args, state, .. } => args
+                .find_dict(key)
+                .or_else(|| state.as_ref().and_then(|state| state.find_dict(key))),
+            _ => None,
+        }
+    }
+
+    /// Number of values in the tree, including this value
+    pub(crate) fn count(&self) -> usize {
+        1 + match self {
+            Value::Tuple(items) | Value::List(items) => items.iter().map(Value::count).sum(),
+            Value::Dict(items) => items.iter().map(|(k, v)| k.count() + v.count()).sum(),
+            Value::Object {
+                callable,
+                args,
+                state,
+            } => callable.count() + args.count() + state.as_ref().map_or(0, |state| state.count()),
+            _ => 0,
+        }
+    }
+
+    /// Name of the global an object is reconstructed with
+    pub(crate) fn callable_name(&self) -> Option<&'a str> {
+        match self {
+            Value::Object { callable, .. } => match callable.as_ref() {
+                Value::Global { name, .. } => Some(name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+fn invalid(message: &str) -> ThymeError {
+    ThymeError::OtherError(format!("Invalid pickle stream. {}", message))
+}
+
+/// Bounds-checked reader over a pickle stream
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ThymeError> {
+        let end = self
+            .position
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| invalid("The stream ended unexpectedly"))?;
+
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, ThymeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ThymeError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, ThymeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ThymeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn len64(&mut self) -> Result<usize, ThymeError> {
+        usize::try_from(self.u64()?).map_err(|_| invalid("A length exceeds addressable memory"))
+    }
+
+    fn line(&mut self) -> Result<&'a str, ThymeError> {
+        let rest = &self.bytes[self.position..];
+        let n = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(|| invalid("A line is not terminated"))?;
+
+        let line = self.take(n + 1)?;
+        std::str::from_utf8(&line[..n]).map_err(|_| invalid("A line is not valid UTF-8"))
+    }
+
+    fn str(&mut self, n: usize) -> Result<&'a str, ThymeError> {
+        std::str::from_utf8(self.take(n)?).map_err(|_| invalid("A string is not valid UTF-8"))
+    }
+}
+
+/// Little-endian two's complement integer of a LONG1 or LONG4 opcode
+///
+/// Integers that do not fit an i64 are not needed to locate arrays and are
+/// read as `None`.
+fn long(bytes: &[u8]) -> Value<'static> {
+    if bytes.is_empty() {
+        return Value::Int(0);
+    }
+
+    if bytes.len() > 8 {
+        return Value::None;
+    }
+
+    let fill = if bytes[bytes.len() - 1] & 0x80 != 0 {
+        0xFF
+    } else {
+        0x00
+    };
+
+    let mut buffer = [fill; 8];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+    Value::Int(i64::from_le_bytes(buffer))
+}
+
+/// Copy a value read from the memo or the top of the stack
+///
+/// # Arguments
+///
+/// * `value` - Value to copy
+/// * `count` - Number of values in the tree of the value
+/// * `copied` - Number of values copied so far by the stream
+fn copy<'a>(value: &Value<'a>, count: usize, copied: &mut usize) -> Result<Value<'a>, ThymeError> {
+    *copied += count;
+
+    if *copied > MAX_COPIED_VALUES {
+        return Err(invalid(&format!(
+            "Memoized values expand to more than {} values",
+            MAX_COPIED_VALUES
+        )));
+    }
+
+    Ok(value.clone())
+}
+
+fn pop<'a>(stack: &mut Vec<Value<'a>>) -> Result<Value<'a>, ThymeError> {
+    stack.pop().ok_or_else(|| invalid("The stack is empty"))
+}
+
+fn last<'s, 'a>(stack: &'s mut [Value<'a>]) -> Result<&'s mut Value<'a>, ThymeError> {
+    stack
+        .last_mut()
+        .ok_or_else(|| invalid("The stack is empty"))
+}
+
+/// Pop the values pushed since the last mark
+fn pop_mark<'a>(
+    stack: &mut Vec<Value<'a>>,
+    marks: &mut Vec<usize>,
+) -> Result<Vec<Value<'a>>, ThymeError> {
+    let mark = marks.pop().ok_or_else(|| invalid("A mark is missing"))?;
+
+    if mark > stack.len() {
+        return Err(invalid("A mark is past the end of the stack"));
+    }
+
+    Ok(stack.split_off(mark))
+}
+
+/// Evaluate a pickle stream without executing any code
+///
+/// Supports the opcodes of pickle protocols 2 to 5 that are written when
+/// pickling builtin containers, strings, numbers, and numpy arrays with
+/// in-band buffers. Objects are returned as `Value::Object` instead of being
+/// reconstructed, so no module is ever imported or called.
+///
+/// # Arguments
+///
+/// * `bytes` - Pickle stream
+pub(crate) fn unpickle(bytes: &[u8]) -> Result<Value<'_>, ThymeError> {
+    let mut reader = Reader { bytes, position: 0 };
+    let mut stack: Vec<Value> = Vec::new();
+    let mut marks: Vec<usize> = Vec::new();
+    let mut memo: HashMap<u32, (Value, usize)> = HashMap::new();
+    let mut copied: usize = 0;
+
+    loop {
+        let opcode = reader.u8()?;
+
+        match opcode {
+            // PROTO and FRAME only describe the stream
+            0x80 => {
+                reader.u8()?;
+            }
+            0x95 => {
+                reader.u64()?;
+            }
+            // STOP
+            b'.' => return pop(&mut stack),
+            // MARK, POP, POP_MARK, DUP
+            b'(' => marks.push(stack.len()),
+            b'0' => {
+                pop(&mut stack)?;
+            }
+            b'1' => {
+                pop_mark(&mut stack, &mut marks)?;
+            }
+            b'2' => {
+                let value = last(&mut stack)?;
+                let value = copy(value, value.count(), &mut copied)?;
+                stack.push(value);
+            }
+            // NONE, NEWTRUE, NEWFALSE
+            b'N' => stack.push(Value::None),
+            0x88 => stack.push(Value::Bool(true)),
+            0x89 => stack.push(Value::Bool(false)),
+            // BININT, BININT1, BININT2, LONG1, LONG4
+            b'J' => stack.push(Value::Int(reader.u32()? as i32 as i64)),
+            b'K' => stack.push(Value::Int(reader.u8()? as i64)),
+            b'M' => stack.push(Value::Int(reader.u16()? as i64)),
+            0x8a => {
+                let n = reader.u8()? as usize;
+                stack.push(long(reader.take(n)?));
+            }
+            0x8b => {
+                let n = reader.u32()? as usize;
+                stack.push(long(reader.take(n)?));
+            }
+            // BINFLOAT
+            b'G' => stack.push(Value::Float(f64::from_be_bytes(
+                reader.take(8)?.try_into().unwrap(),
+            ))),
+            // SHORT_BINUNICODE, BINUNICODE, BINUNICODE8
+            0x8c => {
+                let n = reader.u8()? as usize;
+                stack.push(Value::Str(reader.str(n)?));
+            }
+            b'X' => {
+                let n = reader.u32()? as usize;
+                stack.push(Value::Str(reader.str(n)?));
+            }
+            0x8d => {
+                let n = reader.len64()?;
+                stack.push(Value::Str(reader.str(n)?));
+            }
+            // SHORT_BINSTRING, BINSTRING, SHORT_BINBYTES, BINBYTES, BINBYTES8, BYTEARRAY8
+            b'U' | b'C' => {
+                let n = reader.u8()? as usize;
+                stack.push(Value::Bytes(reader.take(n)?));
+            }
+            b'T' | b'B' => {
+                let n = reader.u32()? as usize;
+                stack.push(Value::Bytes(reader.take(n)?));
+            }
+            0x8e | 0x96 => {
+                let n = reader.len64()?;
+                stack.push(Value::Bytes(reader.take(n)?));
+            }
+            // EMPTY_TUPLE, TUPLE1, TUPLE2, TUPLE3, TUPLE
+            b')' => stack.push(Value::Tuple(vec![])),
+            0x85..=0x87 => {
+                let n = (opcode - 0x84) as usize;
+                let start = stack
+                    .len()
+                    .checked_sub(n)
+                    .ok_or_else(|| invalid("The stack is empty"))?;
+
+                let items = stack.split_off(start);
+                stack.push(Value::Tuple(items));
+            }
+            b't' => {
+                let items = pop_mark(&mut stack, &mut marks)?;
+                stack.push(Value::Tuple(items));
+            }
+            // EMPTY_LIST, LIST, APPEND, APPENDS, and sets which are read as lists
+            b']' | 0x8f => stack.push(Value::List(vec![])),
+            b'l' | 0x91 => {
+                let items = pop_mark(&mut stack, &mut marks)?;
+                stack.push(Value::List(items));
+            }
+            b'a' => {
+                let item = pop(&mut stack)?;
+                if let Value::List(items) = last(&mut stack)? {
+                    items.push(item);
+                }
+            }
+            b'e' | 0x90 => {
+                let new = pop_mark(&mut stack, &mut marks)?;
+                if let Value::List(items) = last(&mut stack)? {
+                    items.extend(new);
+                }
+            }
+            // EMPTY_DICT, DICT, SETITEM, SETITEMS
+            b'}' => stack.push(Value::Dict(vec![])),
+            b'd' | b's' | b'u' => {
+                let new = match opcode {
+                    b's' => {
+                        let value = pop(&mut stack)?;
+                        let key = pop(&mut stack)?;
+                        vec![key, value]
+                    }
+                    _ => pop_mark(&mut stack, &mut marks)?,
+                };
+
+                if new.len() % 2 != 0 {
+                    return Err(invalid("A dictionary has a key without a value"));
+                }
+
+                let mut new = new.into_iter();
+                let pairs = std::iter::from_fn(|| new.next().zip(new.next()));
+
+                if opcode == b'd' {
+                    stack.push(Value::Dict(pairs.collect()));
+                } else {
+                    if let Value::Dict(items) = last(&mut stack)? {
+                        items.extend(pairs);
+                    }
+                }
+            }
+            // GLOBAL, STACK_GLOBAL
+            b'c' => {
+                let module = reader.line()?;
+                let name = reader.line()?;
+                stack.push(Value::Global { module, name });
+            }
+            0x93 => {
+                let name = pop(&mut stack)?;
+                let module = pop(&mut stack)?;
+
+                match (module, name) {
+                    (Value::Str(module), Value::Str(name)) => {
+                        stack.push(Value::Global { module, name })
+                    }
+                    _ => return Err(invalid("A global is not named by strings")),
+                }
+            }
+            // REDUCE, NEWOBJ, NEWOBJ_EX
+            b'R' | 0x81 | 0x92 => {
+                if opcode == 0x92 {
+                    pop(&mut stack)?;
+                }
+
+                let args = pop(&mut stack)?;
+                let callable = pop(&mut stack)?;
+
+                stack.push(Value::Object {
+                    callable: Box::new(callable),
+                    args: Box::new(args),
+                    state: None,
+                });
+            }
+            // BUILD
+            b'b' => {
+                let value = pop(&mut stack)?;
+                if let Value::Object { state, .. } = last(&mut stack)? {
+                    *state = Some(Box::new(value));
+                }
+            }
+            // MEMOIZE, BINPUT, LONG_BINPUT, BINGET, LONG_BINGET
+            0x94 | b'q' | b'r' => {
+                let key = match opcode {
+                    0x94 => memo.len() as u32,
+                    b'q' => reader.u8()? as u32,
+                    _ => reader.u32()?,
+                };
+
+                let value = last(&mut stack)?;
+                let count = value.count();
+                memo.insert(key, (copy(value, count, &mut copied)?, count));
+            }
+            b'h' | b'j' => {
+                let key = match opcode {
+                    b'h' => reader.u8()? as u32,
+                    _ => reader.u32()?,
+                };
+
+                let (value, count) = memo
+                    .get(&key)
+                    .ok_or_else(|| invalid("A memo entry is missing"))?;
+
+                stack.push(copy(value, *count, &mut copied)?);
+            }
+            _ => {
+                return Err(invalid(&format!(
+                    "Opcode 0x{:02x} at byte {} is not supported",
+                    opcode,
+                    reader.position - 1
+                )));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_unpickle() {
+        // pickle.dumps({"masks": [1, -2, 3.5], "name": b"x", "ok": (True, None)}, protocol=4)
+        let bytes = b"\x80\x04\x95\x35\x00\x00\x00\x00\x00\x00\x00}\x94(\x8c\x05masks\x94]\x94(K\x01J\xfe\xff\xff\xffG@\x0c\x00\x00\x00\x00\x00\x00e\x8c\x04name\x94C\x01x\x94\x8c\x02ok\x94\x88N\x86\x94u.";
+
+        let value = unpickle(bytes).unwrap();
+
+        assert_eq!(
+            value.get("masks"),
+            Some(&Value::List(vec![
+                Value::Int(1),
+                Value::Int(-2),
+                Value::Float(3.5)
+            ]))
+        );
+
+        assert_eq!(value.get("name"), Some(&Value::Bytes(b"x")));
+        assert_eq!(
+            value.get("ok"),
+            Some(&Value::Tuple(vec![Value::Bool(true), Value::None]))
+        );
+
+        assert_eq!(long(&[0xff, 0x00]), Value::Int(255));
+        assert_eq!(long(&[0xff]), Value::Int(-1));
+
+        // Truncated streams and unsupported opcodes fail without panicking
+        for end in 0..bytes.len() - 1 {
+            assert!(unpickle(&bytes[..end]).is_err());
+        }
+
+        assert!(unpickle(b"\x80\x04I1\n.").is_err());
+    }
+
+    #[test]
+    fn test_unpickle_memo_expansion() {
+        // Each list holds two copies of the previous one, doubling the decoded tree
+        let nested = |depth: u8| {
+            let mut bytes = b"\x80\x04]\x94".to_vec();
+
+            for key in 0..depth {
+                bytes.extend([b'(', b'h', key, b'h', key, b'l', 0x94]);
+            }
+
+            bytes.push(b'.');
+            bytes
+        };
+
+        let bytes = nested(4);
+        let value = unpickle(&bytes).unwrap();
+        assert_eq!(value.count(), (1 << 5) - 1);
+
+        let err = unpickle(&nested(64)).unwrap_err().to_string();
+        assert!(err.contains("expand to more than"), "{}", err);
+    }
+}
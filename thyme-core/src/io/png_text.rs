@@ -0,0 +1,153 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use png::{BitDepth, ColorType, Decoder, Encoder};
+
+use crate::error::ThymeError;
+use crate::io::write_atomic;
+
+/// Write 8 or 16-bit interleaved samples to a PNG with text chunks
+///
+/// Text chunks are written as uncompressed iTXt chunks before the image data
+/// so that the text is UTF-8 encoded and is read back without decoding the
+/// image. Sixteen-bit samples are written in big-endian byte order.
+///
+/// # Arguments
+///
+/// * `path` - Path to output image
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+/// * `channels` - Number of channels (1 to 4)
+/// * `data` - Interleaved samples as bytes
+/// * `depth` - Bits per sample (8 or 16)
+/// * `text` - Keyword and text of each chunk
+pub(crate) fn write_png_text<P: AsRef<Path>>(
+    path: P,
+    width: u32,
+    height: u32,
+    channels: u32,
+    data: &[u8],
+    depth: u8,
+    text: &[(&str, &str)],
+) -> Result<(), ThymeError> {
+    let color = match channels {
+        1 => ColorType::Grayscale,
+        2 => ColorType::GrayscaleAlpha,
+        3 => ColorType::Rgb,
+        4 => ColorType::Rgba,
+        _ => {
+            return Err(ThymeError::ImageError(
+                "Only 1 to 4 channel images can be saved as a png with text chunks.",
+            ));
+        }
+    };
+
+    let depth = match depth {
+        8 => BitDepth::Eight,
+        16 => BitDepth::Sixteen,
+        _ => {
+            return Err(ThymeError::ImageError(
+                "Only 8 or 16-bit images can be saved as a png with text chunks.",
+            ));
+        }
+    };
+
+    write_atomic(path, |file| {
+        let mut encoder = Encoder::new(file, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+
+        for (keyword, text) in text {
+            encoder
+                .add_itxt_chunk(keyword.to_string(), text.to_string())
+                .map_err(|_| ThymeError::ImageWriteError)?;
+        }
+
+        let mut writer = encoder
+            .write_header()
+            .map_err(|_| ThymeError::ImageWriteError)?;
+
+        writer
+            .write_image_data(data)
+            .map_err(|_| ThymeError::ImageWriteError)?;
+
+        writer.finish().map_err(|_| ThymeError::ImageWriteError)
+    })
+}
+
+/// Read the text chunks of a PNG
+///
+/// Uncompressed (tEXt), compressed (zTXt), and international (iTXt) chunks
+/// written before the image data are returned in that order. The image data
+/// itself is not decoded.
+///
+/// # Arguments
+///
+/// * `path` - Path to a png image
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::io::read_png_text;
+///
+/// for (keyword, text) in read_png_text("crop.png").unwrap() {
+///     println!("{}: {}", keyword, text);
+/// }
+/// ```
+pub fn read_png_text<P: AsRef<Path>>(path: P) -> Result<Vec<(String, String)>, ThymeError> {
+    let file = File::open(path).map_err(|_| ThymeError::ImageReadError)?;
+
+    let reader = Decoder::new(BufReader::new(file))
+        .read_info()
+        .map_err(|_| ThymeError::ImageReadError)?;
+
+    let info = reader.info();
+
+    let mut text: Vec<(String, String)> = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect();
+
+    for chunk in &info.compressed_latin1_text {
+        let value = chunk.get_text().map_err(|_| ThymeError::ImageReadError)?;
+        text.push((chunk.keyword.clone(), value));
+    }
+
+    for chunk in &info.utf8_text {
+        let value = chunk.get_text().map_err(|_| ThymeError::ImageReadError)?;
+        text.push((chunk.keyword.clone(), value));
+    }
+
+    Ok(text)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_png_text_roundtrip() {
+        let path = std::env::temp_dir().join("thyme_test_png_text_roundtrip.png");
+
+        let data: Vec<u8> = (0..2 * 3 * 3).map(|v| v as u8).collect();
+        let text = [("thyme", "{\"label\":\"cell ü\"}"), ("Source", "image.tif")];
+
+        write_png_text(&path, 2, 3, 3, &data, 8, &text).unwrap();
+
+        let observed = read_png_text(&path).unwrap();
+        assert_eq!(observed.len(), 2);
+        assert_eq!(observed[0], ("thyme".to_string(), text[0].1.to_string()));
+        assert_eq!(observed[1], ("Source".to_string(), text[1].1.to_string()));
+
+        assert!(write_png_text(&path, 2, 3, 5, &data, 8, &text).is_err());
+        assert!(write_png_text(&path, 2, 3, 3, &data, 32, &text).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
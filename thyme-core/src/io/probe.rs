@@ -0,0 +1,333 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+use image::{ColorType as DynamicColorType, ImageDecoder, ImageReader};
+use npyz::{DType, NpyFile, TypeChar};
+use tiff::ColorType;
+use tiff::decoder::Decoder;
+use tiff::tags::Tag;
+
+use crate::error::ThymeError;
+
+/// Image properties read from a file header
+///
+/// Channel counts match the image returned by `ThymeImage::open`, so alpha
+/// channels are not counted and palette images are reported as rgb.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    pub channels: u32,
+    /// Subpixel data type (e.g. u8, u16, f32)
+    pub dtype: String,
+}
+
+impl ImageInfo {
+    fn new(width: u32, height: u32, channels: u32, dtype: &str) -> Self {
+        Self {
+            width,
+            height,
+            channels,
+            dtype: dtype.to_string(),
+        }
+    }
+}
+
+/// Read the dimensions, channel count, and data type of an image
+///
+/// Only the file header is read for png (IHDR chunk), tiff (first IFD), and
+/// npy files. Other formats are probed using the header decoder of the image
+/// library without decoding pixel data.
+///
+/// # Arguments
+///
+/// * `path` - Path to an image or mask
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::io::probe_image;
+///
+/// let info = probe_image("image.png").unwrap();
+/// println!("{}x{}x{} {}", info.width, info.height, info.channels, info.dtype);
+/// ```
+pub fn probe_image<P: AsRef<Path>>(path: P) -> Result<ImageInfo, ThymeError> {
+    let path = path.as_ref();
+
+    let extension = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+        .ok_or(ThymeError::ImageExtensionError)?;
+
+    match extension.as_str() {
+        "png" => probe_png(path),
+        "tif" | "tiff" => probe_tiff(path),
+        "npy" => probe_npy(path),
+        _ => probe_dynamic(path),
+    }
+}
+
+/// Read the png IHDR chunk that immediately follows the file signature
+fn probe_png(path: &Path) -> Result<ImageInfo, ThymeError> {
+    let mut header = [0u8; 26];
+
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_err(|_| ThymeError::ImageReadError)?;
+
+    if header[..8] != [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+        || &header[12..16] != b"IHDR"
+    {
+        return Err(ThymeError::ImageFormatError);
+    }
+
+    let width = u32::from_be_bytes(header[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(header[20..24].try_into().unwrap());
+
+    let channels = match header[25] {
+        0 | 4 => 1,
+        2 | 3 | 6 => 3,
+        _ => return Err(ThymeError::ImageFormatError),
+    };
+
+    // Bit depths below 8 are expanded to 8-bit on decoding
+    let dtype = if header[24] == 16 { "u16" } else { "u8" };
+
+    Ok(ImageInfo::new(width, height, channels, dtype))
+}
+
+/// Read the dimensions and sample format from the first tiff IFD
+fn probe_tiff(path: &Path) -> Result<ImageInfo, ThymeError> {
+    let file = File::open(path).map_err(|_| ThymeError::ImageReadError)?;
+    let mut decoder = Decoder::new(BufReader::new(file)).map_err(|_| ThymeError::ImageReadError)?;
+
+    probe_tiff_ifd(&mut decoder)
+}
+
+/// Read the dimensions and sample format from the current tiff IFD
+fn probe_tiff_ifd<R: Read + Seek>(decoder: &mut Decoder<R>) -> Result<ImageInfo, ThymeError> {
+    let (width, height) = decoder
+        .dimensions()
+        .map_err(|_| ThymeError::ImageReadError)?;
+
+    let (channels, bits) = match decoder
+        .colortype()
+        .map_err(|_| ThymeError::ImageFormatError)?
+    {
+        ColorType::Gray(bits) | ColorType::GrayA(bits) => (1, bits),
+        ColorType::RGB(bits) | ColorType::RGBA(bits) | ColorType::Palette(bits) => (3, bits),
+        _ => return Err(ThymeError::ImageFormatError),
+    };
+
+    // Sample format is 1 (unsigned), 2 (signed), or 3 (float) and defaults to unsigned
+    let format = decoder
+        .find_tag_unsigned::<u16>(Tag::SampleFormat)
+        .ok()
+        .flatten()
+        .unwrap_or(1);
+
+    let dtype = match (format, bits) {
+        (1, 1..=8) => "u8",
+        (1, 16) => "u16",
+        (1, 32) => "u32",
+        (1, 64) => "u64",
+        (2, 32) => "i32",
+        (2, 64) => "i64",
+        (3, 32) => "f32",
+        (3, 64) => "f64",
+        _ => return Err(ThymeError::ImageFormatError),
+    };
+
+    Ok(ImageInfo::new(width, height, channels, dtype))
+}
+
+/// Read the dimensions of each resolution level of an image
+///
+/// Pyramidal tiffs store reduced-resolution copies of the image as IFDs that
+/// follow the full-resolution IFD. Levels are read while each IFD is smaller
+/// than the previous one in both dimensions and has the same channel count, so
+/// that multi-page stacks and trailing images (e.g. slide labels) are not
+/// counted. Non-pyramidal tiffs and all other formats have a single level.
+///
+/// # Arguments
+///
+/// * `path` - Path to an image
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::io::probe_levels;
+///
+/// for (level, info) in probe_levels("slide.tif").unwrap().iter().enumerate() {
+///     println!("level {}: {}x{}", level, info.width, info.height);
+/// }
+/// ```
+pub fn probe_levels<P: AsRef<Path>>(path: P) -> Result<Vec<ImageInfo>, ThymeError> {
+    let path = path.as_ref();
+
+    let tiff = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|s| s.eq_ignore_ascii_case("tif") || s.eq_ignore_ascii_case("tiff"));
+
+    if !tiff {
+        return Ok(vec![probe_image(path)?]);
+    }
+
+    let file = File::open(path).map_err(|_| ThymeError::ImageReadError)?;
+    let mut decoder = Decoder::new(BufReader::new(file)).map_err(|_| ThymeError::ImageReadError)?;
+
+    let mut levels = vec![probe_tiff_ifd(&mut decoder)?];
+
+    while decoder.more_images() {
+        if decoder.next_image().is_err() {
+            break;
+        }
+
+        let Ok(info) = probe_tiff_ifd(&mut decoder) else {
+            break;
+        };
+
+        let previous = levels.last().unwrap();
+
+        if info.width >= previous.width
+            || info.height >= previous.height
+            || info.channels != previous.channels
+        {
+            break;
+        }
+
+        levels.push(info);
+    }
+
+    Ok(levels)
+}
+
+/// Read the shape and data type from the npy header
+fn probe_npy(path: &Path) -> Result<ImageInfo, ThymeError> {
+    let file = File::open(path).map_err(|_| ThymeError::ImageReadError)?;
+    let npy = NpyFile::new(BufReader::new(file)).map_err(|_| ThymeError::ImageReadError)?;
+
+    let shape = npy.shape();
+
+    let (height, width, channels) = match shape.len() {
+        2 => (shape[0] as u32, shape[1] as u32, 1),
+        3 => (shape[0] as u32, shape[1] as u32, shape[2] as u32),
+        _ => {
+            return Err(ThymeError::ImageError(
+                "Numpy array inputs must have an (H, W) or (H, W, C) shape.",
+            ));
+        }
+    };
+
+    let dtype = match npy.dtype() {
+        DType::Plain(x) => match (x.type_char(), x.size_field()) {
+            (TypeChar::Uint, 1) => "u8",
+            (TypeChar::Uint, 2) => "u16",
+            (TypeChar::Uint, 4) => "u32",
+            (TypeChar::Uint, 8) => "u64",
+            (TypeChar::Int, 4) => "i32",
+            (TypeChar::Int, 8) => "i64",
+            (TypeChar::Float, 4) => "f32",
+            (TypeChar::Float, 8) => "f64",
+            _ => return Err(ThymeError::ImageFormatError),
+        },
+        _ => return Err(ThymeError::ImageFormatError),
+    };
+
+    Ok(ImageInfo::new(width, height, channels, dtype))
+}
+
+/// Read the dimensions and color type using the image library header decoder
+fn probe_dynamic(path: &Path) -> Result<ImageInfo, ThymeError> {
+    let decoder = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|_| ThymeError::ImageReadError)?
+        .into_decoder()
+        .map_err(|_| ThymeError::ImageReadError)?;
+
+    let (width, height) = decoder.dimensions();
+
+    let (channels, dtype) = match decoder.color_type() {
+        DynamicColorType::L8 | DynamicColorType::La8 => (1, "u8"),
+        DynamicColorType::L16 | DynamicColorType::La16 => (1, "u16"),
+        DynamicColorType::Rgb8 | DynamicColorType::Rgba8 => (3, "u8"),
+        DynamicColorType::Rgb16 | DynamicColorType::Rgba16 => (3, "u16"),
+        DynamicColorType::Rgb32F | DynamicColorType::Rgba32F => (3, "f32"),
+        _ => return Err(ThymeError::ImageFormatError),
+    };
+
+    Ok(ImageInfo::new(width, height, channels, dtype))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::im::{ThymeImage, ThymeMask};
+
+    const TEST_DIR: &str = "../data/tests";
+
+    #[test]
+    fn test_probe_matches_open() {
+        for name in [
+            "test_rgb.png",
+            "test_grayscale.png",
+            "test_rgb.tif",
+            "test_grayscale.tif",
+            "test_rgb.jpeg",
+            "test_grayscale.bmp",
+            "test_rgb_u16.npy",
+            "test_grayscale_f32.npy",
+            "test_rgb_i64.npy",
+        ] {
+            let path = Path::new(TEST_DIR).join(name);
+
+            let info = probe_image(&path).unwrap();
+            let image = ThymeImage::open(&path).unwrap();
+
+            assert_eq!(
+                (info.height, info.width, info.channels),
+                image.shape(),
+                "{}",
+                name
+            );
+        }
+
+        let info = probe_image(Path::new(TEST_DIR).join("test_rgb_u16.npy")).unwrap();
+        assert_eq!(info.dtype, "u16");
+    }
+
+    #[test]
+    fn test_probe_masks() {
+        for name in [
+            "test_mask_integer.png",
+            "test_mask_integer_300_u16.png",
+            "test_mask_integer_300_u32.tif",
+            "test_mask_integer_u16.npy",
+        ] {
+            let path = Path::new(TEST_DIR).join(name);
+
+            let info = probe_image(&path).unwrap();
+            let mask = ThymeMask::open(&path).unwrap();
+
+            assert_eq!((info.width, info.height), (mask.width(), mask.height()));
+            assert_eq!(info.channels, 1);
+        }
+
+        let info = probe_image(Path::new(TEST_DIR).join("test_mask_integer_300_u32.tif")).unwrap();
+        assert_eq!(info.dtype, "u32");
+    }
+
+    #[test]
+    fn test_probe_invalid() {
+        assert!(probe_image(Path::new(TEST_DIR).join("test_polygons.json")).is_err());
+        assert!(probe_image(Path::new(TEST_DIR).join("missing.png")).is_err());
+    }
+}
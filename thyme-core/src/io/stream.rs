@@ -0,0 +1,432 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::constant::POLYGON_JSON_VALID_KEYS;
+use crate::error::ThymeError;
+
+/// Position of a polygon stream within the json document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// The polygon array has not been found yet
+    Start,
+    /// The next value is the first polygon of the array
+    First,
+    /// The next value follows a polygon
+    Next,
+    /// The array has been read or reading failed
+    Done,
+}
+
+/// Stream polygons from a json file one at a time
+///
+/// Polygons are read from the first array stored under a valid polygon key
+/// (e.g. polygons, contours) without reading the file into memory, so only a
+/// single polygon is held at a time. Values stored under other keys are
+/// skipped and anything after the polygon array is never read. Unlike
+/// `Polygons::open`, polygons are not validated or repaired.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use thyme_core::io::PolygonStream;
+///
+/// let json = r#"{"parents": [null, 1], "polygons": [[[0, 0], [2, 0], [2, 2]], [[1, 1], [3, 1.5], [3, 3]]]}"#;
+///
+/// let polygons: Vec<Vec<[f32; 2]>> = PolygonStream::new(Cursor::new(json))
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+///
+/// assert_eq!(polygons.len(), 2);
+/// assert_eq!(polygons[1], [[1., 1.], [3., 1.5], [3., 3.]]);
+/// ```
+pub struct PolygonStream<R: BufRead> {
+    reader: R,
+    state: State,
+}
+
+impl PolygonStream<BufReader<File>> {
+    /// Open a stream of polygons from a json file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to a polygons json file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ThymeError> {
+        let path = path.as_ref();
+
+        let is_json = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+        if !is_json {
+            return Err(ThymeError::PolygonsReadError);
+        }
+
+        let file = File::open(path).map_err(|err| ThymeError::NoFileError(err.to_string()))?;
+
+        Ok(Self::new(BufReader::new(file)))
+    }
+}
+
+impl<R: BufRead> PolygonStream<R> {
+    /// Initialize a stream of polygons from a reader of json data
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Buffered reader of a json document
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            state: State::Start,
+        }
+    }
+
+    /// Peek the next byte without consuming it
+    fn peek(&mut self) -> Result<Option<u8>, ThymeError> {
+        let buffer = self
+            .reader
+            .fill_buf()
+            .map_err(|_| ThymeError::PolygonsReadError)?;
+
+        Ok(buffer.first().copied())
+    }
+
+    /// Consume and return the next byte, failing at the end of the input
+    fn next_byte(&mut self) -> Result<u8, ThymeError> {
+        let byte = self.peek()?.ok_or(ThymeError::PolygonsReadError)?;
+        self.reader.consume(1);
+        Ok(byte)
+    }
+
+    /// Consume whitespace and return the following byte without consuming it
+    fn peek_token(&mut self) -> Result<u8, ThymeError> {
+        loop {
+            match self.peek()? {
+                Some(b' ' | b'\n' | b'\r' | b'\t') => self.reader.consume(1),
+                Some(byte) => return Ok(byte),
+                None => return Err(ThymeError::PolygonsReadError),
+            }
+        }
+    }
+
+    /// Consume the next token if it is the expected byte
+    fn expect(&mut self, expected: u8) -> Result<(), ThymeError> {
+        if self.peek_token()? == expected {
+            self.reader.consume(1);
+            Ok(())
+        } else {
+            Err(ThymeError::PolygonsReadError)
+        }
+    }
+
+    /// Read a string token, returning its raw bytes without unescaping
+    fn read_string(&mut self) -> Result<Vec<u8>, ThymeError> {
+        self.expect(b'"')?;
+
+        let mut string = vec![];
+
+        loop {
+            match self.next_byte()? {
+                b'"' => return Ok(string),
+                b'\\' => {
+                    string.push(b'\\');
+                    string.push(self.next_byte()?);
+                }
+                byte => string.push(byte),
+            }
+        }
+    }
+
+    /// Read a number token
+    ///
+    /// The NaN, Infinity, and -Infinity tokens written by Python's json module
+    /// are read as non-finite values so that they fail a single polygon rather
+    /// than the whole stream.
+    fn read_number(&mut self) -> Result<f32, ThymeError> {
+        self.peek_token()?;
+
+        let mut number = String::new();
+
+        while let Some(byte) = self.peek()? {
+            if !(byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'+' | b'.')) {
+                break;
+            }
+
+            number.push(byte as char);
+            self.reader.consume(1);
+        }
+
+        number
+            .parse::<f64>()
+            .map(|n| n as f32)
+            .map_err(|_| ThymeError::PolygonsReadError)
+    }
+
+    /// Skip over any json value
+    fn skip_value(&mut self) -> Result<(), ThymeError> {
+        let mut depth: usize = 0;
+
+        loop {
+            match self.peek_token()? {
+                b'"' => {
+                    self.read_string()?;
+                }
+                b'{' | b'[' => {
+                    self.reader.consume(1);
+                    depth += 1;
+                    continue;
+                }
+                b'}' | b']' if depth > 0 => {
+                    self.reader.consume(1);
+                    depth -= 1;
+                }
+                b',' | b':' if depth > 0 => {
+                    self.reader.consume(1);
+                    continue;
+                }
+                _ => {
+                    // Numbers, booleans, and null end at a delimiter
+                    while let Some(byte) = self.peek()? {
+                        if matches!(byte, b',' | b']' | b'}' | b' ' | b'\n' | b'\r' | b'\t') {
+                            break;
+                        }
+                        self.reader.consume(1);
+                    }
+                }
+            }
+
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Advance to the opening bracket of the first polygon array
+    fn find_polygons(&mut self) -> Result<(), ThymeError> {
+        self.expect(b'{')?;
+
+        loop {
+            if self.peek_token()? == b'}' {
+                return Err(ThymeError::PolygonsReadError);
+            }
+
+            let key = self.read_string()?;
+            self.expect(b':')?;
+
+            let valid = POLYGON_JSON_VALID_KEYS
+                .iter()
+                .any(|k| k.as_bytes() == key.as_slice());
+
+            if valid && self.peek_token()? == b'[' {
+                self.reader.consume(1);
+                return Ok(());
+            }
+
+            self.skip_value()?;
+
+            match self.peek_token()? {
+                b',' => self.reader.consume(1),
+                _ => return Err(ThymeError::PolygonsReadError),
+            }
+        }
+    }
+
+    /// Read a single polygon as an array of (x, y) points
+    fn read_polygon(&mut self) -> Result<Vec<[f32; 2]>, ThymeError> {
+        self.expect(b'[')?;
+
+        let mut polygon = vec![];
+
+        if self.peek_token()? == b']' {
+            self.reader.consume(1);
+            return Ok(polygon);
+        }
+
+        loop {
+            self.expect(b'[')?;
+            let x = self.read_number()?;
+            self.expect(b',')?;
+            let y = self.read_number()?;
+            self.expect(b']')?;
+
+            polygon.push([x, y]);
+
+            match self.next_token()? {
+                b',' => continue,
+                b']' => return Ok(polygon),
+                _ => return Err(ThymeError::PolygonsReadError),
+            }
+        }
+    }
+
+    /// Consume and return the next non-whitespace byte
+    fn next_token(&mut self) -> Result<u8, ThymeError> {
+        let byte = self.peek_token()?;
+        self.reader.consume(1);
+        Ok(byte)
+    }
+
+    /// Read the next polygon, or None at the end of the polygon array
+    fn read_next(&mut self) -> Result<Option<Vec<[f32; 2]>>, ThymeError> {
+        if self.state == State::Start {
+            self.find_polygons()?;
+            self.state = State::First;
+        }
+
+        match (self.state, self.peek_token()?) {
+            (State::First | State::Next, b']') => {
+                self.reader.consume(1);
+                return Ok(None);
+            }
+            (State::Next, b',') => self.reader.consume(1),
+            (State::Next, _) => return Err(ThymeError::PolygonsReadError),
+            _ => {}
+        }
+
+        let polygon = self.read_polygon()?;
+        self.state = State::Next;
+        Ok(Some(polygon))
+    }
+}
+
+impl<R: BufRead> Iterator for PolygonStream<R> {
+    type Item = Result<Vec<[f32; 2]>, ThymeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.state == State::Done {
+            return None;
+        }
+
+        let next = self.read_next();
+
+        if !matches!(next, Ok(Some(_))) {
+            self.state = State::Done;
+        }
+
+        next.transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::{BufWriter, Cursor, Write};
+
+    use super::*;
+    use crate::im::Polygons;
+    use crate::io::tracked::peak_allocation;
+
+    fn stream(json: &str) -> Result<Vec<Vec<[f32; 2]>>, ThymeError> {
+        PolygonStream::new(Cursor::new(json)).collect()
+    }
+
+    #[test]
+    fn test_polygon_stream_matches_open() {
+        let path = "../data/tests/test_polygons.json";
+
+        // Streamed polygons are repaired like opened polygons when validated
+        let mut polygons = Polygons::try_from_iter(PolygonStream::open(path).unwrap()).unwrap();
+        polygons.validate_and_repair();
+
+        assert_eq!(
+            polygons.as_points(),
+            Polygons::open(path).unwrap().as_points()
+        );
+    }
+
+    #[test]
+    fn test_polygon_stream_json() {
+        let polygons = stream(
+            r#" { "name": "a \"b\" [c]", "meta": {"size": [1, 2], "ok": true},
+                "outlines" : [ [ [0,0] , [ 1e1 , -2.5 ] ,[3,4] ] , [] ] , "tail": [ "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            polygons,
+            vec![vec![[0., 0.], [10., -2.5], [3., 4.]], vec![]]
+        );
+        assert_eq!(stream(r#"{"polygons": []}"#).unwrap().len(), 0);
+
+        let nonfinite = stream(r#"{"polygons": [[[NaN, 0], [Infinity, -Infinity]]]}"#).unwrap();
+        assert!(nonfinite[0][0][0].is_nan());
+        assert_eq!(nonfinite[0][1], [f32::INFINITY, f32::NEG_INFINITY]);
+
+        for invalid in [
+            "",
+            "[]",
+            r#"{"boxes": [[0, 0, 1, 1]]}"#,
+            r#"{"polygons": [[[0, 0, 1]]]}"#,
+            r#"{"polygons": [[[0, "a"]]]}"#,
+            r#"{"polygons": [[[0, true]]]}"#,
+            r#"{"polygons": [[[0, 0]] [[1, 1]]]}"#,
+            r#"{"polygons": [[[0, 0]]"#,
+        ] {
+            assert!(stream(invalid).is_err(), "{}", invalid);
+        }
+
+        // Polygons read before an error are still yielded
+        let mut partial = PolygonStream::new(Cursor::new(r#"{"polygons": [[[0, 0]], [[x"#));
+        assert!(partial.next().unwrap().is_ok());
+        assert!(partial.next().unwrap().is_err());
+        assert!(partial.next().is_none());
+
+        assert!(PolygonStream::open("../data/tests/test_polygons.npy").is_err());
+    }
+
+    #[test]
+    fn test_polygon_stream_memory() {
+        let path = std::env::temp_dir().join("thyme_test_polygon_stream_memory.json");
+        let n = 1_000_000;
+
+        let mut writer = BufWriter::new(File::create(&path).unwrap());
+        write!(writer, "{{\"polygons\": [").unwrap();
+
+        for i in 0..n {
+            let (x, y) = ((i % 1000) as f32 * 2.5, (i / 1000) as f32 * 2.5);
+            let separator = if i == 0 { "" } else { "," };
+
+            write!(
+                writer,
+                "{}[[{},{}],[{},{}],[{},{}],[{},{}]]",
+                separator,
+                x,
+                y,
+                x + 2.,
+                y,
+                x + 2.,
+                y + 2.,
+                x,
+                y + 2.
+            )
+            .unwrap();
+        }
+
+        write!(writer, "]}}").unwrap();
+        drop(writer);
+
+        let mut count = 0;
+        let mut area = 0.0;
+
+        let peak = peak_allocation(|| {
+            for polygon in PolygonStream::open(&path).unwrap() {
+                let polygons = Polygons::new(vec![polygon.unwrap()]).unwrap();
+                area += polygons.areas()[0] as f64;
+                count += 1;
+            }
+        });
+
+        assert_eq!(count, n);
+        assert!((area - 4.0 * n as f64).abs() < 1e-3 * n as f64);
+
+        // Materializing every vertex would take more than 32 MB
+        assert!(peak < 1 << 20, "Peak allocation of {} bytes", peak);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,15 +1,115 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::collections::{BTreeSet, HashMap};
 use std::fs::File;
 use std::path::Path;
 
 use polars::prelude::*;
+use regex::Regex;
 
+use crate::constant;
 use crate::error::ThymeError;
+use crate::io::geometry::check_flat;
+use crate::io::write_atomic;
+
+/// Formatting of float values in CSV and TSV tables
+///
+/// By default floats are written with the shortest representation that
+/// round-trips, which uses scientific notation for very small or large values
+/// (e.g. 1.2e-7). With a precision, floats are written with that many digits
+/// after the decimal point and never in scientific notation. With a fixed
+/// format and no precision, floats are written with the shortest round-trip
+/// digits of their data type without scientific notation. Binary table formats
+/// are unaffected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FloatFormat {
+    /// Number of digits after the decimal point
+    pub precision: Option<usize>,
+    /// Never write floats in scientific notation
+    pub fixed: bool,
+}
+
+impl FloatFormat {
+    /// Initialize a float format from a precision and a format name
+    ///
+    /// # Arguments
+    ///
+    /// * `precision` - Number of digits after the decimal point
+    /// * `format` - Float format (auto or fixed)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_core::io::FloatFormat;
+    ///
+    /// let format = FloatFormat::new(Some(3), "fixed").unwrap();
+    /// assert_eq!((format.precision, format.fixed), (Some(3), true));
+    ///
+    /// assert_eq!(FloatFormat::new(None, "auto").unwrap(), FloatFormat::default());
+    /// assert!(FloatFormat::new(None, "scientific").is_err());
+    /// ```
+    pub fn new(precision: Option<usize>, format: &str) -> Result<Self, ThymeError> {
+        if !constant::SUPPORTED_FLOAT_FORMATS.contains(&format) {
+            return Err(ThymeError::OtherError(format!(
+                "Invalid float_format {}. Must be one of: {:?}",
+                format,
+                constant::SUPPORTED_FLOAT_FORMATS
+            )));
+        }
+
+        let fixed = format == "fixed";
+
+        Ok(FloatFormat { precision, fixed })
+    }
+
+    /// Write a table with a CSV writer using the float format
+    ///
+    /// Polars writes fixed floats without a precision by widening them to
+    /// f64, so f32 columns are formatted as strings with f32 digits instead.
+    fn finish<W: std::io::Write>(
+        &self,
+        writer: CsvWriter<W>,
+        df: &mut DataFrame,
+    ) -> PolarsResult<()> {
+        let mut writer = writer
+            .with_float_precision(self.precision)
+            .with_float_scientific(self.fixed.then_some(false));
+
+        let formatted = df
+            .get_columns()
+            .iter()
+            .any(|column| column.dtype() == &DataType::Float32);
+
+        if !self.fixed || self.precision.is_some() || !formatted {
+            return writer.finish(df);
+        }
+
+        let columns = df
+            .get_columns()
+            .iter()
+            .map(|column| match column.dtype() {
+                DataType::Float32 => column
+                    .f32()
+                    .unwrap()
+                    .into_iter()
+                    .map(|value| value.map(|value| value.to_string()))
+                    .collect::<StringChunked>()
+                    .with_name(column.name().clone())
+                    .into_column(),
+                _ => column.clone(),
+            })
+            .collect();
+
+        writer.finish(&mut DataFrame::new(columns)?)
+    }
+}
 
 /// Write a table to a CSV file
 ///
+/// Records end with a line feed on every platform, and fields containing a
+/// comma, quote, or line break (e.g. in image names) are quoted.
+///
 /// # Arguments
 ///
 /// * `df` - A DataFrame
@@ -32,17 +132,48 @@ pub fn write_table_csv<P: AsRef<Path>>(
     path: P,
     header: bool,
 ) -> Result<(), ThymeError> {
-    let mut output: File = File::create(&path).map_err(|_| {
-        ThymeError::OtherError(format!(
-            "Failed to create CSV file: {}",
-            path.as_ref().to_str().unwrap()
-        ))
-    })?;
+    write_table_csv_with_format(df, path, header, &FloatFormat::default())
+}
+
+/// Write a table to a CSV file with a specified float format
+///
+/// # Arguments
+///
+/// * `df` - A DataFrame
+/// * `output` - A string containing the name of the output file
+/// * `header` - A boolean indicating whether the output file should contain a header
+/// * `float_format` - Precision and notation of float values
+///
+/// # Examples
+///
+/// ```no_run
+/// use polars::prelude::*;
+/// use thyme_core::io::{FloatFormat, write_table_csv_with_format};
+///
+/// let column = vec![Column::new("area".into(), [2.5, 3.1, 1.2e-7])];
+/// let mut df: DataFrame = DataFrame::new(column).unwrap();
+///
+/// let float_format = FloatFormat::new(Some(3), "fixed").unwrap();
+/// write_table_csv_with_format(&mut df, "output.csv", true, &float_format).unwrap()
+/// ```
+pub fn write_table_csv_with_format<P: AsRef<Path>>(
+    df: &mut DataFrame,
+    path: P,
+    header: bool,
+    float_format: &FloatFormat,
+) -> Result<(), ThymeError> {
+    check_flat(df, "CSV")?;
+
+    write_atomic(&path, |output| {
+        let writer = CsvWriter::new(output)
+            .include_header(header)
+            .with_line_terminator("\n".to_string())
+            .with_quote_style(QuoteStyle::Necessary);
 
-    CsvWriter::new(&mut output)
-        .include_header(header)
-        .finish(df)
-        .map_err(|_| ThymeError::OtherError("Failed to write CSV file.".to_string()))
+        float_format
+            .finish(writer, df)
+            .map_err(|_| ThymeError::OtherError("Failed to write CSV file.".to_string()))
+    })
 }
 
 /// Write a table to a TSV file
@@ -69,18 +200,49 @@ pub fn write_table_tsv<P: AsRef<Path>>(
     path: P,
     header: bool,
 ) -> Result<(), ThymeError> {
-    let mut output: File = File::create(&path).map_err(|_| {
-        ThymeError::OtherError(format!(
-            "Failed to create TSV file: {}",
-            path.as_ref().to_str().unwrap()
-        ))
-    })?;
+    write_table_tsv_with_format(df, path, header, &FloatFormat::default())
+}
+
+/// Write a table to a TSV file with a specified float format
+///
+/// # Arguments
+///
+/// * `df` - A DataFrame
+/// * `output` - A string containing the name of the output file
+/// * `header` - A boolean indicating whether the output file should contain a header
+/// * `float_format` - Precision and notation of float values
+///
+/// # Examples
+///
+/// ```no_run
+/// use polars::prelude::*;
+/// use thyme_core::io::{FloatFormat, write_table_tsv_with_format};
+///
+/// let column = vec![Column::new("area".into(), [2.5, 3.1, 1.2e-7])];
+/// let mut df: DataFrame = DataFrame::new(column).unwrap();
+///
+/// let float_format = FloatFormat::new(None, "fixed").unwrap();
+/// write_table_tsv_with_format(&mut df, "output.tsv", true, &float_format).unwrap()
+/// ```
+pub fn write_table_tsv_with_format<P: AsRef<Path>>(
+    df: &mut DataFrame,
+    path: P,
+    header: bool,
+    float_format: &FloatFormat,
+) -> Result<(), ThymeError> {
+    check_flat(df, "TSV")?;
 
-    CsvWriter::new(&mut output)
-        .include_header(header)
-        .with_separator("\t".as_bytes()[0])
-        .finish(df)
-        .map_err(|_| ThymeError::OtherError("Failed to write TSV file.".to_string()))
+    write_atomic(&path, |output| {
+        let writer = CsvWriter::new(output)
+            .include_header(header)
+            .with_separator("\t".as_bytes()[0])
+            .with_line_terminator("\n".to_string())
+            .with_quote_style(QuoteStyle::Necessary);
+
+        float_format
+            .finish(writer, df)
+            .map_err(|_| ThymeError::OtherError("Failed to write TSV file.".to_string()))
+    })
 }
 
 /// Write a table to a parquet file
@@ -102,17 +264,56 @@ pub fn write_table_tsv<P: AsRef<Path>>(
 /// write_table_pq(&mut df, "output.pq").unwrap()
 /// ```
 pub fn write_table_pq<P: AsRef<Path>>(df: &mut DataFrame, path: P) -> Result<(), ThymeError> {
-    let mut output: File = File::create(&path).map_err(|_| {
-        ThymeError::OtherError(format!(
-            "Failed to create TSV file: {}",
-            path.as_ref().to_str().unwrap()
-        ))
-    })?;
+    write_atomic(&path, |output| {
+        ParquetWriter::new(output)
+            .finish(df)
+            .map(|_| ())
+            .map_err(|_| ThymeError::OtherError("Failed to write parquet file.".to_string()))
+    })
+}
+
+/// Write a table to an arrow IPC (feather) file
+///
+/// # Arguments
+///
+/// * `df` - A DataFrame
+/// * `output` - A string containing the name of the output file
+/// * `compression` - Compression codec (lz4, zstd, or none)
+///
+/// # Examples
+///
+/// ```no_run
+/// use polars::prelude::*;
+/// use thyme_core::io::write_table_ipc;
+///
+/// let column = vec![Column::new("area".into(), [2.5, 3.1, 3.4])];
+/// let mut df: DataFrame = DataFrame::new(column).unwrap();
+///
+/// write_table_ipc(&mut df, "output.arrow", "lz4").unwrap()
+/// ```
+pub fn write_table_ipc<P: AsRef<Path>>(
+    df: &mut DataFrame,
+    path: P,
+    compression: &str,
+) -> Result<(), ThymeError> {
+    let compression = match compression {
+        "lz4" => Some(IpcCompression::LZ4),
+        "zstd" => Some(IpcCompression::ZSTD),
+        "none" => None,
+        _ => {
+            return Err(ThymeError::OtherError(format!(
+                "Invalid IPC compression {}. Must be one of: lz4, zstd, or none",
+                compression
+            )));
+        }
+    };
 
-    ParquetWriter::new(&mut output)
-        .finish(df)
-        .map(|_| ())
-        .map_err(|_| ThymeError::OtherError("Failed to write parquet file.".to_string()))
+    write_atomic(&path, |output| {
+        IpcWriter::new(output)
+            .with_compression(compression)
+            .finish(df)
+            .map_err(|_| ThymeError::OtherError("Failed to write IPC file.".to_string()))
+    })
 }
 
 /// Write a DataFrame to disk
@@ -135,6 +336,72 @@ pub fn write_table_pq<P: AsRef<Path>>(df: &mut DataFrame, path: P) -> Result<(),
 /// write_table(&mut df, "output.csv").unwrap()
 /// ```
 pub fn write_table<P: AsRef<Path>>(df: &mut DataFrame, path: P) -> Result<(), ThymeError> {
+    write_table_with_compression(df, path, "none")
+}
+
+/// Write a DataFrame to disk with a specified arrow IPC compression codec
+///
+/// # Arguments
+///
+/// * `df` - A DataFrame
+/// * `output` - A string containing the name of the output file
+/// * `ipc_compression` - Compression codec (lz4, zstd, or none) for arrow/feather output
+///
+/// # Notes
+///
+/// The compression codec is ignored for all non-IPC table formats.
+///
+/// # Examples
+///
+/// ```no_run
+/// use polars::prelude::*;
+/// use thyme_core::io::write_table_with_compression;
+///
+/// let column = vec![Column::new("area".into(), [2.5, 3.1, 3.4])];
+/// let mut df: DataFrame = DataFrame::new(column).unwrap();
+///
+/// write_table_with_compression(&mut df, "output.feather", "zstd").unwrap()
+/// ```
+pub fn write_table_with_compression<P: AsRef<Path>>(
+    df: &mut DataFrame,
+    path: P,
+    ipc_compression: &str,
+) -> Result<(), ThymeError> {
+    write_table_with_float_format(df, path, ipc_compression, &FloatFormat::default())
+}
+
+/// Write a DataFrame to disk with a specified IPC compression and float format
+///
+/// # Arguments
+///
+/// * `df` - A DataFrame
+/// * `output` - A string containing the name of the output file
+/// * `ipc_compression` - Compression codec (lz4, zstd, or none) for arrow/feather output
+/// * `float_format` - Precision and notation of float values in csv, tsv, and txt output
+///
+/// # Notes
+///
+/// The compression codec is ignored for all non-IPC table formats and the
+/// float format is ignored for parquet and IPC table formats.
+///
+/// # Examples
+///
+/// ```no_run
+/// use polars::prelude::*;
+/// use thyme_core::io::{FloatFormat, write_table_with_float_format};
+///
+/// let column = vec![Column::new("area".into(), [2.5, 3.1, 3.4])];
+/// let mut df: DataFrame = DataFrame::new(column).unwrap();
+///
+/// let float_format = FloatFormat::new(Some(2), "auto").unwrap();
+/// write_table_with_float_format(&mut df, "output.csv", "none", &float_format).unwrap()
+/// ```
+pub fn write_table_with_float_format<P: AsRef<Path>>(
+    df: &mut DataFrame,
+    path: P,
+    ipc_compression: &str,
+    float_format: &FloatFormat,
+) -> Result<(), ThymeError> {
     let extension = path
         .as_ref()
         .extension()
@@ -143,16 +410,406 @@ pub fn write_table<P: AsRef<Path>>(df: &mut DataFrame, path: P) -> Result<(), Th
 
     if let Some(ext) = extension {
         match ext.as_str() {
-            "csv" => write_table_csv(df, path, true),
-            "tsv" => write_table_tsv(df, path, true),
-            "txt" => write_table_tsv(df, path, true),
+            "csv" => write_table_csv_with_format(df, path, true, float_format),
+            "tsv" => write_table_tsv_with_format(df, path, true, float_format),
+            "txt" => write_table_tsv_with_format(df, path, true, float_format),
             "parquet" => write_table_pq(df, path),
             "pq" => write_table_pq(df, path),
+            "arrow" => write_table_ipc(df, path, ipc_compression),
+            "feather" => write_table_ipc(df, path, ipc_compression),
             _ => Err(ThymeError::OtherError("Failed to write table.".to_string())),
         }
     } else {
         Err(ThymeError::OtherError(
-            "Provided table path has an invalid extension. Must be one of: csv, tsv, txt, parquet, or pq.".to_string()
+            "Provided table path has an invalid extension. Must be one of: csv, tsv, txt, parquet, pq, arrow, or feather.".to_string()
         ))
     }
 }
+
+/// Read a table from disk
+///
+/// # Arguments
+///
+/// * `path` - A path to a csv, tsv, txt, parquet, pq, arrow, or feather table
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::io::read_table;
+///
+/// let df = read_table("platemap.csv").unwrap();
+/// ```
+pub fn read_table<P: AsRef<Path>>(path: P) -> Result<DataFrame, ThymeError> {
+    let extension = path
+        .as_ref()
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+
+    let read_error =
+        || ThymeError::OtherError(format!("Failed to read table: {}", path.as_ref().display()));
+
+    let separator = match extension.as_deref() {
+        Some("csv") => b',',
+        Some("tsv") | Some("txt") => b'\t',
+        Some("parquet") | Some("pq") => {
+            let file = File::open(&path).map_err(|_| read_error())?;
+            return ParquetReader::new(file).finish().map_err(|_| read_error());
+        }
+        Some("arrow") | Some("feather") => {
+            let file = File::open(&path).map_err(|_| read_error())?;
+            return IpcReader::new(file).finish().map_err(|_| read_error());
+        }
+        _ => {
+            return Err(ThymeError::OtherError(
+                "Provided table path has an invalid extension. Must be one of: csv, tsv, txt, parquet, pq, arrow, or feather.".to_string()
+            ));
+        }
+    };
+
+    CsvReadOptions::default()
+        .with_has_header(true)
+        .with_parse_options(CsvParseOptions::default().with_separator(separator))
+        .try_into_reader_with_file_path(Some(path.as_ref().to_path_buf()))
+        .and_then(|reader| reader.finish())
+        .map_err(|_| read_error())
+}
+
+/// Append metadata columns to a table by matching a key derived from each row
+///
+/// # Arguments
+///
+/// * `df` - A DataFrame with a string column used to derive join keys
+/// * `source` - Name of the column in `df` used to derive join keys (e.g. image)
+/// * `metadata` - A DataFrame of metadata (e.g. a platemap)
+/// * `on` - Name of the key column in `metadata` (e.g. well)
+/// * `pattern` - Optional regex used to extract the key from the source column
+///
+/// # Notes
+///
+/// If a pattern is provided, the key is the first capture group if one is
+/// present, otherwise the full match. Without a pattern, the source value is
+/// used directly as the key. Rows without a matching key are assigned nulls
+/// and duplicate keys in the metadata resolve to the first occurrence. Any
+/// metadata column that already exists in `df` is prefixed with `metadata_`.
+/// The number of unique source values without a match is returned.
+///
+/// # Examples
+///
+/// ```
+/// use polars::prelude::*;
+/// use thyme_core::io::join_metadata;
+///
+/// let mut df = DataFrame::new(vec![
+///     Column::new("image".into(), ["plate_A01", "plate_A02"]),
+/// ]).unwrap();
+///
+/// let metadata = DataFrame::new(vec![
+///     Column::new("well".into(), ["A01"]),
+///     Column::new("compound".into(), ["DMSO"]),
+/// ]).unwrap();
+///
+/// let missing = join_metadata(&mut df, "image", &metadata, "well", Some("_([A-P][0-9]{2})")).unwrap();
+///
+/// assert_eq!(missing, 1);
+/// assert_eq!(df.column("compound").unwrap().null_count(), 1);
+/// ```
+pub fn join_metadata(
+    df: &mut DataFrame,
+    source: &str,
+    metadata: &DataFrame,
+    on: &str,
+    pattern: Option<&str>,
+) -> Result<usize, ThymeError> {
+    let pattern = pattern
+        .map(Regex::new)
+        .transpose()
+        .map_err(|_| ThymeError::OtherError("Invalid metadata key pattern.".to_string()))?;
+
+    let keys = metadata
+        .column(on)
+        .and_then(|column| column.cast(&DataType::String))
+        .map_err(|_| {
+            ThymeError::OtherError(format!("Metadata table is missing join column: {}", on))
+        })?;
+
+    let mut lookup: HashMap<&str, IdxSize> = HashMap::new();
+    for (idx, key) in keys.str().unwrap().into_iter().enumerate() {
+        if let Some(key) = key {
+            lookup.entry(key).or_insert(idx as IdxSize);
+        }
+    }
+
+    let names = df
+        .column(source)
+        .and_then(|column| column.str().cloned())
+        .map_err(|_| ThymeError::OtherError(format!("Table is missing column: {}", source)))?;
+
+    let mut missing: BTreeSet<&str> = BTreeSet::new();
+    let mut indices: Vec<Option<IdxSize>> = Vec::with_capacity(names.len());
+
+    for name in names.into_iter() {
+        let key = match (name, &pattern) {
+            (Some(name), Some(pattern)) => pattern.captures(name).and_then(|captures| {
+                captures
+                    .get(1)
+                    .or_else(|| captures.get(0))
+                    .map(|m| m.as_str())
+            }),
+            (Some(name), None) => Some(name),
+            (None, _) => None,
+        };
+
+        let idx = key.and_then(|key| lookup.get(key).copied());
+
+        if let (Some(name), None) = (name, idx) {
+            missing.insert(name);
+        }
+
+        indices.push(idx);
+    }
+
+    let indices = IdxCa::from_iter_options("index".into(), indices.into_iter());
+
+    for column in metadata.get_columns() {
+        let mut joined = column
+            .take(&indices)
+            .map_err(|_| ThymeError::OtherError("Failed to join metadata.".to_string()))?;
+
+        if df.get_column_names().iter().any(|n| *n == column.name()) {
+            joined.rename(format!("metadata_{}", column.name()).into());
+        }
+
+        df.with_column(joined)
+            .map_err(|_| ThymeError::OtherError("Failed to join metadata.".to_string()))?;
+    }
+
+    Ok(missing.len())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const TEST_PLATEMAP: &str = "../data/tests/test_platemap.csv";
+    const TEST_TABLE_CSV: &str = "../data/tests/test_table_ipc.csv";
+    const TEST_TABLE_IPC: &str = "../data/tests/test_table_ipc";
+
+    #[test]
+    fn test_write_table_ipc() {
+        let mut df = DataFrame::new(vec![
+            Column::new("image".into(), ["a", "a", "b"]),
+            Column::new("object".into(), [0u32, 1, 0]),
+            Column::new("area".into(), [2.5f32, 3.25, 4.0]),
+        ])
+        .unwrap();
+
+        write_table(&mut df, TEST_TABLE_CSV).unwrap();
+        let csv = read_table(TEST_TABLE_CSV).unwrap();
+
+        for (ext, compression) in [("arrow", "none"), ("feather", "lz4"), ("arrow", "zstd")] {
+            let path = format!("{}_{}.{}", TEST_TABLE_IPC, compression, ext);
+            write_table_with_compression(&mut df, &path, compression).unwrap();
+
+            let ipc = read_table(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert!(ipc.equals(&df));
+            assert_eq!(ipc.shape(), csv.shape());
+            assert_eq!(ipc.get_column_names(), csv.get_column_names());
+
+            // CSV output is re-read with inferred (e.g. i64 and f64) data types
+            for (a, b) in ipc.get_columns().iter().zip(csv.get_columns()) {
+                assert!(a.cast(b.dtype()).unwrap().equals(b));
+            }
+        }
+
+        std::fs::remove_file(TEST_TABLE_CSV).unwrap();
+
+        assert!(write_table_ipc(&mut df, "output.arrow", "gzip").is_err());
+    }
+
+    #[test]
+    fn test_write_table_float_format() {
+        let path = "../data/tests/test_table_float_format.csv";
+
+        let mut df = DataFrame::new(vec![
+            Column::new("image".into(), ["a", "b", "c"]),
+            Column::new("area".into(), [1.2e-7f32, 3.5e9, 0.1]),
+            Column::new("embedding_0".into(), [2.5e-12f64, 1.0e21, -0.5]),
+        ])
+        .unwrap();
+
+        let read = |df: &mut DataFrame, float_format: FloatFormat| {
+            write_table_with_float_format(df, path, "none", &float_format).unwrap();
+            let contents = std::fs::read_to_string(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            contents
+        };
+
+        // Floats are written in their shortest form by default
+        let contents = read(&mut df, FloatFormat::default());
+        assert!(contents.contains("a,1.2e-7,2.5e-12\n"), "{}", contents);
+
+        // Every float column is written with the requested number of decimals
+        let contents = read(&mut df, FloatFormat::new(Some(3), "auto").unwrap());
+        assert_eq!(
+            contents,
+            "image,area,embedding_0\n\
+             a,0.000,0.000\n\
+             b,3500000000.000,1000000000000000000000.000\n\
+             c,0.100,-0.500\n"
+        );
+
+        let contents = read(&mut df, FloatFormat::new(Some(9), "fixed").unwrap());
+        assert!(
+            contents.contains("a,0.000000120,0.000000000\n"),
+            "{}",
+            contents
+        );
+        assert!(!contents.lines().skip(1).any(|row| row.contains('e')));
+
+        // Without a precision, fixed floats keep the shortest digits of their type
+        let contents = read(&mut df, FloatFormat::new(None, "fixed").unwrap());
+        assert!(!contents.lines().skip(1).any(|row| row.contains('e')));
+        assert!(
+            contents.contains("a,0.00000012,0.0000000000025\n"),
+            "{}",
+            contents
+        );
+        assert!(contents.contains("c,0.1,-0.5\n"), "{}", contents);
+
+        // Tables are re-read as floats
+        let tsv = "../data/tests/test_table_float_format.tsv";
+        let float_format = FloatFormat::new(None, "fixed").unwrap();
+        write_table_with_float_format(&mut df, tsv, "none", &float_format).unwrap();
+        let table = read_table(tsv).unwrap();
+        std::fs::remove_file(tsv).unwrap();
+
+        assert_eq!(table.column("area").unwrap().dtype(), &DataType::Float64);
+        assert_eq!(
+            table.column("area").unwrap().f64().unwrap().get(1),
+            Some(3.5e9)
+        );
+    }
+
+    #[test]
+    fn test_read_table() {
+        let metadata = read_table(TEST_PLATEMAP).unwrap();
+        assert_eq!(metadata.shape(), (3, 3));
+        assert!(read_table("platemap.json").is_err());
+    }
+
+    #[test]
+    fn test_write_table_csv_escaping() {
+        let path = "../data/tests/test_table_escaping.csv";
+
+        let images = [
+            "plate 1, a01_image",
+            "b01 \"dark\"_image",
+            "c01\r\n_image",
+            "d01_image",
+        ];
+
+        let mut df = DataFrame::new(vec![
+            Column::new("image".into(), images),
+            Column::new("area".into(), [1.5f32, 2.0, 2.5, 3.0]),
+        ])
+        .unwrap();
+
+        write_table(&mut df, path).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+
+        // Records end with a single line feed and only fields that need quotes are quoted
+        assert_eq!(contents.matches('\r').count(), 1);
+        assert_eq!(contents.matches('\n').count(), 6);
+        assert!(contents.ends_with("d01_image,3.0\n"));
+        assert!(contents.contains("\"plate 1, a01_image\",1.5\n"));
+        assert!(contents.contains("\"b01 \"\"dark\"\"_image\",2.0\n"));
+
+        let table = read_table(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        // Floats are re-read as f64 so columns are compared after casting
+        for (a, b) in df.get_columns().iter().zip(table.get_columns()) {
+            assert!(a.cast(b.dtype()).unwrap().equals(b));
+        }
+    }
+
+    #[test]
+    fn test_read_table_crlf() {
+        let path = "../data/tests/test_table_crlf.csv";
+
+        std::fs::write(path, "image,well\r\n\"a, b\",A01\r\nc,A02\r\n").unwrap();
+        let table = read_table(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let image = table.column("image").unwrap().str().unwrap();
+        let well = table.column("well").unwrap().str().unwrap();
+
+        assert_eq!(table.get_column_names(), ["image", "well"]);
+        assert_eq!(image.get(0), Some("a, b"));
+        assert_eq!(well.get(0), Some("A01"));
+        assert_eq!(well.get(1), Some("A02"));
+    }
+
+    #[test]
+    fn test_join_metadata() {
+        let images = [
+            "plate1_A01_image",
+            "plate1_A02_image",
+            "plate1_B01_image",
+            "plate1_B02_image",
+        ];
+
+        // Two objects per image
+        let name: Vec<&str> = images.iter().flat_map(|&image| [image, image]).collect();
+        let item: Vec<u32> = (0..8).map(|i| i % 2).collect();
+
+        let mut df = DataFrame::new(vec![
+            Column::new("image".into(), &name),
+            Column::new("object".into(), &item),
+        ])
+        .unwrap();
+
+        let metadata = read_table(TEST_PLATEMAP).unwrap();
+        let missing = join_metadata(
+            &mut df,
+            "image",
+            &metadata,
+            "well",
+            Some("_([A-P][0-9]{2})_"),
+        )
+        .unwrap();
+
+        assert_eq!(missing, 1);
+        assert_eq!(df.shape(), (8, 5));
+
+        let compound = df.column("compound").unwrap().str().unwrap();
+        assert_eq!(compound.get(0), Some("DMSO"));
+        assert_eq!(compound.get(3), Some("Staurosporine"));
+        assert_eq!(compound.get(5), Some("Taxol"));
+        assert_eq!(compound.get(6), None);
+        assert_eq!(compound.get(7), None);
+
+        let concentration = df.column("concentration").unwrap();
+        assert_eq!(concentration.null_count(), 2);
+    }
+
+    #[test]
+    fn test_join_metadata_errors() {
+        let mut df = DataFrame::new(vec![Column::new("image".into(), ["A01"])]).unwrap();
+        let metadata = read_table(TEST_PLATEMAP).unwrap();
+
+        assert!(join_metadata(&mut df, "image", &metadata, "plate", None).is_err());
+        assert!(join_metadata(&mut df, "name", &metadata, "well", None).is_err());
+        assert!(join_metadata(&mut df, "image", &metadata, "well", Some("(")).is_err());
+
+        // Without a pattern the source column is treated as the key
+        assert_eq!(
+            join_metadata(&mut df, "image", &metadata, "well", None).unwrap(),
+            0
+        );
+    }
+}
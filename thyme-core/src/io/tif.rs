@@ -0,0 +1,183 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::io::{Seek, Write};
+
+use tiff::TiffResult;
+use tiff::encoder::colortype::ColorType;
+use tiff::encoder::compression::{Deflate, Lzw, Packbits, Uncompressed};
+use tiff::encoder::{TiffEncoder, TiffValue};
+
+use crate::error::ThymeError;
+
+/// Compression of each page written to a TIFF file
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::io::TiffCompression;
+///
+/// assert_eq!(TiffCompression::parse("lzw").unwrap(), TiffCompression::Lzw);
+/// assert!(TiffCompression::parse("jpeg").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TiffCompression {
+    /// Store pages without compression
+    None,
+    /// Compress pages with LZW
+    Lzw,
+    /// Compress pages with deflate (zlib)
+    #[default]
+    Deflate,
+    /// Compress pages with PackBits run-length encoding
+    Packbits,
+}
+
+impl TiffCompression {
+    /// Parse a compression from a string (none, lzw, deflate, packbits)
+    ///
+    /// # Arguments
+    ///
+    /// * `compression` - String representation of the compression
+    pub fn parse(compression: &str) -> Result<TiffCompression, ThymeError> {
+        match compression.trim().to_lowercase().as_str() {
+            "none" => Ok(TiffCompression::None),
+            "lzw" => Ok(TiffCompression::Lzw),
+            "deflate" => Ok(TiffCompression::Deflate),
+            "packbits" => Ok(TiffCompression::Packbits),
+            "zstd" => Err(ThymeError::OtherError(
+                "Zstd compression is not supported by the TIFF encoder. Must be one of: none, lzw, deflate, packbits".to_string(),
+            )),
+            _ => Err(ThymeError::OtherError(format!(
+                "Invalid TIFF compression '{}'. Must be one of: none, lzw, deflate, packbits",
+                compression
+            ))),
+        }
+    }
+}
+
+/// Write a single page of interleaved samples to a TIFF file
+///
+/// # Arguments
+///
+/// * `encoder` - Encoder of the TIFF file
+/// * `width` - Width of the page
+/// * `height` - Height of the page
+/// * `data` - Interleaved samples of the page
+/// * `compression` - Compression of the page
+pub(crate) fn write_tiff_page<C, W>(
+    encoder: &mut TiffEncoder<W>,
+    width: u32,
+    height: u32,
+    data: &[C::Inner],
+    compression: TiffCompression,
+) -> TiffResult<()>
+where
+    C: ColorType,
+    [C::Inner]: TiffValue,
+    W: Write + Seek,
+{
+    match compression {
+        TiffCompression::None => encoder
+            .new_image_with_compression::<C, _>(width, height, Uncompressed)?
+            .write_data(data),
+        TiffCompression::Lzw => encoder
+            .new_image_with_compression::<C, _>(width, height, Lzw)?
+            .write_data(data),
+        TiffCompression::Deflate => encoder
+            .new_image_with_compression::<C, _>(width, height, Deflate::default())?
+            .write_data(data),
+        TiffCompression::Packbits => encoder
+            .new_image_with_compression::<C, _>(width, height, Packbits)?
+            .write_data(data),
+    }
+}
+
+/// Write each channel of an interleaved image as a separate grayscale page
+///
+/// # Arguments
+///
+/// * `encoder` - Encoder of the TIFF file
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+/// * `channels` - Number of interleaved channels
+/// * `data` - Interleaved samples of the image
+/// * `compression` - Compression of each page
+pub(crate) fn write_tiff_planes<C, W>(
+    encoder: &mut TiffEncoder<W>,
+    width: u32,
+    height: u32,
+    channels: usize,
+    data: &[C::Inner],
+    compression: TiffCompression,
+) -> TiffResult<()>
+where
+    C: ColorType,
+    C::Inner: Copy,
+    [C::Inner]: TiffValue,
+    W: Write + Seek,
+{
+    if channels == 1 {
+        return write_tiff_page::<C, W>(encoder, width, height, data, compression);
+    }
+
+    for c in 0..channels {
+        let plane: Vec<C::Inner> = data.iter().skip(c).step_by(channels).copied().collect();
+        write_tiff_page::<C, W>(encoder, width, height, &plane, compression)?;
+    }
+
+    Ok(())
+}
+
+/// Interleave planes of equal length into a single buffer
+///
+/// # Arguments
+///
+/// * `planes` - Planes of interleaved samples (e.g. one per TIFF page)
+/// * `samples` - Number of samples per pixel in each plane
+pub(crate) fn interleave_planes<T: Copy>(mut planes: Vec<Vec<T>>, samples: usize) -> Vec<T> {
+    if planes.len() == 1 {
+        return planes.pop().unwrap();
+    }
+
+    let pixels = planes[0].len() / samples;
+    let mut interleaved = Vec::with_capacity(pixels * samples * planes.len());
+
+    for i in 0..pixels {
+        for plane in planes.iter() {
+            interleaved.extend_from_slice(&plane[i * samples..(i + 1) * samples]);
+        }
+    }
+
+    interleaved
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_interleave_planes() {
+        let planes = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        assert_eq!(interleave_planes(planes, 1), vec![1, 4, 2, 5, 3, 6]);
+
+        let planes = vec![vec![1, 2, 3, 4]];
+        assert_eq!(interleave_planes(planes, 2), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_tiff_compression_parse() {
+        assert_eq!(
+            TiffCompression::parse(" Deflate ").unwrap(),
+            TiffCompression::Deflate
+        );
+        assert_eq!(
+            TiffCompression::parse("none").unwrap(),
+            TiffCompression::None
+        );
+
+        let err = TiffCompression::parse("zstd").unwrap_err();
+        assert!(err.to_string().contains("not supported"));
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Allocator tracking the live and peak bytes allocated by each thread
+struct CountingAllocator;
+
+thread_local! {
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track(bytes: isize) {
+    let _ = LIVE.try_with(|live| {
+        live.set(live.get() + bytes);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(live.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size() as isize);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(-(layout.size() as isize));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Peak bytes allocated by the current thread while running `f`
+///
+/// Bytes that were already allocated before `f` ran are not counted, and
+/// allocations made by other threads (e.g. tests running in parallel) are
+/// ignored.
+pub(crate) fn peak_allocation(f: impl FnOnce()) -> isize {
+    let live = LIVE.with(Cell::get);
+    PEAK.with(|peak| peak.set(live));
+
+    f();
+
+    PEAK.with(Cell::get) - live
+}
+
+/// Bytes allocated by the current thread while running `f` that are still live afterwards
+///
+/// Values returned by `f` (e.g. an opened mask) are kept alive until their
+/// allocations are counted.
+pub(crate) fn retained_allocation<T>(f: impl FnOnce() -> T) -> (T, isize) {
+    let live = LIVE.with(Cell::get);
+    let value = f();
+
+    (value, LIVE.with(Cell::get) - live)
+}
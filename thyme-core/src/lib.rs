@@ -1,3 +1,4 @@
+pub mod catalog;
 pub mod cv;
 pub mod im;
 pub mod io;
@@ -4,20 +4,24 @@
 use crate::cv::ellipse::fit_ellipse_lstsq;
 use crate::cv::points::{convex_hull, point_to_segment_distance};
 
+/// Drop a duplicated closing point so closed and open polygons measure the same
+#[inline]
+fn open(points: &[[f32; 2]]) -> &[[f32; 2]] {
+    match points {
+        [first, .., last] if first == last => &points[..points.len() - 1],
+        _ => points,
+    }
+}
+
 #[inline]
 pub fn area(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let mut area = 0.0;
 
     let n = points.len();
-    for i in 0..n - 1 {
+    for i in 0..n {
         let p1 = points[i];
-        let p2 = points[i + 1];
-        area += p1[0] * p2[1] - p2[0] * p1[1];
-    }
-
-    if points[0] != points[n - 1] {
-        let p1 = points[n - 1];
-        let p2 = points[0];
+        let p2 = points[(i + 1) % n];
         area += p1[0] * p2[1] - p2[0] * p1[1];
     }
 
@@ -26,6 +30,7 @@ pub fn area(points: &[[f32; 2]]) -> f32 {
 
 #[inline]
 pub fn area_bbox(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let (mut xmin, mut ymin) = (points[0][0], points[0][1]);
     let (mut xmax, mut ymax) = (points[0][0], points[0][1]);
 
@@ -39,26 +44,24 @@ pub fn area_bbox(points: &[[f32; 2]]) -> f32 {
     (xmax - xmin) * (ymax - ymin)
 }
 
+/// Area of the convex hull of the points, or NaN if any point is not finite
 #[inline]
 pub fn area_convex(points: &[[f32; 2]]) -> f32 {
-    area(&convex_hull(points))
+    convex_hull(open(points)).map_or(f32::NAN, |hull| area(&hull))
 }
 
 #[inline]
 pub fn perimeter(points: &[[f32; 2]]) -> f32 {
-    let n_points = points.len();
+    let points = open(points);
+    let n = points.len();
 
     let mut perimeter = 0.0;
 
-    for i in 0..n_points - 1 {
-        let dx = points[i][0] - points[i + 1][0];
-        let dy = points[i][1] - points[i + 1][1];
-        perimeter += (dx * dx + dy * dy).sqrt();
-    }
-
-    if points[0] != points[n_points - 1] {
-        let dx = points[points.len() - 1][0] - points[0][0];
-        let dy = points[points.len() - 1][1] - points[0][1];
+    for i in 0..n {
+        let p1 = points[i];
+        let p2 = points[(i + 1) % n];
+        let dx = p1[0] - p2[0];
+        let dy = p1[1] - p2[1];
         perimeter += (dx * dx + dy * dy).sqrt();
     }
 
@@ -71,22 +74,21 @@ pub fn centroid(points: &[[f32; 2]]) -> [f32; 2] {
     let mut sum_y = 0.0;
     let mut area = 0.0;
 
+    let points = open(points);
     let n = points.len();
-    let is_closed = points[0] == points[n - 1];
-    let n_end = if is_closed { n - 1 } else { n };
 
-    for i in 0..n_end {
-        let j = (i + 1) % n_end;
+    for i in 0..n {
         let p1 = points[i];
-        let p2 = points[j];
+        let p2 = points[(i + 1) % n];
         let cross = p1[0] * p2[1] - p2[0] * p1[1];
         sum_x += (p1[0] + p2[0]) * cross;
         sum_y += (p1[1] + p2[1]) * cross;
         area += cross;
     }
 
+    // Dividing by the signed area keeps the centroid independent of winding order
     area /= 2.0;
-    [sum_x / (6.0 * area.abs()), sum_y / (6.0 * area.abs())]
+    [sum_x / (6.0 * area), sum_y / (6.0 * area)]
 }
 
 #[inline]
@@ -94,20 +96,20 @@ pub fn center(points: &[[f32; 2]]) -> [f32; 2] {
     let mut sum_x = 0.0;
     let mut sum_y = 0.0;
 
+    let points = open(points);
     let n = points.len();
-    let is_closed = points[0] == points[n - 1];
-    let n_end = if is_closed { n - 1 } else { n };
 
-    for point in points.iter().take(n_end) {
+    for point in points.iter() {
         sum_x += point[0];
         sum_y += point[1];
     }
 
-    [sum_x / n_end as f32, sum_y / n_end as f32]
+    [sum_x / n as f32, sum_y / n as f32]
 }
 
 #[inline]
 pub fn elongation(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let (mut xmin, mut ymin) = (points[0][0], points[0][1]);
     let (mut xmax, mut ymax) = (points[0][0], points[0][1]);
 
@@ -129,6 +131,7 @@ pub fn elongation(points: &[[f32; 2]]) -> f32 {
 
 #[inline]
 pub fn thread_length(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let perimeter = perimeter(points);
     let area = area(points);
 
@@ -146,6 +149,7 @@ pub fn thread_length(points: &[[f32; 2]]) -> f32 {
 
 #[inline]
 pub fn thread_width(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let perimeter = perimeter(points);
     let area = area(points);
 
@@ -164,6 +168,7 @@ pub fn thread_width(points: &[[f32; 2]]) -> f32 {
 
 #[inline]
 pub fn solidity(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let area = area(points);
     let area_convex = area_convex(points);
 
@@ -172,6 +177,7 @@ pub fn solidity(points: &[[f32; 2]]) -> f32 {
 
 #[inline]
 pub fn extent(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let area = area(points);
     let area_bbox = area_bbox(points);
 
@@ -180,6 +186,7 @@ pub fn extent(points: &[[f32; 2]]) -> f32 {
 
 #[inline]
 pub fn form_factor(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let perimeter = perimeter(points);
     let area = area(points);
 
@@ -188,29 +195,36 @@ pub fn form_factor(points: &[[f32; 2]]) -> f32 {
 
 #[inline]
 pub fn equivalent_diameter(points: &[[f32; 2]]) -> f32 {
-    (area(points) / std::f32::consts::PI).sqrt() * 2.0
+    (area(open(points)) / std::f32::consts::PI).sqrt() * 2.0
 }
 
 #[inline]
 pub fn eccentricity(points: &[[f32; 2]]) -> f32 {
-    let ellipse = fit_ellipse_lstsq(points);
+    let ellipse = fit_ellipse_lstsq(open(points));
     ellipse[2]
 }
 
 #[inline]
 pub fn major_axis_length(points: &[[f32; 2]]) -> f32 {
-    let ellipse = fit_ellipse_lstsq(points);
+    let ellipse = fit_ellipse_lstsq(open(points));
     ellipse[0]
 }
 
 #[inline]
 pub fn minor_axis_length(points: &[[f32; 2]]) -> f32 {
-    let ellipse = fit_ellipse_lstsq(points);
+    let ellipse = fit_ellipse_lstsq(open(points));
     ellipse[1]
 }
 
+#[inline]
+pub fn orientation(points: &[[f32; 2]]) -> f32 {
+    let ellipse = fit_ellipse_lstsq(open(points));
+    ellipse[3]
+}
+
 #[inline]
 pub fn min_radius(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let [cx, cy] = centroid(points);
     let mut min_radius = f32::MAX;
 
@@ -229,6 +243,7 @@ pub fn min_radius(points: &[[f32; 2]]) -> f32 {
 
 #[inline]
 pub fn max_radius(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let [x_centroid, y_centroid] = centroid(points);
     let mut maximum_radius = 0.0;
 
@@ -245,86 +260,204 @@ pub fn max_radius(points: &[[f32; 2]]) -> f32 {
 
 #[inline]
 pub fn mean_radius(points: &[[f32; 2]]) -> f32 {
+    let points = open(points);
     let [x_centroid, y_centroid] = centroid(points);
-    let include_last = (points.last().unwrap() == &points[0]) as usize;
 
     let mut mean_radius = 0.0;
-    for point in points.iter().take(points.len() - include_last) {
+    for point in points.iter() {
         let (x, y) = (point[0], point[1]);
         let distance = (x_centroid - x) * (x_centroid - x) + (y_centroid - y) * (y_centroid - y);
         mean_radius += distance.sqrt();
     }
 
-    mean_radius / (points.len() - include_last) as f32
+    mean_radius / points.len() as f32
 }
 
-#[inline]
-pub fn min_feret(points: &[[f32; 2]]) -> f32 {
-    let norm = |x: [f32; 2]| -> f32 { (x[0] * x[0] + x[1] * x[1]).sqrt() };
-    let cross_product = |x: [f32; 2], y: [f32; 2]| -> f32 { x[0] * y[1] - x[1] * y[0] };
-    let n_points = points.len();
+/// Caliper measurements of the convex hull of a polygon
+///
+/// Angles are measured from the x-axis towards the y-axis in [0, pi).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Calipers {
+    /// Minimum caliper diameter
+    pub min_feret: f32,
+    /// Angle of the direction the minimum caliper diameter is measured along
+    pub min_feret_angle: f32,
+    /// Maximum caliper diameter
+    pub max_feret: f32,
+    /// Angle of the line joining the two most distant vertices
+    pub max_feret_angle: f32,
+    /// Longer side of the minimum-area rotated bounding rectangle
+    pub min_rect_width: f32,
+    /// Shorter side of the minimum-area rotated bounding rectangle
+    pub min_rect_height: f32,
+    /// Angle of the longer side of the minimum-area rotated bounding rectangle
+    pub min_rect_angle: f32,
+}
 
-    let mut feret_diameter_minimum = f32::MAX;
-    for i in 0..n_points {
-        let p1 = points[i];
-        let p2 = points[(i + 1) % n_points];
-        let diff_a = [p2[0] - p1[0], p2[1] - p1[1]];
+/// Measure the Feret diameters and minimum-area bounding rectangle of a polygon
+///
+/// Rotating calipers are placed against each edge of the convex hull while
+/// the vertices furthest along, away from, and behind the edge are advanced
+/// around the hull, so each measurement takes linear time in the number of
+/// hull vertices. Polygons whose hull is a point or a line have a minimum
+/// Feret diameter and rectangle height of zero, and polygons with a NaN or
+/// infinite point have NaN measurements.
+///
+/// # Arguments
+///
+/// * `points` - Polygon (x, y) points
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::mp::form::calipers;
+///
+/// let rectangle = [[0., 0.], [10., 0.], [10., 4.], [0., 4.]];
+/// let calipers = calipers(&rectangle);
+///
+/// assert_eq!(calipers.min_feret, 4.0);
+/// assert_eq!(calipers.min_feret_angle, std::f32::consts::FRAC_PI_2);
+/// assert_eq!((calipers.min_rect_width, calipers.min_rect_height), (10.0, 4.0));
+/// assert_eq!(calipers.min_rect_angle, 0.0);
+/// ```
+pub fn calipers(points: &[[f32; 2]]) -> Calipers {
+    let mut hull = match convex_hull(open(points)) {
+        Ok(hull) => hull,
+        Err(_) => {
+            return Calipers {
+                min_feret: f32::NAN,
+                min_feret_angle: f32::NAN,
+                max_feret: f32::NAN,
+                max_feret_angle: f32::NAN,
+                min_rect_width: f32::NAN,
+                min_rect_height: f32::NAN,
+                min_rect_angle: f32::NAN,
+            };
+        }
+    };
 
-        let mut distance = 0.0;
-        for point in points.iter().take(n_points) {
-            let diff_b = [point[0] - p1[0], point[1] - p1[1]];
-            let d = (cross_product(diff_a, diff_b) / norm(diff_a)).abs();
+    if hull.len() > 1 && hull[0] == hull[hull.len() - 1] {
+        hull.pop();
+    }
 
-            if d > distance {
-                distance = d;
-            }
-        }
+    let n = hull.len();
+    let next = |k: usize| (k + 1) % n;
+
+    let angle = |d: [f32; 2]| d[1].atan2(d[0]).rem_euclid(std::f32::consts::PI);
+    let distance =
+        |a: [f32; 2], b: [f32; 2]| ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+
+    if n < 3 {
+        let (a, b) = match n {
+            2 => (hull[0], hull[1]),
+            _ => return Calipers::default(),
+        };
+
+        return Calipers {
+            max_feret: distance(a, b),
+            max_feret_angle: angle([b[0] - a[0], b[1] - a[1]]),
+            min_rect_width: distance(a, b),
+            min_rect_angle: angle([b[0] - a[0], b[1] - a[1]]),
+            ..Default::default()
+        };
+    }
+
+    let mut calipers = Calipers {
+        min_feret: f32::INFINITY,
+        ..Default::default()
+    };
 
-        if distance < feret_diameter_minimum && distance > f32::EPSILON {
-            feret_diameter_minimum = distance;
+    let mut min_area = f32::INFINITY;
+    let (mut right, mut top, mut left) = (1, 1, 1);
+
+    for i in 0..n {
+        let p = hull[i];
+        let q = hull[next(i)];
+
+        let length = distance(p, q);
+
+        if length <= f32::EPSILON {
+            continue;
         }
-    }
 
-    feret_diameter_minimum
-}
+        let u = [(q[0] - p[0]) / length, (q[1] - p[1]) / length];
 
-#[inline]
-pub fn max_feret(points: &[[f32; 2]]) -> f32 {
-    let mut max_diameter = 0f32;
-    let n_points = points.len();
+        let along = |k: usize| (hull[k][0] - p[0]) * u[0] + (hull[k][1] - p[1]) * u[1];
+        let away = |k: usize| ((hull[k][1] - p[1]) * u[0] - (hull[k][0] - p[0]) * u[1]).abs();
 
-    for i in 0..n_points {
-        for j in (i + 1)..n_points {
-            let dx = points[j][0] - points[i][0];
-            let dy = points[j][1] - points[i][1];
+        // Calipers only move forward as the supporting edge rotates around the hull
+        while along(next(right)) > along(right) {
+            right = next(right);
+        }
 
-            if dx == 0.0 && dy == 0.0 {
-                continue;
+        // Every vertex passed by the top caliper is antipodal to the vertex at i
+        let mut farthest = |a: usize, b: usize| {
+            let d = distance(hull[a], hull[b]);
+
+            if d > calipers.max_feret {
+                calipers.max_feret = d;
+                calipers.max_feret_angle =
+                    angle([hull[b][0] - hull[a][0], hull[b][1] - hull[a][1]]);
             }
+        };
 
-            let mut min_proj = f32::INFINITY;
-            let mut max_proj = f32::NEG_INFINITY;
+        farthest(i, top);
 
-            for k in 0..n_points {
-                let proj = (points[k][0] - points[i][0]) * dx + (points[k][1] - points[i][1]) * dy;
+        while away(next(top)) > away(top) {
+            top = next(top);
+            farthest(i, top);
+        }
 
-                min_proj = min_proj.min(proj);
-                max_proj = max_proj.max(proj);
-            }
+        farthest(next(i), top);
+
+        if i == 0 {
+            left = top;
+        }
 
-            let diameter = ((max_proj - min_proj) / ((dx * dx + dy * dy).sqrt())).abs();
-            max_diameter = max_diameter.max(diameter);
+        while along(next(left)) < along(left) {
+            left = next(left);
+        }
+
+        let width = away(top);
+        let extent = along(right) - along(left);
+        let normal = [-u[1], u[0]];
+
+        if width < calipers.min_feret {
+            calipers.min_feret = width;
+            calipers.min_feret_angle = angle(normal);
+        }
+
+        if width * extent < min_area {
+            min_area = width * extent;
+
+            (
+                calipers.min_rect_width,
+                calipers.min_rect_height,
+                calipers.min_rect_angle,
+            ) = match extent >= width {
+                true => (extent, width, angle(u)),
+                false => (width, extent, angle(normal)),
+            };
         }
     }
 
-    max_diameter
+    calipers
 }
 
 #[inline]
-pub fn descriptors(points: &[[f32; 2]]) -> [f32; 23] {
+pub fn min_feret(points: &[[f32; 2]]) -> f32 {
+    calipers(points).min_feret
+}
+
+#[inline]
+pub fn max_feret(points: &[[f32; 2]]) -> f32 {
+    calipers(points).max_feret
+}
+
+#[inline]
+pub fn descriptors(points: &[[f32; 2]]) -> [f32; 29] {
+    let points = open(points);
     let n = points.len();
-    let is_closed = points[0] == points[n - 1];
-    let n_end = if is_closed { n - 1 } else { n };
 
     let mut area = 0f32;
     let mut perimeter = 0f32;
@@ -342,19 +475,15 @@ pub fn descriptors(points: &[[f32; 2]]) -> [f32; 23] {
         let p2 = points[(i + 1) % n];
 
         // Area and centroid
-        if i < n_end {
-            let cross = p1[0] * p2[1] - p2[0] * p1[1];
-            area += cross;
-            sum_x += (p1[0] + p2[0]) * cross;
-            sum_y += (p1[1] + p2[1]) * cross;
-        }
+        let cross = p1[0] * p2[1] - p2[0] * p1[1];
+        area += cross;
+        sum_x += (p1[0] + p2[0]) * cross;
+        sum_y += (p1[1] + p2[1]) * cross;
 
         // Perimeter
-        if !is_closed || (i + 1) % n != 0 {
-            let dx = p1[0] - p2[0];
-            let dy = p1[1] - p2[1];
-            perimeter += (dx * dx + dy * dy).sqrt();
-        }
+        let dx = p1[0] - p2[0];
+        let dy = p1[1] - p2[1];
+        perimeter += (dx * dx + dy * dy).sqrt();
 
         // Bounding box
         xmin = xmin.min(p1[0]);
@@ -363,19 +492,18 @@ pub fn descriptors(points: &[[f32; 2]]) -> [f32; 23] {
         ymax = ymax.max(p1[1]);
 
         // Center
-        if i < n_end {
-            mean_x += p1[0];
-            mean_y += p1[1];
-        }
+        mean_x += p1[0];
+        mean_y += p1[1];
     }
 
-    let area = area.abs() / 2.0;
+    let signed_area = area / 2.0;
+    let area = signed_area.abs();
     let area_bbox = (xmax - xmin) * (ymax - ymin);
 
-    let centroid_x = sum_x / (6.0 * area.abs());
-    let centroid_y = sum_y / (6.0 * area.abs());
-    let center_x = mean_x / n_end as f32;
-    let center_y = mean_y / n_end as f32;
+    let centroid_x = sum_x / (6.0 * signed_area);
+    let centroid_y = sum_y / (6.0 * signed_area);
+    let center_x = mean_x / n as f32;
+    let center_y = mean_y / n as f32;
 
     let elongation = {
         let e = (xmax - xmin) / (ymax - ymin);
@@ -386,9 +514,6 @@ pub fn descriptors(points: &[[f32; 2]]) -> [f32; 23] {
     let mut maximum_radius = 0f32;
     let mut mean_radius = 0f32;
 
-    let mut min_feret = f32::MAX;
-    let mut max_feret = 0f32;
-
     for i in 0..n {
         let p1 = points[i];
         let p2 = points[(i + 1) % n];
@@ -398,85 +523,28 @@ pub fn descriptors(points: &[[f32; 2]]) -> [f32; 23] {
             minimum_radius.min(point_to_segment_distance(centroid_x, centroid_y, p1, p2));
 
         // Max and mean radius
-        if !is_closed || i < n - 1 {
-            let dx = centroid_x - p1[0];
-            let dy = centroid_y - p1[1];
-            let distance_sq = dx * dx + dy * dy;
-            maximum_radius = maximum_radius.max(distance_sq);
-            mean_radius += distance_sq.sqrt();
-        }
-
-        // Feret diameters
-        let diff_a = [p2[0] - p1[0], p2[1] - p1[1]];
-        let norm_a = (diff_a[0] * diff_a[0] + diff_a[1] * diff_a[1]).sqrt();
-
-        let mut max_distance = 0f32;
-        for point in points {
-            let diff_b = [point[0] - p1[0], point[1] - p1[1]];
-            let d = (diff_a[0] * diff_b[1] - diff_a[1] * diff_b[0]).abs() / norm_a;
-            max_distance = max_distance.max(d);
-        }
-
-        if max_distance < min_feret && max_distance > f32::EPSILON {
-            min_feret = max_distance;
-        }
+        let dx = centroid_x - p1[0];
+        let dy = centroid_y - p1[1];
+        let distance_sq = dx * dx + dy * dy;
+        maximum_radius = maximum_radius.max(distance_sq);
+        mean_radius += distance_sq.sqrt();
     }
 
     maximum_radius = maximum_radius.sqrt();
-    mean_radius /= n_end as f32;
-
-    // Max feret
-    for i in 0..n {
-        for j in (i + 1)..n {
-            let dx = points[j][0] - points[i][0];
-            let dy = points[j][1] - points[i][1];
-
-            if dx == 0.0 && dy == 0.0 {
-                continue;
-            }
+    mean_radius /= n as f32;
 
-            let norm = (dx * dx + dy * dy).sqrt();
-
-            let mut min_proj = f32::INFINITY;
-            let mut max_proj = f32::NEG_INFINITY;
-
-            for point in points {
-                let proj =
-                    ((point[0] - points[i][0]) * dx + ((point[1] - points[i][1]) * dy)) / norm;
-                min_proj = min_proj.min(proj);
-                max_proj = max_proj.max(proj);
-            }
-
-            max_feret = max_feret.max((max_proj - min_proj).abs());
-        }
-    }
+    // Feret diameters and minimum-area rectangle
+    let calipers = calipers(points);
 
     // Convex hull
-    let area_convex = {
-        let convex_hull_points = convex_hull(points);
-
-        let mut area = 0.0;
-        let n_hull = convex_hull_points.len();
-        for i in 0..n_hull - 1 {
-            let p1 = convex_hull_points[i];
-            let p2 = convex_hull_points[i + 1];
-            area += p1[0] * p2[1] - p2[0] * p1[1];
-        }
-
-        if convex_hull_points[0] != convex_hull_points[n_hull - 1] {
-            let p1 = convex_hull_points[n_hull - 1];
-            let p2 = convex_hull_points[0];
-            area += p1[0] * p2[1] - p2[0] * p1[1];
-        }
-
-        area.abs() / 2.0
-    };
+    let area_convex = area_convex(points);
 
     // Ellipse fitting
     let ellipse = fit_ellipse_lstsq(points);
     let major_axis = ellipse[0];
     let minor_axis = ellipse[1];
     let eccentricity = ellipse[2];
+    let orientation = ellipse[3];
 
     // Thread width and height
     let thread_left = perimeter.powi(2);
@@ -517,17 +585,26 @@ pub fn descriptors(points: &[[f32; 2]]) -> [f32; 23] {
         minimum_radius,
         maximum_radius,
         mean_radius,
-        min_feret,
-        max_feret,
+        calipers.min_feret,
+        calipers.max_feret,
+        calipers.min_feret_angle,
+        calipers.max_feret_angle,
+        calipers.min_rect_width,
+        calipers.min_rect_height,
+        calipers.min_rect_angle,
+        orientation,
     ]
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ut::synth::SplitMix64;
 
     const EPSILON: f32 = 1e-5;
 
+    type Form = fn(&[[f32; 2]]) -> f32;
+
     fn unit_circle(close: bool) -> Vec<[f32; 2]> {
         let mut points = Vec::with_capacity(360);
         for i in 0..360 {
@@ -564,7 +641,43 @@ mod test {
         points
     }
 
-    fn test_equivalence(f: fn(&[[f32; 2]]) -> f32) {
+    /// Random star-shaped polygons with an optional duplicated closing point
+    fn random_polygons(close: bool) -> Vec<Vec<[f32; 2]>> {
+        let mut rng = SplitMix64::new(1449);
+
+        (0..64)
+            .map(|_| {
+                let n = rng.range(3.0, 48.0) as usize;
+                let (x, y) = (rng.range(-50.0, 50.0), rng.range(-50.0, 50.0));
+
+                let mut angles: Vec<f64> = (0..n)
+                    .map(|_| rng.range(0.0, 2.0 * std::f64::consts::PI))
+                    .collect();
+
+                angles.sort_by(|a, b| a.total_cmp(b));
+
+                let mut points: Vec<[f32; 2]> = angles
+                    .iter()
+                    .map(|t| {
+                        let r = rng.range(0.5, 8.0);
+                        [(x + r * t.cos()) as f32, (y + r * t.sin()) as f32]
+                    })
+                    .collect();
+
+                if rng.range(0.0, 1.0) < 0.5 {
+                    points.reverse();
+                }
+
+                if close {
+                    points.push(points[0]);
+                }
+
+                points
+            })
+            .collect()
+    }
+
+    fn test_equivalence(f: Form) {
         let open_circle = unit_circle(false);
         let open_square = unit_square(false);
         let closed_circle = unit_circle(true);
@@ -572,6 +685,10 @@ mod test {
 
         assert_eq!(f(&open_circle), f(&closed_circle));
         assert_eq!(f(&open_square), f(&closed_square));
+
+        for (open, closed) in random_polygons(false).iter().zip(random_polygons(true)) {
+            assert_eq!(f(open).to_bits(), f(&closed).to_bits());
+        }
     }
 
     #[test]
@@ -597,6 +714,12 @@ mod test {
             let xy = centroid(&circle);
             assert!((xy[0] - 1.0).abs() < EPSILON);
             assert!((xy[1] - 2.0).abs() < EPSILON);
+
+            let clockwise: Vec<[f32; 2]> = circle.into_iter().rev().collect();
+
+            let xy = centroid(&clockwise);
+            assert!((xy[0] - 1.0).abs() < EPSILON);
+            assert!((xy[1] - 2.0).abs() < EPSILON);
         }
     }
 
@@ -660,6 +783,8 @@ mod test {
         }
 
         test_equivalence(area_convex);
+
+        assert!(area_convex(&[[0.0, 0.0], [f32::NAN, 1.0], [1.0, 0.0]]).is_nan());
     }
 
     #[test]
@@ -814,6 +939,56 @@ mod test {
         test_equivalence(min_feret);
     }
 
+    #[test]
+    fn test_calipers_rectangle() {
+        let rectangle = [[0.0, 0.0], [10.0, 0.0], [10.0, 4.0], [0.0, 4.0]];
+        let calipers = calipers(&rectangle);
+
+        assert!((calipers.min_feret - 4.0).abs() < EPSILON);
+        assert!((calipers.min_feret_angle - std::f32::consts::FRAC_PI_2).abs() < EPSILON);
+        assert!((calipers.max_feret - 116f32.sqrt()).abs() < EPSILON);
+        assert!((calipers.max_feret_angle.tan().abs() - 0.4).abs() < EPSILON);
+        assert!((calipers.min_rect_width - 10.0).abs() < EPSILON);
+        assert!((calipers.min_rect_height - 4.0).abs() < EPSILON);
+        assert!(calipers.min_rect_angle.abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_calipers_degenerate() {
+        let line = calipers(&[[0.0, 0.0], [3.0, 4.0], [1.5, 2.0]]);
+        assert_eq!(line.min_feret, 0.0);
+        assert!((line.max_feret - 5.0).abs() < EPSILON);
+
+        assert_eq!(calipers(&[[1.0, 1.0]]), Calipers::default());
+
+        let infinite = calipers(&[[0.0, 0.0], [f32::INFINITY, 0.0], [1.0, 1.0]]);
+        assert!(infinite.min_feret.is_nan() && infinite.max_feret.is_nan());
+        assert_eq!(calipers(&[]), Calipers::default());
+    }
+
+    #[test]
+    fn test_calipers_rotated_rectangle() {
+        let theta = std::f32::consts::FRAC_PI_6;
+        let (sin, cos) = theta.sin_cos();
+
+        let rectangle: Vec<[f32; 2]> = [[0.0, 0.0], [10.0, 0.0], [10.0, 4.0], [0.0, 4.0]]
+            .iter()
+            .map(|[x, y]| [x * cos - y * sin + 20.0, x * sin + y * cos + 20.0])
+            .collect();
+
+        let calipers = calipers(&rectangle);
+
+        assert!((calipers.min_feret - 4.0).abs() < 1e-4);
+        assert!((calipers.min_feret_angle - (theta + std::f32::consts::FRAC_PI_2)).abs() < 1e-4);
+        assert!((calipers.max_feret - 116f32.sqrt()).abs() < 1e-4);
+        assert!((calipers.min_rect_width - 10.0).abs() < 1e-4);
+        assert!((calipers.min_rect_height - 4.0).abs() < 1e-4);
+        assert!((calipers.min_rect_angle - theta).abs() < 1e-4);
+
+        // Axis-aligned bounds overestimate the rotated rectangle area
+        assert!(area_bbox(&rectangle) > calipers.min_rect_width * calipers.min_rect_height);
+    }
+
     #[test]
     fn test_min_radius() {
         for close in [true, false] {
@@ -943,6 +1118,7 @@ mod test {
                 let equivalent_diameter = equivalent_diameter(&points);
                 let major_axis = major_axis_length(&points);
                 let minor_axis = minor_axis_length(&points);
+                let orientation = orientation(&points);
                 let thread_length = thread_length(&points);
                 let thread_width = thread_width(&points);
                 let minimum_radius = min_radius(&points);
@@ -983,7 +1159,92 @@ mod test {
                 assert_eq!(descriptors[20], mean_radius);
                 assert_eq!(descriptors[21], min_feret);
                 assert_eq!(descriptors[22], max_feret);
+
+                let calipers = calipers(&points);
+                assert_eq!(descriptors[23], calipers.min_feret_angle);
+                assert_eq!(descriptors[24], calipers.max_feret_angle);
+                assert_eq!(descriptors[25], calipers.min_rect_width);
+                assert_eq!(descriptors[26], calipers.min_rect_height);
+                assert_eq!(descriptors[27], calipers.min_rect_angle);
+                assert_eq!(descriptors[28], orientation);
             }
         }
     }
+
+    #[test]
+    fn test_equivalence_random() {
+        let functions: [Form; 22] = [
+            area,
+            area_bbox,
+            area_convex,
+            perimeter,
+            elongation,
+            thread_length,
+            thread_width,
+            solidity,
+            extent,
+            form_factor,
+            equivalent_diameter,
+            eccentricity,
+            major_axis_length,
+            minor_axis_length,
+            orientation,
+            min_radius,
+            max_radius,
+            mean_radius,
+            min_feret,
+            max_feret,
+            |points| centroid(points)[0],
+            |points| center(points)[1],
+        ];
+
+        for f in functions {
+            test_equivalence(f);
+        }
+
+        for (open, closed) in random_polygons(false).iter().zip(random_polygons(true)) {
+            assert_eq!(centroid(open), centroid(&closed));
+            assert_eq!(center(open), center(&closed));
+            assert_eq!(calipers(open), calipers(&closed));
+
+            let batch = descriptors(open);
+            assert_eq!(
+                batch.map(f32::to_bits),
+                descriptors(&closed).map(f32::to_bits)
+            );
+
+            // The batch path accumulates in the same order as the single functions
+            let single = [
+                centroid(open)[0],
+                centroid(open)[1],
+                center(open)[0],
+                center(open)[1],
+                area(open),
+                area_bbox(open),
+                area_convex(open),
+                perimeter(open),
+                elongation(open),
+                thread_length(open),
+                thread_width(open),
+                solidity(open),
+                extent(open),
+                form_factor(open),
+                equivalent_diameter(open),
+                eccentricity(open),
+                major_axis_length(open),
+                minor_axis_length(open),
+                min_radius(open),
+                max_radius(open),
+                mean_radius(open),
+                min_feret(open),
+                max_feret(open),
+            ];
+
+            for (i, value) in single.iter().enumerate() {
+                assert_eq!(batch[i].to_bits(), value.to_bits(), "descriptor {i}");
+            }
+
+            assert_eq!(batch[28].to_bits(), orientation(open).to_bits());
+        }
+    }
 }
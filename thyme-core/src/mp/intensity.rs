@@ -7,6 +7,7 @@ use std::ops::Deref;
 use num::{FromPrimitive, ToPrimitive};
 
 use crate::im::ThymeViewBuffer;
+use crate::mp::reduce::IntensitySums;
 
 #[inline]
 pub fn intensity_min<T>(pixels: &[T], channels: usize) -> Vec<f32>
@@ -130,53 +131,297 @@ pub fn intensity_median<T>(pixels: &[T]) -> f32
 where
     T: Copy + Into<f32> + PartialOrd + ToPrimitive,
 {
-    let mut pixels: Vec<f32> = pixels
-        .to_vec()
+    let mut pixels = positive_values(pixels);
+    select_median(&mut pixels)
+}
+
+#[inline]
+pub fn intensity_mad<T>(pixels: &[T]) -> f32
+where
+    T: Copy + Into<f32> + PartialOrd + ToPrimitive,
+{
+    let mut pixels = positive_values(pixels);
+    median_mad(&mut pixels).1
+}
+
+/// Positive values of pixels
+fn positive_values<T>(pixels: &[T]) -> Vec<f32>
+where
+    T: ToPrimitive,
+{
+    pixels
         .iter()
         .map(|x| x.to_f32().unwrap())
         .filter(|x| *x > 0.)
-        .collect();
+        .collect()
+}
+
+/// Ascending order of intensities
+fn compare(a: &f32, b: &f32) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
 
-    pixels.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+/// Median of values found by selection instead of a full sort
+///
+/// Values are partially reordered. The median matches the median of the
+/// sorted values exactly, and is zero if there are no values.
+fn select_median(values: &mut [f32]) -> f32 {
+    let n = values.len();
 
-    let len = pixels.len();
-    let mid = len / 2;
+    if n == 0 {
+        return 0.;
+    }
 
-    if len % 2 == 0 {
-        let left = pixels[mid - 1];
-        let right = pixels[mid];
-        (left + right) / 2.0
+    let mid = n / 2;
+    let (left, median, _) = values.select_nth_unstable_by(mid, compare);
+
+    if n % 2 == 0 {
+        // The largest value left of the middle is the lower middle of the sorted values
+        let lower = left.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        (lower + *median) / 2.0
     } else {
-        pixels[mid]
+        *median
+    }
+}
+
+/// Median and median absolute deviation of values found by selection
+///
+/// Values are overwritten with their absolute deviations from the median.
+fn median_mad(values: &mut [f32]) -> (f32, f32) {
+    let median = select_median(values);
+
+    values
+        .iter_mut()
+        .for_each(|value| *value = (*value - median).abs());
+
+    (median, select_median(values))
+}
+
+/// Move the value of each rank of the sorted values to its sorted position
+///
+/// Ranks must be ascending and unique. Each rank is selected with a
+/// quickselect partition within the partition of the previously selected
+/// rank, so values are only partially reordered.
+fn select_ranks(values: &mut [f32], ranks: &[usize]) {
+    if ranks.is_empty() {
+        return;
     }
+
+    let m = ranks.len() / 2;
+    let rank = ranks[m];
+
+    let (left, _, right) = values.select_nth_unstable_by(rank, compare);
+
+    select_ranks(left, &ranks[..m]);
+
+    let right_ranks: Vec<usize> = ranks[m + 1..].iter().map(|r| r - rank - 1).collect();
+    select_ranks(right, &right_ranks);
 }
 
+/// Ranks of the sorted values interpolated by `percentile` for each percentile
+fn percentile_ranks(n: usize, percentiles: &[f32]) -> Vec<usize> {
+    let mut ranks: Vec<usize> = percentiles
+        .iter()
+        .flat_map(|q| {
+            let position = q * (n - 1) as f32;
+            [position.floor() as usize, position.ceil() as usize]
+        })
+        .collect();
+
+    ranks.sort_unstable();
+    ranks.dedup();
+    ranks
+}
+
+/// Percentiles, skewness, and kurtosis of the positive values of a channel
+///
+/// Values are returned in the order p10, p25, p75, p90, skew, and kurtosis
+/// and are reordered in place.
+///
+/// Above `approx_over` values, percentiles are selected with quickselect
+/// partitions instead of a full sort. Percentiles are the same as with the
+/// sort, but skewness and kurtosis are accumulated in pixel order rather than
+/// sorted order, so they may differ in the last bits. Channels with at most
+/// `approx_over` values are bit-identical to the sorted computation.
+///
+/// # Arguments
+///
+/// * `values` - Positive values of a channel in pixel order
+/// * `approx_over` - Number of values above which the values are not sorted
+fn extended_channel(values: &mut [f32], approx_over: Option<usize>) -> [f32; 6] {
+    let n = EXTENDED_PERCENTILES.len();
+    let mut results = [0.0; 6];
+
+    let (skew, kurtosis) = match approx_over {
+        Some(threshold) if values.len() > threshold => {
+            let moments = central_moments(values);
+            select_ranks(
+                values,
+                &percentile_ranks(values.len(), &EXTENDED_PERCENTILES),
+            );
+            moments
+        }
+        _ => {
+            values.sort_unstable_by(compare);
+            central_moments(values)
+        }
+    };
+
+    for (j, &q) in EXTENDED_PERCENTILES.iter().enumerate() {
+        results[j] = percentile(values, q);
+    }
+
+    results[n] = skew;
+    results[n + 1] = kurtosis;
+    results
+}
+
+/// Percentiles reported by the extended intensity descriptors
+pub const EXTENDED_PERCENTILES: [f32; 4] = [0.10, 0.25, 0.75, 0.90];
+
 #[inline]
-pub fn intensity_mad<T>(pixels: &[T]) -> f32
+pub fn intensity_percentile<T>(pixels: &[T], channels: usize, q: f32) -> Vec<f32>
 where
-    T: Copy + Into<f32> + PartialOrd + ToPrimitive,
+    T: ToPrimitive,
 {
-    let median = intensity_median(pixels);
-    let mut mad = Vec::new();
-    for pixel in pixels.iter() {
-        let pixel = pixel.to_f32().unwrap();
-        if pixel > 0. {
-            mad.push((pixel - median).abs());
+    let mut results = vec![0.0; channels];
+    for (i, result) in results.iter_mut().enumerate() {
+        let mut values = channel_values(pixels, channels, i);
+
+        if !values.is_empty() {
+            let ranks = percentile_ranks(values.len(), &[q]);
+            select_ranks(&mut values, &ranks);
         }
+
+        *result = percentile(&values, q);
     }
 
-    mad.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-    let mid = mad.len() / 2;
-    if mad.len() % 2 == 0 {
-        (mad[mid] + mad[mid - 1]) / 2.0
-    } else {
-        mad[mid]
+    results
+}
+
+#[inline]
+pub fn intensity_skew<T>(pixels: &[T], channels: usize, approx_over: Option<usize>) -> Vec<f32>
+where
+    T: ToPrimitive,
+{
+    let mut results = vec![0.0; channels];
+    for (i, result) in results.iter_mut().enumerate() {
+        let mut values = channel_values(pixels, channels, i);
+        *result = extended_channel(&mut values, approx_over)[4];
     }
+
+    results
+}
+
+#[inline]
+pub fn intensity_kurtosis<T>(pixels: &[T], channels: usize, approx_over: Option<usize>) -> Vec<f32>
+where
+    T: ToPrimitive,
+{
+    let mut results = vec![0.0; channels];
+    for (i, result) in results.iter_mut().enumerate() {
+        let mut values = channel_values(pixels, channels, i);
+        *result = extended_channel(&mut values, approx_over)[5];
+    }
+
+    results
+}
+
+/// Non-zero values for a single channel of interleaved pixels
+fn channel_values<T>(pixels: &[T], channels: usize, channel: usize) -> Vec<f32>
+where
+    T: ToPrimitive,
+{
+    pixels
+        .iter()
+        .skip(channel)
+        .step_by(channels)
+        .map(|v| v.to_f32().unwrap())
+        .filter(|v| *v > 0.)
+        .collect()
+}
+
+/// Linearly interpolated percentile of sorted values (numpy default)
+///
+/// Only the values at the ranks given by `percentile_ranks` are read, so
+/// values whose ranks were moved into place by `select_ranks` need not be
+/// fully sorted.
+fn percentile(sorted: &[f32], q: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.;
+    }
+
+    let position = q * (sorted.len() - 1) as f32;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - lower as f32;
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+}
+
+/// Biased skewness and excess kurtosis of values (scipy.stats defaults)
+fn central_moments(values: &[f32]) -> (f32, f32) {
+    if values.is_empty() {
+        return (0., 0.);
+    }
+
+    let n = values.len() as f64;
+    let mean = values.iter().map(|&v| v as f64).sum::<f64>() / n;
+
+    let (mut m2, mut m3, mut m4) = (0f64, 0f64, 0f64);
+    for &v in values {
+        let d = v as f64 - mean;
+        let d2 = d * d;
+        m2 += d2;
+        m3 += d2 * d;
+        m4 += d2 * d2;
+    }
+
+    m2 /= n;
+    m3 /= n;
+    m4 /= n;
+
+    // Constant intensities have undefined shape so they are reported as zero
+    if m2 <= f64::EPSILON {
+        return (0., 0.);
+    }
+
+    ((m3 / m2.powf(1.5)) as f32, (m4 / (m2 * m2) - 3.) as f32)
+}
+
+/// Percentile, skewness, and kurtosis descriptors for interleaved pixels
+///
+/// Values are stored in chunks that span the number of channels in the
+/// order p10, p25, p75, p90, skew, and kurtosis.
+fn extended_descriptors<T>(pixels: &[T], channels: usize, approx_over: Option<usize>) -> Vec<f32>
+where
+    T: ToPrimitive,
+{
+    let n = EXTENDED_PERCENTILES.len();
+    let mut results = vec![0.0; channels * (n + 2)];
+
+    for i in 0..channels {
+        let mut values = channel_values(pixels, channels, i);
+
+        for (j, value) in extended_channel(&mut values, approx_over)
+            .iter()
+            .enumerate()
+        {
+            results[i + j * channels] = *value;
+        }
+    }
+
+    results
 }
 
 #[inline]
 #[allow(clippy::all)]
-pub fn descriptors<T>(pixels: &[T], channels: usize) -> Vec<f32>
+pub fn descriptors<T>(
+    pixels: &[T],
+    channels: usize,
+    extended: bool,
+    approx_over: Option<usize>,
+) -> Vec<f32>
 where
     T: ToPrimitive,
 {
@@ -186,6 +431,7 @@ where
     // intensity min, max, sum, mean, and standard deviation
     // are stored in chunks that span the number of channels.
     // The last two spots are for median and mad descriptors.
+    // The extended descriptors are optionally appended after.
     let mut results = vec![0.0; channels * 5 + 2];
 
     for i in 0..channels {
@@ -242,144 +488,244 @@ where
         }
     }
 
+    if extended {
+        results.extend(extended_descriptors(&store, channels, approx_over));
+    }
+
     store.retain(|v| *v > 0.);
 
     if store.is_empty() {
         return results;
     }
 
-    store.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-
-    let n = store.len();
-    let mid = n / 2;
-    let len = results.len();
-
-    // Intensity median
-    results[len - 2] = if n % 2 == 0 {
-        (store[mid - 1] + store[mid]) / 2.0
-    } else {
-        store[mid]
-    };
-
-    store
-        .iter_mut()
-        .for_each(|pixel| *pixel = (*pixel - results[len - 2]).abs());
-
-    store.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let len = channels * 5 + 2;
 
-    // Intensity median absolute deviation
-    results[len - 1] = if n % 2 == 0 {
-        (store[mid] + store[mid - 1]) / 2.0
-    } else {
-        store[mid]
-    };
+    // Intensity median and median absolute deviation
+    (results[len - 2], results[len - 1]) = median_mad(&mut store);
 
     results
 }
 
 #[inline]
-#[allow(clippy::all)]
-pub fn objects<T, Container>(object: &ThymeViewBuffer<T, Container>) -> Vec<f32>
+pub fn objects<T, Container>(
+    object: &ThymeViewBuffer<T, Container>,
+    extended: bool,
+    approx_over: Option<usize>,
+) -> Vec<f32>
 where
     T: ToPrimitive + FromPrimitive,
     Container: Deref<Target = [T]>,
 {
-    let c = object.channels();
-    let mut n = vec![0; c];
-
-    // Initial allocation for all intensity measurements. The
-    // intensity min, max, sum, mean, and standard deviation
-    // are stored in chunks that span the number of channels.
-    // The last two spots are for median and mad descriptors.
-    let mut results = vec![0.0; c * 5 + 2];
+    let mut accumulator = IntensityAccumulator::new(object.channels(), object.len());
 
-    for i in 0..c {
-        results[i + 0 * c] = f32::INFINITY;
-        results[i + 1 * c] = f32::NEG_INFINITY;
+    for pixel in object.iter_pixels() {
+        for (i, v) in pixel.iter().enumerate() {
+            accumulator.push(i, v.to_f32().unwrap());
+        }
     }
 
-    let mut store: Vec<f32> = Vec::with_capacity(object.len());
+    accumulator.finish(extended, approx_over)
+}
+
+/// Mean intensity ratios and median intensity differences between channel pairs
+///
+/// Means and medians are computed over the non-zero pixels of each channel.
+/// Ratios of all pairs are followed by the differences of all pairs. Ratios
+/// with a zero denominator are undefined and returned as NaN.
+///
+/// # Arguments
+///
+/// * `object` - Object view
+/// * `pairs` - Numerator and denominator channel of each pair
+pub fn channel_pairs<T, Container>(
+    object: &ThymeViewBuffer<T, Container>,
+    pairs: &[(usize, usize)],
+) -> Vec<f32>
+where
+    T: ToPrimitive + FromPrimitive,
+    Container: Deref<Target = [T]>,
+{
+    let channels = object.channels();
+    let mut values: Vec<Vec<f32>> = vec![vec![]; channels];
 
     for pixel in object.iter_pixels() {
         for (i, v) in pixel.iter().enumerate() {
             let v = v.to_f32().unwrap();
-
             if v > 0. {
-                n[i] += 1;
+                values[i].push(v);
+            }
+        }
+    }
 
-                // Intensity minimum
-                results[i + 0 * c] = results[i + 0 * c].min(v);
+    let mut mean = vec![0f32; channels];
+    let mut median = vec![0f32; channels];
 
-                // Intensity maximum
-                results[i + 1 * c] = results[i + 1 * c].max(v);
+    for (i, values) in values.iter_mut().enumerate() {
+        if values.is_empty() {
+            continue;
+        }
 
-                // Intensity sum/integrated
-                results[i + 2 * c] += v;
-            }
+        values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
 
-            store.push(v);
-        }
+        let n = values.len();
+        mean[i] = values.iter().sum::<f32>() / n as f32;
+        median[i] = match n % 2 {
+            0 => (values[n / 2 - 1] + values[n / 2]) / 2.0,
+            _ => values[n / 2],
+        };
     }
 
-    for v in results.iter_mut().take(c * 2) {
-        if *v == f32::NEG_INFINITY || *v == f32::INFINITY {
-            *v = 0.
+    let ratios = pairs.iter().map(|&(a, b)| match mean[b] == 0. {
+        true => f32::NAN,
+        false => mean[a] / mean[b],
+    });
+
+    let differences = pairs.iter().map(|&(a, b)| median[a] - median[b]);
+
+    ratios.chain(differences).collect()
+}
+
+/// Intensity statistics accumulated one subpixel at a time
+///
+/// Subpixels with non-positive intensity are ignored, so routing the pixels
+/// of an object into several accumulators (e.g. by a mask) gives the same
+/// descriptors as measuring each masked view separately.
+#[derive(Debug)]
+pub(crate) struct IntensityAccumulator {
+    n: Vec<usize>,
+    min: Vec<f32>,
+    max: Vec<f32>,
+    sum: Vec<f32>,
+    // Non-zero values of each channel in pixel order
+    values: Vec<Vec<f32>>,
+}
+
+impl IntensityAccumulator {
+    /// Initialize an accumulator for up to `capacity` subpixels
+    pub(crate) fn new(channels: usize, capacity: usize) -> Self {
+        Self {
+            n: vec![0; channels],
+            min: vec![f32::INFINITY; channels],
+            max: vec![f32::NEG_INFINITY; channels],
+            sum: vec![0.0; channels],
+            values: (0..channels)
+                .map(|_| Vec::with_capacity(capacity / channels.max(1)))
+                .collect(),
         }
     }
 
-    // Intensity mean
-    for i in 0..c {
-        if n[i] > 0 {
-            results[i + 3 * c] = results[i + 2 * c] * 1.0 / n[i] as f32;
+    /// Add the intensity of a single channel of a pixel
+    #[inline]
+    pub(crate) fn push(&mut self, channel: usize, v: f32) {
+        if v > 0. {
+            self.n[channel] += 1;
+            self.min[channel] = self.min[channel].min(v);
+            self.max[channel] = self.max[channel].max(v);
+            self.sum[channel] += v;
+            self.values[channel].push(v);
         }
     }
 
-    // Intensity standard deviation
-    for pixel in store.chunks_exact(c) {
-        for (i, &v) in pixel.iter().enumerate() {
-            if v > 0. {
-                results[i + 4 * c] += (v - results[i + 3 * c]).powi(2);
-            }
-        }
+    /// Compute the intensity descriptors of the accumulated pixels
+    ///
+    /// Descriptors follow the layout of `objects` with min, max, sum, mean,
+    /// and standard deviation stored in chunks that span the number of
+    /// channels, followed by the median, mad, and optional extended descriptors.
+    pub(crate) fn finish(self, extended: bool, approx_over: Option<usize>) -> Vec<f32> {
+        let mut sums = IntensitySums {
+            n: self.n,
+            min: self.min,
+            max: self.max,
+            sum: self.sum,
+            deviations: vec![],
+        };
+
+        let mean = sums.mean();
+
+        sums.deviations = self
+            .values
+            .iter()
+            .zip(mean)
+            .map(|(values, mean)| {
+                values
+                    .iter()
+                    .fold(0.0, |deviations, &v| deviations + (v - mean).powi(2))
+            })
+            .collect();
+
+        finish_reduced(self.values, &sums, extended, approx_over)
     }
+}
+
+/// Compute intensity descriptors from reduced sums and the positive values of each channel
+///
+/// Descriptors follow the layout of `IntensityAccumulator::finish`. The sums
+/// give the min, max, sum, mean, and standard deviation while the median, mad,
+/// and extended descriptors are computed from the values.
+///
+/// # Arguments
+///
+/// * `values` - Positive values of each channel in pixel order
+/// * `sums` - Intensity sums of the same values
+/// * `extended` - Include extended intensity descriptors
+/// * `approx_over` - Number of positive values of a channel above which extended descriptors are not sorted
+#[allow(clippy::identity_op, clippy::erasing_op)]
+pub(crate) fn finish_reduced(
+    mut values: Vec<Vec<f32>>,
+    sums: &IntensitySums,
+    extended: bool,
+    approx_over: Option<usize>,
+) -> Vec<f32> {
+    let c = sums.n.len();
+    let len = c * 5 + 2;
+
+    let mut results = vec![0.0; len];
 
     for i in 0..c {
-        if n[i] > 0 {
-            results[i + 4 * c] = (results[i + 4 * c] * 1.0 / n[i] as f32).sqrt();
+        if sums.min[i] != f32::INFINITY {
+            results[i + 0 * c] = sums.min[i];
         }
-    }
 
-    store.retain(|x| *x > 0.);
+        if sums.max[i] != f32::NEG_INFINITY {
+            results[i + 1 * c] = sums.max[i];
+        }
 
-    if store.is_empty() {
-        return results;
+        results[i + 2 * c] = sums.sum[i];
+
+        // Intensity mean
+        if sums.n[i] > 0 {
+            results[i + 3 * c] = results[i + 2 * c] * 1.0 / sums.n[i] as f32;
+        }
+
+        // Intensity standard deviation
+        results[i + 4 * c] = sums.deviations[i];
+
+        if sums.n[i] > 0 {
+            results[i + 4 * c] = (results[i + 4 * c] * 1.0 / sums.n[i] as f32).sqrt();
+        }
     }
 
-    store.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    if extended {
+        let n = EXTENDED_PERCENTILES.len();
+        let mut descriptors = vec![0.0; c * (n + 2)];
 
-    let n = store.len();
-    let mid = n / 2;
-    let len = results.len();
+        for (i, values) in values.iter_mut().enumerate() {
+            for (j, value) in extended_channel(values, approx_over).iter().enumerate() {
+                descriptors[i + j * c] = *value;
+            }
+        }
 
-    // Intensity median
-    results[len - 2] = if n % 2 == 0 {
-        (store[mid - 1] + store[mid]) / 2.0
-    } else {
-        store[mid]
-    };
+        results.extend(descriptors);
+    }
 
-    store
-        .iter_mut()
-        .for_each(|pixel| *pixel = (*pixel - results[len - 2]).abs());
+    let mut store: Vec<f32> = values.concat();
 
-    store.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    if store.is_empty() {
+        return results;
+    }
 
-    // Intensity median absolute deviation
-    results[len - 1] = if n % 2 == 0 {
-        (store[mid] + store[mid - 1]) / 2.0
-    } else {
-        store[mid]
-    };
+    // Intensity median and median absolute deviation
+    (results[len - 2], results[len - 1]) = median_mad(&mut store);
 
     results
 }
@@ -401,6 +747,32 @@ mod test {
         ThymeBuffer::new(2, 2, 3, pixels).unwrap()
     }
 
+    // Fixed random sample (6 x 6 pixels, 2 channels) with expected values
+    // from numpy.percentile and scipy.stats.skew/kurtosis on non-zero pixels
+    fn test_sample() -> (Vec<u8>, usize) {
+        let channels = 2;
+        let pixels: Vec<u8> = vec![
+            138, 181, 253, 101, 0, 147, 253, 50, 136, 70, 116, 115, 55, 218, 50, 248, 45, 140, 209,
+            79, 0, 82, 242, 0, 47, 159, 157, 242, 44, 0, 69, 217, 191, 182, 103, 58, 212, 17, 208,
+            107, 165, 216, 155, 141, 150, 21, 250, 196, 236, 0, 202, 11, 130, 226, 175, 92, 207,
+            80, 105, 43, 66, 126, 5, 94, 24, 152, 11, 11, 63, 0, 243, 62,
+        ];
+        (pixels, channels)
+    }
+
+    const SAMPLE_PERCENTILES: [[f32; 2]; 4] = [
+        [44.3, 23.2],
+        [63.75, 68.0],
+        [207.75, 181.25],
+        [242.7, 217.9],
+    ];
+    const SAMPLE_SKEW: [f32; 2] = [-0.097022, 0.174767];
+    const SAMPLE_KURTOSIS: [f32; 2] = [-1.297511, -1.082448];
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
     #[test]
     fn test_intensity_min() {
         let (pixels, channels) = test_pixels();
@@ -462,7 +834,7 @@ mod test {
         let median = intensity_median(&pixels);
         let mad = intensity_mad(&pixels);
 
-        let results = descriptors(&pixels, channels);
+        let results = descriptors(&pixels, channels, false, None);
 
         for i in 0..3 {
             assert_eq!(min[i], results[i]);
@@ -490,7 +862,7 @@ mod test {
 
         let buffer = test_object();
         let object = buffer.crop_view(0, 0, 2, 2);
-        let results = objects(&object);
+        let results = objects(&object, false, None);
 
         for i in 0..3 {
             assert_eq!(min[i], results[i]);
@@ -503,4 +875,206 @@ mod test {
         assert_eq!(median, results[3 + 12]);
         assert_eq!(mad, results[3 + 13]);
     }
+
+    #[test]
+    fn test_intensity_percentile() {
+        let (pixels, channels) = test_sample();
+        for (q, expected) in EXTENDED_PERCENTILES.iter().zip(SAMPLE_PERCENTILES) {
+            let percentile = intensity_percentile(&pixels, channels, *q);
+            for i in 0..channels {
+                assert_close(percentile[i], expected[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_intensity_skew() {
+        let (pixels, channels) = test_sample();
+        let skew = intensity_skew(&pixels, channels, None);
+        for i in 0..channels {
+            assert_close(skew[i], SAMPLE_SKEW[i]);
+        }
+    }
+
+    #[test]
+    fn test_intensity_kurtosis() {
+        let (pixels, channels) = test_sample();
+        let kurtosis = intensity_kurtosis(&pixels, channels, None);
+        for i in 0..channels {
+            assert_close(kurtosis[i], SAMPLE_KURTOSIS[i]);
+        }
+    }
+
+    #[test]
+    fn test_intensity_constant() {
+        let (pixels, channels) = test_pixels();
+        let percentile = intensity_percentile(&pixels, channels, 0.9);
+        assert_eq!(percentile[..2], [0.0, 1.0]);
+        assert_close(percentile[2], 4.7);
+        assert_eq!(intensity_skew(&pixels, channels, None)[..2], [0.0, 0.0]);
+        assert_eq!(intensity_kurtosis(&pixels, channels, None)[..2], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_descriptors_extended() {
+        let (pixels, channels) = test_sample();
+
+        let default = descriptors(&pixels, channels, false, None);
+        let results = descriptors(&pixels, channels, true, None);
+
+        assert_eq!(results.len(), channels * 11 + 2);
+        assert_eq!(results[..default.len()], default[..]);
+
+        let offset = default.len();
+        for i in 0..channels {
+            for (j, expected) in SAMPLE_PERCENTILES.iter().enumerate() {
+                assert_close(results[offset + i + j * channels], expected[i]);
+            }
+
+            assert_close(results[offset + i + 4 * channels], SAMPLE_SKEW[i]);
+            assert_close(results[offset + i + 5 * channels], SAMPLE_KURTOSIS[i]);
+        }
+    }
+
+    #[test]
+    fn test_objects_extended() {
+        let (pixels, channels) = test_sample();
+
+        let buffer = ThymeBuffer::new(6, 6, channels as u32, pixels).unwrap();
+        let object = buffer.crop_view(0, 0, 6, 6);
+
+        let default = objects(&object, false, None);
+        let results = objects(&object, true, None);
+
+        assert_eq!(results.len(), channels * 11 + 2);
+        assert_eq!(results[..default.len()], default[..]);
+
+        let offset = default.len();
+        for i in 0..channels {
+            for (j, expected) in SAMPLE_PERCENTILES.iter().enumerate() {
+                assert_close(results[offset + i + j * channels], expected[i]);
+            }
+
+            assert_close(results[offset + i + 4 * channels], SAMPLE_SKEW[i]);
+            assert_close(results[offset + i + 5 * channels], SAMPLE_KURTOSIS[i]);
+        }
+
+        // Percentiles selected without a full sort match the sorted percentiles
+        let selected = objects(&object, true, Some(1));
+        assert_eq!(
+            selected[..offset + 4 * channels],
+            results[..offset + 4 * channels]
+        );
+    }
+
+    #[test]
+    fn test_channel_pairs() {
+        // Channel 0 is twice channel 1 and channel 2 is empty
+        let pixels: Vec<u8> = vec![2, 1, 0, 4, 2, 0, 6, 3, 0, 8, 4, 0];
+        let buffer = ThymeBuffer::new(2, 2, 3, pixels).unwrap();
+        let object = buffer.crop_view(0, 0, 2, 2);
+
+        let results = channel_pairs(&object, &[(0, 1), (1, 0), (0, 2)]);
+
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[..2], [2.0, 0.5]);
+        assert!(results[2].is_nan());
+        assert_eq!(results[3..], [2.5, -2.5, 5.0]);
+
+        assert!(channel_pairs(&object, &[]).is_empty());
+    }
+
+    /// Pseudo-random intensities in [1, 4096] with repeated values
+    fn random_values(n: usize, seed: u64) -> Vec<f32> {
+        let mut state = seed;
+        (0..n)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                ((state >> 33) % 4096 + 1) as f32
+            })
+            .collect()
+    }
+
+    /// Median and mad computed with full sorts (previous implementation)
+    fn sorted_median_mad(values: &[f32]) -> (f32, f32) {
+        let mut store = values.to_vec();
+        store.sort_unstable_by(compare);
+
+        let (n, mid) = (store.len(), store.len() / 2);
+        let median = match n % 2 {
+            0 => (store[mid - 1] + store[mid]) / 2.0,
+            _ => store[mid],
+        };
+
+        store.iter_mut().for_each(|v| *v = (*v - median).abs());
+        store.sort_unstable_by(compare);
+
+        let mad = match n % 2 {
+            0 => (store[mid] + store[mid - 1]) / 2.0,
+            _ => store[mid],
+        };
+
+        (median, mad)
+    }
+
+    #[test]
+    fn test_median_mad_selection() {
+        for n in [1, 2, 3, 4, 101, 1000, 4097] {
+            let values = random_values(n, n as u64);
+            let (median, mad) = sorted_median_mad(&values);
+
+            let mut selected = values.clone();
+            assert_eq!(median_mad(&mut selected), (median, mad), "{}", n);
+            assert_eq!(intensity_median(&values), median);
+            assert_eq!(intensity_mad(&values), mad);
+        }
+
+        assert_eq!(median_mad(&mut []), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_select_ranks() {
+        let values = random_values(999, 7);
+        let mut sorted = values.clone();
+        sorted.sort_unstable_by(compare);
+
+        let ranks = percentile_ranks(values.len(), &EXTENDED_PERCENTILES);
+        let mut selected = values.clone();
+        select_ranks(&mut selected, &ranks);
+
+        for &rank in &ranks {
+            assert_eq!(selected[rank], sorted[rank]);
+        }
+
+        for &q in &EXTENDED_PERCENTILES {
+            assert_eq!(percentile(&selected, q), percentile(&sorted, q));
+        }
+    }
+
+    #[test]
+    fn test_extended_approx_threshold() {
+        let threshold = 500;
+
+        for n in [threshold - 1, threshold, threshold + 1] {
+            let values = random_values(n, 11);
+
+            let mut sorted = values.clone();
+            let exact = extended_channel(&mut sorted, None);
+
+            let mut selected = values.clone();
+            let approx = extended_channel(&mut selected, Some(threshold));
+
+            if n <= threshold {
+                // Bit-identical at and below the threshold
+                assert_eq!(approx, exact, "{}", n);
+            } else {
+                // Percentiles are exact order statistics and moments only differ by rounding
+                assert_eq!(approx[..4], exact[..4], "{}", n);
+                assert_close(approx[4], exact[4]);
+                assert_close(approx[5], exact[5]);
+            }
+        }
+    }
 }
@@ -0,0 +1,176 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::cmp::Ordering;
+use std::ops::Deref;
+
+use num::{FromPrimitive, ToPrimitive};
+
+use crate::cv::morphology::distance_chessboard;
+use crate::im::{ThymeMaskView, ThymeViewBuffer};
+
+#[inline]
+fn median(values: &mut [f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    let n = values.len();
+    let mid = n / 2;
+
+    if n % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Compute boundary band and interior intensity descriptors for an object
+///
+/// The boundary band is defined as all foreground mask pixels within `width`
+/// pixels (chessboard distance) of the background. The remaining foreground
+/// pixels, equivalent to eroding the mask by `width` pixels, define the
+/// interior. The object and mask are assumed to share the same dimensions.
+///
+/// # Arguments
+///
+/// * `object` - An object view
+/// * `mask` - A binary mask view with the same dimensions as the object
+/// * `width` - Width of the boundary band in pixels
+///
+/// # Notes
+///
+/// Boundary mean, boundary median, interior mean, interior median, and the
+/// boundary-to-interior mean ratio are stored in chunks that span the number
+/// of channels. Any empty region, or an interior mean of zero, returns zero.
+#[inline]
+#[allow(clippy::identity_op, clippy::erasing_op)]
+pub fn objects<T, Container>(
+    object: &ThymeViewBuffer<T, Container>,
+    mask: &ThymeMaskView,
+    width: u32,
+) -> Vec<f32>
+where
+    T: ToPrimitive + FromPrimitive,
+    Container: Deref<Target = [T]>,
+{
+    let c = object.channels();
+    let mut results = vec![0.0; c * 5];
+
+    if object.width() != mask.width() || object.height() != mask.height() {
+        return results;
+    }
+
+    let binary: Vec<u32> = mask.iter().copied().collect();
+    let distance = distance_chessboard(mask.width() as u32, mask.height() as u32, &binary);
+
+    let mut boundary: Vec<Vec<f32>> = vec![Vec::with_capacity(object.len()); c];
+    let mut interior: Vec<Vec<f32>> = vec![Vec::with_capacity(object.len()); c];
+
+    for (pixel, &d) in object.iter_pixels().zip(distance.iter()) {
+        if d == 0 {
+            continue;
+        }
+
+        let region = if d <= width {
+            &mut boundary
+        } else {
+            &mut interior
+        };
+
+        for (i, v) in pixel.iter().enumerate() {
+            region[i].push(v.to_f32().unwrap());
+        }
+    }
+
+    for i in 0..c {
+        if !boundary[i].is_empty() {
+            results[i + 0 * c] = boundary[i].iter().sum::<f32>() / boundary[i].len() as f32;
+        }
+
+        if !interior[i].is_empty() {
+            results[i + 2 * c] = interior[i].iter().sum::<f32>() / interior[i].len() as f32;
+        }
+
+        results[i + 1 * c] = median(&mut boundary[i]);
+        results[i + 3 * c] = median(&mut interior[i]);
+
+        if results[i + 2 * c] != 0.0 {
+            results[i + 4 * c] = results[i + 0 * c] / results[i + 2 * c];
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::im::{ThymeBuffer, ThymeMask};
+
+    /// A 9x9 square cell padded by one pixel with a bright two pixel rim
+    fn test_cell() -> (ThymeBuffer<u8, Vec<u8>>, ThymeMask) {
+        let size = 11usize;
+        let mut pixels = vec![0u8; size * size];
+        let mut mask = vec![0u32; size * size];
+
+        for y in 1..10 {
+            for x in 1..10 {
+                let ring = (x - 1).min(y - 1).min(9 - x).min(9 - y);
+                pixels[y * size + x] = if ring < 2 { 200 } else { 50 };
+                mask[y * size + x] = 1;
+            }
+        }
+
+        (
+            ThymeBuffer::new(size as u32, size as u32, 1, pixels).unwrap(),
+            ThymeMask::new(size as u32, size as u32, 1, mask).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_objects_rim() {
+        let (image, mask) = test_cell();
+
+        let object = image.crop_view(0, 0, 11, 11);
+        let mask = mask.crop_view(0, 0, 11, 11);
+
+        let results = objects(&object, &mask, 2);
+
+        assert_eq!(results, vec![200.0, 200.0, 50.0, 50.0, 4.0]);
+    }
+
+    #[test]
+    fn test_objects_band_width() {
+        let (image, mask) = test_cell();
+
+        let object = image.crop_view(0, 0, 11, 11);
+        let mask = mask.crop_view(0, 0, 11, 11);
+
+        // A one pixel band assigns the inner bright ring (24 px) to the
+        // interior alongside the 25 dim interior pixels
+        let results = objects(&object, &mask, 1);
+        let interior = (24.0 * 200.0 + 25.0 * 50.0) / 49.0;
+
+        assert_eq!(results[0], 200.0);
+        assert!((results[2] - interior).abs() < 1e-4);
+        assert!((results[4] - 200.0 / interior).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_objects_empty_interior() {
+        let (image, mask) = test_cell();
+
+        let object = image.crop_view(0, 0, 11, 11);
+        let mask = mask.crop_view(0, 0, 11, 11);
+
+        let results = objects(&object, &mask, 10);
+
+        assert_eq!(results[2], 0.0);
+        assert_eq!(results[3], 0.0);
+        assert_eq!(results[4], 0.0);
+    }
+}
@@ -1,5 +1,8 @@
 pub mod form;
 pub mod intensity;
+pub mod membrane;
 pub mod moments;
+pub mod reduce;
+pub mod spectrum;
 pub mod texture;
 pub mod zernike;
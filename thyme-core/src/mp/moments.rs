@@ -5,7 +5,7 @@ use std::ops::Deref;
 
 use num::{FromPrimitive, ToPrimitive};
 
-use crate::im::ThymeViewBuffer;
+use crate::im::{ThymeMaskView, ThymeViewBuffer};
 
 #[inline]
 pub fn moments_raw<T>(pixels: &[T], width: usize) -> [f32; 10]
@@ -230,22 +230,42 @@ where
     T: ToPrimitive + FromPrimitive,
     Container: Deref<Target = [T]>,
 {
-    let mut m00 = 0.0;
-    let mut m10 = 0.0;
-    let mut m01 = 0.0;
-    let mut m11 = 0.0;
-    let mut m20 = 0.0;
-    let mut m02 = 0.0;
-    let mut m12 = 0.0;
-    let mut m21 = 0.0;
-    let mut m30 = 0.0;
-    let mut m03 = 0.0;
+    let mut moments = MomentsAccumulator::default();
 
     for (i, pixel) in object.iter().enumerate() {
-        let pixel = pixel.to_f32().unwrap();
+        moments.push(
+            i % object.width(),
+            i / object.width(),
+            pixel.to_f32().unwrap(),
+        );
+    }
+
+    moments.finish()
+}
+
+/// Raw moments accumulated one subpixel at a time
+///
+/// Subpixels with non-positive intensity are ignored so that masked views
+/// and single-pass region measurements share the same thresholding.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct MomentsAccumulator {
+    m00: f32,
+    m10: f32,
+    m01: f32,
+    m11: f32,
+    m20: f32,
+    m02: f32,
+    m12: f32,
+    m21: f32,
+    m30: f32,
+    m03: f32,
+}
+
+impl MomentsAccumulator {
+    /// Add a subpixel at position (x, y) of the object
+    #[inline]
+    pub(crate) fn push(&mut self, x: usize, y: usize, pixel: f32) {
         if pixel > 0.0 {
-            let x = i % object.width();
-            let y = i / object.width();
             let xa = x as f32;
             let xb = xa * xa;
             let xc = xb * xa;
@@ -253,64 +273,233 @@ where
             let yb = ya * ya;
             let yc = yb * ya;
 
-            m00 += pixel;
-            m10 += xa * pixel;
-            m01 += ya * pixel;
-            m11 += xa * ya * pixel;
-            m20 += xb * pixel;
-            m02 += yb * pixel;
-            m21 += xb * ya * pixel;
-            m12 += xa * yb * pixel;
-            m30 += xc * pixel;
-            m03 += yc * pixel;
+            self.m00 += pixel;
+            self.m10 += xa * pixel;
+            self.m01 += ya * pixel;
+            self.m11 += xa * ya * pixel;
+            self.m20 += xb * pixel;
+            self.m02 += yb * pixel;
+            self.m21 += xb * ya * pixel;
+            self.m12 += xa * yb * pixel;
+            self.m30 += xc * pixel;
+            self.m03 += yc * pixel;
         }
     }
 
-    if m00 == 0.0 {
-        return [0.0; 24];
+    /// Initialize an accumulator from raw moments in `descriptors` order
+    pub(crate) fn from_raw(raw: [f32; 10]) -> Self {
+        let [m00, m10, m01, m11, m20, m02, m21, m12, m30, m03] = raw;
+
+        MomentsAccumulator {
+            m00,
+            m10,
+            m01,
+            m11,
+            m20,
+            m02,
+            m12,
+            m21,
+            m30,
+            m03,
+        }
     }
 
-    let x = m10 / m00;
-    let y = m01 / m00;
+    /// Raw moments of the accumulated subpixels in `descriptors` order
+    pub(crate) fn raw(&self) -> [f32; 10] {
+        [
+            self.m00, self.m10, self.m01, self.m11, self.m20, self.m02, self.m21, self.m12,
+            self.m30, self.m03,
+        ]
+    }
 
-    let u00 = m00;
-    let u11 = m11 - x * m01;
-    let u20 = m20 - x * m10;
-    let u02 = m02 - y * m01;
-    let u21 = m21 - 2.0 * x * m11 - y * m20 + 2.0 * x * x * m01;
-    let u12 = m12 - 2.0 * y * m11 - x * m02 + 2.0 * y * y * m10;
-    let u30 = m30 - 3.0 * x * m20 + 2.0 * x * x * m10;
-    let u03 = m03 - 3.0 * y * m02 + 2.0 * y * y * m01;
+    /// Compute the raw, central, and Hu moments from the accumulated subpixels
+    pub(crate) fn finish(&self) -> [f32; 24] {
+        let MomentsAccumulator {
+            m00,
+            m10,
+            m01,
+            m11,
+            m20,
+            m02,
+            m12,
+            m21,
+            m30,
+            m03,
+        } = *self;
+
+        if m00 == 0.0 {
+            return [0.0; 24];
+        }
 
-    let s2 = u00 * u00;
-    let s3 = u00.powf(2.5);
+        let x = m10 / m00;
+        let y = m01 / m00;
+
+        let u00 = m00;
+        let u11 = m11 - x * m01;
+        let u20 = m20 - x * m10;
+        let u02 = m02 - y * m01;
+        let u21 = m21 - 2.0 * x * m11 - y * m20 + 2.0 * x * x * m01;
+        let u12 = m12 - 2.0 * y * m11 - x * m02 + 2.0 * y * y * m10;
+        let u30 = m30 - 3.0 * x * m20 + 2.0 * x * x * m10;
+        let u03 = m03 - 3.0 * y * m02 + 2.0 * y * y * m01;
+
+        let s2 = u00 * u00;
+        let s3 = u00.powf(2.5);
+
+        let n20 = u20 / s2;
+        let n02 = u02 / s2;
+        let n11 = u11 / s2;
+        let n30 = u30 / s3;
+        let n03 = u03 / s3;
+        let n21 = u21 / s3;
+        let n12 = u12 / s3;
+
+        let p = n20 - n02;
+        let q = n30 - 3.0 * n12;
+        let r = n30 + n12;
+        let z = n21 + n03;
+        let y = 3.0 * n21 - n03;
+
+        let i1 = n20 + n02;
+        let i2 = p * p + 4.0 * n11 * n11;
+        let i3 = q * q + y * y;
+        let i4 = r * r + z * z;
+        let i5 = q * r * (r * r - 3.0 * z * z) + y * z * (3.0 * r * r - z * z);
+        let i6 = p * (r * r - z * z) + 4.0 * n11 * r * z;
+        let i7 = y * r * (r * r - 3.0 * z * z) - q * z * (3.0 * r * r - z * z);
+
+        [
+            m00, m10, m01, m11, m20, m02, m21, m12, m30, m03, u11, u20, u02, u21, u12, u30, u03,
+            i1, i2, i3, i4, i5, i6, i7,
+        ]
+    }
+}
 
-    let n20 = u20 / s2;
-    let n02 = u02 / s2;
-    let n11 = u11 / s2;
-    let n30 = u30 / s3;
-    let n03 = u03 / s3;
-    let n21 = u21 / s3;
-    let n12 = u12 / s3;
+/// Compute the intensity-weighted centroid of each channel within a mask
+///
+/// Unlike the thresholded moments, every foreground mask pixel contributes
+/// to the centroid weighted by its intensity. The object and mask are
+/// assumed to share the same dimensions.
+///
+/// # Arguments
+///
+/// * `object` - An object view
+/// * `mask` - A binary mask view with the same dimensions as the object
+///
+/// # Notes
+///
+/// The (x, y) centroid is stored for each channel. Channels with a
+/// non-positive total intensity within the mask return the geometric
+/// centroid of the mask.
+#[inline]
+pub fn weighted_centroids<T, Container>(
+    object: &ThymeViewBuffer<T, Container>,
+    mask: &ThymeMaskView,
+) -> Vec<[f32; 2]>
+where
+    T: ToPrimitive + FromPrimitive,
+    Container: Deref<Target = [T]>,
+{
+    let c = object.channels();
 
-    let p = n20 - n02;
-    let q = n30 - 3.0 * n12;
-    let r = n30 + n12;
-    let z = n21 + n03;
-    let y = 3.0 * n21 - n03;
+    if object.width() != mask.width() || object.height() != mask.height() {
+        return vec![[0.0; 2]; c];
+    }
 
-    let i1 = n20 + n02;
-    let i2 = p * p + 4.0 * n11 * n11;
-    let i3 = q * q + y * y;
-    let i4 = r * r + z * z;
-    let i5 = q * r * (r * r - 3.0 * z * z) + y * z * (3.0 * r * r - z * z);
-    let i6 = p * (r * r - z * z) + 4.0 * n11 * r * z;
-    let i7 = y * r * (r * r - 3.0 * z * z) - q * z * (3.0 * r * r - z * z);
+    let width = object.width();
 
-    [
-        m00, m10, m01, m11, m20, m02, m21, m12, m30, m03, u11, u20, u02, u21, u12, u30, u03, i1,
-        i2, i3, i4, i5, i6, i7,
-    ]
+    let mut area = 0.0;
+    let mut geometric = [0.0; 2];
+    let mut m00 = vec![0.0; c];
+    let mut m10 = vec![0.0; c];
+    let mut m01 = vec![0.0; c];
+
+    for (i, (pixel, &m)) in object.iter_pixels().zip(mask.iter()).enumerate() {
+        if m == 0 {
+            continue;
+        }
+
+        let x = (i % width) as f32;
+        let y = (i / width) as f32;
+
+        area += 1.0;
+        geometric[0] += x;
+        geometric[1] += y;
+
+        for (j, v) in pixel.iter().enumerate() {
+            let v = v.to_f32().unwrap();
+            m00[j] += v;
+            m10[j] += x * v;
+            m01[j] += y * v;
+        }
+    }
+
+    if area == 0.0 {
+        return vec![[0.0; 2]; c];
+    }
+
+    let geometric = [geometric[0] / area, geometric[1] / area];
+
+    (0..c)
+        .map(|j| {
+            if m00[j] > 0.0 {
+                [m10[j] / m00[j], m01[j] / m00[j]]
+            } else {
+                geometric
+            }
+        })
+        .collect()
+}
+
+/// Compute the displacement of each channel's intensity-weighted centroid
+///
+/// The displacement is the Euclidean distance between the geometric centroid
+/// of the mask and the intensity-weighted centroid of each channel divided
+/// by the equivalent diameter of the mask, so that it captures the
+/// polarization of a stain independent of object size.
+///
+/// # Arguments
+///
+/// * `object` - An object view
+/// * `mask` - A binary mask view with the same dimensions as the object
+///
+/// # Notes
+///
+/// A displacement is stored for each channel. An empty mask, or a channel
+/// with a non-positive total intensity within the mask, returns zero.
+#[inline]
+pub fn centroid_displacement<T, Container>(
+    object: &ThymeViewBuffer<T, Container>,
+    mask: &ThymeMaskView,
+) -> Vec<f32>
+where
+    T: ToPrimitive + FromPrimitive,
+    Container: Deref<Target = [T]>,
+{
+    let c = object.channels();
+
+    if object.width() != mask.width() || object.height() != mask.height() {
+        return vec![0.0; c];
+    }
+
+    let binary: Vec<u8> = mask.iter().map(|&m| (m != 0) as u8).collect();
+    let [area, x, y, ..] = moments_raw(&binary, mask.width());
+
+    if area == 0.0 {
+        return vec![0.0; c];
+    }
+
+    let geometric = [x / area, y / area];
+    let diameter = (4.0 * area / std::f32::consts::PI).sqrt();
+
+    weighted_centroids(object, mask)
+        .into_iter()
+        .map(|[wx, wy]| {
+            let dx = wx - geometric[0];
+            let dy = wy - geometric[1];
+            (dx * dx + dy * dy).sqrt() / diameter
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -483,4 +672,51 @@ mod test {
         assert_eq!(moments_object_b, moments_array_b);
         assert_eq!(moments_object_c, moments_array_c);
     }
+
+    #[test]
+    fn test_centroid_displacement_half_bright() {
+        // A 10x10 cell padded by one pixel with a dim left half and a bright
+        // right half in the first channel and uniform intensity in the second
+        let size = 12usize;
+        let mut pixels = vec![0u8; size * size * 2];
+        let mut mask = vec![0u32; size * size];
+
+        for y in 1..11 {
+            for x in 1..11 {
+                let idx = y * size + x;
+                pixels[2 * idx] = if x < 6 { 50 } else { 150 };
+                pixels[2 * idx + 1] = 100;
+                mask[idx] = 1;
+            }
+        }
+
+        let image = ThymeBuffer::new(size as u32, size as u32, 2, pixels).unwrap();
+        let mask = crate::im::ThymeMask::new(size as u32, size as u32, 1, mask).unwrap();
+
+        let object = image.crop_view(0, 0, size as u32, size as u32);
+        let mask = mask.crop_view(0, 0, size as u32, size as u32);
+
+        let centroids = weighted_centroids(&object, &mask);
+        assert!((centroids[0][0] - 6.75).abs() < 1e-5);
+        assert!((centroids[0][1] - 5.5).abs() < 1e-5);
+        assert!((centroids[1][0] - 5.5).abs() < 1e-5);
+
+        // Halves weighted 1:3 shift the centroid by (3 - 1) / (3 + 1) * L / 4
+        let expected = 0.5 * 10.0 / 4.0 / (400.0 / std::f32::consts::PI).sqrt();
+
+        let displacement = centroid_displacement(&object, &mask);
+        assert!((displacement[0] - expected).abs() < 1e-5);
+        assert!(displacement[1].abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_centroid_displacement_empty() {
+        let image = ThymeBuffer::new(2, 2, 1, vec![1u8, 2, 3, 4]).unwrap();
+        let mask = crate::im::ThymeMask::new(2, 2, 1, vec![0u32; 4]).unwrap();
+
+        let object = image.crop_view(0, 0, 2, 2);
+        let mask = mask.crop_view(0, 0, 2, 2);
+
+        assert_eq!(centroid_displacement(&object, &mask), vec![0.0]);
+    }
 }
@@ -0,0 +1,764 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use crate::error::ThymeError;
+use crate::mp::moments::MomentsAccumulator;
+
+/// Objects with fewer subpixels are reduced on the cpu by `DeviceReduce`
+pub const DEVICE_MIN_SUBPIXELS: usize = 1 << 20;
+
+/// Device that intensity and moment reductions are computed on
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::mp::reduce::MeasureDevice;
+///
+/// assert_eq!(MeasureDevice::parse("cpu").unwrap(), MeasureDevice::Cpu);
+/// assert_eq!(MeasureDevice::parse("cuda").unwrap(), MeasureDevice::Cuda);
+/// assert!(MeasureDevice::parse("metal").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MeasureDevice {
+    /// Accumulate descriptors in a single pass on the cpu
+    #[default]
+    Cpu,
+    /// Reduce the subpixels of large objects on the first cuda device
+    Cuda,
+}
+
+impl MeasureDevice {
+    /// Parse a measure device from a string (cpu or cuda)
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - String representation of the device
+    pub fn parse(device: &str) -> Result<MeasureDevice, ThymeError> {
+        match device.trim() {
+            "cpu" => Ok(MeasureDevice::Cpu),
+            "cuda" => Ok(MeasureDevice::Cuda),
+            device => Err(ThymeError::OtherError(format!(
+                "Invalid measure device '{}'. Must be one of: cpu, cuda",
+                device
+            ))),
+        }
+    }
+
+    /// Reducer of the device, or None if descriptors are accumulated on the cpu
+    ///
+    /// # Arguments
+    ///
+    /// * `min_subpixels` - Objects with fewer subpixels are reduced on the cpu
+    pub fn reducer(&self, min_subpixels: usize) -> Result<Option<Box<dyn Reduce>>, ThymeError> {
+        match self {
+            MeasureDevice::Cpu => Ok(None),
+            #[cfg(feature = "cuda")]
+            MeasureDevice::Cuda => {
+                let device = candle_core::Device::new_cuda(0).map_err(|err| {
+                    ThymeError::OtherError(format!("Failed to open the cuda device: {}", err))
+                })?;
+
+                Ok(Some(Box::new(DeviceReduce::new(device, min_subpixels))))
+            }
+            #[cfg(not(feature = "cuda"))]
+            MeasureDevice::Cuda => {
+                let _ = min_subpixels;
+                Err(ThymeError::OtherError(
+                    "Measure device cuda requires thyme to be built with the cuda feature"
+                        .to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// Intensity sums of the positive subpixels of each channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntensitySums {
+    /// Number of positive subpixels
+    pub n: Vec<usize>,
+    /// Minimum positive intensity, or infinity if there are none
+    pub min: Vec<f32>,
+    /// Maximum positive intensity, or negative infinity if there are none
+    pub max: Vec<f32>,
+    /// Integrated intensity
+    pub sum: Vec<f32>,
+    /// Sum of squared deviations from the mean intensity
+    pub deviations: Vec<f32>,
+}
+
+impl IntensitySums {
+    /// Mean intensity of each channel, or zero if a channel has no positive subpixels
+    pub fn mean(&self) -> Vec<f32> {
+        self.n
+            .iter()
+            .zip(&self.sum)
+            .map(|(&n, &sum)| match n > 0 {
+                true => sum * 1.0 / n as f32,
+                false => 0.0,
+            })
+            .collect()
+    }
+}
+
+/// Intensity sums and raw moments of a region of an object
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reduction {
+    /// Intensity sums of each channel
+    pub intensity: IntensitySums,
+    /// Raw moments (m00, m10, m01, m11, m20, m02, m21, m12, m30, m03)
+    ///
+    /// Moments are indexed by subpixel as in `moments::objects`.
+    pub moments: [f32; 10],
+}
+
+/// Counts of the positive subpixels of each channel in equal-width bins
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// Lower edge of the first bin and upper edge of the last bin
+    pub range: [f32; 2],
+    /// Number of subpixels in each bin of each channel, indexed as [channel][bin]
+    pub counts: Vec<Vec<u64>>,
+}
+
+/// Check that a histogram has bins and a non-empty range
+fn check_histogram(bins: usize, range: [f32; 2]) -> Result<(), ThymeError> {
+    if bins == 0 || !range.iter().all(|v| v.is_finite()) || range[0] >= range[1] {
+        return Err(ThymeError::OtherError(format!(
+            "Invalid histogram with {} bins over [{}, {}]. Requires at least one bin and a finite range with a lower edge below the upper edge",
+            bins, range[0], range[1]
+        )));
+    }
+
+    Ok(())
+}
+
+/// Bin of a subpixel, or None if it is not positive or outside the range
+///
+/// Subpixels equal to the upper edge are counted in the last bin.
+#[inline]
+fn bin(v: f32, lower: f32, upper: f32, scale: f32, bins: usize) -> Option<usize> {
+    (v > 0. && v >= lower && v <= upper).then(|| (((v - lower) * scale) as usize).min(bins - 1))
+}
+
+/// Reduce the subpixels of an object into intensity sums and raw moments
+///
+/// Reductions are the embarrassingly parallel part of the intensity and
+/// moment descriptors, so they can be computed on another device while the
+/// order statistics (e.g. median) remain on the cpu.
+pub trait Reduce: Send + Sync {
+    /// Reduce the positive subpixels of each region of an object
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - Interleaved subpixels of the object
+    /// * `width` - Width of the object
+    /// * `channels` - Number of channels
+    /// * `regions` - Pixels of each region, or None for every pixel of the object
+    fn reduce(
+        &self,
+        pixels: &[f32],
+        width: usize,
+        channels: usize,
+        regions: &[Option<&[bool]>],
+    ) -> Result<Vec<Reduction>, ThymeError>;
+
+    /// Count the positive subpixels of each region of an object in equal-width bins
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - Interleaved subpixels of the object
+    /// * `channels` - Number of channels
+    /// * `regions` - Pixels of each region, or None for every pixel of the object
+    /// * `bins` - Number of bins
+    /// * `range` - Lower edge of the first bin and upper edge of the last bin. Subpixels outside the range are not counted.
+    fn histogram(
+        &self,
+        pixels: &[f32],
+        channels: usize,
+        regions: &[Option<&[bool]>],
+        bins: usize,
+        range: [f32; 2],
+    ) -> Result<Vec<Histogram>, ThymeError>;
+}
+
+/// Sequential reductions on the cpu
+///
+/// Subpixels are accumulated in the same order as `IntensityAccumulator` and
+/// `MomentsAccumulator`, so descriptors are identical to `descriptors_regions`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuReduce;
+
+impl Reduce for CpuReduce {
+    fn reduce(
+        &self,
+        pixels: &[f32],
+        width: usize,
+        channels: usize,
+        regions: &[Option<&[bool]>],
+    ) -> Result<Vec<Reduction>, ThymeError> {
+        let c = channels;
+
+        let reductions = regions
+            .iter()
+            .map(|region| {
+                let routed = |p: usize| region.is_none_or(|region| region[p]);
+
+                let mut n = vec![0; c];
+                let mut min = vec![f32::INFINITY; c];
+                let mut max = vec![f32::NEG_INFINITY; c];
+                let mut sum = vec![0.0; c];
+                let mut moments = MomentsAccumulator::default();
+
+                for (p, pixel) in pixels.chunks_exact(c).enumerate() {
+                    if !routed(p) {
+                        continue;
+                    }
+
+                    for (k, &v) in pixel.iter().enumerate() {
+                        if v > 0. {
+                            n[k] += 1;
+                            min[k] = min[k].min(v);
+                            max[k] = max[k].max(v);
+                            sum[k] += v;
+                        }
+
+                        let i = p * c + k;
+                        moments.push(i % width, i / width, v);
+                    }
+                }
+
+                let mut intensity = IntensitySums {
+                    n,
+                    min,
+                    max,
+                    sum,
+                    deviations: vec![0.0; c],
+                };
+
+                let mean = intensity.mean();
+
+                for (p, pixel) in pixels.chunks_exact(c).enumerate() {
+                    if !routed(p) {
+                        continue;
+                    }
+
+                    for (k, &v) in pixel.iter().enumerate() {
+                        if v > 0. {
+                            intensity.deviations[k] += (v - mean[k]).powi(2);
+                        }
+                    }
+                }
+
+                Reduction {
+                    intensity,
+                    moments: moments.raw(),
+                }
+            })
+            .collect();
+
+        Ok(reductions)
+    }
+
+    fn histogram(
+        &self,
+        pixels: &[f32],
+        channels: usize,
+        regions: &[Option<&[bool]>],
+        bins: usize,
+        range: [f32; 2],
+    ) -> Result<Vec<Histogram>, ThymeError> {
+        check_histogram(bins, range)?;
+
+        let [lower, upper] = range;
+        let scale = bins as f32 / (upper - lower);
+
+        let histograms = regions
+            .iter()
+            .map(|region| {
+                let mut counts = vec![vec![0u64; bins]; channels];
+
+                for (p, pixel) in pixels.chunks_exact(channels).enumerate() {
+                    if region.is_some_and(|region| !region[p]) {
+                        continue;
+                    }
+
+                    for (k, &v) in pixel.iter().enumerate() {
+                        if let Some(b) = bin(v, lower, upper, scale, bins) {
+                            counts[k][b] += 1;
+                        }
+                    }
+                }
+
+                Histogram { range, counts }
+            })
+            .collect();
+
+        Ok(histograms)
+    }
+}
+
+/// Tensor reductions on a candle device
+///
+/// Each object is uploaded once and every region is reduced on the device.
+/// Objects with fewer than `min_subpixels` subpixels are reduced by
+/// `CpuReduce` since transfers dominate their runtime. Sums are computed in
+/// a different order than on the cpu, so descriptors agree up to rounding.
+#[cfg(feature = "gpu")]
+#[derive(Debug, Clone)]
+pub struct DeviceReduce {
+    device: candle_core::Device,
+    min_subpixels: usize,
+}
+
+#[cfg(feature = "gpu")]
+impl DeviceReduce {
+    /// Initialize a reducer on a device
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - Device the reductions are computed on
+    /// * `min_subpixels` - Objects with fewer subpixels are reduced on the cpu
+    pub fn new(device: candle_core::Device, min_subpixels: usize) -> Self {
+        Self {
+            device,
+            min_subpixels,
+        }
+    }
+
+    /// Reduce every region of an object with candle tensor operations
+    fn reduce_tensors(
+        &self,
+        pixels: &[f32],
+        width: usize,
+        channels: usize,
+        regions: &[Option<&[bool]>],
+    ) -> candle_core::Result<Vec<Reduction>> {
+        use candle_core::{DType, Tensor};
+
+        let device = &self.device;
+        let c = channels;
+        let n_pixels = pixels.len() / c;
+        let rows = pixels.len() / width;
+
+        let values = Tensor::from_slice(pixels, (n_pixels, c), device)?;
+        let positive = values.gt(0f32)?.to_dtype(DType::F32)?;
+
+        // Moments are indexed by subpixel, so rows of the flat subpixels have the object width
+        let x = Tensor::arange(0u32, width as u32, device)?.to_dtype(DType::F32)?;
+        let y = Tensor::arange(0u32, rows as u32, device)?.to_dtype(DType::F32)?;
+        let (x2, y2) = (x.sqr()?, y.sqr()?);
+        let (x3, y3) = ((&x2 * &x)?, (&y2 * &y)?);
+        let x_row = x.reshape((1, width))?;
+        let x2_row = x2.reshape((1, width))?;
+
+        let infinity = Tensor::new(f32::INFINITY, device)?.broadcast_as((n_pixels, c))?;
+        let neg_infinity = Tensor::new(f32::NEG_INFINITY, device)?.broadcast_as((n_pixels, c))?;
+
+        let mut reductions = Vec::with_capacity(regions.len());
+
+        for region in regions {
+            let weights = match region {
+                None => positive.clone(),
+                Some(region) => {
+                    let region: Vec<u8> = region.iter().map(|&r| r as u8).collect();
+                    let region =
+                        Tensor::from_vec(region, (n_pixels, 1), device)?.to_dtype(DType::F32)?;
+
+                    positive.broadcast_mul(&region)?
+                }
+            };
+
+            let kept = weights.gt(0f32)?;
+            let weighted = (&values * &weights)?;
+
+            // Counts are summed in double precision so they stay exact past 2^24
+            let n: Vec<f64> = weights.to_dtype(DType::F64)?.sum(0)?.to_vec1()?;
+            let n: Vec<usize> = n.into_iter().map(|n| n.round() as usize).collect();
+
+            let min: Vec<f32> = kept.where_cond(&values, &infinity)?.min(0)?.to_vec1()?;
+            let max: Vec<f32> = kept.where_cond(&values, &neg_infinity)?.max(0)?.to_vec1()?;
+            let sum: Vec<f32> = weighted.sum(0)?.to_vec1()?;
+
+            let mut intensity = IntensitySums {
+                n,
+                min,
+                max,
+                sum,
+                deviations: vec![],
+            };
+
+            let mean = Tensor::from_vec(intensity.mean(), (1, c), device)?;
+            intensity.deviations = values
+                .broadcast_sub(&mean)?
+                .sqr()?
+                .mul(&weights)?
+                .sum(0)?
+                .to_vec1()?;
+
+            let weighted = weighted.reshape((rows, width))?;
+            let columns = weighted.sum(0)?;
+            let lines = weighted.sum(1)?;
+            let lines_x = weighted.broadcast_mul(&x_row)?.sum(1)?;
+            let lines_x2 = weighted.broadcast_mul(&x2_row)?.sum(1)?;
+
+            let moments = Tensor::stack(
+                &[
+                    columns.sum_all()?,
+                    (&columns * &x)?.sum_all()?,
+                    (&lines * &y)?.sum_all()?,
+                    (&lines_x * &y)?.sum_all()?,
+                    (&columns * &x2)?.sum_all()?,
+                    (&lines * &y2)?.sum_all()?,
+                    (&lines_x2 * &y)?.sum_all()?,
+                    (&lines_x * &y2)?.sum_all()?,
+                    (&columns * &x3)?.sum_all()?,
+                    (&lines * &y3)?.sum_all()?,
+                ],
+                0,
+            )?
+            .to_vec1::<f32>()?;
+
+            reductions.push(Reduction {
+                intensity,
+                moments: moments.try_into().unwrap(),
+            });
+        }
+
+        Ok(reductions)
+    }
+
+    /// Count the subpixels of every region of an object with candle tensor operations
+    ///
+    /// Bins are computed with the same f32 operations as `CpuReduce`, so the
+    /// counts are identical to the cpu.
+    fn histogram_tensors(
+        &self,
+        pixels: &[f32],
+        channels: usize,
+        regions: &[Option<&[bool]>],
+        bins: usize,
+        range: [f32; 2],
+    ) -> candle_core::Result<Vec<Histogram>> {
+        use candle_core::{DType, Tensor};
+
+        let device = &self.device;
+        let c = channels;
+        let n_pixels = pixels.len() / c;
+
+        let [lower, upper] = range;
+        let scale = bins as f32 / (upper - lower);
+
+        // Channels are rows so each channel is counted along the last dimension
+        let values = Tensor::from_slice(pixels, (n_pixels, c), device)?
+            .t()?
+            .contiguous()?;
+
+        let kept = values
+            .gt(0f32)?
+            .mul(&values.ge(lower)?)?
+            .mul(&values.le(upper)?)?;
+
+        let index = values
+            .affine(1.0, -lower as f64)?
+            .affine(scale as f64, 0.0)?
+            .floor()?
+            .clamp(0f32, (bins - 1) as f32)?;
+
+        let zeros = Tensor::zeros((c, n_pixels), DType::F32, device)?;
+        let index = kept.where_cond(&index, &zeros)?.to_dtype(DType::U32)?;
+
+        let mut histograms = Vec::with_capacity(regions.len());
+
+        for region in regions {
+            let weights = match region {
+                None => kept.to_dtype(DType::F64)?,
+                Some(region) => {
+                    let region: Vec<u8> = region.iter().map(|&r| r as u8).collect();
+                    let region = Tensor::from_vec(region, (1, n_pixels), device)?;
+
+                    kept.broadcast_mul(&region)?.to_dtype(DType::F64)?
+                }
+            };
+
+            // Counts are summed in double precision so they stay exact past 2^24
+            let counts: Vec<Vec<f64>> = Tensor::zeros((c, bins), DType::F64, device)?
+                .scatter_add(&index, &weights, 1)?
+                .to_vec2()?;
+
+            histograms.push(Histogram {
+                range,
+                counts: counts
+                    .into_iter()
+                    .map(|channel| channel.into_iter().map(|n| n.round() as u64).collect())
+                    .collect(),
+            });
+        }
+
+        Ok(histograms)
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl Reduce for DeviceReduce {
+    fn reduce(
+        &self,
+        pixels: &[f32],
+        width: usize,
+        channels: usize,
+        regions: &[Option<&[bool]>],
+    ) -> Result<Vec<Reduction>, ThymeError> {
+        if pixels.len() < self.min_subpixels || pixels.is_empty() {
+            return CpuReduce.reduce(pixels, width, channels, regions);
+        }
+
+        self.reduce_tensors(pixels, width, channels, regions)
+            .map_err(|err| {
+                ThymeError::OtherError(format!(
+                    "Failed to reduce object on {:?}: {}",
+                    self.device.location(),
+                    err
+                ))
+            })
+    }
+    fn histogram(
+        &self,
+        pixels: &[f32],
+        channels: usize,
+        regions: &[Option<&[bool]>],
+        bins: usize,
+        range: [f32; 2],
+    ) -> Result<Vec<Histogram>, ThymeError> {
+        check_histogram(bins, range)?;
+
+        if pixels.len() < self.min_subpixels || pixels.is_empty() {
+            return CpuReduce.histogram(pixels, channels, regions, bins, range);
+        }
+
+        self.histogram_tensors(pixels, channels, regions, bins, range)
+            .map_err(|err| {
+                ThymeError::OtherError(format!(
+                    "Failed to count object histogram on {:?}: {}",
+                    self.device.location(),
+                    err
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::ut::synth::SplitMix64;
+
+    /// Random interleaved subpixels with zeros and the pixels of a random region
+    fn random_object(seed: u64, width: usize, height: usize, c: usize) -> (Vec<f32>, Vec<bool>) {
+        let mut rng = SplitMix64::new(seed);
+
+        let pixels = (0..width * height * c)
+            .map(|_| match rng.range(0.0, 1.0) < 0.2 {
+                true => 0.0,
+                false => rng.range(0.0, 255.0) as f32,
+            })
+            .collect();
+
+        let region = (0..width * height)
+            .map(|_| rng.range(0.0, 1.0) < 0.6)
+            .collect();
+
+        (pixels, region)
+    }
+
+    #[test]
+    fn test_cpu_reduce() {
+        let (pixels, region) = random_object(1452, 7, 5, 3);
+        let outside: Vec<bool> = region.iter().map(|r| !r).collect();
+
+        let reductions = CpuReduce
+            .reduce(&pixels, 7, 3, &[None, Some(&region), Some(&outside)])
+            .unwrap();
+
+        for k in 0..3 {
+            let values: Vec<f32> = pixels.iter().skip(k).step_by(3).copied().collect();
+            let positive: Vec<f32> = values.iter().copied().filter(|&v| v > 0.).collect();
+            let complete = &reductions[0].intensity;
+
+            assert_eq!(complete.n[k], positive.len());
+            assert_eq!(
+                complete.min[k],
+                positive.iter().copied().fold(f32::INFINITY, f32::min)
+            );
+            assert_eq!(
+                complete.max[k],
+                positive.iter().copied().fold(0.0, f32::max)
+            );
+
+            // Regions partition the object
+            let parts = reductions[1].intensity.n[k] + reductions[2].intensity.n[k];
+            assert_eq!(parts, complete.n[k]);
+        }
+
+        let complete: [f32; 24] = crate::mp::moments::descriptors(&pixels, 7);
+        assert_eq!(reductions[0].moments, complete[..10]);
+
+        // A region with every pixel reduces like the complete object
+        let all = vec![true; 35];
+        let reduction = CpuReduce.reduce(&pixels, 7, 3, &[Some(&all)]).unwrap();
+        assert_eq!(reduction[0], reductions[0]);
+
+        assert!(MeasureDevice::Cpu.reducer(0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cpu_histogram() {
+        let (pixels, region) = random_object(1452, 9, 6, 2);
+        let outside: Vec<bool> = region.iter().map(|r| !r).collect();
+
+        let histograms = CpuReduce
+            .histogram(
+                &pixels,
+                2,
+                &[None, Some(&region), Some(&outside)],
+                8,
+                [0.0, 128.0],
+            )
+            .unwrap();
+
+        for k in 0..2 {
+            let values: Vec<f32> = pixels.iter().skip(k).step_by(2).copied().collect();
+            let complete = &histograms[0].counts[k];
+
+            for (b, &count) in complete.iter().enumerate() {
+                let [lower, upper] = [16.0 * b as f32, 16.0 * (b + 1) as f32];
+                let expected = values
+                    .iter()
+                    .filter(|&&v| v > 0. && v >= lower && (v < upper || (b == 7 && v == upper)))
+                    .count();
+
+                assert_eq!(count, expected as u64);
+            }
+
+            // Regions partition the object
+            for b in 0..8 {
+                let parts = histograms[1].counts[k][b] + histograms[2].counts[k][b];
+                assert_eq!(parts, complete[b]);
+            }
+        }
+
+        // Values at the upper edge are counted in the last bin and zeros are never counted
+        let histogram = CpuReduce
+            .histogram(&[0.0, 1.0, 2.0, 3.0, 4.0], 1, &[None], 4, [0.0, 4.0])
+            .unwrap();
+        assert_eq!(histogram[0].counts, vec![vec![0, 1, 1, 2]]);
+
+        assert!(
+            CpuReduce
+                .histogram(&pixels, 2, &[None], 0, [0.0, 1.0])
+                .is_err()
+        );
+        assert!(
+            CpuReduce
+                .histogram(&pixels, 2, &[None], 4, [1.0, 1.0])
+                .is_err()
+        );
+    }
+
+    /// Check that two reductions agree within a relative tolerance
+    #[cfg(feature = "gpu")]
+    fn assert_close(a: &[Reduction], b: &[Reduction]) {
+        let close =
+            |a: f32, b: f32| a == b || (a - b).abs() <= 1e-4 * a.abs().max(b.abs()).max(1.0);
+
+        for (a, b) in a.iter().zip(b) {
+            assert_eq!(a.intensity.n, b.intensity.n);
+            assert_eq!(a.intensity.min, b.intensity.min);
+            assert_eq!(a.intensity.max, b.intensity.max);
+
+            for (x, y) in [
+                (&a.intensity.sum, &b.intensity.sum),
+                (&a.intensity.deviations, &b.intensity.deviations),
+            ] {
+                for (&x, &y) in x.iter().zip(y) {
+                    assert!(close(x, y), "{} != {}", x, y);
+                }
+            }
+
+            for (&x, &y) in a.moments.iter().zip(&b.moments) {
+                assert!(close(x, y), "{} != {}", x, y);
+            }
+        }
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn test_device_reduce_cpu() {
+        // The candle cpu backend runs the tensor reductions without a gpu
+        let reduce = DeviceReduce::new(candle_core::Device::Cpu, 0);
+
+        for (seed, (width, height, c)) in [(1, 1, 1), (33, 17, 1), (64, 48, 3), (5, 90, 4)]
+            .into_iter()
+            .enumerate()
+        {
+            let (pixels, region) = random_object(seed as u64, width, height, c);
+            let regions = [None, Some(region.as_slice())];
+
+            assert_close(
+                &reduce.reduce(&pixels, width, c, &regions).unwrap(),
+                &CpuReduce.reduce(&pixels, width, c, &regions).unwrap(),
+            );
+
+            for (bins, range) in [(1, [0.0, 255.0]), (16, [10.0, 200.0]), (256, [0.0, 256.0])] {
+                assert_eq!(
+                    reduce.histogram(&pixels, c, &regions, bins, range).unwrap(),
+                    CpuReduce
+                        .histogram(&pixels, c, &regions, bins, range)
+                        .unwrap()
+                );
+            }
+        }
+
+        // Small objects fall back to the cpu reductions
+        let (pixels, region) = random_object(0, 8, 8, 2);
+        let regions = [Some(region.as_slice())];
+
+        assert_eq!(
+            DeviceReduce::new(candle_core::Device::Cpu, 1 << 10)
+                .reduce(&pixels, 8, 2, &regions)
+                .unwrap(),
+            CpuReduce.reduce(&pixels, 8, 2, &regions).unwrap()
+        );
+    }
+
+    #[cfg(feature = "cuda")]
+    #[test]
+    fn test_device_reduce_cuda() {
+        if !candle_core::utils::cuda_is_available() {
+            return;
+        }
+
+        let reduce = MeasureDevice::Cuda.reducer(0).unwrap().unwrap();
+
+        for (seed, (width, height, c)) in [(33, 17, 1), (256, 192, 3), (1024, 128, 1)]
+            .into_iter()
+            .enumerate()
+        {
+            let (pixels, region) = random_object(seed as u64, width, height, c);
+            let regions = [None, Some(region.as_slice())];
+
+            assert_close(
+                &reduce.reduce(&pixels, width, c, &regions).unwrap(),
+                &CpuReduce.reduce(&pixels, width, c, &regions).unwrap(),
+            );
+
+            assert_eq!(
+                reduce
+                    .histogram(&pixels, c, &regions, 64, [0.0, 255.0])
+                    .unwrap(),
+                CpuReduce
+                    .histogram(&pixels, c, &regions, 64, [0.0, 255.0])
+                    .unwrap()
+            );
+        }
+    }
+}
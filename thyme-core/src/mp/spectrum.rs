@@ -0,0 +1,283 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::f32::consts::PI;
+use std::ops::Deref;
+
+use num::complex::Complex;
+use num::{FromPrimitive, ToPrimitive};
+
+use crate::im::{ThymeMaskView, ThymeViewBuffer};
+
+/// In-place iterative radix-2 fast fourier transform
+///
+/// The length of `values` must be a power of two.
+fn fft(values: &mut [Complex<f32>]) {
+    let n = values.len();
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+
+        j |= bit;
+
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        let angle = -2.0 * PI / len as f32;
+        let twiddles: Vec<Complex<f32>> = (0..half)
+            .map(|k| Complex::from_polar(1.0, angle * k as f32))
+            .collect();
+
+        for chunk in values.chunks_exact_mut(len) {
+            let (lower, upper) = chunk.split_at_mut(half);
+            for ((a, b), w) in lower.iter_mut().zip(upper.iter_mut()).zip(twiddles.iter()) {
+                let t = *b * w;
+                *b = *a - t;
+                *a += t;
+            }
+        }
+
+        len <<= 1;
+    }
+}
+
+/// Two-dimensional fast fourier transform of a row-major n x n grid
+///
+/// Only the first `rows` rows are transformed along x as the remaining rows
+/// are assumed to be zero padding.
+fn fft_2d(grid: &mut [Complex<f32>], n: usize, rows: usize) {
+    for row in grid.chunks_exact_mut(n).take(rows) {
+        fft(row);
+    }
+
+    let mut column = vec![Complex::new(0.0, 0.0); n];
+    for x in 0..n {
+        for (y, value) in column.iter_mut().enumerate() {
+            *value = grid[y * n + x];
+        }
+
+        fft(&mut column);
+
+        for (y, value) in column.iter().enumerate() {
+            grid[y * n + x] = *value;
+        }
+    }
+}
+
+/// Compute power spectrum descriptors for an object
+///
+/// Each channel is mean-centered over the foreground, background pixels are
+/// set to zero, and the object is zero-padded to a square with a power of two
+/// side length `n`. The magnitude of its two-dimensional fourier transform is
+/// radially averaged into integer frequency bins and the radial spectra are
+/// averaged over channels. The object and mask are assumed to share the same
+/// dimensions.
+///
+/// # Arguments
+///
+/// * `object` - An object view
+/// * `mask` - A binary mask view with the same dimensions as the object
+///
+/// # Notes
+///
+/// The peak frequency (in cycles per pixel), the fraction of spectral power
+/// in the peak bin, and the spectral entropy normalized to [0, 1] are
+/// computed over the non-zero frequency bins 1 to n / 2. Empty objects or
+/// objects with a flat spectrum (e.g. uniform intensity) return zero.
+pub fn objects<T, Container>(
+    object: &ThymeViewBuffer<T, Container>,
+    mask: &ThymeMaskView,
+) -> [f32; 3]
+where
+    T: ToPrimitive + FromPrimitive,
+    Container: Deref<Target = [T]>,
+{
+    let (w, h, c) = (object.width(), object.height(), object.channels());
+
+    if w != mask.width() || h != mask.height() || w == 0 || h == 0 {
+        return [0.0; 3];
+    }
+
+    let foreground: Vec<bool> = mask.iter().map(|&m| m != 0).collect();
+    let area = foreground.iter().filter(|&&m| m).count();
+
+    if area == 0 {
+        return [0.0; 3];
+    }
+
+    let n = w.max(h).next_power_of_two();
+    let bins = n / 2 + 1;
+
+    // Radial frequency bin of each fourier coefficient
+    let radius: Vec<usize> = (0..n * n)
+        .map(|i| {
+            let (u, v) = (i % n, i / n);
+            let fu = u.min(n - u) as f32;
+            let fv = v.min(n - v) as f32;
+            (fu * fu + fv * fv).sqrt().round() as usize
+        })
+        .collect();
+
+    let mut counts = vec![0usize; bins];
+    for &r in radius.iter().filter(|&&r| r < bins) {
+        counts[r] += 1;
+    }
+
+    let mut spectrum = vec![0f32; bins];
+    let mut grid = vec![Complex::new(0f32, 0f32); n * n];
+
+    for channel in 0..c {
+        let mean = object
+            .iter_pixels()
+            .zip(foreground.iter())
+            .filter(|(_, m)| **m)
+            .map(|(pixel, _)| pixel[channel].to_f32().unwrap())
+            .sum::<f32>()
+            / area as f32;
+
+        grid.fill(Complex::new(0.0, 0.0));
+
+        for (i, (pixel, &m)) in object.iter_pixels().zip(foreground.iter()).enumerate() {
+            if m {
+                grid[(i / w) * n + i % w] =
+                    Complex::new(pixel[channel].to_f32().unwrap() - mean, 0.0);
+            }
+        }
+
+        fft_2d(&mut grid, n, h);
+
+        for (value, &r) in grid.iter().zip(radius.iter()) {
+            if r < bins {
+                spectrum[r] += value.norm() / (c * counts[r]) as f32;
+            }
+        }
+    }
+
+    let power = &spectrum[1..];
+    let total: f32 = power.iter().sum();
+
+    if !total.is_normal() {
+        return [0.0; 3];
+    }
+
+    let (peak, peak_power) = power
+        .iter()
+        .enumerate()
+        .fold((0, 0f32), |(i_max, max), (i, &p)| match p > max {
+            true => (i, p),
+            false => (i_max, max),
+        });
+
+    let entropy = match power.len() > 1 {
+        true => {
+            -power
+                .iter()
+                .map(|&p| p / total)
+                .filter(|&p| p > 0.0)
+                .map(|p| p * p.ln())
+                .sum::<f32>()
+                / (power.len() as f32).ln()
+        }
+        false => 0.0,
+    };
+
+    [(peak + 1) as f32 / n as f32, peak_power / total, entropy]
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::im::{ThymeBuffer, ThymeMask};
+
+    /// A rectangular object with vertical stripes of a given period
+    fn striped(
+        width: usize,
+        height: usize,
+        period: usize,
+    ) -> (ThymeBuffer<u8, Vec<u8>>, ThymeMask) {
+        let size = width + 4;
+        let mut pixels = vec![0u8; size * (height + 4)];
+        let mut mask = vec![0u32; size * (height + 4)];
+
+        for y in 2..height + 2 {
+            for x in 2..width + 2 {
+                pixels[y * size + x] = if (x - 2) % period < period / 2 {
+                    200
+                } else {
+                    50
+                };
+                mask[y * size + x] = 1;
+            }
+        }
+
+        (
+            ThymeBuffer::new(size as u32, (height + 4) as u32, 1, pixels).unwrap(),
+            ThymeMask::new(size as u32, (height + 4) as u32, 1, mask).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_fft() {
+        // A pure cosine at frequency 3 of 16 concentrates in bins 3 and 13
+        let mut values: Vec<Complex<f32>> = (0..16)
+            .map(|i| Complex::new((2.0 * PI * 3.0 * i as f32 / 16.0).cos(), 0.0))
+            .collect();
+
+        fft(&mut values);
+
+        for (k, value) in values.iter().enumerate() {
+            let expected = if k == 3 || k == 13 { 8.0 } else { 0.0 };
+            assert!((value.norm() - expected).abs() < 1e-4, "{} {}", k, value);
+        }
+    }
+
+    #[test]
+    fn test_objects_stripes() {
+        for (width, height, period) in [(40, 30, 8), (64, 64, 4), (25, 50, 6)] {
+            let (image, mask) = striped(width, height, period);
+            let (w, h) = (image.width(), image.height());
+
+            let object = image.crop_view(0, 0, w, h);
+            let mask = mask.crop_view(0, 0, w, h);
+
+            let n = (w.max(h) as usize).next_power_of_two() as f32;
+            let results = objects(&object, &mask);
+
+            assert!(
+                (results[0] - 1.0 / period as f32).abs() <= 1.0 / n,
+                "Peak frequency {} for period {}",
+                results[0],
+                period
+            );
+
+            assert!(results[1] > 0.0 && results[1] <= 1.0);
+            assert!(results[2] > 0.0 && results[2] < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_objects_uniform() {
+        let image = ThymeBuffer::<u8, Vec<u8>>::new(8, 8, 1, vec![100; 64]).unwrap();
+        let mask = ThymeMask::new(8, 8, 1, vec![1; 64]).unwrap();
+
+        let object = image.crop_view(0, 0, 8, 8);
+        let mask_view = mask.crop_view(0, 0, 8, 8);
+
+        assert_eq!(objects(&object, &mask_view), [0.0; 3]);
+
+        let empty = ThymeMask::new(8, 8, 1, vec![0; 64]).unwrap();
+        assert_eq!(objects(&object, &empty.crop_view(0, 0, 8, 8)), [0.0; 3]);
+    }
+}
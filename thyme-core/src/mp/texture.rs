@@ -5,7 +5,8 @@ use std::ops::Deref;
 
 use num::{FromPrimitive, ToPrimitive};
 
-use crate::cv::features::{GLCM, glcm_multichannel, glcm_multichannel_object};
+use crate::constant::GLCM_ANGLES;
+use crate::cv::features::{GLCM, TexturePreset, glcm_multichannel, glcm_multichannel_object};
 use crate::im::ThymeViewBuffer;
 
 #[inline]
@@ -23,6 +24,10 @@ pub fn texture_contrast(glcm: &GLCM) -> f32 {
     contrast
 }
 
+/// Correlation of gray levels in co-occurring pixels
+///
+/// Correlation is defined as zero if either margin has no variance (e.g. a
+/// constant intensity object), where it would otherwise divide by zero.
 #[inline]
 pub fn texture_correlation(glcm: &GLCM) -> f32 {
     let (px, py) = glcm.margin_sums();
@@ -53,6 +58,10 @@ pub fn texture_correlation(glcm: &GLCM) -> f32 {
         })
         .sqrt();
 
+    if sx * sy <= 0.0 {
+        return 0.0;
+    }
+
     let mut correlation = 0.0;
     for (i, j, g_ij) in glcm.iter() {
         correlation += ((i as f32 + 1.0 - ux) * (j as f32 + 1.0 - uy) * g_ij) / (sx * sy);
@@ -244,6 +253,13 @@ pub fn haralick_features(glcm: &GLCM) -> [f32; 13] {
         })
         .sqrt();
 
+    // Correlation is zero rather than undefined without margin variance
+    let sxy = if sx * sy > 0.0 {
+        sx * sy
+    } else {
+        f32::INFINITY
+    };
+
     let mut hxy1 = 0.0;
     let mut hxy2 = 0.0;
 
@@ -277,7 +293,7 @@ pub fn haralick_features(glcm: &GLCM) -> [f32; 13] {
 
         energy += g_ij * g_ij;
         contrast += dsq * g_ij;
-        correlation += ((i + 1.0 - ux) * (j + 1.0 - uy) * g_ij) / (sx * sy);
+        correlation += ((i + 1.0 - ux) * (j + 1.0 - uy) * g_ij) / sxy;
         sum_of_squares += (i + 1.0 - ux) * (i + 1.0 - ux) * g_ij;
         inverse_difference_moment += (1.0 / (1.0 + dsq)) * g_ij;
         entropy += g_ij * (g_ij + f32::EPSILON).log2();
@@ -327,14 +343,144 @@ pub fn haralick_features(glcm: &GLCM) -> [f32; 13] {
     ]
 }
 
+/// Compute texture descriptors of a co-occurence matrix following pyradiomics conventions
+///
+/// Gray levels are one-based and descriptors are computed in double precision
+/// with the machine epsilon of f64 added inside logarithms and to the
+/// correlation denominator, as in pyradiomics. Descriptors are returned in the
+/// order of `haralick_features`, where the sum average ranges from 2, the sum
+/// and difference variances are the variances of the sums and absolute
+/// differences of paired gray levels, correlation is one without margin
+/// variance, and the first information measure of correlation is not scaled
+/// without margin entropy.
+///
+/// # Arguments
+///
+/// * `glcm` - A normalized co-occurence matrix
+#[inline]
+pub fn pyradiomics_features(glcm: &GLCM) -> [f32; 13] {
+    let eps = f64::EPSILON;
+    let (px, py) = glcm.margin_sums();
+
+    let px: Vec<f64> = px.into_iter().map(|x| x as f64).collect();
+    let py: Vec<f64> = py.into_iter().map(|y| y as f64).collect();
+
+    let (mut ux, mut uy) = (0.0, 0.0);
+    let (mut hx, mut hy) = (0.0, 0.0);
+
+    for k in 0..px.len() {
+        ux += (k as f64 + 1.0) * px[k];
+        uy += (k as f64 + 1.0) * py[k];
+        hx -= px[k] * (px[k] + eps).log2();
+        hy -= py[k] * (py[k] + eps).log2();
+    }
+
+    let (mut sx, mut sy) = (0.0, 0.0);
+
+    for k in 0..px.len() {
+        sx += (k as f64 + 1.0 - ux).powi(2) * px[k];
+        sy += (k as f64 + 1.0 - uy).powi(2) * py[k];
+    }
+
+    let (sx, sy) = (sx.sqrt(), sy.sqrt());
+
+    let mut px_plus_y = vec![0.0; 2 * glcm.rows()];
+    let mut px_minus_y = vec![0.0; glcm.rows()];
+
+    let mut energy = 0.0;
+    let mut contrast = 0.0;
+    let mut covariance = 0.0;
+    let mut sum_of_squares = 0.0;
+    let mut inverse_difference_moment = 0.0;
+    let mut entropy = 0.0;
+    let mut hxy1 = 0.0;
+    let mut hxy2 = 0.0;
+
+    for (i, j, g_ij) in glcm.iter() {
+        let g_ij = g_ij as f64;
+        let pxy = px[i] * py[j];
+
+        px_plus_y[i + j] += g_ij;
+        px_minus_y[(i as i32 - j as i32).unsigned_abs() as usize] += g_ij;
+
+        let gi = i as f64 + 1.0;
+        let gj = j as f64 + 1.0;
+        let dsq = (gi - gj) * (gi - gj);
+
+        energy += g_ij * g_ij;
+        contrast += dsq * g_ij;
+        covariance += (gi - ux) * (gj - uy) * g_ij;
+        sum_of_squares += (gi - ux) * (gi - ux) * g_ij;
+        inverse_difference_moment += g_ij / (1.0 + dsq);
+        entropy -= g_ij * (g_ij + eps).log2();
+        hxy1 -= g_ij * (pxy + eps).log2();
+        hxy2 -= pxy * (pxy + eps).log2();
+    }
+
+    let correlation = if sx * sy == 0.0 {
+        1.0
+    } else {
+        covariance / (sx * sy + eps)
+    };
+
+    // Sums of one-based gray levels start at 2
+    let mut sum_average = 0.0;
+    let mut sum_entropy = 0.0;
+    for (k, &p) in px_plus_y.iter().enumerate() {
+        sum_average += (k as f64 + 2.0) * p;
+        sum_entropy -= p * (p + eps).log2();
+    }
+
+    let sum_variance = px_plus_y.iter().enumerate().fold(0.0, |acc, (k, &p)| {
+        acc + (k as f64 + 2.0 - sum_average).powi(2) * p
+    });
+
+    let mut difference_average = 0.0;
+    let mut difference_entropy = 0.0;
+    for (k, &p) in px_minus_y.iter().enumerate() {
+        difference_average += k as f64 * p;
+        difference_entropy -= p * (p + eps).log2();
+    }
+
+    let difference_variance = px_minus_y.iter().enumerate().fold(0.0, |acc, (k, &p)| {
+        acc + (k as f64 - difference_average).powi(2) * p
+    });
+
+    let information_measure_of_correlation_1 = if hx.max(hy) != 0.0 {
+        (entropy - hxy1) / hx.max(hy)
+    } else {
+        entropy - hxy1
+    };
+
+    let information_measure_of_correlation_2 =
+        (1.0 - (-2.0 * (hxy2 - entropy)).exp()).max(0.0).sqrt();
+
+    [
+        energy,
+        contrast,
+        correlation,
+        sum_of_squares,
+        inverse_difference_moment,
+        sum_average,
+        sum_variance,
+        sum_entropy,
+        entropy,
+        difference_variance,
+        difference_entropy,
+        information_measure_of_correlation_1,
+        information_measure_of_correlation_2,
+    ]
+    .map(|x| x as f32)
+}
+
 #[inline]
 pub fn descriptors<T>(pixels: &[T], width: usize, height: usize, channels: usize) -> [f32; 13]
 where
     T: ToPrimitive,
 {
     let mut haralick: [f32; 13] = [0.0; 13];
-    for i in [0, 45, 90, 135].iter() {
-        for glcm in glcm_multichannel(pixels, width, height, channels, *i as f32, 1.0).iter() {
+    for angle in GLCM_ANGLES {
+        for glcm in glcm_multichannel(pixels, width, height, channels, angle, 1.0).iter() {
             let features = haralick_features(glcm);
             for j in 0..13 {
                 haralick[j] += features[j] / (4.0 * channels as f32);
@@ -347,13 +493,31 @@ where
 
 #[inline]
 pub fn objects<T, Container>(object: &ThymeViewBuffer<T, Container>) -> [f32; 13]
+where
+    T: ToPrimitive + FromPrimitive,
+    Container: Deref<Target = [T]>,
+{
+    objects_strided(object, 1)
+}
+
+/// Compute texture descriptors using co-occurrence pairs anchored on a strided grid
+///
+/// # Arguments
+///
+/// * `object` - A view of the object
+/// * `stride` - Only accumulate pairs anchored at every `stride`-th row and column
+#[inline]
+pub fn objects_strided<T, Container>(
+    object: &ThymeViewBuffer<T, Container>,
+    stride: usize,
+) -> [f32; 13]
 where
     T: ToPrimitive + FromPrimitive,
     Container: Deref<Target = [T]>,
 {
     let mut haralick: [f32; 13] = [0.0; 13];
-    for i in [0, 45, 90, 135].iter() {
-        for glcm in glcm_multichannel_object(object, *i as f32, 1.0).iter() {
+    for angle in GLCM_ANGLES {
+        for glcm in glcm_multichannel_object(object, angle, 1.0, stride).iter() {
             let features = haralick_features(glcm);
             for j in 0..13 {
                 haralick[j] += features[j] / (4.0 * object.channels() as f32);
@@ -364,6 +528,82 @@ where
     haralick
 }
 
+/// Compute texture descriptors of an object following a texture preset
+///
+/// Descriptors of the matrices at every offset of the preset are averaged
+/// over offsets and channels. Offsets that do not fit in the object are
+/// skipped, so that the average only includes matrices with co-occurences.
+///
+/// # Arguments
+///
+/// * `object` - A view of the object
+/// * `preset` - Gray-level discretization, offsets, and descriptor conventions
+#[inline]
+pub fn objects_preset<T, Container>(
+    object: &ThymeViewBuffer<T, Container>,
+    preset: TexturePreset,
+) -> [f32; 13]
+where
+    T: ToPrimitive + FromPrimitive,
+    Container: Deref<Target = [T]>,
+{
+    if preset == TexturePreset::Default {
+        return objects(object);
+    }
+
+    let (width, height) = (object.width() as i32, object.height() as i32);
+    let offsets: Vec<[i32; 2]> = preset
+        .offsets()
+        .into_iter()
+        .filter(|[dx, dy]| dx.abs() < width && dy.abs() < height)
+        .collect();
+
+    let mut haralick: [f32; 13] = [0.0; 13];
+    let n = (offsets.len() * object.channels()) as f32;
+
+    for channel in 0..object.channels() {
+        for &offset in offsets.iter() {
+            let glcm = GLCM::new_from_object_with_preset(object, channel, offset, 1, preset);
+            let features = pyradiomics_features(&glcm);
+            for j in 0..13 {
+                haralick[j] += features[j] / n;
+            }
+        }
+    }
+
+    haralick
+}
+
+/// Escalate the texture stride so that large objects accumulate a bounded number of pairs
+///
+/// # Arguments
+///
+/// * `pixels` - Number of pixels in the object view
+/// * `stride` - Minimum stride
+/// * `threshold` - Pixel count above which the stride is escalated
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::mp::texture::auto_stride;
+///
+/// assert_eq!(auto_stride(1_000, 1, Some(250_000)), 1);
+/// assert_eq!(auto_stride(1_000_000, 1, Some(250_000)), 2);
+/// assert_eq!(auto_stride(1_000_000, 3, Some(250_000)), 3);
+/// assert_eq!(auto_stride(1_000_000, 1, None), 1);
+/// ```
+pub fn auto_stride(pixels: usize, stride: usize, threshold: Option<usize>) -> usize {
+    let stride = stride.max(1);
+
+    match threshold {
+        Some(threshold) if pixels > threshold => {
+            let ratio = pixels as f64 / threshold.max(1) as f64;
+            stride.max(ratio.sqrt().ceil() as usize)
+        }
+        _ => stride,
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -395,6 +635,20 @@ mod test {
         assert_eq!(correlation, 1.0);
     }
 
+    #[test]
+    fn test_texture_constant_object() {
+        let buffer = ThymeBuffer::new(6, 5, 1, vec![120u8; 30]).unwrap();
+        let object = ThymeViewBuffer::new(0, 0, 6, 5, &buffer);
+
+        let glcm = GLCM::new(buffer.as_raw(), 6, 5, 0, 1, 0.0, 1.0);
+        assert_eq!(texture_correlation(&glcm), 0.0);
+        assert_eq!(haralick_features(&glcm)[2], 0.0);
+
+        let texture = objects(&object);
+        assert_eq!(texture[2], 0.0);
+        assert!(texture.iter().all(|x| x.is_finite()), "{:?}", texture);
+    }
+
     #[test]
     fn test_texture_sum_of_squares() {
         let sum_of_squares =
@@ -510,4 +764,140 @@ mod test {
 
         assert_eq!(texture_array, texture_object);
     }
+
+    /// Stationary texture with four gray levels from a linear congruential generator
+    fn homogeneous_texture(width: u32, height: u32) -> ThymeBuffer<u8, Vec<u8>> {
+        let mut state: u32 = 42;
+        let pixels: Vec<u8> = (0..width * height)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                ((state >> 30) * 85) as u8
+            })
+            .collect();
+
+        ThymeBuffer::new(width, height, 1, pixels).unwrap()
+    }
+
+    #[test]
+    fn test_object_texture_stride_one() {
+        let buffer = homogeneous_texture(37, 23);
+        let (width, height) = (buffer.width(), buffer.height());
+        let object = ThymeViewBuffer::new(0, 0, width, height, &buffer);
+
+        let texture_array = descriptors(buffer.as_raw(), width as usize, height as usize, 1);
+
+        assert_eq!(objects_strided(&object, 1), texture_array);
+        assert_eq!(objects_strided(&object, 1), objects(&object));
+    }
+
+    #[test]
+    fn test_object_texture_stride_two() {
+        let buffer = homogeneous_texture(256, 256);
+        let object = ThymeViewBuffer::new(0, 0, 256, 256, &buffer);
+
+        let full = objects(&object);
+        let strided = objects_strided(&object, 2);
+
+        assert_ne!(full, strided);
+
+        for (a, b) in full.iter().zip(strided.iter()) {
+            assert!((a - b).abs() <= 0.05 * a.abs().max(1.0), "{} {}", a, b);
+        }
+    }
+
+    /// Toy image of the pyradiomics co-occurence matrix documentation
+    fn pyradiomics_image() -> ThymeBuffer<u8, Vec<u8>> {
+        #[rustfmt::skip]
+        let pixels = vec![
+            1, 2, 5, 2, 3,
+            3, 2, 1, 3, 1,
+            1, 3, 5, 5, 2,
+            1, 1, 1, 1, 2,
+            1, 2, 4, 3, 5,
+        ];
+
+        ThymeBuffer::new(5, 5, 1, pixels).unwrap()
+    }
+
+    #[test]
+    fn test_pyradiomics_glcm() {
+        let buffer = pyradiomics_image();
+        let object = ThymeViewBuffer::new(0, 0, 5, 5, &buffer);
+        let preset = TexturePreset::Pyradiomics { bin_width: 1.0 };
+
+        // Matrix of the documentation at angle 0 and distance 1 (20 pairs)
+        let expected = [
+            [6, 4, 3, 0, 0],
+            [4, 0, 2, 1, 3],
+            [3, 2, 0, 1, 2],
+            [0, 1, 1, 0, 0],
+            [0, 3, 2, 0, 2],
+        ];
+
+        let glcm = &object.glcm_preset([1, 0], preset)[0];
+        let matrix = glcm.as_matrix();
+
+        for i in 0..5 {
+            for j in 0..5 {
+                assert_eq!((matrix[i][j] * 40.0).round() as u32, expected[i][j]);
+            }
+        }
+
+        assert_eq!(
+            matrix
+                .iter()
+                .map(|row| row.iter().sum::<f32>())
+                .sum::<f32>(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_pyradiomics_preset() {
+        let buffer = pyradiomics_image();
+        let object = ThymeViewBuffer::new(0, 0, 5, 5, &buffer);
+        let preset = TexturePreset::Pyradiomics { bin_width: 1.0 };
+
+        // Mean over the 24 offsets of chebyshev distance 1 to 3 computed with
+        // the pyradiomics co-occurence matrix formulas (binWidth 1, distances 1-3)
+        let texture = object.texture_preset(preset);
+
+        assert!((texture[0] - 0.111312).abs() < 1e-3, "{}", texture[0]);
+        assert!((texture[1] - 4.149769).abs() < 1e-3, "{}", texture[1]);
+        assert!((texture[2] - -0.143656).abs() < 1e-3, "{}", texture[2]);
+        assert!(texture.iter().all(|x| x.is_finite()), "{:?}", texture);
+
+        // Shifting intensities by whole bins keeps the discretized texture
+        let pixels: Vec<u8> = buffer.as_raw().iter().map(|x| x + 20).collect();
+        let shifted = ThymeBuffer::new(5, 5, 1, pixels).unwrap();
+        let shifted = ThymeViewBuffer::new(0, 0, 5, 5, &shifted);
+        assert_eq!(shifted.texture_preset(preset), texture);
+
+        assert_eq!(
+            object.texture_preset(TexturePreset::Default),
+            objects(&object)
+        );
+    }
+
+    #[test]
+    fn test_pyradiomics_constant_object() {
+        let buffer = ThymeBuffer::new(6, 5, 1, vec![120u8; 30]).unwrap();
+        let object = ThymeViewBuffer::new(0, 0, 6, 5, &buffer);
+
+        let texture = objects_preset(&object, TexturePreset::Pyradiomics { bin_width: 25.0 });
+
+        assert!((texture[0] - 1.0).abs() < EPS);
+        assert_eq!(texture[1], 0.0);
+        assert!((texture[2] - 1.0).abs() < EPS);
+        assert!(texture.iter().all(|x| x.is_finite()), "{:?}", texture);
+    }
+
+    #[test]
+    fn test_auto_stride() {
+        assert_eq!(auto_stride(250_000, 1, Some(250_000)), 1);
+        assert_eq!(auto_stride(250_001, 1, Some(250_000)), 2);
+        assert_eq!(auto_stride(4_000_000, 1, Some(250_000)), 4);
+        assert_eq!(auto_stride(4_000_001, 1, Some(250_000)), 5);
+        assert_eq!(auto_stride(100, 0, None), 1);
+    }
 }
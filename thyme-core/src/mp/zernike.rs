@@ -1,93 +1,242 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
+use std::collections::HashMap;
 use std::ops::Deref;
+use std::sync::{Arc, OnceLock, RwLock};
 
 use num::{FromPrimitive, ToPrimitive, complex::Complex};
 
 use crate::{constant::FACTORIAL, im::ThymeViewBuffer};
 
-#[inline]
-fn radial_polynomial(n: usize, m: usize, r: &mut [Complex<f32>]) {
-    let nf = n as i32;
-    let nsm = (n - m) / 2;
-    let nam = (n + m) / 2;
-
-    for ri in r.iter_mut() {
-        let mut r_nm_i = Complex::new(0.0, 0.0);
-        for si in 0..=nsm {
-            let sf = si as f32;
-            let exp = nf - 2 * si as i32;
-
-            let v = ((-1.0f32).powf(sf) * FACTORIAL[n - si])
-                / (FACTORIAL[si] * FACTORIAL[nam - si] * FACTORIAL[nsm - si]);
-
-            let pow_term = if exp >= 0 {
-                ri.powi(exp)
-            } else {
-                ri.powf(exp as f32)
-            };
-            r_nm_i += Complex::new(v, 0.0) * pow_term;
-        }
-        *ri = r_nm_i;
+/// Maximum radial order of the zernike descriptors
+const MAX_ORDER: usize = 9;
+
+/// Number of (n, m) pairs with n <= MAX_ORDER and n - m even
+const N_DESCRIPTORS: usize = 30;
+
+/// Precomputed terms of a zernike radial polynomial
+///
+/// The radial polynomial of order `n` and repetition `m` is stored as a list
+/// of (exponent, coefficient) terms so the factorial ratios are only computed
+/// once rather than for every pixel of every object.
+struct RadialPolynomial {
+    n: usize,
+    m: usize,
+    terms: Vec<(usize, f32)>,
+}
+
+impl RadialPolynomial {
+    fn new(n: usize, m: usize) -> Self {
+        let nsm = (n - m) / 2;
+        let nam = (n + m) / 2;
+
+        let terms = (0..=nsm)
+            .map(|si| {
+                let v = ((-1.0f32).powf(si as f32) * FACTORIAL[n - si])
+                    / (FACTORIAL[si] * FACTORIAL[nam - si] * FACTORIAL[nsm - si]);
+
+                (n - 2 * si, v)
+            })
+            .collect();
+
+        RadialPolynomial { n, m, terms }
+    }
+
+    /// Evaluate the polynomial given powers of the radius (r^0, r^1, .., r^9)
+    #[inline]
+    fn evaluate(&self, powers: &[f32; MAX_ORDER + 1]) -> f32 {
+        self.terms.iter().map(|&(exp, v)| v * powers[exp]).sum()
     }
 }
 
+/// Powers of the radius up to the maximum radial order
 #[inline]
-fn zernike_polynomial(n: usize, m: usize, r: &mut [Complex<f32>], theta: &[f32]) {
-    let m_theta = Complex::new(0.0, m as f32);
-    radial_polynomial(n, m, r);
-    for (ri, &theta_i) in r.iter_mut().zip(theta.iter()) {
-        *ri *= (m_theta * theta_i).exp();
+fn radial_powers(r: f32) -> [f32; MAX_ORDER + 1] {
+    let mut powers = [1.0f32; MAX_ORDER + 1];
+    for (k, power) in powers.iter_mut().enumerate().skip(1) {
+        *power = r.powi(k as i32);
     }
+
+    powers
 }
 
-#[inline]
-pub fn zernike_moments<T>(pixels: &[T], width: usize, height: usize, n: usize, m: usize) -> f32
-where
-    T: ToPrimitive,
-{
-    let half_width = width as f32 / 2.0;
-    let half_height = height as f32 / 2.0;
+/// Radial polynomials for all descriptor orders, computed once on first use
+fn radial_polynomials() -> &'static [RadialPolynomial] {
+    static POLYNOMIALS: OnceLock<Vec<RadialPolynomial>> = OnceLock::new();
+
+    POLYNOMIALS.get_or_init(|| {
+        let mut polynomials = Vec::with_capacity(N_DESCRIPTORS);
+        for n in 0..=MAX_ORDER {
+            for m in 0..=n {
+                if (n - m) % 2 == 0 {
+                    polynomials.push(RadialPolynomial::new(n, m));
+                }
+            }
+        }
+
+        polynomials
+    })
+}
+
+/// Maximum number of basis values held across all cached grids (~128 MB)
+const BASIS_CACHE_CAPACITY: usize = 1 << 24;
+
+/// Conjugated zernike basis functions evaluated on a fixed pixel grid
+///
+/// Each pixel of a `width` x `height` grid is mapped into the unit disk once
+/// and the conjugated basis function of every polynomial is stored for each
+/// in-disk pixel. Objects sharing the same grid then only require a weighted
+/// sum over the stored values.
+struct ZernikeBasis {
+    polynomials: usize,
+    scales: Vec<f32>,
+    indices: Vec<usize>,
+    values: Vec<Complex<f32>>,
+}
+
+impl ZernikeBasis {
+    /// Evaluate basis functions on a grid
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the grid
+    /// * `height` - Height of the grid
+    /// * `len` - Number of row-major values in the grid
+    /// * `polynomials` - Radial polynomials for each (n, m) moment
+    fn new(width: usize, height: usize, len: usize, polynomials: &[RadialPolynomial]) -> Self {
+        let half_width = width as f32 / 2.0;
+        let half_height = height as f32 / 2.0;
+
+        let max_m = polynomials.iter().map(|p| p.m).max().unwrap_or(0);
+        let mut phases = vec![Complex::new(1.0f32, 0.0); max_m + 1];
+
+        let mut indices = Vec::with_capacity(len);
+        let mut values = Vec::with_capacity(len * polynomials.len());
+
+        for i in 0..len {
+            let x_norm = ((i % width) as f32 - half_width) / half_width;
+            let y_norm = ((i / width) as f32 - half_height) / half_height;
+            let r_i = (x_norm * x_norm + y_norm * y_norm).sqrt();
+
+            if r_i <= 1.0 {
+                let theta_i = y_norm.atan2(x_norm);
+                let powers = radial_powers(r_i);
+
+                for (m, phase) in phases.iter_mut().enumerate().skip(1) {
+                    *phase = Complex::new(0.0, m as f32 * theta_i).exp();
+                }
+
+                indices.push(i);
+                values.extend(
+                    polynomials
+                        .iter()
+                        .map(|p| (phases[p.m] * p.evaluate(&powers)).conj()),
+                );
+            }
+        }
 
-    let mut total_mass = 0.0;
+        ZernikeBasis {
+            polynomials: polynomials.len(),
+            scales: polynomials
+                .iter()
+                .map(|p| (p.n as f32 + 1.0) / std::f32::consts::PI)
+                .collect(),
+            indices,
+            values,
+        }
+    }
 
-    let capacity = pixels.len();
+    /// Compute zernike moment magnitudes for pixels on the basis grid
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - Row-major pixels with the same length as the grid
+    /// * `moments` - Output moments with one value per polynomial
+    #[inline]
+    fn moments(&self, pixels: &[f32], moments: &mut [f32]) {
+        let total_mass: f32 = self.indices.iter().map(|&i| pixels[i]).sum();
+
+        if total_mass == 0.0 {
+            moments.iter_mut().for_each(|v| *v = 0.0);
+            return;
+        }
 
-    let mut circle: Vec<f32> = Vec::with_capacity(capacity);
-    let mut theta = Vec::with_capacity(capacity);
-    let mut r: Vec<Complex<f32>> = Vec::with_capacity(capacity);
+        let inv_mass = 1.0 / total_mass;
+        let mut a_nm = vec![Complex::new(0.0f32, 0.0); self.polynomials];
 
-    for (i, pixel) in pixels.iter().enumerate() {
-        let x_norm = ((i % width) as f32 - half_width) / half_width;
-        let y_norm = ((i / width) as f32 - half_height) / half_height;
-        let r_i = (x_norm * x_norm + y_norm * y_norm).sqrt();
+        for (&i, values) in self
+            .indices
+            .iter()
+            .zip(self.values.chunks_exact(self.polynomials))
+        {
+            let mass = Complex::new(pixels[i] * inv_mass, 0.0);
+
+            for (a, z_nm_i) in a_nm.iter_mut().zip(values) {
+                *a += z_nm_i * mass;
+            }
+        }
 
-        if r_i <= 1.0 {
-            let pixel = pixel.to_f32().unwrap();
-            total_mass += pixel;
-            theta.push(y_norm.atan2(x_norm));
-            r.push(Complex::new(r_i, 0.0));
-            circle.push(pixel);
+        for ((moment, a), &scale) in moments.iter_mut().zip(a_nm).zip(&self.scales) {
+            let a = a * Complex::new(scale, 0.0);
+            *moment = (a.re.powi(2) + a.im.powi(2)).sqrt();
         }
     }
+}
+
+/// Cached bases for all descriptor orders keyed by grid size
+#[derive(Default)]
+struct BasisCache {
+    bases: HashMap<(usize, usize, usize), Arc<ZernikeBasis>>,
+    size: usize,
+}
 
-    if total_mass == 0.0 {
-        return 0.0;
+/// Fetch, or compute and cache, the basis of all descriptor orders for a grid
+///
+/// Bases are only cached while the total number of stored values is below
+/// `BASIS_CACHE_CAPACITY` so datasets with many unique object sizes fall back
+/// to computing the basis for each object. Cached bases are fetched under a
+/// shared read lock so workers measuring objects of known sizes never wait
+/// on each other, and the write lock is only taken to insert a new basis.
+fn descriptor_basis(width: usize, height: usize, len: usize) -> Arc<ZernikeBasis> {
+    static CACHE: OnceLock<RwLock<BasisCache>> = OnceLock::new();
+
+    let cache = CACHE.get_or_init(|| RwLock::new(BasisCache::default()));
+    let key = (width, height, len);
+
+    if let Some(basis) = cache.read().unwrap().bases.get(&key) {
+        return basis.clone();
     }
 
-    zernike_polynomial(n, m, &mut r, &theta);
+    let basis = Arc::new(ZernikeBasis::new(width, height, len, radial_polynomials()));
 
-    let inv_mass = 1.0 / total_mass;
-    let mut a_nm = Complex::new(0.0, 0.0);
+    let mut cache = cache.write().unwrap();
 
-    for (i, z_nm_i) in r.iter().enumerate() {
-        a_nm += z_nm_i.conj() * Complex::new(circle[i] * inv_mass, 0.0);
+    // Another worker may have inserted the same grid while the basis was computed
+    if let Some(cached) = cache.bases.get(&key) {
+        return cached.clone();
     }
 
-    a_nm *= Complex::new((n as f32 + 1.0) / std::f32::consts::PI, 0.0);
+    if cache.size + basis.values.len() <= BASIS_CACHE_CAPACITY {
+        cache.size += basis.values.len();
+        cache.bases.insert(key, basis.clone());
+    }
 
-    (a_nm.re.powi(2) + a_nm.im.powi(2)).sqrt()
+    basis
+}
+
+#[inline]
+pub fn zernike_moments<T>(pixels: &[T], width: usize, height: usize, n: usize, m: usize) -> f32
+where
+    T: ToPrimitive,
+{
+    let pixels: Vec<f32> = pixels.iter().map(|p| p.to_f32().unwrap()).collect();
+
+    let mut moment = [0.0];
+    ZernikeBasis::new(width, height, pixels.len(), &[RadialPolynomial::new(n, m)])
+        .moments(&pixels, &mut moment);
+
+    moment[0]
 }
 
 #[inline]
@@ -95,16 +244,10 @@ pub fn descriptors<T>(pixels: &[T], width: usize, height: usize) -> [f32; 30]
 where
     T: ToPrimitive,
 {
-    let mut descriptors: [f32; 30] = [0.0; 30];
-    let mut i = 0;
-    for n in 0..=9 {
-        for m in 0..=n {
-            if (n - m) % 2 == 0 {
-                descriptors[i] = zernike_moments(pixels, width, height, n, m);
-                i += 1;
-            }
-        }
-    }
+    let pixels: Vec<f32> = pixels.iter().map(|p| p.to_f32().unwrap()).collect();
+
+    let mut descriptors: [f32; N_DESCRIPTORS] = [0.0; N_DESCRIPTORS];
+    descriptor_basis(width, height, pixels.len()).moments(&pixels, &mut descriptors);
 
     descriptors
 }
@@ -119,48 +262,18 @@ where
     T: ToPrimitive + FromPrimitive,
     Container: Deref<Target = [T]>,
 {
-    let width = object.width();
-    let half_width = width as f32 / 2.0;
-    let half_height = object.height() as f32 / 2.0;
-
-    let mut total_mass = 0.0;
-
-    let capacity = object.len();
-
-    let mut circle: Vec<f32> = Vec::with_capacity(capacity);
-    let mut theta = Vec::with_capacity(capacity);
-    let mut r: Vec<Complex<f32>> = Vec::with_capacity(capacity);
-
-    for (i, pixel) in object.iter().enumerate() {
-        let x_norm = ((i % width) as f32 - half_width) / half_width;
-        let y_norm = ((i / width) as f32 - half_height) / half_height;
-        let r_i = (x_norm * x_norm + y_norm * y_norm).sqrt();
-
-        if r_i <= 1.0 {
-            let pixel = pixel.to_f32().unwrap();
-            total_mass += pixel;
-            theta.push(y_norm.atan2(x_norm));
-            r.push(Complex::new(r_i, 0.0));
-            circle.push(pixel);
-        }
-    }
-
-    if total_mass == 0.0 {
-        return 0.0;
-    }
-
-    zernike_polynomial(n, m, &mut r, &theta);
-
-    let inv_mass = 1.0 / total_mass;
-    let mut a_nm = Complex::new(0.0, 0.0);
-
-    for (i, z_nm_i) in r.iter().enumerate() {
-        a_nm += z_nm_i.conj() * Complex::new(circle[i] * inv_mass, 0.0);
-    }
-
-    a_nm *= Complex::new((n as f32 + 1.0) / std::f32::consts::PI, 0.0);
-
-    (a_nm.re.powi(2) + a_nm.im.powi(2)).sqrt()
+    let pixels: Vec<f32> = object.iter().map(|p| p.to_f32().unwrap()).collect();
+
+    let mut moment = [0.0];
+    ZernikeBasis::new(
+        object.width(),
+        object.height(),
+        pixels.len(),
+        &[RadialPolynomial::new(n, m)],
+    )
+    .moments(&pixels, &mut moment);
+
+    moment[0]
 }
 
 #[inline]
@@ -169,16 +282,11 @@ where
     T: ToPrimitive + FromPrimitive,
     Container: Deref<Target = [T]>,
 {
-    let mut descriptors: [f32; 30] = [0.0; 30];
-    let mut i = 0;
-    for n in 0..=9 {
-        for m in 0..=n {
-            if (n - m) % 2 == 0 {
-                descriptors[i] = zernike_moments_object(object, n, m);
-                i += 1;
-            }
-        }
-    }
+    let pixels: Vec<f32> = object.iter().map(|p| p.to_f32().unwrap()).collect();
+
+    let mut descriptors: [f32; N_DESCRIPTORS] = [0.0; N_DESCRIPTORS];
+    descriptor_basis(object.width(), object.height(), pixels.len())
+        .moments(&pixels, &mut descriptors);
 
     descriptors
 }
@@ -198,50 +306,136 @@ mod test {
 
     #[test]
     fn test_radial_polynomial() {
-        let mut r = vec![
-            Complex::new(0.0, 0.0),
-            Complex::new(0.5, 0.0),
-            Complex::new(1.0, 0.0),
-        ];
+        let polynomial = RadialPolynomial::new(2, 0);
 
-        radial_polynomial(2, 0, &mut r);
+        let r = [0.0, 0.5, 1.0];
+        let expected = [-1.0, -0.5, 1.0];
 
-        let expected = [
-            Complex::new(-1.0, 0.0),
-            Complex::new(-0.5, 0.0),
-            Complex::new(1.0, 0.0),
-        ];
-
-        for (res, exp) in r.iter().zip(expected.iter()) {
-            assert!((res.re - exp.re).abs() < 1e-6);
+        for (&r_i, exp) in r.iter().zip(expected.iter()) {
+            let res = polynomial.evaluate(&radial_powers(r_i));
+            assert!((res - exp).abs() < 1e-6);
         }
     }
 
     #[test]
     fn test_zernike_polynomial() {
-        let mut r = vec![
-            Complex::new(1.0, 0.0),
-            Complex::new(1.0, 0.0),
-            Complex::new(1.0, 0.0),
-        ];
+        let polynomial = RadialPolynomial::new(2, 2);
 
-        let theta = vec![
+        let theta = [
             0.0,
             std::f32::consts::FRAC_PI_4,
             std::f32::consts::FRAC_PI_2,
         ];
 
-        zernike_polynomial(2, 2, &mut r, &theta);
-
         let expected = [
             Complex::new(1.0, 0.0),
             Complex::new(0.0, 1.0),
             Complex::new(-1.0, 0.0),
         ];
 
-        for (res, exp) in r.iter().zip(expected.iter()) {
+        for (&theta_i, exp) in theta.iter().zip(expected.iter()) {
+            let res =
+                Complex::new(0.0, 2.0 * theta_i).exp() * polynomial.evaluate(&radial_powers(1.0));
             assert!((res.re - exp.re).abs() < 1e-6);
             assert!((res.im - exp.im).abs() < 1e-6);
         }
     }
+
+    /// Reference implementation computing each moment independently
+    fn reference_moment(pixels: &[f32], width: usize, height: usize, n: usize, m: usize) -> f32 {
+        let half_width = width as f32 / 2.0;
+        let half_height = height as f32 / 2.0;
+
+        let mut total_mass = 0.0;
+        let mut circle = vec![];
+        let mut theta = vec![];
+        let mut r = vec![];
+
+        for (i, &pixel) in pixels.iter().enumerate() {
+            let x_norm = ((i % width) as f32 - half_width) / half_width;
+            let y_norm = ((i / width) as f32 - half_height) / half_height;
+            let r_i = (x_norm * x_norm + y_norm * y_norm).sqrt();
+
+            if r_i <= 1.0 {
+                total_mass += pixel;
+                theta.push(y_norm.atan2(x_norm));
+                r.push(Complex::new(r_i, 0.0f32));
+                circle.push(pixel);
+            }
+        }
+
+        if total_mass == 0.0 {
+            return 0.0;
+        }
+
+        let nsm = (n - m) / 2;
+        let nam = (n + m) / 2;
+
+        let mut a_nm = Complex::new(0.0, 0.0);
+
+        for i in 0..r.len() {
+            let mut r_nm_i = Complex::new(0.0, 0.0);
+            for si in 0..=nsm {
+                let v = ((-1.0f32).powf(si as f32) * FACTORIAL[n - si])
+                    / (FACTORIAL[si] * FACTORIAL[nam - si] * FACTORIAL[nsm - si]);
+                r_nm_i += Complex::new(v, 0.0) * r[i].powi(n as i32 - 2 * si as i32);
+            }
+
+            let z_nm_i = r_nm_i * (Complex::new(0.0, m as f32) * theta[i]).exp();
+            a_nm += z_nm_i.conj() * Complex::new(circle[i] / total_mass, 0.0);
+        }
+
+        a_nm *= Complex::new((n as f32 + 1.0) / std::f32::consts::PI, 0.0);
+
+        (a_nm.re.powi(2) + a_nm.im.powi(2)).sqrt()
+    }
+
+    #[test]
+    fn test_zernike_moment_single() {
+        let pixels: Vec<f32> = (0..35).map(|i| ((i * 7) % 11) as f32).collect();
+
+        for n in 0..=MAX_ORDER {
+            for m in (0..=n).filter(|m| (n - m) % 2 == 0) {
+                let moment = zernike_moments(&pixels, 7, 5, n, m);
+                assert!((moment - reference_moment(&pixels, 7, 5, n, m)).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn test_objects_reference() {
+        use crate::im::ThymeMask;
+
+        for path in [
+            "../data/tests/test_mask_binary.png",
+            "../data/tests/test_mask_integer.png",
+        ] {
+            let mut mask = ThymeMask::open(path).unwrap();
+            let (labels, polygons) = mask.polygons().unwrap();
+            let bounding_boxes = polygons.to_bounding_boxes().unwrap();
+
+            for (idx, [min_x, min_y, max_x, max_y]) in bounding_boxes.as_xyxy().iter().enumerate() {
+                let (x, y) = (*min_x as u32, *min_y as u32);
+                let (w, h) = (*max_x as u32 - x, *max_y as u32 - y);
+
+                if w == 0 || h == 0 {
+                    continue;
+                }
+
+                let object = mask.crop_binary(x, y, w, h, labels[idx]).unwrap();
+                let pixels: Vec<f32> = object.iter().map(|&p| p as f32).collect();
+                let results = objects(&object.crop_view(0, 0, w, h));
+
+                let mut i = 0;
+                for n in 0..=MAX_ORDER {
+                    for m in (0..=n).filter(|m| (n - m) % 2 == 0) {
+                        let reference = reference_moment(&pixels, w as usize, h as usize, n, m);
+                        assert!((results[i] - reference).abs() < 1e-5);
+                        i += 1;
+                    }
+                }
+            }
+        }
+    }
 }
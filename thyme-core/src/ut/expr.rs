@@ -0,0 +1,236 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::fmt;
+
+use crate::error::ThymeError;
+
+/// Comparison operator of a condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// Less than (`<`)
+    Lt,
+    /// Less than or equal to (`<=`)
+    Le,
+    /// Greater than (`>`)
+    Gt,
+    /// Greater than or equal to (`>=`)
+    Ge,
+    /// Equal to (`==`)
+    Eq,
+    /// Not equal to (`!=`)
+    Ne,
+}
+
+impl Operator {
+    /// Operators ordered so that two character operators are matched first
+    const ALL: [(&'static str, Operator); 6] = [
+        ("<=", Operator::Le),
+        (">=", Operator::Ge),
+        ("==", Operator::Eq),
+        ("!=", Operator::Ne),
+        ("<", Operator::Lt),
+        (">", Operator::Gt),
+    ];
+
+    /// Compare a value against a threshold
+    fn apply(&self, value: f32, threshold: f32) -> bool {
+        match self {
+            Operator::Lt => value < threshold,
+            Operator::Le => value <= threshold,
+            Operator::Gt => value > threshold,
+            Operator::Ge => value >= threshold,
+            Operator::Eq => value == threshold,
+            Operator::Ne => value != threshold,
+        }
+    }
+
+    /// Symbol of the operator in expressions
+    fn symbol(&self) -> &'static str {
+        Operator::ALL.iter().find(|(_, op)| op == self).unwrap().0
+    }
+}
+
+/// A comparison of a named metric against a constant (e.g. `focus_score<30`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    /// Name of the compared metric
+    pub metric: String,
+    /// Comparison operator
+    pub operator: Operator,
+    /// Constant the metric is compared against
+    pub value: f32,
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.metric, self.operator.symbol(), self.value)
+    }
+}
+
+/// A boolean expression of metric comparisons joined by `||` and `&&`
+///
+/// `&&` binds tighter than `||` and parentheses are not supported, so every
+/// expression is a disjunction of conjunctions of conditions. Metrics that
+/// are not finite never satisfy a condition except `!=`.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::expr::Expression;
+///
+/// let metrics = ["foreground_fraction", "focus_score"];
+/// let expression =
+///     Expression::parse("foreground_fraction<0.001 || focus_score<30", &metrics).unwrap();
+///
+/// let value = |metric: &str| match metric {
+///     "foreground_fraction" => 0.2,
+///     _ => 12.0,
+/// };
+///
+/// assert_eq!(expression.matches(value).unwrap(), "focus_score<30");
+/// assert!(Expression::parse("area<3", &metrics).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    clauses: Vec<Vec<Condition>>,
+}
+
+impl Expression {
+    /// Parse an expression restricted to a set of metric names
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Conditions joined by `||` and `&&`
+    /// * `metrics` - Names of the metrics that may be compared
+    pub fn parse(expression: &str, metrics: &[&str]) -> Result<Expression, ThymeError> {
+        let clauses = expression
+            .split("||")
+            .map(|clause| {
+                clause
+                    .split("&&")
+                    .map(|condition| parse_condition(condition, metrics))
+                    .collect::<Result<Vec<Condition>, ThymeError>>()
+            })
+            .collect::<Result<Vec<Vec<Condition>>, ThymeError>>()?;
+
+        Ok(Expression { clauses })
+    }
+
+    /// Return the first clause satisfied by the metrics, if any
+    ///
+    /// The clause is returned in its canonical form (e.g. `a<1 && b>2`) so it
+    /// can be reported as the reason an expression matched.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value of each metric by name
+    pub fn matches(&self, value: impl Fn(&str) -> f32) -> Option<String> {
+        self.clauses
+            .iter()
+            .find(|clause| {
+                clause
+                    .iter()
+                    .all(|c| c.operator.apply(value(&c.metric), c.value))
+            })
+            .map(|clause| {
+                clause
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" && ")
+            })
+    }
+
+    /// Names of the metrics compared by the expression in order of appearance
+    pub fn metrics(&self) -> Vec<&str> {
+        let mut metrics: Vec<&str> = vec![];
+
+        for condition in self.clauses.iter().flatten() {
+            if !metrics.contains(&condition.metric.as_str()) {
+                metrics.push(&condition.metric);
+            }
+        }
+
+        metrics
+    }
+}
+
+/// Parse a single `metric operator value` condition
+fn parse_condition(condition: &str, metrics: &[&str]) -> Result<Condition, ThymeError> {
+    let invalid = |reason: String| {
+        ThymeError::OtherError(format!(
+            "Invalid condition '{}'. {}",
+            condition.trim(),
+            reason
+        ))
+    };
+
+    let (idx, symbol, operator) = Operator::ALL
+        .iter()
+        .filter_map(|&(symbol, operator)| condition.find(symbol).map(|i| (i, symbol, operator)))
+        .min_by_key(|&(i, symbol, _)| (i, std::cmp::Reverse(symbol.len())))
+        .ok_or_else(|| invalid("Expected one of: <, <=, >, >=, ==, !=".to_string()))?;
+
+    let metric = condition[..idx].trim();
+    let value = condition[idx + symbol.len()..].trim();
+
+    if !metrics.contains(&metric) {
+        return Err(invalid(format!(
+            "Unknown metric '{}'. Must be one of: {}",
+            metric,
+            metrics.join(", ")
+        )));
+    }
+
+    let value: f32 = value
+        .parse()
+        .map_err(|_| invalid(format!("'{}' is not a number", value)))?;
+
+    Ok(Condition {
+        metric: metric.to_string(),
+        operator,
+        value,
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const METRICS: [&str; 3] = ["a", "b", "c"];
+
+    #[test]
+    fn test_expression_precedence() {
+        let expression = Expression::parse("a < 1 && b >= 2 || c != 0", &METRICS).unwrap();
+
+        let values = |a: f32, b: f32, c: f32| {
+            move |metric: &str| match metric {
+                "a" => a,
+                "b" => b,
+                _ => c,
+            }
+        };
+
+        assert_eq!(
+            expression.matches(values(0.0, 2.0, 0.0)).unwrap(),
+            "a<1 && b>=2"
+        );
+        assert_eq!(expression.matches(values(0.0, 1.0, 5.0)).unwrap(), "c!=0");
+        assert_eq!(expression.matches(values(0.0, 1.0, 0.0)), None);
+        assert_eq!(expression.matches(values(f32::NAN, 2.0, 0.0)), None);
+        assert_eq!(expression.metrics(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_expression_invalid() {
+        for invalid in ["", "a", "a < x", "d < 1", "a < 1 ||", "1 < a", "a =< 1"] {
+            assert!(Expression::parse(invalid, &METRICS).is_err(), "{}", invalid);
+        }
+
+        let expression = Expression::parse("a<=-1.5e-3", &METRICS).unwrap();
+        assert_eq!(expression.matches(|_| -1.0).unwrap(), "a<=-0.0015");
+        assert_eq!(expression.matches(|_| 0.0), None);
+    }
+}
@@ -14,6 +14,22 @@ macro_rules! impl_enum_dispatch {
         }
     };
 
+    // Case when the method takes &self with a lifetime and has arguments
+    ($enum_name:ident<$lifetime:lifetime>, $($variant:ident),*; $fn_name:ident(&$self_lifetime:lifetime self, $($arg:ident : $arg_ty:ty),+) -> $ret:ty) => {
+        impl<$lifetime> $enum_name<$lifetime> {
+            pub fn $fn_name(&$self_lifetime self, $($arg: $arg_ty),+) -> $ret {
+                $crate::impl_enum_dispatch!(@match self, $fn_name, ($($arg),+); $($variant),*)
+            }
+        }
+    };
+
+    // Internal case for matching variants while forwarding a group of arguments
+    (@match $self:ident, $fn_name:ident, $args:tt; $($variant:ident),*) => {
+        match $self {
+            $(Self::$variant(v) => v.$fn_name $args,)*
+        }
+    };
+
     // Case when the method takes &self and has arguments
     ($enum_name:ident, $($variant:ident),*; $fn_name:ident(&self, $($arg:ident : $arg_ty:ty),+) -> $ret:ty) => {
         impl $enum_name {
@@ -1,3 +1,11 @@
+pub mod expr;
 pub mod macros;
 pub mod path;
+pub mod sample;
+pub mod split;
+pub mod suggest;
+pub mod synth;
+#[cfg(feature = "parallel")]
+pub mod threads;
+#[cfg(feature = "track")]
 pub mod track;
@@ -2,8 +2,10 @@
 // Licensed under the MIT License
 
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
 
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 use crate::error::ThymeError;
@@ -53,11 +55,12 @@ pub fn create_directory<P: AsRef<Path>>(directory: P) -> Result<PathBuf, ThymeEr
     let parent = directory.parent().unwrap_or_else(|| Path::new("."));
     let base_name = directory
         .file_name()
-        .and_then(|name| name.to_str())
         .ok_or_else(|| ThymeError::DirError("Invalid directory name".to_string()))?;
 
     for index in 0..30 {
-        let new_dir = parent.join(format!("{}_{}", base_name, index));
+        let mut name = base_name.to_os_string();
+        name.push(format!("_{}", index));
+        let new_dir = parent.join(name);
 
         if !new_dir.exists() {
             std::fs::create_dir(&new_dir).map_err(|err| ThymeError::DirError(err.to_string()))?;
@@ -71,6 +74,106 @@ pub fn create_directory<P: AsRef<Path>>(directory: P) -> Result<PathBuf, ThymeEr
     )))
 }
 
+/// Join the components of a relative path with forward slashes
+///
+/// Paths are compared component-wise rather than as strings, so that a
+/// relative path has the same representation (e.g. `plate_1/a01.tif`) on
+/// platforms with forward or back slash separators.
+///
+/// # Arguments
+///
+/// * `path` - A relative path
+///
+/// # Examples
+///
+/// ```
+/// use std::path::PathBuf;
+/// use thyme_core::ut::path::portable_path;
+///
+/// let path: PathBuf = ["plate_1", "a01_image.tif"].iter().collect();
+/// assert_eq!(portable_path(&path), "plate_1/a01_image.tif");
+/// ```
+pub fn portable_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            Component::ParentDir => Some("..".into()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// File stem of a path stored as text, with either slash as separator
+///
+/// Paths read from tables may have been written on another platform (e.g.
+/// `C:\data\a01_image.png` read on linux), where `Path` would treat the
+/// foreign separators as part of the file name.
+///
+/// # Arguments
+///
+/// * `path` - A path with forward or back slash separators
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::path::portable_file_stem;
+///
+/// assert_eq!(portable_file_stem(r"C:\data\a01_image.png"), Some("a01_image"));
+/// assert_eq!(portable_file_stem("plate/a01.tar.gz"), Some("a01.tar"));
+/// assert_eq!(portable_file_stem("plate/"), None);
+/// ```
+pub fn portable_file_stem(path: &str) -> Option<&str> {
+    let name = path.rsplit(['/', '\\']).next()?;
+
+    Path::new(name).file_stem().and_then(|stem| stem.to_str())
+}
+
+/// Check if two paths refer to the same location
+///
+/// Paths are compared by their components, so trailing separators and
+/// repeated separators (e.g. `images/` and `images`) are ignored.
+///
+/// # Arguments
+///
+/// * `a` - A path
+/// * `b` - A path
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::path::same_path;
+///
+/// assert!(same_path("images/", "images"));
+/// assert!(same_path("data//images", "data/images/"));
+/// assert!(!same_path("images", "masks"));
+/// ```
+pub fn same_path<A: AsRef<Path>, B: AsRef<Path>>(a: A, b: B) -> bool {
+    a.as_ref() == b.as_ref()
+}
+
+/// Url schemes of inputs read from object storage rather than the filesystem
+pub const REMOTE_SCHEMES: [&str; 2] = ["s3://", "https://"];
+
+/// Check if an input path is an object storage url (e.g. s3://plates/a01/)
+///
+/// # Arguments
+///
+/// * `path` - An input path or url
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::path::is_remote;
+///
+/// assert!(is_remote("s3://plates/a01/images"));
+/// assert!(is_remote("https://storage.example.org/plates/a01"));
+/// assert!(!is_remote("plates/a01/images"));
+/// ```
+pub fn is_remote(path: &str) -> bool {
+    REMOTE_SCHEMES.iter().any(|scheme| path.starts_with(scheme))
+}
+
 /// Collect file paths from a directory with an optional substring filter
 ///
 /// # Arguments
@@ -110,19 +213,185 @@ where
 
     if let Some(substring) = substring {
         files.retain(|f| {
-            f.file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .contains(&substring)
+            f.file_name().is_some_and(|name| {
+                find_bytes(name.as_encoded_bytes(), substring.as_bytes()).is_some()
+            })
         });
     }
 
     Ok(files)
 }
 
+/// Selection of the files collected from a directory
+///
+/// Substrings are matched against file names, while glob patterns and
+/// regular expressions are matched against the path of each file relative
+/// to the collected directory (e.g. `plate_1/a01_ch1.tif` when collecting
+/// recursively). Relative paths always use forward slashes, so patterns
+/// are portable across platforms. In glob patterns, `*` and `?` do not match path separators
+/// and `**/` matches any number of subdirectories. Regular expressions are
+/// unanchored so they match anywhere in the relative path.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use thyme_core::ut::path::FileSelector;
+///
+/// let selector = FileSelector::new(None, Some("*_ch[12]_*_maxproj.tif"), None).unwrap();
+///
+/// assert!(selector.matches(Path::new("a01_ch1_z0_maxproj.tif")));
+/// assert!(!selector.matches(Path::new("a01_ch3_z0_maxproj.tif")));
+/// assert!(FileSelector::new(Some("_ch1"), Some("*.tif"), None).is_err());
+/// ```
+#[cfg(feature = "io")]
+#[derive(Debug, Clone, Default)]
+pub enum FileSelector {
+    /// Select all files
+    #[default]
+    All,
+    /// Select files with names containing a substring
+    Substring(String),
+    /// Select files with relative paths matching a glob pattern
+    Glob(glob::Pattern),
+    /// Select files with relative paths matching a regular expression
+    Regex(regex::Regex),
+}
+
+#[cfg(feature = "io")]
+impl FileSelector {
+    /// Create a selector from at most one of a substring, glob, or regex
+    ///
+    /// # Arguments
+    ///
+    /// * `substring` - Only include files with names containing this substring
+    /// * `glob` - Only include files with relative paths matching this glob pattern
+    /// * `regex` - Only include files with relative paths matching this regex
+    pub fn new(
+        substring: Option<&str>,
+        glob: Option<&str>,
+        regex: Option<&str>,
+    ) -> Result<FileSelector, ThymeError> {
+        match (substring, glob, regex) {
+            (None, None, None) => Ok(FileSelector::All),
+            (Some(substring), None, None) => Ok(FileSelector::Substring(substring.to_string())),
+            (None, Some(pattern), None) => glob::Pattern::new(pattern)
+                .map(FileSelector::Glob)
+                .map_err(|err| {
+                    ThymeError::OtherError(format!("Invalid glob '{}'. {}", pattern, err))
+                }),
+            (None, None, Some(pattern)) => regex::Regex::new(pattern)
+                .map(FileSelector::Regex)
+                .map_err(|err| {
+                    ThymeError::OtherError(format!("Invalid regex '{}'. {}", pattern, err))
+                }),
+            _ => Err(ThymeError::OtherError(
+                "Only one of a substring, glob, or regex can select the files of an input"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Check if a file is selected
+    ///
+    /// # Arguments
+    ///
+    /// * `relative` - Path of the file relative to the collected directory
+    pub fn matches(&self, relative: &Path) -> bool {
+        match self {
+            FileSelector::All => true,
+            FileSelector::Substring(substring) => relative.file_name().is_some_and(|name| {
+                find_bytes(name.as_encoded_bytes(), substring.as_bytes()).is_some()
+            }),
+            FileSelector::Glob(pattern) => pattern.matches_with(
+                &portable_path(relative),
+                glob::MatchOptions {
+                    require_literal_separator: true,
+                    ..Default::default()
+                },
+            ),
+            FileSelector::Regex(regex) => regex.is_match(&portable_path(relative)),
+        }
+    }
+
+    /// Substring removed from file stems when pairing files
+    pub fn substring(&self) -> Option<String> {
+        match self {
+            FileSelector::Substring(substring) => Some(substring.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Collect the selected file paths from a directory
+///
+/// Files are returned sorted by path. With `recursive`, files in every
+/// subdirectory are collected and matched by their path relative to
+/// `directory`.
+///
+/// # Arguments
+///
+/// * `directory` - Path to directory containing files
+/// * `valid_ext` - Extensions of the collected files
+/// * `selector` - Selection of the collected files
+/// * `recursive` - Collect files from every subdirectory
+///
+/// # Examples
+///
+/// ```no_run
+/// use thyme_core::constant::SUPPORTED_IMAGE_FORMATS;
+/// use thyme_core::ut::path::{FileSelector, select_file_paths};
+///
+/// let selector = FileSelector::new(None, Some("**/*_ch1.tif"), None).unwrap();
+/// let files =
+///     select_file_paths("directory/", SUPPORTED_IMAGE_FORMATS.as_slice(), &selector, true);
+/// ```
+#[cfg(feature = "io")]
+pub fn select_file_paths<P: AsRef<Path>>(
+    directory: P,
+    valid_ext: &[&str],
+    selector: &FileSelector,
+    recursive: bool,
+) -> Result<Vec<PathBuf>, ThymeError> {
+    let root = directory.as_ref();
+    let mut directories = vec![root.to_path_buf()];
+    let mut files = vec![];
+
+    while let Some(directory) = directories.pop() {
+        let entries = std::fs::read_dir(&directory)
+            .map_err(|_| ThymeError::DirError(directory.display().to_string()))?;
+
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            if path.is_dir() {
+                if recursive {
+                    directories.push(path);
+                }
+
+                continue;
+            }
+
+            let valid = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| valid_ext.contains(&ext));
+
+            if valid && path.is_file() && selector.matches(path.strip_prefix(root).unwrap()) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort_unstable();
+
+    Ok(files)
+}
+
 /// Collect file pairs that share matching prefix
 ///
+/// File stems are compared as raw OS strings so that names with spaces or
+/// invalid UTF-8 are paired exactly. The returned identifier is a lossy
+/// UTF-8 copy of the shared stem.
+///
 /// # Arguments
 ///
 /// * `files_a` - List of file paths
@@ -164,25 +433,301 @@ pub fn collect_file_pairs(
     let substring_a = substring_a.unwrap_or_default();
     let substring_b = substring_b.unwrap_or_default();
 
-    let file_map: HashMap<String, &PathBuf> = files_a
+    let file_map: HashMap<Vec<u8>, &PathBuf> = files_a
         .iter()
         .filter_map(|file| {
-            file.file_stem().map(|stem| {
-                let name = stem.to_string_lossy().replace(&substring_a, "");
-                (name, file)
-            })
+            file.file_stem()
+                .map(|stem| (strip_substring(stem, &substring_a), file))
         })
         .collect();
 
+    #[cfg(feature = "parallel")]
+    let files_b = files_b.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let files_b = files_b.iter();
+
     files_b
-        .par_iter()
         .filter_map(|file_b| {
             file_b.file_stem().and_then(|stem| {
-                let name = stem.to_string_lossy().replace(&substring_b, "");
-                file_map
-                    .get(&name)
-                    .map(|file_a| (name, (*file_a).clone(), file_b.clone()))
+                let name = strip_substring(stem, &substring_b);
+                file_map.get(&name).map(|file_a| {
+                    (
+                        String::from_utf8_lossy(&name).to_string(),
+                        (*file_a).clone(),
+                        file_b.clone(),
+                    )
+                })
             })
         })
         .collect()
 }
+
+/// Find the first position of a byte pattern
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Remove every occurrence of a substring from a file stem
+fn strip_substring(stem: &OsStr, substring: &str) -> Vec<u8> {
+    let mut bytes = stem.as_encoded_bytes();
+
+    if substring.is_empty() {
+        return bytes.to_vec();
+    }
+
+    let mut stripped = Vec::with_capacity(bytes.len());
+
+    while let Some(i) = find_bytes(bytes, substring.as_bytes()) {
+        stripped.extend_from_slice(&bytes[..i]);
+        bytes = &bytes[i + substring.len()..];
+    }
+
+    stripped.extend_from_slice(bytes);
+    stripped
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_collect_file_pairs_spaces() {
+        let files_a = [
+            PathBuf::from("directory/plate a01_image.png"),
+            PathBuf::from("directory/plate a02 _image_image.png"),
+        ];
+
+        let files_b = [
+            PathBuf::from("directory/plate a01_mask.png"),
+            PathBuf::from("directory/plate a02 .png"),
+        ];
+
+        let mut pairs = collect_file_pairs(
+            &files_a,
+            &files_b,
+            Some("_image".to_string()),
+            Some("_mask".to_string()),
+        );
+        pairs.sort_unstable();
+
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].0, "plate a01");
+        assert_eq!(pairs[1].0, "plate a02 ");
+        assert_eq!(pairs[1].1, files_a[1]);
+    }
+
+    #[test]
+    fn test_windows_paths() {
+        // Paths built from components use the platform separator
+        let relative: PathBuf = ["plate_1", "a01_ch1.tif"].iter().collect();
+        assert_eq!(portable_path(&relative), "plate_1/a01_ch1.tif");
+        assert_eq!(
+            portable_path(&PathBuf::from(".").join("a01.tif")),
+            "a01.tif"
+        );
+
+        let glob = FileSelector::new(None, Some("plate_1/*_ch1.tif"), None).unwrap();
+        let regex = FileSelector::new(None, None, Some("^plate_1/a01")).unwrap();
+        assert!(glob.matches(&relative));
+        assert!(regex.matches(&relative));
+
+        // Stems of paths written on windows are found on every platform
+        for path in [
+            r"C:\data\plate_1\a01_image.png",
+            r"\\server\share\a01_image.png",
+            r"data/plate 1\a01_image.png",
+            "a01_image.png",
+        ] {
+            assert_eq!(portable_file_stem(path), Some("a01_image"), "{}", path);
+        }
+
+        assert_eq!(portable_file_stem(r"C:\data\"), None);
+
+        let windows: PathBuf = ["C:\\", "data", "images"].iter().collect();
+        let files_a = [windows.join("a01_image.png"), windows.join("a02_image.png")];
+        let files_b = [
+            PathBuf::from("masks").join("a01_mask.png"),
+            PathBuf::from("masks").join("a02_mask.png"),
+        ];
+
+        let mut pairs = collect_file_pairs(
+            &files_a,
+            &files_b,
+            Some("_image".to_string()),
+            Some("_mask".to_string()),
+        );
+        pairs.sort_unstable();
+
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].0, "a01");
+        assert_eq!(pairs[1].1, files_a[1]);
+
+        assert!(same_path(
+            windows.join("images"),
+            windows.join("images").join("")
+        ));
+        assert!(!same_path(&windows, windows.join("images")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_collect_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let directory = std::env::temp_dir().join("thyme_test_collect_non_utf8");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for name in [
+            &b"a\xff b_image.png"[..],
+            b"a\xff b_mask.png",
+            b"a\xfe b_mask.png",
+        ] {
+            std::fs::write(directory.join(OsStr::from_bytes(name)), []).unwrap();
+        }
+
+        let path = directory.to_str().unwrap();
+        let images = collect_file_paths(path, &["png"], Some("_image".to_string())).unwrap();
+        let masks = collect_file_paths(path, &["png"], Some("_mask".to_string())).unwrap();
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(masks.len(), 2);
+
+        // Lossy conversion would map both mask stems to the same name
+        let pairs = collect_file_pairs(
+            &images,
+            &masks,
+            Some("_image".to_string()),
+            Some("_mask".to_string()),
+        );
+
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].0, "a\u{fffd} b");
+        assert_eq!(
+            pairs[0].2.file_name().unwrap().as_bytes(),
+            b"a\xff b_mask.png"
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// Write empty files at paths relative to a directory
+    fn touch(directory: &Path, files: &[&str]) {
+        for file in files {
+            let path = directory.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, []).unwrap();
+        }
+    }
+
+    /// Names of selected files relative to a directory
+    fn selected(directory: &Path, selector: &FileSelector, recursive: bool) -> Vec<String> {
+        select_file_paths(directory, &["tif", "png"], selector, recursive)
+            .unwrap()
+            .iter()
+            .map(|path| path.strip_prefix(directory).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_select_file_paths_glob() {
+        let directory = std::env::temp_dir().join("thyme_test_select_file_paths_glob");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        touch(
+            &directory,
+            &[
+                "a01_ch1_z0.tif",
+                "a01_ch1_maxproj.tif",
+                "a01_ch2_maxproj.tif",
+                "a01_ch3_maxproj.tif",
+                "a01_chX_maxproj.tif",
+                "a01_ch1_maxproj.txt",
+                "plate_2/b01_ch1_maxproj.tif",
+                "plate_2/z/c01_ch2_maxproj.png",
+            ],
+        );
+
+        let glob = |pattern: &str| FileSelector::new(None, Some(pattern), None).unwrap();
+
+        assert_eq!(
+            selected(&directory, &glob("*_ch[12]_maxproj.tif"), false),
+            ["a01_ch1_maxproj.tif", "a01_ch2_maxproj.tif"]
+        );
+        assert_eq!(
+            selected(&directory, &glob("*_ch[!0-9]_*"), false),
+            ["a01_chX_maxproj.tif"]
+        );
+        assert_eq!(
+            selected(&directory, &glob("a01_ch?_z?.tif"), false),
+            ["a01_ch1_z0.tif"]
+        );
+
+        // Without recursion, subdirectories are never collected
+        assert_eq!(selected(&directory, &glob("**/*_ch1_*"), false).len(), 2);
+
+        // With recursion, * stops at separators and **/ matches any depth
+        assert_eq!(
+            selected(&directory, &glob("*_ch1_maxproj.tif"), true),
+            ["a01_ch1_maxproj.tif"]
+        );
+        assert_eq!(
+            selected(&directory, &glob("**/*_maxproj.*"), true),
+            [
+                "a01_ch1_maxproj.tif",
+                "a01_ch2_maxproj.tif",
+                "a01_ch3_maxproj.tif",
+                "a01_chX_maxproj.tif",
+                "plate_2/b01_ch1_maxproj.tif",
+                "plate_2/z/c01_ch2_maxproj.png",
+            ]
+        );
+        assert_eq!(
+            selected(&directory, &glob("plate_2/*"), true),
+            ["plate_2/b01_ch1_maxproj.tif"]
+        );
+
+        // Substrings match file names and regexes match relative paths
+        let substring = FileSelector::new(Some("_ch2"), None, None).unwrap();
+        let regex = FileSelector::new(None, None, Some(r"^plate_2/.*ch[0-9]")).unwrap();
+
+        assert_eq!(
+            selected(&directory, &substring, true),
+            ["a01_ch2_maxproj.tif", "plate_2/z/c01_ch2_maxproj.png"]
+        );
+        assert_eq!(
+            selected(&directory, &regex, true),
+            [
+                "plate_2/b01_ch1_maxproj.tif",
+                "plate_2/z/c01_ch2_maxproj.png"
+            ]
+        );
+        assert_eq!(selected(&directory, &FileSelector::All, false).len(), 5);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_file_selector_precedence() {
+        for (substring, glob, regex) in [
+            (Some("_image"), Some("*.tif"), None),
+            (Some("_image"), None, Some("tif$")),
+            (None, Some("*.tif"), Some("tif$")),
+            (None, Some("[a-"), None),
+            (None, None, Some("(")),
+        ] {
+            assert!(FileSelector::new(substring, glob, regex).is_err());
+        }
+
+        let selector = FileSelector::new(Some("_image"), None, None).unwrap();
+        assert_eq!(selector.substring(), Some("_image".to_string()));
+
+        let selector = FileSelector::new(None, Some("*_image.tif"), None).unwrap();
+        assert_eq!(selector.substring(), None);
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::BTreeMap;
+
+use crate::ut::synth::SplitMix64;
+
+/// Bin edges splitting values into strata with equal numbers of values
+///
+/// Each of the `strata - 1` edges is the smallest value of the next stratum,
+/// so a value belongs to the stratum given by the number of edges that are
+/// less than or equal to it (see `stratum`). Non-finite values are ignored.
+/// Repeated values can produce repeated edges and therefore empty strata.
+///
+/// # Arguments
+///
+/// * `values` - Values to stratify (e.g. object areas)
+/// * `strata` - Number of strata
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::sample::{quantile_edges, stratum};
+///
+/// let values = [5.0, 1.0, 7.0, 3.0, 2.0, 8.0, 4.0, 6.0];
+/// let edges = quantile_edges(&values, 4);
+///
+/// assert_eq!(edges, [3.0, 5.0, 7.0]);
+/// assert_eq!(stratum(2.0, &edges), 0);
+/// assert_eq!(stratum(5.0, &edges), 2);
+/// ```
+pub fn quantile_edges(values: &[f32], strata: usize) -> Vec<f32> {
+    let mut sorted: Vec<f32> = values.iter().copied().filter(|v| v.is_finite()).collect();
+
+    if sorted.is_empty() || strata < 2 {
+        return vec![];
+    }
+
+    sorted.sort_unstable_by(f32::total_cmp);
+
+    (1..strata)
+        .map(|k| sorted[(k * sorted.len() / strata).min(sorted.len() - 1)])
+        .collect()
+}
+
+/// Stratum of a value given the bin edges returned by `quantile_edges`
+///
+/// Values below the first edge and non-finite values belong to stratum 0.
+///
+/// # Arguments
+///
+/// * `value` - Value to assign
+/// * `edges` - Sorted bin edges
+pub fn stratum(value: f32, edges: &[f32]) -> usize {
+    edges.partition_point(|edge| *edge <= value)
+}
+
+/// Sample up to `n` rows spread as evenly as possible across strata
+///
+/// The rows of each stratum are shuffled with the seed and strata take turns
+/// contributing one row, in the order of their keys, until `n` rows are
+/// sampled or every stratum is exhausted. Strata with fewer rows than their
+/// share contribute all of their rows and the remainder is spread over the
+/// other strata, so sampled counts differ by at most one between strata that
+/// are not exhausted. Returns the sampled rows in ascending order.
+///
+/// # Arguments
+///
+/// * `strata` - Stratum of each row
+/// * `n` - Number of rows to sample
+/// * `seed` - Random seed
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::sample::sample_strata;
+///
+/// let strata = [0, 0, 0, 0, 0, 0, 1, 1];
+/// let rows = sample_strata(&strata, 4, 42);
+///
+/// assert_eq!(rows.len(), 4);
+/// assert_eq!(rows.iter().filter(|&&row| strata[row] == 1).count(), 2);
+/// ```
+pub fn sample_strata<S: Ord>(strata: &[S], n: usize, seed: u64) -> Vec<usize> {
+    let mut members: BTreeMap<&S, Vec<usize>> = BTreeMap::new();
+    for (row, stratum) in strata.iter().enumerate() {
+        members.entry(stratum).or_default().push(row);
+    }
+
+    let mut rng = SplitMix64::new(seed);
+    let mut members: Vec<Vec<usize>> = members.into_values().collect();
+
+    for rows in members.iter_mut() {
+        for i in (1..rows.len()).rev() {
+            rows.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+        }
+    }
+
+    let n = n.min(strata.len());
+    let mut sampled: Vec<usize> = Vec::with_capacity(n);
+    let mut taken = 0;
+
+    while sampled.len() < n {
+        for rows in members.iter() {
+            if let Some(&row) = rows.get(taken) {
+                sampled.push(row);
+
+                if sampled.len() == n {
+                    break;
+                }
+            }
+        }
+
+        taken += 1;
+    }
+
+    sampled.sort_unstable();
+    sampled
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_quantile_edges() {
+        assert!(quantile_edges(&[], 4).is_empty());
+        assert!(quantile_edges(&[1.0, 2.0], 1).is_empty());
+
+        let values: Vec<f32> = (0..100).map(|v| v as f32).collect();
+        assert_eq!(quantile_edges(&values, 4), [25.0, 50.0, 75.0]);
+
+        // Non-finite values are ignored and assigned to the first stratum
+        let edges = quantile_edges(&[f32::NAN, 1.0, 2.0, f32::INFINITY], 2);
+        assert_eq!(edges, [2.0]);
+        assert_eq!(stratum(f32::NAN, &edges), 0);
+
+        let counts = values.iter().fold([0; 4], |mut counts, v| {
+            counts[stratum(*v, &quantile_edges(&values, 4))] += 1;
+            counts
+        });
+
+        assert_eq!(counts, [25; 4]);
+    }
+
+    #[test]
+    fn test_sample_strata() {
+        // 90 small and 10 large objects
+        let strata: Vec<usize> = (0..100).map(|row| (row >= 90) as usize).collect();
+
+        let rows = sample_strata(&strata, 10, 3);
+        assert_eq!(rows.iter().filter(|&&row| strata[row] == 1).count(), 5);
+        assert_eq!(rows, sample_strata(&strata, 10, 3));
+        assert_ne!(rows, sample_strata(&strata, 10, 4));
+
+        // The small stratum fills in for the exhausted large stratum
+        let rows = sample_strata(&strata, 30, 3);
+        assert_eq!(rows.iter().filter(|&&row| strata[row] == 1).count(), 10);
+        assert_eq!(rows.len(), 30);
+
+        assert_eq!(sample_strata(&strata, 200, 3), (0..100).collect::<Vec<_>>());
+        assert!(sample_strata::<usize>(&[], 10, 3).is_empty());
+    }
+}
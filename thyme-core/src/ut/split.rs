@@ -0,0 +1,284 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::BTreeMap;
+
+use crate::error::ThymeError;
+use crate::ut::synth::SplitMix64;
+
+/// Fractions of a dataset assigned to each of two (train, test) or three
+/// (train, val, test) splits
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::split::SplitSpec;
+///
+/// let spec = SplitSpec::parse("0.7,0.15,0.15").unwrap();
+/// assert_eq!(spec.names(), ["train", "val", "test"]);
+///
+/// assert_eq!(SplitSpec::parse("0.8, 0.2").unwrap().names(), ["train", "test"]);
+/// assert!(SplitSpec::parse("0.7,0.2").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitSpec {
+    /// Fraction of rows in each split, summing to 1
+    pub fractions: Vec<f64>,
+}
+
+impl SplitSpec {
+    /// Parse two or three comma-separated fractions that sum to 1
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - Split fractions (e.g. 0.7,0.15,0.15)
+    pub fn parse(spec: &str) -> Result<SplitSpec, ThymeError> {
+        let invalid = || {
+            ThymeError::OtherError(format!(
+                "Invalid split '{}'. Must be two (train,test) or three (train,val,test) non-negative fractions that sum to 1",
+                spec
+            ))
+        };
+
+        let fractions: Vec<f64> = spec
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+
+        let valid = matches!(fractions.len(), 2 | 3)
+            && fractions.iter().all(|f| f.is_finite() && *f >= 0.0)
+            && (fractions.iter().sum::<f64>() - 1.0).abs() < 1e-6;
+
+        match valid {
+            true => Ok(SplitSpec { fractions }),
+            false => Err(invalid()),
+        }
+    }
+
+    /// Names of the splits
+    pub fn names(&self) -> &'static [&'static str] {
+        match self.fractions.len() {
+            2 => &["train", "test"],
+            _ => &["train", "val", "test"],
+        }
+    }
+}
+
+/// Assign each row of a dataset to a split while keeping groups together
+///
+/// Rows sharing a group (e.g. objects of one source image) are always
+/// assigned to the same split. Groups are shuffled with the seed and each
+/// group is assigned to the split furthest below its target number of rows,
+/// summed over the rows of the group's stratum and over every row, so that
+/// each stratum (e.g. class) and the whole dataset are split in approximately
+/// the requested fractions. A group spanning several strata belongs to its
+/// most frequent stratum. Assignments only depend on the seed and the rows,
+/// not on how the groups are ordered.
+///
+/// # Arguments
+///
+/// * `groups` - Group of each row
+/// * `strata` - Optional stratum of each row
+/// * `spec` - Split fractions
+/// * `seed` - Random seed
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::split::{SplitSpec, split_groups};
+///
+/// let groups = ["a", "a", "b", "c", "c", "d"];
+/// let spec = SplitSpec::parse("0.5,0.5").unwrap();
+/// let splits = split_groups(&groups, None::<&[&str]>, &spec, 42);
+///
+/// assert_eq!(splits[0], splits[1]);
+/// assert_eq!(splits[3], splits[4]);
+/// assert_eq!(splits.iter().filter(|&&s| s == 0).count(), 3);
+/// ```
+pub fn split_groups<G: Ord, S: Ord>(
+    groups: &[G],
+    strata: Option<&[S]>,
+    spec: &SplitSpec,
+    seed: u64,
+) -> Vec<usize> {
+    let k = spec.fractions.len();
+
+    let mut members: BTreeMap<&G, Vec<usize>> = BTreeMap::new();
+    for (row, group) in groups.iter().enumerate() {
+        members.entry(group).or_default().push(row);
+    }
+
+    // Stratum index of each group, in the order of the sorted group keys
+    let mut stratum_index: BTreeMap<Option<&S>, usize> = BTreeMap::new();
+    let mut entries: Vec<(Vec<usize>, usize)> = Vec::with_capacity(members.len());
+
+    for rows in members.into_values() {
+        let stratum = strata.map(|strata| majority(rows.iter().map(|&row| &strata[row])));
+        let next = stratum_index.len();
+        let index = *stratum_index.entry(stratum).or_insert(next);
+        entries.push((rows, index));
+    }
+
+    let mut rng = SplitMix64::new(seed);
+    for i in (1..entries.len()).rev() {
+        entries.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+    }
+
+    let mut stratum_rows = vec![0usize; stratum_index.len()];
+    for (rows, stratum) in entries.iter() {
+        stratum_rows[*stratum] += rows.len();
+    }
+
+    let mut assigned = vec![vec![0usize; k]; stratum_index.len()];
+    let mut assigned_total = vec![0usize; k];
+    let mut splits = vec![0usize; groups.len()];
+
+    for (rows, stratum) in entries {
+        let deficit = |split: usize| {
+            let fraction = spec.fractions[split];
+            fraction * stratum_rows[stratum] as f64 - assigned[stratum][split] as f64
+                + fraction * groups.len() as f64
+                - assigned_total[split] as f64
+        };
+
+        // Ties are assigned to the earliest split
+        let split = (0..k)
+            .filter(|&split| spec.fractions[split] > 0.0)
+            .max_by(|&a, &b| deficit(a).total_cmp(&deficit(b)).then(b.cmp(&a)))
+            .unwrap_or(0);
+
+        assigned[stratum][split] += rows.len();
+        assigned_total[split] += rows.len();
+
+        for row in rows {
+            splits[row] = split;
+        }
+    }
+
+    splits
+}
+
+/// Most frequent value, with ties resolved to the smallest value
+fn majority<'a, S: Ord>(values: impl Iterator<Item = &'a S>) -> &'a S {
+    let mut counts: BTreeMap<&S, usize> = BTreeMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(value, _)| value)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    /// Rows of 400 groups (images) with 1 to 6 objects of 3 classes
+    fn dataset() -> (Vec<String>, Vec<String>) {
+        let mut rng = SplitMix64::new(5);
+        let (mut groups, mut classes) = (vec![], vec![]);
+
+        for image in 0..400 {
+            let class = ["a", "b", "c"][(image % 7).min(2)];
+
+            for _ in 0..=(rng.next_u64() % 6) {
+                groups.push(format!("image_{}", image));
+                classes.push(class.to_string());
+            }
+        }
+
+        (groups, classes)
+    }
+
+    fn fraction(splits: &[usize], rows: impl Iterator<Item = usize>, split: usize) -> f64 {
+        let rows: Vec<usize> = rows.collect();
+        rows.iter().filter(|&&row| splits[row] == split).count() as f64 / rows.len() as f64
+    }
+
+    #[test]
+    fn test_split_groups_integrity() {
+        let (groups, classes) = dataset();
+        let spec = SplitSpec::parse("0.7,0.15,0.15").unwrap();
+
+        for strata in [None, Some(classes.as_slice())] {
+            let splits = split_groups(&groups, strata, &spec, 42);
+
+            let mut split_of: BTreeMap<&String, usize> = BTreeMap::new();
+            for (group, &split) in groups.iter().zip(splits.iter()) {
+                assert_eq!(*split_of.entry(group).or_insert(split), split, "{}", group);
+            }
+
+            for (split, target) in spec.fractions.iter().enumerate() {
+                let observed = fraction(&splits, 0..groups.len(), split);
+                assert!((observed - target).abs() < 0.02, "{} {}", observed, target);
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_groups_stratified() {
+        let (groups, classes) = dataset();
+        let spec = SplitSpec::parse("0.6,0.2,0.2").unwrap();
+        let splits = split_groups(&groups, Some(&classes), &spec, 1);
+
+        for class in ["a", "b", "c"] {
+            for (split, target) in spec.fractions.iter().enumerate() {
+                let rows = (0..groups.len()).filter(|&row| classes[row] == class);
+                let observed = fraction(&splits, rows, split);
+                assert!((observed - target).abs() < 0.05, "{} {}", class, observed);
+            }
+        }
+
+        // Strata that are constant within groups still split the dataset
+        let splits = split_groups(&groups, Some(&groups), &spec, 1);
+        assert!((fraction(&splits, 0..groups.len(), 0) - 0.6).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_split_groups_seed() {
+        let (groups, _) = dataset();
+        let spec = SplitSpec::parse("0.5,0.5").unwrap();
+
+        let splits = split_groups(&groups, None::<&[String]>, &spec, 42);
+        assert_eq!(splits, split_groups(&groups, None::<&[String]>, &spec, 42));
+        assert_ne!(splits, split_groups(&groups, None::<&[String]>, &spec, 43));
+
+        // Reordering the rows does not change the split of any group
+        let reversed: Vec<String> = groups.iter().rev().cloned().collect();
+        let reversed_splits = split_groups(&reversed, None::<&[String]>, &spec, 42);
+
+        for (row, split) in reversed_splits.iter().rev().enumerate() {
+            assert_eq!(*split, splits[row]);
+        }
+
+        // Splits with a zero fraction are never assigned
+        let spec = SplitSpec::parse("1,0,0").unwrap();
+        let splits = split_groups(&groups, None::<&[String]>, &spec, 0);
+        assert!(splits.iter().all(|&split| split == 0));
+    }
+
+    #[test]
+    fn test_split_spec_parse() {
+        assert_eq!(
+            SplitSpec::parse(" 0.5, 0.25 ,0.25").unwrap().fractions,
+            vec![0.5, 0.25, 0.25]
+        );
+
+        for invalid in [
+            "1",
+            "0.5,0.5,0,0",
+            "0.5,a",
+            "1.2,-0.2",
+            "0.6,0.6",
+            "nan,0.5",
+        ] {
+            assert!(SplitSpec::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
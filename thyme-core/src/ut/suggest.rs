@@ -0,0 +1,151 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+/// Compute the number of single character insertions, deletions, or substitutions between two strings
+///
+/// # Arguments
+///
+/// * `a` - First string
+/// * `b` - Second string
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::suggest::edit_distance;
+///
+/// assert_eq!(edit_distance("mask2polygon", "mask2polygons"), 1);
+/// assert_eq!(edit_distance("dino_vit_smal", "dino_vit_small"), 1);
+/// assert_eq!(edit_distance("weights", "weights"), 0);
+/// ```
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + (ca != *cb) as usize;
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Select the known names that are likely intended by a misspelled name
+///
+/// Names within an edit distance of a third of their length (and at least 2),
+/// or that contain or are contained in the misspelled name, are returned from
+/// the closest to the most distant.
+///
+/// # Arguments
+///
+/// * `name` - Misspelled name
+/// * `known` - Known names
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::suggest::closest;
+///
+/// let known = ["mask2boxes", "mask2polygons", "synth"];
+///
+/// assert_eq!(closest("mask2polygon", known), ["mask2polygons"]);
+/// assert_eq!(closest("mask", known), ["mask2boxes", "mask2polygons"]);
+/// assert!(closest("download", known).is_empty());
+/// ```
+pub fn closest<'a>(name: &str, known: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let lowercase = name.to_lowercase();
+
+    let mut candidates: Vec<(usize, &str)> = known
+        .into_iter()
+        .filter_map(|candidate| {
+            let distance = edit_distance(&lowercase, candidate);
+            let threshold = (candidate.chars().count() / 3).max(2);
+            let contains = lowercase.len() > 2
+                && (candidate.contains(lowercase.as_str()) || lowercase.contains(candidate));
+
+            (distance <= threshold || contains).then_some((distance, candidate))
+        })
+        .collect();
+
+    candidates.sort();
+    candidates
+        .into_iter()
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// Format an error message for an unknown name with suggestions and every known name
+///
+/// # Arguments
+///
+/// * `kind` - Kind of the name (e.g. `Weights`)
+/// * `name` - Unknown name
+/// * `known` - Known names
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::suggest::not_found;
+///
+/// assert_eq!(
+///     not_found("Dataset", "dsb_2091", ["dsb_2019", "hpa_2022"]),
+///     "Dataset dsb_2091 not found. Did you mean: dsb_2019? Available datasets include: dsb_2019, hpa_2022"
+/// );
+/// ```
+pub fn not_found<'a>(kind: &str, name: &str, known: impl IntoIterator<Item = &'a str>) -> String {
+    let known: Vec<&str> = known.into_iter().collect();
+    let suggestions = closest(name, known.iter().copied());
+
+    let mut message = format!("{} {} not found.", kind, name);
+
+    if !suggestions.is_empty() {
+        message.push_str(&format!(" Did you mean: {}?", suggestions.join(", ")));
+    }
+
+    let plural = match kind.ends_with('s') {
+        true => kind.to_lowercase(),
+        false => format!("{}s", kind.to_lowercase()),
+    };
+
+    message.push_str(&format!(
+        " Available {} include: {}",
+        plural,
+        known.join(", ")
+    ));
+
+    message
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("livecell_2021", "livecel_2021"), 1);
+        assert_eq!(edit_distance("ab", "ba"), 2);
+    }
+
+    #[test]
+    fn test_not_found_models() {
+        let message = not_found(
+            "Weights",
+            "dino_vit_smal",
+            crate::constant::SUPPORTED_MODELS,
+        );
+
+        assert!(
+            message.starts_with("Weights dino_vit_smal not found. Did you mean: dino_vit_small")
+        );
+    }
+}
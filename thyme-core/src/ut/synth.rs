@@ -0,0 +1,559 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+#[cfg(feature = "io")]
+use std::path::{Path, PathBuf};
+
+use crate::error::ThymeError;
+use crate::im::{BoundingBoxes, Dtype, Polygons, ThymeBuffer, ThymeImage, ThymeMask};
+
+/// Maximum number of placements attempted for each object
+const MAX_ATTEMPTS: usize = 1000;
+
+/// Intensity of background pixels as a fraction of the data type range
+const BACKGROUND: f64 = 0.1;
+
+/// Settings for generating synthetic images of elliptical objects
+///
+/// Images are generated deterministically from the seed and the image index,
+/// so the same settings always produce the same images regardless of the
+/// order or number of threads used to generate them.
+///
+/// # Examples
+///
+/// ```
+/// use thyme_core::ut::synth::SynthConfig;
+///
+/// let config = SynthConfig {
+///     width: 128,
+///     height: 128,
+///     objects: 10,
+///     ..Default::default()
+/// };
+///
+/// let synth = config.generate(0).unwrap();
+///
+/// assert_eq!(synth.polygons.len(), 10);
+/// assert_eq!(synth.boxes.len(), 10);
+/// assert_eq!(synth.image.width(), 128);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SynthConfig {
+    /// Width of each image
+    pub width: u32,
+    /// Height of each image
+    pub height: u32,
+    /// Number of image channels
+    pub channels: u32,
+    /// Number of objects in each image
+    pub objects: usize,
+    /// Data type of the generated images
+    pub dtype: Dtype,
+    /// Range of the semi-major axis of each object (pixels)
+    pub radius: [f32; 2],
+    /// Range of the eccentricity of each object in [0, 1)
+    pub eccentricity: [f32; 2],
+    /// Maximum fraction of each object that may overlap previously placed objects
+    ///
+    /// Objects are separated by at least one background pixel when zero.
+    pub overlap: f32,
+    /// Standard deviation of gaussian intensity noise as a fraction of the data type range
+    pub noise: f32,
+    /// Seed of the random number generator
+    pub seed: u64,
+}
+
+impl Default for SynthConfig {
+    fn default() -> Self {
+        Self {
+            width: 512,
+            height: 512,
+            channels: 1,
+            objects: 50,
+            dtype: Dtype::U16,
+            radius: [8.0, 16.0],
+            eccentricity: [0.0, 0.8],
+            overlap: 0.0,
+            noise: 0.02,
+            seed: 0,
+        }
+    }
+}
+
+/// A synthetic image with a labeled mask and matching polygons and bounding boxes
+///
+/// Objects are labeled 1, 2, .. in the mask and polygons and bounding boxes
+/// are listed in label order.
+#[derive(Debug)]
+pub struct SynthImage {
+    pub image: ThymeImage,
+    pub mask: ThymeMask,
+    pub polygons: Polygons,
+    pub boxes: BoundingBoxes,
+}
+
+impl SynthConfig {
+    /// Check that objects can be generated with the provided settings
+    pub fn validate(&self) -> Result<(), ThymeError> {
+        let [min_radius, max_radius] = self.radius;
+        let [min_eccentricity, max_eccentricity] = self.eccentricity;
+
+        let error = |message: &str| Err(ThymeError::OtherError(message.to_string()));
+
+        if self.width == 0 || self.height == 0 || self.channels == 0 {
+            return error("Synthetic images must have a positive width, height, and channels");
+        }
+
+        if self.objects > u16::MAX as usize {
+            return error("Synthetic images can have at most 65535 objects");
+        }
+
+        if !(min_radius >= 1.0 && min_radius <= max_radius) {
+            return error("Radius range must satisfy 1 <= min <= max");
+        }
+
+        if 2.0 * max_radius + 2.0 >= self.width.min(self.height) as f32 {
+            return error("Maximum radius is too large for the image size");
+        }
+
+        if !(0.0 <= min_eccentricity && min_eccentricity <= max_eccentricity)
+            || max_eccentricity >= 1.0
+        {
+            return error("Eccentricity range must satisfy 0 <= min <= max < 1");
+        }
+
+        if !(0.0..1.0).contains(&self.overlap) {
+            return error("Overlap must be in [0, 1)");
+        }
+
+        if self.noise.is_nan() || self.noise < 0.0 {
+            return error("Noise must be non-negative");
+        }
+
+        Ok(())
+    }
+
+    /// Generate a synthetic image
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the image, combined with the seed
+    pub fn generate(&self, index: usize) -> Result<SynthImage, ThymeError> {
+        self.validate()?;
+
+        let mut rng = SplitMix64::new(self.seed ^ (index as u64).wrapping_mul(0xD1B54A32D192ED03));
+
+        let (width, height) = (self.width as usize, self.height as usize);
+        let channels = self.channels as usize;
+
+        let mut labels = vec![0u32; width * height];
+        let mut profile = vec![0f64; width * height];
+        let mut peaks: Vec<Vec<f64>> = Vec::with_capacity(self.objects);
+
+        for label in 1..=self.objects as u32 {
+            let placed = (0..MAX_ATTEMPTS).any(|_| {
+                let pixels = self.sample_ellipse(&mut rng);
+
+                let conflicts = pixels
+                    .iter()
+                    .filter(|(idx, _)| self.occupied(&labels, *idx))
+                    .count();
+
+                if pixels.is_empty() || conflicts as f32 > self.overlap * pixels.len() as f32 {
+                    return false;
+                }
+
+                // Overlapping objects only claim pixels that are not yet labeled
+                for &(idx, r) in pixels.iter() {
+                    if labels[idx] == 0 {
+                        labels[idx] = label;
+                        profile[idx] = 1.0 - 0.5 * r;
+                    }
+                }
+
+                true
+            });
+
+            if !placed {
+                return Err(ThymeError::OtherError(format!(
+                    "Could only place {} of {} objects in a {}x{} image. Reduce the number of objects or their radius",
+                    label - 1,
+                    self.objects,
+                    self.width,
+                    self.height
+                )));
+            }
+
+            peaks.push((0..channels).map(|_| 0.4 + 0.5 * rng.uniform()).collect());
+        }
+
+        let mut values = Vec::with_capacity(width * height * channels);
+
+        for (&label, &profile) in labels.iter().zip(&profile) {
+            for c in 0..channels {
+                let signal = match label {
+                    0 => BACKGROUND,
+                    l => BACKGROUND + (peaks[l as usize - 1][c] - BACKGROUND) * profile,
+                };
+
+                let noise = self.noise as f64 * rng.normal();
+                values.push((signal + noise).clamp(0.0, 1.0));
+            }
+        }
+
+        let image = self.image(values)?;
+        let mut mask = ThymeMask::new(self.width, self.height, 1, labels)?;
+
+        let (_, polygons) = mask.polygons()?;
+        let boxes = polygons.to_bounding_boxes()?;
+
+        Ok(SynthImage {
+            image,
+            mask,
+            polygons,
+            boxes,
+        })
+    }
+
+    /// Sample a random ellipse and return its pixels with their normalized squared radius
+    fn sample_ellipse(&self, rng: &mut SplitMix64) -> Vec<(usize, f64)> {
+        let [min_radius, max_radius] = self.radius;
+        let [min_eccentricity, max_eccentricity] = self.eccentricity;
+
+        let a = rng.range(min_radius as f64, max_radius as f64);
+        let e = rng.range(min_eccentricity as f64, max_eccentricity as f64);
+        let b = (a * (1.0 - e * e).sqrt()).max(1.0);
+        let theta = rng.range(0.0, std::f64::consts::PI);
+
+        // Objects are kept at least one pixel away from the image border
+        let (width, height) = (self.width as f64, self.height as f64);
+        let cx = rng.range(a + 1.0, width - a - 1.0);
+        let cy = rng.range(a + 1.0, height - a - 1.0);
+
+        let (sin, cos) = theta.sin_cos();
+
+        let mut pixels = vec![];
+
+        for y in (cy - a).floor() as usize..(cy + a).ceil() as usize {
+            for x in (cx - a).floor() as usize..(cx + a).ceil() as usize {
+                let dx = x as f64 + 0.5 - cx;
+                let dy = y as f64 + 0.5 - cy;
+
+                let u = (dx * cos + dy * sin) / a;
+                let v = (dy * cos - dx * sin) / b;
+                let r = u * u + v * v;
+
+                if r <= 1.0 {
+                    pixels.push((y * self.width as usize + x, r));
+                }
+            }
+        }
+
+        pixels
+    }
+
+    /// Check if a pixel, or any of its neighbors when overlap is disallowed, is labeled
+    fn occupied(&self, labels: &[u32], idx: usize) -> bool {
+        if self.overlap > 0.0 {
+            return labels[idx] != 0;
+        }
+
+        let width = self.width as usize;
+        let (x, y) = (idx % width, idx / width);
+
+        (y.saturating_sub(1)..(y + 2).min(self.height as usize))
+            .any(|j| (x.saturating_sub(1)..(x + 2).min(width)).any(|i| labels[j * width + i] != 0))
+    }
+
+    /// Scale intensities in [0, 1] to the range of the data type
+    fn image(&self, values: Vec<f64>) -> Result<ThymeImage, ThymeError> {
+        let max = self.dtype.scale_range()[1];
+
+        macro_rules! image {
+            ($variant:ident, $t:ty) => {
+                ThymeImage::$variant(ThymeBuffer::new(
+                    self.width,
+                    self.height,
+                    self.channels,
+                    values.iter().map(|v| (v * max).round() as $t).collect(),
+                )?)
+            };
+        }
+
+        Ok(match self.dtype {
+            Dtype::U8 => image!(U8, u8),
+            Dtype::U16 => image!(U16, u16),
+            Dtype::U32 => image!(U32, u32),
+            Dtype::F32 => ThymeImage::F32(ThymeBuffer::new(
+                self.width,
+                self.height,
+                self.channels,
+                values.iter().map(|&v| v as f32).collect(),
+            )?),
+            Dtype::F64 => ThymeImage::F64(ThymeBuffer::new(
+                self.width,
+                self.height,
+                self.channels,
+                values,
+            )?),
+        })
+    }
+
+    /// Generate synthetic images and write them to a directory
+    ///
+    /// Each image `synth_{i}` is written as `synth_{i}_image` (png for 8 and
+    /// 16-bit images with 1 or 3 channels and npy otherwise) alongside a 16-bit
+    /// `synth_{i}_mask.png`, `synth_{i}_polygons.json`, and `synth_{i}_boxes.json`.
+    /// Existing files are overwritten.
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - Number of images
+    /// * `directory` - Output directory (created if missing)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use thyme_core::ut::synth::SynthConfig;
+    ///
+    /// let stems = SynthConfig::default().write(10, "demo").unwrap();
+    /// assert_eq!(stems[0], "synth_0000");
+    /// ```
+    #[cfg(feature = "io")]
+    pub fn write<P: AsRef<Path>>(
+        &self,
+        images: usize,
+        directory: P,
+    ) -> Result<Vec<String>, ThymeError> {
+        #[cfg(feature = "parallel")]
+        use rayon::prelude::*;
+
+        let directory = directory.as_ref();
+
+        std::fs::create_dir_all(directory).map_err(|err| ThymeError::DirError(err.to_string()))?;
+
+        #[cfg(feature = "parallel")]
+        let indices = (0..images).into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let indices = 0..images;
+
+        indices
+            .map(|index| {
+                let stem = format!("synth_{:04}", index);
+                let path =
+                    |suffix: &str| -> PathBuf { directory.join(format!("{}_{}", stem, suffix)) };
+
+                let synth = self.generate(index)?;
+
+                let extension = match (self.dtype, self.channels) {
+                    (Dtype::U8 | Dtype::U16, 1 | 3) => "png",
+                    _ => "npy",
+                };
+
+                synth.image.save(path(&format!("image.{}", extension)))?;
+
+                let mask = synth.mask.as_raw().iter().map(|&l| l as u16).collect();
+                ThymeImage::U16(ThymeBuffer::new(self.width, self.height, 1, mask)?)
+                    .save(path("mask.png"))?;
+
+                synth.polygons.save(path("polygons.json"))?;
+                synth.boxes.save(path("boxes.json"))?;
+
+                Ok(stem)
+            })
+            .collect()
+    }
+}
+
+/// SplitMix64 generator so that generated data only depends on the seed
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in [0, 1)
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform sample in [min, max)
+    pub(crate) fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.uniform()
+    }
+
+    /// Standard normal sample using the Box-Muller transform
+    fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.uniform();
+        let v = self.uniform();
+
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn config() -> SynthConfig {
+        SynthConfig {
+            width: 96,
+            height: 64,
+            objects: 12,
+            radius: [4.0, 8.0],
+            seed: 7,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_synth_deterministic() {
+        let a = config().generate(3).unwrap();
+        let b = config().generate(3).unwrap();
+        let c = config().generate(4).unwrap();
+
+        assert_eq!(a.mask.as_raw(), b.mask.as_raw());
+        assert_ne!(a.mask.as_raw(), c.mask.as_raw());
+
+        match (a.image, b.image) {
+            (ThymeImage::U16(a), ThymeImage::U16(b)) => assert_eq!(a.as_raw(), b.as_raw()),
+            _ => panic!("Expected 16-bit images"),
+        }
+    }
+
+    #[test]
+    fn test_synth_consistent() {
+        for overlap in [0.0, 0.3] {
+            let config = SynthConfig {
+                overlap,
+                ..config()
+            };
+
+            let mut synth = config.generate(0).unwrap();
+
+            assert_eq!(synth.mask.label(), (1..=12).collect::<Vec<u32>>());
+            assert_eq!(synth.polygons.len(), 12);
+            assert_eq!(synth.boxes.len(), 12);
+
+            for (label, xyxy) in (1..).zip(synth.boxes.as_xyxy()) {
+                let [min_x, min_y, max_x, max_y] = xyxy.map(|v| v as usize);
+
+                // Objects never touch the image border
+                assert!(min_x > 0 && min_y > 0 && max_x < 95 && max_y < 63);
+
+                for (idx, &l) in synth.mask.as_raw().iter().enumerate() {
+                    if l == label {
+                        let (x, y) = (idx % 96, idx / 96);
+                        assert!((min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_synth_separated() {
+        let synth = config().generate(1).unwrap();
+        let mask = synth.mask.as_raw();
+
+        // Non-overlapping objects are separated by background pixels
+        for y in 0..63 {
+            for x in 0..95 {
+                let l = mask[y * 96 + x];
+                for n in [mask[y * 96 + x + 1], mask[(y + 1) * 96 + x]] {
+                    assert!(l == 0 || n == 0 || l == n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_synth_dtypes() {
+        for (dtype, channels) in [
+            (Dtype::U8, 3),
+            (Dtype::U32, 1),
+            (Dtype::F32, 2),
+            (Dtype::F64, 1),
+        ] {
+            let config = SynthConfig {
+                dtype,
+                channels,
+                ..config()
+            };
+
+            let synth = config.generate(0).unwrap();
+            assert_eq!(synth.image.channels(), channels);
+
+            let max = dtype.scale_range()[1] as f32;
+            let values = synth.image.to_f32();
+            assert!(values.iter().all(|&v| (0.0..=max).contains(&v)));
+        }
+    }
+
+    #[test]
+    fn test_synth_invalid() {
+        let invalid = [
+            SynthConfig {
+                radius: [0.5, 4.0],
+                ..config()
+            },
+            SynthConfig {
+                radius: [4.0, 40.0],
+                ..config()
+            },
+            SynthConfig {
+                eccentricity: [0.5, 1.0],
+                ..config()
+            },
+            SynthConfig {
+                overlap: 1.0,
+                ..config()
+            },
+            SynthConfig {
+                objects: 1000,
+                ..config()
+            },
+        ];
+
+        for config in invalid {
+            assert!(config.generate(0).is_err(), "{:?}", config);
+        }
+    }
+
+    #[cfg(feature = "io")]
+    #[test]
+    fn test_synth_write() {
+        let directory = std::env::temp_dir().join("thyme_test_synth_write");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let stems = config().write(2, &directory).unwrap();
+        assert_eq!(stems, ["synth_0000", "synth_0001"]);
+
+        let mut mask = ThymeMask::open(directory.join("synth_0001_mask.png")).unwrap();
+        assert_eq!(mask.label().len(), 12);
+        assert_eq!(mask.as_raw(), config().generate(1).unwrap().mask.as_raw());
+
+        let polygons = Polygons::open(directory.join("synth_0001_polygons.json")).unwrap();
+        let boxes = BoundingBoxes::open(directory.join("synth_0001_boxes.json")).unwrap();
+        assert_eq!(polygons.len(), 12);
+        assert_eq!(boxes.len(), 12);
+
+        let image = ThymeImage::open(directory.join("synth_0000_image.png")).unwrap();
+        assert_eq!(image.shape(), (64, 96, 1));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+/// Run an operation on a thread pool with a fixed number of threads
+///
+/// A new pool is created for every call rather than configuring the global
+/// pool, so that several commands can run in the same process (e.g. multi-step
+/// pipelines or embedding thyme in another application) with different thread
+/// counts. The operation runs on the current pool if `threads` is not provided
+/// or a pool could not be created.
+///
+/// # Arguments
+///
+/// * `threads` - Number of threads in the pool
+/// * `op` - Operation to run within the pool
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use thyme_core::ut::threads::install;
+///
+/// assert_eq!(install(Some(2), rayon::current_num_threads), 2);
+/// assert_eq!(install(Some(3), rayon::current_num_threads), 3);
+///
+/// let sum: u32 = install(None, || (0..10u32).into_par_iter().sum());
+/// assert_eq!(sum, 45);
+/// ```
+pub fn install<OP, R>(threads: Option<usize>, op: OP) -> R
+where
+    OP: FnOnce() -> R + Send,
+    R: Send,
+{
+    let Some(threads) = threads else {
+        return op();
+    };
+
+    match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool.install(op),
+        Err(err) => {
+            eprintln!(
+                "[thyme] WARNING: Could not create a pool of {} threads ({}). Running with {} threads instead.",
+                threads,
+                err,
+                rayon::current_num_threads()
+            );
+            op()
+        }
+    }
+}
@@ -2,6 +2,7 @@
 // Licensed under the MIT License
 
 use crate::request;
+use crate::suggest;
 use std::path::Path;
 
 // NOTE: This download scheme isn't really good practice as any
@@ -45,9 +46,12 @@ impl BenchmarkDatasets {
             "runtime" => BenchmarkDatasets::Runtime,
             _ => {
                 let msg = format!(
-                    "[thyme::data::benchmark] Dataset {} not found. Avalaible benchmark datasets include: {}",
-                    name,
-                    "amgad_2022, cnmc_2019, fracatlas_2023, isic_2019, kermany_2018, kromp_2023, matek_2021, murphy_2001, opencell_2024, phillip_2021, recursion_2019, verma_2021, runtime."
+                    "[thyme::data::benchmark] {}.",
+                    suggest::not_found(
+                        "Benchmark dataset",
+                        name,
+                        Self::iter().map(|dataset| dataset.name())
+                    )
                 );
                 eprintln!("{}", msg);
                 std::process::exit(1);
@@ -76,6 +80,11 @@ impl BenchmarkDatasets {
         BENCHMARKS.iter()
     }
 
+    /// Suggest available benchmark datasets for a misspelled dataset name.
+    pub fn suggest(name: &str) -> Vec<String> {
+        suggest::closest(name, Self::iter().map(|dataset| dataset.name()))
+    }
+
     /// Get the name of the model saved on Google drive.
     pub fn name(&self) -> &str {
         match self {
@@ -171,6 +180,62 @@ impl BenchmarkDatasets {
         }
     }
 
+    /// Get a short description of the images in the dataset.
+    pub fn description(&self) -> &str {
+        match self {
+            BenchmarkDatasets::Amgad2022 => "Nuclei classes in H&E stained breast cancer histology",
+            BenchmarkDatasets::Cnmc2019 => {
+                "Normal and malignant white blood cells from leukemia smears"
+            }
+            BenchmarkDatasets::Fracatlas2023 => {
+                "Fractured and non-fractured bones in musculoskeletal radiographs"
+            }
+            BenchmarkDatasets::Isic2019 => "Skin lesion classes in dermoscopic images",
+            BenchmarkDatasets::Kermany2018 => {
+                "Disease classes in retinal optical coherence tomography images"
+            }
+            BenchmarkDatasets::Kromp2023 => "Annotated single nuclei in fluorescence images",
+            BenchmarkDatasets::Matek2021 => "Bone marrow cell classes in cytomorphology images",
+            BenchmarkDatasets::Murphy2001 => {
+                "Subcellular localization patterns in fluorescent HeLa cell images"
+            }
+            BenchmarkDatasets::Opencell2024 => {
+                "Protein subcellular localization in endogenously tagged human cells"
+            }
+            BenchmarkDatasets::Phillip2021 => "Single cell morphology images",
+            BenchmarkDatasets::Recursion2019 => {
+                "Genetic perturbations in multi-channel fluorescent cell images"
+            }
+            BenchmarkDatasets::Verma2021 => "Nuclei classes in H&E stained multi-organ histology",
+            BenchmarkDatasets::Runtime => {
+                "Synthetic images for evaluating processing and profiling runtime"
+            }
+        }
+    }
+
+    /// Get the number of images in the dataset if it was downloaded to a directory.
+    pub fn n_images(&self, directory: &Path) -> Option<usize> {
+        super::count_images(directory, self.name())
+    }
+
+    /// Download each of the provided datasets to an output directory.
+    pub fn download_all<'a, I>(datasets: I, output: &Path, verbose: bool)
+    where
+        I: IntoIterator<Item = &'a BenchmarkDatasets>,
+    {
+        std::fs::create_dir_all(output).unwrap_or_else(|_| {
+            eprintln!(
+                "[thyme::data::benchmark] Could not create directory {}.",
+                output.display()
+            );
+            std::process::exit(1);
+        });
+
+        for dataset in datasets {
+            dataset.download(output, verbose);
+        }
+    }
+
     /// Download the dataset to an output directory.
     pub fn download(&self, output: &Path, verbose: bool) {
         let filename = format!("{}.tar.gz", self.name().replace("_", "-"));
@@ -5,3 +5,122 @@ mod weights;
 pub use benchmark::BenchmarkDatasets;
 pub use segmentation::SegmentationDatasets;
 pub use weights::Weights;
+
+use std::path::Path;
+
+use thyme_core::constant::SUPPORTED_IMAGE_FORMATS;
+
+/// Count the images of a downloaded dataset
+///
+/// Images are the files with a supported image format below an `images`
+/// directory. They are counted in the extracted dataset directory if present
+/// and otherwise from the entries of the downloaded archive.
+///
+/// # Arguments
+///
+/// * `directory` - Directory the dataset was downloaded to
+/// * `name` - Name of the dataset
+fn count_images(directory: &Path, name: &str) -> Option<usize> {
+    let stem = name.replace("_", "-");
+
+    for extracted in [directory.join(name), directory.join(&stem)] {
+        if extracted.is_dir() {
+            return Some(count_extracted(&extracted, &extracted));
+        }
+    }
+
+    let archive = directory.join(format!("{}.tar.gz", stem));
+
+    if archive.is_file() {
+        return count_archive(&archive);
+    }
+
+    None
+}
+
+/// Check if an archive or dataset entry is a dataset image
+fn is_image(path: &Path) -> bool {
+    let in_images = path.parent().is_some_and(|parent| {
+        parent
+            .components()
+            .any(|component| component.as_os_str() == "images")
+    });
+
+    in_images
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SUPPORTED_IMAGE_FORMATS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Count the images below an extracted dataset directory
+fn count_extracted(root: &Path, directory: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| {
+            let path = entry.path();
+
+            if path.is_dir() {
+                count_extracted(root, &path)
+            } else {
+                path.strip_prefix(root).is_ok_and(is_image) as usize
+            }
+        })
+        .sum()
+}
+
+/// Count the images listed in a gzipped dataset archive
+fn count_archive(archive: &Path) -> Option<usize> {
+    let file = std::fs::File::open(archive).ok()?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+
+    let mut n = 0;
+    for entry in archive.entries().ok()? {
+        let entry = entry.ok()?;
+
+        if entry.header().entry_type().is_file() && is_image(&entry.path().ok()?) {
+            n += 1;
+        }
+    }
+
+    Some(n)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_count_images() {
+        let directory = std::env::temp_dir().join("thyme_test_count_images");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let dataset = directory.join("synth_2025");
+        std::fs::create_dir_all(dataset.join("images").join("train")).unwrap();
+        std::fs::create_dir_all(dataset.join("masks")).unwrap();
+
+        for file in ["images/a.png", "images/train/b.TIF", "images/notes.txt"] {
+            std::fs::write(dataset.join(file), b"").unwrap();
+        }
+        std::fs::write(dataset.join("masks").join("a.png"), b"").unwrap();
+
+        assert_eq!(count_images(&directory, "synth_2025"), Some(2));
+        assert_eq!(count_images(&directory, "missing_2025"), None);
+
+        // Archives are counted from their entries without extracting them
+        let archive = std::fs::File::create(directory.join("synth-2024.tar.gz")).unwrap();
+        let encoder = flate2::write::GzEncoder::new(archive, flate2::Compression::fast());
+        let mut builder = tar::Builder::new(encoder);
+        builder.append_dir_all("synth-2024", &dataset).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        assert_eq!(count_images(&directory, "synth_2024"), Some(2));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -2,6 +2,7 @@
 // Licensed under the MIT License
 
 use crate::request;
+use crate::suggest;
 use std::path::Path;
 
 // NOTE: This download scheme isn't really good practice as any
@@ -43,9 +44,8 @@ impl SegmentationDatasets {
             "vicar_2021" => SegmentationDatasets::Vicar2021,
             _ => {
                 let msg = format!(
-                    "[thyme::data::segmentation] Dataset {} not found. Avalaible datasets include: {}",
-                    name,
-                    "almeida_2023, arvidsson_2022, cellpose_2021, conic_2022, cryonuseg_2021, dsb_2019, hpa_2022, livecell_2021, nuinseg_2024, pannuke_2020, tissuenet_2022, vicar_2021."
+                    "[thyme::data::segmentation] {}.",
+                    suggest::not_found("Dataset", name, Self::iter().map(|dataset| dataset.name()))
                 );
                 eprintln!("{}", msg);
                 std::process::exit(1);
@@ -73,6 +73,11 @@ impl SegmentationDatasets {
         ANNOTATED.iter()
     }
 
+    /// Suggest available datasets for a misspelled dataset name.
+    pub fn suggest(name: &str) -> Vec<String> {
+        suggest::closest(name, Self::iter().map(|dataset| dataset.name()))
+    }
+
     /// Get the name of the model saved on Google drive.
     pub fn name(&self) -> &str {
         match self {
@@ -163,6 +168,55 @@ impl SegmentationDatasets {
         }
     }
 
+    /// Get a short description of the images in the dataset.
+    pub fn description(&self) -> &str {
+        match self {
+            SegmentationDatasets::Almeida2023 => "Annotated cell and nucleus microscopy images",
+            SegmentationDatasets::Arvidsson2022 => {
+                "Hoechst-stained nuclei in fluorescence high-content screening images"
+            }
+            SegmentationDatasets::Cellpose2021 => {
+                "Generalist cell segmentation images across many modalities"
+            }
+            SegmentationDatasets::Conic2022 => "Nuclei in H&E stained colon histology",
+            SegmentationDatasets::Cryonuseg2021 => "Nuclei in H&E stained cryosectioned tissue",
+            SegmentationDatasets::Dsb2019 => {
+                "Nuclei from the 2018 Data Science Bowl across imaging modalities"
+            }
+            SegmentationDatasets::Hpa2022 => {
+                "Cells in Human Protein Atlas immunofluorescence images"
+            }
+            SegmentationDatasets::Livecell2021 => "Cells in label-free phase contrast images",
+            SegmentationDatasets::Nuinseg2024 => "Nuclei in H&E stained human and mouse tissue",
+            SegmentationDatasets::Pannuke2020 => "Nuclei in H&E stained pan-cancer histology",
+            SegmentationDatasets::Tissuenet2022 => "Cells and nuclei in multiplexed tissue images",
+            SegmentationDatasets::Vicar2021 => "Cells in label-free microscopy images",
+        }
+    }
+
+    /// Get the number of images in the dataset if it was downloaded to a directory.
+    pub fn n_images(&self, directory: &Path) -> Option<usize> {
+        super::count_images(directory, self.name())
+    }
+
+    /// Download each of the provided datasets to an output directory.
+    pub fn download_all<'a, I>(datasets: I, output: &Path, verbose: bool)
+    where
+        I: IntoIterator<Item = &'a SegmentationDatasets>,
+    {
+        std::fs::create_dir_all(output).unwrap_or_else(|_| {
+            eprintln!(
+                "[thyme::data::segmentation] Could not create directory {}.",
+                output.display()
+            );
+            std::process::exit(1);
+        });
+
+        for dataset in datasets {
+            dataset.download(output, verbose);
+        }
+    }
+
     /// Download the dataset to an output directory.
     pub fn download(&self, output: &Path, verbose: bool) {
         let filename = format!("{}.tar.gz", self.name().replace("_", "-"));
@@ -1,8 +1,11 @@
 // Copyright (c) 2025, Tom Ouellette
 // Licensed under the MIT License
 
-use crate::get_thyme_cache;
+use crate::CacheDirectory;
 use crate::request;
+use crate::suggest;
+
+use thyme_core::error::ThymeError;
 
 // NOTE: This download scheme isn't really good practice as any
 // new dataset additions will require a new release of the library.
@@ -21,21 +24,25 @@ pub enum Weights {
 impl Weights {
     /// Select a weights from the available weights.
     pub fn select(weights_name: &str) -> Self {
+        Self::find(weights_name).unwrap_or_else(|err| {
+            eprintln!("[thyme::data::weights] {}.", err);
+            std::process::exit(1);
+        })
+    }
+
+    /// Look up a weights by name, suggesting close matches if it does not exist.
+    pub fn find(weights_name: &str) -> Result<Self, ThymeError> {
         match weights_name {
-            "dino_vit_small" => Weights::DinoVitSmall,
-            "dino_vit_base" => Weights::DinoVitBase,
-            "dinobloom_vit_base" => Weights::DinobloomVitBase,
-            "scdino_vit_small" => Weights::ScdinoVitSmall,
-            "subcell_vit_base" => Weights::SubcellVitBase,
-            _ => {
-                let msg = format!(
-                    "[thyme::data::weights] Weights {} not found. Avalaible weights include: {}",
-                    weights_name,
-                    "dino_vit_small, dino_vit_base, dinobloom_vit_base, scdino_vit_small, subcell_vit_base."
-                );
-                eprintln!("{}", msg);
-                std::process::exit(1);
-            }
+            "dino_vit_small" => Ok(Weights::DinoVitSmall),
+            "dino_vit_base" => Ok(Weights::DinoVitBase),
+            "dinobloom_vit_base" => Ok(Weights::DinobloomVitBase),
+            "scdino_vit_small" => Ok(Weights::ScdinoVitSmall),
+            "subcell_vit_base" => Ok(Weights::SubcellVitBase),
+            _ => Err(ThymeError::OtherError(suggest::not_found(
+                "Weights",
+                weights_name,
+                Self::iter().map(|weights| weights.model_name()),
+            ))),
         }
     }
 
@@ -52,6 +59,14 @@ impl Weights {
         WEIGHTS.iter()
     }
 
+    /// Suggest available weights for a misspelled weights name.
+    pub fn suggest(weights_name: &str) -> Vec<String> {
+        suggest::closest(
+            weights_name,
+            Self::iter().map(|weights| weights.model_name()),
+        )
+    }
+
     /// Get the name of the model.
     pub fn model_name(&self) -> &str {
         match self {
@@ -119,14 +134,17 @@ impl Weights {
     }
 
     /// Download the model to the thyme cache.
+    ///
+    /// Existing weights that are truncated (e.g. from an interrupted download)
+    /// are downloaded again.
     pub fn download(&self, verbose: bool) {
-        let cache = get_thyme_cache();
-        let model_name = cache.join(self.file_name());
-        if !model_name.exists() {
+        let model_name = self.path();
+        if !request::is_complete(&model_name) {
+            let cache = CacheDirectory::Weights.path();
             request::download_file(self.file_id(), cache.as_path(), self.file_name(), !verbose)
                 .unwrap();
 
-            if !model_name.exists() {
+            if !request::is_complete(&self.path()) {
                 eprintln!("[thyme::data::weights] Failed to download model weights.");
                 std::process::exit(1);
             }
@@ -135,7 +153,6 @@ impl Weights {
 
     /// Get path to model weights.
     pub fn path(&self) -> std::path::PathBuf {
-        let cache = get_thyme_cache();
-        cache.join(self.file_name())
+        CacheDirectory::Weights.resolve(self.file_name())
     }
 }
@@ -19,5 +19,87 @@ pub fn get_thyme_cache() -> std::path::PathBuf {
     std::path::PathBuf::from("/.thyme_cache")
 }
 
+/// Subdirectories used to organize files in the thyme cache
+pub enum CacheDirectory {
+    Weights,
+    Datasets,
+    Benchmarks,
+}
+
+impl CacheDirectory {
+    /// Get the name of the subdirectory.
+    pub fn name(&self) -> &str {
+        match self {
+            CacheDirectory::Weights => "weights",
+            CacheDirectory::Datasets => "datasets",
+            CacheDirectory::Benchmarks => "benchmarks",
+        }
+    }
+
+    /// Get the path to the subdirectory in the thyme cache.
+    pub fn path(&self) -> std::path::PathBuf {
+        get_thyme_cache().join(self.name())
+    }
+
+    /// Get the path to a cached file, migrating files from the legacy flat layout.
+    ///
+    /// Files were previously stored directly in the cache root. If a file is only
+    /// found there, it is moved into the subdirectory. If the move fails (e.g. a
+    /// read-only cache), the legacy path is returned so the file can still be used.
+    pub fn resolve(&self, filename: &str) -> std::path::PathBuf {
+        resolve_cached_file(&get_thyme_cache(), self.name(), filename)
+    }
+}
+
+fn resolve_cached_file(
+    cache: &std::path::Path,
+    directory: &str,
+    filename: &str,
+) -> std::path::PathBuf {
+    let path = cache.join(directory).join(filename);
+    let legacy = cache.join(filename);
+
+    if path.exists() || !legacy.is_file() {
+        return path;
+    }
+
+    // Renames within the cache are atomic so concurrent migrations are safe
+    if std::fs::create_dir_all(cache.join(directory)).is_ok()
+        && std::fs::rename(&legacy, &path).is_ok()
+    {
+        return path;
+    }
+
+    if path.exists() { path } else { legacy }
+}
+
 pub mod data;
+pub mod remote;
 pub mod request;
+pub mod suggest;
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_cached_file_migrates_legacy() {
+        let cache = std::env::temp_dir().join("thyme_test_cache_migrate");
+        let _ = std::fs::remove_dir_all(&cache);
+        std::fs::create_dir_all(&cache).unwrap();
+
+        std::fs::write(cache.join("legacy.safetensors"), b"weights").unwrap();
+
+        let path = resolve_cached_file(&cache, "weights", "legacy.safetensors");
+        assert_eq!(path, cache.join("weights").join("legacy.safetensors"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"weights");
+        assert!(!cache.join("legacy.safetensors").exists());
+
+        let path = resolve_cached_file(&cache, "weights", "missing.safetensors");
+        assert_eq!(path, cache.join("weights").join("missing.safetensors"));
+        assert!(!path.exists());
+
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+
+use anyhow::{Result, anyhow};
+
+use super::{ListPage, ObjectStore};
+
+/// Object store held in memory
+///
+/// Listings are paginated like a remote store and fetches of selected keys
+/// can be made to fail, so code reading remote inputs can be tested without
+/// a network.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    objects: BTreeMap<String, Vec<u8>>,
+    failing: BTreeSet<String>,
+    page_size: usize,
+}
+
+impl MemoryStore {
+    /// Initialize an empty store
+    ///
+    /// # Arguments
+    ///
+    /// * `page_size` - Maximum number of keys returned per listing page
+    pub fn new(page_size: usize) -> Self {
+        MemoryStore {
+            page_size: page_size.max(1),
+            ..Default::default()
+        }
+    }
+
+    /// Add an object to the store
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the object
+    /// * `contents` - Contents of the object
+    pub fn insert(&mut self, key: &str, contents: Vec<u8>) {
+        self.objects.insert(key.to_string(), contents);
+    }
+
+    /// Make fetches of an object fail as if the request had errored
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the object
+    pub fn fail(&mut self, key: &str) {
+        self.failing.insert(key.to_string());
+    }
+}
+
+impl ObjectStore for MemoryStore {
+    fn list_page(&self, prefix: &str, token: Option<&str>) -> Result<ListPage> {
+        let start = match token {
+            Some(token) => Bound::Excluded(token.to_string()),
+            None => Bound::Included(prefix.to_string()),
+        };
+
+        let mut keys: Vec<String> = self
+            .objects
+            .range((start, Bound::Unbounded))
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .take(self.page_size + 1)
+            .cloned()
+            .collect();
+
+        let next = (keys.len() > self.page_size).then(|| {
+            keys.truncate(self.page_size);
+            keys[self.page_size - 1].clone()
+        });
+
+        Ok(ListPage { keys, next })
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>> {
+        if self.failing.contains(key) {
+            return Err(anyhow!("Request for {} failed", key));
+        }
+
+        self.objects
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow!("No object with key {}", key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_memory_store_get() {
+        let mut store = MemoryStore::new(10);
+        store.insert("plate/a01.png", b"image".to_vec());
+        store.insert("plate/a02.png", b"image".to_vec());
+        store.fail("plate/a02.png");
+
+        assert_eq!(store.get("plate/a01.png").unwrap(), b"image");
+        assert!(store.get("plate/a02.png").is_err());
+        assert!(store.get("plate/a03.png").is_err());
+
+        // Failing objects are still listed
+        let page = store.list_page("plate/", None).unwrap();
+        assert_eq!((page.keys.len(), page.next), (2, None));
+    }
+}
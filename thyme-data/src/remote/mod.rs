@@ -0,0 +1,250 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+mod memory;
+mod profile;
+mod store;
+
+pub use memory::MemoryStore;
+pub use profile::AwsProfile;
+pub use store::RemoteStore;
+
+use anyhow::{Result, anyhow};
+
+pub use thyme_core::ut::path::is_remote;
+
+/// One page of the keys returned by listing an object store
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListPage {
+    /// Object keys in the page
+    pub keys: Vec<String>,
+    /// Token requesting the next page if the listing was truncated
+    pub next: Option<String>,
+}
+
+/// Object storage that inputs are listed and fetched from
+pub trait ObjectStore: Send + Sync {
+    /// List one page of the keys starting with a prefix
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Prefix of the listed keys
+    /// * `token` - Token returned with the previous page, if any
+    fn list_page(&self, prefix: &str, token: Option<&str>) -> Result<ListPage>;
+
+    /// Fetch the contents of an object into memory
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the object
+    fn get(&self, key: &str) -> Result<Vec<u8>>;
+}
+
+/// List every key starting with a prefix, following pagination
+///
+/// Returns the keys sorted and deduplicated.
+///
+/// # Arguments
+///
+/// * `store` - Object store
+/// * `prefix` - Prefix of the listed keys
+///
+/// # Examples
+///
+/// ```
+/// use thyme_data::remote::{MemoryStore, list_keys};
+///
+/// let mut store = MemoryStore::new(1);
+/// store.insert("plate/a01.png", b"png".to_vec());
+/// store.insert("plate/a02.png", b"png".to_vec());
+/// store.insert("other/a01.png", b"png".to_vec());
+///
+/// assert_eq!(
+///     list_keys(&store, "plate/").unwrap(),
+///     vec!["plate/a01.png", "plate/a02.png"]
+/// );
+/// ```
+pub fn list_keys(store: &dyn ObjectStore, prefix: &str) -> Result<Vec<String>> {
+    let mut keys = vec![];
+    let mut token: Option<String> = None;
+
+    loop {
+        let page = store.list_page(prefix, token.as_deref())?;
+        keys.extend(page.keys);
+
+        match page.next {
+            Some(next) if token.as_ref() == Some(&next) => {
+                return Err(anyhow!(
+                    "Listing of {} returned the same continuation token twice",
+                    prefix
+                ));
+            }
+            Some(next) => token = Some(next),
+            None => break,
+        }
+    }
+
+    keys.sort_unstable();
+    keys.dedup();
+
+    Ok(keys)
+}
+
+/// Location of an input directory in object storage
+///
+/// `s3://bucket/prefix` urls are read from AWS S3, or from the endpoint set
+/// by `AWS_ENDPOINT_URL_S3`/`AWS_ENDPOINT_URL` (e.g. MinIO). `https://` urls
+/// are read from an HTTP server, with the first path segment as the bucket
+/// (e.g. `https://storage.example.org/bucket/prefix`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteUrl {
+    /// Host of https urls, empty for s3 urls
+    pub host: String,
+    /// Bucket name
+    pub bucket: String,
+    /// Prefix of the keys in the directory, without a trailing slash
+    pub prefix: String,
+}
+
+impl RemoteUrl {
+    /// Parse an s3:// or https:// url
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Url of an input directory
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thyme_data::remote::RemoteUrl;
+    ///
+    /// let url = RemoteUrl::parse("s3://plates/2025/a01/").unwrap();
+    /// assert_eq!((url.bucket.as_str(), url.prefix.as_str()), ("plates", "2025/a01"));
+    /// assert_eq!(url.directory(), "2025/a01/");
+    ///
+    /// let url = RemoteUrl::parse("https://storage.example.org/plates/a01").unwrap();
+    /// assert_eq!(url.host, "storage.example.org");
+    /// assert!(RemoteUrl::parse("plates/a01").is_err());
+    /// ```
+    pub fn parse(url: &str) -> Result<Self> {
+        let (host, path) = if let Some(path) = url.strip_prefix("s3://") {
+            (String::new(), path)
+        } else if let Some(rest) = url.strip_prefix("https://") {
+            let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+            (host.to_string(), path)
+        } else {
+            return Err(anyhow!(
+                "Invalid remote url {}. Must start with s3:// or https://",
+                url
+            ));
+        };
+
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+
+        if bucket.is_empty() || (url.starts_with("https://") && host.is_empty()) {
+            return Err(anyhow!(
+                "Invalid remote url {}. No bucket was provided",
+                url
+            ));
+        }
+
+        Ok(RemoteUrl {
+            host,
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Prefix of the keys in the directory ending in a slash, or empty at the bucket root
+    pub fn directory(&self) -> String {
+        match self.prefix.is_empty() {
+            true => String::new(),
+            false => format!("{}/", self.prefix),
+        }
+    }
+
+    /// Url of an object in the bucket
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the object
+    pub fn object_url(&self, key: &str) -> String {
+        match self.host.is_empty() {
+            true => format!("s3://{}/{}", self.bucket, key),
+            false => format!("https://{}/{}/{}", self.host, self.bucket, key),
+        }
+    }
+
+    /// Open the object store holding the directory
+    ///
+    /// See [`RemoteStore::from_url`] for how stores are configured.
+    pub fn store(&self) -> Result<RemoteStore> {
+        RemoteStore::from_url(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_list_keys_pagination() {
+        let mut store = MemoryStore::new(2);
+
+        for i in 0..5 {
+            store.insert(&format!("plate/a0{}.png", i), vec![i]);
+        }
+
+        store.insert("plater/a00.png", vec![]);
+
+        let page = store.list_page("plate/", None).unwrap();
+        assert_eq!(page.keys, vec!["plate/a00.png", "plate/a01.png"]);
+        assert_eq!(page.next.as_deref(), Some("plate/a01.png"));
+
+        let keys = list_keys(&store, "plate/").unwrap();
+        assert_eq!(keys.len(), 5);
+        assert!(keys.iter().all(|key| key.starts_with("plate/")));
+
+        assert!(list_keys(&store, "missing/").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_keys_repeated_token() {
+        struct Stuck;
+
+        impl ObjectStore for Stuck {
+            fn list_page(&self, _: &str, _: Option<&str>) -> Result<ListPage> {
+                Ok(ListPage {
+                    keys: vec!["a.png".to_string()],
+                    next: Some("a.png".to_string()),
+                })
+            }
+
+            fn get(&self, _: &str) -> Result<Vec<u8>> {
+                Ok(vec![])
+            }
+        }
+
+        assert!(list_keys(&Stuck, "").is_err());
+    }
+
+    #[test]
+    fn test_remote_url() {
+        let url = RemoteUrl::parse("s3://plates").unwrap();
+        assert_eq!((url.prefix.as_str(), url.directory()), ("", String::new()));
+        assert_eq!(url.object_url("a01.png"), "s3://plates/a01.png");
+
+        let url = RemoteUrl::parse("https://minio.local:9000/plates/a/b/").unwrap();
+        assert_eq!(url.host, "minio.local:9000");
+        assert_eq!(url.directory(), "a/b/");
+        assert_eq!(
+            url.object_url("a/b/c.png"),
+            "https://minio.local:9000/plates/a/b/c.png"
+        );
+
+        assert!(RemoteUrl::parse("s3://").is_err());
+        assert!(RemoteUrl::parse("https://host").is_err());
+        assert!(RemoteUrl::parse("http://host/plates").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+
+/// Keys of profiles that resolve credentials in ways that are not supported
+const UNSUPPORTED_KEYS: [&str; 6] = [
+    "sso_session",
+    "sso_start_url",
+    "role_arn",
+    "source_profile",
+    "credential_process",
+    "web_identity_token_file",
+];
+
+/// Settings of a named profile in the shared AWS credentials and config files
+///
+/// Only static credentials, the region, and the endpoint are read. Profiles
+/// that assume roles, use SSO, or run a credential process are rejected so
+/// that requests are never silently sent unsigned or with other credentials.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AwsProfile {
+    /// Name of the profile
+    pub name: String,
+    /// Access key id
+    pub access_key_id: Option<String>,
+    /// Secret access key
+    pub secret_access_key: Option<String>,
+    /// Session token of temporary credentials
+    pub session_token: Option<String>,
+    /// Region of the bucket
+    pub region: Option<String>,
+    /// Endpoint of S3 compatible storage
+    pub endpoint: Option<String>,
+}
+
+impl AwsProfile {
+    /// Resolve the profile selected by the environment
+    ///
+    /// The profile is named by `AWS_PROFILE`, defaulting to `default`, and is
+    /// read from `AWS_SHARED_CREDENTIALS_FILE` and `AWS_CONFIG_FILE`, which
+    /// default to `~/.aws/credentials` and `~/.aws/config`. Returns `None` if
+    /// neither file has the default profile, and an error if a profile named
+    /// by `AWS_PROFILE` is missing or has no usable credentials.
+    pub fn from_env() -> Result<Option<Self>> {
+        let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+        let aws = dirs::home_dir().map(|home| home.join(".aws"));
+
+        let credentials = env("AWS_SHARED_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| aws.as_ref().map(|aws| aws.join("credentials")));
+
+        let config = env("AWS_CONFIG_FILE")
+            .map(PathBuf::from)
+            .or_else(|| aws.as_ref().map(|aws| aws.join("config")));
+
+        Self::load(
+            env("AWS_PROFILE").as_deref(),
+            credentials.as_deref(),
+            config.as_deref(),
+        )
+    }
+
+    /// Read a profile from the shared AWS credentials and config files
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the profile, or `None` for the default profile
+    /// * `credentials` - Path to the shared credentials file
+    /// * `config` - Path to the shared config file
+    pub fn load(
+        name: Option<&str>,
+        credentials: Option<&Path>,
+        config: Option<&Path>,
+    ) -> Result<Option<Self>> {
+        let explicit = name.is_some();
+        let name = name.unwrap_or("default");
+
+        // Sections of the config file are named `profile <name>` except for the default
+        let config_section = match name {
+            "default" => "default".to_string(),
+            name => format!("profile {}", name),
+        };
+
+        let credentials = credentials
+            .map(read_ini)
+            .transpose()?
+            .and_then(|mut sections| sections.remove(name));
+
+        let config = config
+            .map(read_ini)
+            .transpose()?
+            .and_then(|mut sections| sections.remove(&config_section));
+
+        if credentials.is_none() && config.is_none() {
+            return match explicit {
+                true => Err(anyhow!(
+                    "AWS profile {} was not found in the shared credentials or config file (~/.aws/credentials, ~/.aws/config)",
+                    name
+                )),
+                false => Ok(None),
+            };
+        }
+
+        // Credentials file values take precedence over the config file
+        let mut values = config.unwrap_or_default();
+        values.extend(credentials.unwrap_or_default());
+
+        let profile = AwsProfile {
+            name: name.to_string(),
+            access_key_id: values.remove("aws_access_key_id"),
+            secret_access_key: values.remove("aws_secret_access_key"),
+            session_token: values.remove("aws_session_token"),
+            region: values.remove("region"),
+            endpoint: values.remove("endpoint_url"),
+        };
+
+        if profile.access_key_id.is_some() != profile.secret_access_key.is_some() {
+            return Err(anyhow!(
+                "AWS profile {} must set both aws_access_key_id and aws_secret_access_key",
+                name
+            ));
+        }
+
+        if profile.access_key_id.is_none() {
+            if let Some(key) = UNSUPPORTED_KEYS
+                .iter()
+                .find(|key| values.contains_key(**key))
+            {
+                return Err(anyhow!(
+                    "AWS profile {} resolves credentials with {}, which is not supported. Export static credentials with `aws configure export-credentials --profile {} --format env` instead",
+                    name,
+                    key,
+                    name
+                ));
+            }
+
+            if explicit {
+                return Err(anyhow!(
+                    "AWS profile {} has no aws_access_key_id and aws_secret_access_key",
+                    name
+                ));
+            }
+        }
+
+        Ok(Some(profile))
+    }
+}
+
+/// Parse the sections of an ini file, returning no sections if it does not exist
+///
+/// # Arguments
+///
+/// * `path` - Path to the ini file
+fn read_ini(path: &Path) -> Result<HashMap<String, HashMap<String, String>>> {
+    if !path.is_file() {
+        return Ok(HashMap::new());
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut section: Option<String> = None;
+
+    for line in contents.lines() {
+        // Nested settings (e.g. the indented keys of `s3 =`) are skipped
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+            sections.entry(name.clone()).or_default();
+            section = Some(name);
+            continue;
+        }
+
+        if let (Some(section), Some((key, value))) = (&section, line.split_once('=')) {
+            let value = value.trim();
+
+            if !value.is_empty() {
+                sections
+                    .get_mut(section)
+                    .unwrap()
+                    .insert(key.trim().to_lowercase(), value.to_string());
+            }
+        }
+    }
+
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_aws_profile() {
+        let directory = std::env::temp_dir().join("thyme_test_aws_profile");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let credentials = directory.join("credentials");
+        let config = directory.join("config");
+
+        std::fs::write(
+            &credentials,
+            "[default]\naws_access_key_id = AKIA1\naws_secret_access_key = secret1\n\n\
+             [lab]\naws_access_key_id=AKIA2\naws_secret_access_key=secret2\naws_session_token=token\n",
+        )
+        .unwrap();
+
+        std::fs::write(
+            &config,
+            "[default]\nregion = us-east-1\n\n[profile lab]\nregion = eu-west-1\nendpoint_url = https://s3.example.org\n\n\
+             [profile sso]\nsso_session = lab\nregion = us-west-2\ns3 =\n  region = ignored\n\n[profile empty]\nregion = us-west-2\n",
+        )
+        .unwrap();
+
+        let load = |name: Option<&str>| AwsProfile::load(name, Some(&credentials), Some(&config));
+
+        let profile = load(None).unwrap().unwrap();
+        assert_eq!(profile.access_key_id.as_deref(), Some("AKIA1"));
+        assert_eq!(profile.region.as_deref(), Some("us-east-1"));
+        assert_eq!(profile.endpoint, None);
+
+        let profile = load(Some("lab")).unwrap().unwrap();
+        assert_eq!(profile.secret_access_key.as_deref(), Some("secret2"));
+        assert_eq!(profile.session_token.as_deref(), Some("token"));
+        assert_eq!(profile.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(profile.endpoint.as_deref(), Some("https://s3.example.org"));
+
+        // Profiles without static credentials fail with the reason
+        let err = load(Some("sso")).unwrap_err().to_string();
+        assert!(err.contains("sso_session"), "{}", err);
+        assert!(err.contains("export-credentials"), "{}", err);
+
+        assert!(load(Some("empty")).is_err());
+        assert!(
+            load(Some("missing"))
+                .unwrap_err()
+                .to_string()
+                .contains("missing")
+        );
+
+        // Missing files leave credentials to the environment
+        let missing = directory.join("absent");
+        assert_eq!(AwsProfile::load(None, Some(&missing), None).unwrap(), None);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{Context, Result, anyhow};
+use futures::TryStreamExt;
+use object_store::ObjectStore as _;
+use object_store::aws::AmazonS3Builder;
+use object_store::http::HttpBuilder;
+use object_store::path::Path;
+
+use super::{AwsProfile, ListPage, ObjectStore, RemoteUrl};
+
+/// Runtime shared by every remote store to send requests from synchronous code
+fn runtime() -> Result<&'static tokio::runtime::Runtime> {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .context("Failed to start the object storage runtime")?;
+
+    // A runtime started by a concurrent caller is kept and this one is dropped
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Bucket of an S3 object store or directory of an HTTP server
+///
+/// Requests are sent by [`object_store`] on a tokio runtime shared by every
+/// store, so stores can be used from synchronous code and shared across
+/// threads.
+#[derive(Debug, Clone)]
+pub struct RemoteStore {
+    store: Arc<dyn object_store::ObjectStore>,
+    url: String,
+}
+
+impl RemoteStore {
+    /// Initialize a store for the bucket of a remote url
+    ///
+    /// `s3://` urls are read with [`AmazonS3Builder::from_env`], which takes
+    /// credentials, region, and endpoint from the `AWS_*` environment
+    /// variables (e.g. `AWS_ACCESS_KEY_ID`, `AWS_REGION`, `AWS_ENDPOINT_URL`).
+    /// Without `AWS_ACCESS_KEY_ID`, the profile named by `AWS_PROFILE` (or
+    /// the default profile) is read from `~/.aws/credentials` and
+    /// `~/.aws/config` (see [`AwsProfile`]), and the environment still takes
+    /// precedence over its region and endpoint. `AWS_ENDPOINT_URL_S3` takes
+    /// precedence over `AWS_ENDPOINT_URL`, and `AWS_SKIP_SIGNATURE=true`
+    /// sends unsigned requests to public buckets.
+    /// `https://` urls are read with [`HttpBuilder`] from the directory
+    /// `https://{host}/{bucket}`, which must support WebDAV listings.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Remote url of an input directory
+    pub fn from_url(url: &RemoteUrl) -> Result<Self> {
+        let root = match url.host.is_empty() {
+            true => format!("s3://{}", url.bucket),
+            false => format!("https://{}/{}", url.host, url.bucket),
+        };
+
+        let store: Arc<dyn object_store::ObjectStore> = match url.host.is_empty() {
+            true => {
+                let mut builder = AmazonS3Builder::from_env().with_bucket_name(&url.bucket);
+
+                let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+
+                // Credentials in the environment take precedence over profiles
+                let profile = match env("AWS_ACCESS_KEY_ID").is_some()
+                    || env("AWS_SKIP_SIGNATURE").is_some_and(|v| v.eq_ignore_ascii_case("true"))
+                {
+                    true => None,
+                    false => AwsProfile::from_env()
+                        .with_context(|| format!("Failed to open {}", root))?,
+                };
+
+                if let Some(profile) = profile {
+                    if let (Some(key), Some(secret)) =
+                        (profile.access_key_id, profile.secret_access_key)
+                    {
+                        builder = builder
+                            .with_access_key_id(key)
+                            .with_secret_access_key(secret);
+                    }
+
+                    if let Some(token) = profile.session_token {
+                        builder = builder.with_token(token);
+                    }
+
+                    if let Some(region) = profile
+                        .region
+                        .filter(|_| env("AWS_REGION").or(env("AWS_DEFAULT_REGION")).is_none())
+                    {
+                        builder = builder.with_region(region);
+                    }
+
+                    if let Some(endpoint) = profile.endpoint {
+                        builder = builder.with_endpoint(endpoint);
+                    }
+                }
+
+                let endpoint = env("AWS_ENDPOINT_URL_S3").or(env("AWS_ENDPOINT_URL"));
+
+                if let Some(endpoint) = endpoint {
+                    builder = builder.with_endpoint(endpoint);
+                }
+
+                Arc::new(
+                    builder
+                        .build()
+                        .with_context(|| format!("Failed to open {}", root))?,
+                )
+            }
+            false => Arc::new(
+                HttpBuilder::new()
+                    .with_url(&root)
+                    .build()
+                    .with_context(|| format!("Failed to open {}", root))?,
+            ),
+        };
+
+        Ok(RemoteStore { store, url: root })
+    }
+}
+
+impl ObjectStore for RemoteStore {
+    /// List every key starting with a prefix in a single page
+    ///
+    /// Pages of the listing are followed by [`object_store`], so the
+    /// returned page never has a continuation token.
+    fn list_page(&self, prefix: &str, _token: Option<&str>) -> Result<ListPage> {
+        // Listings match whole path segments, so the directory of a partial
+        // prefix (e.g. plate/img_) is listed and its keys are filtered below
+        let directory = prefix.rfind('/').map_or("", |idx| &prefix[..idx]);
+        let location = Path::from(directory);
+
+        let keys = runtime()?
+            .block_on(async {
+                self.store
+                    .list(Some(&location))
+                    .map_ok(|meta| meta.location.to_string())
+                    .try_collect::<Vec<String>>()
+                    .await
+            })
+            .with_context(|| format!("Failed to list {}/{}", self.url, prefix))?;
+
+        let keys = keys
+            .into_iter()
+            .filter(|key| key.starts_with(prefix))
+            .collect();
+
+        Ok(ListPage { keys, next: None })
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let location =
+            Path::parse(key).map_err(|err| anyhow!("Invalid object key {}. {}", key, err))?;
+
+        let bytes = runtime()?
+            .block_on(async { self.store.get(&location).await?.bytes().await })
+            .with_context(|| format!("Failed to fetch {}/{}", self.url, key))?;
+
+        Ok(bytes.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use object_store::PutPayload;
+    use object_store::memory::InMemory;
+
+    use super::*;
+
+    #[test]
+    fn test_remote_store() {
+        let memory = InMemory::new();
+
+        runtime()
+            .unwrap()
+            .block_on(async {
+                for key in ["plate/a01.png", "plate/nested/a02.png", "plater/a03.png"] {
+                    memory
+                        .put(&Path::from(key), PutPayload::from(key.as_bytes().to_vec()))
+                        .await?;
+                }
+
+                Ok::<_, object_store::Error>(())
+            })
+            .unwrap();
+
+        let store = RemoteStore {
+            store: Arc::new(memory),
+            url: "s3://bucket".to_string(),
+        };
+
+        let page = store.list_page("plate/", None).unwrap();
+        assert_eq!(page.keys, vec!["plate/a01.png", "plate/nested/a02.png"]);
+        assert_eq!(page.next, None);
+
+        assert_eq!(store.list_page("", None).unwrap().keys.len(), 3);
+
+        // Partial file names and directory names match keys by prefix
+        assert_eq!(
+            store.list_page("plate/a0", None).unwrap().keys,
+            vec!["plate/a01.png"]
+        );
+        assert_eq!(
+            store.list_page("plate", None).unwrap().keys,
+            vec!["plate/a01.png", "plate/nested/a02.png", "plater/a03.png"]
+        );
+        assert_eq!(
+            store.list_page("plate/nested/a", None).unwrap().keys,
+            vec!["plate/nested/a02.png"]
+        );
+        assert!(store.list_page("missing/", None).unwrap().keys.is_empty());
+
+        assert_eq!(store.get("plate/a01.png").unwrap(), b"plate/a01.png");
+
+        let err = format!("{:#}", store.get("plate/a04.png").unwrap_err());
+        assert!(err.contains("s3://bucket/plate/a04.png"), "{}", err);
+    }
+
+    #[test]
+    fn test_remote_store_from_url() {
+        let url = RemoteUrl::parse("s3://plates/a01").unwrap();
+        assert_eq!(RemoteStore::from_url(&url).unwrap().url, "s3://plates");
+
+        let url = RemoteUrl::parse("https://storage.example.org/plates/a01").unwrap();
+        assert_eq!(
+            RemoteStore::from_url(&url).unwrap().url,
+            "https://storage.example.org/plates"
+        );
+    }
+}
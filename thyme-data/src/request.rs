@@ -5,6 +5,7 @@ use anyhow::{Context, Result, anyhow};
 use kdam::BarExt;
 use reqwest::{Client, redirect::Policy};
 use scraper::{Html, Selector};
+use std::io::Read;
 use std::path::Path;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
@@ -13,6 +14,11 @@ use thyme_core::ut::track::{progress_bar, progress_log};
 
 /// Download a file from Google drive
 ///
+/// Concurrent downloads of the same file (e.g. many cluster jobs starting on
+/// an empty cache) are serialized with an advisory lock. The first process
+/// downloads the file while the others wait and then re-use the completed
+/// file if it is valid.
+///
 /// # Arguments
 ///
 /// * `file_id` - Unique google drive file identifier
@@ -26,9 +32,59 @@ pub async fn download_file(
     filename: &str,
     silent: bool,
 ) -> Result<()> {
-    let client = create_http_client()?;
     let initial_url = format!("https://drive.google.com/uc?id={}&export=download", file_id);
-    let download_url = handle_virus_scan_warning(&client, &initial_url).await?;
+    download_locked(&initial_url, true, output_dir, filename, silent).await
+}
+
+/// Download a file from a url
+///
+/// Downloads are serialized across processes in the same way as `download_file`.
+///
+/// # Arguments
+///
+/// * `url` - Url of the file
+/// * `output_dir` - Directory to download file to
+/// * `filename` - Filename of downloaded file
+/// * `silent` - Turn off download messages
+#[tokio::main]
+pub async fn download_url(
+    url: &str,
+    output_dir: &Path,
+    filename: &str,
+    silent: bool,
+) -> Result<()> {
+    download_locked(url, false, output_dir, filename, silent).await
+}
+
+async fn download_locked(
+    url: &str,
+    google_drive: bool,
+    output_dir: &Path,
+    filename: &str,
+    silent: bool,
+) -> Result<()> {
+    tokio::fs::create_dir_all(output_dir)
+        .await
+        .context("Failed to create output directory")?;
+
+    let _lock = DownloadLock::acquire(output_dir, filename)?;
+
+    if is_complete(&output_dir.join(filename)) {
+        progress_log(
+            format!("Using existing download of {}", filename).as_str(),
+            !silent,
+        );
+        return Ok(());
+    }
+
+    let client = create_http_client()?;
+
+    let download_url = if google_drive {
+        handle_virus_scan_warning(&client, url).await?
+    } else {
+        url.to_string()
+    };
+
     download_file_with_progress(&client, &download_url, output_dir, filename, silent).await?;
     if !silent {
         println!();
@@ -37,6 +93,80 @@ pub async fn download_file(
     Ok(())
 }
 
+/// Check if a downloaded file exists and is not truncated
+///
+/// Safetensors files are additionally checked for a complete header since
+/// truncated or interleaved downloads otherwise fail when loading weights.
+///
+/// # Arguments
+///
+/// * `path` - Path to the downloaded file
+pub fn is_complete(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+
+    if !metadata.is_file() || metadata.len() == 0 {
+        return false;
+    }
+
+    if path.extension().and_then(|s| s.to_str()) != Some("safetensors") {
+        return true;
+    }
+
+    let mut header = [0u8; 9];
+    let Ok(mut file) = std::fs::File::open(path) else {
+        return false;
+    };
+
+    if file.read_exact(&mut header).is_err() {
+        return false;
+    }
+
+    let n = u64::from_le_bytes(header[..8].try_into().unwrap());
+
+    n > 0 && n.saturating_add(8) <= metadata.len() && header[8] == b'{'
+}
+
+/// An advisory lock on a file download that is released when dropped
+///
+/// The lock is held on a `.{filename}.lock` file next to the download and is
+/// released by the operating system if the process exits. Locking is only
+/// supported on unix platforms, elsewhere downloads still write to a temporary
+/// file that is atomically renamed so partial files are never observed.
+struct DownloadLock {
+    _file: std::fs::File,
+}
+
+impl DownloadLock {
+    /// Block until the lock for a download is acquired
+    fn acquire(output_dir: &Path, filename: &str) -> Result<Self> {
+        let path = output_dir.join(format!(".{}.lock", filename));
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .context("Failed to create download lock file")?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            // SAFETY: The file descriptor is valid for the lifetime of the file
+            while unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(err).context("Failed to acquire download lock");
+                }
+            }
+        }
+
+        Ok(Self { _file: file })
+    }
+}
+
 fn create_http_client() -> Result<Client> {
     Client::builder()
         .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
@@ -131,23 +261,163 @@ async fn download_file_with_progress(
         std::process::exit(1);
     }
 
-    tokio::fs::create_dir_all(output_dir)
-        .await
-        .context("Failed to create output directory")?;
+    // Files are downloaded to a temporary path and renamed once complete so
+    // that other processes never observe a partially written file
     let filepath = output_dir.join(filename);
-    let mut file = File::create(&filepath)
+    let partial = output_dir.join(format!(".{}.{}.part", filename, std::process::id()));
+
+    let mut file = File::create(&partial)
         .await
         .context("Failed to create output file")?;
 
+    let mut downloaded: u64 = 0;
+
     while let Some(chunk) = resp.chunk().await.context("Failed to read chunk")? {
         file.write_all(&chunk)
             .await
             .context("Failed to write chunk to file")?;
 
+        downloaded += chunk.len() as u64;
+
         if !silent {
             pb.update(chunk.len())?;
         }
     }
 
+    file.flush()
+        .await
+        .context("Failed to write chunk to file")?;
+    drop(file);
+
+    if downloaded != total_size {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(anyhow!(
+            "Download of {} was incomplete ({} of {} bytes)",
+            filename,
+            downloaded,
+            total_size
+        ));
+    }
+
+    tokio::fs::rename(&partial, &filepath)
+        .await
+        .context("Failed to move completed download")?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serve a fixed body slowly so that concurrent downloads overlap
+    fn serve(body: Vec<u8>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/weights", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                    line.clear();
+                }
+
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+
+                let _ = stream.write_all(header.as_bytes());
+                for chunk in body.chunks(body.len() / 8) {
+                    let _ = stream.write_all(chunk);
+                    let _ = stream.flush();
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                }
+            }
+        });
+
+        (url, requests)
+    }
+
+    /// A minimal safetensors file with a json header followed by data
+    fn safetensors() -> Vec<u8> {
+        let header = b"{\"__metadata__\":{}}";
+        let mut body = (header.len() as u64).to_le_bytes().to_vec();
+        body.extend(header);
+        body.extend((0..64 * 1024).map(|i| (i % 251) as u8));
+        body
+    }
+
+    #[test]
+    fn test_concurrent_download_single_file() {
+        let directory = std::env::temp_dir().join("thyme_test_concurrent_download");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let body = safetensors();
+        let (url, requests) = serve(body.clone());
+
+        let handles: Vec<_> = (0..6)
+            .map(|_| {
+                let url = url.clone();
+                let directory = directory.clone();
+                std::thread::spawn(move || {
+                    download_url(&url, &directory, "model.safetensors", true).unwrap()
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let path = directory.join("model.safetensors");
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(is_complete(&path));
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+
+        let partial = std::fs::read_dir(&directory)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().ends_with(".part")
+            })
+            .count();
+        assert_eq!(partial, 0);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_is_complete_safetensors() {
+        let directory = std::env::temp_dir().join("thyme_test_is_complete");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("model.safetensors");
+
+        std::fs::write(&path, safetensors()).unwrap();
+        assert!(is_complete(&path));
+
+        std::fs::write(&path, &safetensors()[..12]).unwrap();
+        assert!(!is_complete(&path));
+
+        std::fs::write(&path, b"").unwrap();
+        assert!(!is_complete(&path));
+
+        assert!(!is_complete(&directory.join("missing.safetensors")));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+pub use thyme_core::ut::suggest::{closest, edit_distance, not_found};
+
+#[cfg(test)]
+mod test {
+
+    use crate::data::{BenchmarkDatasets, SegmentationDatasets, Weights};
+
+    #[test]
+    fn test_registry_suggestions() {
+        assert!(Weights::suggest("dino_vit_smal").contains(&"dino_vit_small".to_string()));
+        assert_eq!(
+            Weights::suggest("DINOBLOOM_VIT_BASE"),
+            ["dinobloom_vit_base"]
+        );
+        assert!(Weights::suggest("resnet50").is_empty());
+
+        assert!(
+            SegmentationDatasets::suggest("cellpose2021").contains(&"cellpose_2021".to_string())
+        );
+        assert!(SegmentationDatasets::suggest("tisuenet_2022").contains(&"tissuenet_2022".into()));
+
+        assert!(BenchmarkDatasets::suggest("isic_2091").contains(&"isic_2019".to_string()));
+        assert!(BenchmarkDatasets::suggest("runtim").contains(&"runtime".to_string()));
+    }
+
+    #[test]
+    fn test_weights_match_supported_models() {
+        let models: Vec<&str> = Weights::iter()
+            .map(|weights| weights.model_name())
+            .collect();
+        assert_eq!(models, thyme_core::constant::SUPPORTED_MODELS);
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright (c) 2025, Tom Ouellette
+// Licensed under the MIT License
+
+use std::sync::OnceLock;
+
+use candle_core::{Device, Error, Result};
+
+use thyme_core::im::ThymeImage;
+
+use crate::nn::Models;
+
+/// A model that embeds a single image on a device
+pub trait Embed: Send + Sync {
+    /// Embed an image on the provided device
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Image (e.g. an object crop) to embed
+    /// * `device` - Device the model weights are loaded on
+    fn embed(&self, image: &ThymeImage, device: &Device) -> Result<Vec<f32>>;
+}
+
+impl Embed for Models {
+    fn embed(&self, image: &ThymeImage, device: &Device) -> Result<Vec<f32>> {
+        self.forward(&self.preprocess(image, device)?)?
+            .get(0)?
+            .to_vec1()
+    }
+}
+
+/// Fallback used to embed an object after an allocation failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fallback {
+    /// Embedded on the first attempt
+    None,
+    /// Embedded on a second attempt after synchronizing the device
+    Retry,
+    /// Embedded after downscaling the object crop
+    Downscale,
+    /// Embedded by a copy of the model on the cpu
+    Cpu,
+}
+
+impl Fallback {
+    /// Name of the fallback written to embedding tables
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Fallback::None => "none",
+            Fallback::Retry => "retry",
+            Fallback::Downscale => "downscale",
+            Fallback::Cpu => "cpu",
+        }
+    }
+}
+
+/// Check if an error was raised because a device ran out of memory
+///
+/// # Arguments
+///
+/// * `err` - Error returned by a model
+pub fn is_allocation_error(err: &Error) -> bool {
+    let message = err.to_string().to_lowercase();
+
+    ["out of memory", "out_of_memory", "alloc"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Embeds objects with a fallback chain for allocation failures
+///
+/// If an object fails to embed because the device ran out of memory, the
+/// object is retried once after synchronizing the device so that memory
+/// released by the failed attempt is reclaimed, then embedded from a crop
+/// downscaled to a maximum edge length, and finally embedded by a copy of
+/// the model on the cpu if a cpu fallback was provided. Errors that are not
+/// allocation failures are returned without a fallback.
+pub struct FallbackEmbedder<M> {
+    model: M,
+    device: Device,
+    max_edge: u32,
+    cpu: Option<Box<dyn Fn() -> M + Send + Sync>>,
+    cpu_model: OnceLock<M>,
+}
+
+impl<M: Embed> FallbackEmbedder<M> {
+    /// Initialize an embedder without a cpu fallback
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - Model loaded on `device`
+    /// * `device` - Device objects are embedded on
+    /// * `max_edge` - Maximum edge length of downscaled crops
+    pub fn new(model: M, device: Device, max_edge: u32) -> Self {
+        FallbackEmbedder {
+            model,
+            device,
+            max_edge: max_edge.max(1),
+            cpu: None,
+            cpu_model: OnceLock::new(),
+        }
+    }
+
+    /// Add a cpu fallback loaded the first time it is needed
+    ///
+    /// # Arguments
+    ///
+    /// * `load` - Loads a copy of the model on the cpu
+    pub fn with_cpu_fallback<F>(mut self, load: F) -> Self
+    where
+        F: Fn() -> M + Send + Sync + 'static,
+    {
+        self.cpu = Some(Box::new(load));
+        self
+    }
+
+    /// Device objects are embedded on
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Embed an image and report the fallback that produced the embedding
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Image (e.g. an object crop) to embed
+    pub fn embed(&self, image: &ThymeImage) -> Result<(Vec<f32>, Fallback)> {
+        let mut error = None;
+
+        for fallback in [
+            Fallback::None,
+            Fallback::Retry,
+            Fallback::Downscale,
+            Fallback::Cpu,
+        ] {
+            let result = match fallback {
+                Fallback::None => self.model.embed(image, &self.device),
+                Fallback::Retry => {
+                    self.device.synchronize()?;
+                    self.model.embed(image, &self.device)
+                }
+                Fallback::Downscale => match downscale(image, self.max_edge)? {
+                    Some(downscaled) => self.model.embed(&downscaled, &self.device),
+                    None => continue,
+                },
+                Fallback::Cpu => match &self.cpu {
+                    Some(load) => self.cpu_model.get_or_init(load).embed(image, &Device::Cpu),
+                    None => continue,
+                },
+            };
+
+            match result {
+                Ok(embedding) => return Ok((embedding, fallback)),
+                Err(err) if is_allocation_error(&err) => error = Some(err),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(error.unwrap())
+    }
+}
+
+/// Resize an image so that its longest edge is at most `max_edge` pixels
+///
+/// Returns `None` if the image already fits.
+fn downscale(image: &ThymeImage, max_edge: u32) -> Result<Option<ThymeImage>> {
+    let edge = image.width().max(image.height());
+
+    if edge <= max_edge {
+        return Ok(None);
+    }
+
+    let scale = max_edge as f32 / edge as f32;
+    let width = ((image.width() as f32 * scale).round() as u32).clamp(1, max_edge);
+    let height = ((image.height() as f32 * scale).round() as u32).clamp(1, max_edge);
+
+    image
+        .resize(width, height)
+        .map(Some)
+        .map_err(|err| Error::Msg(err.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use thyme_core::im::ThymeBuffer;
+
+    use super::*;
+
+    /// Runs out of memory on images with an edge longer than `limit`
+    struct Stub {
+        limit: u32,
+        transient: AtomicUsize,
+        broken: bool,
+    }
+
+    impl Stub {
+        fn new(limit: u32) -> Self {
+            Stub {
+                limit,
+                transient: AtomicUsize::new(0),
+                broken: false,
+            }
+        }
+    }
+
+    impl Embed for Stub {
+        fn embed(&self, image: &ThymeImage, _device: &Device) -> Result<Vec<f32>> {
+            if self.broken {
+                return Err(Error::Msg("shape mismatch".to_string()));
+            }
+
+            let transient = self.transient.load(Ordering::SeqCst);
+            if transient > 0 || image.width().max(image.height()) > self.limit {
+                self.transient
+                    .store(transient.saturating_sub(1), Ordering::SeqCst);
+                return Err(Error::Msg("CUDA_ERROR_OUT_OF_MEMORY".to_string()));
+            }
+
+            Ok(vec![image.width() as f32, image.height() as f32])
+        }
+    }
+
+    fn image(width: u32, height: u32) -> ThymeImage {
+        let buffer = vec![0; (width * height) as usize];
+        ThymeImage::U8(ThymeBuffer::new(width, height, 1, buffer).unwrap())
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        let embedder = FallbackEmbedder::new(Stub::new(16), Device::Cpu, 8);
+
+        assert_eq!(
+            embedder.embed(&image(10, 4)).unwrap(),
+            (vec![10., 4.], Fallback::None)
+        );
+
+        // Crops that run out of memory are downscaled to the maximum edge
+        assert_eq!(
+            embedder.embed(&image(32, 16)).unwrap(),
+            (vec![8., 4.], Fallback::Downscale)
+        );
+
+        embedder.model.transient.store(1, Ordering::SeqCst);
+        assert_eq!(
+            embedder.embed(&image(10, 4)).unwrap(),
+            (vec![10., 4.], Fallback::Retry)
+        );
+
+        // Without a cpu fallback the allocation failure is returned
+        let embedder = FallbackEmbedder::new(Stub::new(4), Device::Cpu, 8);
+        let err = embedder.embed(&image(32, 16)).unwrap_err();
+        assert!(is_allocation_error(&err));
+
+        let embedder = embedder.with_cpu_fallback(|| Stub::new(u32::MAX));
+        assert_eq!(
+            embedder.embed(&image(32, 16)).unwrap(),
+            (vec![32., 16.], Fallback::Cpu)
+        );
+    }
+
+    #[test]
+    fn test_fallback_other_error() {
+        let embedder = FallbackEmbedder::new(
+            Stub {
+                broken: true,
+                ..Stub::new(16)
+            },
+            Device::Cpu,
+            8,
+        )
+        .with_cpu_fallback(|| Stub::new(u32::MAX));
+
+        let err = embedder.embed(&image(4, 4)).unwrap_err();
+        assert!(!is_allocation_error(&err));
+    }
+
+    #[test]
+    fn test_downscale() {
+        assert!(downscale(&image(8, 8), 8).unwrap().is_none());
+
+        let downscaled = downscale(&image(100, 3), 10).unwrap().unwrap();
+        assert_eq!((downscaled.width(), downscaled.height()), (10, 1));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod fallback;
 pub mod load;
 pub mod models;
 pub mod nn;
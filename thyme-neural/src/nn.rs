@@ -54,6 +54,15 @@ impl Models {
         }
     }
 
+    /// Number of embedding dimensions produced by a model without loading weights
+    pub fn embedding_dim(model_name: &str) -> Option<usize> {
+        match model_name {
+            "dino_vit_small" | "scdino_vit_small" => Some(384),
+            "dino_vit_base" | "dinobloom_vit_base" | "subcell_vit_base" => Some(768),
+            _ => None,
+        }
+    }
+
     pub fn preprocess(&self, image: &ThymeImage, device: &Device) -> Result<Tensor> {
         match self {
             Models::DinoVitSmall(_) => preprocess_imagenet(image, device),
@@ -65,7 +74,7 @@ impl Models {
     }
 
     pub fn forward(&self, input: &Tensor) -> Result<Tensor> {
-        let input = input.unsqueeze(0).unwrap();
+        let input = input.unsqueeze(0)?;
         match self {
             Models::DinoVitSmall(model) => model.forward(&input),
             Models::DinoVitBase(model) => model.forward(&input),
@@ -106,6 +115,13 @@ mod test {
         assert_eq!(n_columns, n_embed);
     }
 
+    #[test]
+    fn test_embedding_dim() {
+        assert_eq!(Models::embedding_dim("dino_vit_small"), Some(384));
+        assert_eq!(Models::embedding_dim("subcell_vit_base"), Some(768));
+        assert_eq!(Models::embedding_dim("missing"), None);
+    }
+
     #[test]
     fn test_dinov2_small_rgb() {
         test_model("dino_vit_small", "rgb", 384);